@@ -63,6 +63,8 @@ pub use minterpolate::{InterpolationFunction, InterpolationPrimitive};
 pub use self::{
     bundle::{AnimationBundle, SamplingBundle, VertexSkinningBundle},
     material::{MaterialChannel, MaterialPrimitive},
+    material_overrides::MaterialOverridesChannel,
+    morph::MorphWeightsChannel,
     prefab::{AnimatablePrefab, AnimationHierarchyPrefab, AnimationPrefab, AnimationSetPrefab},
     resources::{
         Animation, AnimationCommand, AnimationControl, AnimationControlSet, AnimationHierarchy,
@@ -81,6 +83,8 @@ pub use self::{
 
 mod bundle;
 mod material;
+mod material_overrides;
+mod morph;
 mod prefab;
 mod resources;
 mod skinning;
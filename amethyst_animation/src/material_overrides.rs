@@ -0,0 +1,79 @@
+use amethyst_core::math::zero;
+use amethyst_rendy::mtl::MaterialOverrides;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    resources::{AnimationSampling, ApplyData, BlendMethod},
+    util::SamplerPrimitive,
+};
+
+/// Channels that can be animated on `MaterialOverrides`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MaterialOverridesChannel {
+    /// The tint color, in sRGBA
+    Tint,
+    /// The emission factor
+    Emission,
+    /// The texture coordinate offset
+    UvOffset,
+    /// The texture coordinate scrolling speed
+    UvScroll,
+}
+
+impl<'a> ApplyData<'a> for MaterialOverrides {
+    type ApplyData = ();
+}
+
+impl AnimationSampling for MaterialOverrides {
+    type Primitive = SamplerPrimitive<f32>;
+    type Channel = MaterialOverridesChannel;
+
+    fn apply_sample(&mut self, channel: &Self::Channel, data: &SamplerPrimitive<f32>, _: &()) {
+        use crate::util::SamplerPrimitive::*;
+
+        use self::MaterialOverridesChannel::*;
+
+        match (channel, *data) {
+            (&Tint, Vec4(ref d)) => {
+                self.tint = amethyst_rendy::palette::Srgba::new(d[0], d[1], d[2], d[3]);
+            }
+            (&Emission, Scalar(ref d)) => {
+                self.emission = *d;
+            }
+            (&UvOffset, Vec2(ref d)) => {
+                self.uv_offset = (*d).into();
+            }
+            (&UvScroll, Vec2(ref d)) => {
+                self.uv_scroll = (*d).into();
+            }
+            _ => panic!("Attempt to apply invalid sample to MaterialOverrides"),
+        }
+    }
+
+    fn current_sample(&self, channel: &Self::Channel, _: &()) -> SamplerPrimitive<f32> {
+        use self::MaterialOverridesChannel::*;
+        match channel {
+            Tint => {
+                let (r, g, b, a) = self.tint.into_components();
+                SamplerPrimitive::Vec4([r, g, b, a])
+            }
+            Emission => SamplerPrimitive::Scalar(self.emission),
+            UvOffset => SamplerPrimitive::Vec2(self.uv_offset.into()),
+            UvScroll => SamplerPrimitive::Vec2(self.uv_scroll.into()),
+        }
+    }
+
+    fn default_primitive(channel: &Self::Channel) -> Self::Primitive {
+        use self::MaterialOverridesChannel::*;
+        match channel {
+            Tint => SamplerPrimitive::Vec4([zero(); 4]),
+            Emission => SamplerPrimitive::Scalar(zero()),
+            UvOffset | UvScroll => SamplerPrimitive::Vec2([zero(); 2]),
+        }
+    }
+
+    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
+        Some(BlendMethod::Linear)
+    }
+}
@@ -0,0 +1,56 @@
+use amethyst_rendy::morph::MorphWeights;
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    resources::{AnimationSampling, ApplyData, BlendMethod},
+    util::SamplerPrimitive,
+};
+
+/// Channels that can be animated on `MorphWeights`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MorphWeightsChannel {
+    /// The weight of the morph target with the given index
+    Weight(usize),
+}
+
+impl<'a> ApplyData<'a> for MorphWeights {
+    type ApplyData = ();
+}
+
+impl AnimationSampling for MorphWeights {
+    type Primitive = SamplerPrimitive<f32>;
+    type Channel = MorphWeightsChannel;
+
+    fn apply_sample(&mut self, channel: &Self::Channel, data: &SamplerPrimitive<f32>, _: &()) {
+        match (*channel, *data) {
+            (MorphWeightsChannel::Weight(index), SamplerPrimitive::Scalar(weight)) => {
+                if self.weights.len() <= index {
+                    self.weights.resize(index + 1, 0.0);
+                }
+                self.weights[index] = weight;
+            }
+            _ => {
+                let message = "Attempt to apply invalid sample to MorphWeights".to_string();
+                error!("{}", message);
+                panic!("{}", message);
+            }
+        }
+    }
+
+    fn current_sample(&self, channel: &Self::Channel, _: &()) -> SamplerPrimitive<f32> {
+        match *channel {
+            MorphWeightsChannel::Weight(index) => {
+                SamplerPrimitive::Scalar(self.weights.get(index).cloned().unwrap_or(0.0))
+            }
+        }
+    }
+
+    fn default_primitive(_: &Self::Channel) -> Self::Primitive {
+        SamplerPrimitive::Scalar(0.0)
+    }
+
+    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
+        Some(BlendMethod::Linear)
+    }
+}
@@ -26,6 +26,9 @@ pub struct VertexSkinningSystem {
     updated: BitSet,
     #[system_desc(skip)]
     updated_skins: BitSet,
+    /// Meshes whose joint transforms were already recomputed this frame through their skin.
+    #[system_desc(skip)]
+    updated_meshes: BitSet,
     /// Used for tracking modifications to global transforms
     #[system_desc(flagged_storage_reader(Transform))]
     updated_id: ReaderId<ComponentEvent>,
@@ -37,6 +40,7 @@ impl VertexSkinningSystem {
         Self {
             updated: BitSet::default(),
             updated_skins: BitSet::default(),
+            updated_meshes: BitSet::default(),
             updated_id,
         }
     }
@@ -67,6 +71,7 @@ impl<'a> System<'a> for VertexSkinningSystem {
             });
 
         self.updated_skins.clear();
+        self.updated_meshes.clear();
 
         for (_, joint) in (&self.updated, &joints).join() {
             for skin in &joint.skins {
@@ -100,8 +105,10 @@ impl<'a> System<'a> for VertexSkinningSystem {
             );
 
             // update the joint matrices in all referenced mesh entities
-            for (_, mesh_global, matrix) in (&skin.meshes, &global_transforms, &mut matrices).join()
+            for (id, mesh_global, matrix) in
+                (&skin.meshes, &global_transforms, &mut matrices).join()
             {
+                self.updated_meshes.add(id);
                 if let Some(global_inverse) = mesh_global.global_matrix().try_inverse() {
                     matrix.matrices.clear();
                     matrix
@@ -113,8 +120,14 @@ impl<'a> System<'a> for VertexSkinningSystem {
             }
         }
 
-        for (_, mesh_global, joint_transform) in
-            (&self.updated, &global_transforms, &mut matrices).join()
+        // Meshes that moved without their skin changing still need their matrices rebased.
+        for (_, _, mesh_global, joint_transform) in (
+            &self.updated,
+            !&self.updated_meshes,
+            &global_transforms,
+            &mut matrices,
+        )
+            .join()
         {
             if let Some(global_inverse) = mesh_global.global_matrix().try_inverse() {
                 if let Some(skin) = skins.get(joint_transform.skin) {
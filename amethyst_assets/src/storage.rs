@@ -13,7 +13,7 @@ use rayon::ThreadPool;
 
 use amethyst_core::{
     ecs::{
-        hibitset::BitSet,
+        hibitset::{BitSet, BitSetLike},
         prelude::{Component, Read, ReadExpect, System, SystemData, VecStorage, World, Write},
         storage::UnprotectedStorage,
     },
@@ -207,6 +207,13 @@ impl<A: Asset> AssetStorage<A> {
         &self.assets.get(id).0
     }
 
+    /// Iterate over the loaded assets.
+    pub fn iter(&self) -> impl Iterator<Item = &A> {
+        (&self.bitset)
+            .iter()
+            .map(move |id| unsafe { &self.assets.get(id).0 })
+    }
+
     /// Get an asset mutably from a given asset handle.
     pub fn get_mut(&mut self, handle: &Handle<A>) -> Option<&mut A> {
         if self.bitset.contains(handle.id()) {
@@ -0,0 +1,313 @@
+//! Deserialization of RON configurations, with errors telling where and why it failed.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use ron::de::{Error as DeError, ParseError};
+use serde::Deserialize;
+
+use crate::{layers::field_at, ConfigError};
+
+/// Error deserializing a configuration, with the location of the error in the text.
+///
+/// Displayed as `file:line:column: field `path`: message`, followed by a suggestion when an
+/// unknown field or variant is close to a known one:
+///
+/// ```text
+/// display.ron:3:5: field `fulscreen`: unknown field `fulscreen`, expected one of `title`,
+/// `fullscreen`, `dimensions` (did you mean `fullscreen`?)
+/// ```
+#[derive(Debug)]
+pub struct DeserializeError {
+    error: DeError,
+    file: Option<PathBuf>,
+    line: usize,
+    column: usize,
+    path: Option<String>,
+    suggestion: Option<String>,
+}
+
+impl DeserializeError {
+    fn new(error: DeError, bytes: &[u8], offset: usize) -> Self {
+        let (line, column) = match error {
+            DeError::Parser(_, position) => (position.line, position.col),
+            _ => line_column(bytes, offset),
+        };
+        // fields are only looked up in valid text
+        let field = || {
+            std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|text| field_at(text, offset))
+        };
+        let (path, suggestion) = match &error {
+            // the error is found at the end of the structure, after its last field
+            DeError::Message(msg) if msg.starts_with("missing field") => (None, None),
+            DeError::Message(msg) => (field(), suggest(msg)),
+            _ => (field(), None),
+        };
+        DeserializeError {
+            path,
+            error,
+            file: None,
+            line,
+            column,
+            suggestion,
+        }
+    }
+
+    /// Returns the error of the deserializer.
+    pub fn error(&self) -> &DeError {
+        &self.error
+    }
+
+    /// Returns the configuration file, if the configuration was loaded from one.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Returns the line and column of the error, starting at 1.
+    pub fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    /// Returns the path of the field the error is in, e.g. `window.title`.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Returns the known field or variant closest to the unknown one, if any is close.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        if let Some(path) = &self.path {
+            write!(f, "field `{}`: ", path)?;
+        }
+        match &self.error {
+            // the position is already written
+            DeError::Parser(ParseError::Utf8Error(error), _) => write!(f, "{}", error)?,
+            DeError::Parser(_, position) => {
+                let message = self.error.to_string();
+                let prefix = format!("{}: ", position);
+                write!(f, "{}", message.trim_start_matches(prefix.as_str()))?
+            }
+            error => write!(f, "{}", error)?,
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{}`?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+/// Deserializes RON text, locating the errors in the given file.
+pub(crate) fn deserialize<T>(bytes: &[u8], file: Option<&Path>) -> Result<T, ConfigError>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    let mut de = ron::de::Deserializer::from_bytes(bytes)?;
+    let result = T::deserialize(&mut de).and_then(|value| de.end().map(|_| value));
+    result.map_err(|error| {
+        let offset = bytes.len().saturating_sub(de.remainder().len());
+        let mut error = DeserializeError::new(error, bytes, offset);
+        error.file = file.map(Path::to_path_buf);
+        ConfigError::Deserialize(Box::new(error))
+    })
+}
+
+/// Returns the line and column of the byte offset, both starting at 1, counting columns in
+/// characters.
+fn line_column(bytes: &[u8], offset: usize) -> (usize, usize) {
+    let before = &bytes[..offset.min(bytes.len())];
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |index| index + 1);
+    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+    let column = String::from_utf8_lossy(&before[line_start..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// Suggests the expected name closest to an unknown field or variant of a serde error like
+/// "unknown field `fulscreen`, expected one of `title`, `fullscreen`".
+fn suggest(msg: &str) -> Option<String> {
+    if !msg.starts_with("unknown field") && !msg.starts_with("unknown variant") {
+        return None;
+    }
+    // names are quoted by backticks
+    let mut names = msg.split('`').skip(1).step_by(2);
+    let unknown = names.next()?;
+    names
+        .map(|name| (distance(unknown, name), name))
+        .filter(|(distance, name)| *distance <= (name.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.to_string())
+}
+
+/// Levenshtein distance between the strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::Deserialize;
+
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Display {
+        title: String,
+        #[serde(default)]
+        fullscreen: bool,
+        window: Window,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Window {
+        width: u32,
+    }
+
+    fn deserialize_error(bytes: &[u8]) -> DeserializeError {
+        match deserialize::<Display>(bytes, Some(Path::new("display.ron"))) {
+            Err(ConfigError::Deserialize(error)) => *error,
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_located_and_suggested() {
+        let error = deserialize_error(
+            b"(\n    title: \"Game\",\n    window: (\n        wdth: 800,\n    ),\n)",
+        );
+        assert_eq!(error.position(), (4, 13));
+        assert_eq!(error.path(), Some("window.wdth"));
+        assert_eq!(error.suggestion(), Some("width"));
+        assert_eq!(
+            error.to_string(),
+            "display.ron:4:13: field `window.wdth`: unknown field `wdth`, expected `width` \
+             (did you mean `width`?)"
+        );
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let error = deserialize_error("(title: \"héllo\", fulscreen: true)".as_bytes());
+        assert_eq!(error.position(), (1, 27));
+        assert_eq!(error.path(), Some("fulscreen"));
+        assert_eq!(error.suggestion(), Some("fullscreen"));
+    }
+
+    #[test]
+    fn invalid_utf8_is_located_without_fields() {
+        // the comment isn't valid UTF-8, but the error after it is still located
+        let error = deserialize_error(b"// \xff\xff\n(title: \"\xc3\xa9\", fulscreen: true)");
+        assert_eq!(error.position(), (2, 23));
+        assert_eq!(error.path(), None);
+        assert_eq!(error.suggestion(), Some("fullscreen"));
+
+        let error = deserialize_error(b"(title: \"\xff\", fullscreen: true)");
+        assert!(error.to_string().contains("invalid utf-8"), "{}", error);
+    }
+
+    #[test]
+    fn syntax_errors_are_located_once() {
+        let error = deserialize_error(b"(window: (width: wide))");
+        assert_eq!(
+            error.to_string(),
+            "display.ron:1:18: field `window.width`: Expected integer"
+        );
+    }
+
+    #[test]
+    fn line_column_of_byte_offsets() {
+        let text = b"ab\n\xc3\xa7d\n\xff";
+        assert_eq!(line_column(b"", 0), (1, 1));
+        assert_eq!(line_column(text, 0), (1, 1));
+        assert_eq!(line_column(text, 2), (1, 3));
+        assert_eq!(line_column(text, 3), (2, 1));
+        assert_eq!(line_column(text, 5), (2, 2));
+        assert_eq!(line_column(text, 100), (3, 2));
+        // offsets within a character or invalid bytes don't panic
+        assert_eq!(line_column(text, 4), (2, 2));
+        assert_eq!(line_column(b"a\n\xff\xfe", 4), (2, 3));
+    }
+
+    #[test]
+    fn field_at_finds_innermost_field() {
+        let text = "(\n    a: 1,\n    b: B(\n        c: \"(\",\n    ),\n)";
+        assert_eq!(
+            field_at(text, text.find('1').unwrap()).as_deref(),
+            Some("a")
+        );
+        assert_eq!(
+            field_at(text, text.find('c').unwrap()).as_deref(),
+            Some("b.c")
+        );
+        assert_eq!(
+            field_at(text, text.find('B').unwrap()).as_deref(),
+            Some("b")
+        );
+        assert_eq!(field_at(text, 0), None);
+        // fields before a syntax error are still found
+        assert_eq!(field_at("(a: 1, b: (c: 2 d", 14).as_deref(), Some("b.c"));
+    }
+
+    #[test]
+    fn suggestions_are_close_names() {
+        assert_eq!(
+            suggest("unknown field `fulscreen`, expected one of `title`, `fullscreen`").as_deref(),
+            Some("fullscreen")
+        );
+        assert_eq!(
+            suggest("unknown variant `Bordreless`, expected `Windowed` or `Borderless`").as_deref(),
+            Some("Borderless")
+        );
+        assert_eq!(
+            suggest("unknown field `size`, expected one of `title`, `fullscreen`"),
+            None
+        );
+        assert_eq!(
+            suggest("invalid type: integer `3`, expected a boolean"),
+            None
+        );
+    }
+
+    #[test]
+    fn distance_counts_edits() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("title", "title"), 0);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("fulscreen", "fullscreen"), 1);
+        assert_eq!(distance("widht", "width"), 2);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("é", "e"), 1);
+    }
+}
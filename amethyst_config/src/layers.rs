@@ -0,0 +1,948 @@
+//! Loading of a configuration from several layers overriding each other.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::ConfigError;
+
+/// Layers of a configuration: a base file, overlaid by an optional user file, overridden by
+/// environment variables.
+///
+/// The files are written in RON, in TOML when their extension is `toml` and the `toml` feature is
+/// enabled, or in YAML when their extension is `yml` or `yaml` and the `yaml` feature is enabled,
+/// and the formats can be mixed. TOML tables and YAML mappings with string keys are read as
+/// structures, unless they replace a map of the layer below.
+///
+/// The fields of the structures of a layer replace the same fields of the layers below, while
+/// the fields a layer doesn't have keep their values. Other values, like lists or options, are
+/// replaced as a whole.
+///
+/// An environment variable overrides a field when its name is the prefix followed by the path of
+/// the field, separated by double underscores. Its value is written in RON and replaces the field
+/// as a whole, even a structure:
+///
+/// ```sh
+/// AMETHYST_DISPLAY__FULLSCREEN=None
+/// AMETHYST_DISPLAY__DIMENSIONS='Some((1920, 1080))'
+/// AMETHYST_DISPLAY__TITLE='"My game"'
+/// ```
+///
+/// The names in the path are matched ignoring case against the fields of the files, so
+/// `AMETHYST_DISPLAY__VSYNC` also overrides a field renamed to `vSync` by serde. Fields that none
+/// of the files have are named in lower case.
+///
+/// ```rust,ignore
+/// let (display, report) = ConfigLayers::new("config/display.ron")
+///     .with_user_file(user_dir.join("display.ron"))
+///     .with_env_prefix("AMETHYST_DISPLAY")
+///     .load::<DisplayConfig>()?;
+/// println!("{}", report);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConfigLayers {
+    base: PathBuf,
+    user: Option<PathBuf>,
+    env_prefix: Option<String>,
+}
+
+impl ConfigLayers {
+    /// Creates the layers of a configuration with the given base file.
+    pub fn new<P: AsRef<Path>>(base: P) -> Self {
+        ConfigLayers {
+            base: base.as_ref().to_path_buf(),
+            user: None,
+            env_prefix: None,
+        }
+    }
+
+    /// Overlays the base file with the given user file, which is skipped if it doesn't exist.
+    pub fn with_user_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.user = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Overrides the files with the environment variables starting with the given prefix.
+    pub fn with_env_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Loads the configuration from its layers, returning it with the layer of each value.
+    pub fn load<T>(&self) -> Result<(T, ConfigReport), ConfigError>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        let vars = std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        });
+        self.load_with_vars(vars)
+    }
+
+    fn load_with_vars<T, I>(&self, vars: I) -> Result<(T, ConfigReport), ConfigError>
+    where
+        T: for<'a> Deserialize<'a>,
+        I: Iterator<Item = (String, String)>,
+    {
+        let mut report = ConfigReport::default();
+        let mut file = read_layer(&self.base)?;
+        report.record("", &file.root, &ConfigSource::Base(self.base.clone()));
+
+        if let Some(user) = self.user.as_ref().filter(|user| user.exists()) {
+            let overlay = read_layer(user)?;
+            file.extensions.extend(overlay.extensions);
+            merge(
+                &mut file.root,
+                overlay.root,
+                "",
+                &ConfigSource::User(user.clone()),
+                &mut report,
+            );
+        }
+
+        if let Some(prefix) = &self.env_prefix {
+            let prefix = format!("{}__", prefix);
+            let mut vars = vars
+                .filter(|(name, _)| name.starts_with(&prefix))
+                .collect::<Vec<_>>();
+            vars.sort();
+            for (name, value) in vars {
+                let fields = name[prefix.len()..].split("__").collect::<Vec<_>>();
+                let source = ConfigSource::Env(name.clone());
+                override_field(&mut file.root, &fields, value.trim(), &source, &mut report);
+            }
+        }
+
+        let text = file.to_string();
+        let mut de = ron::de::Deserializer::from_str(&text)?;
+        let config = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok((config, report))
+    }
+}
+
+/// Layer a value of a configuration was taken from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// The base file.
+    Base(PathBuf),
+    /// The user file.
+    User(PathBuf),
+    /// The environment variable with the given name.
+    Env(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Base(path) | ConfigSource::User(path) => write!(f, "{}", path.display()),
+            ConfigSource::Env(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+/// Layers the values of a configuration loaded by `ConfigLayers` were taken from.
+///
+/// Values are named by their path, the names of their fields separated by dots, e.g.
+/// `window.title`. Fields missing from every layer have their default value, and aren't reported.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigReport {
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl ConfigReport {
+    /// Returns the layer the value at the given path was taken from.
+    pub fn source(&self, path: &str) -> Option<&ConfigSource> {
+        self.sources.get(path)
+    }
+
+    /// Iterates over the paths of the values and their layers, ordered by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ConfigSource)> {
+        self.sources
+            .iter()
+            .map(|(path, source)| (path.as_str(), source))
+    }
+
+    fn record(&mut self, path: &str, node: &Node, source: &ConfigSource) {
+        match node {
+            Node::Struct { fields, .. } if !fields.is_empty() => {
+                for (name, value) in fields {
+                    self.record(&join(path, name), value, source);
+                }
+            }
+            _ => {
+                self.sources.insert(path.to_string(), source.clone());
+            }
+        }
+    }
+
+    fn replace(&mut self, path: &str, node: &Node, source: &ConfigSource) {
+        let nested = format!("{}.", path);
+        self.sources
+            .retain(|p, _| !path.is_empty() && p != path && !p.starts_with(&nested));
+        self.record(path, node, source);
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, source) in self.iter() {
+            writeln!(f, "{}: {}", path, source)?;
+        }
+        Ok(())
+    }
+}
+
+/// A RON value, where structures are kept apart so their fields can be merged.
+#[derive(Debug)]
+enum Node {
+    Struct {
+        name: Option<String>,
+        fields: Vec<(String, Node)>,
+    },
+    Value(String),
+}
+
+impl Node {
+    fn is_map(&self) -> bool {
+        matches!(self, Node::Value(value) if value.starts_with('{'))
+    }
+}
+
+#[derive(Debug)]
+struct LayerFile {
+    extensions: Vec<String>,
+    root: Node,
+}
+
+impl fmt::Display for LayerFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut written = Vec::new();
+        for extension in &self.extensions {
+            if !written.contains(&extension) {
+                writeln!(f, "{}", extension)?;
+                written.push(extension);
+            }
+        }
+        write!(f, "{}", self.root)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Struct { name, fields } => {
+                // fields are written on their own lines, ending the comments the values may have
+                writeln!(f, "{}(", name.as_deref().unwrap_or(""))?;
+                for (name, value) in fields {
+                    writeln!(f, "{}: {},", name, value)?;
+                }
+                write!(f, ")")
+            }
+            Node::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+fn read_layer(path: &Path) -> Result<LayerFile, ConfigError> {
+    let text = match path.extension().and_then(OsStr::to_str) {
+        Some("ron") => fs::read_to_string(path)?,
+        #[cfg(feature = "toml")]
+        Some("toml") => {
+            let value = toml::from_str(&fs::read_to_string(path)?)?;
+            return Ok(LayerFile {
+                // options missing from TOML files are `None`, and present ones are `Some`
+                extensions: vec!["#![enable(implicit_some)]".to_string()],
+                root: toml_node(value)?,
+            });
+        }
+        #[cfg(feature = "yaml")]
+        Some("yml") | Some("yaml") => {
+            let value = serde_yaml::from_str(&fs::read_to_string(path)?)
+                .map_err(ConfigError::YamlParser)?;
+            return Ok(LayerFile {
+                // like in TOML files, present options are `Some` and `null` ones are `None`
+                extensions: vec!["#![enable(implicit_some)]".to_string()],
+                root: yaml_node(value)?,
+            });
+        }
+        _ => return Err(ConfigError::Extension(path.to_path_buf())),
+    };
+    Parser::new(&text)
+        .file()
+        .map_err(|msg| ConfigError::Layer(path.to_path_buf(), msg))
+}
+
+/// Converts a TOML value to a node, splitting its tables into structures.
+#[cfg(feature = "toml")]
+fn toml_node(value: toml::Value) -> Result<Node, ConfigError> {
+    match value {
+        toml::Value::Table(table) => Ok(Node::Struct {
+            name: None,
+            fields: table
+                .into_iter()
+                .map(|(name, value)| Ok((name, toml_node(value)?)))
+                .collect::<Result<_, ConfigError>>()?,
+        }),
+        value => Ok(Node::Value(toml_to_ron(value)?)),
+    }
+}
+
+/// Writes a TOML value in RON, with its tables as structures.
+#[cfg(feature = "toml")]
+fn toml_to_ron(value: toml::Value) -> Result<String, ConfigError> {
+    Ok(match value {
+        toml::Value::Table(table) => {
+            let fields = table
+                .into_iter()
+                .map(|(name, value)| Ok(format!("{}: {}", name, toml_to_ron(value)?)))
+                .collect::<Result<Vec<_>, ConfigError>>()?;
+            format!("({})", fields.join(", "))
+        }
+        toml::Value::Array(values) => {
+            let values = values
+                .into_iter()
+                .map(toml_to_ron)
+                .collect::<Result<Vec<_>, _>>()?;
+            format!("[{}]", values.join(", "))
+        }
+        toml::Value::Datetime(datetime) => ron::ser::to_string(&datetime.to_string())?,
+        value => ron::ser::to_string(&value)?,
+    })
+}
+
+/// Converts a YAML value to a node, splitting its mappings with string keys into structures.
+#[cfg(feature = "yaml")]
+fn yaml_node(value: serde_yaml::Value) -> Result<Node, ConfigError> {
+    match value {
+        serde_yaml::Value::Mapping(mapping) if mapping.iter().all(|(key, _)| key.is_string()) => {
+            Ok(Node::Struct {
+                name: None,
+                fields: mapping
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let name = name.as_str().unwrap_or_default().to_string();
+                        Ok((name, yaml_node(value)?))
+                    })
+                    .collect::<Result<_, ConfigError>>()?,
+            })
+        }
+        value => Ok(Node::Value(yaml_to_ron(value)?)),
+    }
+}
+
+/// Writes a YAML value in RON, with its mappings with string keys as structures.
+#[cfg(feature = "yaml")]
+fn yaml_to_ron(value: serde_yaml::Value) -> Result<String, ConfigError> {
+    Ok(match value {
+        serde_yaml::Value::Null => "None".to_string(),
+        serde_yaml::Value::Mapping(mapping) => {
+            let structure = mapping.iter().all(|(key, _)| key.is_string());
+            let entries = mapping
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        serde_yaml::Value::String(name) if structure => name,
+                        key => yaml_to_ron(key)?,
+                    };
+                    Ok(format!("{}: {}", key, yaml_to_ron(value)?))
+                })
+                .collect::<Result<Vec<_>, ConfigError>>()?;
+            if structure {
+                format!("({})", entries.join(", "))
+            } else {
+                format!("{{{}}}", entries.join(", "))
+            }
+        }
+        serde_yaml::Value::Sequence(values) => {
+            let values = values
+                .into_iter()
+                .map(yaml_to_ron)
+                .collect::<Result<Vec<_>, _>>()?;
+            format!("[{}]", values.join(", "))
+        }
+        value => ron::ser::to_string(&value)?,
+    })
+}
+
+fn merge(
+    base: &mut Node,
+    overlay: Node,
+    path: &str,
+    source: &ConfigSource,
+    report: &mut ConfigReport,
+) {
+    match (base, overlay) {
+        (
+            Node::Struct { name, fields },
+            Node::Struct {
+                name: overlay_name,
+                fields: overlay_fields,
+            },
+        ) if name.is_none() || overlay_name.is_none() || *name == overlay_name => {
+            if name.is_none() {
+                *name = overlay_name;
+            }
+            for (field, value) in overlay_fields {
+                let field_path = join(path, &field);
+                match fields.iter_mut().find(|(f, _)| *f == field) {
+                    Some((_, base_value)) => merge(base_value, value, &field_path, source, report),
+                    None => {
+                        report.replace(&field_path, &value, source);
+                        fields.push((field, value));
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            let overlay = match overlay {
+                // TOML tables can be maps as well, which replace the maps of the layer below
+                Node::Struct { name: None, fields } if base.is_map() => Node::Value(format!(
+                    "{{{}}}",
+                    fields
+                        .iter()
+                        .map(|(key, value)| format!("{:?}: {}", key, value))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+                overlay => overlay,
+            };
+            report.replace(path, &overlay, source);
+            *base = overlay;
+        }
+    }
+}
+
+fn override_field(
+    node: &mut Node,
+    fields: &[&str],
+    value: &str,
+    source: &ConfigSource,
+    report: &mut ConfigReport,
+) {
+    let mut names = Vec::with_capacity(fields.len());
+    let mut current = Some(&*node);
+    for field in fields {
+        let existing = match current {
+            Some(Node::Struct { fields, .. }) => {
+                fields.iter().find(|(name, _)| name == field).or_else(|| {
+                    fields
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(field))
+                })
+            }
+            _ => None,
+        };
+        names.push(existing.map_or_else(|| field.to_lowercase(), |(name, _)| name.clone()));
+        current = existing.map(|(_, value)| value);
+    }
+
+    let mut overlay = Node::Value(value.to_string());
+    for name in names.into_iter().rev() {
+        overlay = Node::Struct {
+            name: None,
+            fields: vec![(name, overlay)],
+        };
+    }
+    merge(node, overlay, "", source, report);
+}
+
+/// Parser splitting RON text into structures and other values, which are kept as text.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    path: Vec<&'a str>,
+    /// Path of every parsed field, with the byte range from its name to the end of its value.
+    spans: Vec<(String, usize, usize)>,
+}
+
+/// Returns the path of the innermost field of the RON text at the byte offset, e.g. the field
+/// an error was found in.
+pub(crate) fn field_at(text: &str, offset: usize) -> Option<String> {
+    let mut parser = Parser::new(text);
+    // the fields parsed before a syntax error are still found
+    let _ = parser.file();
+    parser
+        .spans
+        .iter()
+        .filter(|(_, start, end)| *start <= offset && offset <= *end)
+        .max_by_key(|(path, _, _)| path.len())
+        .map(|(path, _, _)| path.clone())
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser {
+            text,
+            pos: 0,
+            path: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    fn file(&mut self) -> Result<LayerFile, String> {
+        let mut extensions = Vec::new();
+        self.skip_whitespace();
+        while self.rest().starts_with("#!") {
+            let start = self.pos;
+            self.pos += self
+                .rest()
+                .find(']')
+                .ok_or("Unclosed extension attribute")?
+                + 1;
+            extensions.push(self.text[start..self.pos].to_string());
+            self.skip_whitespace();
+        }
+        let root = self.value()?;
+        self.skip_whitespace();
+        if self.pos < self.text.len() {
+            return Err(format!("Unexpected text at byte {}", self.pos));
+        }
+        Ok(LayerFile { extensions, root })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                self.pos += trimmed.find("*/").map_or(trimmed.len(), |end| end + 2);
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 || rest.starts_with(|c: char| c.is_numeric()) {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn value(&mut self) -> Result<Node, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let name = self.identifier();
+        self.skip_whitespace();
+        if self.peek() == Some('(') && self.starts_fields() {
+            self.pos += 1;
+            let fields = self.fields()?;
+            return Ok(Node::Struct {
+                name: name.map(str::to_string),
+                fields,
+            });
+        }
+        self.pos = start;
+        self.skip_value();
+        Ok(Node::Value(
+            self.text[start..self.pos].trim_end().to_string(),
+        ))
+    }
+
+    /// Returns true if the parenthesis at the current position starts the fields of a structure.
+    fn starts_fields(&mut self) -> bool {
+        let start = self.pos;
+        self.pos += 1;
+        self.skip_whitespace();
+        let fields = self.identifier().is_some() && {
+            self.skip_whitespace();
+            self.rest().starts_with(':') && !self.rest().starts_with("::")
+        };
+        self.pos = start;
+        fields
+    }
+
+    fn fields(&mut self) -> Result<Vec<(String, Node)>, String> {
+        let mut fields = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(')') {
+                self.pos += 1;
+                return Ok(fields);
+            }
+            let start = self.pos;
+            let name = self
+                .identifier()
+                .ok_or_else(|| format!("Expected a field name at byte {}", self.pos))?;
+            self.skip_whitespace();
+            if self.peek() != Some(':') {
+                return Err(format!("Expected ':' at byte {}", self.pos));
+            }
+            self.pos += 1;
+            self.path.push(name);
+            let path = self.path.join(".");
+            let value = self.value();
+            self.spans.push((path, start, self.pos));
+            self.path.pop();
+            fields.push((name.to_string(), value?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(')') => {}
+                _ => return Err(format!("Expected ',' or ')' at byte {}", self.pos)),
+            }
+        }
+    }
+
+    /// Skips a value that isn't split, up to the next separator.
+    fn skip_value(&mut self) {
+        let mut depth = 0usize;
+        let mut previous = ' ';
+        while let Some(c) = self.peek() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' | ',' if depth == 0 => return,
+                ')' | ']' | '}' => depth -= 1,
+                '"' => {
+                    self.skip_string();
+                    previous = '"';
+                    continue;
+                }
+                'r' if !(previous.is_alphanumeric() || previous == '_')
+                    && self.rest()[1..].trim_start_matches('#').starts_with('"') =>
+                {
+                    self.skip_raw_string();
+                    previous = '"';
+                    continue;
+                }
+                '\'' => {
+                    self.skip_char();
+                    previous = '\'';
+                    continue;
+                }
+                '/' if self.rest().starts_with("//") || self.rest().starts_with("/*") => {
+                    self.skip_whitespace();
+                    previous = ' ';
+                    continue;
+                }
+                _ => {}
+            }
+            previous = c;
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn skip_string(&mut self) {
+        let mut escaped = false;
+        for (index, c) in self.rest().char_indices().skip(1) {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                self.pos += index + 1;
+                return;
+            }
+        }
+        self.pos = self.text.len();
+    }
+
+    fn skip_raw_string(&mut self) {
+        let hashes = self.rest()[1..].len() - self.rest()[1..].trim_start_matches('#').len();
+        let end = format!("\"{}", "#".repeat(hashes));
+        let start = 2 + hashes;
+        self.pos += self.rest()[start..]
+            .find(&end)
+            .map_or(self.rest().len(), |index| start + index + end.len());
+    }
+
+    fn skip_char(&mut self) {
+        let rest = self.rest();
+        let len = if rest[1..].starts_with('\\') {
+            // the escaped character may be a quote
+            rest.get(3..)
+                .and_then(|r| r.find('\''))
+                .map(|index| index + 4)
+        } else {
+            rest[1..].chars().next().map(|c| 1 + c.len_utf8() + 1)
+        };
+        self.pos += len.unwrap_or(rest.len()).min(rest.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Display {
+        title: String,
+        dimensions: Option<(u32, u32)>,
+        window: Window,
+        keys: HashMap<String, u32>,
+        note: String,
+        #[serde(rename = "vSync", default)]
+        v_sync: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Window {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        maximized: bool,
+    }
+
+    const BASE: &str = r##"#![enable(implicit_some)]
+// the base configuration, with ( and , in comments
+(
+    title: "Game, (the) \"first\"", // a title
+    dimensions: (800, 600),
+    window: Window(
+        width: 800,
+        height: 600, /* block ( comment */
+    ),
+    keys: { "jump": 1, "run": 2 },
+    note: r#"raw ) "string", "#,
+)
+"##;
+
+    const USER: &str = "(
+    window: (height: 720),
+    keys: { \"jump\": 3 },
+    vSync: true,
+)";
+
+    /// Writes the files of a test to a directory of its own.
+    fn files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("config_layers_{}_{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, text) in files {
+            fs::write(dir.join(name), text).unwrap();
+        }
+        dir
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn base_file_keeps_comments_strings_and_extensions() {
+        let dir = files("base", &[("base.ron", BASE)]);
+        let (display, report) = ConfigLayers::new(dir.join("base.ron"))
+            .load_with_vars::<Display, _>(vars(&[]))
+            .unwrap();
+
+        assert_eq!(display.title, "Game, (the) \"first\"");
+        assert_eq!(display.dimensions, Some((800, 600)));
+        assert_eq!(display.note, "raw ) \"string\", ");
+        assert_eq!(display.keys.len(), 2);
+        assert!(report
+            .iter()
+            .all(|(_, source)| *source == ConfigSource::Base(dir.join("base.ron"))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn layers_override_in_order() {
+        let dir = files("order", &[("base.ron", BASE), ("user.ron", USER)]);
+        let (base, user) = (dir.join("base.ron"), dir.join("user.ron"));
+        let (display, report) = ConfigLayers::new(&base)
+            .with_user_file(&user)
+            .with_env_prefix("GAME")
+            .load_with_vars::<Display, _>(vars(&[
+                ("GAME__TITLE", "\"Env\""),
+                ("GAME__WINDOW__MAXIMIZED", " true "),
+                ("GAME__VSYNC", "false"),
+                ("OTHER__TITLE", "\"Other\""),
+            ]))
+            .unwrap();
+
+        assert_eq!(display.title, "Env");
+        assert_eq!(display.dimensions, Some((800, 600)));
+        // Structures are merged field by field, other values like maps are replaced.
+        assert_eq!(
+            display.window,
+            Window {
+                width: 800,
+                height: 720,
+                maximized: true,
+            }
+        );
+        assert_eq!(display.keys.len(), 1);
+        assert_eq!(display.keys["jump"], 3);
+        assert!(!display.v_sync);
+
+        let env = |name: &str| ConfigSource::Env(name.to_string());
+        let sources = report
+            .iter()
+            .map(|(path, source)| (path.to_string(), source.clone()))
+            .collect::<Vec<_>>();
+        let expected = vec![
+            ("dimensions", ConfigSource::Base(base.clone())),
+            ("keys", ConfigSource::User(user.clone())),
+            ("note", ConfigSource::Base(base.clone())),
+            ("title", env("GAME__TITLE")),
+            ("vSync", env("GAME__VSYNC")),
+            ("window.height", ConfigSource::User(user.clone())),
+            ("window.maximized", env("GAME__WINDOW__MAXIMIZED")),
+            ("window.width", ConfigSource::Base(base.clone())),
+        ];
+        let expected = expected
+            .into_iter()
+            .map(|(path, source)| (path.to_string(), source))
+            .collect::<Vec<_>>();
+        assert_eq!(sources, expected);
+        assert!(report
+            .to_string()
+            .contains(&format!("window.height: {}\n", user.display())));
+        assert!(report.to_string().contains("title: $GAME__TITLE\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn environment_values_replace_whole_structures() {
+        let dir = files("structures", &[("base.ron", BASE)]);
+        let (display, report) = ConfigLayers::new(dir.join("base.ron"))
+            .with_env_prefix("GAME")
+            .load_with_vars::<Display, _>(vars(&[("GAME__WINDOW", "(width: 1920, height: 1080)")]))
+            .unwrap();
+
+        assert_eq!(
+            display.window,
+            Window {
+                width: 1920,
+                height: 1080,
+                maximized: false,
+            }
+        );
+        assert_eq!(report.source("window.width"), None);
+        assert_eq!(
+            report.source("window"),
+            Some(&ConfigSource::Env("GAME__WINDOW".to_string()))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_user_file_is_skipped() {
+        let dir = files("missing", &[("base.ron", BASE)]);
+        let (display, _) = ConfigLayers::new(dir.join("base.ron"))
+            .with_user_file(dir.join("user.ron"))
+            .load_with_vars::<Display, _>(vars(&[]))
+            .unwrap();
+        assert_eq!(display.window.height, 600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_layers_are_errors() {
+        let dir = files(
+            "invalid",
+            &[
+                ("base.txt", BASE),
+                ("broken.ron", "(title: \"Game\", 1: 2)"),
+            ],
+        );
+        match ConfigLayers::new(dir.join("base.txt")).load_with_vars::<Display, _>(vars(&[])) {
+            Err(ConfigError::Extension(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match ConfigLayers::new(dir.join("broken.ron")).load_with_vars::<Display, _>(vars(&[])) {
+            Err(ConfigError::Layer(_, msg)) => assert_eq!(msg, "Expected a field name at byte 16"),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_layers_are_merged() {
+        const TOML: &str = "
+vSync = true
+note = 'from TOML'
+
+[window]
+height = 900
+
+[keys]
+dash = 4
+";
+        let dir = files("toml", &[("base.ron", BASE), ("user.toml", TOML)]);
+        let user = dir.join("user.toml");
+        let (display, report) = ConfigLayers::new(dir.join("base.ron"))
+            .with_user_file(&user)
+            .load_with_vars::<Display, _>(vars(&[]))
+            .unwrap();
+
+        assert_eq!(display.note, "from TOML");
+        assert!(display.v_sync);
+        assert_eq!((display.window.width, display.window.height), (800, 900));
+        assert_eq!(display.keys.len(), 1);
+        assert_eq!(display.keys["dash"], 4);
+        assert_eq!(
+            report.source("window.height"),
+            Some(&ConfigSource::User(user))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_layers_are_merged() {
+        const YAML: &str = "
+vSync: true
+note: from YAML
+dimensions: null
+window:
+  height: 900
+keys:
+  dash: 4
+";
+        let dir = files("yaml", &[("base.ron", BASE), ("user.yaml", YAML)]);
+        let user = dir.join("user.yaml");
+        let (display, report) = ConfigLayers::new(dir.join("base.ron"))
+            .with_user_file(&user)
+            .load_with_vars::<Display, _>(vars(&[]))
+            .unwrap();
+
+        assert_eq!(display.note, "from YAML");
+        assert!(display.v_sync);
+        assert_eq!(display.dimensions, None);
+        assert_eq!((display.window.width, display.window.height), (800, 900));
+        assert_eq!(display.keys.len(), 1);
+        assert_eq!(display.keys["dash"], 4);
+        assert_eq!(
+            report.source("window.height"),
+            Some(&ConfigSource::User(user))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -23,6 +23,16 @@ use std::{
 use ron::{self, de::Error as DeError, ser::Error as SerError};
 use serde::{Deserialize, Serialize};
 
+pub use crate::{
+    deserialize::DeserializeError,
+    layers::{ConfigLayers, ConfigReport, ConfigSource},
+    watcher::ConfigWatcher,
+};
+
+mod deserialize;
+mod layers;
+mod watcher;
+
 /// Error related to anything that manages/creates configurations as well as
 /// "workspace"-related things.
 #[derive(Debug)]
@@ -31,10 +41,29 @@ pub enum ConfigError {
     File(io::Error),
     /// Errors related to serde's parsing of configuration files.
     Parser(DeError),
+    /// Errors deserializing a configuration file, with the field and position they occurred at.
+    Deserialize(Box<DeserializeError>),
     /// Occurs if a value is ill-formed during serialization (like a poisoned mutex).
     Serializer(SerError),
+    /// Errors related to serde's parsing of TOML configuration files.
+    #[cfg(feature = "toml")]
+    TomlParser(toml::de::Error),
+    /// Occurs if a value can't be written to a TOML configuration file.
+    #[cfg(feature = "toml")]
+    TomlSerializer(toml::ser::Error),
+    /// Errors related to serde's parsing of YAML configuration files.
+    #[cfg(feature = "yaml")]
+    YamlParser(serde_yaml::Error),
+    /// Occurs if a value can't be written to a YAML configuration file.
+    #[cfg(feature = "yaml")]
+    YamlSerializer(serde_yaml::Error),
     /// Related to the path of the file.
     Extension(PathBuf),
+    /// Occurs if a file of a `ConfigLayers` can't be split into fields to merge.
+    Layer(PathBuf, String),
+    /// Occurs if a configuration is rejected by `Validate::validate`, e.g. when loaded by
+    /// `Config::load_validated` or reloaded by a `ConfigWatcher`.
+    Invalid(PathBuf, String),
 }
 
 impl fmt::Display for ConfigError {
@@ -42,7 +71,16 @@ impl fmt::Display for ConfigError {
         match *self {
             ConfigError::File(ref err) => write!(f, "{}", err),
             ConfigError::Parser(ref msg) => write!(f, "{}", msg),
+            ConfigError::Deserialize(ref err) => write!(f, "{}", err),
             ConfigError::Serializer(ref msg) => write!(f, "{}", msg),
+            #[cfg(feature = "toml")]
+            ConfigError::TomlParser(ref msg) => write!(f, "{}", msg),
+            #[cfg(feature = "toml")]
+            ConfigError::TomlSerializer(ref msg) => write!(f, "{}", msg),
+            #[cfg(feature = "yaml")]
+            ConfigError::YamlParser(ref msg) | ConfigError::YamlSerializer(ref msg) => {
+                write!(f, "{}", msg)
+            }
             ConfigError::Extension(ref path) => {
                 let found = match path.extension() {
                     Some(extension) => format!("{:?}", extension),
@@ -51,11 +89,15 @@ impl fmt::Display for ConfigError {
 
                 write!(
                     f,
-                    "{}: Invalid path extension, expected \"ron\", got {}.",
-                    path.display().to_string(),
+                    "{}: Invalid path extension, expected {}, got {}.",
+                    path.display(),
+                    EXTENSIONS,
                     found,
                 )
             }
+            ConfigError::Layer(ref path, ref msg) | ConfigError::Invalid(ref path, ref msg) => {
+                write!(f, "{}: {}", path.display(), msg)
+            }
         }
     }
 }
@@ -78,25 +120,67 @@ impl From<SerError> for ConfigError {
     }
 }
 
+#[cfg(feature = "toml")]
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::TomlParser(e)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<toml::ser::Error> for ConfigError {
+    fn from(e: toml::ser::Error) -> Self {
+        ConfigError::TomlSerializer(e)
+    }
+}
+
 impl Error for ConfigError {
     fn description(&self) -> &str {
         match *self {
             ConfigError::File(_) => "Project file error",
-            ConfigError::Parser(_) => "Project parser error",
+            ConfigError::Parser(_) | ConfigError::Deserialize(_) => "Project parser error",
             ConfigError::Serializer(_) => "Project serializer error",
+            #[cfg(feature = "toml")]
+            ConfigError::TomlParser(_) => "Project parser error",
+            #[cfg(feature = "toml")]
+            ConfigError::TomlSerializer(_) => "Project serializer error",
+            #[cfg(feature = "yaml")]
+            ConfigError::YamlParser(_) => "Project parser error",
+            #[cfg(feature = "yaml")]
+            ConfigError::YamlSerializer(_) => "Project serializer error",
             ConfigError::Extension(_) => "Invalid extension or directory for a file",
+            ConfigError::Layer(..) => "Project layer error",
+            ConfigError::Invalid(..) => "Invalid project file",
         }
     }
 
     fn cause(&self) -> Option<&dyn Error> {
         match *self {
             ConfigError::File(ref err) => Some(err),
+            ConfigError::Deserialize(ref err) => Some(err.error()),
+            #[cfg(feature = "toml")]
+            ConfigError::TomlParser(ref err) => Some(err),
+            #[cfg(feature = "yaml")]
+            ConfigError::YamlParser(ref err) => Some(err),
             _ => None,
         }
     }
 }
 
+#[cfg(not(any(feature = "toml", feature = "yaml")))]
+const EXTENSIONS: &str = "\"ron\"";
+#[cfg(all(feature = "toml", not(feature = "yaml")))]
+const EXTENSIONS: &str = "\"ron\" or \"toml\"";
+#[cfg(all(not(feature = "toml"), feature = "yaml"))]
+const EXTENSIONS: &str = "\"ron\", \"yml\" or \"yaml\"";
+#[cfg(all(feature = "toml", feature = "yaml"))]
+const EXTENSIONS: &str = "\"ron\", \"toml\", \"yml\" or \"yaml\"";
+
 /// Trait implemented by the `config!` macro.
+///
+/// Files are read and written in RON, in TOML when their extension is `toml` and the `toml`
+/// feature is enabled, or in YAML when their extension is `yml` or `yaml` and the `yaml` feature
+/// is enabled.
 pub trait Config
 where
     Self: Sized,
@@ -110,13 +194,42 @@ where
         Self::load(path)
     }
 
-    /// Loads configuration structure from raw bytes.
+    /// Loads configuration structure from raw RON bytes.
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError>;
 
+    /// Loads a configuration structure from a file, and checks it with `Validate::validate`.
+    fn load_validated<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError>
+    where
+        Self: Validate,
+    {
+        let config = Self::load(&path)?;
+        config
+            .validate()
+            .map_err(|msg| ConfigError::Invalid(path.as_ref().to_path_buf(), msg))?;
+        Ok(config)
+    }
+
     /// Writes a configuration structure to a file.
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError>;
 }
 
+/// Semantic checks of a configuration, run by `Config::load_validated` once it's deserialized.
+///
+/// ```rust,ignore
+/// impl Validate for DisplayConfig {
+///     fn validate(&self) -> Result<(), String> {
+///         match self.dimensions {
+///             Some((0, _)) | Some((_, 0)) => Err("dimensions must be greater than 0".into()),
+///             _ => Ok(()),
+///         }
+///     }
+/// }
+/// ```
+pub trait Validate {
+    /// Returns a message explaining what's wrong if the configuration isn't valid.
+    fn validate(&self) -> Result<(), String>;
+}
+
 impl<T> Config for T
 where
     T: for<'a> Deserialize<'a> + Serialize,
@@ -134,28 +247,109 @@ where
             buffer
         };
 
-        if path.extension().and_then(std::ffi::OsStr::to_str) == Some("ron") {
-            Self::load_bytes(&content)
-        } else {
-            Err(ConfigError::Extension(path.to_path_buf()))
+        match path.extension().and_then(std::ffi::OsStr::to_str) {
+            Some("ron") => deserialize::deserialize(&content, Some(path)),
+            #[cfg(feature = "toml")]
+            Some("toml") => Ok(toml::from_slice(&content)?),
+            #[cfg(feature = "yaml")]
+            Some("yml") | Some("yaml") => {
+                serde_yaml::from_slice(&content).map_err(ConfigError::YamlParser)
+            }
+            _ => Err(ConfigError::Extension(path.to_path_buf())),
         }
     }
 
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError> {
-        let mut de = ron::de::Deserializer::from_bytes(bytes)?;
-        let val = T::deserialize(&mut de)?;
-        de.end()?;
-
-        Ok(val)
+        deserialize::deserialize(bytes, None)
     }
 
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
         use ron::ser::to_string_pretty;
         use std::{fs::File, io::Write};
 
-        let s = to_string_pretty(self, Default::default())?;
+        let path = path.as_ref();
+        let s = match path.extension().and_then(std::ffi::OsStr::to_str) {
+            // going through a `Value` writes the tables after the plain values, as TOML requires
+            #[cfg(feature = "toml")]
+            Some("toml") => toml::to_string_pretty(&toml::Value::try_from(self)?)?,
+            #[cfg(feature = "yaml")]
+            Some("yml") | Some("yaml") => {
+                serde_yaml::to_string(self).map_err(ConfigError::YamlSerializer)?
+            }
+            _ => to_string_pretty(self, Default::default())?,
+        };
         File::create(path)?.write_all(s.as_bytes())?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Display {
+        title: String,
+        dimensions: Option<(u32, u32)>,
+        window: Window,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Window {
+        width: u32,
+        maximized: bool,
+    }
+
+    fn display() -> Display {
+        Display {
+            title: "Game".to_string(),
+            dimensions: Some((800, 600)),
+            window: Window {
+                width: 800,
+                maximized: true,
+            },
+        }
+    }
+
+    /// Writes the display config to a file with the given extension and loads it back.
+    fn round_trip(extension: &str) -> Display {
+        let dir = std::env::temp_dir().join(format!("config_format_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("display.{}", extension));
+        display().write(&path).unwrap();
+        Display::load(&path).unwrap()
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        assert_eq!(round_trip("ron"), display());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_round_trip() {
+        assert_eq!(round_trip("toml"), display());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_round_trip() {
+        assert_eq!(round_trip("yml"), display());
+        assert_eq!(round_trip("yaml"), display());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_errors_are_reported() {
+        let dir = std::env::temp_dir().join(format!("config_yaml_error_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("display.yaml");
+        std::fs::write(&path, "title: Game\nwindow: [800\n").unwrap();
+        match Display::load(&path) {
+            Err(ConfigError::YamlParser(_)) => {}
+            other => panic!("expected a YAML parser error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Reloading of a configuration file when it changes on disk.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt, fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use crate::{Config, ConfigError, Validate};
+
+/// Watches a configuration file, loading it again when its content changes on disk.
+///
+/// The content is compared rather than the modification time, so writes within the resolution of
+/// the file system clock are noticed, and saving the file unchanged doesn't reload it.
+///
+/// ```rust,ignore
+/// let mut watcher = ConfigWatcher::<DisplayConfig>::new("config/display.ron").with_validation();
+/// if let Some(Ok(display)) = watcher.poll() {
+///     // apply the new configuration
+/// }
+/// ```
+pub struct ConfigWatcher<T> {
+    path: PathBuf,
+    fingerprint: Option<Fingerprint>,
+    validate: Option<fn(&T) -> Result<(), String>>,
+}
+
+/// Length and hash of the content of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fingerprint {
+    len: usize,
+    hash: u64,
+}
+
+impl<T> fmt::Debug for ConfigWatcher<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigWatcher")
+            .field("path", &self.path)
+            .field("fingerprint", &self.fingerprint)
+            .field("validate", &self.validate.is_some())
+            .finish()
+    }
+}
+
+impl<T: Config> ConfigWatcher<T> {
+    /// Creates a watcher of the file, which is considered loaded as it is now.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        ConfigWatcher {
+            fingerprint: fingerprint(&path),
+            path,
+            validate: None,
+        }
+    }
+
+    /// Checks reloaded configurations with `Validate::validate`, rejecting them when it returns
+    /// an error.
+    pub fn with_validation(mut self) -> Self
+    where
+        T: Validate,
+    {
+        self.validate = Some(T::validate);
+        self
+    }
+
+    /// Returns the path of the watched file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the file again if its content changed since it was last loaded, returning `None` if
+    /// it didn't.
+    ///
+    /// A file that fails to load or to validate isn't loaded again until it changes again.
+    pub fn poll(&mut self) -> Option<Result<T, ConfigError>> {
+        let fingerprint = fingerprint(&self.path);
+        if fingerprint.is_none() || fingerprint == self.fingerprint {
+            return None;
+        }
+        self.fingerprint = fingerprint;
+        Some(self.load())
+    }
+
+    fn load(&self) -> Result<T, ConfigError> {
+        let config = T::load(&self.path)?;
+        if let Some(validate) = self.validate {
+            validate(&config).map_err(|msg| ConfigError::Invalid(self.path.clone(), msg))?;
+        }
+        Ok(config)
+    }
+}
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let content = fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    Some(Fingerprint {
+        len: content.len(),
+        hash: hasher.finish(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Volume {
+        music: f32,
+    }
+
+    impl Validate for Volume {
+        fn validate(&self) -> Result<(), String> {
+            if self.music <= 1.0 {
+                Ok(())
+            } else {
+                Err("music is too loud".to_string())
+            }
+        }
+    }
+
+    fn path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("config_watcher_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(format!("{}.ron", test))
+    }
+
+    #[test]
+    fn reloads_changed_content() {
+        let path = path("changed");
+        Volume { music: 0.5 }.write(&path).unwrap();
+        let mut watcher = ConfigWatcher::<Volume>::new(&path);
+        assert!(watcher.poll().is_none());
+
+        // Written right away, likely with the same modification time.
+        Volume { music: 0.8 }.write(&path).unwrap();
+        assert_eq!(watcher.poll().unwrap().unwrap(), Volume { music: 0.8 });
+        assert!(watcher.poll().is_none());
+
+        // Saving the file unchanged doesn't reload it.
+        Volume { music: 0.8 }.write(&path).unwrap();
+        assert!(watcher.poll().is_none());
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.poll().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        let path = path("invalid");
+        Volume { music: 0.5 }.write(&path).unwrap();
+        let mut watcher = ConfigWatcher::<Volume>::new(&path).with_validation();
+
+        Volume { music: 2.0 }.write(&path).unwrap();
+        match watcher.poll() {
+            Some(Err(ConfigError::Invalid(_, msg))) => assert_eq!(msg, "music is too loud"),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(watcher.poll().is_none());
+
+        Volume { music: 1.0 }.write(&path).unwrap();
+        assert_eq!(watcher.poll().unwrap().unwrap(), Volume { music: 1.0 });
+        fs::remove_file(&path).unwrap();
+    }
+}
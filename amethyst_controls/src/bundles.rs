@@ -1,16 +1,44 @@
 use std::marker::PhantomData;
 
 use amethyst_core::{
-    bundle::SystemBundle,
+    bundle::{BundleError, SystemBundle},
     ecs::prelude::{DispatcherBuilder, World},
-    math::one,
     SystemDesc,
 };
 use amethyst_error::Error;
-use amethyst_input::BindingTypes;
+use amethyst_input::{BindingTypes, InputHandler};
 
 use super::*;
 
+/// The `ControlSettings` values set on a control bundle.
+///
+/// Only these are written to the resource, so that bundles added to the same world don't reset
+/// each other's settings to the defaults.
+#[derive(Debug, Default, Clone, Copy)]
+struct ControlSettingsOverrides {
+    speed: Option<f32>,
+    sprint_multiplier: Option<f32>,
+    sensitivity: Option<(f32, f32)>,
+}
+
+impl ControlSettingsOverrides {
+    fn write(self, world: &mut World) {
+        let mut settings = world
+            .entry::<ControlSettings>()
+            .or_insert_with(ControlSettings::default);
+        if let Some(speed) = self.speed {
+            settings.speed = speed;
+        }
+        if let Some(multiplier) = self.sprint_multiplier {
+            settings.sprint_multiplier = multiplier;
+        }
+        if let Some((x, y)) = self.sensitivity {
+            settings.sensitivity_x = x;
+            settings.sensitivity_y = y;
+        }
+    }
+}
+
 /// The bundle that creates a flying movement system.
 ///
 /// Note: Will not actually create a moving entity. It will only register the needed resources and
@@ -18,12 +46,21 @@ use super::*;
 ///
 /// You might want to add `"fly_movement"` and `"free_rotation"` as dependencies of the
 /// `TransformSystem` in order to apply changes made by these systems in the same frame.
-/// Adding this bundle will grab the mouse, hide it and keep it centered.
+/// Adding this bundle will grab the mouse, hide it and keep it centered, until Escape is
+/// pressed or the window loses the focus.
+///
+/// Fails with a `BundleError` if the `InputBundle` isn't added before it.
 ///
 /// # Type parameters
 ///
 /// * `T`: This are the keys the `InputHandler` is using for axes and actions. Often, this is a `StringBindings`.
 ///
+/// # Resources
+///
+/// The speed and sensitivity are written to the `ControlSettings` resource, which can be
+/// changed at runtime. The resource is inserted if no other control bundle inserted it yet, and
+/// only the values set on this bundle are written, keeping those of other control bundles.
+///
 /// # Systems
 ///
 /// This bundle adds the following systems:
@@ -34,12 +71,11 @@ use super::*;
 /// * `CursorHideSystem`
 #[derive(Debug)]
 pub struct FlyControlBundle<T: BindingTypes> {
-    sensitivity_x: f32,
-    sensitivity_y: f32,
-    speed: f32,
+    settings: ControlSettingsOverrides,
     right_input_axis: Option<T::Axis>,
     up_input_axis: Option<T::Axis>,
     forward_input_axis: Option<T::Axis>,
+    sprint_action: Option<T::Action>,
 }
 
 impl<T: BindingTypes> FlyControlBundle<T> {
@@ -50,25 +86,30 @@ impl<T: BindingTypes> FlyControlBundle<T> {
         forward_input_axis: Option<T::Axis>,
     ) -> Self {
         FlyControlBundle {
-            sensitivity_x: 1.0,
-            sensitivity_y: 1.0,
-            speed: one(),
+            settings: ControlSettingsOverrides::default(),
             right_input_axis,
             up_input_axis,
             forward_input_axis,
+            sprint_action: None,
         }
     }
 
     /// Alters the mouse sensitivy on this `FlyControlBundle`
     pub fn with_sensitivity(mut self, x: f32, y: f32) -> Self {
-        self.sensitivity_x = x;
-        self.sensitivity_y = y;
+        self.settings.sensitivity = Some((x, y));
         self
     }
 
     /// Alters the speed on this `FlyControlBundle`.
     pub fn with_speed(mut self, speed: f32) -> Self {
-        self.speed = speed;
+        self.settings.speed = Some(speed);
+        self
+    }
+
+    /// Speeds up the movement by `multiplier` while the given action is held down.
+    pub fn with_sprint(mut self, action: T::Action, multiplier: f32) -> Self {
+        self.sprint_action = Some(action);
+        self.settings.sprint_multiplier = Some(multiplier);
         self
     }
 }
@@ -79,19 +120,27 @@ impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for FlyControlBundle<T> {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
+        if !world.has_value::<InputHandler<T>>() {
+            return Err(
+                BundleError::missing_resource::<InputHandler<T>>("FlyControlBundle")
+                    .with_suggestion("add InputBundle before FlyControlBundle")
+                    .into(),
+            );
+        }
+        self.settings.write(world);
         builder.add(
             FlyMovementSystemDesc::<T>::new(
-                self.speed,
                 self.right_input_axis,
                 self.up_input_axis,
                 self.forward_input_axis,
+                self.sprint_action,
             )
             .build(world),
             "fly_movement",
             &[],
         );
         builder.add(
-            FreeRotationSystemDesc::new(self.sensitivity_x, self.sensitivity_y).build(world),
+            FreeRotationSystemDesc::default().build(world),
             "free_rotation",
             &[],
         );
@@ -109,12 +158,145 @@ impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for FlyControlBundle<T> {
     }
 }
 
+/// The bundle that creates a first person character controller.
+///
+/// Note: Will not actually create a character. It will only register the needed resources and
+/// systems for entities with a `FirstPersonControl`.
+///
+/// You might want to add `"first_person_control"` as a dependency of the `TransformSystem` in
+/// order to apply changes made by this system in the same frame.
+/// Adding this bundle will grab the mouse, hide it and keep it centered, until Escape is
+/// pressed or the window loses the focus.
+///
+/// Fails with a `BundleError` if the `InputBundle` isn't added before it.
+///
+/// # Type parameters
+///
+/// * `T`: This are the keys the `InputHandler` is using for axes and actions. Often, this is a `StringBindings`.
+///
+/// # Resources
+///
+/// The speed and sensitivity are written to the `ControlSettings` resource, which can be
+/// changed at runtime. The resource is inserted if no other control bundle inserted it yet, and
+/// only the values set on this bundle are written, keeping those of other control bundles.
+///
+/// # Systems
+///
+/// This bundle adds the following systems:
+///
+/// * `FirstPersonControlSystem`
+/// * `MouseFocusUpdateSystem`
+/// * `CursorHideSystem`
+#[derive(Debug)]
+pub struct FirstPersonControlBundle<T: BindingTypes> {
+    settings: ControlSettingsOverrides,
+    right_input_axis: Option<T::Axis>,
+    forward_input_axis: Option<T::Axis>,
+    jump_action: Option<T::Action>,
+    crouch_action: Option<T::Action>,
+    sprint_action: Option<T::Action>,
+}
+
+impl<T: BindingTypes> FirstPersonControlBundle<T> {
+    /// Builds a new first person control bundle using the provided axes as controls.
+    pub fn new(right_input_axis: Option<T::Axis>, forward_input_axis: Option<T::Axis>) -> Self {
+        FirstPersonControlBundle {
+            settings: ControlSettingsOverrides::default(),
+            right_input_axis,
+            forward_input_axis,
+            jump_action: None,
+            crouch_action: None,
+            sprint_action: None,
+        }
+    }
+
+    /// Alters the mouse sensitivy on this `FirstPersonControlBundle`.
+    pub fn with_sensitivity(mut self, x: f32, y: f32) -> Self {
+        self.settings.sensitivity = Some((x, y));
+        self
+    }
+
+    /// Alters the speed on this `FirstPersonControlBundle`.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.settings.speed = Some(speed);
+        self
+    }
+
+    /// Sends `FirstPersonEvent::Jump` when the given action is pressed.
+    pub fn with_jump(mut self, action: T::Action) -> Self {
+        self.jump_action = Some(action);
+        self
+    }
+
+    /// Crouches while the given action is held down.
+    pub fn with_crouch(mut self, action: T::Action) -> Self {
+        self.crouch_action = Some(action);
+        self
+    }
+
+    /// Speeds up the movement by `multiplier` while the given action is held down.
+    pub fn with_sprint(mut self, action: T::Action, multiplier: f32) -> Self {
+        self.sprint_action = Some(action);
+        self.settings.sprint_multiplier = Some(multiplier);
+        self
+    }
+}
+
+impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for FirstPersonControlBundle<T> {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        if !world.has_value::<InputHandler<T>>() {
+            return Err(BundleError::missing_resource::<InputHandler<T>>(
+                "FirstPersonControlBundle",
+            )
+            .with_suggestion("add InputBundle before FirstPersonControlBundle")
+            .into());
+        }
+        self.settings.write(world);
+        builder.add(
+            FirstPersonControlSystemDesc::<T>::new(
+                self.right_input_axis,
+                self.forward_input_axis,
+                self.jump_action,
+                self.crouch_action,
+                self.sprint_action,
+            )
+            .build(world),
+            "first_person_control",
+            &[],
+        );
+        builder.add(
+            MouseFocusUpdateSystemDesc::default().build(world),
+            "mouse_focus",
+            &["first_person_control"],
+        );
+        builder.add(
+            CursorHideSystemDesc::default().build(world),
+            "cursor_hide",
+            &["mouse_focus"],
+        );
+        Ok(())
+    }
+}
+
 /// The bundle that creates an arc ball movement system.
 /// Note: Will not actually create a moving entity. It will only register the needed resources and systems.
 /// The generic parameters A and B are the ones used in InputHandler<A,B>.
 /// You might want to add "fly_movement" and "free_rotation" as dependencies of the TransformSystem.
 /// Adding this bundle will grab the mouse, hide it and keep it centered.
 ///
+/// It also adds the `ArcBallControlSystem` for cameras with an `ArcBallControl`, which orbit,
+/// zoom and pan with the mouse. Set `HideCursor::hide` and `HideCursor::release_on_escape` to
+/// false to keep the cursor visible for editor-like cameras.
+///
+/// Fails with a `BundleError` if the `InputBundle` isn't added before it.
+///
+/// The sensitivity is written to the `ControlSettings` resource, keeping the speed of a
+/// `FlyControlBundle` or `FirstPersonControlBundle` added to the same dispatcher.
+///
 /// See the `arc_ball_camera` example to see how to use the arc ball camera.
 #[derive(Debug)]
 pub struct ArcBallControlBundle<T: BindingTypes> {
@@ -153,9 +335,29 @@ impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for ArcBallControlBundle<T> {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
+        if !world.has_value::<InputHandler<T>>() {
+            return Err(
+                BundleError::missing_resource::<InputHandler<T>>("ArcBallControlBundle")
+                    .with_suggestion("add InputBundle before ArcBallControlBundle")
+                    .into(),
+            );
+        }
+        // the speed belongs to the other control bundles, only the sensitivity is overwritten
+        {
+            let mut settings = world
+                .entry::<ControlSettings>()
+                .or_insert_with(ControlSettings::default);
+            settings.sensitivity_x = self.sensitivity_x;
+            settings.sensitivity_y = self.sensitivity_y;
+        }
         builder.add(ArcBallRotationSystem::default(), "arc_ball_rotation", &[]);
         builder.add(
-            FreeRotationSystemDesc::new(self.sensitivity_x, self.sensitivity_y).build(world),
+            ArcBallControlSystemDesc::<T>::default().build(world),
+            "arc_ball_control",
+            &[],
+        );
+        builder.add(
+            FreeRotationSystemDesc::default().build(world),
             "free_rotation",
             &[],
         );
@@ -172,3 +374,106 @@ impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for ArcBallControlBundle<T> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::WorldExt;
+    use amethyst_input::StringBindings;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert(InputHandler::<StringBindings>::new());
+        world
+    }
+
+    #[test]
+    fn arc_ball_bundle_keeps_the_fly_speed() {
+        let mut world = world();
+        // both bundles add a `free_rotation` system, so they're built into separate dispatchers
+        FlyControlBundle::<StringBindings>::new(None, None, None)
+            .with_speed(5.0)
+            .with_sprint("sprint".to_string(), 4.0)
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+        ArcBallControlBundle::<StringBindings>::new()
+            .with_sensitivity(0.5, 0.25)
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+
+        assert_eq!(
+            *world.read_resource::<ControlSettings>(),
+            ControlSettings {
+                speed: 5.0,
+                sprint_multiplier: 4.0,
+                sensitivity_x: 0.5,
+                sensitivity_y: 0.25,
+            }
+        );
+    }
+
+    #[test]
+    fn fly_bundle_keeps_the_arc_ball_sensitivity() {
+        let mut world = world();
+        ArcBallControlBundle::<StringBindings>::new()
+            .with_sensitivity(0.5, 0.25)
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+        FlyControlBundle::<StringBindings>::new(None, None, None)
+            .with_speed(5.0)
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+
+        assert_eq!(
+            *world.read_resource::<ControlSettings>(),
+            ControlSettings {
+                speed: 5.0,
+                sprint_multiplier: 2.0,
+                sensitivity_x: 0.5,
+                sensitivity_y: 0.25,
+            }
+        );
+    }
+
+    #[test]
+    fn first_person_bundle_keeps_the_arc_ball_sensitivity() {
+        let mut world = world();
+        ArcBallControlBundle::<StringBindings>::new()
+            .with_sensitivity(0.5, 0.25)
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+        FirstPersonControlBundle::<StringBindings>::new(None, None)
+            .with_speed(3.0)
+            .with_sprint("sprint".to_string(), 1.5)
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+
+        assert_eq!(
+            *world.read_resource::<ControlSettings>(),
+            ControlSettings {
+                speed: 3.0,
+                sprint_multiplier: 1.5,
+                sensitivity_x: 0.5,
+                sensitivity_y: 0.25,
+            }
+        );
+    }
+
+    #[test]
+    fn bundles_suggest_adding_the_input_bundle_first() {
+        let error = FlyControlBundle::<StringBindings>::new(None, None, None)
+            .build(&mut World::new(), &mut DispatcherBuilder::new())
+            .expect_err("FlyControlBundle built without InputBundle");
+        assert!(error.to_string().contains("InputHandler"));
+        assert!(error
+            .to_string()
+            .ends_with("add InputBundle before FlyControlBundle"));
+
+        let error = ArcBallControlBundle::<StringBindings>::new()
+            .build(&mut World::new(), &mut DispatcherBuilder::new())
+            .expect_err("ArcBallControlBundle built without InputBundle");
+        assert!(error
+            .to_string()
+            .ends_with("add InputBundle before ArcBallControlBundle"));
+    }
+}
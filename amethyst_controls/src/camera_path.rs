@@ -0,0 +1,436 @@
+use amethyst_assets::{Asset, AssetStorage, Handle, Processor};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::prelude::{
+        Component, DenseVecStorage, DispatcherBuilder, Entities, Entity, Join, Read, System, World,
+        Write, WriteStorage,
+    },
+    math::{UnitQuaternion, Vector3},
+    shrev::EventChannel,
+    timing::Time,
+    transform::Transform,
+};
+use amethyst_error::Error;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use crate::components::OrbitTarget;
+
+/// An asset handle to a camera path.
+pub type CameraPathHandle = Handle<CameraPath>;
+
+/// How the playback of a `CameraPath` speeds up and slows down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+    /// Starts slowly.
+    EaseIn,
+    /// Ends slowly.
+    EaseOut,
+    /// Starts and ends slowly.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the progress between 0.0 and 1.0 to the eased progress.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// How the positions between the keys of a `CameraPath` are computed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PathInterpolation {
+    /// Straight lines between the keys.
+    Linear,
+    /// A smooth Catmull-Rom spline through the keys.
+    #[default]
+    CatmullRom,
+}
+
+/// A point of a `CameraPath`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraKey {
+    /// Seconds from the start of the path.
+    pub time: f32,
+    /// Position of the camera.
+    pub position: Vector3<f32>,
+    /// Rotation of the camera, ignored while the `CameraPathPlayer` looks at a target.
+    #[serde(default)]
+    pub rotation: Option<UnitQuaternion<f32>>,
+}
+
+/// Path moving a camera for cutscenes, played by a `CameraPathPlayer`.
+///
+/// Paths are usually loaded from RON files with `RonFormat`:
+///
+/// ```ron
+/// (
+///     keys: [
+///         (time: 0.0, position: [0.0, 2.0, 10.0]),
+///         (time: 2.0, position: [5.0, 3.0, 5.0]),
+///         (time: 5.0, position: [0.0, 8.0, 0.0]),
+///     ],
+///     easing: EaseInOut,
+/// )
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    /// The keys of the path, sorted by time.
+    pub keys: Vec<CameraKey>,
+    /// How the positions between the keys are computed.
+    #[serde(default)]
+    pub interpolation: PathInterpolation,
+    /// How the playback speeds up and slows down over the whole path.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl Asset for CameraPath {
+    const NAME: &'static str = "controls::CameraPath";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<CameraPathHandle>;
+}
+
+impl CameraPath {
+    /// Returns the time of the last key.
+    pub fn duration(&self) -> f32 {
+        self.keys.last().map_or(0.0, |key| key.time)
+    }
+
+    /// Returns the position and rotation of the camera at the given time, with easing applied.
+    ///
+    /// The rotation is `None` if either key around the time has no rotation.
+    pub fn sample(&self, time: f32) -> Option<(Vector3<f32>, Option<UnitQuaternion<f32>>)> {
+        let first = self.keys.first()?;
+        let duration = self.duration();
+        if duration <= first.time {
+            return Some((first.position, first.rotation));
+        }
+        let start = first.time;
+        let time =
+            start + self.easing.apply((time - start) / (duration - start)) * (duration - start);
+
+        let next = self
+            .keys
+            .iter()
+            .position(|key| key.time > time)
+            .unwrap_or(self.keys.len() - 1)
+            .max(1);
+        let (a, b) = (&self.keys[next - 1], &self.keys[next]);
+        let t = if b.time > a.time {
+            ((time - a.time) / (b.time - a.time)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let position = match self.interpolation {
+            PathInterpolation::Linear => a.position.lerp(&b.position, t),
+            PathInterpolation::CatmullRom => {
+                let p0 = next
+                    .checked_sub(2)
+                    .and_then(|index| self.keys.get(index))
+                    .unwrap_or(a)
+                    .position;
+                let p3 = self.keys.get(next + 1).unwrap_or(b).position;
+                catmull_rom(p0, a.position, b.position, p3, t)
+            }
+        };
+        let rotation = match (a.rotation, b.rotation) {
+            (Some(from), Some(to)) => Some(from.slerp(&to, t)),
+            _ => None,
+        };
+        Some((position, rotation))
+    }
+}
+
+fn catmull_rom(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    p2: Vector3<f32>,
+    p3: Vector3<f32>,
+    t: f32,
+) -> Vector3<f32> {
+    let (t2, t3) = (t * t, t * t * t);
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Component moving a camera along a `CameraPath`.
+///
+/// A `CameraPathEvent::Finished` is sent when a path that doesn't loop reaches its end, e.g. to
+/// start the next shot of a cutscene.
+#[derive(Clone, Debug)]
+pub struct CameraPathPlayer {
+    /// The path to play.
+    pub path: CameraPathHandle,
+    /// Seconds since the start of the path.
+    pub time: f32,
+    /// Playback speed multiplier.
+    pub speed: f32,
+    /// Whether the path starts over after its end.
+    pub looping: bool,
+    /// What the camera looks at instead of the rotations of the path.
+    pub look_at: Option<OrbitTarget>,
+    /// Whether the path is playing.
+    pub playing: bool,
+}
+
+impl Component for CameraPathPlayer {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl CameraPathPlayer {
+    /// Plays the path from its start.
+    pub fn new(path: CameraPathHandle) -> Self {
+        CameraPathPlayer {
+            path,
+            time: 0.0,
+            speed: 1.0,
+            looping: false,
+            look_at: None,
+            playing: true,
+        }
+    }
+
+    /// Looks at the given target while playing.
+    pub fn looking_at(mut self, target: OrbitTarget) -> Self {
+        self.look_at = Some(target);
+        self
+    }
+
+    /// Starts the path over after its end.
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+}
+
+/// Event sent by the `CameraPathSystem`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraPathEvent {
+    /// The `CameraPathPlayer` of the entity reached the end of its path and stopped.
+    Finished(Entity),
+}
+
+/// The system that moves the cameras with a `CameraPathPlayer` along their paths.
+#[derive(Debug, Default)]
+pub struct CameraPathSystem;
+
+impl<'a> System<'a> for CameraPathSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, AssetStorage<CameraPath>>,
+        Write<'a, EventChannel<CameraPathEvent>>,
+        WriteStorage<'a, CameraPathPlayer>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, paths, mut events, mut players, mut transforms): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("camera_path_system");
+
+        let mut moved = Vec::new();
+        for (entity, player) in (&entities, &mut players).join() {
+            if !player.playing {
+                continue;
+            }
+            let path = match paths.get(&player.path) {
+                Some(path) => path,
+                None => continue,
+            };
+            player.time += time.delta_seconds() * player.speed;
+            let duration = path.duration();
+            if player.time >= duration {
+                if player.looping && duration > 0.0 {
+                    player.time %= duration;
+                } else {
+                    player.time = duration;
+                    player.playing = false;
+                    events.single_write(CameraPathEvent::Finished(entity));
+                }
+            }
+            if let Some((position, rotation)) = path.sample(player.time) {
+                let look_at = match player.look_at {
+                    Some(OrbitTarget::Entity(target)) => {
+                        transforms.get(target).map(|target| *target.translation())
+                    }
+                    Some(OrbitTarget::Point(point)) => Some(point),
+                    None => None,
+                };
+                moved.push((entity, position, rotation, look_at));
+            }
+        }
+        for (entity, position, rotation, look_at) in moved {
+            if let Some(transform) = transforms.get_mut(entity) {
+                *transform.translation_mut() = position;
+                if let Some(look_at) = look_at {
+                    transform.face_towards(look_at, Vector3::y());
+                } else if let Some(rotation) = rotation {
+                    *transform.rotation_mut() = rotation;
+                }
+            }
+        }
+    }
+}
+
+/// The bundle that plays `CameraPath`s.
+///
+/// # Systems
+///
+/// This bundle adds the following systems:
+///
+/// * `Processor<CameraPath>`
+/// * `CameraPathSystem`
+///
+/// You might want to add `"camera_path"` as a dependency of the `TransformSystem`.
+#[derive(Debug, Default)]
+pub struct CameraPathBundle;
+
+impl<'a, 'b> SystemBundle<'a, 'b> for CameraPathBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(Processor::<CameraPath>::new(), "camera_path_processor", &[]);
+        builder.add(CameraPathSystem, "camera_path", &["camera_path_processor"]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, RunNow, WorldExt};
+    use std::time::Duration;
+
+    fn path(interpolation: PathInterpolation, easing: Easing) -> CameraPath {
+        let key = |time, x| CameraKey {
+            time,
+            position: Vector3::new(x, 0.0, 0.0),
+            rotation: None,
+        };
+        CameraPath {
+            keys: vec![key(0.0, 0.0), key(1.0, 1.0), key(2.0, 4.0), key(3.0, 9.0)],
+            interpolation,
+            easing,
+        }
+    }
+
+    fn x_at(path: &CameraPath, time: f32) -> f32 {
+        path.sample(time).unwrap().0.x
+    }
+
+    #[test]
+    fn spline_passes_through_the_keys() {
+        let path = path(PathInterpolation::CatmullRom, Easing::Linear);
+        for (time, x) in &[(0.0, 0.0), (1.0, 1.0), (2.0, 4.0), (3.0, 9.0)] {
+            assert!((x_at(&path, *time) - x).abs() < 1e-5);
+        }
+        // The spline bends towards the neighbouring keys, unlike the straight lines.
+        let linear = self::path(PathInterpolation::Linear, Easing::Linear);
+        assert!((x_at(&linear, 1.5) - 2.5).abs() < 1e-5);
+        assert!(x_at(&path, 1.5) < 2.5);
+    }
+
+    #[test]
+    fn sampling_clamps_to_the_end_keys() {
+        let path = path(PathInterpolation::CatmullRom, Easing::Linear);
+        assert!((x_at(&path, -1.0) - 0.0).abs() < 1e-5);
+        assert!((x_at(&path, 10.0) - 9.0).abs() < 1e-5);
+        assert_eq!(CameraPath::default().sample(1.0), None);
+    }
+
+    #[test]
+    fn easing_is_clamped() {
+        for easing in &[
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-5);
+    }
+
+    fn run_player(looping: bool, seconds: &[u64]) -> (Vec<f32>, Vec<CameraPathEvent>) {
+        let mut world = World::new();
+        world.register::<CameraPathPlayer>();
+        world.register::<Transform>();
+        world.insert(Time::default());
+        world.insert(AssetStorage::<CameraPath>::new());
+        world.insert(EventChannel::<CameraPathEvent>::new());
+        let mut reader = world
+            .fetch_mut::<EventChannel<CameraPathEvent>>()
+            .register_reader();
+        let handle = world
+            .write_resource::<AssetStorage<CameraPath>>()
+            .insert(path(PathInterpolation::Linear, Easing::Linear));
+        let player = CameraPathPlayer::new(handle);
+        let camera = world
+            .create_entity()
+            .with(if looping { player.looping() } else { player })
+            .with(Transform::default())
+            .build();
+
+        let mut positions = Vec::new();
+        for seconds in seconds {
+            world
+                .write_resource::<Time>()
+                .set_delta_time(Duration::from_secs(*seconds));
+            CameraPathSystem.run_now(&world);
+            positions.push(
+                world
+                    .read_storage::<Transform>()
+                    .get(camera)
+                    .unwrap()
+                    .translation()
+                    .x,
+            );
+        }
+        let events = world
+            .read_resource::<EventChannel<CameraPathEvent>>()
+            .read(&mut reader)
+            .cloned()
+            .collect();
+        (positions, events)
+    }
+
+    #[test]
+    fn player_stops_at_the_end() {
+        let (positions, events) = run_player(false, &[0, 1, 4, 1]);
+        assert_eq!(positions, [0.0, 1.0, 9.0, 9.0]);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn player_loops() {
+        let (positions, events) = run_player(true, &[0, 2, 2]);
+        assert_eq!(positions, [0.0, 4.0, 1.0]);
+        assert!(events.is_empty());
+    }
+}
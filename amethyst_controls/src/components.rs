@@ -1,6 +1,10 @@
 use amethyst_assets::PrefabData;
-use amethyst_core::ecs::prelude::{Component, Entity, HashMapStorage, NullStorage, WriteStorage};
+use amethyst_core::{
+    ecs::prelude::{Component, Entity, HashMapStorage, NullStorage, WriteStorage},
+    math::{UnitQuaternion, Vector3},
+};
 use amethyst_error::Error;
+use winit::MouseButton;
 
 use serde::{Deserialize, Serialize};
 
@@ -29,6 +33,342 @@ impl Component for ArcBallControlTag {
     type Storage = HashMapStorage<ArcBallControlTag>;
 }
 
+/// What an `ArcBallControl` camera orbits around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrbitTarget {
+    /// The translation of the `Transform` of an entity, followed as it moves.
+    Entity(Entity),
+    /// A fixed point.
+    Point(Vector3<f32>),
+}
+
+/// Orbit camera controller, e.g. for editor views or inspecting a third person character.
+///
+/// The camera rotates around its target while the `rotate_button` is held down, zooms with the
+/// mouse wheel and pans while the `pan_button` is held down. The rotation uses the sensitivity
+/// of the `ControlSettings` resource. Unlike the `ArcBallControlTag`, it doesn't need the
+/// `FlyControlTag` and keeps the camera upright.
+///
+/// `yaw`, `pitch`, `distance` and `pan_offset` are the goals the camera moves to, smoothed over
+/// `smoothing` seconds. Setting them moves the camera like the mouse does.
+#[derive(Debug, Clone)]
+pub struct ArcBallControl {
+    /// What the camera orbits around.
+    pub target: OrbitTarget,
+    /// Rotation around the vertical axis in radians.
+    pub yaw: f32,
+    /// Rotation above the target in radians, positive values look down on it.
+    pub pitch: f32,
+    /// The distance from the target.
+    pub distance: f32,
+    /// Offset of the orbit center from the target, moved by panning.
+    pub pan_offset: Vector3<f32>,
+    /// The lowest and highest `pitch`.
+    pub pitch_limits: (f32, f32),
+    /// The smallest and largest `distance`.
+    pub distance_limits: (f32, f32),
+    /// Fraction of the distance zoomed per step of the mouse wheel.
+    pub zoom_speed: f32,
+    /// Fraction of the distance panned per pixel of mouse motion.
+    pub pan_speed: f32,
+    /// Mouse button to hold down for rotating, or `None` to disable rotating with the mouse.
+    pub rotate_button: Option<MouseButton>,
+    /// Mouse button to hold down for panning, or `None` to disable panning.
+    pub pan_button: Option<MouseButton>,
+    /// Time in seconds the camera takes to catch up with most of a change, or 0.0 to follow the
+    /// input directly.
+    pub smoothing: f32,
+    current: Option<(f32, f32, f32, Vector3<f32>)>,
+}
+
+impl Component for ArcBallControl {
+    type Storage = HashMapStorage<ArcBallControl>;
+}
+
+impl ArcBallControl {
+    /// Orbits around the target at the given distance, rotating with the right mouse button and
+    /// panning with the middle one.
+    pub fn new(target: OrbitTarget, distance: f32) -> Self {
+        let pitch_limit = std::f32::consts::FRAC_PI_2 - 0.01;
+        ArcBallControl {
+            target,
+            yaw: 0.0,
+            pitch: 0.0,
+            distance,
+            pan_offset: Vector3::zeros(),
+            pitch_limits: (-pitch_limit, pitch_limit),
+            distance_limits: (0.1, f32::MAX),
+            zoom_speed: 0.1,
+            pan_speed: 0.002,
+            rotate_button: Some(MouseButton::Right),
+            pan_button: Some(MouseButton::Middle),
+            smoothing: 0.0,
+            current: None,
+        }
+    }
+
+    /// Limits the pitch to the given range in radians.
+    pub fn with_pitch_limits(mut self, min: f32, max: f32) -> Self {
+        self.pitch_limits = (min, max);
+        self
+    }
+
+    /// Limits the distance to the given range.
+    pub fn with_distance_limits(mut self, min: f32, max: f32) -> Self {
+        self.distance_limits = (min, max);
+        self
+    }
+
+    /// Smooths the movement of the camera over the given time in seconds.
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Sets the mouse buttons to hold down for rotating and panning.
+    pub fn with_buttons(
+        mut self,
+        rotate_button: Option<MouseButton>,
+        pan_button: Option<MouseButton>,
+    ) -> Self {
+        self.rotate_button = rotate_button;
+        self.pan_button = pan_button;
+        self
+    }
+
+    /// Rotates the camera by the given angles in radians.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch)
+            .max(self.pitch_limits.0)
+            .min(self.pitch_limits.1);
+    }
+
+    /// Moves the camera closer for positive steps and farther for negative ones.
+    pub fn zoom(&mut self, steps: f32) {
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(steps))
+            .max(self.distance_limits.0)
+            .min(self.distance_limits.1);
+    }
+
+    /// Moves the orbit center along the view plane, following a mouse motion of the given pixels.
+    pub fn pan(&mut self, x: f32, y: f32) {
+        let scale = self.pan_speed * self.distance;
+        self.pan_offset += self.rotation(self.yaw, self.pitch) * Vector3::new(-x, y, 0.0) * scale;
+    }
+
+    fn rotation(&self, yaw: f32, pitch: f32) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&Vector3::y_axis(), yaw)
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -pitch)
+    }
+
+    /// Moves the smoothed state towards the goals over `delta_seconds`, and returns the
+    /// translation and rotation of the camera orbiting around `target`.
+    pub fn update(
+        &mut self,
+        target: Vector3<f32>,
+        delta_seconds: f32,
+    ) -> (Vector3<f32>, UnitQuaternion<f32>) {
+        let goal = (
+            self.yaw,
+            self.pitch,
+            self.distance,
+            target + self.pan_offset,
+        );
+        let (yaw, pitch, distance, center) = match self.current {
+            Some((yaw, pitch, distance, center)) if self.smoothing > 0.0 => {
+                let t = 1.0 - (-delta_seconds / self.smoothing).exp();
+                (
+                    yaw + (goal.0 - yaw) * t,
+                    pitch + (goal.1 - pitch) * t,
+                    distance + (goal.2 - distance) * t,
+                    center + (goal.3 - center) * t,
+                )
+            }
+            _ => goal,
+        };
+        self.current = Some((yaw, pitch, distance, center));
+        let rotation = self.rotation(yaw, pitch);
+        (
+            center + rotation * Vector3::new(0.0, 0.0, distance),
+            rotation,
+        )
+    }
+}
+
+/// Third person camera controller following a target entity from behind, e.g. a player
+/// character.
+///
+/// The camera is moved by the `FollowControlSystem` to the `offset` from the target, turned with
+/// the target, and looks at the `look_offset` from the target, moved ahead along the velocity of
+/// the target. Add a `CameraObstruction` resource to pull the camera in front of geometry
+/// blocking the view.
+#[derive(Debug, Clone)]
+pub struct FollowControl {
+    /// The entity followed by the camera.
+    pub target: Entity,
+    /// Position of the camera relative to the target, in the frame of the target, e.g. behind
+    /// and above it.
+    pub offset: Vector3<f32>,
+    /// Point looked at relative to the target, in the frame of the target, e.g. its head.
+    pub look_offset: Vector3<f32>,
+    /// Seconds of target movement the camera looks ahead of the target.
+    pub look_ahead: f32,
+    /// Time in seconds the camera takes to catch up with most of a movement, or 0.0 to follow the
+    /// target directly.
+    pub smoothing: f32,
+    /// Distance kept between the camera and the geometry found by the `CameraObstruction`.
+    pub obstruction_margin: f32,
+    last_target: Option<Vector3<f32>>,
+    current: Option<(Vector3<f32>, Vector3<f32>)>,
+}
+
+impl Component for FollowControl {
+    type Storage = HashMapStorage<FollowControl>;
+}
+
+impl FollowControl {
+    /// Follows the target from the given offset, looking at the target.
+    pub fn new(target: Entity, offset: Vector3<f32>) -> Self {
+        FollowControl {
+            target,
+            offset,
+            look_offset: Vector3::zeros(),
+            look_ahead: 0.0,
+            smoothing: 0.0,
+            obstruction_margin: 0.2,
+            last_target: None,
+            current: None,
+        }
+    }
+
+    /// Looks at the given point relative to the target instead of its origin.
+    pub fn with_look_offset(mut self, look_offset: Vector3<f32>) -> Self {
+        self.look_offset = look_offset;
+        self
+    }
+
+    /// Looks ahead of the target by the distance it moves in the given seconds.
+    pub fn with_look_ahead(mut self, look_ahead: f32) -> Self {
+        self.look_ahead = look_ahead;
+        self
+    }
+
+    /// Smooths the movement of the camera over the given time in seconds.
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Returns the translation and rotation of the camera following a target at the given
+    /// translation and rotation, `delta_seconds` after the last update.
+    ///
+    /// `obstruction` returns the distance from the looked at point to the first geometry towards
+    /// the camera, like `CameraObstruction`.
+    pub fn update(
+        &mut self,
+        translation: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        delta_seconds: f32,
+        obstruction: Option<&dyn Fn(&Vector3<f32>, &Vector3<f32>) -> Option<f32>>,
+    ) -> (Vector3<f32>, UnitQuaternion<f32>) {
+        let velocity = match self.last_target {
+            Some(last) if delta_seconds > 0.0 => (translation - last) / delta_seconds,
+            _ => Vector3::zeros(),
+        };
+        self.last_target = Some(translation);
+
+        let goal_position = translation + rotation * self.offset;
+        let goal_focus = translation + rotation * self.look_offset + velocity * self.look_ahead;
+        let (mut position, focus) = match self.current {
+            Some((position, focus)) if self.smoothing > 0.0 => {
+                let t = 1.0 - (-delta_seconds / self.smoothing).exp();
+                (
+                    position + (goal_position - position) * t,
+                    focus + (goal_focus - focus) * t,
+                )
+            }
+            _ => (goal_position, goal_focus),
+        };
+
+        let view = position - focus;
+        let distance = view.norm();
+        if let Some(hit) = obstruction.and_then(|obstruction| obstruction(&focus, &position)) {
+            if hit < distance {
+                position = focus + view * ((hit - self.obstruction_margin).max(0.0) / distance);
+            }
+        }
+        self.current = Some((position, focus));
+
+        let rotation = if distance > f32::EPSILON {
+            UnitQuaternion::face_towards(&view, &Vector3::y())
+        } else {
+            rotation
+        };
+        (position, rotation)
+    }
+}
+
+/// First person character controller, turning the body of the character around the vertical
+/// axis and tilting its camera up and down with the mouse, and moving it relative to where it
+/// faces.
+///
+/// Put it on the body of the character and the `Camera` on a child entity set as `camera`,
+/// e.g. at eye height. Without a `camera`, the body is tilted too. Jumping and crouching are
+/// sent as `FirstPersonEvent`s, so that the game or its physics engine can handle them.
+#[derive(Debug, Clone)]
+pub struct FirstPersonControl {
+    /// The child entity holding the camera, tilted up and down.
+    pub camera: Option<Entity>,
+    /// Rotation around the vertical axis in radians.
+    pub yaw: f32,
+    /// Rotation of the camera in radians, positive values look up.
+    pub pitch: f32,
+    /// The lowest and highest `pitch`.
+    pub pitch_limits: (f32, f32),
+    /// The factor applied to the speed while crouching.
+    pub crouch_speed: f32,
+    /// Whether the crouch action is held down.
+    pub crouched: bool,
+}
+
+impl Component for FirstPersonControl {
+    type Storage = HashMapStorage<FirstPersonControl>;
+}
+
+impl FirstPersonControl {
+    /// Creates a controller tilting the given camera entity.
+    pub fn new(camera: Option<Entity>) -> Self {
+        let pitch_limit = std::f32::consts::FRAC_PI_2 - 0.01;
+        FirstPersonControl {
+            camera,
+            yaw: 0.0,
+            pitch: 0.0,
+            pitch_limits: (-pitch_limit, pitch_limit),
+            crouch_speed: 0.5,
+            crouched: false,
+        }
+    }
+
+    /// Turns the character by the given angles in radians.
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch)
+            .max(self.pitch_limits.0)
+            .min(self.pitch_limits.1);
+    }
+
+    /// Returns the rotation of the body around the vertical axis.
+    pub fn body_rotation(&self) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.yaw)
+    }
+
+    /// Returns the tilt of the camera relative to the body.
+    pub fn camera_rotation(&self) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&Vector3::x_axis(), self.pitch)
+    }
+}
+
 /// `PrefabData` for loading control tags on an `Entity`
 ///
 /// Will always load a `FlyControlTag`
@@ -14,17 +14,27 @@
 #![allow(clippy::new_without_default)]
 
 pub use self::{
-    bundles::{ArcBallControlBundle, FlyControlBundle},
-    components::{ArcBallControlTag, ControlTagPrefab, FlyControlTag},
-    resources::{HideCursor, WindowFocus},
+    bundles::{ArcBallControlBundle, FirstPersonControlBundle, FlyControlBundle},
+    camera_path::{
+        CameraKey, CameraPath, CameraPathBundle, CameraPathEvent, CameraPathHandle,
+        CameraPathPlayer, CameraPathSystem, Easing, PathInterpolation,
+    },
+    components::{
+        ArcBallControl, ArcBallControlTag, ControlTagPrefab, FirstPersonControl, FlyControlTag,
+        FollowControl, OrbitTarget,
+    },
+    resources::{CameraObstruction, ControlSettings, HideCursor, WindowFocus},
     systems::{
-        ArcBallRotationSystem, CursorHideSystem, CursorHideSystemDesc, FlyMovementSystem,
-        FlyMovementSystemDesc, FreeRotationSystem, FreeRotationSystemDesc, MouseFocusUpdateSystem,
+        ArcBallControlSystem, ArcBallControlSystemDesc, ArcBallRotationSystem, CursorHideSystem,
+        CursorHideSystemDesc, FirstPersonControlSystem, FirstPersonControlSystemDesc,
+        FirstPersonEvent, FlyMovementSystem, FlyMovementSystemDesc, FollowControlSystem,
+        FreeRotationSystem, FreeRotationSystemDesc, MouseFocusUpdateSystem,
         MouseFocusUpdateSystemDesc,
     },
 };
 
 mod bundles;
+mod camera_path;
 mod components;
 mod resources;
 mod systems;
@@ -1,3 +1,4 @@
+use amethyst_core::math::Vector3;
 use serde::{Deserialize, Serialize};
 
 /// Struct which holds information about whether the window is focused.
@@ -21,10 +22,70 @@ impl WindowFocus {
 pub struct HideCursor {
     /// If true this system will take control of the cursor.
     pub hide: bool,
+    /// If true the MouseFocusUpdateSystem releases the cursor when Escape is pressed, and grabs
+    /// it again when the window is clicked afterwards. Setting `hide` to false yourself leaves
+    /// the cursor released until `hide` is set back to true.
+    #[serde(default)]
+    pub release_on_escape: bool,
 }
 
 impl Default for HideCursor {
     fn default() -> Self {
-        HideCursor { hide: true }
+        HideCursor {
+            hide: true,
+            release_on_escape: true,
+        }
+    }
+}
+
+/// Resource holding the speed and mouse sensitivity of the camera controls, so that they can be
+/// changed at runtime, e.g. from an options menu.
+///
+/// It's inserted by the `FlyControlBundle`, the `FirstPersonControlBundle` and the
+/// `ArcBallControlBundle`, which each write the values set on them and keep the others. The
+/// `ArcBallControlBundle` only writes the sensitivity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ControlSettings {
+    /// The movement speed in units per second.
+    pub speed: f32,
+    /// The factor applied to the speed while the sprint action is held down.
+    pub sprint_multiplier: f32,
+    /// The horizontal mouse sensitivity, in degrees per pixel of mouse motion.
+    pub sensitivity_x: f32,
+    /// The vertical mouse sensitivity, in degrees per pixel of mouse motion.
+    pub sensitivity_y: f32,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        ControlSettings {
+            speed: 1.0,
+            sprint_multiplier: 2.0,
+            sensitivity_x: 1.0,
+            sensitivity_y: 1.0,
+        }
+    }
+}
+
+/// Resource pulling `FollowControl` cameras in front of geometry blocking the view of their
+/// target, e.g. with a raycast of a physics engine.
+///
+/// The function gets the point the camera looks at and the position of the camera, and returns
+/// the distance from the point to the first obstruction towards the camera, if any.
+#[allow(missing_debug_implementations)]
+pub struct CameraObstruction {
+    /// The function finding obstructions.
+    pub function: Box<dyn Fn(&Vector3<f32>, &Vector3<f32>) -> Option<f32> + Send + Sync>,
+}
+
+impl CameraObstruction {
+    /// Creates a new resource with the given function.
+    pub fn new<F>(function: F) -> Self
+    where
+        F: Fn(&Vector3<f32>, &Vector3<f32>) -> Option<f32> + Send + Sync + 'static,
+    {
+        CameraObstruction {
+            function: Box::new(function),
+        }
     }
 }
@@ -1,11 +1,16 @@
 use derive_new::new;
-use winit::{DeviceEvent, Event, Window, WindowEvent};
+use std::marker::PhantomData;
+use winit::{
+    DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent,
+};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 use amethyst_core::{
-    ecs::prelude::{Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage},
+    ecs::prelude::{
+        Entities, Entity, Join, Read, ReadStorage, System, SystemData, Write, WriteStorage,
+    },
     math::{convert, Unit, Vector3},
     shrev::{EventChannel, ReaderId},
     timing::Time,
@@ -13,14 +18,21 @@ use amethyst_core::{
 };
 use amethyst_derive::SystemDesc;
 use amethyst_input::{get_input_axis_simple, BindingTypes, InputHandler};
+use amethyst_window::{CursorGrab, CursorState};
 
 use crate::{
-    components::{ArcBallControlTag, FlyControlTag},
-    resources::{HideCursor, WindowFocus},
+    components::{
+        ArcBallControl, ArcBallControlTag, FirstPersonControl, FlyControlTag, FollowControl,
+        OrbitTarget,
+    },
+    resources::{CameraObstruction, ControlSettings, HideCursor, WindowFocus},
 };
 
 /// The system that manages the fly movement.
 ///
+/// The speed is read from the `ControlSettings` resource, and multiplied by its
+/// `sprint_multiplier` while the sprint action is held down.
+///
 /// # Type parameters
 ///
 /// * `T`: This are the keys the `InputHandler` is using for axes and actions. Often, this is a `StringBindings`.
@@ -30,29 +42,29 @@ pub struct FlyMovementSystem<T>
 where
     T: BindingTypes,
 {
-    /// The movement speed of the movement in units per second.
-    speed: f32,
     /// The name of the input axis to locally move in the x coordinates.
     right_input_axis: Option<T::Axis>,
     /// The name of the input axis to locally move in the y coordinates.
     up_input_axis: Option<T::Axis>,
     /// The name of the input axis to locally move in the z coordinates.
     forward_input_axis: Option<T::Axis>,
+    /// The name of the input action speeding up the movement.
+    sprint_action: Option<T::Action>,
 }
 
 impl<T: BindingTypes> FlyMovementSystem<T> {
-    /// Builds a new `FlyMovementSystem` using the provided speeds and axis controls.
+    /// Builds a new `FlyMovementSystem` using the provided axis and action controls.
     pub fn new(
-        speed: f32,
         right_input_axis: Option<T::Axis>,
         up_input_axis: Option<T::Axis>,
         forward_input_axis: Option<T::Axis>,
+        sprint_action: Option<T::Action>,
     ) -> Self {
         FlyMovementSystem {
-            speed,
             right_input_axis,
             up_input_axis,
             forward_input_axis,
+            sprint_action,
         }
     }
 }
@@ -63,20 +75,31 @@ impl<'a, T: BindingTypes> System<'a> for FlyMovementSystem<T> {
         WriteStorage<'a, Transform>,
         Read<'a, InputHandler<T>>,
         ReadStorage<'a, FlyControlTag>,
+        Read<'a, ControlSettings>,
     );
 
-    fn run(&mut self, (time, mut transform, input, tag): Self::SystemData) {
+    fn run(&mut self, (time, mut transform, input, tag, settings): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("fly_movement_system");
 
         let x = get_input_axis_simple(&self.right_input_axis, &input);
         let y = get_input_axis_simple(&self.up_input_axis, &input);
         let z = get_input_axis_simple(&self.forward_input_axis, &input);
+        let sprinting = self
+            .sprint_action
+            .as_ref()
+            .and_then(|action| input.action_is_down(action))
+            .unwrap_or(false);
+        let speed = if sprinting {
+            settings.speed * settings.sprint_multiplier
+        } else {
+            settings.speed
+        };
 
         if let Some(dir) = Unit::try_new(Vector3::new(x, y, z), convert(1.0e-6)) {
             for (transform, _) in (&mut transform, &tag).join() {
                 let delta_sec = time.delta_seconds();
-                transform.append_translation_along(dir, delta_sec * self.speed);
+                transform.append_translation_along(dir, delta_sec * speed);
             }
         }
     }
@@ -116,9 +139,335 @@ impl<'a> System<'a> for ArcBallRotationSystem {
     }
 }
 
+/// The system that moves the cameras with an `ArcBallControl` around their targets, following
+/// the mouse motion and wheel.
+///
+/// Mouse motion is ignored while the window isn't focused (`WindowFocus` resource).
+///
+/// # Type parameters
+///
+/// * `T`: This are the keys the `InputHandler` is using for axes and actions. Often, this is a `StringBindings`.
+#[derive(Debug, SystemDesc)]
+#[system_desc(name(ArcBallControlSystemDesc))]
+pub struct ArcBallControlSystem<T>
+where
+    T: BindingTypes,
+{
+    #[system_desc(event_channel_reader)]
+    event_reader: ReaderId<Event>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: BindingTypes> ArcBallControlSystem<T> {
+    /// Builds a new `ArcBallControlSystem` reading the mouse motion from the given reader.
+    pub fn new(event_reader: ReaderId<Event>) -> Self {
+        ArcBallControlSystem {
+            event_reader,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: BindingTypes> System<'a> for ArcBallControlSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, EventChannel<Event>>,
+        Read<'a, InputHandler<T>>,
+        Read<'a, WindowFocus>,
+        Read<'a, ControlSettings>,
+        WriteStorage<'a, ArcBallControl>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, events, input, focus, settings, mut controls, mut transforms): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("arc_ball_control_system");
+
+        let focused = focus.is_focused;
+        let (mut x, mut y) = (0.0, 0.0);
+        for event in events.read(&mut self.event_reader) {
+            if !focused {
+                continue;
+            }
+            if let Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } = *event
+            {
+                x += delta.0 as f32;
+                y += delta.1 as f32;
+            }
+        }
+        let scroll = input.mouse_wheel_value(false);
+        let is_down = |button: Option<MouseButton>| button.map(|b| input.mouse_button_is_down(b));
+
+        let mut moved = Vec::new();
+        for (entity, control) in (&entities, &mut controls).join() {
+            if is_down(control.pan_button) == Some(true) {
+                control.pan(x, y);
+            } else if is_down(control.rotate_button) == Some(true) {
+                control.rotate(
+                    (-x * settings.sensitivity_x).to_radians(),
+                    (y * settings.sensitivity_y).to_radians(),
+                );
+            }
+            if scroll != 0.0 {
+                control.zoom(scroll);
+            }
+            let target = match control.target {
+                OrbitTarget::Entity(target) => match transforms.get(target) {
+                    Some(transform) => *transform.translation(),
+                    None => continue,
+                },
+                OrbitTarget::Point(point) => point,
+            };
+            moved.push((entity, control.update(target, time.delta_seconds())));
+        }
+        for (entity, (translation, rotation)) in moved {
+            if let Some(transform) = transforms.get_mut(entity) {
+                *transform.translation_mut() = translation;
+                *transform.rotation_mut() = rotation;
+            }
+        }
+    }
+}
+
+/// The system that moves the cameras with a `FollowControl` behind their targets, pulling them
+/// in front of the obstructions found by the `CameraObstruction` resource if there is one.
+///
+/// You might want to add `"follow_control"` as a dependency of the `TransformSystem`, after the
+/// systems moving the targets.
+#[derive(Debug, Default)]
+pub struct FollowControlSystem;
+
+impl<'a> System<'a> for FollowControlSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Option<Read<'a, CameraObstruction>>,
+        WriteStorage<'a, FollowControl>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, obstruction, mut controls, mut transforms): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("follow_control_system");
+
+        let obstruction = obstruction
+            .as_ref()
+            .map(|obstruction| &*obstruction.function as _);
+        let mut moved = Vec::new();
+        for (entity, control) in (&entities, &mut controls).join() {
+            if let Some(target) = transforms.get(control.target) {
+                moved.push((
+                    entity,
+                    control.update(
+                        *target.translation(),
+                        *target.rotation(),
+                        time.delta_seconds(),
+                        obstruction,
+                    ),
+                ));
+            }
+        }
+        for (entity, (translation, rotation)) in moved {
+            if let Some(transform) = transforms.get_mut(entity) {
+                *transform.translation_mut() = translation;
+                *transform.rotation_mut() = rotation;
+            }
+        }
+    }
+}
+
+/// Event sent by the `FirstPersonControlSystem` for the actions the game handles itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirstPersonEvent {
+    /// The jump action was pressed for the character entity.
+    Jump(Entity),
+    /// The crouch action was pressed for the character entity.
+    CrouchStarted(Entity),
+    /// The crouch action was released for the character entity.
+    CrouchEnded(Entity),
+}
+
+/// The system that turns and moves the characters with a `FirstPersonControl`.
+///
+/// The mouse turns the characters while the window is focused and the `HideCursor` resource
+/// hides the cursor, with the sensitivity of the `ControlSettings` resource. The movement axes
+/// move them along the ground relative to where they face, at the speed of the
+/// `ControlSettings`. Jumping and crouching are sent as `FirstPersonEvent`s.
+///
+/// # Type parameters
+///
+/// * `T`: This are the keys the `InputHandler` is using for axes and actions. Often, this is a `StringBindings`.
+#[derive(Debug, SystemDesc)]
+#[system_desc(name(FirstPersonControlSystemDesc))]
+pub struct FirstPersonControlSystem<T>
+where
+    T: BindingTypes,
+{
+    /// The name of the input axis to move sideways.
+    right_input_axis: Option<T::Axis>,
+    /// The name of the input axis to move forwards, with negative values like the
+    /// `FlyMovementSystem`.
+    forward_input_axis: Option<T::Axis>,
+    /// The name of the input action to jump.
+    jump_action: Option<T::Action>,
+    /// The name of the input action to crouch.
+    crouch_action: Option<T::Action>,
+    /// The name of the input action speeding up the movement.
+    sprint_action: Option<T::Action>,
+    #[system_desc(event_channel_reader)]
+    event_reader: ReaderId<Event>,
+    #[system_desc(skip)]
+    jump_was_down: bool,
+}
+
+impl<T: BindingTypes> FirstPersonControlSystem<T> {
+    /// Builds a new `FirstPersonControlSystem` using the provided axis and action controls.
+    pub fn new(
+        right_input_axis: Option<T::Axis>,
+        forward_input_axis: Option<T::Axis>,
+        jump_action: Option<T::Action>,
+        crouch_action: Option<T::Action>,
+        sprint_action: Option<T::Action>,
+        event_reader: ReaderId<Event>,
+    ) -> Self {
+        FirstPersonControlSystem {
+            right_input_axis,
+            forward_input_axis,
+            jump_action,
+            crouch_action,
+            sprint_action,
+            event_reader,
+            jump_was_down: false,
+        }
+    }
+}
+
+impl<'a, T: BindingTypes> System<'a> for FirstPersonControlSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, EventChannel<Event>>,
+        Read<'a, InputHandler<T>>,
+        Read<'a, ControlSettings>,
+        Read<'a, WindowFocus>,
+        Read<'a, HideCursor>,
+        Write<'a, EventChannel<FirstPersonEvent>>,
+        WriteStorage<'a, FirstPersonControl>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("first_person_control_system");
+
+        let (
+            entities,
+            time,
+            events,
+            input,
+            settings,
+            focus,
+            hide,
+            mut first_person_events,
+            mut controls,
+            mut transforms,
+        ) = data;
+
+        let (mut x, mut y) = (0.0, 0.0);
+        for event in events.read(&mut self.event_reader) {
+            if let Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } = *event
+            {
+                x += delta.0 as f32;
+                y += delta.1 as f32;
+            }
+        }
+        if !focus.is_focused || !hide.hide {
+            x = 0.0;
+            y = 0.0;
+        }
+
+        let is_down = |action: &Option<T::Action>| {
+            action
+                .as_ref()
+                .and_then(|action| input.action_is_down(action))
+                .unwrap_or(false)
+        };
+        let jump_down = is_down(&self.jump_action);
+        let jumped = jump_down && !self.jump_was_down;
+        self.jump_was_down = jump_down;
+        let crouch_down = is_down(&self.crouch_action);
+        let speed = if is_down(&self.sprint_action) {
+            settings.speed * settings.sprint_multiplier
+        } else {
+            settings.speed
+        };
+        let movement = Vector3::new(
+            get_input_axis_simple(&self.right_input_axis, &input),
+            0.0,
+            get_input_axis_simple(&self.forward_input_axis, &input),
+        );
+        let movement = if movement.norm() > 1.0 {
+            movement.normalize()
+        } else {
+            movement
+        };
+
+        for (entity, control) in (&entities, &mut controls).join() {
+            control.look(
+                (-x * settings.sensitivity_x).to_radians(),
+                (-y * settings.sensitivity_y).to_radians(),
+            );
+            if jumped {
+                first_person_events.single_write(FirstPersonEvent::Jump(entity));
+            }
+            if crouch_down != control.crouched {
+                control.crouched = crouch_down;
+                first_person_events.single_write(if crouch_down {
+                    FirstPersonEvent::CrouchStarted(entity)
+                } else {
+                    FirstPersonEvent::CrouchEnded(entity)
+                });
+            }
+            let speed = if control.crouched {
+                speed * control.crouch_speed
+            } else {
+                speed
+            };
+
+            let body_rotation = control.body_rotation();
+            if let Some(transform) = transforms.get_mut(entity) {
+                *transform.translation_mut() +=
+                    body_rotation * movement * speed * time.delta_seconds();
+                *transform.rotation_mut() = match control.camera {
+                    Some(_) => body_rotation,
+                    None => body_rotation * control.camera_rotation(),
+                };
+            }
+            if let Some(camera) = control.camera {
+                if let Some(transform) = transforms.get_mut(camera) {
+                    *transform.rotation_mut() = control.camera_rotation();
+                }
+            }
+        }
+    }
+}
+
 /// The system that manages the view rotation.
 ///
-/// Controlled by the mouse.
+/// Controlled by the mouse, with the sensitivity of the `ControlSettings` resource.
 /// Goes into an inactive state if the window is not focused (`WindowFocus` resource).
 ///
 /// Can be manually disabled by making the mouse visible using the `HideCursor` resource:
@@ -126,8 +475,6 @@ impl<'a> System<'a> for ArcBallRotationSystem {
 #[derive(Debug, SystemDesc, new)]
 #[system_desc(name(FreeRotationSystemDesc))]
 pub struct FreeRotationSystem {
-    sensitivity_x: f32,
-    sensitivity_y: f32,
     #[system_desc(event_channel_reader)]
     event_reader: ReaderId<Event>,
 }
@@ -139,9 +486,10 @@ impl<'a> System<'a> for FreeRotationSystem {
         ReadStorage<'a, FlyControlTag>,
         Read<'a, WindowFocus>,
         Read<'a, HideCursor>,
+        Read<'a, ControlSettings>,
     );
 
-    fn run(&mut self, (events, mut transform, tag, focus, hide): Self::SystemData) {
+    fn run(&mut self, (events, mut transform, tag, focus, hide, settings): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("free_rotation_system");
 
@@ -152,10 +500,10 @@ impl<'a> System<'a> for FreeRotationSystem {
                     if let DeviceEvent::MouseMotion { delta: (x, y) } = *event {
                         for (transform, _) in (&mut transform, &tag).join() {
                             transform.append_rotation_x_axis(
-                                (-(y as f32) * self.sensitivity_y).to_radians(),
+                                (-(y as f32) * settings.sensitivity_y).to_radians(),
                             );
                             transform.prepend_rotation_y_axis(
-                                (-(x as f32) * self.sensitivity_x).to_radians(),
+                                (-(x as f32) * settings.sensitivity_x).to_radians(),
                             );
                         }
                     }
@@ -165,32 +513,69 @@ impl<'a> System<'a> for FreeRotationSystem {
     }
 }
 
-/// A system which reads Events and saves if a window has lost focus in a WindowFocus resource
+/// A system which reads Events and saves if a window has lost focus in a WindowFocus resource.
+///
+/// It also releases the cursor by setting `HideCursor::hide` to false when Escape is pressed,
+/// and grabs it again when the window is clicked afterwards, unless
+/// `HideCursor::release_on_escape` is false. A cursor shown by setting `HideCursor::hide` to
+/// false elsewhere, e.g. for a menu, isn't grabbed on clicks.
 #[derive(Debug, SystemDesc, new)]
 #[system_desc(name(MouseFocusUpdateSystemDesc))]
 pub struct MouseFocusUpdateSystem {
     #[system_desc(event_channel_reader)]
     event_reader: ReaderId<Event>,
+    /// Whether the cursor was released by this system on Escape.
+    #[new(default)]
+    #[system_desc(skip)]
+    released: bool,
 }
 
 impl<'a> System<'a> for MouseFocusUpdateSystem {
-    type SystemData = (Read<'a, EventChannel<Event>>, Write<'a, WindowFocus>);
+    type SystemData = (
+        Read<'a, EventChannel<Event>>,
+        Write<'a, WindowFocus>,
+        Write<'a, HideCursor>,
+    );
 
-    fn run(&mut self, (events, mut focus): Self::SystemData) {
+    fn run(&mut self, (events, mut focus, mut hide): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("mouse_focus_update_system");
 
+        if hide.hide {
+            self.released = false;
+        }
         for event in events.read(&mut self.event_reader) {
             if let Event::WindowEvent { ref event, .. } = *event {
-                if let WindowEvent::Focused(focused) = *event {
-                    focus.is_focused = focused;
+                match *event {
+                    WindowEvent::Focused(focused) => focus.is_focused = focused,
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } if hide.release_on_escape && hide.hide => {
+                        hide.hide = false;
+                        self.released = true;
+                    }
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        ..
+                    } if hide.release_on_escape && self.released => {
+                        hide.hide = true;
+                        self.released = false;
+                    }
+                    _ => {}
                 }
             }
         }
     }
 }
 
-/// System which hides the cursor when the window is focused.
+/// System which hides and confines the cursor through the `CursorState` when the window is
+/// focused.
 /// Requires the usage MouseFocusUpdateSystem at the same time.
 #[derive(Debug, SystemDesc, new)]
 #[system_desc(name(CursorHideSystemDesc))]
@@ -208,28 +593,423 @@ impl Default for CursorHideSystem {
 
 impl<'a> System<'a> for CursorHideSystem {
     type SystemData = (
-        ReadExpect<'a, Window>,
+        Write<'a, CursorState>,
         Read<'a, HideCursor>,
         Read<'a, WindowFocus>,
     );
 
-    fn run(&mut self, (win, hide, focus): Self::SystemData) {
+    fn run(&mut self, (mut cursor, hide, focus): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("cursor_hide_system");
 
         let should_be_hidden = focus.is_focused && hide.hide;
-        if !self.is_hidden && should_be_hidden {
-            if let Err(err) = win.grab_cursor(true) {
-                log::error!("Unable to grab the cursor. Error: {:?}", err);
-            }
-            win.hide_cursor(true);
-            self.is_hidden = true;
-        } else if self.is_hidden && !should_be_hidden {
-            if let Err(err) = win.grab_cursor(false) {
-                log::error!("Unable to release the cursor. Error: {:?}", err);
-            }
-            win.hide_cursor(false);
-            self.is_hidden = false;
+        if self.is_hidden != should_be_hidden {
+            cursor.visible = !should_be_hidden;
+            cursor.grab = if should_be_hidden {
+                CursorGrab::Confined
+            } else {
+                CursorGrab::Free
+            };
+            self.is_hidden = should_be_hidden;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::{
+        ecs::{Builder, RunNow, World, WorldExt},
+        math::UnitQuaternion,
+    };
+    use amethyst_input::{Axis, Button, InputEvent, StringBindings};
+    use std::time::Duration;
+    use winit::{DeviceId, ModifiersState, MouseScrollDelta, WindowId};
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<FlyControlTag>();
+        world.register::<ArcBallControl>();
+        world.register::<FollowControl>();
+        world.register::<FirstPersonControl>();
+        let mut time = Time::default();
+        time.set_delta_time(Duration::from_secs(1));
+        world.insert(time);
+        world.insert(ControlSettings::default());
+        world.insert(WindowFocus::new());
+        world.insert(HideCursor::default());
+        world.insert(EventChannel::<Event>::new());
+        world.insert(EventChannel::<InputEvent<StringBindings>>::new());
+        world.insert(EventChannel::<FirstPersonEvent>::new());
+
+        let mut input = InputHandler::<StringBindings>::new();
+        let key = |key| Button::Key(key);
+        let axes = [
+            ("right", VirtualKeyCode::D, VirtualKeyCode::A),
+            ("forward", VirtualKeyCode::S, VirtualKeyCode::W),
+        ];
+        for (axis, pos, neg) in &axes {
+            input
+                .bindings
+                .insert_axis(
+                    *axis,
+                    Axis::Emulated {
+                        pos: key(*pos),
+                        neg: key(*neg),
+                    },
+                )
+                .unwrap();
+        }
+        let actions = [
+            ("jump", VirtualKeyCode::Space),
+            ("crouch", VirtualKeyCode::LControl),
+            ("sprint", VirtualKeyCode::LShift),
+        ];
+        for (action, button) in &actions {
+            input
+                .bindings
+                .insert_action_binding(action.to_string(), vec![key(*button)])
+                .unwrap();
+        }
+        world.insert(input);
+        world
+    }
+
+    /// Starts a new frame of the `InputHandler` and sends it the given events.
+    fn send(world: &World, events: Vec<Event>) {
+        let mut input = world.write_resource::<InputHandler<StringBindings>>();
+        let mut input_events = world.write_resource::<EventChannel<InputEvent<StringBindings>>>();
+        input.send_frame_begin();
+        for event in events {
+            input.send_event(&event, &mut input_events, 1.0);
+            world
+                .write_resource::<EventChannel<Event>>()
+                .single_write(event);
+        }
+    }
+
+    fn modifiers() -> ModifiersState {
+        ModifiersState {
+            shift: false,
+            ctrl: false,
+            alt: false,
+            logo: false,
+        }
+    }
+
+    fn key(key: VirtualKeyCode, state: ElementState) -> Event {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::KeyboardInput {
+                device_id: unsafe { DeviceId::dummy() },
+                input: KeyboardInput {
+                    scancode: key as u32,
+                    state,
+                    virtual_keycode: Some(key),
+                    modifiers: modifiers(),
+                },
+            },
+        }
+    }
+
+    fn mouse_button(button: MouseButton, state: ElementState) -> Event {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::MouseInput {
+                device_id: unsafe { DeviceId::dummy() },
+                state,
+                button,
+                modifiers: modifiers(),
+            },
+        }
+    }
+
+    fn mouse_motion(x: f64, y: f64) -> Event {
+        Event::DeviceEvent {
+            device_id: unsafe { DeviceId::dummy() },
+            event: DeviceEvent::MouseMotion { delta: (x, y) },
+        }
+    }
+
+    fn mouse_wheel(steps: f32) -> Event {
+        Event::DeviceEvent {
+            device_id: unsafe { DeviceId::dummy() },
+            event: DeviceEvent::MouseWheel {
+                delta: MouseScrollDelta::LineDelta(0.0, steps),
+            },
+        }
+    }
+
+    fn translation(world: &World, entity: Entity) -> Vector3<f32> {
+        *world
+            .read_storage::<Transform>()
+            .get(entity)
+            .unwrap()
+            .translation()
+    }
+
+    fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-4,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn arc_ball_control_rotates_zooms_and_pans() {
+        let mut world = world();
+        let target = world.create_entity().with(Transform::default()).build();
+        let camera = world
+            .create_entity()
+            .with(ArcBallControl::new(OrbitTarget::Entity(target), 10.0))
+            .with(Transform::default())
+            .build();
+        let reader = world
+            .write_resource::<EventChannel<Event>>()
+            .register_reader();
+        let mut system = ArcBallControlSystem::<StringBindings>::new(reader);
+
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(0.0, 0.0, 10.0));
+
+        // The camera only rotates while the rotate button is held down.
+        send(&world, vec![mouse_motion(-90.0, 0.0)]);
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(0.0, 0.0, 10.0));
+        send(
+            &world,
+            vec![
+                mouse_button(MouseButton::Right, ElementState::Pressed),
+                mouse_motion(-90.0, 0.0),
+            ],
+        );
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(10.0, 0.0, 0.0));
+
+        // Mouse motion is ignored while the window isn't focused.
+        world.write_resource::<WindowFocus>().is_focused = false;
+        send(&world, vec![mouse_motion(-90.0, 0.0)]);
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(10.0, 0.0, 0.0));
+        world.write_resource::<WindowFocus>().is_focused = true;
+
+        send(&world, vec![mouse_wheel(1.0)]);
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(9.0, 0.0, 0.0));
+
+        send(
+            &world,
+            vec![
+                mouse_button(MouseButton::Right, ElementState::Released),
+                mouse_button(MouseButton::Middle, ElementState::Pressed),
+                mouse_motion(0.0, 100.0),
+            ],
+        );
+        system.run_now(&world);
+        let panned = translation(&world, camera);
+        assert!((panned.y - 100.0 * 0.002 * 9.0).abs() < 1e-4);
+
+        // The camera follows the target entity.
+        world
+            .write_storage::<Transform>()
+            .get_mut(target)
+            .unwrap()
+            .set_translation_x(5.0);
+        send(&world, vec![]);
+        system.run_now(&world);
+        assert_near(
+            translation(&world, camera),
+            panned + Vector3::new(5.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn follow_control_follows_target_and_avoids_obstructions() {
+        let mut world = world();
+        let mut target_transform = Transform::default();
+        *target_transform.rotation_mut() =
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+        let target = world.create_entity().with(target_transform).build();
+        let camera = world
+            .create_entity()
+            .with(FollowControl::new(target, Vector3::new(0.0, 2.0, 5.0)))
+            .with(Transform::default())
+            .build();
+        let mut system = FollowControlSystem;
+
+        // The offset turns with the target.
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(5.0, 2.0, 0.0));
+        let view = {
+            let transforms = world.read_storage::<Transform>();
+            transforms.get(camera).unwrap().rotation() * -Vector3::z()
+        };
+        assert_near(view, -Vector3::new(5.0, 2.0, 0.0).normalize());
+
+        world
+            .write_storage::<Transform>()
+            .get_mut(target)
+            .unwrap()
+            .set_translation_x(1.0);
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(6.0, 2.0, 0.0));
+
+        world.insert(CameraObstruction::new(|_, _| Some(2.0)));
+        system.run_now(&world);
+        let distance = (translation(&world, camera) - translation(&world, target)).norm();
+        assert!((distance - 1.8).abs() < 1e-4);
+    }
+
+    #[test]
+    fn first_person_control_moves_turns_and_sends_events() {
+        use ElementState::{Pressed, Released};
+        use VirtualKeyCode::{LControl, LShift, Space, W};
+
+        let mut world = world();
+        let head = world.create_entity().with(Transform::default()).build();
+        let body = world
+            .create_entity()
+            .with(FirstPersonControl::new(Some(head)))
+            .with(Transform::default())
+            .build();
+        let reader = world
+            .write_resource::<EventChannel<Event>>()
+            .register_reader();
+        let mut events = world
+            .write_resource::<EventChannel<FirstPersonEvent>>()
+            .register_reader();
+        let mut system = FirstPersonControlSystem::<StringBindings>::new(
+            Some("right".to_string()),
+            Some("forward".to_string()),
+            Some("jump".to_string()),
+            Some("crouch".to_string()),
+            Some("sprint".to_string()),
+            reader,
+        );
+        let mut step = |world: &World, input: Vec<Event>| {
+            send(world, input);
+            system.run_now(world);
+            world
+                .read_resource::<EventChannel<FirstPersonEvent>>()
+                .read(&mut events)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        assert!(step(&world, vec![key(W, Pressed)]).is_empty());
+        assert_near(translation(&world, body), Vector3::new(0.0, 0.0, -1.0));
+        step(&world, vec![key(LShift, Pressed)]);
+        assert_near(translation(&world, body), Vector3::new(0.0, 0.0, -3.0));
+        assert_eq!(
+            step(&world, vec![key(LShift, Released), key(LControl, Pressed)]),
+            [FirstPersonEvent::CrouchStarted(body)]
+        );
+        assert_near(translation(&world, body), Vector3::new(0.0, 0.0, -3.5));
+        assert_eq!(
+            step(
+                &world,
+                vec![
+                    key(W, Released),
+                    key(LControl, Released),
+                    key(Space, Pressed)
+                ]
+            ),
+            [
+                FirstPersonEvent::Jump(body),
+                FirstPersonEvent::CrouchEnded(body)
+            ]
+        );
+        // Holding the jump action down doesn't jump again.
+        assert!(step(&world, vec![]).is_empty());
+        assert_near(translation(&world, body), Vector3::new(0.0, 0.0, -3.5));
+
+        // The mouse turns the body around the vertical axis and tilts the camera.
+        step(
+            &world,
+            vec![key(Space, Released), mouse_motion(-90.0, -45.0)],
+        );
+        step(&world, vec![key(W, Pressed)]);
+        assert_near(translation(&world, body), Vector3::new(-1.0, 0.0, -3.5));
+        let transforms = world.read_storage::<Transform>();
+        let yaw = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+        let pitch =
+            UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::FRAC_PI_4);
+        assert!(transforms.get(body).unwrap().rotation().angle_to(&yaw) < 1e-4);
+        assert!(transforms.get(head).unwrap().rotation().angle_to(&pitch) < 1e-4);
+        drop(transforms);
+
+        // The mouse is ignored while the window isn't focused.
+        world.write_resource::<WindowFocus>().is_focused = false;
+        step(&world, vec![key(W, Released), mouse_motion(-90.0, 0.0)]);
+        let transforms = world.read_storage::<Transform>();
+        assert!(transforms.get(body).unwrap().rotation().angle_to(&yaw) < 1e-4);
+    }
+
+    #[test]
+    fn fly_movement_is_sped_up_while_sprinting() {
+        use ElementState::Pressed;
+        use VirtualKeyCode::{LShift, W};
+
+        let mut world = world();
+        *world.write_resource::<ControlSettings>() = ControlSettings {
+            speed: 2.0,
+            sprint_multiplier: 3.0,
+            ..Default::default()
+        };
+        let camera = world
+            .create_entity()
+            .with(FlyControlTag)
+            .with(Transform::default())
+            .build();
+        let mut system = FlyMovementSystem::<StringBindings>::new(
+            Some("right".to_string()),
+            None,
+            Some("forward".to_string()),
+            Some("sprint".to_string()),
+        );
+
+        send(&world, vec![key(W, Pressed)]);
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(0.0, 0.0, -2.0));
+
+        // speed * sprint_multiplier while the sprint key is held down
+        send(&world, vec![key(LShift, Pressed)]);
+        system.run_now(&world);
+        assert_near(translation(&world, camera), Vector3::new(0.0, 0.0, -8.0));
+    }
+
+    #[test]
+    fn mouse_focus_only_grabs_the_cursor_again_after_escape() {
+        let world = world();
+        let reader = world
+            .write_resource::<EventChannel<Event>>()
+            .register_reader();
+        let mut system = MouseFocusUpdateSystem::new(reader);
+        let hidden = |world: &World| world.read_resource::<HideCursor>().hide;
+
+        send(
+            &world,
+            vec![key(VirtualKeyCode::Escape, ElementState::Pressed)],
+        );
+        system.run_now(&world);
+        assert!(!hidden(&world));
+        send(
+            &world,
+            vec![mouse_button(MouseButton::Left, ElementState::Pressed)],
+        );
+        system.run_now(&world);
+        assert!(hidden(&world));
+
+        // A cursor shown for a menu stays free when the window is clicked.
+        world.write_resource::<HideCursor>().hide = false;
+        send(
+            &world,
+            vec![mouse_button(MouseButton::Left, ElementState::Pressed)],
+        );
+        system.run_now(&world);
+        assert!(!hidden(&world));
+    }
+}
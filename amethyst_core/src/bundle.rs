@@ -1,5 +1,7 @@
 //! Provides a trait for adding bundles of systems to a dispatcher.
 
+use std::{error, fmt};
+
 use crate::ecs::prelude::{DispatcherBuilder, World};
 use amethyst_error::Error;
 
@@ -12,3 +14,141 @@ pub trait SystemBundle<'a, 'b> {
         dispatcher: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error>;
 }
+
+/// Error returned by a bundle when something it depends on wasn't set up, usually because another
+/// bundle wasn't added before it.
+///
+/// ```rust
+/// use amethyst_core::bundle::BundleError;
+///
+/// let error = BundleError::missing_system("UiBundle", "input_system")
+///     .with_suggestion("add InputBundle before UiBundle");
+/// assert_eq!(
+///     "`UiBundle` depends on the `input_system` system, which isn't registered: add InputBundle before UiBundle",
+///     error.to_string()
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BundleError {
+    /// A system the systems of the bundle depend on isn't registered.
+    MissingSystem {
+        /// Name of the bundle.
+        bundle: String,
+        /// Name of the missing system.
+        system: String,
+        /// How to register the system.
+        suggestion: Option<String>,
+    },
+    /// A resource the bundle needs isn't in the `World`.
+    MissingResource {
+        /// Name of the bundle.
+        bundle: String,
+        /// Type of the missing resource.
+        resource: String,
+        /// How to add the resource.
+        suggestion: Option<String>,
+    },
+}
+
+impl BundleError {
+    /// Returns an error for a bundle depending on a system which isn't registered.
+    pub fn missing_system(bundle: impl Into<String>, system: impl Into<String>) -> Self {
+        BundleError::MissingSystem {
+            bundle: bundle.into(),
+            system: system.into(),
+            suggestion: None,
+        }
+    }
+
+    /// Returns an error for a bundle needing a resource of type `R` which isn't in the `World`.
+    pub fn missing_resource<R>(bundle: impl Into<String>) -> Self {
+        Self::missing_resource_named(bundle, std::any::type_name::<R>())
+    }
+
+    /// Returns an error for a bundle needing a resource which isn't in the `World`, named by
+    /// `resource` instead of its full type name, e.g. for type aliases like `ParentHierarchy`.
+    pub fn missing_resource_named(bundle: impl Into<String>, resource: impl Into<String>) -> Self {
+        BundleError::MissingResource {
+            bundle: bundle.into(),
+            resource: resource.into(),
+            suggestion: None,
+        }
+    }
+
+    /// Sets how to fix the error, e.g. "add TransformBundle before UiBundle".
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        match &mut self {
+            BundleError::MissingSystem { suggestion: s, .. }
+            | BundleError::MissingResource { suggestion: s, .. } => *s = Some(suggestion.into()),
+        }
+        self
+    }
+
+    /// Returns the name of the bundle which failed to build.
+    pub fn bundle(&self) -> &str {
+        match self {
+            BundleError::MissingSystem { bundle, .. }
+            | BundleError::MissingResource { bundle, .. } => bundle,
+        }
+    }
+
+    /// Returns how to fix the error, if known.
+    pub fn suggestion(&self) -> Option<&str> {
+        match self {
+            BundleError::MissingSystem { suggestion, .. }
+            | BundleError::MissingResource { suggestion, .. } => suggestion.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::MissingSystem { bundle, system, .. } => write!(
+                f,
+                "`{}` depends on the `{}` system, which isn't registered",
+                bundle, system
+            )?,
+            BundleError::MissingResource {
+                bundle, resource, ..
+            } => write!(
+                f,
+                "`{}` needs the `{}` resource, which isn't in the world",
+                bundle, resource
+            )?,
+        }
+        if let Some(suggestion) = self.suggestion() {
+            write!(f, ": {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for BundleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::BundleError;
+
+    struct Score;
+
+    #[test]
+    fn bundle_error_names_the_missing_resource() {
+        let error = BundleError::missing_resource::<Score>("MyBundle");
+        assert_eq!("MyBundle", error.bundle());
+        assert_eq!(None, error.suggestion());
+        let message = error.to_string();
+        assert!(message.starts_with("`MyBundle` needs the `"));
+        assert!(message.contains("Score"));
+
+        let error = BundleError::missing_resource_named("MyBundle", "Score");
+        assert_eq!(
+            "`MyBundle` needs the `Score` resource, which isn't in the world",
+            error.to_string()
+        );
+
+        let error = error.with_suggestion("insert a Score");
+        assert_eq!(Some("insert a Score"), error.suggestion());
+        assert!(error.to_string().ends_with(": insert a Score"));
+    }
+}
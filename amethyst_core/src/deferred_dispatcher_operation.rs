@@ -4,7 +4,7 @@ use std::marker::PhantomData;
 
 use derivative::Derivative;
 
-use amethyst_error::Error;
+use amethyst_error::{Error, ResultExt};
 
 use crate::{
     ecs::prelude::{DispatcherBuilder, RunNow, System, World},
@@ -172,7 +172,13 @@ where
         world: &mut World,
         dispatcher_builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
-        self.bundle.build(world, dispatcher_builder)?;
-        Ok(())
+        self.bundle
+            .build(world, dispatcher_builder)
+            .with_context(|_| {
+                Error::from_string(format!(
+                    "Failed to build bundle `{}`",
+                    std::any::type_name::<B>()
+                ))
+            })
     }
 }
@@ -149,6 +149,7 @@ impl Default for FrameRateLimitConfig {
 pub struct FrameLimiter {
     frame_duration: Duration,
     strategy: FrameRateLimitStrategy,
+    fps: u32,
     last_call: Instant,
 }
 
@@ -164,6 +165,7 @@ impl FrameLimiter {
         let mut s = Self {
             frame_duration: Duration::from_secs(0),
             strategy: Default::default(),
+            fps: 0,
             last_call: Instant::now(),
         };
         s.set_rate(strategy, fps);
@@ -177,9 +179,15 @@ impl FrameLimiter {
             fps = 144;
         }
         self.strategy = strategy;
+        self.fps = fps;
         self.frame_duration = Duration::from_secs(1) / fps;
     }
 
+    /// Returns the current frame rate limit, e.g. to restore it after changing it temporarily.
+    pub fn config(&self) -> FrameRateLimitConfig {
+        FrameRateLimitConfig::new(self.strategy.clone(), self.fps)
+    }
+
     /// Creates a new frame limiter with the given config.
     pub fn from_config(config: FrameRateLimitConfig) -> Self {
         Self::new(config.strategy, config.fps)
@@ -45,6 +45,7 @@ pub use self::{
     hide_system::{HideHierarchySystem, HideHierarchySystemDesc},
     named::{Named, WithNamed},
     system_desc::{RunNowDesc, SystemDesc},
+    system_error::{SystemError, SystemErrors},
 };
 
 pub mod bundle;
@@ -60,6 +61,7 @@ mod hidden;
 mod hide_system;
 mod named;
 mod system_desc;
+mod system_error;
 mod system_ext;
 
 /// A rayon thread pool wrapped in an `Arc`. This should be used as resource in `World`.
@@ -0,0 +1,170 @@
+//! Reporting recoverable errors from systems, instead of panicking in the middle of a frame.
+
+use std::fmt;
+
+use amethyst_error::Error;
+
+use crate::{
+    ecs::{
+        shred::{ResourceId, SystemData},
+        Entity, World, Write,
+    },
+    shrev::EventChannel,
+};
+
+/// Recoverable error reported by a system, e.g. for a missing asset or bad data on an entity.
+///
+/// The application logs the errors reported in a frame once the systems have run. They can also
+/// be read from the `EventChannel<SystemError>` resource, e.g. to display them in game.
+#[derive(Debug)]
+pub struct SystemError {
+    system: String,
+    entity: Option<Entity>,
+    error: Error,
+}
+
+impl SystemError {
+    /// Returns an error reported by the given system.
+    pub fn new(system: impl Into<String>, error: impl Into<Error>) -> Self {
+        SystemError {
+            system: system.into(),
+            entity: None,
+            error: error.into(),
+        }
+    }
+
+    /// Sets the entity the system failed to process.
+    pub fn with_entity(mut self, entity: Entity) -> Self {
+        self.entity = Some(entity);
+        self
+    }
+
+    /// Returns the name of the system which reported the error.
+    pub fn system(&self) -> &str {
+        &self.system
+    }
+
+    /// Returns the entity the system failed to process, if any.
+    pub fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+
+    /// Returns the reported error.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+impl fmt::Display for SystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.entity {
+            Some(entity) => write!(
+                f,
+                "`{}` failed on entity {:?}: {}",
+                self.system, entity, self.error
+            ),
+            None => write!(f, "`{}` failed: {}", self.system, self.error),
+        }
+    }
+}
+
+/// `SystemData` for reporting recoverable errors from a system.
+///
+/// ```rust
+/// use amethyst_core::{
+///     ecs::{Entities, Join, ReadStorage, System, VecStorage, Component},
+///     SystemErrors,
+/// };
+/// use amethyst_error::Error;
+///
+/// struct Health(i32);
+/// impl Component for Health {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct CheckHealthSystem;
+/// impl<'a> System<'a> for CheckHealthSystem {
+///     type SystemData = (Entities<'a>, ReadStorage<'a, Health>, SystemErrors<'a>);
+///
+///     fn run(&mut self, (entities, healths, mut errors): Self::SystemData) {
+///         for (entity, health) in (&entities, &healths).join() {
+///             if health.0 < 0 {
+///                 let error = Error::from_string(format!("Negative health {}", health.0));
+///                 errors.report_entity("check_health_system", entity, error);
+///             }
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemData)]
+#[allow(missing_debug_implementations)]
+pub struct SystemErrors<'a> {
+    channel: Write<'a, EventChannel<SystemError>>,
+}
+
+impl<'a> SystemErrors<'a> {
+    /// Reports an error from the given system.
+    pub fn report(&mut self, system: impl Into<String>, error: impl Into<Error>) {
+        self.channel.single_write(SystemError::new(system, error));
+    }
+
+    /// Reports an error from the given system when processing an entity.
+    pub fn report_entity(
+        &mut self,
+        system: impl Into<String>,
+        entity: Entity,
+        error: impl Into<Error>,
+    ) {
+        self.channel
+            .single_write(SystemError::new(system, error).with_entity(entity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_error::Error;
+
+    use super::{SystemError, SystemErrors};
+    use crate::{
+        ecs::{Builder, RunNow, System, World, WorldExt},
+        shrev::EventChannel,
+    };
+
+    struct FailingSystem;
+    impl<'a> System<'a> for FailingSystem {
+        type SystemData = SystemErrors<'a>;
+
+        fn run(&mut self, mut errors: Self::SystemData) {
+            errors.report("failing_system", Error::from_string("missing asset"));
+        }
+    }
+
+    #[test]
+    fn reported_errors_are_sent_to_the_channel() {
+        let mut world = World::new();
+        let mut system = FailingSystem;
+        System::setup(&mut system, &mut world);
+        let mut reader = world
+            .write_resource::<EventChannel<SystemError>>()
+            .register_reader();
+
+        system.run_now(&world);
+        let entity = world.create_entity().build();
+        world.system_data::<SystemErrors<'_>>().report_entity(
+            "other_system",
+            entity,
+            Error::from_string("bad data"),
+        );
+
+        let channel = world.read_resource::<EventChannel<SystemError>>();
+        let errors = channel.read(&mut reader).collect::<Vec<_>>();
+        assert_eq!(2, errors.len());
+        assert_eq!(
+            "`failing_system` failed: missing asset",
+            errors[0].to_string()
+        );
+        assert_eq!("other_system", errors[1].system());
+        assert_eq!(Some(entity), errors[1].entity());
+        assert_eq!("bad data", errors[1].error().to_string());
+    }
+}
@@ -29,6 +29,8 @@ pub struct Time {
     fixed_time_accumulator: f32,
     /// Fixed update interpolation alpha
     interpolation_alpha: f32,
+    /// Duration of every frame when the time is driven manually instead of by the clock.
+    manual_delta: Option<Duration>,
 }
 
 impl Time {
@@ -143,6 +145,30 @@ impl Time {
         self.fixed_time = time;
     }
 
+    /// Starts a new frame which lasted the given duration, incrementing the frame number and
+    /// setting the delta time.
+    ///
+    /// This is called by the engine every frame, and may be called by tests stepping the time
+    /// deterministically.
+    pub fn advance(&mut self, delta: Duration) {
+        self.increment_frame_number();
+        self.set_delta_time(delta);
+    }
+
+    /// Gets the duration of every frame when the time is driven manually.
+    pub fn manual_delta(&self) -> Option<Duration> {
+        self.manual_delta
+    }
+
+    /// Drives the time manually, the application advancing it by the given duration every frame
+    /// instead of the time measured by the clock, or by the clock again if `None`.
+    ///
+    /// This makes the frames deterministic, e.g. to test systems depending on the time without
+    /// sleeping.
+    pub fn set_manual_delta(&mut self, delta: Option<Duration>) {
+        self.manual_delta = delta;
+    }
+
     /// Increments the current frame number by 1.
     ///
     /// This should only be called by the engine.  Bad things might happen if you call this in
@@ -209,6 +235,7 @@ impl Default for Time {
             absolute_real_time: Duration::default(),
             absolute_time: Duration::default(),
             time_scale: 1.0,
+            manual_delta: None,
         }
     }
 }
@@ -443,6 +470,34 @@ mod tests {
         }
         assert_eq!(fixed_count, 2);
     }
+
+    // Test that advancing the time manually is exact, without depending on the clock
+    #[test]
+    fn advance() {
+        use super::Time;
+
+        let mut time = Time::default();
+        time.set_fixed_time(Duration::from_millis(250));
+        time.set_time_scale(2.0);
+
+        let mut fixed_count = 0;
+        for _ in 0..4 {
+            time.advance(Duration::from_millis(500));
+            time.start_fixed_update();
+
+            while time.step_fixed_update() {
+                fixed_count += 1;
+            }
+
+            time.finish_fixed_update();
+        }
+
+        assert_eq!(time.frame_number(), 4);
+        assert_eq!(time.delta_time(), Duration::from_secs(1));
+        assert_eq!(time.absolute_real_time(), Duration::from_secs(2));
+        assert_eq!(time.absolute_time(), Duration::from_secs(4));
+        assert_eq!(fixed_count, 8);
+    }
 }
 
 /// Converts a Duration to the time in seconds.
@@ -14,6 +14,10 @@ pub enum Error {
     )]
     InvalidSceneGltf(usize),
 
+    /// GLTF has no scene with the requested name
+    #[error(display = "Gltf has no scene named {}", _0)]
+    MissingScene(String),
+
     /// GLTF primitive missing positions
     #[error(display = "Primitive missing positions")]
     MissingPositions,
@@ -26,6 +30,24 @@ pub enum Error {
     #[error(display = "Channel missing outputs")]
     MissingOutputs,
 
+    /// GLTF skin uses a joint outside of the loaded scene
+    #[error(display = "Skin joint {} is not part of the loaded scene", _0)]
+    MissingJoint(usize),
+
+    /// GLTF requires an extension that isn't supported
+    #[error(display = "Required extension {} is not supported", _0)]
+    UnsupportedExtension(String),
+
+    /// GLTF buffer view compressed with `EXT_meshopt_compression` could not be decoded
+    #[cfg(feature = "meshopt")]
+    #[error(display = "Compressed buffer view could not be decoded")]
+    MeshoptDecode(gltf::json::Path),
+
+    /// GLTF primitive compressed with `KHR_draco_mesh_compression` could not be decoded
+    #[cfg(feature = "draco")]
+    #[error(display = "Compressed primitive could not be decoded")]
+    DracoDecode(gltf::json::Path),
+
     /// Not implemented yet
     #[error(display = "Not implemented")]
     NotImplemented,
@@ -3,46 +3,127 @@ use std::collections::HashMap;
 use amethyst_error::Error;
 
 use amethyst_animation::{
-    AnimationPrefab, AnimationSetPrefab, InterpolationFunction, InterpolationPrimitive, Sampler,
-    SamplerPrimitive, TransformChannel,
+    AnimationPrefab, AnimationSetPrefab, InterpolationFunction, InterpolationPrimitive,
+    MorphWeightsChannel, Sampler, SamplerPrimitive, TransformChannel,
 };
 use amethyst_core::{
     math::{convert, Vector3, Vector4},
     Transform,
 };
+use amethyst_rendy::morph::MorphWeights;
 
 use super::Buffers;
-use crate::error;
+use crate::{error, GltfAnimations};
 
+/// The animations of the transforms and of the morph target weights, and the names of the
+/// animations.
+pub type LoadedAnimations = (
+    AnimationSetPrefab<usize, Transform>,
+    AnimationSetPrefab<usize, MorphWeights>,
+    GltfAnimations,
+);
+
+/// Loads every animation of the file that animates nodes of the loaded scene, together with the
+/// names of the animations, as exported from e.g. the actions of Blender.
 pub fn load_animations(
     gltf: &gltf::Gltf,
     buffers: &Buffers,
     node_map: &HashMap<usize, usize>,
-) -> Result<AnimationSetPrefab<usize, Transform>, Error> {
-    let mut prefab = AnimationSetPrefab::default();
+) -> Result<LoadedAnimations, Error> {
+    let mut transforms = AnimationSetPrefab::default();
+    let mut morph_weights = AnimationSetPrefab::default();
+    let mut names = GltfAnimations::default();
     for animation in gltf.animations() {
-        let anim = load_animation(&animation, buffers)?;
-        if anim
-            .samplers
-            .iter()
-            .any(|sampler| node_map.contains_key(&sampler.0))
-        {
-            prefab.animations.push((animation.index(), anim));
+        let (transform, weights) = load_animation(&animation, buffers, node_map)?;
+        if transform.samplers.is_empty() && weights.samplers.is_empty() {
+            continue;
+        }
+        if !transform.samplers.is_empty() {
+            transforms.animations.push((animation.index(), transform));
+        }
+        if !weights.samplers.is_empty() {
+            morph_weights.animations.push((animation.index(), weights));
+        }
+        if let Some(name) = animation.name() {
+            names.insert(name.to_string(), animation.index());
         }
     }
-    Ok(prefab)
+    Ok((transforms, morph_weights, names))
 }
 
 fn load_animation(
     animation: &gltf::Animation<'_>,
     buffers: &Buffers,
-) -> Result<AnimationPrefab<Transform>, Error> {
-    let mut a = AnimationPrefab::default();
-    a.samplers = animation
-        .channels()
-        .map(|ref channel| load_channel(channel, buffers))
-        .collect::<Result<Vec<_>, Error>>()?;
-    Ok(a)
+    node_map: &HashMap<usize, usize>,
+) -> Result<(AnimationPrefab<Transform>, AnimationPrefab<MorphWeights>), Error> {
+    use gltf::animation::Property;
+    let mut transform = AnimationPrefab::default();
+    let mut weights = AnimationPrefab::default();
+    for channel in animation.channels() {
+        // The animation only starts if every node it targets is in the hierarchy, so channels
+        // targeting nodes of other scenes are dropped.
+        if !node_map.contains_key(&channel.target().node().index()) {
+            continue;
+        }
+        if channel.target().property() == Property::MorphTargetWeights {
+            weights
+                .samplers
+                .extend(load_morph_channel(&channel, buffers)?);
+        } else {
+            transform.samplers.push(load_channel(&channel, buffers)?);
+        }
+    }
+    Ok((transform, weights))
+}
+
+/// Splits the weights of all morph targets of a node into one sampler per target.
+fn load_morph_channel(
+    channel: &gltf::animation::Channel<'_>,
+    buffers: &Buffers,
+) -> Result<Vec<(usize, MorphWeightsChannel, Sampler<SamplerPrimitive<f32>>)>, Error> {
+    use gltf::animation::util::ReadOutputs::MorphTargetWeights;
+    let node = channel.target().node();
+    let num_targets = node
+        .mesh()
+        .and_then(|mesh| {
+            mesh.primitives()
+                .map(|primitive| primitive.morph_targets().len())
+                .max()
+        })
+        .unwrap_or(0);
+    if num_targets == 0 {
+        return Ok(Vec::new());
+    }
+
+    let reader = channel.reader(|buffer| buffers.buffer(&buffer));
+    let input = reader
+        .read_inputs()
+        .ok_or(error::Error::MissingInputs)?
+        .collect::<Vec<_>>();
+    let output = match reader.read_outputs().ok_or(error::Error::MissingOutputs)? {
+        MorphTargetWeights(weights) => weights.into_f32().collect::<Vec<_>>(),
+        _ => return Err(error::Error::MissingOutputs.into()),
+    };
+    let function = map_interpolation_type(channel.sampler().interpolation());
+
+    // The weights of every target are interleaved for each key frame, and cubic splines store
+    // the in-tangents, values and out-tangents of all targets after each other.
+    Ok((0..num_targets)
+        .map(|index| {
+            (
+                node.index(),
+                MorphWeightsChannel::Weight(index),
+                Sampler {
+                    input: input.clone(),
+                    function: function.clone(),
+                    output: output
+                        .chunks_exact(num_targets)
+                        .map(|weights| SamplerPrimitive::Scalar(weights[index]))
+                        .collect(),
+                },
+            )
+        })
+        .collect())
 }
 
 fn load_channel(
@@ -0,0 +1,355 @@
+//! Decoding of the attribute values of the points of a mesh.
+
+use super::{
+    buffer::{decode_symbols, Buffer},
+    connectivity::{Connectivity, CornerTable},
+    prediction::{Context, MeshData, Octahedron, Positions, Scheme},
+    traversal::{self, Sequence},
+};
+
+pub const POSITION: u8 = 0;
+const NAMED_ATTRIBUTES: u8 = 5;
+
+const VERTEX_ATTRIBUTE: u8 = 0;
+const CORNER_ATTRIBUTE: u8 = 1;
+
+const GENERIC: u8 = 0;
+const INTEGER: u8 = 1;
+const QUANTIZATION: u8 = 2;
+const NORMALS: u8 = 3;
+
+const PREDICTION_NONE: i8 = -2;
+
+const DT_INT8: u8 = 1;
+const DT_UINT8: u8 = 2;
+const DT_INT16: u8 = 3;
+const DT_UINT16: u8 = 4;
+const DT_INT32: u8 = 5;
+const DT_UINT32: u8 = 6;
+const DT_INT64: u8 = 7;
+const DT_UINT64: u8 = 8;
+const DT_FLOAT32: u8 = 9;
+const DT_FLOAT64: u8 = 10;
+const DT_BOOL: u8 = 11;
+
+/// A decoded attribute of the points.
+#[derive(Debug)]
+pub struct Attribute {
+    pub attribute_type: u8,
+    pub unique_id: u32,
+    pub num_components: usize,
+    /// The value of each point.
+    pub point_values: Vec<u32>,
+    /// The components of each value.
+    pub values: Vec<f64>,
+    data_type: u8,
+    decoder: u8,
+    /// The integer values the attribute was coded with.
+    portable: Vec<i32>,
+}
+
+impl Attribute {
+    /// Returns whether the values were floats before being coded.
+    pub fn is_float(&self) -> bool {
+        self.data_type == DT_FLOAT32 || self.data_type == DT_FLOAT64
+    }
+}
+
+/// The size of the values of a data type.
+fn data_type_size(data_type: u8) -> Option<usize> {
+    match data_type {
+        DT_INT8 | DT_UINT8 | DT_BOOL => Some(1),
+        DT_INT16 | DT_UINT16 => Some(2),
+        DT_INT32 | DT_UINT32 | DT_FLOAT32 => Some(4),
+        DT_INT64 | DT_UINT64 | DT_FLOAT64 => Some(8),
+        _ => None,
+    }
+}
+
+/// Reads a little endian value of the data type.
+fn read_value(bytes: &[u8], data_type: u8) -> f64 {
+    let mut le = [0; 8];
+    le[..bytes.len()].copy_from_slice(bytes);
+    let unsigned = u64::from_le_bytes(le);
+    match data_type {
+        DT_INT8 => f64::from(unsigned as i8),
+        DT_INT16 => f64::from(unsigned as i16),
+        DT_INT32 => f64::from(unsigned as i32),
+        DT_INT64 => unsigned as i64 as f64,
+        DT_FLOAT32 => f64::from(f32::from_bits(unsigned as u32)),
+        DT_FLOAT64 => f64::from_bits(unsigned),
+        _ => unsigned as f64,
+    }
+}
+
+/// Converts an integer value like a cast to the data type.
+fn cast_value(value: i32, data_type: u8) -> Option<f64> {
+    Some(match data_type {
+        DT_INT8 => f64::from(value as i8),
+        DT_UINT8 | DT_BOOL => f64::from(value as u8),
+        DT_INT16 => f64::from(value as i16),
+        DT_UINT16 => f64::from(value as u16),
+        DT_INT32 => f64::from(value),
+        DT_UINT32 => f64::from(value as u32),
+        _ => return None,
+    })
+}
+
+/// How the entries of the attributes of a decoder were ordered.
+struct Traversal<'a> {
+    table: &'a CornerTable,
+    sequence: Sequence,
+}
+
+/// Decodes the attributes of the points of the faces.
+pub fn decode_attributes(
+    buffer: &mut Buffer<'_>,
+    connectivity: &Connectivity,
+) -> Option<Vec<Attribute>> {
+    let num_decoders = buffer.u8()?;
+    let mut setups = Vec::with_capacity(num_decoders as usize);
+    for _ in 0..num_decoders {
+        if connectivity.edgebreaker.is_some() {
+            let data = buffer.i8()?;
+            let decoder_type = buffer.u8()?;
+            let method = buffer.u8()?;
+            setups.push(Some((data, decoder_type, method)));
+        } else {
+            setups.push(None);
+        }
+    }
+
+    let mut attributes = Vec::new();
+    let mut decoders = Vec::with_capacity(setups.len());
+    for _ in &setups {
+        let count = buffer.varint()? as usize;
+        if count == 0 || count > buffer.remaining().len() / 5 {
+            return None;
+        }
+        let first = attributes.len();
+        for _ in 0..count {
+            let attribute_type = buffer.u8()?;
+            let data_type = buffer.u8()?;
+            let num_components = buffer.u8()? as usize;
+            let _normalized = buffer.u8()?;
+            let unique_id = buffer.varint()?;
+            if attribute_type >= NAMED_ATTRIBUTES
+                || data_type_size(data_type).is_none()
+                || num_components == 0
+            {
+                return None;
+            }
+            attributes.push(Attribute {
+                attribute_type,
+                unique_id,
+                num_components,
+                point_values: Vec::new(),
+                values: Vec::new(),
+                data_type,
+                decoder: GENERIC,
+                portable: Vec::new(),
+            });
+        }
+        for attribute in &mut attributes[first..] {
+            attribute.decoder = buffer.u8()?;
+            let supported = match attribute.decoder {
+                GENERIC | QUANTIZATION => true,
+                INTEGER => attribute.data_type <= DT_UINT32 || attribute.data_type == DT_BOOL,
+                NORMALS => attribute.num_components == 3 && attribute.data_type == DT_FLOAT32,
+                _ => false,
+            };
+            if !supported {
+                return None;
+            }
+        }
+        decoders.push(first..attributes.len());
+    }
+
+    // the position data is only traversed once
+    let position_setups = setups.iter().filter(|setup| match setup {
+        Some((data, _, _)) => *data < 0,
+        None => false,
+    });
+    if position_setups.count() > 1 {
+        return None;
+    }
+    let position = attributes.iter().position(|a| a.attribute_type == POSITION);
+    for (setup, range) in setups.into_iter().zip(decoders) {
+        let traversal = match (setup, &connectivity.edgebreaker) {
+            (Some((data, decoder_type, method)), Some(edgebreaker)) => {
+                let base = &edgebreaker.corner_table;
+                let table = match decoder_type {
+                    VERTEX_ATTRIBUTE => base,
+                    CORNER_ATTRIBUTE if data >= 0 && method == traversal::DEPTH_FIRST => {
+                        &edgebreaker.attributes.get(data as usize)?.corner_table
+                    }
+                    _ => return None,
+                };
+                let num_vertices = if data >= 0 {
+                    let attribute = &edgebreaker.attributes.get(data as usize)?.corner_table;
+                    attribute.num_vertices().max(base.num_vertices())
+                } else {
+                    base.num_vertices()
+                };
+                let sequence =
+                    traversal::traverse(table, &connectivity.faces, method, num_vertices)?;
+                Some(Traversal { table, sequence })
+            }
+            _ => None,
+        };
+        let point_values = match &traversal {
+            Some(traversal) => traversal.sequence.point_values(
+                traversal.table,
+                &connectivity.faces,
+                connectivity.num_points,
+            )?,
+            None => (0..connectivity.num_points as u32).collect(),
+        };
+        let linear;
+        let entry_points = match &traversal {
+            Some(traversal) => &traversal.sequence.entry_points[..],
+            None => {
+                linear = point_values.clone();
+                &linear[..]
+            }
+        };
+
+        for index in range.clone() {
+            attributes[index].point_values = point_values.clone();
+        }
+        for index in range.clone() {
+            let (decoded, rest) = attributes.split_at_mut(index);
+            let attribute = &mut rest[0];
+            let positions = position.filter(|p| *p < index).map(|p| Positions {
+                values: &decoded[p].portable,
+                point_values: &decoded[p].point_values,
+            });
+            let context = Context {
+                mesh: traversal.as_ref().map(|traversal| MeshData {
+                    table: traversal.table,
+                    vertex_entries: &traversal.sequence.vertex_entries,
+                    entry_corners: &traversal.sequence.entry_corners,
+                }),
+                entry_points,
+                positions,
+            };
+            decode_portable(buffer, attribute, &context)?;
+        }
+        for attribute in &mut attributes[range] {
+            decode_original(buffer, attribute)?;
+        }
+    }
+    Some(attributes)
+}
+
+/// Decodes the values of the attribute in the form they were coded with.
+fn decode_portable(
+    buffer: &mut Buffer<'_>,
+    attribute: &mut Attribute,
+    context: &Context<'_>,
+) -> Option<()> {
+    let num_entries = context.entry_points.len();
+    if attribute.decoder == GENERIC {
+        let size = data_type_size(attribute.data_type)?;
+        let count = num_entries.checked_mul(attribute.num_components)?;
+        let bytes = buffer.bytes(count.checked_mul(size)?)?;
+        attribute.values = bytes
+            .chunks_exact(size)
+            .map(|value| read_value(value, attribute.data_type))
+            .collect();
+        attribute.portable = attribute.values.iter().map(|v| *v as i32).collect();
+        return Some(());
+    }
+
+    let normals = attribute.decoder == NORMALS;
+    let components = if normals { 2 } else { attribute.num_components };
+    let scheme = match buffer.i8()? {
+        PREDICTION_NONE => None,
+        method => {
+            let transform = buffer.i8()?;
+            Some(Scheme::new(
+                method,
+                transform,
+                normals,
+                context.mesh.is_some(),
+            )?)
+        }
+    };
+
+    let count = num_entries.checked_mul(components)?;
+    let mut values = if buffer.u8()? > 0 {
+        decode_symbols(buffer, count, components)?
+    } else {
+        let size = buffer.u8()? as usize;
+        if size == 0 || size > 4 {
+            return None;
+        }
+        let bytes = buffer.bytes(count.checked_mul(size)?)?;
+        bytes
+            .chunks_exact(size)
+            .map(|value| value.iter().rev().fold(0, |v, b| v << 8 | u32::from(*b)))
+            .collect()
+    };
+    let positive = scheme.as_ref().map_or(false, Scheme::corrections_positive);
+    if !positive {
+        for value in &mut values {
+            *value = if *value & 1 == 1 {
+                (-((*value >> 1) as i32) - 1) as u32
+            } else {
+                *value >> 1
+            };
+        }
+    }
+    let values = values.into_iter().map(|v| v as i32).collect::<Vec<_>>();
+    attribute.portable = match scheme {
+        Some(scheme) => scheme.decode(buffer, context, &values, components)?,
+        None => values,
+    };
+    Some(())
+}
+
+/// Reads the parameters of the coding of the attribute, and converts the values back.
+fn decode_original(buffer: &mut Buffer<'_>, attribute: &mut Attribute) -> Option<()> {
+    match attribute.decoder {
+        INTEGER => {
+            let data_type = attribute.data_type;
+            attribute.values = attribute
+                .portable
+                .iter()
+                .map(|v| cast_value(*v, data_type))
+                .collect::<Option<_>>()?;
+        }
+        QUANTIZATION => {
+            let min = (0..attribute.num_components)
+                .map(|_| buffer.f32())
+                .collect::<Option<Vec<_>>>()?;
+            let range = buffer.f32()?;
+            let bits = buffer.u8()?;
+            if !(1..=30).contains(&bits) {
+                return None;
+            }
+            let delta = range / ((1u32 << bits) - 1) as f32;
+            attribute.values = attribute
+                .portable
+                .chunks_exact(attribute.num_components)
+                .flat_map(|value| {
+                    value
+                        .iter()
+                        .zip(&min)
+                        .map(|(v, min)| f64::from(*v as f32 * delta + min))
+                })
+                .collect();
+        }
+        NORMALS => {
+            let octahedron = Octahedron::new(buffer.u8()?)?;
+            attribute.values = attribute
+                .portable
+                .chunks_exact(2)
+                .flat_map(|st| octahedron.to_vector(st[0], st[1]).to_vec())
+                .map(f64::from)
+                .collect();
+        }
+        _ => {}
+    }
+    Some(())
+}
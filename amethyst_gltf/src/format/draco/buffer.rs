@@ -0,0 +1,336 @@
+//! Reading of the Draco bitstream: fixed size values, varints, raw bits and the rANS coders.
+
+use std::convert::TryFrom;
+
+const IO_BASE: u32 = 256;
+const BIT_PRECISION: u32 = 256;
+const BIT_L_BASE: u32 = 4096;
+const TAGGED_SYMBOLS: u8 = 0;
+const RAW_SYMBOLS: u8 = 1;
+
+/// A cursor over the bitstream, reading little endian values.
+#[derive(Clone, Debug)]
+pub struct Buffer<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Buffer<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Buffer { data, position: 0 }
+    }
+
+    /// The data which hasn't been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position..]
+    }
+
+    pub fn bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.remaining().get(..length)?;
+        self.position += length;
+        Some(bytes)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    pub fn i8(&mut self) -> Option<i8> {
+        self.u8().map(|value| value as i8)
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.bytes(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn i32(&mut self) -> Option<i32> {
+        self.u32().map(|value| value as i32)
+    }
+
+    pub fn f32(&mut self) -> Option<f32> {
+        self.u32().map(f32::from_bits)
+    }
+
+    /// Reads an unsigned LEB128 varint.
+    pub fn varint64(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    pub fn varint(&mut self) -> Option<u32> {
+        u32::try_from(self.varint64()?).ok()
+    }
+
+    /// Starts reading single bits from the remaining data, until `end_bits` is called.
+    pub fn start_bits(&self) -> BitReader<'a> {
+        BitReader {
+            data: self.remaining(),
+            bit: 0,
+        }
+    }
+
+    /// Skips the bytes read by the bit reader.
+    pub fn end_bits(&mut self, bits: &BitReader<'_>) {
+        self.position += (bits.bit + 7) / 8;
+    }
+}
+
+/// Reads bits starting from the least significant bit of each byte.
+#[derive(Clone, Debug)]
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// Reads `count` bits, the first bit read being the least significant one.
+    ///
+    /// Reading past the end of the data returns zeros, like the reference decoder.
+    pub fn bits(&mut self, count: u32) -> u32 {
+        let mut value = 0;
+        for i in 0..count {
+            let bit = self
+                .data
+                .get(self.bit / 8)
+                .map_or(0, |byte| (byte >> (self.bit % 8)) & 1);
+            self.bit += 1;
+            value |= u32::from(bit) << i;
+        }
+        value
+    }
+}
+
+/// Reads the initial state of a rANS coder, stored at the end of its data.
+fn read_state(data: &[u8], l_base: u32, allow_four_bytes: bool) -> Option<(u32, usize)> {
+    let length = data.len();
+    let last = *data.last()?;
+    let le = |bytes: &[u8]| bytes.iter().rev().fold(0, |v, b| v << 8 | u32::from(*b));
+    let (state, offset) = match last >> 6 {
+        0 => (u32::from(last & 0x3f), length - 1),
+        1 if length >= 2 => (le(&data[length - 2..]) & 0x3fff, length - 2),
+        2 if length >= 3 => (le(&data[length - 3..]) & 0x3f_ffff, length - 3),
+        3 if length >= 4 && allow_four_bytes => (le(&data[length - 4..]) & 0x3fff_ffff, length - 4),
+        _ => return None,
+    };
+    let state = state + l_base;
+    if state >= l_base * IO_BASE {
+        return None;
+    }
+    Some((state, offset))
+}
+
+/// Decodes bits coded with a single probability.
+#[derive(Debug)]
+pub struct RAnsBitDecoder<'a> {
+    data: &'a [u8],
+    offset: usize,
+    state: u32,
+    prob_zero: u32,
+}
+
+impl<'a> RAnsBitDecoder<'a> {
+    /// Reads the probability and the coded data, and moves the buffer past them.
+    pub fn new(buffer: &mut Buffer<'a>) -> Option<Self> {
+        let prob_zero = u32::from(buffer.u8()?);
+        let length = buffer.varint()? as usize;
+        let data = buffer.bytes(length)?;
+        let (state, offset) = read_state(data, BIT_L_BASE, false)?;
+        Some(RAnsBitDecoder {
+            data,
+            offset,
+            state,
+            prob_zero,
+        })
+    }
+
+    pub fn bit(&mut self) -> bool {
+        let p = BIT_PRECISION - self.prob_zero;
+        if self.state < BIT_L_BASE && self.offset > 0 {
+            self.offset -= 1;
+            self.state = self.state * IO_BASE + u32::from(self.data[self.offset]);
+        }
+        let quotient = self.state / BIT_PRECISION;
+        let remainder = self.state % BIT_PRECISION;
+        let xn = quotient * p;
+        if remainder < p {
+            self.state = xn + remainder;
+            true
+        } else {
+            self.state -= xn + p;
+            false
+        }
+    }
+}
+
+/// Decodes symbols coded with the probability table stored before them.
+#[derive(Debug)]
+struct RAnsSymbolDecoder<'a> {
+    precision: u32,
+    l_base: u32,
+    /// The probability and cumulative probability of each symbol.
+    symbols: Vec<(u32, u32)>,
+    /// The symbol of each slot of the precision range.
+    lookup: Vec<u32>,
+    data: &'a [u8],
+    offset: usize,
+    state: u32,
+}
+
+impl<'a> RAnsSymbolDecoder<'a> {
+    /// Reads the probability table and the coded data, and moves the buffer past them.
+    ///
+    /// `bit_length` is the length of the largest coded symbol, which sets the precision.
+    fn new(buffer: &mut Buffer<'a>, bit_length: u32) -> Option<Self> {
+        let precision = 1 << ((3 * bit_length) / 2).max(12).min(20);
+        let count = buffer.varint()? as usize;
+        if count == 0 {
+            return None;
+        }
+        let mut probabilities = Vec::new();
+        while probabilities.len() < count {
+            let data = buffer.u8()?;
+            if data & 3 == 3 {
+                // a run of symbols which never appear
+                let run = (data >> 2) as usize + 1;
+                if probabilities.len() + run > count {
+                    return None;
+                }
+                probabilities.extend(std::iter::repeat(0).take(run));
+            } else {
+                let mut probability = u32::from(data >> 2);
+                for i in 0..(data & 3) {
+                    probability |= u32::from(buffer.u8()?) << (8 * (i + 1) - 2);
+                }
+                probabilities.push(probability);
+            }
+        }
+
+        let mut symbols = Vec::with_capacity(probabilities.len());
+        let mut lookup = Vec::with_capacity(precision as usize);
+        let mut cumulative = 0;
+        for (symbol, probability) in probabilities.into_iter().enumerate() {
+            symbols.push((probability, cumulative));
+            cumulative += probability;
+            if cumulative > precision {
+                return None;
+            }
+            lookup.resize(cumulative as usize, symbol as u32);
+        }
+        if cumulative != precision {
+            return None;
+        }
+
+        let length = usize::try_from(buffer.varint64()?).ok()?;
+        let data = buffer.bytes(length)?;
+        let l_base = precision * 4;
+        let (state, offset) = read_state(data, l_base, true)?;
+        Some(RAnsSymbolDecoder {
+            precision,
+            l_base,
+            symbols,
+            lookup,
+            data,
+            offset,
+            state,
+        })
+    }
+
+    fn symbol(&mut self) -> u32 {
+        while self.state < self.l_base && self.offset > 0 {
+            self.offset -= 1;
+            self.state = self.state * IO_BASE + u32::from(self.data[self.offset]);
+        }
+        let quotient = self.state / self.precision;
+        let remainder = self.state % self.precision;
+        let symbol = self.lookup[remainder as usize];
+        let (probability, cumulative) = self.symbols[symbol as usize];
+        self.state = quotient * probability + remainder - cumulative;
+        symbol
+    }
+}
+
+/// Decodes `count` entropy coded values, of entries with `components` values each.
+pub fn decode_symbols(
+    buffer: &mut Buffer<'_>,
+    count: usize,
+    components: usize,
+) -> Option<Vec<u32>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    let mut values = Vec::new();
+    match buffer.u8()? {
+        TAGGED_SYMBOLS => {
+            // the bit length of each entry is coded, followed by the raw bits of its values
+            let mut tags = RAnsSymbolDecoder::new(buffer, 5)?;
+            let mut bits = buffer.start_bits();
+            while values.len() < count {
+                let bit_length = tags.symbol();
+                if bit_length > 32 {
+                    return None;
+                }
+                for _ in 0..components.min(count - values.len()) {
+                    values.push(bits.bits(bit_length));
+                }
+            }
+            buffer.end_bits(&bits);
+        }
+        RAW_SYMBOLS => {
+            let bit_length = u32::from(buffer.u8()?);
+            if bit_length == 0 || bit_length > 18 {
+                return None;
+            }
+            let mut symbols = RAnsSymbolDecoder::new(buffer, bit_length)?;
+            values.extend((0..count).map(|_| symbols.symbol()));
+        }
+        _ => return None,
+    }
+    Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_varints_and_bits() {
+        let mut buffer = Buffer::new(&[0x7f, 0xac, 0x02, 0b1011_0110, 0xff]);
+        assert_eq!(buffer.varint(), Some(127));
+        assert_eq!(buffer.varint(), Some(300));
+        let mut bits = buffer.start_bits();
+        assert_eq!(bits.bits(1), 0);
+        assert_eq!(bits.bits(3), 0b011);
+        buffer.end_bits(&bits);
+        assert_eq!(buffer.u8(), Some(0xff));
+        assert_eq!(buffer.u8(), None);
+    }
+
+    #[test]
+    fn decodes_tagged_symbols() {
+        // a single tag of 3 bits, which takes the whole probability range
+        let data = [TAGGED_SYMBOLS, 4, (2 << 2) | 3, 1, 64, 1, 0, 209, 8];
+        let mut buffer = Buffer::new(&data);
+        assert_eq!(decode_symbols(&mut buffer, 4, 2), Some(vec![1, 2, 3, 4]));
+        assert!(buffer.remaining().is_empty());
+    }
+
+    #[test]
+    fn decodes_bits_with_certain_probability() {
+        let data = [0, 1, 0];
+        let mut decoder = RAnsBitDecoder::new(&mut Buffer::new(&data)).unwrap();
+        assert!((0..16).all(|_| decoder.bit()));
+    }
+}
@@ -0,0 +1,846 @@
+//! Decoding of the faces of a mesh, coded either as sequential indices or with the Edgebreaker
+//! traversal, along with the seams of the attributes which aren't shared by adjacent faces.
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use super::buffer::{decode_symbols, BitReader, Buffer, RAnsBitDecoder};
+
+/// Index of a corner, face or vertex which doesn't exist.
+pub const INVALID: u32 = u32::MAX;
+
+const SEQUENTIAL_COMPRESSED_INDICES: u8 = 0;
+const SEQUENTIAL_UNCOMPRESSED_INDICES: u8 = 1;
+
+const STANDARD_TRAVERSAL: u8 = 0;
+const VALENCE_TRAVERSAL: u8 = 2;
+
+const TOPOLOGY_C: u32 = 0;
+const TOPOLOGY_S: u32 = 1;
+const TOPOLOGY_L: u32 = 3;
+const TOPOLOGY_R: u32 = 5;
+const TOPOLOGY_E: u32 = 7;
+const TOPOLOGY_INVALID: u32 = 9;
+/// The symbols of the valence traversal, by their coded id.
+const VALENCE_SYMBOLS: [u32; 5] = [TOPOLOGY_C, TOPOLOGY_S, TOPOLOGY_L, TOPOLOGY_R, TOPOLOGY_E];
+const MIN_VALENCE: usize = 2;
+const MAX_VALENCE: usize = 7;
+
+/// The faces of a mesh as corners, each corner `c` being a vertex of the face `c / 3`.
+///
+/// The corner opposite to a corner is the corner of the adjacent face across the edge facing it.
+#[derive(Clone, Debug, Default)]
+pub struct CornerTable {
+    vertices: Vec<u32>,
+    opposites: Vec<u32>,
+    left_most_corners: Vec<u32>,
+}
+
+impl CornerTable {
+    fn with_faces(num_faces: usize) -> Self {
+        CornerTable {
+            vertices: vec![INVALID; num_faces * 3],
+            opposites: vec![INVALID; num_faces * 3],
+            left_most_corners: Vec::new(),
+        }
+    }
+
+    pub fn num_faces(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    pub fn num_corners(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.left_most_corners.len()
+    }
+
+    pub fn vertex(&self, corner: u32) -> u32 {
+        self.vertices
+            .get(corner as usize)
+            .cloned()
+            .unwrap_or(INVALID)
+    }
+
+    pub fn opposite(&self, corner: u32) -> u32 {
+        self.opposites
+            .get(corner as usize)
+            .cloned()
+            .unwrap_or(INVALID)
+    }
+
+    pub fn left_most_corner(&self, vertex: u32) -> u32 {
+        self.left_most_corners
+            .get(vertex as usize)
+            .cloned()
+            .unwrap_or(INVALID)
+    }
+
+    pub fn next(&self, corner: u32) -> u32 {
+        match corner {
+            INVALID => INVALID,
+            c if c % 3 == 2 => c - 2,
+            c => c + 1,
+        }
+    }
+
+    pub fn previous(&self, corner: u32) -> u32 {
+        match corner {
+            INVALID => INVALID,
+            c if c % 3 == 0 => c + 2,
+            c => c - 1,
+        }
+    }
+
+    /// The next corner of the vertex of `corner`, going counterclockwise.
+    pub fn swing_left(&self, corner: u32) -> u32 {
+        self.next(self.opposite(self.next(corner)))
+    }
+
+    /// The next corner of the vertex of `corner`, going clockwise.
+    pub fn swing_right(&self, corner: u32) -> u32 {
+        self.previous(self.opposite(self.previous(corner)))
+    }
+
+    /// The corner opposite to the edge on the left of `corner`.
+    pub fn left_corner(&self, corner: u32) -> u32 {
+        self.opposite(self.previous(corner))
+    }
+
+    /// The corner opposite to the edge on the right of `corner`.
+    pub fn right_corner(&self, corner: u32) -> u32 {
+        self.opposite(self.next(corner))
+    }
+
+    pub fn is_on_boundary(&self, vertex: u32) -> bool {
+        self.swing_left(self.left_most_corner(vertex)) == INVALID
+    }
+
+    /// Returns the corners of the vertex of `corner`, starting with `corner` and going
+    /// counterclockwise, then clockwise from `corner` once a boundary is reached.
+    pub fn vertex_corners(&self, corner: u32) -> Vec<u32> {
+        let mut corners = Vec::new();
+        let mut current = corner;
+        while current != INVALID {
+            corners.push(current);
+            current = self.swing_left(current);
+            if current == corner {
+                return corners;
+            }
+        }
+        current = self.swing_right(corner);
+        while current != INVALID && current != corner {
+            corners.push(current);
+            current = self.swing_right(current);
+        }
+        corners
+    }
+
+    fn set_opposites(&mut self, a: u32, b: u32) {
+        self.opposites[a as usize] = b;
+        self.opposites[b as usize] = a;
+    }
+
+    fn map_corner(&mut self, corner: u32, vertex: u32) {
+        self.vertices[corner as usize] = vertex;
+    }
+
+    fn add_vertex(&mut self) -> u32 {
+        self.left_most_corners.push(INVALID);
+        self.left_most_corners.len() as u32 - 1
+    }
+
+    fn set_left_most_corner(&mut self, vertex: u32, corner: u32) {
+        if let Some(left_most) = self.left_most_corners.get_mut(vertex as usize) {
+            *left_most = corner;
+        }
+    }
+}
+
+/// The connectivity of an attribute whose values aren't shared across some edges.
+#[derive(Debug)]
+pub struct AttributeConnectivity {
+    /// The corner table of the attribute, whose seam edges have no opposite corners.
+    pub corner_table: CornerTable,
+    /// Whether each vertex of the mesh is on a seam of the attribute.
+    on_seam: Vec<bool>,
+}
+
+/// The faces decoded with the Edgebreaker traversal.
+#[derive(Debug)]
+pub struct Edgebreaker {
+    pub corner_table: CornerTable,
+    pub attributes: Vec<AttributeConnectivity>,
+}
+
+/// The decoded faces of a mesh.
+#[derive(Debug)]
+pub struct Connectivity {
+    /// The points of each face.
+    pub faces: Vec<[u32; 3]>,
+    pub num_points: usize,
+    /// The corner tables the attributes are traversed with, if the faces were coded with the
+    /// Edgebreaker traversal.
+    pub edgebreaker: Option<Edgebreaker>,
+}
+
+/// Decodes faces coded as a list of indices.
+pub fn decode_sequential(buffer: &mut Buffer<'_>) -> Option<Connectivity> {
+    let num_faces = buffer.varint()? as usize;
+    let num_points = buffer.varint()? as usize;
+    let num_indices = num_faces.checked_mul(3)?;
+    let indices = match buffer.u8()? {
+        SEQUENTIAL_COMPRESSED_INDICES => {
+            // the differences to the previous index are coded
+            let mut last = 0i64;
+            decode_symbols(buffer, num_indices, 1)?
+                .into_iter()
+                .map(|value| {
+                    let difference = i64::from(value >> 1);
+                    last += if value & 1 == 1 {
+                        -difference
+                    } else {
+                        difference
+                    };
+                    u32::try_from(last).ok()
+                })
+                .collect::<Option<Vec<_>>>()?
+        }
+        SEQUENTIAL_UNCOMPRESSED_INDICES => (0..num_indices)
+            .map(|_| match num_points {
+                n if n < 1 << 8 => buffer.u8().map(u32::from),
+                n if n < 1 << 16 => buffer.u16().map(u32::from),
+                n if n < 1 << 21 => buffer.varint(),
+                _ => buffer.u32(),
+            })
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    if indices.iter().any(|index| *index as usize >= num_points) {
+        return None;
+    }
+    Some(Connectivity {
+        faces: indices
+            .chunks_exact(3)
+            .map(|face| [face[0], face[1], face[2]])
+            .collect(),
+        num_points,
+        edgebreaker: None,
+    })
+}
+
+/// A split of the mesh, where a face decoded from a `TOPOLOGY_S` symbol is attached to the edge
+/// of another face instead of the edge on the top of the active stack.
+#[derive(Debug)]
+struct TopologySplit {
+    source_symbol: u32,
+    split_symbol: u32,
+    /// Whether the edge is on the right of the source face, rather than on its left.
+    right_edge: bool,
+}
+
+fn decode_topology_splits(buffer: &mut Buffer<'_>) -> Option<Vec<TopologySplit>> {
+    let count = buffer.varint()? as usize;
+    let mut splits = Vec::new();
+    let mut last_source = 0u32;
+    for _ in 0..count {
+        let source_symbol = last_source.checked_add(buffer.varint()?)?;
+        let split_symbol = source_symbol.checked_sub(buffer.varint()?)?;
+        splits.push(TopologySplit {
+            source_symbol,
+            split_symbol,
+            right_edge: false,
+        });
+        last_source = source_symbol;
+    }
+    if count > 0 {
+        let mut bits = buffer.start_bits();
+        for split in &mut splits {
+            split.right_edge = bits.bits(1) == 1;
+        }
+        buffer.end_bits(&bits);
+    }
+    Some(splits)
+}
+
+/// Valences of the decoded vertices, which select the context the next symbol is read from.
+#[derive(Debug)]
+struct Valences {
+    valences: Vec<usize>,
+    /// The symbols of each context, read from the back.
+    contexts: Vec<Vec<u32>>,
+    context: Option<usize>,
+}
+
+/// Reads the symbols, start faces and attribute seams of the Edgebreaker traversal.
+#[derive(Debug)]
+struct Traversal<'a> {
+    symbols: BitReader<'a>,
+    valences: Option<Valences>,
+    last_symbol: u32,
+    start_faces: RAnsBitDecoder<'a>,
+    seams: Vec<RAnsBitDecoder<'a>>,
+}
+
+impl<'a> Traversal<'a> {
+    fn new(
+        buffer: &mut Buffer<'a>,
+        method: u8,
+        num_vertices: usize,
+        num_faces: usize,
+        num_attributes: usize,
+    ) -> Option<Self> {
+        let symbols = if method == STANDARD_TRAVERSAL {
+            let length = usize::try_from(buffer.varint64()?).ok()?;
+            let symbols = buffer.start_bits();
+            buffer.bytes(length)?;
+            symbols
+        } else {
+            Buffer::new(&[]).start_bits()
+        };
+        let start_faces = RAnsBitDecoder::new(buffer)?;
+        let seams = (0..num_attributes)
+            .map(|_| RAnsBitDecoder::new(buffer))
+            .collect::<Option<Vec<_>>>()?;
+        let valences = if method == VALENCE_TRAVERSAL {
+            let contexts = (MIN_VALENCE..=MAX_VALENCE)
+                .map(|_| {
+                    let count = buffer.varint()? as usize;
+                    if count > num_faces {
+                        return None;
+                    }
+                    decode_symbols(buffer, count, 1)
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(Valences {
+                valences: vec![0; num_vertices],
+                contexts,
+                context: None,
+            })
+        } else {
+            None
+        };
+        Some(Traversal {
+            symbols,
+            valences,
+            last_symbol: TOPOLOGY_INVALID,
+            start_faces,
+            seams,
+        })
+    }
+
+    fn symbol(&mut self) -> u32 {
+        self.last_symbol = match &mut self.valences {
+            Some(valences) => match valences.context {
+                // the first symbol of the traversal is always an E
+                None => TOPOLOGY_E,
+                Some(context) => valences.contexts[context]
+                    .pop()
+                    .and_then(|id| VALENCE_SYMBOLS.get(id as usize).cloned())
+                    .unwrap_or(TOPOLOGY_INVALID),
+            },
+            None => match self.symbols.bits(1) {
+                0 => TOPOLOGY_C,
+                _ => 1 | self.symbols.bits(2) << 1,
+            },
+        };
+        self.last_symbol
+    }
+
+    /// Updates the valences of the vertices of the face which was just decoded.
+    fn active_corner_reached(&mut self, table: &CornerTable, corner: u32) {
+        let valences = match &mut self.valences {
+            Some(valences) => valences,
+            None => return,
+        };
+        let next = table.vertex(table.next(corner)) as usize;
+        let previous = table.vertex(table.previous(corner)) as usize;
+        let tip = table.vertex(corner) as usize;
+        let increments: &[(usize, usize)] = match self.last_symbol {
+            TOPOLOGY_C | TOPOLOGY_S => &[(next, 1), (previous, 1)],
+            TOPOLOGY_R => &[(tip, 1), (next, 1), (previous, 2)],
+            TOPOLOGY_L => &[(tip, 1), (next, 2), (previous, 1)],
+            TOPOLOGY_E => &[(tip, 2), (next, 2), (previous, 2)],
+            _ => &[],
+        };
+        for (vertex, increment) in increments {
+            if let Some(valence) = valences.valences.get_mut(*vertex) {
+                *valence += increment;
+            }
+        }
+        let valence = valences.valences.get(next).cloned().unwrap_or(0);
+        valences.context = Some(valence.max(MIN_VALENCE).min(MAX_VALENCE) - MIN_VALENCE);
+    }
+
+    fn merge_vertices(&mut self, destination: u32, source: u32) {
+        if let Some(valences) = &mut self.valences {
+            let source = valences.valences.get(source as usize).cloned().unwrap_or(0);
+            if let Some(valence) = valences.valences.get_mut(destination as usize) {
+                *valence += source;
+            }
+        }
+    }
+}
+
+/// Decodes faces coded with the Edgebreaker traversal.
+pub fn decode_edgebreaker(buffer: &mut Buffer<'_>) -> Option<Connectivity> {
+    let method = buffer.u8()?;
+    if method != STANDARD_TRAVERSAL && method != VALENCE_TRAVERSAL {
+        return None;
+    }
+    let num_encoded_vertices = buffer.varint()? as usize;
+    let num_faces = buffer.varint()? as usize;
+    let num_attributes = buffer.u8()? as usize;
+    let num_symbols = buffer.varint()? as usize;
+    let num_split_symbols = buffer.varint()? as usize;
+    if num_faces < num_symbols
+        || num_faces > num_symbols + num_symbols / 3
+        || num_split_symbols > num_symbols
+    {
+        return None;
+    }
+    let mut splits = decode_topology_splits(buffer)?;
+    let max_vertices = num_encoded_vertices + num_split_symbols;
+    let mut traversal = Traversal::new(buffer, method, max_vertices, num_faces, num_attributes)?;
+
+    let mut decoder = EdgebreakerDecoder {
+        table: CornerTable::with_faces(num_faces),
+        is_hole: vec![true; max_vertices],
+        num_faces: 0,
+    };
+    decoder.table.left_most_corners.reserve(max_vertices);
+    let num_vertices = decoder.decode_symbols(
+        &mut traversal,
+        &mut splits,
+        num_symbols,
+        num_attributes == 0,
+    )?;
+
+    let mut seams = vec![Vec::new(); num_attributes];
+    for face in 0..num_faces as u32 {
+        decoder.decode_seams(face * 3, &mut traversal, &mut seams);
+    }
+    let attributes = seams
+        .into_iter()
+        .map(|seams| attribute_connectivity(&decoder.table, &seams))
+        .collect::<Option<Vec<_>>>()?;
+
+    let (faces, num_points) = if attributes.is_empty() {
+        let faces = decoder
+            .table
+            .vertices
+            .chunks_exact(3)
+            .map(|face| [face[0], face[1], face[2]])
+            .collect();
+        (faces, num_vertices)
+    } else {
+        assign_points(&decoder.table, &decoder.is_hole, &attributes)?
+    };
+    Some(Connectivity {
+        faces,
+        num_points,
+        edgebreaker: Some(Edgebreaker {
+            corner_table: decoder.table,
+            attributes,
+        }),
+    })
+}
+
+#[derive(Debug)]
+struct EdgebreakerDecoder {
+    table: CornerTable,
+    /// Whether each vertex is on a boundary of the mesh.
+    is_hole: Vec<bool>,
+    num_faces: usize,
+}
+
+impl EdgebreakerDecoder {
+    fn new_face(&mut self) -> u32 {
+        self.num_faces += 1;
+        (self.num_faces as u32 - 1) * 3
+    }
+
+    fn add_vertex(&mut self) -> Option<u32> {
+        if self.table.num_vertices() >= self.is_hole.len() {
+            return None;
+        }
+        Some(self.table.add_vertex())
+    }
+
+    /// Rebuilds the faces from the symbols, which are decoded in the reverse order of the
+    /// traversal of the encoder. Returns the number of vertices.
+    fn decode_symbols(
+        &mut self,
+        traversal: &mut Traversal<'_>,
+        splits: &mut Vec<TopologySplit>,
+        num_symbols: usize,
+        remove_isolated: bool,
+    ) -> Option<usize> {
+        // corners opposite to the open edges new faces are attached to
+        let mut active_corners = Vec::new();
+        let mut split_corners = HashMap::new();
+        let mut isolated = Vec::new();
+
+        for symbol_id in 0..num_symbols {
+            let symbol = traversal.symbol();
+            let table = &mut self.table;
+            let mut check_split = false;
+            match symbol {
+                TOPOLOGY_C => {
+                    let corner_a = *active_corners.last()?;
+                    let vertex_x = table.vertex(table.next(corner_a));
+                    let corner_b = table.next(table.left_most_corner(vertex_x));
+                    if corner_b == INVALID
+                        || corner_a == corner_b
+                        || table.opposite(corner_a) != INVALID
+                        || table.opposite(corner_b) != INVALID
+                    {
+                        return None;
+                    }
+                    let corner = self.new_face();
+                    let table = &mut self.table;
+                    table.set_opposites(corner_a, corner + 1);
+                    table.set_opposites(corner_b, corner + 2);
+                    let vertex_a_previous = table.vertex(table.previous(corner_a));
+                    let vertex_b_next = table.vertex(table.next(corner_b));
+                    if vertex_x == vertex_a_previous || vertex_x == vertex_b_next {
+                        return None;
+                    }
+                    table.map_corner(corner, vertex_x);
+                    table.map_corner(corner + 1, vertex_b_next);
+                    table.map_corner(corner + 2, vertex_a_previous);
+                    table.set_left_most_corner(vertex_a_previous, corner + 2);
+                    *self.is_hole.get_mut(vertex_x as usize)? = false;
+                    *active_corners.last_mut()? = corner;
+                }
+                TOPOLOGY_R | TOPOLOGY_L => {
+                    let corner_a = *active_corners.last()?;
+                    if table.opposite(corner_a) != INVALID {
+                        return None;
+                    }
+                    let corner = self.new_face();
+                    let (opposite, corner_l, corner_r) = if symbol == TOPOLOGY_R {
+                        (corner + 2, corner + 1, corner)
+                    } else {
+                        (corner + 1, corner, corner + 2)
+                    };
+                    let vertex = self.add_vertex()?;
+                    let table = &mut self.table;
+                    table.set_opposites(opposite, corner_a);
+                    table.map_corner(opposite, vertex);
+                    table.set_left_most_corner(vertex, opposite);
+                    let vertex_r = table.vertex(table.previous(corner_a));
+                    table.map_corner(corner_r, vertex_r);
+                    table.set_left_most_corner(vertex_r, corner_r);
+                    let vertex_l = table.vertex(table.next(corner_a));
+                    table.map_corner(corner_l, vertex_l);
+                    *active_corners.last_mut()? = corner;
+                    check_split = true;
+                }
+                TOPOLOGY_S => {
+                    let corner_b = active_corners.pop()?;
+                    if let Some(corner) = split_corners.get(&symbol_id) {
+                        active_corners.push(*corner);
+                    }
+                    let corner_a = *active_corners.last()?;
+                    if corner_a == corner_b
+                        || table.opposite(corner_a) != INVALID
+                        || table.opposite(corner_b) != INVALID
+                    {
+                        return None;
+                    }
+                    let corner = self.new_face();
+                    let table = &mut self.table;
+                    table.set_opposites(corner_a, corner + 2);
+                    table.set_opposites(corner_b, corner + 1);
+                    let vertex_p = table.vertex(table.previous(corner_a));
+                    table.map_corner(corner, vertex_p);
+                    table.map_corner(corner + 1, table.vertex(table.next(corner_a)));
+                    let vertex_b_previous = table.vertex(table.previous(corner_b));
+                    table.map_corner(corner + 2, vertex_b_previous);
+                    table.set_left_most_corner(vertex_b_previous, corner + 2);
+
+                    // the vertex "n" is merged into the vertex "p"
+                    let mut corner_n = table.next(corner_b);
+                    let vertex_n = table.vertex(corner_n);
+                    traversal.merge_vertices(vertex_p, vertex_n);
+                    let table = &mut self.table;
+                    table.set_left_most_corner(vertex_p, table.left_most_corner(vertex_n));
+                    let first = corner_n;
+                    while corner_n != INVALID {
+                        table.map_corner(corner_n, vertex_p);
+                        corner_n = table.swing_left(corner_n);
+                        if corner_n == first {
+                            return None;
+                        }
+                    }
+                    table.set_left_most_corner(vertex_n, INVALID);
+                    if remove_isolated {
+                        isolated.push(vertex_n);
+                    }
+                    *active_corners.last_mut()? = corner;
+                }
+                TOPOLOGY_E => {
+                    let corner = self.new_face();
+                    for i in 0..3 {
+                        let vertex = self.add_vertex()?;
+                        self.table.map_corner(corner + i, vertex);
+                        self.table.set_left_most_corner(vertex, corner + i);
+                    }
+                    active_corners.push(corner);
+                    check_split = true;
+                }
+                _ => return None,
+            }
+            traversal.active_corner_reached(&self.table, *active_corners.last()?);
+
+            if check_split {
+                // the encoder numbers the symbols in the reverse order
+                let encoder_symbol = (num_symbols - symbol_id - 1) as u32;
+                while let Some(split) = splits.last() {
+                    if split.source_symbol > encoder_symbol {
+                        return None;
+                    }
+                    if split.source_symbol != encoder_symbol {
+                        break;
+                    }
+                    let top = *active_corners.last()?;
+                    let corner = if split.right_edge {
+                        self.table.next(top)
+                    } else {
+                        self.table.previous(top)
+                    };
+                    let decoder_symbol =
+                        num_symbols.checked_sub(split.split_symbol as usize + 1)?;
+                    split_corners.insert(decoder_symbol, corner);
+                    splits.pop();
+                }
+            }
+        }
+
+        // the faces the traversal started from
+        while let Some(corner) = active_corners.pop() {
+            if traversal.start_faces.bit() {
+                // an interior face, attached to three open edges
+                if self.num_faces >= self.table.num_faces() {
+                    return None;
+                }
+                let table = &self.table;
+                let vertex_n = table.vertex(table.next(corner));
+                let corner_b = table.next(table.left_most_corner(vertex_n));
+                let vertex_x = table.vertex(table.next(corner_b));
+                let corner_c = table.next(table.left_most_corner(vertex_x));
+                if corner_b == INVALID
+                    || corner_c == INVALID
+                    || corner == corner_b
+                    || corner == corner_c
+                    || corner_b == corner_c
+                    || table.opposite(corner) != INVALID
+                    || table.opposite(corner_b) != INVALID
+                    || table.opposite(corner_c) != INVALID
+                {
+                    return None;
+                }
+                let vertex_p = table.vertex(table.next(corner_c));
+                let new_corner = self.new_face();
+                let table = &mut self.table;
+                table.set_opposites(new_corner, corner);
+                table.set_opposites(new_corner + 1, corner_b);
+                table.set_opposites(new_corner + 2, corner_c);
+                table.map_corner(new_corner, vertex_x);
+                table.map_corner(new_corner + 1, vertex_p);
+                table.map_corner(new_corner + 2, vertex_n);
+                for vertex in &[vertex_x, vertex_p, vertex_n] {
+                    *self.is_hole.get_mut(*vertex as usize)? = false;
+                }
+            }
+        }
+        if self.num_faces != self.table.num_faces() {
+            return None;
+        }
+
+        // move the last vertices into the place of the merged ones, so all vertices are used
+        let mut num_vertices = self.table.num_vertices();
+        for vertex in isolated {
+            let mut source = num_vertices as u32 - 1;
+            while self.table.left_most_corner(source) == INVALID {
+                num_vertices -= 1;
+                source = num_vertices as u32 - 1;
+            }
+            if source < vertex {
+                continue;
+            }
+            for corner in self
+                .table
+                .vertex_corners(self.table.left_most_corner(source))
+            {
+                if self.table.vertex(corner) != source {
+                    return None;
+                }
+                self.table.map_corner(corner, vertex);
+            }
+            let left_most = self.table.left_most_corner(source);
+            self.table.set_left_most_corner(vertex, left_most);
+            self.table.set_left_most_corner(source, INVALID);
+            self.is_hole[vertex as usize] = self.is_hole[source as usize];
+            self.is_hole[source as usize] = false;
+            num_vertices -= 1;
+        }
+        Some(num_vertices)
+    }
+
+    /// Reads which edges of the face are seams of each attribute, on edges not read yet.
+    fn decode_seams(&self, corner: u32, traversal: &mut Traversal<'_>, seams: &mut [Vec<u32>]) {
+        let table = &self.table;
+        for corner in [corner, table.next(corner), table.previous(corner)].iter() {
+            let opposite = table.opposite(*corner);
+            if opposite == INVALID {
+                // boundaries are always seams
+                for seams in seams.iter_mut() {
+                    seams.push(*corner);
+                }
+            } else if opposite / 3 >= corner / 3 {
+                for (seams, decoder) in seams.iter_mut().zip(&mut traversal.seams) {
+                    if decoder.bit() {
+                        seams.push(*corner);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds the corner table of an attribute, whose vertices are split along its seams.
+fn attribute_connectivity(table: &CornerTable, seams: &[u32]) -> Option<AttributeConnectivity> {
+    let mut on_seam_edge = vec![false; table.num_corners()];
+    let mut on_seam = vec![false; table.num_vertices()];
+    let mut mark = |corner: u32, on_seam_edge: &mut Vec<bool>| {
+        on_seam_edge[corner as usize] = true;
+        for vertex in &[
+            table.vertex(table.next(corner)),
+            table.vertex(table.previous(corner)),
+        ] {
+            if let Some(on_seam) = on_seam.get_mut(*vertex as usize) {
+                *on_seam = true;
+            }
+        }
+    };
+    for corner in seams {
+        mark(*corner, &mut on_seam_edge);
+        let opposite = table.opposite(*corner);
+        if opposite != INVALID {
+            mark(opposite, &mut on_seam_edge);
+        }
+    }
+
+    let mut attribute = CornerTable {
+        vertices: vec![INVALID; table.num_corners()],
+        opposites: table
+            .opposites
+            .iter()
+            .zip(&on_seam_edge)
+            .map(|(opposite, seam)| if *seam { INVALID } else { *opposite })
+            .collect(),
+        left_most_corners: Vec::new(),
+    };
+    for vertex in 0..table.num_vertices() as u32 {
+        let corner = table.left_most_corner(vertex);
+        if corner == INVALID {
+            continue;
+        }
+        // start from the first corner after a seam, going counterclockwise
+        let mut first = corner;
+        if on_seam[vertex as usize] {
+            let mut current = attribute.swing_left(first);
+            while current != INVALID {
+                first = current;
+                current = attribute.swing_left(current);
+                if current == corner {
+                    return None;
+                }
+            }
+        }
+        let mut attribute_vertex = attribute.add_vertex();
+        attribute.set_left_most_corner(attribute_vertex, first);
+        attribute.map_corner(first, attribute_vertex);
+        let mut current = table.swing_right(first);
+        while current != INVALID && current != first {
+            if on_seam_edge[table.next(current) as usize] {
+                attribute_vertex = attribute.add_vertex();
+                attribute.set_left_most_corner(attribute_vertex, current);
+            }
+            attribute.map_corner(current, attribute_vertex);
+            current = table.swing_right(current);
+        }
+    }
+    Some(AttributeConnectivity {
+        corner_table: attribute,
+        on_seam,
+    })
+}
+
+/// Gives a point to each distinct combination of attribute vertices around each vertex.
+fn assign_points(
+    table: &CornerTable,
+    is_hole: &[bool],
+    attributes: &[AttributeConnectivity],
+) -> Option<(Vec<[u32; 3]>, usize)> {
+    let mut corner_points = vec![INVALID; table.num_corners()];
+    let mut num_points = 0;
+    for vertex in 0..table.num_vertices() as u32 {
+        let corner = table.left_most_corner(vertex);
+        if corner == INVALID {
+            continue;
+        }
+        // boundary vertices start from their left most corner, others from any seam
+        let mut first = corner;
+        if !is_hole[vertex as usize] {
+            for attribute in attributes {
+                if !attribute.on_seam[vertex as usize] {
+                    continue;
+                }
+                let attribute_vertex = attribute.corner_table.vertex(corner);
+                let mut current = table.swing_right(corner);
+                let mut found = false;
+                while current != corner {
+                    if current == INVALID {
+                        return None;
+                    }
+                    if attribute.corner_table.vertex(current) != attribute_vertex {
+                        first = current;
+                        found = true;
+                        break;
+                    }
+                    current = table.swing_right(current);
+                }
+                if found {
+                    break;
+                }
+            }
+        }
+
+        corner_points[first as usize] = num_points;
+        num_points += 1;
+        let mut previous = first;
+        let mut current = table.swing_right(first);
+        while current != INVALID && current != first {
+            let seam = attributes.iter().any(|attribute| {
+                attribute.corner_table.vertex(current) != attribute.corner_table.vertex(previous)
+            });
+            corner_points[current as usize] = if seam {
+                num_points += 1;
+                num_points - 1
+            } else {
+                corner_points[previous as usize]
+            };
+            previous = current;
+            current = table.swing_right(current);
+        }
+    }
+    let faces = corner_points
+        .chunks_exact(3)
+        .map(|face| [face[0], face[1], face[2]])
+        .collect();
+    Some((faces, num_points as usize))
+}
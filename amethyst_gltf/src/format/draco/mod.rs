@@ -0,0 +1,183 @@
+//! Decoder of `KHR_draco_mesh_compression`, following version 2.2 of the Draco bitstream the
+//! extension requires, for triangular meshes coded either sequentially or with Edgebreaker.
+
+use self::{
+    attributes::decode_attributes,
+    buffer::Buffer,
+    connectivity::{decode_edgebreaker, decode_sequential},
+};
+
+pub use self::attributes::Attribute;
+
+mod attributes;
+mod buffer;
+mod connectivity;
+mod prediction;
+mod traversal;
+
+const TRIANGULAR_MESH: u8 = 1;
+const SEQUENTIAL: u8 = 0;
+const EDGEBREAKER: u8 = 1;
+const METADATA_FLAG: u16 = 0x8000;
+
+/// A decoded mesh.
+#[derive(Debug)]
+pub struct DracoMesh {
+    /// The points of each triangle.
+    pub faces: Vec<[u32; 3]>,
+    pub num_points: usize,
+    pub attributes: Vec<Attribute>,
+}
+
+impl DracoMesh {
+    /// Returns the attribute with the unique id the glTF attributes refer to.
+    pub fn attribute(&self, unique_id: u32) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.unique_id == unique_id)
+    }
+}
+
+/// Decodes a compressed mesh, returning `None` if the data is malformed or not supported.
+pub fn decode(data: &[u8]) -> Option<DracoMesh> {
+    let mut buffer = Buffer::new(data);
+    if buffer.bytes(5)? != b"DRACO" {
+        return None;
+    }
+    let version = (buffer.u8()?, buffer.u8()?);
+    if version != (2, 2) || buffer.u8()? != TRIANGULAR_MESH {
+        return None;
+    }
+    let method = buffer.u8()?;
+    if buffer.u16()? & METADATA_FLAG != 0 {
+        skip_metadata(&mut buffer)?;
+    }
+    let connectivity = match method {
+        SEQUENTIAL => decode_sequential(&mut buffer)?,
+        EDGEBREAKER => decode_edgebreaker(&mut buffer)?,
+        _ => return None,
+    };
+    let attributes = decode_attributes(&mut buffer, &connectivity)?;
+    Some(DracoMesh {
+        faces: connectivity.faces,
+        num_points: connectivity.num_points,
+        attributes,
+    })
+}
+
+/// Skips the metadata of the attributes and of the mesh, which glTF doesn't use.
+fn skip_metadata(buffer: &mut Buffer<'_>) -> Option<()> {
+    let num_attributes = buffer.varint()?;
+    for _ in 0..num_attributes {
+        let _unique_id = buffer.varint()?;
+        skip_metadata_tree(buffer)?;
+    }
+    skip_metadata_tree(buffer)
+}
+
+/// Skips a metadata entry along with its named children, which are stored depth first.
+fn skip_metadata_tree(buffer: &mut Buffer<'_>) -> Option<()> {
+    let mut remaining = 1u64;
+    let mut root = true;
+    while remaining > 0 {
+        remaining -= 1;
+        if !root {
+            let length = buffer.u8()?;
+            buffer.bytes(length as usize)?;
+        }
+        root = false;
+        for _ in 0..buffer.varint()? {
+            let length = buffer.u8()?;
+            buffer.bytes(length as usize)?;
+            let size = buffer.varint()? as usize;
+            if size == 0 {
+                return None;
+            }
+            buffer.bytes(size)?;
+        }
+        let children = buffer.varint()?;
+        if children as usize > buffer.remaining().len() {
+            return None;
+        }
+        remaining += u64::from(children);
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_sequential_mesh() {
+        let mut data = b"DRACO".to_vec();
+        // version, mesh, sequential, metadata
+        data.extend(&[2, 2, 1, 0, 0x00, 0x80]);
+        // no attribute metadata, a mesh entry with one child
+        data.extend(&[
+            0, 1, 4, b'n', b'a', b'm', b'e', 1, b'x', 1, 4, b'p', b'a', b'r', b't',
+        ]);
+        data.extend(&[0, 0]);
+        // one face of three points, with uncompressed indices
+        data.extend(&[1, 3, 1, 2, 0, 1]);
+        // a single decoder of two attributes
+        data.extend(&[1, 2]);
+        // float positions of the generic decoder
+        data.extend(&[0, 9, 3, 0, 7]);
+        // unsigned short generic attribute of the integer decoder, with difference prediction
+        data.extend(&[4, 4, 1, 0, 8]);
+        data.extend(&[0, 1]);
+        for value in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            data.extend(&value.to_le_bytes());
+        }
+        // prediction, wrap transform, uncompressed single bytes: corrections 5, 2, -3
+        data.extend(&[0, 1, 0, 1, 10, 4, 5]);
+        data.extend(&0i32.to_le_bytes());
+        data.extend(&10i32.to_le_bytes());
+
+        let mesh = decode(&data).unwrap();
+        assert_eq!(mesh.faces, vec![[2, 0, 1]]);
+        assert_eq!(mesh.num_points, 3);
+        let positions = mesh.attribute(7).unwrap();
+        assert_eq!(positions.num_components, 3);
+        assert_eq!(positions.point_values, vec![0, 1, 2]);
+        assert_eq!(positions.values[3..6], [1.0, 0.0, 0.0]);
+        assert_eq!(mesh.attribute(8).unwrap().values, vec![5.0, 7.0, 4.0]);
+        assert!(mesh.attribute(0).is_none());
+    }
+
+    #[test]
+    fn decodes_edgebreaker_mesh() {
+        let mut data = b"DRACO".to_vec();
+        data.extend(&[2, 2, 1, 1, 0, 0]);
+        // standard traversal of 4 vertices and 2 faces, without splits
+        data.extend(&[0, 4, 2, 0, 2, 0, 0]);
+        // the symbols E and R, and a start face which isn't interior
+        data.extend(&[1, 0b10_1111, 255, 1, 1]);
+        // a decoder of the position data, traversed depth first
+        data.extend(&[1, 0xff, 0, 0, 1, 0, 5, 2, 0, 0, 1]);
+        // parallelogram prediction with the wrap transform, uncompressed
+        data.extend(&[1, 1, 0, 1, 20, 0, 19, 20, 0, 19, 0, 0]);
+        data.extend(&0i32.to_le_bytes());
+        data.extend(&10i32.to_le_bytes());
+
+        let mesh = decode(&data).unwrap();
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [2, 1, 3]]);
+        assert_eq!(mesh.num_points, 4);
+        let positions = mesh.attribute(0).unwrap();
+        let values = (0..4)
+            .flat_map(|point| {
+                let value = positions.point_values[point] as usize * 2;
+                positions.values[value..value + 2].to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![0.0, 0.0, 10.0, 0.0, 0.0, 10.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let data = b"DRACO\x02\x01\x01\x00\x00\x00";
+        assert!(decode(data).is_none());
+        assert!(decode(b"DRAC").is_none());
+    }
+}
@@ -0,0 +1,847 @@
+//! Prediction schemes of integer attribute values, which code the correction of each value to a
+//! value predicted from the values decoded before it.
+
+use super::{
+    buffer::{Buffer, RAnsBitDecoder},
+    connectivity::{CornerTable, INVALID},
+};
+
+const DIFFERENCE: i8 = 0;
+const PARALLELOGRAM: i8 = 1;
+const MULTI_PARALLELOGRAM: i8 = 2;
+const CONSTRAINED_MULTI_PARALLELOGRAM: i8 = 4;
+const TEX_COORDS_PORTABLE: i8 = 5;
+const GEOMETRIC_NORMAL: i8 = 6;
+
+const WRAP: i8 = 1;
+const NORMAL_OCTAHEDRON: i8 = 2;
+const NORMAL_OCTAHEDRON_CANONICALIZED: i8 = 3;
+
+const MAX_PARALLELOGRAMS: usize = 4;
+
+/// How the entries of an attribute were visited on the mesh.
+#[derive(Debug)]
+pub struct MeshData<'a> {
+    pub table: &'a CornerTable,
+    /// The entry of each vertex of the table, `INVALID` for vertices which weren't visited.
+    pub vertex_entries: &'a [u32],
+    /// The corner each entry was visited from.
+    pub entry_corners: &'a [u32],
+}
+
+impl MeshData<'_> {
+    fn entry(&self, corner: u32) -> u32 {
+        self.vertex_entries
+            .get(self.table.vertex(corner) as usize)
+            .cloned()
+            .unwrap_or(INVALID)
+    }
+}
+
+/// The integer positions the texture coordinates and normals are predicted from.
+#[derive(Debug)]
+pub struct Positions<'a> {
+    /// The three components of each position value.
+    pub values: &'a [i32],
+    /// The value of each point.
+    pub point_values: &'a [u32],
+}
+
+/// What the predictions of an attribute are computed from.
+#[derive(Debug)]
+pub struct Context<'a> {
+    /// Only set for meshes coded with the Edgebreaker traversal, the others are only predicted
+    /// from the previous values.
+    pub mesh: Option<MeshData<'a>>,
+    /// The point of each entry.
+    pub entry_points: &'a [u32],
+    pub positions: Option<Positions<'a>>,
+}
+
+impl Context<'_> {
+    fn position(&self, entry: u32) -> Option<[i64; 3]> {
+        let point = *self.entry_points.get(entry as usize)?;
+        let positions = self.positions.as_ref()?;
+        let value = *positions.point_values.get(point as usize)? as usize;
+        let position = positions.values.get(value * 3..value * 3 + 3)?;
+        Some([
+            i64::from(position[0]),
+            i64::from(position[1]),
+            i64::from(position[2]),
+        ])
+    }
+}
+
+/// The quantized octahedral coordinates normals are coded with.
+#[derive(Clone, Copy, Debug)]
+pub struct Octahedron {
+    max_quantized: i32,
+    max_value: i32,
+    center: i32,
+}
+
+impl Octahedron {
+    pub fn new(bits: u8) -> Option<Self> {
+        if !(2..=30).contains(&bits) {
+            return None;
+        }
+        let max_quantized = (1 << bits) - 1;
+        Some(Octahedron {
+            max_quantized,
+            max_value: max_quantized - 1,
+            center: (max_quantized - 1) / 2,
+        })
+    }
+
+    /// Converts octahedral coordinates to a unit vector.
+    pub fn to_vector(self, s: i32, t: i32) -> [f32; 3] {
+        let scale = 2.0 / self.max_value as f32;
+        let y = s as f32 * scale - 1.0;
+        let z = t as f32 * scale - 1.0;
+        let x = 1.0 - y.abs() - z.abs();
+        let offset = (-x).max(0.0);
+        let y = y + if y < 0.0 { offset } else { -offset };
+        let z = z + if z < 0.0 { offset } else { -offset };
+        let norm_squared = x * x + y * y + z * z;
+        if norm_squared < 1e-6 {
+            return [0.0; 3];
+        }
+        let d = 1.0 / norm_squared.sqrt();
+        [x * d, y * d, z * d]
+    }
+
+    fn is_in_diamond(self, [s, t]: [i32; 2]) -> bool {
+        s.unsigned_abs() + t.unsigned_abs() <= self.center as u32
+    }
+
+    fn invert_diamond(self, [s, t]: [i32; 2]) -> [i32; 2] {
+        let (sign_s, sign_t) = if s >= 0 && t >= 0 {
+            (1, 1)
+        } else if s <= 0 && t <= 0 {
+            (-1, -1)
+        } else {
+            (if s > 0 { 1 } else { -1 }, if t > 0 { 1 } else { -1 })
+        };
+        let corner_s = (sign_s * self.center) as u32;
+        let corner_t = (sign_t * self.center) as u32;
+        let us = (s as u32).wrapping_mul(2).wrapping_sub(corner_s);
+        let ut = (t as u32).wrapping_mul(2).wrapping_sub(corner_t);
+        let (us, ut) = if sign_s * sign_t >= 0 {
+            (ut.wrapping_neg(), us.wrapping_neg())
+        } else {
+            (ut, us)
+        };
+        [
+            us.wrapping_add(corner_s) as i32 / 2,
+            ut.wrapping_add(corner_t) as i32 / 2,
+        ]
+    }
+
+    fn mod_max(self, x: i32) -> i32 {
+        if x > self.center {
+            x - self.max_quantized
+        } else if x < -self.center {
+            x + self.max_quantized
+        } else {
+            x
+        }
+    }
+
+    /// Scales a vector so the sum of its absolute components is the center value.
+    fn canonicalize_vector(self, vector: [i32; 3]) -> [i32; 3] {
+        let abs_sum: i64 = vector.iter().map(|v| i64::from(*v).abs()).sum();
+        if abs_sum == 0 {
+            return [self.center, 0, 0];
+        }
+        let x = (i64::from(vector[0]) * i64::from(self.center) / abs_sum) as i32;
+        let y = (i64::from(vector[1]) * i64::from(self.center) / abs_sum) as i32;
+        let z = self.center - x.abs() - y.abs();
+        [x, y, if vector[2] >= 0 { z } else { -z }]
+    }
+
+    fn vector_to_coordinates(self, [x, y, z]: [i32; 3]) -> [i32; 2] {
+        let (s, t) = if x >= 0 {
+            (y + self.center, z + self.center)
+        } else {
+            (
+                if y < 0 {
+                    z.abs()
+                } else {
+                    self.max_value - z.abs()
+                },
+                if z < 0 {
+                    y.abs()
+                } else {
+                    self.max_value - y.abs()
+                },
+            )
+        };
+        let (max, center) = (self.max_value, self.center);
+        if (s == 0 && (t == 0 || t == max)) || (s == max && t == 0) {
+            [max, max]
+        } else if s == 0 && t > center {
+            [s, center - (t - center)]
+        } else if s == max && t < center {
+            [s, center + (center - t)]
+        } else if t == max && s < center {
+            [center + (center - s), t]
+        } else if t == 0 && s > center {
+            [center - (s - center), t]
+        } else {
+            [s, t]
+        }
+    }
+}
+
+fn rotation_count([s, t]: [i32; 2]) -> u32 {
+    match (s.signum(), t.signum()) {
+        (0, 0) => 0,
+        (0, 1) => 3,
+        (0, _) => 1,
+        (1, 0) | (1, 1) => 2,
+        (1, _) => 1,
+        (_, 1) => 3,
+        _ => 0,
+    }
+}
+
+fn rotate([s, t]: [i32; 2], count: u32) -> [i32; 2] {
+    match count {
+        1 => [t, -s],
+        2 => [-s, -t],
+        3 => [-t, s],
+        _ => [s, t],
+    }
+}
+
+/// Converts a prediction and its correction to the original value.
+#[derive(Debug)]
+enum Transform {
+    /// The values are wrapped into the range of the attribute.
+    Wrap { min: i32, max: i32 },
+    /// The octahedral coordinates of normals are wrapped around the octahedron.
+    Octahedron {
+        octahedron: Octahedron,
+        canonicalized: bool,
+    },
+}
+
+impl Transform {
+    fn read(kind: i8, buffer: &mut Buffer<'_>) -> Option<Self> {
+        match kind {
+            WRAP => {
+                let min = buffer.i32()?;
+                let max = buffer.i32()?;
+                if min > max || i64::from(max) - i64::from(min) >= i64::from(i32::MAX) {
+                    return None;
+                }
+                Some(Transform::Wrap { min, max })
+            }
+            NORMAL_OCTAHEDRON | NORMAL_OCTAHEDRON_CANONICALIZED => {
+                let max_quantized = buffer.i32()?;
+                if max_quantized <= 0 || max_quantized % 2 == 0 {
+                    return None;
+                }
+                let bits = 32 - max_quantized.leading_zeros();
+                Some(Transform::Octahedron {
+                    octahedron: Octahedron::new(bits as u8)?,
+                    canonicalized: kind == NORMAL_OCTAHEDRON_CANONICALIZED,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn original(&self, predicted: &[i32], corrections: &[i32], original: &mut [i32]) {
+        match *self {
+            Transform::Wrap { min, max } => {
+                let range = max.wrapping_sub(min).wrapping_add(1);
+                for ((p, c), o) in predicted.iter().zip(corrections).zip(original) {
+                    let value = (*p).max(min).min(max).wrapping_add(*c);
+                    *o = if value > max {
+                        value.wrapping_sub(range)
+                    } else if value < min {
+                        value.wrapping_add(range)
+                    } else {
+                        value
+                    };
+                }
+            }
+            Transform::Octahedron {
+                octahedron,
+                canonicalized,
+            } => {
+                let center = octahedron.center;
+                let mut p = [predicted[0] - center, predicted[1] - center];
+                let in_diamond = octahedron.is_in_diamond(p);
+                if !in_diamond {
+                    p = octahedron.invert_diamond(p);
+                }
+                let in_bottom_left = !canonicalized || p == [0, 0] || (p[0] < 0 && p[1] <= 0);
+                let rotation = rotation_count(p);
+                if !in_bottom_left {
+                    p = rotate(p, rotation);
+                }
+                let mut o = [
+                    octahedron.mod_max(p[0].wrapping_add(corrections[0])),
+                    octahedron.mod_max(p[1].wrapping_add(corrections[1])),
+                ];
+                if !in_bottom_left {
+                    o = rotate(o, (4 - rotation) % 4);
+                }
+                if !in_diamond {
+                    o = octahedron.invert_diamond(o);
+                }
+                original[0] = o[0] + center;
+                original[1] = o[1] + center;
+            }
+        }
+    }
+}
+
+/// A prediction scheme along with the transform of its corrections.
+#[derive(Debug)]
+pub struct Scheme {
+    method: i8,
+    transform: i8,
+}
+
+impl Scheme {
+    /// Returns the scheme of the coded method and transform, falling back to the difference to
+    /// the previous value for mesh predictions without an Edgebreaker traversal, like the
+    /// encoder does.
+    ///
+    /// Normals are predicted with octahedron transforms, other attributes with the wrap
+    /// transform.
+    pub fn new(method: i8, transform: i8, normals: bool, traversed: bool) -> Option<Self> {
+        let transform_supported = if normals {
+            transform == NORMAL_OCTAHEDRON || transform == NORMAL_OCTAHEDRON_CANONICALIZED
+        } else {
+            transform == WRAP
+        };
+        if !transform_supported {
+            return None;
+        }
+        let method = match method {
+            DIFFERENCE => DIFFERENCE,
+            GEOMETRIC_NORMAL if !normals => return None,
+            PARALLELOGRAM
+            | MULTI_PARALLELOGRAM
+            | CONSTRAINED_MULTI_PARALLELOGRAM
+            | TEX_COORDS_PORTABLE
+            | GEOMETRIC_NORMAL => {
+                if traversed {
+                    method
+                } else {
+                    DIFFERENCE
+                }
+            }
+            _ => return None,
+        };
+        Some(Scheme { method, transform })
+    }
+
+    /// Whether the corrections are coded without their sign.
+    pub fn corrections_positive(&self) -> bool {
+        self.transform != WRAP
+    }
+
+    /// Reads the data of the scheme, and computes the values from their corrections.
+    pub fn decode(
+        &self,
+        buffer: &mut Buffer<'_>,
+        context: &Context<'_>,
+        corrections: &[i32],
+        components: usize,
+    ) -> Option<Vec<i32>> {
+        let mut values = vec![0; corrections.len()];
+        if self.method == DIFFERENCE {
+            let transform = Transform::read(self.transform, buffer)?;
+            difference(&transform, corrections, components, &mut values);
+            return Some(values);
+        }
+        let mesh = context.mesh.as_ref()?;
+        if mesh.entry_corners.len() * components != corrections.len() {
+            return None;
+        }
+        match self.method {
+            PARALLELOGRAM | MULTI_PARALLELOGRAM => {
+                let transform = Transform::read(self.transform, buffer)?;
+                let multi = self.method == MULTI_PARALLELOGRAM;
+                parallelogram(
+                    &transform,
+                    mesh,
+                    multi,
+                    corrections,
+                    components,
+                    &mut values,
+                );
+            }
+            CONSTRAINED_MULTI_PARALLELOGRAM => {
+                let mut creases = Vec::with_capacity(MAX_PARALLELOGRAMS);
+                for _ in 0..MAX_PARALLELOGRAMS {
+                    let count = buffer.varint()? as usize;
+                    if count > mesh.table.num_corners() {
+                        return None;
+                    }
+                    let mut flags = Vec::with_capacity(count);
+                    if count > 0 {
+                        let mut decoder = RAnsBitDecoder::new(buffer)?;
+                        flags.extend((0..count).map(|_| decoder.bit()));
+                    }
+                    creases.push(flags);
+                }
+                let transform = Transform::read(self.transform, buffer)?;
+                let predictor = ConstrainedParallelogram { mesh, creases };
+                predictor.decode(&transform, corrections, components, &mut values)?;
+            }
+            TEX_COORDS_PORTABLE => {
+                if components != 2 {
+                    return None;
+                }
+                let count = buffer.i32()?;
+                if count < 0 || count as usize > corrections.len() {
+                    return None;
+                }
+                let mut decoder = RAnsBitDecoder::new(buffer)?;
+                let mut orientation = true;
+                let mut orientations = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    if !decoder.bit() {
+                        orientation = !orientation;
+                    }
+                    orientations.push(orientation);
+                }
+                let transform = Transform::read(self.transform, buffer)?;
+                let mut predictor = TexCoords {
+                    mesh,
+                    context,
+                    orientations,
+                };
+                for (entry, corner) in mesh.entry_corners.iter().enumerate() {
+                    let predicted = predictor.predict(*corner, entry as u32, &values)?;
+                    let range = entry * 2..entry * 2 + 2;
+                    transform.original(&predicted, &corrections[range.clone()], &mut values[range]);
+                }
+            }
+            GEOMETRIC_NORMAL => {
+                if components != 2 {
+                    return None;
+                }
+                let transform = Transform::read(self.transform, buffer)?;
+                let octahedron = match transform {
+                    Transform::Octahedron { octahedron, .. } => octahedron,
+                    Transform::Wrap { .. } => return None,
+                };
+                let mut flips = RAnsBitDecoder::new(buffer)?;
+                for (entry, corner) in mesh.entry_corners.iter().enumerate() {
+                    let normal = predict_normal(mesh, context, *corner)?;
+                    let mut normal = octahedron.canonicalize_vector(normal);
+                    if flips.bit() {
+                        normal = [-normal[0], -normal[1], -normal[2]];
+                    }
+                    let predicted = octahedron.vector_to_coordinates(normal);
+                    let range = entry * 2..entry * 2 + 2;
+                    transform.original(&predicted, &corrections[range.clone()], &mut values[range]);
+                }
+            }
+            _ => return None,
+        }
+        Some(values)
+    }
+}
+
+/// Predicts each value with the previous one.
+fn difference(transform: &Transform, corrections: &[i32], components: usize, values: &mut [i32]) {
+    let mut predicted = vec![0; components];
+    for (correction, value) in corrections
+        .chunks_exact(components)
+        .zip(values.chunks_exact_mut(components))
+    {
+        transform.original(&predicted, correction, value);
+        predicted.copy_from_slice(value);
+    }
+}
+
+/// Predicts the value of the vertex opposite to `corner` by completing the parallelogram of the
+/// adjacent face, if the values of its vertices were decoded before the entry.
+fn parallelogram_prediction(
+    mesh: &MeshData<'_>,
+    entry: usize,
+    corner: u32,
+    values: &[i32],
+    components: usize,
+    predicted: &mut [i32],
+) -> bool {
+    let table = mesh.table;
+    let opposite = table.opposite(corner);
+    if opposite == INVALID {
+        return false;
+    }
+    let entries = [
+        mesh.entry(opposite),
+        mesh.entry(table.next(opposite)),
+        mesh.entry(table.previous(opposite)),
+    ];
+    if entries.iter().any(|e| *e as usize >= entry) {
+        return false;
+    }
+    let value = |e: u32, c: usize| i64::from(values[e as usize * components + c]);
+    for (c, predicted) in predicted.iter_mut().enumerate() {
+        *predicted = (value(entries[1], c) + value(entries[2], c) - value(entries[0], c)) as i32;
+    }
+    true
+}
+
+/// Predicts each value with the parallelogram of one face, or the average of the parallelograms
+/// of all faces around it if `multi`.
+fn parallelogram(
+    transform: &Transform,
+    mesh: &MeshData<'_>,
+    multi: bool,
+    corrections: &[i32],
+    components: usize,
+    values: &mut [i32],
+) {
+    let mut predicted = vec![0; components];
+    let mut parallelogram = vec![0; components];
+    transform.original(
+        &predicted,
+        &corrections[..components],
+        &mut values[..components],
+    );
+    for (entry, start) in mesh.entry_corners.iter().enumerate().skip(1) {
+        let mut count = 0;
+        if multi {
+            predicted.iter_mut().for_each(|p| *p = 0);
+            let mut corner = *start;
+            while corner != INVALID {
+                if parallelogram_prediction(
+                    mesh,
+                    entry,
+                    corner,
+                    values,
+                    components,
+                    &mut parallelogram,
+                ) {
+                    for (p, v) in predicted.iter_mut().zip(&parallelogram) {
+                        *p = p.wrapping_add(*v);
+                    }
+                    count += 1;
+                }
+                corner = mesh.table.swing_right(corner);
+                if corner == *start {
+                    break;
+                }
+            }
+            predicted.iter_mut().for_each(|p| *p /= count.max(1));
+        } else if parallelogram_prediction(mesh, entry, *start, values, components, &mut predicted)
+        {
+            count = 1;
+        }
+        let range = entry * components..(entry + 1) * components;
+        if count == 0 {
+            let previous = (entry - 1) * components;
+            predicted.copy_from_slice(&values[previous..previous + components]);
+        }
+        transform.original(&predicted, &corrections[range.clone()], &mut values[range]);
+    }
+}
+
+/// Predicts each value with the average of the parallelograms around it which don't cross a
+/// crease, as flagged by the encoder.
+struct ConstrainedParallelogram<'a> {
+    mesh: &'a MeshData<'a>,
+    /// The crease flags of the parallelograms, for each number of available parallelograms.
+    creases: Vec<Vec<bool>>,
+}
+
+impl ConstrainedParallelogram<'_> {
+    fn decode(
+        &self,
+        transform: &Transform,
+        corrections: &[i32],
+        components: usize,
+        values: &mut [i32],
+    ) -> Option<()> {
+        let table = self.mesh.table;
+        let mut parallelograms = vec![vec![0; components]; MAX_PARALLELOGRAMS];
+        let mut predicted = vec![0; components];
+        let mut positions = [0; MAX_PARALLELOGRAMS];
+        transform.original(
+            &predicted,
+            &corrections[..components],
+            &mut values[..components],
+        );
+        for (entry, start) in self.mesh.entry_corners.iter().enumerate().skip(1) {
+            // swing left from the corner, then right once a boundary is reached
+            let mut count = 0;
+            let mut first_pass = true;
+            let mut corner = *start;
+            while corner != INVALID {
+                let parallelogram = &mut parallelograms[count];
+                if parallelogram_prediction(
+                    self.mesh,
+                    entry,
+                    corner,
+                    values,
+                    components,
+                    parallelogram,
+                ) {
+                    count += 1;
+                    if count == MAX_PARALLELOGRAMS {
+                        break;
+                    }
+                }
+                corner = if first_pass {
+                    table.swing_left(corner)
+                } else {
+                    table.swing_right(corner)
+                };
+                if corner == *start {
+                    break;
+                }
+                if corner == INVALID && first_pass {
+                    first_pass = false;
+                    corner = table.swing_right(*start);
+                }
+            }
+
+            let mut used = 0;
+            predicted.iter_mut().for_each(|p| *p = 0);
+            for parallelogram in &parallelograms[..count] {
+                let context = count - 1;
+                let crease = *self.creases[context].get(positions[context])?;
+                positions[context] += 1;
+                if !crease {
+                    used += 1;
+                    for (p, v) in predicted.iter_mut().zip(parallelogram) {
+                        *p = p.wrapping_add(*v);
+                    }
+                }
+            }
+            let range = entry * components..(entry + 1) * components;
+            if used == 0 {
+                let previous = (entry - 1) * components;
+                predicted.copy_from_slice(&values[previous..previous + components]);
+            } else {
+                predicted.iter_mut().for_each(|p| *p /= used);
+            }
+            transform.original(&predicted, &corrections[range.clone()], &mut values[range]);
+        }
+        Some(())
+    }
+}
+
+/// Predicts texture coordinates by mapping the triangle of the positions of a face onto the
+/// texture coordinates of its other two corners.
+struct TexCoords<'a> {
+    mesh: &'a MeshData<'a>,
+    context: &'a Context<'a>,
+    /// On which side of the opposite edge each predicted coordinate is, read from the back.
+    orientations: Vec<bool>,
+}
+
+impl TexCoords<'_> {
+    fn predict(&mut self, corner: u32, entry: u32, values: &[i32]) -> Option<[i32; 2]> {
+        let table = self.mesh.table;
+        let next = self.mesh.entry(table.next(corner));
+        let previous = self.mesh.entry(table.previous(corner));
+        let uv = |e: u32| {
+            let i = e as usize * 2;
+            [i64::from(values[i]), i64::from(values[i + 1])]
+        };
+        if previous < entry && next < entry {
+            let n_uv = uv(next);
+            let p_uv = uv(previous);
+            if p_uv == n_uv {
+                return Some([p_uv[0] as i32, p_uv[1] as i32]);
+            }
+            let tip = self.context.position(entry)?;
+            let next_position = self.context.position(next)?;
+            let previous_position = self.context.position(previous)?;
+            let pn = sub(previous_position, next_position);
+            let pn_norm_squared = dot(pn, pn) as u64;
+            if pn_norm_squared != 0 {
+                let cn = sub(tip, next_position);
+                let cn_dot_pn = dot(pn, cn);
+                let pn_uv = [p_uv[0] - n_uv[0], p_uv[1] - n_uv[1]];
+
+                // don't overflow the computations below
+                let n_uv_max = n_uv[0].abs().max(n_uv[1].abs()) as u64;
+                if n_uv_max > i64::MAX as u64 / pn_norm_squared {
+                    return None;
+                }
+                let pn_uv_max = pn_uv[0].abs().max(pn_uv[1].abs());
+                if cn_dot_pn > i64::MAX / pn_uv_max {
+                    return None;
+                }
+                let pn_max = pn[0].abs().max(pn[1].abs()).max(pn[2].abs());
+                if cn_dot_pn > i64::MAX / pn_max {
+                    return None;
+                }
+
+                // the projection of the tip on the opposite edge, scaled by its squared length
+                let norm = pn_norm_squared as i64;
+                let x_uv = [
+                    n_uv[0]
+                        .wrapping_mul(norm)
+                        .wrapping_add(cn_dot_pn.wrapping_mul(pn_uv[0])),
+                    n_uv[1]
+                        .wrapping_mul(norm)
+                        .wrapping_add(cn_dot_pn.wrapping_mul(pn_uv[1])),
+                ];
+                let x_position = [
+                    next_position[0] + cn_dot_pn.wrapping_mul(pn[0]) / norm,
+                    next_position[1] + cn_dot_pn.wrapping_mul(pn[1]) / norm,
+                    next_position[2] + cn_dot_pn.wrapping_mul(pn[2]) / norm,
+                ];
+                let cx = sub(tip, x_position);
+                let cx_norm_squared = dot(cx, cx) as u64;
+                let scale = int_sqrt(cx_norm_squared.wrapping_mul(pn_norm_squared)) as i64;
+                let cx_uv = [
+                    pn_uv[1].wrapping_mul(scale),
+                    (-pn_uv[0]).wrapping_mul(scale),
+                ];
+                let orientation = self.orientations.pop()?;
+                let predict = |i: usize| {
+                    let uv = if orientation {
+                        (x_uv[i] as u64).wrapping_add(cx_uv[i] as u64)
+                    } else {
+                        (x_uv[i] as u64).wrapping_sub(cx_uv[i] as u64)
+                    };
+                    (uv as i64 / norm) as i32
+                };
+                return Some([predict(0), predict(1)]);
+            }
+        }
+        // fall back to the value of the next corner, or the previous entry
+        let fallback = if next < entry {
+            next
+        } else if entry > 0 {
+            entry - 1
+        } else {
+            return Some([0, 0]);
+        };
+        let i = fallback as usize * 2;
+        Some([values[i], values[i + 1]])
+    }
+}
+
+/// Predicts the normal of the vertex of `corner` as the sum of the normals of the faces around
+/// it, weighted by their areas.
+fn predict_normal(mesh: &MeshData<'_>, context: &Context<'_>, corner: u32) -> Option<[i32; 3]> {
+    let table = mesh.table;
+    let position = |corner: u32| context.position(mesh.entry(corner));
+    let center = position(corner)?;
+    let mut normal = [0i64; 3];
+    for corner in table.vertex_corners(corner) {
+        let next = sub(position(table.next(corner))?, center);
+        let previous = sub(position(table.previous(corner))?, center);
+        let cross = [
+            next[1]
+                .wrapping_mul(previous[2])
+                .wrapping_sub(next[2].wrapping_mul(previous[1])),
+            next[2]
+                .wrapping_mul(previous[0])
+                .wrapping_sub(next[0].wrapping_mul(previous[2])),
+            next[0]
+                .wrapping_mul(previous[1])
+                .wrapping_sub(next[1].wrapping_mul(previous[0])),
+        ];
+        for (n, c) in normal.iter_mut().zip(&cross) {
+            *n = n.wrapping_add(*c);
+        }
+    }
+    const UPPER_BOUND: i64 = 1 << 29;
+    let abs_sum = normal
+        .iter()
+        .fold(0i64, |sum, n| sum.saturating_add(n.wrapping_abs()));
+    if abs_sum > UPPER_BOUND {
+        let quotient = abs_sum / UPPER_BOUND;
+        normal.iter_mut().for_each(|n| *n /= quotient);
+    }
+    Some([normal[0] as i32, normal[1] as i32, normal[2] as i32])
+}
+
+fn sub(a: [i64; 3], b: [i64; 3]) -> [i64; 3] {
+    [
+        a[0].wrapping_sub(b[0]),
+        a[1].wrapping_sub(b[1]),
+        a[2].wrapping_sub(b[2]),
+    ]
+}
+
+fn dot(a: [i64; 3], b: [i64; 3]) -> i64 {
+    a[0].wrapping_mul(b[0])
+        .wrapping_add(a[1].wrapping_mul(b[1]))
+        .wrapping_add(a[2].wrapping_mul(b[2]))
+}
+
+/// The integer square root, rounded down.
+fn int_sqrt(number: u64) -> u64 {
+    if number == 0 {
+        return 0;
+    }
+    let mut root = 1u64;
+    let mut remaining = number;
+    while remaining >= 2 {
+        root *= 2;
+        remaining /= 4;
+    }
+    loop {
+        root = (root + number / root) / 2;
+        if root.wrapping_mul(root) <= number {
+            return root;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_values_into_range() {
+        let transform = Transform::Wrap { min: 0, max: 9 };
+        let mut values = [0; 2];
+        transform.original(&[8, 12], &[3, -1], &mut values);
+        assert_eq!(values, [1, 8]);
+    }
+
+    #[test]
+    fn decodes_octahedral_coordinates() {
+        let octahedron = Octahedron::new(4).unwrap();
+        let center = octahedron.center;
+        assert_eq!(octahedron.to_vector(center, center), [1.0, 0.0, 0.0]);
+        let [x, y, z] = octahedron.to_vector(0, center);
+        assert!(x.abs() < 1e-6 && (y + 1.0).abs() < 1e-6 && z.abs() < 1e-6);
+        assert_eq!(
+            octahedron.vector_to_coordinates([center, 0, 0]),
+            [center, center]
+        );
+        // predictions corrected to themselves are kept
+        for canonicalized in &[false, true] {
+            let transform = Transform::Octahedron {
+                octahedron,
+                canonicalized: *canonicalized,
+            };
+            for predicted in &[[1, 2], [7, 7], [13, 3], [2, 12]] {
+                let mut values = [0; 2];
+                transform.original(predicted, &[0, 0], &mut values);
+                assert_eq!(&values, predicted);
+            }
+        }
+    }
+
+    #[test]
+    fn computes_integer_square_roots() {
+        assert_eq!(int_sqrt(0), 0);
+        assert_eq!(int_sqrt(15), 3);
+        assert_eq!(int_sqrt(16), 4);
+        assert_eq!(
+            int_sqrt(u64::from(u32::MAX) * u64::from(u32::MAX)),
+            u64::from(u32::MAX)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! The order the attribute values of a mesh coded with Edgebreaker are stored in, given by a
+//! traversal of its faces.
+
+use super::connectivity::{CornerTable, INVALID};
+
+pub const DEPTH_FIRST: u8 = 0;
+pub const PREDICTION_DEGREE: u8 = 1;
+
+const MAX_PRIORITY: usize = 3;
+
+/// The entries of an attribute, in the order they were visited.
+#[derive(Debug, Default)]
+pub struct Sequence {
+    /// The point of each entry.
+    pub entry_points: Vec<u32>,
+    /// The corner each entry was visited from.
+    pub entry_corners: Vec<u32>,
+    /// The entry of each vertex, `INVALID` for vertices which weren't visited.
+    pub vertex_entries: Vec<u32>,
+}
+
+impl Sequence {
+    /// Maps each point of the faces to the entry of its vertex.
+    pub fn point_values(
+        &self,
+        table: &CornerTable,
+        faces: &[[u32; 3]],
+        num_points: usize,
+    ) -> Option<Vec<u32>> {
+        let mut point_values = vec![0; num_points];
+        for (face, points) in faces.iter().enumerate() {
+            for (i, point) in points.iter().enumerate() {
+                let vertex = table.vertex(face as u32 * 3 + i as u32);
+                let entry = *self.vertex_entries.get(vertex as usize)?;
+                if entry as usize >= num_points {
+                    return None;
+                }
+                *point_values.get_mut(*point as usize)? = entry;
+            }
+        }
+        Some(point_values)
+    }
+}
+
+/// Visits the vertices of the table from each face in order, like the encoder did.
+///
+/// `num_vertices` is the number of vertex entries to keep, which may be more than the vertices of
+/// the table.
+pub fn traverse(
+    table: &CornerTable,
+    faces: &[[u32; 3]],
+    method: u8,
+    num_vertices: usize,
+) -> Option<Sequence> {
+    let mut traverser = Traverser {
+        table,
+        faces,
+        visited_faces: vec![false; table.num_faces()],
+        visited_vertices: vec![false; table.num_vertices()],
+        sequence: Sequence {
+            vertex_entries: vec![INVALID; num_vertices.max(table.num_vertices())],
+            ..Sequence::default()
+        },
+    };
+    match method {
+        DEPTH_FIRST => {
+            for face in 0..table.num_faces() as u32 {
+                traverser.depth_first(face * 3)?;
+            }
+        }
+        PREDICTION_DEGREE => {
+            let mut degrees = vec![0; table.num_vertices()];
+            for face in 0..table.num_faces() as u32 {
+                traverser.prediction_degree(face * 3, &mut degrees)?;
+            }
+        }
+        _ => return None,
+    }
+    Some(traverser.sequence)
+}
+
+struct Traverser<'a> {
+    table: &'a CornerTable,
+    faces: &'a [[u32; 3]],
+    visited_faces: Vec<bool>,
+    visited_vertices: Vec<bool>,
+    sequence: Sequence,
+}
+
+impl Traverser<'_> {
+    fn is_face_visited(&self, corner: u32) -> bool {
+        corner == INVALID || self.visited_faces[corner as usize / 3]
+    }
+
+    /// Adds the entry of the vertex of `corner`, if it wasn't visited yet.
+    fn visit(&mut self, corner: u32) -> Option<()> {
+        let vertex = self.table.vertex(corner);
+        if *self.visited_vertices.get(vertex as usize)? {
+            return Some(());
+        }
+        self.visited_vertices[vertex as usize] = true;
+        let point = self.faces.get(corner as usize / 3)?[corner as usize % 3];
+        self.sequence.vertex_entries[vertex as usize] = self.sequence.entry_points.len() as u32;
+        self.sequence.entry_points.push(point);
+        self.sequence.entry_corners.push(corner);
+        Some(())
+    }
+
+    fn depth_first(&mut self, start: u32) -> Option<()> {
+        if self.is_face_visited(start) {
+            return Some(());
+        }
+        let table = self.table;
+        self.visit(table.next(start))?;
+        self.visit(table.previous(start))?;
+        let mut stack = vec![start];
+        while let Some(&top) = stack.last() {
+            if self.is_face_visited(top) {
+                stack.pop();
+                continue;
+            }
+            let mut corner = top;
+            loop {
+                if corner == INVALID {
+                    return None;
+                }
+                self.visited_faces[corner as usize / 3] = true;
+                let vertex = table.vertex(corner);
+                if !*self.visited_vertices.get(vertex as usize)? {
+                    let on_boundary = table.is_on_boundary(vertex);
+                    self.visit(corner)?;
+                    if !on_boundary {
+                        corner = table.right_corner(corner);
+                        continue;
+                    }
+                }
+                let right = table.right_corner(corner);
+                let left = table.left_corner(corner);
+                match (self.is_face_visited(right), self.is_face_visited(left)) {
+                    (true, true) => {
+                        stack.pop();
+                        break;
+                    }
+                    (true, false) => corner = left,
+                    (false, true) => corner = right,
+                    (false, false) => {
+                        // the right face is traversed first
+                        *stack.last_mut()? = left;
+                        stack.push(right);
+                        break;
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Traverses the faces whose tip vertices can be predicted from the most decoded vertices
+    /// first.
+    fn prediction_degree(&mut self, start: u32, degrees: &mut [u32]) -> Option<()> {
+        let table = self.table;
+        let mut stacks = vec![Vec::new(); MAX_PRIORITY];
+        let mut best_priority = 0;
+        stacks[0].push(start);
+        self.visit(table.next(start))?;
+        self.visit(table.previous(start))?;
+        self.visit(start)?;
+
+        loop {
+            let priority = match (best_priority..MAX_PRIORITY).find(|p| !stacks[*p].is_empty()) {
+                Some(priority) => priority,
+                None => return Some(()),
+            };
+            best_priority = priority;
+            let mut corner = stacks[priority].pop()?;
+            if self.is_face_visited(corner) {
+                continue;
+            }
+            loop {
+                self.visited_faces[corner as usize / 3] = true;
+                self.visit(corner)?;
+                let right = table.right_corner(corner);
+                let left = table.left_corner(corner);
+                let right_visited = self.is_face_visited(right);
+                if !self.is_face_visited(left) {
+                    let priority = self.push(left, degrees, &mut stacks, &mut best_priority)?;
+                    if right_visited && priority <= best_priority {
+                        corner = left;
+                        continue;
+                    }
+                }
+                if !right_visited {
+                    let priority = self.push(right, degrees, &mut stacks, &mut best_priority)?;
+                    if priority <= best_priority {
+                        corner = right;
+                        continue;
+                    }
+                }
+                break;
+            }
+        }
+    }
+
+    /// Adds a corner to the stack of its priority, which is the lowest for faces whose tip was
+    /// visited, and higher for tips reached from fewer faces.
+    fn push(
+        &self,
+        corner: u32,
+        degrees: &mut [u32],
+        stacks: &mut [Vec<u32>],
+        best_priority: &mut usize,
+    ) -> Option<usize> {
+        let vertex = self.table.vertex(corner) as usize;
+        let priority = if *self.visited_vertices.get(vertex)? {
+            0
+        } else {
+            degrees[vertex] += 1;
+            if degrees[vertex] > 1 {
+                1
+            } else {
+                2
+            }
+        };
+        stacks[priority].push(corner);
+        *best_priority = (*best_priority).min(priority);
+        Some(priority)
+    }
+}
@@ -0,0 +1,472 @@
+//! Extensions that the `gltf` crate doesn't parse, read from the raw JSON document.
+
+use std::collections::HashMap;
+
+use log::warn;
+use serde::Deserialize;
+
+/// `KHR_materials_emissive_strength`
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmissiveStrength {
+    #[serde(default = "one")]
+    pub emissive_strength: f32,
+}
+
+/// `KHR_materials_clearcoat`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Clearcoat {
+    pub clearcoat_factor: f32,
+    pub clearcoat_roughness_factor: f32,
+}
+
+/// `KHR_materials_transmission`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Transmission {
+    pub transmission_factor: f32,
+}
+
+/// `KHR_materials_unlit`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Unlit {}
+
+/// The supported extensions of a single material.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    pub emissive_strength: Option<EmissiveStrength>,
+    #[serde(rename = "KHR_materials_clearcoat")]
+    pub clearcoat: Option<Clearcoat>,
+    #[serde(rename = "KHR_materials_transmission")]
+    pub transmission: Option<Transmission>,
+    #[serde(rename = "KHR_materials_unlit")]
+    pub unlit: Option<Unlit>,
+}
+
+/// `EXT_meshopt_compression` of a buffer view, which is decoded into the buffer view.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshoptCompression {
+    pub buffer: usize,
+    #[serde(default)]
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    pub byte_stride: usize,
+    pub count: usize,
+    pub mode: MeshoptMode,
+    #[serde(default)]
+    pub filter: MeshoptFilter,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MeshoptMode {
+    Attributes,
+    Triangles,
+    Indices,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MeshoptFilter {
+    None,
+    Octahedral,
+    Quaternion,
+    Exponential,
+}
+
+impl Default for MeshoptFilter {
+    fn default() -> Self {
+        MeshoptFilter::None
+    }
+}
+
+/// `EXT_meshopt_compression` of a buffer, only holding the decoded buffer views if `fallback`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MeshoptBuffer {
+    pub fallback: bool,
+}
+
+/// `KHR_draco_mesh_compression` of a primitive, whose accessors are decoded from the buffer view.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DracoPrimitive {
+    pub buffer_view: usize,
+    /// The accessor of the indices, if the primitive is indexed.
+    pub indices: Option<usize>,
+    /// The accessor of each compressed attribute, with the id of its Draco attribute.
+    pub attributes: Vec<(usize, u32)>,
+}
+
+/// `MSFT_lod` of a node, along with the `MSFT_screencoverage` of its extras.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodeLod {
+    /// Nodes of the lower levels of detail, ordered from the most to the least detailed.
+    pub ids: Vec<usize>,
+    /// Screen coverage down to which each level is drawn, starting with the node itself.
+    pub screen_coverage: Vec<f32>,
+}
+
+/// The extensions of the document the `gltf` crate doesn't read.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Extensions {
+    /// Extensions the file can't be loaded without.
+    pub required: Vec<String>,
+    /// The supported extensions of each material.
+    pub materials: Vec<MaterialExtensions>,
+    /// The meshopt compression of each buffer.
+    pub meshopt_buffers: Vec<Option<MeshoptBuffer>>,
+    /// The meshopt compression of each buffer view.
+    pub meshopt_views: Vec<Option<MeshoptCompression>>,
+    /// The levels of detail of each node.
+    pub lods: Vec<Option<NodeLod>>,
+    /// The Draco compression of the primitives of each mesh.
+    pub draco_primitives: Vec<Vec<Option<DracoPrimitive>>>,
+}
+
+impl Extensions {
+    /// Returns true if the buffer has no data of its own, because it only holds meshopt
+    /// compressed buffer views.
+    pub fn is_meshopt_fallback(&self, buffer: usize) -> bool {
+        self.meshopt_buffers
+            .get(buffer)
+            .and_then(Option::as_ref)
+            .map_or(false, |buffer| buffer.fallback)
+    }
+
+    /// Returns the levels of detail of the node, if it has any.
+    pub fn lod(&self, node: usize) -> Option<&NodeLod> {
+        self.lods.get(node).and_then(Option::as_ref)
+    }
+
+    /// Returns the Draco compression of the primitive, if it's compressed.
+    pub fn draco(&self, mesh: usize, primitive: usize) -> Option<&DracoPrimitive> {
+        self.draco_primitives
+            .get(mesh)
+            .and_then(|primitives| primitives.get(primitive))
+            .and_then(Option::as_ref)
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Root {
+    extensions_required: Vec<String>,
+    materials: Vec<Material>,
+    buffers: Vec<Buffer>,
+    buffer_views: Vec<BufferView>,
+    nodes: Vec<Node>,
+    meshes: Vec<Mesh>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Material {
+    extensions: MaterialExtensions,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Buffer {
+    extensions: BufferExtensions,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct BufferExtensions {
+    #[serde(rename = "EXT_meshopt_compression")]
+    meshopt: Option<MeshoptBuffer>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct BufferView {
+    extensions: BufferViewExtensions,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct BufferViewExtensions {
+    #[serde(rename = "EXT_meshopt_compression")]
+    meshopt: Option<MeshoptCompression>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    extensions: NodeExtensions,
+    extras: NodeExtras,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct NodeExtensions {
+    #[serde(rename = "MSFT_lod")]
+    lod: Option<Lod>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Lod {
+    ids: Vec<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct NodeExtras {
+    #[serde(rename = "MSFT_screencoverage")]
+    screen_coverage: Vec<f32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Mesh {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    extensions: PrimitiveExtensions,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PrimitiveExtensions {
+    #[serde(rename = "KHR_draco_mesh_compression")]
+    draco: Option<DracoCompression>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DracoCompression {
+    buffer_view: usize,
+    attributes: HashMap<String, u32>,
+}
+
+impl Primitive {
+    fn draco(&self) -> Option<DracoPrimitive> {
+        let compression = self.extensions.draco.as_ref()?;
+        let mut attributes = compression
+            .attributes
+            .iter()
+            .filter_map(|(name, id)| Some((*self.attributes.get(name)?, *id)))
+            .collect::<Vec<_>>();
+        attributes.sort();
+        Some(DracoPrimitive {
+            buffer_view: compression.buffer_view,
+            indices: self.indices,
+            attributes,
+        })
+    }
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+/// Reads the extensions of the document.
+///
+/// Extensions that can't be read are ignored, the file is then loaded without them.
+pub fn load_extensions(json: &[u8]) -> Extensions {
+    match serde_json::from_slice::<Root>(json) {
+        Ok(root) => Extensions {
+            required: root.extensions_required,
+            materials: root
+                .materials
+                .into_iter()
+                .map(|material| material.extensions)
+                .collect(),
+            meshopt_buffers: root
+                .buffers
+                .into_iter()
+                .map(|buffer| buffer.extensions.meshopt)
+                .collect(),
+            meshopt_views: root
+                .buffer_views
+                .into_iter()
+                .map(|view| view.extensions.meshopt)
+                .collect(),
+            lods: root
+                .nodes
+                .into_iter()
+                .map(|node| {
+                    let screen_coverage = node.extras.screen_coverage;
+                    node.extensions.lod.map(|lod| NodeLod {
+                        ids: lod.ids,
+                        screen_coverage,
+                    })
+                })
+                .collect(),
+            draco_primitives: root
+                .meshes
+                .into_iter()
+                .map(|mesh| {
+                    mesh.primitives
+                        .into_iter()
+                        .map(|primitive| primitive.draco())
+                        .collect()
+                })
+                .collect(),
+        },
+        Err(err) => {
+            warn!("Ignoring the extensions of the glTF file: {}", err);
+            Extensions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_material_extensions() {
+        let json = br#"{
+            "asset": { "version": "2.0" },
+            "materials": [
+                {},
+                {
+                    "extensions": {
+                        "KHR_materials_emissive_strength": { "emissiveStrength": 4.0 },
+                        "KHR_materials_clearcoat": { "clearcoatFactor": 1.0 },
+                        "KHR_materials_transmission": { "transmissionFactor": 0.5 },
+                        "KHR_materials_unlit": {},
+                        "KHR_materials_sheen": { "sheenRoughnessFactor": 0.5 }
+                    }
+                }
+            ]
+        }"#;
+        let extensions = load_extensions(json).materials;
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions[0], MaterialExtensions::default());
+        assert_eq!(
+            extensions[1],
+            MaterialExtensions {
+                emissive_strength: Some(EmissiveStrength {
+                    emissive_strength: 4.0
+                }),
+                clearcoat: Some(Clearcoat {
+                    clearcoat_factor: 1.0,
+                    clearcoat_roughness_factor: 0.0,
+                }),
+                transmission: Some(Transmission {
+                    transmission_factor: 0.5
+                }),
+                unlit: Some(Unlit {}),
+            }
+        );
+    }
+
+    #[test]
+    fn reads_meshopt_compression() {
+        let json = br#"{
+            "asset": { "version": "2.0" },
+            "extensionsRequired": ["EXT_meshopt_compression"],
+            "buffers": [
+                { "byteLength": 100, "uri": "data.bin" },
+                { "byteLength": 400, "extensions": { "EXT_meshopt_compression": { "fallback": true } } }
+            ],
+            "bufferViews": [
+                {
+                    "buffer": 1,
+                    "byteLength": 400,
+                    "byteStride": 8,
+                    "extensions": {
+                        "EXT_meshopt_compression": {
+                            "buffer": 0,
+                            "byteLength": 100,
+                            "byteStride": 8,
+                            "count": 50,
+                            "mode": "ATTRIBUTES",
+                            "filter": "OCTAHEDRAL"
+                        }
+                    }
+                }
+            ]
+        }"#;
+        let extensions = load_extensions(json);
+        assert_eq!(
+            extensions.required,
+            vec!["EXT_meshopt_compression".to_string()]
+        );
+        assert!(!extensions.is_meshopt_fallback(0));
+        assert!(extensions.is_meshopt_fallback(1));
+        assert_eq!(
+            extensions.meshopt_views,
+            vec![Some(MeshoptCompression {
+                buffer: 0,
+                byte_offset: 0,
+                byte_length: 100,
+                byte_stride: 8,
+                count: 50,
+                mode: MeshoptMode::Attributes,
+                filter: MeshoptFilter::Octahedral,
+            })]
+        );
+    }
+
+    #[test]
+    fn reads_draco_compression() {
+        let json = br#"{
+            "asset": { "version": "2.0" },
+            "meshes": [{
+                "primitives": [
+                    { "attributes": { "POSITION": 0 } },
+                    {
+                        "attributes": { "POSITION": 2, "NORMAL": 3, "COLOR_0": 4 },
+                        "indices": 1,
+                        "extensions": {
+                            "KHR_draco_mesh_compression": {
+                                "bufferView": 5,
+                                "attributes": { "POSITION": 0, "NORMAL": 1 }
+                            }
+                        }
+                    }
+                ]
+            }]
+        }"#;
+        let extensions = load_extensions(json);
+        assert_eq!(extensions.draco(0, 0), None);
+        assert_eq!(
+            extensions.draco(0, 1),
+            Some(&DracoPrimitive {
+                buffer_view: 5,
+                indices: Some(1),
+                attributes: vec![(2, 0), (3, 1)],
+            })
+        );
+        assert_eq!(extensions.draco(1, 0), None);
+    }
+
+    #[test]
+    fn reads_lods() {
+        let json = br#"{
+            "asset": { "version": "2.0" },
+            "nodes": [
+                {
+                    "mesh": 0,
+                    "extensions": { "MSFT_lod": { "ids": [1, 2] } },
+                    "extras": { "MSFT_screencoverage": [0.5, 0.2, 0.01] }
+                },
+                { "mesh": 1 },
+                { "mesh": 2 }
+            ]
+        }"#;
+        let extensions = load_extensions(json);
+        assert_eq!(
+            extensions.lod(0),
+            Some(&NodeLod {
+                ids: vec![1, 2],
+                screen_coverage: vec![0.5, 0.2, 0.01],
+            })
+        );
+        assert_eq!(extensions.lod(1), None);
+        assert_eq!(extensions.lod(3), None);
+    }
+}
@@ -4,6 +4,7 @@ use amethyst_assets::Source as AssetSource;
 use amethyst_error::Error;
 use gltf::{self, json, Gltf};
 
+use super::extensions::{load_extensions, Extensions};
 use crate::error;
 
 #[derive(Debug)]
@@ -48,17 +49,28 @@ impl Buffers {
     }
 }
 
-/// Imports glTF 2.0
-pub fn import<P>(source: Arc<dyn AssetSource>, path: P) -> Result<(Gltf, Buffers), Error>
+/// Imports glTF 2.0, along with the extensions the `gltf` crate doesn't read.
+///
+/// Buffer views compressed with `EXT_meshopt_compression` are decoded into their buffers, and
+/// primitives compressed with `KHR_draco_mesh_compression` into a new buffer their accessors
+/// are pointed to.
+///
+/// Only the buffers `needed` returns true for are read, the others are left empty.
+pub fn import<P, F>(
+    source: Arc<dyn AssetSource>,
+    path: P,
+    needed: F,
+) -> Result<(Gltf, Buffers, Extensions), Error>
 where
     P: AsRef<Path>,
+    F: FnOnce(&Gltf, &Extensions) -> Result<Vec<bool>, Error>,
 {
     let path = path.as_ref();
     let data = read_to_end(source.clone(), path)?;
     if data.starts_with(b"glTF") {
-        import_binary(&data, source, path)
+        import_binary(&data, source, path, needed)
     } else {
-        import_standard(&data, source, path)
+        import_standard(&data, source, path, needed)
     }
 }
 
@@ -80,11 +92,22 @@ fn load_external_buffers(
     source: Arc<dyn AssetSource>,
     base_path: &Path,
     gltf: &Gltf,
+    extensions: &Extensions,
+    needed: &[bool],
     mut bin: Option<Vec<u8>>,
 ) -> Result<Vec<Vec<u8>>, Error> {
     use gltf::buffer::Source;
     let mut buffers = vec![];
     for (index, buffer) in gltf.buffers().enumerate() {
+        if !needed.get(index).cloned().unwrap_or(true) {
+            buffers.push(Vec::new());
+            continue;
+        }
+        if extensions.is_meshopt_fallback(index) {
+            // filled by decoding the buffer views
+            buffers.push(vec![0; buffer.length()]);
+            continue;
+        }
         let data = match buffer.source() {
             Source::Uri(uri) => {
                 if uri.starts_with("data:") {
@@ -111,26 +134,300 @@ fn load_external_buffers(
     Ok(buffers)
 }
 
-fn import_standard(
-    data: &[u8],
+fn check_required_extensions(extensions: &Extensions) -> Result<(), Error> {
+    let unsupported = |name: &str| {
+        (name == "KHR_draco_mesh_compression" && !cfg!(feature = "draco"))
+            || (name == "EXT_meshopt_compression" && !cfg!(feature = "meshopt"))
+    };
+    match extensions.required.iter().find(|name| unsupported(name)) {
+        Some(name) => Err(error::Error::UnsupportedExtension(name.clone()).into()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "meshopt")]
+fn decode_meshopt(
+    gltf: &Gltf,
+    extensions: &Extensions,
+    needed: &[bool],
+    buffers: &mut [Vec<u8>],
+) -> Result<(), Error> {
+    for view in gltf.views() {
+        if !needed.get(view.buffer().index()).cloned().unwrap_or(true) {
+            continue;
+        }
+        let compression = match extensions.meshopt_views.get(view.index()) {
+            Some(Some(compression)) => compression,
+            _ => continue,
+        };
+        let path = || json::Path::new().field("bufferViews").index(view.index());
+        let start = compression.byte_offset;
+        let data = buffers
+            .get(compression.buffer)
+            .and_then(|buffer| buffer.get(start..start + compression.byte_length))
+            .ok_or_else(|| error::Error::BufferLength(path()))?;
+        let decoded = super::meshopt::decode(compression, data)
+            .ok_or_else(|| error::Error::MeshoptDecode(path()))?;
+        let buffer = &mut buffers[view.buffer().index()];
+        let end = view.offset() + decoded.len();
+        if buffer.len() < end {
+            buffer.resize(end, 0);
+        }
+        buffer[view.offset()..end].copy_from_slice(&decoded);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "meshopt"))]
+fn decode_meshopt(_: &Gltf, _: &Extensions, _: &[bool], _: &mut [Vec<u8>]) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Decodes the primitives compressed with `KHR_draco_mesh_compression` into a new buffer, and
+/// returns the document with their accessors pointing to the decoded data.
+#[cfg(feature = "draco")]
+fn decode_draco(
+    json: &[u8],
+    gltf: Gltf,
+    extensions: &Extensions,
+    needed: &[bool],
+    buffers: &mut Vec<Vec<u8>>,
+) -> Result<Gltf, Error> {
+    use std::collections::{hash_map::Entry, HashMap};
+
+    use serde_json::Value;
+
+    let mut decoded = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = HashMap::new();
+    let mut meshes = HashMap::new();
+    for mesh in gltf.meshes() {
+        for primitive in mesh.primitives() {
+            let draco = match extensions.draco(mesh.index(), primitive.index()) {
+                Some(draco) => draco,
+                None => continue,
+            };
+            let path = || {
+                json::Path::new()
+                    .field("meshes")
+                    .index(mesh.index())
+                    .field("primitives")
+                    .index(primitive.index())
+            };
+            let view = gltf
+                .views()
+                .nth(draco.buffer_view)
+                .ok_or_else(|| error::Error::DracoDecode(path()))?;
+            if !needed.get(view.buffer().index()).cloned().unwrap_or(true) {
+                continue;
+            }
+            if let Entry::Vacant(entry) = meshes.entry(view.index()) {
+                let data = buffers
+                    .get(view.buffer().index())
+                    .and_then(|buffer| buffer.get(view.offset()..view.offset() + view.length()))
+                    .ok_or_else(|| {
+                        error::Error::BufferLength(
+                            json::Path::new().field("bufferViews").index(view.index()),
+                        )
+                    })?;
+                let decoded_mesh =
+                    super::draco::decode(data).ok_or_else(|| error::Error::DracoDecode(path()))?;
+                entry.insert(decoded_mesh);
+            }
+            let draco_mesh = &meshes[&view.index()];
+
+            let targets = draco
+                .indices
+                .map(|accessor| (accessor, None))
+                .into_iter()
+                .chain(
+                    draco
+                        .attributes
+                        .iter()
+                        .map(|(accessor, id)| (*accessor, Some(*id))),
+                );
+            for (index, id) in targets {
+                if accessors.contains_key(&index) {
+                    continue;
+                }
+                let data = gltf.accessors().nth(index).and_then(|accessor| match id {
+                    None => encode_draco_indices(draco_mesh, &accessor),
+                    Some(id) => encode_draco_attribute(draco_mesh, id, &accessor),
+                });
+                let data = data.ok_or_else(|| error::Error::DracoDecode(path()))?;
+                decoded.resize((decoded.len() + 3) / 4 * 4, 0);
+                accessors.insert(index, views.len());
+                views.push((decoded.len(), data.len()));
+                decoded.extend(data);
+            }
+        }
+    }
+    if accessors.is_empty() {
+        return Ok(gltf);
+    }
+
+    // the accessors are read from new buffer views of a new buffer
+    fn push(root: &mut Value, field: &str, value: Value) -> usize {
+        let array = &mut root[field];
+        if !array.is_array() {
+            *array = Value::Array(Vec::new());
+        }
+        let array = array
+            .as_array_mut()
+            .expect("Unreachable: the field is an array");
+        array.push(value);
+        array.len() - 1
+    }
+    let mut root = serde_json::from_slice::<Value>(json)?;
+    let buffer = push(
+        &mut root,
+        "buffers",
+        serde_json::json!({ "byteLength": decoded.len() }),
+    );
+    let views = views
+        .into_iter()
+        .map(|(offset, length)| {
+            let view =
+                serde_json::json!({ "buffer": buffer, "byteOffset": offset, "byteLength": length });
+            push(&mut root, "bufferViews", view)
+        })
+        .collect::<Vec<_>>();
+    for (accessor, view) in accessors {
+        let accessor = &mut root["accessors"][accessor];
+        accessor["bufferView"] = serde_json::json!(views[view]);
+        accessor["byteOffset"] = serde_json::json!(0);
+    }
+    buffers.resize(buffer, Vec::new());
+    buffers.push(decoded);
+    Ok(Gltf::from_slice(&serde_json::to_vec(&root)?)?)
+}
+
+#[cfg(not(feature = "draco"))]
+fn decode_draco(
+    _: &[u8],
+    gltf: Gltf,
+    _: &Extensions,
+    _: &[bool],
+    _: &mut Vec<Vec<u8>>,
+) -> Result<Gltf, Error> {
+    Ok(gltf)
+}
+
+/// Writes the indices of the decoded mesh in the format of the accessor.
+#[cfg(feature = "draco")]
+fn encode_draco_indices(
+    mesh: &super::draco::DracoMesh,
+    accessor: &gltf::Accessor<'_>,
+) -> Option<Vec<u8>> {
+    use gltf::accessor::{DataType, Dimensions};
+    use std::convert::TryFrom;
+
+    if accessor.count() != mesh.faces.len() * 3 || accessor.dimensions() != Dimensions::Scalar {
+        return None;
+    }
+    let mut data = Vec::with_capacity(accessor.count() * accessor.size());
+    for index in mesh.faces.iter().flat_map(|face| face.iter()) {
+        match accessor.data_type() {
+            DataType::U8 => data.push(u8::try_from(*index).ok()?),
+            DataType::U16 => data.extend(&u16::try_from(*index).ok()?.to_le_bytes()),
+            DataType::U32 => data.extend(&index.to_le_bytes()),
+            _ => return None,
+        }
+    }
+    Some(data)
+}
+
+/// Writes the values of the decoded attribute of each point in the format of the accessor.
+#[cfg(feature = "draco")]
+fn encode_draco_attribute(
+    mesh: &super::draco::DracoMesh,
+    id: u32,
+    accessor: &gltf::Accessor<'_>,
+) -> Option<Vec<u8>> {
+    use gltf::accessor::DataType;
+
+    let attribute = mesh.attribute(id)?;
+    let components = accessor.dimensions().multiplicity();
+    if accessor.count() != mesh.num_points || attribute.num_components != components {
+        return None;
+    }
+    // normalized integers may be coded as floats
+    let scale = |max: f64| {
+        if accessor.normalized() && attribute.is_float() {
+            max
+        } else {
+            1.0
+        }
+    };
+    let mut data = Vec::with_capacity(accessor.count() * accessor.size());
+    for value in attribute.point_values.iter().take(mesh.num_points) {
+        let start = *value as usize * components;
+        for value in attribute.values.get(start..start + components)? {
+            match accessor.data_type() {
+                DataType::I8 => data.push((value * scale(127.0)).round() as i8 as u8),
+                DataType::U8 => data.push((value * scale(255.0)).round() as u8),
+                DataType::I16 => {
+                    data.extend(&((value * scale(32767.0)).round() as i16).to_le_bytes())
+                }
+                DataType::U16 => {
+                    data.extend(&((value * scale(65535.0)).round() as u16).to_le_bytes())
+                }
+                DataType::U32 => data.extend(&(*value as u32).to_le_bytes()),
+                DataType::F32 => data.extend(&(*value as f32).to_bits().to_le_bytes()),
+            }
+        }
+    }
+    Some(data)
+}
+
+fn load_buffers(
     source: Arc<dyn AssetSource>,
     base_path: &Path,
+    json: &[u8],
+    gltf: Gltf,
+    extensions: &Extensions,
+    needed: &[bool],
+    bin: Option<Vec<u8>>,
 ) -> Result<(Gltf, Buffers), Error> {
+    check_required_extensions(extensions)?;
+    let mut buffers = load_external_buffers(source, base_path, &gltf, extensions, needed, bin)?;
+    decode_meshopt(&gltf, extensions, needed, &mut buffers)?;
+    let gltf = decode_draco(json, gltf, extensions, needed, &mut buffers)?;
+    Ok((gltf, Buffers(buffers)))
+}
+
+fn import_standard<F>(
+    data: &[u8],
+    source: Arc<dyn AssetSource>,
+    base_path: &Path,
+    needed: F,
+) -> Result<(Gltf, Buffers, Extensions), Error>
+where
+    F: FnOnce(&Gltf, &Extensions) -> Result<Vec<bool>, Error>,
+{
     let gltf = Gltf::from_slice(data)?;
-    let buffers = Buffers(load_external_buffers(source, base_path, &gltf, None)?);
-    Ok((gltf, buffers))
+    let extensions = load_extensions(data);
+    let needed = needed(&gltf, &extensions)?;
+    let (gltf, buffers) = load_buffers(source, base_path, data, gltf, &extensions, &needed, None)?;
+    Ok((gltf, buffers, extensions))
 }
 
-fn import_binary(
+fn import_binary<F>(
     data: &[u8],
     source: Arc<dyn AssetSource>,
     base_path: &Path,
-) -> Result<(Gltf, Buffers), Error> {
+    needed: F,
+) -> Result<(Gltf, Buffers, Extensions), Error>
+where
+    F: FnOnce(&Gltf, &Extensions) -> Result<Vec<bool>, Error>,
+{
     let gltf::binary::Glb { json, bin, .. } = gltf::binary::Glb::from_slice(data)?;
     let gltf = Gltf::from_slice(&json)?;
     let bin = bin.map(|x| x.to_vec());
-    let buffers = Buffers(load_external_buffers(source, base_path, &gltf, bin)?);
-    Ok((gltf, buffers))
+    let extensions = load_extensions(&json);
+    let needed = needed(&gltf, &extensions)?;
+    let (gltf, buffers) = load_buffers(source, base_path, &json, gltf, &extensions, &needed, bin)?;
+    Ok((gltf, buffers, extensions))
 }
 
 pub fn get_image_data(
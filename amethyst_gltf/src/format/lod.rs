@@ -0,0 +1,112 @@
+use amethyst_error::{format_err, Error};
+use amethyst_rendy::rendy::mesh::MeshBuilder;
+use gltf::{self, Gltf};
+
+use super::{extensions::NodeLod, importer::Buffers, mesh::LoadedPrimitive};
+use crate::{GltfLod, GltfLodLevel, GltfNodeExtent, GltfSceneOptions};
+
+/// Loads the `MSFT_lod` levels of detail of a node, one `GltfLod` per primitive of its mesh.
+///
+/// The primitives of the levels are matched to the primitives of the mesh by index, a level
+/// missing a primitive ends the levels of that primitive. The materials of the levels aren't
+/// loaded, every level is drawn with the material of the mesh.
+pub fn load_lods(
+    gltf: &Gltf,
+    lod: Option<&NodeLod>,
+    primitives: &[LoadedPrimitive],
+    buffers: &Buffers,
+    options: &GltfSceneOptions,
+) -> Result<Vec<Option<GltfLod>>, Error> {
+    let lod = match lod {
+        Some(lod) => lod,
+        None => return Ok(primitives.iter().map(|_| None).collect()),
+    };
+    let levels = lod
+        .ids
+        .iter()
+        .map(|id| load_level(gltf, *id, buffers, options))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // the screen coverage is the one of the whole mesh
+    let mut extent = GltfNodeExtent::default();
+    for (_, _, bounds, _) in primitives {
+        extent.extend_range(bounds);
+    }
+    let radius = if extent.valid() {
+        extent.distance().magnitude() * 0.5
+    } else {
+        0.0
+    };
+
+    Ok((0..primitives.len())
+        .map(|primitive| {
+            Some(GltfLod {
+                radius,
+                coverage: coverage(lod, 0),
+                levels: levels
+                    .iter()
+                    .take_while(|meshes| meshes.len() > primitive)
+                    .enumerate()
+                    .map(|(index, meshes)| GltfLodLevel {
+                        mesh: Some(meshes[primitive].clone()),
+                        handle: None,
+                        coverage: coverage(lod, index + 1),
+                    })
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+fn load_level(
+    gltf: &Gltf,
+    node: usize,
+    buffers: &Buffers,
+    options: &GltfSceneOptions,
+) -> Result<Vec<MeshBuilder<'static>>, Error> {
+    let node = gltf
+        .nodes()
+        .nth(node)
+        .ok_or_else(|| format_err!("Level of detail node {} does not exist", node))?;
+    match node.mesh() {
+        Some(mesh) => Ok(super::mesh::load_mesh(&mesh, buffers, options)?
+            .into_iter()
+            .map(|(mesh, _, _, _)| mesh)
+            .collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Returns the screen coverage down to which the level is drawn. Without `MSFT_screencoverage`
+/// every level halves the coverage, and the last level is always drawn.
+fn coverage(lod: &NodeLod, level: usize) -> f32 {
+    if lod.screen_coverage.is_empty() {
+        if level < lod.ids.len() {
+            0.5f32.powi(level as i32 + 1)
+        } else {
+            0.0
+        }
+    } else {
+        lod.screen_coverage.get(level).cloned().unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coverage_of_levels() {
+        let mut lod = NodeLod {
+            ids: vec![1, 2],
+            screen_coverage: vec![0.5, 0.2, 0.01],
+        };
+        assert_eq!(coverage(&lod, 0), 0.5);
+        assert_eq!(coverage(&lod, 2), 0.01);
+
+        lod.screen_coverage.clear();
+        assert_eq!(coverage(&lod, 0), 0.5);
+        assert_eq!(coverage(&lod, 1), 0.25);
+        assert_eq!(coverage(&lod, 2), 0.0);
+    }
+}
@@ -1,4 +1,6 @@
-use super::{get_image_data, Buffers, ImageFormat as ImportDataFormat};
+use super::{
+    extensions::MaterialExtensions, get_image_data, Buffers, ImageFormat as ImportDataFormat,
+};
 use amethyst_assets::Source;
 use amethyst_error::Error;
 use amethyst_rendy::{
@@ -8,19 +10,24 @@ use amethyst_rendy::{
         hal,
         texture::{
             image::{load_from_image, ImageFormat as DataFormat, ImageTextureConfig, Repr},
-            palette::{load_from_linear_rgba, load_from_srgba},
+            palette::{load_from_linear_rgba, load_from_linear_rgba_f32, load_from_srgba},
             MipLevels, TextureBuilder,
         },
     },
 };
 
 use gltf::{self, material::AlphaMode};
+use log::warn;
 use std::sync::Arc;
 
 // Load a single material, and transform into a format usable by the engine
+//
+// The renderer has no clearcoat, transmission or unlit shading, so those extensions are
+// approximated with the parameters it has.
 pub fn load_material(
     material: &gltf::Material<'_>,
     buffers: &Buffers,
+    extensions: MaterialExtensions,
     source: Arc<dyn Source>,
     name: &str,
 ) -> Result<MaterialPrefab, Error> {
@@ -28,10 +35,17 @@ pub fn load_material(
 
     let pbr = material.pbr_metallic_roughness();
 
+    // transmission is approximated by alpha blending
+    let transmission = extensions
+        .transmission
+        .map_or(0.0, |transmission| transmission.transmission_factor);
+    let mut base_color_factor = pbr.base_color_factor();
+    base_color_factor[3] *= 1.0 - transmission.max(0.0).min(1.0);
+
     prefab.albedo = Some(
         load_texture_with_factor(
             pbr.base_color_texture(),
-            pbr.base_color_factor(),
+            base_color_factor,
             buffers,
             source.clone(),
             name,
@@ -40,11 +54,41 @@ pub fn load_material(
         .map(|(texture, _)| TexturePrefab::Data(texture.into()))?,
     );
 
+    if extensions.unlit.is_some() {
+        // the fallback of the extension: rough metal reflects next to no light, and the base
+        // color is emitted instead
+        prefab.metallic_roughness = Some(TexturePrefab::Data(
+            load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0)).into(),
+        ));
+        prefab.emission = Some(TexturePrefab::Data(
+            load_texture_with_factor(
+                pbr.base_color_texture(),
+                base_color_factor,
+                buffers,
+                source,
+                name,
+                true,
+            )?
+            .0
+            .into(),
+        ));
+        set_alpha_mode(material, transmission, &mut prefab);
+        return Ok(prefab);
+    }
+
+    // a clearcoat makes the surface look glossier
+    let mut roughness = pbr.roughness_factor();
+    if let Some(clearcoat) = extensions.clearcoat {
+        let coated = roughness
+            + (clearcoat.clearcoat_roughness_factor - roughness) * clearcoat.clearcoat_factor;
+        roughness = roughness.min(coated);
+    }
+
     // metallic from B channel
     // roughness from G channel
     let metallic_roughness = load_texture_with_factor(
         pbr.metallic_roughness_texture(),
-        [1.0, pbr.roughness_factor(), pbr.metallic_factor(), 1.0],
+        [1.0, roughness, pbr.metallic_factor(), 1.0],
         buffers,
         source.clone(),
         name,
@@ -54,19 +98,38 @@ pub fn load_material(
 
     prefab.metallic_roughness = Some(TexturePrefab::Data(metallic_roughness.into()));
 
+    let strength = extensions
+        .emissive_strength
+        .map_or(1.0, |strength| strength.emissive_strength);
     let em_factor = material.emissive_factor();
-    prefab.emission = Some(TexturePrefab::Data(
-        load_texture_with_factor(
-            material.emissive_texture(),
-            [em_factor[0], em_factor[1], em_factor[2], 1.0],
-            buffers,
-            source.clone(),
-            name,
-            true,
-        )?
-        .0
+    prefab.emission = Some(TexturePrefab::Data(match material.emissive_texture() {
+        // an emission stronger than 1.0 needs a floating point texture
+        None if (strength - 1.0).abs() > f32::EPSILON => load_from_linear_rgba_f32(LinSrgba::new(
+            em_factor[0] * strength,
+            em_factor[1] * strength,
+            em_factor[2] * strength,
+            1.0,
+        ))
         .into(),
-    ));
+        texture => {
+            if texture.is_some() && (strength - 1.0).abs() > f32::EPSILON {
+                warn!(
+                    "Ignoring the emissive strength of a textured material in '{}'",
+                    name
+                );
+            }
+            load_texture_with_factor(
+                texture,
+                [em_factor[0], em_factor[1], em_factor[2], 1.0],
+                buffers,
+                source.clone(),
+                name,
+                true,
+            )?
+            .0
+            .into()
+        }
+    }));
 
     // Can't use map/and_then because of Result returning from the load_texture function
     prefab.normal = match material.normal_texture() {
@@ -100,6 +163,11 @@ pub fn load_material(
         None => None,
     };
 
+    set_alpha_mode(material, transmission, &mut prefab);
+    Ok(prefab)
+}
+
+fn set_alpha_mode(material: &gltf::Material<'_>, transmission: f32, prefab: &mut MaterialPrefab) {
     match material.alpha_mode() {
         AlphaMode::Blend => {
             prefab.transparent = true;
@@ -111,7 +179,9 @@ pub fn load_material(
             prefab.alpha_cutoff = 0.0;
         }
     }
-    Ok(prefab)
+    if transmission > 0.0 {
+        prefab.transparent = true;
+    }
 }
 
 fn load_texture_with_factor(
@@ -3,6 +3,7 @@ use crate::{error, GltfSceneOptions};
 use amethyst_core::math::{zero, Vector3};
 use amethyst_error::Error;
 use amethyst_rendy::{
+    morph::{MorphTarget, MorphTargets},
     rendy::mesh::{Color, MeshBuilder, Normal, Position, Tangent, TexCoord},
     skinning::JointCombined,
 };
@@ -50,11 +51,19 @@ impl Indices {
     }
 }
 
+/// A mesh primitive with its material index, bounds and morph targets.
+pub type LoadedPrimitive = (
+    MeshBuilder<'static>,
+    Option<usize>,
+    Range<[f32; 3]>,
+    Option<MorphTargets>,
+);
+
 pub fn load_mesh(
     mesh: &gltf::Mesh<'_>,
     buffers: &Buffers,
     options: &GltfSceneOptions,
-) -> Result<Vec<(MeshBuilder<'static>, Option<usize>, Range<[f32; 3]>)>, Error> {
+) -> Result<Vec<LoadedPrimitive>, Error> {
     trace!("Loading mesh");
     let mut primitives = vec![];
 
@@ -148,6 +157,20 @@ pub fn load_mesh(
             }
         });
 
+        let targets = compute_if(options.load_animations, || {
+            trace!("Loading morph targets");
+            reader
+                .read_morph_targets()
+                .map(|(target_positions, target_normals, _)| MorphTarget {
+                    positions: target_positions
+                        .map(|deltas| deltas.collect())
+                        .unwrap_or_else(|| vec![[0.0; 3]; positions.len()]),
+                    normals: target_normals.map(|deltas| deltas.collect()),
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
         match indices {
             Indices::U16(vec) => {
                 builder.set_indices(vec);
@@ -158,19 +181,31 @@ pub fn load_mesh(
             Indices::None => {}
         };
 
-        builder.add_vertices(positions);
-        normals.map(|v| builder.add_vertices(v));
         tangents.map(|v| builder.add_vertices(v));
         tex_coords.map(|v| builder.add_vertices(v));
         colors.map(|v| builder.add_vertices(v));
         joints.map(|v| builder.add_vertices(v));
 
+        // the morph targets displace the positions and normals of the other attributes
+        let morph_targets = if targets.is_empty() {
+            None
+        } else {
+            Some(MorphTargets::new(
+                builder.clone(),
+                positions.clone(),
+                normals.clone(),
+                targets,
+            ))
+        };
+        builder.add_vertices(positions);
+        normals.map(|v| builder.add_vertices(v));
+
         trace!("Loading bounding box");
         let bounds = primitive.bounding_box();
         let bounds = bounds.min..bounds.max;
         let material = primitive.material().index();
 
-        primitives.push((builder, material, bounds));
+        primitives.push((builder, material, bounds, morph_targets));
     }
     trace!("Loaded mesh");
     Ok(primitives)
@@ -0,0 +1,449 @@
+//! Decoders of `EXT_meshopt_compression`, following the bitstreams of the meshoptimizer library
+//! the extension is defined by: version 0 of the vertex codec, and version 1 of the index and
+//! index sequence codecs.
+
+use super::extensions::{MeshoptCompression, MeshoptFilter, MeshoptMode};
+
+const VERTEX_HEADER: u8 = 0xa0;
+const INDEX_HEADER: u8 = 0xe0;
+const SEQUENCE_HEADER: u8 = 0xd0;
+
+const BYTE_GROUP_SIZE: usize = 16;
+const BYTE_GROUP_DECODE_LIMIT: usize = 24;
+const VERTEX_BLOCK_SIZE_BYTES: usize = 8192;
+const VERTEX_BLOCK_MAX_SIZE: usize = 256;
+const TAIL_MAX_SIZE: usize = 32;
+
+/// Decodes a compressed buffer view, returning `None` if the data is malformed.
+pub fn decode(compression: &MeshoptCompression, data: &[u8]) -> Option<Vec<u8>> {
+    let (count, stride) = (compression.count, compression.byte_stride);
+    let mut decoded = match compression.mode {
+        MeshoptMode::Attributes => decode_vertex_buffer(data, count, stride)?,
+        MeshoptMode::Triangles => decode_index_buffer(data, count, stride)?,
+        MeshoptMode::Indices => decode_index_sequence(data, count, stride)?,
+    };
+    match compression.filter {
+        MeshoptFilter::None => {}
+        MeshoptFilter::Octahedral => filter_octahedral(&mut decoded, stride)?,
+        MeshoptFilter::Quaternion => filter_quaternion(&mut decoded, stride)?,
+        MeshoptFilter::Exponential => filter_exponential(&mut decoded)?,
+    }
+    Some(decoded)
+}
+
+fn unzigzag8(v: u8) -> u8 {
+    (0u8.wrapping_sub(v & 1)) ^ (v >> 1)
+}
+
+fn vertex_block_size(vertex_size: usize) -> usize {
+    let size = (VERTEX_BLOCK_SIZE_BYTES / vertex_size) & !(BYTE_GROUP_SIZE - 1);
+    size.min(VERTEX_BLOCK_MAX_SIZE)
+}
+
+fn decode_bytes_group(data: &[u8], buffer: &mut [u8], bitslog2: u8) -> usize {
+    match bitslog2 {
+        0 => {
+            buffer[..BYTE_GROUP_SIZE].iter_mut().for_each(|b| *b = 0);
+            0
+        }
+        1 | 2 => {
+            let bits = 1 << bitslog2;
+            let packed = BYTE_GROUP_SIZE * bits / 8;
+            let escape = (1u8 << bits) - 1;
+            let mut var = packed;
+            for (i, out) in buffer[..BYTE_GROUP_SIZE].iter_mut().enumerate() {
+                let shift = 8 - bits - (i * bits) % 8;
+                let enc = (data[i * bits / 8] >> shift) & escape;
+                if enc == escape {
+                    *out = data[var];
+                    var += 1;
+                } else {
+                    *out = enc;
+                }
+            }
+            var
+        }
+        _ => {
+            buffer[..BYTE_GROUP_SIZE].copy_from_slice(&data[..BYTE_GROUP_SIZE]);
+            BYTE_GROUP_SIZE
+        }
+    }
+}
+
+fn decode_bytes(data: &[u8], buffer: &mut [u8]) -> Option<usize> {
+    let header_size = (buffer.len() / BYTE_GROUP_SIZE + 3) / 4;
+    if data.len() < header_size {
+        return None;
+    }
+    let mut offset = header_size;
+    for group in 0..buffer.len() / BYTE_GROUP_SIZE {
+        if data.len() - offset < BYTE_GROUP_DECODE_LIMIT {
+            return None;
+        }
+        let bitslog2 = (data[group / 4] >> ((group % 4) * 2)) & 3;
+        offset += decode_bytes_group(
+            &data[offset..],
+            &mut buffer[group * BYTE_GROUP_SIZE..],
+            bitslog2,
+        );
+    }
+    Some(offset)
+}
+
+fn decode_vertex_buffer(data: &[u8], count: usize, size: usize) -> Option<Vec<u8>> {
+    if size == 0 || size > 256 || size % 4 != 0 || data.len() < 1 + size {
+        return None;
+    }
+    if data[0] != VERTEX_HEADER {
+        return None;
+    }
+    let mut last_vertex = data[data.len() - size..].to_vec();
+    let mut vertices = vec![0; count * size];
+    let block_size = vertex_block_size(size);
+    let mut buffer = [0u8; VERTEX_BLOCK_MAX_SIZE];
+    let mut offset = 1;
+
+    let mut start = 0;
+    while start < count {
+        let block = block_size.min(count - start);
+        let aligned = (block + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1);
+        for k in 0..size {
+            offset += decode_bytes(&data[offset..], &mut buffer[..aligned])?;
+            let mut previous = last_vertex[k];
+            for (i, delta) in buffer[..block].iter().enumerate() {
+                let value = unzigzag8(*delta).wrapping_add(previous);
+                vertices[(start + i) * size + k] = value;
+                previous = value;
+            }
+        }
+        let last = (start + block - 1) * size;
+        last_vertex.copy_from_slice(&vertices[last..last + size]);
+        start += block;
+    }
+
+    if data.len() - offset != size.max(TAIL_MAX_SIZE) {
+        return None;
+    }
+    Some(vertices)
+}
+
+fn decode_vbyte(data: &[u8], offset: &mut usize) -> u32 {
+    let lead = data[*offset];
+    *offset += 1;
+    if lead < 128 {
+        return u32::from(lead);
+    }
+    let mut result = u32::from(lead & 127);
+    let mut shift = 7;
+    for _ in 0..4 {
+        let group = data[*offset];
+        *offset += 1;
+        result |= u32::from(group & 127) << shift;
+        shift += 7;
+        if group < 128 {
+            break;
+        }
+    }
+    result
+}
+
+fn decode_index(data: &[u8], offset: &mut usize, last: u32) -> u32 {
+    let v = decode_vbyte(data, offset);
+    last.wrapping_add((v >> 1) ^ 0u32.wrapping_sub(v & 1))
+}
+
+fn write_index(destination: &mut [u8], index: usize, size: usize, value: u32) {
+    if size == 2 {
+        destination[index * 2..index * 2 + 2].copy_from_slice(&(value as u16).to_le_bytes());
+    } else {
+        destination[index * 4..index * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+struct Fifos {
+    edges: [[u32; 2]; 16],
+    edge_offset: usize,
+    vertices: [u32; 16],
+    vertex_offset: usize,
+}
+
+impl Fifos {
+    fn push_vertex(&mut self, v: u32, advance: bool) {
+        self.vertices[self.vertex_offset] = v;
+        self.vertex_offset = (self.vertex_offset + advance as usize) & 15;
+    }
+
+    fn push_edge(&mut self, a: u32, b: u32) {
+        self.edges[self.edge_offset] = [a, b];
+        self.edge_offset = (self.edge_offset + 1) & 15;
+    }
+
+    fn vertex(&self, back: usize) -> u32 {
+        self.vertices[self.vertex_offset.wrapping_sub(back) & 15]
+    }
+}
+
+fn decode_index_buffer(data: &[u8], count: usize, size: usize) -> Option<Vec<u8>> {
+    if count % 3 != 0 || (size != 2 && size != 4) || data.len() < 1 + count / 3 + 16 {
+        return None;
+    }
+    if data[0] & 0xf0 != INDEX_HEADER || data[0] & 0x0f != 1 {
+        return None;
+    }
+    let mut indices = vec![0; count * size];
+    let mut fifos = Fifos {
+        edges: [[u32::MAX; 2]; 16],
+        edge_offset: 0,
+        vertices: [u32::MAX; 16],
+        vertex_offset: 0,
+    };
+    let (mut next, mut last) = (0u32, 0u32);
+    let safe_end = data.len() - 16;
+    let codeaux_table = &data[safe_end..];
+    let mut offset = 1 + count / 3;
+
+    for i in (0..count).step_by(3) {
+        // every triangle reads at most 16 bytes
+        if offset > safe_end {
+            return None;
+        }
+        let codetri = data[1 + i / 3];
+        let (a, b, c);
+        if codetri < 0xf0 {
+            let edge =
+                fifos.edges[fifos.edge_offset.wrapping_sub(1 + (codetri >> 4) as usize) & 15];
+            a = edge[0];
+            b = edge[1];
+            let fec = codetri & 15;
+            if fec < 13 {
+                c = if fec == 0 {
+                    next
+                } else {
+                    fifos.vertex(1 + fec as usize)
+                };
+                if fec == 0 {
+                    next += 1;
+                }
+                fifos.push_vertex(c, fec == 0);
+            } else {
+                c = match fec {
+                    13 => last.wrapping_sub(1),
+                    14 => last.wrapping_add(1),
+                    _ => decode_index(data, &mut offset, last),
+                };
+                last = c;
+                fifos.push_vertex(c, true);
+            }
+            fifos.push_edge(c, b);
+            fifos.push_edge(a, c);
+        } else {
+            // explicit indices are only encoded by the slow path, not by the codeaux table
+            let explicit = codetri >= 0xfe;
+            let (feb, fec, fea);
+            if !explicit {
+                let codeaux = codeaux_table[(codetri & 15) as usize];
+                fea = 0;
+                feb = codeaux >> 4;
+                fec = codeaux & 15;
+            } else {
+                let codeaux = data[offset];
+                offset += 1;
+                if codeaux == 0 {
+                    next = 0;
+                }
+                fea = if codetri == 0xfe { 0 } else { 15 };
+                feb = codeaux >> 4;
+                fec = codeaux & 15;
+            }
+            let mut take = |fe: u8, fifos: &Fifos| {
+                if fe == 0 {
+                    next += 1;
+                    next - 1
+                } else {
+                    fifos.vertex(fe as usize)
+                }
+            };
+            let mut va = if fea == 0 { take(0, &fifos) } else { 0 };
+            let mut vb = take(feb, &fifos);
+            let mut vc = take(fec, &fifos);
+            if explicit && fea == 15 {
+                va = decode_index(data, &mut offset, last);
+                last = va;
+            }
+            if explicit && feb == 15 {
+                vb = decode_index(data, &mut offset, last);
+                last = vb;
+            }
+            if explicit && fec == 15 {
+                vc = decode_index(data, &mut offset, last);
+                last = vc;
+            }
+            a = va;
+            b = vb;
+            c = vc;
+            fifos.push_vertex(a, true);
+            fifos.push_vertex(b, feb == 0 || (explicit && feb == 15));
+            fifos.push_vertex(c, fec == 0 || (explicit && fec == 15));
+            fifos.push_edge(b, a);
+            fifos.push_edge(c, b);
+            fifos.push_edge(a, c);
+        }
+        write_index(&mut indices, i, size, a);
+        write_index(&mut indices, i + 1, size, b);
+        write_index(&mut indices, i + 2, size, c);
+    }
+
+    if offset != safe_end {
+        return None;
+    }
+    Some(indices)
+}
+
+fn decode_index_sequence(data: &[u8], count: usize, size: usize) -> Option<Vec<u8>> {
+    if (size != 2 && size != 4) || data.len() < 1 + count + 4 {
+        return None;
+    }
+    if data[0] & 0xf0 != SEQUENCE_HEADER || data[0] & 0x0f > 1 {
+        return None;
+    }
+    let mut indices = vec![0; count * size];
+    let safe_end = data.len() - 4;
+    let mut last = [0u32; 2];
+    let mut offset = 1;
+    for i in 0..count {
+        // every index reads at most 5 bytes
+        if offset >= safe_end {
+            return None;
+        }
+        let v = decode_vbyte(data, &mut offset);
+        let baseline = (v & 1) as usize;
+        let v = v >> 1;
+        let index = last[baseline].wrapping_add((v >> 1) ^ 0u32.wrapping_sub(v & 1));
+        last[baseline] = index;
+        write_index(&mut indices, i, size, index);
+    }
+    if offset != safe_end {
+        return None;
+    }
+    Some(indices)
+}
+
+fn round(v: f32) -> i32 {
+    (v + if v >= 0.0 { 0.5 } else { -0.5 }) as i32
+}
+
+fn filter_octahedral(data: &mut [u8], stride: usize) -> Option<()> {
+    match stride {
+        4 => {
+            for vertex in data.chunks_exact_mut(4) {
+                let read = |i: usize| f32::from(vertex[i] as i8);
+                let [x, y, z] = octahedral([read(0), read(1), read(2)], 127.0);
+                vertex[0] = x as i8 as u8;
+                vertex[1] = y as i8 as u8;
+                vertex[2] = z as i8 as u8;
+            }
+        }
+        8 => {
+            for vertex in data.chunks_exact_mut(8) {
+                let read = |i: usize| f32::from(i16::from_le_bytes([vertex[i], vertex[i + 1]]));
+                let xyz = octahedral([read(0), read(2), read(4)], 32767.0);
+                for (i, value) in xyz.iter().enumerate() {
+                    vertex[i * 2..i * 2 + 2].copy_from_slice(&(*value as i16).to_le_bytes());
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+fn octahedral([x, y, z]: [f32; 3], max: f32) -> [i32; 3] {
+    let z = z - x.abs() - y.abs();
+    let t = z.min(0.0);
+    let x = x + if x >= 0.0 { t } else { -t };
+    let y = y + if y >= 0.0 { t } else { -t };
+    let s = max / (x * x + y * y + z * z).sqrt();
+    [round(x * s), round(y * s), round(z * s)]
+}
+
+fn filter_quaternion(data: &mut [u8], stride: usize) -> Option<()> {
+    if stride != 8 {
+        return None;
+    }
+    let scale = 1.0 / 2.0f32.sqrt();
+    for vertex in data.chunks_exact_mut(8) {
+        let read = |i: usize| i16::from_le_bytes([vertex[i * 2], vertex[i * 2 + 1]]);
+        let last = read(3);
+        let s = scale / f32::from(last | 3);
+        let x = f32::from(read(0)) * s;
+        let y = f32::from(read(1)) * s;
+        let z = f32::from(read(2)) * s;
+        let w = (1.0 - x * x - y * y - z * z).max(0.0).sqrt();
+        let qc = (last & 3) as usize;
+        let values = [
+            ((qc + 1) & 3, round(x * 32767.0)),
+            ((qc + 2) & 3, round(y * 32767.0)),
+            ((qc + 3) & 3, round(z * 32767.0)),
+            (qc, (w * 32767.0 + 0.5) as i32),
+        ];
+        for (i, value) in &values {
+            vertex[i * 2..i * 2 + 2].copy_from_slice(&(*value as i16).to_le_bytes());
+        }
+    }
+    Some(())
+}
+
+fn filter_exponential(data: &mut [u8]) -> Option<()> {
+    if data.len() % 4 != 0 {
+        return None;
+    }
+    for value in data.chunks_exact_mut(4) {
+        let v = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+        let mantissa = ((v << 8) as i32) >> 8;
+        let exponent = (v as i32) >> 24;
+        let decoded = f32::from_bits(((exponent + 127) as u32) << 23) * mantissa as f32;
+        value.copy_from_slice(&decoded.to_le_bytes());
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_unchanged_vertices() {
+        // all deltas are zero, so the vertex is the last vertex stored in the tail
+        let mut data = vec![VERTEX_HEADER, 0, 0, 0, 0];
+        data.extend_from_slice(&[0; 28]);
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(decode_vertex_buffer(&data, 1, 4), Some(vec![1, 2, 3, 4]));
+        assert_eq!(decode_vertex_buffer(&data[..data.len() - 1], 1, 4), None);
+    }
+
+    #[test]
+    fn decodes_triangle_with_codeaux_table() {
+        let mut data = vec![INDEX_HEADER | 1, 0xf0];
+        data.extend_from_slice(&[0; 16]);
+        let indices = decode_index_buffer(&data, 3, 2).unwrap();
+        assert_eq!(indices, vec![0, 0, 1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn decodes_index_sequence() {
+        let data = [SEQUENCE_HEADER | 1, 0, 4, 4, 0, 0, 0, 0];
+        let indices = decode_index_sequence(&data, 3, 4).unwrap();
+        assert_eq!(indices, vec![0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn applies_filters() {
+        let mut normal = vec![127, 0, 127, 0];
+        filter_octahedral(&mut normal, 4).unwrap();
+        assert_eq!(normal, vec![127, 0, 0, 0]);
+
+        let mut value = 5u32.to_le_bytes().to_vec();
+        filter_exponential(&mut value).unwrap();
+        assert_eq!(value, 5.0f32.to_le_bytes().to_vec());
+    }
+}
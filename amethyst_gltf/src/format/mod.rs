@@ -6,29 +6,39 @@ use gltf::{self, Gltf};
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-use amethyst_animation::AnimationHierarchyPrefab;
+use amethyst_animation::{AnimatablePrefab, AnimationHierarchyPrefab};
 use amethyst_assets::{Format, FormatValue, Prefab, Source};
 use amethyst_core::{
     math::{convert, Quaternion, Unit, Vector3, Vector4},
     transform::Transform,
 };
 use amethyst_error::{format_err, Error, ResultExt};
-use amethyst_rendy::{camera::CameraPrefab, light::LightPrefab};
+use amethyst_rendy::{camera::CameraPrefab, light::LightPrefab, morph::MorphWeights};
 
 use crate::{error, GltfMaterialSet, GltfNodeExtent, GltfPrefab, GltfSceneOptions, Named};
 
 use self::{
     animation::load_animations,
+    extensions::Extensions,
     importer::{get_image_data, import, Buffers, ImageFormat},
+    lod::load_lods,
     material::load_material,
     mesh::load_mesh,
+    selection::{needed_buffers, NodeSelection},
     skin::load_skin,
 };
 
 mod animation;
+#[cfg(feature = "draco")]
+mod draco;
+mod extensions;
 mod importer;
+mod lod;
 mod material;
 mod mesh;
+#[cfg(feature = "meshopt")]
+mod meshopt;
+mod selection;
 mod skin;
 
 /// Gltf scene format, will load a single scene from a Gltf file.
@@ -37,6 +47,9 @@ mod skin;
 /// to an entity in ECS, and the system will then load the full scene using the given entity
 /// as the root node of the scene hierarchy.
 ///
+/// The scene to load is chosen with `scene_index` or `scene_name` of the `GltfSceneOptions`, and
+/// the entities of its named nodes can be found through the `GltfNodes` of the root entity.
+///
 /// See `GltfSceneOptions` for more information about the load options.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -66,16 +79,21 @@ fn load_gltf(
     options: &GltfSceneOptions,
 ) -> Result<Prefab<GltfPrefab>, Error> {
     debug!("Loading GLTF scene '{}'", name);
-    import(source.clone(), name)
+    let needed = |gltf: &Gltf, extensions: &Extensions| {
+        let scene_index = get_scene_index(gltf, options)?;
+        Ok(needed_buffers(gltf, scene_index, extensions, options))
+    };
+    import(source.clone(), name, needed)
         .with_context(|_| error::Error::GltfImporterError)
-        .and_then(|(gltf, buffers)| {
-            load_data(&gltf, &buffers, options, source, name).map_err(Into::into)
+        .and_then(|(gltf, buffers, extensions)| {
+            load_data(&gltf, &buffers, &extensions, options, source, name).map_err(Into::into)
         })
 }
 
 fn load_data(
     gltf: &Gltf,
     buffers: &Buffers,
+    extensions: &Extensions,
     options: &GltfSceneOptions,
     source: Arc<dyn Source>,
     name: &str,
@@ -86,6 +104,7 @@ fn load_data(
         gltf,
         scene_index,
         buffers,
+        extensions,
         options,
         source,
         name,
@@ -95,6 +114,13 @@ fn load_data(
 }
 
 fn get_scene_index(gltf: &Gltf, options: &GltfSceneOptions) -> Result<usize, Error> {
+    if let Some(name) = &options.scene_name {
+        return gltf
+            .scenes()
+            .find(|scene| scene.name() == Some(name.as_str()))
+            .map(|scene| scene.index())
+            .ok_or_else(|| error::Error::MissingScene(name.clone()).into());
+    }
     let num_scenes = gltf.scenes().len();
     match (options.scene_index, gltf.default_scene()) {
         (Some(index), _) if index >= num_scenes => {
@@ -111,6 +137,7 @@ fn load_scene(
     gltf: &Gltf,
     scene_index: usize,
     buffers: &Buffers,
+    extensions: &Extensions,
     options: &GltfSceneOptions,
     source: Arc<dyn Source>,
     name: &str,
@@ -124,14 +151,20 @@ fn load_scene(
     let mut skin_map = HashMap::new();
     let mut bounding_box = GltfNodeExtent::default();
     let mut material_set = GltfMaterialSet::default();
-    for node in scene.nodes() {
+    let selection = NodeSelection::new(&scene, options);
+    for node in scene
+        .nodes()
+        .filter(|node| selection.includes(node.index()))
+    {
         let index = prefab.add(Some(0), None);
         load_node(
             gltf,
             &node,
             index,
             buffers,
+            extensions,
             options,
+            &selection,
             source.clone(),
             name,
             prefab,
@@ -146,6 +179,21 @@ fn load_scene(
     }
     prefab.data_or_default(0).materials = Some(material_set);
 
+    // map node names to their entities, the first node of the file wins for duplicate names
+    let mut named_nodes = node_map
+        .iter()
+        .filter_map(|(node, entity)| {
+            let name = gltf.nodes().nth(*node).and_then(|node| node.name())?;
+            Some((*node, name.to_string(), *entity))
+        })
+        .collect::<Vec<_>>();
+    named_nodes.sort_by_key(|(node, _, _)| *node);
+    let mut nodes = HashMap::new();
+    for (_, name, entity) in named_nodes {
+        nodes.entry(name).or_insert(entity);
+    }
+    prefab.data_or_default(0).nodes = Some(nodes);
+
     // load skins
     for (node_index, skin_info) in skin_map {
         load_skin(
@@ -164,22 +212,35 @@ fn load_scene(
 
     // load animations, if applicable
     if options.load_animations {
-        let mut hierarchy_prefab = AnimationHierarchyPrefab::default();
-        hierarchy_prefab.nodes = node_map
+        let nodes = node_map
             .iter()
             .map(|(node, entity)| (*node, *entity))
-            .collect();
+            .collect::<Vec<_>>();
+        let mut hierarchy_prefab = AnimationHierarchyPrefab::default();
+        hierarchy_prefab.nodes = nodes.clone();
         prefab
             .data_or_default(0)
             .animatable
             .get_or_insert_with(Default::default)
             .hierarchy = Some(hierarchy_prefab);
 
-        prefab
-            .data_or_default(0)
-            .animatable
+        let (animation_set, morph_animation_set, animations) =
+            load_animations(gltf, buffers, &node_map)?;
+        let root = prefab.data_or_default(0);
+        root.animatable
             .get_or_insert_with(Default::default)
-            .animation_set = Some(load_animations(gltf, buffers, &node_map)?);
+            .animation_set = Some(animation_set);
+        // morph target weights are animated separately, with the same animation ids
+        if !morph_animation_set.animations.is_empty() {
+            let mut hierarchy_prefab = AnimationHierarchyPrefab::default();
+            hierarchy_prefab.nodes = nodes;
+            root.morph_animatable = Some(AnimatablePrefab {
+                animation_set: Some(morph_animation_set),
+                hierarchy: Some(hierarchy_prefab),
+                rest_state: None,
+            });
+        }
+        root.animations = Some(animations);
     }
 
     Ok(())
@@ -196,7 +257,9 @@ fn load_node(
     node: &gltf::Node<'_>,
     entity_index: usize,
     buffers: &Buffers,
+    extensions: &Extensions,
     options: &GltfSceneOptions,
+    selection: &NodeSelection,
     source: Arc<dyn Source>,
     name: &str,
     prefab: &mut Prefab<GltfPrefab>,
@@ -222,8 +285,11 @@ fn load_node(
     *local_transform.scale_mut() = convert::<_, Vector3<f32>>(Vector3::from(scale));
     prefab.data_or_default(entity_index).transform = Some(local_transform);
 
+    // only the transforms of the ancestors of the selected nodes are loaded
+    let contents = selection.loads_contents(node.index());
+
     // Load camera
-    if let Some(camera) = node.camera() {
+    if let Some(camera) = node.camera().filter(|_| contents) {
         prefab.data_or_default(entity_index).camera = Some(match camera.projection() {
             gltf::camera::Projection::Orthographic(proj) => CameraPrefab::Orthographic {
                 left: -proj.xmag(),
@@ -247,12 +313,12 @@ fn load_node(
     }
 
     // Load lights
-    if let Some(light) = node.light() {
+    if let Some(light) = node.light().filter(|_| contents) {
         prefab.data_or_default(entity_index).light = Some(LightPrefab::from(light));
     }
 
     // check for skinning
-    let mut skin = node.skin().map(|skin| SkinInfo {
+    let mut skin = node.skin().filter(|_| contents).map(|skin| SkinInfo {
         skin_index: skin.index(),
         mesh_indices: Vec::default(),
     });
@@ -260,22 +326,57 @@ fn load_node(
     let mut bounding_box = GltfNodeExtent::default();
 
     // load graphics
-    if let Some(mesh) = node.mesh() {
+    if let Some(mesh) = node.mesh().filter(|_| contents) {
+        // the weights of the morph targets are shared by all primitives of the mesh
+        let num_targets = mesh
+            .primitives()
+            .map(|primitive| primitive.morph_targets().len())
+            .max()
+            .unwrap_or(0);
+        if options.load_animations && num_targets > 0 {
+            let mut weights = node
+                .weights()
+                .or_else(|| mesh.weights())
+                .map(<[f32]>::to_vec)
+                .unwrap_or_default();
+            weights.resize(num_targets, 0.0);
+            prefab.data_or_default(entity_index).morph_weights = Some(MorphWeights::new(weights));
+        }
+
         let mut graphics = load_mesh(&mesh, buffers, options)?;
+        let mut lods = load_lods(
+            gltf,
+            extensions.lod(node.index()),
+            &graphics,
+            buffers,
+            options,
+        )?;
         match graphics.len().cmp(&1) {
             Ordering::Equal => {
                 // single primitive can be loaded directly onto the node
-                let (mesh, material_index, bounds) = graphics.remove(0);
+                let (mesh, material_index, bounds, morph_targets) = graphics.remove(0);
                 bounding_box.extend_range(&bounds);
                 let prefab_data = prefab.data_or_default(entity_index);
                 prefab_data.mesh = Some(mesh);
+                prefab_data.morph_targets = morph_targets;
+                prefab_data.lod = lods.remove(0);
                 if let Some((material_id, material)) =
                     material_index.and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
                 {
                     material_set
                         .materials
                         .entry(material_id)
-                        .or_insert(load_material(&material, buffers, source.clone(), name)?);
+                        .or_insert(load_material(
+                            &material,
+                            buffers,
+                            extensions
+                                .materials
+                                .get(material_id)
+                                .cloned()
+                                .unwrap_or_default(),
+                            source.clone(),
+                            name,
+                        )?);
                     prefab_data.material_id = Some(material_id);
                 }
                 // if we have a skin we need to track the mesh entities
@@ -286,18 +387,32 @@ fn load_node(
             Ordering::Greater => {
                 // if we have multiple primitives,
                 // we need to add each primitive as a child entity to the node
-                for (mesh, material_index, bounds) in graphics {
+                for ((mesh, material_index, bounds, morph_targets), lod) in
+                    graphics.into_iter().zip(lods)
+                {
                     let mesh_entity = prefab.add(Some(entity_index), None);
                     let prefab_data = prefab.data_or_default(mesh_entity);
                     prefab_data.transform = Some(Transform::default());
                     prefab_data.mesh = Some(mesh);
+                    prefab_data.morph_targets = morph_targets;
+                    prefab_data.lod = lod;
                     if let Some((material_id, material)) = material_index
                         .and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
                     {
                         material_set
                             .materials
                             .entry(material_id)
-                            .or_insert(load_material(&material, buffers, source.clone(), name)?);
+                            .or_insert(load_material(
+                                &material,
+                                buffers,
+                                extensions
+                                    .materials
+                                    .get(material_id)
+                                    .cloned()
+                                    .unwrap_or_default(),
+                                source.clone(),
+                                name,
+                            )?);
                         prefab_data.material_id = Some(material_id);
                     }
 
@@ -316,14 +431,19 @@ fn load_node(
     }
 
     // load children
-    for child in node.children() {
+    for child in node
+        .children()
+        .filter(|child| selection.includes(child.index()))
+    {
         let index = prefab.add(Some(entity_index), None);
         load_node(
             gltf,
             &child,
             index,
             buffers,
+            extensions,
             options,
+            selection,
             source.clone(),
             name,
             prefab,
@@ -345,3 +465,27 @@ fn load_node(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_index_by_name() {
+        let gltf = Gltf::from_slice(
+            br#"{
+                "asset": { "version": "2.0" },
+                "scenes": [{ "name": "Menu", "nodes": [] }, { "name": "Level", "nodes": [] }]
+            }"#,
+        )
+        .unwrap();
+        let mut options = GltfSceneOptions::default();
+        assert!(get_scene_index(&gltf, &options).is_err());
+
+        options.scene_name = Some("Level".to_string());
+        assert_eq!(get_scene_index(&gltf, &options).unwrap(), 1);
+
+        options.scene_name = Some("Credits".to_string());
+        assert!(get_scene_index(&gltf, &options).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+
+use gltf::{self, Gltf};
+
+use super::extensions::Extensions;
+use crate::GltfSceneOptions;
+
+/// Nodes of a scene selected by the `load_nodes` of the `GltfSceneOptions`.
+///
+/// Selected nodes are loaded with their descendants, while their ancestors are only loaded with
+/// their names and transforms so the selected nodes keep their place in the scene.
+#[derive(Debug, Default)]
+pub struct NodeSelection {
+    loaded: Option<HashSet<usize>>,
+    ancestors: HashSet<usize>,
+}
+
+impl NodeSelection {
+    /// Selects the nodes of the scene to load.
+    pub fn new(scene: &gltf::Scene<'_>, options: &GltfSceneOptions) -> Self {
+        let names = match &options.load_nodes {
+            Some(names) => names,
+            None => return NodeSelection::default(),
+        };
+        let mut selection = NodeSelection {
+            loaded: Some(HashSet::new()),
+            ancestors: HashSet::new(),
+        };
+        for node in scene.nodes() {
+            selection.select(&node, names, &mut Vec::new(), false);
+        }
+        selection
+    }
+
+    fn select(
+        &mut self,
+        node: &gltf::Node<'_>,
+        names: &[String],
+        path: &mut Vec<usize>,
+        parent_loaded: bool,
+    ) {
+        let selected = node
+            .name()
+            .map_or(false, |name| names.iter().any(|n| n == name));
+        let loaded = parent_loaded || selected;
+        if loaded {
+            if let Some(nodes) = &mut self.loaded {
+                nodes.insert(node.index());
+            }
+            self.ancestors.extend(path.iter().cloned());
+        }
+        path.push(node.index());
+        for child in node.children() {
+            self.select(&child, names, path, loaded);
+        }
+        path.pop();
+    }
+
+    /// Returns true if the node is loaded, either fully or only with its transform.
+    pub fn includes(&self, node: usize) -> bool {
+        self.loads_contents(node) || self.ancestors.contains(&node)
+    }
+
+    /// Returns true if the meshes, cameras and lights of the node are loaded.
+    pub fn loads_contents(&self, node: usize) -> bool {
+        self.loaded
+            .as_ref()
+            .map_or(true, |nodes| nodes.contains(&node))
+    }
+
+    /// Returns true if only some nodes of the scene are loaded.
+    pub fn is_partial(&self) -> bool {
+        self.loaded.is_some()
+    }
+}
+
+/// Returns which buffers hold data of the selected nodes, so that the others aren't read.
+///
+/// Buffers of images and animations are always read.
+pub fn needed_buffers(
+    gltf: &Gltf,
+    scene_index: usize,
+    extensions: &Extensions,
+    options: &GltfSceneOptions,
+) -> Vec<bool> {
+    let scene = match gltf.scenes().nth(scene_index) {
+        Some(scene) => scene,
+        None => return vec![true; gltf.buffers().len()],
+    };
+    let selection = NodeSelection::new(&scene, options);
+    if !selection.is_partial() {
+        return vec![true; gltf.buffers().len()];
+    }
+
+    let mut views = HashSet::new();
+    let mut compressed = Vec::new();
+    let mut add_accessor = |accessor: gltf::Accessor<'_>| {
+        views.extend(accessor.view().map(|view| view.index()));
+        if let Some(sparse) = accessor.sparse() {
+            views.insert(sparse.indices().view().index());
+            views.insert(sparse.values().view().index());
+        }
+    };
+    for node in gltf
+        .nodes()
+        .filter(|node| selection.loads_contents(node.index()))
+    {
+        let lods = extensions
+            .lod(node.index())
+            .map(|lod| lod.ids.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| gltf.nodes().nth(*id));
+        for mesh in Some(node.clone())
+            .into_iter()
+            .chain(lods)
+            .filter_map(|n| n.mesh())
+        {
+            for primitive in mesh.primitives() {
+                // compressed accessors are decoded from the buffer view of the extension
+                if let Some(draco) = extensions.draco(mesh.index(), primitive.index()) {
+                    compressed.push(draco.buffer_view);
+                }
+                let targets = primitive.morph_targets().flat_map(|target| {
+                    target
+                        .positions()
+                        .into_iter()
+                        .chain(target.normals())
+                        .chain(target.tangents())
+                });
+                primitive
+                    .attributes()
+                    .map(|(_, accessor)| accessor)
+                    .chain(primitive.indices())
+                    .chain(targets)
+                    .for_each(&mut add_accessor);
+            }
+        }
+        if let Some(accessor) = node.skin().and_then(|skin| skin.inverse_bind_matrices()) {
+            add_accessor(accessor);
+        }
+    }
+    if options.load_animations {
+        for sampler in gltf.animations().flat_map(|animation| animation.samplers()) {
+            add_accessor(sampler.input());
+            add_accessor(sampler.output());
+        }
+    }
+    views.extend(compressed);
+    for image in gltf.images() {
+        if let gltf::image::Source::View { view, .. } = image.source() {
+            views.insert(view.index());
+        }
+    }
+
+    let mut needed = vec![false; gltf.buffers().len()];
+    for view in gltf.views().filter(|view| views.contains(&view.index())) {
+        needed[view.buffer().index()] = true;
+        // compressed views are decoded from another buffer
+        if let Some(Some(compression)) = extensions.meshopt_views.get(view.index()) {
+            if let Some(source) = needed.get_mut(compression.buffer) {
+                *source = true;
+            }
+        }
+    }
+    needed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_nodes_and_ancestors() {
+        let gltf = Gltf::from_slice(
+            br#"{
+                "asset": { "version": "2.0" },
+                "scenes": [{ "nodes": [0, 3] }],
+                "nodes": [
+                    { "name": "city", "children": [1] },
+                    { "name": "district", "children": [2] },
+                    { "name": "house" },
+                    { "name": "forest" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let scene = gltf.scenes().next().unwrap();
+        let mut options = GltfSceneOptions::default();
+        let selection = NodeSelection::new(&scene, &options);
+        assert!(!selection.is_partial());
+        assert!((0..4).all(|node| selection.loads_contents(node)));
+
+        options.load_nodes = Some(vec!["district".to_string()]);
+        let selection = NodeSelection::new(&scene, &options);
+        assert!(selection.is_partial());
+        assert!(selection.includes(0) && !selection.loads_contents(0));
+        assert!(selection.loads_contents(1) && selection.loads_contents(2));
+        assert!(!selection.includes(3));
+    }
+}
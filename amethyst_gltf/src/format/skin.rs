@@ -7,7 +7,7 @@ use amethyst_error::Error;
 use amethyst_rendy::skinning::JointTransformsPrefab;
 
 use super::Buffers;
-use crate::GltfPrefab;
+use crate::{error, GltfPrefab};
 
 pub fn load_skin(
     skin: &gltf::Skin<'_>,
@@ -20,11 +20,12 @@ pub fn load_skin(
     let joints = skin
         .joints()
         .map(|j| {
-            node_map.get(&j.index()).cloned().expect(
-                "Unreachable: `node_map` is initialized with the indexes from the `Gltf` object",
-            )
+            node_map
+                .get(&j.index())
+                .cloned()
+                .ok_or_else(|| error::Error::MissingJoint(j.index()).into())
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, Error>>()?;
 
     let reader = skin.reader(|buffer| buffers.buffer(&buffer));
 
@@ -38,7 +39,7 @@ pub fn load_skin(
         })
         .unwrap_or_else(|| vec![Matrix4::identity(); joints.len()]);
 
-    for (_bind_index, joint_index) in joints.iter().enumerate() {
+    for joint_index in &joints {
         prefab
             .data_or_default(*joint_index)
             .skinnable
@@ -19,15 +19,21 @@ use amethyst_assets::{
     ProgressCounter,
 };
 use amethyst_core::{
-    ecs::prelude::{Entity, Read, ReadExpect, Write, WriteStorage},
+    ecs::prelude::{Component, DenseVecStorage, Entity, Read, ReadExpect, Write, WriteStorage},
     math::{convert, Point3, Vector3},
     transform::Transform,
     Named,
 };
 use amethyst_error::Error;
 use amethyst_rendy::{
-    camera::CameraPrefab, formats::mtl::MaterialPrefab, light::LightPrefab,
-    rendy::mesh::MeshBuilder, types::Mesh, visibility::BoundingSphere,
+    camera::CameraPrefab,
+    formats::mtl::MaterialPrefab,
+    light::LightPrefab,
+    lod::{LodGroup, LodLevel},
+    morph::{MorphTargets, MorphWeights},
+    rendy::mesh::MeshBuilder,
+    types::Mesh,
+    visibility::BoundingSphere,
 };
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
@@ -65,15 +71,28 @@ pub struct GltfPrefab {
     pub material: Option<MaterialPrefab>,
     /// Loaded animations, if applicable, will always only be placed on the main `Entity`
     pub animatable: Option<AnimatablePrefab<usize, Transform>>,
+    /// Loaded animations of morph target weights, if applicable, will always only be placed on
+    /// the main `Entity` and use the same ids as `animatable`. They are played by an
+    /// `AnimationBundle<usize, MorphWeights>` and blended by the `MorphTargetSystem`
+    pub morph_animatable: Option<AnimatablePrefab<usize, MorphWeights>>,
+    /// Names of the loaded animations, placed on the main `Entity` along with `animatable`
+    pub animations: Option<GltfAnimations>,
+    /// Indices of the named nodes in the prefab, placed on the main `Entity` as `GltfNodes`
+    pub(crate) nodes: Option<HashMap<String, usize>>,
     /// Skin data is placed on `Entity`s involved in the skin, skeleton or graphical primitives
     /// using the skin
     pub skinnable: Option<SkinnablePrefab>,
+    /// Morph targets are placed on all `Entity`s with graphics primitives that have them
+    pub morph_targets: Option<MorphTargets>,
+    /// Default weights of the morph targets, placed on the `Entity` of the node with the mesh
+    pub morph_weights: Option<MorphWeights>,
     /// Node extent
     pub extent: Option<GltfNodeExtent>,
     /// Node name
     pub name: Option<Named>,
     pub(crate) materials: Option<GltfMaterialSet>,
     pub(crate) material_id: Option<usize>,
+    pub(crate) lod: Option<GltfLod>,
 }
 
 impl GltfPrefab {
@@ -101,6 +120,73 @@ impl GltfPrefab {
     }
 }
 
+/// Component mapping the names of the animations of a Gltf file to their ids in the
+/// `AnimationSet` of the main `Entity`, so clips can be started by name:
+///
+/// ```rust,ignore
+/// let id = animations.id("Walk").expect("Missing walk animation");
+/// let handle = set.get(&id).cloned();
+/// ```
+///
+/// The ids are the indices of the animations in the file, animations without a name can only be
+/// started by id.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GltfAnimations {
+    names: HashMap<String, usize>,
+}
+
+impl Component for GltfAnimations {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl GltfAnimations {
+    /// Returns the id of the animation with the given name.
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.names.get(name).cloned()
+    }
+
+    /// Iterates over the names and ids of the named animations.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.names.iter().map(|(name, id)| (name.as_str(), *id))
+    }
+
+    pub(crate) fn insert(&mut self, name: String, id: usize) {
+        self.names.insert(name, id);
+    }
+}
+
+/// Component mapping the names of the nodes of a loaded Gltf scene to their entities, placed on
+/// the main `Entity` so sockets and other named parts can be found after loading:
+///
+/// ```rust,ignore
+/// let muzzle = nodes.get("muzzle_socket").expect("Missing muzzle socket");
+/// ```
+///
+/// Names are not unique in Gltf files, if several nodes share a name the first node in the file
+/// is used.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GltfNodes {
+    entities: HashMap<String, Entity>,
+}
+
+impl Component for GltfNodes {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl GltfNodes {
+    /// Returns the entity of the node with the given name.
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.entities.get(name).cloned()
+    }
+
+    /// Iterates over the names and entities of the named nodes.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Entity)> {
+        self.entities
+            .iter()
+            .map(|(name, entity)| (name.as_str(), *entity))
+    }
+}
+
 /// A GLTF node extent
 #[derive(Clone, Debug)]
 pub struct GltfNodeExtent {
@@ -190,6 +276,24 @@ pub struct GltfMaterialSet {
     pub(crate) materials: HashMap<usize, MaterialPrefab>,
 }
 
+/// Lower levels of detail of a mesh from `MSFT_lod`, placed in a `LodGroup` with the mesh
+#[derive(Debug)]
+pub(crate) struct GltfLod {
+    /// Radius of the bounding sphere of the mesh
+    pub(crate) radius: f32,
+    /// Screen coverage down to which the mesh itself is drawn
+    pub(crate) coverage: f32,
+    pub(crate) levels: Vec<GltfLodLevel>,
+}
+
+#[derive(Debug)]
+pub(crate) struct GltfLodLevel {
+    pub(crate) mesh: Option<MeshBuilder<'static>>,
+    pub(crate) handle: Option<Handle<Mesh>>,
+    /// Screen coverage down to which this level is drawn
+    pub(crate) coverage: f32,
+}
+
 /// Options used when loading a GLTF file
 #[derive(Debug, Clone, Derivative, Serialize, Deserialize)]
 #[derivative(Default)]
@@ -217,6 +321,12 @@ pub struct GltfSceneOptions {
     /// Load the given scene index, if not supplied will either load the default scene (if set),
     /// or the first scene (only if there is only one scene, otherwise an `Error` will be returned).
     pub scene_index: Option<usize>,
+    /// Load the scene with the given name, used instead of `scene_index` if supplied.
+    pub scene_name: Option<String>,
+    /// Only load the nodes with the given names and their children, along with the transforms
+    /// of their ancestors. Buffers of other meshes aren't read, so parts of a large scene can be
+    /// loaded first and the rest streamed later by loading the file again with other nodes.
+    pub load_nodes: Option<Vec<String>>,
 }
 
 type SysDataOf<'a, T> = <T as PrefabData<'a>>::SystemData;
@@ -231,7 +341,13 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         SysDataOf<'a, LightPrefab>,
         SysDataOf<'a, MaterialPrefab>,
         SysDataOf<'a, AnimatablePrefab<usize, Transform>>,
+        SysDataOf<'a, AnimatablePrefab<usize, MorphWeights>>,
         SysDataOf<'a, SkinnablePrefab>,
+        WriteStorage<'a, GltfAnimations>,
+        WriteStorage<'a, GltfNodes>,
+        WriteStorage<'a, LodGroup>,
+        WriteStorage<'a, MorphTargets>,
+        WriteStorage<'a, MorphWeights>,
         WriteStorage<'a, BoundingSphere>,
         WriteStorage<'a, Handle<Mesh>>,
         Read<'a, AssetStorage<Mesh>>,
@@ -254,7 +370,13 @@ impl<'a> PrefabData<'a> for GltfPrefab {
             lights,
             materials,
             animatables,
+            morph_animatables,
             skinnables,
+            animation_names,
+            node_names,
+            lod_groups,
+            morph_targets,
+            morph_weights,
             bound,
             meshes,
             _,
@@ -266,6 +388,18 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         }
         if let Some(mesh) = &self.mesh_handle {
             meshes.insert(entity, mesh.clone())?;
+            if let Some(lod) = &self.lod {
+                let level = |mesh: &Handle<Mesh>, coverage| {
+                    LodLevel::screen_coverage(mesh.clone(), lod.radius, coverage)
+                };
+                let levels = lod
+                    .levels
+                    .iter()
+                    .filter_map(|l| l.handle.as_ref().map(|mesh| level(mesh, l.coverage)));
+                let group =
+                    LodGroup::new(Some(level(mesh, lod.coverage)).into_iter().chain(levels));
+                lod_groups.insert(entity, group.with_fov_scaling())?;
+            }
         }
         if let Some(camera) = &self.camera {
             camera.add_to_entity(entity, cameras, entities, children)?;
@@ -282,6 +416,25 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         if let Some(animatable) = &self.animatable {
             animatable.add_to_entity(entity, animatables, entities, children)?;
         }
+        if let Some(animatable) = &self.morph_animatable {
+            animatable.add_to_entity(entity, morph_animatables, entities, children)?;
+        }
+        if let Some(targets) = &self.morph_targets {
+            morph_targets.insert(entity, targets.clone())?;
+        }
+        if let Some(weights) = &self.morph_weights {
+            morph_weights.insert(entity, weights.clone())?;
+        }
+        if let Some(animations) = &self.animations {
+            animation_names.insert(entity, animations.clone())?;
+        }
+        if let Some(nodes) = &self.nodes {
+            let entities = nodes
+                .iter()
+                .map(|(name, index)| (name.clone(), entities[*index]))
+                .collect();
+            node_names.insert(entity, GltfNodes { entities })?;
+        }
         if let Some(skinnable) = &self.skinnable {
             skinnable.add_to_entity(entity, skinnables, entities, children)?;
         }
@@ -296,8 +449,26 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         progress: &mut ProgressCounter,
         system_data: &mut Self::SystemData,
     ) -> Result<bool, Error> {
-        let (_, _, _, _, materials, animatables, _, _, _, meshes_storage, loader, mat_set) =
-            system_data;
+        let (
+            _,
+            _,
+            _,
+            _,
+            materials,
+            animatables,
+            morph_animatables,
+            _,
+            _,
+            _,
+            _,
+            _,
+            _,
+            _,
+            _,
+            meshes_storage,
+            loader,
+            mat_set,
+        ) = system_data;
 
         let mut ret = false;
         if let Some(mut mats) = self.materials.take() {
@@ -317,9 +488,21 @@ impl<'a> PrefabData<'a> for GltfPrefab {
                 Some(loader.load_from_data(mesh.clone().into(), &mut *progress, meshes_storage));
             ret = true;
         }
+        if let Some(lod) = &mut self.lod {
+            for level in &mut lod.levels {
+                if let Some(mesh) = level.mesh.take() {
+                    level.handle =
+                        Some(loader.load_from_data(mesh.into(), &mut *progress, meshes_storage));
+                    ret = true;
+                }
+            }
+        }
         if let Some(animatable) = &mut self.animatable {
             ret |= animatable.load_sub_assets(progress, animatables)?;
         }
+        if let Some(animatable) = &mut self.morph_animatable {
+            ret |= animatable.load_sub_assets(progress, morph_animatables)?;
+        }
         Ok(ret)
     }
 }
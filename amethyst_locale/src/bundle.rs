@@ -0,0 +1,38 @@
+use amethyst_assets::Processor;
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::prelude::{DispatcherBuilder, World},
+};
+use amethyst_error::Error;
+
+use crate::{Locale, LocalizationSystem};
+
+/// Bundle processing the loaded `Locale`s and building the messages of the `Localization`
+/// resource.
+///
+/// Adds the `locale_processor` and `localization_system` systems.
+#[derive(Debug, Default)]
+pub struct LocaleBundle;
+
+impl LocaleBundle {
+    /// Creates a new locale bundle.
+    pub fn new() -> Self {
+        LocaleBundle
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for LocaleBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(Processor::<Locale>::new(), "locale_processor", &[]);
+        builder.add(
+            LocalizationSystem,
+            "localization_system",
+            &["locale_processor"],
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 //! # amethyst_locale
 //!
 //! Localisation binding a `Fluent` file to an Asset<Locale> via the use of amethyst_assets.
+//!
+//! The `Localization` resource formats messages from the locales of the current language,
+//! falling back to other languages, and the `LocalizationSystem` of the `LocaleBundle` sends a
+//! `LocalizationEvent` when the language is switched so localized text can be resolved again.
 
 #![doc(
     html_logo_url = "https://amethyst.rs/brand/logo-standard.svg",
@@ -14,12 +18,24 @@
 )]
 #![warn(clippy::all)]
 
+use std::sync::Arc;
+
 use amethyst_assets::{Asset, Format, Handle};
 use amethyst_core::ecs::prelude::VecStorage;
 use amethyst_error::Error;
-pub use fluent::{concurrent::FluentBundle, FluentResource};
+pub use fluent::{concurrent::FluentBundle, fluent_args, FluentArgs, FluentResource, FluentValue};
 use serde::{Deserialize, Serialize};
-use unic_langid::langid;
+pub use unic_langid::{langid, LanguageIdentifier};
+
+pub use crate::{
+    bundle::LocaleBundle,
+    localization::{LocaleArg, Localization, LocalizationEvent, LocalizationSystem},
+    pseudo::pseudo_localize,
+};
+
+mod bundle;
+mod localization;
+mod pseudo;
 
 /// Loads the strings from localisation files.
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -36,15 +52,15 @@ impl Format<Locale> for LocaleFormat {
     fn import_simple(&self, bytes: Vec<u8>) -> Result<Locale, Error> {
         let s = String::from_utf8(bytes)?;
 
-        let resource = FluentResource::try_new(s).expect("Failed to parse locale data");
+        let resource = Arc::new(FluentResource::try_new(s).expect("Failed to parse locale data"));
         let lang_en = langid!("en");
         let mut bundle = FluentBundle::new(&[lang_en]);
 
         bundle
-            .add_resource(resource)
+            .add_resource(resource.clone())
             .expect("Failed to add resource");
 
-        Ok(Locale { bundle })
+        Ok(Locale { bundle, resource })
     }
 }
 
@@ -54,8 +70,11 @@ pub type LocaleHandle = Handle<Locale>;
 /// A loaded locale.
 #[allow(missing_debug_implementations)]
 pub struct Locale {
-    /// The bundle stores its resources for now.
-    pub bundle: FluentBundle<FluentResource>,
+    /// Bundle of the messages of the file, formatted with the plural rules of English. Use the
+    /// `Localization` resource to format them in their own language.
+    pub bundle: FluentBundle<Arc<FluentResource>>,
+    /// The messages of the file.
+    pub resource: Arc<FluentResource>,
 }
 
 impl Asset for Locale {
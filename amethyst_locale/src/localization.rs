@@ -0,0 +1,424 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::prelude::{Read, System, Write},
+    shrev::EventChannel,
+};
+use fluent::{concurrent::FluentBundle, FluentArgs, FluentResource, FluentValue};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use unic_langid::LanguageIdentifier;
+
+use crate::{pseudo, Locale};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Value of a message argument that can be stored, e.g. in components or prefabs.
+///
+/// Numbers select the plural variants of the messages, e.g. `{ $count -> [one] ... }`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LocaleArg {
+    /// A number, formatted and selected with the rules of the language.
+    Number(f64),
+    /// A string.
+    String(String),
+}
+
+impl LocaleArg {
+    /// Returns the argument as a Fluent value.
+    pub fn to_fluent(&self) -> FluentValue<'_> {
+        match self {
+            LocaleArg::Number(number) => FluentValue::from(*number),
+            LocaleArg::String(string) => FluentValue::from(string.as_str()),
+        }
+    }
+}
+
+impl From<f64> for LocaleArg {
+    fn from(number: f64) -> Self {
+        LocaleArg::Number(number)
+    }
+}
+
+impl From<i32> for LocaleArg {
+    fn from(number: i32) -> Self {
+        LocaleArg::Number(number.into())
+    }
+}
+
+impl From<String> for LocaleArg {
+    fn from(string: String) -> Self {
+        LocaleArg::String(string)
+    }
+}
+
+impl From<&str> for LocaleArg {
+    fn from(string: &str) -> Self {
+        LocaleArg::String(string.to_string())
+    }
+}
+
+/// Event sent by the `LocalizationSystem` when localized text has to be resolved again.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LocalizationEvent {
+    /// The current language was switched to the given language.
+    LanguageChanged(LanguageIdentifier),
+    /// Locales were added, loaded or reloaded.
+    Reloaded,
+}
+
+/// Resource formatting messages from the locales of a chain of languages, the first language
+/// being the current one. A message missing from the current language is taken from the next
+/// language of the chain having it.
+///
+/// ```rust,ignore
+/// let mut localization = Localization::new(vec![langid!("fr"), langid!("en")])
+///     .with_locale(langid!("en"), en_handle)
+///     .with_locale(langid!("fr"), fr_handle);
+/// let text = localization.format("apples", Some(&fluent_args!["count" => 3]));
+/// ```
+///
+/// The messages are available once the `LocalizationSystem` has run after the locales loaded.
+#[derive(Default)]
+pub struct Localization {
+    languages: Vec<LanguageIdentifier>,
+    locales: Vec<(LanguageIdentifier, Handle<Locale>)>,
+    bundles: Vec<FluentBundle<Arc<FluentResource>>>,
+    versions: Vec<Option<u32>>,
+    changed: bool,
+    switched: Option<LanguageIdentifier>,
+    pseudo: bool,
+}
+
+impl fmt::Debug for Localization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Localization")
+            .field("languages", &self.languages)
+            .field("locales", &self.locales)
+            .field("pseudo", &self.pseudo)
+            .finish()
+    }
+}
+
+impl Localization {
+    /// Creates a localization with the given chain of languages, starting with the current one.
+    pub fn new(languages: Vec<LanguageIdentifier>) -> Self {
+        Localization {
+            languages,
+            changed: true,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a locale of the given language, see `add_locale`.
+    pub fn with_locale(mut self, language: LanguageIdentifier, locale: Handle<Locale>) -> Self {
+        self.add_locale(language, locale);
+        self
+    }
+
+    /// Adds a locale of the given language. A language can have several locales, e.g. one file
+    /// per menu, as long as they don't define the same messages.
+    pub fn add_locale(&mut self, language: LanguageIdentifier, locale: Handle<Locale>) {
+        self.locales.push((language, locale));
+        self.changed = true;
+    }
+
+    /// Returns the chain of languages, starting with the current one.
+    pub fn languages(&self) -> &[LanguageIdentifier] {
+        &self.languages
+    }
+
+    /// Returns the current language.
+    pub fn language(&self) -> Option<&LanguageIdentifier> {
+        self.languages.first()
+    }
+
+    /// Switches the current language, keeping the other languages of the chain as fallbacks.
+    ///
+    /// The `LocalizationSystem` sends a `LocalizationEvent::LanguageChanged` on its next run.
+    pub fn set_language(&mut self, language: LanguageIdentifier) {
+        if self.language() == Some(&language) {
+            return;
+        }
+        self.languages.retain(|l| *l != language);
+        self.languages.insert(0, language.clone());
+        self.changed = true;
+        self.switched = Some(language);
+    }
+
+    /// Enables pseudo-localization, see `set_pseudo_localization`.
+    pub fn with_pseudo_localization(mut self) -> Self {
+        self.set_pseudo_localization(true);
+        self
+    }
+
+    /// Enables or disables pseudo-localization, a debugging mode where the text of every message
+    /// gets accents, is lengthened by 30% and is wrapped in brackets: `[Ñéw gámé~~]`.
+    ///
+    /// Text that isn't localized stands out, and layouts can be checked against longer
+    /// translations. The `LocalizationSystem` sends a `LocalizationEvent::Reloaded` on its next
+    /// run so localized text is resolved again.
+    pub fn set_pseudo_localization(&mut self, pseudo: bool) {
+        if self.pseudo != pseudo {
+            self.pseudo = pseudo;
+            self.changed = true;
+        }
+    }
+
+    /// Returns true if pseudo-localization is enabled.
+    pub fn pseudo_localization(&self) -> bool {
+        self.pseudo
+    }
+
+    /// Returns true if a language of the chain has the message.
+    pub fn has_message(&self, id: &str) -> bool {
+        self.bundles.iter().any(|bundle| bundle.has_message(id))
+    }
+
+    /// Formats the value of the message with the given arguments, or returns `None` if no
+    /// language of the chain has the message.
+    pub fn format(&self, id: &str, args: Option<&FluentArgs<'_>>) -> Option<String> {
+        self.bundles.iter().find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value?;
+            let mut errors = Vec::new();
+            let value = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                warn!("Failed to format message '{}': {:?}", id, errors);
+            }
+            if self.pseudo {
+                Some(format!("[{}]", value))
+            } else {
+                Some(value.into_owned())
+            }
+        })
+    }
+
+    /// Formats the value of the message with stored arguments, see `format`.
+    pub fn format_with(&self, id: &str, args: &HashMap<String, LocaleArg>) -> Option<String> {
+        let args = args
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_fluent()))
+            .collect::<FluentArgs<'_>>();
+        self.format(id, Some(&args))
+    }
+
+    /// Builds the bundles of the chain again if the languages changed or locales were loaded,
+    /// returning true if they were.
+    fn rebuild(&mut self, storage: &AssetStorage<Locale>) -> bool {
+        let versions = self
+            .locales
+            .iter()
+            .map(|(_, handle)| storage.get_version(handle))
+            .collect::<Vec<_>>();
+        if !self.changed && versions == self.versions {
+            return false;
+        }
+
+        self.bundles = self
+            .languages
+            .iter()
+            .map(|language| {
+                let mut bundle = FluentBundle::new(std::slice::from_ref(language));
+                // bidirectional isolation marks are drawn by fonts
+                bundle.set_use_isolating(false);
+                if self.pseudo {
+                    bundle.set_transform(Some(pseudo::transform));
+                }
+                let locales = self.locales.iter().filter(|(l, _)| l == language);
+                for (_, handle) in locales {
+                    if let Some(locale) = storage.get(handle) {
+                        if let Err(errors) = bundle.add_resource(locale.resource.clone()) {
+                            warn!("Failed to add locale of {}: {:?}", language, errors);
+                        }
+                    }
+                }
+                bundle
+            })
+            .collect();
+        self.versions = versions;
+        self.changed = false;
+        true
+    }
+}
+
+/// System building the messages of the `Localization` when its locales load or its language is
+/// switched, and sending a `LocalizationEvent` afterwards.
+#[derive(Debug, Default)]
+pub struct LocalizationSystem;
+
+impl<'a> System<'a> for LocalizationSystem {
+    type SystemData = (
+        Write<'a, Localization>,
+        Read<'a, AssetStorage<Locale>>,
+        Write<'a, EventChannel<LocalizationEvent>>,
+    );
+
+    fn run(&mut self, (mut localization, storage, mut events): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("localization_system");
+
+        let switched = localization.switched.take();
+        if localization.rebuild(&storage) {
+            events.single_write(match switched {
+                Some(language) => LocalizationEvent::LanguageChanged(language),
+                None => LocalizationEvent::Reloaded,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocaleFormat;
+    use amethyst_assets::Format;
+    use amethyst_core::ecs::{RunNow, World, WorldExt};
+    use unic_langid::langid;
+
+    /// Creates a world with a `Localization` of the given languages and in-memory locales.
+    fn world(languages: Vec<LanguageIdentifier>, locales: &[(LanguageIdentifier, &str)]) -> World {
+        let mut world = World::new();
+        let mut storage = AssetStorage::<Locale>::new();
+        let mut localization = Localization::new(languages);
+        for (language, source) in locales {
+            let locale = LocaleFormat
+                .import_simple(source.as_bytes().to_vec())
+                .unwrap();
+            localization.add_locale(language.clone(), storage.insert(locale));
+        }
+        world.insert(storage);
+        world.insert(localization);
+        world.insert(EventChannel::<LocalizationEvent>::new());
+        world
+    }
+
+    /// Runs the `LocalizationSystem` and returns the events it sent.
+    fn run(world: &World) -> Vec<LocalizationEvent> {
+        let mut reader = world
+            .write_resource::<EventChannel<LocalizationEvent>>()
+            .register_reader();
+        LocalizationSystem.run_now(world);
+        world
+            .read_resource::<EventChannel<LocalizationEvent>>()
+            .read(&mut reader)
+            .cloned()
+            .collect()
+    }
+
+    fn two_languages() -> World {
+        world(
+            vec![langid!("fr"), langid!("en")],
+            &[
+                (langid!("en"), "hello = Hello\nbye = Goodbye"),
+                (langid!("fr"), "hello = Bonjour"),
+            ],
+        )
+    }
+
+    #[test]
+    fn falls_back_to_the_next_language() {
+        let world = two_languages();
+        assert_eq!(
+            world.read_resource::<Localization>().format("hello", None),
+            None
+        );
+
+        assert_eq!(run(&world), [LocalizationEvent::Reloaded]);
+        let localization = world.read_resource::<Localization>();
+        assert_eq!(localization.format("hello", None).unwrap(), "Bonjour");
+        assert_eq!(localization.format("bye", None).unwrap(), "Goodbye");
+        assert!(localization.has_message("bye"));
+        assert!(!localization.has_message("missing"));
+        assert_eq!(localization.format("missing", None), None);
+    }
+
+    #[test]
+    fn set_language_moves_it_to_the_front() {
+        let world = two_languages();
+        run(&world);
+
+        world
+            .write_resource::<Localization>()
+            .set_language(langid!("en"));
+        assert_eq!(
+            world.read_resource::<Localization>().languages(),
+            [langid!("en"), langid!("fr")]
+        );
+        assert_eq!(
+            run(&world),
+            [LocalizationEvent::LanguageChanged(langid!("en"))]
+        );
+        assert_eq!(
+            world
+                .read_resource::<Localization>()
+                .format("hello", None)
+                .unwrap(),
+            "Hello"
+        );
+
+        // Nothing changed, so the messages don't have to be resolved again.
+        world
+            .write_resource::<Localization>()
+            .set_language(langid!("en"));
+        assert!(run(&world).is_empty());
+    }
+
+    #[test]
+    fn plurals_use_the_rules_of_the_language() {
+        let apples =
+            "apples = { $count ->\n    [one] { $count } apple\n   *[other] { $count } apples\n}";
+        let pommes =
+            "apples = { $count ->\n    [one] { $count } pomme\n   *[other] { $count } pommes\n}";
+        let world = world(
+            vec![langid!("en")],
+            &[(langid!("en"), apples), (langid!("fr"), pommes)],
+        );
+        run(&world);
+
+        let format = |count: i32| {
+            let mut args = HashMap::new();
+            args.insert("count".to_string(), LocaleArg::from(count));
+            world
+                .read_resource::<Localization>()
+                .format_with("apples", &args)
+                .unwrap()
+        };
+        assert_eq!(format(0), "0 apples");
+        assert_eq!(format(1), "1 apple");
+        assert_eq!(format(3), "3 apples");
+
+        // French uses the singular for zero.
+        world
+            .write_resource::<Localization>()
+            .set_language(langid!("fr"));
+        run(&world);
+        assert_eq!(format(0), "0 pomme");
+        assert_eq!(format(3), "3 pommes");
+    }
+
+    #[test]
+    fn pseudo_localization_leaves_arguments_as_they_are() {
+        let world = world(
+            vec![langid!("en")],
+            &[(langid!("en"), "greeting = Hello { $name }")],
+        );
+        run(&world);
+
+        world
+            .write_resource::<Localization>()
+            .set_pseudo_localization(true);
+        assert_eq!(run(&world), [LocalizationEvent::Reloaded]);
+        let mut args = HashMap::new();
+        args.insert("name".to_string(), LocaleArg::from("Sam"));
+        assert_eq!(
+            world
+                .read_resource::<Localization>()
+                .format_with("greeting", &args)
+                .unwrap(),
+            "[Hélló ~~Sam]"
+        );
+    }
+}
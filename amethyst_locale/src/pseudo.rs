@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+/// Characters appended to pseudo-localized text, lengthening it by 30% like translations to
+/// longer languages do.
+const EXPANSION: char = '~';
+
+/// Pseudo-localizes text: letters get accents and the text is lengthened by 30%, so that text
+/// that isn't localized stands out and layouts are checked against longer translations.
+///
+/// The `Localization` also wraps the messages in brackets when pseudo-localization is enabled,
+/// showing where messages are cut off.
+pub fn pseudo_localize(text: &str) -> String {
+    let mut pseudo = text.chars().map(accent).collect::<String>();
+    let letters = text.chars().filter(|c| c.is_alphanumeric()).count();
+    for _ in 0..(letters * 3 + 5) / 10 {
+        pseudo.push(EXPANSION);
+    }
+    pseudo
+}
+
+/// Transform of the text fragments of Fluent messages, leaving arguments as they are.
+pub(crate) fn transform(text: &str) -> Cow<'_, str> {
+    Cow::Owned(pseudo_localize(text))
+}
+
+fn accent(c: char) -> char {
+    match c {
+        'a' => 'á',
+        'c' => 'ç',
+        'e' => 'é',
+        'i' => 'í',
+        'n' => 'ñ',
+        'o' => 'ó',
+        's' => 'š',
+        'u' => 'ü',
+        'y' => 'ý',
+        'z' => 'ž',
+        'A' => 'Å',
+        'C' => 'Ç',
+        'E' => 'É',
+        'I' => 'Î',
+        'N' => 'Ñ',
+        'O' => 'Ö',
+        'S' => 'Š',
+        'U' => 'Û',
+        'Y' => 'Ý',
+        'Z' => 'Ž',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_get_accents() {
+        assert_eq!(pseudo_localize("New game"), "Ñéw gámé~~");
+        assert_eq!(pseudo_localize("Zoë, 42!"), "Žóë, 42!~~");
+    }
+
+    #[test]
+    fn text_is_lengthened_by_thirty_percent() {
+        assert_eq!(pseudo_localize(""), "");
+        assert_eq!(pseudo_localize("?"), "?");
+        assert_eq!(pseudo_localize("b"), "b");
+        assert_eq!(pseudo_localize("bb"), "bb~");
+        assert_eq!(
+            pseudo_localize(&"b".repeat(10)),
+            format!("{}~~~", "b".repeat(10))
+        );
+        assert_eq!(pseudo_localize(&"b".repeat(100)).len(), 130);
+    }
+}
@@ -2,14 +2,54 @@
 //! more utilities to make their way into this module. e.g. "Component synchronization",
 //! "Matchmaking", etc.
 
+mod authority;
+mod channel;
+mod compression;
+mod conditioner;
+mod delta;
 mod events;
+mod lockstep;
 mod message;
 mod requirements;
+mod rpc;
+#[cfg(feature = "encryption")]
+mod security;
+mod session;
+mod stats;
 mod timing;
 mod transport;
 
+pub use authority::{
+    AuthoritySystem, AuthorityViolation, Authorized, EntityMessage, NetworkId, Ownership,
+};
+pub use channel::{ChannelConfig, ChannelId, TransportGuarantees};
+pub use compression::{
+    Compression, CompressionConfig, CompressionStats, DEFAULT_MAX_DECOMPRESSED_SIZE,
+};
+pub use conditioner::{LinkConditions, NetworkConditioner};
+pub use delta::{DeltaDecoder, DeltaEncoder};
 pub use events::NetworkSimulationEvent;
+pub use lockstep::{
+    LockstepConfig, LockstepInput, LockstepNetworkSystem, LockstepPacket, LockstepRequest,
+    LockstepSession,
+};
 pub use message::Message;
 pub use requirements::{DeliveryRequirement, UrgencyRequirement};
+pub use rpc::{
+    decode_message, encode_message, MessageSender, NetworkMessage, NetworkMessageBundle,
+    NetworkMessageSystem, Received,
+};
+#[cfg(feature = "encryption")]
+pub use security::{
+    Authenticator, PublicKey, SecureNetworkBundle, SecureNetworkEvent, SecureSessionSystem,
+    SecureSessions, SecurityConfig, StaticSecret,
+};
+pub use session::{
+    SessionBundle, SessionConfig, SessionEvent, SessionManager, SessionMessage, SessionSystem,
+    SessionToken,
+};
+pub use stats::{ConnectionStats, NetworkStats, NetworkStatsSystem};
 pub use timing::{NetworkSimulationTime, NetworkSimulationTimeSystem};
-pub use transport::{laminar, tcp, udp, TransportResource};
+#[cfg(feature = "websocket")]
+pub use transport::websocket;
+pub use transport::{fallback, laminar, tcp, udp, TransportResource};
@@ -0,0 +1,246 @@
+//! Entity ownership for replicated entities. Every replicated entity has a `NetworkId` shared by
+//! all peers and an `Ownership` deciding which peers may change it. Typed messages which write to
+//! an entity are checked by an `AuthoritySystem` on the server before they are applied, so clients
+//! can be authoritative over their own (e.g. cosmetic) entities while writes to everything else
+//! are rejected.
+
+use crate::simulation::rpc::{NetworkMessage, Received};
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, System, Write},
+    shrev::{EventChannel, ReaderId},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, net::SocketAddr};
+
+/// Identifier of a replicated entity which is the same on all peers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NetworkId(pub u64);
+
+impl Component for NetworkId {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Which peers are allowed to change a replicated entity.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ownership {
+    /// Only the server may change the entity.
+    Server,
+    /// The entity is owned by the client with the given address. The server may still change it.
+    Client(SocketAddr),
+    /// Every peer may change the entity.
+    Shared,
+}
+
+impl Ownership {
+    /// Returns true if the given client may change the entity.
+    pub fn allows(&self, client: SocketAddr) -> bool {
+        match self {
+            Ownership::Server => false,
+            Ownership::Client(owner) => *owner == client,
+            Ownership::Shared => true,
+        }
+    }
+}
+
+impl Component for Ownership {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A typed message which changes a replicated entity.
+pub trait EntityMessage: NetworkMessage {
+    /// Returns the id of the entity the message changes.
+    fn network_id(&self) -> NetworkId;
+}
+
+/// A message which passed the ownership check, together with the entity it changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Authorized<T> {
+    /// The peer which sent the message.
+    pub from: SocketAddr,
+    /// The local entity with the `NetworkId` of the message.
+    pub entity: Entity,
+    /// The message itself.
+    pub message: T,
+}
+
+/// Emitted when a client tried to change an entity it doesn't own.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AuthorityViolation {
+    /// The peer which sent the message.
+    pub from: SocketAddr,
+    /// The id of the entity it tried to change.
+    pub network_id: NetworkId,
+}
+
+/// System forwarding the received messages of type `T` to the `EventChannel<Authorized<T>>` if
+/// their sender owns the entity. Rejected messages are reported as `AuthorityViolation`s. Entities
+/// without an `Ownership` component are owned by the server.
+#[allow(missing_debug_implementations)]
+pub struct AuthoritySystem<T: EntityMessage> {
+    reader: ReaderId<Received<T>>,
+}
+
+impl<T: EntityMessage> AuthoritySystem<T> {
+    pub fn new(reader: ReaderId<Received<T>>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'s, T: EntityMessage + Clone> System<'s> for AuthoritySystem<T> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, NetworkId>,
+        ReadStorage<'s, Ownership>,
+        Read<'s, EventChannel<Received<T>>>,
+        Write<'s, EventChannel<Authorized<T>>>,
+        Write<'s, EventChannel<AuthorityViolation>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, ids, ownerships, received, mut authorized, mut violations): Self::SystemData,
+    ) {
+        let mut messages = received.read(&mut self.reader).peekable();
+        if messages.peek().is_none() {
+            return;
+        }
+        let lookup = (&entities, &ids)
+            .join()
+            .map(|(entity, id)| (*id, entity))
+            .collect::<HashMap<_, _>>();
+
+        for Received { from, message } in messages {
+            let network_id = message.network_id();
+            let entity = match lookup.get(&network_id) {
+                Some(entity) => *entity,
+                None => {
+                    warn!(
+                        "Dropping message {} for unknown entity {:?}",
+                        T::ID,
+                        network_id
+                    );
+                    continue;
+                }
+            };
+            let ownership = ownerships.get(entity).copied().unwrap_or(Ownership::Server);
+            if ownership.allows(*from) {
+                authorized.single_write(Authorized {
+                    from: *from,
+                    entity,
+                    message: message.clone(),
+                });
+            } else {
+                violations.single_write(AuthorityViolation {
+                    from: *from,
+                    network_id,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct SetColor {
+        id: NetworkId,
+        color: u32,
+    }
+
+    impl NetworkMessage for SetColor {
+        const ID: u16 = 10;
+    }
+
+    impl EntityMessage for SetColor {
+        fn network_id(&self) -> NetworkId {
+            self.id
+        }
+    }
+
+    #[test]
+    fn test_ownership_allows() {
+        let owner = "127.0.0.1:3000".parse().unwrap();
+        let other = "127.0.0.1:3001".parse().unwrap();
+        assert!(!Ownership::Server.allows(owner));
+        assert!(Ownership::Client(owner).allows(owner));
+        assert!(!Ownership::Client(owner).allows(other));
+        assert!(Ownership::Shared.allows(other));
+    }
+
+    #[test]
+    fn test_writes_to_foreign_entities_are_rejected() {
+        let owner = "127.0.0.1:3000".parse().unwrap();
+        let other = "127.0.0.1:3001".parse().unwrap();
+        let mut world = World::new();
+        world.register::<NetworkId>();
+        world.register::<Ownership>();
+        let owned = world
+            .create_entity()
+            .with(NetworkId(1))
+            .with(Ownership::Client(owner))
+            .build();
+        world.create_entity().with(NetworkId(2)).build();
+
+        let mut received = EventChannel::<Received<SetColor>>::new();
+        let mut system = AuthoritySystem::new(received.register_reader());
+        received.iter_write(vec![
+            Received {
+                from: owner,
+                message: SetColor {
+                    id: NetworkId(1),
+                    color: 1,
+                },
+            },
+            Received {
+                from: other,
+                message: SetColor {
+                    id: NetworkId(1),
+                    color: 2,
+                },
+            },
+            Received {
+                from: owner,
+                message: SetColor {
+                    id: NetworkId(2),
+                    color: 3,
+                },
+            },
+        ]);
+        world.insert(received);
+        let mut authorized_reader = world
+            .entry::<EventChannel<Authorized<SetColor>>>()
+            .or_insert_with(EventChannel::new)
+            .register_reader();
+        let mut violation_reader = world
+            .entry::<EventChannel<AuthorityViolation>>()
+            .or_insert_with(EventChannel::new)
+            .register_reader();
+
+        system.run_now(&world);
+
+        let authorized = world.fetch::<EventChannel<Authorized<SetColor>>>();
+        let authorized = authorized.read(&mut authorized_reader).collect::<Vec<_>>();
+        assert_eq!(authorized.len(), 1);
+        assert_eq!(authorized[0].entity, owned);
+        assert_eq!(authorized[0].message.color, 1);
+        let violations = world.fetch::<EventChannel<AuthorityViolation>>();
+        let violations = violations.read(&mut violation_reader).collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            vec![
+                &AuthorityViolation {
+                    from: other,
+                    network_id: NetworkId(1),
+                },
+                &AuthorityViolation {
+                    from: owner,
+                    network_id: NetworkId(2),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Virtual channels allow messages with different delivery guarantees and priorities to share a
+//! single connection without competing with each other. e.g. chat messages, state snapshots and
+//! player input can each be given their own channel.
+
+use super::requirements::DeliveryRequirement;
+
+/// Identifier of a virtual channel. When a channel uses an ordered or sequenced delivery
+/// requirement without an explicit stream id, the channel id is used as the stream id so that
+/// channels are ordered independently from each other.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChannelId(pub u8);
+
+/// Configuration of a virtual channel registered on the `TransportResource`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChannelConfig {
+    /// The delivery guarantee of every message sent on this channel.
+    pub delivery: DeliveryRequirement,
+    /// Messages of channels with a higher priority are handed to the transport first.
+    pub priority: u8,
+    /// Maximum number of payload bytes sent on this channel to each destination per simulation
+    /// tick. The first message which doesn't fit in the budget stays queued until the next tick,
+    /// along with the messages queued after it. At least one message is always sent per tick so
+    /// that a single large message can't stall the channel.
+    pub budget_bytes: Option<usize>,
+}
+
+impl ChannelConfig {
+    /// Creates a new channel configuration with the given delivery guarantee, the lowest
+    /// priority and no budget.
+    pub fn new(delivery: DeliveryRequirement) -> Self {
+        Self {
+            delivery,
+            priority: 0,
+            budget_bytes: None,
+        }
+    }
+
+    /// Creates a reliable and ordered channel configuration.
+    pub fn reliable_ordered() -> Self {
+        Self::new(DeliveryRequirement::ReliableOrdered(None))
+    }
+
+    /// Creates a reliable but unordered channel configuration.
+    pub fn reliable_unordered() -> Self {
+        Self::new(DeliveryRequirement::Reliable)
+    }
+
+    /// Creates an unreliable channel configuration which only delivers the newest messages.
+    pub fn unreliable_sequenced() -> Self {
+        Self::new(DeliveryRequirement::UnreliableSequenced(None))
+    }
+
+    /// Sets the priority of the channel.
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the per tick budget of the channel in bytes.
+    pub fn with_budget_bytes(mut self, budget_bytes: usize) -> Self {
+        self.budget_bytes = Some(budget_bytes);
+        self
+    }
+
+    /// Returns the delivery requirement to use for messages sent on the channel with the given
+    /// id. Ordered and sequenced requirements without a stream id get the channel id as stream id.
+    pub(crate) fn delivery_for(&self, id: ChannelId) -> DeliveryRequirement {
+        match self.delivery {
+            DeliveryRequirement::UnreliableSequenced(None) => {
+                DeliveryRequirement::UnreliableSequenced(Some(id.0))
+            }
+            DeliveryRequirement::ReliableSequenced(None) => {
+                DeliveryRequirement::ReliableSequenced(Some(id.0))
+            }
+            DeliveryRequirement::ReliableOrdered(None) => {
+                DeliveryRequirement::ReliableOrdered(Some(id.0))
+            }
+            delivery => delivery,
+        }
+    }
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self::new(DeliveryRequirement::Default)
+    }
+}
+
+/// The delivery guarantees the active transport provides. Transport bundles set them on the
+/// `TransportResource`, which checks channels against them and sends channel messages with a
+/// requirement the transport supports.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TransportGuarantees {
+    /// Every delivery requirement is supported, e.g. by the laminar transport. This is the
+    /// default before a transport bundle is built.
+    #[default]
+    All,
+    /// Messages are written to a reliable and ordered stream, e.g. by the TCP and WebSocket
+    /// transports. The stream meets every delivery requirement, so messages are sent as
+    /// `ReliableOrdered` without a stream id.
+    Stream,
+    /// Messages are sent as datagrams without any guarantee, e.g. by the UDP transport.
+    Unreliable,
+}
+
+impl TransportGuarantees {
+    /// Returns the requirement the transport sends a message of the given requirement with, or
+    /// `None` if the transport can't provide the guarantee.
+    pub fn delivery(self, delivery: DeliveryRequirement) -> Option<DeliveryRequirement> {
+        match (self, delivery) {
+            (_, DeliveryRequirement::Default) | (TransportGuarantees::All, _) => Some(delivery),
+            (TransportGuarantees::Stream, _) => Some(DeliveryRequirement::ReliableOrdered(None)),
+            (TransportGuarantees::Unreliable, DeliveryRequirement::Unreliable) => Some(delivery),
+            (TransportGuarantees::Unreliable, _) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_id_is_used_as_stream_id() {
+        let id = ChannelId(3);
+        assert_eq!(
+            ChannelConfig::reliable_ordered().delivery_for(id),
+            DeliveryRequirement::ReliableOrdered(Some(3))
+        );
+        assert_eq!(
+            ChannelConfig::unreliable_sequenced().delivery_for(id),
+            DeliveryRequirement::UnreliableSequenced(Some(3))
+        );
+        assert_eq!(
+            ChannelConfig::reliable_unordered().delivery_for(id),
+            DeliveryRequirement::Reliable
+        );
+    }
+
+    #[test]
+    fn test_explicit_stream_id_is_kept() {
+        let config = ChannelConfig::new(DeliveryRequirement::ReliableOrdered(Some(7)));
+        assert_eq!(
+            config.delivery_for(ChannelId(1)),
+            DeliveryRequirement::ReliableOrdered(Some(7))
+        );
+    }
+
+    #[test]
+    fn test_streams_deliver_every_requirement_in_order() {
+        use DeliveryRequirement::*;
+        for delivery in [
+            Unreliable,
+            UnreliableSequenced(Some(1)),
+            Reliable,
+            ReliableOrdered(Some(2)),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                TransportGuarantees::Stream.delivery(*delivery),
+                Some(ReliableOrdered(None))
+            );
+        }
+        assert_eq!(TransportGuarantees::Stream.delivery(Default), Some(Default));
+    }
+
+    #[test]
+    fn test_unreliable_transports_reject_guarantees() {
+        use DeliveryRequirement::*;
+        let guarantees = TransportGuarantees::Unreliable;
+        assert_eq!(guarantees.delivery(Unreliable), Some(Unreliable));
+        assert_eq!(guarantees.delivery(Default), Some(Default));
+        assert_eq!(guarantees.delivery(UnreliableSequenced(Some(1))), None);
+        assert_eq!(guarantees.delivery(Reliable), None);
+        assert_eq!(TransportGuarantees::All.delivery(Reliable), Some(Reliable));
+    }
+}
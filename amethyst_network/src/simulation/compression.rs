@@ -0,0 +1,289 @@
+//! Optional compression of message payloads. When compression is enabled every payload is
+//! prefixed with a single header byte describing how it was encoded, so both peers must use the
+//! same `CompressionConfig` algorithm family. Compression only makes sense for message based
+//! transports (UDP, laminar and WebSockets), which compress payloads when sending them; the TCP
+//! transport doesn't frame messages, so it sends and receives payloads uncompressed, including
+//! the TCP routes of the fallback transport.
+
+use bytes::Bytes;
+use std::io;
+
+const HEADER_UNCOMPRESSED: u8 = 0;
+#[cfg(feature = "lz4")]
+const HEADER_LZ4: u8 = 1;
+#[cfg(feature = "zstd")]
+const HEADER_ZSTD: u8 = 2;
+
+/// Default limit of the size of decompressed payloads, in bytes.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 1024 * 1024;
+
+/// Compression algorithm applied to outgoing message payloads.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Payloads are sent as is, without any header.
+    None,
+    /// Payloads are compressed with LZ4, which is fast but has a lower compression ratio.
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Payloads are compressed with zstd using the given compression level.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+/// Configuration of the payload compression of the `TransportResource`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// The algorithm used to compress payloads.
+    pub algorithm: Compression,
+    /// Payloads smaller than this are sent uncompressed, since the compression overhead would
+    /// outweigh the savings.
+    pub min_size_bytes: usize,
+    /// Compressed payloads claiming to decompress to more bytes than this are rejected before
+    /// decompressing them, as the size is sent by the peer. `DEFAULT_MAX_DECOMPRESSED_SIZE` by
+    /// default.
+    pub max_decompressed_size: usize,
+}
+
+impl CompressionConfig {
+    /// Creates a new configuration compressing every payload with the given algorithm.
+    pub fn new(algorithm: Compression) -> Self {
+        Self {
+            algorithm,
+            min_size_bytes: 0,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+
+    /// Sets the minimum payload size for compression to be applied.
+    pub fn with_min_size_bytes(mut self, min_size_bytes: usize) -> Self {
+        self.min_size_bytes = min_size_bytes;
+        self
+    }
+
+    /// Sets the maximum size of decompressed payloads.
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+
+    /// Encodes the given payload. Payloads are only sent compressed if that makes them smaller.
+    pub fn compress(&self, payload: &[u8]) -> Bytes {
+        if self.algorithm == Compression::None {
+            return Bytes::copy_from_slice(payload);
+        }
+
+        let mut encoded = Vec::with_capacity(payload.len() + 1);
+        match self.compress_with_algorithm(payload) {
+            Some((header, data)) if data.len() < payload.len() => {
+                encoded.push(header);
+                encoded.extend_from_slice(&data);
+            }
+            _ => {
+                encoded.push(HEADER_UNCOMPRESSED);
+                encoded.extend_from_slice(payload);
+            }
+        }
+        Bytes::from(encoded)
+    }
+
+    fn compress_with_algorithm(&self, payload: &[u8]) -> Option<(u8, Vec<u8>)> {
+        if payload.len() < self.min_size_bytes {
+            return None;
+        }
+        match self.algorithm {
+            Compression::None => None,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Some((HEADER_LZ4, lz4_flex::compress_prepend_size(payload))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => zstd::block::compress(payload, level)
+                .ok()
+                .map(|data| (HEADER_ZSTD, prepend_size(payload.len(), data))),
+        }
+    }
+
+    /// Decodes a payload which was encoded by a peer using the same algorithm family.
+    ///
+    /// Fails without decompressing the payload if it would decompress to more than
+    /// `max_decompressed_size` bytes.
+    pub fn decompress(&self, payload: &[u8]) -> io::Result<Bytes> {
+        if self.algorithm == Compression::None {
+            return Ok(Bytes::copy_from_slice(payload));
+        }
+        match payload.split_first() {
+            Some((&HEADER_UNCOMPRESSED, data)) => Ok(Bytes::copy_from_slice(data)),
+            #[cfg(feature = "lz4")]
+            Some((&HEADER_LZ4, data)) => {
+                let (size, data) = self.split_size(data)?;
+                lz4_flex::decompress(data, size)
+                    .map(Bytes::from)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))
+            }
+            #[cfg(feature = "zstd")]
+            Some((&HEADER_ZSTD, data)) => {
+                let (size, data) = self.split_size(data)?;
+                zstd::block::decompress(data, size).map(Bytes::from)
+            }
+            Some((header, _)) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown compression header {}", header),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Empty compressed payload",
+            )),
+        }
+    }
+
+    // Splits the decompressed size prepended to compressed data off, checking it against the
+    // maximum size.
+    #[cfg(any(feature = "lz4", feature = "zstd"))]
+    fn split_size<'a>(&self, data: &'a [u8]) -> io::Result<(usize, &'a [u8])> {
+        if data.len() < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Compressed payload is missing its size",
+            ));
+        }
+        let (size, data) = data.split_at(4);
+        let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+        if size > self.max_decompressed_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Compressed payload decompresses to {} bytes, more than the maximum of {}",
+                    size, self.max_decompressed_size
+                ),
+            ));
+        }
+        Ok((size, data))
+    }
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self::new(Compression::None)
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn prepend_size(size: usize, data: Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 4);
+    out.extend_from_slice(&(size as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    out
+}
+
+/// Running totals of how many bytes an encoding step received and produced.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Number of bytes before encoding.
+    pub uncompressed_bytes: u64,
+    /// Number of bytes after encoding.
+    pub compressed_bytes: u64,
+}
+
+impl CompressionStats {
+    /// Records a single encoded payload.
+    pub fn record(&mut self, uncompressed: usize, compressed: usize) {
+        self.uncompressed_bytes += uncompressed as u64;
+        self.compressed_bytes += compressed as u64;
+    }
+
+    /// Returns the number of bytes saved by encoding. This is negative when the encoding
+    /// overhead outweighed the savings.
+    pub fn bytes_saved(&self) -> i64 {
+        self.uncompressed_bytes as i64 - self.compressed_bytes as i64
+    }
+
+    /// Returns the ratio of encoded to original bytes, e.g. 0.25 when payloads shrunk to a
+    /// quarter of their size.
+    pub fn ratio(&self) -> f32 {
+        if self.uncompressed_bytes == 0 {
+            1.0
+        } else {
+            self.compressed_bytes as f32 / self.uncompressed_bytes as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_compression_keeps_payload() {
+        let config = CompressionConfig::default();
+        let encoded = config.compress(b"payload");
+        assert_eq!(&encoded[..], b"payload");
+        assert_eq!(&config.decompress(&encoded).unwrap()[..], b"payload");
+    }
+
+    #[test]
+    fn test_stats_bytes_saved() {
+        let mut stats = CompressionStats::default();
+        stats.record(100, 25);
+        stats.record(100, 25);
+        assert_eq!(stats.bytes_saved(), 150);
+        assert!((stats.ratio() - 0.25).abs() < f32::EPSILON);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4_round_trip() {
+        let config = CompressionConfig::new(Compression::Lz4);
+        let payload = vec![7; 512];
+        let encoded = config.compress(&payload);
+        assert_eq!(encoded[0], HEADER_LZ4);
+        assert!(encoded.len() < payload.len());
+        assert_eq!(&config.decompress(&encoded).unwrap()[..], &payload[..]);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_small_payloads_are_not_compressed() {
+        let config = CompressionConfig::new(Compression::Lz4).with_min_size_bytes(64);
+        let encoded = config.compress(&[7; 16]);
+        assert_eq!(encoded[0], HEADER_UNCOMPRESSED);
+        assert_eq!(&config.decompress(&encoded).unwrap()[..], &[7; 16][..]);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        let config = CompressionConfig::new(Compression::Zstd(3));
+        let payload = vec![3; 512];
+        let encoded = config.compress(&payload);
+        assert_eq!(encoded[0], HEADER_ZSTD);
+        assert_eq!(&config.decompress(&encoded).unwrap()[..], &payload[..]);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4_oversized_header_is_rejected() {
+        let config = CompressionConfig::new(Compression::Lz4);
+        let error = config
+            .decompress(&[HEADER_LZ4, 0xff, 0xff, 0xff, 0xff])
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let encoded = config.compress(&[7; 512]);
+        let config = config.with_max_decompressed_size(256);
+        assert!(config.decompress(&encoded).is_err());
+        let config = config.with_max_decompressed_size(512);
+        assert_eq!(config.decompress(&encoded).unwrap().len(), 512);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_oversized_header_is_rejected() {
+        let config = CompressionConfig::new(Compression::Zstd(3));
+        let error = config
+            .decompress(&[HEADER_ZSTD, 0xff, 0xff, 0xff, 0xff])
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let encoded = config.compress(&[3; 512]);
+        let config = config.with_max_decompressed_size(256);
+        assert!(config.decompress(&encoded).is_err());
+    }
+}
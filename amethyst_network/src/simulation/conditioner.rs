@@ -0,0 +1,374 @@
+//! Simulation of bad network conditions, so netcode can be tested without a real bad network. The
+//! `NetworkConditioner` is owned by the `TransportResource` and can be toggled at runtime. It
+//! delays, drops, duplicates and reorders outgoing messages before they are handed to the
+//! transport, and incoming messages before they are written to the event channel.
+//!
+//! Reliable messages and messages received over stream based transports (TCP and WebSockets) are
+//! only delayed by the latency and keep their order, since dropping or reordering them would
+//! break the guarantees of the transport instead of simulating them.
+
+use super::{events::NetworkSimulationEvent, message::Message, requirements::DeliveryRequirement};
+use amethyst_core::shrev::EventChannel;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// Conditions applied to the messages travelling in one direction.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LinkConditions {
+    /// Time every message is held back.
+    pub latency: Duration,
+    /// Maximum random time added to the latency of unreliable messages.
+    pub jitter: Duration,
+    /// Chance in 0.0-1.0 that an unreliable message is dropped.
+    pub packet_loss: f32,
+    /// Chance in 0.0-1.0 that an unreliable message is delivered twice.
+    pub duplication: f32,
+    /// Chance in 0.0-1.0 that an unreliable message is held back for `reorder_delay` on top of
+    /// its latency, so that messages sent after it overtake it.
+    pub reordering: f32,
+    /// Additional delay of reordered messages.
+    pub reorder_delay: Duration,
+}
+
+impl LinkConditions {
+    /// Sets the latency and jitter.
+    pub fn with_latency(mut self, latency: Duration, jitter: Duration) -> Self {
+        self.latency = latency;
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the chance of messages being dropped.
+    pub fn with_packet_loss(mut self, packet_loss: f32) -> Self {
+        self.packet_loss = packet_loss;
+        self
+    }
+
+    /// Sets the chance of messages being duplicated.
+    pub fn with_duplication(mut self, duplication: f32) -> Self {
+        self.duplication = duplication;
+        self
+    }
+
+    /// Sets the chance of messages being reordered and how long they are held back.
+    pub fn with_reordering(mut self, reordering: f32, reorder_delay: Duration) -> Self {
+        self.reordering = reordering;
+        self.reorder_delay = reorder_delay;
+        self
+    }
+}
+
+/// Injects latency, jitter, packet loss, duplication and reordering into the traffic of a
+/// transport. It is disabled by default.
+#[derive(Debug)]
+pub struct NetworkConditioner {
+    enabled: bool,
+    outgoing: LinkConditions,
+    incoming: LinkConditions,
+    held_outgoing: Vec<(Instant, Message)>,
+    held_incoming: Vec<(Instant, NetworkSimulationEvent)>,
+    rng: StdRng,
+}
+
+impl NetworkConditioner {
+    /// Creates a new, disabled conditioner.
+    pub fn new() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+
+    /// Creates a new, disabled conditioner whose random decisions are reproducible.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rng: StdRng) -> Self {
+        Self {
+            enabled: false,
+            outgoing: LinkConditions::default(),
+            incoming: LinkConditions::default(),
+            held_outgoing: Vec::new(),
+            held_incoming: Vec::new(),
+            rng,
+        }
+    }
+
+    /// Returns true if the conditions are applied.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables or disables the conditioner. Messages which are already held back are still
+    /// delivered once they are due.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns the conditions applied to outgoing messages.
+    pub fn outgoing(&self) -> &LinkConditions {
+        &self.outgoing
+    }
+
+    /// Sets the conditions applied to outgoing messages.
+    pub fn set_outgoing(&mut self, conditions: LinkConditions) {
+        self.outgoing = conditions;
+    }
+
+    /// Returns the conditions applied to incoming messages.
+    pub fn incoming(&self) -> &LinkConditions {
+        &self.incoming
+    }
+
+    /// Sets the conditions applied to incoming messages.
+    pub fn set_incoming(&mut self, conditions: LinkConditions) {
+        self.incoming = conditions;
+    }
+
+    /// Sets the same conditions for both directions.
+    pub fn set_conditions(&mut self, conditions: LinkConditions) {
+        self.outgoing = conditions;
+        self.incoming = conditions;
+    }
+
+    /// Returns the number of messages currently held back in both directions.
+    pub fn held_messages(&self) -> usize {
+        self.held_outgoing.len() + self.held_incoming.len()
+    }
+
+    /// Applies the outgoing conditions to the given messages and returns the messages which are
+    /// due to be sent.
+    pub(crate) fn condition_outgoing(&mut self, messages: Vec<Message>) -> Vec<Message> {
+        // The clock is only read when needed, it isn't available to wasm builds.
+        if !self.enabled && self.held_outgoing.is_empty() {
+            return messages;
+        }
+        self.condition_outgoing_at(Instant::now(), messages)
+    }
+
+    fn condition_outgoing_at(&mut self, now: Instant, messages: Vec<Message>) -> Vec<Message> {
+        if !self.enabled && self.held_outgoing.is_empty() {
+            return messages;
+        }
+        for message in messages {
+            if !self.enabled {
+                self.held_outgoing.push((now, message));
+                continue;
+            }
+            let reliable = is_reliable(message.delivery);
+            let conditions = self.outgoing;
+            let last_due = self.held_outgoing.iter().map(|(due, _)| *due).max();
+            for due in schedule(&mut self.rng, &conditions, now, reliable, last_due) {
+                let copy = Message {
+                    destination: message.destination,
+                    payload: message.payload.clone(),
+                    delivery: message.delivery,
+                    urgency: message.urgency,
+                    channel: message.channel,
+                };
+                self.held_outgoing.push((due, copy));
+            }
+        }
+        release(&mut self.held_outgoing, now)
+    }
+
+    /// Applies the incoming conditions to a received event and writes all events which are due
+    /// to the channel. `reliable` should be true if the event was received with a reliable
+    /// delivery guarantee. Only `Message` events are conditioned.
+    pub(crate) fn condition_incoming(
+        &mut self,
+        event: NetworkSimulationEvent,
+        reliable: bool,
+        channel: &mut EventChannel<NetworkSimulationEvent>,
+    ) {
+        if !self.enabled && self.held_incoming.is_empty() {
+            channel.single_write(event);
+            return;
+        }
+        let now = Instant::now();
+        self.hold_incoming_at(now, event, reliable);
+        self.release_incoming_at(now, channel);
+    }
+
+    /// Writes all held incoming events which are due to the channel.
+    pub(crate) fn release_incoming(&mut self, channel: &mut EventChannel<NetworkSimulationEvent>) {
+        if !self.held_incoming.is_empty() {
+            self.release_incoming_at(Instant::now(), channel);
+        }
+    }
+
+    fn hold_incoming_at(&mut self, now: Instant, event: NetworkSimulationEvent, reliable: bool) {
+        let (addr, payload) = match event {
+            NetworkSimulationEvent::Message(addr, payload) if self.enabled => (addr, payload),
+            event => {
+                self.held_incoming.push((now, event));
+                return;
+            }
+        };
+        let conditions = self.incoming;
+        let last_due = self.held_incoming.iter().map(|(due, _)| *due).max();
+        for due in schedule(&mut self.rng, &conditions, now, reliable, last_due) {
+            self.held_incoming
+                .push((due, NetworkSimulationEvent::Message(addr, payload.clone())));
+        }
+    }
+
+    fn release_incoming_at(
+        &mut self,
+        now: Instant,
+        channel: &mut EventChannel<NetworkSimulationEvent>,
+    ) {
+        if !self.held_incoming.is_empty() {
+            channel.iter_write(release(&mut self.held_incoming, now));
+        }
+    }
+}
+
+impl Default for NetworkConditioner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the times at which copies of a message are due, which is none if it is dropped.
+/// Reliable messages are never due before the last held message, so they keep their order.
+fn schedule(
+    rng: &mut StdRng,
+    conditions: &LinkConditions,
+    now: Instant,
+    reliable: bool,
+    last_due: Option<Instant>,
+) -> Vec<Instant> {
+    let due = now + conditions.latency;
+    if reliable {
+        return vec![last_due.map_or(due, |last_due| last_due.max(due))];
+    }
+    if rng.gen::<f32>() < conditions.packet_loss {
+        return Vec::new();
+    }
+    let copies = if rng.gen::<f32>() < conditions.duplication {
+        2
+    } else {
+        1
+    };
+    (0..copies)
+        .map(|_| {
+            let mut due = due + conditions.jitter.mul_f32(rng.gen());
+            if rng.gen::<f32>() < conditions.reordering {
+                due += conditions.reorder_delay;
+            }
+            due
+        })
+        .collect()
+}
+
+/// Removes and returns the held items which are due, in the order they are due.
+fn release<T>(held: &mut Vec<(Instant, T)>, now: Instant) -> Vec<T> {
+    // Stable sort, so items which are due at the same time keep their order.
+    held.sort_by_key(|(due, _)| *due);
+    let due = held.iter().take_while(|(due, _)| *due <= now).count();
+    held.drain(..due).map(|(_, item)| item).collect()
+}
+
+fn is_reliable(delivery: DeliveryRequirement) -> bool {
+    matches!(
+        delivery,
+        DeliveryRequirement::Reliable
+            | DeliveryRequirement::ReliableSequenced(_)
+            | DeliveryRequirement::ReliableOrdered(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::requirements::UrgencyRequirement;
+    use bytes::Bytes;
+
+    fn message(payload: &[u8], delivery: DeliveryRequirement) -> Message {
+        Message::new(
+            "127.0.0.1:3000".parse().unwrap(),
+            payload,
+            delivery,
+            UrgencyRequirement::OnTick,
+        )
+    }
+
+    #[test]
+    fn test_disabled_conditioner_passes_messages_through() {
+        let mut conditioner = NetworkConditioner::with_seed(0);
+        conditioner.set_conditions(LinkConditions::default().with_packet_loss(1.0));
+        let sent =
+            conditioner.condition_outgoing(vec![message(b"a", DeliveryRequirement::Unreliable)]);
+        assert_eq!(sent.len(), 1);
+    }
+
+    #[test]
+    fn test_latency_holds_messages_back() {
+        let mut conditioner = NetworkConditioner::with_seed(0);
+        conditioner.set_enabled(true);
+        conditioner.set_outgoing(
+            LinkConditions::default().with_latency(Duration::from_millis(100), Duration::default()),
+        );
+        let now = Instant::now();
+        let sent = conditioner
+            .condition_outgoing_at(now, vec![message(b"a", DeliveryRequirement::Unreliable)]);
+        assert!(sent.is_empty());
+        assert_eq!(conditioner.held_messages(), 1);
+        let sent = conditioner.condition_outgoing_at(now + Duration::from_millis(100), Vec::new());
+        assert_eq!(sent.len(), 1);
+    }
+
+    #[test]
+    fn test_loss_and_duplication_only_affect_unreliable_messages() {
+        let mut conditioner = NetworkConditioner::with_seed(0);
+        conditioner.set_enabled(true);
+        conditioner.set_outgoing(LinkConditions::default().with_packet_loss(1.0));
+        let sent = conditioner.condition_outgoing(vec![
+            message(b"a", DeliveryRequirement::Unreliable),
+            message(b"b", DeliveryRequirement::ReliableOrdered(None)),
+        ]);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(&sent[0].payload[..], b"b");
+
+        conditioner.set_outgoing(LinkConditions::default().with_duplication(1.0));
+        let sent =
+            conditioner.condition_outgoing(vec![message(b"a", DeliveryRequirement::Unreliable)]);
+        assert_eq!(sent.len(), 2);
+    }
+
+    #[test]
+    fn test_reordering() {
+        let mut conditioner = NetworkConditioner::with_seed(0);
+        conditioner.set_enabled(true);
+        let now = Instant::now();
+        conditioner.set_incoming(
+            LinkConditions::default().with_reordering(1.0, Duration::from_millis(10)),
+        );
+        let addr = "127.0.0.1:3000".parse().unwrap();
+        conditioner.hold_incoming_at(
+            now,
+            NetworkSimulationEvent::Message(addr, Bytes::from_static(b"first")),
+            false,
+        );
+        conditioner.set_incoming(LinkConditions::default());
+        conditioner.hold_incoming_at(
+            now,
+            NetworkSimulationEvent::Message(addr, Bytes::from_static(b"second")),
+            false,
+        );
+
+        let mut channel = EventChannel::new();
+        let mut reader = channel.register_reader();
+        conditioner.release_incoming_at(now + Duration::from_millis(10), &mut channel);
+        let payloads = channel
+            .read(&mut reader)
+            .map(|event| match event {
+                NetworkSimulationEvent::Message(_, payload) => payload.clone(),
+                _ => panic!("Unexpected event"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            payloads,
+            vec![Bytes::from_static(b"second"), Bytes::from_static(b"first")]
+        );
+    }
+}
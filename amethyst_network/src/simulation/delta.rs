@@ -0,0 +1,276 @@
+//! Delta encoding of serialized state snapshots. Instead of sending the full state every tick, a
+//! `DeltaEncoder` sends only the bytes which changed since the last snapshot the receiver
+//! acknowledged. Use one encoder and decoder pair per connection.
+//!
+//! The receiver decodes packets with a `DeltaDecoder` and sends the returned sequence number back
+//! to the sender, which passes it to `DeltaEncoder::acknowledge`. Until a snapshot has been
+//! acknowledged, full snapshots are sent.
+
+use super::compression::CompressionStats;
+use bytes::Bytes;
+use std::{collections::VecDeque, io};
+
+const NO_BASELINE: u32 = u32::MAX;
+const HEADER_LEN: usize = 12;
+
+/// Encodes snapshots as differences to the last snapshot acknowledged by the receiver.
+#[derive(Debug)]
+pub struct DeltaEncoder {
+    history: VecDeque<(u32, Bytes)>,
+    capacity: usize,
+    acknowledged: Option<u32>,
+    stats: CompressionStats,
+}
+
+impl DeltaEncoder {
+    /// Creates a new encoder remembering up to `capacity` unacknowledged snapshots. When the
+    /// acknowledged snapshot is older than that, full snapshots are sent again.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            acknowledged: None,
+            stats: CompressionStats::default(),
+        }
+    }
+
+    /// Encodes the state of the snapshot with the given sequence number, which must be increasing
+    /// over time, and returns the packet to send.
+    pub fn encode(&mut self, sequence: u32, state: &[u8]) -> Bytes {
+        let baseline = self.acknowledged.and_then(|acknowledged| {
+            self.history
+                .iter()
+                .find(|(sequence, _)| *sequence == acknowledged)
+        });
+
+        let mut packet = Vec::with_capacity(HEADER_LEN + state.len());
+        packet.extend_from_slice(&sequence.to_le_bytes());
+        match baseline {
+            Some((baseline_sequence, baseline)) => {
+                packet.extend_from_slice(&baseline_sequence.to_le_bytes());
+                packet.extend_from_slice(&(state.len() as u32).to_le_bytes());
+                encode_runs(baseline, state, &mut packet);
+            }
+            None => {
+                packet.extend_from_slice(&NO_BASELINE.to_le_bytes());
+                packet.extend_from_slice(&(state.len() as u32).to_le_bytes());
+                packet.extend_from_slice(state);
+            }
+        }
+        self.stats.record(state.len(), packet.len());
+
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back((sequence, Bytes::copy_from_slice(state)));
+        Bytes::from(packet)
+    }
+
+    /// Marks the snapshot with the given sequence number as received by the peer. Older
+    /// acknowledgements are ignored.
+    pub fn acknowledge(&mut self, sequence: u32) {
+        match self.acknowledged {
+            Some(acknowledged) if acknowledged >= sequence => {}
+            _ => self.acknowledged = Some(sequence),
+        }
+    }
+
+    /// Returns the sequence number of the last acknowledged snapshot.
+    pub fn acknowledged(&self) -> Option<u32> {
+        self.acknowledged
+    }
+
+    /// Returns how many bytes the encoded packets took compared to full snapshots.
+    pub fn stats(&self) -> CompressionStats {
+        self.stats
+    }
+}
+
+/// Decodes packets produced by a `DeltaEncoder` back into full snapshots.
+#[derive(Debug)]
+pub struct DeltaDecoder {
+    history: VecDeque<(u32, Bytes)>,
+    capacity: usize,
+}
+
+impl DeltaDecoder {
+    /// Creates a new decoder remembering up to `capacity` received snapshots. This should be at
+    /// least the capacity of the encoder on the other side.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Decodes a packet and returns its sequence number together with the full snapshot. The
+    /// sequence number should be acknowledged to the sender.
+    pub fn decode(&mut self, packet: &[u8]) -> io::Result<(u32, Bytes)> {
+        if packet.len() < HEADER_LEN {
+            return Err(invalid_data("Delta packet is missing its header"));
+        }
+        let sequence = read_u32(&packet[0..4]);
+        let baseline_sequence = read_u32(&packet[4..8]);
+        let len = read_u32(&packet[8..12]) as usize;
+        let body = &packet[HEADER_LEN..];
+
+        let state = if baseline_sequence == NO_BASELINE {
+            if body.len() != len {
+                return Err(invalid_data("Full snapshot has an unexpected length"));
+            }
+            Bytes::copy_from_slice(body)
+        } else {
+            let baseline = self
+                .history
+                .iter()
+                .find(|(sequence, _)| *sequence == baseline_sequence)
+                .map(|(_, state)| state)
+                .ok_or_else(|| invalid_data("Delta baseline is no longer known"))?;
+            Bytes::from(decode_runs(baseline, body, len)?)
+        };
+
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back((sequence, state.clone()));
+        Ok((sequence, state))
+    }
+}
+
+/// Writes the XOR difference between `baseline` and `state` as pairs of unchanged and changed
+/// runs. Each pair is the varint length of the unchanged run, the varint length of the changed run
+/// and the XORed bytes of the changed run.
+fn encode_runs(baseline: &[u8], state: &[u8], out: &mut Vec<u8>) {
+    let diff = |i: usize| state[i] ^ baseline.get(i).copied().unwrap_or(0);
+    let mut i = 0;
+    while i < state.len() {
+        let unchanged_start = i;
+        while i < state.len() && diff(i) == 0 {
+            i += 1;
+        }
+        let changed_start = i;
+        while i < state.len() && diff(i) != 0 {
+            i += 1;
+        }
+        write_varint(changed_start - unchanged_start, out);
+        write_varint(i - changed_start, out);
+        out.extend((changed_start..i).map(diff));
+    }
+}
+
+fn decode_runs(baseline: &[u8], mut body: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    let mut state = Vec::with_capacity(len);
+    state.extend((0..len).map(|i| baseline.get(i).copied().unwrap_or(0)));
+    let mut i = 0usize;
+    while !body.is_empty() {
+        i = i.saturating_add(read_varint(&mut body)?);
+        let changed = read_varint(&mut body)?;
+        if i.saturating_add(changed) > len || changed > body.len() {
+            return Err(invalid_data("Delta run is out of bounds"));
+        }
+        for (byte, delta) in state[i..i + changed].iter_mut().zip(&body[..changed]) {
+            *byte ^= delta;
+        }
+        body = &body[changed..];
+        i += changed;
+    }
+    Ok(state)
+}
+
+fn write_varint(mut value: usize, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(input: &mut &[u8]) -> io::Result<usize> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = input
+            .split_first()
+            .ok_or_else(|| invalid_data("Truncated delta run"))?;
+        *input = rest;
+        if shift >= std::mem::size_of::<usize>() * 8 {
+            return Err(invalid_data("Delta run length overflows"));
+        }
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_snapshot_until_acknowledged() {
+        let mut encoder = DeltaEncoder::new(8);
+        let mut decoder = DeltaDecoder::new(8);
+
+        let packet = encoder.encode(0, b"hello world");
+        assert_eq!(packet.len(), HEADER_LEN + 11);
+        assert_eq!(&decoder.decode(&packet).unwrap().1[..], b"hello world");
+
+        let packet = encoder.encode(1, b"hello there");
+        assert_eq!(packet.len(), HEADER_LEN + 11);
+        assert_eq!(&decoder.decode(&packet).unwrap().1[..], b"hello there");
+    }
+
+    #[test]
+    fn test_delta_against_acknowledged_snapshot() {
+        let mut encoder = DeltaEncoder::new(8);
+        let mut decoder = DeltaDecoder::new(8);
+        let mut state = vec![0u8; 256];
+
+        let (sequence, _) = decoder.decode(&encoder.encode(0, &state)).unwrap();
+        encoder.acknowledge(sequence);
+
+        state[10] = 1;
+        state[200] = 2;
+        let packet = encoder.encode(1, &state);
+        assert!(packet.len() < HEADER_LEN + 16);
+        let (sequence, decoded) = decoder.decode(&packet).unwrap();
+        assert_eq!(sequence, 1);
+        assert_eq!(&decoded[..], &state[..]);
+        assert!(encoder.stats().bytes_saved() > 200);
+    }
+
+    #[test]
+    fn test_delta_with_changing_length() {
+        let mut encoder = DeltaEncoder::new(8);
+        let mut decoder = DeltaDecoder::new(8);
+
+        decoder.decode(&encoder.encode(0, b"abcdef")).unwrap();
+        encoder.acknowledge(0);
+
+        let (_, grown) = decoder.decode(&encoder.encode(1, b"abcXefgh")).unwrap();
+        assert_eq!(&grown[..], b"abcXefgh");
+        let (_, shrunk) = decoder.decode(&encoder.encode(2, b"ab")).unwrap();
+        assert_eq!(&shrunk[..], b"ab");
+    }
+
+    #[test]
+    fn test_unknown_baseline_is_an_error() {
+        let mut encoder = DeltaEncoder::new(8);
+        let mut decoder = DeltaDecoder::new(8);
+
+        encoder.encode(0, b"abc");
+        encoder.acknowledge(0);
+        assert!(decoder.decode(&encoder.encode(1, b"abd")).is_err());
+    }
+}
@@ -0,0 +1,436 @@
+//! An alternative networking mode for deterministic games which only exchanges player inputs,
+//! e.g. for fighting or real-time strategy games. Every peer runs the full simulation and the
+//! `LockstepSession` tells the game when to advance it with which inputs.
+//!
+//! Inputs are delayed by a configurable number of frames to hide latency. Without prediction the
+//! session only advances once the inputs of all players for the next frame are known (lockstep).
+//! With prediction it guesses missing remote inputs by repeating their last input, and rolls back
+//! once the real inputs differ from the guess. The game handles this by executing the
+//! `LockstepRequest`s returned by `LockstepSession::advance_frame` in order.
+
+use crate::simulation::{
+    events::NetworkSimulationEvent,
+    rpc::{decode_message, encode_message, NetworkMessage},
+    timing::NetworkSimulationTime,
+    transport::TransportResource,
+};
+use amethyst_core::{
+    ecs::{Read, System, Write, WriteExpect},
+    shrev::{EventChannel, ReaderId},
+};
+use log::warn;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, marker::PhantomData, net::SocketAddr};
+
+/// Player input exchanged by a `LockstepSession`. Implemented for all suitable types.
+pub trait LockstepInput:
+    Clone + PartialEq + Default + Serialize + DeserializeOwned + Send + Sync + 'static
+{
+}
+
+impl<T> LockstepInput for T where
+    T: Clone + PartialEq + Default + Serialize + DeserializeOwned + Send + Sync + 'static
+{
+}
+
+/// Configuration of a `LockstepSession`, which must be the same on all peers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LockstepConfig {
+    /// Number of frames local inputs are delayed before they are applied.
+    pub input_delay: u32,
+    /// Number of frames the session may run ahead of the confirmed frame by predicting remote
+    /// inputs. Zero disables rollback and makes the session wait for all inputs.
+    pub max_prediction: u32,
+}
+
+impl Default for LockstepConfig {
+    fn default() -> Self {
+        Self {
+            input_delay: 2,
+            max_prediction: 0,
+        }
+    }
+}
+
+/// What the game has to do to follow the session, in the returned order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LockstepRequest<I> {
+    /// Save the game state before the given frame is simulated.
+    SaveState(u32),
+    /// Restore the game state saved before the given frame was simulated.
+    LoadState(u32),
+    /// Simulate the given frame with the inputs of all players, indexed by player.
+    AdvanceFrame {
+        /// The frame to simulate.
+        frame: u32,
+        /// The input of every player.
+        inputs: Vec<I>,
+    },
+}
+
+/// Packet carrying the unacknowledged inputs of the sending player.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockstepPacket<I> {
+    /// The first frame the receiver of the packet doesn't know the input of.
+    pub ack_frame: u32,
+    /// The frame of the first input.
+    pub start_frame: u32,
+    /// Inputs of consecutive frames.
+    pub inputs: Vec<I>,
+}
+
+impl<I: LockstepInput> NetworkMessage for LockstepPacket<I> {
+    const ID: u16 = 0xff00;
+}
+
+struct Player<I> {
+    addr: Option<SocketAddr>,
+    inputs: BTreeMap<u32, I>,
+    /// First frame this player's input is missing for.
+    received_until: u32,
+    /// First frame of our inputs this player hasn't acknowledged yet.
+    acked_until: u32,
+}
+
+/// Resource driving a lockstep or rollback session between a fixed set of players.
+#[allow(missing_debug_implementations)]
+pub struct LockstepSession<I> {
+    config: LockstepConfig,
+    local_player: usize,
+    players: Vec<Player<I>>,
+    current_frame: u32,
+    /// Inputs the frames which haven't been confirmed yet were simulated with.
+    simulated: BTreeMap<u32, Vec<I>>,
+    rollback_to: Option<u32>,
+}
+
+impl<I: LockstepInput> LockstepSession<I> {
+    /// Creates a new session. `players` holds the address of every player, `None` for the local
+    /// player. All peers must list the players in the same order.
+    pub fn new(config: LockstepConfig, players: Vec<Option<SocketAddr>>) -> Self {
+        let local_player = players
+            .iter()
+            .position(Option::is_none)
+            .expect("One of the players must be the local player");
+        let players = players
+            .into_iter()
+            .map(|addr| Player {
+                addr,
+                // Nobody has inputs for the delayed frames at the start of the session.
+                inputs: (0..config.input_delay)
+                    .map(|frame| (frame, I::default()))
+                    .collect(),
+                received_until: config.input_delay,
+                acked_until: config.input_delay,
+            })
+            .collect();
+        Self {
+            config,
+            local_player,
+            players,
+            current_frame: 0,
+            simulated: BTreeMap::new(),
+            rollback_to: None,
+        }
+    }
+
+    /// Returns the configuration of the session.
+    pub fn config(&self) -> &LockstepConfig {
+        &self.config
+    }
+
+    /// Returns the index of the local player.
+    pub fn local_player(&self) -> usize {
+        self.local_player
+    }
+
+    /// Returns the next frame which will be simulated.
+    pub fn current_frame(&self) -> u32 {
+        self.current_frame
+    }
+
+    /// Returns the first frame whose inputs aren't known for all players yet. All frames before
+    /// it are final and will never be rolled back.
+    pub fn confirmed_frame(&self) -> u32 {
+        self.players
+            .iter()
+            .map(|player| player.received_until)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Sets the input of the local player for the current frame, which is applied after the
+    /// input delay. Call it once before every call to `advance_frame`.
+    pub fn add_local_input(&mut self, input: I) {
+        let frame = self.current_frame + self.config.input_delay;
+        let player = &mut self.players[self.local_player];
+        player.inputs.insert(frame, input);
+        advance_received(player);
+    }
+
+    /// Advances the session by one frame if possible and returns what the game has to do.
+    /// Returns no `AdvanceFrame` request if the session has to wait for remote inputs.
+    pub fn advance_frame(&mut self) -> Vec<LockstepRequest<I>> {
+        let mut requests = Vec::new();
+        if let Some(start) = self.rollback_to.take() {
+            requests.push(LockstepRequest::LoadState(start));
+            for frame in start..self.current_frame {
+                if frame != start {
+                    requests.push(LockstepRequest::SaveState(frame));
+                }
+                requests.push(self.simulate(frame));
+            }
+        }
+
+        if self.current_frame < self.confirmed_frame() + self.config.max_prediction {
+            if self.config.max_prediction > 0 {
+                requests.push(LockstepRequest::SaveState(self.current_frame));
+            }
+            requests.push(self.simulate(self.current_frame));
+            self.current_frame += 1;
+        }
+
+        self.forget_confirmed_frames();
+        requests
+    }
+
+    /// Handles a packet received from the given peer.
+    pub fn handle_packet(&mut self, from: SocketAddr, packet: LockstepPacket<I>) {
+        let index = match self.players.iter().position(|p| p.addr == Some(from)) {
+            Some(index) => index,
+            None => {
+                warn!("Dropping lockstep inputs from unknown peer {}", from);
+                return;
+            }
+        };
+        let current_frame = self.current_frame;
+        let player = &mut self.players[index];
+        player.acked_until = player.acked_until.max(packet.ack_frame);
+        for (frame, input) in (packet.start_frame..).zip(packet.inputs) {
+            if frame < player.received_until || player.inputs.contains_key(&frame) {
+                continue;
+            }
+            let mispredicted = match self.simulated.get(&frame) {
+                Some(inputs) => frame < current_frame && inputs[index] != input,
+                None => false,
+            };
+            if mispredicted {
+                self.rollback_to = Some(self.rollback_to.map_or(frame, |f| f.min(frame)));
+            }
+            player.inputs.insert(frame, input);
+        }
+        advance_received(player);
+    }
+
+    /// Returns the packets to send to the remote players, containing all local inputs they
+    /// haven't acknowledged yet.
+    pub fn outgoing_packets(&self) -> Vec<(SocketAddr, LockstepPacket<I>)> {
+        let local = &self.players[self.local_player];
+        self.players
+            .iter()
+            .filter_map(|player| {
+                player.addr.map(|addr| {
+                    let inputs = local
+                        .inputs
+                        .range(player.acked_until..local.received_until)
+                        .map(|(_, input)| input.clone())
+                        .collect();
+                    let packet = LockstepPacket {
+                        ack_frame: player.received_until,
+                        start_frame: player.acked_until,
+                        inputs,
+                    };
+                    (addr, packet)
+                })
+            })
+            .collect()
+    }
+
+    /// Drops the inputs which are neither needed to simulate unconfirmed frames nor to resend
+    /// them. The last confirmed input is kept to predict the next ones.
+    fn forget_confirmed_frames(&mut self) {
+        let confirmed = self.confirmed_frame();
+        self.simulated = self.simulated.split_off(&confirmed);
+        let resend_from = self
+            .players
+            .iter()
+            .filter(|player| player.addr.is_some())
+            .map(|player| player.acked_until)
+            .min()
+            .unwrap_or(confirmed);
+        for (index, player) in self.players.iter_mut().enumerate() {
+            let keep_from = if index == self.local_player {
+                confirmed.min(resend_from)
+            } else {
+                confirmed
+            };
+            player.inputs = player.inputs.split_off(&keep_from.saturating_sub(1));
+        }
+    }
+
+    fn simulate(&mut self, frame: u32) -> LockstepRequest<I> {
+        let inputs = self
+            .players
+            .iter()
+            .map(|player| match player.inputs.get(&frame) {
+                Some(input) => input.clone(),
+                // Predict that the player keeps doing what they did last.
+                None => player
+                    .inputs
+                    .range(..frame)
+                    .next_back()
+                    .map(|(_, input)| input.clone())
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        self.simulated.insert(frame, inputs.clone());
+        LockstepRequest::AdvanceFrame { frame, inputs }
+    }
+}
+
+fn advance_received<I>(player: &mut Player<I>) {
+    while player.inputs.contains_key(&player.received_until) {
+        player.received_until += 1;
+    }
+}
+
+/// System exchanging the inputs of a `LockstepSession` over the `TransportResource`. The game
+/// inserts the session resource and calls `advance_frame` itself.
+#[allow(missing_debug_implementations)]
+pub struct LockstepNetworkSystem<I> {
+    reader: ReaderId<NetworkSimulationEvent>,
+    _marker: PhantomData<I>,
+}
+
+impl<I: LockstepInput> LockstepNetworkSystem<I> {
+    pub fn new(reader: ReaderId<NetworkSimulationEvent>) -> Self {
+        Self {
+            reader,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'s, I: LockstepInput> System<'s> for LockstepNetworkSystem<I> {
+    type SystemData = (
+        WriteExpect<'s, LockstepSession<I>>,
+        Write<'s, TransportResource>,
+        Read<'s, NetworkSimulationTime>,
+        Read<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (mut session, mut transport, sim_time, events): Self::SystemData) {
+        for event in events.read(&mut self.reader) {
+            if let NetworkSimulationEvent::Message(from, payload) = event {
+                match decode_message::<LockstepPacket<I>>(payload) {
+                    Some(Ok(packet)) => session.handle_packet(*from, packet),
+                    Some(Err(e)) => warn!("Dropping malformed lockstep inputs: {}", e),
+                    None => {}
+                }
+            }
+        }
+
+        if sim_time.should_send_message_now() {
+            for (addr, packet) in session.outgoing_packets() {
+                match encode_message(&packet) {
+                    Ok(payload) => transport.send(addr, &payload),
+                    Err(e) => warn!("Failed to encode lockstep inputs: {}", e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn advanced_frames(requests: &[LockstepRequest<u8>]) -> Vec<(u32, Vec<u8>)> {
+        requests
+            .iter()
+            .filter_map(|request| match request {
+                LockstepRequest::AdvanceFrame { frame, inputs } => Some((*frame, inputs.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Delivers the packets of `from` to `to`.
+    fn exchange(from: &LockstepSession<u8>, from_addr: SocketAddr, to: &mut LockstepSession<u8>) {
+        for (_, packet) in from.outgoing_packets() {
+            to.handle_packet(from_addr, packet);
+        }
+    }
+
+    #[test]
+    fn test_lockstep_waits_for_remote_inputs() {
+        let config = LockstepConfig {
+            input_delay: 1,
+            max_prediction: 0,
+        };
+        let mut a = LockstepSession::<u8>::new(config, vec![None, Some(addr(2))]);
+        let mut b = LockstepSession::<u8>::new(config, vec![Some(addr(1)), None]);
+
+        // Frame 0 is covered by the input delay.
+        a.add_local_input(1);
+        assert_eq!(advanced_frames(&a.advance_frame()), vec![(0, vec![0, 0])]);
+        a.add_local_input(1);
+        assert!(advanced_frames(&a.advance_frame()).is_empty());
+
+        b.add_local_input(2);
+        b.advance_frame();
+        exchange(&b, addr(2), &mut a);
+        assert_eq!(advanced_frames(&a.advance_frame()), vec![(1, vec![1, 2])]);
+        assert_eq!(a.confirmed_frame(), 2);
+    }
+
+    #[test]
+    fn test_rollback_on_misprediction() {
+        let config = LockstepConfig {
+            input_delay: 0,
+            max_prediction: 4,
+        };
+        let mut a = LockstepSession::<u8>::new(config, vec![None, Some(addr(2))]);
+        let mut b = LockstepSession::<u8>::new(config, vec![Some(addr(1)), None]);
+
+        b.add_local_input(5);
+        b.advance_frame();
+        exchange(&b, addr(2), &mut a);
+
+        a.add_local_input(1);
+        assert_eq!(advanced_frames(&a.advance_frame()), vec![(0, vec![1, 5])]);
+        // The input of b for frame 1 is predicted to be the same as for frame 0.
+        a.add_local_input(1);
+        assert_eq!(advanced_frames(&a.advance_frame()), vec![(1, vec![1, 5])]);
+
+        b.add_local_input(7);
+        b.advance_frame();
+        exchange(&b, addr(2), &mut a);
+
+        a.add_local_input(1);
+        let requests = a.advance_frame();
+        assert_eq!(requests[0], LockstepRequest::LoadState(1));
+        assert_eq!(
+            advanced_frames(&requests),
+            vec![(1, vec![1, 7]), (2, vec![1, 7])]
+        );
+        assert!(requests.contains(&LockstepRequest::SaveState(2)));
+    }
+
+    #[test]
+    fn test_acknowledged_inputs_are_not_resent() {
+        let config = LockstepConfig::default();
+        let mut a = LockstepSession::<u8>::new(config, vec![None, Some(addr(2))]);
+        let mut b = LockstepSession::<u8>::new(config, vec![Some(addr(1)), None]);
+
+        a.add_local_input(1);
+        assert_eq!(a.outgoing_packets()[0].1.inputs, vec![1]);
+        exchange(&a, addr(1), &mut b);
+        exchange(&b, addr(2), &mut a);
+        assert!(a.outgoing_packets()[0].1.inputs.is_empty());
+    }
+}
@@ -1,4 +1,7 @@
-use super::requirements::{DeliveryRequirement, UrgencyRequirement};
+use super::{
+    channel::ChannelId,
+    requirements::{DeliveryRequirement, UrgencyRequirement},
+};
 use bytes::Bytes;
 use std::net::SocketAddr;
 
@@ -14,6 +17,8 @@ pub struct Message {
     pub delivery: DeliveryRequirement,
     /// The requirement around when this message should be sent.
     pub urgency: UrgencyRequirement,
+    /// The virtual channel this message was sent on, if any.
+    pub(crate) channel: Option<ChannelId>,
 }
 
 impl Message {
@@ -29,6 +34,12 @@ impl Message {
             payload: Bytes::copy_from_slice(payload),
             delivery,
             urgency,
+            channel: None,
         }
     }
+
+    /// Returns the virtual channel this message was sent on, if any.
+    pub fn channel(&self) -> Option<ChannelId> {
+        self.channel
+    }
 }
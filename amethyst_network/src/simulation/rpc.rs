@@ -0,0 +1,299 @@
+//! Typed messages routed to per-type event channels, so games don't have to slice incoming
+//! payloads by hand. Every message type implements `NetworkMessage` with an id which is unique
+//! among the message types of the game and the same on all peers. Messages are sent with the
+//! `MessageSender` system data and received from an `EventChannel<Received<T>>` once the type has
+//! been registered with the `NetworkMessageBundle`.
+//!
+//! A typed message payload is the little endian id followed by the message serialized with
+//! bincode.
+
+use crate::simulation::{
+    authority::{AuthoritySystem, EntityMessage},
+    channel::ChannelId,
+    events::NetworkSimulationEvent,
+    requirements::{DeliveryRequirement, UrgencyRequirement},
+    transport::{TransportResource, NETWORK_RECV_SYSTEM_NAME},
+};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{
+        shred::{ResourceId, SystemData},
+        DispatcherBuilder, Read, System, World, Write,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+use amethyst_error::Error;
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{marker::PhantomData, net::SocketAddr};
+
+/// A message type which can be sent over the network.
+pub trait NetworkMessage: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Id identifying the message type on the wire. It must be unique among all message types
+    /// and the same on all peers. Ids from `0xff00` on are reserved for this crate.
+    const ID: u16;
+}
+
+/// A typed message received from a peer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Received<T> {
+    /// The peer which sent the message.
+    pub from: SocketAddr,
+    /// The message itself.
+    pub message: T,
+}
+
+/// Serializes a typed message into a payload which can be handed to the `TransportResource`.
+pub fn encode_message<T: NetworkMessage>(message: &T) -> Result<Vec<u8>, Error> {
+    let mut payload = T::ID.to_le_bytes().to_vec();
+    bincode::serialize_into(&mut payload, message).map_err(Error::new)?;
+    Ok(payload)
+}
+
+/// Deserializes a payload if it holds a typed message of type `T`. Returns `None` if the payload
+/// holds a message of another type.
+pub fn decode_message<T: NetworkMessage>(payload: &[u8]) -> Option<Result<T, Error>> {
+    if payload.len() < 2 || u16::from_le_bytes([payload[0], payload[1]]) != T::ID {
+        return None;
+    }
+    Some(bincode::deserialize(&payload[2..]).map_err(Error::new))
+}
+
+/// System data to send typed messages.
+#[derive(SystemData)]
+#[allow(missing_debug_implementations)]
+pub struct MessageSender<'a> {
+    transport: Write<'a, TransportResource>,
+}
+
+impl<'a> MessageSender<'a> {
+    /// Sends the message with the default requirements of the transport.
+    pub fn send<T: NetworkMessage>(&mut self, peer: SocketAddr, message: &T) -> Result<(), Error> {
+        let payload = encode_message(message)?;
+        self.transport.send(peer, &payload);
+        Ok(())
+    }
+
+    /// Sends the message on the given virtual channel.
+    pub fn send_on_channel<T: NetworkMessage>(
+        &mut self,
+        peer: SocketAddr,
+        channel: ChannelId,
+        message: &T,
+    ) -> Result<(), Error> {
+        let payload = encode_message(message)?;
+        self.transport.send_on_channel(peer, channel, &payload);
+        Ok(())
+    }
+
+    /// Sends the message with the given requirements.
+    pub fn send_with_requirements<T: NetworkMessage>(
+        &mut self,
+        peer: SocketAddr,
+        message: &T,
+        delivery: DeliveryRequirement,
+        urgency: UrgencyRequirement,
+    ) -> Result<(), Error> {
+        let payload = encode_message(message)?;
+        self.transport
+            .send_with_requirements(peer, &payload, delivery, urgency);
+        Ok(())
+    }
+}
+
+/// Registers the routing systems of all typed messages of a game. Add it after the transport
+/// bundle.
+#[allow(missing_debug_implementations)]
+#[derive(Default)]
+pub struct NetworkMessageBundle {
+    registrations: Vec<Registration>,
+}
+
+type Registration = Box<dyn FnOnce(&mut World, &mut DispatcherBuilder<'_, '_>)>;
+
+impl NetworkMessageBundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes messages of type `T` to the `EventChannel<Received<T>>`.
+    pub fn with_message<T: NetworkMessage>(mut self) -> Self {
+        self.registrations.push(Box::new(|world, builder| {
+            let reader = world
+                .entry::<EventChannel<NetworkSimulationEvent>>()
+                .or_insert_with(EventChannel::new)
+                .register_reader();
+            world
+                .entry::<EventChannel<Received<T>>>()
+                .or_insert_with(EventChannel::new);
+            builder.add(
+                NetworkMessageSystem::<T>::new(reader),
+                &format!("network_message_{}", T::ID),
+                &[NETWORK_RECV_SYSTEM_NAME],
+            );
+        }));
+        self
+    }
+
+    /// Routes messages of type `T` to the `EventChannel<Received<T>>`, and the messages whose
+    /// sender owns the changed entity to the `EventChannel<Authorized<T>>`. This is meant for the
+    /// server, clients accept all changes made by the server.
+    pub fn with_entity_message<T: EntityMessage + Clone>(mut self) -> Self {
+        self = self.with_message::<T>();
+        self.registrations.push(Box::new(|world, builder| {
+            let reader = world
+                .entry::<EventChannel<Received<T>>>()
+                .or_insert_with(EventChannel::new)
+                .register_reader();
+            builder.add(
+                AuthoritySystem::<T>::new(reader),
+                &format!("network_authority_{}", T::ID),
+                &[&format!("network_message_{}", T::ID)],
+            );
+        }));
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for NetworkMessageBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        for registration in self.registrations {
+            registration(world, builder);
+        }
+        Ok(())
+    }
+}
+
+/// System routing received messages of type `T` to the `EventChannel<Received<T>>`.
+#[allow(missing_debug_implementations)]
+pub struct NetworkMessageSystem<T> {
+    reader: ReaderId<NetworkSimulationEvent>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: NetworkMessage> NetworkMessageSystem<T> {
+    pub fn new(reader: ReaderId<NetworkSimulationEvent>) -> Self {
+        Self {
+            reader,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'s, T: NetworkMessage> System<'s> for NetworkMessageSystem<T> {
+    type SystemData = (
+        Read<'s, EventChannel<NetworkSimulationEvent>>,
+        Write<'s, EventChannel<Received<T>>>,
+    );
+
+    fn run(&mut self, (events, mut received): Self::SystemData) {
+        for event in events.read(&mut self.reader) {
+            if let NetworkSimulationEvent::Message(from, payload) = event {
+                match decode_message::<T>(payload) {
+                    Some(Ok(message)) => received.single_write(Received {
+                        from: *from,
+                        message,
+                    }),
+                    Some(Err(e)) => {
+                        warn!("Dropping malformed message {} from {}: {}", T::ID, from, e)
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{RunNow, WorldExt};
+    use bytes::Bytes;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Chat {
+        text: String,
+    }
+
+    impl NetworkMessage for Chat {
+        const ID: u16 = 1;
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Move {
+        x: f32,
+        y: f32,
+    }
+
+    impl NetworkMessage for Move {
+        const ID: u16 = 2;
+    }
+
+    #[test]
+    fn test_messages_are_routed_by_type() {
+        let addr = "127.0.0.1:3000".parse().unwrap();
+        let mut world = World::new();
+        world.insert(TransportResource::default());
+        let mut events = EventChannel::<NetworkSimulationEvent>::new();
+        let mut chat_system = NetworkMessageSystem::<Chat>::new(events.register_reader());
+        let mut move_system = NetworkMessageSystem::<Move>::new(events.register_reader());
+        world.insert(events);
+        world.insert(EventChannel::<Received<Chat>>::new());
+        world.insert(EventChannel::<Received<Move>>::new());
+        let mut chat_reader = world
+            .fetch_mut::<EventChannel<Received<Chat>>>()
+            .register_reader();
+        let mut move_reader = world
+            .fetch_mut::<EventChannel<Received<Move>>>()
+            .register_reader();
+
+        {
+            let mut sender = MessageSender::fetch(&world);
+            sender
+                .send(
+                    addr,
+                    &Chat {
+                        text: "hello".to_string(),
+                    },
+                )
+                .unwrap();
+            sender.send(addr, &Move { x: 1.0, y: 2.0 }).unwrap();
+        }
+        // Loop the sent messages back as received ones.
+        let sent = world
+            .fetch_mut::<TransportResource>()
+            .drain_messages(|_| true);
+        world
+            .fetch_mut::<EventChannel<NetworkSimulationEvent>>()
+            .iter_write(
+                sent.into_iter()
+                    .map(|message| NetworkSimulationEvent::Message(addr, message.payload)),
+            );
+        chat_system.run_now(&world);
+        move_system.run_now(&world);
+
+        let chats = world.fetch::<EventChannel<Received<Chat>>>();
+        let chats = chats.read(&mut chat_reader).collect::<Vec<_>>();
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chats[0].message.text, "hello");
+        assert_eq!(chats[0].from, addr);
+        let moves = world.fetch::<EventChannel<Received<Move>>>();
+        let moves = moves.read(&mut move_reader).collect::<Vec<_>>();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].message, Move { x: 1.0, y: 2.0 });
+    }
+
+    #[test]
+    fn test_decode_ignores_other_types() {
+        let payload = encode_message(&Move { x: 0.0, y: 0.0 }).unwrap();
+        assert!(decode_message::<Chat>(&payload).is_none());
+        assert!(decode_message::<Chat>(&Bytes::from_static(&[1, 0, 255]))
+            .unwrap()
+            .is_err());
+    }
+}
@@ -0,0 +1,925 @@
+//! Encrypted and authenticated connections on top of any transport. Peers perform an X25519 key
+//! exchange and afterwards only exchange ChaCha20-Poly1305 encrypted packets. The handshake can be
+//! authenticated with a pre-shared key and/or a static server key pinned by the clients, which
+//! plays the role of a server certificate. Before a client is promoted to "joined", the server
+//! passes the token presented by the client to an `Authenticator`.
+//!
+//! Every handshake packet past the first hello is authenticated with the derived keys and sessions
+//! only change once a packet authenticated, so forged packets can't abort a handshake or
+//! disconnect a joined peer. A joined peer is replaced once it completes a new handshake.
+//!
+//! Handshake packets are sent with the default delivery requirement of the transport, so on plain
+//! UDP a lost handshake packet has to be retried by calling `SecureSessions::connect` again.
+
+use crate::simulation::{
+    events::NetworkSimulationEvent,
+    requirements::{DeliveryRequirement, UrgencyRequirement},
+    transport::{TransportResource, NETWORK_RECV_SYSTEM_NAME},
+};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{DispatcherBuilder, Read, System, World, Write},
+    shrev::{EventChannel, ReaderId},
+};
+use amethyst_error::Error;
+use bytes::Bytes;
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hkdf::Hkdf;
+use log::warn;
+use rand_core::OsRng;
+use sha2::Sha256;
+use std::{collections::HashMap, fmt, io, net::SocketAddr};
+use x25519_dalek::EphemeralSecret;
+pub use x25519_dalek::{PublicKey, StaticSecret};
+
+const SECURE_SESSION_SYSTEM_NAME: &str = "secure_session";
+
+const CLIENT_HELLO: u8 = 1;
+const SERVER_HELLO: u8 = 2;
+const CLIENT_AUTH: u8 = 3;
+const ACCEPT: u8 = 4;
+const REJECT: u8 = 5;
+const DATA: u8 = 6;
+
+// Nonces 0 and 1 are used by the handshake, data packets count up from here.
+const FIRST_DATA_NONCE: u64 = 2;
+// Handshakes a server keeps per peer until the client authenticates one of them, so that forged
+// hellos can't replace the handshake of the actual client.
+const MAX_PENDING_HANDSHAKES: usize = 4;
+const REPLAY_WINDOW: u64 = 64;
+const KEY_INFO: &[u8] = b"amethyst_network secure session";
+
+/// Use this bundle after one of the transport bundles to encrypt and authenticate all traffic.
+pub struct SecureNetworkBundle {
+    config: SecurityConfig,
+    authenticator: Option<Box<dyn Authenticator>>,
+}
+
+impl SecureNetworkBundle {
+    pub fn new(config: SecurityConfig) -> Self {
+        Self {
+            config,
+            authenticator: None,
+        }
+    }
+
+    /// Sets the hook deciding whether a client presenting a token may join.
+    pub fn with_authenticator(mut self, authenticator: impl Authenticator) -> Self {
+        self.authenticator = Some(Box::new(authenticator));
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for SecureNetworkBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        let reader = world
+            .entry::<EventChannel<NetworkSimulationEvent>>()
+            .or_insert_with(EventChannel::new)
+            .register_reader();
+        builder.add(
+            SecureSessionSystem::new(reader),
+            SECURE_SESSION_SYSTEM_NAME,
+            &[NETWORK_RECV_SYSTEM_NAME],
+        );
+
+        let mut sessions = SecureSessions::new(self.config);
+        if let Some(authenticator) = self.authenticator {
+            sessions.authenticator = authenticator;
+        }
+        world.insert(sessions);
+        Ok(())
+    }
+}
+
+/// Keys and credentials used to secure connections.
+#[derive(Default)]
+pub struct SecurityConfig {
+    /// A key known to all peers. Peers using a different key fail the handshake.
+    pub pre_shared_key: Option<[u8; 32]>,
+    /// The static key of a server. Its public key has to be handed to all clients as their
+    /// `trusted_server_key`, they then only connect to the server owning the secret key.
+    pub identity: Option<StaticSecret>,
+    /// The public key of the server a client expects to talk to.
+    pub trusted_server_key: Option<PublicKey>,
+    /// The token a client presents to the server's `Authenticator`, e.g. a login session token.
+    pub auth_token: Vec<u8>,
+}
+
+impl fmt::Debug for SecurityConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecurityConfig")
+            .field("pre_shared_key", &self.pre_shared_key.map(|_| ".."))
+            .field("identity", &self.identity.as_ref().map(|_| ".."))
+            .field("trusted_server_key", &self.trusted_server_key)
+            .field("auth_token", &"..")
+            .finish()
+    }
+}
+
+/// Hook deciding whether a client presenting the given token is allowed to join. Closures with the
+/// same signature implement this trait.
+pub trait Authenticator: Send + Sync + 'static {
+    /// Returns true if the client at `peer` may join.
+    fn authenticate(&self, peer: SocketAddr, token: &[u8]) -> bool;
+}
+
+impl<F> Authenticator for F
+where
+    F: Fn(SocketAddr, &[u8]) -> bool + Send + Sync + 'static,
+{
+    fn authenticate(&self, peer: SocketAddr, token: &[u8]) -> bool {
+        self(peer, token)
+    }
+}
+
+/// Events emitted by the `SecureSessionSystem`.
+#[derive(Debug)]
+pub enum SecureNetworkEvent {
+    /// The handshake with the peer completed and it may exchange messages.
+    Joined(SocketAddr),
+    /// A decrypted message was received from a joined peer.
+    Message(SocketAddr, Bytes),
+    /// The server rejected our token, or we rejected the peer's token.
+    Rejected(SocketAddr),
+    /// A handshake packet of the peer was dropped, e.g. because of mismatching keys. The pending
+    /// handshake is kept, since the packet could have been forged by somebody else; call
+    /// `SecureSessions::disconnect` to give up on the peer.
+    HandshakeError(SocketAddr, io::Error),
+    /// The underlying connection to a peer was closed.
+    Disconnected(SocketAddr),
+}
+
+/// Resource owning the keys of all secure sessions. Use it to connect to a server and to send
+/// encrypted messages.
+pub struct SecureSessions {
+    config: SecurityConfig,
+    authenticator: Box<dyn Authenticator>,
+    sessions: HashMap<SocketAddr, Session>,
+    // Keys of the handshakes started by clients, until one of them authenticates.
+    pending: HashMap<SocketAddr, Vec<SessionKeys>>,
+}
+
+impl SecureSessions {
+    /// Creates a new resource accepting all clients which complete the handshake.
+    pub fn new(config: SecurityConfig) -> Self {
+        Self {
+            config,
+            authenticator: Box::new(|_: SocketAddr, _: &[u8]| true),
+            sessions: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Starts the handshake with a server.
+    pub fn connect(&mut self, transport: &mut TransportResource, server: SocketAddr) {
+        // A fresh secret per connection; it is only kept as a `StaticSecret` because the client
+        // performs a second key exchange with the pinned server key.
+        let secret = StaticSecret::new(OsRng);
+        let public = PublicKey::from(&secret);
+        send_handshake(transport, server, CLIENT_HELLO, public.as_bytes());
+        self.sessions
+            .insert(server, Session::AwaitingServerHello { secret, public });
+    }
+
+    /// Returns true if the handshake with the peer completed.
+    pub fn is_joined(&self, peer: SocketAddr) -> bool {
+        matches!(self.sessions.get(&peer), Some(Session::Joined(_)))
+    }
+
+    /// Returns the addresses of all joined peers.
+    pub fn joined_peers(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.sessions
+            .iter()
+            .filter(|(_, session)| matches!(session, Session::Joined(_)))
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Forgets the keys of the given peer.
+    pub fn disconnect(&mut self, peer: SocketAddr) {
+        self.forget(peer);
+    }
+
+    /// Forgets the keys of the given peer, returning true if a session with it was started.
+    fn forget(&mut self, peer: SocketAddr) -> bool {
+        let pending = self.pending.remove(&peer).is_some();
+        self.sessions.remove(&peer).is_some() || pending
+    }
+
+    /// Encrypts the payload and queues it with the default requirements of the transport.
+    pub fn send(
+        &mut self,
+        transport: &mut TransportResource,
+        peer: SocketAddr,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        self.send_with_requirements(
+            transport,
+            peer,
+            payload,
+            DeliveryRequirement::Default,
+            UrgencyRequirement::OnTick,
+        )
+    }
+
+    /// Encrypts the payload and queues it with the given requirements. Fails if the handshake
+    /// with the peer hasn't completed yet.
+    pub fn send_with_requirements(
+        &mut self,
+        transport: &mut TransportResource,
+        peer: SocketAddr,
+        payload: &[u8],
+        delivery: DeliveryRequirement,
+        urgency: UrgencyRequirement,
+    ) -> io::Result<()> {
+        let keys = match self.sessions.get_mut(&peer) {
+            Some(Session::Joined(keys)) => keys,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "The secure handshake with the peer hasn't completed",
+                ))
+            }
+        };
+        let nonce = keys.next_nonce();
+        let mut packet = Vec::with_capacity(payload.len() + 25);
+        packet.push(DATA);
+        packet.extend_from_slice(&nonce.to_le_bytes());
+        packet.extend_from_slice(&seal(&keys.send, nonce, payload)?);
+        transport.send_with_requirements(peer, &packet, delivery, urgency);
+        Ok(())
+    }
+
+    fn handle(
+        &mut self,
+        transport: &mut TransportResource,
+        peer: SocketAddr,
+        packet: &[u8],
+    ) -> Result<Option<SecureNetworkEvent>, io::Error> {
+        let (&kind, body) = packet
+            .split_first()
+            .ok_or_else(|| invalid_data("Empty secure packet"))?;
+        // Sessions are only changed once a packet authenticated, so that forged or garbled
+        // packets can't abort handshakes or disconnect joined peers.
+        match kind {
+            CLIENT_HELLO => {
+                let client_public = read_public_key(body)?;
+                let secret = EphemeralSecret::new(OsRng);
+                let server_public = PublicKey::from(&secret);
+                let mut ikm = secret.diffie_hellman(&client_public).to_bytes().to_vec();
+                if let Some(identity) = self.config.identity.as_ref() {
+                    ikm.extend_from_slice(identity.diffie_hellman(&client_public).as_bytes());
+                }
+                let keys = self.derive_keys(&ikm, &client_public, &server_public, false);
+
+                let mut reply = server_public.as_bytes().to_vec();
+                reply.extend_from_slice(&seal(&keys.send, 0, &[])?);
+                send_handshake(transport, peer, SERVER_HELLO, &reply);
+                let pending = self.pending.entry(peer).or_default();
+                if pending.len() == MAX_PENDING_HANDSHAKES {
+                    pending.remove(0);
+                }
+                pending.push(keys);
+                Ok(None)
+            }
+            SERVER_HELLO => {
+                let keys = match self.sessions.get(&peer) {
+                    Some(Session::AwaitingServerHello { secret, public }) => {
+                        let server_public = read_public_key(body)?;
+                        let mut ikm = secret.diffie_hellman(&server_public).to_bytes().to_vec();
+                        if let Some(trusted) = self.config.trusted_server_key.as_ref() {
+                            ikm.extend_from_slice(secret.diffie_hellman(trusted).as_bytes());
+                        }
+                        let keys = self.derive_keys(&ikm, public, &server_public, true);
+                        open(&keys.receive, 0, &body[32..])?;
+                        keys
+                    }
+                    _ => return Err(unexpected_packet()),
+                };
+
+                let token = seal(&keys.send, 0, &self.config.auth_token)?;
+                send_handshake(transport, peer, CLIENT_AUTH, &token);
+                self.sessions.insert(peer, Session::AwaitingAccept(keys));
+                Ok(None)
+            }
+            CLIENT_AUTH => {
+                let pending = self.pending.get(&peer).ok_or_else(unexpected_packet)?;
+                let (index, token) = pending
+                    .iter()
+                    .enumerate()
+                    .find_map(|(index, keys)| Some((index, open(&keys.receive, 0, body).ok()?)))
+                    .ok_or_else(|| invalid_data("Secure packet failed authentication"))?;
+                let keys = self
+                    .pending
+                    .remove(&peer)
+                    .expect("Pending handshakes were just read")
+                    .swap_remove(index);
+
+                if self.authenticator.authenticate(peer, &token) {
+                    send_handshake(transport, peer, ACCEPT, &seal(&keys.send, 1, &[])?);
+                    self.sessions.insert(peer, Session::Joined(keys));
+                    Ok(Some(SecureNetworkEvent::Joined(peer)))
+                } else {
+                    send_handshake(transport, peer, REJECT, &seal(&keys.send, 1, &[])?);
+                    Ok(Some(SecureNetworkEvent::Rejected(peer)))
+                }
+            }
+            ACCEPT | REJECT => {
+                match self.sessions.get(&peer) {
+                    Some(Session::AwaitingAccept(keys)) => open(&keys.receive, 1, body)?,
+                    _ => return Err(unexpected_packet()),
+                };
+                match self.sessions.remove(&peer) {
+                    Some(Session::AwaitingAccept(keys)) if kind == ACCEPT => {
+                        self.sessions.insert(peer, Session::Joined(keys));
+                        Ok(Some(SecureNetworkEvent::Joined(peer)))
+                    }
+                    _ => Ok(Some(SecureNetworkEvent::Rejected(peer))),
+                }
+            }
+            DATA => {
+                let keys = match self.sessions.get_mut(&peer) {
+                    Some(Session::Joined(keys)) => keys,
+                    _ => return Err(unexpected_packet()),
+                };
+                if body.len() < 8 {
+                    return Err(invalid_data("Secure packet is missing its nonce"));
+                }
+                let (nonce, ciphertext) = body.split_at(8);
+                let mut nonce_bytes = [0; 8];
+                nonce_bytes.copy_from_slice(nonce);
+                let nonce = u64::from_le_bytes(nonce_bytes);
+                if !keys.replay.check(nonce) {
+                    return Err(invalid_data("Replayed secure packet"));
+                }
+                let payload = open(&keys.receive, nonce, ciphertext)?;
+                keys.replay.accept(nonce);
+                Ok(Some(SecureNetworkEvent::Message(
+                    peer,
+                    Bytes::from(payload),
+                )))
+            }
+            _ => Err(unexpected_packet()),
+        }
+    }
+
+    fn derive_keys(
+        &self,
+        ikm: &[u8],
+        client: &PublicKey,
+        server: &PublicKey,
+        is_client: bool,
+    ) -> SessionKeys {
+        let salt = self.config.pre_shared_key.unwrap_or([0; 32]);
+        let mut info = KEY_INFO.to_vec();
+        info.extend_from_slice(client.as_bytes());
+        info.extend_from_slice(server.as_bytes());
+        let mut okm = [0; 64];
+        Hkdf::<Sha256>::new(Some(&salt), ikm)
+            .expand(&info, &mut okm)
+            .expect("64 bytes is a valid HKDF-SHA256 output length");
+        let mut client_to_server = [0; 32];
+        let mut server_to_client = [0; 32];
+        client_to_server.copy_from_slice(&okm[..32]);
+        server_to_client.copy_from_slice(&okm[32..]);
+        let (send, receive) = if is_client {
+            (client_to_server, server_to_client)
+        } else {
+            (server_to_client, client_to_server)
+        };
+        SessionKeys {
+            send: ChaCha20Poly1305::new(&Key::from(send)),
+            receive: ChaCha20Poly1305::new(&Key::from(receive)),
+            next_nonce: FIRST_DATA_NONCE,
+            replay: ReplayWindow::default(),
+        }
+    }
+}
+
+impl Default for SecureSessions {
+    fn default() -> Self {
+        Self::new(SecurityConfig::default())
+    }
+}
+
+enum Session {
+    AwaitingServerHello {
+        secret: StaticSecret,
+        public: PublicKey,
+    },
+    AwaitingAccept(SessionKeys),
+    Joined(SessionKeys),
+}
+
+struct SessionKeys {
+    send: ChaCha20Poly1305,
+    receive: ChaCha20Poly1305,
+    next_nonce: u64,
+    replay: ReplayWindow,
+}
+
+impl SessionKeys {
+    fn next_nonce(&mut self) -> u64 {
+        let nonce = self.next_nonce;
+        self.next_nonce += 1;
+        nonce
+    }
+}
+
+/// Sliding window of recently received nonces, so that packets can arrive out of order but
+/// can't be replayed.
+#[derive(Default)]
+struct ReplayWindow {
+    highest: u64,
+    seen: u64,
+}
+
+impl ReplayWindow {
+    fn check(&self, nonce: u64) -> bool {
+        if nonce < FIRST_DATA_NONCE {
+            false
+        } else if nonce > self.highest {
+            true
+        } else {
+            let age = self.highest - nonce;
+            age < REPLAY_WINDOW && self.seen & (1 << age) == 0
+        }
+    }
+
+    fn accept(&mut self, nonce: u64) {
+        if nonce > self.highest {
+            let shift = nonce - self.highest;
+            self.seen = if shift >= REPLAY_WINDOW {
+                0
+            } else {
+                self.seen << shift
+            };
+            self.seen |= 1;
+            self.highest = nonce;
+        } else {
+            self.seen |= 1 << (self.highest - nonce);
+        }
+    }
+}
+
+/// System decrypting incoming packets and driving the handshakes of all secure sessions.
+pub struct SecureSessionSystem {
+    reader: ReaderId<NetworkSimulationEvent>,
+}
+
+impl SecureSessionSystem {
+    pub fn new(reader: ReaderId<NetworkSimulationEvent>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'s> System<'s> for SecureSessionSystem {
+    type SystemData = (
+        Write<'s, SecureSessions>,
+        Write<'s, TransportResource>,
+        Read<'s, EventChannel<NetworkSimulationEvent>>,
+        Write<'s, EventChannel<SecureNetworkEvent>>,
+    );
+
+    fn run(&mut self, (mut sessions, mut transport, events, mut secure_events): Self::SystemData) {
+        for event in events.read(&mut self.reader) {
+            match event {
+                NetworkSimulationEvent::Message(addr, payload) => {
+                    match sessions.handle(&mut transport, *addr, payload) {
+                        Ok(Some(event)) => secure_events.single_write(event),
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!("Dropping secure packet from {}: {}", addr, e);
+                            if !sessions.is_joined(*addr) {
+                                secure_events
+                                    .single_write(SecureNetworkEvent::HandshakeError(*addr, e));
+                            }
+                        }
+                    }
+                }
+                NetworkSimulationEvent::Disconnect(addr) if sessions.forget(*addr) => {
+                    secure_events.single_write(SecureNetworkEvent::Disconnected(*addr));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn send_handshake(transport: &mut TransportResource, peer: SocketAddr, kind: u8, body: &[u8]) {
+    let mut packet = Vec::with_capacity(body.len() + 1);
+    packet.push(kind);
+    packet.extend_from_slice(body);
+    transport.send_immediate(peer, &packet);
+}
+
+fn seal(cipher: &ChaCha20Poly1305, nonce: u64, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+    cipher
+        .encrypt(&nonce_bytes(nonce), Payload::from(plaintext))
+        .map_err(|_| invalid_data("Encrypting a secure packet failed"))
+}
+
+fn open(cipher: &ChaCha20Poly1305, nonce: u64, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+    cipher
+        .decrypt(&nonce_bytes(nonce), Payload::from(ciphertext))
+        .map_err(|_| invalid_data("Secure packet failed authentication"))
+}
+
+fn nonce_bytes(nonce: u64) -> Nonce {
+    let mut bytes = [0; 12];
+    bytes[..8].copy_from_slice(&nonce.to_le_bytes());
+    Nonce::from(bytes)
+}
+
+fn read_public_key(body: &[u8]) -> io::Result<PublicKey> {
+    if body.len() < 32 {
+        return Err(invalid_data("Secure handshake is missing a public key"));
+    }
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(&body[..32]);
+    Ok(PublicKey::from(bytes))
+}
+
+fn unexpected_packet() -> io::Error {
+    invalid_data("Unexpected secure packet")
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{RunNow, WorldExt};
+
+    fn client_addr() -> SocketAddr {
+        "127.0.0.1:3000".parse().unwrap()
+    }
+
+    fn server_addr() -> SocketAddr {
+        "127.0.0.1:3001".parse().unwrap()
+    }
+
+    /// Hands every queued packet of `transport` to `receiver` and returns the emitted events.
+    fn deliver(
+        from: SocketAddr,
+        transport: &mut TransportResource,
+        receiver: &mut SecureSessions,
+        receiver_transport: &mut TransportResource,
+    ) -> Vec<io::Result<Option<SecureNetworkEvent>>> {
+        transport
+            .drain_messages(|_| true)
+            .into_iter()
+            .map(|message| receiver.handle(receiver_transport, from, &message.payload))
+            .collect()
+    }
+
+    /// Runs the handshake and returns the first event emitted on the client.
+    fn handshake(
+        client: &mut SecureSessions,
+        client_transport: &mut TransportResource,
+        server: &mut SecureSessions,
+        server_transport: &mut TransportResource,
+    ) -> io::Result<Option<SecureNetworkEvent>> {
+        client.connect(client_transport, server_addr());
+        for _ in 0..2 {
+            for result in deliver(client_addr(), client_transport, server, server_transport) {
+                result?;
+            }
+            for result in deliver(server_addr(), server_transport, client, client_transport) {
+                if let Some(event) = result? {
+                    return Ok(Some(event));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    #[test]
+    fn test_handshake_and_encrypted_round_trip() {
+        let identity = StaticSecret::new(OsRng);
+        let mut server = SecureSessions::new(SecurityConfig {
+            pre_shared_key: Some([7; 32]),
+            trusted_server_key: None,
+            auth_token: Vec::new(),
+            identity: Some(identity.clone()),
+        });
+        let mut client = SecureSessions::new(SecurityConfig {
+            pre_shared_key: Some([7; 32]),
+            trusted_server_key: Some(PublicKey::from(&identity)),
+            auth_token: b"token".to_vec(),
+            identity: None,
+        });
+        let (mut client_transport, mut server_transport) =
+            (TransportResource::new(), TransportResource::new());
+
+        let event = handshake(
+            &mut client,
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        )
+        .unwrap();
+        assert!(matches!(event, Some(SecureNetworkEvent::Joined(_))));
+        assert!(client.is_joined(server_addr()));
+        assert!(server.is_joined(client_addr()));
+
+        client
+            .send(&mut client_transport, server_addr(), b"hello")
+            .unwrap();
+        assert_ne!(&client_transport.get_messages()[0].payload[9..], b"hello");
+        let events = deliver(
+            client_addr(),
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        );
+        match &events[0] {
+            Ok(Some(SecureNetworkEvent::Message(addr, payload))) => {
+                assert_eq!(*addr, client_addr());
+                assert_eq!(&payload[..], b"hello");
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_rejected_token() {
+        let mut server = SecureSessions::new(SecurityConfig::default());
+        server.authenticator = Box::new(|_: SocketAddr, token: &[u8]| token == b"secret");
+        let mut client = SecureSessions::new(SecurityConfig {
+            auth_token: b"guess".to_vec(),
+            ..SecurityConfig::default()
+        });
+        let (mut client_transport, mut server_transport) =
+            (TransportResource::new(), TransportResource::new());
+
+        let event = handshake(
+            &mut client,
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        )
+        .unwrap();
+        assert!(matches!(event, Some(SecureNetworkEvent::Rejected(_))));
+        assert!(!server.is_joined(client_addr()));
+        assert!(client
+            .send(&mut client_transport, server_addr(), b"hello")
+            .is_err());
+    }
+
+    #[test]
+    fn test_mismatching_pre_shared_key_fails() {
+        let mut server = SecureSessions::new(SecurityConfig {
+            pre_shared_key: Some([1; 32]),
+            ..SecurityConfig::default()
+        });
+        let mut client = SecureSessions::new(SecurityConfig {
+            pre_shared_key: Some([2; 32]),
+            ..SecurityConfig::default()
+        });
+        let (mut client_transport, mut server_transport) =
+            (TransportResource::new(), TransportResource::new());
+
+        assert!(handshake(
+            &mut client,
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_tampered_and_replayed_packets_are_dropped() {
+        let mut server = SecureSessions::default();
+        let mut client = SecureSessions::default();
+        let (mut client_transport, mut server_transport) =
+            (TransportResource::new(), TransportResource::new());
+        handshake(
+            &mut client,
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        )
+        .unwrap();
+
+        client
+            .send(&mut client_transport, server_addr(), b"hello")
+            .unwrap();
+        let packet = client_transport.drain_messages(|_| true).remove(0).payload;
+        let mut tampered = packet.to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(server
+            .handle(&mut server_transport, client_addr(), &tampered)
+            .is_err());
+        assert!(server
+            .handle(&mut server_transport, client_addr(), &packet)
+            .is_ok());
+        assert!(server
+            .handle(&mut server_transport, client_addr(), &packet)
+            .is_err());
+        assert!(server.is_joined(client_addr()));
+    }
+
+    /// Takes the only packet queued on `transport`.
+    fn take_packet(transport: &mut TransportResource) -> Bytes {
+        let mut messages = transport.drain_messages(|_| true);
+        assert_eq!(messages.len(), 1);
+        messages.remove(0).payload
+    }
+
+    /// A well formed handshake packet of the given kind carrying a random public key.
+    fn forged_packet(kind: u8) -> Vec<u8> {
+        let mut packet = vec![kind];
+        packet.extend_from_slice(PublicKey::from(&StaticSecret::new(OsRng)).as_bytes());
+        packet.extend_from_slice(&[0; 16]);
+        packet
+    }
+
+    #[test]
+    fn test_forged_handshake_packets_keep_pending_sessions() {
+        let mut server = SecureSessions::default();
+        let mut client = SecureSessions::default();
+        let (mut client_transport, mut server_transport) =
+            (TransportResource::new(), TransportResource::new());
+
+        client.connect(&mut client_transport, server_addr());
+        let client_hello = take_packet(&mut client_transport);
+        for packet in &[vec![SERVER_HELLO, 1, 2], forged_packet(SERVER_HELLO)] {
+            assert!(client
+                .handle(&mut client_transport, server_addr(), packet)
+                .is_err());
+        }
+
+        let server_hello = server
+            .handle(&mut server_transport, client_addr(), &client_hello)
+            .map(|_| take_packet(&mut server_transport))
+            .unwrap();
+        assert!(server
+            .handle(&mut server_transport, client_addr(), &[CLIENT_AUTH, 1, 2])
+            .is_err());
+        // A hello spoofed by somebody else must not replace the handshake of the client.
+        server
+            .handle(
+                &mut server_transport,
+                client_addr(),
+                &forged_packet(CLIENT_HELLO)[..33],
+            )
+            .unwrap();
+        server_transport.drain_messages(|_| true);
+
+        let client_auth = client
+            .handle(&mut client_transport, server_addr(), &server_hello)
+            .map(|_| take_packet(&mut client_transport))
+            .unwrap();
+        for packet in &[vec![ACCEPT, 1, 2], vec![REJECT], forged_packet(REJECT)] {
+            assert!(client
+                .handle(&mut client_transport, server_addr(), packet)
+                .is_err());
+        }
+
+        let event = server
+            .handle(&mut server_transport, client_addr(), &client_auth)
+            .unwrap();
+        assert!(matches!(event, Some(SecureNetworkEvent::Joined(_))));
+        let accept = take_packet(&mut server_transport);
+        let event = client
+            .handle(&mut client_transport, server_addr(), &accept)
+            .unwrap();
+        assert!(matches!(event, Some(SecureNetworkEvent::Joined(_))));
+    }
+
+    #[test]
+    fn test_forged_handshake_packets_keep_joined_sessions() {
+        let mut server = SecureSessions::default();
+        let mut client = SecureSessions::default();
+        let (mut client_transport, mut server_transport) =
+            (TransportResource::new(), TransportResource::new());
+        handshake(
+            &mut client,
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        )
+        .unwrap();
+
+        server
+            .handle(
+                &mut server_transport,
+                client_addr(),
+                &forged_packet(CLIENT_HELLO)[..33],
+            )
+            .unwrap();
+        server_transport.drain_messages(|_| true);
+        assert!(server.is_joined(client_addr()));
+        for packet in &[
+            forged_packet(SERVER_HELLO),
+            forged_packet(ACCEPT),
+            forged_packet(REJECT),
+        ] {
+            assert!(client
+                .handle(&mut client_transport, server_addr(), packet)
+                .is_err());
+        }
+        assert!(client.is_joined(server_addr()));
+
+        client
+            .send(&mut client_transport, server_addr(), b"hello")
+            .unwrap();
+        let events = deliver(
+            client_addr(),
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        );
+        assert!(matches!(
+            events[0],
+            Ok(Some(SecureNetworkEvent::Message(_, _)))
+        ));
+    }
+
+    #[test]
+    fn test_rejoining_replaces_joined_session_once_authenticated() {
+        let mut server = SecureSessions::default();
+        let mut client = SecureSessions::default();
+        let (mut client_transport, mut server_transport) =
+            (TransportResource::new(), TransportResource::new());
+        for _ in 0..2 {
+            let event = handshake(
+                &mut client,
+                &mut client_transport,
+                &mut server,
+                &mut server_transport,
+            )
+            .unwrap();
+            assert!(matches!(event, Some(SecureNetworkEvent::Joined(_))));
+        }
+
+        client
+            .send(&mut client_transport, server_addr(), b"hello")
+            .unwrap();
+        let events = deliver(
+            client_addr(),
+            &mut client_transport,
+            &mut server,
+            &mut server_transport,
+        );
+        assert!(matches!(
+            events[0],
+            Ok(Some(SecureNetworkEvent::Message(_, _)))
+        ));
+    }
+
+    #[test]
+    fn test_system_keeps_session_on_handshake_error() {
+        let mut world = World::new();
+        let mut client = SecureSessions::default();
+        let mut transport = TransportResource::new();
+        client.connect(&mut transport, server_addr());
+        world.insert(client);
+        world.insert(transport);
+        world.insert(EventChannel::<SecureNetworkEvent>::new());
+        let mut events = EventChannel::<NetworkSimulationEvent>::new();
+        let mut system = SecureSessionSystem::new(events.register_reader());
+        let mut secure_reader = world
+            .fetch_mut::<EventChannel<SecureNetworkEvent>>()
+            .register_reader();
+        events.single_write(NetworkSimulationEvent::Message(
+            server_addr(),
+            Bytes::from(forged_packet(SERVER_HELLO)),
+        ));
+        world.insert(events);
+
+        system.run_now(&world);
+
+        let secure_events = world.fetch::<EventChannel<SecureNetworkEvent>>();
+        assert!(matches!(
+            secure_events.read(&mut secure_reader).next(),
+            Some(SecureNetworkEvent::HandshakeError(_, _))
+        ));
+        assert!(matches!(
+            world.fetch::<SecureSessions>().sessions.get(&server_addr()),
+            Some(Session::AwaitingServerHello { .. })
+        ));
+    }
+
+    #[test]
+    fn test_replay_window_accepts_out_of_order() {
+        let mut window = ReplayWindow::default();
+        window.accept(5);
+        window.accept(3);
+        assert!(!window.check(3));
+        assert!(window.check(4));
+        assert!(!window.check(1));
+        window.accept(100);
+        assert!(!window.check(5));
+    }
+}
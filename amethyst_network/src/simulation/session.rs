@@ -0,0 +1,640 @@
+//! Sessions which survive dropped connections. The host hands every client a `SessionToken` when
+//! it joins. A client which drops can reconnect with its token within the reconnect timeout and
+//! keeps its session, so the game can resync its state instead of treating it as a new player.
+//!
+//! The host also shares the list of all sessions with the clients. When the host of a listen
+//! server disappears, the first remaining client in that list becomes the new host and the others
+//! resume their sessions with it. Session tokens are therefore known to all members of a session
+//! and shouldn't be used to authenticate players.
+
+use crate::simulation::{
+    events::NetworkSimulationEvent,
+    rpc::{decode_message, encode_message, NetworkMessage},
+    transport::{TransportResource, NETWORK_RECV_SYSTEM_NAME},
+};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{DispatcherBuilder, Read, System, World, Write},
+    shrev::{EventChannel, ReaderId},
+};
+use amethyst_error::Error;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+const SESSION_SYSTEM_NAME: &str = "network_session";
+
+/// Use this bundle after one of the transport bundles to keep sessions across reconnects. Clients
+/// join the host with `SessionManager::connect`.
+#[derive(Debug)]
+pub struct SessionBundle {
+    sessions: SessionManager,
+}
+
+impl SessionBundle {
+    pub fn new(sessions: SessionManager) -> Self {
+        Self { sessions }
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for SessionBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        let reader = world
+            .entry::<EventChannel<NetworkSimulationEvent>>()
+            .or_insert_with(EventChannel::new)
+            .register_reader();
+        world
+            .entry::<EventChannel<SessionEvent>>()
+            .or_insert_with(EventChannel::new);
+        world.insert(self.sessions);
+        builder.add(
+            SessionSystem::new(reader),
+            SESSION_SYSTEM_NAME,
+            &[NETWORK_RECV_SYSTEM_NAME],
+        );
+        Ok(())
+    }
+}
+
+/// Token identifying a session across connections.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SessionToken(pub u64);
+
+/// Configuration of a `SessionManager`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// How long the session of a dropped client is kept for it to reconnect.
+    pub reconnect_timeout: Duration,
+    /// Whether the clients take over the host role when the host disappears.
+    pub host_migration: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            reconnect_timeout: Duration::from_secs(30),
+            host_migration: false,
+        }
+    }
+}
+
+/// Events emitted by the `SessionSystem`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// Host only: a new client joined.
+    Joined(SocketAddr, SessionToken),
+    /// Host only: a client reconnected within the timeout. The game should resync its state.
+    Resumed {
+        /// The token of the session.
+        token: SessionToken,
+        /// The address the client used before it dropped.
+        old_addr: SocketAddr,
+        /// The address the client uses now.
+        addr: SocketAddr,
+    },
+    /// Host only: a client dropped. Its session is kept until the reconnect timeout.
+    Dropped(SocketAddr, SessionToken),
+    /// Host only: a dropped client didn't reconnect in time.
+    Expired(SessionToken),
+    /// Client only: the host accepted us. `resumed` is true if our previous session was resumed.
+    Welcomed {
+        /// Our session token.
+        token: SessionToken,
+        /// Whether our previous session was kept.
+        resumed: bool,
+    },
+    /// Client only: the connection to the host was lost.
+    HostLost(SocketAddr),
+    /// Client only: we took over the host role.
+    BecameHost,
+    /// Client only: another client took over the host role and we reconnect to it.
+    HostMigrated(SocketAddr),
+}
+
+/// Messages exchanged by the `SessionSystem`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SessionMessage {
+    /// Sent by a client to join or resume a session.
+    Hello {
+        /// The token of the session to resume.
+        resume: Option<SessionToken>,
+    },
+    /// Sent by the host to accept a client.
+    Welcome {
+        /// The session token of the client.
+        token: SessionToken,
+        /// Whether the previous session of the client was kept.
+        resumed: bool,
+    },
+    /// Sent by the host to all clients when the connected clients change, in the order they
+    /// joined.
+    Roster(Vec<(SessionToken, SocketAddr)>),
+}
+
+impl NetworkMessage for SessionMessage {
+    const ID: u16 = 0xff01;
+}
+
+#[derive(Copy, Clone, Debug)]
+struct PeerSession {
+    addr: SocketAddr,
+    dropped_at: Option<Instant>,
+}
+
+/// Resource managing the sessions of the host, or the session of a client.
+#[derive(Debug)]
+pub struct SessionManager {
+    config: SessionConfig,
+    is_host: bool,
+    /// Host: all sessions, in the order they joined.
+    sessions: Vec<(SessionToken, PeerSession)>,
+    /// Client: the current host.
+    host: Option<SocketAddr>,
+    /// Client: our own token.
+    token: Option<SessionToken>,
+    /// Client: the connected clients last shared by the host.
+    roster: Vec<(SessionToken, SocketAddr)>,
+}
+
+impl SessionManager {
+    /// Creates the session manager of a host.
+    pub fn host(config: SessionConfig) -> Self {
+        Self {
+            is_host: true,
+            ..Self::client(config)
+        }
+    }
+
+    /// Creates the session manager of a client. Join a host with `connect`.
+    pub fn client(config: SessionConfig) -> Self {
+        Self {
+            config,
+            is_host: false,
+            sessions: Vec::new(),
+            host: None,
+            token: None,
+            roster: Vec::new(),
+        }
+    }
+
+    /// Returns true if we are the host.
+    pub fn is_host(&self) -> bool {
+        self.is_host
+    }
+
+    /// Returns the current host if we are a client.
+    pub fn current_host(&self) -> Option<SocketAddr> {
+        self.host
+    }
+
+    /// Returns our session token once a host welcomed us.
+    pub fn token(&self) -> Option<SessionToken> {
+        self.token
+    }
+
+    /// Returns the address of the client with the given session, if it is connected.
+    pub fn peer(&self, token: SessionToken) -> Option<SocketAddr> {
+        self.sessions
+            .iter()
+            .find(|(t, session)| *t == token && session.dropped_at.is_none())
+            .map(|(_, session)| session.addr)
+    }
+
+    /// Joins the given host, resuming our previous session if we have one.
+    pub fn connect(&mut self, transport: &mut TransportResource, host: SocketAddr) {
+        self.host = Some(host);
+        send(
+            transport,
+            host,
+            &SessionMessage::Hello { resume: self.token },
+        );
+    }
+
+    fn handle_message(
+        &mut self,
+        transport: &mut TransportResource,
+        from: SocketAddr,
+        message: SessionMessage,
+        events: &mut Vec<SessionEvent>,
+    ) {
+        match message {
+            SessionMessage::Hello { resume } if self.is_host => {
+                let resumed = resume.and_then(|token| {
+                    self.sessions
+                        .iter_mut()
+                        .find(|(t, _)| *t == token)
+                        .map(|(token, session)| (*token, session))
+                });
+                let (token, resumed) = match resumed {
+                    Some((token, session)) => {
+                        let old_addr = session.addr;
+                        *session = PeerSession {
+                            addr: from,
+                            dropped_at: None,
+                        };
+                        events.push(SessionEvent::Resumed {
+                            token,
+                            old_addr,
+                            addr: from,
+                        });
+                        (token, true)
+                    }
+                    None => {
+                        if let Some((token, _)) = self.sessions.iter().find(|(_, s)| s.addr == from)
+                        {
+                            // The client didn't get our welcome, send it again.
+                            let token = *token;
+                            send(
+                                transport,
+                                from,
+                                &SessionMessage::Welcome {
+                                    token,
+                                    resumed: false,
+                                },
+                            );
+                            return;
+                        }
+                        let token = SessionToken(rand::random());
+                        self.sessions.push((
+                            token,
+                            PeerSession {
+                                addr: from,
+                                dropped_at: None,
+                            },
+                        ));
+                        events.push(SessionEvent::Joined(from, token));
+                        (token, false)
+                    }
+                };
+                send(transport, from, &SessionMessage::Welcome { token, resumed });
+                self.broadcast_roster(transport);
+            }
+            SessionMessage::Welcome { token, resumed } if Some(from) == self.host => {
+                self.token = Some(token);
+                events.push(SessionEvent::Welcomed { token, resumed });
+            }
+            SessionMessage::Roster(roster) if Some(from) == self.host => {
+                self.roster = roster;
+            }
+            message => warn!("Ignoring unexpected {:?} from {}", message, from),
+        }
+    }
+
+    fn handle_disconnect(
+        &mut self,
+        transport: &mut TransportResource,
+        addr: SocketAddr,
+        now: Instant,
+        events: &mut Vec<SessionEvent>,
+    ) {
+        if self.is_host {
+            let dropped = self
+                .sessions
+                .iter_mut()
+                .find(|(_, session)| session.addr == addr && session.dropped_at.is_none());
+            if let Some((token, session)) = dropped {
+                session.dropped_at = Some(now);
+                events.push(SessionEvent::Dropped(addr, *token));
+                self.broadcast_roster(transport);
+            }
+        } else if Some(addr) == self.host {
+            events.push(SessionEvent::HostLost(addr));
+            self.host = None;
+            if self.config.host_migration {
+                self.migrate(transport, addr, now, events);
+            }
+        }
+    }
+
+    /// Takes over the host role or connects to the client which does.
+    fn migrate(
+        &mut self,
+        transport: &mut TransportResource,
+        old_host: SocketAddr,
+        now: Instant,
+        events: &mut Vec<SessionEvent>,
+    ) {
+        let successor = self
+            .roster
+            .iter()
+            .find(|(_, addr)| *addr != old_host)
+            .copied();
+        match successor {
+            Some((token, _)) if Some(token) == self.token => {
+                self.is_host = true;
+                // All other clients have to reconnect to us.
+                self.sessions = self
+                    .roster
+                    .iter()
+                    .filter(|(t, _)| *t != token)
+                    .map(|(token, addr)| {
+                        (
+                            *token,
+                            PeerSession {
+                                addr: *addr,
+                                dropped_at: Some(now),
+                            },
+                        )
+                    })
+                    .collect();
+                self.roster.clear();
+                events.push(SessionEvent::BecameHost);
+            }
+            Some((_, addr)) => {
+                self.connect(transport, addr);
+                events.push(SessionEvent::HostMigrated(addr));
+            }
+            None => {}
+        }
+    }
+
+    fn expire(&mut self, now: Instant, events: &mut Vec<SessionEvent>) {
+        let timeout = self.config.reconnect_timeout;
+        self.sessions.retain(|(token, session)| {
+            let expired = match session.dropped_at {
+                Some(dropped_at) => now.duration_since(dropped_at) >= timeout,
+                None => false,
+            };
+            if expired {
+                events.push(SessionEvent::Expired(*token));
+            }
+            !expired
+        });
+    }
+
+    fn broadcast_roster(&self, transport: &mut TransportResource) {
+        let connected = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.dropped_at.is_none())
+            .map(|(token, session)| (*token, session.addr))
+            .collect::<Vec<_>>();
+        let roster = SessionMessage::Roster(connected.clone());
+        for (_, addr) in connected {
+            send(transport, addr, &roster);
+        }
+    }
+}
+
+fn send(transport: &mut TransportResource, addr: SocketAddr, message: &SessionMessage) {
+    match encode_message(message) {
+        Ok(payload) => transport.send_immediate(addr, &payload),
+        Err(e) => warn!("Failed to encode session message: {}", e),
+    }
+}
+
+/// System driving the `SessionManager` resource.
+pub struct SessionSystem {
+    reader: ReaderId<NetworkSimulationEvent>,
+}
+
+impl SessionSystem {
+    pub fn new(reader: ReaderId<NetworkSimulationEvent>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'s> System<'s> for SessionSystem {
+    type SystemData = (
+        Write<'s, SessionManager>,
+        Write<'s, TransportResource>,
+        Read<'s, EventChannel<NetworkSimulationEvent>>,
+        Write<'s, EventChannel<SessionEvent>>,
+    );
+
+    fn run(&mut self, (mut sessions, mut transport, events, mut session_events): Self::SystemData) {
+        let now = Instant::now();
+        let mut new_events = Vec::new();
+        for event in events.read(&mut self.reader) {
+            match event {
+                NetworkSimulationEvent::Message(from, payload) => {
+                    match decode_message::<SessionMessage>(payload) {
+                        Some(Ok(message)) => {
+                            sessions.handle_message(&mut transport, *from, message, &mut new_events)
+                        }
+                        Some(Err(e)) => warn!("Dropping malformed session message: {}", e),
+                        None => {}
+                    }
+                }
+                NetworkSimulationEvent::Disconnect(addr) => {
+                    sessions.handle_disconnect(&mut transport, *addr, now, &mut new_events)
+                }
+                _ => {}
+            }
+        }
+        sessions.expire(now, &mut new_events);
+        session_events.iter_write(new_events);
+    }
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::client(SessionConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    /// Delivers all messages the peer at `from` sent to the given peers.
+    fn deliver(
+        from: SocketAddr,
+        transport: &mut TransportResource,
+        peers: &mut [(SocketAddr, &mut SessionManager, &mut TransportResource)],
+    ) -> Vec<(SocketAddr, SessionEvent)> {
+        let mut events = Vec::new();
+        for message in transport.drain_messages(|_| true) {
+            let peer = peers
+                .iter_mut()
+                .find(|(addr, _, _)| *addr == message.destination);
+            if let Some((to, sessions, transport)) = peer {
+                let message = decode_message(&message.payload).unwrap().unwrap();
+                let mut new_events = Vec::new();
+                sessions.handle_message(transport, from, message, &mut new_events);
+                events.extend(new_events.into_iter().map(|event| (*to, event)));
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_client_resumes_session() {
+        let config = SessionConfig::default();
+        let (host_addr, client_addr, new_client_addr) = (addr(3000), addr(3001), addr(3002));
+        let mut host = SessionManager::host(config);
+        let mut client = SessionManager::client(config);
+        let mut host_transport = TransportResource::default();
+        let mut client_transport = TransportResource::default();
+        let now = Instant::now();
+
+        client.connect(&mut client_transport, host_addr);
+        let events = deliver(
+            client_addr,
+            &mut client_transport,
+            &mut [(host_addr, &mut host, &mut host_transport)],
+        );
+        let token = match events.as_slice() {
+            [(_, SessionEvent::Joined(addr, token))] if *addr == client_addr => *token,
+            events => panic!("unexpected events {:?}", events),
+        };
+        deliver(
+            host_addr,
+            &mut host_transport,
+            &mut [(client_addr, &mut client, &mut client_transport)],
+        );
+        assert_eq!(client.token(), Some(token));
+
+        let mut events = Vec::new();
+        host.handle_disconnect(&mut host_transport, client_addr, now, &mut events);
+        assert_eq!(events, vec![SessionEvent::Dropped(client_addr, token)]);
+        assert_eq!(host.peer(token), None);
+
+        // The client comes back from another address.
+        client.connect(&mut client_transport, host_addr);
+        let events = deliver(
+            new_client_addr,
+            &mut client_transport,
+            &mut [(host_addr, &mut host, &mut host_transport)],
+        );
+        assert_eq!(
+            events,
+            vec![(
+                host_addr,
+                SessionEvent::Resumed {
+                    token,
+                    old_addr: client_addr,
+                    addr: new_client_addr,
+                }
+            )]
+        );
+        assert_eq!(host.peer(token), Some(new_client_addr));
+    }
+
+    #[test]
+    fn test_dropped_sessions_expire() {
+        let config = SessionConfig::default();
+        let mut host = SessionManager::host(config);
+        let mut transport = TransportResource::default();
+        let now = Instant::now();
+        let mut events = Vec::new();
+        host.handle_message(
+            &mut transport,
+            addr(3001),
+            SessionMessage::Hello { resume: None },
+            &mut events,
+        );
+        host.handle_disconnect(&mut transport, addr(3001), now, &mut events);
+        let token = match events[0] {
+            SessionEvent::Joined(_, token) => token,
+            ref event => panic!("unexpected event {:?}", event),
+        };
+
+        events.clear();
+        host.expire(now + Duration::from_secs(29), &mut events);
+        assert!(events.is_empty());
+        host.expire(now + config.reconnect_timeout, &mut events);
+        assert_eq!(events, vec![SessionEvent::Expired(token)]);
+
+        // Resuming the expired session starts a new one.
+        events.clear();
+        host.handle_message(
+            &mut transport,
+            addr(3001),
+            SessionMessage::Hello {
+                resume: Some(token),
+            },
+            &mut events,
+        );
+        assert!(matches!(events.as_slice(), [SessionEvent::Joined(..)]));
+    }
+
+    #[test]
+    fn test_host_migrates_to_first_client() {
+        let config = SessionConfig {
+            host_migration: true,
+            ..SessionConfig::default()
+        };
+        let (host_addr, a_addr, b_addr) = (addr(3000), addr(3001), addr(3002));
+        let mut host = SessionManager::host(config);
+        let mut a = SessionManager::client(config);
+        let mut b = SessionManager::client(config);
+        let mut host_transport = TransportResource::default();
+        let mut a_transport = TransportResource::default();
+        let mut b_transport = TransportResource::default();
+        let now = Instant::now();
+
+        for (client_addr, client, transport) in &mut [
+            (a_addr, &mut a, &mut a_transport),
+            (b_addr, &mut b, &mut b_transport),
+        ] {
+            client.connect(transport, host_addr);
+            deliver(
+                *client_addr,
+                transport,
+                &mut [(host_addr, &mut host, &mut host_transport)],
+            );
+        }
+        deliver(
+            host_addr,
+            &mut host_transport,
+            &mut [
+                (a_addr, &mut a, &mut a_transport),
+                (b_addr, &mut b, &mut b_transport),
+            ],
+        );
+
+        let mut a_events = Vec::new();
+        a.handle_disconnect(&mut a_transport, host_addr, now, &mut a_events);
+        assert_eq!(
+            a_events,
+            vec![SessionEvent::HostLost(host_addr), SessionEvent::BecameHost]
+        );
+        assert!(a.is_host());
+        let mut b_events = Vec::new();
+        b.handle_disconnect(&mut b_transport, host_addr, now, &mut b_events);
+        assert_eq!(
+            b_events,
+            vec![
+                SessionEvent::HostLost(host_addr),
+                SessionEvent::HostMigrated(a_addr)
+            ]
+        );
+
+        let b_token = b.token().unwrap();
+        let events = deliver(
+            b_addr,
+            &mut b_transport,
+            &mut [(a_addr, &mut a, &mut a_transport)],
+        );
+        assert_eq!(
+            events,
+            vec![(
+                a_addr,
+                SessionEvent::Resumed {
+                    token: b_token,
+                    old_addr: b_addr,
+                    addr: b_addr,
+                }
+            )]
+        );
+        deliver(
+            a_addr,
+            &mut a_transport,
+            &mut [(b_addr, &mut b, &mut b_transport)],
+        );
+        assert_eq!(b.current_host(), Some(a_addr));
+        assert_eq!(b.token(), Some(b_token));
+    }
+}
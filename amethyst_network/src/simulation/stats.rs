@@ -0,0 +1,225 @@
+//! Per-connection network statistics, e.g. to show a ping indicator or to adapt send rates.
+
+use super::events::NetworkSimulationEvent;
+use amethyst_core::{
+    ecs::{Read, System, Write},
+    shrev::{EventChannel, ReaderId},
+    timing::Time,
+};
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+/// Length of the window over which the bandwidth is measured.
+const BANDWIDTH_WINDOW: Duration = Duration::from_secs(1);
+
+/// Weight of a new round-trip time sample in the smoothed round-trip time.
+const RTT_SMOOTHING: f32 = 0.125;
+
+/// Statistics of a single connection.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectionStats {
+    /// Smoothed round-trip time, if it has been measured.
+    pub rtt: Option<Duration>,
+    /// Estimated share of sent messages which were lost in 0.0-1.0.
+    pub packet_loss: f32,
+    /// Bytes sent per second, measured over the last second.
+    pub bytes_sent_per_second: f32,
+    /// Bytes received per second, measured over the last second.
+    pub bytes_received_per_second: f32,
+    /// Total number of bytes sent.
+    pub total_bytes_sent: u64,
+    /// Total number of bytes received.
+    pub total_bytes_received: u64,
+    /// Total number of messages sent.
+    pub messages_sent: u64,
+    /// Total number of messages received.
+    pub messages_received: u64,
+    /// Total number of messages which were reported as lost.
+    pub messages_lost: u64,
+    /// Total number of messages which had to be sent again.
+    pub resends: u64,
+    window_bytes_sent: u64,
+    window_bytes_received: u64,
+}
+
+/// Resource holding the statistics of all connections. The transports record the sent and
+/// received traffic, and the `NetworkStatsSystem` updates the bandwidth every frame.
+///
+/// None of the built-in transports expose acknowledgements, so round-trip times, losses and
+/// resends have to be reported by the layer measuring them, e.g. a game echoing timestamps.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkStats {
+    connections: HashMap<SocketAddr, ConnectionStats>,
+    window_elapsed: Duration,
+}
+
+impl NetworkStats {
+    /// Returns the statistics of the given connection.
+    pub fn connection(&self, addr: SocketAddr) -> Option<&ConnectionStats> {
+        self.connections.get(&addr)
+    }
+
+    /// Returns the statistics of all connections.
+    pub fn connections(&self) -> impl Iterator<Item = (&SocketAddr, &ConnectionStats)> {
+        self.connections.iter()
+    }
+
+    /// Returns the bytes sent per second over all connections.
+    pub fn bytes_sent_per_second(&self) -> f32 {
+        self.connections
+            .values()
+            .map(|stats| stats.bytes_sent_per_second)
+            .sum()
+    }
+
+    /// Returns the bytes received per second over all connections.
+    pub fn bytes_received_per_second(&self) -> f32 {
+        self.connections
+            .values()
+            .map(|stats| stats.bytes_received_per_second)
+            .sum()
+    }
+
+    /// Records a message sent to the given peer.
+    pub fn record_sent(&mut self, addr: SocketAddr, bytes: usize) {
+        let stats = self.connections.entry(addr).or_default();
+        stats.messages_sent += 1;
+        stats.total_bytes_sent += bytes as u64;
+        stats.window_bytes_sent += bytes as u64;
+        stats.update_packet_loss();
+    }
+
+    /// Records a message received from the given peer.
+    pub fn record_received(&mut self, addr: SocketAddr, bytes: usize) {
+        let stats = self.connections.entry(addr).or_default();
+        stats.messages_received += 1;
+        stats.total_bytes_received += bytes as u64;
+        stats.window_bytes_received += bytes as u64;
+    }
+
+    /// Records a round-trip time sample of the given peer.
+    pub fn record_rtt(&mut self, addr: SocketAddr, rtt: Duration) {
+        let stats = self.connections.entry(addr).or_default();
+        stats.rtt = Some(match stats.rtt {
+            Some(smoothed) => smoothed.mul_f32(1.0 - RTT_SMOOTHING) + rtt.mul_f32(RTT_SMOOTHING),
+            None => rtt,
+        });
+    }
+
+    /// Records a message to the given peer which was lost.
+    pub fn record_lost(&mut self, addr: SocketAddr) {
+        let stats = self.connections.entry(addr).or_default();
+        stats.messages_lost += 1;
+        stats.update_packet_loss();
+    }
+
+    /// Records a message to the given peer which had to be sent again.
+    pub fn record_resend(&mut self, addr: SocketAddr) {
+        self.connections.entry(addr).or_default().resends += 1;
+    }
+
+    /// Forgets the statistics of the given peer.
+    pub fn remove(&mut self, addr: SocketAddr) {
+        self.connections.remove(&addr);
+    }
+
+    /// Advances the bandwidth measurement window by the given time.
+    pub fn update(&mut self, elapsed: Duration) {
+        self.window_elapsed += elapsed;
+        if self.window_elapsed < BANDWIDTH_WINDOW {
+            return;
+        }
+        let seconds = self.window_elapsed.as_secs_f32();
+        for stats in self.connections.values_mut() {
+            stats.bytes_sent_per_second = stats.window_bytes_sent as f32 / seconds;
+            stats.bytes_received_per_second = stats.window_bytes_received as f32 / seconds;
+            stats.window_bytes_sent = 0;
+            stats.window_bytes_received = 0;
+        }
+        self.window_elapsed = Duration::default();
+    }
+}
+
+impl ConnectionStats {
+    fn update_packet_loss(&mut self) {
+        if self.messages_sent > 0 {
+            self.packet_loss = (self.messages_lost as f32 / self.messages_sent as f32).min(1.0);
+        }
+    }
+}
+
+/// System updating the `NetworkStats` every frame and dropping the statistics of disconnected
+/// peers.
+pub struct NetworkStatsSystem {
+    reader: ReaderId<NetworkSimulationEvent>,
+}
+
+impl NetworkStatsSystem {
+    pub fn new(reader: ReaderId<NetworkSimulationEvent>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'s> System<'s> for NetworkStatsSystem {
+    type SystemData = (
+        Write<'s, NetworkStats>,
+        Read<'s, Time>,
+        Read<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (mut stats, time, events): Self::SystemData) {
+        for event in events.read(&mut self.reader) {
+            if let NetworkSimulationEvent::Disconnect(addr) = event {
+                stats.remove(*addr);
+            }
+        }
+        stats.update(time.delta_real_time());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:3000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_bandwidth_is_measured_per_second() {
+        let mut stats = NetworkStats::default();
+        stats.record_sent(addr(), 100);
+        stats.record_sent(addr(), 100);
+        stats.record_received(addr(), 50);
+        stats.update(Duration::from_millis(500));
+        assert!(stats.connection(addr()).unwrap().bytes_sent_per_second < f32::EPSILON);
+
+        stats.update(Duration::from_millis(500));
+        let connection = stats.connection(addr()).unwrap();
+        assert!((connection.bytes_sent_per_second - 200.0).abs() < f32::EPSILON);
+        assert!((connection.bytes_received_per_second - 50.0).abs() < f32::EPSILON);
+        assert_eq!(connection.total_bytes_sent, 200);
+        assert_eq!(connection.messages_received, 1);
+    }
+
+    #[test]
+    fn test_rtt_is_smoothed() {
+        let mut stats = NetworkStats::default();
+        stats.record_rtt(addr(), Duration::from_millis(100));
+        stats.record_rtt(addr(), Duration::from_millis(180));
+        let rtt = stats.connection(addr()).unwrap().rtt.unwrap();
+        assert_eq!(rtt.as_millis(), 110);
+    }
+
+    #[test]
+    fn test_packet_loss() {
+        let mut stats = NetworkStats::default();
+        for _ in 0..4 {
+            stats.record_sent(addr(), 10);
+        }
+        stats.record_lost(addr());
+        stats.record_resend(addr());
+        let connection = stats.connection(addr()).unwrap();
+        assert!((connection.packet_loss - 0.25).abs() < f32::EPSILON);
+        assert_eq!(connection.resends, 1);
+    }
+}
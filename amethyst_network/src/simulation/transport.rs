@@ -2,25 +2,49 @@
 //! protocols. One important thing to note if you're implementing your own, the underlying sockets
 //! MUST be non-blocking in order to play nicely with the ECS scheduler.
 
+pub mod fallback;
 pub mod laminar;
 pub mod tcp;
 pub mod udp;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
 const NETWORK_SIM_TIME_SYSTEM_NAME: &str = "simulation_time";
 const NETWORK_SEND_SYSTEM_NAME: &str = "network_send";
-const NETWORK_RECV_SYSTEM_NAME: &str = "network_recv";
+pub(crate) const NETWORK_RECV_SYSTEM_NAME: &str = "network_recv";
 const NETWORK_POLL_SYSTEM_NAME: &str = "network_poll";
+const NETWORK_STATS_SYSTEM_NAME: &str = "network_stats";
 
 use crate::simulation::{
+    channel::{ChannelConfig, ChannelId, TransportGuarantees},
+    compression::{CompressionConfig, CompressionStats},
+    conditioner::NetworkConditioner,
+    events::NetworkSimulationEvent,
     message::Message,
     requirements::{DeliveryRequirement, UrgencyRequirement},
+    stats::NetworkStatsSystem,
+};
+use amethyst_core::{
+    ecs::{DispatcherBuilder, World},
+    shrev::EventChannel,
+};
+use amethyst_error::{format_err, Error};
+use bytes::Bytes;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    net::SocketAddr,
 };
-use std::{collections::VecDeque, net::SocketAddr};
 
 /// Resource serving as the owner of the queue of messages to be sent. This resource also serves
 /// as the interface for other systems to send messages.
 pub struct TransportResource {
     messages: VecDeque<Message>,
+    channels: HashMap<ChannelId, ChannelConfig>,
+    guarantees: TransportGuarantees,
+    compression: CompressionConfig,
+    compression_stats: CompressionStats,
+    conditioner: NetworkConditioner,
     frame_budget_bytes: i32,
     latency_nanos: i64,
     packet_loss: f32,
@@ -31,6 +55,11 @@ impl TransportResource {
     pub fn new() -> Self {
         Self {
             messages: VecDeque::new(),
+            channels: HashMap::new(),
+            guarantees: TransportGuarantees::default(),
+            compression: CompressionConfig::default(),
+            compression_stats: CompressionStats::default(),
+            conditioner: NetworkConditioner::new(),
             frame_budget_bytes: 0,
             latency_nanos: 0,
             packet_loss: 0.0,
@@ -77,6 +106,87 @@ impl TransportResource {
         self.packet_loss = loss;
     }
 
+    /// Returns the compression applied to outgoing payloads.
+    pub fn compression(&self) -> &CompressionConfig {
+        &self.compression
+    }
+
+    /// Sets the compression applied to outgoing payloads. Message based transports compress the
+    /// payloads when sending them and decode incoming payloads with the same configuration, so all
+    /// peers must use the same setting. The TCP transport sends payloads as is.
+    pub fn set_compression(&mut self, compression: CompressionConfig) {
+        self.compression = compression;
+    }
+
+    /// Returns how many bytes payload compression saved so far.
+    pub fn compression_stats(&self) -> CompressionStats {
+        self.compression_stats
+    }
+
+    /// Returns the conditioner simulating bad network conditions.
+    pub fn conditioner(&self) -> &NetworkConditioner {
+        &self.conditioner
+    }
+
+    /// Returns the conditioner simulating bad network conditions, e.g. to toggle it at runtime.
+    pub fn conditioner_mut(&mut self) -> &mut NetworkConditioner {
+        &mut self.conditioner
+    }
+
+    /// Returns the delivery guarantees of the active transport.
+    pub fn guarantees(&self) -> TransportGuarantees {
+        self.guarantees
+    }
+
+    /// Sets the delivery guarantees of the active transport. This should be called by a transport
+    /// implementation, and fails if a registered channel needs a guarantee the transport can't
+    /// provide.
+    pub fn set_guarantees(&mut self, guarantees: TransportGuarantees) -> Result<(), Error> {
+        for (id, config) in &self.channels {
+            check_channel(guarantees, *id, config)?;
+        }
+        self.guarantees = guarantees;
+        Ok(())
+    }
+
+    /// Registers a virtual channel with the given configuration, replacing any previous
+    /// configuration of the channel.
+    ///
+    /// Fails if the active transport can't provide the delivery guarantee of the channel, e.g. a
+    /// reliable channel over UDP. Stream transports like TCP and WebSockets deliver the messages
+    /// of every channel reliably and in order.
+    pub fn register_channel(&mut self, id: ChannelId, config: ChannelConfig) -> Result<(), Error> {
+        check_channel(self.guarantees, id, &config)?;
+        self.channels.insert(id, config);
+        Ok(())
+    }
+
+    /// Returns the configuration of the given channel if it has been registered.
+    pub fn channel(&self, id: ChannelId) -> Option<&ChannelConfig> {
+        self.channels.get(&id)
+    }
+
+    /// Creates a `Message` with the guarantees of the given channel and pushes it onto the messages
+    /// queue to be sent on next sim tick. Channels which haven't been registered use the default
+    /// `ChannelConfig`. The message gets the requirement the active transport meets the channel
+    /// guarantees with, see `TransportGuarantees`.
+    pub fn send_on_channel(&mut self, destination: SocketAddr, channel: ChannelId, payload: &[u8]) {
+        let delivery = self
+            .channels
+            .get(&channel)
+            .copied()
+            .unwrap_or_default()
+            .delivery_for(channel);
+        // registered channels were checked against the transport
+        let delivery = self
+            .guarantees
+            .delivery(delivery)
+            .unwrap_or(DeliveryRequirement::Default);
+        let mut message = Message::new(destination, payload, delivery, UrgencyRequirement::OnTick);
+        message.channel = Some(channel);
+        self.messages.push_back(message);
+    }
+
     /// Creates a `Message` with the default guarantees provided by the `Socket` implementation and
     /// pushes it onto the messages queue to be sent on next sim tick.
     pub fn send(&mut self, destination: SocketAddr, payload: &[u8]) {
@@ -111,6 +221,15 @@ impl TransportResource {
         self.messages.push_back(message);
     }
 
+    /// Encodes a payload with the configured compression right before a message based transport
+    /// sends it, recording the bytes saved.
+    pub(crate) fn compress(&mut self, payload: &[u8]) -> Bytes {
+        let compressed = self.compression.compress(payload);
+        self.compression_stats
+            .record(payload.len(), compressed.len());
+        compressed
+    }
+
     /// Returns true if there are messages enqueued to be sent.
     pub fn has_messages(&self) -> bool {
         !self.messages.is_empty()
@@ -122,39 +241,119 @@ impl TransportResource {
     }
 
     /// Returns the messages to send by returning the immediate messages or anything adhering to
-    /// the given filter.
+    /// the given filter. Messages sent on a channel are limited by the channel budget of each
+    /// destination, keeping the messages queued after the first one which didn't fit, and the
+    /// returned messages are ordered by descending channel priority. When the conditioner is
+    /// enabled, messages it held back are returned once they are due.
     pub fn drain_messages_to_send(
         &mut self,
         mut filter: impl FnMut(&mut Message) -> bool,
     ) -> Vec<Message> {
-        self.drain_messages(|message| {
-            message.urgency == UrgencyRequirement::Immediate || filter(message)
-        })
+        let channels = &self.channels;
+        let mut spent_bytes = HashMap::<(SocketAddr, ChannelId), usize>::new();
+        let mut exhausted = HashSet::<(SocketAddr, ChannelId)>::new();
+        let drained = drain_messages(&mut self.messages, |message| {
+            if message.urgency != UrgencyRequirement::Immediate && !filter(message) {
+                return false;
+            }
+            let budget = message
+                .channel
+                .and_then(|id| channels.get(&id).and_then(|config| config.budget_bytes));
+            match (message.channel, budget) {
+                (Some(id), Some(budget)) => {
+                    let key = (message.destination, id);
+                    // later messages would overtake the queued one on ordered channels
+                    if exhausted.contains(&key) {
+                        return false;
+                    }
+                    let spent = spent_bytes.entry(key).or_insert(0);
+                    if *spent > 0 && *spent + message.payload.len() > budget {
+                        exhausted.insert(key);
+                        return false;
+                    }
+                    *spent += message.payload.len();
+                    true
+                }
+                _ => true,
+            }
+        });
+        let mut drained = self.conditioner.condition_outgoing(drained);
+        // Stable sort, so messages of the same priority keep their queue order.
+        drained.sort_by_key(|message| {
+            Reverse(
+                message
+                    .channel
+                    .and_then(|id| channels.get(&id))
+                    .map_or(0, |config| config.priority),
+            )
+        });
+        drained
     }
 
     /// Drains the messages queue and returns the drained messages. The filter allows you to drain
     /// only messages that adhere to your filter. This might be useful in a scenario like draining
     /// messages with a particular urgency requirement.
-    pub fn drain_messages(&mut self, mut filter: impl FnMut(&mut Message) -> bool) -> Vec<Message> {
-        let mut drained = Vec::with_capacity(self.messages.len());
-        let mut i = 0;
-        while i != self.messages.len() {
-            if filter(&mut self.messages[i]) {
-                if let Some(m) = self.messages.remove(i) {
-                    drained.push(m);
-                }
-            } else {
-                i += 1;
+    pub fn drain_messages(&mut self, filter: impl FnMut(&mut Message) -> bool) -> Vec<Message> {
+        drain_messages(&mut self.messages, filter)
+    }
+}
+
+/// Adds the `NetworkStatsSystem` after the send and receive systems of a transport bundle.
+fn add_network_stats_system(world: &mut World, builder: &mut DispatcherBuilder<'_, '_>) {
+    let reader = world
+        .entry::<EventChannel<NetworkSimulationEvent>>()
+        .or_insert_with(EventChannel::new)
+        .register_reader();
+    builder.add(
+        NetworkStatsSystem::new(reader),
+        NETWORK_STATS_SYSTEM_NAME,
+        &[NETWORK_SEND_SYSTEM_NAME, NETWORK_RECV_SYSTEM_NAME],
+    );
+}
+
+fn check_channel(
+    guarantees: TransportGuarantees,
+    id: ChannelId,
+    config: &ChannelConfig,
+) -> Result<(), Error> {
+    match guarantees.delivery(config.delivery) {
+        Some(_) => Ok(()),
+        None => Err(format_err!(
+            "Channel {} needs {:?} delivery, which isn't supported by the transport ({:?})",
+            id.0,
+            config.delivery,
+            guarantees
+        )),
+    }
+}
+
+fn drain_messages(
+    messages: &mut VecDeque<Message>,
+    mut filter: impl FnMut(&mut Message) -> bool,
+) -> Vec<Message> {
+    let mut drained = Vec::with_capacity(messages.len());
+    let mut i = 0;
+    while i != messages.len() {
+        if filter(&mut messages[i]) {
+            if let Some(m) = messages.remove(i) {
+                drained.push(m);
             }
+        } else {
+            i += 1;
         }
-        drained
     }
+    drained
 }
 
 impl Default for TransportResource {
     fn default() -> Self {
         Self {
             messages: VecDeque::new(),
+            channels: HashMap::new(),
+            guarantees: TransportGuarantees::default(),
+            compression: CompressionConfig::default(),
+            compression_stats: CompressionStats::default(),
+            conditioner: NetworkConditioner::new(),
             frame_budget_bytes: 0,
             latency_nanos: 0,
             packet_loss: 0.0,
@@ -292,6 +491,192 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_send_on_channel_uses_channel_requirements() {
+        let mut resource = create_test_resource();
+        let addr = "127.0.0.1:3000".parse().unwrap();
+        resource
+            .register_channel(ChannelId(2), ChannelConfig::unreliable_sequenced())
+            .unwrap();
+
+        resource.send_on_channel(addr, ChannelId(2), test_payload());
+        resource.send_on_channel(addr, ChannelId(5), test_payload());
+
+        assert_eq!(
+            resource.messages[0].delivery,
+            DeliveryRequirement::UnreliableSequenced(Some(2))
+        );
+        assert_eq!(resource.messages[0].channel, Some(ChannelId(2)));
+        assert_eq!(resource.messages[1].delivery, DeliveryRequirement::Default);
+        assert_eq!(resource.messages[1].channel, Some(ChannelId(5)));
+    }
+
+    #[test]
+    fn test_drain_orders_by_channel_priority() {
+        let mut resource = create_test_resource();
+        let addr = "127.0.0.1:3000".parse().unwrap();
+        let chat = ChannelId(0);
+        let input = ChannelId(1);
+        resource
+            .register_channel(chat, ChannelConfig::reliable_ordered())
+            .unwrap();
+        resource
+            .register_channel(
+                input,
+                ChannelConfig::unreliable_sequenced().with_priority(10),
+            )
+            .unwrap();
+
+        resource.send_on_channel(addr, chat, b"chat");
+        resource.send(addr, b"plain");
+        resource.send_on_channel(addr, input, b"input");
+
+        let drained = resource.drain_messages_to_send(|_| true);
+        let payloads = drained
+            .iter()
+            .map(|message| &message.payload[..])
+            .collect::<Vec<_>>();
+        assert_eq!(payloads, vec![&b"input"[..], &b"chat"[..], &b"plain"[..]]);
+    }
+
+    #[test]
+    fn test_drain_respects_channel_budget() {
+        let mut resource = create_test_resource();
+        let addr = "127.0.0.1:3000".parse().unwrap();
+        let snapshots = ChannelId(0);
+        resource
+            .register_channel(
+                snapshots,
+                ChannelConfig::reliable_unordered().with_budget_bytes(10),
+            )
+            .unwrap();
+
+        for _ in 0..3 {
+            resource.send_on_channel(addr, snapshots, b"12345678");
+        }
+
+        assert_eq!(resource.drain_messages_to_send(|_| true).len(), 1);
+        assert_eq!(resource.drain_messages_to_send(|_| true).len(), 1);
+        assert_eq!(resource.drain_messages_to_send(|_| true).len(), 1);
+        assert!(!resource.has_messages());
+    }
+
+    #[test]
+    fn test_channel_budget_is_per_destination_and_keeps_order() {
+        let mut resource = create_test_resource();
+        let busy = "127.0.0.1:3000".parse().unwrap();
+        let quiet = "127.0.0.1:3001".parse().unwrap();
+        let snapshots = ChannelId(0);
+        resource
+            .register_channel(
+                snapshots,
+                ChannelConfig::reliable_ordered().with_budget_bytes(10),
+            )
+            .unwrap();
+
+        resource.send_on_channel(busy, snapshots, b"12345678");
+        resource.send_on_channel(busy, snapshots, b"12345678");
+        resource.send_on_channel(busy, snapshots, b"12");
+        resource.send_on_channel(quiet, snapshots, b"12345678");
+
+        let drained = resource.drain_messages_to_send(|_| true);
+        let sent = drained
+            .iter()
+            .map(|message| (message.destination, message.payload.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(sent, vec![(busy, 8), (quiet, 8)]);
+
+        let drained = resource.drain_messages_to_send(|_| true);
+        let sent = drained
+            .iter()
+            .map(|message| message.payload.len())
+            .collect::<Vec<_>>();
+        assert_eq!(sent, vec![8, 2]);
+    }
+
+    #[test]
+    fn test_stream_transports_send_channels_without_stream_ids() {
+        let mut resource = create_test_resource();
+        let addr = "127.0.0.1:3000".parse().unwrap();
+        resource
+            .register_channel(ChannelId(1), ChannelConfig::reliable_ordered())
+            .unwrap();
+        resource
+            .set_guarantees(TransportGuarantees::Stream)
+            .unwrap();
+        resource
+            .register_channel(ChannelId(2), ChannelConfig::unreliable_sequenced())
+            .unwrap();
+
+        resource.send_on_channel(addr, ChannelId(1), test_payload());
+        resource.send_on_channel(addr, ChannelId(2), test_payload());
+        resource.send_on_channel(addr, ChannelId(3), test_payload());
+
+        let deliveries = resource
+            .messages
+            .iter()
+            .map(|message| message.delivery)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deliveries,
+            vec![
+                DeliveryRequirement::ReliableOrdered(None),
+                DeliveryRequirement::ReliableOrdered(None),
+                DeliveryRequirement::Default,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_channels_are_rejected() {
+        let mut resource = create_test_resource();
+        resource
+            .register_channel(ChannelId(1), ChannelConfig::reliable_unordered())
+            .unwrap();
+        assert!(resource
+            .set_guarantees(TransportGuarantees::Unreliable)
+            .is_err());
+        assert_eq!(resource.guarantees(), TransportGuarantees::All);
+
+        let mut resource = create_test_resource();
+        resource
+            .set_guarantees(TransportGuarantees::Unreliable)
+            .unwrap();
+        assert!(resource
+            .register_channel(ChannelId(1), ChannelConfig::reliable_ordered())
+            .is_err());
+        assert!(resource.channel(ChannelId(1)).is_none());
+        resource
+            .register_channel(
+                ChannelId(2),
+                ChannelConfig::new(DeliveryRequirement::Unreliable),
+            )
+            .unwrap();
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_payloads_are_compressed() {
+        use crate::simulation::compression::Compression;
+
+        let mut resource = create_test_resource();
+        let addr = "127.0.0.1:3000".parse().unwrap();
+        resource.set_compression(CompressionConfig::new(Compression::Lz4));
+
+        resource.send(addr, &[1; 256]);
+
+        // Payloads are queued as is, since the TCP transport can't decode them.
+        assert_eq!(&resource.messages[0].payload[..], &[1; 256][..]);
+        let payload = resource.messages[0].payload.clone();
+        let compressed = resource.compress(&payload);
+        assert!(compressed.len() < 256);
+        assert!(resource.compression_stats().bytes_saved() > 0);
+        assert_eq!(
+            &resource.compression().decompress(&compressed).unwrap()[..],
+            &[1; 256][..]
+        );
+    }
+
     fn test_payload() -> &'static [u8] {
         b"test"
     }
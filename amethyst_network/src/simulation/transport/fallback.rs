@@ -0,0 +1,346 @@
+//! Network systems implementation sending over UDP, falling back to TCP for peers which can't be
+//! reached over UDP, e.g. behind firewalls or proxies blocking UDP traffic.
+//!
+//! A peer is switched to TCP if it didn't answer any message within the fallback timeout, or if
+//! it was routed over TCP with `TransportRoutes::set_transport`. Servers have to listen for both
+//! protocols on the same address. Clients which fell back show up on the server with the address
+//! of their TCP stream.
+
+use crate::simulation::{
+    channel::TransportGuarantees,
+    events::NetworkSimulationEvent,
+    requirements::DeliveryRequirement,
+    stats::NetworkStats,
+    timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
+    transport::{
+        add_network_stats_system,
+        tcp::{
+            connect_stream, remove_inactive_streams, write_message, TcpConnectionListenerSystem,
+            TcpNetworkRecvSystem, TcpNetworkResource, CONNECTION_LISTENER_SYSTEM_NAME,
+            STREAM_MANAGEMENT_SYSTEM_NAME,
+        },
+        udp::{UdpNetworkRecvSystem, UdpSocketResource},
+        TransportResource, NETWORK_RECV_SYSTEM_NAME, NETWORK_SEND_SYSTEM_NAME,
+        NETWORK_SIM_TIME_SYSTEM_NAME,
+    },
+};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{DispatcherBuilder, Read, System, World, Write},
+    shrev::{EventChannel, ReaderId},
+};
+use amethyst_error::Error;
+use log::info;
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener, UdpSocket},
+    time::{Duration, Instant},
+};
+
+const UDP_RECV_SYSTEM_NAME: &str = "network_recv_udp";
+
+/// The protocol used to talk to a peer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransportKind {
+    /// Unreliable datagrams, the default.
+    Udp,
+    /// A reliable, ordered stream.
+    Tcp,
+}
+
+/// Use this network bundle to add the UDP transport layer with TCP fallback to your game.
+pub struct FallbackNetworkBundle {
+    socket: Option<UdpSocket>,
+    listener: Option<TcpListener>,
+    recv_buffer_size_bytes: usize,
+    fallback_timeout: Option<Duration>,
+}
+
+impl FallbackNetworkBundle {
+    pub fn new(
+        socket: Option<UdpSocket>,
+        listener: Option<TcpListener>,
+        recv_buffer_size_bytes: usize,
+    ) -> Self {
+        Self {
+            socket,
+            listener,
+            recv_buffer_size_bytes,
+            fallback_timeout: Some(Duration::from_secs(3)),
+        }
+    }
+
+    /// Sets how long a peer may leave our UDP messages unanswered before we switch to TCP. `None`
+    /// disables the automatic fallback.
+    pub fn with_fallback_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.fallback_timeout = timeout;
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for FallbackNetworkBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        world
+            .entry::<TransportResource>()
+            .or_insert_with(Default::default)
+            .set_guarantees(TransportGuarantees::Unreliable)?;
+        builder.add(
+            NetworkSimulationTimeSystem,
+            NETWORK_SIM_TIME_SYSTEM_NAME,
+            &[],
+        );
+        builder.add(
+            TcpConnectionListenerSystem,
+            CONNECTION_LISTENER_SYSTEM_NAME,
+            &[NETWORK_SIM_TIME_SYSTEM_NAME],
+        );
+        builder.add(
+            FallbackStreamManagementSystem,
+            STREAM_MANAGEMENT_SYSTEM_NAME,
+            &[NETWORK_SIM_TIME_SYSTEM_NAME],
+        );
+        builder.add(
+            UdpNetworkRecvSystem::with_buffer_capacity(self.recv_buffer_size_bytes),
+            UDP_RECV_SYSTEM_NAME,
+            &[NETWORK_SIM_TIME_SYSTEM_NAME],
+        );
+        builder.add(
+            TcpNetworkRecvSystem,
+            NETWORK_RECV_SYSTEM_NAME,
+            &[
+                UDP_RECV_SYSTEM_NAME,
+                STREAM_MANAGEMENT_SYSTEM_NAME,
+                CONNECTION_LISTENER_SYSTEM_NAME,
+            ],
+        );
+        let reader = world
+            .entry::<EventChannel<NetworkSimulationEvent>>()
+            .or_insert_with(EventChannel::new)
+            .register_reader();
+        builder.add(
+            FallbackNetworkSendSystem::new(reader),
+            NETWORK_SEND_SYSTEM_NAME,
+            &[
+                STREAM_MANAGEMENT_SYSTEM_NAME,
+                CONNECTION_LISTENER_SYSTEM_NAME,
+            ],
+        );
+        add_network_stats_system(world, builder);
+
+        world.insert(UdpSocketResource::new(self.socket));
+        world.insert(TcpNetworkResource::new(
+            self.listener,
+            self.recv_buffer_size_bytes,
+        ));
+        world.insert(TransportRoutes::new(self.fallback_timeout));
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Route {
+    kind: TransportKind,
+    answered: bool,
+    first_unanswered_send: Option<Instant>,
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Self {
+            kind: TransportKind::Udp,
+            answered: false,
+            first_unanswered_send: None,
+        }
+    }
+}
+
+/// Resource deciding which protocol is used to talk to each peer.
+#[derive(Debug, Default)]
+pub struct TransportRoutes {
+    fallback_timeout: Option<Duration>,
+    routes: HashMap<SocketAddr, Route>,
+}
+
+impl TransportRoutes {
+    /// Creates the routes with the given fallback timeout. `None` disables the automatic
+    /// fallback.
+    pub fn new(fallback_timeout: Option<Duration>) -> Self {
+        Self {
+            fallback_timeout,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Returns the protocol used to talk to the given peer.
+    pub fn transport(&self, addr: SocketAddr) -> TransportKind {
+        self.routes
+            .get(&addr)
+            .map_or(TransportKind::Udp, |route| route.kind)
+    }
+
+    /// Selects the protocol used to talk to the given peer, e.g. before connecting to a server
+    /// known to be unreachable over UDP. Peers routed over UDP still fall back to TCP if they
+    /// don't answer.
+    pub fn set_transport(&mut self, addr: SocketAddr, kind: TransportKind) {
+        *self.routes.entry(addr).or_default() = Route {
+            kind,
+            ..Route::default()
+        };
+    }
+
+    /// Records that the given peer answered over UDP, so it won't fall back to TCP.
+    pub fn record_answer(&mut self, addr: SocketAddr) {
+        self.routes.entry(addr).or_default().answered = true;
+    }
+
+    /// Returns the protocol to send a message to the given peer with, falling back to TCP if the
+    /// peer didn't answer in time.
+    fn route(&mut self, addr: SocketAddr, now: Instant) -> TransportKind {
+        let route = self.routes.entry(addr).or_default();
+        if route.kind == TransportKind::Udp && !route.answered {
+            if let Some(timeout) = self.fallback_timeout {
+                let since = *route.first_unanswered_send.get_or_insert(now);
+                if now.duration_since(since) >= timeout {
+                    info!("{} didn't answer over UDP, falling back to TCP", addr);
+                    route.kind = TransportKind::Tcp;
+                }
+            }
+        }
+        route.kind
+    }
+}
+
+/// System opening the TCP streams of the peers routed over TCP.
+pub struct FallbackStreamManagementSystem;
+
+impl<'s> System<'s> for FallbackStreamManagementSystem {
+    type SystemData = (
+        Write<'s, TcpNetworkResource>,
+        Read<'s, TransportResource>,
+        Read<'s, TransportRoutes>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (mut net, transport, routes, mut event_channel): Self::SystemData) {
+        for message in transport.get_messages() {
+            if routes.transport(message.destination) == TransportKind::Tcp {
+                connect_stream(&mut net, message.destination, &mut event_channel);
+            }
+        }
+        remove_inactive_streams(&mut net, &mut event_channel);
+    }
+}
+
+/// System sending each message over the protocol of its destination.
+pub struct FallbackNetworkSendSystem {
+    reader: ReaderId<NetworkSimulationEvent>,
+}
+
+impl FallbackNetworkSendSystem {
+    pub fn new(reader: ReaderId<NetworkSimulationEvent>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'s> System<'s> for FallbackNetworkSendSystem {
+    type SystemData = (
+        Write<'s, TransportResource>,
+        Write<'s, TransportRoutes>,
+        Write<'s, UdpSocketResource>,
+        Write<'s, TcpNetworkResource>,
+        Read<'s, NetworkSimulationTime>,
+        Write<'s, NetworkStats>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut transport, mut routes, mut socket, mut net, sim_time, mut stats, mut channel) =
+            data;
+        for event in channel.read(&mut self.reader) {
+            if let NetworkSimulationEvent::Message(addr, _) = event {
+                routes.record_answer(*addr);
+            }
+        }
+
+        let now = Instant::now();
+        let messages = transport.drain_messages_to_send(|_| sim_time.should_send_message_now());
+        for mut message in messages {
+            // Peers which connected to our listener can only be reached over their stream, which
+            // carries payloads uncompressed.
+            if net.get_stream(message.destination).is_some()
+                || routes.route(message.destination, now) == TransportKind::Tcp
+            {
+                write_message(message, &mut net, &mut stats, &mut channel);
+                continue;
+            }
+            message.payload = transport.compress(&message.payload);
+            let socket = match socket.get_mut() {
+                Some(socket) => socket,
+                None => continue,
+            };
+            match message.delivery {
+                DeliveryRequirement::Unreliable | DeliveryRequirement::Default => {
+                    match socket.send_to(&message.payload, message.destination) {
+                        Ok(len) => stats.record_sent(message.destination, len),
+                        Err(e) => {
+                            channel.single_write(NetworkSimulationEvent::SendError(e, message))
+                        }
+                    }
+                }
+                delivery => panic!(
+                    "{:?} is unsupported over UDP, route the peer over TCP instead.",
+                    delivery
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:3000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_unanswered_peers_fall_back_to_tcp() {
+        let mut routes = TransportRoutes::new(Some(Duration::from_secs(3)));
+        let now = Instant::now();
+        assert_eq!(routes.route(addr(), now), TransportKind::Udp);
+        assert_eq!(
+            routes.route(addr(), now + Duration::from_secs(2)),
+            TransportKind::Udp
+        );
+        assert_eq!(
+            routes.route(addr(), now + Duration::from_secs(3)),
+            TransportKind::Tcp
+        );
+        assert_eq!(routes.transport(addr()), TransportKind::Tcp);
+    }
+
+    #[test]
+    fn test_answered_peers_stay_on_udp() {
+        let mut routes = TransportRoutes::new(Some(Duration::from_secs(3)));
+        let now = Instant::now();
+        routes.route(addr(), now);
+        routes.record_answer(addr());
+        assert_eq!(
+            routes.route(addr(), now + Duration::from_secs(10)),
+            TransportKind::Udp
+        );
+
+        let mut routes = TransportRoutes::new(None);
+        assert_eq!(
+            routes.route(addr(), now + Duration::from_secs(10)),
+            TransportKind::Udp
+        );
+        routes.set_transport(addr(), TransportKind::Tcp);
+        assert_eq!(routes.route(addr(), now), TransportKind::Tcp);
+    }
+}
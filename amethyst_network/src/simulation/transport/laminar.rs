@@ -1,12 +1,14 @@
 //! Network systems implementation backed by the Laminar network protocol.
 
 use crate::simulation::{
+    channel::TransportGuarantees,
     events::NetworkSimulationEvent,
     requirements::DeliveryRequirement,
+    stats::NetworkStats,
     timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
     transport::{
-        TransportResource, NETWORK_POLL_SYSTEM_NAME, NETWORK_RECV_SYSTEM_NAME,
-        NETWORK_SEND_SYSTEM_NAME, NETWORK_SIM_TIME_SYSTEM_NAME,
+        add_network_stats_system, TransportResource, NETWORK_POLL_SYSTEM_NAME,
+        NETWORK_RECV_SYSTEM_NAME, NETWORK_SEND_SYSTEM_NAME, NETWORK_SIM_TIME_SYSTEM_NAME,
     },
 };
 use amethyst_core::{
@@ -16,9 +18,8 @@ use amethyst_core::{
 };
 use amethyst_error::Error;
 pub use laminar::{Config as LaminarConfig, ErrorKind, Socket as LaminarSocket};
-use laminar::{Packet, SocketEvent};
+use laminar::{DeliveryGuarantee, Packet, SocketEvent};
 
-use bytes::Bytes;
 use log::error;
 use std::time::Instant;
 
@@ -39,6 +40,10 @@ impl<'a, 'b> SystemBundle<'a, 'b> for LaminarNetworkBundle {
         world: &mut World,
         builder: &mut DispatcherBuilder<'_, '_>,
     ) -> Result<(), Error> {
+        world
+            .entry::<TransportResource>()
+            .or_insert_with(Default::default)
+            .set_guarantees(TransportGuarantees::All)?;
         builder.add(
             NetworkSimulationTimeSystem,
             NETWORK_SIM_TIME_SYSTEM_NAME,
@@ -62,6 +67,8 @@ impl<'a, 'b> SystemBundle<'a, 'b> for LaminarNetworkBundle {
             &[NETWORK_POLL_SYSTEM_NAME],
         );
 
+        add_network_stats_system(world, builder);
+
         world.insert(LaminarSocketResource::new(self.socket));
         Ok(())
     }
@@ -74,14 +81,19 @@ impl<'s> System<'s> for LaminarNetworkSendSystem {
         Write<'s, TransportResource>,
         Write<'s, LaminarSocketResource>,
         Read<'s, NetworkSimulationTime>,
+        Write<'s, NetworkStats>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
     );
 
-    fn run(&mut self, (mut transport, mut socket, sim_time, mut event_channel): Self::SystemData) {
+    fn run(
+        &mut self,
+        (mut transport, mut socket, sim_time, mut stats, mut event_channel): Self::SystemData,
+    ) {
         if let Some(socket) = socket.get_mut() {
             let messages = transport.drain_messages_to_send(|_| sim_time.should_send_message_now());
 
-            for message in messages {
+            for mut message in messages {
+                message.payload = transport.compress(&message.payload);
                 let packet = match message.delivery {
                     DeliveryRequirement::Unreliable => {
                         Packet::unreliable(message.destination, message.payload.to_vec())
@@ -122,7 +134,7 @@ impl<'s> System<'s> for LaminarNetworkSendSystem {
                     Err(e) => {
                         error!("Error sending message: {:?}", e);
                     }
-                    Ok(_) => {}
+                    Ok(_) => stats.record_sent(message.destination, message.payload.len()),
                 }
             }
         }
@@ -146,23 +158,35 @@ struct LaminarNetworkRecvSystem;
 impl<'s> System<'s> for LaminarNetworkRecvSystem {
     type SystemData = (
         Write<'s, LaminarSocketResource>,
+        Write<'s, TransportResource>,
+        Write<'s, NetworkStats>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
     );
 
-    fn run(&mut self, (mut socket, mut event_channel): Self::SystemData) {
+    fn run(&mut self, (mut socket, mut transport, mut stats, mut event_channel): Self::SystemData) {
         if let Some(socket) = socket.get_mut() {
             while let Some(event) = socket.recv() {
-                let event = match event {
-                    SocketEvent::Packet(packet) => NetworkSimulationEvent::Message(
-                        packet.addr(),
-                        Bytes::copy_from_slice(packet.payload()),
-                    ),
-                    SocketEvent::Connect(addr) => NetworkSimulationEvent::Connect(addr),
-                    SocketEvent::Timeout(addr) => NetworkSimulationEvent::Disconnect(addr),
+                let (event, reliable) = match event {
+                    SocketEvent::Packet(packet) => {
+                        stats.record_received(packet.addr(), packet.payload().len());
+                        let reliable = packet.delivery_guarantee() == DeliveryGuarantee::Reliable;
+                        let event = match transport.compression().decompress(packet.payload()) {
+                            Ok(payload) => NetworkSimulationEvent::Message(packet.addr(), payload),
+                            Err(e) => NetworkSimulationEvent::RecvError(e),
+                        };
+                        (event, reliable)
+                    }
+                    SocketEvent::Connect(addr) => (NetworkSimulationEvent::Connect(addr), true),
+                    SocketEvent::Timeout(addr) => (NetworkSimulationEvent::Disconnect(addr), true),
                 };
-                event_channel.single_write(event);
+                transport
+                    .conditioner_mut()
+                    .condition_incoming(event, reliable, &mut event_channel);
             }
         }
+        transport
+            .conditioner_mut()
+            .release_incoming(&mut event_channel);
     }
 }
 
@@ -1,13 +1,15 @@
 //! Network systems implementation backed by the TCP network protocol.
 
 use crate::simulation::{
+    channel::TransportGuarantees,
     events::NetworkSimulationEvent,
     message::Message,
     requirements::DeliveryRequirement,
+    stats::NetworkStats,
     timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
     transport::{
-        TransportResource, NETWORK_RECV_SYSTEM_NAME, NETWORK_SEND_SYSTEM_NAME,
-        NETWORK_SIM_TIME_SYSTEM_NAME,
+        add_network_stats_system, TransportResource, NETWORK_RECV_SYSTEM_NAME,
+        NETWORK_SEND_SYSTEM_NAME, NETWORK_SIM_TIME_SYSTEM_NAME,
     },
 };
 use amethyst_core::{
@@ -25,8 +27,8 @@ use std::{
     ops::DerefMut,
 };
 
-const CONNECTION_LISTENER_SYSTEM_NAME: &str = "connection_listener";
-const STREAM_MANAGEMENT_SYSTEM_NAME: &str = "stream_management";
+pub(super) const CONNECTION_LISTENER_SYSTEM_NAME: &str = "connection_listener";
+pub(super) const STREAM_MANAGEMENT_SYSTEM_NAME: &str = "stream_management";
 
 /// Use this network bundle to add the TCP transport layer to your game.
 pub struct TcpNetworkBundle {
@@ -49,6 +51,10 @@ impl<'a, 'b> SystemBundle<'a, 'b> for TcpNetworkBundle {
         world: &mut World,
         builder: &mut DispatcherBuilder<'_, '_>,
     ) -> Result<(), Error> {
+        world
+            .entry::<TransportResource>()
+            .or_insert_with(Default::default)
+            .set_guarantees(TransportGuarantees::Stream)?;
         // NetworkSimulationTime should run first
         // followed by TcpConnectionListenerSystem and TcpStreamManagementSystem
         // then TcpNetworkSendSystem and TcpNetworkRecvSystem
@@ -89,6 +95,8 @@ impl<'a, 'b> SystemBundle<'a, 'b> for TcpNetworkBundle {
             ],
         );
 
+        add_network_stats_system(world, builder);
+
         world.insert(TcpNetworkResource::new(
             self.listener,
             self.recv_buffer_size_bytes,
@@ -107,37 +115,47 @@ impl<'s> System<'s> for TcpStreamManagementSystem {
         Write<'s, EventChannel<NetworkSimulationEvent>>,
     );
 
-    // We cannot use `net.streams.entry(message.destination).or_insert_with(|| { .. })` because
-    // there is a `return;` statement for early exit, which is not allowed within the closure.
-    #[allow(clippy::map_entry)]
     fn run(&mut self, (mut net, transport, mut event_channel): Self::SystemData) {
         // Make connections for each message in the channel if one hasn't yet been established
         transport.get_messages().iter().for_each(|message| {
-            if !net.streams.contains_key(&message.destination) {
-                let s = match TcpStream::connect(message.destination) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        event_channel.single_write(NetworkSimulationEvent::ConnectionError(
-                            e,
-                            Some(message.destination),
-                        ));
-                        return;
-                    }
-                };
-                s.set_nonblocking(true).expect("Setting non-blocking mode");
-                s.set_nodelay(true).expect("Setting nodelay");
-                net.streams.insert(message.destination, (true, s));
-            }
+            connect_stream(&mut net, message.destination, &mut event_channel);
         });
+        remove_inactive_streams(&mut net, &mut event_channel);
+    }
+}
 
-        // Remove inactive connections
-        net.streams.retain(|addr, (active, _)| {
-            if !*active {
-                event_channel.single_write(NetworkSimulationEvent::Disconnect(*addr));
-            }
-            *active
-        });
+/// Opens a stream to the given peer if there is none yet.
+pub(super) fn connect_stream(
+    net: &mut TcpNetworkResource,
+    addr: SocketAddr,
+    event_channel: &mut EventChannel<NetworkSimulationEvent>,
+) {
+    if net.streams.contains_key(&addr) {
+        return;
     }
+    let s = match TcpStream::connect(addr) {
+        Ok(s) => s,
+        Err(e) => {
+            event_channel.single_write(NetworkSimulationEvent::ConnectionError(e, Some(addr)));
+            return;
+        }
+    };
+    s.set_nonblocking(true).expect("Setting non-blocking mode");
+    s.set_nodelay(true).expect("Setting nodelay");
+    net.streams.insert(addr, (true, s));
+}
+
+/// Drops the streams which were marked inactive by the receiving system.
+pub(super) fn remove_inactive_streams(
+    net: &mut TcpNetworkResource,
+    event_channel: &mut EventChannel<NetworkSimulationEvent>,
+) {
+    net.streams.retain(|addr, (active, _)| {
+        if !*active {
+            event_channel.single_write(NetworkSimulationEvent::Disconnect(*addr));
+        }
+        *active
+    });
 }
 
 /// System to listen for incoming connections and cache them to the resource.
@@ -184,19 +202,23 @@ impl<'s> System<'s> for TcpNetworkSendSystem {
         Write<'s, TransportResource>,
         Write<'s, TcpNetworkResource>,
         Read<'s, NetworkSimulationTime>,
+        Write<'s, NetworkStats>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
     );
 
-    fn run(&mut self, (mut transport, mut net, sim_time, mut channel): Self::SystemData) {
+    fn run(
+        &mut self,
+        (mut transport, mut net, sim_time, mut stats, mut channel): Self::SystemData,
+    ) {
         let messages = transport.drain_messages_to_send(|_| sim_time.should_send_message_now());
         for message in messages {
             match message.delivery {
                 DeliveryRequirement::ReliableOrdered(Some(_)) => {
                     warn!("Streams are not supported by TCP and will be ignored.");
-                    write_message(message, &mut net, &mut channel);
+                    write_message(message, &mut net, &mut stats, &mut channel);
                 }
                 DeliveryRequirement::ReliableOrdered(_) | DeliveryRequirement::Default => {
-                    write_message(message, &mut net, &mut channel);
+                    write_message(message, &mut net, &mut stats, &mut channel);
                 }
                 delivery => panic!(
                     "{:?} is unsupported. TCP only supports ReliableOrdered by design.",
@@ -207,14 +229,16 @@ impl<'s> System<'s> for TcpNetworkSendSystem {
     }
 }
 
-fn write_message(
+pub(super) fn write_message(
     message: Message,
     net: &mut TcpNetworkResource,
+    stats: &mut NetworkStats,
     channel: &mut EventChannel<NetworkSimulationEvent>,
 ) {
     if let Some((_, stream)) = net.get_stream(message.destination) {
-        if let Err(e) = stream.write(&message.payload) {
-            channel.single_write(NetworkSimulationEvent::SendError(e, message));
+        match stream.write(&message.payload) {
+            Ok(len) => stats.record_sent(message.destination, len),
+            Err(e) => channel.single_write(NetworkSimulationEvent::SendError(e, message)),
         }
     }
 }
@@ -225,10 +249,12 @@ pub struct TcpNetworkRecvSystem;
 impl<'s> System<'s> for TcpNetworkRecvSystem {
     type SystemData = (
         Write<'s, TcpNetworkResource>,
+        Write<'s, TransportResource>,
+        Write<'s, NetworkStats>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
     );
 
-    fn run(&mut self, (mut net, mut event_channel): Self::SystemData) {
+    fn run(&mut self, (mut net, mut transport, mut stats, mut event_channel): Self::SystemData) {
         let resource = net.deref_mut();
         for (_, (active, stream)) in resource.streams.iter_mut() {
             // If we can't get a peer_addr, there is likely something pretty wrong with the
@@ -246,11 +272,16 @@ impl<'s> System<'s> for TcpNetworkRecvSystem {
                 match stream.read(&mut resource.recv_buffer) {
                     Ok(recv_len) => {
                         if recv_len > 0 {
+                            stats.record_received(peer_addr, recv_len);
                             let event = NetworkSimulationEvent::Message(
                                 peer_addr,
                                 Bytes::copy_from_slice(&resource.recv_buffer[..recv_len]),
                             );
-                            event_channel.single_write(event);
+                            transport.conditioner_mut().condition_incoming(
+                                event,
+                                true,
+                                &mut event_channel,
+                            );
                         } else {
                             *active = false;
                             break;
@@ -271,6 +302,9 @@ impl<'s> System<'s> for TcpNetworkRecvSystem {
                 }
             }
         }
+        transport
+            .conditioner_mut()
+            .release_incoming(&mut event_channel);
     }
 }
 
@@ -330,3 +364,62 @@ impl Default for TcpNetworkResource {
         }
     }
 }
+
+#[cfg(all(test, feature = "lz4"))]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{RunNow, WorldExt};
+
+    #[test]
+    fn test_message_round_trip_with_compression() {
+        use crate::simulation::compression::{Compression, CompressionConfig};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let mut world = World::new();
+        world.insert(TcpNetworkResource::new(Some(listener), 2048));
+        world.insert(EventChannel::<NetworkSimulationEvent>::new());
+        world.insert(NetworkSimulationTime::default());
+        world.insert(NetworkStats::default());
+        let mut transport = TransportResource::default();
+        transport.set_compression(CompressionConfig::new(Compression::Lz4));
+        transport.send_immediate(server_addr, &[7; 512]);
+        world.insert(transport);
+        let mut reader = world
+            .fetch_mut::<EventChannel<NetworkSimulationEvent>>()
+            .register_reader();
+
+        TcpStreamManagementSystem.run_now(&world);
+        TcpNetworkSendSystem.run_now(&world);
+        let mut received = Vec::new();
+        for _ in 0..1000 {
+            TcpConnectionListenerSystem.run_now(&world);
+            TcpNetworkRecvSystem.run_now(&world);
+            received.extend(
+                world
+                    .fetch::<EventChannel<NetworkSimulationEvent>>()
+                    .read(&mut reader)
+                    .filter_map(|event| match event {
+                        NetworkSimulationEvent::Message(_, payload) => Some(payload.clone()),
+                        _ => None,
+                    }),
+            );
+            if received.iter().map(Bytes::len).sum::<usize>() >= 512 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        // The stream carries the payload as is, without a compression header.
+        assert_eq!(received.concat(), vec![7; 512]);
+        assert_eq!(
+            world
+                .fetch::<TransportResource>()
+                .compression_stats()
+                .bytes_saved(),
+            0
+        );
+    }
+}
@@ -1,12 +1,14 @@
 //! Network systems implementation backed by the UDP network protocol.
 
 use crate::simulation::{
+    channel::TransportGuarantees,
     events::NetworkSimulationEvent,
     requirements::DeliveryRequirement,
+    stats::NetworkStats,
     timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
     transport::{
-        TransportResource, NETWORK_RECV_SYSTEM_NAME, NETWORK_SEND_SYSTEM_NAME,
-        NETWORK_SIM_TIME_SYSTEM_NAME,
+        add_network_stats_system, TransportResource, NETWORK_RECV_SYSTEM_NAME,
+        NETWORK_SEND_SYSTEM_NAME, NETWORK_SIM_TIME_SYSTEM_NAME,
     },
 };
 use amethyst_core::{
@@ -15,7 +17,6 @@ use amethyst_core::{
     shrev::EventChannel,
 };
 use amethyst_error::Error;
-use bytes::Bytes;
 use std::{io, net::UdpSocket};
 
 /// Use this network bundle to add the UDP transport layer to your game.
@@ -39,6 +40,10 @@ impl<'a, 'b> SystemBundle<'a, 'b> for UdpNetworkBundle {
         world: &mut World,
         builder: &mut DispatcherBuilder<'_, '_>,
     ) -> Result<(), Error> {
+        world
+            .entry::<TransportResource>()
+            .or_insert_with(Default::default)
+            .set_guarantees(TransportGuarantees::Unreliable)?;
         builder.add(
             NetworkSimulationTimeSystem,
             NETWORK_SIM_TIME_SYSTEM_NAME,
@@ -54,6 +59,7 @@ impl<'a, 'b> SystemBundle<'a, 'b> for UdpNetworkBundle {
             NETWORK_SEND_SYSTEM_NAME,
             &[NETWORK_SIM_TIME_SYSTEM_NAME],
         );
+        add_network_stats_system(world, builder);
 
         world.insert(UdpSocketResource::new(self.socket));
         Ok(())
@@ -67,17 +73,25 @@ impl<'s> System<'s> for UdpNetworkSendSystem {
         Write<'s, TransportResource>,
         Write<'s, UdpSocketResource>,
         Read<'s, NetworkSimulationTime>,
+        Write<'s, NetworkStats>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
     );
 
-    fn run(&mut self, (mut transport, mut socket, sim_time, mut channel): Self::SystemData) {
+    fn run(
+        &mut self,
+        (mut transport, mut socket, sim_time, mut stats, mut channel): Self::SystemData,
+    ) {
         if let Some(socket) = socket.get_mut() {
             let messages = transport.drain_messages_to_send(|_| sim_time.should_send_message_now());
-            for message in messages {
+            for mut message in messages {
+                message.payload = transport.compress(&message.payload);
                 match message.delivery {
                     DeliveryRequirement::Unreliable | DeliveryRequirement::Default => {
-                        if let Err(e) = socket.send_to(&message.payload, message.destination) {
-                            channel.single_write(NetworkSimulationEvent::SendError(e, message));
+                        match socket.send_to(&message.payload, message.destination) {
+                            Ok(len) => stats.record_sent(message.destination, len),
+                            Err(e) => {
+                                channel.single_write(NetworkSimulationEvent::SendError(e, message))
+                            }
                         }
                     }
                     delivery => panic!(
@@ -106,20 +120,30 @@ impl UdpNetworkRecvSystem {
 impl<'s> System<'s> for UdpNetworkRecvSystem {
     type SystemData = (
         Write<'s, UdpSocketResource>,
+        Write<'s, TransportResource>,
+        Write<'s, NetworkStats>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
     );
 
-    fn run(&mut self, (mut socket, mut event_channel): Self::SystemData) {
+    fn run(&mut self, (mut socket, mut transport, mut stats, mut event_channel): Self::SystemData) {
         if let Some(socket) = socket.get_mut() {
             loop {
                 match socket.recv_from(&mut self.recv_buffer) {
                     Ok((recv_len, address)) => {
-                        let event = NetworkSimulationEvent::Message(
-                            address,
-                            Bytes::copy_from_slice(&self.recv_buffer[..recv_len]),
-                        );
+                        stats.record_received(address, recv_len);
+                        let event = match transport
+                            .compression()
+                            .decompress(&self.recv_buffer[..recv_len])
+                        {
+                            Ok(payload) => NetworkSimulationEvent::Message(address, payload),
+                            Err(e) => NetworkSimulationEvent::RecvError(e),
+                        };
                         // TODO: Handle other types of events.
-                        event_channel.single_write(event);
+                        transport.conditioner_mut().condition_incoming(
+                            event,
+                            false,
+                            &mut event_channel,
+                        );
                     }
                     Err(e) => {
                         if e.kind() != io::ErrorKind::WouldBlock {
@@ -130,6 +154,9 @@ impl<'s> System<'s> for UdpNetworkRecvSystem {
                 }
             }
         }
+        transport
+            .conditioner_mut()
+            .release_incoming(&mut event_channel);
     }
 }
 
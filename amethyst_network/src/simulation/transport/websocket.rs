@@ -0,0 +1,28 @@
+//! Network systems implementation backed by the WebSocket protocol. WebSockets are the only
+//! socket-like connection available to browser builds compiled to wasm, so the same transport
+//! connects browser clients to a native server. Every message is sent as a single binary WebSocket
+//! frame.
+//!
+//! Native builds listen for connections and open outgoing ones with non-blocking sockets. wasm32
+//! builds open connections with the browser `WebSocket` API and can't listen. Both are used through
+//! the `TransportResource` like the other transports. There is no WebRTC transport.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::{
+    WebSocketConnectionListenerSystem, WebSocketNetworkBundle, WebSocketNetworkRecvSystem,
+    WebSocketNetworkResource, WebSocketNetworkSendSystem, WebSocketStreamManagementSystem,
+};
+#[cfg(target_arch = "wasm32")]
+pub use web::{
+    WebSocketNetworkBundle, WebSocketNetworkRecvSystem, WebSocketNetworkResource,
+    WebSocketNetworkSendSystem, WebSocketStreamManagementSystem,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const CONNECTION_LISTENER_SYSTEM_NAME: &str = "connection_listener";
+const STREAM_MANAGEMENT_SYSTEM_NAME: &str = "stream_management";
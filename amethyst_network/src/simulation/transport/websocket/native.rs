@@ -0,0 +1,623 @@
+//! The native WebSocket transport, listening for connections and opening outgoing ones with
+//! non-blocking sockets.
+
+use crate::simulation::{
+    channel::TransportGuarantees,
+    events::NetworkSimulationEvent,
+    message::Message,
+    requirements::DeliveryRequirement,
+    stats::NetworkStats,
+    timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
+    transport::{
+        add_network_stats_system,
+        websocket::{CONNECTION_LISTENER_SYSTEM_NAME, STREAM_MANAGEMENT_SYSTEM_NAME},
+        TransportResource, NETWORK_RECV_SYSTEM_NAME, NETWORK_SEND_SYSTEM_NAME,
+        NETWORK_SIM_TIME_SYSTEM_NAME,
+    },
+};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{DispatcherBuilder, Read, System, World, Write},
+    shrev::EventChannel,
+};
+use amethyst_error::Error;
+use bytes::Bytes;
+use log::warn;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, TcpListener, TcpStream},
+    ops::DerefMut,
+};
+use tungstenite::{
+    handshake::{
+        client::ClientHandshake,
+        server::{NoCallback, ServerHandshake},
+        HandshakeError, MidHandshake,
+    },
+    Error as WebSocketError, Message as WebSocketMessage, WebSocket,
+};
+
+type PendingHandshake = MidHandshake<ServerHandshake<TcpStream, NoCallback>>;
+type PendingClientHandshake = MidHandshake<ClientHandshake<TcpStream>>;
+
+/// Use this network bundle to add the WebSocket transport layer to your game.
+pub struct WebSocketNetworkBundle {
+    listener: Option<TcpListener>,
+}
+
+impl WebSocketNetworkBundle {
+    pub fn new(listener: Option<TcpListener>) -> Self {
+        Self { listener }
+    }
+
+    /// Creates a bundle which only opens outgoing connections, like the browser client.
+    pub fn client() -> Self {
+        Self::new(None)
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for WebSocketNetworkBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        world
+            .entry::<TransportResource>()
+            .or_insert_with(Default::default)
+            .set_guarantees(TransportGuarantees::Stream)?;
+        builder.add(
+            NetworkSimulationTimeSystem,
+            NETWORK_SIM_TIME_SYSTEM_NAME,
+            &[],
+        );
+
+        builder.add(
+            WebSocketConnectionListenerSystem,
+            CONNECTION_LISTENER_SYSTEM_NAME,
+            &[NETWORK_SIM_TIME_SYSTEM_NAME],
+        );
+
+        builder.add(
+            WebSocketStreamManagementSystem,
+            STREAM_MANAGEMENT_SYSTEM_NAME,
+            &[NETWORK_SIM_TIME_SYSTEM_NAME],
+        );
+
+        builder.add(
+            WebSocketNetworkSendSystem,
+            NETWORK_SEND_SYSTEM_NAME,
+            &[
+                STREAM_MANAGEMENT_SYSTEM_NAME,
+                CONNECTION_LISTENER_SYSTEM_NAME,
+            ],
+        );
+
+        builder.add(
+            WebSocketNetworkRecvSystem,
+            NETWORK_RECV_SYSTEM_NAME,
+            &[
+                STREAM_MANAGEMENT_SYSTEM_NAME,
+                CONNECTION_LISTENER_SYSTEM_NAME,
+            ],
+        );
+
+        if let Some(listener) = self.listener.as_ref() {
+            listener.set_nonblocking(true)?;
+        }
+        add_network_stats_system(world, builder);
+
+        world.insert(WebSocketNetworkResource::new(self.listener));
+        Ok(())
+    }
+}
+
+/// System to open client connections for outgoing messages and to remove inactive connections.
+///
+/// Connections are opened without blocking: the system starts connecting, and drives the
+/// handshake of established connections on the following frames. Messages sent in the meantime
+/// are written once the handshake completes.
+pub struct WebSocketStreamManagementSystem;
+
+impl<'s> System<'s> for WebSocketStreamManagementSystem {
+    type SystemData = (
+        Write<'s, WebSocketNetworkResource>,
+        Read<'s, TransportResource>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (mut net, transport, mut event_channel): Self::SystemData) {
+        let resource = net.deref_mut();
+        // Start connecting for each message in the channel if no connection is open or opening.
+        for message in transport.get_messages() {
+            let addr = message.destination;
+            if resource.sockets.contains_key(&addr) || resource.is_connecting(addr) {
+                continue;
+            }
+            match start_connect(addr) {
+                Ok(stream) => resource.connecting.push((addr, stream)),
+                Err(e) => event_channel
+                    .single_write(NetworkSimulationEvent::ConnectionError(e, Some(addr))),
+            }
+        }
+
+        let mut handshakes = resource
+            .client_pending
+            .drain(..)
+            .map(|(addr, mid)| (addr, mid.handshake()))
+            .collect::<Vec<_>>();
+        let mut connecting = Vec::new();
+        for (addr, stream) in resource.connecting.drain(..) {
+            match is_connected(&stream) {
+                Ok(true) => {
+                    let request = format!("ws://{}/", addr);
+                    handshakes.push((addr, tungstenite::client(request.as_str(), stream)));
+                }
+                Ok(false) => connecting.push((addr, stream)),
+                Err(e) => {
+                    resource.queued.remove(&addr);
+                    event_channel
+                        .single_write(NetworkSimulationEvent::ConnectionError(e, Some(addr)));
+                }
+            }
+        }
+        resource.connecting = connecting;
+
+        for (addr, handshake) in handshakes {
+            match handshake {
+                Ok((socket, _)) => {
+                    resource.sockets.insert(addr, (true, socket));
+                }
+                Err(HandshakeError::Interrupted(mid)) => {
+                    resource.client_pending.push((addr, mid));
+                }
+                Err(HandshakeError::Failure(e)) => {
+                    resource.queued.remove(&addr);
+                    event_channel.single_write(NetworkSimulationEvent::ConnectionError(
+                        into_io_error(e),
+                        Some(addr),
+                    ));
+                }
+            }
+        }
+
+        // Remove inactive connections
+        resource.sockets.retain(|addr, (active, _)| {
+            if !*active {
+                event_channel.single_write(NetworkSimulationEvent::Disconnect(*addr));
+            }
+            *active
+        });
+    }
+}
+
+/// Starts connecting a non-blocking socket to the address, without waiting for the connection to
+/// be established.
+fn start_connect(addr: SocketAddr) -> io::Result<TcpStream> {
+    let domain = if addr.is_ipv4() {
+        Domain::ipv4()
+    } else {
+        Domain::ipv6()
+    };
+    let socket = Socket::new(domain, Type::stream(), Some(Protocol::tcp()))?;
+    socket.set_nonblocking(true)?;
+    socket.set_nodelay(true)?;
+    match socket.connect(&SockAddr::from(addr)) {
+        Ok(()) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+        // there's no `io::ErrorKind` for a connection in progress
+        #[cfg(unix)]
+        Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) => {}
+        Err(e) => return Err(e),
+    }
+    Ok(socket.into_tcp_stream())
+}
+
+/// Returns true once a socket opened with `start_connect` is connected, or the error the
+/// connection failed with.
+fn is_connected(stream: &TcpStream) -> io::Result<bool> {
+    if let Some(e) = stream.take_error()? {
+        return Err(e);
+    }
+    match stream.peer_addr() {
+        Ok(_) => Ok(true),
+        Err(ref e) if e.kind() == io::ErrorKind::NotConnected => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// System to listen for incoming connections and to drive their handshakes to completion without
+/// blocking.
+pub struct WebSocketConnectionListenerSystem;
+
+impl<'s> System<'s> for WebSocketConnectionListenerSystem {
+    type SystemData = (
+        Write<'s, WebSocketNetworkResource>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (mut net, mut event_channel): Self::SystemData) {
+        let resource = net.deref_mut();
+        let mut handshakes = resource
+            .pending
+            .drain(..)
+            .map(|(addr, mid)| (addr, mid.handshake()))
+            .collect::<Vec<_>>();
+
+        if let Some(ref listener) = resource.listener {
+            loop {
+                match listener.accept() {
+                    Ok((stream, addr)) => {
+                        stream
+                            .set_nonblocking(true)
+                            .expect("Setting nonblocking mode");
+                        stream.set_nodelay(true).expect("Setting nodelay");
+                        handshakes.push((addr, tungstenite::accept(stream)));
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        break;
+                    }
+                    Err(e) => {
+                        event_channel
+                            .single_write(NetworkSimulationEvent::ConnectionError(e, None));
+                        break;
+                    }
+                };
+            }
+        }
+
+        for (addr, handshake) in handshakes {
+            match handshake {
+                Ok(socket) => {
+                    resource.sockets.insert(addr, (true, socket));
+                    event_channel.single_write(NetworkSimulationEvent::Connect(addr));
+                }
+                Err(HandshakeError::Interrupted(mid)) => {
+                    resource.pending.push((addr, mid));
+                }
+                Err(HandshakeError::Failure(e)) => {
+                    event_channel.single_write(NetworkSimulationEvent::ConnectionError(
+                        into_io_error(e),
+                        Some(addr),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// System to send messages to a particular open WebSocket.
+pub struct WebSocketNetworkSendSystem;
+
+impl<'s> System<'s> for WebSocketNetworkSendSystem {
+    type SystemData = (
+        Write<'s, TransportResource>,
+        Write<'s, WebSocketNetworkResource>,
+        Read<'s, NetworkSimulationTime>,
+        Write<'s, NetworkStats>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (mut transport, mut net, sim_time, mut stats, mut channel): Self::SystemData,
+    ) {
+        // Write the messages which were waiting for their connection to open.
+        let opened = net
+            .queued
+            .keys()
+            .filter(|addr| net.sockets.contains_key(addr))
+            .copied()
+            .collect::<Vec<_>>();
+        for addr in opened {
+            for message in net.queued.remove(&addr).unwrap_or_default() {
+                write_message(message, &mut net, &mut stats, &mut channel);
+            }
+        }
+
+        let messages = transport.drain_messages_to_send(|_| sim_time.should_send_message_now());
+        for mut message in messages {
+            message.payload = transport.compress(&message.payload);
+            match message.delivery {
+                DeliveryRequirement::ReliableOrdered(Some(_)) => {
+                    warn!("Streams are not supported by WebSockets and will be ignored.");
+                    write_message(message, &mut net, &mut stats, &mut channel);
+                }
+                DeliveryRequirement::ReliableOrdered(_) | DeliveryRequirement::Default => {
+                    write_message(message, &mut net, &mut stats, &mut channel);
+                }
+                delivery => panic!(
+                    "{:?} is unsupported. WebSockets only support ReliableOrdered by design.",
+                    delivery
+                ),
+            }
+        }
+
+        // Flush frames which couldn't be written completely on previous frames.
+        for (_, (active, socket)) in net.sockets.iter_mut() {
+            match socket.write_pending() {
+                Ok(()) => {}
+                Err(WebSocketError::Io(ref e)) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(WebSocketError::ConnectionClosed) | Err(WebSocketError::AlreadyClosed) => {
+                    *active = false;
+                }
+                Err(e) => {
+                    warn!("Encountered an error flushing a WebSocket: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+fn write_message(
+    message: Message,
+    net: &mut WebSocketNetworkResource,
+    stats: &mut NetworkStats,
+    channel: &mut EventChannel<NetworkSimulationEvent>,
+) {
+    let destination = message.destination;
+    let socket = match net.sockets.get_mut(&destination) {
+        Some((_, socket)) => socket,
+        None => {
+            if net.is_connecting(destination) {
+                net.queued.entry(destination).or_default().push(message);
+            }
+            return;
+        }
+    };
+    match socket.write_message(WebSocketMessage::binary(&message.payload[..])) {
+        Ok(()) => stats.record_sent(destination, message.payload.len()),
+        // The frame has been queued and will be flushed on a later frame.
+        Err(WebSocketError::Io(ref e)) if e.kind() == io::ErrorKind::WouldBlock => {
+            stats.record_sent(destination, message.payload.len())
+        }
+        Err(e) => {
+            channel.single_write(NetworkSimulationEvent::SendError(into_io_error(e), message));
+        }
+    }
+}
+
+/// System to receive messages from all open WebSockets.
+pub struct WebSocketNetworkRecvSystem;
+
+impl<'s> System<'s> for WebSocketNetworkRecvSystem {
+    type SystemData = (
+        Write<'s, WebSocketNetworkResource>,
+        Write<'s, TransportResource>,
+        Write<'s, NetworkStats>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (mut net, mut transport, mut stats, mut event_channel): Self::SystemData) {
+        for (addr, (active, socket)) in net.sockets.iter_mut() {
+            loop {
+                match socket.read_message() {
+                    Ok(WebSocketMessage::Binary(payload)) => {
+                        stats.record_received(*addr, payload.len());
+                        let event = match transport.compression().decompress(&payload) {
+                            Ok(payload) => NetworkSimulationEvent::Message(*addr, payload),
+                            Err(e) => NetworkSimulationEvent::RecvError(e),
+                        };
+                        transport.conditioner_mut().condition_incoming(
+                            event,
+                            true,
+                            &mut event_channel,
+                        );
+                    }
+                    Ok(WebSocketMessage::Text(payload)) => {
+                        stats.record_received(*addr, payload.len());
+                        transport.conditioner_mut().condition_incoming(
+                            NetworkSimulationEvent::Message(*addr, Bytes::from(payload)),
+                            true,
+                            &mut event_channel,
+                        );
+                    }
+                    // Pings are answered by tungstenite itself.
+                    Ok(WebSocketMessage::Ping(_)) | Ok(WebSocketMessage::Pong(_)) => {}
+                    Ok(WebSocketMessage::Close(_)) => {
+                        *active = false;
+                        break;
+                    }
+                    Err(WebSocketError::Io(ref e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                        break;
+                    }
+                    Err(WebSocketError::ConnectionClosed) | Err(WebSocketError::AlreadyClosed) => {
+                        *active = false;
+                        break;
+                    }
+                    Err(WebSocketError::Io(ref e))
+                        if e.kind() == io::ErrorKind::ConnectionReset =>
+                    {
+                        *active = false;
+                        break;
+                    }
+                    Err(e) => {
+                        event_channel
+                            .single_write(NetworkSimulationEvent::RecvError(into_io_error(e)));
+                        break;
+                    }
+                }
+            }
+        }
+        transport
+            .conditioner_mut()
+            .release_incoming(&mut event_channel);
+    }
+}
+
+fn into_io_error(error: WebSocketError) -> io::Error {
+    match error {
+        WebSocketError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Resource owning the WebSocket listener and all open WebSockets.
+pub struct WebSocketNetworkResource {
+    listener: Option<TcpListener>,
+    sockets: HashMap<SocketAddr, (bool, WebSocket<TcpStream>)>,
+    pending: Vec<(SocketAddr, PendingHandshake)>,
+    connecting: Vec<(SocketAddr, TcpStream)>,
+    client_pending: Vec<(SocketAddr, PendingClientHandshake)>,
+    queued: HashMap<SocketAddr, Vec<Message>>,
+}
+
+impl WebSocketNetworkResource {
+    pub fn new(listener: Option<TcpListener>) -> Self {
+        Self {
+            listener,
+            sockets: HashMap::new(),
+            pending: Vec::new(),
+            connecting: Vec::new(),
+            client_pending: Vec::new(),
+            queued: HashMap::new(),
+        }
+    }
+
+    /// Returns true while an outgoing connection to the address is being opened.
+    pub fn is_connecting(&self, addr: SocketAddr) -> bool {
+        self.connecting.iter().any(|(a, _)| *a == addr)
+            || self.client_pending.iter().any(|(a, _)| *a == addr)
+    }
+
+    /// Returns an immutable reference to the listener if there is one configured.
+    pub fn get(&self) -> Option<&TcpListener> {
+        self.listener.as_ref()
+    }
+
+    /// Returns a mutable reference to the listener if there is one configured.
+    pub fn get_mut(&mut self) -> Option<&mut TcpListener> {
+        self.listener.as_mut()
+    }
+
+    /// Sets the bound listener to the `WebSocketNetworkResource`.
+    pub fn set_listener(&mut self, listener: TcpListener) {
+        self.listener = Some(listener);
+    }
+
+    /// Drops the listener from the `WebSocketNetworkResource`.
+    pub fn drop_listener(&mut self) {
+        self.listener = None;
+    }
+
+    /// Returns a tuple of an open WebSocket and whether or not it is active.
+    pub fn get_socket(&mut self, addr: SocketAddr) -> Option<&mut (bool, WebSocket<TcpStream>)> {
+        self.sockets.get_mut(&addr)
+    }
+
+    /// Drops the WebSocket with the given `SocketAddr`. This will be called when a peer seems to
+    /// have been disconnected.
+    pub fn drop_socket(&mut self, addr: SocketAddr) -> Option<(bool, WebSocket<TcpStream>)> {
+        self.sockets.remove(&addr)
+    }
+}
+
+impl Default for WebSocketNetworkResource {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{RunNow, WorldExt};
+
+    #[test]
+    fn test_message_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        let mut server = World::new();
+        server.insert(WebSocketNetworkResource::new(Some(listener)));
+        server.insert(EventChannel::<NetworkSimulationEvent>::new());
+        server.insert(TransportResource::default());
+        server.insert(NetworkStats::default());
+        let mut reader = server
+            .fetch_mut::<EventChannel<NetworkSimulationEvent>>()
+            .register_reader();
+
+        let mut client = World::new();
+        client.insert(WebSocketNetworkResource::default());
+        client.insert(EventChannel::<NetworkSimulationEvent>::new());
+        client.insert(TransportResource::default());
+        client.insert(NetworkStats::default());
+        client.insert(NetworkSimulationTime::default());
+        client
+            .fetch_mut::<TransportResource>()
+            .send_immediate(server_addr, b"ping");
+
+        // Both peers run on this thread, since neither connecting nor the handshakes block.
+        let mut stream_system = WebSocketStreamManagementSystem;
+        let mut send_system = WebSocketNetworkSendSystem;
+        let mut listener_system = WebSocketConnectionListenerSystem;
+        let mut recv_system = WebSocketNetworkRecvSystem;
+        let mut received = Vec::new();
+        for _ in 0..1000 {
+            stream_system.run_now(&client);
+            send_system.run_now(&client);
+            listener_system.run_now(&server);
+            recv_system.run_now(&server);
+            received.extend(
+                server
+                    .fetch::<EventChannel<NetworkSimulationEvent>>()
+                    .read(&mut reader)
+                    .filter_map(|event| match event {
+                        NetworkSimulationEvent::Message(_, payload) => Some(payload.clone()),
+                        _ => None,
+                    }),
+            );
+            if !received.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        assert_eq!(received, vec![Bytes::from_static(b"ping")]);
+        assert!(!client
+            .fetch::<WebSocketNetworkResource>()
+            .is_connecting(server_addr));
+        let stats = server.fetch::<NetworkStats>();
+        let (_, connection) = stats.connections().next().unwrap();
+        assert_eq!(connection.total_bytes_received, 4);
+    }
+
+    #[test]
+    fn test_refused_connection_is_reported() {
+        // Bind and drop a listener to get an address nothing listens on.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut client = World::new();
+        client.insert(WebSocketNetworkResource::default());
+        client.insert(EventChannel::<NetworkSimulationEvent>::new());
+        client.insert(TransportResource::default());
+        let mut reader = client
+            .fetch_mut::<EventChannel<NetworkSimulationEvent>>()
+            .register_reader();
+        client.fetch_mut::<TransportResource>().send(addr, b"ping");
+
+        let mut stream_system = WebSocketStreamManagementSystem;
+        let mut failed = false;
+        for _ in 0..1000 {
+            stream_system.run_now(&client);
+            failed = client
+                .fetch::<EventChannel<NetworkSimulationEvent>>()
+                .read(&mut reader)
+                .any(|event| match event {
+                    NetworkSimulationEvent::ConnectionError(_, Some(a)) => *a == addr,
+                    _ => false,
+                });
+            if failed {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(failed);
+    }
+}
@@ -0,0 +1,314 @@
+//! The browser WebSocket transport, opening outgoing connections with the browser `WebSocket` API.
+
+use crate::simulation::{
+    channel::TransportGuarantees,
+    events::NetworkSimulationEvent,
+    message::Message,
+    requirements::DeliveryRequirement,
+    stats::NetworkStats,
+    timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
+    transport::{
+        add_network_stats_system, websocket::STREAM_MANAGEMENT_SYSTEM_NAME, TransportResource,
+        NETWORK_RECV_SYSTEM_NAME, NETWORK_SEND_SYSTEM_NAME, NETWORK_SIM_TIME_SYSTEM_NAME,
+    },
+};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{DispatcherBuilder, Read, System, World, Write},
+    shrev::EventChannel,
+};
+use amethyst_error::Error;
+use log::warn;
+use std::{cell::RefCell, collections::HashMap, io, net::SocketAddr, rc::Rc};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{BinaryType, Event, MessageEvent, WebSocket};
+
+/// Use this network bundle to add the WebSocket transport layer to your game.
+///
+/// Browsers can't listen for connections, so the bundle only opens outgoing connections.
+pub struct WebSocketNetworkBundle;
+
+impl WebSocketNetworkBundle {
+    /// Creates a bundle which only opens outgoing connections.
+    pub fn client() -> Self {
+        Self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for WebSocketNetworkBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        world
+            .entry::<TransportResource>()
+            .or_insert_with(Default::default)
+            .set_guarantees(TransportGuarantees::Stream)?;
+        builder.add(
+            NetworkSimulationTimeSystem,
+            NETWORK_SIM_TIME_SYSTEM_NAME,
+            &[],
+        );
+
+        builder.add(
+            WebSocketStreamManagementSystem,
+            STREAM_MANAGEMENT_SYSTEM_NAME,
+            &[NETWORK_SIM_TIME_SYSTEM_NAME],
+        );
+
+        builder.add(
+            WebSocketNetworkSendSystem,
+            NETWORK_SEND_SYSTEM_NAME,
+            &[STREAM_MANAGEMENT_SYSTEM_NAME],
+        );
+
+        builder.add(
+            WebSocketNetworkRecvSystem,
+            NETWORK_RECV_SYSTEM_NAME,
+            &[STREAM_MANAGEMENT_SYSTEM_NAME],
+        );
+
+        add_network_stats_system(world, builder);
+
+        world.insert(WebSocketNetworkResource::default());
+        Ok(())
+    }
+}
+
+/// System to open connections for outgoing messages and to remove closed connections.
+///
+/// The browser opens connections in the background: messages sent while a connection is opening
+/// are written once it is open.
+pub struct WebSocketStreamManagementSystem;
+
+impl<'s> System<'s> for WebSocketStreamManagementSystem {
+    type SystemData = (
+        Write<'s, WebSocketNetworkResource>,
+        Read<'s, TransportResource>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (mut net, transport, mut event_channel): Self::SystemData) {
+        // Open a connection for each message in the channel if none is open or opening.
+        for message in transport.get_messages() {
+            let addr = message.destination;
+            if net.sockets.contains_key(&addr) {
+                continue;
+            }
+            match BrowserSocket::open(addr) {
+                Ok(socket) => {
+                    net.sockets.insert(addr, socket);
+                }
+                Err(e) => event_channel
+                    .single_write(NetworkSimulationEvent::ConnectionError(e, Some(addr))),
+            }
+        }
+
+        net.sockets.retain(|addr, socket| {
+            let mut state = socket.state.borrow_mut();
+            if let Some(e) = state.error.take() {
+                event_channel.single_write(NetworkSimulationEvent::ConnectionError(e, Some(*addr)));
+            }
+            // Keep closed connections until the messages received before closing are read.
+            if socket.socket.ready_state() != WebSocket::CLOSED || !state.received.is_empty() {
+                return true;
+            }
+            if socket.opened {
+                event_channel.single_write(NetworkSimulationEvent::Disconnect(*addr));
+            }
+            false
+        });
+    }
+}
+
+/// System to send messages to a particular open WebSocket.
+pub struct WebSocketNetworkSendSystem;
+
+impl<'s> System<'s> for WebSocketNetworkSendSystem {
+    type SystemData = (
+        Write<'s, TransportResource>,
+        Write<'s, WebSocketNetworkResource>,
+        Read<'s, NetworkSimulationTime>,
+        Write<'s, NetworkStats>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (mut transport, mut net, sim_time, mut stats, mut channel): Self::SystemData,
+    ) {
+        // Write the messages which were waiting for their connection to open.
+        for socket in net.sockets.values_mut() {
+            if socket.socket.ready_state() == WebSocket::OPEN {
+                socket.opened = true;
+                for message in std::mem::take(&mut socket.queued) {
+                    write_message(message, socket, &mut stats, &mut channel);
+                }
+            }
+        }
+
+        let messages = transport.drain_messages_to_send(|_| sim_time.should_send_message_now());
+        for mut message in messages {
+            message.payload = transport.compress(&message.payload);
+            match message.delivery {
+                DeliveryRequirement::ReliableOrdered(Some(_)) => {
+                    warn!("Streams are not supported by WebSockets and will be ignored.");
+                }
+                DeliveryRequirement::ReliableOrdered(_) | DeliveryRequirement::Default => {}
+                delivery => panic!(
+                    "{:?} is unsupported. WebSockets only support ReliableOrdered by design.",
+                    delivery
+                ),
+            }
+            if let Some(socket) = net.sockets.get_mut(&message.destination) {
+                match socket.socket.ready_state() {
+                    WebSocket::CONNECTING => socket.queued.push(message),
+                    WebSocket::OPEN => write_message(message, socket, &mut stats, &mut channel),
+                    // The connection is closing, the message is dropped like on native sockets.
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn write_message(
+    message: Message,
+    socket: &BrowserSocket,
+    stats: &mut NetworkStats,
+    channel: &mut EventChannel<NetworkSimulationEvent>,
+) {
+    match socket.socket.send_with_u8_array(&message.payload[..]) {
+        Ok(()) => stats.record_sent(message.destination, message.payload.len()),
+        Err(e) => {
+            channel.single_write(NetworkSimulationEvent::SendError(into_io_error(e), message));
+        }
+    }
+}
+
+/// System to receive messages from all open WebSockets.
+pub struct WebSocketNetworkRecvSystem;
+
+impl<'s> System<'s> for WebSocketNetworkRecvSystem {
+    type SystemData = (
+        Write<'s, WebSocketNetworkResource>,
+        Write<'s, TransportResource>,
+        Write<'s, NetworkStats>,
+        Write<'s, EventChannel<NetworkSimulationEvent>>,
+    );
+
+    fn run(&mut self, (net, mut transport, mut stats, mut event_channel): Self::SystemData) {
+        for (addr, socket) in net.sockets.iter() {
+            let mut state = socket.state.borrow_mut();
+            for payload in state.received.drain(..) {
+                stats.record_received(*addr, payload.len());
+                let event = match transport.compression().decompress(&payload) {
+                    Ok(payload) => NetworkSimulationEvent::Message(*addr, payload),
+                    Err(e) => NetworkSimulationEvent::RecvError(e),
+                };
+                transport
+                    .conditioner_mut()
+                    .condition_incoming(event, true, &mut event_channel);
+            }
+        }
+        transport
+            .conditioner_mut()
+            .release_incoming(&mut event_channel);
+    }
+}
+
+fn into_io_error(error: JsValue) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{:?}", error))
+}
+
+/// What the callbacks of a browser WebSocket report, read by the systems on the next frame.
+#[derive(Default)]
+struct SocketState {
+    received: Vec<Vec<u8>>,
+    error: Option<io::Error>,
+}
+
+/// A browser WebSocket with the callbacks it was registered with.
+struct BrowserSocket {
+    socket: WebSocket,
+    state: Rc<RefCell<SocketState>>,
+    queued: Vec<Message>,
+    opened: bool,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_error: Closure<dyn FnMut(Event)>,
+}
+
+impl BrowserSocket {
+    fn open(addr: SocketAddr) -> io::Result<Self> {
+        let socket = WebSocket::new(&format!("ws://{}/", addr)).map_err(into_io_error)?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+        let state = Rc::new(RefCell::new(SocketState::default()));
+
+        let received = Rc::clone(&state);
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let data = event.data();
+            let payload = if let Some(buffer) = data.dyn_ref::<js_sys::ArrayBuffer>() {
+                js_sys::Uint8Array::new(buffer).to_vec()
+            } else if let Some(text) = data.as_string() {
+                text.into_bytes()
+            } else {
+                return;
+            };
+            received.borrow_mut().received.push(payload);
+        }) as Box<dyn FnMut(MessageEvent)>);
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let errors = Rc::clone(&state);
+        // Browsers don't tell why a WebSocket failed.
+        let on_error = Closure::wrap(Box::new(move |_: Event| {
+            errors.borrow_mut().error = Some(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "the WebSocket connection failed",
+            ));
+        }) as Box<dyn FnMut(Event)>);
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            state,
+            queued: Vec::new(),
+            opened: false,
+            _on_message: on_message,
+            _on_error: on_error,
+        })
+    }
+}
+
+impl Drop for BrowserSocket {
+    fn drop(&mut self) {
+        self.socket.set_onmessage(None);
+        self.socket.set_onerror(None);
+        let _ = self.socket.close();
+    }
+}
+
+/// Resource owning all open browser WebSockets.
+#[derive(Default)]
+pub struct WebSocketNetworkResource {
+    sockets: HashMap<SocketAddr, BrowserSocket>,
+}
+
+// The browser WebSockets and their callbacks are JavaScript objects, which can't be sent between
+// threads. wasm32 builds run on a single thread, so the resource is never shared.
+unsafe impl Send for WebSocketNetworkResource {}
+unsafe impl Sync for WebSocketNetworkResource {}
+
+impl WebSocketNetworkResource {
+    /// Returns true while an outgoing connection to the address is being opened.
+    pub fn is_connecting(&self, addr: SocketAddr) -> bool {
+        self.sockets.get(&addr).map_or(false, |socket| {
+            socket.socket.ready_state() == WebSocket::CONNECTING
+        })
+    }
+
+    /// Closes the WebSocket with the given `SocketAddr`, returning whether one was open.
+    pub fn drop_socket(&mut self, addr: SocketAddr) -> bool {
+        self.sockets.remove(&addr).is_some()
+    }
+}
@@ -0,0 +1,44 @@
+use amethyst_rendy::{batch::SortedBatch, pod::SpriteArgs};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+// Sprites of a 2D scene, spread over 16 textures and 8 depth layers
+fn setup() -> Vec<(u32, u32, SpriteArgs)> {
+    (0..100_000u32)
+        .map(|i| {
+            let texture = i.wrapping_mul(2_654_435_761) % 16;
+            let depth = (i % 8) as f32;
+            let sprite = SpriteArgs {
+                dir_x: [32.0, 0.0].into(),
+                dir_y: [0.0, -32.0].into(),
+                pos: [(i % 320) as f32, (i / 320) as f32].into(),
+                u_offset: [0.0, 1.0].into(),
+                v_offset: [0.0, 1.0].into(),
+                depth,
+                tint: [1.0; 4].into(),
+            };
+            (texture, depth as u32, sprite)
+        })
+        .collect()
+}
+
+pub fn sort_sprites_100k(b: &mut Criterion) {
+    let sprites = setup();
+    let mut batch = SortedBatch::<(u32, u32), u32, SpriteArgs>::default();
+    let mut buffer = Vec::with_capacity(sprites.len());
+
+    b.bench_function("sort_sprites_100k", move |b| {
+        b.iter(|| {
+            batch.swap_clear();
+            for &(texture, depth, sprite) in &sprites {
+                batch.insert(texture, (texture, depth), sprite);
+            }
+            batch.sort();
+            buffer.clear();
+            buffer.extend(batch.data().copied());
+        });
+    });
+}
+
+criterion_group!(sprite_batch, sort_sprites_100k);
+criterion_main!(sprite_batch);
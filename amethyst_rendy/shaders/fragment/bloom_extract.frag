@@ -0,0 +1,20 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler source_sampler;
+layout(set = 0, binding = 1) uniform texture2D source;
+
+// x: brightness threshold
+layout(push_constant) uniform Params {
+    vec4 a;
+    vec4 b;
+} params;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec3 color = texture(sampler2D(source, source_sampler), uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - params.a.x, 0.0) / max(brightness, 0.0001);
+    out_color = vec4(color * contribution, 1.0);
+}
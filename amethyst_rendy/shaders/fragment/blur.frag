@@ -0,0 +1,25 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler source_sampler;
+layout(set = 0, binding = 1) uniform texture2D source;
+
+// xy: blur direction, (1, 0) or (0, 1)
+layout(push_constant) uniform Params {
+    vec4 a;
+    vec4 b;
+} params;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    vec2 texel = params.a.xy / vec2(textureSize(sampler2D(source, source_sampler), 0));
+    vec3 color = texture(sampler2D(source, source_sampler), uv).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        vec2 offset = texel * float(i);
+        color += texture(sampler2D(source, source_sampler), uv + offset).rgb * weights[i];
+        color += texture(sampler2D(source, source_sampler), uv - offset).rgb * weights[i];
+    }
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,47 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler source_sampler;
+layout(set = 0, binding = 1) uniform texture2D source;
+layout(set = 1, binding = 0) uniform sampler2D lut;
+layout(set = 2, binding = 0) uniform sampler2D blend_lut;
+
+// a.x: weight of blend_lut, a.y: intensity of the grading
+layout(push_constant) uniform Params {
+    vec4 a;
+} params;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+vec3 to_srgb(vec3 color) {
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+vec3 to_linear(vec3 color) {
+    return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+// Returns the coordinates of the two square slices around the blue channel in a strip of slices
+// side by side, and the weight of the second slice.
+vec3 lut_coords(vec3 color, float size, out vec2 coords_1) {
+    float slice = color.b * (size - 1.0);
+    float slice_0 = floor(slice);
+    float slice_1 = min(slice_0 + 1.0, size - 1.0);
+    vec2 texel = color.rg * (size - 1.0) + 0.5;
+    vec2 strip_size = vec2(size * size, size);
+    coords_1 = (texel + vec2(slice_1 * size, 0.0)) / strip_size;
+    return vec3((texel + vec2(slice_0 * size, 0.0)) / strip_size, slice - slice_0);
+}
+
+void main() {
+    vec3 color = to_srgb(clamp(texture(sampler2D(source, source_sampler), uv).rgb, 0.0, 1.0));
+
+    vec2 coords_1;
+    vec3 coords = lut_coords(color, float(textureSize(lut, 0).y), coords_1);
+    vec3 graded = mix(texture(lut, coords.xy).rgb, texture(lut, coords_1).rgb, coords.z);
+    coords = lut_coords(color, float(textureSize(blend_lut, 0).y), coords_1);
+    vec3 blended = mix(texture(blend_lut, coords.xy).rgb, texture(blend_lut, coords_1).rgb, coords.z);
+
+    graded = mix(graded, blended, params.a.x);
+    out_color = vec4(to_linear(mix(color, graded, params.a.y)), 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler source_sampler;
+layout(set = 0, binding = 1) uniform texture2D source;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = vec4(texture(sampler2D(source, source_sampler), uv).rgb, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 450
+
+#include "header/debug.frag"
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec3 normal;
+    vec2 tex_coord;
+    vec4 color;
+    float emission;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = debug_color(vertex.normal, vertex.tex_coord);
+}
@@ -0,0 +1,17 @@
+#version 450
+
+#include "header/debug.frag"
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec2 tex_coord;
+    vec4 color;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Without vertex normals the faces are shown flat. The screen y axis points down.
+    vec3 normal = cross(dFdy(vertex.position), dFdx(vertex.position));
+    out_color = debug_color(normal, vertex.tex_coord);
+}
@@ -0,0 +1,44 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform DecalView {
+    mat4 proj_view;
+    mat4 inverse_proj_view;
+    vec2 screen_size;
+};
+
+layout(set = 1, binding = 0) uniform sampler depth_sampler;
+layout(set = 1, binding = 1) uniform texture2D scene_depth;
+
+layout(set = 2, binding = 0) uniform sampler2D decal;
+
+layout(location = 0) flat in vec4 inverse_model_x;
+layout(location = 1) flat in vec4 inverse_model_y;
+layout(location = 2) flat in vec4 inverse_model_z;
+layout(location = 3) flat in vec4 inverse_model_w;
+layout(location = 4) flat in vec4 decal_color;
+layout(location = 5) flat in float decal_min_facing;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec2 screen_uv = gl_FragCoord.xy / screen_size;
+    float depth = texture(sampler2D(scene_depth, depth_sampler), screen_uv).r;
+    // Nothing was drawn at this pixel.
+    if (depth <= 0.0) discard;
+
+    vec4 world = inverse_proj_view * vec4(screen_uv * 2.0 - 1.0, depth, 1.0);
+    vec3 position = world.xyz / world.w;
+
+    mat4 inverse_model = mat4(inverse_model_x, inverse_model_y, inverse_model_z, inverse_model_w);
+    vec3 local = (inverse_model * vec4(position, 1.0)).xyz;
+    if (any(greaterThan(abs(local), vec3(0.5)))) discard;
+
+    // Fade out on surfaces turning away from the projection axis, the local z axis.
+    vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+    vec3 axis = normalize(vec3(inverse_model[0][2], inverse_model[1][2], inverse_model[2][2]));
+    float facing = abs(dot(normal, axis));
+    float fade = smoothstep(decal_min_facing, mix(decal_min_facing, 1.0, 0.25), facing);
+
+    vec4 color = texture(decal, vec2(local.x + 0.5, 0.5 - local.y)) * decal_color;
+    out_color = vec4(color.rgb, color.a * fade);
+}
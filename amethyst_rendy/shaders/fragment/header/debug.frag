@@ -0,0 +1,60 @@
+#ifndef DEBUG_FRAG
+#define DEBUG_FRAG
+
+#include "math.frag"
+
+// Values of `DebugRenderMode::shader_mode`.
+const uint DEBUG_NORMALS = 1u;
+const uint DEBUG_OVERDRAW = 2u;
+const uint DEBUG_MIP_LEVEL = 3u;
+
+layout(push_constant) uniform DebugMode {
+    uint mode;
+} debug;
+
+layout(std140, set = 1, binding = 0) uniform Material {
+    UvOffset uv_offset;
+    float alpha_cutoff;
+};
+
+layout(set = 1, binding = 1) uniform sampler2D albedo;
+
+// Colors of the mip levels, starting with magnified textures.
+const vec3 MIP_COLORS[6] = vec3[](
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(1.0, 1.0, 0.0),
+    vec3(1.0, 0.5, 0.0),
+    vec3(1.0, 0.0, 0.0),
+    vec3(1.0, 0.0, 1.0)
+);
+
+vec3 mip_level_color(vec2 tex_coords) {
+    vec2 texels = tex_coords * vec2(textureSize(albedo, 0));
+    vec2 dx = dFdx(texels);
+    vec2 dy = dFdy(texels);
+    float level = 0.5 * log2(max(dot(dx, dx), dot(dy, dy)));
+    float index = clamp(level + 1.0, 0.0, 5.0);
+    int lower = int(floor(index));
+    int upper = min(lower + 1, 5);
+    return mix(MIP_COLORS[lower], MIP_COLORS[upper], index - float(lower));
+}
+
+// Returns the color visualizing the fragment in the current debug mode. Every fragment adds a
+// fixed amount of color with additive blending for the overdraw, turning from red to white.
+vec4 debug_color(vec3 normal, vec2 tex_coord) {
+    vec2 final_tex_coords = tex_coords(tex_coord, uv_offset);
+    vec4 albedo_alpha = texture(albedo, final_tex_coords);
+    if(albedo_alpha.a < alpha_cutoff) discard;
+
+    if (debug.mode == DEBUG_NORMALS) {
+        return vec4(normalize(normal) * 0.5 + 0.5, 1.0);
+    } else if (debug.mode == DEBUG_OVERDRAW) {
+        return vec4(0.1, 0.04, 0.02, 1.0);
+    } else {
+        float luminance = dot(albedo_alpha.rgb, vec3(0.2126, 0.7152, 0.0722));
+        return vec4(mip_level_color(final_tex_coords) * (0.5 + 0.5 * luminance), 1.0);
+    }
+}
+
+#endif
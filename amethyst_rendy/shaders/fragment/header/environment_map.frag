@@ -0,0 +1,67 @@
+// Environment map shader definition.
+// Set ENVIRONMENT_MAP_SET, which has to be defined before including it.
+// Keep in sync with amethyst_rendy/src/submodules/environment_map.rs
+
+layout(std140, set = ENVIRONMENT_MAP_SET, binding = 0) uniform EnvironmentMap {
+    vec4 irradiance[9];
+    float environment_intensity;
+    int specular_levels;
+};
+
+layout(set = ENVIRONMENT_MAP_SET, binding = 1) uniform sampler2DArray specular_map;
+
+// Keep in sync with `equirect_coordinates` in amethyst_rendy/src/environment_map.rs
+vec2 equirect_uv(vec3 direction) {
+    vec3 d = normalize(direction);
+    return vec2(atan(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+}
+
+// Keep in sync with `sh_basis` in amethyst_rendy/src/environment_map.rs
+vec3 sh_irradiance(vec3 d) {
+    return irradiance[0].rgb * 0.282095
+        + irradiance[1].rgb * 0.488603 * d.y
+        + irradiance[2].rgb * 0.488603 * d.z
+        + irradiance[3].rgb * 0.488603 * d.x
+        + irradiance[4].rgb * 1.092548 * d.x * d.y
+        + irradiance[5].rgb * 1.092548 * d.y * d.z
+        + irradiance[6].rgb * 0.315392 * (3.0 * d.z * d.z - 1.0)
+        + irradiance[7].rgb * 1.092548 * d.x * d.z
+        + irradiance[8].rgb * 0.546274 * (d.x * d.x - d.y * d.y);
+}
+
+vec3 prefiltered_radiance(vec3 direction, float roughness) {
+    vec2 uv = equirect_uv(direction);
+    float last = float(specular_levels - 1);
+    float level = roughness * last;
+    float lower = floor(level);
+    vec3 a = texture(specular_map, vec3(uv, lower)).rgb;
+    vec3 b = texture(specular_map, vec3(uv, min(lower + 1.0, last))).rgb;
+    return mix(a, b, level - lower);
+}
+
+// Analytic approximation of the split sum environment BRDF by Karis.
+vec3 environment_brdf(vec3 fresnel_base, float roughness, float NdotV) {
+    const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    vec4 r = roughness * c0 + c1;
+    float a004 = min(r.x * r.x, exp2(-9.28 * NdotV)) * r.x + r.y;
+    vec2 scale_bias = vec2(-1.04, 1.04) * a004 + r.zw;
+    return fresnel_base * scale_bias.x + scale_bias.y;
+}
+
+// Diffuse and specular light of the environment map, black without environment map.
+vec3 image_based_light(vec3 normal,
+                       vec3 view_direction,
+                       vec3 albedo,
+                       float roughness,
+                       float metallic,
+                       vec3 fresnel_base) {
+    if (specular_levels == 0) {
+        return vec3(0.0);
+    }
+    float NdotV = max(dot(normal, view_direction), 0.0);
+    vec3 specular_color = environment_brdf(fresnel_base, roughness, NdotV);
+    vec3 diffuse = max(sh_irradiance(normal), 0.0) * albedo * (1.0 - specular_color) * (1.0 - metallic);
+    vec3 specular = prefiltered_radiance(reflect(-view_direction, normal), roughness) * specular_color;
+    return (diffuse + specular) * environment_intensity;
+}
@@ -0,0 +1,82 @@
+// Shadow shader definition.
+// Set 3.
+// Keep in sync with amethyst_rendy/src/submodules/shadow.rs
+
+struct ShadowView {
+    mat4 proj_view;
+    vec4 rect;
+};
+
+layout(std140, set = 3, binding = 0) uniform Shadows {
+    vec4 cascade_splits;
+    vec3 camera_forward;
+    int cascade_count;
+    int shadow_light;
+    float directional_bias;
+    int directional_filter;
+    float point_bias;
+    int point_filter;
+    ivec4 point_shadows[32];
+};
+
+layout(std140, set = 3, binding = 1) uniform ShadowViews {
+    ShadowView shadow_views[28];
+};
+
+layout(set = 3, binding = 2) uniform sampler2D shadow_map;
+
+// Returns how much of the light reaches the position as seen from the given shadow view,
+// averaging a square of (2 * radius + 1)^2 shadow map texels.
+float sample_shadow(int view, vec3 position, float bias, int radius) {
+    vec4 clip = shadow_views[view].proj_view * vec4(position, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+    if (ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    vec4 rect = shadow_views[view].rect;
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    vec2 uv = rect.xy + (ndc.xy * 0.5 + 0.5) * rect.zw;
+    vec2 uv_min = rect.xy + texel * 0.5;
+    vec2 uv_max = rect.xy + rect.zw - texel * 0.5;
+
+    float lit = 0.0;
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            vec2 tap = clamp(uv + vec2(x, y) * texel, uv_min, uv_max);
+            lit += ndc.z - bias <= texture(shadow_map, tap).r ? 1.0 : 0.0;
+        }
+    }
+    float taps = float((2 * radius + 1) * (2 * radius + 1));
+    return lit / taps;
+}
+
+float directional_shadow(vec3 position) {
+    float depth = dot(position - camera_position, camera_forward);
+    for (int i = 0; i < cascade_count; i++) {
+        if (depth < cascade_splits[i]) {
+            return sample_shadow(i, position, directional_bias, directional_filter);
+        }
+    }
+    return 1.0;
+}
+
+// Faces are ordered +X, -X, +Y, -Y, +Z, -Z.
+float point_shadow(int light, vec3 position) {
+    int first_view = point_shadows[light / 4][light % 4];
+    if (first_view < 0) {
+        return 1.0;
+    }
+
+    vec3 to_position = position - plight[light].position;
+    vec3 distance = abs(to_position);
+    int face;
+    if (distance.x >= distance.y && distance.x >= distance.z) {
+        face = to_position.x > 0.0 ? 0 : 1;
+    } else if (distance.y >= distance.z) {
+        face = to_position.y > 0.0 ? 2 : 3;
+    } else {
+        face = to_position.z > 0.0 ? 4 : 5;
+    }
+    return sample_shadow(first_view + face, position, point_bias, point_filter);
+}
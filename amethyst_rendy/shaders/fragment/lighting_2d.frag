@@ -0,0 +1,125 @@
+#version 450
+
+// 2D lighting definition.
+// Set 1.
+// Keep in sync with amethyst_rendy/src/submodules/lighting_2d.rs
+
+struct Light2D {
+    vec3 color;
+    float radius;
+    vec2 position;
+    float height;
+    float source_radius;
+    vec2 direction;
+    float cone_outer;
+    float cone_inner;
+    int cast_shadows;
+};
+
+layout(set = 0, binding = 0) uniform sampler buffer_sampler;
+layout(set = 0, binding = 1) uniform texture2D normals;
+
+layout(std140, set = 1, binding = 0) uniform Lighting2D {
+    mat4 inverse_proj_view;
+    vec3 ambient_color;
+    int light_count;
+    int segment_count;
+    int shadow_samples;
+};
+
+layout(std140, set = 1, binding = 1) uniform Lights2D {
+    Light2D lights[64];
+};
+
+// Occluder outline segments from xy to zw, wound counter-clockwise.
+layout(std140, set = 1, binding = 2) uniform Occluders2D {
+    vec4 segments[512];
+};
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+// Point where the view ray through the pixel hits the z = 0 plane.
+vec2 plane_position(vec2 ndc) {
+    vec4 near = inverse_proj_view * vec4(ndc, 1.0, 1.0);
+    vec4 far = inverse_proj_view * vec4(ndc, 0.0, 1.0);
+    vec3 origin = near.xyz / near.w;
+    vec3 ray = far.xyz / far.w - origin;
+    float t = abs(ray.z) > 0.000001 ? -origin.z / ray.z : 0.0;
+    return origin.xy + ray.xy * t;
+}
+
+float cross2(vec2 a, vec2 b) {
+    return a.x * b.y - a.y * b.x;
+}
+
+// Whether the path from the point to the target enters an occluder. As the outlines are wound
+// counter-clockwise, it enters through the segments it crosses from right to left.
+bool occluded(vec2 from, vec2 to) {
+    vec2 path = to - from;
+    for (int i = 0; i < segment_count; i++) {
+        vec2 start = segments[i].xy;
+        vec2 edge = segments[i].zw - start;
+        float denom = cross2(path, edge);
+        if (denom >= 0.0) {
+            continue;
+        }
+        vec2 offset = start - from;
+        float t = cross2(offset, edge) / denom;
+        float u = cross2(offset, path) / denom;
+        if (t > 0.0 && t < 1.0 && u >= 0.0 && u <= 1.0) {
+            return true;
+        }
+    }
+    return false;
+}
+
+vec3 light_contribution(Light2D light, vec2 position, vec3 normal) {
+    vec2 to_light = light.position - position;
+    float dist = length(to_light);
+    if (dist >= light.radius) {
+        return vec3(0.0);
+    }
+
+    float falloff = 1.0 - (dist * dist) / (light.radius * light.radius);
+    falloff *= falloff;
+
+    vec2 dir = dist > 0.0001 ? to_light / dist : vec2(0.0, 1.0);
+    float cone = 1.0;
+    if (light.cone_outer > -1.5) {
+        float inner = max(light.cone_inner, light.cone_outer + 0.0001);
+        cone = smoothstep(light.cone_outer, inner, dot(-dir, light.direction));
+    }
+
+    float diffuse = max(dot(normal, normalize(vec3(to_light, light.height))), 0.0);
+    float intensity = falloff * cone * diffuse;
+    if (intensity <= 0.0) {
+        return vec3(0.0);
+    }
+
+    if (light.cast_shadows != 0 && segment_count > 0) {
+        // Rays towards points spread over the light source, across the direction to it.
+        vec2 side = vec2(-dir.y, dir.x) * light.source_radius;
+        float lit = 0.0;
+        for (int i = 0; i < shadow_samples; i++) {
+            float spread = shadow_samples > 1 ? float(i) / float(shadow_samples - 1) * 2.0 - 1.0 : 0.0;
+            if (!occluded(position, light.position + side * spread)) {
+                lit += 1.0;
+            }
+        }
+        intensity *= lit / float(shadow_samples);
+    }
+
+    return light.color * intensity;
+}
+
+void main() {
+    vec3 normal = normalize(texture(sampler2D(normals, buffer_sampler), uv).xyz * 2.0 - 1.0);
+    vec2 position = plane_position(uv * 2.0 - 1.0);
+
+    vec3 light = ambient_color;
+    for (int i = 0; i < light_count; i++) {
+        light += light_contribution(lights[i], position, normal);
+    }
+    out_color = vec4(light, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler buffer_sampler;
+layout(set = 0, binding = 2) uniform texture2D emission;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = vec4(texture(sampler2D(emission, buffer_sampler), uv).rgb, 0.0);
+}
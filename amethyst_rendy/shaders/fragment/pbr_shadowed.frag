@@ -0,0 +1,4 @@
+#version 450
+
+#define SHADOWS
+#include "header/pbr.frag"
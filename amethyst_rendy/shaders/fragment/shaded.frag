@@ -17,6 +17,7 @@ layout(location = 0) in VertexData {
     vec3 normal;
     vec2 tex_coord;
     vec4 color;
+    float emission;
 } vertex;
 
 layout(location = 0) out vec4 out_color;
@@ -29,7 +30,7 @@ void main() {
     if(alpha < alpha_cutoff) discard;
 
     vec3 albedo = albedo_alpha.rgb;
-    vec3 emission = texture(emission, final_tex_coords).rgb;
+    vec3 emission = texture(emission, final_tex_coords).rgb * vertex.emission;
 
     vec3 lighting = vec3(0.0);
     vec3 normal = normalize(vertex.normal);
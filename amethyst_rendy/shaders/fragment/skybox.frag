@@ -1,5 +1,7 @@
 #version 450
 
+#include "header/math.frag"
+
 layout(early_fragment_tests) in;
 
 layout(location = 0) in VertexData {
@@ -14,8 +16,26 @@ layout(std140, set = 1, binding = 0) uniform _ {
     vec3 nadir_color;
 };
 
+// Environment map of the `Skybox` resource, see amethyst_rendy/src/submodules/environment_map.rs
+layout(std140, set = 2, binding = 0) uniform EnvironmentMap {
+    vec4 irradiance[9];
+    float intensity;
+    int levels;
+};
+
+layout(set = 2, binding = 1) uniform sampler2D radiance;
+
+// Keep in sync with `equirect_uv` in header/environment_map.frag
+vec2 equirect_uv(vec3 d) {
+    return vec2(atan(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+}
+
 void main() {
     vec3 normalized_position = normalize(vertex.position.xyz);
+    if (levels > 0) {
+        out_color = vec4(texture(radiance, equirect_uv(normalized_position)).rgb * intensity, 1.0);
+        return;
+    }
     vec3 horizon_color = mix(nadir_color, zenith_color, smoothstep(-1., 1., normalized_position.y));
     out_color = vec4(horizon_color, 1.0f);
 }
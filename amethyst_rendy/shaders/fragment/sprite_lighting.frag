@@ -0,0 +1,30 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform sampler2D albedo;
+layout(set = 2, binding = 0) uniform sampler2D normal_map;
+layout(set = 3, binding = 0) uniform sampler2D emission_map;
+
+layout(location = 0) in VertexData {
+    vec2 tex_uv;
+    vec4 color;
+    vec2 tangent;
+    vec2 bitangent;
+} vertex;
+
+layout(location = 0) out vec4 out_normal;
+layout(location = 1) out vec4 out_emission;
+
+void main() {
+    float alpha = texture(albedo, vertex.tex_uv).a * vertex.color.a;
+    if (alpha == 0.0) {
+        discard;
+    }
+
+    vec3 normal = texture(normal_map, vertex.tex_uv).rgb * 2.0 - 1.0;
+    vec3 world_normal = normalize(vec3(
+        normal.x * vertex.tangent + normal.y * vertex.bitangent,
+        normal.z
+    ));
+    out_normal = vec4(world_normal * 0.5 + 0.5, alpha);
+    out_emission = vec4(texture(emission_map, vertex.tex_uv).rgb, alpha);
+}
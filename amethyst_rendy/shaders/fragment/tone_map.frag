@@ -0,0 +1,56 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler source_sampler;
+layout(set = 0, binding = 1) uniform texture2D scene;
+layout(set = 0, binding = 2) uniform texture2D bloom;
+
+// a.x: exposure, a.y: tone mapping operator, a.z: bloom intensity
+// b.x: vignette intensity, b.y: vignette smoothness
+layout(push_constant) uniform Params {
+    vec4 a;
+    vec4 b;
+} params;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+// Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// Hable's Uncharted 2 curve.
+vec3 hable(vec3 x) {
+    return ((x * (0.15 * x + 0.05) + 0.004) / (x * (0.15 * x + 0.5) + 0.06)) - 0.02 / 0.3;
+}
+
+vec3 filmic(vec3 x) {
+    return hable(x * 2.0) / hable(vec3(11.2));
+}
+
+void main() {
+    vec3 color = texture(sampler2D(scene, source_sampler), uv).rgb;
+    color += texture(sampler2D(bloom, source_sampler), uv).rgb * params.a.z;
+    color *= params.a.x;
+
+    int op = int(params.a.y + 0.5);
+    if (op == 1) {
+        color = reinhard(color);
+    } else if (op == 2) {
+        color = aces(color);
+    } else if (op == 3) {
+        color = filmic(color);
+    } else {
+        color = clamp(color, 0.0, 1.0);
+    }
+
+    // Distance to the center, 1.0 in the corners.
+    float dist = distance(uv, vec2(0.5)) * 1.41421356;
+    color *= 1.0 - params.b.x * smoothstep(1.0 - params.b.y, 1.0, dist);
+
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,44 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform DecalView {
+    mat4 proj_view;
+    mat4 inverse_proj_view;
+    vec2 screen_size;
+};
+
+layout(location = 0) in mat4 model; // instance rate
+layout(location = 4) in mat4 inverse_model; // instance rate
+layout(location = 8) in vec4 color; // instance rate
+layout(location = 9) in float min_facing; // instance rate
+
+// Columns of the inverse model matrix, which can't be passed as a matrix.
+layout(location = 0) flat out vec4 inverse_model_x;
+layout(location = 1) flat out vec4 inverse_model_y;
+layout(location = 2) flat out vec4 inverse_model_z;
+layout(location = 3) flat out vec4 inverse_model_w;
+layout(location = 4) flat out vec4 decal_color;
+layout(location = 5) flat out float decal_min_facing;
+
+// Triangles of the unit cube centered on the origin, wound counter-clockwise seen from the
+// outside. Corner i is at the bits of i, minus 0.5.
+const int indices[36] = int[](
+    0, 4, 6, 0, 6, 2,
+    1, 3, 7, 1, 7, 5,
+    0, 1, 5, 0, 5, 4,
+    2, 6, 7, 2, 7, 3,
+    0, 2, 3, 0, 3, 1,
+    4, 5, 7, 4, 7, 6
+);
+
+void main() {
+    int corner = indices[gl_VertexIndex];
+    vec3 position = vec3(float(corner & 1), float((corner >> 1) & 1), float((corner >> 2) & 1)) - 0.5;
+
+    inverse_model_x = inverse_model[0];
+    inverse_model_y = inverse_model[1];
+    inverse_model_z = inverse_model[2];
+    inverse_model_w = inverse_model[3];
+    decal_color = color;
+    decal_min_facing = min_facing;
+    gl_Position = proj_view * model * vec4(position, 1.0);
+}
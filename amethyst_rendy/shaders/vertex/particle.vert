@@ -0,0 +1,36 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+// Particle instance.
+layout(location = 0) in vec3 position;
+layout(location = 1) in float size;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out VertexData {
+    vec2 uv;
+    vec4 color;
+} vertex;
+
+const vec2 corners[4] = vec2[](
+    vec2(0.5, -0.5), // Right bottom
+    vec2(-0.5, -0.5), // Left bottom
+    vec2(0.5, 0.5), // Right top
+    vec2(-0.5, 0.5) // Left top
+);
+
+void main() {
+    vec2 corner = corners[gl_VertexIndex];
+    // Rows of the view matrix are the camera axes in world space
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+
+    vertex.uv = corner * 2.0;
+    vertex.color = color;
+    vec3 world_pos = position + (right * corner.x + up * corner.y) * size;
+    gl_Position = proj_view * vec4(world_pos, 1.0);
+}
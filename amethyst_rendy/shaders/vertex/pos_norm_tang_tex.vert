@@ -12,6 +12,7 @@ layout(location = 2) in vec4 tangent;
 layout(location = 3) in vec2 tex_coord;
 layout(location = 4) in mat4 model; // instance rate
 layout(location = 8) in vec4 tint; // instance rate
+layout(location = 9) in vec4 material_overrides; // instance rate
 
 layout(location = 0) out VertexData {
     vec3 position;
@@ -20,6 +21,7 @@ layout(location = 0) out VertexData {
     float tang_handedness;
     vec2 tex_coord;
     vec4 color;
+    float emission;
 } vertex;
 
 void main() {
@@ -28,7 +30,8 @@ void main() {
     vertex.normal = mat3(model) * normal;
     vertex.tangent = mat3(model) * tangent.xyz;
     vertex.tang_handedness = tangent.w;
-    vertex.tex_coord = tex_coord;
+    vertex.tex_coord = tex_coord + material_overrides.xy;
     vertex.color = tint;
+    vertex.emission = material_overrides.z;
     gl_Position = proj_view * vertex_position;
 }
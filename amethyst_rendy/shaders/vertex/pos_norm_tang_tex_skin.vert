@@ -19,6 +19,7 @@ layout(location = 5) in vec4 joint_weights;
 layout(location = 6) in mat4 model; // instance rate
 layout(location = 10) in vec4 tint; // instance rate
 layout(location = 11) in uint joints_offset; // instance rate
+layout(location = 12) in vec4 material_overrides; // instance rate
 
 layout(location = 0) out VertexData {
     vec3 position;
@@ -27,6 +28,7 @@ layout(location = 0) out VertexData {
     float tang_handedness;
     vec2 tex_coord;
     vec4 color;
+    float emission;
 } vertex;
 
 void main() {
@@ -42,7 +44,8 @@ void main() {
     vertex.normal = mat3_transform * normal;
     vertex.tangent = mat3_transform * tangent.xyz;
     vertex.tang_handedness = tangent.w;
-    vertex.tex_coord = tex_coord;
+    vertex.tex_coord = tex_coord + material_overrides.xy;
     vertex.color = tint;
+    vertex.emission = material_overrides.z;
     gl_Position = proj_view * vertex_position;
 }
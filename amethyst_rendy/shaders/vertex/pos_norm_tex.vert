@@ -11,19 +11,22 @@ layout(location = 1) in vec3 normal;
 layout(location = 2) in vec2 tex_coord;
 layout(location = 3) in mat4 model; // instance rate
 layout(location = 7) in vec4 tint; // instance rate
+layout(location = 8) in vec4 material_overrides; // instance rate
 
 layout(location = 0) out VertexData {
     vec3 position;
     vec3 normal;
     vec2 tex_coord;
     vec4 color;
+    float emission;
 } vertex;
 
 void main() {
     vec4 vertex_position = model * vec4(position, 1.0);
     vertex.position = vertex_position.xyz;
     vertex.normal = mat3(model) * normal;
-    vertex.tex_coord = tex_coord;
+    vertex.tex_coord = tex_coord + material_overrides.xy;
     vertex.color = tint;
+    vertex.emission = material_overrides.z;
     gl_Position = proj_view * vertex_position;
 }
@@ -18,12 +18,14 @@ layout(location = 4) in vec4 joint_weights;
 layout(location = 5) in mat4 model; // instance rate
 layout(location = 9) in vec4 tint; // instance rate
 layout(location = 10) in uint joints_offset; // instance rate
+layout(location = 11) in vec4 material_overrides; // instance rate
 
 layout(location = 0) out VertexData {
     vec3 position;
     vec3 normal;
     vec2 tex_coord;
     vec4 color;
+    float emission;
 } vertex;
 
 void main() {
@@ -37,8 +39,9 @@ void main() {
     mat3 mat3_transform = mat3(model) * mat3(joint_transform);
     vertex.position = vertex_position.xyz;
     vertex.normal = mat3_transform * normal;
-    vertex.tex_coord = tex_coord;
+    vertex.tex_coord = tex_coord + material_overrides.xy;
     vertex.color = tint;
+    vertex.emission = material_overrides.z;
     gl_Position = proj_view * vertex_position;
 
 }
@@ -10,6 +10,7 @@ layout(location = 0) in vec3 position;
 layout(location = 1) in vec2 tex_coord;
 layout(location = 2) in mat4 model; // instance rate
 layout(location = 6) in vec4 tint; // instance rate
+layout(location = 7) in vec4 material_overrides; // instance rate
 
 layout(location = 0) out VertexData {
     vec3 position;
@@ -20,7 +21,7 @@ layout(location = 0) out VertexData {
 void main() {
     vec4 vertex_position = model * vec4(position, 1.0);
     vertex.position = vertex_position.xyz;
-    vertex.tex_coord = tex_coord;
+    vertex.tex_coord = tex_coord + material_overrides.xy;
     vertex.color = tint;
     gl_Position = proj_view * vertex_position;
 }
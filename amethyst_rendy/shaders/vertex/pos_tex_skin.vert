@@ -17,6 +17,7 @@ layout(location = 3) in vec4 joint_weights;
 layout(location = 4) in mat4 model; // instance rate
 layout(location = 8) in vec4 tint; // instance rate
 layout(location = 9) in uint joints_offset; // instance rate
+layout(location = 10) in vec4 material_overrides; // instance rate
 
 layout(location = 0) out VertexData {
     vec3 position;
@@ -33,7 +34,7 @@ void main() {
 
     vec4 vertex_position = model * joint_transform * vec4(position, 1.0);
     vertex.position = vertex_position.xyz;
-    vertex.tex_coord = tex_coord;
+    vertex.tex_coord = tex_coord + material_overrides.xy;
     vertex.color = tint;
     gl_Position = proj_view * vertex_position;
 }
@@ -0,0 +1,19 @@
+#version 450
+
+// Projection of the shadow view, mapped to its tile of the shadow map.
+layout(push_constant) uniform ShadowView {
+    mat4 proj_view;
+};
+
+layout(location = 0) in vec3 position;
+// Columns of the model matrix, instance rate.
+layout(location = 1) in vec4 model_x;
+layout(location = 2) in vec4 model_y;
+layout(location = 3) in vec4 model_z;
+layout(location = 4) in vec4 model_w;
+layout(location = 5) in vec4 tint; // instance rate
+
+void main() {
+    mat4 model = mat4(model_x, model_y, model_z, model_w);
+    gl_Position = proj_view * model * vec4(position, 1.0);
+}
@@ -0,0 +1,32 @@
+#version 450
+
+// Projection of the shadow view, mapped to its tile of the shadow map.
+layout(push_constant) uniform ShadowView {
+    mat4 proj_view;
+};
+
+layout(std430, set = 0, binding = 0) readonly buffer JointTransforms {
+    mat4 joints[];
+};
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in uvec4 joint_ids;
+layout(location = 2) in vec4 joint_weights;
+// Columns of the model matrix, instance rate.
+layout(location = 3) in vec4 model_x;
+layout(location = 4) in vec4 model_y;
+layout(location = 5) in vec4 model_z;
+layout(location = 6) in vec4 model_w;
+layout(location = 7) in vec4 tint; // instance rate
+layout(location = 8) in uint joints_offset; // instance rate
+
+void main() {
+    mat4 joint_transform =
+        joint_weights.x * joints[int(joints_offset + joint_ids.x)] +
+        joint_weights.y * joints[int(joints_offset + joint_ids.y)] +
+        joint_weights.z * joints[int(joints_offset + joint_ids.z)] +
+        joint_weights.w * joints[int(joints_offset + joint_ids.w)];
+
+    mat4 model = mat4(model_x, model_y, model_z, model_w);
+    gl_Position = proj_view * model * joint_transform * vec4(position, 1.0);
+}
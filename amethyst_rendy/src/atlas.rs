@@ -0,0 +1,672 @@
+//! Texture atlases packed at runtime from many small textures, e.g. UI icons or sprites supplied
+//! by mods, so they're drawn with fewer texture binds and draw calls.
+use crate::{
+    sprite::{Sprite, SpriteRender, SpriteSheet},
+    types::{Backend, Texture, TextureData},
+};
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::ecs::{Read, ReadExpect, System, World, Write};
+use rendy::{
+    command::{
+        CommandPool, Encoder, Family, Graphics, IndividualReset, MultiShot, NoSimultaneousUse,
+        PrimaryLevel, Submit,
+    },
+    factory::Factory,
+    frame::{cirque::CommandCirque, Frames},
+    graph::{GraphContext, Node, NodeBuffer, NodeDesc, NodeImage, NodeSubmittable},
+    hal::{
+        self,
+        image::{Filter, Kind},
+    },
+    texture::{pixel::Rgba8Srgb, TextureBuilder},
+};
+use std::{collections::HashMap, marker::PhantomData};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Area of an atlas page taken by a packed texture, in pixels from the top left corner of the
+/// page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasRect {
+    /// Index of the page.
+    pub page: usize,
+    /// Left side of the area.
+    pub x: u32,
+    /// Top of the area.
+    pub y: u32,
+    /// Width of the area.
+    pub width: u32,
+    /// Height of the area.
+    pub height: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    used: u32,
+}
+
+/// Packs rectangles on shelves into pages of a fixed size, opening a new page when a rectangle
+/// doesn't fit on the existing ones. Rectangles are kept `padding` pixels apart from each other
+/// and from the borders of the page, so filtering doesn't bleed between them.
+#[derive(Clone, Debug)]
+pub struct AtlasPacker {
+    width: u32,
+    height: u32,
+    padding: u32,
+    pages: Vec<Vec<Shelf>>,
+}
+
+impl AtlasPacker {
+    /// Create a packer for pages of the given size.
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Returns the width and height of the pages.
+    pub fn page_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the number of pages opened so far.
+    pub fn pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Pack a rectangle of the given size, returning the area it takes. Returns `None` if the
+    /// rectangle doesn't fit on a page at all.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<AtlasRect> {
+        let padding = self.padding;
+        if width + 2 * padding > self.width || height + 2 * padding > self.height {
+            return None;
+        }
+
+        // The shelf wasting the least height, if any has room left.
+        let mut best: Option<(usize, usize, u32)> = None;
+        for (page, shelves) in self.pages.iter().enumerate() {
+            for (index, shelf) in shelves.iter().enumerate() {
+                let fits = shelf.height >= height && shelf.used + width + padding <= self.width;
+                if fits && best.iter().all(|&(_, _, best)| shelf.height < best) {
+                    best = Some((page, index, shelf.height));
+                }
+            }
+        }
+
+        let (page, index) = match best {
+            Some((page, index, _)) => (page, index),
+            None => {
+                let bottom = |shelves: &[Shelf]| {
+                    shelves
+                        .last()
+                        .map_or(padding, |shelf| shelf.y + shelf.height + padding)
+                };
+                let page = match self
+                    .pages
+                    .iter()
+                    .position(|shelves| bottom(shelves) + height + padding <= self.height)
+                {
+                    Some(page) => page,
+                    None => {
+                        self.pages.push(Vec::new());
+                        self.pages.len() - 1
+                    }
+                };
+                let shelves = &mut self.pages[page];
+                shelves.push(Shelf {
+                    y: bottom(shelves),
+                    height,
+                    used: padding,
+                });
+                (page, shelves.len() - 1)
+            }
+        };
+
+        let shelf = &mut self.pages[page][index];
+        let rect = AtlasRect {
+            page,
+            x: shelf.used,
+            y: shelf.y,
+            width,
+            height,
+        };
+        shelf.used += width + padding;
+        Some(rect)
+    }
+}
+
+/// Identifies a texture inserted into the `TextureAtlas`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AtlasSprite(usize);
+
+#[derive(Debug)]
+struct AtlasEntry {
+    source: Handle<Texture>,
+    sprite: Option<SpriteRender>,
+}
+
+#[derive(Debug)]
+struct AtlasPage {
+    texture: Handle<Texture>,
+    sheet: Handle<SpriteSheet>,
+    sprites: Vec<Sprite>,
+    copies: Vec<(Handle<Texture>, AtlasRect)>,
+}
+
+/// Resource packing textures into shared atlas pages at runtime. Every page is a texture with a
+/// `SpriteSheet` holding a sprite for each texture packed into it, so sprites of different
+/// textures on the same page are batched together by `DrawFlat2D`.
+///
+/// Textures are packed by the `TextureAtlasSystem` once they are loaded and copied into their
+/// page on the GPU, both added by the `RenderTextureAtlas` plugin. Only the first mip level of
+/// textures with 32 bit texels is copied, and pages are sampled with the filter of the atlas
+/// rather than the samplers of the packed textures.
+#[derive(Debug)]
+pub struct TextureAtlas {
+    packer: AtlasPacker,
+    filter: Filter,
+    entries: Vec<AtlasEntry>,
+    sources: HashMap<Handle<Texture>, AtlasSprite>,
+    pending: Vec<AtlasSprite>,
+    pages: Vec<AtlasPage>,
+}
+
+impl Default for TextureAtlas {
+    fn default() -> Self {
+        Self::new(1024, 1024)
+    }
+}
+
+impl TextureAtlas {
+    /// Create an atlas with pages of the given size and one pixel of padding between textures.
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        Self {
+            packer: AtlasPacker::new(page_width, page_height, 1),
+            filter: Filter::Nearest,
+            entries: Vec::new(),
+            sources: HashMap::new(),
+            pending: Vec::new(),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Set the number of pixels kept around packed textures, 1 by default.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        let (width, height) = self.packer.page_size();
+        self.packer = AtlasPacker::new(width, height, padding);
+        self
+    }
+
+    /// Set the filter the pages are sampled with, `Filter::Nearest` by default.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Insert a texture to be packed into the atlas once it's loaded. Inserting the same texture
+    /// again returns the sprite it was inserted as before.
+    pub fn insert(&mut self, texture: &Handle<Texture>) -> AtlasSprite {
+        if let Some(&sprite) = self.sources.get(texture) {
+            return sprite;
+        }
+        let sprite = AtlasSprite(self.entries.len());
+        self.entries.push(AtlasEntry {
+            source: texture.clone(),
+            sprite: None,
+        });
+        self.sources.insert(texture.clone(), sprite);
+        self.pending.push(sprite);
+        sprite
+    }
+
+    /// Returns the sprite rendering the texture from its atlas page, once the texture is packed.
+    pub fn sprite(&self, sprite: AtlasSprite) -> Option<&SpriteRender> {
+        self.entries[sprite.0].sprite.as_ref()
+    }
+
+    /// Returns `true` if the texture is packed into a page.
+    pub fn is_packed(&self, sprite: AtlasSprite) -> bool {
+        self.entries[sprite.0].sprite.is_some()
+    }
+
+    /// Returns the textures of the atlas pages.
+    pub fn pages(&self) -> impl Iterator<Item = &Handle<Texture>> {
+        self.pages.iter().map(|page| &page.texture)
+    }
+
+    /// Packs the pending textures with a known size, creating the pages they need. Returns the
+    /// indices of the pages with new sprites.
+    fn pack_loaded(
+        &mut self,
+        size: impl Fn(&Handle<Texture>) -> Option<(u32, u32)>,
+        mut new_page: impl FnMut(&TextureAtlas) -> (Handle<Texture>, Handle<SpriteSheet>),
+    ) -> Vec<usize> {
+        let mut changed = Vec::new();
+        let pending = std::mem::take(&mut self.pending);
+        for sprite in pending {
+            let source = self.entries[sprite.0].source.clone();
+            let (width, height) = match size(&source) {
+                Some(size) => size,
+                None => {
+                    self.pending.push(sprite);
+                    continue;
+                }
+            };
+            let rect = match self.packer.pack(width, height) {
+                Some(rect) => rect,
+                None => {
+                    log::error!(
+                        "Texture {:?} of {}x{} pixels doesn't fit into atlas pages of {:?}",
+                        source.id(),
+                        width,
+                        height,
+                        self.packer.page_size(),
+                    );
+                    continue;
+                }
+            };
+            while self.pages.len() <= rect.page {
+                let (texture, sheet) = new_page(self);
+                self.pages.push(AtlasPage {
+                    texture,
+                    sheet,
+                    sprites: Vec::new(),
+                    copies: Vec::new(),
+                });
+            }
+
+            let (page_width, page_height) = self.packer.page_size();
+            let page = &mut self.pages[rect.page];
+            page.sprites.push(Sprite::from_pixel_values(
+                page_width,
+                page_height,
+                width,
+                height,
+                rect.x,
+                rect.y,
+                [0.0; 2],
+                false,
+                false,
+            ));
+            page.copies.push((source, rect));
+            self.entries[sprite.0].sprite = Some(SpriteRender::new(
+                page.sheet.clone(),
+                page.sprites.len() - 1,
+            ));
+            if !changed.contains(&rect.page) {
+                changed.push(rect.page);
+            }
+        }
+        changed
+    }
+
+    /// Returns the data of a new, transparent page texture.
+    fn page_data(&self) -> TextureData {
+        let (width, height) = self.packer.page_size();
+        TextureBuilder::new()
+            .with_kind(Kind::D2(width, height, 1, 1))
+            .with_view_kind(hal::image::ViewKind::D2)
+            .with_data_width(width)
+            .with_data_height(height)
+            .with_sampler_info(hal::image::SamplerInfo::new(
+                self.filter,
+                hal::image::WrapMode::Clamp,
+            ))
+            .with_data(vec![Rgba8Srgb::default(); (width * height) as usize])
+            .into()
+    }
+
+    /// Takes the pending copies into pages which are loaded, as page texture, source texture and
+    /// destination area.
+    fn take_copies(
+        &mut self,
+        loaded: impl Fn(&Handle<Texture>) -> bool,
+    ) -> Vec<(Handle<Texture>, Handle<Texture>, AtlasRect)> {
+        let mut copies = Vec::new();
+        for page in self.pages.iter_mut().filter(|page| loaded(&page.texture)) {
+            let texture = &page.texture;
+            copies.extend(
+                page.copies
+                    .drain(..)
+                    .map(|(source, rect)| (texture.clone(), source, rect)),
+            );
+        }
+        copies
+    }
+}
+
+/// Packs the textures inserted into the `TextureAtlas` once they are loaded, creating atlas pages
+/// as needed and updating their sprite sheets.
+#[derive(Debug, derivative::Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct TextureAtlasSystem<B: Backend>(PhantomData<B>);
+
+impl<'a, B: Backend> System<'a> for TextureAtlasSystem<B> {
+    type SystemData = (
+        Write<'a, TextureAtlas>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Texture>>,
+        Write<'a, AssetStorage<SpriteSheet>>,
+    );
+
+    fn run(&mut self, (mut atlas, loader, textures, mut sheets): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("texture_atlas");
+
+        let changed = atlas.pack_loaded(
+            |source| {
+                let extent = B::unwrap_texture(textures.get(source)?)?
+                    .image()
+                    .kind()
+                    .extent();
+                Some((extent.width, extent.height))
+            },
+            |atlas| {
+                let texture = loader.load_from_data(atlas.page_data(), (), &textures);
+                let sheet = sheets.insert(SpriteSheet {
+                    texture: texture.clone(),
+                    sprites: Vec::new(),
+                    durations: Vec::new(),
+                });
+                (texture, sheet)
+            },
+        );
+        for page in changed.into_iter().map(|page| &atlas.pages[page]) {
+            sheets.replace(
+                &page.sheet,
+                SpriteSheet {
+                    texture: page.texture.clone(),
+                    sprites: page.sprites.clone(),
+                    durations: Vec::new(),
+                },
+            );
+        }
+    }
+}
+
+/// Copies the textures packed into the `TextureAtlas` into their pages.
+#[derive(Debug)]
+pub(crate) struct CopyToAtlasDesc;
+
+impl<B: Backend> NodeDesc<B, World> for CopyToAtlasDesc {
+    type Node = CopyToAtlas<B>;
+
+    fn build<'a>(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _aux: &World,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Self::Node, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let pool = factory
+            .create_command_pool(family)?
+            .with_capability()
+            .map_err(|_| failure::format_err!("Texture atlases require a graphics queue"))?;
+        Ok(CopyToAtlas {
+            pool,
+            cirque: CommandCirque::new(),
+        })
+    }
+}
+
+/// Node copying the textures packed into the `TextureAtlas` into their pages.
+#[derive(Debug)]
+pub(crate) struct CopyToAtlas<B: Backend> {
+    pool: CommandPool<B, Graphics, IndividualReset>,
+    cirque: CommandCirque<B, Graphics>,
+}
+
+impl<'a, B: Backend> NodeSubmittable<'a, B> for CopyToAtlas<B> {
+    type Submittable = Submit<B>;
+    type Submittables = Option<Submit<B>>;
+}
+
+impl<B: Backend> Node<B, World> for CopyToAtlas<B> {
+    type Capability = Graphics;
+    type Desc = CopyToAtlasDesc;
+
+    fn run<'a>(
+        &'a mut self,
+        _ctx: &GraphContext<B>,
+        _factory: &Factory<B>,
+        world: &World,
+        frames: &'a Frames<B>,
+    ) -> Option<Submit<B>> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run");
+
+        let texture_storage = world.fetch::<AssetStorage<Texture>>();
+        let copies = world
+            .fetch_mut::<TextureAtlas>()
+            .take_copies(|page| texture_storage.contains(page));
+        let images = copies
+            .iter()
+            .filter_map(|(page, source, rect)| {
+                let page = B::unwrap_texture(texture_storage.get(page)?)?.image();
+                let source = B::unwrap_texture(texture_storage.get(source)?)?.image();
+                if page.format().surface_desc().bits != source.format().surface_desc().bits {
+                    log::error!(
+                        "Can't copy texture of format {:?} into atlas page of format {:?}",
+                        source.format(),
+                        page.format(),
+                    );
+                    return None;
+                }
+                Some((page.raw(), source.raw(), rect))
+            })
+            .collect::<Vec<_>>();
+
+        // Copies are only recorded for the frame they are taken in.
+        let submit = self.cirque.encode(frames, &mut self.pool, |cbuf| {
+            cbuf.or_reset(|cbuf| cbuf.reset()).init(|cbuf| {
+                let mut cbuf = cbuf.begin(MultiShot(NoSimultaneousUse), ());
+                let mut encoder = cbuf.encoder();
+                for &(page, source, rect) in &images {
+                    unsafe {
+                        copy_into_page(&mut encoder, source, page, rect);
+                    }
+                }
+                cbuf.finish()
+            })
+        });
+        Some(submit)
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _world: &World) {
+        let pool = &mut self.pool;
+        self.cirque.dispose(|buffer| {
+            buffer.either_with(
+                &mut *pool,
+                |pool, executable| pool.free_buffers(Some(executable)),
+                |pool, pending| pool.free_buffers(Some(pending.mark_complete())),
+            );
+        });
+        factory.destroy_command_pool(self.pool.with_queue_type());
+    }
+}
+
+/// Records copying the first mip level of a texture into an area of an atlas page. Both images
+/// are kept in the layout used for sampling outside of the copy.
+unsafe fn copy_into_page<B: Backend>(
+    encoder: &mut Encoder<'_, B, Graphics, PrimaryLevel>,
+    source: &B::Image,
+    page: &B::Image,
+    rect: &AtlasRect,
+) {
+    let range = hal::image::SubresourceRange {
+        aspects: hal::format::Aspects::COLOR,
+        levels: 0..1,
+        layers: 0..1,
+    };
+    let layers = hal::image::SubresourceLayers {
+        aspects: hal::format::Aspects::COLOR,
+        level: 0,
+        layers: 0..1,
+    };
+    let barrier =
+        |target, states: std::ops::Range<hal::image::State>| hal::memory::Barrier::Image {
+            states,
+            target,
+            families: None,
+            range: range.clone(),
+        };
+    let sampled = (
+        hal::image::Access::SHADER_READ,
+        hal::image::Layout::ShaderReadOnlyOptimal,
+    );
+    let transfer_src = (
+        hal::image::Access::TRANSFER_READ,
+        hal::image::Layout::TransferSrcOptimal,
+    );
+    let transfer_dst = (
+        hal::image::Access::TRANSFER_WRITE,
+        hal::image::Layout::TransferDstOptimal,
+    );
+    let shaders = hal::pso::PipelineStage::VERTEX_SHADER | hal::pso::PipelineStage::FRAGMENT_SHADER;
+
+    encoder.pipeline_barrier(
+        shaders..hal::pso::PipelineStage::TRANSFER,
+        hal::memory::Dependencies::empty(),
+        vec![
+            barrier(source, sampled..transfer_src),
+            barrier(page, sampled..transfer_dst),
+        ],
+    );
+    encoder.copy_image(
+        source,
+        hal::image::Layout::TransferSrcOptimal,
+        page,
+        hal::image::Layout::TransferDstOptimal,
+        Some(hal::command::ImageCopy {
+            src_subresource: layers.clone(),
+            src_offset: hal::image::Offset::ZERO,
+            dst_subresource: layers,
+            dst_offset: hal::image::Offset {
+                x: rect.x as i32,
+                y: rect.y as i32,
+                z: 0,
+            },
+            extent: hal::image::Extent {
+                width: rect.width,
+                height: rect.height,
+                depth: 1,
+            },
+        }),
+    );
+    encoder.pipeline_barrier(
+        hal::pso::PipelineStage::TRANSFER..shaders,
+        hal::memory::Dependencies::empty(),
+        vec![
+            barrier(source, transfer_src..sampled),
+            barrier(page, transfer_dst..sampled),
+        ],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::TextureCoordinates;
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn packer_fills_shelves_before_opening_pages() {
+        let mut packer = AtlasPacker::new(64, 64, 1);
+        let rects = (0..4)
+            .map(|_| packer.pack(30, 20).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rects
+                .iter()
+                .map(|rect| (rect.x, rect.y))
+                .collect::<Vec<_>>(),
+            vec![(1, 1), (32, 1), (1, 22), (32, 22)]
+        );
+        assert_eq!(packer.pages(), 1);
+
+        // A shorter rectangle fits on an existing shelf with room left, a taller one doesn't.
+        let mut packer = AtlasPacker::new(64, 64, 1);
+        packer.pack(30, 20).unwrap();
+        assert_eq!((packer.pack(10, 8).unwrap().x, packer.pages()), (32, 1));
+        assert_eq!(packer.pack(10, 30).unwrap().y, 22);
+
+        // Too tall for the room left on the first page.
+        assert_eq!(packer.pack(10, 40).unwrap().page, 1);
+        assert_eq!(packer.pack(63, 10), None);
+    }
+
+    #[test]
+    fn atlas_packs_loaded_textures_into_pages() {
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let textures = AssetStorage::<Texture>::default();
+        let mut sheets = AssetStorage::<SpriteSheet>::default();
+        let mut atlas = TextureAtlas::new(64, 32);
+        let icons = (0..3)
+            .map(|_| loader.load_from_data(atlas.page_data(), (), &textures))
+            .collect::<Vec<_>>();
+
+        let first = atlas.insert(&icons[0]);
+        let second = atlas.insert(&icons[1]);
+        let third = atlas.insert(&icons[2]);
+        assert_eq!(atlas.insert(&icons[0]), first);
+
+        // The third icon isn't loaded yet.
+        let mut new_page = |atlas: &TextureAtlas| {
+            let texture = loader.load_from_data(atlas.page_data(), (), &textures);
+            let sheet = sheets.insert(SpriteSheet {
+                texture: texture.clone(),
+                sprites: Vec::new(),
+                durations: Vec::new(),
+            });
+            (texture, sheet)
+        };
+        let changed = atlas.pack_loaded(
+            |source| {
+                if *source == icons[2] {
+                    None
+                } else {
+                    Some((16, 30))
+                }
+            },
+            &mut new_page,
+        );
+        assert_eq!(changed, vec![0]);
+        assert!(atlas.is_packed(first) && atlas.is_packed(second));
+        assert!(!atlas.is_packed(third));
+        assert_eq!(atlas.sprite(second).unwrap().sprite_number, 1);
+        assert_eq!(
+            atlas.pages[0].sprites[1].tex_coords,
+            TextureCoordinates {
+                left: 18.0 / 64.0,
+                right: 34.0 / 64.0,
+                top: 1.0 / 32.0,
+                bottom: 31.0 / 32.0,
+            }
+        );
+
+        let changed = atlas.pack_loaded(|_| Some((48, 20)), &mut new_page);
+        assert_eq!(changed, vec![1]);
+        assert_eq!(atlas.pages().count(), 2);
+        let third = atlas.sprite(third).unwrap();
+        assert_eq!(third.sprite_sheet, atlas.pages[1].sheet);
+        assert_eq!(third.sprite_number, 0);
+
+        let first_page = atlas.pages[0].texture.clone();
+        assert_eq!(atlas.take_copies(|page| *page == first_page).len(), 2);
+        assert_eq!(atlas.take_copies(|_| true).len(), 1);
+        assert!(atlas.take_copies(|_| true).is_empty());
+    }
+}
@@ -390,6 +390,93 @@ where
     }
 }
 
+/// A batching implementation sorting data `D` by a sort key `K`, then grouping it by contiguous
+/// ranges of equal primary key `PK`. This is used, for example, to sort sprites by texture and
+/// depth before drawing them with one instanced draw call per texture.
+///
+/// Only the sort keys and indices are sorted, the data itself is only moved when it's read in
+/// sorted order through [SortedBatch::data], e.g. straight into a vertex buffer.
+#[derive(Derivative, Debug)]
+#[derivative(Default(bound = ""))]
+pub struct SortedBatch<K, PK, D>
+where
+    PK: PartialEq,
+{
+    order: Vec<(K, u32)>,
+    items: Vec<(PK, D)>,
+    old_keys: Vec<(PK, u32)>,
+    keys_list: Vec<(PK, u32)>,
+}
+
+impl<K, PK, D> SortedBatch<K, PK, D>
+where
+    K: Ord,
+    PK: PartialEq + Copy,
+{
+    /// Clears all data and indices from this batch set.
+    pub fn swap_clear(&mut self) {
+        std::mem::swap(&mut self.old_keys, &mut self.keys_list);
+        self.keys_list.clear();
+        self.order.clear();
+        self.items.clear();
+    }
+
+    /// Inserts an item of batch data for `PK`, drawn in the order of its sort key.
+    pub fn insert(&mut self, pk: PK, key: K, data: D) {
+        self.order.push((key, self.items.len() as u32));
+        self.items.push((pk, data));
+    }
+
+    /// Sorts the inserted data and groups it by primary key. Items with equal sort keys are kept
+    /// in insertion order.
+    pub fn sort(&mut self) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("sorted_batch_sort");
+
+        // A stable sort is faster than an unstable one here, as sprites usually share few
+        // distinct keys.
+        self.order.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let items = &self.items;
+        let keys_list = &mut self.keys_list;
+        for &(_, index) in &self.order {
+            let pk = items[index as usize].0;
+            match keys_list.last_mut() {
+                Some((last_pk, last_len)) if *last_pk == pk => *last_len += 1,
+                _ => keys_list.push((pk, 1)),
+            }
+        }
+    }
+
+    /// Returns an iterator over the batch data in sorted order.
+    pub fn data(&self) -> impl Iterator<Item = &D> {
+        let items = &self.items;
+        self.order
+            .iter()
+            .map(move |&(_, index)| &items[index as usize].1)
+    }
+
+    /// Iterator that returns primary keys and ranges of the sorted data using them.
+    pub fn iter(&self) -> impl Iterator<Item = (&PK, Range<u32>)> {
+        let mut offset = 0;
+        self.keys_list.iter().map(move |(pk, size)| {
+            let range = offset..offset + *size;
+            offset = range.end;
+            (pk, range)
+        })
+    }
+
+    /// Returns true if sorting this batch resulted in a change in order.
+    pub fn changed(&self) -> bool {
+        self.keys_list != self.old_keys
+    }
+
+    /// Returns the number of items currently in this batch.
+    pub fn count(&self) -> usize {
+        self.items.len()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +509,46 @@ mod tests {
         assert_eq!(batch.count(), 0);
         assert_eq!(batch.iter().collect::<Vec<_>>(), vec![]);
     }
+
+    #[test]
+    fn test_sorted_batch_groups_by_primary_key() {
+        let mut batch = SortedBatch::<(u32, u32), u32, char>::default();
+        batch.insert(1, (1, 0), 'a');
+        batch.insert(0, (0, 1), 'b');
+        batch.insert(1, (1, 1), 'c');
+        batch.insert(0, (0, 0), 'd');
+        batch.sort();
+        assert_eq!(batch.count(), 4);
+        assert_eq!(batch.data().collect::<String>(), "dbac");
+        assert_eq!(
+            batch.iter().collect::<Vec<_>>(),
+            vec![(&0, 0..2), (&1, 2..4)]
+        );
+    }
+
+    #[test]
+    fn test_sorted_batch_keeps_insertion_order_of_equal_keys() {
+        let mut batch = SortedBatch::<u32, u32, char>::default();
+        batch.insert(0, 0, 'a');
+        batch.insert(1, 0, 'b');
+        batch.insert(0, 0, 'c');
+        batch.sort();
+        assert_eq!(batch.data().collect::<String>(), "abc");
+        assert_eq!(
+            batch.iter().collect::<Vec<_>>(),
+            vec![(&0, 0..1), (&1, 1..2), (&0, 2..3)]
+        );
+    }
+
+    #[test]
+    fn test_sorted_batch_changed() {
+        let mut batch = SortedBatch::<u32, u32, ()>::default();
+        batch.insert(0, 0, ());
+        batch.sort();
+        assert!(batch.changed());
+        batch.swap_clear();
+        batch.insert(0, 0, ());
+        batch.sort();
+        assert!(!batch.changed());
+    }
 }
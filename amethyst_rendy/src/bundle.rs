@@ -6,11 +6,12 @@ use crate::{
         factory::Factory,
         graph::{
             render::{RenderGroupBuilder, RenderPassNodeBuilder, SubpassBuilder},
-            GraphBuilder, ImageId, NodeId,
+            BufferId, GraphBuilder, ImageId, NodeId,
         },
         hal,
         wsi::Surface,
     },
+    shader::ShaderAsset,
     system::{GraphCreator, MeshProcessorSystem, RenderingSystem, TextureProcessorSystem},
     types::Backend,
     SpriteSheet,
@@ -84,6 +85,7 @@ impl<'a, 'b, B: Backend> SystemBundle<'a, 'b> for RenderingBundle<B> {
             "sprite_sheet_processor",
             &[],
         );
+        builder.add(Processor::<ShaderAsset>::new(), "shader_processor", &[]);
 
         // make sure that all renderer-specific systems run after game code
         builder.add_barrier();
@@ -126,8 +128,8 @@ impl<B: Backend> GraphCreator<B> for PluggableRenderGraphCreator<B> {
 /// Basic building block of rendering in [RenderingBundle].
 ///
 /// Can be used to register rendering-related systems to the dispatcher,
-/// building render graph by registering render targets, adding [RenderableAction]s to them
-/// and signalling when the graph has to be rebuild.
+/// building render graph by registering render targets, adding [RenderableAction]s to them,
+/// defining targets backed by custom graph nodes and signalling when the graph has to be rebuild.
 pub trait RenderPlugin<B: Backend>: std::fmt::Debug {
     /// Hook for adding systems and bundles to the dispatcher.
     fn on_build<'a, 'b>(
@@ -191,6 +193,28 @@ impl<B: Backend> RenderPlan<B> {
         Ok(())
     }
 
+    /// Define a render target backed by a custom render graph node instead of a render pass,
+    /// e.g. a compute dispatch or a pass with its own pipeline layout. The closure adds the node
+    /// to the graph and returns its id. Images registered through the context can be used by
+    /// other targets like regular target outputs, and buffers registered through it are
+    /// retrieved with `get_buffer`. Compute shaders are dispatched with `DispatchComputeDesc`.
+    ///
+    /// Custom node targets can't be extended with render groups.
+    pub fn define_custom_node(
+        &mut self,
+        target: Target,
+        closure: impl FnOnce(&mut CustomNodeContext<'_, B>) -> Result<NodeId, Error> + 'static,
+    ) -> Result<(), Error> {
+        let target_plan = self
+            .targets
+            .entry(target)
+            .or_insert_with(|| TargetPlan::new(target));
+
+        target_plan.set_custom_node(Box::new(closure))?;
+
+        Ok(())
+    }
+
     /// Extend the rendering plan of a render target. Target can be defined in other plugins.
     /// The closure is evaluated only if the target contributes to the rendering result, e.g.
     /// is rendered to a window or is a dependency of other evaluated target.
@@ -216,6 +240,7 @@ impl<B: Backend> RenderPlan<B> {
             targets: self.targets,
             passes: Default::default(),
             outputs: Default::default(),
+            buffers: Default::default(),
             graph_builder: GraphBuilder::new(),
         };
 
@@ -261,6 +286,7 @@ struct PlanContext<B: Backend> {
     target_metadata: HashMap<Target, TargetMetadata>,
     passes: HashMap<Target, EvaluationState>,
     outputs: HashMap<TargetImage, ImageId>,
+    buffers: HashMap<(Target, usize), BufferId>,
     graph_builder: GraphBuilder<B, World>,
 }
 
@@ -289,6 +315,11 @@ impl<B: Backend> PlanContext<B> {
         target: Target,
         pass: RenderPassNodeBuilder<B, World>,
     ) -> Result<(), Error> {
+        let node = self.graph_builder.add_node(pass);
+        self.submit_node(target, node)
+    }
+
+    fn submit_node(&mut self, target: Target, node: NodeId) -> Result<(), Error> {
         match self.passes.get(&target) {
             None => {}
             Some(EvaluationState::Evaluating) => {}
@@ -298,7 +329,6 @@ impl<B: Backend> PlanContext<B> {
                 target
             ),
         };
-        let node = self.graph_builder.add_node(pass);
         self.passes.insert(target, EvaluationState::Built(node));
         Ok(())
     }
@@ -356,6 +386,40 @@ impl<B: Backend> PlanContext<B> {
         Ok(())
     }
 
+    fn get_buffer(&mut self, target: Target, index: usize) -> Result<BufferId, Error> {
+        self.try_get_buffer(target, index)?.ok_or_else(|| {
+            format_err!(
+                "Buffer {} is not registered by the target {:?}.",
+                index,
+                target
+            )
+        })
+    }
+
+    fn try_get_buffer(&mut self, target: Target, index: usize) -> Result<Option<BufferId>, Error> {
+        if !self.passes.get(&target).map_or(false, |t| t.is_built()) {
+            self.evaluate_target(target)?;
+        }
+        Ok(self.buffers.get(&(target, index)).cloned())
+    }
+
+    fn register_buffer(
+        &mut self,
+        target: Target,
+        index: usize,
+        buffer: BufferId,
+    ) -> Result<(), Error> {
+        if self.buffers.contains_key(&(target, index)) {
+            return Err(format_err!(
+                "Trying to register already registered buffer {} of {:?}",
+                index,
+                target
+            ));
+        }
+        self.buffers.insert((target, index), buffer);
+        Ok(())
+    }
+
     pub fn graph(&mut self) -> &mut GraphBuilder<B, World> {
         &mut self.graph_builder
     }
@@ -364,6 +428,10 @@ impl<B: Backend> PlanContext<B> {
         self.graph_builder
             .create_image(options.kind, options.levels, options.format, options.clear)
     }
+
+    pub fn create_buffer(&mut self, size: u64) -> BufferId {
+        self.graph_builder.create_buffer(size)
+    }
 }
 
 /// A planning context focused on specific render target.
@@ -373,6 +441,7 @@ pub struct TargetPlanContext<'a, B: Backend> {
     key: Target,
     colors: usize,
     depth: bool,
+    samples: hal::image::NumSamples,
     actions: Vec<(i32, RenderableAction<B>)>,
     deps: Vec<NodeId>,
 }
@@ -413,6 +482,12 @@ impl<'a, B: Backend> TargetPlanContext<'a, B> {
         self.depth
     }
 
+    /// Get number of samples per pixel of current render target. Pipelines of render groups
+    /// added to multisampled targets must use the same number of samples.
+    pub fn samples(&self) -> hal::image::NumSamples {
+        self.samples
+    }
+
     /// Retrieve an image produced by other render target.
     ///
     /// Results in an error if such image doesn't exist or
@@ -444,9 +519,24 @@ impl<'a, B: Backend> TargetPlanContext<'a, B> {
         })
     }
 
+    /// Retrieve a buffer registered by a custom node target, e.g. the output of a compute
+    /// dispatch. The node registering it is added as a dependency.
+    ///
+    /// Results in an error if such buffer doesn't exist or
+    /// retreiving it would result in a dependency cycle.
+    pub fn get_buffer(&mut self, target: Target, index: usize) -> Result<BufferId, Error> {
+        let buffer = self.plan_context.get_buffer(target, index)?;
+        let node = self
+            .plan_context
+            .get_pass_node_raw(target)
+            .expect("Buffer without target node");
+        self.add_dep(node);
+        Ok(buffer)
+    }
+
     /// Add explicit dependency on another node.
     ///
-    /// This is done automatically when you use `get_image`.
+    /// This is done automatically when you use `get_image` or `get_buffer`.
     pub fn add_dep(&mut self, node: NodeId) {
         if !self.deps.contains(&node) {
             self.deps.push(node);
@@ -472,6 +562,131 @@ impl<'a, B: Backend> TargetPlanContext<'a, B> {
     }
 }
 
+/// A planning context of a render target backed by a custom render graph node.
+#[derive(Debug)]
+pub struct CustomNodeContext<'a, B: Backend> {
+    plan_context: &'a mut PlanContext<B>,
+    key: Target,
+    deps: Vec<NodeId>,
+}
+
+impl<'a, B: Backend> CustomNodeContext<'a, B> {
+    /// Retrieve an image produced by other render target.
+    /// The node producing it is added to `dependencies`.
+    ///
+    /// Results in an error if such image doesn't exist or
+    /// retreiving it would result in a dependency cycle.
+    pub fn get_image(&mut self, image: TargetImage) -> Result<ImageId, Error> {
+        let id = self.plan_context.get_image(image)?;
+        self.add_image_dep(image);
+        Ok(id)
+    }
+
+    /// Retrieve an image produced by other render target.
+    /// Returns `None` when such image isn't registered.
+    ///
+    /// Results in an error if retreiving it would result in a dependency cycle.
+    pub fn try_get_image(&mut self, image: TargetImage) -> Result<Option<ImageId>, Error> {
+        let id = self.plan_context.try_get_image(image)?;
+        if id.is_some() {
+            self.add_image_dep(image);
+        }
+        Ok(id)
+    }
+
+    /// Retrieve a buffer registered by another custom node target.
+    /// The node registering it is added to `dependencies`.
+    ///
+    /// Results in an error if such buffer doesn't exist or
+    /// retreiving it would result in a dependency cycle.
+    pub fn get_buffer(&mut self, target: Target, index: usize) -> Result<BufferId, Error> {
+        let buffer = self.plan_context.get_buffer(target, index)?;
+        self.add_target_dep(target);
+        Ok(buffer)
+    }
+
+    /// Retrieve a buffer registered by another custom node target.
+    /// Returns `None` when such buffer isn't registered.
+    ///
+    /// Results in an error if retreiving it would result in a dependency cycle.
+    pub fn try_get_buffer(
+        &mut self,
+        target: Target,
+        index: usize,
+    ) -> Result<Option<BufferId>, Error> {
+        let buffer = self.plan_context.try_get_buffer(target, index)?;
+        if buffer.is_some() {
+            self.add_target_dep(target);
+        }
+        Ok(buffer)
+    }
+
+    fn add_image_dep(&mut self, image: TargetImage) {
+        self.add_target_dep(image.target());
+    }
+
+    fn add_target_dep(&mut self, target: Target) {
+        let node = self
+            .plan_context
+            .get_pass_node_raw(target)
+            .expect("Resource without target node");
+        if !self.deps.contains(&node) {
+            self.deps.push(node);
+        }
+    }
+
+    /// Nodes producing the images retrieved so far. The custom node must depend on them.
+    pub fn dependencies(&self) -> &[NodeId] {
+        &self.deps
+    }
+
+    /// Create a new image in the render graph.
+    pub fn create_image(&mut self, options: ImageOptions) -> ImageId {
+        self.plan_context.create_image(options)
+    }
+
+    /// Create a new buffer of the given size in bytes in the render graph. Its contents are kept
+    /// between frames, but not when the render graph is rebuilt.
+    pub fn create_buffer(&mut self, size: u64) -> BufferId {
+        self.plan_context.create_buffer(size)
+    }
+
+    /// Register a buffer written by the custom node as buffer output of this target,
+    /// so other targets can retrieve it with `get_buffer(target, index)`.
+    pub fn register_buffer(&mut self, index: usize, buffer: BufferId) -> Result<(), Error> {
+        self.plan_context.register_buffer(self.key, index, buffer)
+    }
+
+    /// Register an image written by the custom node as color output of this target,
+    /// so other targets can retrieve it as `TargetImage::Color(target, index)`.
+    pub fn register_color(&mut self, index: usize, image: ImageId) -> Result<(), Error> {
+        self.plan_context
+            .register_output(TargetImage::Color(self.key, index), image)
+    }
+
+    /// Register an image written by the custom node as depth output of this target,
+    /// so other targets can retrieve it as `TargetImage::Depth(target)`.
+    pub fn register_depth(&mut self, image: ImageId) -> Result<(), Error> {
+        self.plan_context
+            .register_output(TargetImage::Depth(self.key), image)
+    }
+
+    /// Access underlying rendy's GraphBuilder to add the custom node.
+    pub fn graph(&mut self) -> &mut GraphBuilder<B, World> {
+        self.plan_context.graph()
+    }
+
+    /// Retrieve render target metadata, e.g. size.
+    pub fn target_metadata(&self, target: Target) -> Option<TargetMetadata> {
+        self.plan_context.target_metadata(target)
+    }
+
+    /// Access computed NodeId of render target.
+    pub fn get_node(&mut self, target: Target) -> Result<NodeId, Error> {
+        self.plan_context.get_node(target)
+    }
+}
+
 /// An identifier for output image of specific render target.
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum TargetImage {
@@ -522,6 +737,9 @@ pub struct TargetPlanOutputs<B: Backend> {
     pub depth: Option<ImageOptions>,
 }
 
+type CustomNodeFn<B> =
+    Box<dyn FnOnce(&mut CustomNodeContext<'_, B>) -> Result<NodeId, Error> + 'static>;
+
 #[derive(derivative::Derivative)]
 #[derivative(Debug(bound = ""))]
 struct TargetPlan<B: Backend> {
@@ -529,6 +747,8 @@ struct TargetPlan<B: Backend> {
     #[derivative(Debug = "ignore")]
     extensions: Vec<Box<dyn FnOnce(&mut TargetPlanContext<'_, B>) -> Result<(), Error> + 'static>>,
     outputs: Option<TargetPlanOutputs<B>>,
+    #[derivative(Debug = "ignore")]
+    custom_node: Option<CustomNodeFn<B>>,
 }
 
 impl<B: Backend> TargetPlan<B> {
@@ -537,6 +757,7 @@ impl<B: Backend> TargetPlan<B> {
             key,
             extensions: vec![],
             outputs: None,
+            custom_node: None,
         }
     }
 
@@ -590,13 +811,43 @@ impl<B: Backend> TargetPlan<B> {
     }
 
     fn set_outputs(&mut self, outputs: TargetPlanOutputs<B>) -> Result<(), Error> {
-        if self.outputs.is_some() {
+        if self.outputs.is_some() || self.custom_node.is_some() {
             return Err(format_err!("Target {:?} already defined.", self.key));
         }
         self.outputs.replace(outputs);
         Ok(())
     }
 
+    fn set_custom_node(&mut self, custom_node: CustomNodeFn<B>) -> Result<(), Error> {
+        if self.outputs.is_some() || self.custom_node.is_some() {
+            return Err(format_err!("Target {:?} already defined.", self.key));
+        }
+        self.custom_node.replace(custom_node);
+        Ok(())
+    }
+
+    fn evaluate_custom_node(
+        self,
+        custom_node: CustomNodeFn<B>,
+        ctx: &mut PlanContext<B>,
+    ) -> Result<(), Error> {
+        if !self.extensions.is_empty() {
+            return Err(format_err!(
+                "Target {:?} is a custom node and can't be extended with render groups.",
+                self.key
+            ));
+        }
+        ctx.mark_evaluating(self.key)?;
+
+        let mut node_ctx = CustomNodeContext {
+            plan_context: ctx,
+            key: self.key,
+            deps: vec![],
+        };
+        let node = custom_node(&mut node_ctx)?;
+        ctx.submit_node(self.key, node)
+    }
+
     fn add_extension(
         &mut self,
         extension: Box<dyn FnOnce(&mut TargetPlanContext<'_, B>) -> Result<(), Error> + 'static>,
@@ -604,10 +855,13 @@ impl<B: Backend> TargetPlan<B> {
         self.extensions.push(extension);
     }
 
-    fn evaluate(self, ctx: &mut PlanContext<B>) -> Result<(), Error> {
+    fn evaluate(mut self, ctx: &mut PlanContext<B>) -> Result<(), Error> {
+        if let Some(custom_node) = self.custom_node.take() {
+            return self.evaluate_custom_node(custom_node, ctx);
+        }
         if self.outputs.is_none() {
             return Err(format_err!(
-                "Trying to evaluate not fully defined pass {:?}. Missing `define_pass` call.",
+                "Trying to evaluate not fully defined pass {:?}. Missing `define_pass` or `define_custom_node` call.",
                 self.key
             ));
         }
@@ -615,12 +869,29 @@ impl<B: Backend> TargetPlan<B> {
 
         ctx.mark_evaluating(self.key)?;
 
+        let samples = outputs
+            .colors
+            .iter()
+            .filter_map(|color| match color {
+                OutputColor::Image(options) => Some(options.kind.num_samples()),
+                OutputColor::Surface(..) => None,
+            })
+            .chain(
+                outputs
+                    .depth
+                    .iter()
+                    .map(|options| options.kind.num_samples()),
+            )
+            .max()
+            .unwrap_or(1);
+
         let mut target_ctx = TargetPlanContext {
             plan_context: ctx,
             key: self.key,
             actions: vec![],
             colors: outputs.colors.len(),
             depth: outputs.depth.is_some(),
+            samples,
             deps: vec![],
         };
 
@@ -763,6 +1034,16 @@ pub enum Target {
     ShadowMap,
     /// Custom render target identifier.
     Custom(&'static str),
+    /// Node copying the color output of the `Custom` target with the same name into the texture
+    /// of a `RenderTexture`.
+    TextureCopy(&'static str),
+    /// Node writing a GPU timestamp after the target timed under the given name by the
+    /// `RenderStatistics` plugin.
+    Timestamp(&'static str),
+    /// Node copying the textures packed into the `TextureAtlas` into their pages.
+    AtlasCopy,
+    /// Node reading back the color output of the target captured by the `RenderCapture` plugin.
+    Capture,
 }
 
 impl Default for Target {
@@ -775,11 +1056,13 @@ impl Default for Target {
 mod tests {
     use super::*;
     use crate::{
+        pass::{ComputeAccess, DispatchComputeDesc},
+        rendy::shader::SpirvShader,
         rendy::{
             command::QueueId,
             graph::{
                 render::{RenderGroup, RenderGroupDesc},
-                GraphContext, NodeBuffer, NodeImage,
+                GraphContext, NodeBuffer, NodeDesc, NodeImage,
             },
         },
         types::{Backend, DefaultBackend},
@@ -827,6 +1110,86 @@ mod tests {
         }
     }
 
+    #[test]
+    fn custom_node_target_cannot_be_redefined() {
+        let kind = crate::Kind::D2(1, 1, 1, 1);
+        let mut plan = RenderPlan::<DefaultBackend>::new();
+        plan.define_pass(
+            Target::Custom("outline"),
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba8Unorm,
+                    clear: None,
+                })],
+                depth: None,
+            },
+        )
+        .unwrap();
+        assert!(plan
+            .define_custom_node(Target::Custom("outline"), |_| unreachable!())
+            .is_err());
+
+        plan.define_custom_node(Target::Custom("fog"), |_| unreachable!())
+            .unwrap();
+        assert!(plan
+            .define_custom_node(Target::Custom("fog"), |_| unreachable!())
+            .is_err());
+    }
+
+    #[test]
+    fn custom_node_buffers_are_shared() {
+        let histogram = Target::Custom("histogram");
+        let exposure = Target::Custom("exposure");
+        let dispatch = |access| {
+            // Any SPIR-V will do, the graph is only planned and never built on a device.
+            let shader = SpirvShader::from_bytes(
+                include_bytes!("../compiled/vertex/pos_tex.vert.spv"),
+                hal::pso::ShaderStageFlags::COMPUTE,
+                "main",
+            )
+            .unwrap();
+            DispatchComputeDesc::new(shader, [1, 1, 1]).with_buffer_access(access)
+        };
+
+        let mut plan = RenderPlan::<DefaultBackend>::new();
+        plan.define_custom_node(histogram, move |ctx| {
+            let buffer = ctx.create_buffer(256 * 4);
+            ctx.register_buffer(0, buffer)?;
+            assert!(ctx.register_buffer(0, buffer).is_err());
+            let builder = dispatch(ComputeAccess::Write).builder().with_buffer(buffer);
+            Ok(ctx.graph().add_node(builder))
+        })
+        .unwrap();
+        plan.define_custom_node(exposure, move |ctx| {
+            let buffer = ctx.get_buffer(histogram, 0)?;
+            assert!(ctx.try_get_buffer(histogram, 1)?.is_none());
+            let histogram_node = ctx.get_node(histogram)?;
+            assert_eq!(ctx.dependencies(), &[histogram_node]);
+            let builder = dispatch(ComputeAccess::Read)
+                .builder()
+                .with_buffer(buffer)
+                .with_dependency(histogram_node);
+            Ok(ctx.graph().add_node(builder))
+        })
+        .unwrap();
+        plan.add_root(exposure);
+
+        // Plans without render passes are evaluated without the target metadata of a device.
+        let mut ctx = PlanContext {
+            targets: plan.targets,
+            target_metadata: Default::default(),
+            passes: Default::default(),
+            outputs: Default::default(),
+            buffers: Default::default(),
+            graph_builder: GraphBuilder::new(),
+        };
+        ctx.evaluate_target(exposure).unwrap();
+        assert!(ctx.get_pass_node_raw(histogram).is_some());
+        assert!(ctx.get_pass_node_raw(exposure).is_some());
+    }
+
     #[test]
     #[ignore] // CI can't run tests requiring actual backend
     fn main_pass_color_image_plan() {
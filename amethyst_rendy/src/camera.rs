@@ -59,6 +59,32 @@ impl Camera {
         )
     }
 
+    /// Create a pixel-perfect camera for 2D.
+    ///
+    /// Will use an orthographic projection of a window of `width` by `height` pixels, where one
+    /// world unit covers `scale` pixels. The origin lies on a pixel corner, also for odd sizes,
+    /// so that sprites at whole unit positions aren't sampled across pixels.
+    /// View transformation will be multiplicative identity.
+    ///
+    /// * panics if `scale` is zero
+    pub fn pixel_perfect_2d(width: u32, height: u32, scale: u32) -> Self {
+        assert!(
+            scale > 0,
+            "The scale of a pixel-perfect camera must not be zero."
+        );
+        let scale = scale as f32;
+        let left = -((width / 2) as f32) / scale;
+        let bottom = -((height / 2) as f32) / scale;
+        Self::orthographic(
+            left,
+            left + width as f32 / scale,
+            bottom,
+            bottom + height as f32 / scale,
+            0.125,
+            2000.0,
+        )
+    }
+
     /// An appropriate orthographic projection for the coordinate space used by Amethyst.
     /// Because we use vulkan coordinates internally and within the rendering engine, normal nalgebra
     /// projection objects (`Orthographic3` are incorrect for our use case.
@@ -385,6 +411,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pixel_perfect_2d_aligns_units_to_pixels() {
+        let diagonal = Vector2::new(801.0, 600.0);
+        let camera = Camera::pixel_perfect_2d(801, 600, 2);
+        let transform = Transform::default();
+
+        // The origin is on the corner of pixel (400, 300), one unit further are two pixels.
+        assert_abs_diff_eq!(
+            camera.world_to_screen(Point3::new(0.0, 0.0, -1.0), diagonal, &transform),
+            Point2::new(400.0, 300.0),
+            epsilon = 1e-3
+        );
+        assert_abs_diff_eq!(
+            camera.world_to_screen(Point3::new(1.0, 1.0, -1.0), diagonal, &transform),
+            Point2::new(402.0, 298.0),
+            epsilon = 1e-3
+        );
+    }
+
     #[test]
     fn screen_to_world_2d() {
         let diagonal = Vector2::new(1024.0, 768.0);
@@ -0,0 +1,306 @@
+//! Reading rendered frames back from the GPU, e.g. to compare them with reference images in
+//! tests.
+use crate::types::Backend;
+use amethyst_core::ecs::World;
+use image::RgbaImage;
+use rendy::{
+    command::{
+        CommandPool, Family, Graphics, IndividualReset, MultiShot, NoSimultaneousUse, Submit,
+    },
+    factory::Factory,
+    frame::{cirque::CommandCirque, Frames},
+    graph::{
+        gfx_acquire_barriers, gfx_release_barriers, GraphContext, ImageAccess, Node, NodeBuffer,
+        NodeDesc, NodeImage, NodeSubmittable,
+    },
+    hal::{self, command::RawCommandBuffer, format::Format},
+    memory::Download,
+    resource::{Buffer, BufferInfo, Escape},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Color output of a render target read back from the GPU, as 8 bit RGBA pixels in rows from top
+/// to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CapturedImage {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl CapturedImage {
+    /// Creates an image from its RGBA pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there aren't 4 bytes per pixel.
+    pub fn new(width: u32, height: u32, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            width as usize * height as usize * 4,
+            "Captured images have 4 bytes per pixel"
+        );
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the RGBA pixels of the image.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Converts the image into an `image` crate buffer, e.g. to save it.
+    pub fn into_rgba_image(self) -> RgbaImage {
+        RgbaImage::from_raw(self.width, self.height, self.data)
+            .expect("Captured images have 4 bytes per pixel")
+    }
+}
+
+impl From<RgbaImage> for CapturedImage {
+    fn from(image: RgbaImage) -> Self {
+        let (width, height) = image.dimensions();
+        Self::new(width, height, image.into_raw())
+    }
+}
+
+/// Resource holding the latest frame read back by the `RenderCapture` plugin.
+///
+/// Frames are read back a few frames after they're rendered, once the GPU is done with them, so
+/// the latest capture lags behind the rendered frame by the number of frames in flight.
+#[derive(Debug, Default)]
+pub struct CapturedFrames {
+    latest: Option<CapturedImage>,
+    captured: u64,
+}
+
+impl CapturedFrames {
+    /// Returns the latest captured frame.
+    pub fn latest(&self) -> Option<&CapturedImage> {
+        self.latest.as_ref()
+    }
+
+    /// Takes the latest captured frame out of the resource.
+    pub fn take(&mut self) -> Option<CapturedImage> {
+        self.latest.take()
+    }
+
+    /// Returns the number of frames captured since the resource was created.
+    pub fn captured(&self) -> u64 {
+        self.captured
+    }
+
+    fn record(&mut self, image: CapturedImage) {
+        self.latest = Some(image);
+        self.captured += 1;
+    }
+}
+
+/// Describes a render graph node copying a color image into host visible buffers every frame,
+/// which are read back into the `CapturedFrames` resource.
+#[derive(Debug, Default)]
+pub(crate) struct CaptureImageDesc;
+
+impl<B: Backend> NodeDesc<B, World> for CaptureImageDesc {
+    type Node = CaptureImage<B>;
+
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: hal::image::Access::TRANSFER_READ,
+            usage: hal::image::Usage::TRANSFER_SRC,
+            layout: hal::image::Layout::TransferSrcOptimal,
+            stages: hal::pso::PipelineStage::TRANSFER,
+        }]
+    }
+
+    fn build<'a>(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _world: &World,
+        _buffers: Vec<NodeBuffer>,
+        mut images: Vec<NodeImage>,
+    ) -> Result<Self::Node, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let image = images.remove(0);
+        let source = ctx.get_image(image.id).expect("Image does not exist");
+        let bgra = match source.format() {
+            Format::Rgba8Unorm | Format::Rgba8Srgb => false,
+            Format::Bgra8Unorm | Format::Bgra8Srgb => true,
+            format => {
+                return Err(failure::format_err!(
+                    "Captured images need an 8 bit RGBA or BGRA format, not {:?}",
+                    format
+                ))
+            }
+        };
+        let extent = source.kind().extent();
+
+        // One buffer per frame in flight, so a buffer is only reused after it has been read back.
+        let size = u64::from(extent.width) * u64::from(extent.height) * 4;
+        let buffers = (0..ctx.frames_in_flight)
+            .map(|_| {
+                factory.create_buffer(
+                    BufferInfo {
+                        size,
+                        usage: hal::buffer::Usage::TRANSFER_DST,
+                    },
+                    Download,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let pool = factory
+            .create_command_pool(family)?
+            .with_capability()
+            .map_err(|_| failure::format_err!("Captures require a graphics queue"))?;
+        Ok(CaptureImage {
+            image,
+            extent,
+            bgra,
+            buffers,
+            pool,
+            cirque: CommandCirque::new(),
+        })
+    }
+}
+
+/// Node copying a color image into host visible buffers and reading them back.
+#[derive(Debug)]
+pub(crate) struct CaptureImage<B: Backend> {
+    image: NodeImage,
+    extent: hal::image::Extent,
+    bgra: bool,
+    buffers: Vec<Escape<Buffer<B>>>,
+    pool: CommandPool<B, Graphics, IndividualReset>,
+    cirque: CommandCirque<B, Graphics>,
+}
+
+impl<B: Backend> CaptureImage<B> {
+    fn read_back(&mut self, factory: &Factory<B>, index: usize) -> Option<CapturedImage> {
+        let buffer = &mut self.buffers[index];
+        let size = buffer.size();
+        let mut data = unsafe {
+            let mut mapped = buffer.map(factory.device(), 0..size).ok()?;
+            mapped.read::<u8>(factory.device(), 0..size).ok()?.to_vec()
+        };
+        if self.bgra {
+            data.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+        Some(CapturedImage::new(
+            self.extent.width,
+            self.extent.height,
+            data,
+        ))
+    }
+}
+
+impl<'a, B: Backend> NodeSubmittable<'a, B> for CaptureImage<B> {
+    type Submittable = Submit<B>;
+    type Submittables = Option<Submit<B>>;
+}
+
+impl<B: Backend> Node<B, World> for CaptureImage<B> {
+    type Capability = Graphics;
+    type Desc = CaptureImageDesc;
+
+    fn run<'a>(
+        &'a mut self,
+        ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        world: &World,
+        frames: &'a Frames<B>,
+    ) -> Option<Submit<B>> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run");
+
+        let frame = frames.next().index();
+        let index = (frame % self.buffers.len() as u64) as usize;
+
+        // The graph waits for the frame that last wrote the buffer before running the nodes.
+        if frame >= self.buffers.len() as u64 {
+            if let Some(image) = self.read_back(factory, index) {
+                if let Some(mut captured) = world.try_fetch_mut::<CapturedFrames>() {
+                    captured.record(image);
+                }
+            }
+        }
+
+        let image = &self.image;
+        let extent = self.extent;
+        let buffer = self.buffers[index].raw();
+        let submit = self.cirque.encode(frames, &mut self.pool, |cbuf| {
+            cbuf.or_reset(|cbuf| cbuf.reset()).init(|cbuf| {
+                let mut cbuf = cbuf.begin(MultiShot(NoSimultaneousUse), ());
+                unsafe {
+                    let raw = cbuf.raw();
+                    let (stages, barriers) = gfx_acquire_barriers(ctx, None, Some(image));
+                    if !barriers.is_empty() {
+                        raw.pipeline_barrier(stages, hal::memory::Dependencies::empty(), barriers);
+                    }
+                    let source = ctx.get_image(image.id).expect("Image does not exist");
+                    raw.copy_image_to_buffer(
+                        source.raw(),
+                        image.layout,
+                        buffer,
+                        Some(hal::command::BufferImageCopy {
+                            buffer_offset: 0,
+                            buffer_width: extent.width,
+                            buffer_height: extent.height,
+                            image_layers: hal::image::SubresourceLayers {
+                                aspects: hal::format::Aspects::COLOR,
+                                level: 0,
+                                layers: 0..1,
+                            },
+                            image_offset: hal::image::Offset::ZERO,
+                            image_extent: extent,
+                        }),
+                    );
+                    raw.pipeline_barrier(
+                        hal::pso::PipelineStage::TRANSFER..hal::pso::PipelineStage::HOST,
+                        hal::memory::Dependencies::empty(),
+                        Some(hal::memory::Barrier::whole_buffer(
+                            buffer,
+                            hal::buffer::Access::TRANSFER_WRITE..hal::buffer::Access::HOST_READ,
+                        )),
+                    );
+                    let (stages, barriers) = gfx_release_barriers(ctx, None, Some(image));
+                    if !barriers.is_empty() {
+                        raw.pipeline_barrier(stages, hal::memory::Dependencies::empty(), barriers);
+                    }
+                }
+                cbuf.finish()
+            })
+        });
+        Some(submit)
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _world: &World) {
+        let pool = &mut self.pool;
+        self.cirque.dispose(|buffer| {
+            buffer.either_with(
+                &mut *pool,
+                |pool, executable| pool.free_buffers(Some(executable)),
+                |pool, pending| pool.free_buffers(Some(pending.mark_complete())),
+            );
+        });
+        factory.destroy_command_pool(self.pool.with_queue_type());
+    }
+}
@@ -0,0 +1,206 @@
+//! Color grading of the final image with lookup tables, see `RenderPostProcess::with_color_grading`.
+
+use crate::types::{Texture, TextureData};
+use amethyst_assets::Handle;
+use amethyst_core::ecs::{Read, SystemData, World};
+use amethyst_error::{format_err, Error};
+use rendy::{
+    hal::{
+        self,
+        image::{Filter, Kind, ViewKind, WrapMode},
+    },
+    texture::{pixel::Rgba8Unorm, TextureBuilder},
+};
+
+/// Resource selecting the color lookup tables applied by `RenderPostProcess` with color grading
+/// enabled. The image isn't graded when the resource is missing.
+///
+/// A lookup table is a strip of `size` square slices of `size` by `size` texels side by side,
+/// with red increasing to the right within each slice, green increasing downwards and blue
+/// increasing from slice to slice, like the neutral table of `neutral_lut`. Both the input and
+/// the output colors are in sRGB. Strips are loaded from images with `ImageFormat::lut`, and
+/// Adobe `.cube` files with `CubeLutFormat`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorGrading {
+    /// Lookup table applied to the image, or `None` for the neutral table.
+    pub lut: Option<Handle<Texture>>,
+    /// Lookup table blended with `lut` by `blend`, e.g. for transitions from day to night or
+    /// between zones. `None` for the neutral table.
+    pub blend_lut: Option<Handle<Texture>>,
+    /// Weight of `blend_lut` in 0.0-1.0.
+    pub blend: f32,
+    /// Weight of the graded image over the original one in 0.0-1.0.
+    pub intensity: f32,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            lut: None,
+            blend_lut: None,
+            blend: 0.0,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl ColorGrading {
+    /// Grades the image with the given lookup table.
+    pub fn new(lut: Handle<Texture>) -> Self {
+        Self {
+            lut: Some(lut),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the weight of `blend_lut` and the intensity, as handed to the shader.
+    pub(crate) fn params(&self) -> [f32; 4] {
+        [
+            self.blend.clamp(0.0, 1.0),
+            self.intensity.clamp(0.0, 1.0),
+            0.0,
+            0.0,
+        ]
+    }
+}
+
+/// Returns the `ColorGrading` of the world, if there is one.
+pub(crate) fn color_grading(world: &World) -> Option<ColorGrading> {
+    <Option<Read<'_, ColorGrading>>>::fetch(world).map(|grading| grading.clone())
+}
+
+/// Returns a lookup table of the given size mapping every color to itself.
+pub fn neutral_lut(size: u32) -> TextureData {
+    lut_strip(size, &neutral_colors(size))
+}
+
+/// Returns the colors of the neutral lookup table of the given size, ordered row by row.
+fn neutral_colors(size: u32) -> Vec<[f32; 3]> {
+    let max = (size - 1).max(1) as f32;
+    (0..size)
+        .flat_map(|green| {
+            (0..size * size).map(move |x| [(x % size) as f32, green as f32, (x / size) as f32])
+        })
+        .map(|color| [color[0] / max, color[1] / max, color[2] / max])
+        .collect()
+}
+
+/// Builds a lookup table strip of the given size from its colors, ordered row by row.
+fn lut_strip(size: u32, colors: &[[f32; 3]]) -> TextureData {
+    let width = size * size;
+    let pixels = colors
+        .iter()
+        .map(|color| {
+            let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            Rgba8Unorm {
+                repr: [channel(color[0]), channel(color[1]), channel(color[2]), 255],
+            }
+        })
+        .collect::<Vec<_>>();
+    TextureBuilder::new()
+        .with_kind(Kind::D2(width, size, 1, 1))
+        .with_view_kind(ViewKind::D2)
+        .with_data_width(width)
+        .with_data_height(size)
+        .with_sampler_info(hal::image::SamplerInfo::new(
+            Filter::Linear,
+            WrapMode::Clamp,
+        ))
+        .with_data(pixels)
+        .into()
+}
+
+/// Parses an Adobe `.cube` file with a 3D table into a lookup table strip.
+///
+/// The domain of the table is assumed to be 0.0-1.0.
+pub(crate) fn parse_cube_lut(source: &str) -> Result<TextureData, Error> {
+    let (size, colors) = parse_cube_colors(source)?;
+    Ok(lut_strip(size, &colors))
+}
+
+/// Parses an Adobe `.cube` file into its size and colors, reordered row by row of the strip.
+fn parse_cube_colors(source: &str) -> Result<(u32, Vec<[f32; 3]>), Error> {
+    let mut size = None;
+    let mut table = Vec::new();
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        match keyword {
+            "LUT_3D_SIZE" => {
+                size = Some(
+                    words
+                        .next()
+                        .and_then(|size| size.parse::<u32>().ok())
+                        .filter(|size| *size >= 2)
+                        .ok_or_else(|| format_err!("Invalid LUT_3D_SIZE: {}", line))?,
+                );
+            }
+            "LUT_1D_SIZE" => return Err(format_err!("1D lookup tables aren't supported")),
+            "TITLE" | "DOMAIN_MIN" | "DOMAIN_MAX" => {}
+            _ => {
+                let values = line
+                    .split_whitespace()
+                    .map(str::parse::<f32>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format_err!("Invalid line in lookup table: {}", line))?;
+                match values.as_slice() {
+                    [red, green, blue] => table.push([*red, *green, *blue]),
+                    _ => return Err(format_err!("Invalid line in lookup table: {}", line)),
+                }
+            }
+        }
+    }
+
+    let size = size.ok_or_else(|| format_err!("Lookup table is missing LUT_3D_SIZE"))?;
+    if table.len() != (size * size * size) as usize {
+        return Err(format_err!(
+            "Lookup table of size {} has {} entries instead of {}",
+            size,
+            table.len(),
+            size * size * size
+        ));
+    }
+
+    // The table is ordered with red changing fastest, then green, then blue.
+    let colors = (0..size)
+        .flat_map(|green| {
+            (0..size * size).map(move |x| {
+                let (red, blue) = (x % size, x / size);
+                (red + green * size + blue * size * size) as usize
+            })
+        })
+        .map(|index| table[index])
+        .collect();
+    Ok((size, colors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_lut_matches_neutral_strip() {
+        let mut cube = String::from("# neutral\nTITLE \"neutral\"\nLUT_3D_SIZE 2\n\n");
+        for blue in 0..2 {
+            for green in 0..2 {
+                for red in 0..2 {
+                    cube.push_str(&format!("{}.0 {}.0 {}.0\n", red, green, blue));
+                }
+            }
+        }
+        let (size, colors) = parse_cube_colors(&cube).unwrap();
+        assert_eq!(size, 2);
+        assert_eq!(colors, neutral_colors(2));
+        // Second slice on the first row: red 0, green 0, blue 1.
+        assert_eq!(colors[2], [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn cube_lut_rejects_invalid_tables() {
+        assert!(parse_cube_colors("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(parse_cube_colors("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").is_err());
+    }
+}
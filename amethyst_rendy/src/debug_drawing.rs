@@ -22,8 +22,22 @@ impl AsVertex for DebugLine {
 }
 
 impl DebugLine {
-    fn new(start: PosColor, end: PosColor) -> Self {
-        Self { start, end }
+    pub(crate) fn new(
+        start: Point3<f32>,
+        end: Point3<f32>,
+        start_color: Srgba,
+        end_color: Srgba,
+    ) -> Self {
+        Self {
+            start: PosColor {
+                position: start.to_homogeneous().xyz().into(),
+                color: Color(start_color.into_pod()),
+            },
+            end: PosColor {
+                position: end.to_homogeneous().xyz().into(),
+                color: Color(end_color.into_pod()),
+            },
+        }
     }
 }
 
@@ -83,17 +97,8 @@ impl DebugLinesComponent {
         start_color: Srgba,
         end_color: Srgba,
     ) {
-        let vertex = DebugLine::new(
-            PosColor {
-                position: start.to_homogeneous().xyz().into(),
-                color: Color(start_color.into_pod()),
-            },
-            PosColor {
-                position: end.to_homogeneous().xyz().into(),
-                color: Color(end_color.into_pod()),
-            },
-        );
-        self.lines.push(vertex);
+        self.lines
+            .push(DebugLine::new(start, end, start_color, end_color));
     }
 
     /// Adds multiple lines that form a rectangle to be rendered by giving a Z coordinate, a min and a max position.
@@ -0,0 +1,85 @@
+//! Debug render modes visualizing the meshes drawn by the 3D passes.
+
+use amethyst_core::ecs::{Read, SystemData, World};
+
+/// Resource replacing the shading of the meshes drawn by `RenderBase3D` plugins, e.g.
+/// `RenderPbr3D`, to diagnose content issues. The meshes are drawn normally when the resource
+/// is missing.
+///
+/// Changing the mode rebuilds the render graph with pipelines specialized for the mode, so it can
+/// be toggled at runtime, e.g. with `next` bound to a key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum DebugRenderMode {
+    /// Meshes are drawn normally.
+    #[default]
+    Off,
+    /// Only the edges of the triangles are drawn, with the shading of the pass. Meshes are drawn
+    /// normally on devices that can't rasterize lines from triangles.
+    Wireframe,
+    /// World space normals, mapped from `[-1, 1]` to colors. Passes without vertex normals, like
+    /// `RenderFlat3D`, show the normals of the faces.
+    Normals,
+    /// Every drawn fragment adds to the color regardless of depth, turning from dark red to white
+    /// where many layers are drawn over each other.
+    Overdraw,
+    /// The mip level of the albedo texture sampled by each fragment, blue where the texture is
+    /// magnified and green, yellow, orange, red and purple from level 0 to 4.
+    MipLevel,
+}
+
+impl DebugRenderMode {
+    /// Returns the mode following this one, cycling back to `Off` after `MipLevel`.
+    pub fn next(self) -> Self {
+        match self {
+            DebugRenderMode::Off => DebugRenderMode::Wireframe,
+            DebugRenderMode::Wireframe => DebugRenderMode::Normals,
+            DebugRenderMode::Normals => DebugRenderMode::Overdraw,
+            DebugRenderMode::Overdraw => DebugRenderMode::MipLevel,
+            DebugRenderMode::MipLevel => DebugRenderMode::Off,
+        }
+    }
+
+    /// Returns the mode pushed to the debug fragment shaders, or `None` if the fragment shader
+    /// of the pass is used.
+    pub(crate) fn shader_mode(self) -> Option<u32> {
+        match self {
+            DebugRenderMode::Off | DebugRenderMode::Wireframe => None,
+            DebugRenderMode::Normals => Some(1),
+            DebugRenderMode::Overdraw => Some(2),
+            DebugRenderMode::MipLevel => Some(3),
+        }
+    }
+}
+
+/// Fetches the `DebugRenderMode` resource, or `DebugRenderMode::Off` if it's missing.
+pub(crate) fn debug_render_mode(world: &World) -> DebugRenderMode {
+    <Option<Read<'_, DebugRenderMode>>>::fetch(world)
+        .map(|mode| *mode)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::WorldExt;
+
+    #[test]
+    fn next_cycles_through_modes() {
+        let mut mode = DebugRenderMode::Off;
+        let mut shader_modes = Vec::new();
+        for _ in 0..5 {
+            mode = mode.next();
+            shader_modes.push(mode.shader_mode());
+        }
+        assert_eq!(mode, DebugRenderMode::Off);
+        assert_eq!(shader_modes, vec![None, Some(1), Some(2), Some(3), None]);
+    }
+
+    #[test]
+    fn missing_resource_is_off() {
+        let mut world = World::new();
+        assert_eq!(debug_render_mode(&world), DebugRenderMode::Off);
+        world.insert(DebugRenderMode::Normals);
+        assert_eq!(debug_render_mode(&world), DebugRenderMode::Normals);
+    }
+}
@@ -0,0 +1,85 @@
+//! Decals projecting textures onto the geometry of the scene, see `RenderDecals`.
+
+use crate::types::Texture;
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::{Matrix4, Vector3},
+};
+
+/// A texture projected onto the geometry inside an oriented box, e.g. a bullet hole, a blood
+/// splat or a road marking. Uses the `Transform` set of components for positioning.
+///
+/// The box is centered on the entity and the texture is projected along its local Z axis, with
+/// the top of the texture towards its local Y axis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decal {
+    /// Texture projected onto the geometry. Its alpha channel blends it with the geometry.
+    pub texture: Handle<Texture>,
+    /// Color multiplied with the texture, in sRGBA.
+    pub color: palette::Srgba,
+    /// Size of the box: the width and height of the texture and the depth of the projection.
+    pub size: Vector3<f32>,
+    /// Maximum angle in radians between the projection axis and the normal of the surfaces the
+    /// decal is projected onto. The decal fades out on surfaces turning further away, so that
+    /// it doesn't stretch along walls.
+    pub max_angle: f32,
+}
+
+impl Component for Decal {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Decal {
+    /// Create a decal projecting the texture onto a box of the given size.
+    pub fn new(texture: Handle<Texture>, size: Vector3<f32>) -> Self {
+        Decal {
+            texture,
+            color: palette::Srgba::new(1.0, 1.0, 1.0, 1.0),
+            size,
+            max_angle: std::f32::consts::FRAC_PI_3,
+        }
+    }
+
+    /// Transformation from the unit cube centered on the origin to the world space box of the
+    /// decal placed by the given global matrix.
+    pub fn box_matrix(&self, global_matrix: &Matrix4<f32>) -> Matrix4<f32> {
+        global_matrix * Matrix4::new_nonuniform_scaling(&self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::texture::TextureGenerator;
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::math::{Point3, Translation3};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn create_texture() -> Handle<Texture> {
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        let storage: AssetStorage<Texture> = AssetStorage::default();
+        loader.load_from_data(
+            TextureGenerator::Srgba(1.0, 1.0, 1.0, 1.0).data(),
+            (),
+            &storage,
+        )
+    }
+
+    #[test]
+    fn box_matrix_spans_decal_size() {
+        let decal = Decal::new(create_texture(), Vector3::new(2.0, 4.0, 1.0));
+        let global = Translation3::new(10.0, 0.0, -5.0).to_homogeneous();
+        let matrix = decal.box_matrix(&global);
+
+        let corner = matrix.transform_point(&Point3::new(0.5, 0.5, 0.5));
+        assert_eq!(corner, Point3::new(11.0, 2.0, -4.5));
+
+        // World space points are mapped back into the unit cube by the inverse.
+        let inverse = matrix.try_inverse().unwrap();
+        let local = inverse.transform_point(&Point3::new(9.0, -1.0, -5.0));
+        assert_eq!(local, Point3::new(-0.5, -0.25, 0.0));
+    }
+}
@@ -0,0 +1,644 @@
+//! Environment maps drawn by the skybox and lighting PBR materials.
+//!
+//! An `EnvironmentMap` is loaded from an equirectangular image, usually an HDR, with
+//! `EquirectangularFormat` or from the six faces of a cubemap with `CubemapFormat`. Its
+//! irradiance and prefiltered specular maps are generated on the CPU while loading. The
+//! environment map of the scene is selected in the `Skybox` resource.
+
+use crate::types::{Texture, TextureData};
+use amethyst_assets::{
+    Asset, AssetStorage, Handle, HotReloadStrategy, Loader, ProcessingState, ThreadPool,
+};
+use amethyst_core::{
+    ecs::prelude::{DenseVecStorage, Read, ReadExpect, System, Write},
+    math::Vector3,
+    timing::Time,
+};
+use amethyst_error::{format_err, Error};
+use rendy::{
+    hal::{
+        format::Format,
+        image::{Filter, Kind, SamplerInfo, ViewKind, WrapMode},
+    },
+    texture::TextureBuilder,
+};
+use std::{f32::consts::PI, sync::Arc};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of roughness levels of the prefiltered specular map, evenly spaced from 0.0 to 1.0.
+pub const SPECULAR_LEVELS: usize = 6;
+const SPECULAR_WIDTH: u32 = 128;
+const SPECULAR_HEIGHT: u32 = 64;
+const SPECULAR_SAMPLES: u32 = 64;
+const IRRADIANCE_WIDTH: u32 = 64;
+
+/// An asset handle to an environment map.
+pub type EnvironmentMapHandle = Handle<EnvironmentMap>;
+
+/// Resource selecting the environment map drawn by the skybox and lighting PBR materials.
+///
+/// Without an environment map, the skybox draws its gradient and materials are only lit by the
+/// ambient color.
+#[derive(Clone, Debug)]
+pub struct Skybox {
+    /// Environment map of the scene.
+    pub environment: Option<EnvironmentMapHandle>,
+    /// Multiplier of the radiance drawn by the skybox.
+    pub intensity: f32,
+    /// Multiplier of the image-based lighting of PBR materials.
+    pub lighting_intensity: f32,
+}
+
+impl Default for Skybox {
+    fn default() -> Self {
+        Self {
+            environment: None,
+            intensity: 1.0,
+            lighting_intensity: 1.0,
+        }
+    }
+}
+
+/// Equirectangular image of linear RGB radiance, with rows from the zenith to the nadir.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 3]>,
+}
+
+impl HdrImage {
+    /// Creates an image from its pixels, row by row.
+    pub fn new(width: u32, height: u32, pixels: Vec<[f32; 3]>) -> Result<Self, Error> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize {
+            return Err(format_err!(
+                "Image of {}x{} pixels can't have {} pixels",
+                width,
+                height,
+                pixels.len()
+            ));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes a Radiance HDR image, or any other image format which is converted from sRGB.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        if let image::ImageFormat::HDR = image::guess_format(bytes)? {
+            let decoder = image::hdr::HDRDecoder::new(bytes)?;
+            let metadata = decoder.metadata();
+            let pixels = decoder
+                .read_image_hdr()?
+                .into_iter()
+                .map(|pixel| pixel.0)
+                .collect();
+            Self::new(metadata.width, metadata.height, pixels)
+        } else {
+            let image = image::load_from_memory(bytes)?.to_rgb();
+            let (width, height) = image.dimensions();
+            let pixels = image
+                .pixels()
+                .map(|pixel| {
+                    let [r, g, b] = pixel.0;
+                    [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b)]
+                })
+                .collect();
+            Self::new(width, height, pixels)
+        }
+    }
+
+    /// Creates an equirectangular image from the six square faces of a cubemap, in the order
+    /// +X, -X, +Y, -Y, +Z, -Z.
+    pub fn from_cube_faces(faces: &[HdrImage]) -> Result<Self, Error> {
+        if faces.len() != 6 {
+            return Err(format_err!(
+                "Cubemap has {} faces instead of 6",
+                faces.len()
+            ));
+        }
+        let size = faces[0].width;
+        if faces
+            .iter()
+            .any(|face| face.width != size || face.height != size)
+        {
+            return Err(format_err!(
+                "Cubemap faces must be squares of the same size"
+            ));
+        }
+
+        let (width, height) = (size * 4, size * 2);
+        let pixels = texel_directions(width, height)
+            .map(|direction| {
+                let (face, u, v) = cube_face_coordinates(&direction);
+                faces[face].bilinear(u, v, false)
+            })
+            .collect();
+        Self::new(width, height, pixels)
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the pixels of the image, row by row.
+    pub fn pixels(&self) -> &[[f32; 3]] {
+        &self.pixels
+    }
+
+    /// Returns the bilinearly filtered radiance in the given direction.
+    pub fn sample(&self, direction: &Vector3<f32>) -> [f32; 3] {
+        let (u, v) = equirect_coordinates(direction);
+        self.bilinear(u, v, true)
+    }
+
+    fn pixel(&self, x: i64, y: i64, wrap: bool) -> [f32; 3] {
+        let (width, height) = (i64::from(self.width), i64::from(self.height));
+        let x = if wrap {
+            x.rem_euclid(width)
+        } else {
+            x.max(0).min(width - 1)
+        };
+        let y = y.max(0).min(height - 1);
+        self.pixels[(y * width + x) as usize]
+    }
+
+    fn bilinear(&self, u: f32, v: f32, wrap: bool) -> [f32; 3] {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = lerp(self.pixel(x0, y0, wrap), self.pixel(x0 + 1, y0, wrap), fx);
+        let bottom = lerp(
+            self.pixel(x0, y0 + 1, wrap),
+            self.pixel(x0 + 1, y0 + 1, wrap),
+            fx,
+        );
+        lerp(top, bottom, fy)
+    }
+
+    /// Returns the image at half the size, averaging each 2x2 block of pixels.
+    fn downsample(&self) -> HdrImage {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..i64::from(height) {
+            for x in 0..i64::from(width) {
+                let mut sum = [0.0; 3];
+                for &(dx, dy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let pixel = self.pixel(x * 2 + dx, y * 2 + dy, false);
+                    for (sum, channel) in sum.iter_mut().zip(&pixel) {
+                        *sum += channel * 0.25;
+                    }
+                }
+                pixels.push(sum);
+            }
+        }
+        HdrImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Data of an environment map, with the irradiance and prefiltered specular maps generated
+/// from its radiance.
+#[derive(Clone, Debug)]
+pub struct EnvironmentMapData {
+    /// Radiance drawn by the skybox.
+    pub radiance: HdrImage,
+    /// Radiance prefiltered for each of the `SPECULAR_LEVELS` roughness levels.
+    pub specular: Vec<HdrImage>,
+    /// Spherical harmonics of the diffuse irradiance divided by pi, in linear RGB.
+    pub irradiance: [[f32; 3]; 9],
+}
+
+amethyst_assets::register_format_type!(EnvironmentMapData);
+
+impl EnvironmentMapData {
+    /// Generates the irradiance and prefiltered specular maps of the given radiance.
+    pub fn new(radiance: HdrImage) -> Self {
+        #[cfg(feature = "profiler")]
+        profile_scope!("generate_environment_map");
+
+        let mut mips = Vec::new();
+        while mips.last().unwrap_or(&radiance).width > 1 {
+            let mip = mips.last().unwrap_or(&radiance).downsample();
+            mips.push(mip);
+        }
+        let mips = std::iter::once(&radiance)
+            .chain(mips.iter())
+            .collect::<Vec<_>>();
+
+        let irradiance = irradiance(
+            mips.iter()
+                .find(|mip| mip.width <= IRRADIANCE_WIDTH)
+                .unwrap_or(&mips[mips.len() - 1]),
+        );
+        let specular = (0..SPECULAR_LEVELS)
+            .map(|level| prefilter(&mips, level as f32 / (SPECULAR_LEVELS - 1) as f32))
+            .collect();
+
+        Self {
+            radiance,
+            specular,
+            irradiance,
+        }
+    }
+}
+
+/// Environment map drawn by the skybox and lighting PBR materials, see `Skybox`.
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    radiance: Handle<Texture>,
+    specular: Handle<Texture>,
+    irradiance: [[f32; 3]; 9],
+}
+
+impl Asset for EnvironmentMap {
+    const NAME: &'static str = "renderer::EnvironmentMap";
+    type Data = EnvironmentMapData;
+    type HandleStorage = DenseVecStorage<Handle<Self>>;
+}
+
+impl EnvironmentMap {
+    /// Returns the equirectangular radiance texture.
+    pub fn radiance(&self) -> &Handle<Texture> {
+        &self.radiance
+    }
+
+    /// Returns the equirectangular texture array of the radiance prefiltered for each roughness
+    /// level.
+    pub fn specular(&self) -> &Handle<Texture> {
+        &self.specular
+    }
+
+    /// Returns the spherical harmonics of the diffuse irradiance divided by pi.
+    pub fn irradiance(&self) -> &[[f32; 3]; 9] {
+        &self.irradiance
+    }
+}
+
+/// Asset processing system for `EnvironmentMap` asset type, loading its maps as textures.
+///
+/// Added by the `RenderSkybox` plugin.
+#[derive(Debug, Default)]
+pub struct EnvironmentMapProcessorSystem;
+
+impl<'a> System<'a> for EnvironmentMapProcessorSystem {
+    type SystemData = (
+        Write<'a, AssetStorage<EnvironmentMap>>,
+        Read<'a, AssetStorage<Texture>>,
+        ReadExpect<'a, Loader>,
+        Read<'a, Time>,
+        ReadExpect<'a, Arc<ThreadPool>>,
+        Option<Read<'a, HotReloadStrategy>>,
+    );
+
+    fn run(&mut self, (mut maps, textures, loader, time, pool, strategy): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("environment_map_processor");
+
+        maps.process(
+            |data| {
+                let radiance = hdr_texture(std::slice::from_ref(&data.radiance), ViewKind::D2);
+                let specular = hdr_texture(&data.specular, ViewKind::D2Array);
+                Ok(ProcessingState::Loaded(EnvironmentMap {
+                    radiance: loader.load_from_data(radiance, (), &textures),
+                    specular: loader.load_from_data(specular, (), &textures),
+                    irradiance: data.irradiance,
+                }))
+            },
+            time.frame_number(),
+            &**pool,
+            strategy.as_deref(),
+        );
+    }
+}
+
+/// Builds a half precision texture from equirectangular images of the same size, one per layer.
+fn hdr_texture(layers: &[HdrImage], view_kind: ViewKind) -> TextureData {
+    let (width, height) = (layers[0].width, layers[0].height);
+    let mut data = Vec::with_capacity(layers.len() * width as usize * height as usize * 8);
+    for pixel in layers.iter().flat_map(|layer| &layer.pixels) {
+        for &channel in pixel.iter().chain(Some(&1.0)) {
+            data.extend_from_slice(&f16_bits(channel).to_ne_bytes());
+        }
+    }
+
+    let mut sampler_info = SamplerInfo::new(Filter::Linear, WrapMode::Clamp);
+    sampler_info.wrap_mode.0 = WrapMode::Tile;
+    TextureBuilder::new()
+        .with_kind(Kind::D2(width, height, layers.len() as u16, 1))
+        .with_view_kind(view_kind)
+        .with_data_width(width)
+        .with_data_height(height)
+        .with_sampler_info(sampler_info)
+        .with_raw_data(data, Format::Rgba16Sfloat)
+        .into()
+}
+
+/// Returns the bits of the half precision float closest to the given value, rounding towards
+/// zero and clamping finite values to the largest half.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x007f_ffff;
+    if value.is_nan() {
+        sign | 0x7e00
+    } else if value.is_infinite() {
+        sign | 0x7c00
+    } else if exponent >= 0x1f {
+        sign | 0x7bff
+    } else if exponent <= 0 {
+        if exponent < -10 {
+            sign
+        } else {
+            sign | ((mantissa | 0x0080_0000) >> (14 - exponent)) as u16
+        }
+    } else {
+        sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = f32::from(value) / 255.0;
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    ]
+}
+
+/// Returns the direction at the given texture coordinates of an equirectangular image.
+/// Keep in sync with `equirect_uv` in shaders/fragment/header/environment_map.frag
+fn equirect_direction(u: f32, v: f32) -> Vector3<f32> {
+    let (sin_phi, cos_phi) = ((u - 0.5) * 2.0 * PI).sin_cos();
+    let (sin_theta, cos_theta) = (v * PI).sin_cos();
+    Vector3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi)
+}
+
+/// Returns the texture coordinates of the given direction in an equirectangular image.
+fn equirect_coordinates(direction: &Vector3<f32>) -> (f32, f32) {
+    let direction = direction.normalize();
+    (
+        direction.z.atan2(direction.x) / (2.0 * PI) + 0.5,
+        direction.y.max(-1.0).min(1.0).acos() / PI,
+    )
+}
+
+/// Returns the directions at the centers of the texels of an equirectangular image.
+fn texel_directions(width: u32, height: u32) -> impl Iterator<Item = Vector3<f32>> {
+    (0..height).flat_map(move |y| {
+        (0..width).map(move |x| {
+            equirect_direction(
+                (x as f32 + 0.5) / width as f32,
+                (y as f32 + 0.5) / height as f32,
+            )
+        })
+    })
+}
+
+/// Returns the face of a cubemap in the given direction, along with the texture coordinates in
+/// that face.
+fn cube_face_coordinates(direction: &Vector3<f32>) -> (usize, f32, f32) {
+    let d = direction;
+    let (x, y, z) = (d.x.abs(), d.y.abs(), d.z.abs());
+    let (face, s, t, major) = if x >= y && x >= z {
+        if d.x > 0.0 {
+            (0, -d.z, -d.y, x)
+        } else {
+            (1, d.z, -d.y, x)
+        }
+    } else if y >= z {
+        if d.y > 0.0 {
+            (2, d.x, d.z, y)
+        } else {
+            (3, d.x, -d.z, y)
+        }
+    } else if d.z > 0.0 {
+        (4, d.x, -d.y, z)
+    } else {
+        (5, -d.x, -d.y, z)
+    };
+    (face, (s / major + 1.0) * 0.5, (t / major + 1.0) * 0.5)
+}
+
+/// Returns the first 9 real spherical harmonics in the given direction.
+/// Keep in sync with `sh_irradiance` in shaders/fragment/header/environment_map.frag
+fn sh_basis(d: &Vector3<f32>) -> [f32; 9] {
+    [
+        0.282_095,
+        0.488_603 * d.y,
+        0.488_603 * d.z,
+        0.488_603 * d.x,
+        1.092_548 * d.x * d.y,
+        1.092_548 * d.y * d.z,
+        0.315_392 * (3.0 * d.z * d.z - 1.0),
+        1.092_548 * d.x * d.z,
+        0.546_274 * (d.x * d.x - d.y * d.y),
+    ]
+}
+
+/// Projects the radiance on spherical harmonics convolved with the cosine lobe and divided by
+/// pi, so evaluating them in the direction of a normal gives the diffuse light.
+fn irradiance(radiance: &HdrImage) -> [[f32; 3]; 9] {
+    // Convolution with the cosine lobe divided by pi, per band
+    const BANDS: [f32; 9] = [
+        1.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+    ];
+
+    let (width, height) = (radiance.width, radiance.height);
+    let texel_angle = (2.0 * PI / width as f32) * (PI / height as f32);
+    let mut coefficients = [[0.0; 3]; 9];
+    for (pixel, direction) in radiance.pixels.iter().zip(texel_directions(width, height)) {
+        let solid_angle = texel_angle * (1.0 - direction.y * direction.y).sqrt();
+        for (coefficient, basis) in coefficients.iter_mut().zip(&sh_basis(&direction)) {
+            for (coefficient, channel) in coefficient.iter_mut().zip(pixel) {
+                *coefficient += channel * basis * solid_angle;
+            }
+        }
+    }
+    for (coefficient, band) in coefficients.iter_mut().zip(&BANDS) {
+        for channel in coefficient.iter_mut() {
+            *channel *= band;
+        }
+    }
+    coefficients
+}
+
+/// Prefilters the radiance with the GGX distribution of the given roughness, sampling the mips
+/// of the radiance according to the density of the samples.
+fn prefilter(mips: &[&HdrImage], roughness: f32) -> HdrImage {
+    let source_angle = 4.0 * PI / (mips[0].width * mips[0].height) as f32;
+    let alpha = roughness * roughness;
+
+    let pixels = texel_directions(SPECULAR_WIDTH, SPECULAR_HEIGHT)
+        .map(|normal| {
+            if roughness == 0.0 {
+                let texel_angle = 4.0 * PI / (SPECULAR_WIDTH * SPECULAR_HEIGHT) as f32;
+                return sample_mips(mips, &normal, 0.5 * (texel_angle / source_angle).log2());
+            }
+
+            let up = if normal.y.abs() < 0.999 {
+                Vector3::y()
+            } else {
+                Vector3::x()
+            };
+            let tangent = up.cross(&normal).normalize();
+            let bitangent = normal.cross(&tangent);
+
+            let mut sum = [0.0; 3];
+            let mut weight = 0.0;
+            for i in 0..SPECULAR_SAMPLES {
+                let (e1, e2) = hammersley(i, SPECULAR_SAMPLES);
+                let (sin_phi, cos_phi) = (2.0 * PI * e1).sin_cos();
+                let cos_theta = ((1.0 - e2) / (1.0 + (alpha * alpha - 1.0) * e2)).sqrt();
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let halfway = tangent * (sin_theta * cos_phi)
+                    + bitangent * (sin_theta * sin_phi)
+                    + normal * cos_theta;
+                let light = halfway * (2.0 * normal.dot(&halfway)) - normal;
+                let n_dot_l = normal.dot(&light);
+                if n_dot_l <= 0.0 {
+                    continue;
+                }
+
+                // With the view along the normal, the pdf of the light direction is D / 4
+                let pdf = ggx_distribution(cos_theta, alpha) / 4.0;
+                let sample_angle = 1.0 / (SPECULAR_SAMPLES as f32 * pdf + 0.0001);
+                let lod = 0.5 * (sample_angle / source_angle).log2() + 1.0;
+                let radiance = sample_mips(mips, &light, lod);
+                for (sum, channel) in sum.iter_mut().zip(&radiance) {
+                    *sum += channel * n_dot_l;
+                }
+                weight += n_dot_l;
+            }
+            let weight = weight.max(std::f32::EPSILON);
+            [sum[0] / weight, sum[1] / weight, sum[2] / weight]
+        })
+        .collect();
+
+    HdrImage {
+        width: SPECULAR_WIDTH,
+        height: SPECULAR_HEIGHT,
+        pixels,
+    }
+}
+
+fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denominator * denominator)
+}
+
+/// Returns the `i`th point of the Hammersley sequence of `count` points.
+fn hammersley(i: u32, count: u32) -> (f32, f32) {
+    (
+        i as f32 / count as f32,
+        i.reverse_bits() as f32 * 2.328_306_4e-10,
+    )
+}
+
+/// Samples the mips of an equirectangular image with trilinear filtering.
+fn sample_mips(mips: &[&HdrImage], direction: &Vector3<f32>, lod: f32) -> [f32; 3] {
+    let lod = lod.max(0.0).min((mips.len() - 1) as f32);
+    let level = lod.floor() as usize;
+    let sample = mips[level].sample(direction);
+    if level + 1 < mips.len() {
+        lerp(sample, mips[level + 1].sample(direction), lod.fract())
+    } else {
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn uniform(width: u32, height: u32, color: [f32; 3]) -> HdrImage {
+        HdrImage::new(width, height, vec![color; (width * height) as usize]).unwrap()
+    }
+
+    #[test]
+    fn equirect_coordinates_round_trip() {
+        for &(u, v) in &[(0.25, 0.5), (0.6, 0.1), (0.9, 0.8)] {
+            let (u2, v2) = equirect_coordinates(&equirect_direction(u, v));
+            assert_relative_eq!(u, u2, epsilon = 1e-5);
+            assert_relative_eq!(v, v2, epsilon = 1e-5);
+        }
+        assert_relative_eq!(equirect_direction(0.3, 0.0), Vector3::y(), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn cube_faces_map_to_directions() {
+        let faces = (0..6)
+            .map(|face| uniform(4, 4, [face as f32, 0.0, 0.0]))
+            .collect::<Vec<_>>();
+        let image = HdrImage::from_cube_faces(&faces).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+        assert_eq!(image.sample(&Vector3::x())[0], 0.0);
+        assert_eq!(image.sample(&-Vector3::y())[0], 3.0);
+        assert_eq!(image.sample(&-Vector3::z())[0], 5.0);
+        assert!(HdrImage::from_cube_faces(&faces[..5]).is_err());
+    }
+
+    #[test]
+    fn uniform_radiance_gives_uniform_lighting() {
+        let data = EnvironmentMapData::new(uniform(32, 16, [2.0, 1.0, 0.5]));
+        let diffuse = sh_basis(&Vector3::new(0.6, 0.0, 0.8))
+            .iter()
+            .zip(&data.irradiance)
+            .fold(0.0, |sum, (basis, coefficient)| {
+                sum + basis * coefficient[0]
+            });
+        assert_relative_eq!(diffuse, 2.0, epsilon = 0.02);
+
+        assert_eq!(data.specular.len(), SPECULAR_LEVELS);
+        for level in &data.specular {
+            assert_eq!(level.width(), SPECULAR_WIDTH);
+            for pixel in level.pixels() {
+                assert_relative_eq!(pixel[1], 1.0, epsilon = 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn converts_to_half_floats() {
+        assert_eq!(f16_bits(1.0), 0x3c00);
+        assert_eq!(f16_bits(-2.0), 0xc000);
+        assert_eq!(f16_bits(0.5), 0x3800);
+        assert_eq!(f16_bits(1e6), 0x7bff);
+        assert_eq!(f16_bits(std::f32::INFINITY), 0x7c00);
+        assert_eq!(f16_bits(2f32.powi(-24)), 0x0001);
+    }
+}
@@ -7,6 +7,9 @@ use std::{error, fmt};
 pub(crate) enum Error {
     /// Failed to parse a Spritesheet from RON.
     LoadSpritesheetError(ron::de::Error),
+    /// Failed to parse a Spritesheet from JSON.
+    #[cfg(feature = "json")]
+    LoadJsonSpritesheetError(serde_json::Error),
 }
 
 impl error::Error for Error {}
@@ -17,6 +20,8 @@ impl fmt::Display for Error {
 
         match *self {
             LoadSpritesheetError(..) => write!(fmt, "Failed to parse SpriteSheet"),
+            #[cfg(feature = "json")]
+            LoadJsonSpritesheetError(..) => write!(fmt, "Failed to parse SpriteSheet from JSON"),
         }
     }
 }
@@ -0,0 +1,86 @@
+//! Environment map formats implementation.
+use crate::environment_map::{EnvironmentMapData, HdrImage};
+use amethyst_assets::{Format, FormatValue, Source};
+use amethyst_error::{format_err, Error, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Loads an `EnvironmentMap` from an equirectangular image, usually a Radiance HDR (`.hdr`).
+/// Other image formats are converted from sRGB.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct EquirectangularFormat;
+
+amethyst_assets::register_format!(
+    "EQUIRECTANGULAR",
+    EquirectangularFormat as EnvironmentMapData
+);
+impl Format<EnvironmentMapData> for EquirectangularFormat {
+    fn name(&self) -> &'static str {
+        "EQUIRECTANGULAR"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<EnvironmentMapData, Error> {
+        Ok(EnvironmentMapData::new(HdrImage::decode(&bytes)?))
+    }
+}
+
+/// Loads an `EnvironmentMap` from the six square faces of a cubemap. The asset is a RON file
+/// listing the images of the faces, with paths relative to the asset directory:
+///
+/// ```ron
+/// (
+///     pos_x: "skybox/px.hdr",
+///     neg_x: "skybox/nx.hdr",
+///     pos_y: "skybox/py.hdr",
+///     neg_y: "skybox/ny.hdr",
+///     pos_z: "skybox/pz.hdr",
+///     neg_z: "skybox/nz.hdr",
+/// )
+/// ```
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct CubemapFormat;
+
+#[derive(Debug, Deserialize)]
+struct CubemapFaces {
+    pos_x: String,
+    neg_x: String,
+    pos_y: String,
+    neg_y: String,
+    pos_z: String,
+    neg_z: String,
+}
+
+amethyst_assets::register_format!("CUBEMAP", CubemapFormat as EnvironmentMapData);
+impl Format<EnvironmentMapData> for CubemapFormat {
+    fn name(&self) -> &'static str {
+        "CUBEMAP"
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        _create_reload: Option<Box<dyn Format<EnvironmentMapData>>>,
+    ) -> Result<FormatValue<EnvironmentMapData>, Error> {
+        let bytes = source.load(&name)?;
+        let faces: CubemapFaces = ron::de::from_bytes(&bytes)
+            .with_context(|_| format_err!("Failed to parse cubemap {}", name))?;
+        let faces = [
+            &faces.pos_x,
+            &faces.neg_x,
+            &faces.pos_y,
+            &faces.neg_y,
+            &faces.pos_z,
+            &faces.neg_z,
+        ]
+        .iter()
+        .map(|face| {
+            HdrImage::decode(&source.load(face)?)
+                .with_context(|_| format_err!("Failed to load cubemap face {}", face))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+        Ok(FormatValue::data(EnvironmentMapData::new(
+            HdrImage::from_cube_faces(&faces)?,
+        )))
+    }
+}
@@ -0,0 +1,56 @@
+//! Window icon format implementation.
+use amethyst_assets::{Asset, Format, Handle, ProcessableAsset, ProcessingState};
+use amethyst_core::ecs::VecStorage;
+use amethyst_error::Error;
+use amethyst_window::Icon;
+use serde::{Deserialize, Serialize};
+
+/// A handle to a `WindowIcon` asset.
+pub type WindowIconHandle = Handle<WindowIcon>;
+
+/// An icon for the window, loaded from an image file with `IconFormat` and applied with
+/// `WindowCommands::set_icon`.
+///
+/// # Example
+///
+/// ```ignore
+/// let icon = icon_storage.get(&icon_handle).map(WindowIcon::icon).cloned();
+/// if icon.is_some() {
+///     window_commands.set_icon(icon);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowIcon(pub Icon);
+
+impl WindowIcon {
+    /// Returns the icon to pass to `WindowCommands::set_icon`.
+    pub fn icon(&self) -> &Icon {
+        &self.0
+    }
+}
+
+impl Asset for WindowIcon {
+    const NAME: &'static str = "renderer::WindowIcon";
+    type Data = Icon;
+    type HandleStorage = VecStorage<WindowIconHandle>;
+}
+
+impl ProcessableAsset for WindowIcon {
+    fn process(data: Icon) -> Result<ProcessingState<WindowIcon>, Error> {
+        Ok(ProcessingState::Loaded(WindowIcon(data)))
+    }
+}
+
+/// Loads a `WindowIcon` from any image file supported by `winit`, e.g. PNG or ICO.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct IconFormat;
+
+impl Format<Icon> for IconFormat {
+    fn name(&self) -> &'static str {
+        "ICON"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<Icon, Error> {
+        Icon::from_bytes(&bytes).map_err(Error::new)
+    }
+}
@@ -1,6 +1,10 @@
 //! Pre-defined graphical formats and data provided by amethyst_rendy
+pub mod environment_map;
+#[cfg(feature = "window")]
+pub mod icon;
 pub mod mesh;
 pub mod mtl;
+pub mod shader;
 pub mod texture;
 
 use self::{mesh::MeshPrefab, mtl::MaterialPrefab};
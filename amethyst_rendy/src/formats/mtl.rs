@@ -3,7 +3,7 @@
 use crate::{
     formats::texture::TexturePrefab,
     mtl::{Material, MaterialDefaults, TextureOffset},
-    transparent::Transparent,
+    transparent::{RenderQueue, Transparent},
     types::Texture,
 };
 use amethyst_assets::{AssetStorage, Handle, Loader, PrefabData, ProgressCounter};
@@ -33,6 +33,8 @@ pub struct MaterialPrefab {
     pub transparent: bool,
     /// Alpha cutoff: the value below which we do not draw the pixel
     pub alpha_cutoff: f32,
+    /// Queue the meshes using the material are drawn in, overriding `transparent`.
+    pub queue: Option<RenderQueue>,
     /// Clone handle only
     #[serde(skip)]
     handle: Option<Handle<Material>>,
@@ -63,6 +65,7 @@ impl Default for MaterialPrefab {
             uv_offset: TextureOffset::default(),
             transparent: false,
             alpha_cutoff: std::f32::MIN_POSITIVE,
+            queue: None,
             handle: None,
         }
     }
@@ -158,6 +161,7 @@ impl<'a> PrefabData<'a> for MaterialPrefab {
                 cavity: load_handle(&self.cavity, &mat_default.0.cavity),
                 uv_offset: self.uv_offset.clone(),
                 alpha_cutoff: self.alpha_cutoff,
+                queue: self.queue,
             };
 
             self.handle
@@ -0,0 +1,136 @@
+//! Shader formats implementation.
+use crate::shader::{ShaderAsset, ShaderStage};
+use amethyst_assets::Format;
+use amethyst_error::{format_err, Error};
+use rendy::shader::SpirvShader;
+use serde::{Deserialize, Serialize};
+
+fn main_entry() -> String {
+    "main".to_string()
+}
+
+/// Loads a `ShaderAsset` from compiled SPIR-V bytecode (`.spv`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpirvFormat {
+    /// Stage the shader is written for.
+    pub stage: ShaderStage,
+    /// Name of the entry point function.
+    #[serde(default = "main_entry")]
+    pub entry: String,
+}
+
+impl SpirvFormat {
+    /// Create a format loading shaders of the given stage with the `main` entry point.
+    pub fn new(stage: ShaderStage) -> Self {
+        Self {
+            stage,
+            entry: main_entry(),
+        }
+    }
+
+    /// Set the name of the entry point function.
+    pub fn with_entry(mut self, entry: impl Into<String>) -> Self {
+        self.entry = entry.into();
+        self
+    }
+}
+
+amethyst_assets::register_format!("SPIRV", SpirvFormat as ShaderAsset);
+impl Format<ShaderAsset> for SpirvFormat {
+    fn name(&self) -> &'static str {
+        "SPIRV"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<ShaderAsset, Error> {
+        if bytes.is_empty() {
+            return Err(format_err!("SPIR-V shader is empty"));
+        }
+        SpirvShader::from_bytes(&bytes, self.stage.flags(), &self.entry)
+            .map(ShaderAsset)
+            .map_err(|e| format_err!("Invalid SPIR-V shader: {}", e))
+    }
+}
+
+/// Compiles a `ShaderAsset` from GLSL source code (`.vert`, `.frag`, ...) at runtime.
+/// `#include` directives aren't supported.
+#[cfg(feature = "shader-compiler")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GlslFormat {
+    /// Stage the shader is written for.
+    pub stage: ShaderStage,
+    /// Name of the entry point function.
+    #[serde(default = "main_entry")]
+    pub entry: String,
+}
+
+#[cfg(feature = "shader-compiler")]
+impl GlslFormat {
+    /// Create a format compiling shaders of the given stage with the `main` entry point.
+    pub fn new(stage: ShaderStage) -> Self {
+        Self {
+            stage,
+            entry: main_entry(),
+        }
+    }
+
+    /// Set the name of the entry point function.
+    pub fn with_entry(mut self, entry: impl Into<String>) -> Self {
+        self.entry = entry.into();
+        self
+    }
+}
+
+#[cfg(feature = "shader-compiler")]
+amethyst_assets::register_format!("GLSL", GlslFormat as ShaderAsset);
+#[cfg(feature = "shader-compiler")]
+impl Format<ShaderAsset> for GlslFormat {
+    fn name(&self) -> &'static str {
+        "GLSL"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<ShaderAsset, Error> {
+        use rendy::shader::{ShaderKind, SourceCodeShaderInfo, SourceLanguage};
+
+        let source = String::from_utf8(bytes)
+            .map_err(|e| format_err!("GLSL shader isn't valid UTF-8: {}", e))?;
+        let kind = match self.stage {
+            ShaderStage::Vertex => ShaderKind::Vertex,
+            ShaderStage::Fragment => ShaderKind::Fragment,
+            ShaderStage::Geometry => ShaderKind::Geometry,
+            ShaderStage::Compute => ShaderKind::Compute,
+        };
+        SourceCodeShaderInfo::new(
+            source.as_str(),
+            "shader.glsl",
+            kind,
+            SourceLanguage::GLSL,
+            self.entry.as_str(),
+        )
+        .precompile()
+        .map(ShaderAsset)
+        .map_err(|e| format_err!("Failed to compile GLSL shader: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rendy::{hal::pso::ShaderStageFlags, shader::Shader};
+
+    #[test]
+    fn spirv_format_loads_bytecode() {
+        let bytes = include_bytes!("../../compiled/fragment/flat.frag.spv").to_vec();
+        let asset = SpirvFormat::new(ShaderStage::Fragment)
+            .import_simple(bytes)
+            .unwrap();
+        assert_eq!(asset.shader().stage(), ShaderStageFlags::FRAGMENT);
+        assert_eq!(asset.shader().entry(), "main");
+    }
+
+    #[test]
+    fn spirv_format_rejects_invalid_bytecode() {
+        let format = SpirvFormat::new(ShaderStage::Vertex).with_entry("vs_main");
+        assert!(format.import_simple(Vec::new()).is_err());
+        assert!(format.import_simple(vec![1, 2, 3]).is_err());
+    }
+}
@@ -1,5 +1,8 @@
 //! Texture formats implementation.
-use crate::types::{Texture, TextureData};
+use crate::{
+    color_grading::parse_cube_lut,
+    types::{Texture, TextureData},
+};
 use amethyst_assets::{
     AssetStorage, Format, Handle, Loader, PrefabData, ProgressCounter, SerializableFormat,
 };
@@ -100,6 +103,40 @@ impl Format<TextureData> for ImageFormat {
     }
 }
 
+impl ImageFormat {
+    /// Format of color lookup table strips for `ColorGrading`, loading the colors as they are
+    /// stored, with linear filtering and clamping.
+    pub fn lut() -> Self {
+        use rendy::{
+            hal::image::{SamplerInfo, WrapMode},
+            texture::image::Repr,
+        };
+
+        let mut format = Self::default();
+        format.0.repr = Repr::Unorm;
+        format.0.sampler_info = SamplerInfo::new(Filter::Linear, WrapMode::Clamp);
+        format.0.premultiply_alpha = false;
+        format
+    }
+}
+
+/// Loads a color lookup table for `ColorGrading` from an Adobe `.cube` file with a 3D table.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct CubeLutFormat;
+
+amethyst_assets::register_format!("CUBE_LUT", CubeLutFormat as TextureData);
+impl Format<TextureData> for CubeLutFormat {
+    fn name(&self) -> &'static str {
+        "CUBE_LUT"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<TextureData, Error> {
+        let source = String::from_utf8(bytes)
+            .map_err(|_| amethyst_error::format_err!("Lookup table isn't valid UTF-8"))?;
+        parse_cube_lut(&source)
+    }
+}
+
 /// `PrefabData` for loading `Texture`s.
 ///
 /// Will not add any `Component`s to the `Entity`, will only return a `Handle`
@@ -0,0 +1,587 @@
+//! Immediate-mode debug gizmos, drawn by the `RenderGizmos` plugin.
+//!
+//! Gizmos are submitted to the `Gizmos` resource every frame they should be visible, or once with
+//! a duration. They're useful to visualize physics shapes, AI paths and other debug information.
+
+use crate::debug_drawing::DebugLine;
+use amethyst_core::{
+    ecs::{Read, System, Write},
+    math::{Point3, UnitQuaternion, Vector3},
+    timing::Time,
+};
+use palette::Srgba;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of segments of the circles of spheres and capsules.
+const CIRCLE_SEGMENTS: u32 = 24;
+
+/// Resource collecting the gizmos to draw.
+///
+/// Every submitted gizmo is drawn at least once. Gizmos without a duration are removed after being
+/// drawn, so they need to be submitted again every frame.
+#[derive(Debug, Default)]
+pub struct Gizmos {
+    items: Vec<Gizmo>,
+}
+
+impl Gizmos {
+    /// Creates an empty gizmos resource.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Submits a line from `start` to `end`.
+    pub fn line(&mut self, start: Point3<f32>, end: Point3<f32>, color: Srgba) -> &mut Gizmo {
+        self.push(GizmoShape::Line { start, end }, color)
+    }
+
+    /// Submits an arrow from `start` pointing at `end`.
+    pub fn arrow(&mut self, start: Point3<f32>, end: Point3<f32>, color: Srgba) -> &mut Gizmo {
+        self.push(GizmoShape::Arrow { start, end }, color)
+    }
+
+    /// Submits the edges of a box centered on `center` with the given half extents and rotation.
+    pub fn wire_box(
+        &mut self,
+        center: Point3<f32>,
+        half_extents: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        color: Srgba,
+    ) -> &mut Gizmo {
+        self.push(
+            GizmoShape::WireBox {
+                center,
+                half_extents,
+                rotation,
+            },
+            color,
+        )
+    }
+
+    /// Submits three orthogonal circles outlining a sphere.
+    pub fn wire_sphere(&mut self, center: Point3<f32>, radius: f32, color: Srgba) -> &mut Gizmo {
+        self.push(GizmoShape::WireSphere { center, radius }, color)
+    }
+
+    /// Submits the outline of a capsule, whose segment goes from `start` to `end`.
+    pub fn wire_capsule(
+        &mut self,
+        start: Point3<f32>,
+        end: Point3<f32>,
+        radius: f32,
+        color: Srgba,
+    ) -> &mut Gizmo {
+        self.push(GizmoShape::WireCapsule { start, end, radius }, color)
+    }
+
+    /// Submits a text label centered on `position`, facing the camera, with the given height of
+    /// its characters in world units.
+    ///
+    /// Labels are drawn with a stroke font supporting ASCII letters, digits and common
+    /// punctuation. Other characters are drawn as boxes.
+    pub fn text(
+        &mut self,
+        position: Point3<f32>,
+        text: impl Into<String>,
+        height: f32,
+        color: Srgba,
+    ) -> &mut Gizmo {
+        self.push(
+            GizmoShape::Text {
+                position,
+                text: text.into(),
+                height,
+            },
+            color,
+        )
+    }
+
+    /// Removes all gizmos, including those with a remaining duration.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Returns the gizmos to draw.
+    pub fn iter(&self) -> impl Iterator<Item = &Gizmo> + '_ {
+        self.items.iter()
+    }
+
+    fn push(&mut self, shape: GizmoShape, color: Srgba) -> &mut Gizmo {
+        self.items.push(Gizmo {
+            shape,
+            color,
+            duration: 0.0,
+            depth_test: true,
+            drawn: false,
+        });
+        self.items.last_mut().unwrap()
+    }
+
+    /// Marks all gizmos as drawn.
+    pub(crate) fn mark_drawn(&mut self) {
+        for gizmo in &mut self.items {
+            gizmo.drawn = true;
+        }
+    }
+
+    /// Counts down the duration of drawn gizmos and removes the expired ones.
+    pub(crate) fn update(&mut self, delta_seconds: f32) {
+        for gizmo in self.items.iter_mut().filter(|gizmo| gizmo.drawn) {
+            gizmo.duration -= delta_seconds;
+        }
+        self.items
+            .retain(|gizmo| !gizmo.drawn || gizmo.duration > 0.0);
+    }
+}
+
+/// A single gizmo submitted to `Gizmos`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gizmo {
+    shape: GizmoShape,
+    color: Srgba,
+    duration: f32,
+    depth_test: bool,
+    drawn: bool,
+}
+
+impl Gizmo {
+    /// Keeps drawing the gizmo for the given number of seconds of game time.
+    pub fn with_duration(&mut self, seconds: f32) -> &mut Self {
+        self.duration = seconds;
+        self
+    }
+
+    /// Sets whether the gizmo is hidden behind the geometry in front of it, which is the
+    /// default. Gizmos without depth test are drawn on top of everything else.
+    pub fn with_depth_test(&mut self, depth_test: bool) -> &mut Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    /// Returns the shape of the gizmo.
+    pub fn shape(&self) -> &GizmoShape {
+        &self.shape
+    }
+
+    /// Returns whether the gizmo is depth tested.
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    /// Appends the lines forming the gizmo to `lines`. Text labels are laid out in the plane
+    /// spanned by the `right` and `up` camera axes.
+    pub(crate) fn lines(&self, right: Vector3<f32>, up: Vector3<f32>, lines: &mut Vec<DebugLine>) {
+        let mut line = |start: Point3<f32>, end: Point3<f32>| {
+            lines.push(DebugLine::new(start, end, self.color, self.color))
+        };
+        match self.shape {
+            GizmoShape::Line { start, end } => line(start, end),
+            GizmoShape::Arrow { start, end } => {
+                line(start, end);
+                let direction = end - start;
+                let length = direction.norm();
+                if length <= std::f32::EPSILON {
+                    return;
+                }
+                let (u, v) = orthonormal_basis(direction / length);
+                let head = length.min(1.0) * 0.2;
+                let base = end - direction / length * head;
+                for side in &[u, -u, v, -v] {
+                    line(end, base + side * head * 0.5);
+                }
+            }
+            GizmoShape::WireBox {
+                center,
+                half_extents,
+                rotation,
+            } => {
+                let corner = |x: f32, y: f32, z: f32| {
+                    center
+                        + rotation
+                            * Vector3::new(
+                                x * half_extents.x,
+                                y * half_extents.y,
+                                z * half_extents.z,
+                            )
+                };
+                for &a in &[-1.0, 1.0] {
+                    for &b in &[-1.0, 1.0] {
+                        line(corner(-1.0, a, b), corner(1.0, a, b));
+                        line(corner(a, -1.0, b), corner(a, 1.0, b));
+                        line(corner(a, b, -1.0), corner(a, b, 1.0));
+                    }
+                }
+            }
+            GizmoShape::WireSphere { center, radius } => {
+                let (x, y, z) = (Vector3::x(), Vector3::y(), Vector3::z());
+                for &(u, v) in &[(x, y), (y, z), (z, x)] {
+                    arc(&mut line, center, u * radius, v * radius, 0.0, 1.0);
+                }
+            }
+            GizmoShape::WireCapsule { start, end, radius } => {
+                let axis = end - start;
+                let axis = if axis.norm() > std::f32::EPSILON {
+                    axis.normalize()
+                } else {
+                    Vector3::y()
+                };
+                let (u, v) = orthonormal_basis(axis);
+                for &(point, cap) in &[(start, -axis), (end, axis)] {
+                    arc(&mut line, point, u * radius, v * radius, 0.0, 1.0);
+                    arc(&mut line, point, u * radius, cap * radius, 0.0, 0.5);
+                    arc(&mut line, point, v * radius, cap * radius, 0.0, 0.5);
+                    arc(&mut line, point, -u * radius, cap * radius, 0.0, 0.5);
+                    arc(&mut line, point, -v * radius, cap * radius, 0.0, 0.5);
+                }
+                for side in &[u, -u, v, -v] {
+                    line(start + side * radius, end + side * radius);
+                }
+            }
+            GizmoShape::Text {
+                position,
+                ref text,
+                height,
+            } => {
+                let scale = height / GLYPH_HEIGHT;
+                let rows = text.lines().count().max(1) as f32;
+                for (row, text_line) in text.lines().enumerate() {
+                    let width = text_line.chars().count() as f32 * GLYPH_ADVANCE - 1.0;
+                    let origin = Vector3::new(
+                        -width / 2.0,
+                        (rows / 2.0 - row as f32 - 1.0) * LINE_ADVANCE + 1.0,
+                        0.0,
+                    );
+                    for (column, c) in text_line.chars().enumerate() {
+                        let x = origin.x + column as f32 * GLYPH_ADVANCE;
+                        for &(x0, y0, x1, y1) in glyph(c) {
+                            let point = |gx: u8, gy: u8| {
+                                position
+                                    + right * (x + f32::from(gx)) * scale
+                                    + up * (origin.y + f32::from(gy)) * scale
+                            };
+                            line(point(x0, y0), point(x1, y1));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The shape drawn by a `Gizmo`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GizmoShape {
+    /// A line from `start` to `end`.
+    Line {
+        /// Start of the line.
+        start: Point3<f32>,
+        /// End of the line.
+        end: Point3<f32>,
+    },
+    /// An arrow from `start` pointing at `end`.
+    Arrow {
+        /// Start of the arrow.
+        start: Point3<f32>,
+        /// Tip of the arrow.
+        end: Point3<f32>,
+    },
+    /// The edges of a rotated box.
+    WireBox {
+        /// Center of the box.
+        center: Point3<f32>,
+        /// Half of the size of the box along each of its axes.
+        half_extents: Vector3<f32>,
+        /// Rotation of the box around its center.
+        rotation: UnitQuaternion<f32>,
+    },
+    /// Three orthogonal circles outlining a sphere.
+    WireSphere {
+        /// Center of the sphere.
+        center: Point3<f32>,
+        /// Radius of the sphere.
+        radius: f32,
+    },
+    /// The outline of a capsule.
+    WireCapsule {
+        /// Center of the first hemisphere.
+        start: Point3<f32>,
+        /// Center of the second hemisphere.
+        end: Point3<f32>,
+        /// Radius of the capsule.
+        radius: f32,
+    },
+    /// A text label facing the camera.
+    Text {
+        /// Center of the label.
+        position: Point3<f32>,
+        /// Text of the label, which may have multiple lines.
+        text: String,
+        /// Height of the characters in world units.
+        height: f32,
+    },
+}
+
+/// Counts down the duration of the `Gizmos` and removes the expired ones.
+///
+/// Added by the `RenderGizmos` plugin.
+#[derive(Debug, Default)]
+pub struct GizmosSystem;
+
+impl<'a> System<'a> for GizmosSystem {
+    type SystemData = (Read<'a, Time>, Write<'a, Gizmos>);
+
+    fn run(&mut self, (time, mut gizmos): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("gizmos_system");
+
+        gizmos.update(time.delta_seconds());
+    }
+}
+
+/// Returns two unit vectors orthogonal to `axis` and to each other.
+fn orthonormal_basis(axis: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let reference = if axis.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    let u = axis.cross(&reference).normalize();
+    (u, axis.cross(&u))
+}
+
+/// Draws the part of the ellipse with axes `u` and `v` from `start` to `end` turns.
+fn arc(
+    line: &mut impl FnMut(Point3<f32>, Point3<f32>),
+    center: Point3<f32>,
+    u: Vector3<f32>,
+    v: Vector3<f32>,
+    start: f32,
+    end: f32,
+) {
+    let segments = ((end - start) * CIRCLE_SEGMENTS as f32).ceil().max(1.0) as u32;
+    let point = |i: u32| {
+        let angle =
+            std::f32::consts::PI * 2.0 * (start + (end - start) * i as f32 / segments as f32);
+        center + u * angle.cos() + v * angle.sin()
+    };
+    for i in 0..segments {
+        line(point(i), point(i + 1));
+    }
+}
+
+/// Height of the glyphs in font units.
+const GLYPH_HEIGHT: f32 = 6.0;
+/// Horizontal distance between characters in font units.
+const GLYPH_ADVANCE: f32 = 5.0;
+/// Vertical distance between lines in font units.
+const LINE_ADVANCE: f32 = 8.0;
+
+type Stroke = (u8, u8, u8, u8);
+
+/// Returns the strokes of a character on a 4x6 grid, with the origin at the bottom left.
+fn glyph(c: char) -> &'static [Stroke] {
+    match c.to_ascii_uppercase() {
+        ' ' => &[],
+        '0' => &[
+            (0, 0, 4, 0),
+            (4, 0, 4, 6),
+            (4, 6, 0, 6),
+            (0, 6, 0, 0),
+            (0, 0, 4, 6),
+        ],
+        '1' => &[(2, 0, 2, 6), (2, 6, 1, 5), (1, 0, 3, 0)],
+        '2' => &[
+            (0, 6, 4, 6),
+            (4, 6, 4, 3),
+            (4, 3, 0, 3),
+            (0, 3, 0, 0),
+            (0, 0, 4, 0),
+        ],
+        '3' => &[(0, 6, 4, 6), (4, 6, 4, 0), (4, 0, 0, 0), (1, 3, 4, 3)],
+        '4' => &[(0, 6, 0, 3), (0, 3, 4, 3), (4, 6, 4, 0)],
+        '5' | 'S' => &[
+            (4, 6, 0, 6),
+            (0, 6, 0, 3),
+            (0, 3, 4, 3),
+            (4, 3, 4, 0),
+            (4, 0, 0, 0),
+        ],
+        '6' => &[
+            (4, 6, 0, 6),
+            (0, 6, 0, 0),
+            (0, 0, 4, 0),
+            (4, 0, 4, 3),
+            (4, 3, 0, 3),
+        ],
+        '7' => &[(0, 6, 4, 6), (4, 6, 1, 0)],
+        '8' => &[
+            (0, 0, 4, 0),
+            (4, 0, 4, 6),
+            (4, 6, 0, 6),
+            (0, 6, 0, 0),
+            (0, 3, 4, 3),
+        ],
+        '9' => &[
+            (4, 3, 0, 3),
+            (0, 3, 0, 6),
+            (0, 6, 4, 6),
+            (4, 6, 4, 0),
+            (4, 0, 0, 0),
+        ],
+        'A' => &[
+            (0, 0, 0, 4),
+            (0, 4, 2, 6),
+            (2, 6, 4, 4),
+            (4, 4, 4, 0),
+            (0, 3, 4, 3),
+        ],
+        'B' => &[
+            (0, 0, 0, 6),
+            (0, 6, 3, 6),
+            (3, 6, 4, 5),
+            (4, 5, 4, 4),
+            (4, 4, 3, 3),
+            (0, 3, 3, 3),
+            (3, 3, 4, 2),
+            (4, 2, 4, 1),
+            (4, 1, 3, 0),
+            (3, 0, 0, 0),
+        ],
+        'C' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 0, 4, 0)],
+        'D' => &[
+            (0, 0, 0, 6),
+            (0, 6, 2, 6),
+            (2, 6, 4, 4),
+            (4, 4, 4, 2),
+            (4, 2, 2, 0),
+            (2, 0, 0, 0),
+        ],
+        'E' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 0, 4, 0), (0, 3, 3, 3)],
+        'F' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 3, 3, 3)],
+        'G' => &[
+            (4, 6, 0, 6),
+            (0, 6, 0, 0),
+            (0, 0, 4, 0),
+            (4, 0, 4, 3),
+            (4, 3, 2, 3),
+        ],
+        'H' => &[(0, 0, 0, 6), (4, 0, 4, 6), (0, 3, 4, 3)],
+        'I' => &[(0, 6, 4, 6), (2, 6, 2, 0), (0, 0, 4, 0)],
+        'J' => &[(4, 6, 4, 0), (4, 0, 0, 0), (0, 0, 0, 2)],
+        'K' => &[(0, 0, 0, 6), (4, 6, 0, 3), (0, 3, 4, 0)],
+        'L' => &[(0, 6, 0, 0), (0, 0, 4, 0)],
+        'M' => &[(0, 0, 0, 6), (0, 6, 2, 3), (2, 3, 4, 6), (4, 6, 4, 0)],
+        'N' => &[(0, 0, 0, 6), (0, 6, 4, 0), (4, 0, 4, 6)],
+        'O' => &[(0, 0, 4, 0), (4, 0, 4, 6), (4, 6, 0, 6), (0, 6, 0, 0)],
+        'P' => &[(0, 0, 0, 6), (0, 6, 4, 6), (4, 6, 4, 3), (4, 3, 0, 3)],
+        'Q' => &[
+            (0, 0, 4, 0),
+            (4, 0, 4, 6),
+            (4, 6, 0, 6),
+            (0, 6, 0, 0),
+            (2, 2, 4, 0),
+        ],
+        'R' => &[
+            (0, 0, 0, 6),
+            (0, 6, 4, 6),
+            (4, 6, 4, 3),
+            (4, 3, 0, 3),
+            (2, 3, 4, 0),
+        ],
+        'T' => &[(0, 6, 4, 6), (2, 6, 2, 0)],
+        'U' => &[(0, 6, 0, 0), (0, 0, 4, 0), (4, 0, 4, 6)],
+        'V' => &[(0, 6, 2, 0), (2, 0, 4, 6)],
+        'W' => &[(0, 6, 1, 0), (1, 0, 2, 3), (2, 3, 3, 0), (3, 0, 4, 6)],
+        'X' => &[(0, 0, 4, 6), (0, 6, 4, 0)],
+        'Y' => &[(0, 6, 2, 3), (4, 6, 2, 3), (2, 3, 2, 0)],
+        'Z' => &[(0, 6, 4, 6), (4, 6, 0, 0), (0, 0, 4, 0)],
+        '.' => &[(2, 0, 2, 1)],
+        ',' => &[(2, 1, 1, 0)],
+        ':' => &[(2, 1, 2, 2), (2, 4, 2, 5)],
+        ';' => &[(2, 1, 1, 0), (2, 4, 2, 5)],
+        '-' => &[(1, 3, 3, 3)],
+        '+' => &[(1, 3, 3, 3), (2, 2, 2, 4)],
+        '=' => &[(1, 2, 3, 2), (1, 4, 3, 4)],
+        '_' => &[(0, 0, 4, 0)],
+        '/' => &[(0, 0, 4, 6)],
+        '\\' => &[(0, 6, 4, 0)],
+        '(' => &[(3, 6, 1, 4), (1, 4, 1, 2), (1, 2, 3, 0)],
+        ')' => &[(1, 6, 3, 4), (3, 4, 3, 2), (3, 2, 1, 0)],
+        '[' => &[(3, 6, 1, 6), (1, 6, 1, 0), (1, 0, 3, 0)],
+        ']' => &[(1, 6, 3, 6), (3, 6, 3, 0), (3, 0, 1, 0)],
+        '<' => &[(4, 5, 0, 3), (0, 3, 4, 1)],
+        '>' => &[(0, 5, 4, 3), (4, 3, 0, 1)],
+        '!' => &[(2, 6, 2, 2), (2, 1, 2, 0)],
+        '?' => &[
+            (0, 5, 1, 6),
+            (1, 6, 3, 6),
+            (3, 6, 4, 5),
+            (4, 5, 4, 4),
+            (4, 4, 2, 3),
+            (2, 3, 2, 2),
+            (2, 1, 2, 0),
+        ],
+        '%' => &[(0, 0, 4, 6), (0, 6, 0, 5), (4, 1, 4, 0)],
+        '*' => &[(2, 1, 2, 5), (0, 2, 4, 4), (0, 4, 4, 2)],
+        '#' => &[(1, 0, 1, 6), (3, 0, 3, 6), (0, 2, 4, 2), (0, 4, 4, 4)],
+        '\'' => &[(2, 6, 2, 4)],
+        '"' => &[(1, 6, 1, 4), (3, 6, 3, 4)],
+        _ => &[(0, 0, 4, 0), (4, 0, 4, 6), (4, 6, 0, 6), (0, 6, 0, 0)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white() -> Srgba {
+        Srgba::new(1.0, 1.0, 1.0, 1.0)
+    }
+
+    #[test]
+    fn gizmos_expire_after_being_drawn() {
+        let mut gizmos = Gizmos::new();
+        gizmos.line(Point3::origin(), Point3::new(1.0, 0.0, 0.0), white());
+        gizmos
+            .wire_sphere(Point3::origin(), 1.0, white())
+            .with_duration(1.0);
+
+        gizmos.update(0.5);
+        assert_eq!(gizmos.iter().count(), 2);
+
+        gizmos.mark_drawn();
+        gizmos.update(0.5);
+        assert_eq!(gizmos.iter().count(), 1);
+        gizmos.update(0.6);
+        assert_eq!(gizmos.iter().count(), 0);
+    }
+
+    #[test]
+    fn shapes_produce_lines() {
+        let mut gizmos = Gizmos::new();
+        gizmos.line(Point3::origin(), Point3::new(1.0, 0.0, 0.0), white());
+        gizmos.arrow(Point3::origin(), Point3::new(0.0, 2.0, 0.0), white());
+        gizmos.wire_box(
+            Point3::origin(),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::identity(),
+            white(),
+        );
+        gizmos
+            .text(Point3::origin(), "Hi", 1.0, white())
+            .with_depth_test(false);
+
+        let counts: Vec<_> = gizmos
+            .iter()
+            .map(|gizmo| {
+                let mut lines = Vec::new();
+                gizmo.lines(Vector3::x(), Vector3::y(), &mut lines);
+                lines.len()
+            })
+            .collect();
+        assert_eq!(counts, vec![1, 5, 12, 6]);
+        assert!(!gizmos.iter().last().unwrap().depth_test());
+    }
+}
@@ -0,0 +1,277 @@
+//! Comparison of rendered frames with reference images, to catch rendering regressions in tests.
+//!
+//! Frames rendered offscreen with the `RenderOffscreen` plugin and read back by the
+//! `RenderCapture` plugin are checked against reference PNGs with a perceptual difference
+//! threshold, so small differences in rounding between GPUs don't fail the tests:
+//!
+//! ```rust,ignore
+//! let frame = world.read_resource::<CapturedFrames>().latest().cloned().unwrap();
+//! GoldenImage::new("tests/golden/sprites.png").check(&frame)?;
+//! ```
+//!
+//! The reference images are created or updated by running the tests with the
+//! `AMETHYST_UPDATE_GOLDEN` environment variable set.
+use crate::capture::CapturedImage;
+use amethyst_error::{format_err, Error};
+use image::{Rgba, RgbaImage};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable which, when set, makes `GoldenImage::check` write the reference images
+/// instead of comparing with them.
+pub const UPDATE_GOLDEN_VAR: &str = "AMETHYST_UPDATE_GOLDEN";
+
+/// Largest squared YIQ difference between two colors.
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+/// Difference between two images of the same size.
+#[derive(Clone, Debug)]
+pub struct ImageDiff {
+    differing: usize,
+    max_difference: f32,
+    image: RgbaImage,
+}
+
+impl ImageDiff {
+    /// Returns the number of pixels differing by more than the threshold.
+    pub fn differing_pixels(&self) -> usize {
+        self.differing
+    }
+
+    /// Returns the ratio of pixels differing by more than the threshold.
+    pub fn differing_ratio(&self) -> f32 {
+        let (width, height) = self.image.dimensions();
+        match width as usize * height as usize {
+            0 => 0.0,
+            pixels => self.differing as f32 / pixels as f32,
+        }
+    }
+
+    /// Returns the largest perceptual difference between two pixels, from 0.0 for identical
+    /// colors to 1.0 for the most different ones.
+    pub fn max_difference(&self) -> f32 {
+        self.max_difference
+    }
+
+    /// Returns an image showing the differing pixels in red over the faded expected image.
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+}
+
+/// Compares two images pixel by pixel with the perceptual YIQ color difference, pixels differing
+/// by more than the threshold counting as different.
+///
+/// Fails if the images don't have the same size.
+pub fn compare_images(
+    actual: &RgbaImage,
+    expected: &RgbaImage,
+    threshold: f32,
+) -> Result<ImageDiff, Error> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(format_err!(
+            "Image of size {:?} compared with an image of size {:?}",
+            actual.dimensions(),
+            expected.dimensions()
+        ));
+    }
+
+    let (width, height) = expected.dimensions();
+    let mut image = RgbaImage::new(width, height);
+    let mut differing = 0;
+    let mut max_difference = 0.0f32;
+    for ((actual, expected), diff) in actual
+        .pixels()
+        .zip(expected.pixels())
+        .zip(image.pixels_mut())
+    {
+        let difference = color_difference(actual, expected);
+        max_difference = max_difference.max(difference);
+        *diff = if difference > threshold {
+            differing += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let gray = 255.0 - (255.0 - luma(expected)) * 0.1;
+            Rgba([gray as u8, gray as u8, gray as u8, 255])
+        };
+    }
+
+    Ok(ImageDiff {
+        differing,
+        max_difference,
+        image,
+    })
+}
+
+/// Perceptual difference between two colors blended over white, from 0.0 to 1.0.
+fn color_difference(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    if a == b {
+        return 0.0;
+    }
+    let (a, b) = (blend_white(a), blend_white(b));
+    let y = yiq_y(a) - yiq_y(b);
+    let i = yiq_i(a) - yiq_i(b);
+    let q = yiq_q(a) - yiq_q(b);
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+    (delta / MAX_YIQ_DELTA).sqrt().min(1.0)
+}
+
+fn blend_white(color: &Rgba<u8>) -> [f32; 3] {
+    let alpha = f32::from(color[3]) / 255.0;
+    let blend = |c: u8| 255.0 + (f32::from(c) - 255.0) * alpha;
+    [blend(color[0]), blend(color[1]), blend(color[2])]
+}
+
+fn luma(color: &Rgba<u8>) -> f32 {
+    yiq_y(blend_white(color))
+}
+
+fn yiq_y([r, g, b]: [f32; 3]) -> f32 {
+    r * 0.298_895 + g * 0.586_622 + b * 0.114_482
+}
+
+fn yiq_i([r, g, b]: [f32; 3]) -> f32 {
+    r * 0.595_978 - g * 0.274_176 - b * 0.321_802
+}
+
+fn yiq_q([r, g, b]: [f32; 3]) -> f32 {
+    r * 0.211_470 - g * 0.522_617 + b * 0.311_147
+}
+
+/// Reference image rendered frames are checked against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoldenImage {
+    reference: PathBuf,
+    threshold: f32,
+    max_differing: f32,
+}
+
+impl GoldenImage {
+    /// Creates a check against the reference PNG at the given path, failing if any pixel differs
+    /// by more than 0.1.
+    pub fn new(reference: impl Into<PathBuf>) -> Self {
+        Self {
+            reference: reference.into(),
+            threshold: 0.1,
+            max_differing: 0.0,
+        }
+    }
+
+    /// Sets the perceptual difference above which pixels differ, from 0.0 to 1.0.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the ratio of differing pixels tolerated, e.g. for anti-aliased edges.
+    pub fn with_max_differing(mut self, ratio: f32) -> Self {
+        self.max_differing = ratio;
+        self
+    }
+
+    /// Returns the path of the reference image.
+    pub fn reference(&self) -> &Path {
+        &self.reference
+    }
+
+    /// Writes the image as the reference, creating its directory if needed.
+    pub fn update(&self, image: &CapturedImage) -> Result<(), Error> {
+        if let Some(dir) = self.reference.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        image.clone().into_rgba_image().save(&self.reference)?;
+        Ok(())
+    }
+
+    /// Checks the image against the reference, or writes it as the reference if the
+    /// `AMETHYST_UPDATE_GOLDEN` environment variable is set.
+    ///
+    /// When the check fails, the image and the differing pixels are written next to the
+    /// reference, e.g. `sprites.actual.png` and `sprites.diff.png` for `sprites.png`.
+    pub fn check(&self, image: &CapturedImage) -> Result<(), Error> {
+        if matches!(std::env::var_os(UPDATE_GOLDEN_VAR), Some(value) if !value.is_empty()) {
+            return self.update(image);
+        }
+        if !self.reference.exists() {
+            return Err(format_err!(
+                "Reference image {} doesn't exist, run with {}=1 to create it",
+                self.reference.display(),
+                UPDATE_GOLDEN_VAR
+            ));
+        }
+
+        let expected = image::open(&self.reference)?.to_rgba();
+        let actual = image.clone().into_rgba_image();
+        let diff = compare_images(&actual, &expected, self.threshold)?;
+        if diff.differing_ratio() <= self.max_differing {
+            return Ok(());
+        }
+
+        let actual_path = self.reference.with_extension("actual.png");
+        let diff_path = self.reference.with_extension("diff.png");
+        actual.save(&actual_path)?;
+        diff.image().save(&diff_path)?;
+        Err(format_err!(
+            "{} pixels differ from {} by up to {:.3}, see {} and {}",
+            diff.differing_pixels(),
+            self.reference.display(),
+            diff.max_difference(),
+            actual_path.display(),
+            diff_path.display()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(pixels: &[[u8; 4]]) -> RgbaImage {
+        let data = pixels.iter().flatten().copied().collect();
+        RgbaImage::from_raw(pixels.len() as u32, 1, data).unwrap()
+    }
+
+    #[test]
+    fn compares_perceptually() {
+        let expected = image(&[[0, 0, 0, 255], [200, 100, 50, 255], [10, 20, 30, 0]]);
+        let actual = image(&[[255, 255, 255, 255], [201, 99, 50, 255], [90, 80, 70, 0]]);
+
+        let diff = compare_images(&actual, &expected, 0.1).unwrap();
+        // Transparent pixels blend to white whatever their color.
+        assert_eq!(diff.differing_pixels(), 1);
+        assert_eq!(diff.image().get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert!(diff.max_difference() > 0.9);
+        assert!((diff.differing_ratio() - 1.0 / 3.0).abs() < 1e-6);
+
+        let diff = compare_images(&expected, &expected, 0.0).unwrap();
+        assert_eq!(diff.differing_pixels(), 0);
+        assert_eq!(diff.max_difference(), 0.0);
+
+        assert!(compare_images(&actual, &image(&[[0, 0, 0, 255]]), 0.1).is_err());
+    }
+
+    #[test]
+    fn checks_and_updates_references() {
+        let dir = std::env::temp_dir().join(format!("golden_{}", std::process::id()));
+        let golden = GoldenImage::new(dir.join("square.png")).with_threshold(0.05);
+        let square = CapturedImage::from(image(&[[255, 0, 0, 255], [0, 0, 255, 255]]));
+
+        assert!(golden.check(&square).is_err());
+        golden.update(&square).unwrap();
+        golden.check(&square).unwrap();
+
+        let changed = CapturedImage::from(image(&[[255, 0, 0, 255], [0, 255, 0, 255]]));
+        assert!(golden.check(&changed).is_err());
+        assert!(dir.join("square.actual.png").exists());
+        assert!(dir.join("square.diff.png").exists());
+        golden
+            .clone()
+            .with_max_differing(0.5)
+            .check(&changed)
+            .unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -16,24 +16,50 @@
 //! * [`DrawShadedDesc`](crate::pass::shaded::DrawShadedDesc)
 //! * [`DrawSkyboxDesc`](crate::pass::skybox::DrawSkyboxDesc)
 //! * [`DrawDebugLinesDesc`](crate::pass::debug_lines::DrawDebugLinesDesc)
+//! * [`DrawGizmosDesc`](crate::pass::gizmos::DrawGizmosDesc)
+//! * [`DrawParticlesDesc`](crate::pass::particles::DrawParticlesDesc)
+//! * [`DrawLighting2DBuffersDesc`](crate::pass::lighting_2d::DrawLighting2DBuffersDesc)
+//! * [`DrawLighting2DDesc`](crate::pass::lighting_2d::DrawLighting2DDesc)
+//! * [`DrawDecalsDesc`](crate::pass::decal::DrawDecalsDesc)
+//! * [`DrawColorGradingDesc`](crate::pass::color_grading::DrawColorGradingDesc)
+//! * [`DispatchComputeDesc`](crate::pass::compute::DispatchComputeDesc)
+//! * [`WriteTimestampDesc`](crate::pass::timestamp::WriteTimestampDesc)
 //!
 //! ## Systems
 //!
 //! * [`RenderingSystem`](crate::system::RenderingSystem)
 //! * [`VisibilitySortingSystem`](crate::visibility::VisibilitySortingSystem)
+//! * [`LodSystem`](crate::lod::LodSystem)
+//! * [`RenderScaleSystem`](crate::render_scale::RenderScaleSystem)
+//! * [`TextureAtlasSystem`](crate::atlas::TextureAtlasSystem)
+//! * [`SpriteAnimationSystem`](crate::sprite::animation::SpriteAnimationSystem)
 //! * [`SpriteVisibilitySortingSystem`](crate::sprite_visibility::SpriteVisibilitySortingSystem)
+//! * [`GizmosSystem`](crate::gizmos::GizmosSystem)
+//! * [`ParticleSystem`](crate::particles::ParticleSystem)
+//! * [`EnvironmentMapProcessorSystem`](crate::environment_map::EnvironmentMapProcessorSystem)
+//! * [`VideoSystem`](crate::video::VideoSystem) with the `video` feature
 //!
 //! ## Components
 //!
 //! * [`Camera`](camera::Camera)
+//! * [`CameraViewport`](viewport::CameraViewport)
 //! * [`SpriteVisibility`](sprite_visibility::SpriteVisibility)
 //! * [`Visibility`](visibility::Visibility)
 //! * [`BoundingSphere`](visibility::BoundingSphere)
+//! * [`RenderLayers`](visibility::RenderLayers)
 //! * [`DebugLinesComponent`](debug_drawing::DebugLinesComponent)
+//! * [`Decal`](decal::Decal)
 //! * [`Light`](light::Light)
+//! * [`Light2D`](light_2d::Light2D)
+//! * [`LodGroup`](lod::LodGroup)
+//! * [`Occluder2D`](light_2d::Occluder2D)
+//! * [`ParticleEmitter`](particles::ParticleEmitter)
 //! * [`Tint`](resources::Tint)
 //! * [`JointTransforms`](skinning::JointTransforms)
 //! * [`SpriteRender`](sprite::SpriteRender)
+//! * [`SpriteAnimation`](sprite::animation::SpriteAnimation)
+//! * [`SpriteMaterial`](light_2d::SpriteMaterial)
+//! * [`VideoPlayer`](video::VideoPlayer) with the `video` feature
 
 #![doc(
     html_logo_url = "https://amethyst.rs/brand/logo-standard.svg",
@@ -62,31 +88,56 @@ pub use rendy;
 
 pub mod pass;
 
+pub mod atlas;
 pub mod batch;
 pub mod bundle;
 pub mod camera;
+pub mod capture;
+pub mod color_grading;
 pub mod debug_drawing;
+pub mod debug_mode;
+pub mod decal;
+pub mod environment_map;
 pub mod error;
 pub mod formats;
+pub mod gizmos;
 pub mod light;
+pub mod light_2d;
+pub mod lod;
+pub mod morph;
 pub mod mtl;
+pub mod particles;
 pub mod pipeline;
 pub mod plugins;
+pub mod render_scale;
+pub mod render_texture;
 pub mod resources;
 pub mod serde_shim;
+pub mod settings;
+pub mod shader;
+pub mod shadow;
 pub mod shape;
 pub mod skinning;
 pub mod sprite;
 pub mod sprite_visibility;
+pub mod stats;
 pub mod submodules;
 pub mod system;
 pub mod transparent;
 pub mod types;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod viewport;
 pub mod visibility;
+pub mod window_camera;
 
 pub mod pod;
 pub mod util;
 
+#[cfg(feature = "test-support")]
+pub mod golden;
+#[cfg(feature = "window")]
+mod present;
 #[cfg(feature = "test-support")]
 mod render_test_bundle;
 
@@ -102,7 +153,7 @@ pub use crate::{
     plugins::*,
     sprite::{Sprite, SpriteRender, SpriteSheet, SpriteSheetFormat},
     system::{GraphCreator, MeshProcessorSystem, RenderingSystem, TextureProcessorSystem},
-    transparent::Transparent,
+    transparent::{RenderQueue, Transparent},
     types::{Backend, Mesh, Texture},
     util::{simple_shader_set, ChangeDetection},
 };
@@ -39,6 +39,9 @@ pub struct DirectionalLight {
     pub intensity: f32,
     /// Direction that the light is pointing.
     pub direction: Vector3<f32>,
+    /// Whether the light casts shadows, see `ShadowConfig`. Only the first directional light
+    /// casting shadows does.
+    pub cast_shadows: bool,
 }
 
 impl Default for DirectionalLight {
@@ -47,6 +50,7 @@ impl Default for DirectionalLight {
             color: Default::default(),
             intensity: 1.0,
             direction: [-1.0, -1.0, -1.0].into(),
+            cast_shadows: false,
         }
     }
 }
@@ -91,6 +95,8 @@ pub struct PointLight {
     /// Smoothness of the light-to-dark transition from the center to the
     /// radius.
     pub smoothness: f32,
+    /// Whether the light casts shadows up to its radius, see `ShadowConfig`.
+    pub cast_shadows: bool,
 }
 
 impl Default for PointLight {
@@ -100,6 +106,7 @@ impl Default for PointLight {
             intensity: 10.0,
             radius: 10.0,
             smoothness: 4.0,
+            cast_shadows: false,
         }
     }
 }
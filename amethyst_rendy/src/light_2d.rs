@@ -0,0 +1,304 @@
+//! Lights, shadow occluders and sprite materials of the 2D lighting pass.
+//!
+//! Lighting is evaluated in the `z = 0` plane of the world, see `RenderLighting2D`.
+
+use crate::types::Texture;
+use amethyst_assets::{Handle, PrefabData};
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage, Entity, WriteStorage},
+    math::{Matrix4, Point3, Vector2},
+};
+use amethyst_error::Error;
+
+/// Number of segments approximating the outline of circle occluders.
+const CIRCLE_SEGMENTS: usize = 16;
+
+/// A light in the 2D plane. Uses the `Transform` set of components for positioning, and the
+/// direction of spot lights rotates with it.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, PrefabData)]
+#[prefab(Component)]
+pub enum Light2D {
+    /// A point light.
+    Point(PointLight2D),
+    /// A spot light.
+    Spot(SpotLight2D),
+}
+
+impl Component for Light2D {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A point light lighting every direction up to its radius.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PointLight2D {
+    /// Color of the light in SRGB format.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub color: palette::Srgb,
+    /// Brightness of the light at its center.
+    pub intensity: f32,
+    /// Distance at which the light fades out completely.
+    pub radius: f32,
+    /// Height of the light above the plane, tilting the light direction used with normal maps.
+    pub height: f32,
+    /// Whether `Occluder2D`s block the light.
+    pub cast_shadows: bool,
+    /// Radius of the light source, softening the edges of its shadows.
+    pub source_radius: f32,
+}
+
+impl Default for PointLight2D {
+    fn default() -> Self {
+        PointLight2D {
+            color: palette::Srgb::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+            radius: 100.0,
+            height: 20.0,
+            cast_shadows: false,
+            source_radius: 0.0,
+        }
+    }
+}
+
+impl From<PointLight2D> for Light2D {
+    fn from(point: PointLight2D) -> Self {
+        Light2D::Point(point)
+    }
+}
+
+/// A spot light lighting a cone up to its radius.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SpotLight2D {
+    /// Color of the light in SRGB format.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub color: palette::Srgb,
+    /// Brightness of the light at its center.
+    pub intensity: f32,
+    /// Distance at which the light fades out completely.
+    pub radius: f32,
+    /// Height of the light above the plane, tilting the light direction used with normal maps.
+    pub height: f32,
+    /// Direction the light is pointing in the local space of the entity.
+    pub direction: Vector2<f32>,
+    /// Opening angle of the light cone in radians.
+    pub angle: f32,
+    /// Share of the cone over which the light fades out towards its edges in 0.0-1.0.
+    pub smoothness: f32,
+    /// Whether `Occluder2D`s block the light.
+    pub cast_shadows: bool,
+    /// Radius of the light source, softening the edges of its shadows.
+    pub source_radius: f32,
+}
+
+impl Default for SpotLight2D {
+    fn default() -> Self {
+        SpotLight2D {
+            color: palette::Srgb::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+            radius: 100.0,
+            height: 20.0,
+            direction: [0.0, -1.0].into(),
+            angle: std::f32::consts::FRAC_PI_3,
+            smoothness: 0.25,
+            cast_shadows: false,
+            source_radius: 0.0,
+        }
+    }
+}
+
+impl From<SpotLight2D> for Light2D {
+    fn from(spot: SpotLight2D) -> Self {
+        Light2D::Spot(spot)
+    }
+}
+
+/// A shape blocking the `Light2D`s casting shadows, in the local space of the entity's
+/// `Transform`.
+///
+/// The light is only blocked where it enters the shape, so the shape itself is lit from the
+/// sides facing the light.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, PrefabData)]
+#[prefab(Component)]
+pub enum Occluder2D {
+    /// A rectangle centered on the entity.
+    Rectangle {
+        /// Width of the rectangle.
+        width: f32,
+        /// Height of the rectangle.
+        height: f32,
+    },
+    /// A circle centered on the entity.
+    Circle {
+        /// Radius of the circle.
+        radius: f32,
+    },
+    /// A closed polygon through the given points, in either winding order.
+    Polygon(Vec<Vector2<f32>>),
+}
+
+impl Component for Occluder2D {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Occluder2D {
+    /// Outline of the shape transformed by the given global matrix, as segments in
+    /// counter-clockwise order.
+    pub fn segments(&self, global_matrix: &Matrix4<f32>) -> Vec<[Vector2<f32>; 2]> {
+        let local = match self {
+            Occluder2D::Rectangle { width, height } => {
+                let (x, y) = (width / 2.0, height / 2.0);
+                vec![
+                    Vector2::new(-x, -y),
+                    Vector2::new(x, -y),
+                    Vector2::new(x, y),
+                    Vector2::new(-x, y),
+                ]
+            }
+            Occluder2D::Circle { radius } => (0..CIRCLE_SEGMENTS)
+                .map(|i| {
+                    let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+                    Vector2::new(angle.cos(), angle.sin()) * *radius
+                })
+                .collect(),
+            Occluder2D::Polygon(points) => points.clone(),
+        };
+        if local.len() < 3 {
+            return Vec::new();
+        }
+
+        let mut points = local
+            .iter()
+            .map(|p| {
+                global_matrix
+                    .transform_point(&Point3::new(p.x, p.y, 0.0))
+                    .xy()
+                    .coords
+            })
+            .collect::<Vec<_>>();
+        if signed_area(&points) < 0.0 {
+            points.reverse();
+        }
+        points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| [*a, *b])
+            .collect()
+    }
+}
+
+/// Twice the signed area of a polygon, positive for counter-clockwise winding.
+fn signed_area(points: &[Vector2<f32>]) -> f32 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum()
+}
+
+/// Normal and emission maps of a sprite lit by `RenderLighting2D`. The maps are sampled with
+/// the texture coordinates of the sprite in its sprite sheet, so they must share the layout of
+/// the sprite sheet texture.
+///
+/// Sprites without the component are lit as flat surfaces facing the camera.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpriteMaterial {
+    /// Tangent space normal map, with green pointing up. The flat default normal map is used
+    /// when unset.
+    pub normal: Option<Handle<Texture>>,
+    /// Emission map added on top of the lit sprite. Nothing is emitted when unset.
+    pub emission: Option<Handle<Texture>>,
+}
+
+impl Component for SpriteMaterial {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Resource controlling the 2D lighting of `RenderLighting2D`. The defaults are used when the
+/// resource is missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lighting2D {
+    /// Light reaching every pixel regardless of the lights, in SRGB format.
+    pub ambient: palette::Srgb,
+    /// Number of rays traced towards every light casting shadows, spread over its source
+    /// radius. More rays give smoother penumbras.
+    pub shadow_samples: u32,
+}
+
+impl Default for Lighting2D {
+    fn default() -> Self {
+        Lighting2D {
+            ambient: palette::Srgb::new(0.1, 0.1, 0.1),
+            shadow_samples: 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::{Translation3, UnitQuaternion, Vector3};
+
+    fn assert_ccw(segments: &[[Vector2<f32>; 2]]) {
+        let points = segments.iter().map(|s| s[0]).collect::<Vec<_>>();
+        assert!(signed_area(&points) > 0.0);
+        for (segment, next) in segments.iter().zip(segments.iter().cycle().skip(1)) {
+            assert_eq!(segment[1], next[0]);
+        }
+    }
+
+    #[test]
+    fn rectangle_segments_are_transformed() {
+        let matrix = Translation3::new(10.0, 5.0, 3.0).to_homogeneous();
+        let segments = Occluder2D::Rectangle {
+            width: 4.0,
+            height: 2.0,
+        }
+        .segments(&matrix);
+
+        assert_eq!(segments.len(), 4);
+        assert_ccw(&segments);
+        assert_eq!(
+            segments[0],
+            [Vector2::new(8.0, 4.0), Vector2::new(12.0, 4.0)]
+        );
+    }
+
+    #[test]
+    fn polygon_segments_are_wound_counter_clockwise() {
+        let clockwise = vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 1.0),
+            Vector2::new(1.0, 0.0),
+        ];
+        let segments = Occluder2D::Polygon(clockwise).segments(&Matrix4::identity());
+        assert_eq!(segments.len(), 3);
+        assert_ccw(&segments);
+
+        // Mirroring flips the winding, which is restored.
+        let mirror = Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0));
+        let rectangle = Occluder2D::Rectangle {
+            width: 1.0,
+            height: 1.0,
+        };
+        assert_ccw(&rectangle.segments(&mirror));
+    }
+
+    #[test]
+    fn circle_segments_follow_rotation() {
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 1.0).to_homogeneous();
+        let segments = Occluder2D::Circle { radius: 2.0 }.segments(&rotation);
+
+        assert_eq!(segments.len(), CIRCLE_SEGMENTS);
+        assert_ccw(&segments);
+        for segment in &segments {
+            assert!((segment[0].norm() - 2.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn degenerate_polygons_have_no_segments() {
+        let line = Occluder2D::Polygon(vec![Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0)]);
+        assert!(line.segments(&Matrix4::identity()).is_empty());
+    }
+}
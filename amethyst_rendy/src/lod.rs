@@ -0,0 +1,401 @@
+//! Level of detail: switching the meshes of entities by their distance to the camera.
+
+use crate::{
+    camera::{ActiveCamera, Camera},
+    mtl::Material,
+    resources::Tint,
+    transparent::Transparent,
+    types::Mesh,
+};
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::{
+        hibitset::BitSet,
+        prelude::{
+            Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, System,
+            WriteStorage,
+        },
+    },
+    math::{distance, Point3, UnitQuaternion, Vector3},
+    Hidden, HiddenPropagate, Parent, Transform,
+};
+use std::{cmp::Ordering, collections::HashMap};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// A level of detail of a `LodGroup`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodLevel {
+    /// Mesh drawn at this level.
+    pub mesh: Handle<Mesh>,
+    /// Distance to the camera up to which this level is drawn.
+    pub max_distance: f32,
+}
+
+impl LodLevel {
+    /// Create a level drawing the mesh up to the given distance to the camera.
+    pub fn new(mesh: Handle<Mesh>, max_distance: f32) -> Self {
+        Self { mesh, max_distance }
+    }
+
+    /// Create a level drawing the mesh while a sphere of the given radius covers at least
+    /// `min_coverage` of the screen height, for groups using `with_fov_scaling`.
+    pub fn screen_coverage(mesh: Handle<Mesh>, radius: f32, min_coverage: f32) -> Self {
+        Self::new(mesh, radius / min_coverage)
+    }
+}
+
+/// Meshes of an entity at decreasing levels of detail, switched by `LodSystem` by the distance
+/// between the entity and the camera.
+///
+/// The mesh of the selected level is written to the `Handle<Mesh>` component of the entity, which
+/// is removed beyond the max distance of the last level so that nothing is drawn. Use
+/// `f32::INFINITY` as the max distance of the last level to always draw it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodGroup {
+    levels: Vec<LodLevel>,
+    /// Distance before the max distance of a level over which the next level fades in, or 0.0 to
+    /// switch levels without cross-fading.
+    pub fade_range: f32,
+    /// Divide the distance to a perspective camera by its focal length, so that the max distances
+    /// are for a vertical field of view of 90 degrees and levels switch at the same size on
+    /// screen when the camera zooms.
+    pub fov_scaling: bool,
+}
+
+impl Component for LodGroup {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Levels of a `LodGroup` to draw at some distance to the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodSelection {
+    /// Index of the level to draw, or `None` if the entity is beyond every level.
+    pub level: Option<usize>,
+    /// Index of the next level fading in over the drawn one, and its opacity.
+    pub fade: Option<(usize, f32)>,
+}
+
+impl LodGroup {
+    /// Create a group switching between the given levels, ordered by their max distance.
+    pub fn new(levels: impl IntoIterator<Item = LodLevel>) -> Self {
+        let mut levels: Vec<_> = levels.into_iter().collect();
+        levels.sort_by(|a, b| {
+            a.max_distance
+                .partial_cmp(&b.max_distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        Self {
+            levels,
+            fade_range: 0.0,
+            fov_scaling: false,
+        }
+    }
+
+    /// Cross-fade to the next level over the given distance before the max distance of a level.
+    pub fn with_fade_range(mut self, fade_range: f32) -> Self {
+        self.fade_range = fade_range;
+        self
+    }
+
+    /// Scale distances by the field of view of the camera, see `fov_scaling`.
+    pub fn with_fov_scaling(mut self) -> Self {
+        self.fov_scaling = true;
+        self
+    }
+
+    /// Returns the levels of the group, ordered by their max distance.
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+
+    /// Returns the levels to draw at the given distance to the camera.
+    pub fn select(&self, distance: f32) -> LodSelection {
+        let level = self
+            .levels
+            .iter()
+            .position(|level| distance <= level.max_distance);
+        let fade = level.and_then(|index| {
+            let fade_start = self.levels[index].max_distance - self.fade_range;
+            if self.fade_range > 0.0 && index + 1 < self.levels.len() && distance > fade_start {
+                Some((index + 1, (distance - fade_start) / self.fade_range))
+            } else {
+                None
+            }
+        });
+        LodSelection { level, fade }
+    }
+}
+
+/// Selects the mesh of every `LodGroup` by its distance to the active camera, or the first camera
+/// if there is no active one. The selection is skipped without a camera.
+///
+/// While cross-fading, the next level is drawn over the current one by a child entity sharing the
+/// `Handle<Material>` of the group, with the opacity of the fade multiplied into its `Tint`.
+///
+/// Note that this should run after `Transform` has been updated for the current frame, and
+/// before `VisibilitySortingSystem`.
+#[derive(Debug, Default)]
+pub struct LodSystem {
+    fades: HashMap<Entity, Entity>,
+    fading: BitSet,
+}
+
+impl LodSystem {
+    /// Create new level of detail system
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for LodSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, LodGroup>,
+        ReadStorage<'a, Hidden>,
+        ReadStorage<'a, HiddenPropagate>,
+        WriteStorage<'a, Handle<Mesh>>,
+        WriteStorage<'a, Handle<Material>>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Parent>,
+        WriteStorage<'a, Tint>,
+        WriteStorage<'a, Transparent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            active,
+            cameras,
+            groups,
+            hidden,
+            hidden_prop,
+            mut meshes,
+            mut materials,
+            mut transforms,
+            mut parents,
+            mut tints,
+            mut transparents,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("lod_system");
+
+        let origin = Point3::origin();
+        let (camera_position, focal_length) = {
+            let mut camera_join = (&cameras, &transforms).join();
+            match active
+                .entity
+                .and_then(|a| camera_join.get(a, &entities))
+                .or_else(|| camera_join.next())
+            {
+                Some((camera, transform)) => (
+                    transform.global_matrix().transform_point(&origin),
+                    focal_length(camera),
+                ),
+                None => return,
+            }
+        };
+
+        self.fading.clear();
+        for (entity, group, _, _) in (&entities, &groups, !&hidden, !&hidden_prop).join() {
+            let transform = match transforms.get(entity) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            let position = transform.global_matrix().transform_point(&origin);
+            let mut camera_distance = distance(&position, &camera_position);
+            if group.fov_scaling {
+                camera_distance /= focal_length;
+            }
+            let selection = group.select(camera_distance);
+
+            match selection.level {
+                Some(level) => {
+                    let mesh = &group.levels[level].mesh;
+                    if meshes.get(entity) != Some(mesh) {
+                        meshes
+                            .insert(entity, mesh.clone())
+                            .expect("Unreachable: entity is alive");
+                    }
+                }
+                None => {
+                    meshes.remove(entity);
+                }
+            }
+
+            let (next, opacity) = match selection.fade {
+                Some(fade) => fade,
+                None => continue,
+            };
+            let material = match materials.get(entity) {
+                Some(material) => material.clone(),
+                None => continue,
+            };
+            let mut tint = tints
+                .get(entity)
+                .cloned()
+                .unwrap_or_else(|| Tint(palette::Srgba::new(1.0, 1.0, 1.0, 1.0)));
+            tint.0.alpha *= opacity;
+
+            let helper = match self.fades.get(&entity) {
+                Some(&helper) if entities.is_alive(helper) => helper,
+                _ => {
+                    // Start from the global matrix of the group for the first frame, until the
+                    // hierarchy places the child.
+                    let mut helper_transform = transform.clone();
+                    *helper_transform.translation_mut() = Vector3::zeros();
+                    *helper_transform.rotation_mut() = UnitQuaternion::identity();
+                    helper_transform.set_scale(Vector3::new(1.0, 1.0, 1.0));
+
+                    let helper = entities.create();
+                    transforms
+                        .insert(helper, helper_transform)
+                        .expect("Unreachable: entity is alive");
+                    parents
+                        .insert(helper, Parent { entity })
+                        .expect("Unreachable: entity is alive");
+                    transparents
+                        .insert(helper, Transparent)
+                        .expect("Unreachable: entity is alive");
+                    self.fades.insert(entity, helper);
+                    helper
+                }
+            };
+            meshes
+                .insert(helper, group.levels[next].mesh.clone())
+                .expect("Unreachable: entity is alive");
+            materials
+                .insert(helper, material)
+                .expect("Unreachable: entity is alive");
+            tints
+                .insert(helper, tint)
+                .expect("Unreachable: entity is alive");
+            self.fading.add(entity.id());
+        }
+
+        let fading = &self.fading;
+        self.fades.retain(|entity, helper| {
+            let keep = fading.contains(entity.id()) && entities.is_alive(*entity);
+            if !keep {
+                // The helper is already gone if the group was deleted with its children.
+                let _ = entities.delete(*helper);
+            }
+            keep
+        });
+    }
+}
+
+/// Returns the focal length of a perspective camera in units of half the screen height, or 1.0
+/// for other projections.
+fn focal_length(camera: &Camera) -> f32 {
+    if camera.matrix[(3, 2)] != 0.0 {
+        camera.matrix[(1, 1)].abs()
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rendy::mesh::MeshBuilder, types::MeshData};
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn create_meshes(count: usize) -> Vec<Handle<Mesh>> {
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        let storage: AssetStorage<Mesh> = AssetStorage::default();
+        (0..count)
+            .map(|_| loader.load_from_data(MeshData(MeshBuilder::new()), (), &storage))
+            .collect()
+    }
+
+    fn create_group(meshes: &[Handle<Mesh>]) -> LodGroup {
+        LodGroup::new(vec![
+            LodLevel::new(meshes[1].clone(), 50.0),
+            LodLevel::new(meshes[0].clone(), 10.0),
+        ])
+    }
+
+    #[test]
+    fn select_levels_by_distance() {
+        let group = create_group(&create_meshes(2)).with_fade_range(2.0);
+
+        let select = |distance| group.select(distance);
+        assert_eq!(select(0.0).level, Some(0));
+        assert_eq!(select(0.0).fade, None);
+        assert_eq!(select(9.0).level, Some(0));
+        assert_eq!(select(9.0).fade, Some((1, 0.5)));
+        assert_eq!(select(10.5).level, Some(1));
+        assert_eq!(select(10.5).fade, None);
+        // Nothing fades in after the last level.
+        assert_eq!(select(49.0).fade, None);
+        assert_eq!(select(50.5).level, None);
+    }
+
+    #[test]
+    fn screen_coverage_levels() {
+        let meshes = create_meshes(1);
+        let level = LodLevel::screen_coverage(meshes[0].clone(), 2.0, 0.25);
+        assert_eq!(level.max_distance, 8.0);
+        // the focal length of a vertical field of view of 60 degrees is sqrt(3)
+        let camera = Camera::perspective(1.0, std::f32::consts::FRAC_PI_3, 0.1);
+        assert!((focal_length(&camera) - 3.0f32.sqrt()).abs() < 1e-5);
+        assert_eq!(focal_length(&Camera::standard_2d(10.0, 10.0)), 1.0);
+    }
+
+    #[test]
+    fn system_switches_meshes_of_groups() {
+        let meshes = create_meshes(2);
+        let mut world = World::new();
+        world.register::<Camera>();
+        world.register::<LodGroup>();
+        world.register::<Hidden>();
+        world.register::<HiddenPropagate>();
+        world.register::<Handle<Mesh>>();
+        world.register::<Handle<Material>>();
+        world.register::<Transform>();
+        world.register::<Parent>();
+        world.register::<Tint>();
+        world.register::<Transparent>();
+        world.insert(ActiveCamera::default());
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(1.0, 1.0))
+            .with(Transform::default())
+            .build();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, -20.0);
+        transform.copy_local_to_global();
+        let entity = world
+            .create_entity()
+            .with(create_group(&meshes))
+            .with(transform)
+            .build();
+
+        let mut system = LodSystem::new();
+        system.run_now(&world);
+        assert_eq!(
+            world.read_storage::<Handle<Mesh>>().get(entity),
+            Some(&meshes[1])
+        );
+
+        world
+            .write_storage::<Transform>()
+            .get_mut(entity)
+            .unwrap()
+            .set_translation_xyz(0.0, 0.0, -60.0)
+            .copy_local_to_global();
+        system.run_now(&world);
+        assert_eq!(world.read_storage::<Handle<Mesh>>().get(entity), None);
+    }
+}
@@ -0,0 +1,239 @@
+//! Morph targets, e.g. blend shapes for facial animation.
+//!
+//! A `MorphTargets` component stores the position and normal displacements of a mesh, which are
+//! blended on the CPU by the `MorphTargetSystem` with the `MorphWeights` of the entity, or of its
+//! parent, into a new mesh whenever the weights change.
+use crate::types::Mesh;
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::{
+    ecs::prelude::{
+        BitSet, Component, ComponentEvent, DenseVecStorage, Entities, FlaggedStorage, Join, Read,
+        ReadExpect, ReadStorage, ReaderId, System, SystemData, WriteStorage,
+    },
+    Parent,
+};
+use amethyst_derive::SystemDesc;
+use rendy::mesh::{MeshBuilder, Normal, Position};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Displacements of a single morph target, one per vertex of the mesh.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MorphTarget {
+    /// Displacements of the vertex positions.
+    pub positions: Vec<[f32; 3]>,
+    /// Displacements of the vertex normals, if the target changes them.
+    #[serde(default)]
+    pub normals: Option<Vec<[f32; 3]>>,
+}
+
+/// Morph targets of a mesh, blended into the `Handle<Mesh>` of the entity by the
+/// `MorphTargetSystem`.
+///
+/// Tangents aren't displaced, so normal mapped surfaces keep the tangents of the base mesh.
+#[derive(Clone, Debug)]
+pub struct MorphTargets {
+    base: MeshBuilder<'static>,
+    positions: Vec<Position>,
+    normals: Option<Vec<Normal>>,
+    targets: Vec<MorphTarget>,
+}
+
+impl Component for MorphTargets {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl MorphTargets {
+    /// Creates morph targets from a mesh without positions and normals, and the positions and
+    /// normals the targets displace.
+    pub fn new(
+        base: MeshBuilder<'static>,
+        positions: Vec<Position>,
+        normals: Option<Vec<Normal>>,
+        targets: Vec<MorphTarget>,
+    ) -> Self {
+        MorphTargets {
+            base,
+            positions,
+            normals,
+            targets,
+        }
+    }
+
+    /// Returns the morph targets.
+    pub fn targets(&self) -> &[MorphTarget] {
+        &self.targets
+    }
+
+    /// Builds the mesh displaced by the targets with the given weights. Missing weights are 0.0.
+    pub fn blend(&self, weights: &[f32]) -> MeshBuilder<'static> {
+        let (positions, normals) = self.displace(weights);
+        let mut builder = self.base.clone();
+        builder.add_vertices(positions);
+        if let Some(normals) = normals {
+            builder.add_vertices(normals);
+        }
+        builder
+    }
+
+    fn displace(&self, weights: &[f32]) -> (Vec<Position>, Option<Vec<Normal>>) {
+        let mut positions = self.positions.clone();
+        let mut normals = self.normals.clone();
+        for (target, &weight) in self.targets.iter().zip(weights) {
+            if weight == 0.0 {
+                continue;
+            }
+            for (position, delta) in positions.iter_mut().zip(&target.positions) {
+                for (value, delta) in position.0.iter_mut().zip(delta) {
+                    *value += delta * weight;
+                }
+            }
+            if let (Some(normals), Some(deltas)) = (&mut normals, &target.normals) {
+                for (normal, delta) in normals.iter_mut().zip(deltas) {
+                    for (value, delta) in normal.0.iter_mut().zip(delta) {
+                        *value += delta * weight;
+                    }
+                }
+            }
+        }
+
+        if let Some(normals) = &mut normals {
+            for normal in normals {
+                let length = normal.0.iter().map(|n| n * n).sum::<f32>().sqrt();
+                if length > 0.0 {
+                    normal.0.iter_mut().for_each(|n| *n /= length);
+                }
+            }
+        }
+        (positions, normals)
+    }
+}
+
+/// Weights of the morph targets of an entity, one per target.
+///
+/// The weights apply to the `MorphTargets` of the entity and of its children, like the
+/// primitives of a glTF mesh.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MorphWeights {
+    /// The weight of each target, usually between 0.0 and 1.0.
+    pub weights: Vec<f32>,
+}
+
+impl Component for MorphWeights {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl MorphWeights {
+    /// Creates morph weights with the given weights.
+    pub fn new(weights: Vec<f32>) -> Self {
+        MorphWeights { weights }
+    }
+}
+
+/// System blending the `MorphTargets` into new meshes when their `MorphWeights` change.
+///
+/// The mesh is rebuilt and uploaded again for every change of the weights, so it's meant for
+/// meshes of moderate size like faces.
+#[derive(Debug, SystemDesc)]
+#[system_desc(name(MorphTargetSystemDesc))]
+pub struct MorphTargetSystem {
+    #[system_desc(skip)]
+    updated: BitSet,
+    #[system_desc(flagged_storage_reader(MorphWeights))]
+    weights_id: ReaderId<ComponentEvent>,
+}
+
+impl MorphTargetSystem {
+    /// Creates a new `MorphTargetSystem`.
+    pub fn new(weights_id: ReaderId<ComponentEvent>) -> Self {
+        MorphTargetSystem {
+            updated: BitSet::default(),
+            weights_id,
+        }
+    }
+}
+
+impl<'a> System<'a> for MorphTargetSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, MorphWeights>,
+        ReadStorage<'a, MorphTargets>,
+        ReadStorage<'a, Parent>,
+        WriteStorage<'a, Handle<Mesh>>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Mesh>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, weights, targets, parents, mut meshes, loader, storage): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("morph_target_system");
+
+        self.updated.clear();
+        for event in weights.channel().read(&mut self.weights_id) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    self.updated.add(*id);
+                }
+                ComponentEvent::Removed(_) => {}
+            }
+        }
+
+        for (entity, targets) in (&entities, &targets).join() {
+            let source = if self.updated.contains(entity.id()) {
+                Some(entity)
+            } else {
+                parents
+                    .get(entity)
+                    .map(|parent| parent.entity)
+                    .filter(|parent| self.updated.contains(parent.id()))
+            };
+            if let Some(weights) = source.and_then(|source| weights.get(source)) {
+                let mesh =
+                    loader.load_from_data(targets.blend(&weights.weights).into(), (), &storage);
+                if let Err(err) = meshes.insert(entity, mesh) {
+                    log::error!("Failed to insert the morphed mesh: {}", err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_displaces_positions_and_normals() {
+        let targets = MorphTargets::new(
+            MeshBuilder::new(),
+            vec![Position([0.0, 0.0, 0.0]), Position([1.0, 0.0, 0.0])],
+            Some(vec![Normal([0.0, 1.0, 0.0]); 2]),
+            vec![
+                MorphTarget {
+                    positions: vec![[0.0, 2.0, 0.0], [0.0, 0.0, 0.0]],
+                    normals: Some(vec![[1.0, -1.0, 0.0], [0.0, 0.0, 0.0]]),
+                },
+                MorphTarget {
+                    positions: vec![[1.0, 0.0, 0.0]; 2],
+                    normals: None,
+                },
+            ],
+        );
+        let (positions, normals) = targets.displace(&[0.5, 1.0]);
+        assert_eq!(positions[0].0, [1.0, 1.0, 0.0]);
+        assert_eq!(positions[1].0, [2.0, 0.0, 0.0]);
+        let normals = normals.unwrap();
+        let half = 0.5f32.sqrt();
+        assert!((normals[0].0[0] - half).abs() < 1e-6);
+        assert!((normals[0].0[1] - half).abs() < 1e-6);
+        assert_eq!(normals[1].0, [0.0, 1.0, 0.0]);
+
+        let (positions, _) = targets.displace(&[]);
+        assert_eq!(positions[0].0, [0.0, 0.0, 0.0]);
+    }
+}
@@ -1,8 +1,12 @@
 //! Physically-based material.
 
-use crate::types::Texture;
-use amethyst_assets::{Asset, Handle};
-use amethyst_core::ecs::prelude::DenseVecStorage;
+use crate::{transparent::RenderQueue, types::Texture};
+use amethyst_assets::{Asset, Handle, PrefabData};
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage, Entity, WriteStorage},
+    math::Vector2,
+};
+use amethyst_error::Error;
 
 /// Material reference this part of the texture
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -41,6 +45,9 @@ pub struct Material {
     pub cavity: Handle<Texture>,
     /// Texture offset
     pub uv_offset: TextureOffset,
+    /// Queue the meshes using the material are drawn in, overriding their `Transparent`
+    /// component.
+    pub queue: Option<RenderQueue>,
 }
 
 impl Asset for Material {
@@ -57,6 +64,56 @@ impl Asset for Material {
 #[derive(Debug, Clone)]
 pub struct MaterialDefaults(pub Material);
 
+/// Per-entity overrides of the parameters of its `Material`, applied by the 3D passes.
+///
+/// Entities sharing a material keep sharing its instanced draw calls, so effects like damage
+/// flashes or scrolling conveyor belts don't need a copy of the material per entity. The
+/// overrides can be animated with `amethyst_animation`.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct MaterialOverrides {
+    /// Color multiplied with the material, on top of the `Tint` of the entity.
+    #[serde(with = "crate::serde_shim::srgba")]
+    pub tint: palette::Srgba,
+    /// Factor applied to the emission of the material.
+    pub emission: f32,
+    /// Offset added to the texture coordinates of the mesh. Coordinates outside of 0.0-1.0
+    /// wrap around according to the sampler of the textures.
+    pub uv_offset: Vector2<f32>,
+    /// Speed in texture coordinates per second at which the textures scroll, on top of
+    /// `uv_offset`.
+    pub uv_scroll: Vector2<f32>,
+}
+
+impl Default for MaterialOverrides {
+    fn default() -> Self {
+        MaterialOverrides {
+            tint: palette::Srgba::new(1.0, 1.0, 1.0, 1.0),
+            emission: 1.0,
+            uv_offset: Vector2::zeros(),
+            uv_scroll: Vector2::zeros(),
+        }
+    }
+}
+
+impl Component for MaterialOverrides {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl MaterialOverrides {
+    /// Texture coordinate offset at the given time in seconds. The scrolled distance wraps
+    /// around at whole textures, which keeps long running scrolls precise.
+    pub fn uv_offset_at(&self, time: f64) -> Vector2<f32> {
+        let wrap =
+            |offset: f32, scroll: f32| offset + (f64::from(scroll) * time).rem_euclid(1.0) as f32;
+        Vector2::new(
+            wrap(self.uv_offset.x, self.uv_scroll.x),
+            wrap(self.uv_offset.y, self.uv_scroll.y),
+        )
+    }
+}
+
 /// Trait providing generic access to a collection of texture handles
 pub trait StaticTextureSet<'a>:
     Clone + Copy + std::fmt::Debug + PartialEq + Eq + std::hash::Hash + Send + Sync + 'static
@@ -136,3 +193,22 @@ impl_texture_set_tuple!(A, B, C);
 impl_texture_set_tuple!(A, B, C, D);
 impl_texture_set_tuple!(A, B, C, D, E);
 impl_texture_set_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uv_offset_scrolls_and_wraps() {
+        let overrides = MaterialOverrides {
+            uv_offset: Vector2::new(0.25, 0.5),
+            uv_scroll: Vector2::new(0.5, -0.25),
+            ..Default::default()
+        };
+        assert_eq!(overrides.uv_offset_at(0.0), Vector2::new(0.25, 0.5));
+        assert_eq!(overrides.uv_offset_at(1.0), Vector2::new(0.75, 1.25));
+        assert_eq!(overrides.uv_offset_at(3.0), Vector2::new(0.75, 0.75));
+        // Long running scrolls stay precise.
+        assert_eq!(overrides.uv_offset_at(1_000_000.0), Vector2::new(0.25, 0.5));
+    }
+}
@@ -0,0 +1,94 @@
+//! Curves of values over the lifetime of a particle.
+
+use serde::{Deserialize, Serialize};
+
+/// Values which can be linearly interpolated.
+pub trait Lerp: Copy {
+    /// Interpolates between `self` at 0.0 and `other` at 1.0.
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 4] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        [
+            self[0].lerp(other[0], t),
+            self[1].lerp(other[1], t),
+            self[2].lerp(other[2], t),
+            self[3].lerp(other[3], t),
+        ]
+    }
+}
+
+/// Piecewise linear curve through keys of `(time, value)`, with the time going from 0.0 at the
+/// birth of a particle to 1.0 at its death.
+///
+/// The keys must be sorted by time. The curve is constant before the first and after the last
+/// key. In RON, a curve is written as a list of keys: `[(0.0, 1.0), (1.0, 0.0)]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+    /// Creates a curve through the given keys, which are sorted by time.
+    pub fn new(mut keys: Vec<(f32, T)>) -> Self {
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        Self { keys }
+    }
+
+    /// Creates a curve with the same value over the whole lifetime.
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    /// Returns the keys of the curve.
+    pub fn keys(&self) -> &[(f32, T)] {
+        &self.keys
+    }
+
+    /// Samples the curve at the given time, or returns `None` when it has no keys.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let next = self.keys.iter().position(|&(key, _)| key > time);
+        match next {
+            None => self.keys.last().map(|&(_, value)| value),
+            Some(0) => Some(self.keys[0].1),
+            Some(next) => {
+                let (start, from) = self.keys[next - 1];
+                let (end, to) = self.keys[next];
+                Some(from.lerp(to, (time - start) / (end - start)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_interpolates_between_keys() {
+        let curve = Curve::new(vec![(1.0, 0.0), (0.0, 1.0), (0.5, 1.0)]);
+        assert_eq!(curve.sample(-1.0), Some(1.0));
+        assert_eq!(curve.sample(0.25), Some(1.0));
+        assert_eq!(curve.sample(0.75), Some(0.5));
+        assert_eq!(curve.sample(2.0), Some(0.0));
+        assert_eq!(Curve::<f32>::new(vec![]).sample(0.5), None);
+    }
+
+    #[test]
+    fn deserialize_from_key_list() {
+        let curve: Curve<[f32; 4]> =
+            ron::de::from_str("[(0.0, (1.0, 1.0, 1.0, 1.0)), (1.0, (1.0, 0.0, 0.0, 0.0))]")
+                .unwrap();
+        assert_eq!(curve.sample(0.5), Some([1.0, 0.5, 0.5, 0.5]));
+    }
+}
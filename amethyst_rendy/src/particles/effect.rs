@@ -0,0 +1,192 @@
+//! Particle effect assets.
+
+use super::curve::Curve;
+use amethyst_assets::{Asset, Handle};
+use amethyst_core::{
+    ecs::prelude::DenseVecStorage,
+    math::{Point3, Vector3},
+};
+use serde::{Deserialize, Serialize};
+
+/// An asset handle to a particle effect.
+pub type ParticleEffectHandle = Handle<ParticleEffect>;
+
+/// Description of the particles spawned by a `ParticleEmitter`.
+///
+/// Effects are usually loaded from RON files with `RonFormat`:
+///
+/// ```ron
+/// (
+///     rate: 50.0,
+///     max_particles: 500,
+///     shape: Cone(angle: 0.3, radius: 0.1),
+///     lifetime: (1.0, 2.0),
+///     speed: (2.0, 3.0),
+///     acceleration: [0.0, -9.81, 0.0],
+///     color_over_life: [(0.0, (1.0, 0.8, 0.2, 1.0)), (1.0, (1.0, 0.1, 0.0, 0.0))],
+///     size_over_life: [(0.0, 0.1), (1.0, 0.4)],
+///     blend: Additive,
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleEffect {
+    /// Particles spawned per second.
+    pub rate: f32,
+    /// Particles spawned at once when the emitter starts playing.
+    pub burst: u32,
+    /// Maximum number of living particles of an emitter, no particles are spawned above it.
+    pub max_particles: usize,
+    /// Seconds the emitter spawns particles for after it starts playing, forever when `None`.
+    pub duration: Option<f32>,
+    /// Volume in which particles are spawned and their initial direction.
+    pub shape: EmitterShape,
+    /// Minimum and maximum lifetime of the particles in seconds.
+    pub lifetime: (f32, f32),
+    /// Minimum and maximum initial speed of the particles along their direction.
+    pub speed: (f32, f32),
+    /// Acceleration applied to the particles, e.g. gravity.
+    pub acceleration: Vector3<f32>,
+    /// Multiplier of the velocity over the lifetime of the particles, e.g. for drag.
+    pub velocity_over_life: Curve<f32>,
+    /// Linear RGBA color over the lifetime of the particles.
+    pub color_over_life: Curve<[f32; 4]>,
+    /// Size of the particles in world units over their lifetime.
+    pub size_over_life: Curve<f32>,
+    /// How the particles are blended with what's behind them.
+    pub blend: ParticleBlend,
+}
+
+impl Default for ParticleEffect {
+    fn default() -> Self {
+        Self {
+            rate: 10.0,
+            burst: 0,
+            max_particles: 1000,
+            duration: None,
+            shape: EmitterShape::Point,
+            lifetime: (1.0, 1.0),
+            speed: (1.0, 1.0),
+            acceleration: Vector3::zeros(),
+            velocity_over_life: Curve::constant(1.0),
+            color_over_life: Curve::constant([1.0; 4]),
+            size_over_life: Curve::constant(0.1),
+            blend: ParticleBlend::Alpha,
+        }
+    }
+}
+
+impl Asset for ParticleEffect {
+    const NAME: &'static str = "renderer::ParticleEffect";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<Handle<Self>>;
+}
+
+/// Volume in which particles are spawned, in the local space of the emitter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EmitterShape {
+    /// Particles spawn at the origin, moving in random directions.
+    Point,
+    /// Particles spawn inside a sphere, moving away from its center.
+    Sphere {
+        /// Radius of the sphere.
+        radius: f32,
+    },
+    /// Particles spawn inside a box, moving along the Y axis.
+    Box {
+        /// Half of the size of the box along each axis.
+        half_extents: Vector3<f32>,
+    },
+    /// Particles spawn on a disk in the XZ plane, moving along the Y axis within a cone.
+    Cone {
+        /// Angle between the Y axis and the side of the cone in radians.
+        angle: f32,
+        /// Radius of the disk.
+        radius: f32,
+    },
+}
+
+impl EmitterShape {
+    /// Returns a position and a direction of unit length for a new particle, using `random`
+    /// to generate numbers between 0.0 and 1.0.
+    pub(crate) fn spawn(&self, mut random: impl FnMut() -> f32) -> (Point3<f32>, Vector3<f32>) {
+        match *self {
+            EmitterShape::Point => (Point3::origin(), random_direction(&mut random)),
+            EmitterShape::Sphere { radius } => {
+                let direction = random_direction(&mut random);
+                (
+                    Point3::from(direction * radius * random().cbrt()),
+                    direction,
+                )
+            }
+            EmitterShape::Box { half_extents } => {
+                let mut coordinate = |extent: f32| (random() * 2.0 - 1.0) * extent;
+                (
+                    Point3::new(
+                        coordinate(half_extents.x),
+                        coordinate(half_extents.y),
+                        coordinate(half_extents.z),
+                    ),
+                    Vector3::y(),
+                )
+            }
+            EmitterShape::Cone { angle, radius } => {
+                let (sin, cos) = (random() * std::f32::consts::PI * 2.0).sin_cos();
+                let distance = random().sqrt();
+                let spread = angle.tan() * distance;
+                (
+                    Point3::new(cos * radius * distance, 0.0, sin * radius * distance),
+                    Vector3::new(cos * spread, 1.0, sin * spread).normalize(),
+                )
+            }
+        }
+    }
+}
+
+/// Returns a uniformly distributed direction of unit length.
+fn random_direction(random: &mut impl FnMut() -> f32) -> Vector3<f32> {
+    let z = random() * 2.0 - 1.0;
+    let (sin, cos) = (random() * std::f32::consts::PI * 2.0).sin_cos();
+    let r = (1.0 - z * z).sqrt();
+    Vector3::new(r * cos, r * sin, z)
+}
+
+/// How particles are blended with what's behind them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleBlend {
+    /// Particles are blended by their alpha and drawn from back to front.
+    Alpha,
+    /// Particles add their color weighted by their alpha, e.g. for fire and sparks.
+    Additive,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_effect() {
+        let effect: ParticleEffect = ron::de::from_str(
+            "(
+                rate: 50.0,
+                shape: Cone(angle: 0.3, radius: 0.1),
+                lifetime: (1.0, 2.0),
+                acceleration: [0.0, -9.81, 0.0],
+                size_over_life: [(0.0, 0.1), (1.0, 0.4)],
+                blend: Additive,
+            )",
+        )
+        .unwrap();
+        assert_eq!(effect.rate, 50.0);
+        assert_eq!(
+            effect.shape,
+            EmitterShape::Cone {
+                angle: 0.3,
+                radius: 0.1
+            }
+        );
+        assert_eq!(effect.size_over_life.sample(0.5), Some(0.25));
+        assert_eq!(effect.max_particles, 1000);
+        assert_eq!(effect.blend, ParticleBlend::Additive);
+    }
+}
@@ -0,0 +1,244 @@
+//! Particle emitter component and the simulated particles.
+
+use super::effect::{ParticleEffect, ParticleEffectHandle};
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::{Point3, Vector3},
+};
+
+/// A particle simulated by a `ParticleEmitter`, in world space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Particle {
+    /// Position of the particle.
+    pub position: Point3<f32>,
+    /// Velocity of the particle without the `velocity_over_life` multiplier.
+    pub velocity: Vector3<f32>,
+    /// Seconds since the particle was spawned.
+    pub age: f32,
+    /// Seconds the particle lives for.
+    pub lifetime: f32,
+    /// Linear RGBA color of the particle.
+    pub color: [f32; 4],
+    /// Size of the particle in world units.
+    pub size: f32,
+}
+
+impl Particle {
+    /// Returns the fraction of the lifetime of the particle which has passed, between 0.0 and 1.0.
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/// Component spawning the particles of a `ParticleEffect` at the `Transform` of its entity.
+///
+/// The particles are simulated on the CPU by the `ParticleSystem` and drawn by the
+/// `RenderParticles` plugin.
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    effect: ParticleEffectHandle,
+    playing: bool,
+    pub(crate) elapsed: f32,
+    pub(crate) spawn_debt: f32,
+    pub(crate) started: bool,
+    pub(crate) seed: u32,
+    pub(crate) particles: Vec<Particle>,
+}
+
+impl Component for ParticleEmitter {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl ParticleEmitter {
+    /// Creates an emitter of the given effect, which starts playing immediately.
+    pub fn new(effect: ParticleEffectHandle) -> Self {
+        Self {
+            effect,
+            playing: true,
+            elapsed: 0.0,
+            spawn_debt: 0.0,
+            started: false,
+            seed: 0x9E37_79B9,
+            particles: Vec::new(),
+        }
+    }
+
+    /// Seeds the random numbers of the emitter, so emitters of the same effect differ.
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        // Xorshift gets stuck at zero
+        self.seed = seed.max(1);
+        self
+    }
+
+    /// Returns the handle to the effect of the emitter.
+    pub fn effect(&self) -> &ParticleEffectHandle {
+        &self.effect
+    }
+
+    /// Returns whether the emitter spawns new particles.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Starts spawning particles again from the beginning of the effect, including its burst.
+    /// Living particles are kept.
+    pub fn play(&mut self) {
+        self.playing = true;
+        self.started = false;
+        self.elapsed = 0.0;
+        self.spawn_debt = 0.0;
+    }
+
+    /// Stops spawning new particles. Living particles keep being simulated until they die.
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    /// Removes all living particles.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Returns the living particles.
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Advances the simulation by `delta_seconds`, spawning new particles at the given position
+    /// and with the given rotation applied to the emitter shape.
+    pub(crate) fn update(
+        &mut self,
+        effect: &ParticleEffect,
+        delta_seconds: f32,
+        spawn: impl Fn(Point3<f32>, Vector3<f32>) -> (Point3<f32>, Vector3<f32>),
+    ) {
+        for particle in &mut self.particles {
+            particle.age += delta_seconds;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        for particle in &mut self.particles {
+            let life = particle.life();
+            particle.velocity += effect.acceleration * delta_seconds;
+            let multiplier = effect.velocity_over_life.sample(life).unwrap_or(1.0);
+            particle.position += particle.velocity * multiplier * delta_seconds;
+        }
+
+        if self.playing {
+            let mut count = 0;
+            if !self.started {
+                self.started = true;
+                count += effect.burst as usize;
+            }
+            if effect
+                .duration
+                .map_or(true, |duration| self.elapsed < duration)
+            {
+                self.spawn_debt += effect.rate * delta_seconds;
+                count += self.spawn_debt as usize;
+                self.spawn_debt = self.spawn_debt.fract();
+            } else {
+                self.playing = false;
+            }
+            self.elapsed += delta_seconds;
+
+            let count = count.min(effect.max_particles.saturating_sub(self.particles.len()));
+            for _ in 0..count {
+                let particle = self.spawn_particle(effect, &spawn);
+                self.particles.push(particle);
+            }
+        }
+
+        for particle in &mut self.particles {
+            let life = particle.life();
+            if let Some(color) = effect.color_over_life.sample(life) {
+                particle.color = color;
+            }
+            if let Some(size) = effect.size_over_life.sample(life) {
+                particle.size = size;
+            }
+        }
+    }
+
+    fn spawn_particle(
+        &mut self,
+        effect: &ParticleEffect,
+        spawn: &impl Fn(Point3<f32>, Vector3<f32>) -> (Point3<f32>, Vector3<f32>),
+    ) -> Particle {
+        let (position, direction) = effect.shape.spawn(|| self.random());
+        let (position, direction) = spawn(position, direction);
+        let speed = self.random_between(effect.speed);
+        Particle {
+            position,
+            velocity: direction * speed,
+            age: 0.0,
+            lifetime: self.random_between(effect.lifetime).max(std::f32::EPSILON),
+            color: [1.0; 4],
+            size: 0.0,
+        }
+    }
+
+    fn random_between(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.random()
+    }
+
+    /// Returns a random number between 0.0 and 1.0 from a xorshift generator.
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particles::{Curve, EmitterShape};
+    use amethyst_assets::AssetStorage;
+
+    fn emitter() -> ParticleEmitter {
+        let mut storage = AssetStorage::<ParticleEffect>::new();
+        ParticleEmitter::new(storage.insert(ParticleEffect::default()))
+    }
+
+    #[test]
+    fn spawns_at_rate_with_burst() {
+        let effect = ParticleEffect {
+            rate: 10.0,
+            burst: 5,
+            max_particles: 8,
+            lifetime: (10.0, 10.0),
+            ..Default::default()
+        };
+        let mut emitter = emitter();
+        emitter.update(&effect, 0.25, |position, direction| (position, direction));
+        assert_eq!(emitter.particles().len(), 7);
+        emitter.update(&effect, 0.25, |position, direction| (position, direction));
+        assert_eq!(emitter.particles().len(), 8);
+    }
+
+    #[test]
+    fn particles_move_and_die() {
+        let effect = ParticleEffect {
+            rate: 0.0,
+            burst: 1,
+            lifetime: (1.0, 1.0),
+            speed: (2.0, 2.0),
+            shape: EmitterShape::Box {
+                half_extents: Vector3::zeros(),
+            },
+            size_over_life: Curve::new(vec![(0.0, 0.0), (1.0, 1.0)]),
+            ..Default::default()
+        };
+        let mut emitter = emitter();
+        emitter.update(&effect, 0.0, |position, direction| (position, direction));
+        emitter.update(&effect, 0.5, |position, direction| (position, direction));
+        let particle = &emitter.particles()[0];
+        assert_eq!(particle.position, Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(particle.size, 0.5);
+        emitter.update(&effect, 0.5, |position, direction| (position, direction));
+        assert!(emitter.particles().is_empty());
+    }
+}
@@ -0,0 +1,17 @@
+//! Particle effects simulated on the CPU and drawn as camera-facing billboards.
+//!
+//! A `ParticleEmitter` component spawns the particles described by a `ParticleEffect` asset,
+//! which is usually loaded from a RON file. The `RenderParticles` plugin adds the
+//! `ParticleSystem` simulating the emitters and the pass drawing them.
+
+mod curve;
+mod effect;
+mod emitter;
+mod system;
+
+pub use self::{
+    curve::{Curve, Lerp},
+    effect::{EmitterShape, ParticleBlend, ParticleEffect, ParticleEffectHandle},
+    emitter::{Particle, ParticleEmitter},
+    system::ParticleSystem,
+};
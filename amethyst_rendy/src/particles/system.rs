@@ -0,0 +1,57 @@
+//! Simulation of the particle emitters.
+
+use super::{effect::ParticleEffect, emitter::ParticleEmitter};
+use amethyst_assets::AssetStorage;
+use amethyst_core::{
+    ecs::prelude::{Join, Read, ReadStorage, System, WriteStorage},
+    math::{convert, Matrix4},
+    timing::Time,
+    Transform,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Simulates the particles of every `ParticleEmitter` on the CPU, spawning new particles at the
+/// global `Transform` of the emitter. Emitters without a `Transform` spawn at the origin.
+///
+/// Added by the `RenderParticles` plugin.
+#[derive(Debug, Default)]
+pub struct ParticleSystem;
+
+impl ParticleSystem {
+    /// Returns a new particle system
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, AssetStorage<ParticleEffect>>,
+        ReadStorage<'a, Transform>,
+        WriteStorage<'a, ParticleEmitter>,
+    );
+
+    fn run(&mut self, (time, effects, transforms, mut emitters): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("particle_system");
+
+        for (emitter, transform) in (&mut emitters, transforms.maybe()).join() {
+            let effect = match effects.get(emitter.effect()) {
+                Some(effect) => effect,
+                None => continue,
+            };
+            let matrix = transform
+                .map(|transform| convert::<_, Matrix4<f32>>(*transform.global_matrix()))
+                .unwrap_or_else(Matrix4::identity);
+            emitter.update(effect, time.delta_seconds(), |position, direction| {
+                (
+                    matrix.transform_point(&position),
+                    matrix.transform_vector(&direction).normalize(),
+                )
+            });
+        }
+    }
+}
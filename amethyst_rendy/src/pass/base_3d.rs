@@ -1,19 +1,25 @@
 use crate::{
     batch::{GroupIterator, OrderedTwoLevelBatch, TwoLevelBatch},
-    mtl::{FullTextureSet, Material, StaticTextureSet},
+    debug_mode::DebugRenderMode,
+    mtl::{FullTextureSet, Material, MaterialOverrides, StaticTextureSet},
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::{SkinnedVertexArgs, VertexArgs},
     resources::Tint,
     skinning::JointTransforms,
-    submodules::{DynamicVertexBuffer, EnvironmentSub, MaterialId, MaterialSub, SkinningSub},
+    stats::{DrawCounts, RenderStats},
+    submodules::{
+        DynamicVertexBuffer, EnvironmentImage, EnvironmentMapSub, EnvironmentSub, MaterialId,
+        MaterialSub, ShadowSub, SkinningSub,
+    },
     transparent::Transparent,
     types::{Backend, Mesh},
     util,
-    visibility::Visibility,
+    visibility::{CameraVisibility, Visibility},
 };
 use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
-    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    ecs::{Entity, Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    timing::Time,
     transform::Transform,
     Hidden, HiddenPropagate,
 };
@@ -23,9 +29,9 @@ use rendy::{
     factory::Factory,
     graph::{
         render::{PrepareResult, RenderGroup, RenderGroupDesc},
-        GraphContext, NodeBuffer, NodeImage,
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
     },
-    hal::{self, device::Device, pso},
+    hal::{self, adapter::PhysicalDevice, device::Device, pso},
     mesh::{AsVertex, VertexFormat},
     shader::{Shader, SpirvShader},
 };
@@ -61,6 +67,24 @@ pub trait Base3DPassDef: 'static + std::fmt::Debug + Send + Sync {
     /// Returns the fragment `SpirvShader` which will be used for this pass
     fn fragment_shader() -> &'static SpirvShader;
 
+    /// Returns the fragment `SpirvShader` sampling the shadow map, which will be used for this
+    /// pass when shadows are enabled. Passes without one don't support shadows.
+    fn shadowed_fragment_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the fragment `SpirvShader` visualizing the `DebugRenderMode`s of the fragment
+    /// shader, selected by a push constant. Passes without one draw these modes normally.
+    fn debug_fragment_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns whether the fragment shaders of this pass light meshes with the environment map
+    /// of the `Skybox` resource.
+    fn image_based_lighting() -> bool {
+        false
+    }
+
     /// Returns the `VertexFormat` of this pass
     fn base_format() -> Vec<VertexFormat>;
 
@@ -73,6 +97,10 @@ pub trait Base3DPassDef: 'static + std::fmt::Debug + Send + Sync {
 #[derivative(Debug(bound = ""), Default(bound = ""))]
 pub struct DrawBase3DDesc<B: Backend, T: Base3DPassDef> {
     skinning: bool,
+    shadows: bool,
+    camera: Option<Entity>,
+    samples: hal::image::NumSamples,
+    debug_mode: DebugRenderMode,
     marker: PhantomData<(B, T)>,
 }
 
@@ -86,6 +114,10 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DDesc<B, T> {
     pub fn skinned() -> Self {
         Self {
             skinning: true,
+            shadows: false,
+            camera: None,
+            samples: 1,
+            debug_mode: DebugRenderMode::Off,
             marker: PhantomData,
         }
     }
@@ -95,32 +127,66 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DDesc<B, T> {
         self.skinning = skinned;
         self
     }
+
+    /// Create pass sampling the shadow map if true is passed. The shadow map has to be added
+    /// as the only image of the group.
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Create pass drawing the view of the given camera entity instead of the `ActiveCamera`,
+    /// e.g. a camera rendering into a `RenderTexture`.
+    pub fn with_camera(mut self, camera: Option<Entity>) -> Self {
+        self.camera = camera;
+        self
+    }
+
+    /// Create pass drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Create pass drawing the meshes in the given `DebugRenderMode`.
+    pub fn with_debug_mode(mut self, debug_mode: DebugRenderMode) -> Self {
+        self.debug_mode = debug_mode;
+        self
+    }
 }
 
 impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<B, T> {
+    fn images(&self) -> Vec<ImageAccess> {
+        shadow_map_access(self.shadows)
+    }
+
     fn build(
         self,
-        _ctx: &GraphContext<B>,
+        ctx: &GraphContext<B>,
         factory: &mut Factory<B>,
-        _queue: QueueId,
+        queue: QueueId,
         _aux: &World,
         framebuffer_width: u32,
         framebuffer_height: u32,
         subpass: hal::pass::Subpass<'_, B>,
         _buffers: Vec<NodeBuffer>,
-        _images: Vec<NodeImage>,
+        images: Vec<NodeImage>,
     ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
         profile_scope_impl!("build");
 
-        let env = EnvironmentSub::new(
+        let mut env = EnvironmentSub::new(
             factory,
             [
                 hal::pso::ShaderStageFlags::VERTEX,
                 hal::pso::ShaderStageFlags::FRAGMENT,
             ],
         )?;
+        env.set_camera(self.camera);
         let materials = MaterialSub::new(factory)?;
         let skinning = SkinningSub::new(factory)?;
+        let (shadows, fragment_shader) =
+            build_shadows::<B, T>(ctx, factory, self.shadows, &images)?;
+        let environment_map = build_environment_map::<B, T>(factory, queue)?;
 
         let mut vertex_format_base = T::base_format();
         let mut vertex_format_skinned = T::skinned_format();
@@ -132,13 +198,20 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
             framebuffer_height,
             &vertex_format_base,
             &vertex_format_skinned,
+            fragment_shader,
             self.skinning,
             false,
-            vec![
-                env.raw_layout(),
-                materials.raw_layout(),
-                skinning.raw_layout(),
-            ],
+            self.samples,
+            self.debug_mode,
+            lighting_layouts(
+                vec![
+                    env.raw_layout(),
+                    materials.raw_layout(),
+                    skinning.raw_layout(),
+                ],
+                &shadows,
+                &environment_map,
+            ),
         )?;
 
         vertex_format_base.sort();
@@ -155,8 +228,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
             env,
             materials,
             skinning,
+            shadows,
+            environment_map,
+            camera: self.camera,
+            debug_mode: shader_debug_mode::<T>(self.debug_mode),
             models: DynamicVertexBuffer::new(),
             skinned_models: DynamicVertexBuffer::new(),
+            draws: DrawCounts::default(),
             marker: PhantomData,
         }))
     }
@@ -177,8 +255,13 @@ pub struct DrawBase3D<B: Backend, T: Base3DPassDef> {
     env: EnvironmentSub<B>,
     materials: MaterialSub<B, T::TextureSet>,
     skinning: SkinningSub<B>,
+    shadows: Option<ShadowSub<B>>,
+    environment_map: Option<EnvironmentMapSub<B>>,
+    camera: Option<Entity>,
+    debug_mode: Option<u32>,
     models: DynamicVertexBuffer<B, VertexArgs>,
     skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    draws: DrawCounts,
     marker: PhantomData<T>,
 }
 
@@ -196,6 +279,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
         let (
             mesh_storage,
             visibility,
+            camera_visibility,
             transparent,
             hiddens,
             hiddens_prop,
@@ -204,9 +288,12 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             transforms,
             joints,
             tints,
+            overrides,
+            time,
         ) = <(
             Read<'_, AssetStorage<Mesh>>,
             ReadExpect<'_, Visibility>,
+            Read<'_, CameraVisibility>,
             ReadStorage<'_, Transparent>,
             ReadStorage<'_, Hidden>,
             ReadStorage<'_, HiddenPropagate>,
@@ -215,10 +302,21 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             ReadStorage<'_, Transform>,
             ReadStorage<'_, JointTransforms>,
             ReadStorage<'_, Tint>,
+            ReadStorage<'_, MaterialOverrides>,
+            Read<'_, Time>,
         )>::fetch(resources);
+        let no_visibility = Visibility::default();
+        let visibility = camera_visibility_or(&visibility, &camera_visibility, self.camera)
+            .unwrap_or(&no_visibility);
 
         // Prepare environment
         self.env.process(factory, index, resources);
+        if let Some(shadows) = self.shadows.as_mut() {
+            shadows.process(factory, index, resources);
+        }
+        if let Some(environment_map) = self.environment_map.as_mut() {
+            environment_map.process(factory, index, resources);
+        }
         self.materials.maintain();
 
         self.static_batches.clear_inner();
@@ -229,14 +327,38 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
         let statics_ref = &mut self.static_batches;
         let skinned_ref = &mut self.skinned_batches;
 
-        let static_input = || ((&materials, &meshes, &transforms, tints.maybe()), !&joints);
-        let skinned_input = || (&materials, &meshes, &transforms, tints.maybe(), &joints);
+        let time = time.absolute_time_seconds();
+        let static_input = || {
+            (
+                (
+                    &materials,
+                    &meshes,
+                    &transforms,
+                    tints.maybe(),
+                    overrides.maybe(),
+                ),
+                !&joints,
+            )
+        };
+        let skinned_input = || {
+            (
+                (
+                    &materials,
+                    &meshes,
+                    &transforms,
+                    tints.maybe(),
+                    overrides.maybe(),
+                ),
+                &joints,
+            )
+        };
         {
             profile_scope_impl!("prepare");
             (static_input(), &visibility.visible_unordered)
                 .join()
-                .map(|(((mat, mesh, tform, tint), _), _)| {
-                    ((mat, mesh.id()), VertexArgs::from_object_data(tform, tint))
+                .map(|(((mat, mesh, tform, tint, overrides), _), _)| {
+                    let args = VertexArgs::from_object_data(tform, tint);
+                    ((mat, mesh.id()), args.with_overrides(overrides, time))
                 })
                 .for_each_group(|(mat, mesh_id), data| {
                     if mesh_storage.contains_id(mesh_id) {
@@ -251,15 +373,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
 
             (skinned_input(), &visibility.visible_unordered)
                 .join()
-                .map(|((mat, mesh, tform, tint, joints), _)| {
-                    (
-                        (mat, mesh.id()),
-                        SkinnedVertexArgs::from_object_data(
-                            tform,
-                            tint,
-                            skinning_ref.insert(joints),
-                        ),
-                    )
+                .map(|(((mat, mesh, tform, tint, overrides), joints), _)| {
+                    let args = SkinnedVertexArgs::from_object_data(
+                        tform,
+                        tint,
+                        skinning_ref.insert(joints),
+                    );
+                    ((mat, mesh.id()), args.with_overrides(overrides, time))
                 })
                 .for_each_group(|(mat, mesh_id), data| {
                     if mesh_storage.contains_id(mesh_id) {
@@ -303,12 +423,22 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
     ) {
         profile_scope_impl!("draw opaque");
 
-        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
+        let (mesh_storage, stats) =
+            <(Read<'_, AssetStorage<Mesh>>, Read<'_, RenderStats>)>::fetch(resources);
+        self.draws.clear();
         let models_loc = self.vertex_format_base.len() as u32;
         let skin_models_loc = self.vertex_format_skinned.len() as u32;
 
         encoder.bind_graphics_pipeline(&self.pipeline_basic);
+        push_debug_mode(self.debug_mode, &self.pipeline_layout, &mut encoder);
         self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+        if let Some(shadows) = self.shadows.as_ref() {
+            shadows.bind(index, &self.pipeline_layout, 3, &mut encoder);
+        }
+        if let Some(environment_map) = self.environment_map.as_ref() {
+            let set_id = environment_map_set(&self.shadows);
+            environment_map.bind(index, &self.pipeline_layout, set_id, &mut encoder);
+        }
 
         if self.models.bind(index, models_loc, 0, &mut encoder) {
             let mut instances_drawn = 0;
@@ -328,6 +458,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
                                 &mut encoder,
                             )
                             .unwrap();
+                            self.draws.record_mesh_draw(mesh, batch_data.len() as u32);
                         }
                         instances_drawn += batch_data.len() as u32;
                     }
@@ -362,6 +493,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
                                     &mut encoder,
                                 )
                                 .unwrap();
+                                self.draws.record_mesh_draw(mesh, batch_data.len() as u32);
                             }
                             instances_drawn += batch_data.len() as u32;
                         }
@@ -369,6 +501,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
                 }
             }
         }
+        stats.report_draws(&self.draws);
     }
 
     fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
@@ -392,6 +525,10 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
 #[derivative(Debug(bound = ""), Default(bound = ""))]
 pub struct DrawBase3DTransparentDesc<B: Backend, T: Base3DPassDef> {
     skinning: bool,
+    shadows: bool,
+    camera: Option<Entity>,
+    samples: hal::image::NumSamples,
+    debug_mode: DebugRenderMode,
     marker: PhantomData<(B, T)>,
 }
 
@@ -400,6 +537,10 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DTransparentDesc<B, T> {
     pub fn new() -> Self {
         Self {
             skinning: false,
+            shadows: false,
+            camera: None,
+            samples: 1,
+            debug_mode: DebugRenderMode::Off,
             marker: PhantomData,
         }
     }
@@ -408,6 +549,10 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DTransparentDesc<B, T> {
     pub fn skinned() -> Self {
         Self {
             skinning: true,
+            shadows: false,
+            camera: None,
+            samples: 1,
+            debug_mode: DebugRenderMode::Off,
             marker: PhantomData,
         }
     }
@@ -417,31 +562,65 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DTransparentDesc<B, T> {
         self.skinning = skinned;
         self
     }
+
+    /// Create pass sampling the shadow map if true is passed. The shadow map has to be added
+    /// as the only image of the group.
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Create pass drawing the view of the given camera entity instead of the `ActiveCamera`,
+    /// e.g. a camera rendering into a `RenderTexture`.
+    pub fn with_camera(mut self, camera: Option<Entity>) -> Self {
+        self.camera = camera;
+        self
+    }
+
+    /// Create pass drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Create pass drawing the meshes in the given `DebugRenderMode`.
+    pub fn with_debug_mode(mut self, debug_mode: DebugRenderMode) -> Self {
+        self.debug_mode = debug_mode;
+        self
+    }
 }
 
 impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTransparentDesc<B, T> {
+    fn images(&self) -> Vec<ImageAccess> {
+        shadow_map_access(self.shadows)
+    }
+
     fn build(
         self,
-        _ctx: &GraphContext<B>,
+        ctx: &GraphContext<B>,
         factory: &mut Factory<B>,
-        _queue: QueueId,
+        queue: QueueId,
         _aux: &World,
         framebuffer_width: u32,
         framebuffer_height: u32,
         subpass: hal::pass::Subpass<'_, B>,
         _buffers: Vec<NodeBuffer>,
-        _images: Vec<NodeImage>,
+        images: Vec<NodeImage>,
     ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
-        let env = EnvironmentSub::new(
+        let mut env = EnvironmentSub::new(
             factory,
             [
                 hal::pso::ShaderStageFlags::VERTEX,
                 hal::pso::ShaderStageFlags::FRAGMENT,
             ],
         )?;
+        env.set_camera(self.camera);
 
         let materials = MaterialSub::new(factory)?;
         let skinning = SkinningSub::new(factory)?;
+        let (shadows, fragment_shader) =
+            build_shadows::<B, T>(ctx, factory, self.shadows, &images)?;
+        let environment_map = build_environment_map::<B, T>(factory, queue)?;
 
         let mut vertex_format_base = T::base_format();
         let mut vertex_format_skinned = T::skinned_format();
@@ -453,13 +632,20 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
             framebuffer_height,
             &vertex_format_base,
             &vertex_format_skinned,
+            fragment_shader,
             self.skinning,
             true,
-            vec![
-                env.raw_layout(),
-                materials.raw_layout(),
-                skinning.raw_layout(),
-            ],
+            self.samples,
+            self.debug_mode,
+            lighting_layouts(
+                vec![
+                    env.raw_layout(),
+                    materials.raw_layout(),
+                    skinning.raw_layout(),
+                ],
+                &shadows,
+                &environment_map,
+            ),
         )?;
 
         vertex_format_base.sort();
@@ -476,8 +662,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
             env,
             materials,
             skinning,
+            shadows,
+            environment_map,
+            camera: self.camera,
+            debug_mode: shader_debug_mode::<T>(self.debug_mode),
             models: DynamicVertexBuffer::new(),
             skinned_models: DynamicVertexBuffer::new(),
+            draws: DrawCounts::default(),
             change: Default::default(),
             marker: PhantomData,
         }))
@@ -498,8 +689,13 @@ pub struct DrawBase3DTransparent<B: Backend, T: Base3DPassDef> {
     env: EnvironmentSub<B>,
     materials: MaterialSub<B, FullTextureSet>,
     skinning: SkinningSub<B>,
+    shadows: Option<ShadowSub<B>>,
+    environment_map: Option<EnvironmentMapSub<B>>,
+    camera: Option<Entity>,
+    debug_mode: Option<u32>,
     models: DynamicVertexBuffer<B, VertexArgs>,
     skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    draws: DrawCounts,
     change: util::ChangeDetection,
     marker: PhantomData<T>,
 }
@@ -515,19 +711,47 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
     ) -> PrepareResult {
         profile_scope_impl!("prepare transparent");
 
-        let (mesh_storage, visibility, meshes, materials, transforms, joints, tints) =
-            <(
-                Read<'_, AssetStorage<Mesh>>,
-                ReadExpect<'_, Visibility>,
-                ReadStorage<'_, Handle<Mesh>>,
-                ReadStorage<'_, Handle<Material>>,
-                ReadStorage<'_, Transform>,
-                ReadStorage<'_, JointTransforms>,
-                ReadStorage<'_, Tint>,
-            )>::fetch(resources);
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(resources).report_draws(&self.draws);
+
+        let (
+            mesh_storage,
+            visibility,
+            camera_visibility,
+            meshes,
+            materials,
+            transforms,
+            joints,
+            tints,
+            overrides,
+            time,
+        ) = <(
+            Read<'_, AssetStorage<Mesh>>,
+            ReadExpect<'_, Visibility>,
+            Read<'_, CameraVisibility>,
+            ReadStorage<'_, Handle<Mesh>>,
+            ReadStorage<'_, Handle<Material>>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, JointTransforms>,
+            ReadStorage<'_, Tint>,
+            ReadStorage<'_, MaterialOverrides>,
+            Read<'_, Time>,
+        )>::fetch(resources);
+        let no_visibility = Visibility::default();
+        let visibility = camera_visibility_or(&visibility, &camera_visibility, self.camera)
+            .unwrap_or(&no_visibility);
 
         // Prepare environment
         self.env.process(factory, index, resources);
+        if let Some(shadows) = self.shadows.as_mut() {
+            shadows.process(factory, index, resources);
+        }
+        let environment_changed = self
+            .environment_map
+            .as_mut()
+            .map_or(false, |environment_map| {
+                environment_map.process(factory, index, resources)
+            });
         self.materials.maintain();
 
         self.static_batches.swap_clear();
@@ -537,15 +761,27 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
         let skinning_ref = &mut self.skinning;
         let statics_ref = &mut self.static_batches;
         let skinned_ref = &mut self.skinned_batches;
-        let mut changed = false;
-
-        let mut joined = ((&materials, &meshes, &transforms, tints.maybe()), !&joints).join();
+        let mut changed = environment_changed;
+
+        let time = time.absolute_time_seconds();
+        let mut joined = (
+            (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                overrides.maybe(),
+            ),
+            !&joints,
+        )
+            .join();
         visibility
             .visible_ordered
             .iter()
             .filter_map(|e| joined.get_unchecked(e.id()))
-            .map(|((mat, mesh, tform, tint), _)| {
-                ((mat, mesh.id()), VertexArgs::from_object_data(tform, tint))
+            .map(|((mat, mesh, tform, tint, overrides), _)| {
+                let args = VertexArgs::from_object_data(tform, tint);
+                ((mat, mesh.id()), args.with_overrides(overrides, time))
             })
             .for_each_group(|(mat, mesh_id), data| {
                 if mesh_storage.contains_id(mesh_id) {
@@ -558,21 +794,29 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
             });
 
         if self.pipeline_skinned.is_some() {
-            let mut joined = (&materials, &meshes, &transforms, tints.maybe(), &joints).join();
+            let mut joined = (
+                (
+                    &materials,
+                    &meshes,
+                    &transforms,
+                    tints.maybe(),
+                    overrides.maybe(),
+                ),
+                &joints,
+            )
+                .join();
 
             visibility
                 .visible_ordered
                 .iter()
                 .filter_map(|e| joined.get_unchecked(e.id()))
-                .map(|(mat, mesh, tform, tint, joints)| {
-                    (
-                        (mat, mesh.id()),
-                        SkinnedVertexArgs::from_object_data(
-                            tform,
-                            tint,
-                            skinning_ref.insert(joints),
-                        ),
-                    )
+                .map(|((mat, mesh, tform, tint, overrides), joints)| {
+                    let args = SkinnedVertexArgs::from_object_data(
+                        tform,
+                        tint,
+                        skinning_ref.insert(joints),
+                    );
+                    ((mat, mesh.id()), args.with_overrides(overrides, time))
                 })
                 .for_each_group(|(mat, mesh_id), data| {
                     if mesh_storage.contains_id(mesh_id) {
@@ -617,7 +861,9 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
     ) {
         profile_scope_impl!("draw transparent");
 
-        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
+        let (mesh_storage, stats) =
+            <(Read<'_, AssetStorage<Mesh>>, Read<'_, RenderStats>)>::fetch(resources);
+        self.draws.clear();
         let layout = &self.pipeline_layout;
         let encoder = &mut encoder;
 
@@ -625,7 +871,14 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
         let skin_models_loc = self.vertex_format_skinned.len() as u32;
 
         encoder.bind_graphics_pipeline(&self.pipeline_basic);
+        push_debug_mode(self.debug_mode, layout, encoder);
         self.env.bind(index, layout, 0, encoder);
+        if let Some(shadows) = self.shadows.as_ref() {
+            shadows.bind(index, layout, 3, encoder);
+        }
+        if let Some(environment_map) = self.environment_map.as_ref() {
+            environment_map.bind(index, layout, environment_map_set(&self.shadows), encoder);
+        }
 
         if self.models.bind(index, models_loc, 0, encoder) {
             for (&mat, batches) in self.static_batches.iter() {
@@ -648,6 +901,8 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
                                     T::NAME,
                                     T::base_format(),
                                 );
+                            } else {
+                                self.draws.record_mesh_draw(mesh, range.end - range.start);
                             }
                         }
                     }
@@ -680,6 +935,8 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
                                         T::NAME,
                                         T::skinned_format(),
                                     );
+                                } else {
+                                    self.draws.record_mesh_draw(mesh, range.end - range.start);
                                 }
                             }
                         }
@@ -687,6 +944,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
                 }
             }
         }
+        stats.report_draws(&self.draws);
     }
 
     fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
@@ -704,6 +962,108 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
     }
 }
 
+/// Returns the visibility of the given camera, or of the active camera if there is none.
+fn camera_visibility_or<'a>(
+    visibility: &'a Visibility,
+    camera_visibility: &'a CameraVisibility,
+    camera: Option<Entity>,
+) -> Option<&'a Visibility> {
+    match camera {
+        Some(camera) => camera_visibility.get(camera),
+        None => Some(visibility),
+    }
+}
+
+/// Returns the access to the shadow map of a pass with shadows.
+fn shadow_map_access(shadows: bool) -> Vec<ImageAccess> {
+    if shadows {
+        vec![ImageAccess {
+            access: hal::image::Access::SHADER_READ,
+            usage: hal::image::Usage::SAMPLED,
+            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+            stages: pso::PipelineStage::FRAGMENT_SHADER,
+        }]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Creates the shadow submodule of a pass with shadows, along with the fragment shader to use.
+fn build_shadows<B: Backend, T: Base3DPassDef>(
+    ctx: &GraphContext<B>,
+    factory: &Factory<B>,
+    shadows: bool,
+    images: &[NodeImage],
+) -> Result<(Option<ShadowSub<B>>, &'static SpirvShader), failure::Error> {
+    if !shadows {
+        return Ok((None, T::fragment_shader()));
+    }
+    let fragment_shader = T::shadowed_fragment_shader()
+        .ok_or_else(|| failure::format_err!("Pass {} doesn't support shadows", T::NAME))?;
+    let shadow_map = images
+        .get(0)
+        .ok_or_else(|| failure::format_err!("Pass {} is missing the shadow map", T::NAME))?;
+    Ok((
+        Some(ShadowSub::new(ctx, factory, shadow_map)?),
+        fragment_shader,
+    ))
+}
+
+/// Creates the environment map submodule of a pass with image based lighting.
+fn build_environment_map<B: Backend, T: Base3DPassDef>(
+    factory: &mut Factory<B>,
+    queue: QueueId,
+) -> Result<Option<EnvironmentMapSub<B>>, failure::Error> {
+    if !T::image_based_lighting() {
+        return Ok(None);
+    }
+    Ok(Some(EnvironmentMapSub::new(
+        factory,
+        queue,
+        EnvironmentImage::Specular,
+    )?))
+}
+
+/// Returns the descriptor set of the environment map, which follows the shadows if any.
+fn environment_map_set<B: Backend>(shadows: &Option<ShadowSub<B>>) -> u32 {
+    if shadows.is_some() {
+        4
+    } else {
+        3
+    }
+}
+
+/// Appends the layouts of the shadows and the environment map to the descriptor set layouts
+/// of a pass.
+fn lighting_layouts<'a, B: Backend>(
+    mut layouts: Vec<&'a B::DescriptorSetLayout>,
+    shadows: &'a Option<ShadowSub<B>>,
+    environment_map: &'a Option<EnvironmentMapSub<B>>,
+) -> Vec<&'a B::DescriptorSetLayout> {
+    layouts.extend(shadows.as_ref().map(ShadowSub::raw_layout));
+    layouts.extend(environment_map.as_ref().map(EnvironmentMapSub::raw_layout));
+    layouts
+}
+
+/// Returns the mode pushed to the debug fragment shader of a pass drawn in the given mode, or
+/// `None` if the fragment shader of the pass is used.
+fn shader_debug_mode<T: Base3DPassDef>(debug_mode: DebugRenderMode) -> Option<u32> {
+    T::debug_fragment_shader().and(debug_mode.shader_mode())
+}
+
+/// Pushes the mode of the debug fragment shader, if it's used.
+fn push_debug_mode<B: Backend>(
+    debug_mode: Option<u32>,
+    layout: &B::PipelineLayout,
+    encoder: &mut RenderPassEncoder<'_, B>,
+) {
+    if let Some(debug_mode) = debug_mode {
+        unsafe {
+            encoder.push_constants(layout, pso::ShaderStageFlags::FRAGMENT, 0, &[debug_mode]);
+        }
+    }
+}
+
 fn build_pipelines<B: Backend, T: Base3DPassDef>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
@@ -711,14 +1071,19 @@ fn build_pipelines<B: Backend, T: Base3DPassDef>(
     framebuffer_height: u32,
     vertex_format_base: &[VertexFormat],
     vertex_format_skinned: &[VertexFormat],
+    fragment_shader: &SpirvShader,
     skinning: bool,
     transparent: bool,
+    samples: hal::image::NumSamples,
+    debug_mode: DebugRenderMode,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let shader_debug_mode = shader_debug_mode::<T>(debug_mode);
+    let push_constants = shader_debug_mode.map(|_| (pso::ShaderStageFlags::FRAGMENT, 0..1));
     let pipeline_layout = unsafe {
         factory
             .device()
-            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+            .create_pipeline_layout(layouts, push_constants)
     }?;
 
     let vertex_desc = vertex_format_base
@@ -731,8 +1096,12 @@ fn build_pipelines<B: Backend, T: Base3DPassDef>(
         .collect::<Vec<_>>();
 
     let shader_vertex_basic = unsafe { T::vertex_shader().module(factory).unwrap() };
-    let shader_fragment = unsafe { T::fragment_shader().module(factory).unwrap() };
-    let pipe_desc = PipelineDescBuilder::new()
+    let fragment_shader = match (shader_debug_mode, T::debug_fragment_shader()) {
+        (Some(_), Some(debug_shader)) => debug_shader,
+        _ => fragment_shader,
+    };
+    let shader_fragment = unsafe { fragment_shader.module(factory).unwrap() };
+    let mut pipe_desc = PipelineDescBuilder::new()
         .with_vertex_desc(&vertex_desc)
         .with_shaders(util::simple_shader_set(
             &shader_vertex_basic,
@@ -741,6 +1110,7 @@ fn build_pipelines<B: Backend, T: Base3DPassDef>(
         .with_layout(&pipeline_layout)
         .with_subpass(subpass)
         .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_samples(samples)
         .with_face_culling(pso::Face::BACK)
         .with_depth_test(pso::DepthTest {
             fun: pso::Comparison::Greater,
@@ -754,6 +1124,31 @@ fn build_pipelines<B: Backend, T: Base3DPassDef>(
                 None
             },
         }]);
+    match debug_mode {
+        DebugRenderMode::Wireframe => {
+            if factory
+                .physical()
+                .features()
+                .contains(hal::Features::NON_FILL_POLYGON_MODE)
+            {
+                pipe_desc.set_rasterizer(pso::Rasterizer {
+                    polygon_mode: pso::PolygonMode::Line(pso::State::Static(1.0)),
+                    cull_face: pso::Face::BACK,
+                    ..pso::Rasterizer::FILL
+                });
+            } else {
+                log::warn!("Wireframes aren't supported by the device, drawing meshes normally.");
+            }
+        }
+        DebugRenderMode::Overdraw => {
+            pipe_desc.set_depth_test(pso::DepthTest::PASS_TEST);
+            pipe_desc.set_blend_targets(vec![pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: Some(pso::BlendState::ADD),
+            }]);
+        }
+        _ => {}
+    }
 
     let pipelines = if skinning {
         let shader_vertex_skinned = unsafe { T::vertex_skinned_shader().module(factory).unwrap() };
@@ -0,0 +1,337 @@
+use crate::{
+    color_grading::{color_grading, neutral_lut, ColorGrading},
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    stats::{DrawCounts, RenderStats},
+    submodules::{TextureId, TextureSub},
+    types::{Backend, Texture},
+    util,
+};
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::ecs::{Read, ReadExpect, SystemData, World};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::Swizzle,
+        image::{Filter, SamplerInfo, ViewKind, WrapMode},
+        pso,
+    },
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::Shader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Size of the neutral lookup table used in place of missing ones.
+const NEUTRAL_LUT_SIZE: u32 = 16;
+
+/// Describes grading the colors of an image rendered to another target with the lookup tables
+/// of the `ColorGrading` resource. The image is added to the group with
+/// `builder().with_image(id)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawColorGradingDesc {
+    depth: bool,
+    samples: hal::image::NumSamples,
+}
+
+impl DrawColorGradingDesc {
+    /// Create instance of `DrawColorGrading` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set whether the target the group is added to has a depth output. Depth isn't tested.
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+impl Default for DrawColorGradingDesc {
+    fn default() -> Self {
+        Self {
+            depth: false,
+            samples: 1,
+        }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawColorGradingDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: hal::image::Access::SHADER_READ,
+            usage: hal::image::Usage::SAMPLED,
+            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+            stages: pso::PipelineStage::FRAGMENT_SHADER,
+        }]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        world: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let source = match images.as_slice() {
+            [source] => source,
+            _ => {
+                return Err(failure::format_err!(
+                    "Color grading expects the image to grade, got {} images",
+                    images.len()
+                ))
+            }
+        };
+
+        let layout: RendyHandle<DescriptorSetLayout<B>> = set_layout! {
+            factory,
+            [1] Sampler pso::ShaderStageFlags::FRAGMENT,
+            [1] SampledImage pso::ShaderStageFlags::FRAGMENT
+        };
+        let set = factory.create_descriptor_set(layout.clone())?;
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+        let image = ctx
+            .get_image(source.id)
+            .ok_or_else(|| failure::format_err!("Input image {:?} doesn't exist", source.id))?;
+        let view = factory.create_image_view(
+            image.clone(),
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: image.format(),
+                swizzle: Swizzle::NO,
+                range: source.range.clone(),
+            },
+        )?;
+
+        unsafe {
+            let raw_set = set.raw();
+            factory.write_descriptor_sets(vec![
+                util::desc_write(raw_set, 0, pso::Descriptor::Sampler(sampler.raw())),
+                util::desc_write(
+                    raw_set,
+                    1,
+                    pso::Descriptor::Image(view.raw(), source.layout),
+                ),
+            ]);
+        }
+
+        let textures = TextureSub::new(factory)?;
+        let (pipeline, pipeline_layout) = build_color_grading_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.samples,
+            vec![layout.raw(), textures.raw_layout(), textures.raw_layout()],
+        )?;
+
+        let (loader, texture_storage) =
+            <(ReadExpect<'_, Loader>, Read<'_, AssetStorage<Texture>>)>::fetch(world);
+        let neutral = loader.load_from_data(neutral_lut(NEUTRAL_LUT_SIZE), (), &texture_storage);
+
+        Ok(Box::new(DrawColorGrading::<B> {
+            pipeline,
+            pipeline_layout,
+            _layout: layout,
+            set,
+            _sampler: sampler,
+            _view: view,
+            textures,
+            neutral,
+            luts: None,
+            params: [0.0; 4],
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Grades the colors of an image with lookup tables.
+#[derive(Debug)]
+pub struct DrawColorGrading<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _view: Escape<ImageView<B>>,
+    textures: TextureSub<B>,
+    neutral: Handle<Texture>,
+    luts: Option<(TextureId, TextureId)>,
+    params: [f32; 4],
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawColorGrading<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        // Without the resource the neutral tables are drawn with no intensity.
+        let grading = color_grading(world).unwrap_or(ColorGrading {
+            intensity: 0.0,
+            ..Default::default()
+        });
+        let layout = hal::image::Layout::ShaderReadOnlyOptimal;
+        let textures = &mut self.textures;
+        let mut insert = |lut: &Handle<Texture>| {
+            textures
+                .insert(factory, world, lut, layout)
+                .map(|(tex_id, _)| tex_id)
+        };
+
+        // Tables that aren't loaded yet are replaced by the neutral one.
+        let neutral = insert(&self.neutral);
+        let lut = grading.lut.as_ref().and_then(&mut insert).or(neutral);
+        let blend_lut = grading.blend_lut.as_ref().and_then(&mut insert).or(neutral);
+        self.luts = match (lut, blend_lut) {
+            (Some(lut), Some(blend_lut)) => Some((lut, blend_lut)),
+            _ => None,
+        };
+        self.params = grading.params();
+        self.textures.maintain(factory, world);
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(world);
+        self.draws.clear();
+
+        let (lut, blend_lut) = match self.luts {
+            Some((lut, blend_lut))
+                if self.textures.loaded(lut) && self.textures.loaded(blend_lut) =>
+            {
+                (lut, blend_lut)
+            }
+            _ => return,
+        };
+
+        let layout = &self.pipeline_layout;
+        let constants = self
+            .params
+            .iter()
+            .map(|value| value.to_bits())
+            .collect::<Vec<_>>();
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                0,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+        self.textures.bind(layout, 1, lut, &mut encoder);
+        self.textures.bind(layout, 2, blend_lut, &mut encoder);
+        self.draws.record_draw(hal::Primitive::TriangleList, 3, 1);
+        unsafe {
+            encoder.push_constants(layout, pso::ShaderStageFlags::FRAGMENT, 0, &constants);
+            encoder.draw(0..3, 0..1);
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_color_grading_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    samples: hal::image::NumSamples,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, Some((pso::ShaderStageFlags::FRAGMENT, 0..4)))
+    }?;
+
+    let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::COLOR_GRADING_FRAGMENT.module(factory).unwrap() };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_samples(samples)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
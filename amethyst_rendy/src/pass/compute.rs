@@ -0,0 +1,422 @@
+use crate::{types::Backend, util};
+use amethyst_core::ecs::World;
+use derivative::Derivative;
+use rendy::{
+    command::{
+        CommandPool, Compute, Family, IndividualReset, MultiShot, NoSimultaneousUse, Submit,
+    },
+    factory::Factory,
+    frame::{cirque::CommandCirque, Frames},
+    graph::{
+        gfx_acquire_barriers, gfx_release_barriers, BufferAccess, GraphContext, ImageAccess, Node,
+        NodeBuffer, NodeDesc, NodeImage, NodeSubmittable,
+    },
+    hal::{
+        self,
+        command::RawCommandBuffer,
+        device::Device,
+        format::Swizzle,
+        image::{Filter, SamplerInfo, ViewKind, WrapMode},
+        pso,
+    },
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::{Shader, SpirvShader},
+};
+use std::sync::Arc;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Params pushed to compute shaders dispatched by `DispatchComputeDesc`.
+pub type ComputeParams = [[f32; 4]; 2];
+
+/// How a compute shader accesses a buffer or an image of the render graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeAccess {
+    /// The resource is only read.
+    Read,
+    /// The resource is only written.
+    Write,
+    /// The resource is read and written.
+    ReadWrite,
+}
+
+impl ComputeAccess {
+    fn reads(self) -> bool {
+        self != ComputeAccess::Write
+    }
+
+    fn writes(self) -> bool {
+        self != ComputeAccess::Read
+    }
+
+    fn buffer_access(self) -> BufferAccess {
+        let mut access = hal::buffer::Access::empty();
+        if self.reads() {
+            access |= hal::buffer::Access::SHADER_READ;
+        }
+        if self.writes() {
+            access |= hal::buffer::Access::SHADER_WRITE;
+        }
+        BufferAccess {
+            access,
+            usage: hal::buffer::Usage::STORAGE,
+            stages: pso::PipelineStage::COMPUTE_SHADER,
+        }
+    }
+
+    fn image_access(self) -> ImageAccess {
+        match self {
+            ComputeAccess::Read => ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::COMPUTE_SHADER,
+            },
+            ComputeAccess::Write | ComputeAccess::ReadWrite => {
+                let mut access = hal::image::Access::SHADER_WRITE;
+                if self.reads() {
+                    access |= hal::image::Access::SHADER_READ;
+                }
+                ImageAccess {
+                    access,
+                    usage: hal::image::Usage::STORAGE,
+                    layout: hal::image::Layout::General,
+                    stages: pso::PipelineStage::COMPUTE_SHADER,
+                }
+            }
+        }
+    }
+
+    fn image_descriptor(self) -> pso::DescriptorType {
+        match self {
+            ComputeAccess::Read => pso::DescriptorType::CombinedImageSampler,
+            ComputeAccess::Write | ComputeAccess::ReadWrite => pso::DescriptorType::StorageImage,
+        }
+    }
+}
+
+/// Describes a render graph node dispatching a compute shader over buffers and images of the
+/// render graph, e.g. to update GPU particles or to build a luminance histogram. Added to the
+/// plan with `RenderPlan::define_custom_node`.
+///
+/// The shader gets the buffers as storage buffers at set 0 from binding 0 on, followed by the
+/// images: images only read as `sampler2D`s with a linear clamping sampler, and written images
+/// as `image2D`s. The params are a push constant block of two `vec4`s. The buffers and images
+/// are added with `builder().with_buffer(id)` and `builder().with_image(id)`, in binding order.
+/// The render graph inserts the barriers between the node and the other nodes accessing them.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct DispatchComputeDesc {
+    shader: SpirvShader,
+    buffers: Vec<ComputeAccess>,
+    images: Vec<ComputeAccess>,
+    #[derivative(Debug = "ignore")]
+    params: Arc<dyn Fn(&World) -> ComputeParams + Send + Sync>,
+    #[derivative(Debug = "ignore")]
+    groups: Arc<dyn Fn(&World) -> [u32; 3] + Send + Sync>,
+}
+
+impl DispatchComputeDesc {
+    /// Create a compute node dispatching the given compute shader with the given number of
+    /// workgroups every frame.
+    pub fn new(shader: SpirvShader, groups: [u32; 3]) -> Self {
+        Self {
+            shader,
+            buffers: Vec::new(),
+            images: Vec::new(),
+            params: Arc::new(|_| [[0.0; 4]; 2]),
+            groups: Arc::new(move |_| groups),
+        }
+    }
+
+    /// Set the function computing the shader params every frame.
+    pub fn with_params(
+        mut self,
+        params: impl Fn(&World) -> ComputeParams + Send + Sync + 'static,
+    ) -> Self {
+        self.params = Arc::new(params);
+        self
+    }
+
+    /// Set the function computing the number of workgroups every frame, e.g. from the number of
+    /// particles alive. Nothing is dispatched for frames with no workgroups.
+    pub fn with_groups(
+        mut self,
+        groups: impl Fn(&World) -> [u32; 3] + Send + Sync + 'static,
+    ) -> Self {
+        self.groups = Arc::new(groups);
+        self
+    }
+
+    /// Append a buffer accessed by the shader, bound after the previous buffers.
+    pub fn with_buffer_access(mut self, access: ComputeAccess) -> Self {
+        self.buffers.push(access);
+        self
+    }
+
+    /// Append an image accessed by the shader, bound after the buffers and previous images.
+    pub fn with_image_access(mut self, access: ComputeAccess) -> Self {
+        self.images.push(access);
+        self
+    }
+}
+
+impl<B: Backend> NodeDesc<B, World> for DispatchComputeDesc {
+    type Node = DispatchCompute<B>;
+
+    fn buffers(&self) -> Vec<BufferAccess> {
+        self.buffers
+            .iter()
+            .map(|access| access.buffer_access())
+            .collect()
+    }
+
+    fn images(&self) -> Vec<ImageAccess> {
+        self.images
+            .iter()
+            .map(|access| access.image_access())
+            .collect()
+    }
+
+    fn build<'a>(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _world: &World,
+        buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Self::Node, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let bindings = self
+            .buffers
+            .iter()
+            .map(|_| pso::DescriptorType::StorageBuffer)
+            .chain(self.images.iter().map(|access| access.image_descriptor()))
+            .map(|ty| (1, ty, pso::ShaderStageFlags::COMPUTE));
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(bindings))?
+            .into();
+        let set = factory.create_descriptor_set(layout.clone())?;
+
+        let sampler = if self.images.contains(&ComputeAccess::Read) {
+            Some(factory.get_sampler(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))?)
+        } else {
+            None
+        };
+        let views = images
+            .iter()
+            .map(|node_image| {
+                let image = ctx.get_image(node_image.id).ok_or_else(|| {
+                    failure::format_err!("Compute image {:?} doesn't exist", node_image.id)
+                })?;
+                factory.create_image_view(
+                    image.clone(),
+                    ImageViewInfo {
+                        view_kind: ViewKind::D2,
+                        format: image.format(),
+                        swizzle: Swizzle::NO,
+                        range: node_image.range.clone(),
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut writes = Vec::with_capacity(buffers.len() + images.len());
+        for (binding, node_buffer) in buffers.iter().enumerate() {
+            let buffer = ctx.get_buffer(node_buffer.id).ok_or_else(|| {
+                failure::format_err!("Compute buffer {:?} doesn't exist", node_buffer.id)
+            })?;
+            writes.push(util::desc_write(
+                set.raw(),
+                binding as u32,
+                pso::Descriptor::Buffer(
+                    buffer.raw(),
+                    Some(node_buffer.range.start)..Some(node_buffer.range.end),
+                ),
+            ));
+        }
+        for (index, ((node_image, view), access)) in
+            images.iter().zip(&views).zip(&self.images).enumerate()
+        {
+            let binding = (buffers.len() + index) as u32;
+            let descriptor = match (access, &sampler) {
+                (ComputeAccess::Read, Some(sampler)) => pso::Descriptor::CombinedImageSampler(
+                    view.raw(),
+                    node_image.layout,
+                    sampler.raw(),
+                ),
+                _ => pso::Descriptor::Image(view.raw(), node_image.layout),
+            };
+            writes.push(util::desc_write(set.raw(), binding, descriptor));
+        }
+        unsafe {
+            factory.write_descriptor_sets(writes);
+        }
+
+        let pipeline_layout = unsafe {
+            factory.device().create_pipeline_layout(
+                Some(layout.raw()),
+                Some((pso::ShaderStageFlags::COMPUTE, 0..8)),
+            )
+        }?;
+        let pipeline = unsafe {
+            let module = self.shader.module(factory)?;
+            let pipeline = factory.device().create_compute_pipeline(
+                &pso::ComputePipelineDesc::new(
+                    pso::EntryPoint {
+                        entry: "main",
+                        module: &module,
+                        specialization: pso::Specialization::default(),
+                    },
+                    &pipeline_layout,
+                ),
+                None,
+            );
+            factory.destroy_shader_module(module);
+            match pipeline {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                    return Err(e.into());
+                }
+            }
+        };
+
+        let pool = factory
+            .create_command_pool(family)?
+            .with_capability()
+            .map_err(|_| failure::format_err!("Compute nodes require a compute queue"))?;
+        Ok(DispatchCompute {
+            pipeline,
+            pipeline_layout,
+            _layout: layout,
+            set,
+            _sampler: sampler,
+            _views: views,
+            buffers,
+            images,
+            params: self.params,
+            groups: self.groups,
+            pool,
+            cirque: CommandCirque::new(),
+        })
+    }
+}
+
+/// Node dispatching a compute shader.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DispatchCompute<B: Backend> {
+    #[derivative(Debug = "ignore")]
+    pipeline: B::ComputePipeline,
+    #[derivative(Debug = "ignore")]
+    pipeline_layout: B::PipelineLayout,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    _sampler: Option<RendyHandle<Sampler<B>>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    buffers: Vec<NodeBuffer>,
+    images: Vec<NodeImage>,
+    #[derivative(Debug = "ignore")]
+    params: Arc<dyn Fn(&World) -> ComputeParams + Send + Sync>,
+    #[derivative(Debug = "ignore")]
+    groups: Arc<dyn Fn(&World) -> [u32; 3] + Send + Sync>,
+    pool: CommandPool<B, Compute, IndividualReset>,
+    cirque: CommandCirque<B, Compute>,
+}
+
+impl<'a, B: Backend> NodeSubmittable<'a, B> for DispatchCompute<B> {
+    type Submittable = Submit<B>;
+    type Submittables = Option<Submit<B>>;
+}
+
+impl<B: Backend> Node<B, World> for DispatchCompute<B> {
+    type Capability = Compute;
+    type Desc = DispatchComputeDesc;
+
+    fn run<'a>(
+        &'a mut self,
+        ctx: &GraphContext<B>,
+        _factory: &Factory<B>,
+        world: &World,
+        frames: &'a Frames<B>,
+    ) -> Option<Submit<B>> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run");
+
+        let constants = (self.params)(world)
+            .iter()
+            .flat_map(|vec| vec.iter().map(|value| value.to_bits()))
+            .collect::<Vec<_>>();
+        let [x, y, z] = (self.groups)(world);
+        let (pipeline, layout, set) = (&self.pipeline, &self.pipeline_layout, &self.set);
+        let (buffers, images) = (&self.buffers, &self.images);
+
+        // The params and workgroups may change every frame, so the commands are recorded every
+        // frame.
+        let submit = self.cirque.encode(frames, &mut self.pool, |cbuf| {
+            cbuf.or_reset(|cbuf| cbuf.reset()).init(|cbuf| {
+                let mut cbuf = cbuf.begin(MultiShot(NoSimultaneousUse), ());
+                unsafe {
+                    let mut encoder = cbuf.encoder();
+                    let (stages, barriers) = gfx_acquire_barriers(ctx, buffers, images);
+                    if !barriers.is_empty() {
+                        encoder.pipeline_barrier(
+                            stages,
+                            hal::memory::Dependencies::empty(),
+                            barriers,
+                        );
+                    }
+                    encoder.bind_compute_pipeline(pipeline);
+                    encoder.bind_compute_descriptor_sets(
+                        layout,
+                        0,
+                        Some(set.raw()),
+                        std::iter::empty(),
+                    );
+                    // The encoder only pushes constants to graphics pipelines.
+                    cbuf.raw().push_compute_constants(layout, 0, &constants);
+
+                    let mut encoder = cbuf.encoder();
+                    if x > 0 && y > 0 && z > 0 {
+                        encoder.dispatch(x, y, z);
+                    }
+                    let (stages, barriers) = gfx_release_barriers(ctx, buffers, images);
+                    if !barriers.is_empty() {
+                        encoder.pipeline_barrier(
+                            stages,
+                            hal::memory::Dependencies::empty(),
+                            barriers,
+                        );
+                    }
+                }
+                cbuf.finish()
+            })
+        });
+        Some(submit)
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _world: &World) {
+        let pool = &mut self.pool;
+        self.cirque.dispose(|buffer| {
+            buffer.either_with(
+                &mut *pool,
+                |pool, executable| pool.free_buffers(Some(executable)),
+                |pool, pending| pool.free_buffers(Some(pending.mark_complete())),
+            );
+        });
+        factory.destroy_command_pool(self.pool.with_queue_type());
+        factory.device().destroy_compute_pipeline(self.pipeline);
+        factory
+            .device()
+            .destroy_pipeline_layout(self.pipeline_layout);
+    }
+}
@@ -2,6 +2,7 @@ use crate::{
     debug_drawing::{DebugLine, DebugLines, DebugLinesComponent, DebugLinesParams},
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::ViewArgs,
+    stats::{DrawCounts, RenderStats},
     submodules::{gather::CameraGatherer, DynamicUniform, DynamicVertexBuffer},
     types::Backend,
     util,
@@ -32,13 +33,21 @@ struct DebugLinesArgs {
 /// Draw opaque sprites without lighting.
 #[derive(Clone, Debug, PartialEq, Derivative)]
 #[derivative(Default(bound = ""))]
-pub struct DrawDebugLinesDesc;
+pub struct DrawDebugLinesDesc {
+    samples: hal::image::NumSamples,
+}
 
 impl DrawDebugLinesDesc {
     /// Create instance of `DrawDebugLines` render group
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
@@ -66,6 +75,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
             subpass,
             framebuffer_width,
             framebuffer_height,
+            self.samples,
             vec![env.raw_layout(), args.raw_layout()],
         )?;
 
@@ -79,6 +89,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
             framebuffer_height: framebuffer_height as f32,
             lines: Vec::new(),
             change: Default::default(),
+            draws: DrawCounts::default(),
         }))
     }
 }
@@ -95,6 +106,7 @@ pub struct DrawDebugLines<B: Backend> {
     framebuffer_height: f32,
     lines: Vec<DebugLine>,
     change: util::ChangeDetection,
+    draws: DrawCounts,
 }
 
 impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
@@ -109,6 +121,9 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
         #[cfg(feature = "profiler")]
         profile_scope!("prepare");
 
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(resources).report_draws(&self.draws);
+
         let (lines_comps, lines_res, line_params) = <(
             WriteStorage<'_, DebugLinesComponent>,
             Option<Write<'_, DebugLines>>,
@@ -160,12 +175,16 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
         mut encoder: RenderPassEncoder<'_, B>,
         index: usize,
         _subpass: hal::pass::Subpass<'_, B>,
-        _resources: &World,
+        resources: &World,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("draw");
 
+        let stats = <Read<'_, RenderStats>>::fetch(resources);
+        self.draws.clear();
+
         if self.lines.is_empty() {
+            stats.report_draws(&self.draws);
             return;
         }
 
@@ -174,9 +193,12 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
         self.env.bind(index, layout, 0, &mut encoder);
         self.args.bind(index, layout, 1, &mut encoder);
         self.vertex.bind(index, 0, 0, &mut encoder);
+        self.draws
+            .record_draw(hal::Primitive::TriangleStrip, 4, self.lines.len() as u32);
         unsafe {
             encoder.draw(0..4, 0..self.lines.len() as u32);
         }
+        stats.report_draws(&self.draws);
     }
 
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
@@ -194,6 +216,7 @@ fn build_lines_pipeline<B: Backend>(
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
+    samples: hal::image::NumSamples,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
@@ -217,6 +240,7 @@ fn build_lines_pipeline<B: Backend>(
                 .with_layout(&pipeline_layout)
                 .with_subpass(subpass)
                 .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_samples(samples)
                 .with_blend_targets(vec![pso::ColorBlendDesc {
                     mask: pso::ColorMask::ALL,
                     blend: Some(pso::BlendState::ALPHA),
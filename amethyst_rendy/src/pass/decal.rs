@@ -0,0 +1,363 @@
+use crate::{
+    batch::OneLevelBatch,
+    decal::Decal,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{DecalArgs, DecalView},
+    stats::{DrawCounts, RenderStats},
+    submodules::{
+        gather::CameraGatherer, DynamicUniform, DynamicVertexBuffer, TextureId, TextureSub,
+    },
+    types::Backend,
+    util,
+};
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    math::Matrix4,
+    transform::Transform,
+    Hidden, HiddenPropagate,
+};
+use glsl_layout::AsStd140;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::Swizzle,
+        image::{Filter, SamplerInfo, ViewKind, WrapMode},
+        pso,
+    },
+    mesh::AsVertex,
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::Shader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Describes drawing `Decal`s onto the scene rendered to another target, whose depth image is
+/// added to the group with `builder().with_image(id)`.
+///
+/// The box of every decal is rasterized, and the world position of the scene behind every
+/// pixel is reconstructed from the depth image and projected into the decal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawDecalsDesc {
+    depth: bool,
+    samples: hal::image::NumSamples,
+}
+
+impl DrawDecalsDesc {
+    /// Create instance of `DrawDecals` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set whether the target the group is added to has a depth output. Depth isn't tested.
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+impl Default for DrawDecalsDesc {
+    fn default() -> Self {
+        Self {
+            depth: false,
+            samples: 1,
+        }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawDecalsDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: hal::image::Access::SHADER_READ,
+            usage: hal::image::Usage::SAMPLED,
+            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+            stages: pso::PipelineStage::FRAGMENT_SHADER,
+        }]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _world: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let depth = match images.as_slice() {
+            [depth] => depth,
+            _ => {
+                return Err(failure::format_err!(
+                    "Decals expect the depth image of the scene, got {} images",
+                    images.len()
+                ))
+            }
+        };
+
+        let layout: RendyHandle<DescriptorSetLayout<B>> = set_layout! {
+            factory,
+            [1] Sampler pso::ShaderStageFlags::FRAGMENT,
+            [1] SampledImage pso::ShaderStageFlags::FRAGMENT
+        };
+        let set = factory.create_descriptor_set(layout.clone())?;
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+        let image = ctx
+            .get_image(depth.id)
+            .ok_or_else(|| failure::format_err!("Input image {:?} doesn't exist", depth.id))?;
+        let view = factory.create_image_view(
+            image.clone(),
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: image.format(),
+                swizzle: Swizzle::NO,
+                range: depth.range.clone(),
+            },
+        )?;
+
+        unsafe {
+            let raw_set = set.raw();
+            factory.write_descriptor_sets(vec![
+                util::desc_write(raw_set, 0, pso::Descriptor::Sampler(sampler.raw())),
+                util::desc_write(raw_set, 1, pso::Descriptor::Image(view.raw(), depth.layout)),
+            ]);
+        }
+
+        let env = DynamicUniform::new(
+            factory,
+            pso::ShaderStageFlags::VERTEX | pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let textures = TextureSub::new(factory)?;
+        let (pipeline, pipeline_layout) = build_decal_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.samples,
+            vec![env.raw_layout(), layout.raw(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawDecals::<B> {
+            pipeline,
+            pipeline_layout,
+            _layout: layout,
+            set,
+            _sampler: sampler,
+            _view: view,
+            env,
+            textures,
+            vertex: DynamicVertexBuffer::new(),
+            decals: Default::default(),
+            screen_size: [framebuffer_width as f32, framebuffer_height as f32],
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Draws `Decal`s onto the scene, with one instanced draw call per texture.
+#[derive(Debug)]
+pub struct DrawDecals<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _view: Escape<ImageView<B>>,
+    env: DynamicUniform<B, DecalView>,
+    textures: TextureSub<B>,
+    vertex: DynamicVertexBuffer<B, DecalArgs>,
+    decals: OneLevelBatch<TextureId, DecalArgs>,
+    screen_size: [f32; 2],
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawDecals<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (decals, transforms, hiddens, hidden_props) = <(
+            ReadStorage<'_, Decal>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Hidden>,
+            ReadStorage<'_, HiddenPropagate>,
+        )>::fetch(world);
+
+        let proj_view = CameraGatherer::gather_proj_view(world, None);
+        let inverse_proj_view = proj_view.try_inverse().unwrap_or_else(Matrix4::identity);
+        let proj_view: [[f32; 4]; 4] = proj_view.into();
+        let inverse_proj_view: [[f32; 4]; 4] = inverse_proj_view.into();
+        self.env.write(
+            factory,
+            index,
+            DecalView {
+                proj_view: proj_view.into(),
+                inverse_proj_view: inverse_proj_view.into(),
+                screen_size: self.screen_size.into(),
+            }
+            .std140(),
+        );
+
+        self.decals.clear_inner();
+        let decals_ref = &mut self.decals;
+        let textures_ref = &mut self.textures;
+        let layout = hal::image::Layout::ShaderReadOnlyOptimal;
+        (&decals, &transforms, !&hiddens, !&hidden_props)
+            .join()
+            .filter_map(|(decal, transform, _, _)| {
+                let args = DecalArgs::from_decal(decal, transform.global_matrix())?;
+                let (tex_id, _) = textures_ref.insert(factory, world, &decal.texture, layout)?;
+                Some((tex_id, args))
+            })
+            .for_each(|(tex_id, args)| decals_ref.insert(tex_id, Some(args)));
+        self.decals.prune();
+
+        self.textures.maintain(factory, world);
+        self.vertex.write(
+            factory,
+            index,
+            self.decals.count() as u64,
+            self.decals.data(),
+        );
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(world);
+        self.draws.clear();
+
+        if self.decals.count() == 0 {
+            return;
+        }
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, layout, 0, &mut encoder);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                1,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+        self.vertex.bind(index, 0, 0, &mut encoder);
+        for (&tex, range) in self.decals.iter() {
+            if self.textures.loaded(tex) {
+                self.textures.bind(layout, 2, tex, &mut encoder);
+                self.draws
+                    .record_draw(hal::Primitive::TriangleList, 36, range.len() as u32);
+                unsafe {
+                    encoder.draw(0..36, range);
+                }
+            }
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_decal_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    samples: hal::image::NumSamples,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::DECAL_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::DECAL_FRAGMENT.module(factory).unwrap() };
+
+    // Only the back faces of the boxes are drawn, so that every pixel is covered once and decals
+    // still show with the camera inside of their box.
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(DecalArgs::vertex(), pso::VertexInputRate::Instance(1))])
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_samples(samples)
+                .with_face_culling(pso::Face::FRONT)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -20,6 +20,9 @@ impl Base3DPassDef for FlatPassDef {
     fn fragment_shader() -> &'static SpirvShader {
         &super::FLAT_FRAGMENT
     }
+    fn debug_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::DEBUG_POS_TEX_FRAGMENT)
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![Position::vertex(), TexCoord::vertex()]
     }
@@ -1,15 +1,16 @@
 use crate::{
-    batch::{GroupIterator, OneLevelBatch, OrderedOneLevelBatch},
+    batch::SortedBatch,
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::SpriteArgs,
     resources::Tint,
     sprite::{SpriteRender, SpriteSheet},
     sprite_visibility::SpriteVisibility,
+    stats::{DrawCounts, RenderStats},
     submodules::{DynamicVertexBuffer, FlatEnvironmentSub, TextureId, TextureSub},
     types::{Backend, Texture},
     util,
 };
-use amethyst_assets::AssetStorage;
+use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
     ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
     transform::Transform,
@@ -34,13 +35,21 @@ use thread_profiler::profile_scope;
 /// Draw opaque sprites without lighting.
 #[derive(Clone, Debug, PartialEq, Derivative)]
 #[derivative(Default(bound = ""))]
-pub struct DrawFlat2DDesc;
+pub struct DrawFlat2DDesc {
+    samples: hal::image::NumSamples,
+}
 
 impl DrawFlat2DDesc {
     /// Create instance of `DrawFlat2D` render group
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawFlat2DDesc {
@@ -69,6 +78,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawFlat2DDesc {
             framebuffer_width,
             framebuffer_height,
             false,
+            self.samples,
             vec![env.raw_layout(), textures.raw_layout()],
         )?;
 
@@ -79,11 +89,15 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawFlat2DDesc {
             textures,
             vertex,
             sprites: Default::default(),
+            draws: DrawCounts::default(),
         }))
     }
 }
 
 /// Draws opaque 2D sprites to the screen without lighting.
+///
+/// Sprites are sorted by texture and drawn front to back, with one instanced draw call per
+/// texture.
 #[derive(Debug)]
 pub struct DrawFlat2D<B: Backend> {
     pipeline: B::GraphicsPipeline,
@@ -91,7 +105,8 @@ pub struct DrawFlat2D<B: Backend> {
     env: FlatEnvironmentSub<B>,
     textures: TextureSub<B>,
     vertex: DynamicVertexBuffer<B, SpriteArgs>,
-    sprites: OneLevelBatch<TextureId, SpriteArgs>,
+    sprites: SortedBatch<(TextureId, u32), TextureId, SpriteArgs>,
+    draws: DrawCounts,
 }
 
 impl<B: Backend> RenderGroup<B, World> for DrawFlat2D<B> {
@@ -130,8 +145,9 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2D<B> {
 
         let sprites_ref = &mut self.sprites;
         let textures_ref = &mut self.textures;
+        let mut last_texture = None;
 
-        sprites_ref.clear_inner();
+        sprites_ref.swap_clear();
 
         {
             #[cfg(feature = "profiler")]
@@ -152,16 +168,14 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2D<B> {
                         &global,
                         tint,
                     )?;
-                    let (tex_id, _) = textures_ref.insert(
-                        factory,
-                        world,
-                        texture,
-                        hal::image::Layout::ShaderReadOnlyOptimal,
-                    )?;
+                    let (tex_id, _) =
+                        insert_texture(textures_ref, &mut last_texture, factory, world, texture)?;
                     Some((tex_id, batch_data))
                 })
-                .for_each_group(|tex_id, batch_data| {
-                    sprites_ref.insert(tex_id, batch_data.drain(..))
+                .for_each(|(tex_id, batch_data)| {
+                    // Front to back, so that hidden fragments fail the depth test
+                    let key = (tex_id, !depth_key(batch_data.depth));
+                    sprites_ref.insert(tex_id, key, batch_data);
                 });
         }
 
@@ -171,12 +185,12 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2D<B> {
             #[cfg(feature = "profiler")]
             profile_scope!("write");
 
-            sprites_ref.prune();
+            sprites_ref.sort();
             self.vertex.write(
                 factory,
                 index,
                 self.sprites.count() as u64,
-                self.sprites.data(),
+                self.sprites.data().map(std::slice::from_ref),
             );
         }
 
@@ -188,11 +202,14 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2D<B> {
         mut encoder: RenderPassEncoder<'_, B>,
         index: usize,
         _subpass: hal::pass::Subpass<'_, B>,
-        _world: &World,
+        world: &World,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("draw opaque");
 
+        let stats = <Read<'_, RenderStats>>::fetch(world);
+        self.draws.clear();
+
         let layout = &self.pipeline_layout;
         encoder.bind_graphics_pipeline(&self.pipeline);
         self.env.bind(index, layout, 0, &mut encoder);
@@ -200,11 +217,14 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2D<B> {
         for (&tex, range) in self.sprites.iter() {
             if self.textures.loaded(tex) {
                 self.textures.bind(layout, 1, tex, &mut encoder);
+                self.draws
+                    .record_draw(hal::Primitive::TriangleStrip, 4, range.len() as u32);
                 unsafe {
                     encoder.draw(0..4, range);
                 }
             }
         }
+        stats.report_draws(&self.draws);
     }
 
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
@@ -219,13 +239,21 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2D<B> {
 /// Describes drawing transparent sprites without lighting.
 #[derive(Clone, Debug, PartialEq, Derivative)]
 #[derivative(Default(bound = ""))]
-pub struct DrawFlat2DTransparentDesc;
+pub struct DrawFlat2DTransparentDesc {
+    samples: hal::image::NumSamples,
+}
 
 impl DrawFlat2DTransparentDesc {
     /// Create instance of `DrawFlat2D` render group
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawFlat2DTransparentDesc {
@@ -254,6 +282,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawFlat2DTransparentDesc {
             framebuffer_width,
             framebuffer_height,
             true,
+            self.samples,
             vec![env.raw_layout(), textures.raw_layout()],
         )?;
 
@@ -265,11 +294,15 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawFlat2DTransparentDesc {
             vertex,
             sprites: Default::default(),
             change: Default::default(),
+            draws: DrawCounts::default(),
         }))
     }
 }
 
 /// Draws transparent sprites without lighting.
+///
+/// Sprites are drawn back to front, with sprites at the same depth grouped by texture so that
+/// they share instanced draw calls.
 #[derive(Debug)]
 pub struct DrawFlat2DTransparent<B: Backend> {
     pipeline: B::GraphicsPipeline,
@@ -277,8 +310,9 @@ pub struct DrawFlat2DTransparent<B: Backend> {
     env: FlatEnvironmentSub<B>,
     textures: TextureSub<B>,
     vertex: DynamicVertexBuffer<B, SpriteArgs>,
-    sprites: OrderedOneLevelBatch<TextureId, SpriteArgs>,
+    sprites: SortedBatch<(u32, TextureId), TextureId, SpriteArgs>,
     change: util::ChangeDetection,
+    draws: DrawCounts,
 }
 
 impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
@@ -293,6 +327,9 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
         #[cfg(feature = "profiler")]
         profile_scope!("prepare transparent");
 
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(world).report_draws(&self.draws);
+
         let (sprite_sheet_storage, tex_storage, visibility, sprite_renders, transforms, tints) =
             <(
                 Read<'_, AssetStorage<SpriteSheet>>,
@@ -309,6 +346,7 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
 
         let sprites_ref = &mut self.sprites;
         let textures_ref = &mut self.textures;
+        let mut last_texture = None;
 
         {
             #[cfg(feature = "profiler")]
@@ -327,20 +365,18 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
                         &global,
                         tint,
                     )?;
-                    let (tex_id, this_changed) = textures_ref.insert(
-                        factory,
-                        world,
-                        texture,
-                        hal::image::Layout::ShaderReadOnlyOptimal,
-                    )?;
+                    let (tex_id, this_changed) =
+                        insert_texture(textures_ref, &mut last_texture, factory, world, texture)?;
                     changed = changed || this_changed;
                     Some((tex_id, batch_data))
                 })
-                .for_each_group(|tex_id, batch_data| {
-                    sprites_ref.insert(tex_id, batch_data.drain(..));
+                .for_each(|(tex_id, batch_data)| {
+                    let key = (depth_key(batch_data.depth), tex_id);
+                    sprites_ref.insert(tex_id, key, batch_data);
                 });
         }
         self.textures.maintain(factory, world);
+        self.sprites.sort();
         changed = changed || self.sprites.changed();
 
         {
@@ -351,7 +387,7 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
                 factory,
                 index,
                 self.sprites.count() as u64,
-                Some(self.sprites.data()),
+                self.sprites.data().map(std::slice::from_ref),
             );
         }
 
@@ -363,11 +399,14 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
         mut encoder: RenderPassEncoder<'_, B>,
         index: usize,
         _subpass: hal::pass::Subpass<'_, B>,
-        _world: &World,
+        world: &World,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("draw transparent");
 
+        let stats = <Read<'_, RenderStats>>::fetch(world);
+        self.draws.clear();
+
         let layout = &self.pipeline_layout;
         encoder.bind_graphics_pipeline(&self.pipeline);
         self.env.bind(index, layout, 0, &mut encoder);
@@ -375,11 +414,14 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
         for (&tex, range) in self.sprites.iter() {
             if self.textures.loaded(tex) {
                 self.textures.bind(layout, 1, tex, &mut encoder);
+                self.draws
+                    .record_draw(hal::Primitive::TriangleStrip, 4, range.len() as u32);
                 unsafe {
                     encoder.draw(0..4, range);
                 }
             }
         }
+        stats.report_draws(&self.draws);
     }
 
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
@@ -392,12 +434,47 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
     }
 }
 
+/// Inserts the texture of a sprite, skipping the lookup when it's the texture of the previous
+/// sprite, which is the common case for sprites of the same sprite sheet.
+pub(super) fn insert_texture<B: Backend>(
+    textures: &mut TextureSub<B>,
+    last_texture: &mut Option<(u32, TextureId)>,
+    factory: &Factory<B>,
+    world: &World,
+    handle: &Handle<Texture>,
+) -> Option<(TextureId, bool)> {
+    match *last_texture {
+        Some((id, tex_id)) if id == handle.id() => Some((tex_id, false)),
+        _ => {
+            let (tex_id, changed) = textures.insert(
+                factory,
+                world,
+                handle,
+                hal::image::Layout::ShaderReadOnlyOptimal,
+            )?;
+            *last_texture = Some((handle.id(), tex_id));
+            Some((tex_id, changed))
+        }
+    }
+}
+
+/// Maps a depth to an integer with the same ordering, for sorting sprites by depth.
+pub(super) fn depth_key(depth: f32) -> u32 {
+    let bits = depth.to_bits();
+    if bits >> 31 == 1 {
+        !bits
+    } else {
+        bits | 0x8000_0000
+    }
+}
+
 fn build_sprite_pipeline<B: Backend>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
     transparent: bool,
+    samples: hal::image::NumSamples,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
@@ -421,6 +498,7 @@ fn build_sprite_pipeline<B: Backend>(
                 .with_layout(&pipeline_layout)
                 .with_subpass(subpass)
                 .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_samples(samples)
                 .with_blend_targets(vec![pso::ColorBlendDesc {
                     mask: pso::ColorMask::ALL,
                     blend: if transparent {
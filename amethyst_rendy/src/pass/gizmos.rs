@@ -0,0 +1,315 @@
+use crate::{
+    debug_drawing::{DebugLine, DebugLinesParams},
+    gizmos::Gizmos,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::ViewArgs,
+    stats::{DrawCounts, RenderStats},
+    submodules::{gather::CameraGatherer, DynamicUniform, DynamicVertexBuffer},
+    types::Backend,
+    util,
+};
+use amethyst_core::{
+    ecs::{Read, ReadStorage, SystemData, World, Write},
+    math::{convert, Matrix4, Vector3},
+    transform::Transform,
+};
+use derivative::Derivative;
+use glsl_layout::*;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::AsVertex,
+    shader::Shader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+#[derive(Debug, Clone, AsStd140)]
+struct GizmosArgs {
+    screen_space_thickness: vec2,
+}
+
+/// Draw the gizmos of the `Gizmos` resource.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawGizmosDesc {
+    samples: hal::image::NumSamples,
+}
+
+impl DrawGizmosDesc {
+    /// Create instance of `DrawGizmos` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawGizmosDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = DynamicUniform::new(factory, pso::ShaderStageFlags::VERTEX)?;
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::VERTEX)?;
+        let vertex = DynamicVertexBuffer::new();
+
+        let (pipelines, pipeline_layout) = build_gizmos_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.samples,
+            vec![env.raw_layout(), args.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawGizmos::<B> {
+            pipelines,
+            pipeline_layout,
+            env,
+            args,
+            vertex,
+            framebuffer_width: framebuffer_width as f32,
+            framebuffer_height: framebuffer_height as f32,
+            depth_lines: Vec::new(),
+            overlay_lines: Vec::new(),
+            change: Default::default(),
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Draws the gizmos of the `Gizmos` resource. Depth tested gizmos are drawn first, then the
+/// gizmos without depth test on top of them.
+#[derive(Debug)]
+pub struct DrawGizmos<B: Backend> {
+    pipelines: Vec<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    env: DynamicUniform<B, ViewArgs>,
+    args: DynamicUniform<B, GizmosArgs>,
+    vertex: DynamicVertexBuffer<B, DebugLine>,
+    framebuffer_width: f32,
+    framebuffer_height: f32,
+    depth_lines: Vec<DebugLine>,
+    overlay_lines: Vec<DebugLine>,
+    change: util::ChangeDetection,
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawGizmos<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(resources).report_draws(&self.draws);
+
+        let (gizmos, line_params, transforms) = <(
+            Option<Write<'_, Gizmos>>,
+            Option<Read<'_, DebugLinesParams>>,
+            ReadStorage<'_, Transform>,
+        )>::fetch(resources);
+
+        // Text labels face the camera
+        let (right, up) = CameraGatherer::gather_camera_entity(resources)
+            .and_then(|camera| transforms.get(camera))
+            .map(|transform| {
+                let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+                (
+                    matrix.column(0).xyz().normalize(),
+                    matrix.column(1).xyz().normalize(),
+                )
+            })
+            .unwrap_or_else(|| (Vector3::x(), Vector3::y()));
+
+        let old_len = (self.depth_lines.len(), self.overlay_lines.len());
+        self.depth_lines.clear();
+        self.overlay_lines.clear();
+        if let Some(mut gizmos) = gizmos {
+            for gizmo in gizmos.iter() {
+                let lines = if gizmo.depth_test() {
+                    &mut self.depth_lines
+                } else {
+                    &mut self.overlay_lines
+                };
+                gizmo.lines(right, up, lines);
+            }
+            gizmos.mark_drawn();
+        }
+
+        let cam = CameraGatherer::gather(resources);
+        let line_width = line_params
+            .map(|p| p.line_width)
+            .unwrap_or(DebugLinesParams::default().line_width);
+
+        self.env.write(factory, index, cam.projview);
+        self.args.write(
+            factory,
+            index,
+            GizmosArgs {
+                screen_space_thickness: [
+                    (line_width * 2.0) / self.framebuffer_width,
+                    (line_width * 2.0) / self.framebuffer_height,
+                ]
+                .into(),
+            }
+            .std140(),
+        );
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+            let count = self.depth_lines.len() + self.overlay_lines.len();
+            self.vertex.write(
+                factory,
+                index,
+                count as u64,
+                &[&self.depth_lines, &self.overlay_lines],
+            );
+        }
+
+        let changed = old_len != (self.depth_lines.len(), self.overlay_lines.len());
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(resources);
+        self.draws.clear();
+
+        let depth_count = self.depth_lines.len() as u32;
+        let count = depth_count + self.overlay_lines.len() as u32;
+        if count == 0 {
+            stats.report_draws(&self.draws);
+            return;
+        }
+
+        let layout = &self.pipeline_layout;
+        self.vertex.bind(index, 0, 0, &mut encoder);
+        for (pipeline, instances) in self
+            .pipelines
+            .iter()
+            .zip(vec![0..depth_count, depth_count..count])
+        {
+            if instances.start == instances.end {
+                continue;
+            }
+            encoder.bind_graphics_pipeline(pipeline);
+            self.env.bind(index, layout, 0, &mut encoder);
+            self.args.bind(index, layout, 1, &mut encoder);
+            self.draws.record_draw(
+                hal::Primitive::TriangleStrip,
+                4,
+                instances.end - instances.start,
+            );
+            unsafe {
+                encoder.draw(0..4, instances);
+            }
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            for pipeline in self.pipelines {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_gizmos_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    samples: hal::image::NumSamples,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::DEBUG_LINES_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::DEBUG_LINES_FRAGMENT.module(factory).unwrap() };
+
+    let pipe_desc = PipelineDescBuilder::new()
+        .with_vertex_desc(&[(DebugLine::vertex(), pso::VertexInputRate::Instance(1))])
+        .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
+        .with_shaders(util::simple_shader_set(
+            &shader_vertex,
+            Some(&shader_fragment),
+        ))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_samples(samples)
+        .with_blend_targets(vec![pso::ColorBlendDesc {
+            mask: pso::ColorMask::ALL,
+            blend: Some(pso::BlendState::ALPHA),
+        }]);
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(pipe_desc.clone().with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::GreaterEqual,
+            write: false,
+        }))
+        .with_child_pipeline(0, pipe_desc)
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
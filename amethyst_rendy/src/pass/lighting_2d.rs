@@ -0,0 +1,709 @@
+use super::flat2d::{depth_key, insert_texture};
+use crate::{
+    batch::SortedBatch,
+    light_2d::SpriteMaterial,
+    mtl::MaterialDefaults,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::SpriteArgs,
+    resources::Tint,
+    sprite::{SpriteRender, SpriteSheet},
+    sprite_visibility::SpriteVisibility,
+    stats::{DrawCounts, RenderStats},
+    submodules::{DynamicVertexBuffer, FlatEnvironmentSub, Lighting2DSub, TextureId, TextureSub},
+    types::{Backend, Texture},
+    util,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::Swizzle,
+        image::{Filter, SamplerInfo, ViewKind, WrapMode},
+        pso,
+    },
+    mesh::AsVertex,
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::Shader,
+};
+use std::ops::Range;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Albedo, normal and emission textures of a sprite.
+type SpriteMaps = (TextureId, TextureId, TextureId);
+
+/// Describes drawing the normals and emission of sprites into the two color outputs of the 2D
+/// lighting buffers, which are composited over the sprites by `DrawLighting2DDesc`.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawLighting2DBuffersDesc;
+
+impl DrawLighting2DBuffersDesc {
+    /// Create instance of `DrawLighting2DBuffers` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawLighting2DBuffersDesc {
+    fn colors(&self) -> usize {
+        2
+    }
+
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _world: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = FlatEnvironmentSub::new(factory)?;
+        let textures = TextureSub::new(factory)?;
+
+        let (mut pipelines, pipeline_layout) = build_buffers_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![
+                env.raw_layout(),
+                textures.raw_layout(),
+                textures.raw_layout(),
+                textures.raw_layout(),
+            ],
+        )?;
+
+        Ok(Box::new(DrawLighting2DBuffers::<B> {
+            pipeline_transparent: pipelines.pop().unwrap(),
+            pipeline: pipelines.pop().unwrap(),
+            pipeline_layout,
+            env,
+            textures,
+            vertex: DynamicVertexBuffer::new(),
+            sprites: Default::default(),
+            transparent_sprites: Default::default(),
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Draws the normals and emission of sprites, rotated into the plane, into the 2D lighting
+/// buffers.
+///
+/// Opaque sprites are drawn first, then transparent sprites are blended over them back to front.
+#[derive(Debug)]
+pub struct DrawLighting2DBuffers<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_transparent: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: FlatEnvironmentSub<B>,
+    textures: TextureSub<B>,
+    vertex: DynamicVertexBuffer<B, SpriteArgs>,
+    sprites: SortedBatch<(SpriteMaps, u32), SpriteMaps, SpriteArgs>,
+    transparent_sprites: SortedBatch<(u32, SpriteMaps), SpriteMaps, SpriteArgs>,
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawLighting2DBuffers<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (
+            sprite_sheet_storage,
+            tex_storage,
+            material_defaults,
+            visibility,
+            sprite_renders,
+            sprite_materials,
+            transforms,
+            tints,
+        ) = <(
+            Read<'_, AssetStorage<SpriteSheet>>,
+            Read<'_, AssetStorage<Texture>>,
+            ReadExpect<'_, MaterialDefaults>,
+            ReadExpect<'_, SpriteVisibility>,
+            ReadStorage<'_, SpriteRender>,
+            ReadStorage<'_, SpriteMaterial>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Tint>,
+        )>::fetch(world);
+
+        self.env.process(factory, index, world);
+
+        self.sprites.swap_clear();
+        self.transparent_sprites.swap_clear();
+
+        let textures_ref = &mut self.textures;
+        let mut last_texture = None;
+        let mut sprite_maps = |sprite_render: &SpriteRender,
+                               global: &Transform,
+                               tint: Option<&Tint>,
+                               material: Option<&SpriteMaterial>| {
+            let (batch_data, texture) = SpriteArgs::from_data(
+                &tex_storage,
+                &sprite_sheet_storage,
+                sprite_render,
+                global,
+                tint,
+            )?;
+            let (albedo, _) =
+                insert_texture(textures_ref, &mut last_texture, factory, world, texture)?;
+            let normal = insert_map(
+                textures_ref,
+                factory,
+                world,
+                material.and_then(|m| m.normal.as_ref()),
+                &material_defaults.0.normal,
+            )?;
+            let emission = insert_map(
+                textures_ref,
+                factory,
+                world,
+                material.and_then(|m| m.emission.as_ref()),
+                &material_defaults.0.emission,
+            )?;
+            Some(((albedo, normal, emission), batch_data))
+        };
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("gather_sprites");
+
+            let sprites_ref = &mut self.sprites;
+            (
+                &sprite_renders,
+                &transforms,
+                tints.maybe(),
+                sprite_materials.maybe(),
+                &visibility.visible_unordered,
+            )
+                .join()
+                .filter_map(|(sprite_render, global, tint, material, _)| {
+                    sprite_maps(sprite_render, global, tint, material)
+                })
+                .for_each(|(maps, batch_data)| {
+                    // Front to back, so that hidden fragments fail the depth test
+                    let key = (maps, !depth_key(batch_data.depth));
+                    sprites_ref.insert(maps, key, batch_data);
+                });
+
+            let transparent_ref = &mut self.transparent_sprites;
+            let mut joined = (
+                &sprite_renders,
+                &transforms,
+                tints.maybe(),
+                sprite_materials.maybe(),
+            )
+                .join();
+            visibility
+                .visible_ordered
+                .iter()
+                .filter_map(|e| joined.get_unchecked(e.id()))
+                .filter_map(|(sprite_render, global, tint, material)| {
+                    sprite_maps(sprite_render, global, tint, material)
+                })
+                .for_each(|(maps, batch_data)| {
+                    let key = (depth_key(batch_data.depth), maps);
+                    transparent_ref.insert(maps, key, batch_data);
+                });
+        }
+
+        self.textures.maintain(factory, world);
+        self.sprites.sort();
+        self.transparent_sprites.sort();
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.vertex.write(
+                factory,
+                index,
+                (self.sprites.count() + self.transparent_sprites.count()) as u64,
+                self.sprites
+                    .data()
+                    .chain(self.transparent_sprites.data())
+                    .map(std::slice::from_ref),
+            );
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(world);
+        self.draws.clear();
+
+        let layout = &self.pipeline_layout;
+        let textures = &self.textures;
+        let draws = &mut self.draws;
+        if !self.vertex.bind(index, 0, 0, &mut encoder) {
+            return;
+        }
+
+        let mut draw =
+            |maps: SpriteMaps, range: Range<u32>, encoder: &mut RenderPassEncoder<'_, B>| {
+                let (albedo, normal, emission) = maps;
+                if textures.loaded(albedo) && textures.loaded(normal) && textures.loaded(emission) {
+                    textures.bind(layout, 1, albedo, encoder);
+                    textures.bind(layout, 2, normal, encoder);
+                    textures.bind(layout, 3, emission, encoder);
+                    draws.record_draw(hal::Primitive::TriangleStrip, 4, range.len() as u32);
+                    unsafe {
+                        encoder.draw(0..4, range);
+                    }
+                }
+            };
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, layout, 0, &mut encoder);
+        for (&maps, range) in self.sprites.iter() {
+            draw(maps, range, &mut encoder);
+        }
+
+        // Transparent sprites are stored after the opaque ones in the vertex buffer.
+        let offset = self.sprites.count() as u32;
+        encoder.bind_graphics_pipeline(&self.pipeline_transparent);
+        self.env.bind(index, layout, 0, &mut encoder);
+        for (&maps, range) in self.transparent_sprites.iter() {
+            draw(maps, range.start + offset..range.end + offset, &mut encoder);
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_transparent);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Inserts the map of a sprite material, falling back to the default texture when the material
+/// doesn't set it or it isn't loaded yet.
+fn insert_map<B: Backend>(
+    textures: &mut TextureSub<B>,
+    factory: &Factory<B>,
+    world: &World,
+    handle: Option<&Handle<Texture>>,
+    default: &Handle<Texture>,
+) -> Option<TextureId> {
+    let layout = hal::image::Layout::ShaderReadOnlyOptimal;
+    handle
+        .and_then(|handle| textures.insert(factory, world, handle, layout))
+        .or_else(|| textures.insert(factory, world, default, layout))
+        .map(|(id, _)| id)
+}
+
+fn build_buffers_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::SPRITE_LIGHTING_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::SPRITE_LIGHTING_FRAGMENT.module(factory).unwrap() };
+
+    let pipe_desc = PipelineDescBuilder::new()
+        .with_vertex_desc(&[(SpriteArgs::vertex(), pso::VertexInputRate::Instance(1))])
+        .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
+        .with_shaders(util::simple_shader_set(
+            &shader_vertex,
+            Some(&shader_fragment),
+        ))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_blend_targets(vec![pso::ColorBlendDesc::EMPTY; 2])
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::Greater,
+            write: true,
+        });
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(pipe_desc.clone())
+        .with_child_pipeline(
+            0,
+            pipe_desc
+                .with_blend_targets(vec![
+                    pso::ColorBlendDesc {
+                        mask: pso::ColorMask::ALL,
+                        blend: Some(pso::BlendState::ALPHA),
+                    };
+                    2
+                ])
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Greater,
+                    write: false,
+                }),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
+
+/// Describes compositing the 2D lighting over the sprites of the target it's added to.
+///
+/// The color of the target is multiplied with the ambient light plus the light of the
+/// `Light2D`s, evaluated with the normals of the lighting buffers and shadowed by the
+/// `Occluder2D`s, then the emission of the lighting buffers is added. The normal and emission
+/// images of the buffers are added to the group with `builder().with_image(id)`, in that order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawLighting2DDesc {
+    depth: bool,
+    samples: hal::image::NumSamples,
+}
+
+impl DrawLighting2DDesc {
+    /// Create instance of `DrawLighting2D` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set whether the target the group is added to has a depth output. Depth isn't tested.
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+impl Default for DrawLighting2DDesc {
+    fn default() -> Self {
+        Self {
+            depth: false,
+            samples: 1,
+        }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawLighting2DDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            2
+        ]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _world: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        if images.len() != 2 {
+            return Err(failure::format_err!(
+                "2D lighting expects the normal and emission images, got {} images",
+                images.len()
+            ));
+        }
+
+        let layout: RendyHandle<DescriptorSetLayout<B>> = set_layout! {
+            factory,
+            [1] Sampler pso::ShaderStageFlags::FRAGMENT,
+            [2] SampledImage pso::ShaderStageFlags::FRAGMENT
+        };
+        let set = factory.create_descriptor_set(layout.clone())?;
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+        let views = images
+            .iter()
+            .map(|node_image| {
+                let image = ctx.get_image(node_image.id).ok_or_else(|| {
+                    failure::format_err!("Input image {:?} doesn't exist", node_image.id)
+                })?;
+                let view = factory.create_image_view(
+                    image.clone(),
+                    ImageViewInfo {
+                        view_kind: ViewKind::D2,
+                        format: image.format(),
+                        swizzle: Swizzle::NO,
+                        range: node_image.range.clone(),
+                    },
+                )?;
+                Ok((view, node_image.layout))
+            })
+            .collect::<Result<Vec<_>, failure::Error>>()?;
+
+        unsafe {
+            let raw_set = set.raw();
+            let mut writes = vec![util::desc_write(
+                raw_set,
+                0,
+                pso::Descriptor::Sampler(sampler.raw()),
+            )];
+            for (i, (view, layout)) in views.iter().enumerate() {
+                writes.push(util::desc_write(
+                    raw_set,
+                    i as u32 + 1,
+                    pso::Descriptor::Image(view.raw(), *layout),
+                ));
+            }
+            factory.write_descriptor_sets(writes);
+        }
+
+        let lighting = Lighting2DSub::new(factory)?;
+        let (mut pipelines, pipeline_layout) = build_composite_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.samples,
+            vec![layout.raw(), lighting.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawLighting2D::<B> {
+            pipeline_emission: pipelines.pop().unwrap(),
+            pipeline: pipelines.pop().unwrap(),
+            pipeline_layout,
+            _layout: layout,
+            set,
+            _sampler: sampler,
+            _views: views.into_iter().map(|(view, _)| view).collect(),
+            lighting,
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Composites the 2D lighting over the sprites of a render target.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawLighting2D<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_emission: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    lighting: Lighting2DSub<B>,
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawLighting2D<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        self.lighting.process(factory, index, world);
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(world);
+        self.draws.clear();
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                0,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+        self.lighting.bind(index, layout, 1, &mut encoder);
+        self.draws.record_draw(hal::Primitive::TriangleList, 3, 1);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+
+        encoder.bind_graphics_pipeline(&self.pipeline_emission);
+        self.draws.record_draw(hal::Primitive::TriangleList, 3, 1);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                0,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+            encoder.draw(0..3, 0..1);
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_emission);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_composite_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    samples: hal::image::NumSamples,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+    let shader_light = unsafe { super::LIGHTING_2D_FRAGMENT.module(factory).unwrap() };
+    let shader_emission = unsafe {
+        super::LIGHTING_2D_EMISSION_FRAGMENT
+            .module(factory)
+            .unwrap()
+    };
+
+    // The light multiplies the color of the sprites, then the emission is added on top.
+    let pipe_desc = PipelineDescBuilder::new()
+        .with_shaders(util::simple_shader_set(&shader_vertex, Some(&shader_light)))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_samples(samples)
+        .with_blend_targets(vec![pso::ColorBlendDesc {
+            mask: pso::ColorMask::ALL,
+            blend: Some(pso::BlendState::MULTIPLY),
+        }]);
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(pipe_desc.clone())
+        .with_child_pipeline(
+            0,
+            pipe_desc
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_emission),
+                ))
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ADD),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_light);
+        factory.destroy_shader_module(shader_emission);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
@@ -1,14 +1,27 @@
 //! Passes and shaders implemented by amethyst
 
 mod base_3d;
+mod color_grading;
+mod compute;
 mod debug_lines;
+mod decal;
 mod flat;
 mod flat2d;
+mod gizmos;
+mod lighting_2d;
+mod particles;
 mod pbr;
+mod post_process;
 mod shaded;
+mod shadow;
 mod skybox;
+mod timestamp;
 
-pub use self::{base_3d::*, debug_lines::*, flat::*, flat2d::*, pbr::*, shaded::*, skybox::*};
+pub use self::{
+    base_3d::*, color_grading::*, compute::*, debug_lines::*, decal::*, flat::*, flat2d::*,
+    gizmos::*, lighting_2d::*, particles::*, pbr::*, post_process::*, shaded::*, shadow::*,
+    skybox::*, timestamp::*,
+};
 
 use rendy::{hal::pso::ShaderStageFlags, shader::SpirvShader};
 
@@ -67,6 +80,42 @@ lazy_static::lazy_static! {
         "main",
     ).unwrap();
 
+    static ref DEBUG_POS_TEX_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/debug_pos_tex.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref DEBUG_POS_NORM_TEX_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/debug_pos_norm_tex.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref DEBUG_POS_NORM_TANG_TEX_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/debug_pos_norm_tang_tex.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref PBR_SHADOWED_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/pbr_shadowed.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref SHADOW_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/shadow.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref SHADOW_SKIN_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/shadow_skin.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
     static ref SPRITE_VERTEX: SpirvShader = SpirvShader::from_bytes(
         include_bytes!("../../compiled/vertex/sprite.vert.spv"),
         ShaderStageFlags::VERTEX,
@@ -79,6 +128,30 @@ lazy_static::lazy_static! {
         "main",
     ).unwrap();
 
+    static ref SPRITE_LIGHTING_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/sprite_lighting.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref SPRITE_LIGHTING_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/sprite_lighting.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref LIGHTING_2D_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/lighting_2d.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref LIGHTING_2D_EMISSION_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/lighting_2d_emission.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
     static ref SKYBOX_VERTEX: SpirvShader = SpirvShader::from_bytes(
         include_bytes!("../../compiled/vertex/skybox.vert.spv"),
         ShaderStageFlags::VERTEX,
@@ -102,4 +175,64 @@ lazy_static::lazy_static! {
         ShaderStageFlags::FRAGMENT,
         "main",
     ).unwrap();
+
+    static ref FULLSCREEN_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/fullscreen.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref BLOOM_EXTRACT_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/bloom_extract.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref BLUR_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/blur.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref TONE_MAP_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/tone_map.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref PARTICLE_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/particle.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref PARTICLE_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/particle.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/decal.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/decal.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref COLOR_GRADING_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/color_grading.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref COPY_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/copy.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
 }
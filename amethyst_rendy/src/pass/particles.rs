@@ -0,0 +1,316 @@
+use crate::{
+    particles::{ParticleBlend, ParticleEffect, ParticleEmitter},
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{IntoPod, ParticleArgs, ViewArgs},
+    stats::{DrawCounts, RenderStats},
+    submodules::{gather::CameraGatherer, DynamicUniform, DynamicVertexBuffer},
+    types::Backend,
+    util,
+};
+use amethyst_assets::AssetStorage;
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::AsVertex,
+    shader::Shader,
+};
+use std::cmp::Ordering;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw the particles of every `ParticleEmitter` as camera-facing billboards.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawParticlesDesc {
+    samples: hal::image::NumSamples,
+}
+
+impl DrawParticlesDesc {
+    /// Create instance of `DrawParticles` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawParticlesDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = DynamicUniform::new(factory, pso::ShaderStageFlags::VERTEX)?;
+        let vertex = DynamicVertexBuffer::new();
+
+        let (pipelines, pipeline_layout) = build_particle_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.samples,
+            vec![env.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawParticles::<B> {
+            pipelines,
+            pipeline_layout,
+            env,
+            vertex,
+            alpha: Vec::new(),
+            additive: Vec::new(),
+            change: Default::default(),
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Draws the particles of every `ParticleEmitter`. Alpha blended particles are sorted from back
+/// to front and drawn first, then the additive particles, which don't need sorting.
+#[derive(Debug)]
+pub struct DrawParticles<B: Backend> {
+    pipelines: Vec<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    env: DynamicUniform<B, ViewArgs>,
+    vertex: DynamicVertexBuffer<B, ParticleArgs>,
+    alpha: Vec<ParticleArgs>,
+    additive: Vec<ParticleArgs>,
+    change: util::ChangeDetection,
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawParticles<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(resources).report_draws(&self.draws);
+
+        let (effects, emitters, transforms) = <(
+            Read<'_, AssetStorage<ParticleEffect>>,
+            ReadStorage<'_, ParticleEmitter>,
+            ReadStorage<'_, Transform>,
+        )>::fetch(resources);
+
+        let camera_position = CameraGatherer::gather_camera_entity(resources)
+            .and_then(|camera| transforms.get(camera))
+            .map(|transform| {
+                let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+                Point3::from(matrix.column(3).xyz())
+            })
+            .unwrap_or_else(Point3::origin);
+
+        let old_len = (self.alpha.len(), self.additive.len());
+        self.alpha.clear();
+        self.additive.clear();
+
+        let mut alpha = Vec::new();
+        for emitter in (&emitters).join() {
+            let blend = match effects.get(emitter.effect()) {
+                Some(effect) => effect.blend,
+                None => continue,
+            };
+            for particle in emitter.particles() {
+                let args = ParticleArgs {
+                    position: particle.position.coords.into_pod(),
+                    size: particle.size,
+                    color: particle.color.into(),
+                };
+                match blend {
+                    ParticleBlend::Alpha => {
+                        alpha.push(((particle.position - camera_position).norm_squared(), args))
+                    }
+                    ParticleBlend::Additive => self.additive.push(args),
+                }
+            }
+        }
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("sort");
+            alpha.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+            self.alpha.extend(alpha.into_iter().map(|(_, args)| args));
+        }
+
+        let cam = CameraGatherer::gather(resources);
+        self.env.write(factory, index, cam.projview);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+            let count = self.alpha.len() + self.additive.len();
+            self.vertex
+                .write(factory, index, count as u64, &[&self.alpha, &self.additive]);
+        }
+
+        let changed = old_len != (self.alpha.len(), self.additive.len());
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(resources);
+        self.draws.clear();
+
+        let alpha_count = self.alpha.len() as u32;
+        let count = alpha_count + self.additive.len() as u32;
+        if count == 0 {
+            stats.report_draws(&self.draws);
+            return;
+        }
+
+        let layout = &self.pipeline_layout;
+        self.vertex.bind(index, 0, 0, &mut encoder);
+        for (pipeline, instances) in self
+            .pipelines
+            .iter()
+            .zip(vec![0..alpha_count, alpha_count..count])
+        {
+            if instances.start == instances.end {
+                continue;
+            }
+            encoder.bind_graphics_pipeline(pipeline);
+            self.env.bind(index, layout, 0, &mut encoder);
+            self.draws.record_draw(
+                hal::Primitive::TriangleStrip,
+                4,
+                instances.end - instances.start,
+            );
+            unsafe {
+                encoder.draw(0..4, instances);
+            }
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            for pipeline in self.pipelines {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_particle_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    samples: hal::image::NumSamples,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::PARTICLE_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::PARTICLE_FRAGMENT.module(factory).unwrap() };
+
+    let pipe_desc = PipelineDescBuilder::new()
+        .with_vertex_desc(&[(ParticleArgs::vertex(), pso::VertexInputRate::Instance(1))])
+        .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
+        .with_shaders(util::simple_shader_set(
+            &shader_vertex,
+            Some(&shader_fragment),
+        ))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_samples(samples)
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::GreaterEqual,
+            write: false,
+        });
+
+    let additive = pso::BlendState {
+        color: pso::BlendOp::Add {
+            src: pso::Factor::SrcAlpha,
+            dst: pso::Factor::One,
+        },
+        alpha: pso::BlendOp::Add {
+            src: pso::Factor::Zero,
+            dst: pso::Factor::One,
+        },
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            pipe_desc
+                .clone()
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ALPHA),
+                }]),
+        )
+        .with_child_pipeline(
+            0,
+            pipe_desc.with_blend_targets(vec![pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: Some(additive),
+            }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
@@ -20,6 +20,15 @@ impl Base3DPassDef for PbrPassDef {
     fn fragment_shader() -> &'static SpirvShader {
         &super::PBR_FRAGMENT
     }
+    fn shadowed_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::PBR_SHADOWED_FRAGMENT)
+    }
+    fn debug_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::DEBUG_POS_NORM_TANG_TEX_FRAGMENT)
+    }
+    fn image_based_lighting() -> bool {
+        true
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![
             Position::vertex(),
@@ -0,0 +1,480 @@
+use crate::{
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    stats::{DrawCounts, RenderStats},
+    types::Backend,
+    util,
+    viewport::ViewportRect,
+};
+use amethyst_core::ecs::{Read, SystemData, World};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::Swizzle,
+        image::{Filter, SamplerInfo, ViewKind, WrapMode},
+        pso,
+    },
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle, ImageView, ImageViewInfo, Sampler,
+    },
+    shader::{Shader, SpirvShader},
+};
+use std::sync::Arc;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Parameters handed to a fullscreen shader as a push constant block of two `vec4`s.
+pub type FullscreenParams = [[f32; 4]; 2];
+
+/// Operator mapping HDR colors to the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Clamp colors without any tone mapping.
+    None,
+    /// Simple Reinhard operator.
+    Reinhard,
+    /// Fit of the ACES filmic curve.
+    Aces,
+    /// Hable's filmic curve known from Uncharted 2.
+    Filmic,
+}
+
+/// Resource controlling the built-in post-processing effects of `RenderPostProcess`.
+/// The defaults are used when the resource is missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostProcessSettings {
+    /// Multiplier applied to the HDR colors before tone mapping.
+    pub exposure: f32,
+    /// Operator mapping HDR colors to the displayable range.
+    pub tone_mapping: ToneMapping,
+    /// Brightness above which pixels contribute to bloom.
+    pub bloom_threshold: f32,
+    /// Strength of the bloom added to the image, 0.0 disables it.
+    pub bloom_intensity: f32,
+    /// Darkening of the image corners in 0.0-1.0, 0.0 disables it.
+    pub vignette_intensity: f32,
+    /// Share of the distance from the corners over which the vignette fades out in 0.0-1.0.
+    pub vignette_smoothness: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            tone_mapping: ToneMapping::Aces,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.5,
+            vignette_intensity: 0.0,
+            vignette_smoothness: 0.5,
+        }
+    }
+}
+
+impl PostProcessSettings {
+    pub(crate) fn bloom_extract_params(&self) -> FullscreenParams {
+        [[self.bloom_threshold, 0.0, 0.0, 0.0], [0.0; 4]]
+    }
+
+    pub(crate) fn tone_map_params(&self) -> FullscreenParams {
+        let operator = match self.tone_mapping {
+            ToneMapping::None => 0.0,
+            ToneMapping::Reinhard => 1.0,
+            ToneMapping::Aces => 2.0,
+            ToneMapping::Filmic => 3.0,
+        };
+        [
+            [self.exposure, operator, self.bloom_intensity, 0.0],
+            [self.vignette_intensity, self.vignette_smoothness, 0.0, 0.0],
+        ]
+    }
+}
+
+/// Fragment shader copying an opaque image.
+pub fn copy_shader() -> SpirvShader {
+    super::COPY_FRAGMENT.clone()
+}
+
+/// Fragment shader extracting the bright parts of an image for bloom.
+pub fn bloom_extract_shader() -> SpirvShader {
+    super::BLOOM_EXTRACT_FRAGMENT.clone()
+}
+
+/// Fragment shader blurring an image in the direction given by `params[0][0..2]`.
+pub fn blur_shader() -> SpirvShader {
+    super::BLUR_FRAGMENT.clone()
+}
+
+/// Fragment shader combining an HDR image with its bloom, applying exposure, tone mapping and
+/// vignette.
+pub fn tone_map_shader() -> SpirvShader {
+    super::TONE_MAP_FRAGMENT.clone()
+}
+
+/// Describes drawing a fullscreen triangle with a fragment shader sampling the images of other
+/// render targets, e.g. a post-processing effect.
+///
+/// The fragment shader gets a linear clamping `sampler` at set 0 binding 0, the input images as
+/// `texture2D`s from binding 1 on and the params as a push constant block of two `vec4`s. The
+/// input images are added to the group with `builder().with_image(id)`, in binding order.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct DrawFullscreenDesc {
+    fragment: SpirvShader,
+    inputs: usize,
+    #[derivative(Debug = "ignore")]
+    params: Arc<dyn Fn(&World) -> FullscreenParams + Send + Sync>,
+    depth: bool,
+    samples: hal::image::NumSamples,
+    viewport: Option<ViewportRect>,
+}
+
+impl DrawFullscreenDesc {
+    /// Create a fullscreen render group with the given fragment shader sampling the given number
+    /// of input images.
+    pub fn new(fragment: SpirvShader, inputs: usize) -> Self {
+        Self {
+            fragment,
+            inputs,
+            params: Arc::new(|_| [[0.0; 4]; 2]),
+            depth: false,
+            samples: 1,
+            viewport: None,
+        }
+    }
+
+    /// Set the function computing the shader params every frame.
+    pub fn with_params(
+        mut self,
+        params: impl Fn(&World) -> FullscreenParams + Send + Sync + 'static,
+    ) -> Self {
+        self.params = Arc::new(params);
+        self
+    }
+
+    /// Set whether the target the group is added to has a depth output. Depth isn't tested.
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Set the number of samples per pixel of the target the group is added to.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Draw into the given rectangle of the target instead of all of it. Nothing is drawn outside
+    /// of the rectangle.
+    pub fn with_viewport(mut self, viewport: ViewportRect) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawFullscreenDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            self.inputs
+        ]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _resources: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        if images.len() != self.inputs {
+            return Err(failure::format_err!(
+                "Fullscreen pass expects {} input images, got {}",
+                self.inputs,
+                images.len()
+            ));
+        }
+
+        let layout: Handle<DescriptorSetLayout<B>> = set_layout! {
+            factory,
+            [1] Sampler pso::ShaderStageFlags::FRAGMENT,
+            [self.inputs] SampledImage pso::ShaderStageFlags::FRAGMENT
+        };
+        let set = factory.create_descriptor_set(layout.clone())?;
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))?;
+        let views = images
+            .iter()
+            .map(|node_image| {
+                let image = ctx.get_image(node_image.id).ok_or_else(|| {
+                    failure::format_err!("Input image {:?} doesn't exist", node_image.id)
+                })?;
+                let view = factory.create_image_view(
+                    image.clone(),
+                    ImageViewInfo {
+                        view_kind: ViewKind::D2,
+                        format: image.format(),
+                        swizzle: Swizzle::NO,
+                        range: node_image.range.clone(),
+                    },
+                )?;
+                Ok((view, node_image.layout))
+            })
+            .collect::<Result<Vec<_>, failure::Error>>()?;
+
+        unsafe {
+            let raw_set = set.raw();
+            let mut writes = vec![util::desc_write(
+                raw_set,
+                0,
+                pso::Descriptor::Sampler(sampler.raw()),
+            )];
+            for (i, (view, layout)) in views.iter().enumerate() {
+                writes.push(util::desc_write(
+                    raw_set,
+                    i as u32 + 1,
+                    pso::Descriptor::Image(view.raw(), *layout),
+                ));
+            }
+            factory.write_descriptor_sets(writes);
+        }
+
+        let (pipeline, pipeline_layout) = build_fullscreen_pipeline(
+            factory,
+            &self.fragment,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.viewport
+                .map(|viewport| viewport.pixels(framebuffer_width, framebuffer_height)),
+            self.samples,
+            layout.raw(),
+        )?;
+
+        Ok(Box::new(DrawFullscreen::<B> {
+            pipeline,
+            pipeline_layout,
+            _layout: layout,
+            set,
+            _sampler: sampler,
+            _views: views.into_iter().map(|(view, _)| view).collect(),
+            params_fn: self.params,
+            params: [[0.0; 4]; 2],
+            change: Default::default(),
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Draws a fullscreen triangle with a fragment shader sampling other render targets.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawFullscreen<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    _layout: Handle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    _sampler: Handle<Sampler<B>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    #[derivative(Debug = "ignore")]
+    params_fn: Arc<dyn Fn(&World) -> FullscreenParams + Send + Sync>,
+    params: FullscreenParams,
+    change: util::ChangeDetection,
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawFullscreen<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(resources).report_draws(&self.draws);
+
+        let params = (self.params_fn)(resources);
+        let changed = params != self.params;
+        self.params = params;
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(resources);
+        self.draws.clear();
+
+        let constants = self
+            .params
+            .iter()
+            .flatten()
+            .map(|value| value.to_bits())
+            .collect::<Vec<_>>();
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.draws.record_draw(hal::Primitive::TriangleList, 3, 1);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                &self.pipeline_layout,
+                0,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+            encoder.push_constants(
+                &self.pipeline_layout,
+                pso::ShaderStageFlags::FRAGMENT,
+                0,
+                &constants,
+            );
+            encoder.draw(0..3, 0..1);
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Returns the params of the built-in effects from the `PostProcessSettings` resource.
+pub(crate) fn settings_params(
+    world: &World,
+    params: fn(&PostProcessSettings) -> FullscreenParams,
+) -> FullscreenParams {
+    <Option<Read<'_, PostProcessSettings>>>::fetch(world)
+        .map(|settings| params(&settings))
+        .unwrap_or_else(|| params(&PostProcessSettings::default()))
+}
+
+fn build_fullscreen_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    fragment: &SpirvShader,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    viewport: Option<pso::Rect>,
+    samples: hal::image::NumSamples,
+    layout: &B::DescriptorSetLayout,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(Some(layout), Some((pso::ShaderStageFlags::FRAGMENT, 0..8)))
+    }?;
+
+    let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+    let shader_fragment = match unsafe { fragment.module(factory) } {
+        Ok(module) => module,
+        Err(e) => {
+            unsafe {
+                factory.destroy_shader_module(shader_vertex);
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e);
+        }
+    };
+
+    let mut desc = PipelineDescBuilder::new()
+        .with_shaders(util::simple_shader_set(
+            &shader_vertex,
+            Some(&shader_fragment),
+        ))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_samples(samples)
+        .with_blend_targets(vec![pso::ColorBlendDesc {
+            mask: pso::ColorMask::ALL,
+            blend: None,
+        }]);
+    if let Some(rect) = viewport {
+        desc.set_baked_states(pso::BakedStates {
+            viewport: Some(pso::Viewport {
+                rect,
+                depth: 0.0..1.0,
+            }),
+            scissor: Some(rect),
+            ..Default::default()
+        });
+    }
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(desc)
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_map_params_encode_settings() {
+        let settings = PostProcessSettings {
+            exposure: 2.0,
+            tone_mapping: ToneMapping::Filmic,
+            bloom_intensity: 0.25,
+            vignette_intensity: 0.5,
+            vignette_smoothness: 0.75,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.tone_map_params(),
+            [[2.0, 3.0, 0.25, 0.0], [0.5, 0.75, 0.0, 0.0]]
+        );
+        assert_eq!(settings.bloom_extract_params()[0][0], 1.0);
+    }
+}
@@ -23,6 +23,9 @@ impl Base3DPassDef for ShadedPassDef {
     fn fragment_shader() -> &'static SpirvShader {
         &super::SHADED_FRAGMENT
     }
+    fn debug_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::DEBUG_POS_NORM_TEX_FRAGMENT)
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![Position::vertex(), Normal::vertex(), TexCoord::vertex()]
     }
@@ -0,0 +1,409 @@
+use crate::{
+    batch::{GroupIterator, OneLevelBatch},
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{SkinnedVertexArgs, VertexArgs},
+    resources::Tint,
+    shadow::{shadow_config, ShadowViews},
+    skinning::{JointCombined, JointTransforms},
+    stats::{DrawCounts, RenderStats},
+    submodules::{DynamicVertexBuffer, SkinningSub},
+    types::{Backend, Mesh},
+    util,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    transform::Transform,
+    Hidden, HiddenPropagate,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::{AsVertex, Position, VertexFormat},
+    shader::Shader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw the depth of static and skinned meshes into the shadow map atlas, once for every shadow
+/// view of the lights casting shadows.
+///
+/// Skinned meshes are posed in the vertex shader from the same joint matrices as the color passes.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawShadowsDesc;
+
+impl DrawShadowsDesc {
+    /// Create instance of `DrawShadows` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawShadowsDesc {
+    fn colors(&self) -> usize {
+        0
+    }
+
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let skinning = SkinningSub::new(factory)?;
+        let vertex_format = vec![Position::vertex()];
+        let vertex_format_skinned = vec![Position::vertex(), JointCombined::vertex()];
+        let (mut pipelines, pipeline_layout) = build_shadow_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format,
+            &vertex_format_skinned,
+            skinning.raw_layout(),
+        )?;
+
+        Ok(Box::new(DrawShadows::<B> {
+            pipeline_skinned: pipelines.pop().unwrap(),
+            pipeline: pipelines.pop().unwrap(),
+            pipeline_layout,
+            vertex_format,
+            vertex_format_skinned,
+            atlas: (framebuffer_width, framebuffer_height),
+            batches: Default::default(),
+            skinned_batches: Default::default(),
+            models: DynamicVertexBuffer::new(),
+            skinned_models: DynamicVertexBuffer::new(),
+            skinning,
+            views: Vec::new(),
+            draws: DrawCounts::default(),
+        }))
+    }
+}
+
+/// Draws the depth of static and skinned meshes into the shadow map atlas.
+#[derive(Debug)]
+pub struct DrawShadows<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_skinned: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    vertex_format: Vec<VertexFormat>,
+    vertex_format_skinned: Vec<VertexFormat>,
+    atlas: (u32, u32),
+    batches: OneLevelBatch<u32, VertexArgs>,
+    skinned_batches: OneLevelBatch<u32, SkinnedVertexArgs>,
+    models: DynamicVertexBuffer<B, VertexArgs>,
+    skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    skinning: SkinningSub<B>,
+    views: Vec<(pso::Rect, [u32; 16])>,
+    draws: DrawCounts,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawShadows<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (mesh_storage, hiddens, hiddens_prop, meshes, transforms, joints, tints) =
+            <(
+                Read<'_, AssetStorage<Mesh>>,
+                ReadStorage<'_, Hidden>,
+                ReadStorage<'_, HiddenPropagate>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, JointTransforms>,
+                ReadStorage<'_, Tint>,
+            )>::fetch(world);
+
+        let atlas = self.atlas;
+        self.views = ShadowViews::gather(world, &shadow_config(world))
+            .views
+            .iter()
+            .map(|view| {
+                let (x, y, size) = view.tile;
+                let rect = pso::Rect {
+                    x: x as i16,
+                    y: y as i16,
+                    w: size as i16,
+                    h: size as i16,
+                };
+                let proj_view: [[f32; 4]; 4] = view.tile_proj_view(atlas).into();
+                let mut constants = [0; 16];
+                for (constant, value) in constants.iter_mut().zip(proj_view.iter().flatten()) {
+                    *constant = value.to_bits();
+                }
+                (rect, constants)
+            })
+            .collect();
+
+        self.batches.clear_inner();
+        self.skinned_batches.clear_inner();
+        if !self.views.is_empty() {
+            let batches_ref = &mut self.batches;
+            let skinned_ref = &mut self.skinned_batches;
+            let skinning_ref = &mut self.skinning;
+            (
+                (&meshes, &transforms, tints.maybe()),
+                !&joints,
+                !&hiddens,
+                !&hiddens_prop,
+            )
+                .join()
+                .map(|((mesh, tform, tint), _, _, _)| {
+                    (mesh.id(), VertexArgs::from_object_data(tform, tint))
+                })
+                .for_each_group(|mesh_id, data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        batches_ref.insert(mesh_id, data.drain(..));
+                    }
+                });
+            (
+                (&meshes, &transforms, tints.maybe(), &joints),
+                !&hiddens,
+                !&hiddens_prop,
+            )
+                .join()
+                .map(|((mesh, tform, tint, joints), _, _)| {
+                    (
+                        mesh.id(),
+                        SkinnedVertexArgs::from_object_data(
+                            tform,
+                            tint,
+                            skinning_ref.insert(joints),
+                        ),
+                    )
+                })
+                .for_each_group(|mesh_id, data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        skinned_ref.insert(mesh_id, data.drain(..));
+                    }
+                });
+        }
+        self.batches.prune();
+        self.skinned_batches.prune();
+
+        self.models.write(
+            factory,
+            index,
+            self.batches.count() as u64,
+            self.batches.data(),
+        );
+        self.skinned_models.write(
+            factory,
+            index,
+            self.skinned_batches.count() as u64,
+            self.skinned_batches.data(),
+        );
+        self.skinning.commit(factory, index);
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if self.views.is_empty() {
+            return;
+        }
+
+        let (mesh_storage, stats) =
+            <(Read<'_, AssetStorage<Mesh>>, Read<'_, RenderStats>)>::fetch(world);
+        self.draws.clear();
+        let models_loc = self.vertex_format.len() as u32;
+        let skinned_models_loc = self.vertex_format_skinned.len() as u32;
+
+        if self.batches.count() > 0 && self.models.bind(index, models_loc, 0, &mut encoder) {
+            encoder.bind_graphics_pipeline(&self.pipeline);
+            for (rect, constants) in &self.views {
+                self.set_view(rect, constants, &mut encoder);
+                for (&mesh_id, range) in self.batches.iter() {
+                    debug_assert!(mesh_storage.contains_id(mesh_id));
+                    if let Some(mesh) =
+                        B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(mesh_id) })
+                    {
+                        self.draws.record_mesh_draw(mesh, range.len() as u32);
+                        mesh.bind_and_draw(0, &self.vertex_format, range, &mut encoder)
+                            .unwrap();
+                    }
+                }
+            }
+        }
+
+        if self.skinned_batches.count() > 0
+            && self
+                .skinned_models
+                .bind(index, skinned_models_loc, 0, &mut encoder)
+        {
+            encoder.bind_graphics_pipeline(&self.pipeline_skinned);
+            self.skinning
+                .bind(index, &self.pipeline_layout, 0, &mut encoder);
+            for (rect, constants) in &self.views {
+                self.set_view(rect, constants, &mut encoder);
+                for (&mesh_id, range) in self.skinned_batches.iter() {
+                    debug_assert!(mesh_storage.contains_id(mesh_id));
+                    if let Some(mesh) =
+                        B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(mesh_id) })
+                    {
+                        self.draws.record_mesh_draw(mesh, range.len() as u32);
+                        mesh.bind_and_draw(0, &self.vertex_format_skinned, range, &mut encoder)
+                            .unwrap();
+                    }
+                }
+            }
+        }
+        stats.report_draws(&self.draws);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_skinned);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+impl<B: Backend> DrawShadows<B> {
+    /// Restrict drawing to the tile of a shadow view and upload its projection.
+    fn set_view(
+        &self,
+        rect: &pso::Rect,
+        constants: &[u32; 16],
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        unsafe {
+            encoder.set_scissors(0, Some(rect));
+            encoder.push_constants(
+                &self.pipeline_layout,
+                pso::ShaderStageFlags::VERTEX,
+                0,
+                constants,
+            );
+        }
+    }
+}
+
+fn build_shadow_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format: &[VertexFormat],
+    vertex_format_skinned: &[VertexFormat],
+    skinning_layout: &B::DescriptorSetLayout,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory.device().create_pipeline_layout(
+            Some(skinning_layout),
+            Some((pso::ShaderStageFlags::VERTEX, 0..64)),
+        )
+    }?;
+
+    let vertex_desc = vertex_format
+        .iter()
+        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            VertexArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
+
+    let vertex_desc_skinned = vertex_format_skinned
+        .iter()
+        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            SkinnedVertexArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
+
+    let shader_vertex = unsafe { super::SHADOW_VERTEX.module(factory).unwrap() };
+    let shader_vertex_skinned = unsafe { super::SHADOW_SKIN_VERTEX.module(factory).unwrap() };
+
+    // Every shadow view is drawn into its own tile of the atlas, which is selected with a
+    // dynamic scissor while the viewport covers the whole atlas.
+    let pipe_desc = PipelineDescBuilder::new()
+        .with_vertex_desc(&vertex_desc)
+        .with_shaders(util::simple_shader_set(&shader_vertex, None))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_baked_states(pso::BakedStates {
+            viewport: Some(pso::Viewport {
+                rect: pso::Rect {
+                    x: 0,
+                    y: 0,
+                    w: framebuffer_width as i16,
+                    h: framebuffer_height as i16,
+                },
+                depth: 0.0..1.0,
+            }),
+            scissor: None,
+            blend_color: None,
+            depth_bounds: None,
+        })
+        .with_face_culling(pso::Face::NONE)
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::Less,
+            write: true,
+        })
+        .with_blend_targets(Vec::new());
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(pipe_desc.clone())
+        .with_child_pipeline(
+            0,
+            pipe_desc
+                .with_vertex_desc(&vertex_desc_skinned)
+                .with_shaders(util::simple_shader_set(&shader_vertex_skinned, None)),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_vertex_skinned);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
@@ -3,7 +3,8 @@ use crate::{
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::IntoPod,
     shape::Shape,
-    submodules::{DynamicUniform, FlatEnvironmentSub},
+    stats::{DrawCounts, RenderStats},
+    submodules::{DynamicUniform, EnvironmentImage, EnvironmentMapSub, FlatEnvironmentSub},
     types::Backend,
     util,
 };
@@ -56,11 +57,13 @@ impl SkyboxSettings {
     }
 }
 
-/// Describe drawing a skybox around the camera view
+/// Describe drawing a skybox around the camera view. The skybox shows the environment map of the
+/// `Skybox` resource once loaded, and a gradient between the nadir and zenith colors otherwise.
 #[derive(Clone, Debug, PartialEq, Derivative)]
 #[derivative(Default(bound = ""))]
 pub struct DrawSkyboxDesc {
     default_settings: SkyboxSettings,
+    samples: hal::image::NumSamples,
 }
 
 impl DrawSkyboxDesc {
@@ -76,8 +79,15 @@ impl DrawSkyboxDesc {
                 nadir_color,
                 zenith_color,
             },
+            samples: 1,
         }
     }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
@@ -98,6 +108,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
 
         let env = FlatEnvironmentSub::new(factory)?;
         let colors = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+        let environment_map = EnvironmentMapSub::new(factory, queue, EnvironmentImage::Radiance)?;
         let mesh = Shape::Sphere(16, 16)
             .generate::<Vec<PosTex>>(None)
             .build(queue, factory)?;
@@ -107,7 +118,12 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
             subpass,
             framebuffer_width,
             framebuffer_height,
-            vec![env.raw_layout(), colors.raw_layout()],
+            self.samples,
+            vec![
+                env.raw_layout(),
+                colors.raw_layout(),
+                environment_map.raw_layout(),
+            ],
         )?;
 
         Ok(Box::new(DrawSkybox::<B> {
@@ -115,8 +131,10 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
             pipeline_layout,
             env,
             colors,
+            environment_map,
             mesh,
             default_settings: self.default_settings,
+            draws: DrawCounts::default(),
         }))
     }
 }
@@ -128,8 +146,10 @@ pub struct DrawSkybox<B: Backend> {
     pipeline_layout: B::PipelineLayout,
     env: FlatEnvironmentSub<B>,
     colors: DynamicUniform<B, SkyboxUniform>,
+    environment_map: EnvironmentMapSub<B>,
     mesh: Mesh<B>,
     default_settings: SkyboxSettings,
+    draws: DrawCounts,
 }
 
 impl<B: Backend> RenderGroup<B, World> for DrawSkybox<B> {
@@ -144,12 +164,16 @@ impl<B: Backend> RenderGroup<B, World> for DrawSkybox<B> {
         #[cfg(feature = "profiler")]
         profile_scope!("prepare");
 
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(resources).report_draws(&self.draws);
+
         let settings = <Option<Read<'_, SkyboxSettings>>>::fetch(resources)
             .map(|s| s.uniform())
             .unwrap_or_else(|| self.default_settings.uniform());
 
         self.env.process(factory, index, resources);
         let changed = self.colors.write(factory, index, settings);
+        let changed = self.environment_map.process(factory, index, resources) || changed;
 
         if changed {
             PrepareResult::DrawRecord
@@ -163,20 +187,27 @@ impl<B: Backend> RenderGroup<B, World> for DrawSkybox<B> {
         mut encoder: RenderPassEncoder<'_, B>,
         index: usize,
         _subpass: hal::pass::Subpass<'_, B>,
-        _resources: &World,
+        resources: &World,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("draw");
+
+        let stats = <Read<'_, RenderStats>>::fetch(resources);
+        self.draws.clear();
         encoder.bind_graphics_pipeline(&self.pipeline);
         self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
         self.colors
             .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        self.environment_map
+            .bind(index, &self.pipeline_layout, 2, &mut encoder);
         self.mesh
             .bind(0, &[PosTex::vertex()], &mut encoder)
             .unwrap();
+        self.draws.record_mesh_draw(&self.mesh, 1);
         unsafe {
             encoder.draw(0..self.mesh.len(), 0..1);
         }
+        stats.report_draws(&self.draws);
     }
 
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
@@ -194,6 +225,7 @@ fn build_skybox_pipeline<B: Backend>(
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
+    samples: hal::image::NumSamples,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
@@ -216,6 +248,7 @@ fn build_skybox_pipeline<B: Backend>(
                 .with_layout(&pipeline_layout)
                 .with_subpass(subpass)
                 .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_samples(samples)
                 .with_depth_test(pso::DepthTest {
                     fun: pso::Comparison::GreaterEqual,
                     write: false,
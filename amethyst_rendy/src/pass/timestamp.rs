@@ -0,0 +1,182 @@
+use crate::{stats::RenderStats, types::Backend};
+use amethyst_core::ecs::World;
+use derivative::Derivative;
+use rendy::{
+    command::{
+        CommandPool, Family, Graphics, IndividualReset, MultiShot, NoSimultaneousUse, Submit,
+    },
+    factory::Factory,
+    frame::{cirque::CommandCirque, Frames},
+    graph::{GraphContext, Node, NodeBuffer, NodeDesc, NodeImage, NodeSubmittable},
+    hal::{command::RawCommandBuffer, device::Device, pso, query},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Describes a render graph node writing a GPU timestamp once the nodes it depends on are done,
+/// which is read back into the `RenderStats` resource. Added to the plan by the
+/// `RenderStatistics` plugin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteTimestampDesc {
+    name: Option<&'static str>,
+    period: f32,
+}
+
+impl WriteTimestampDesc {
+    /// Create a node marking the end of the target timed under the given name.
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name: Some(name),
+            period: 1.0,
+        }
+    }
+
+    /// Create a node marking the start of the frame, which the timed targets depend on.
+    pub fn frame_start() -> Self {
+        Self {
+            name: None,
+            period: 1.0,
+        }
+    }
+
+    /// Set the number of nanoseconds per timestamp tick of the device, 1.0 by default.
+    pub fn with_period(mut self, period: f32) -> Self {
+        self.period = period;
+        self
+    }
+}
+
+impl<B: Backend> NodeDesc<B, World> for WriteTimestampDesc {
+    type Node = WriteTimestamp<B>;
+
+    fn build<'a>(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _world: &World,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Self::Node, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        // One query per frame in flight, so a query is only reused after it has been read back.
+        let queries = ctx.frames_in_flight;
+        let query_pool = unsafe {
+            factory
+                .device()
+                .create_query_pool(query::Type::Timestamp, queries)
+        }?;
+        let pool = match factory.create_command_pool(family)?.with_capability() {
+            Ok(pool) => pool,
+            Err(_) => {
+                unsafe {
+                    factory.device().destroy_query_pool(query_pool);
+                }
+                return Err(failure::format_err!(
+                    "Timestamp nodes require a graphics queue"
+                ));
+            }
+        };
+        Ok(WriteTimestamp {
+            name: self.name,
+            period: self.period,
+            query_pool,
+            queries,
+            pool,
+            cirque: CommandCirque::new(),
+        })
+    }
+}
+
+/// Node writing a GPU timestamp.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct WriteTimestamp<B: Backend> {
+    name: Option<&'static str>,
+    period: f32,
+    #[derivative(Debug = "ignore")]
+    query_pool: B::QueryPool,
+    queries: u32,
+    pool: CommandPool<B, Graphics, IndividualReset>,
+    cirque: CommandCirque<B, Graphics>,
+}
+
+impl<'a, B: Backend> NodeSubmittable<'a, B> for WriteTimestamp<B> {
+    type Submittable = Submit<B>;
+    type Submittables = Option<Submit<B>>;
+}
+
+impl<B: Backend> Node<B, World> for WriteTimestamp<B> {
+    type Capability = Graphics;
+    type Desc = WriteTimestampDesc;
+
+    fn run<'a>(
+        &'a mut self,
+        _ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        world: &World,
+        frames: &'a Frames<B>,
+    ) -> Option<Submit<B>> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run");
+
+        let frame = frames.next().index();
+        let query = (frame % u64::from(self.queries)) as u32;
+
+        // The graph waits for the frame that last wrote the query before running the nodes.
+        if frame >= u64::from(self.queries) {
+            let mut data = [0; 8];
+            let available = unsafe {
+                factory.device().get_query_pool_results(
+                    &self.query_pool,
+                    query..query + 1,
+                    &mut data,
+                    8,
+                    query::ResultFlags::BITS_64,
+                )
+            };
+            if let (Ok(true), Some(mut stats)) = (available, world.try_fetch_mut::<RenderStats>()) {
+                let ticks = u64::from_ne_bytes(data);
+                let nanos = (ticks as f64 * f64::from(self.period)) as u64;
+                stats.record_timestamp(self.name, nanos);
+            }
+        }
+
+        let query_pool = &self.query_pool;
+        let submit = self.cirque.encode(frames, &mut self.pool, |cbuf| {
+            cbuf.or_reset(|cbuf| cbuf.reset()).init(|cbuf| {
+                let mut cbuf = cbuf.begin(MultiShot(NoSimultaneousUse), ());
+                unsafe {
+                    let raw = cbuf.raw();
+                    raw.reset_query_pool(query_pool, query..query + 1);
+                    raw.write_timestamp(
+                        pso::PipelineStage::BOTTOM_OF_PIPE,
+                        query::Query {
+                            pool: query_pool,
+                            id: query,
+                        },
+                    );
+                }
+                cbuf.finish()
+            })
+        });
+        Some(submit)
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _world: &World) {
+        let pool = &mut self.pool;
+        self.cirque.dispose(|buffer| {
+            buffer.either_with(
+                &mut *pool,
+                |pool, executable| pool.free_buffers(Some(executable)),
+                |pool, pending| pool.free_buffers(Some(pending.mark_complete())),
+            );
+        });
+        factory.destroy_command_pool(self.pool.with_queue_type());
+        factory.device().destroy_query_pool(self.query_pool);
+    }
+}
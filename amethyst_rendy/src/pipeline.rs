@@ -5,6 +5,7 @@ use rendy::{
     factory::Factory,
     hal::{
         device::Device,
+        image::NumSamples,
         pass::Subpass,
         pso::{
             AttributeDesc, BakedStates, BasePipeline, BlendDesc, ColorBlendDesc, DepthStencilDesc,
@@ -135,6 +136,27 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
         self.multisampling = multisampling;
     }
 
+    /// Build with multisampling using the provided number of samples per pixel, which must match
+    /// the samples of the render target. 1 or less disables multisampling.
+    pub fn with_samples(mut self, samples: NumSamples) -> Self {
+        self.set_samples(samples);
+        self
+    }
+    /// Set to use multisampling with the provided number of samples per pixel
+    pub fn set_samples(&mut self, samples: NumSamples) {
+        self.multisampling = if samples > 1 {
+            Some(Multisampling {
+                rasterization_samples: samples,
+                sample_shading: None,
+                sample_mask: !0,
+                alpha_coverage: false,
+                alpha_to_one: false,
+            })
+        } else {
+            None
+        };
+    }
+
     /// Build with the provided `BakedStates`
     pub fn with_baked_states(mut self, baked_states: BakedStates) -> Self {
         self.set_baked_states(baked_states);
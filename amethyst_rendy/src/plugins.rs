@@ -1,46 +1,98 @@
 //! Set of predefined implementations of `RenderPlugin` for use with `RenderingBundle`.
 
+#[cfg(feature = "video")]
+use crate::video::VideoSystem;
 use crate::{
-    bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
+    atlas::{CopyToAtlasDesc, TextureAtlas, TextureAtlasSystem},
+    bundle::{
+        ImageOptions, OutputColor, RenderOrder, RenderPlan, RenderPlugin, Target, TargetImage,
+        TargetPlanOutputs,
+    },
+    capture::{CaptureImageDesc, CapturedFrames},
+    debug_mode::{debug_render_mode, DebugRenderMode},
+    environment_map::{EnvironmentMapHandle, EnvironmentMapProcessorSystem, Skybox},
+    gizmos::{Gizmos, GizmosSystem},
+    lod::{LodGroup, LodSystem},
+    particles::{ParticleEffect, ParticleEmitter, ParticleSystem},
     pass::*,
+    render_texture::{
+        render_texture_camera, CopyToTextureDesc, RenderTexture, RENDER_TEXTURE_FORMAT,
+    },
+    shadow::{shadow_config, ShadowConfig},
+    sprite::animation::SpriteAnimationSystem,
     sprite_visibility::SpriteVisibilitySortingSystem,
+    viewport::{viewport_camera, CameraViewport},
     visibility::VisibilitySortingSystem,
-    Backend, Factory,
+    window_camera::{window_camera, WindowCamera},
+    Backend, Factory, Format, Kind,
 };
-use amethyst_core::ecs::{DispatcherBuilder, World};
+use amethyst_assets::Processor;
+use amethyst_core::ecs::{DispatcherBuilder, Entities, Entity, Join, ReadStorage, World, WorldExt};
 use amethyst_error::Error;
 use palette::Srgb;
-use rendy::graph::render::RenderGroupDesc;
+use rendy::{
+    graph::render::RenderGroupDesc,
+    graph::NodeDesc,
+    hal::command::{ClearColor, ClearDepthStencil, ClearValue},
+};
 
 #[cfg(feature = "window")]
-pub use window::RenderToWindow;
+pub use window::{
+    PostEffect, RenderDecals, RenderLighting2D, RenderPostProcess, RenderScaled,
+    RenderToAdditionalWindow, RenderToWindow, RenderViewports, DECALS_SOURCE, LIGHTING_2D_BUFFERS,
+    POST_PROCESS_SOURCE, SCALED_SOURCE,
+};
 
 #[cfg(feature = "window")]
 mod window {
     use super::*;
     use crate::{
-        bundle::{ImageOptions, OutputColor},
-        Format, Kind,
+        decal::Decal,
+        formats::icon::WindowIcon,
+        light_2d::{Light2D, Lighting2D, Occluder2D, SpriteMaterial},
+        present::{PresentDesc, ResolveDesc},
+        render_scale::{render_scale, RenderScale, RenderScaleSystem},
+        settings::{render_settings, RenderSettings},
+        shader::{loaded_shader, ShaderHandle, ShaderVersions},
     };
     use amethyst_config::{Config, ConfigError};
-    use amethyst_core::{
-        ecs::{ReadExpect, SystemData},
-        SystemBundle,
-    };
-    use amethyst_window::{DisplayConfig, ScreenDimensions, Window, WindowBundle};
-    use rendy::hal::command::{ClearColor, ClearDepthStencil, ClearValue};
-    use std::path::Path;
+    use amethyst_core::{bundle::BundleError, SystemBundle};
+    use amethyst_window::{DisplayConfig, ScreenDimensions, Window, WindowBundle, Windows};
+    use rendy::{hal::adapter::PhysicalDevice, shader::SpirvShader, wsi::Surface};
+    use std::{path::Path, sync::Arc};
+
+    /// Node presenting the window target when it isn't rendered to the surface directly.
+    const WINDOW_PRESENT: Target = Target::Custom("window_present");
+
+    const WINDOW_SUGGESTION: &str =
+        "add WindowBundle before RenderingBundle, or create RenderToWindow with a DisplayConfig";
+
+    /// Returns the dimensions of the window, for plugins planned before their first
+    /// `should_rebuild`.
+    fn screen_dimensions(world: &World, plugin: &str) -> Result<ScreenDimensions, BundleError> {
+        world
+            .try_fetch::<ScreenDimensions>()
+            .map(|dimensions| (*dimensions).clone())
+            .ok_or_else(|| {
+                BundleError::missing_resource::<ScreenDimensions>(plugin)
+                    .with_suggestion(WINDOW_SUGGESTION)
+            })
+    }
 
     /// A [RenderPlugin] for opening a window and displaying a render target to it.
     ///
     /// When you provide [`DisplayConfig`], it opens a window for you using [`WindowBundle`].
+    /// Multisampling and the present mode are controlled at runtime with the [RenderSettings]
+    /// resource, and `WindowIcon` assets are processed for `WindowCommands::set_icon`.
     #[derive(Default, Debug)]
     pub struct RenderToWindow {
         target: Target,
         config: Option<DisplayConfig>,
         dimensions: Option<ScreenDimensions>,
+        settings: Option<RenderSettings>,
         dirty: bool,
         clear: Option<ClearColor>,
+        clear_depth: f32,
     }
 
     impl RenderToWindow {
@@ -86,6 +138,78 @@ mod window {
             self.clear = Some(clear.into());
             self
         }
+
+        /// Set the value the depth buffer of the window is cleared to every frame, 0.0 by default
+        /// for the reversed depth of the `Camera` projections.
+        pub fn with_clear_depth(mut self, clear_depth: f32) -> Self {
+            self.clear_depth = clear_depth;
+            self
+        }
+
+        /// Plans rendering the target to an image, which is resolved when multisampled and
+        /// presented by a separate node.
+        fn plan_presented<B: Backend>(
+            &self,
+            plan: &mut RenderPlan<B>,
+            factory: &Factory<B>,
+            surface: Surface<B>,
+            settings: RenderSettings,
+            width: u32,
+            height: u32,
+        ) -> Result<(), Error> {
+            let limits = factory.physical().limits();
+            let samples = settings.supported_samples(
+                limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts,
+            );
+            let format = factory.get_surface_format(&surface);
+            let kind = Kind::D2(width, height, 1, samples);
+
+            plan.define_pass(
+                self.target,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format,
+                        clear: self.clear.map(ClearValue::Color),
+                    })],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(
+                            self.clear_depth,
+                            0,
+                        ))),
+                    }),
+                },
+            )?;
+
+            let target = self.target;
+            plan.define_custom_node(WINDOW_PRESENT, move |ctx| {
+                let mut image = ctx.get_image(TargetImage::Color(target, 0))?;
+                let mut dependencies = ctx.dependencies().to_vec();
+                if samples > 1 {
+                    let resolved = ctx.create_image(ImageOptions {
+                        kind: Kind::D2(width, height, 1, 1),
+                        levels: 1,
+                        format,
+                        clear: None,
+                    });
+                    let mut resolve = ResolveDesc.builder().with_image(image).with_image(resolved);
+                    for dependency in dependencies.drain(..) {
+                        resolve = resolve.with_dependency(dependency);
+                    }
+                    dependencies.push(ctx.graph().add_node(resolve));
+                    image = resolved;
+                }
+                Ok(ctx
+                    .graph()
+                    .add_node(PresentDesc::new(surface, image, settings, dependencies)))
+            })?;
+            plan.add_root(WINDOW_PRESENT);
+            Ok(())
+        }
     }
 
     impl<B: Backend> RenderPlugin<B> for RenderToWindow {
@@ -97,6 +221,10 @@ mod window {
             if let Some(config) = self.config.take() {
                 WindowBundle::from_config(config).build(world, builder)?;
             }
+            builder.add(Processor::<WindowIcon>::new(), "window_icon_processor", &[]);
+            world
+                .entry::<RenderSettings>()
+                .or_insert_with(Default::default);
 
             Ok(())
         }
@@ -109,7 +237,7 @@ mod window {
                 self.dimensions = new_dimensions.map(|d| (*d).clone());
                 return false;
             }
-            self.dirty
+            self.dirty || self.settings.as_ref() != Some(&render_settings(world))
         }
 
         fn on_plan(
@@ -119,76 +247,1228 @@ mod window {
             world: &World,
         ) -> Result<(), Error> {
             self.dirty = false;
+            let settings = render_settings(world);
+            self.settings = Some(settings.clone());
 
-            let window = <ReadExpect<'_, Window>>::fetch(world);
+            let window = world.try_fetch::<Window>().ok_or_else(|| {
+                BundleError::missing_resource::<Window>("RenderToWindow")
+                    .with_suggestion(WINDOW_SUGGESTION)
+            })?;
             let surface = factory.create_surface(&window);
             let dimensions = self.dimensions.as_ref().unwrap();
-            let window_kind = Kind::D2(dimensions.width() as u32, dimensions.height() as u32, 1, 1);
+            let (width, height) = (dimensions.width() as u32, dimensions.height() as u32);
+            if !settings.is_direct() {
+                return self.plan_presented(plan, factory, surface, settings, width, height);
+            }
+            let window_kind = Kind::D2(width, height, 1, 1);
 
             let depth_options = ImageOptions {
                 kind: window_kind,
                 levels: 1,
                 format: Format::D32Sfloat,
-                clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                clear: Some(ClearValue::DepthStencil(ClearDepthStencil(
+                    self.clear_depth,
+                    0,
+                ))),
+            };
+
+            plan.add_root(Target::Main);
+            plan.define_pass(
+                self.target,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Surface(
+                        surface,
+                        self.clear.map(ClearValue::Color),
+                    )],
+                    depth: Some(depth_options),
+                },
+            )?;
+
+            Ok(())
+        }
+    }
+
+    /// A [RenderPlugin] displaying a render target in an additional window opened with the
+    /// [`Windows`] resource, e.g. the view of a camera with a `WindowCamera`.
+    ///
+    /// The target is named after the window and presented directly to its surface, without
+    /// multisampling. Nothing is rendered until the window is open, and the render graph is
+    /// rebuilt when it opens, closes or is resized.
+    #[derive(Debug)]
+    pub struct RenderToAdditionalWindow {
+        window: &'static str,
+        dimensions: Option<ScreenDimensions>,
+        dirty: bool,
+        clear: Option<ClearColor>,
+        clear_depth: f32,
+    }
+
+    impl RenderToAdditionalWindow {
+        /// Create a plugin presenting the target of the additional window named `window`.
+        pub fn new(window: &'static str) -> Self {
+            Self {
+                window,
+                dimensions: None,
+                dirty: false,
+                clear: None,
+                clear_depth: 0.0,
+            }
+        }
+
+        /// Returns the render target presented to the window.
+        pub fn target(&self) -> Target {
+            Target::Custom(self.window)
+        }
+
+        /// Clear the window with specified linear RGBA color every frame.
+        pub fn with_clear(mut self, clear: impl Into<ClearColor>) -> Self {
+            self.clear = Some(clear.into());
+            self
+        }
+
+        /// Set the value the depth buffer of the window is cleared to every frame, 0.0 by default
+        /// for the reversed depth of the `Camera` projections.
+        pub fn with_clear_depth(mut self, clear_depth: f32) -> Self {
+            self.clear_depth = clear_depth;
+            self
+        }
+    }
+
+    impl<B: Backend> RenderPlugin<B> for RenderToAdditionalWindow {
+        fn on_build<'a, 'b>(
+            &mut self,
+            world: &mut World,
+            _builder: &mut DispatcherBuilder<'a, 'b>,
+        ) -> Result<(), Error> {
+            world.entry::<Windows>().or_insert_with(Default::default);
+            world.register::<WindowCamera>();
+            Ok(())
+        }
+
+        fn should_rebuild(&mut self, world: &World) -> bool {
+            let windows = world.fetch::<Windows>();
+            let new_dimensions = windows.dimensions(self.window);
+            if self.dimensions.as_ref() != new_dimensions {
+                self.dirty = true;
+                self.dimensions = new_dimensions.cloned();
+                return false;
+            }
+            self.dirty
+        }
+
+        fn on_plan(
+            &mut self,
+            plan: &mut RenderPlan<B>,
+            factory: &mut Factory<B>,
+            world: &World,
+        ) -> Result<(), Error> {
+            self.dirty = false;
+            let windows = world.fetch::<Windows>();
+            let (window, dimensions) = match (windows.window(self.window), &self.dimensions) {
+                (Some(window), Some(dimensions)) => (window, dimensions),
+                _ => return Ok(()),
+            };
+            let surface = factory.create_surface(window);
+            let kind = Kind::D2(dimensions.width() as u32, dimensions.height() as u32, 1, 1);
+
+            let target = self.target();
+            plan.add_root(target);
+            plan.define_pass(
+                target,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Surface(
+                        surface,
+                        self.clear.map(ClearValue::Color),
+                    )],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(
+                            self.clear_depth,
+                            0,
+                        ))),
+                    }),
+                },
+            )?;
+
+            Ok(())
+        }
+    }
+
+    /// Target the scene is rendered to for post-processing by [RenderPostProcess]. Point the
+    /// other render plugins to it with their `with_target` method.
+    pub const POST_PROCESS_SOURCE: Target = Target::Custom("post_process_source");
+
+    const BLOOM_EXTRACT: Target = Target::Custom("post_process_bloom_extract");
+    const BLOOM_BLUR_X: Target = Target::Custom("post_process_bloom_blur_x");
+    const BLOOM_BLUR_Y: Target = Target::Custom("post_process_bloom_blur_y");
+    const COLOR_GRADING_SOURCE: Target = Target::Custom("post_process_color_grading_source");
+
+    /// A custom fullscreen effect applied by [RenderPostProcess] to the HDR image before tone
+    /// mapping. See [DrawFullscreenDesc] for the interface of the fragment shader, which gets
+    /// the output of the previous effect as its only input image.
+    #[derive(Clone, derivative::Derivative)]
+    #[derivative(Debug)]
+    pub struct PostEffect {
+        name: &'static str,
+        fragment: EffectShader,
+        #[derivative(Debug = "ignore")]
+        params: Arc<dyn Fn(&World) -> FullscreenParams + Send + Sync>,
+    }
+
+    #[derive(Clone, Debug)]
+    enum EffectShader {
+        Static(SpirvShader),
+        Asset(ShaderHandle),
+    }
+
+    impl PostEffect {
+        /// Create an effect with the given fragment shader. The name identifies the render
+        /// target of the effect and must be unique.
+        pub fn new(name: &'static str, fragment: SpirvShader) -> Self {
+            Self::with_shader(name, EffectShader::Static(fragment))
+        }
+
+        /// Create an effect with the fragment shader of a shader asset. The effect is skipped
+        /// until the shader is loaded, and the render graph is rebuilt when it's reloaded.
+        pub fn from_asset(name: &'static str, fragment: ShaderHandle) -> Self {
+            Self::with_shader(name, EffectShader::Asset(fragment))
+        }
+
+        fn with_shader(name: &'static str, fragment: EffectShader) -> Self {
+            Self {
+                name,
+                fragment,
+                params: Arc::new(|_| [[0.0; 4]; 2]),
+            }
+        }
+
+        /// Set the function computing the shader params every frame.
+        pub fn with_params(
+            mut self,
+            params: impl Fn(&World) -> FullscreenParams + Send + Sync + 'static,
+        ) -> Self {
+            self.params = Arc::new(params);
+            self
+        }
+
+        /// Render target the output of the effect is rendered to.
+        pub fn target(&self) -> Target {
+            Target::Custom(self.name)
+        }
+
+        fn shader_asset(&self) -> Option<&ShaderHandle> {
+            match &self.fragment {
+                EffectShader::Static(_) => None,
+                EffectShader::Asset(handle) => Some(handle),
+            }
+        }
+
+        fn fragment(&self, world: &World) -> Option<SpirvShader> {
+            match &self.fragment {
+                EffectShader::Static(shader) => Some(shader.clone()),
+                EffectShader::Asset(handle) => loaded_shader(world, handle),
+            }
+        }
+    }
+
+    /// A [RenderPlugin] rendering the scene to an HDR image and applying a chain of
+    /// post-processing effects before presenting it to the output target: custom
+    /// [PostEffect]s, bloom, tone mapping and vignette.
+    ///
+    /// The other render plugins must render to [POST_PROCESS_SOURCE]. The built-in effects are
+    /// controlled at runtime with the [PostProcessSettings] resource.
+    #[derive(Debug)]
+    pub struct RenderPostProcess {
+        target: Target,
+        bloom: bool,
+        color_grading: bool,
+        effects: Vec<PostEffect>,
+        shaders: ShaderVersions,
+        dimensions: Option<ScreenDimensions>,
+        dirty: bool,
+    }
+
+    impl Default for RenderPostProcess {
+        fn default() -> Self {
+            Self {
+                target: Target::default(),
+                bloom: true,
+                color_grading: false,
+                effects: Vec::new(),
+                shaders: ShaderVersions::new(),
+                dimensions: None,
+                dirty: true,
+            }
+        }
+    }
+
+    impl RenderPostProcess {
+        /// Set target to which the final image will be rendered.
+        pub fn with_target(mut self, target: Target) -> Self {
+            self.target = target;
+            self
+        }
+
+        /// Skip the bloom passes entirely instead of just setting the bloom intensity to 0.
+        pub fn without_bloom(mut self) -> Self {
+            self.bloom = false;
+            self
+        }
+
+        /// Grade the colors of the tone mapped image with the lookup tables of the `ColorGrading`
+        /// resource as the final step.
+        pub fn with_color_grading(mut self) -> Self {
+            self.color_grading = true;
+            self
+        }
+
+        /// Append a custom effect to the chain. Effects are applied in the order they were added.
+        pub fn with_effect(mut self, effect: PostEffect) -> Self {
+            self.effects.push(effect);
+            self
+        }
+    }
+
+    impl<B: Backend> RenderPlugin<B> for RenderPostProcess {
+        fn should_rebuild(&mut self, world: &World) -> bool {
+            let new_dimensions = world.try_fetch::<ScreenDimensions>();
+            if self.dimensions.as_ref() != new_dimensions.as_deref() {
+                self.dirty = true;
+                self.dimensions = new_dimensions.map(|d| (*d).clone());
+                return false;
+            }
+            let shaders = self.effects.iter().filter_map(PostEffect::shader_asset);
+            if self.shaders.changed(world, shaders) {
+                self.dirty = true;
+            }
+            self.dirty
+        }
+
+        fn on_plan(
+            &mut self,
+            plan: &mut RenderPlan<B>,
+            _factory: &mut Factory<B>,
+            world: &World,
+        ) -> Result<(), Error> {
+            self.dirty = false;
+            let dimensions = match &self.dimensions {
+                Some(dimensions) => dimensions.clone(),
+                None => screen_dimensions(world, "RenderPostProcess")?,
+            };
+            let (width, height) = (dimensions.width() as u32, dimensions.height() as u32);
+            let kind = Kind::D2(width, height, 1, 1);
+            let half_kind = Kind::D2((width / 2).max(1), (height / 2).max(1), 1, 1);
+            let hdr_image = |kind| {
+                OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba16Sfloat,
+                    clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0, 0.0, 0.0, 1.0]))),
+                })
+            };
+
+            plan.define_pass(
+                POST_PROCESS_SOURCE,
+                TargetPlanOutputs {
+                    colors: vec![hdr_image(kind)],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                    }),
+                },
+            )?;
+
+            let mut source = POST_PROCESS_SOURCE;
+            for effect in &self.effects {
+                // Effects with a shader asset that isn't loaded yet are skipped until it is.
+                let fragment = match effect.fragment(world) {
+                    Some(fragment) => fragment,
+                    None => continue,
+                };
+                plan.define_pass(
+                    effect.target(),
+                    TargetPlanOutputs {
+                        colors: vec![hdr_image(kind)],
+                        depth: None,
+                    },
+                )?;
+                let params = effect.params.clone();
+                plan.extend_target(effect.target(), move |ctx| {
+                    let input = ctx.get_image(TargetImage::Color(source, 0))?;
+                    ctx.add(
+                        RenderOrder::LinearPostEffects,
+                        DrawFullscreenDesc::new(fragment, 1)
+                            .with_params(move |world| params(world))
+                            .with_samples(ctx.samples())
+                            .builder()
+                            .with_image(input),
+                    )
+                });
+                source = effect.target();
+            }
+
+            let bloom = if self.bloom {
+                let passes = [
+                    (BLOOM_EXTRACT, source, bloom_extract_shader(), None),
+                    (BLOOM_BLUR_X, BLOOM_EXTRACT, blur_shader(), Some([1.0, 0.0])),
+                    (BLOOM_BLUR_Y, BLOOM_BLUR_X, blur_shader(), Some([0.0, 1.0])),
+                ];
+                for (target, input, shader, direction) in passes.iter().cloned() {
+                    plan.define_pass(
+                        target,
+                        TargetPlanOutputs {
+                            colors: vec![hdr_image(half_kind)],
+                            depth: None,
+                        },
+                    )?;
+                    plan.extend_target(target, move |ctx| {
+                        let input = ctx.get_image(TargetImage::Color(input, 0))?;
+                        let desc = match direction {
+                            Some([x, y]) => DrawFullscreenDesc::new(shader, 1)
+                                .with_params(move |_| [[x, y, 0.0, 0.0], [0.0; 4]]),
+                            None => DrawFullscreenDesc::new(shader, 1).with_params(|world| {
+                                settings_params(world, PostProcessSettings::bloom_extract_params)
+                            }),
+                        };
+                        ctx.add(
+                            RenderOrder::LinearPostEffects,
+                            desc.with_samples(ctx.samples()).builder().with_image(input),
+                        )
+                    });
+                }
+                Some(BLOOM_BLUR_Y)
+            } else {
+                None
+            };
+
+            // With color grading the tone mapped image is graded into the target as the final step.
+            let tone_map_target = if self.color_grading {
+                plan.define_pass(
+                    COLOR_GRADING_SOURCE,
+                    TargetPlanOutputs {
+                        colors: vec![hdr_image(kind)],
+                        depth: None,
+                    },
+                )?;
+                plan.extend_target(self.target, move |ctx| {
+                    let input = ctx.get_image(TargetImage::Color(COLOR_GRADING_SOURCE, 0))?;
+                    let depth = ctx.depth();
+                    let samples = ctx.samples();
+                    ctx.add(
+                        RenderOrder::ToneMap,
+                        DrawColorGradingDesc::new()
+                            .with_depth(depth)
+                            .with_samples(samples)
+                            .builder()
+                            .with_image(input),
+                    )
+                });
+                COLOR_GRADING_SOURCE
+            } else {
+                self.target
+            };
+
+            plan.extend_target(tone_map_target, move |ctx| {
+                let scene = ctx.get_image(TargetImage::Color(source, 0))?;
+                // Without bloom passes the scene is bound in place of the bloom image, with no
+                // intensity.
+                let (bloom, bloom_scale) = match bloom {
+                    Some(bloom) => (ctx.get_image(TargetImage::Color(bloom, 0))?, 1.0),
+                    None => (scene, 0.0),
+                };
+                let depth = ctx.depth();
+                let samples = ctx.samples();
+                ctx.add(
+                    RenderOrder::ToneMap,
+                    DrawFullscreenDesc::new(tone_map_shader(), 2)
+                        .with_params(move |world| {
+                            let mut params =
+                                settings_params(world, PostProcessSettings::tone_map_params);
+                            params[0][2] *= bloom_scale;
+                            params
+                        })
+                        .with_depth(depth)
+                        .with_samples(samples)
+                        .builder()
+                        .with_image(scene)
+                        .with_image(bloom),
+                )
+            });
+            Ok(())
+        }
+    }
+
+    /// Target the normals and emission of sprites are rendered to by [RenderLighting2D].
+    pub const LIGHTING_2D_BUFFERS: Target = Target::Custom("lighting_2d_buffers");
+
+    /// A [RenderPlugin] lighting the sprites drawn by [RenderFlat2D] with [Light2D]s in the
+    /// `z = 0` plane, casting soft shadows from [Occluder2D]s.
+    ///
+    /// Sprites with a [SpriteMaterial] are lit with its normal map and add its emission map on
+    /// top. Both are rendered to [LIGHTING_2D_BUFFERS], then the lighting is composited over the
+    /// target after the transparent sprites. The ambient light and the shadow quality are
+    /// controlled at runtime with the [Lighting2D] resource. Add it with the same target as
+    /// [RenderFlat2D].
+    #[derive(Default, Debug)]
+    pub struct RenderLighting2D {
+        target: Target,
+        dimensions: Option<ScreenDimensions>,
+        dirty: bool,
+    }
+
+    impl RenderLighting2D {
+        /// Set target to which the lighting will be composited.
+        pub fn with_target(mut self, target: Target) -> Self {
+            self.target = target;
+            self
+        }
+    }
+
+    impl<B: Backend> RenderPlugin<B> for RenderLighting2D {
+        fn on_build<'a, 'b>(
+            &mut self,
+            world: &mut World,
+            _builder: &mut DispatcherBuilder<'a, 'b>,
+        ) -> Result<(), Error> {
+            world.register::<Light2D>();
+            world.register::<Occluder2D>();
+            world.register::<SpriteMaterial>();
+            world.entry::<Lighting2D>().or_insert_with(Default::default);
+            Ok(())
+        }
+
+        fn should_rebuild(&mut self, world: &World) -> bool {
+            let new_dimensions = world.try_fetch::<ScreenDimensions>();
+            if self.dimensions.as_ref() != new_dimensions.as_deref() {
+                self.dirty = true;
+                self.dimensions = new_dimensions.map(|d| (*d).clone());
+                return false;
+            }
+            self.dirty
+        }
+
+        fn on_plan(
+            &mut self,
+            plan: &mut RenderPlan<B>,
+            _factory: &mut Factory<B>,
+            world: &World,
+        ) -> Result<(), Error> {
+            self.dirty = false;
+            let dimensions = match &self.dimensions {
+                Some(dimensions) => dimensions.clone(),
+                None => screen_dimensions(world, "RenderLighting2D")?,
+            };
+            let kind = Kind::D2(dimensions.width() as u32, dimensions.height() as u32, 1, 1);
+
+            plan.define_pass(
+                LIGHTING_2D_BUFFERS,
+                TargetPlanOutputs {
+                    colors: vec![
+                        // Unlit pixels face the camera.
+                        OutputColor::Image(ImageOptions {
+                            kind,
+                            levels: 1,
+                            format: Format::Rgba8Unorm,
+                            clear: Some(ClearValue::Color(ClearColor::Sfloat([
+                                0.5, 0.5, 1.0, 0.0,
+                            ]))),
+                        }),
+                        OutputColor::Image(ImageOptions {
+                            kind,
+                            levels: 1,
+                            format: Format::Rgba16Sfloat,
+                            clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0; 4]))),
+                        }),
+                    ],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                    }),
+                },
+            )?;
+            plan.extend_target(LIGHTING_2D_BUFFERS, |ctx| {
+                ctx.add(
+                    RenderOrder::Opaque,
+                    DrawLighting2DBuffersDesc::new().builder(),
+                )
+            });
+
+            plan.extend_target(self.target, |ctx| {
+                let normals = ctx.get_image(TargetImage::Color(LIGHTING_2D_BUFFERS, 0))?;
+                let emission = ctx.get_image(TargetImage::Color(LIGHTING_2D_BUFFERS, 1))?;
+                let depth = ctx.depth();
+                let samples = ctx.samples();
+                ctx.add(
+                    RenderOrder::AfterTransparent,
+                    DrawLighting2DDesc::new()
+                        .with_depth(depth)
+                        .with_samples(samples)
+                        .builder()
+                        .with_image(normals)
+                        .with_image(emission),
+                )
+            });
+            Ok(())
+        }
+    }
+
+    /// Target the scene is rendered to before [RenderDecals] projects the decals onto it.
+    pub const DECALS_SOURCE: Target = Target::Custom("decals_source");
+
+    /// A [RenderPlugin] projecting [Decal]s onto the geometry of the scene.
+    ///
+    /// The other render plugins drawing the scene must render to [DECALS_SOURCE]. The scene is
+    /// copied to the target, replacing what was drawn to it before, and the decals are drawn on
+    /// top of it where their boxes intersect the depth of the scene. Decals are drawn over
+    /// transparent objects as well. To combine decals with [RenderPostProcess], set the target
+    /// to [POST_PROCESS_SOURCE].
+    #[derive(Default, Debug)]
+    pub struct RenderDecals {
+        target: Target,
+        dimensions: Option<ScreenDimensions>,
+        dirty: bool,
+    }
+
+    impl RenderDecals {
+        /// Set target to which the scene and the decals will be rendered.
+        pub fn with_target(mut self, target: Target) -> Self {
+            self.target = target;
+            self
+        }
+    }
+
+    impl<B: Backend> RenderPlugin<B> for RenderDecals {
+        fn on_build<'a, 'b>(
+            &mut self,
+            world: &mut World,
+            _builder: &mut DispatcherBuilder<'a, 'b>,
+        ) -> Result<(), Error> {
+            world.register::<Decal>();
+            Ok(())
+        }
+
+        fn should_rebuild(&mut self, world: &World) -> bool {
+            let new_dimensions = world.try_fetch::<ScreenDimensions>();
+            if self.dimensions.as_ref() != new_dimensions.as_deref() {
+                self.dirty = true;
+                self.dimensions = new_dimensions.map(|d| (*d).clone());
+                return false;
+            }
+            self.dirty
+        }
+
+        fn on_plan(
+            &mut self,
+            plan: &mut RenderPlan<B>,
+            _factory: &mut Factory<B>,
+            world: &World,
+        ) -> Result<(), Error> {
+            self.dirty = false;
+            let dimensions = match &self.dimensions {
+                Some(dimensions) => dimensions.clone(),
+                None => screen_dimensions(world, "RenderDecals")?,
             };
+            let kind = Kind::D2(dimensions.width() as u32, dimensions.height() as u32, 1, 1);
+
+            plan.define_pass(
+                DECALS_SOURCE,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::Rgba16Sfloat,
+                        clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0, 0.0, 0.0, 1.0]))),
+                    })],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                    }),
+                },
+            )?;
+
+            plan.extend_target(self.target, |ctx| {
+                let scene = ctx.get_image(TargetImage::Color(DECALS_SOURCE, 0))?;
+                let scene_depth = ctx.get_image(TargetImage::Depth(DECALS_SOURCE))?;
+                let depth = ctx.depth();
+                let samples = ctx.samples();
+                ctx.add(
+                    RenderOrder::BeforeOpaque,
+                    DrawFullscreenDesc::new(copy_shader(), 1)
+                        .with_depth(depth)
+                        .with_samples(samples)
+                        .builder()
+                        .with_image(scene),
+                )?;
+                ctx.add(
+                    RenderOrder::AfterOpaque,
+                    DrawDecalsDesc::new()
+                        .with_depth(depth)
+                        .with_samples(samples)
+                        .builder()
+                        .with_image(scene_depth),
+                )
+            });
+            Ok(())
+        }
+    }
+
+    /// Target the scene is rendered to at the resolution set by [RenderScale].
+    pub const SCALED_SOURCE: Target = Target::Custom("scaled_source");
+
+    /// A [RenderPlugin] rendering the scene at a fraction of the window resolution set by the
+    /// [RenderScale] resource, and upsampling it to the output target with linear filtering.
+    ///
+    /// The render plugins drawing the scene must render to [SCALED_SOURCE], while plugins drawing
+    /// to `target` itself, like `RenderUi`, still draw at the native resolution. The scale can be
+    /// adjusted every frame, or automatically for a target frame time by the added
+    /// [RenderScaleSystem].
+    #[derive(Default, Debug)]
+    pub struct RenderScaled {
+        target: Target,
+        dimensions: Option<ScreenDimensions>,
+        scale: Option<f32>,
+        dirty: bool,
+    }
+
+    impl RenderScaled {
+        /// Set target to which the upsampled scene will be rendered.
+        pub fn with_target(mut self, target: Target) -> Self {
+            self.target = target;
+            self
+        }
+    }
+
+    impl<B: Backend> RenderPlugin<B> for RenderScaled {
+        fn on_build<'a, 'b>(
+            &mut self,
+            world: &mut World,
+            builder: &mut DispatcherBuilder<'a, 'b>,
+        ) -> Result<(), Error> {
+            world
+                .entry::<RenderScale>()
+                .or_insert_with(Default::default);
+            builder.add(RenderScaleSystem::new(), "render_scale_system", &[]);
+            Ok(())
+        }
+
+        fn should_rebuild(&mut self, world: &World) -> bool {
+            let new_dimensions = world.try_fetch::<ScreenDimensions>();
+            if self.dimensions.as_ref() != new_dimensions.as_deref() {
+                self.dirty = true;
+                self.dimensions = new_dimensions.map(|d| (*d).clone());
+                return false;
+            }
+            self.dirty || self.scale != Some(render_scale(world).effective_scale())
+        }
+
+        fn on_plan(
+            &mut self,
+            plan: &mut RenderPlan<B>,
+            _factory: &mut Factory<B>,
+            world: &World,
+        ) -> Result<(), Error> {
+            self.dirty = false;
+            let render_scale = render_scale(world);
+            self.scale = Some(render_scale.effective_scale());
+            let dimensions = match &self.dimensions {
+                Some(dimensions) => dimensions.clone(),
+                None => screen_dimensions(world, "RenderScaled")?,
+            };
+            let (width, height) =
+                render_scale.scaled_size(dimensions.width() as u32, dimensions.height() as u32);
+            let kind = Kind::D2(width, height, 1, 1);
+
+            plan.define_pass(
+                SCALED_SOURCE,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::Rgba16Sfloat,
+                        clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0, 0.0, 0.0, 1.0]))),
+                    })],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                    }),
+                },
+            )?;
+
+            plan.extend_target(self.target, |ctx| {
+                let scene = ctx.get_image(TargetImage::Color(SCALED_SOURCE, 0))?;
+                let depth = ctx.depth();
+                let samples = ctx.samples();
+                ctx.add(
+                    RenderOrder::BeforeOpaque,
+                    DrawFullscreenDesc::new(copy_shader(), 1)
+                        .with_depth(depth)
+                        .with_samples(samples)
+                        .builder()
+                        .with_image(scene),
+                )
+            });
+            Ok(())
+        }
+    }
+
+    /// A [RenderPlugin] drawing the views of cameras with a [CameraViewport] into their rectangles
+    /// of the window, e.g. for split-screen local multiplayer.
+    ///
+    /// Defines the render target of every viewport, sized to its rectangle, and draws the targets
+    /// in their order before anything else is drawn to `target`. Nothing is drawn into the
+    /// viewport targets by this plugin, add other plugins with `with_target` set to
+    /// `CameraViewport::target` for that, e.g. a `RenderUi` for the HUD of a single player.
+    /// Plugins drawing to `target` itself, like a shared `RenderUi`, draw over the whole window.
+    #[derive(Default, Debug)]
+    pub struct RenderViewports {
+        target: Target,
+        viewports: Vec<CameraViewport>,
+        dimensions: Option<ScreenDimensions>,
+        dirty: bool,
+    }
+
+    impl RenderViewports {
+        /// Set target into which the viewports will be drawn.
+        pub fn with_target(mut self, target: Target) -> Self {
+            self.target = target;
+            self
+        }
+
+        fn viewports(world: &World) -> Vec<CameraViewport> {
+            let viewports = world.read_storage::<CameraViewport>();
+            let mut viewports = viewports.join().cloned().collect::<Vec<_>>();
+            viewports.sort_by_key(CameraViewport::order);
+            viewports
+        }
+    }
+
+    impl<B: Backend> RenderPlugin<B> for RenderViewports {
+        fn on_build<'a, 'b>(
+            &mut self,
+            world: &mut World,
+            _builder: &mut DispatcherBuilder<'a, 'b>,
+        ) -> Result<(), Error> {
+            world.register::<CameraViewport>();
+            Ok(())
+        }
+
+        fn should_rebuild(&mut self, world: &World) -> bool {
+            let new_dimensions = world.try_fetch::<ScreenDimensions>();
+            if self.dimensions.as_ref() != new_dimensions.as_deref() {
+                self.dirty = true;
+                self.dimensions = new_dimensions.map(|d| (*d).clone());
+                return false;
+            }
+            self.dirty || self.viewports != Self::viewports(world)
+        }
+
+        fn on_plan(
+            &mut self,
+            plan: &mut RenderPlan<B>,
+            _factory: &mut Factory<B>,
+            world: &World,
+        ) -> Result<(), Error> {
+            self.dirty = false;
+            self.viewports = Self::viewports(world);
+            let dimensions = match &self.dimensions {
+                Some(dimensions) => dimensions.clone(),
+                None => screen_dimensions(world, "RenderViewports")?,
+            };
+
+            let (width, height) = (dimensions.width() as u32, dimensions.height() as u32);
+            for viewport in &self.viewports {
+                let rect = viewport.window_rect(width, height).pixels(width, height);
+                let kind = Kind::D2(rect.w as u32, rect.h as u32, 1, 1);
+                plan.define_pass(
+                    viewport.target(),
+                    TargetPlanOutputs {
+                        colors: vec![OutputColor::Image(ImageOptions {
+                            kind,
+                            levels: 1,
+                            format: Format::Rgba8Srgb,
+                            clear: Some(ClearValue::Color(ClearColor::Sfloat(
+                                viewport.clear_color(),
+                            ))),
+                        })],
+                        depth: Some(ImageOptions {
+                            kind,
+                            levels: 1,
+                            format: Format::D32Sfloat,
+                            clear: Some(ClearValue::DepthStencil(ClearDepthStencil(
+                                viewport.clear_depth(),
+                                0,
+                            ))),
+                        }),
+                    },
+                )?;
+            }
+
+            let viewports = self
+                .viewports
+                .iter()
+                .map(|viewport| (viewport.target(), viewport.window_rect(width, height)))
+                .collect::<Vec<_>>();
+            plan.extend_target(self.target, move |ctx| {
+                let depth = ctx.depth();
+                let samples = ctx.samples();
+                for &(target, rect) in &viewports {
+                    let image = ctx.get_image(TargetImage::Color(target, 0))?;
+                    ctx.add(
+                        RenderOrder::BeforeOpaque,
+                        DrawFullscreenDesc::new(copy_shader(), 1)
+                            .with_depth(depth)
+                            .with_samples(samples)
+                            .with_viewport(rect)
+                            .builder()
+                            .with_image(image),
+                    )?;
+                }
+                Ok(())
+            });
+            Ok(())
+        }
+    }
+}
+
+/// A `RenderPlugin` for forward rendering of 3d objects using flat shading.
+pub type RenderFlat3D = RenderBase3D<crate::pass::FlatPassDef>;
+/// A `RenderPlugin` for forward rendering of 3d objects using shaded shading.
+pub type RenderShaded3D = RenderBase3D<crate::pass::ShadedPassDef>;
+/// A `RenderPlugin` for forward rendering of 3d objects using physically-based shading.
+pub type RenderPbr3D = RenderBase3D<crate::pass::PbrPassDef>;
+
+/// A `RenderPlugin` for forward rendering of 3d objects.
+/// Generic over 3d pass rendering method.
+///
+/// The meshes are visualized in the `DebugRenderMode` of the world, if the resource is present.
+#[derive(derivative::Derivative)]
+#[derivative(Default(bound = ""), Debug(bound = ""))]
+pub struct RenderBase3D<D: Base3DPassDef> {
+    target: Target,
+    skinning: bool,
+    shadows: bool,
+    shadow_atlas: Option<(u32, u32)>,
+    camera: Option<Entity>,
+    debug_mode: DebugRenderMode,
+    marker: std::marker::PhantomData<D>,
+}
+
+impl<D: Base3DPassDef> RenderBase3D<D> {
+    /// Set target to which 3d meshes will be rendered. When the target belongs to a
+    /// `RenderTexture` or a `CameraViewport`, the meshes are rendered as seen by its camera.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Enable rendering for skinned meshes.
+    ///
+    /// NOTE: You must register `VertexSkinningBundle` yourself.
+    pub fn with_skinning(mut self) -> Self {
+        self.skinning = true;
+        self
+    }
+
+    /// Enable shadows of the lights with `cast_shadows` set, configured by the `ShadowConfig`
+    /// resource. Only static meshes cast shadows.
+    ///
+    /// NOTE: The pass must support shadows, like `RenderPbr3D`. Only one plugin can render
+    /// shadows, as they are rendered into the single `Target::ShadowMap`.
+    pub fn with_shadows(mut self) -> Self {
+        self.shadows = true;
+        self
+    }
+}
+
+impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderBase3D<D> {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(LodSystem::new(), "lod_system", &[]);
+        builder.add(
+            VisibilitySortingSystem::new(),
+            "visibility_system",
+            &["lod_system"],
+        );
+        world.register::<RenderTexture>();
+        world.register::<CameraViewport>();
+        world.register::<WindowCamera>();
+        world.register::<LodGroup>();
+        if self.shadows {
+            world
+                .entry::<ShadowConfig>()
+                .or_insert_with(Default::default);
+        }
+        Ok(())
+    }
+
+    fn should_rebuild(&mut self, world: &World) -> bool {
+        self.camera != target_camera(world, self.target)
+            || self.shadows && self.shadow_atlas != Some(shadow_config(world).atlas_size())
+            || self.debug_mode != debug_render_mode(world)
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        world: &World,
+    ) -> Result<(), Error> {
+        let skinning = self.skinning;
+        let shadows = self.shadows;
+        let camera = target_camera(world, self.target);
+        self.camera = camera;
+        let debug_mode = debug_render_mode(world);
+        self.debug_mode = debug_mode;
+        if shadows {
+            let (width, height) = shadow_config(world).atlas_size();
+            self.shadow_atlas = Some((width, height));
+            plan.define_pass(
+                Target::ShadowMap,
+                TargetPlanOutputs {
+                    colors: Vec::new(),
+                    depth: Some(ImageOptions {
+                        kind: Kind::D2(width, height, 1, 1),
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
+                    }),
+                },
+            )?;
+            plan.extend_target(Target::ShadowMap, |ctx| {
+                ctx.add(RenderOrder::Opaque, DrawShadowsDesc::new().builder())
+            });
+        }
+        plan.extend_target(self.target, move |ctx| {
+            let mut opaque = DrawBase3DDesc::<B, D>::new()
+                .with_skinning(skinning)
+                .with_shadows(shadows)
+                .with_camera(camera)
+                .with_samples(ctx.samples())
+                .with_debug_mode(debug_mode)
+                .builder();
+            let mut transparent = DrawBase3DTransparentDesc::<B, D>::new()
+                .with_skinning(skinning)
+                .with_shadows(shadows)
+                .with_camera(camera)
+                .with_samples(ctx.samples())
+                .with_debug_mode(debug_mode)
+                .builder();
+            if shadows {
+                let shadow_map = ctx.get_image(TargetImage::Depth(Target::ShadowMap))?;
+                opaque = opaque.with_image(shadow_map);
+                transparent = transparent.with_image(shadow_map);
+            }
+            ctx.add(RenderOrder::Opaque, opaque)?;
+            ctx.add(RenderOrder::Transparent, transparent)?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// Returns the camera entity rendering into the given target, if it belongs to a `RenderTexture`,
+/// a `CameraViewport` or a `WindowCamera`.
+fn target_camera(world: &World, target: Target) -> Option<Entity> {
+    render_texture_camera(world, target)
+        .or_else(|| viewport_camera(world, target))
+        .or_else(|| window_camera(world, target))
+}
+
+/// A [RenderPlugin] defining the render targets of cameras with a `RenderTexture` and copying
+/// their output into the textures.
+///
+/// Nothing is drawn into the targets by this plugin, add other plugins with `with_target` set to
+/// `RenderTexture::target` for that.
+#[derive(Default, Debug)]
+pub struct RenderCameraTextures {
+    textures: Vec<(Entity, RenderTexture)>,
+}
+
+impl RenderCameraTextures {
+    fn render_textures(world: &World) -> Vec<(Entity, RenderTexture)> {
+        let (entities, render_textures) =
+            world.system_data::<(Entities<'_>, ReadStorage<'_, RenderTexture>)>();
+        (&entities, &render_textures)
+            .join()
+            .map(|(entity, render_texture)| (entity, render_texture.clone()))
+            .collect()
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderCameraTextures {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<RenderTexture>();
+        Ok(())
+    }
 
-            plan.add_root(Target::Main);
+    fn should_rebuild(&mut self, world: &World) -> bool {
+        self.textures != Self::render_textures(world)
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        world: &World,
+    ) -> Result<(), Error> {
+        self.textures = Self::render_textures(world);
+        for (_, render_texture) in &self.textures {
+            let (width, height) = render_texture.size();
+            let kind = Kind::D2(width, height, 1, 1);
+            let target = render_texture.target();
             plan.define_pass(
-                self.target,
-                crate::bundle::TargetPlanOutputs {
-                    colors: vec![OutputColor::Surface(
-                        surface,
-                        self.clear.map(ClearValue::Color),
-                    )],
-                    depth: Some(depth_options),
+                target,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: RENDER_TEXTURE_FORMAT,
+                        clear: Some(ClearValue::Color(ClearColor::Sfloat(
+                            render_texture.clear_color(),
+                        ))),
+                    })],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(
+                            render_texture.clear_depth(),
+                            0,
+                        ))),
+                    }),
                 },
             )?;
 
-            Ok(())
+            let copy = render_texture.copy_target();
+            let texture = render_texture.texture().clone();
+            plan.define_custom_node(copy, move |ctx| {
+                let image = ctx.get_image(TargetImage::Color(target, 0))?;
+                let mut builder = CopyToTextureDesc::new(texture).builder().with_image(image);
+                for dependency in ctx.dependencies().to_vec() {
+                    builder = builder.with_dependency(dependency);
+                }
+                Ok(ctx.graph().add_node(builder))
+            })?;
+            plan.add_root(copy);
         }
+        Ok(())
     }
 }
 
-/// A `RenderPlugin` for forward rendering of 3d objects using flat shading.
-pub type RenderFlat3D = RenderBase3D<crate::pass::FlatPassDef>;
-/// A `RenderPlugin` for forward rendering of 3d objects using shaded shading.
-pub type RenderShaded3D = RenderBase3D<crate::pass::ShadedPassDef>;
-/// A `RenderPlugin` for forward rendering of 3d objects using physically-based shading.
-pub type RenderPbr3D = RenderBase3D<crate::pass::PbrPassDef>;
+/// A [RenderPlugin] rendering the main target into an image of a fixed size instead of a window,
+/// e.g. to render frames in tests without opening a window.
+///
+/// The image isn't displayed, so the main target is only rendered when something uses it, like
+/// the `RenderCapture` plugin reading it back.
+#[derive(Debug)]
+pub struct RenderOffscreen {
+    width: u32,
+    height: u32,
+    clear: ClearColor,
+    clear_depth: f32,
+}
 
-/// A `RenderPlugin` for forward rendering of 3d objects.
-/// Generic over 3d pass rendering method.
-#[derive(derivative::Derivative)]
-#[derivative(Default(bound = ""), Debug(bound = ""))]
-pub struct RenderBase3D<D: Base3DPassDef> {
+impl RenderOffscreen {
+    /// Renders the main target into an image of the given size, cleared to opaque black.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            clear: ClearColor::Sfloat([0.0, 0.0, 0.0, 1.0]),
+            clear_depth: 0.0,
+        }
+    }
+
+    /// Sets the color the image is cleared to before rendering.
+    pub fn with_clear(mut self, clear: impl Into<ClearColor>) -> Self {
+        self.clear = clear.into();
+        self
+    }
+
+    /// Sets the value the depth buffer is cleared to before rendering, 0.0 by default for the
+    /// reversed depth of the `Camera` projections.
+    pub fn with_clear_depth(mut self, clear_depth: f32) -> Self {
+        self.clear_depth = clear_depth;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderOffscreen {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let kind = Kind::D2(self.width, self.height, 1, 1);
+        plan.define_pass(
+            Target::Main,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba8Srgb,
+                    clear: Some(ClearValue::Color(self.clear)),
+                })],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(
+                        self.clear_depth,
+                        0,
+                    ))),
+                }),
+            },
+        )
+    }
+}
+
+/// A [RenderPlugin] reading the color output of a target back from the GPU every frame into the
+/// `CapturedFrames` resource, e.g. to compare the frames rendered by `RenderOffscreen` with
+/// reference images using `golden::GoldenImage` in tests.
+///
+/// The target must have an 8 bit RGBA or BGRA color image output, which excludes targets
+/// rendered directly to a window surface.
+#[derive(Default, Debug)]
+pub struct RenderCapture {
     target: Target,
-    skinning: bool,
-    marker: std::marker::PhantomData<D>,
 }
 
-impl<D: Base3DPassDef> RenderBase3D<D> {
-    /// Set target to which 3d meshes will be rendered.
+impl RenderCapture {
+    /// Set the target to read back, the main target by default.
     pub fn with_target(mut self, target: Target) -> Self {
         self.target = target;
         self
     }
-
-    /// Enable rendering for skinned meshes.
-    ///
-    /// NOTE: You must register `VertexSkinningBundle` yourself.
-    pub fn with_skinning(mut self) -> Self {
-        self.skinning = true;
-        self
-    }
 }
 
-impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderBase3D<D> {
+impl<B: Backend> RenderPlugin<B> for RenderCapture {
     fn on_build<'a, 'b>(
         &mut self,
         world: &mut World,
-        builder: &mut DispatcherBuilder<'a, 'b>,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
-        builder.add(VisibilitySortingSystem::new(), "visibility_system", &[]);
+        world
+            .entry::<CapturedFrames>()
+            .or_insert_with(Default::default);
         Ok(())
     }
 
@@ -198,22 +1478,16 @@ impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderBase3D<D> {
         _factory: &mut Factory<B>,
         _world: &World,
     ) -> Result<(), Error> {
-        let skinning = self.skinning;
-        plan.extend_target(self.target, move |ctx| {
-            ctx.add(
-                RenderOrder::Opaque,
-                DrawBase3DDesc::<B, D>::new()
-                    .with_skinning(skinning)
-                    .builder(),
-            )?;
-            ctx.add(
-                RenderOrder::Transparent,
-                DrawBase3DTransparentDesc::<B, D>::new()
-                    .with_skinning(skinning)
-                    .builder(),
-            )?;
-            Ok(())
-        });
+        let target = self.target;
+        plan.define_custom_node(Target::Capture, move |ctx| {
+            let image = ctx.get_image(TargetImage::Color(target, 0))?;
+            let mut builder = CaptureImageDesc.builder().with_image(image);
+            for dependency in ctx.dependencies().to_vec() {
+                builder = builder.with_dependency(dependency);
+            }
+            Ok(ctx.graph().add_node(builder))
+        })?;
+        plan.add_root(Target::Capture);
         Ok(())
     }
 }
@@ -239,10 +1513,11 @@ impl<B: Backend> RenderPlugin<B> for RenderFlat2D {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
+        builder.add(SpriteAnimationSystem::new(), "sprite_animation_system", &[]);
         builder.add(
             SpriteVisibilitySortingSystem::new(),
             "sprite_visibility_system",
-            &[],
+            &["sprite_animation_system"],
         );
         Ok(())
     }
@@ -254,10 +1529,16 @@ impl<B: Backend> RenderPlugin<B> for RenderFlat2D {
         _world: &World,
     ) -> Result<(), Error> {
         plan.extend_target(self.target, |ctx| {
-            ctx.add(RenderOrder::Opaque, DrawFlat2DDesc::new().builder())?;
+            let samples = ctx.samples();
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawFlat2DDesc::new().with_samples(samples).builder(),
+            )?;
             ctx.add(
                 RenderOrder::Transparent,
-                DrawFlat2DTransparentDesc::new().builder(),
+                DrawFlat2DTransparentDesc::new()
+                    .with_samples(samples)
+                    .builder(),
             )?;
             Ok(())
         });
@@ -290,7 +1571,106 @@ impl<B: Backend> RenderPlugin<B> for RenderDebugLines {
         plan.extend_target(self.target, |ctx| {
             ctx.add(
                 RenderOrder::BeforeTransparent,
-                DrawDebugLinesDesc::new().builder(),
+                DrawDebugLinesDesc::new()
+                    .with_samples(ctx.samples())
+                    .builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] for drawing debug gizmos submitted to the [gizmos::Gizmos] resource.
+/// The line width is controlled by the [debug_drawing::DebugLinesParams] resource.
+#[derive(Default, Debug)]
+pub struct RenderGizmos {
+    target: Target,
+}
+
+impl RenderGizmos {
+    /// Set target to which gizmos will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderGizmos {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.entry::<Gizmos>().or_insert_with(Default::default);
+        builder.add(GizmosSystem, "gizmos_system", &[]);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_target(self.target, |ctx| {
+            ctx.add(
+                RenderOrder::AfterTransparent,
+                DrawGizmosDesc::new().with_samples(ctx.samples()).builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] for simulating and drawing the particles of [particles::ParticleEmitter]
+/// components, with [particles::ParticleEffect] assets loaded through the asset loader.
+#[derive(Default, Debug)]
+pub struct RenderParticles {
+    target: Target,
+}
+
+impl RenderParticles {
+    /// Set target to which particles will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderParticles {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<ParticleEmitter>();
+        builder.add(
+            Processor::<ParticleEffect>::new(),
+            "particle_effect_processor",
+            &[],
+        );
+        builder.add(
+            ParticleSystem::new(),
+            "particle_system",
+            &["particle_effect_processor"],
+        );
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_target(self.target, |ctx| {
+            ctx.add(
+                RenderOrder::Transparent,
+                DrawParticlesDesc::new()
+                    .with_samples(ctx.samples())
+                    .builder(),
             )?;
             Ok(())
         });
@@ -299,10 +1679,15 @@ impl<B: Backend> RenderPlugin<B> for RenderDebugLines {
 }
 
 /// RenderPlugin for rendering skyboxes.
+///
+/// The skybox draws the environment map selected in the `Skybox` resource, which also lights
+/// the materials of `RenderPbr3D` once loaded. Without one, a gradient between the nadir and
+/// zenith colors is drawn.
 #[derive(Default, Debug)]
 pub struct RenderSkybox {
     target: Target,
     colors: Option<(Srgb, Srgb)>,
+    environment: Option<EnvironmentMapHandle>,
 }
 
 impl RenderSkybox {
@@ -311,9 +1696,16 @@ impl RenderSkybox {
         Self {
             target: Default::default(),
             colors: Some((nadir_color, zenith_color)),
+            environment: None,
         }
     }
 
+    /// Select the environment map in the `Skybox` resource when the plugin is built.
+    pub fn with_environment(mut self, environment: EnvironmentMapHandle) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
     /// Set target to which skybox will be rendered.
     pub fn with_target(mut self, target: Target) -> Self {
         self.target = target;
@@ -322,6 +1714,23 @@ impl RenderSkybox {
 }
 
 impl<B: Backend> RenderPlugin<B> for RenderSkybox {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(
+            EnvironmentMapProcessorSystem,
+            "environment_map_processor",
+            &[],
+        );
+        let mut skybox = world.entry::<Skybox>().or_insert_with(Default::default);
+        if let Some(environment) = self.environment.take() {
+            skybox.environment = Some(environment);
+        }
+        Ok(())
+    }
+
     fn on_plan(
         &mut self,
         plan: &mut RenderPlan<B>,
@@ -330,11 +1739,12 @@ impl<B: Backend> RenderPlugin<B> for RenderSkybox {
     ) -> Result<(), Error> {
         let colors = self.colors;
         plan.extend_target(self.target, move |ctx| {
-            let group = if let Some((nadir, zenith)) = colors {
-                DrawSkyboxDesc::with_colors(nadir, zenith).builder()
+            let desc = if let Some((nadir, zenith)) = colors {
+                DrawSkyboxDesc::with_colors(nadir, zenith)
             } else {
-                DrawSkyboxDesc::new().builder()
+                DrawSkyboxDesc::new()
             };
+            let group = desc.with_samples(ctx.samples()).builder();
 
             ctx.add(RenderOrder::AfterOpaque, group)?;
             Ok(())
@@ -342,3 +1752,161 @@ impl<B: Backend> RenderPlugin<B> for RenderSkybox {
         Ok(())
     }
 }
+
+/// A [RenderPlugin] measuring the GPU time of render targets into the `RenderStats` resource with
+/// timestamp queries. The draw counts and memory usage in `RenderStats` are collected without it.
+///
+/// The timed targets must be render passes defined by other plugins. A timestamp is written when
+/// the frame starts, before any timed target, and after every timed target.
+#[derive(Debug)]
+pub struct RenderStatistics {
+    targets: Vec<(&'static str, Target)>,
+    period: f32,
+}
+
+impl Default for RenderStatistics {
+    fn default() -> Self {
+        Self {
+            targets: vec![("main", Target::Main)],
+            period: 1.0,
+        }
+    }
+}
+
+impl RenderStatistics {
+    /// Name of the timestamp target marking the start of the frame.
+    pub const FRAME_START: &'static str = "frame_start";
+
+    /// Time another target under the given name, besides the main target.
+    pub fn with_timed_target(mut self, name: &'static str, target: Target) -> Self {
+        self.targets.push((name, target));
+        self
+    }
+
+    /// Set the number of nanoseconds per timestamp tick, 1.0 by default. The period depends on
+    /// the device and isn't reported by `gfx-hal`, e.g. it's 1.0 on most discrete GPUs but
+    /// larger on many integrated ones.
+    pub fn with_timestamp_period(mut self, period: f32) -> Self {
+        self.period = period;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderStatistics {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let period = self.period;
+        let start = Target::Timestamp(Self::FRAME_START);
+        plan.define_custom_node(start, move |ctx| {
+            let desc = WriteTimestampDesc::frame_start().with_period(period);
+            Ok(ctx.graph().add_node(desc.builder()))
+        })?;
+
+        for &(name, target) in &self.targets {
+            plan.extend_target(target, move |ctx| {
+                let start = ctx.get_node(start)?;
+                ctx.add_dep(start);
+                Ok(())
+            });
+
+            let timestamp = Target::Timestamp(name);
+            plan.define_custom_node(timestamp, move |ctx| {
+                let node = ctx.get_node(target)?;
+                let desc = WriteTimestampDesc::new(name).with_period(period);
+                Ok(ctx.graph().add_node(desc.builder().with_dependency(node)))
+            })?;
+            plan.add_root(timestamp);
+        }
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] packing the textures inserted into the `TextureAtlas` resource into shared
+/// atlas pages, e.g. UI icons or sprites supplied by mods.
+///
+/// The packed textures are copied into their pages before the target drawing the atlas sprites
+/// is rendered, so a sprite may be drawn from an empty page for the frame it's packed in.
+#[derive(Default, Debug)]
+pub struct RenderTextureAtlas {
+    target: Target,
+    atlas: Option<TextureAtlas>,
+}
+
+impl RenderTextureAtlas {
+    /// Insert the given atlas when the plugin is built, e.g. to change the size of the pages.
+    pub fn with_atlas(mut self, atlas: TextureAtlas) -> Self {
+        self.atlas = Some(atlas);
+        self
+    }
+
+    /// Set the target drawing the atlas sprites.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderTextureAtlas {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        if let Some(atlas) = self.atlas.take() {
+            world.insert(atlas);
+        }
+        builder.add(
+            TextureAtlasSystem::<B>::default(),
+            "texture_atlas_system",
+            &[],
+        );
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.define_custom_node(Target::AtlasCopy, |ctx| {
+            Ok(ctx.graph().add_node(CopyToAtlasDesc.builder()))
+        })?;
+        plan.extend_target(self.target, |ctx| {
+            let copy = ctx.get_node(Target::AtlasCopy)?;
+            ctx.add_dep(copy);
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] playing the videos of the `VideoPlayer` components into their textures.
+#[cfg(feature = "video")]
+#[derive(Default, Debug)]
+pub struct RenderVideo;
+
+#[cfg(feature = "video")]
+impl<B: Backend> RenderPlugin<B> for RenderVideo {
+    fn on_build<'a, 'b>(
+        &mut self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(VideoSystem::<B>::default(), "video_system", &[]);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        _plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 //! GPU POD data types.
 use crate::{
-    mtl,
+    decal::Decal,
+    mtl::{self, MaterialOverrides as MaterialOverridesComponent},
     resources::Tint as TintComponent,
     sprite::{SpriteRender, SpriteSheet},
     types::Texture,
@@ -77,10 +78,47 @@ impl AsAttribute for Tint {
     const FORMAT: Format = Format::Rgba32Sfloat;
 }
 
+/// MaterialOverrides
+/// ```glsl,ignore
+/// vec4 material_overrides; // uv offset, emission factor, unused
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, AsStd140)]
+#[repr(C, align(16))]
+pub struct MaterialOverrides {
+    /// Texture coordinate offset, emission factor and an unused component as `Rgba32Sfloat`
+    pub material_overrides: vec4,
+}
+
+impl AsAttribute for MaterialOverrides {
+    const NAME: &'static str = "material_overrides";
+    const FORMAT: Format = Format::Rgba32Sfloat;
+}
+
+/// Instance-rate `MaterialOverrides` without any effect.
+fn no_overrides() -> vec4 {
+    [0.0, 0.0, 1.0, 0.0].into()
+}
+
+/// Applies the `MaterialOverridesComponent` at the given time in seconds to the instance-rate
+/// tint and overrides.
+fn apply_overrides(
+    tint: &mut vec4,
+    material_overrides: &mut vec4,
+    overrides: &MaterialOverridesComponent,
+    time: f64,
+) {
+    let (r, g, b, a) = overrides.tint.into_linear().into_components();
+    let [tr, tg, tb, ta] = *AsRef::<[f32; 4]>::as_ref(tint);
+    *tint = [tr * r, tg * g, tb * b, ta * a].into();
+    let uv_offset = overrides.uv_offset_at(time);
+    *material_overrides = [uv_offset.x, uv_offset.y, overrides.emission, 0.0].into();
+}
+
 /// Instance-rate vertex arguments
 /// ```glsl,ignore
 ///  mat4 model;
 ///  vec4 tint;
+///  vec4 material_overrides;
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 #[repr(C, align(16))]
@@ -89,6 +127,8 @@ pub struct VertexArgs {
     pub model: mat4,
     /// Instance-rate model `Tint`
     pub tint: vec4,
+    /// Instance-rate `MaterialOverrides`
+    pub material_overrides: vec4,
 }
 
 impl VertexArgs {
@@ -104,13 +144,33 @@ impl VertexArgs {
                 let (r, g, b, a) = t.0.into_linear().into_components();
                 [r, g, b, a].into()
             }),
+            material_overrides: no_overrides(),
         }
     }
+
+    /// Applies the `MaterialOverridesComponent` of the object at the given time in seconds,
+    /// which drives the texture scrolling.
+    #[inline]
+    pub fn with_overrides(
+        mut self,
+        overrides: Option<&MaterialOverridesComponent>,
+        time: f64,
+    ) -> Self {
+        if let Some(overrides) = overrides {
+            apply_overrides(
+                &mut self.tint,
+                &mut self.material_overrides,
+                overrides,
+                time,
+            );
+        }
+        self
+    }
 }
 
 impl AsVertex for VertexArgs {
     fn vertex() -> VertexFormat {
-        VertexFormat::new((Model::vertex(), Tint::vertex()))
+        VertexFormat::new((Model::vertex(), Tint::vertex(), MaterialOverrides::vertex()))
     }
 }
 
@@ -135,6 +195,7 @@ impl AsAttribute for JointsOffset {
 ///  mat4 model;
 ///  vec4 tint;
 ///  uint joints_offset:
+///  vec4 material_overrides;
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 #[repr(C, packed)]
@@ -145,11 +206,18 @@ pub struct SkinnedVertexArgs {
     pub tint: vec4,
     /// Instance-rate joint offset as `u32`
     pub joints_offset: u32,
+    /// Instance-rate `MaterialOverrides`
+    pub material_overrides: vec4,
 }
 
 impl AsVertex for SkinnedVertexArgs {
     fn vertex() -> VertexFormat {
-        VertexFormat::new((Model::vertex(), Tint::vertex(), JointsOffset::vertex()))
+        VertexFormat::new((
+            Model::vertex(),
+            Tint::vertex(),
+            JointsOffset::vertex(),
+            MaterialOverrides::vertex(),
+        ))
     }
 }
 
@@ -170,8 +238,27 @@ impl SkinnedVertexArgs {
                 [r, g, b, a].into()
             }),
             joints_offset,
+            material_overrides: no_overrides(),
         }
     }
+
+    /// Applies the `MaterialOverridesComponent` of the object at the given time in seconds,
+    /// which drives the texture scrolling.
+    #[inline]
+    pub fn with_overrides(
+        mut self,
+        overrides: Option<&MaterialOverridesComponent>,
+        time: f64,
+    ) -> Self {
+        if let Some(overrides) = overrides {
+            // Copy out of the packed struct to avoid unaligned references.
+            let (mut tint, mut material_overrides) = (self.tint, self.material_overrides);
+            apply_overrides(&mut tint, &mut material_overrides, overrides, time);
+            self.tint = tint;
+            self.material_overrides = material_overrides;
+        }
+        self
+    }
 }
 
 /// point light struct
@@ -264,6 +351,67 @@ pub struct Environment {
     pub spot_light_count: int,
 }
 
+/// 2D lighting Uniform
+/// ```glsl,ignore
+/// uniform Lighting2D {
+///    mat4 inverse_proj_view;
+///    vec3 ambient_color;
+///    int light_count;
+///    int segment_count;
+///    int shadow_samples;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct Lighting2D {
+    /// Inverse of the premultiplied projection and view matrix of the camera
+    pub inverse_proj_view: mat4,
+    /// Linear ambient color for the entire image
+    pub ambient_color: vec3,
+    /// Number of lights
+    pub light_count: int,
+    /// Number of occluder segments
+    pub segment_count: int,
+    /// Number of shadow rays per light
+    pub shadow_samples: int,
+}
+
+/// 2D light struct
+/// ```glsl,ignore
+/// struct Light2D {
+///    vec3 color;
+///    float radius;
+///    vec2 position;
+///    float height;
+///    float source_radius;
+///    vec2 direction;
+///    float cone_outer;
+///    float cone_inner;
+///    int cast_shadows;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct Light2D {
+    /// Linear light color premultiplied by the intensity
+    pub color: vec3,
+    /// Distance at which the light fades out
+    pub radius: float,
+    /// Light world position in the plane
+    pub position: vec2,
+    /// Height of the light above the plane
+    pub height: float,
+    /// Radius of the light source
+    pub source_radius: float,
+    /// Spot light direction in the plane
+    pub direction: vec2,
+    /// Cosine of the angle from the direction at which the light fades out, below -1 for point
+    /// lights
+    pub cone_outer: float,
+    /// Cosine of the angle from the direction at which the light starts fading out
+    pub cone_inner: float,
+    /// Whether occluders block the light
+    pub cast_shadows: int,
+}
+
 /// Material Uniform
 /// ```glsl,ignore
 /// uniform Material {
@@ -290,6 +438,76 @@ impl Material {
     }
 }
 
+/// Shadows Uniform
+/// ```glsl,ignore
+/// uniform Shadows {
+///    vec4 cascade_splits;
+///    vec3 camera_forward;
+///    int cascade_count;
+///    int shadow_light;
+///    float directional_bias;
+///    int directional_filter;
+///    float point_bias;
+///    int point_filter;
+///    ivec4 point_shadows[32];
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct Shadows {
+    /// View depth at which each cascade ends
+    pub cascade_splits: vec4,
+    /// World space direction the camera looks at
+    pub camera_forward: vec3,
+    /// Number of cascades of the directional light shadow
+    pub cascade_count: int,
+    /// Index of the directional light casting shadows, -1 if there is none
+    pub shadow_light: int,
+    /// Depth bias of the directional light shadow
+    pub directional_bias: float,
+    /// Filter radius of the directional light shadow in texels
+    pub directional_filter: int,
+    /// Depth bias of the point light shadows
+    pub point_bias: float,
+    /// Filter radius of the point light shadows in texels
+    pub point_filter: int,
+    /// First shadow view of each point light packed by four, -1 if the light casts no shadow
+    pub point_shadows: [ivec4; 32],
+}
+
+/// Environment map Uniform
+/// ```glsl,ignore
+/// uniform EnvironmentMap {
+///    vec4 irradiance[9];
+///    float intensity;
+///    int levels;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct EnvironmentMapArgs {
+    /// Spherical harmonics of the diffuse irradiance divided by pi
+    pub irradiance: [vec4; 9],
+    /// Multiplier of the radiance of the environment map
+    pub intensity: float,
+    /// Number of layers of the environment map texture, 0 without environment map
+    pub levels: int,
+}
+
+/// Shadow view
+/// ```glsl,ignore
+/// struct ShadowView {
+///    mat4 proj_view;
+///    vec4 rect;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+#[repr(C, align(16))]
+pub struct ShadowView {
+    /// Projection and view matrix of the light
+    pub proj_view: mat4,
+    /// Tile of the shadow map atlas as offset and size in texture coordinates
+    pub rect: vec4,
+}
+
 /// Sprite Vertex Data
 /// ```glsl,ignore
 /// vec2 dir_x;
@@ -333,6 +551,103 @@ impl AsVertex for SpriteArgs {
     }
 }
 
+/// Particle Instance Data
+/// ```glsl,ignore
+/// vec3 position;
+/// float size;
+/// vec4 color;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, AsStd140)]
+#[repr(C, align(4))]
+pub struct ParticleArgs {
+    /// World position of the particle
+    pub position: vec3,
+    /// Size of the particle in world units
+    pub size: float,
+    /// Linear RGBA color of the particle
+    pub color: vec4,
+}
+
+impl AsVertex for ParticleArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgb32Sfloat, "position"),
+            (Format::R32Sfloat, "size"),
+            (Format::Rgba32Sfloat, "color"),
+        ))
+    }
+}
+
+/// Decal view
+/// ```glsl,ignore
+/// uniform DecalView {
+///    mat4 proj_view;
+///    mat4 inverse_proj_view;
+///    vec2 screen_size;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+#[repr(C, align(16))]
+pub struct DecalView {
+    /// Premultiplied projection and view matrix of the camera
+    pub proj_view: mat4,
+    /// Inverse of the premultiplied projection and view matrix of the camera
+    pub inverse_proj_view: mat4,
+    /// Size of the render target in pixels
+    pub screen_size: vec2,
+}
+
+/// Decal Instance Data
+/// ```glsl,ignore
+/// mat4 model;
+/// mat4 inverse_model;
+/// vec4 color;
+/// float min_facing;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, packed)]
+pub struct DecalArgs {
+    /// Transformation of the unit cube to the box of the decal
+    pub model: mat4,
+    /// Transformation of world space into the unit cube of the decal
+    pub inverse_model: mat4,
+    /// Linear RGBA color of the decal
+    pub color: vec4,
+    /// Cosine of the maximum angle between the projection axis and the surfaces
+    pub min_facing: float,
+}
+
+impl AsVertex for DecalArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Model::vertex(),
+            (Format::Rgba32Sfloat, "inverse_model"),
+            (Format::Rgba32Sfloat, "inverse_model"),
+            (Format::Rgba32Sfloat, "inverse_model"),
+            (Format::Rgba32Sfloat, "inverse_model"),
+            (Format::Rgba32Sfloat, "color"),
+            (Format::R32Sfloat, "min_facing"),
+        ))
+    }
+}
+
+impl DecalArgs {
+    /// Populates `DecalArgs` from a `Decal` placed by the given global matrix, or returns `None`
+    /// when its box is degenerate.
+    pub fn from_decal(decal: &Decal, global_matrix: &Matrix4<f32>) -> Option<Self> {
+        let model = decal.box_matrix(global_matrix);
+        let inverse_model: [[f32; 4]; 4] = model.try_inverse()?.into();
+        let model: [[f32; 4]; 4] = model.into();
+        let (r, g, b, a) = decal.color.into_linear().into_components();
+        Some(DecalArgs {
+            model: model.into(),
+            inverse_model: inverse_model.into(),
+            color: [r, g, b, a].into(),
+            min_facing: decal.max_angle.cos().max(0.0),
+        })
+    }
+}
+
 impl SpriteArgs {
     /// Extracts POD vertex data from the provided storages for a sprite.
     ///
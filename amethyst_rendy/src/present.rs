@@ -0,0 +1,260 @@
+//! Render graph nodes presenting the window target when it can't be rendered to the surface
+//! directly, because it's multisampled or presented in a custom mode.
+use crate::{settings::RenderSettings, types::Backend};
+use amethyst_core::ecs::World;
+use rendy::{
+    command::{
+        CommandPool, Family, FamilyId, Graphics, IndividualReset, MultiShot, NoSimultaneousUse,
+        Submit,
+    },
+    factory::Factory,
+    frame::{cirque::CommandCirque, Frames},
+    graph::{
+        gfx_acquire_barriers, gfx_release_barriers, present::PresentNode, BufferAccess, BufferId,
+        DynNode, GraphContext, ImageAccess, ImageId, Node, NodeBuffer, NodeBuilder, NodeDesc,
+        NodeId, NodeImage, NodeSubmittable,
+    },
+    hal::{self, command::RawCommandBuffer},
+    wsi::Surface,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Resolves a multisampled image into an image with a single sample per pixel.
+///
+/// The first image of the node is the multisampled source, the second the destination.
+#[derive(Debug, Default)]
+pub(crate) struct ResolveDesc;
+
+impl<B: Backend> NodeDesc<B, World> for ResolveDesc {
+    type Node = Resolve<B>;
+
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::TRANSFER_READ,
+                usage: hal::image::Usage::TRANSFER_SRC,
+                layout: hal::image::Layout::TransferSrcOptimal,
+                stages: hal::pso::PipelineStage::TRANSFER,
+            },
+            ImageAccess {
+                access: hal::image::Access::TRANSFER_WRITE,
+                usage: hal::image::Usage::TRANSFER_DST,
+                layout: hal::image::Layout::TransferDstOptimal,
+                stages: hal::pso::PipelineStage::TRANSFER,
+            },
+        ]
+    }
+
+    fn build<'a>(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _aux: &World,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Self::Node, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let pool = factory
+            .create_command_pool(family)?
+            .with_capability()
+            .map_err(|_| failure::format_err!("Multisampling requires a graphics queue"))?;
+        let mut images = images.into_iter();
+        match (images.next(), images.next()) {
+            (Some(source), Some(destination)) => Ok(Resolve {
+                source,
+                destination,
+                pool,
+                cirque: CommandCirque::new(),
+            }),
+            _ => Err(failure::format_err!(
+                "Resolve node expects a source and a destination image"
+            )),
+        }
+    }
+}
+
+/// Node resolving a multisampled image.
+#[derive(Debug)]
+pub(crate) struct Resolve<B: Backend> {
+    source: NodeImage,
+    destination: NodeImage,
+    pool: CommandPool<B, Graphics, IndividualReset>,
+    cirque: CommandCirque<B, Graphics>,
+}
+
+impl<'a, B: Backend> NodeSubmittable<'a, B> for Resolve<B> {
+    type Submittable = Submit<B>;
+    type Submittables = Option<Submit<B>>;
+}
+
+impl<B: Backend> Node<B, World> for Resolve<B> {
+    type Capability = Graphics;
+    type Desc = ResolveDesc;
+
+    fn run<'a>(
+        &'a mut self,
+        ctx: &GraphContext<B>,
+        _factory: &Factory<B>,
+        _world: &World,
+        frames: &'a Frames<B>,
+    ) -> Option<Submit<B>> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run");
+
+        let images = [&self.source, &self.destination];
+        // The images don't change during the lifetime of the graph, so the commands are
+        // recorded once for every frame in flight.
+        let submit = self.cirque.encode(frames, &mut self.pool, |cbuf| {
+            cbuf.or_init(|cbuf| {
+                let mut cbuf = cbuf.begin(MultiShot(NoSimultaneousUse), ());
+                unsafe {
+                    let (stages, barriers) =
+                        gfx_acquire_barriers(ctx, None, images.iter().copied());
+                    if !barriers.is_empty() {
+                        cbuf.encoder().pipeline_barrier(
+                            stages,
+                            hal::memory::Dependencies::empty(),
+                            barriers,
+                        );
+                    }
+                    resolve_image(ctx, cbuf.raw(), images[0], images[1]);
+                    let (stages, barriers) =
+                        gfx_release_barriers(ctx, None, images.iter().copied());
+                    if !barriers.is_empty() {
+                        cbuf.encoder().pipeline_barrier(
+                            stages,
+                            hal::memory::Dependencies::empty(),
+                            barriers,
+                        );
+                    }
+                }
+                cbuf.finish()
+            })
+        });
+        Some(submit)
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _world: &World) {
+        let pool = &mut self.pool;
+        self.cirque.dispose(|buffer| {
+            buffer.either_with(
+                &mut *pool,
+                |pool, executable| pool.free_buffers(Some(executable)),
+                |pool, pending| pool.free_buffers(Some(pending.mark_complete())),
+            );
+        });
+        factory.destroy_command_pool(self.pool.with_queue_type());
+    }
+}
+
+unsafe fn resolve_image<B: Backend>(
+    ctx: &GraphContext<B>,
+    raw: &mut B::CommandBuffer,
+    source: &NodeImage,
+    destination: &NodeImage,
+) {
+    let source_image = ctx.get_image(source.id).expect("Image does not exist");
+    let destination_image = ctx.get_image(destination.id).expect("Image does not exist");
+    let layers = hal::image::SubresourceLayers {
+        aspects: hal::format::Aspects::COLOR,
+        level: 0,
+        layers: 0..1,
+    };
+    raw.resolve_image(
+        source_image.raw(),
+        source.layout,
+        destination_image.raw(),
+        destination.layout,
+        Some(hal::command::ImageResolve {
+            src_subresource: layers.clone(),
+            src_offset: hal::image::Offset::ZERO,
+            dst_subresource: layers,
+            dst_offset: hal::image::Offset::ZERO,
+            extent: source_image.kind().extent(),
+        }),
+    );
+}
+
+/// Presents an image to a surface with the present mode picked by `RenderSettings`.
+///
+/// Wraps rendy's `PresentNode`, whose builder can only be created with access to the factory.
+#[derive(Debug)]
+pub(crate) struct PresentDesc<B: Backend> {
+    surface: Surface<B>,
+    image: ImageId,
+    settings: RenderSettings,
+    dependencies: Vec<NodeId>,
+}
+
+impl<B: Backend> PresentDesc<B> {
+    pub(crate) fn new(
+        surface: Surface<B>,
+        image: ImageId,
+        settings: RenderSettings,
+        dependencies: Vec<NodeId>,
+    ) -> Self {
+        Self {
+            surface,
+            image,
+            settings,
+            dependencies,
+        }
+    }
+}
+
+impl<B: Backend> NodeBuilder<B, World> for PresentDesc<B> {
+    fn family(&self, factory: &mut Factory<B>, families: &[Family<B>]) -> Option<FamilyId> {
+        families
+            .iter()
+            .find(|family| factory.surface_support(family.id(), &self.surface))
+            .map(Family::id)
+    }
+
+    fn buffers(&self) -> Vec<(BufferId, BufferAccess)> {
+        Vec::new()
+    }
+
+    fn images(&self) -> Vec<(ImageId, ImageAccess)> {
+        vec![(
+            self.image,
+            ImageAccess {
+                access: hal::image::Access::TRANSFER_READ,
+                layout: hal::image::Layout::TransferSrcOptimal,
+                usage: hal::image::Usage::TRANSFER_SRC,
+                stages: hal::pso::PipelineStage::TRANSFER,
+            },
+        )]
+    }
+
+    fn dependencies(&self) -> Vec<NodeId> {
+        self.dependencies.clone()
+    }
+
+    fn build<'a>(
+        self: Box<Self>,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        queue: usize,
+        aux: &World,
+        buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn DynNode<B, World>>, failure::Error> {
+        let Self {
+            surface,
+            image,
+            settings,
+            ..
+        } = *self;
+        let present = PresentNode::builder(factory, surface, image)
+            .with_present_modes_priority(|mode| settings.present_mode_priority(mode));
+        log::debug!("Presenting in {:?} mode", present.present_mode());
+        Box::new(present).build(ctx, factory, family, queue, aux, buffers, images)
+    }
+}
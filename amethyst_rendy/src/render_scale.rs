@@ -0,0 +1,172 @@
+//! Dynamic resolution scaling of the scene rendered by `RenderScaled`.
+
+use amethyst_core::{
+    ecs::{Read, System, SystemData, World, Write},
+    timing::Time,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Steps the scale is rounded to, as changing it rebuilds the render graph.
+const SCALE_STEP: f32 = 0.05;
+
+/// Seconds the automatic adjustment waits after changing the scale, so that the frame time of
+/// the new resolution is measured before the next change.
+const ADJUST_INTERVAL: f32 = 0.5;
+
+/// Resource controlling the resolution the `RenderScaled` plugin renders the scene at, relative
+/// to the resolution of the window. The defaults are used when the resource is missing.
+///
+/// The scale is rounded to steps of 5%, and changing the rounded scale rebuilds the render graph.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RenderScale {
+    /// Fraction of the window resolution the scene is rendered at.
+    pub scale: f32,
+    /// Lowest scale, also for the automatic adjustment.
+    pub min_scale: f32,
+    /// Highest scale, also for the automatic adjustment. Values above 1.0 supersample the scene.
+    pub max_scale: f32,
+    /// Frame time in seconds `RenderScaleSystem` adjusts the scale for, or `None` to keep the
+    /// scale as set.
+    pub target_frame_time: Option<f32>,
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+            target_frame_time: None,
+        }
+    }
+}
+
+impl RenderScale {
+    /// Returns the scale the scene is rendered at, clamped and rounded to steps of 5%.
+    pub fn effective_scale(&self) -> f32 {
+        let scale = self.scale.max(self.min_scale).min(self.max_scale);
+        ((scale / SCALE_STEP).round() * SCALE_STEP).max(SCALE_STEP)
+    }
+
+    /// Returns the size in pixels the scene is rendered at for a window of the given size.
+    pub fn scaled_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let scale = self.effective_scale();
+        let scaled = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+        (scaled(width), scaled(height))
+    }
+}
+
+/// Returns the `RenderScale` of the world, or the defaults when the resource is missing.
+pub(crate) fn render_scale(world: &World) -> RenderScale {
+    <Option<Read<'_, RenderScale>>>::fetch(world)
+        .map(|scale| scale.clone())
+        .unwrap_or_default()
+}
+
+/// Adjusts `RenderScale::scale` by the average frame time when the resource has a
+/// `target_frame_time`: lowering the resolution when frames take longer than the target, and
+/// raising it when they are well below.
+#[derive(Debug, Default)]
+pub struct RenderScaleSystem {
+    average_frame_time: Option<f32>,
+    since_change: f32,
+}
+
+impl RenderScaleSystem {
+    /// Create new render scale system
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for RenderScaleSystem {
+    type SystemData = (Read<'a, Time>, Option<Write<'a, RenderScale>>);
+
+    fn run(&mut self, (time, render_scale): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("render_scale_system");
+
+        let mut render_scale = match render_scale {
+            Some(render_scale) => render_scale,
+            None => return,
+        };
+        let target = match render_scale.target_frame_time {
+            Some(target) => target,
+            None => {
+                self.average_frame_time = None;
+                return;
+            }
+        };
+
+        let frame_time = time.delta_real_seconds();
+        let average = match self.average_frame_time {
+            Some(average) => average * 0.9 + frame_time * 0.1,
+            None => frame_time,
+        };
+        self.average_frame_time = Some(average);
+        self.since_change += frame_time;
+        if self.since_change < ADJUST_INTERVAL {
+            return;
+        }
+
+        let scale = render_scale.effective_scale();
+        let adjusted = if average > target * 1.05 {
+            scale - SCALE_STEP
+        } else if average < target * 0.8 {
+            scale + SCALE_STEP
+        } else {
+            return;
+        };
+        let adjusted = adjusted
+            .max(render_scale.min_scale)
+            .min(render_scale.max_scale);
+        if (adjusted - scale).abs() > SCALE_STEP / 2.0 {
+            render_scale.scale = adjusted;
+            self.since_change = 0.0;
+            // Measure the frame time of the new resolution from scratch.
+            self.average_frame_time = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{RunNow, WorldExt};
+    use approx::assert_ulps_eq;
+    use std::time::Duration;
+
+    #[test]
+    fn effective_scale_is_clamped_and_rounded() {
+        let scale = |scale| RenderScale {
+            scale,
+            ..Default::default()
+        };
+        assert_ulps_eq!(scale(0.73).effective_scale(), 0.75);
+        assert_ulps_eq!(scale(0.2).effective_scale(), 0.5);
+        assert_ulps_eq!(scale(1.5).effective_scale(), 1.0);
+        assert_eq!(scale(0.75).scaled_size(1280, 721), (960, 541));
+    }
+
+    #[test]
+    fn system_lowers_scale_of_slow_frames() {
+        let mut world = World::new();
+        let mut time = Time::default();
+        time.set_delta_time(Duration::from_millis(25));
+        world.insert(time);
+        world.insert(RenderScale {
+            target_frame_time: Some(1.0 / 60.0),
+            ..Default::default()
+        });
+
+        let mut system = RenderScaleSystem::new();
+        for _ in 0..30 {
+            system.run_now(&world);
+        }
+        // One step every half second of 25 ms frames.
+        assert_ulps_eq!(world.read_resource::<RenderScale>().effective_scale(), 0.95);
+    }
+}
@@ -0,0 +1,374 @@
+//! Offscreen textures rendered by cameras, e.g. for minimaps, mirrors and portals.
+use crate::{
+    bundle::Target,
+    types::{Backend, Texture, TextureData},
+};
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::ecs::{Component, DenseVecStorage, Entities, Entity, Join, ReadStorage, World};
+use rendy::{
+    command::{
+        CommandPool, Encoder, Family, Graphics, IndividualReset, MultiShot, NoSimultaneousUse,
+        PrimaryLevel, Submit,
+    },
+    factory::Factory,
+    frame::{cirque::CommandCirque, Frames},
+    graph::{
+        gfx_acquire_barriers, gfx_release_barriers, GraphContext, ImageAccess, Node, NodeBuffer,
+        NodeDesc, NodeImage, NodeSubmittable,
+    },
+    hal::{self, format::Format, image::Kind},
+    texture::{pixel::Rgba8Srgb, TextureBuilder},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Renders the view of the `Camera` on the same entity into a texture instead of the window.
+///
+/// The scene is rendered into the `Target::Custom` named by the render texture, so plugins have
+/// to be pointed at it with `with_target` to draw anything, e.g.
+/// `RenderPbr3D::default().with_target(render_texture.target())`. The plugins look the camera up
+/// by its target. The result is copied into `texture` every frame, which can be used on materials
+/// and `UiImage`s like any other texture. The copy isn't ordered after the rendering of the rest
+/// of the frame, so the texture may show the previous frame.
+///
+/// Requires the `RenderCameraTextures` plugin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderTexture {
+    name: &'static str,
+    width: u32,
+    height: u32,
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    texture: Handle<Texture>,
+}
+
+impl Component for RenderTexture {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl RenderTexture {
+    /// Creates a render texture of the given size with a new texture to render into, cleared to
+    /// opaque black. `name` identifies the render target and must be unique.
+    pub fn new(
+        name: &'static str,
+        width: u32,
+        height: u32,
+        loader: &Loader,
+        storage: &AssetStorage<Texture>,
+    ) -> Self {
+        let texture = loader.load_from_data(texture_data(width, height), (), storage);
+        Self {
+            name,
+            width,
+            height,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            clear_depth: 0.0,
+            texture,
+        }
+    }
+
+    /// Sets the color the texture is cleared to before rendering.
+    pub fn with_clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// Sets the value the depth buffer is cleared to before rendering, 0.0 by default for the
+    /// reversed depth of the `Camera` projections.
+    pub fn with_clear_depth(mut self, clear_depth: f32) -> Self {
+        self.clear_depth = clear_depth;
+        self
+    }
+
+    /// Returns the render target the scene is rendered into.
+    pub fn target(&self) -> Target {
+        Target::Custom(self.name)
+    }
+
+    /// Returns the target of the node copying the rendered scene into the texture.
+    pub(crate) fn copy_target(&self) -> Target {
+        Target::TextureCopy(self.name)
+    }
+
+    /// Returns the width and height of the texture.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the color the texture is cleared to before rendering.
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Returns the value the depth buffer is cleared to before rendering.
+    pub fn clear_depth(&self) -> f32 {
+        self.clear_depth
+    }
+
+    /// Returns the texture the camera renders into.
+    pub fn texture(&self) -> &Handle<Texture> {
+        &self.texture
+    }
+}
+
+/// Format of the render targets and textures of `RenderTexture`s.
+pub(crate) const RENDER_TEXTURE_FORMAT: Format = Format::Rgba8Srgb;
+
+fn texture_data(width: u32, height: u32) -> TextureData {
+    TextureBuilder::new()
+        .with_kind(Kind::D2(width, height, 1, 1))
+        .with_view_kind(hal::image::ViewKind::D2)
+        .with_data_width(width)
+        .with_data_height(height)
+        .with_sampler_info(hal::image::SamplerInfo::new(
+            hal::image::Filter::Linear,
+            hal::image::WrapMode::Clamp,
+        ))
+        .with_data(vec![Rgba8Srgb::default(); (width * height) as usize])
+        .into()
+}
+
+/// Returns the camera entity rendering into the given target, if the target belongs to a
+/// `RenderTexture`.
+pub fn render_texture_camera(world: &World, target: Target) -> Option<Entity> {
+    if target == Target::Main {
+        return None;
+    }
+    let (entities, render_textures) =
+        world.system_data::<(Entities<'_>, ReadStorage<'_, RenderTexture>)>();
+    (&entities, &render_textures)
+        .join()
+        .find(|(_, render_texture)| render_texture.target() == target)
+        .map(|(entity, _)| entity)
+}
+
+/// Copies the color output of a camera's render target into the texture of its
+/// `RenderTexture`.
+#[derive(Debug)]
+pub(crate) struct CopyToTextureDesc {
+    texture: Handle<Texture>,
+}
+
+impl CopyToTextureDesc {
+    pub(crate) fn new(texture: Handle<Texture>) -> Self {
+        Self { texture }
+    }
+}
+
+impl<B: Backend> NodeDesc<B, World> for CopyToTextureDesc {
+    type Node = CopyToTexture<B>;
+
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: hal::image::Access::TRANSFER_READ,
+            usage: hal::image::Usage::TRANSFER_SRC,
+            layout: hal::image::Layout::TransferSrcOptimal,
+            stages: hal::pso::PipelineStage::TRANSFER,
+        }]
+    }
+
+    fn build<'a>(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _aux: &World,
+        _buffers: Vec<NodeBuffer>,
+        mut images: Vec<NodeImage>,
+    ) -> Result<Self::Node, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let pool = factory
+            .create_command_pool(family)?
+            .with_capability()
+            .map_err(|_| failure::format_err!("Render textures require a graphics queue"))?;
+        Ok(CopyToTexture {
+            texture: self.texture,
+            image: images.remove(0),
+            pool,
+            cirque: CommandCirque::new(),
+        })
+    }
+}
+
+/// Node copying the color output of a camera's render target into its texture.
+#[derive(Debug)]
+pub(crate) struct CopyToTexture<B: Backend> {
+    texture: Handle<Texture>,
+    image: NodeImage,
+    pool: CommandPool<B, Graphics, IndividualReset>,
+    cirque: CommandCirque<B, Graphics>,
+}
+
+impl<'a, B: Backend> NodeSubmittable<'a, B> for CopyToTexture<B> {
+    type Submittable = Submit<B>;
+    type Submittables = Option<Submit<B>>;
+}
+
+impl<B: Backend> Node<B, World> for CopyToTexture<B> {
+    type Capability = Graphics;
+    type Desc = CopyToTextureDesc;
+
+    fn run<'a>(
+        &'a mut self,
+        ctx: &GraphContext<B>,
+        _factory: &Factory<B>,
+        world: &World,
+        frames: &'a Frames<B>,
+    ) -> Option<Submit<B>> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run");
+
+        let texture_storage = world.fetch::<AssetStorage<Texture>>();
+        let texture = texture_storage
+            .get(&self.texture)
+            .and_then(B::unwrap_texture);
+        let image = &self.image;
+
+        // The texture may not be loaded yet, so the commands are recorded every frame.
+        let submit = self.cirque.encode(frames, &mut self.pool, |cbuf| {
+            cbuf.or_reset(|cbuf| cbuf.reset()).init(|cbuf| {
+                let mut cbuf = cbuf.begin(MultiShot(NoSimultaneousUse), ());
+                let mut encoder = cbuf.encoder();
+                unsafe {
+                    let (stages, barriers) = gfx_acquire_barriers(ctx, None, Some(image));
+                    if !barriers.is_empty() {
+                        encoder.pipeline_barrier(
+                            stages,
+                            hal::memory::Dependencies::empty(),
+                            barriers,
+                        );
+                    }
+                    if let Some(texture) = texture {
+                        copy_image(ctx, &mut encoder, image, texture.image().raw());
+                    }
+                    let (stages, barriers) = gfx_release_barriers(ctx, None, Some(image));
+                    if !barriers.is_empty() {
+                        encoder.pipeline_barrier(
+                            stages,
+                            hal::memory::Dependencies::empty(),
+                            barriers,
+                        );
+                    }
+                }
+                cbuf.finish()
+            })
+        });
+        Some(submit)
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _world: &World) {
+        let pool = &mut self.pool;
+        self.cirque.dispose(|buffer| {
+            buffer.either_with(
+                &mut *pool,
+                |pool, executable| pool.free_buffers(Some(executable)),
+                |pool, pending| pool.free_buffers(Some(pending.mark_complete())),
+            );
+        });
+        factory.destroy_command_pool(self.pool.with_queue_type());
+    }
+}
+
+/// Records copying the node image into the texture image, which is kept in the layout used for
+/// sampling outside of the copy.
+unsafe fn copy_image<B: Backend>(
+    ctx: &GraphContext<B>,
+    encoder: &mut Encoder<'_, B, Graphics, PrimaryLevel>,
+    image: &NodeImage,
+    texture: &B::Image,
+) {
+    let source = ctx.get_image(image.id).expect("Image does not exist");
+    let extent = source.kind().extent();
+    let range = hal::image::SubresourceRange {
+        aspects: hal::format::Aspects::COLOR,
+        levels: 0..1,
+        layers: 0..1,
+    };
+    let layers = hal::image::SubresourceLayers {
+        aspects: hal::format::Aspects::COLOR,
+        level: 0,
+        layers: 0..1,
+    };
+    let barrier = |states: std::ops::Range<hal::image::State>| hal::memory::Barrier::Image {
+        states,
+        target: texture,
+        families: None,
+        range: range.clone(),
+    };
+    let sampled = (
+        hal::image::Access::SHADER_READ,
+        hal::image::Layout::ShaderReadOnlyOptimal,
+    );
+    let transfer = (
+        hal::image::Access::TRANSFER_WRITE,
+        hal::image::Layout::TransferDstOptimal,
+    );
+
+    encoder.pipeline_barrier(
+        hal::pso::PipelineStage::VERTEX_SHADER | hal::pso::PipelineStage::FRAGMENT_SHADER
+            ..hal::pso::PipelineStage::TRANSFER,
+        hal::memory::Dependencies::empty(),
+        Some(barrier(sampled..transfer)),
+    );
+    encoder.copy_image(
+        source.raw(),
+        image.layout,
+        texture,
+        hal::image::Layout::TransferDstOptimal,
+        Some(hal::command::ImageCopy {
+            src_subresource: layers.clone(),
+            src_offset: hal::image::Offset::ZERO,
+            dst_subresource: layers,
+            dst_offset: hal::image::Offset::ZERO,
+            extent,
+        }),
+    );
+    encoder.pipeline_barrier(
+        hal::pso::PipelineStage::TRANSFER
+            ..hal::pso::PipelineStage::VERTEX_SHADER | hal::pso::PipelineStage::FRAGMENT_SHADER,
+        hal::memory::Dependencies::empty(),
+        Some(barrier(transfer..sampled)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, WorldExt};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn finds_camera_by_target() {
+        let mut world = World::new();
+        world.register::<RenderTexture>();
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let storage = AssetStorage::<Texture>::default();
+        let minimap = RenderTexture::new("minimap", 64, 64, &loader, &storage);
+        let mirror = RenderTexture::new("mirror", 128, 32, &loader, &storage);
+        assert_eq!(mirror.size(), (128, 32));
+        assert_ne!(minimap.target(), minimap.copy_target());
+
+        let minimap_target = minimap.target();
+        let minimap_camera = world.create_entity().with(minimap).build();
+        let mirror_camera = world.create_entity().with(mirror).build();
+
+        assert_eq!(
+            render_texture_camera(&world, minimap_target),
+            Some(minimap_camera)
+        );
+        assert_eq!(
+            render_texture_camera(&world, Target::Custom("mirror")),
+            Some(mirror_camera)
+        );
+        assert_eq!(
+            render_texture_camera(&world, Target::Custom("portal")),
+            None
+        );
+        assert_eq!(render_texture_camera(&world, Target::Main), None);
+    }
+}
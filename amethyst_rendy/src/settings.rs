@@ -0,0 +1,154 @@
+//! Runtime settings of the presentation to the window.
+
+use amethyst_core::ecs::{Read, SystemData, World};
+use rendy::hal::{self, image::NumSamples};
+
+/// Resource controlling how `RenderToWindow` renders and presents to the window.
+/// The defaults are used when the resource is missing.
+///
+/// Changing the settings rebuilds the render graph, recreating the swapchain and the window
+/// render target.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    /// Number of samples per pixel of the window target used for multisample anti-aliasing,
+    /// 1 disables it. Rounded down to a count supported by the device.
+    pub samples: u8,
+    /// Wait for the vertical blank before presenting a frame, which prevents tearing.
+    pub vsync: bool,
+    /// Present mode used instead of the one picked by `vsync`. Falls back to `vsync` when the
+    /// surface doesn't support it.
+    pub present_mode: Option<PresentMode>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            samples: 1,
+            vsync: true,
+            present_mode: None,
+        }
+    }
+}
+
+/// The way rendered frames are queued for presentation to the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PresentMode {
+    /// Frames are presented immediately, which may tear.
+    Immediate,
+    /// The latest frame replaces the queued one and is presented on the vertical blank.
+    Mailbox,
+    /// Frames are queued and presented on the vertical blank, always supported.
+    Fifo,
+    /// Like `Fifo`, but a late frame is presented immediately, which may tear.
+    Relaxed,
+}
+
+impl From<PresentMode> for hal::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Immediate => hal::PresentMode::Immediate,
+            PresentMode::Mailbox => hal::PresentMode::Mailbox,
+            PresentMode::Fifo => hal::PresentMode::Fifo,
+            PresentMode::Relaxed => hal::PresentMode::Relaxed,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Returns true when the window can be rendered to directly, presenting in `Fifo` mode
+    /// without multisampling.
+    pub(crate) fn is_direct(&self) -> bool {
+        self.samples <= 1
+            && self
+                .present_mode
+                .map_or(self.vsync, |mode| mode == PresentMode::Fifo)
+    }
+
+    /// Priority of the given present mode, higher is preferred and `None` isn't used.
+    /// `Fifo` is always allowed, as it's the only mode every surface supports.
+    pub(crate) fn present_mode_priority(&self, mode: hal::PresentMode) -> Option<usize> {
+        if self.present_mode.map(hal::PresentMode::from) == Some(mode) {
+            return Some(3);
+        }
+        match (self.vsync, mode) {
+            (_, hal::PresentMode::Fifo) => Some(0),
+            (false, hal::PresentMode::Immediate) => Some(2),
+            (false, hal::PresentMode::Mailbox) => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Returns the largest sample count not above `samples` set in the `supported` mask of
+    /// sample counts.
+    pub(crate) fn supported_samples(&self, supported: NumSamples) -> NumSamples {
+        let mut samples = self.samples.max(1).next_power_of_two();
+        if samples > self.samples.max(1) {
+            samples >>= 1;
+        }
+        while samples > 1 && supported & samples == 0 {
+            samples >>= 1;
+        }
+        samples
+    }
+}
+
+/// Returns the `RenderSettings` of the world, or the defaults when the resource is missing.
+pub(crate) fn render_settings(world: &World) -> RenderSettings {
+    <Option<Read<'_, RenderSettings>>>::fetch(world)
+        .map(|settings| settings.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_round_down_to_supported() {
+        let settings = |samples| RenderSettings {
+            samples,
+            ..Default::default()
+        };
+        assert_eq!(settings(0).supported_samples(0b1111), 1);
+        assert_eq!(settings(4).supported_samples(0b1111), 4);
+        assert_eq!(settings(6).supported_samples(0b1111), 4);
+        assert_eq!(settings(8).supported_samples(0b0101), 4);
+        assert_eq!(settings(16).supported_samples(0b0001), 1);
+    }
+
+    #[test]
+    fn present_mode_priority() {
+        let vsync = RenderSettings::default();
+        assert!(vsync.is_direct());
+        assert_eq!(
+            vsync.present_mode_priority(hal::PresentMode::Immediate),
+            None
+        );
+        assert_eq!(vsync.present_mode_priority(hal::PresentMode::Fifo), Some(0));
+
+        let no_vsync = RenderSettings {
+            vsync: false,
+            ..Default::default()
+        };
+        assert!(!no_vsync.is_direct());
+        assert!(
+            no_vsync.present_mode_priority(hal::PresentMode::Immediate)
+                > no_vsync.present_mode_priority(hal::PresentMode::Mailbox)
+        );
+
+        let mailbox = RenderSettings {
+            present_mode: Some(PresentMode::Mailbox),
+            ..Default::default()
+        };
+        assert!(!mailbox.is_direct());
+        assert_eq!(
+            mailbox.present_mode_priority(hal::PresentMode::Mailbox),
+            Some(3)
+        );
+        assert_eq!(
+            mailbox.present_mode_priority(hal::PresentMode::Fifo),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Shaders loaded as assets, for custom passes and post effects.
+//!
+//! A `ShaderAsset` is loaded from SPIR-V with `SpirvFormat` or, with the `shader-compiler`
+//! feature, compiled from GLSL at runtime with `GlslFormat`. Like other assets, shaders are
+//! reloaded when their file changes if hot reloading is enabled with `HotReloadBundle`. Render
+//! plugins track the shaders they use with `ShaderVersions` to rebuild the render graph with the
+//! new shader.
+
+use amethyst_assets::{Asset, AssetStorage, Handle};
+use amethyst_core::ecs::prelude::{DenseVecStorage, Read, SystemData, World};
+use rendy::{hal::pso::ShaderStageFlags, shader::SpirvShader};
+use serde::{Deserialize, Serialize};
+
+/// An asset handle to a shader.
+pub type ShaderHandle = Handle<ShaderAsset>;
+
+/// Pipeline stage a shader is written for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ShaderStage {
+    /// Vertex shader.
+    Vertex,
+    /// Fragment shader.
+    Fragment,
+    /// Geometry shader.
+    Geometry,
+    /// Compute shader.
+    Compute,
+}
+
+impl ShaderStage {
+    /// Returns the stage flags of the stage.
+    pub fn flags(self) -> ShaderStageFlags {
+        match self {
+            ShaderStage::Vertex => ShaderStageFlags::VERTEX,
+            ShaderStage::Fragment => ShaderStageFlags::FRAGMENT,
+            ShaderStage::Geometry => ShaderStageFlags::GEOMETRY,
+            ShaderStage::Compute => ShaderStageFlags::COMPUTE,
+        }
+    }
+}
+
+/// A SPIR-V shader loaded from an asset file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderAsset(pub SpirvShader);
+
+amethyst_assets::register_format_type!(ShaderAsset);
+
+impl Asset for ShaderAsset {
+    const NAME: &'static str = "renderer::Shader";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<Handle<Self>>;
+}
+
+impl ShaderAsset {
+    /// Returns the loaded shader.
+    pub fn shader(&self) -> &SpirvShader {
+        &self.0
+    }
+}
+
+/// Returns a copy of the shader behind the handle, or `None` while it isn't loaded.
+pub fn loaded_shader(world: &World, handle: &ShaderHandle) -> Option<SpirvShader> {
+    <Option<Read<'_, AssetStorage<ShaderAsset>>>>::fetch(world)?
+        .get(handle)
+        .map(|asset| asset.shader().clone())
+}
+
+/// Tracks the versions of shader assets, so that a render plugin can rebuild the render graph
+/// when a shader it uses is loaded or reloaded.
+///
+/// ```
+/// # use amethyst_rendy::shader::{ShaderHandle, ShaderVersions};
+/// # use amethyst_core::ecs::World;
+/// struct MyPlugin {
+///     shader: ShaderHandle,
+///     versions: ShaderVersions,
+/// }
+///
+/// impl MyPlugin {
+///     // Called from `RenderPlugin::should_rebuild`.
+///     fn should_rebuild(&mut self, world: &World) -> bool {
+///         self.versions.changed(world, Some(&self.shader))
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderVersions {
+    versions: Vec<(u32, Option<u32>)>,
+}
+
+impl ShaderVersions {
+    /// Create an empty tracker, reporting the first loaded version of every shader as a change.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns whether the set of shaders or the versions of their loaded assets changed since
+    /// the last call.
+    pub fn changed<'a>(
+        &mut self,
+        world: &World,
+        shaders: impl IntoIterator<Item = &'a ShaderHandle>,
+    ) -> bool {
+        let storage = <Option<Read<'_, AssetStorage<ShaderAsset>>>>::fetch(world);
+        let versions = shaders
+            .into_iter()
+            .map(|handle| {
+                let version = storage
+                    .as_ref()
+                    .and_then(|storage| storage.get_version(handle));
+                (handle.id(), version)
+            })
+            .collect::<Vec<_>>();
+        if versions == self.versions {
+            false
+        } else {
+            self.versions = versions;
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::WorldExt;
+
+    fn shader() -> ShaderAsset {
+        ShaderAsset(SpirvShader::new(
+            vec![0x0723_0203],
+            ShaderStageFlags::FRAGMENT,
+            "main",
+        ))
+    }
+
+    #[test]
+    fn versions_change_when_shaders_reload() {
+        let mut world = World::new();
+        world.insert(AssetStorage::<ShaderAsset>::new());
+        let handle = world
+            .write_resource::<AssetStorage<ShaderAsset>>()
+            .insert(shader());
+
+        let mut versions = ShaderVersions::new();
+        assert!(versions.changed(&world, Some(&handle)));
+        assert!(!versions.changed(&world, Some(&handle)));
+
+        world
+            .write_resource::<AssetStorage<ShaderAsset>>()
+            .replace(&handle, shader());
+        assert!(versions.changed(&world, Some(&handle)));
+        assert!(!versions.changed(&world, Some(&handle)));
+
+        // Dropping the shader from the set is a change as well.
+        assert!(versions.changed(&world, None));
+    }
+
+    #[test]
+    fn missing_storage_reports_unloaded_shaders() {
+        let mut world = World::new();
+        world.insert(AssetStorage::<ShaderAsset>::new());
+        let handle = world
+            .write_resource::<AssetStorage<ShaderAsset>>()
+            .insert(shader());
+        assert!(loaded_shader(&world, &handle).is_some());
+
+        let empty = World::new();
+        assert!(loaded_shader(&empty, &handle).is_none());
+        let mut versions = ShaderVersions::new();
+        assert!(versions.changed(&empty, Some(&handle)));
+        assert!(!versions.changed(&empty, Some(&handle)));
+    }
+}
@@ -0,0 +1,518 @@
+//! Shadow mapping for directional and point lights.
+//!
+//! Shadows are rendered into a single depth texture, the shadow map atlas. The first directional
+//! light casting shadows gets cascaded shadow maps placed side by side in the top row of the
+//! atlas, and each point light casting shadows gets a row of six cube faces below it.
+
+use crate::{
+    camera::Camera,
+    light::Light,
+    submodules::{gather::CameraGatherer, MAX_DIR_LIGHTS, MAX_POINT_LIGHTS},
+};
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3, Vector3, Vector4},
+    transform::Transform,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Maximum number of cascades of the directional light shadow.
+pub const MAX_CASCADES: usize = 4;
+/// Maximum number of point lights casting shadows.
+pub const MAX_SHADOWED_POINT_LIGHTS: usize = 4;
+/// Maximum number of views rendered into the shadow map atlas.
+pub(crate) const MAX_SHADOW_VIEWS: usize = MAX_CASCADES + 6 * MAX_SHADOWED_POINT_LIGHTS;
+
+const POINT_SHADOW_NEAR: f32 = 0.05;
+
+/// Resource controlling the quality of the shadows rendered by `RenderPbr3D::with_shadows`.
+/// The defaults are used when the resource is missing.
+///
+/// Only lights with `cast_shadows` set cast shadows. Changing the number of cascades, the number
+/// of point lights or any resolution resizes the shadow map, which rebuilds the render graph.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ShadowConfig {
+    /// Settings of the directional light shadow.
+    pub directional: DirectionalShadowConfig,
+    /// Settings of the point light shadows.
+    pub point: PointShadowConfig,
+}
+
+/// Cascaded shadow map settings of the directional light.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DirectionalShadowConfig {
+    /// Number of cascades the shadowed distance is split into, up to `MAX_CASCADES`.
+    /// 0 disables directional light shadows.
+    pub cascades: u32,
+    /// Width and height of each cascade in texels.
+    pub resolution: u32,
+    /// Distance from the camera up to which shadows are drawn.
+    pub distance: f32,
+    /// Blend between uniform (0.0) and logarithmic (1.0) cascade splits.
+    pub split_lambda: f32,
+    /// Depth bias against shadow acne.
+    pub bias: f32,
+    /// Radius of the filter softening the shadow edges in texels, 0 draws hard edges.
+    pub filter_radius: u32,
+}
+
+impl Default for DirectionalShadowConfig {
+    fn default() -> Self {
+        Self {
+            cascades: 3,
+            resolution: 1024,
+            distance: 50.0,
+            split_lambda: 0.75,
+            bias: 0.002,
+            filter_radius: 1,
+        }
+    }
+}
+
+/// Cube shadow map settings of the point lights.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PointShadowConfig {
+    /// Number of point lights which can cast shadows, up to `MAX_SHADOWED_POINT_LIGHTS`.
+    /// 0 disables point light shadows.
+    pub max_lights: u32,
+    /// Width and height of each cube face in texels.
+    pub resolution: u32,
+    /// Depth bias against shadow acne.
+    pub bias: f32,
+    /// Radius of the filter softening the shadow edges in texels, 0 draws hard edges.
+    pub filter_radius: u32,
+}
+
+impl Default for PointShadowConfig {
+    fn default() -> Self {
+        Self {
+            max_lights: 2,
+            resolution: 512,
+            bias: 0.0005,
+            filter_radius: 1,
+        }
+    }
+}
+
+impl ShadowConfig {
+    fn cascades(&self) -> u32 {
+        self.directional.cascades.min(MAX_CASCADES as u32)
+    }
+
+    fn point_lights(&self) -> u32 {
+        self.point.max_lights.min(MAX_SHADOWED_POINT_LIGHTS as u32)
+    }
+
+    fn directional_resolution(&self) -> u32 {
+        self.directional.resolution.max(1)
+    }
+
+    fn point_resolution(&self) -> u32 {
+        self.point.resolution.max(1)
+    }
+
+    /// Returns the size of the shadow map atlas in texels.
+    pub fn atlas_size(&self) -> (u32, u32) {
+        let (cascades, point_lights) = (self.cascades(), self.point_lights());
+        let (dir_res, point_res) = (self.directional_resolution(), self.point_resolution());
+        let row = if cascades > 0 { dir_res } else { 0 };
+        let width = (cascades * dir_res).max(if point_lights > 0 { 6 * point_res } else { 0 });
+        (width.max(1), (row + point_lights * point_res).max(1))
+    }
+
+    /// Returns the tile of the atlas the given cascade is rendered to, as x, y and size in texels.
+    pub(crate) fn cascade_tile(&self, cascade: u32) -> (u32, u32, u32) {
+        let res = self.directional_resolution();
+        (cascade * res, 0, res)
+    }
+
+    /// Returns the tile of the atlas the given cube face of the given point light is rendered to.
+    pub(crate) fn point_tile(&self, light: u32, face: u32) -> (u32, u32, u32) {
+        let row = if self.cascades() > 0 {
+            self.directional_resolution()
+        } else {
+            0
+        };
+        let res = self.point_resolution();
+        (face * res, row + light * res, res)
+    }
+}
+
+/// A view rendered into a tile of the shadow map atlas.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ShadowView {
+    /// Projection and view matrix of the light.
+    pub proj_view: Matrix4<f32>,
+    /// Tile of the atlas as x, y and size in texels.
+    pub tile: (u32, u32, u32),
+}
+
+impl ShadowView {
+    /// Returns the tile as offset and size in texture coordinates.
+    pub fn rect(&self, atlas: (u32, u32)) -> [f32; 4] {
+        let (x, y, size) = self.tile;
+        let (width, height) = (atlas.0 as f32, atlas.1 as f32);
+        [
+            x as f32 / width,
+            y as f32 / height,
+            size as f32 / width,
+            size as f32 / height,
+        ]
+    }
+
+    /// Returns the projection mapping the view to its tile of the whole atlas.
+    pub fn tile_proj_view(&self, atlas: (u32, u32)) -> Matrix4<f32> {
+        let [x, y, width, height] = self.rect(atlas);
+        let mut tile = Matrix4::identity();
+        tile[(0, 0)] = width;
+        tile[(1, 1)] = height;
+        tile[(0, 3)] = 2.0 * x + width - 1.0;
+        tile[(1, 3)] = 2.0 * y + height - 1.0;
+        tile * self.proj_view
+    }
+}
+
+/// Shadow views and the data needed to look them up, gathered from the world every frame.
+#[derive(Clone, Debug)]
+pub(crate) struct ShadowViews {
+    /// Cascades of the directional light first, then six cube faces for each point light.
+    pub views: Vec<ShadowView>,
+    /// View depth at which each cascade ends.
+    pub cascade_splits: [f32; MAX_CASCADES],
+    /// Number of cascades in `views`.
+    pub cascade_count: usize,
+    /// World space direction the camera looks at.
+    pub camera_forward: Vector3<f32>,
+    /// Index of the directional light casting shadows among the directional lights.
+    pub shadow_light: Option<usize>,
+    /// Index of each point light casting shadows among the point lights, and its first view.
+    pub point_shadows: Vec<(usize, usize)>,
+}
+
+impl ShadowViews {
+    /// Gathers the shadow views of the lights in the world, indexing lights the same way as the
+    /// `EnvironmentSub`.
+    pub fn gather(world: &World, config: &ShadowConfig) -> Self {
+        #[cfg(feature = "profiler")]
+        profile_scope!("gather_shadows");
+
+        let (lights, transforms, cameras) = <(
+            ReadStorage<'_, Light>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Camera>,
+        )>::fetch(world);
+
+        let mut shadows = Self {
+            views: Vec::new(),
+            cascade_splits: [0.0; MAX_CASCADES],
+            cascade_count: 0,
+            camera_forward: -Vector3::z(),
+            shadow_light: None,
+            point_shadows: Vec::new(),
+        };
+
+        let camera = CameraGatherer::gather_camera_entity(world)
+            .and_then(|entity| Some((cameras.get(entity)?, transforms.get(entity)?)));
+        let directional = lights
+            .join()
+            .filter_map(|light| match light {
+                Light::Directional(light) => Some(light),
+                _ => None,
+            })
+            .take(MAX_DIR_LIGHTS)
+            .enumerate()
+            .find(|(_, light)| light.cast_shadows);
+
+        if let (Some((camera, transform)), Some((index, light))) = (camera, directional) {
+            let camera_matrix: Matrix4<f32> = convert(*transform.global_matrix());
+            shadows.camera_forward = -camera_matrix.column(2).xyz().normalize();
+            let cascades = cascades(&camera.inverse, &camera_matrix, &light.direction, config);
+            for (i, (split, proj_view)) in cascades.into_iter().enumerate() {
+                shadows.cascade_splits[i] = split;
+                shadows.views.push(ShadowView {
+                    proj_view,
+                    tile: config.cascade_tile(i as u32),
+                });
+            }
+            shadows.cascade_count = shadows.views.len();
+            if shadows.cascade_count > 0 {
+                shadows.shadow_light = Some(index);
+            }
+        }
+
+        let point_lights = (&lights, &transforms)
+            .join()
+            .filter_map(|(light, transform)| match light {
+                Light::Point(light) => Some((light, transform)),
+                _ => None,
+            })
+            .take(MAX_POINT_LIGHTS)
+            .enumerate()
+            .filter(|(_, (light, _))| light.cast_shadows)
+            .take(config.point_lights() as usize);
+        for (slot, (index, (light, transform))) in point_lights.enumerate() {
+            let position = Point3::from(convert::<_, Vector3<f32>>(
+                transform.global_matrix().column(3).xyz(),
+            ));
+            shadows.point_shadows.push((index, shadows.views.len()));
+            let proj = perspective_90(POINT_SHADOW_NEAR, light.radius.max(2.0 * POINT_SHADOW_NEAR));
+            for (face, (direction, up)) in cube_faces().iter().enumerate() {
+                shadows.views.push(ShadowView {
+                    proj_view: proj * Matrix4::look_at_rh(&position, &(position + direction), up),
+                    tile: config.point_tile(slot as u32, face as u32),
+                });
+            }
+        }
+
+        shadows
+    }
+}
+
+/// Fetches the `ShadowConfig` resource, or the default configuration if it's missing.
+pub(crate) fn shadow_config(world: &World) -> ShadowConfig {
+    <Option<Read<'_, ShadowConfig>>>::fetch(world)
+        .map(|config| config.clone())
+        .unwrap_or_default()
+}
+
+/// Splits the view distance from `near` to `far` into `count` cascades, blending uniform and
+/// logarithmic splits with `lambda`. Returns the view depth each cascade ends at.
+pub(crate) fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let fraction = i as f32 / count as f32;
+            let uniform = near + (far - near) * fraction;
+            let logarithmic = near * (far / near).powf(fraction);
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// Computes the view depth each cascade ends at and the projection of the light covering it.
+fn cascades(
+    inverse_projection: &Matrix4<f32>,
+    camera_matrix: &Matrix4<f32>,
+    direction: &Vector3<f32>,
+    config: &ShadowConfig,
+) -> Vec<(f32, Matrix4<f32>)> {
+    let direction = match direction.try_normalize(std::f32::EPSILON) {
+        Some(direction) => direction,
+        None => return Vec::new(),
+    };
+    // Projections are reversed, so NDC depth 1.0 is the near plane. Any point between the near
+    // plane and NDC depth 0.5 gives the edges of the frustum, even for infinite projections.
+    let unproject = |x: f32, y: f32, z: f32| {
+        let point = inverse_projection * Vector4::new(x, y, z, 1.0);
+        point.xyz() / point.w
+    };
+    let edges = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .iter()
+        .map(|&(x, y)| (unproject(x, y, 1.0), unproject(x, y, 0.5)))
+        .collect::<Vec<_>>();
+    let corner = |(near, mid): &(Vector3<f32>, Vector3<f32>), depth: f32| {
+        let t = (depth + near.z) / (near.z - mid.z);
+        let point = near + (mid - near) * t;
+        Point3::from((camera_matrix * point.push(1.0)).xyz())
+    };
+
+    let near = -unproject(0.0, 0.0, 1.0).z;
+    let far = config.directional.distance.max(near * 2.0);
+    let splits = cascade_splits(
+        near,
+        far,
+        config.cascades() as usize,
+        config.directional.split_lambda,
+    );
+    let resolution = config.directional_resolution() as f32;
+    let up = if direction.y.abs() > 0.99 {
+        Vector3::z()
+    } else {
+        Vector3::y()
+    };
+    let rotation = Matrix4::look_at_rh(&Point3::origin(), &Point3::from(direction), &up);
+    let inverse_rotation = rotation.transpose();
+
+    let mut start = near;
+    splits
+        .into_iter()
+        .map(|end| {
+            let corners = edges
+                .iter()
+                .flat_map(|edge| vec![corner(edge, start), corner(edge, end)])
+                .collect::<Vec<_>>();
+            start = end;
+
+            let center = corners.iter().map(|c| c.coords).sum::<Vector3<f32>>() / 8.0;
+            let radius = corners
+                .iter()
+                .map(|c| (c.coords - center).norm())
+                .fold(0.0f32, f32::max);
+            // Keep the size and the texel grid of the cascade fixed while the camera moves,
+            // so the shadow edges don't shimmer.
+            let radius = (radius * 16.0).ceil() / 16.0;
+            let texel = 2.0 * radius / resolution;
+            let mut center = rotation * center.push(1.0);
+            center.x = (center.x / texel).floor() * texel;
+            center.y = (center.y / texel).floor() * texel;
+            let center = Point3::from((inverse_rotation * center).xyz());
+
+            // Leave room for shadow casters between the light and the cascade.
+            let eye = center - direction * 3.0 * radius;
+            let view = Matrix4::look_at_rh(&eye, &center, &up);
+            (end, orthographic(radius, 4.0 * radius) * view)
+        })
+        .collect()
+}
+
+/// Orthographic projection of a square of the given half size, with depth from 0.0 at the eye to
+/// 1.0 at `far`.
+fn orthographic(half_size: f32, far: f32) -> Matrix4<f32> {
+    let mut matrix = Matrix4::identity();
+    matrix[(0, 0)] = 1.0 / half_size;
+    matrix[(1, 1)] = 1.0 / half_size;
+    matrix[(2, 2)] = -1.0 / far;
+    matrix
+}
+
+/// Perspective projection with a field of view of 90 degrees, with depth from 0.0 at `near` to
+/// 1.0 at `far`.
+fn perspective_90(near: f32, far: f32) -> Matrix4<f32> {
+    let mut matrix = Matrix4::zeros();
+    matrix[(0, 0)] = 1.0;
+    matrix[(1, 1)] = 1.0;
+    matrix[(2, 2)] = far / (near - far);
+    matrix[(2, 3)] = near * far / (near - far);
+    matrix[(3, 2)] = -1.0;
+    matrix
+}
+
+/// Directions and up vectors of the cube faces, ordered +X, -X, +Y, -Y, +Z, -Z.
+fn cube_faces() -> [(Vector3<f32>, Vector3<f32>); 6] {
+    [
+        (Vector3::x(), -Vector3::y()),
+        (-Vector3::x(), -Vector3::y()),
+        (Vector3::y(), Vector3::z()),
+        (-Vector3::y(), -Vector3::z()),
+        (Vector3::z(), -Vector3::y()),
+        (-Vector3::z(), -Vector3::y()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::{DirectionalLight, PointLight};
+    use amethyst_core::ecs::{Builder, WorldExt};
+
+    #[test]
+    fn atlas_fits_cascades_and_cube_faces() {
+        let config = ShadowConfig::default();
+        assert_eq!(config.atlas_size(), (3072, 2048));
+        assert_eq!(config.cascade_tile(2), (2048, 0, 1024));
+        assert_eq!(config.point_tile(1, 5), (2560, 1536, 512));
+
+        let config = ShadowConfig {
+            directional: DirectionalShadowConfig {
+                cascades: 0,
+                ..Default::default()
+            },
+            point: PointShadowConfig {
+                max_lights: 10,
+                ..Default::default()
+            },
+        };
+        assert_eq!(config.atlas_size(), (3072, 2048));
+        assert_eq!(config.point_tile(0, 0), (0, 0, 512));
+    }
+
+    #[test]
+    fn cascade_splits_blend_uniform_and_logarithmic() {
+        assert_eq!(cascade_splits(1.0, 9.0, 2, 0.0), vec![5.0, 9.0]);
+        let splits = cascade_splits(1.0, 9.0, 2, 1.0);
+        assert!((splits[0] - 3.0).abs() < 1e-5);
+        assert!((splits[1] - 9.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn shadow_views_cover_casting_lights() {
+        let mut world = World::new();
+        world.register::<Light>();
+        world.register::<Transform>();
+        world.register::<Camera>();
+        world.insert(crate::camera::ActiveCamera::default());
+
+        let mut camera = Transform::default();
+        camera.set_translation_xyz(0.0, 0.0, 10.0);
+        camera.copy_local_to_global();
+        world
+            .create_entity()
+            .with(Camera::standard_3d(16.0, 9.0))
+            .with(camera)
+            .build();
+        let casting = |light: Light| {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(0.0, 5.0, 0.0);
+            transform.copy_local_to_global();
+            (light, transform)
+        };
+        for (light, transform) in [
+            casting(DirectionalLight::default().into()),
+            casting(
+                DirectionalLight {
+                    cast_shadows: true,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            casting(PointLight::default().into()),
+            casting(
+                PointLight {
+                    cast_shadows: true,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        ] {
+            world.create_entity().with(light).with(transform).build();
+        }
+
+        let config = ShadowConfig::default();
+        let shadows = ShadowViews::gather(&world, &config);
+        assert_eq!(shadows.shadow_light, Some(1));
+        assert_eq!(shadows.cascade_count, 3);
+        assert_eq!(shadows.point_shadows, vec![(1, 3)]);
+        assert_eq!(shadows.views.len(), 3 + 6);
+        assert!((shadows.camera_forward - -Vector3::z()).norm() < 1e-5);
+        assert!(shadows.cascade_splits[2] > 49.0 && shadows.cascade_splits[2] < 51.0);
+
+        // A point in front of the camera is inside the first cascade.
+        let point = shadows.views[0].proj_view * Vector4::new(0.0, 0.0, 9.0, 1.0);
+        let ndc = point.xyz() / point.w;
+        assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && ndc.z >= 0.0 && ndc.z <= 1.0);
+
+        // A point below the light is seen by the -Y face.
+        let point = shadows.views[3 + 3].proj_view * Vector4::new(0.0, 1.0, 0.0, 1.0);
+        let ndc = point.xyz() / point.w;
+        assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && ndc.z >= 0.0 && ndc.z <= 1.0);
+    }
+
+    #[test]
+    fn tile_projection_maps_into_the_tile() {
+        let view = ShadowView {
+            proj_view: Matrix4::identity(),
+            tile: (512, 0, 512),
+        };
+        let atlas = (1024, 1024);
+        assert_eq!(view.rect(atlas), [0.5, 0.0, 0.5, 0.5]);
+        let tile = view.tile_proj_view(atlas);
+        let top_left = tile * Vector4::new(-1.0, -1.0, 0.5, 1.0);
+        let bottom_right = tile * Vector4::new(1.0, 1.0, 0.5, 1.0);
+        assert_eq!(top_left, Vector4::new(0.0, -1.0, 0.5, 1.0));
+        assert_eq!(bottom_right, Vector4::new(1.0, 0.0, 0.5, 1.0));
+    }
+}
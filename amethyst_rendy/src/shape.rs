@@ -1,4 +1,4 @@
-//! Basic shape prefabs.
+//! Basic shape prefabs, and meshes built at runtime with `MeshBuilder`.
 use crate::types::Mesh;
 use amethyst_assets::{AssetStorage, Handle, Loader, PrefabData, Progress, ProgressCounter};
 use amethyst_core::{
@@ -8,7 +8,7 @@ use amethyst_core::{
     },
     math::Vector3,
 };
-use amethyst_error::Error;
+use amethyst_error::{format_err, Error};
 use genmesh::{
     generators::{
         Circle, Cone, Cube, Cylinder, IcoSphere, IndexedPolygon, Plane, SharedVertex, SphereUv,
@@ -16,10 +16,8 @@ use genmesh::{
     },
     EmitTriangles, MapVertex, Triangulate, Vertex, Vertices,
 };
-use rendy::mesh::{
-    MeshBuilder, Normal, PosNormTangTex, PosNormTex, PosTex, Position, Tangent, TexCoord,
-};
-use std::marker::PhantomData;
+use rendy::mesh::{self, Normal, PosNormTangTex, PosNormTex, PosTex, Position, Tangent, TexCoord};
+use std::{f32::consts::PI, marker::PhantomData};
 
 fn option_none<T>() -> Option<T> {
     None
@@ -50,7 +48,7 @@ pub struct ShapePrefab<V> {
 
 impl<'a, V> PrefabData<'a> for ShapePrefab<V>
 where
-    V: FromShape + Into<MeshBuilder<'static>>,
+    V: FromShape + Into<mesh::MeshBuilder<'static>>,
 {
     type SystemData = (
         ReadExpect<'a, Loader>,
@@ -176,7 +174,7 @@ impl Shape {
         progress: P,
     ) -> Handle<Mesh>
     where
-        V: FromShape + Into<MeshBuilder<'static>>,
+        V: FromShape + Into<mesh::MeshBuilder<'static>>,
         P: Progress,
     {
         upload
@@ -198,9 +196,9 @@ impl Shape {
     /// - `Vec<PosNormTex>`
     /// - `Vec<PosNormTangTex>`
     /// - `ComboMeshCreator`
-    pub fn generate<V>(&self, scale: Option<(f32, f32, f32)>) -> MeshBuilder<'static>
+    pub fn generate<V>(&self, scale: Option<(f32, f32, f32)>) -> mesh::MeshBuilder<'static>
     where
-        V: FromShape + Into<MeshBuilder<'static>>,
+        V: FromShape + Into<mesh::MeshBuilder<'static>>,
     {
         V::from(&self.generate_internal(scale)).into()
     }
@@ -285,20 +283,11 @@ where
                         Vector3::new(v.normal.x * x, v.normal.y * y, v.normal.z * z).normalize()
                     })
                     .unwrap_or_else(|| Vector3::from(v.normal));
-                let tangent1 = normal.cross(&Vector3::x());
-                let tangent2 = normal.cross(&Vector3::y());
-                let tangent = if tangent1.norm_squared() > tangent2.norm_squared() {
-                    tangent1
-                } else {
-                    tangent2
-                }
-                .cross(&normal);
-
                 (
                     pos.into(),
                     normal.into(),
                     [(v.pos.x + 1.) / 2., (v.pos.y + 1.) / 2.],
-                    tangent.into(),
+                    tangent(&normal).into(),
                 )
             })
         })
@@ -306,6 +295,337 @@ where
         .collect::<Vec<_>>()
 }
 
+/// Returns a tangent perpendicular to the normal.
+fn tangent(normal: &Vector3<f32>) -> Vector3<f32> {
+    let tangent1 = normal.cross(&Vector3::x());
+    let tangent2 = normal.cross(&Vector3::y());
+    if tangent1.norm_squared() > tangent2.norm_squared() {
+        tangent1
+    } else {
+        tangent2
+    }
+    .cross(normal)
+}
+
+/// Builder of indexed triangle meshes from vertex data computed at runtime, e.g. for terrain or
+/// other procedural geometry.
+///
+/// Missing normals are computed by averaging the normals of the triangles sharing a vertex, and
+/// missing texture coordinates are zero. The data is validated when building the mesh with
+/// `build`. Meshes that change over time are loaded once and then updated with `MeshUpdates`.
+///
+/// ```
+/// # use amethyst_rendy::{rendy::mesh::PosNormTangTex, shape::MeshBuilder};
+/// let mesh = MeshBuilder::new()
+///     .with_positions(vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])
+///     .with_indices(vec![0, 1, 2])
+///     .build::<Vec<PosNormTangTex>>()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Option<Vec<[f32; 3]>>,
+    tex_coords: Option<Vec<[f32; 2]>>,
+    indices: Option<Vec<u32>>,
+}
+
+impl MeshBuilder {
+    /// Create an empty mesh builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the positions of the vertices.
+    pub fn with_positions(mut self, positions: Vec<[f32; 3]>) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    /// Set the normals of the vertices, one per position.
+    pub fn with_normals(mut self, normals: Vec<[f32; 3]>) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Set the texture coordinates of the vertices, one per position.
+    pub fn with_tex_coords(mut self, tex_coords: Vec<[f32; 2]>) -> Self {
+        self.tex_coords = Some(tex_coords);
+        self
+    }
+
+    /// Set the indices of the triangles, three per triangle in counter-clockwise order. Without
+    /// indices every three vertices form a triangle.
+    pub fn with_indices(mut self, indices: Vec<u32>) -> Self {
+        self.indices = Some(indices);
+        self
+    }
+
+    /// Returns the positions of the vertices.
+    pub fn positions(&self) -> &[[f32; 3]] {
+        &self.positions
+    }
+
+    /// Returns the indices of the triangles.
+    pub fn indices(&self) -> Vec<u32> {
+        match &self.indices {
+            Some(indices) => indices.clone(),
+            None => (0..self.positions.len() as u32).collect(),
+        }
+    }
+
+    /// Grid in the XZ plane facing up, centered on the origin, with the given size and number of
+    /// cells along the x and z axes. The texture coordinates span the whole grid.
+    pub fn plane_grid(width: f32, depth: f32, columns: usize, rows: usize) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let mut positions = Vec::with_capacity((columns + 1) * (rows + 1));
+        let mut tex_coords = Vec::with_capacity(positions.capacity());
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (u, v) = (column as f32 / columns as f32, row as f32 / rows as f32);
+                positions.push([(u - 0.5) * width, 0.0, (v - 0.5) * depth]);
+                tex_coords.push([u, v]);
+            }
+        }
+        let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+        Self::new()
+            .with_positions(positions)
+            .with_normals(normals)
+            .with_tex_coords(tex_coords)
+            .with_indices(grid_indices(columns, rows))
+    }
+
+    /// Capsule along the y axis, centered on the origin: a cylinder of the given radius and
+    /// height capped by two hemispheres, with the given number of segments around the axis and
+    /// rings per hemisphere.
+    pub fn capsule(radius: f32, height: f32, segments: usize, rings: usize) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(1));
+        let total_height = height + 2.0 * radius;
+        // Rows from the top pole to the bottom pole. The two rows at the equators of the
+        // hemispheres bound the cylinder.
+        let rows = (0..=rings)
+            .map(|ring| (ring as f32 / rings as f32 * PI / 2.0, height / 2.0))
+            .chain((0..=rings).map(|ring| {
+                let latitude = PI / 2.0 + ring as f32 / rings as f32 * PI / 2.0;
+                (latitude, -height / 2.0)
+            }))
+            .collect::<Vec<_>>();
+
+        let mut positions = Vec::with_capacity(rows.len() * (segments + 1));
+        let mut normals = Vec::with_capacity(positions.capacity());
+        let mut tex_coords = Vec::with_capacity(positions.capacity());
+        for (latitude, center) in &rows {
+            for segment in 0..=segments {
+                let longitude = segment as f32 / segments as f32 * 2.0 * PI;
+                let normal = [
+                    latitude.sin() * longitude.cos(),
+                    latitude.cos(),
+                    -latitude.sin() * longitude.sin(),
+                ];
+                let y = center + radius * normal[1];
+                positions.push([radius * normal[0], y, radius * normal[2]]);
+                normals.push(normal);
+                tex_coords.push([
+                    segment as f32 / segments as f32,
+                    0.5 - y / total_height.max(f32::EPSILON),
+                ]);
+            }
+        }
+        Self::new()
+            .with_positions(positions)
+            .with_normals(normals)
+            .with_tex_coords(tex_coords)
+            .with_indices(grid_indices(segments, rows.len() - 1))
+    }
+
+    /// Torus around the y axis, centered on the origin, with the given distance from the origin
+    /// to the center of the tube, radius of the tube, number of segments around the y axis and
+    /// number of segments around the tube.
+    pub fn torus(
+        radius: f32,
+        tube_radius: f32,
+        radial_segments: usize,
+        tubular_segments: usize,
+    ) -> Self {
+        let (radial_segments, tubular_segments) = (radial_segments.max(3), tubular_segments.max(3));
+        let mut positions = Vec::with_capacity((radial_segments + 1) * (tubular_segments + 1));
+        let mut normals = Vec::with_capacity(positions.capacity());
+        let mut tex_coords = Vec::with_capacity(positions.capacity());
+        for tubular in 0..=tubular_segments {
+            let v = tubular as f32 / tubular_segments as f32;
+            let (tube_sin, tube_cos) = (v * 2.0 * PI).sin_cos();
+            for radial in 0..=radial_segments {
+                let u = radial as f32 / radial_segments as f32;
+                let (sin, cos) = (u * 2.0 * PI).sin_cos();
+                let normal = [tube_cos * cos, tube_sin, tube_cos * sin];
+                positions.push([
+                    radius * cos + tube_radius * normal[0],
+                    tube_radius * normal[1],
+                    radius * sin + tube_radius * normal[2],
+                ]);
+                normals.push(normal);
+                tex_coords.push([u, v]);
+            }
+        }
+        Self::new()
+            .with_positions(positions)
+            .with_normals(normals)
+            .with_tex_coords(tex_coords)
+            .with_indices(grid_indices(radial_segments, tubular_segments))
+    }
+
+    /// Check that the vertex data describes a valid triangle mesh.
+    pub fn validate(&self) -> Result<(), Error> {
+        let vertices = self.positions.len();
+        if vertices == 0 {
+            return Err(format_err!("Mesh has no vertices"));
+        }
+        if let Some(position) = self
+            .positions
+            .iter()
+            .find(|p| p.iter().any(|c| !c.is_finite()))
+        {
+            return Err(format_err!("Mesh has a non-finite position {:?}", position));
+        }
+        if let Some(normals) = &self.normals {
+            if normals.len() != vertices {
+                return Err(format_err!(
+                    "Mesh has {} normals for {} vertices",
+                    normals.len(),
+                    vertices
+                ));
+            }
+        }
+        if let Some(tex_coords) = &self.tex_coords {
+            if tex_coords.len() != vertices {
+                return Err(format_err!(
+                    "Mesh has {} texture coordinates for {} vertices",
+                    tex_coords.len(),
+                    vertices
+                ));
+            }
+        }
+        let indices = self.indices.as_ref().map_or(vertices, Vec::len);
+        if indices % 3 != 0 {
+            return Err(format_err!(
+                "Mesh has {} indices, which isn't a multiple of 3",
+                indices
+            ));
+        }
+        if let Some(index) = self
+            .indices
+            .iter()
+            .flatten()
+            .find(|index| **index as usize >= vertices)
+        {
+            return Err(format_err!(
+                "Mesh index {} is out of range of {} vertices",
+                index,
+                vertices
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validate the vertex data and build it into vertices of format `V`, one per position.
+    ///
+    /// ### Type parameters:
+    ///
+    /// `V`: Vertex format to use, must to be one of:
+    ///
+    /// - `Vec<PosTex>`
+    /// - `Vec<PosNormTex>`
+    /// - `Vec<PosNormTangTex>`
+    /// - `ComboMeshCreator`
+    pub fn build_vertices<V>(&self) -> Result<V, Error>
+    where
+        V: FromShape,
+    {
+        self.validate()?;
+        let normals = match &self.normals {
+            Some(normals) => normals.iter().map(|n| Vector3::from(*n)).collect(),
+            None => self.smooth_normals(),
+        };
+        let vertices = self
+            .positions
+            .iter()
+            .zip(&normals)
+            .enumerate()
+            .map(|(index, (position, normal))| {
+                let tex_coord = self
+                    .tex_coords
+                    .as_ref()
+                    .map_or([0.0, 0.0], |tex_coords| tex_coords[index]);
+                (
+                    *position,
+                    (*normal).into(),
+                    tex_coord,
+                    tangent(normal).into(),
+                )
+            })
+            .collect();
+        Ok(V::from(&InternalShape(vertices)))
+    }
+
+    /// Validate the vertex data and build it into an indexed `MeshBuilder` of rendy with
+    /// vertices of format `V`, which can be loaded as `MeshData` or sent to `MeshUpdates`.
+    ///
+    /// ### Type parameters:
+    ///
+    /// `V`: Vertex format to use, must to be one of:
+    ///
+    /// - `Vec<PosTex>`
+    /// - `Vec<PosNormTex>`
+    /// - `Vec<PosNormTangTex>`
+    /// - `ComboMeshCreator`
+    pub fn build<V>(&self) -> Result<mesh::MeshBuilder<'static>, Error>
+    where
+        V: FromShape + Into<mesh::MeshBuilder<'static>>,
+    {
+        let builder: mesh::MeshBuilder<'static> = self.build_vertices::<V>()?.into();
+        Ok(builder.with_indices(self.indices()))
+    }
+
+    fn smooth_normals(&self) -> Vec<Vector3<f32>> {
+        let mut normals = vec![Vector3::zeros(); self.positions.len()];
+        for triangle in self.indices().chunks(3) {
+            let [a, b, c] = [
+                Vector3::from(self.positions[triangle[0] as usize]),
+                Vector3::from(self.positions[triangle[1] as usize]),
+                Vector3::from(self.positions[triangle[2] as usize]),
+            ];
+            // Weighted by the area of the triangle.
+            let normal = (b - a).cross(&(c - a));
+            for index in triangle {
+                normals[*index as usize] += normal;
+            }
+        }
+        normals
+            .into_iter()
+            .map(|normal| {
+                normal
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector3::y)
+            })
+            .collect()
+    }
+}
+
+/// Returns the indices of a grid of quads with `columns + 1` vertices per row, two
+/// counter-clockwise triangles per quad.
+fn grid_indices(columns: usize, rows: usize) -> Vec<u32> {
+    let stride = columns as u32 + 1;
+    let mut indices = Vec::with_capacity(columns * rows * 6);
+    for row in 0..rows as u32 {
+        for column in 0..columns as u32 {
+            let a = row * stride + column;
+            let (b, c, d) = (a + 1, a + stride, a + stride + 1);
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+    indices
+}
+
 impl FromInternalVertex for Position {
     fn from_internal(v: &InternalVertexData) -> Self {
         Position([v.0[0], v.0[1], v.0[2]])
@@ -389,4 +709,80 @@ mod tests {
             Shape::Plane(None).generate::<Vec<PosNormTangTex>>(None)
         );
     }
+
+    #[test]
+    fn generated_meshes_face_outwards() {
+        let meshes = vec![
+            MeshBuilder::plane_grid(4.0, 2.0, 4, 2),
+            MeshBuilder::capsule(0.5, 1.0, 8, 4),
+            MeshBuilder::torus(1.0, 0.25, 12, 6),
+        ];
+        for mesh in meshes {
+            mesh.validate().unwrap();
+            let vertices = mesh.build_vertices::<Vec<PosNormTex>>().unwrap();
+            for triangle in mesh.indices().chunks(3) {
+                let [a, b, c] = [
+                    Vector3::from(vertices[triangle[0] as usize].position.0),
+                    Vector3::from(vertices[triangle[1] as usize].position.0),
+                    Vector3::from(vertices[triangle[2] as usize].position.0),
+                ];
+                let face = (b - a).cross(&(c - a));
+                // Triangles at the poles of the capsule are degenerate.
+                if face.norm() > 1e-6 {
+                    let normal = Vector3::from(vertices[triangle[0] as usize].normal.0);
+                    assert!(face.dot(&normal) > 0.0, "{:?} faces inwards", triangle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn plane_grid_has_vertex_per_corner() {
+        let mesh = MeshBuilder::plane_grid(2.0, 2.0, 2, 3);
+        assert_eq!(mesh.positions().len(), 3 * 4);
+        assert_eq!(mesh.indices().len(), 2 * 3 * 6);
+        assert_eq!(mesh.positions()[0], [-1.0, 0.0, -1.0]);
+        assert_eq!(mesh.positions()[11], [1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn missing_normals_are_computed() {
+        let vertices = MeshBuilder::new()
+            .with_positions(vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])
+            .build_vertices::<Vec<PosNormTex>>()
+            .unwrap();
+        assert!(vertices
+            .iter()
+            .all(|vertex| vertex.normal.0 == [0.0, 0.0, 1.0] && vertex.tex_coord.0 == [0.0, 0.0]));
+    }
+
+    #[test]
+    fn invalid_meshes_are_rejected() {
+        let triangle = || {
+            MeshBuilder::new().with_positions(vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ])
+        };
+        assert!(MeshBuilder::new().validate().is_err());
+        assert!(triangle()
+            .with_normals(vec![[0.0, 0.0, 1.0]])
+            .validate()
+            .is_err());
+        assert!(triangle()
+            .with_tex_coords(vec![[0.0, 0.0]; 4])
+            .validate()
+            .is_err());
+        assert!(triangle().with_indices(vec![0, 1]).validate().is_err());
+        assert!(triangle().with_indices(vec![0, 1, 3]).validate().is_err());
+        assert!(triangle()
+            .with_positions(vec![[f32::NAN, 0.0, 0.0]; 3])
+            .validate()
+            .is_err());
+        assert!(triangle()
+            .with_indices(vec![0, 1, 2, 2, 1, 0])
+            .validate()
+            .is_ok());
+    }
 }
@@ -0,0 +1,407 @@
+//! Frame based animation of the sprite of a `SpriteRender`.
+use serde::{Deserialize, Serialize};
+
+use super::{SpriteRender, SpriteSheet};
+use amethyst_core::{
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Join, Read, System, Write, WriteStorage,
+    },
+    shrev::EventChannel,
+    timing::Time,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// How a `SpriteAnimation` continues after its last frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoopMode {
+    /// Stop on the last frame.
+    Once,
+    /// Start over from the first frame.
+    #[default]
+    Loop,
+    /// Play the frames backwards to the first frame, then forwards again.
+    PingPong,
+}
+
+/// Named event of a frame of a `SpriteAnimation`, sent every time the frame is reached.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameEvent {
+    /// Index of the frame in `SpriteAnimation::frames`.
+    pub frame: usize,
+    /// Name sent with the `SpriteAnimationEvent`.
+    pub name: String,
+}
+
+/// Event sent by the `SpriteAnimationSystem` to the `EventChannel<SpriteAnimationEvent>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpriteAnimationEvent {
+    /// The animation of the entity reached a frame with a `FrameEvent`.
+    Frame {
+        /// Entity of the animation.
+        entity: Entity,
+        /// Name of the `FrameEvent`.
+        name: String,
+    },
+    /// The animation of the entity played its last frame with `LoopMode::Once`.
+    Finished {
+        /// Entity of the animation.
+        entity: Entity,
+    },
+}
+
+/// Plays a list of sprites of the sprite sheet of the `SpriteRender` on the same entity, for 2D
+/// animation without the `amethyst_animation` crate.
+///
+/// The durations can be taken from the `SpriteSheet`, for sheets imported with frame durations
+/// like the `AsepriteFormat`, or the animation can be deserialized as part of a prefab.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteAnimation {
+    /// Sprite numbers of the frames, in the order they are played.
+    pub frames: Vec<usize>,
+    /// Duration of each frame in seconds, in the order of `frames`.
+    pub durations: Vec<f32>,
+    /// How the animation continues after the last frame.
+    #[serde(default)]
+    pub mode: LoopMode,
+    /// Events sent when frames are reached.
+    #[serde(default)]
+    pub events: Vec<FrameEvent>,
+    #[serde(skip)]
+    state: Playback,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Playback {
+    frame: usize,
+    elapsed: f32,
+    reverse: bool,
+    started: bool,
+    finished: bool,
+    playing: bool,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            frame: 0,
+            elapsed: 0.0,
+            reverse: false,
+            started: false,
+            finished: false,
+            playing: true,
+        }
+    }
+}
+
+impl Component for SpriteAnimation {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl SpriteAnimation {
+    /// Create a looping animation of the given sprite numbers, played at `fps` frames per second.
+    pub fn new(frames: Vec<usize>, fps: f32) -> Self {
+        let durations = vec![1.0 / fps; frames.len()];
+        SpriteAnimation {
+            frames,
+            durations,
+            mode: LoopMode::default(),
+            events: Vec::new(),
+            state: Playback::default(),
+        }
+    }
+
+    /// Create a looping animation of the given sprite numbers, with the frame durations of the
+    /// sprite sheet. Returns `None` if the sheet has no duration for one of the sprites.
+    pub fn from_sheet(
+        sheet: &SpriteSheet,
+        frames: impl IntoIterator<Item = usize>,
+    ) -> Option<Self> {
+        let frames = frames.into_iter().collect::<Vec<_>>();
+        let durations = frames
+            .iter()
+            .map(|&sprite| sheet.durations.get(sprite).copied())
+            .collect::<Option<_>>()?;
+        Some(SpriteAnimation {
+            frames,
+            durations,
+            mode: LoopMode::default(),
+            events: Vec::new(),
+            state: Playback::default(),
+        })
+    }
+
+    /// Set how the animation continues after the last frame.
+    pub fn with_mode(mut self, mode: LoopMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Add an event sent whenever the frame at `frame` in `frames` is reached.
+    pub fn with_event(mut self, frame: usize, name: impl Into<String>) -> Self {
+        self.events.push(FrameEvent {
+            frame,
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Sprite number of the current frame, `None` if the animation has no frames.
+    pub fn sprite_number(&self) -> Option<usize> {
+        self.frames.get(self.state.frame).copied()
+    }
+
+    /// Returns `true` if the animation played its last frame with `LoopMode::Once`.
+    pub fn is_finished(&self) -> bool {
+        self.state.finished
+    }
+
+    /// Returns `true` if the animation isn't paused.
+    pub fn is_playing(&self) -> bool {
+        self.state.playing
+    }
+
+    /// Resume the animation.
+    pub fn play(&mut self) {
+        self.state.playing = true;
+    }
+
+    /// Pause the animation on the current frame.
+    pub fn pause(&mut self) {
+        self.state.playing = false;
+    }
+
+    /// Play the animation again from the first frame.
+    pub fn restart(&mut self) {
+        self.state = Playback::default();
+    }
+
+    /// Advances the animation by `delta_seconds`, pushing the events of the reached frames.
+    pub(crate) fn update(
+        &mut self,
+        entity: Entity,
+        delta_seconds: f32,
+        events: &mut Vec<SpriteAnimationEvent>,
+    ) {
+        if self.frames.is_empty() || self.state.finished || !self.state.playing {
+            return;
+        }
+        if !self.state.started {
+            self.state.started = true;
+            self.push_frame_events(entity, events);
+        }
+        // Without any time to play, the animation would never leave the current cycle.
+        let cycle: f32 = self.durations.iter().take(self.frames.len()).sum();
+        if cycle <= 0.0 {
+            return;
+        }
+
+        self.state.elapsed += delta_seconds;
+        loop {
+            let duration = self.durations.get(self.state.frame).copied().unwrap_or(0.0);
+            if self.state.elapsed < duration {
+                break;
+            }
+            if !self.step() {
+                self.state.elapsed = 0.0;
+                self.state.finished = true;
+                events.push(SpriteAnimationEvent::Finished { entity });
+                break;
+            }
+            self.state.elapsed -= duration;
+            self.push_frame_events(entity, events);
+        }
+    }
+
+    /// Moves to the next frame, returns `false` if the animation has no next frame.
+    fn step(&mut self) -> bool {
+        let last = self.frames.len() - 1;
+        let state = &mut self.state;
+        match self.mode {
+            LoopMode::Once if state.frame == last => return false,
+            LoopMode::Once => state.frame += 1,
+            LoopMode::Loop => state.frame = (state.frame + 1) % self.frames.len(),
+            LoopMode::PingPong if last == 0 => {}
+            LoopMode::PingPong if state.reverse => {
+                if state.frame == 0 {
+                    state.reverse = false;
+                    state.frame = 1;
+                } else {
+                    state.frame -= 1;
+                }
+            }
+            LoopMode::PingPong => {
+                if state.frame == last {
+                    state.reverse = true;
+                    state.frame = last - 1;
+                } else {
+                    state.frame += 1;
+                }
+            }
+        }
+        true
+    }
+
+    fn push_frame_events(&self, entity: Entity, events: &mut Vec<SpriteAnimationEvent>) {
+        let frame = self.state.frame;
+        events.extend(
+            self.events
+                .iter()
+                .filter(|event| event.frame == frame)
+                .map(|event| SpriteAnimationEvent::Frame {
+                    entity,
+                    name: event.name.clone(),
+                }),
+        );
+    }
+}
+
+/// Plays the `SpriteAnimation` of every entity by updating the sprite number of its
+/// `SpriteRender`, and sends the events of the animations to the
+/// `EventChannel<SpriteAnimationEvent>`.
+///
+/// Added by the `RenderFlat2D` plugin.
+#[derive(Debug, Default)]
+pub struct SpriteAnimationSystem {
+    events: Vec<SpriteAnimationEvent>,
+}
+
+impl SpriteAnimationSystem {
+    /// Returns a new sprite animation system
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a> System<'a> for SpriteAnimationSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        WriteStorage<'a, SpriteAnimation>,
+        WriteStorage<'a, SpriteRender>,
+        Write<'a, EventChannel<SpriteAnimationEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, mut animations, mut sprites, mut channel): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("sprite_animation_system");
+
+        for (entity, animation, sprite) in (&entities, &mut animations, &mut sprites).join() {
+            animation.update(entity, time.delta_seconds(), &mut self.events);
+            if let Some(sprite_number) = animation.sprite_number() {
+                if sprite.sprite_number != sprite_number {
+                    sprite.sprite_number = sprite_number;
+                }
+            }
+        }
+        channel.drain_vec_write(&mut self.events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formats::texture::TextureGenerator, types::Texture};
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::ecs::{Builder, World, WorldExt};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn entity() -> Entity {
+        World::new().create_entity().build()
+    }
+
+    fn play(animation: &mut SpriteAnimation, steps: usize) -> Vec<Option<usize>> {
+        let entity = entity();
+        (0..steps)
+            .map(|_| {
+                animation.update(entity, 0.1, &mut Vec::new());
+                animation.sprite_number()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_modes() {
+        let mut animation = SpriteAnimation::new(vec![4, 5, 6], 10.0);
+        assert_eq!(
+            play(&mut animation, 5),
+            vec![Some(5), Some(6), Some(4), Some(5), Some(6)]
+        );
+
+        let mut animation = SpriteAnimation::new(vec![4, 5, 6], 10.0).with_mode(LoopMode::Once);
+        assert_eq!(play(&mut animation, 3), vec![Some(5), Some(6), Some(6)]);
+        assert!(animation.is_finished());
+
+        let mut animation = SpriteAnimation::new(vec![4, 5, 6], 10.0).with_mode(LoopMode::PingPong);
+        assert_eq!(
+            play(&mut animation, 5),
+            vec![Some(5), Some(6), Some(5), Some(4), Some(5)]
+        );
+    }
+
+    #[test]
+    fn sends_frame_and_finished_events() {
+        let entity = entity();
+        let mut animation = SpriteAnimation::new(vec![0, 1, 2], 4.0)
+            .with_mode(LoopMode::Once)
+            .with_event(0, "start")
+            .with_event(2, "land");
+        let mut events = Vec::new();
+
+        animation.update(entity, 0.1, &mut events);
+        assert_eq!(
+            events,
+            vec![SpriteAnimationEvent::Frame {
+                entity,
+                name: "start".into()
+            }]
+        );
+
+        // A long frame plays every frame it covers.
+        events.clear();
+        animation.update(entity, 1.0, &mut events);
+        assert_eq!(
+            events,
+            vec![
+                SpriteAnimationEvent::Frame {
+                    entity,
+                    name: "land".into()
+                },
+                SpriteAnimationEvent::Finished { entity },
+            ]
+        );
+        assert_eq!(animation.sprite_number(), Some(2));
+
+        animation.restart();
+        assert_eq!(animation.sprite_number(), Some(0));
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn uses_durations_of_sheet() {
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        let storage: AssetStorage<Texture> = AssetStorage::default();
+        let sheet = SpriteSheet {
+            texture: loader.load_from_data(
+                TextureGenerator::Srgba(1.0, 1., 1., 1.).data(),
+                (),
+                &storage,
+            ),
+            sprites: Vec::new(),
+            durations: vec![0.1, 0.25, 0.05],
+        };
+        let animation = SpriteAnimation::from_sheet(&sheet, vec![2, 1]).unwrap();
+        assert_eq!(animation.durations, vec![0.05, 0.25]);
+        assert!(SpriteAnimation::from_sheet(&sheet, 1..4).is_none());
+
+        let mut animation = animation;
+        animation.pause();
+        assert_eq!(play(&mut animation, 2), vec![Some(2), Some(2)]);
+    }
+}
@@ -0,0 +1,312 @@
+//! Sprite sheet formats of the JSON data exported by TexturePacker and Aseprite.
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::fmt;
+
+use super::{Sprite, SpriteSheet};
+use crate::{error, types::Texture};
+use amethyst_assets::{Format, Handle};
+use amethyst_error::{format_err, Error};
+
+/// Allows loading of sprite sheets from the JSON data exported by TexturePacker, with the frames
+/// either as a hash or as an array.
+///
+/// Trimmed frames are offset to keep their position within the untrimmed frame. Rotated frames
+/// aren't supported, so rotation has to be disabled when packing the sheet.
+///
+/// Like the `SpriteSheetFormat`, the format is used with the handle of the sheet texture:
+/// ```rust,no_run
+/// # use amethyst_core::ecs::{World, WorldExt};
+/// # use amethyst_assets::{Loader, AssetStorage};
+/// # use amethyst_rendy::{sprite::{json::TexturePackerFormat, SpriteSheet}, Texture, formats::texture::ImageFormat};
+/// #
+/// # fn load_sprite_sheet() {
+/// #   let world = World::new(); // Normally, you would use Amethyst's world
+/// #   let loader = world.read_resource::<Loader>();
+/// #   let spritesheet_storage = world.read_resource::<AssetStorage<SpriteSheet>>();
+/// #   let texture_storage = world.read_resource::<AssetStorage<Texture>>();
+/// let texture_handle = loader.load(
+///     "characters.png",
+///     ImageFormat(Default::default()),
+///     (),
+///     &texture_storage,
+/// );
+/// let spritesheet_handle = loader.load(
+///     "characters.json",
+///     TexturePackerFormat(texture_handle),
+///     (),
+///     &spritesheet_storage,
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TexturePackerFormat(pub Handle<Texture>);
+
+impl Format<SpriteSheet> for TexturePackerFormat {
+    fn name(&self) -> &'static str {
+        "TEXTURE_PACKER"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<SpriteSheet, Error> {
+        let sheet: JsonSheet =
+            serde_json::from_slice(&bytes).map_err(error::Error::LoadJsonSpritesheetError)?;
+        Ok(SpriteSheet {
+            texture: self.0.clone(),
+            sprites: sheet.build_sprites()?,
+            durations: Vec::new(),
+        })
+    }
+}
+
+/// Allows loading of sprite sheets from the JSON data exported by Aseprite, with the frames
+/// either as a hash or as an array, keeping the duration of every frame in
+/// `SpriteSheet::durations`.
+///
+/// The format is used with the handle of the sheet texture, like the `TexturePackerFormat`.
+#[derive(Clone, Debug)]
+pub struct AsepriteFormat(pub Handle<Texture>);
+
+impl Format<SpriteSheet> for AsepriteFormat {
+    fn name(&self) -> &'static str {
+        "ASEPRITE"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<SpriteSheet, Error> {
+        let sheet: JsonSheet =
+            serde_json::from_slice(&bytes).map_err(error::Error::LoadJsonSpritesheetError)?;
+        let durations = sheet
+            .frames
+            .0
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                frame.duration.map(|millis| millis / 1000.0).ok_or_else(|| {
+                    format_err!("Frame {} of the sprite sheet has no duration", index)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(SpriteSheet {
+            texture: self.0.clone(),
+            sprites: sheet.build_sprites()?,
+            durations,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonSheet {
+    frames: JsonFrames,
+    meta: JsonMeta,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonMeta {
+    size: JsonSize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct JsonSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct JsonRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFrame {
+    frame: JsonRect,
+    #[serde(default)]
+    rotated: bool,
+    sprite_source_size: Option<JsonRect>,
+    source_size: Option<JsonSize>,
+    /// Duration in milliseconds.
+    duration: Option<f32>,
+}
+
+/// Frames in the order of the file, from either a hash keyed by the frame names or an array.
+#[derive(Debug)]
+struct JsonFrames(Vec<JsonFrame>);
+
+impl<'de> Deserialize<'de> for JsonFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = JsonFrames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a hash or an array of frames")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonFrames, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(frame) = seq.next_element()? {
+                    frames.push(frame);
+                }
+                Ok(JsonFrames(frames))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonFrames, A::Error> {
+                let mut frames = Vec::new();
+                while let Some((_, frame)) = map.next_entry::<de::IgnoredAny, _>()? {
+                    frames.push(frame);
+                }
+                Ok(JsonFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+}
+
+impl JsonSheet {
+    fn build_sprites(&self) -> Result<Vec<Sprite>, Error> {
+        self.frames
+            .0
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                if frame.rotated {
+                    return Err(format_err!(
+                        "Frame {} of the sprite sheet is rotated, which isn't supported",
+                        index
+                    ));
+                }
+                Ok(Sprite::from_pixel_values(
+                    self.meta.size.w,
+                    self.meta.size.h,
+                    frame.frame.w,
+                    frame.frame.h,
+                    frame.frame.x,
+                    frame.frame.y,
+                    frame.trim_offsets(),
+                    false,
+                    false,
+                ))
+            })
+            .collect()
+    }
+}
+
+impl JsonFrame {
+    /// Offsets moving a trimmed frame from the center of the untrimmed frame back to where it was
+    /// trimmed from.
+    fn trim_offsets(&self) -> [f32; 2] {
+        match (self.sprite_source_size, self.source_size) {
+            (Some(trimmed), Some(source)) => [
+                (source.w as f32 - trimmed.w as f32) / 2.0 - trimmed.x as f32,
+                trimmed.y as f32 - (source.h as f32 - trimmed.h as f32) / 2.0,
+            ],
+            _ => [0.0; 2],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::texture::TextureGenerator;
+    use amethyst_assets::{AssetStorage, Loader};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn create_texture() -> Handle<Texture> {
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        let storage: AssetStorage<Texture> = AssetStorage::default();
+        loader.load_from_data(
+            TextureGenerator::Srgba(1.0, 1., 1., 1.).data(),
+            (),
+            &storage,
+        )
+    }
+
+    #[test]
+    fn texture_packer_offsets_trimmed_frames() {
+        let json = r#"{
+            "frames": [
+                {
+                    "filename": "idle.png",
+                    "frame": {"x": 0, "y": 0, "w": 16, "h": 32},
+                    "rotated": false,
+                    "trimmed": false,
+                    "spriteSourceSize": {"x": 0, "y": 0, "w": 16, "h": 32},
+                    "sourceSize": {"w": 16, "h": 32}
+                },
+                {
+                    "filename": "jump.png",
+                    "frame": {"x": 16, "y": 0, "w": 10, "h": 20},
+                    "rotated": false,
+                    "trimmed": true,
+                    "spriteSourceSize": {"x": 6, "y": 12, "w": 10, "h": 20},
+                    "sourceSize": {"w": 16, "h": 32}
+                }
+            ],
+            "meta": {"image": "characters.png", "size": {"w": 32, "h": 32}, "scale": "1"}
+        }"#;
+
+        let sheet = TexturePackerFormat(create_texture())
+            .import_simple(json.into())
+            .unwrap();
+        assert_eq!(sheet.sprites.len(), 2);
+        assert_eq!(sheet.sprites[0].offsets, [0.0, 0.0]);
+        assert_eq!(
+            sheet.sprites[1],
+            Sprite::from_pixel_values(32, 32, 10, 20, 16, 0, [-3.0, 6.0], false, false)
+        );
+        assert!(sheet.durations.is_empty());
+    }
+
+    #[test]
+    fn texture_packer_rejects_rotated_frames() {
+        let json = r#"{
+            "frames": {
+                "idle.png": {"frame": {"x": 0, "y": 0, "w": 16, "h": 32}, "rotated": true}
+            },
+            "meta": {"size": {"w": 32, "h": 32}}
+        }"#;
+
+        assert!(TexturePackerFormat(create_texture())
+            .import_simple(json.into())
+            .is_err());
+    }
+
+    #[test]
+    fn aseprite_keeps_frame_order_and_durations() {
+        let json = r#"{
+            "frames": {
+                "walk 10.aseprite": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "duration": 100},
+                "walk 2.aseprite": {"frame": {"x": 8, "y": 0, "w": 8, "h": 8}, "duration": 250},
+                "walk 1.aseprite": {"frame": {"x": 16, "y": 0, "w": 8, "h": 8}, "duration": 50}
+            },
+            "meta": {"app": "http://www.aseprite.org/", "size": {"w": 24, "h": 8}, "frameTags": []}
+        }"#;
+
+        let sheet = AsepriteFormat(create_texture())
+            .import_simple(json.into())
+            .unwrap();
+        assert_eq!(
+            sheet
+                .sprites
+                .iter()
+                .map(|sprite| sprite.tex_coords.left)
+                .collect::<Vec<_>>(),
+            vec![0.0, 8.0 / 24.0, 16.0 / 24.0]
+        );
+        assert_eq!(sheet.durations, vec![0.1, 0.25, 0.05]);
+
+        let json = json.replace(", \"duration\": 250", "");
+        assert!(AsepriteFormat(create_texture())
+            .import_simple(json.into())
+            .is_err());
+    }
+}
@@ -7,6 +7,9 @@ use amethyst_assets::{Asset, Format, Handle};
 use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
 use amethyst_error::Error;
 
+pub mod animation;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod prefab;
 
 /// An asset handle to sprite sheet metadata.
@@ -21,6 +24,9 @@ pub struct SpriteSheet {
     pub texture: Handle<Texture>,
     /// A list of sprites in this sprite sheet.
     pub sprites: Vec<Sprite>,
+    /// Duration of each sprite as an animation frame in seconds, in the order of `sprites`.
+    /// Empty if the sheet wasn't imported with frame durations.
+    pub durations: Vec<f32>,
 }
 
 impl Asset for SpriteSheet {
@@ -496,6 +502,7 @@ impl Format<SpriteSheet> for SpriteSheetFormat {
         Ok(SpriteSheet {
             texture: self.0.clone(),
             sprites: sprites.build_sprites(),
+            durations: Vec::new(),
         })
     }
 }
@@ -79,6 +79,7 @@ impl<'a> PrefabData<'a> for SpriteSheetPrefab {
             let spritesheet = SpriteSheet {
                 texture: texture_handle,
                 sprites,
+                durations: Vec::new(),
             };
 
             let handle = loader.load_from_data(spritesheet, progress, &storage);
@@ -294,6 +295,7 @@ mod tests {
                 SpriteSheet {
                     texture,
                     sprites: vec![],
+                    durations: Vec::new(),
                 },
                 (),
                 &data.1,
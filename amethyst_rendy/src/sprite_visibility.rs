@@ -1,14 +1,19 @@
-//! Transparency, visibility sorting and camera centroid culling for 2D Sprites.
+//! Transparency, visibility sorting and frustum culling for 2D Sprites.
 use crate::{
     camera::{ActiveCamera, Camera},
+    sprite::{Sprite, SpriteRender, SpriteSheet},
     transparent::Transparent,
+    visibility::{
+        layers_visible, BoundingSphere, CullingCounts, CullingStats, Frustum, RenderLayers,
+    },
 };
+use amethyst_assets::AssetStorage;
 use amethyst_core::{
     ecs::{
         hibitset::BitSet,
         prelude::{Entities, Entity, Join, Read, ReadStorage, System, Write},
     },
-    math::{Point3, Vector3},
+    math::{convert, Matrix4, Point3, Vector3},
     Hidden, HiddenPropagate, Transform,
 };
 use derivative::Derivative;
@@ -33,6 +38,11 @@ pub struct SpriteVisibility {
 /// The sprite render pass should draw all sprites without semi-transparent pixels, then draw the
 /// sprites with semi-transparent pixels from far to near.
 ///
+/// Sprites are culled against the frustum of the active camera with a sphere enclosing their
+/// quad, and the counts are written to `CullingStats`. Sprites not sharing a `RenderLayers` layer
+/// with the active camera are skipped. Sprites whose sprite sheet isn't loaded
+/// yet are only culled when they're behind the camera.
+///
 /// Note that this should run after `Transform` has been updated for the current frame, and
 /// before rendering occurs.
 #[derive(Derivative)]
@@ -58,21 +68,50 @@ impl SpriteVisibilitySortingSystem {
     }
 }
 
+/// Returns a sphere enclosing the quad of the sprite drawn with the given global matrix.
+pub fn sprite_bounds(sprite: &Sprite, global_matrix: &Matrix4<f32>) -> BoundingSphere {
+    let center =
+        global_matrix.transform_point(&Point3::new(-sprite.offsets[0], -sprite.offsets[1], 0.0));
+    let half_x = global_matrix.column(0).xyz() * (sprite.width / 2.0);
+    let half_y = global_matrix.column(1).xyz() * (sprite.height / 2.0);
+    BoundingSphere::new(
+        center,
+        (half_x.magnitude_squared() + half_y.magnitude_squared()).sqrt(),
+    )
+}
+
 impl<'a> System<'a> for SpriteVisibilitySortingSystem {
     type SystemData = (
         Entities<'a>,
         Write<'a, SpriteVisibility>,
+        Write<'a, CullingStats>,
         ReadStorage<'a, Hidden>,
         ReadStorage<'a, HiddenPropagate>,
         Read<'a, ActiveCamera>,
         ReadStorage<'a, Camera>,
         ReadStorage<'a, Transparent>,
         ReadStorage<'a, Transform>,
+        ReadStorage<'a, SpriteRender>,
+        Read<'a, AssetStorage<SpriteSheet>>,
+        ReadStorage<'a, RenderLayers>,
     );
 
     fn run(
         &mut self,
-        (entities, mut visibility, hidden, hidden_prop, active, camera, transparent, transform): Self::SystemData,
+        (
+            entities,
+            mut visibility,
+            mut stats,
+            hidden,
+            hidden_prop,
+            active,
+            camera,
+            transparent,
+            transform,
+            sprite_renders,
+            sprite_sheets,
+            layers,
+        ): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("sprite_visibility_sorting_system");
@@ -81,24 +120,65 @@ impl<'a> System<'a> for SpriteVisibilitySortingSystem {
 
         // The camera position is used to determine culling, but the sprites are ordered based on
         // the Z coordinate
-        let camera: Option<&Transform> = active
+        let mut camera_join = (&entities, &camera, &transform).join();
+        let active_camera = active
             .entity
-            .and_then(|a| transform.get(a))
-            .or_else(|| (&camera, &transform).join().map(|ct| ct.1).next());
+            .and_then(|a| camera_join.get(a, &entities))
+            .or_else(|| camera_join.next());
+        let camera_layers = active_camera
+            .and_then(|(entity, _, _)| layers.get(entity).copied())
+            .unwrap_or_default();
+        let camera = active_camera.map(|(_, camera, transform)| (camera, transform));
         let camera_backward = camera
-            .map(|c| c.global_matrix().column(2).xyz())
+            .map(|(_, c)| c.global_matrix().column(2).xyz())
             .unwrap_or_else(Vector3::z);
         let camera_centroid = camera
-            .map(|t| t.global_matrix().transform_point(&origin))
+            .map(|(_, t)| t.global_matrix().transform_point(&origin))
             .unwrap_or_else(|| origin);
+        let frustum = camera.and_then(|(camera, transform)| {
+            Some(Frustum::new(
+                convert::<_, Matrix4<f32>>(camera.matrix)
+                    * transform.global_matrix().try_inverse()?,
+            ))
+        });
 
+        let mut counts = CullingCounts::default();
         self.centroids.clear();
         self.centroids.extend(
-            (&*entities, &transform, !&hidden, !&hidden_prop)
+            (
+                &*entities,
+                &transform,
+                &sprite_renders,
+                layers.maybe(),
+                !&hidden,
+                !&hidden_prop,
+            )
                 .join()
-                .map(|(e, t, _, _)| (e, t.global_matrix().transform_point(&origin)))
-                // filter entities behind the camera
-                .filter(|(_, c)| (c - camera_centroid).dot(&camera_backward) < 0.0)
+                .filter(|(_, _, _, layers, _, _)| layers_visible(camera_layers, *layers))
+                .filter(|(_, t, sprite_render, _, _, _)| {
+                    let matrix = t.global_matrix();
+                    let sprite = sprite_sheets
+                        .get(&sprite_render.sprite_sheet)
+                        .and_then(|sheet| sheet.sprites.get(sprite_render.sprite_number));
+                    let visible = match (&frustum, sprite) {
+                        (Some(frustum), Some(sprite)) => {
+                            let bounds = sprite_bounds(sprite, matrix);
+                            frustum.check_sphere(&bounds.center, bounds.radius)
+                        }
+                        // filter entities behind the camera
+                        _ => {
+                            let centroid = matrix.transform_point(&origin);
+                            (centroid - camera_centroid).dot(&camera_backward) < 0.0
+                        }
+                    };
+                    if visible {
+                        counts.visible += 1;
+                    } else {
+                        counts.culled += 1;
+                    }
+                    visible
+                })
+                .map(|(e, t, _, _, _, _)| (e, t.global_matrix().transform_point(&origin)))
                 .map(|(entity, centroid)| Internals {
                     entity,
                     transparent: transparent.contains(entity),
@@ -132,5 +212,104 @@ impl<'a> System<'a> for SpriteVisibilitySortingSystem {
         visibility
             .visible_ordered
             .extend(self.transparent.iter().map(|c| c.entity));
+        stats.sprites = counts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formats::texture::TextureGenerator, types::Texture};
+    use amethyst_assets::Loader;
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn sprite_world() -> (World, SpriteRender) {
+        let mut world = World::new();
+        world.register::<Camera>();
+        world.register::<Transform>();
+        world.register::<SpriteRender>();
+        world.register::<Hidden>();
+
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        let texture = loader.load_from_data(
+            TextureGenerator::Srgba(1.0, 1.0, 1.0, 1.0).data(),
+            (),
+            &AssetStorage::<Texture>::default(),
+        );
+        let mut sheets = AssetStorage::<SpriteSheet>::default();
+        let sprite_sheet = sheets.insert(SpriteSheet {
+            texture,
+            sprites: vec![Sprite::from((
+                (30.0, 30.0),
+                [0.0, 0.0],
+                [0.0, 1.0, 0.0, 1.0],
+            ))],
+            durations: Vec::new(),
+        });
+        world.insert(sheets);
+
+        let mut camera_transform = Transform::default();
+        camera_transform.set_translation_z(10.0);
+        camera_transform.copy_local_to_global();
+        world
+            .create_entity()
+            .with(Camera::standard_2d(100.0, 100.0))
+            .with(camera_transform)
+            .build();
+        (world, SpriteRender::new(sprite_sheet, 0))
+    }
+
+    fn add_sprite(world: &mut World, sprite: &SpriteRender, x: f32) -> Entity {
+        let mut transform = Transform::default();
+        transform.set_translation_x(x);
+        transform.copy_local_to_global();
+        world
+            .create_entity()
+            .with(sprite.clone())
+            .with(transform)
+            .build()
+    }
+
+    #[test]
+    fn sprites_outside_the_frustum_are_culled() {
+        let (mut world, sprite) = sprite_world();
+        let center = add_sprite(&mut world, &sprite, 0.0);
+        // Overlaps the right edge of the view at x = 50.
+        let edge = add_sprite(&mut world, &sprite, 60.0);
+        let outside = add_sprite(&mut world, &sprite, 200.0);
+        let hidden = add_sprite(&mut world, &sprite, 0.0);
+        world
+            .write_storage::<Hidden>()
+            .insert(hidden, Hidden)
+            .unwrap();
+
+        let mut system = SpriteVisibilitySortingSystem::new();
+        RunNow::setup(&mut system, &mut world);
+        system.run_now(&world);
+
+        let visibility = world.read_resource::<SpriteVisibility>();
+        assert!(visibility.visible_unordered.contains(center.id()));
+        assert!(visibility.visible_unordered.contains(edge.id()));
+        assert!(!visibility.visible_unordered.contains(outside.id()));
+        assert!(!visibility.visible_unordered.contains(hidden.id()));
+        assert_eq!(
+            world.read_resource::<CullingStats>().sprites,
+            CullingCounts {
+                visible: 2,
+                culled: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn sprite_bounds_follow_scale_and_offsets() {
+        let sprite = Sprite::from(((30.0, 40.0), [10.0, 0.0], [0.0, 1.0, 0.0, 1.0]));
+        let matrix = Matrix4::new_scaling(2.0);
+        let bounds = sprite_bounds(&sprite, &matrix);
+        assert_eq!(bounds.center, Point3::new(-20.0, 0.0, 0.0));
+        assert!((bounds.radius - 50.0).abs() < 1e-5);
     }
 }
@@ -0,0 +1,231 @@
+//! Rendering statistics collected every frame.
+use crate::types::{Backend, Texture};
+use amethyst_assets::AssetStorage;
+use rendy::{factory::Factory, hal::Primitive, memory::Block};
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+/// Time spent on the GPU by a render target timed by the `RenderStatistics` plugin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassTiming {
+    /// Name the target is timed under.
+    pub name: &'static str,
+    /// Time between the end of the previous timed target, or the start of the frame, and the
+    /// end of this target. This is the time of the target itself when every target it depends
+    /// on is timed too.
+    pub gpu_time: Duration,
+}
+
+/// Draw calls recorded by a render group.
+///
+/// Recorded commands can be reused over several frames, so groups report their counts to the
+/// `RenderStats` when preparing every frame, and again after recording new draw calls.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DrawCounts {
+    /// Number of draw calls.
+    pub draw_calls: u64,
+    /// Number of triangles drawn, counting every instance.
+    pub triangles: u64,
+}
+
+impl DrawCounts {
+    /// Reset the counts before recording new draw calls.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Count a draw call of the given number of instances of `vertices` vertices or indices.
+    /// Only triangle lists and strips add to the triangle count.
+    pub fn record_draw(&mut self, primitive: Primitive, vertices: u32, instances: u32) {
+        let triangles = match primitive {
+            Primitive::TriangleList => vertices / 3,
+            Primitive::TriangleStrip => vertices.saturating_sub(2),
+            _ => 0,
+        };
+        self.draw_calls += 1;
+        self.triangles += u64::from(triangles) * u64::from(instances);
+    }
+
+    /// Count a draw call of the given number of instances of a mesh.
+    pub fn record_mesh_draw<B: Backend>(&mut self, mesh: &rendy::mesh::Mesh<B>, instances: u32) {
+        self.record_draw(mesh.primitive(), mesh.len(), instances);
+    }
+}
+
+/// Resource holding the rendering statistics of the last rendered frame, e.g. for a performance
+/// overlay or to catch regressions in benchmarks. Updated by the `RenderingSystem` after every
+/// frame.
+///
+/// The draw counts are reported by the render groups with `report_draws`. The GPU timings are
+/// only measured for the targets timed by the `RenderStatistics` plugin, and lag behind the
+/// other statistics by the number of frames in flight.
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    /// Number of draw calls.
+    pub draw_calls: u64,
+    /// Number of triangles drawn, counting every instance.
+    pub triangles: u64,
+    /// Bytes of device memory used by the loaded textures.
+    pub texture_memory: u64,
+    /// Bytes of device memory used by all textures, buffers and render graph images.
+    pub memory_used: u64,
+    /// Bytes of device memory allocated by the renderer, including the unused parts of the
+    /// allocated blocks.
+    pub memory_allocated: u64,
+    /// GPU time of the timed targets, in the order they were executed.
+    pub passes: Vec<PassTiming>,
+    /// GPU time from the start of the frame to the end of the last timed target.
+    pub gpu_time: Option<Duration>,
+    draws: Mutex<HashMap<usize, DrawCounts>>,
+    timestamps: Vec<(Option<&'static str>, u64)>,
+}
+
+impl RenderStats {
+    /// Report the draw calls of a render group for the current frame. Counts are identified by
+    /// their address, so reporting the same `DrawCounts` again in a frame replaces the counts
+    /// reported before.
+    pub fn report_draws(&self, counts: &DrawCounts) {
+        let key = counts as *const DrawCounts as usize;
+        self.draws
+            .lock()
+            .expect("Draw counts poisoned")
+            .insert(key, counts.clone());
+    }
+
+    /// Record a timestamp read back from the GPU, in nanoseconds. Timestamps without a name mark
+    /// the start of the frame.
+    pub(crate) fn record_timestamp(&mut self, name: Option<&'static str>, nanos: u64) {
+        self.timestamps.push((name, nanos));
+    }
+
+    /// Moves the counts of the frame into the statistics and resets them for the next frame.
+    pub(crate) fn finish_frame(&mut self) {
+        let draws = self.draws.get_mut().expect("Draw counts poisoned");
+        self.draw_calls = draws.values().map(|counts| counts.draw_calls).sum();
+        self.triangles = draws.values().map(|counts| counts.triangles).sum();
+        draws.clear();
+
+        // Timestamps of a frame are read back together, and the timings are kept until the next
+        // ones are available.
+        let start = self
+            .timestamps
+            .iter()
+            .find(|(name, _)| name.is_none())
+            .map(|&(_, nanos)| nanos);
+        if let Some(start) = start {
+            let mut ends = self
+                .timestamps
+                .iter()
+                .filter_map(|&(name, nanos)| name.map(|name| (name, nanos)))
+                .collect::<Vec<_>>();
+            ends.sort_by_key(|&(_, nanos)| nanos);
+
+            let mut previous = start;
+            self.passes.clear();
+            for (name, nanos) in ends {
+                self.passes.push(PassTiming {
+                    name,
+                    gpu_time: Duration::from_nanos(nanos.saturating_sub(previous)),
+                });
+                previous = previous.max(nanos);
+            }
+            self.gpu_time = Some(Duration::from_nanos(previous - start));
+        }
+        self.timestamps.clear();
+    }
+
+    /// Updates the memory statistics from the allocations of the factory and the loaded
+    /// textures.
+    pub(crate) fn update_memory<B: Backend>(
+        &mut self,
+        factory: &Factory<B>,
+        textures: &AssetStorage<Texture>,
+    ) {
+        let utilization = factory.memory_utilization();
+        self.memory_used = utilization
+            .heaps
+            .iter()
+            .map(|heap| heap.utilization.used)
+            .sum();
+        self.memory_allocated = utilization
+            .heaps
+            .iter()
+            .map(|heap| heap.utilization.effective)
+            .sum();
+        self.texture_memory = textures
+            .iter()
+            .filter_map(B::unwrap_texture)
+            .filter_map(|texture| texture.image().block())
+            .map(|block| block.size())
+            .sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_count_triangles_of_every_instance() {
+        let mut counts = DrawCounts::default();
+        counts.record_draw(Primitive::TriangleStrip, 4, 10);
+        counts.record_draw(Primitive::TriangleList, 36, 2);
+        counts.record_draw(Primitive::LineList, 8, 1);
+        assert_eq!(
+            counts,
+            DrawCounts {
+                draw_calls: 3,
+                triangles: 20 + 24,
+            }
+        );
+    }
+
+    #[test]
+    fn reported_draws_replace_earlier_reports() {
+        let mut stats = RenderStats::default();
+        let mut opaque = DrawCounts::default();
+        let mut transparent = DrawCounts::default();
+        opaque.record_draw(Primitive::TriangleList, 3, 1);
+        transparent.record_draw(Primitive::TriangleList, 3, 2);
+
+        // Reported when preparing, and again when recorded.
+        stats.report_draws(&opaque);
+        stats.report_draws(&transparent);
+        opaque.record_draw(Primitive::TriangleList, 6, 1);
+        stats.report_draws(&opaque);
+        stats.finish_frame();
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.triangles, 5);
+
+        stats.finish_frame();
+        assert_eq!(stats.draw_calls, 0);
+        assert_eq!(stats.triangles, 0);
+    }
+
+    #[test]
+    fn pass_timings_follow_timestamp_order() {
+        let mut stats = RenderStats::default();
+        stats.record_timestamp(Some("main"), 1_500);
+        stats.record_timestamp(None, 1_000);
+        stats.record_timestamp(Some("shadows"), 1_200);
+        stats.finish_frame();
+
+        assert_eq!(
+            stats.passes,
+            vec![
+                PassTiming {
+                    name: "shadows",
+                    gpu_time: Duration::from_nanos(200),
+                },
+                PassTiming {
+                    name: "main",
+                    gpu_time: Duration::from_nanos(300),
+                },
+            ]
+        );
+        assert_eq!(stats.gpu_time, Some(Duration::from_nanos(500)));
+
+        // Timings are kept until new timestamps are read back.
+        stats.finish_frame();
+        assert_eq!(stats.passes.len(), 2);
+    }
+}
@@ -15,7 +15,7 @@ use crate::{
     util::{self, TapCountIter},
 };
 use amethyst_core::{
-    ecs::{Join, ReadStorage, SystemData, World},
+    ecs::{Entity, Join, ReadStorage, SystemData, World},
     math::{convert, Vector3},
     transform::Transform,
 };
@@ -24,8 +24,8 @@ use glsl_layout::*;
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
-const MAX_POINT_LIGHTS: usize = 128;
-const MAX_DIR_LIGHTS: usize = 16;
+pub(crate) const MAX_POINT_LIGHTS: usize = 128;
+pub(crate) const MAX_DIR_LIGHTS: usize = 16;
 const MAX_SPOT_LIGHTS: usize = 128;
 
 /// Submodule for loading and binding descriptor sets for a 3D, lit environment.
@@ -34,6 +34,7 @@ const MAX_SPOT_LIGHTS: usize = 128;
 #[derive(Debug)]
 pub struct EnvironmentSub<B: Backend> {
     layout: RendyHandle<DescriptorSetLayout<B>>,
+    camera: Option<Entity>,
     per_image: Vec<PerImageEnvironmentSub<B>>,
 }
 
@@ -55,10 +56,16 @@ impl<B: Backend> EnvironmentSub<B> {
     ) -> Result<Self, failure::Error> {
         Ok(Self {
             layout: set_layout! {factory, [1] UniformBuffer flags[0], [4] UniformBuffer flags[1]},
+            camera: None,
             per_image: Vec::new(),
         })
     }
 
+    /// Sets the camera entity to gather the view from instead of the `ActiveCamera`.
+    pub fn set_camera(&mut self, camera: Option<Entity>) {
+        self.camera = camera;
+    }
+
     /// Returns the raw `DescriptorSetLayout` for this environment
     pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
         self.layout.raw()
@@ -76,7 +83,7 @@ impl<B: Backend> EnvironmentSub<B> {
             }
             &mut self.per_image[index]
         };
-        this_image.process(factory, world)
+        this_image.process(factory, world, self.camera)
     }
 
     /// Binds this environment set for all images.
@@ -117,7 +124,7 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
         }
     }
 
-    fn process(&mut self, factory: &Factory<B>, world: &World) -> bool {
+    fn process(&mut self, factory: &Factory<B>, world: &World, camera: Option<Entity>) -> bool {
         let align = factory
             .physical()
             .limits()
@@ -171,7 +178,7 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
             let CameraGatherer {
                 camera_position,
                 projview,
-            } = CameraGatherer::gather(world);
+            } = CameraGatherer::gather_with(world, camera);
 
             let mut mapped = buffer.map(factory, whole_range.clone()).unwrap();
             let mut writer = unsafe { mapped.write::<u8>(factory, whole_range).unwrap() };
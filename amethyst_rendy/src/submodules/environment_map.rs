@@ -0,0 +1,238 @@
+//! Environment map submodule for binding the environment map of the `Skybox` resource.
+use crate::{
+    environment_map::{EnvironmentMap, Skybox, SPECULAR_LEVELS},
+    pod,
+    rendy::{
+        command::{QueueId, RenderPassEncoder},
+        factory::{Factory, ImageState},
+        hal::{
+            self,
+            device::Device,
+            format::Format,
+            image::{Filter, Kind, SamplerInfo, ViewKind, WrapMode},
+            pso::Descriptor,
+        },
+        memory::Write as _,
+        resource::{Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle},
+        texture::{Texture as RendyTexture, TextureBuilder},
+    },
+    types::{Backend, Texture},
+    util,
+};
+use amethyst_assets::AssetStorage;
+use amethyst_core::ecs::{Read, SystemData, World};
+use glsl_layout::*;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Image of the environment map bound by an `EnvironmentMapSub`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvironmentImage {
+    /// Radiance drawn by the skybox, bound as a `sampler2D`.
+    Radiance,
+    /// Radiance prefiltered for each roughness level, bound as a `sampler2DArray`.
+    Specular,
+}
+
+/// Submodule for binding the environment map of the `Skybox` resource along with its
+/// irradiance, with per-image submissions. Binds a black texture while there is no loaded
+/// environment map.
+#[derive(Debug)]
+pub struct EnvironmentMapSub<B: Backend> {
+    layout: RendyHandle<DescriptorSetLayout<B>>,
+    image: EnvironmentImage,
+    fallback: RendyTexture<B>,
+    per_image: Vec<PerImageEnvironmentMapSub<B>>,
+}
+
+#[derive(Debug)]
+struct PerImageEnvironmentMapSub<B: Backend> {
+    buffer: Option<Escape<Buffer<B>>>,
+    set: Escape<DescriptorSet<B>>,
+    // Id and version of the bound texture, `Some(None)` for the fallback
+    texture: Option<Option<(u32, u32)>>,
+}
+
+impl<B: Backend> EnvironmentMapSub<B> {
+    /// Create a new `EnvironmentMapSub` binding the given image of the environment map.
+    pub fn new(
+        factory: &mut Factory<B>,
+        queue: QueueId,
+        image: EnvironmentImage,
+    ) -> Result<Self, failure::Error> {
+        let view_kind = match image {
+            EnvironmentImage::Radiance => ViewKind::D2,
+            EnvironmentImage::Specular => ViewKind::D2Array,
+        };
+        let fallback = TextureBuilder::new()
+            .with_kind(Kind::D2(1, 1, 1, 1))
+            .with_view_kind(view_kind)
+            .with_data_width(1)
+            .with_data_height(1)
+            .with_sampler_info(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))
+            .with_raw_data(vec![0; 8], Format::Rgba16Sfloat)
+            .build(
+                ImageState {
+                    queue,
+                    stage: hal::pso::PipelineStage::FRAGMENT_SHADER,
+                    access: hal::image::Access::SHADER_READ,
+                    layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                },
+                factory,
+            )?;
+        Ok(Self {
+            layout: set_layout! {
+                factory,
+                [1] UniformBuffer hal::pso::ShaderStageFlags::FRAGMENT,
+                [1] CombinedImageSampler hal::pso::ShaderStageFlags::FRAGMENT
+            },
+            image,
+            fallback,
+            per_image: Vec::new(),
+        })
+    }
+
+    /// Returns the raw `DescriptorSetLayout` of the environment map.
+    pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
+        self.layout.raw()
+    }
+
+    /// Writes the environment map of the `Skybox` resource to GPU memory, returning whether
+    /// the bound texture changed.
+    pub fn process(&mut self, factory: &Factory<B>, index: usize, world: &World) -> bool {
+        #[cfg(feature = "profiler")]
+        profile_scope!("process");
+
+        let (skybox, maps, textures) = <(
+            Option<Read<'_, Skybox>>,
+            Option<Read<'_, AssetStorage<EnvironmentMap>>>,
+            Read<'_, AssetStorage<Texture>>,
+        )>::fetch(world);
+
+        let map = skybox
+            .as_ref()
+            .and_then(|skybox| skybox.environment.as_ref())
+            .and_then(|handle| maps.as_ref()?.get(handle));
+        let loaded = map.and_then(|map| {
+            let handle = match self.image {
+                EnvironmentImage::Radiance => map.radiance(),
+                EnvironmentImage::Specular => map.specular(),
+            };
+            let (texture, version) = textures.get_with_version(handle)?;
+            Some((map, texture, (handle.id(), *version)))
+        });
+
+        let args = match (loaded, skybox.as_ref()) {
+            (Some((map, _, _)), Some(skybox)) => {
+                let mut irradiance = [[0.0; 4].into(); 9];
+                for (packed, coefficient) in irradiance.iter_mut().zip(map.irradiance()) {
+                    *packed = [coefficient[0], coefficient[1], coefficient[2], 0.0].into();
+                }
+                let (intensity, levels) = match self.image {
+                    EnvironmentImage::Radiance => (skybox.intensity, 1),
+                    EnvironmentImage::Specular => (skybox.lighting_intensity, SPECULAR_LEVELS),
+                };
+                pod::EnvironmentMapArgs {
+                    irradiance,
+                    intensity,
+                    levels: levels as i32,
+                }
+            }
+            _ => pod::EnvironmentMapArgs {
+                irradiance: [[0.0; 4].into(); 9],
+                intensity: 0.0,
+                levels: 0,
+            },
+        }
+        .std140();
+
+        while self.per_image.len() <= index {
+            self.per_image.push(PerImageEnvironmentMapSub {
+                buffer: None,
+                set: factory.create_descriptor_set(self.layout.clone()).unwrap(),
+                texture: None,
+            });
+        }
+        let fallback = &self.fallback;
+        let this_image = &mut self.per_image[index];
+
+        let key = loaded.map(|(_, _, key)| key);
+        let changed = this_image.texture != Some(key);
+        if changed {
+            let desc = loaded
+                .and_then(|(_, texture, _)| {
+                    util::texture_desc(texture, hal::image::Layout::ShaderReadOnlyOptimal)
+                })
+                .unwrap_or_else(|| {
+                    Descriptor::CombinedImageSampler(
+                        fallback.view().raw(),
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                        fallback.sampler().raw(),
+                    )
+                });
+            unsafe {
+                factory.write_descriptor_sets(Some(util::desc_write(
+                    this_image.set.raw(),
+                    1,
+                    desc,
+                )));
+            }
+            this_image.texture = Some(key);
+        }
+
+        this_image.write(factory, args);
+        changed
+    }
+
+    /// Binds the environment map for the given image.
+    #[inline]
+    pub fn bind(
+        &self,
+        index: usize,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                pipeline_layout,
+                set_id,
+                Some(self.per_image[index].set.raw()),
+                std::iter::empty(),
+            );
+        }
+    }
+}
+
+impl<B: Backend> PerImageEnvironmentMapSub<B> {
+    fn write(&mut self, factory: &Factory<B>, args: <pod::EnvironmentMapArgs as AsStd140>::Std140) {
+        let size = util::align_size::<pod::EnvironmentMapArgs>(1, 1);
+        let new_buffer = util::ensure_buffer(
+            factory,
+            &mut self.buffer,
+            hal::buffer::Usage::UNIFORM,
+            rendy::memory::Dynamic,
+            size,
+        )
+        .unwrap();
+        let buffer = match self.buffer.as_mut() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        if new_buffer {
+            unsafe {
+                factory.write_descriptor_sets(Some(util::desc_write(
+                    self.set.raw(),
+                    0,
+                    Descriptor::Buffer(buffer.raw(), util::opt_range(0..size)),
+                )));
+            }
+        }
+
+        let mut mapped = buffer.map(factory, 0..size).unwrap();
+        let mut writer = unsafe { mapped.write::<u8>(factory, 0..size).unwrap() };
+        let dst_slice = unsafe { writer.slice() };
+        util::write_into_slice(dst_slice, Some(args));
+    }
+}
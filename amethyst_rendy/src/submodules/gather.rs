@@ -66,9 +66,54 @@ impl CameraGatherer {
     ///
     /// The matrix returned is the camera's `Projection` matrix and the camera `Transform::global_view_matrix`
     pub fn gather(world: &World) -> Self {
+        Self::gather_with(world, None)
+    }
+
+    /// Like `gather`, but uses the given camera entity instead of the `ActiveCamera` when it's
+    /// provided, e.g. for cameras rendering into a `RenderTexture`.
+    pub fn gather_with(world: &World, camera_entity: Option<Entity>) -> Self {
         #[cfg(feature = "profiler")]
         profile_scope!("gather_cameras");
 
+        Self::with_camera(world, camera_entity, |camera, transform| {
+            let camera_position =
+                convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz()).into_pod();
+
+            let proj = &camera.matrix;
+            let view = transform.global_view_matrix();
+
+            let proj_view: [[f32; 4]; 4] = ((*proj) * view).into();
+            let proj: [[f32; 4]; 4] = (*proj).into();
+            let view: [[f32; 4]; 4] =
+                convert::<_, Matrix4<f32>>(transform.global_view_matrix()).into();
+
+            let projview = pod::ViewArgs {
+                proj: proj.into(),
+                view: view.into(),
+                proj_view: proj_view.into(),
+            }
+            .std140();
+
+            Self {
+                camera_position,
+                projview,
+            }
+        })
+    }
+
+    /// Returns the premultiplied projection and view matrix of the camera selected like in
+    /// `gather_with`, e.g. for mapping screen positions back into the world.
+    pub fn gather_proj_view(world: &World, camera_entity: Option<Entity>) -> Matrix4<f32> {
+        Self::with_camera(world, camera_entity, |camera, transform| {
+            camera.matrix * transform.global_view_matrix()
+        })
+    }
+
+    fn with_camera<R>(
+        world: &World,
+        camera_entity: Option<Entity>,
+        f: impl FnOnce(&Camera, &Transform) -> R,
+    ) -> R {
         let (active_camera, cameras, transforms) = <(
             Read<'_, ActiveCamera>,
             ReadStorage<'_, Camera>,
@@ -78,8 +123,8 @@ impl CameraGatherer {
         let defcam = Camera::standard_2d(1.0, 1.0);
         let identity = Transform::default();
 
-        let (camera, transform) = active_camera
-            .entity
+        let (camera, transform) = camera_entity
+            .or(active_camera.entity)
             .as_ref()
             .and_then(|ac| {
                 cameras
@@ -93,27 +138,7 @@ impl CameraGatherer {
                     .unwrap_or((&defcam, &identity))
             });
 
-        let camera_position =
-            convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz()).into_pod();
-
-        let proj = &camera.matrix;
-        let view = transform.global_view_matrix();
-
-        let proj_view: [[f32; 4]; 4] = ((*proj) * view).into();
-        let proj: [[f32; 4]; 4] = (*proj).into();
-        let view: [[f32; 4]; 4] = convert::<_, Matrix4<f32>>(transform.global_view_matrix()).into();
-
-        let projview = pod::ViewArgs {
-            proj: proj.into(),
-            view: view.into(),
-            proj_view: proj_view.into(),
-        }
-        .std140();
-
-        Self {
-            camera_position,
-            projview,
-        }
+        f(camera, transform)
     }
 }
 
@@ -0,0 +1,258 @@
+//! 2D lighting submodule for the lights and occluders of the 2D lighting composite.
+//! Fetches and sets the lighting descriptor set information.
+use crate::{
+    light_2d::{Light2D, Lighting2D, Occluder2D},
+    pod,
+    rendy::{
+        command::RenderPassEncoder,
+        factory::Factory,
+        hal::{self, adapter::PhysicalDevice, device::Device, pso::Descriptor},
+        memory::Write as _,
+        resource::{Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle},
+    },
+    submodules::gather::CameraGatherer,
+    types::Backend,
+    util::{self, TapCountIter},
+};
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    math::{Matrix4, Vector2, Vector4},
+    transform::Transform,
+};
+use glsl_layout::*;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+pub(crate) const MAX_LIGHTS_2D: usize = 64;
+pub(crate) const MAX_OCCLUDER_SEGMENTS: usize = 512;
+
+/// Submodule for loading and binding the descriptor set of the 2D lights and shadow occluders.
+/// This also abstracts away the need for handling multiple images in flight, as it provides
+/// per-image submissions.
+#[derive(Debug)]
+pub struct Lighting2DSub<B: Backend> {
+    layout: RendyHandle<DescriptorSetLayout<B>>,
+    per_image: Vec<PerImageLighting2DSub<B>>,
+}
+
+#[derive(Debug)]
+struct PerImageLighting2DSub<B: Backend> {
+    buffer: Option<Escape<Buffer<B>>>,
+    set: Escape<DescriptorSet<B>>,
+}
+
+impl<B: Backend> Lighting2DSub<B> {
+    /// Create and allocate a new `Lighting2DSub` with the provided rendy `Factory`
+    pub fn new(factory: &Factory<B>) -> Result<Self, failure::Error> {
+        Ok(Self {
+            layout: set_layout! {factory, [3] UniformBuffer hal::pso::ShaderStageFlags::FRAGMENT},
+            per_image: Vec::new(),
+        })
+    }
+
+    /// Returns the raw `DescriptorSetLayout` for the 2D lighting
+    pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
+        self.layout.raw()
+    }
+
+    /// Performs any re-allocation and GPU memory writing required for the 2D lighting set.
+    pub fn process(&mut self, factory: &Factory<B>, index: usize, world: &World) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("process");
+
+        let this_image = {
+            while self.per_image.len() <= index {
+                self.per_image
+                    .push(PerImageLighting2DSub::new(factory, &self.layout));
+            }
+            &mut self.per_image[index]
+        };
+        this_image.process(factory, world)
+    }
+
+    /// Binds the 2D lighting set.
+    #[inline]
+    pub fn bind(
+        &self,
+        index: usize,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        self.per_image[index].bind(pipeline_layout, set_id, encoder);
+    }
+}
+
+impl<B: Backend> PerImageLighting2DSub<B> {
+    fn new(factory: &Factory<B>, layout: &RendyHandle<DescriptorSetLayout<B>>) -> Self {
+        Self {
+            buffer: None,
+            set: factory.create_descriptor_set(layout.clone()).unwrap(),
+        }
+    }
+
+    #[inline]
+    fn bind(
+        &self,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                pipeline_layout,
+                set_id,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+    }
+
+    fn process(&mut self, factory: &Factory<B>, world: &World) {
+        let align = factory
+            .physical()
+            .limits()
+            .min_uniform_buffer_offset_alignment;
+
+        let lighting_buf_size = util::align_size::<pod::Lighting2D>(align, 1);
+        let lights_buf_size = util::align_size::<pod::Light2D>(align, MAX_LIGHTS_2D);
+        let segments_buf_size = util::align_size::<vec4>(align, MAX_OCCLUDER_SEGMENTS);
+
+        let lighting_range = 0..lighting_buf_size;
+        let lights_range = util::next_range(&lighting_range, lights_buf_size);
+        let segments_range = util::next_range(&lights_range, segments_buf_size);
+
+        let whole_range = 0..segments_range.end;
+
+        let new_buffer = util::ensure_buffer(
+            factory,
+            &mut self.buffer,
+            hal::buffer::Usage::UNIFORM,
+            rendy::memory::Dynamic,
+            whole_range.end,
+        )
+        .unwrap();
+        if let Some(buffer) = self.buffer.as_mut() {
+            if new_buffer {
+                use util::{desc_write, opt_range};
+                let buffer = buffer.raw();
+                let set = self.set.raw();
+
+                let desc_lighting = Descriptor::Buffer(buffer, opt_range(lighting_range.clone()));
+                let desc_lights = Descriptor::Buffer(buffer, opt_range(lights_range.clone()));
+                let desc_segments = Descriptor::Buffer(buffer, opt_range(segments_range.clone()));
+
+                unsafe {
+                    factory.write_descriptor_sets(vec![
+                        desc_write(set, 0, desc_lighting),
+                        desc_write(set, 1, desc_lights),
+                        desc_write(set, 2, desc_segments),
+                    ]);
+                }
+            }
+
+            let (settings, lights, occluders, transforms) = <(
+                Option<Read<'_, Lighting2D>>,
+                ReadStorage<'_, Light2D>,
+                ReadStorage<'_, Occluder2D>,
+                ReadStorage<'_, Transform>,
+            )>::fetch(world);
+            let settings = settings.map(|s| s.clone()).unwrap_or_default();
+
+            let inverse_proj_view: [[f32; 4]; 4] = CameraGatherer::gather_proj_view(world, None)
+                .try_inverse()
+                .unwrap_or_else(Matrix4::identity)
+                .into();
+            let (r, g, b) = settings.ambient.into_linear().into_components();
+            let mut lighting = pod::Lighting2D {
+                inverse_proj_view: inverse_proj_view.into(),
+                ambient_color: [r, g, b].into(),
+                light_count: 0,
+                segment_count: 0,
+                shadow_samples: settings.shadow_samples.max(1) as i32,
+            };
+
+            let pod_lights = (&lights, &transforms)
+                .join()
+                .map(|(light, transform)| light_pod(light, transform).std140())
+                .take(MAX_LIGHTS_2D);
+
+            let segments = (&occluders, &transforms)
+                .join()
+                .flat_map(|(occluder, transform)| occluder.segments(transform.global_matrix()))
+                .map(|[a, b]| -> vec4 { [a.x, a.y, b.x, b.y].into() })
+                .take(MAX_OCCLUDER_SEGMENTS);
+
+            let mut mapped = buffer.map(factory, whole_range.clone()).unwrap();
+            let mut writer = unsafe { mapped.write::<u8>(factory, whole_range).unwrap() };
+            let dst_slice = unsafe { writer.slice() };
+
+            use util::{usize_range, write_into_slice};
+            write_into_slice(
+                &mut dst_slice[usize_range(lights_range)],
+                pod_lights.tap_count(&mut lighting.light_count),
+            );
+            write_into_slice(
+                &mut dst_slice[usize_range(segments_range)],
+                segments.tap_count(&mut lighting.segment_count),
+            );
+            write_into_slice(
+                &mut dst_slice[usize_range(lighting_range)],
+                Some(lighting.std140()),
+            );
+        }
+    }
+}
+
+/// Converts a light to its GPU representation, positioned and oriented by its transform.
+fn light_pod(light: &Light2D, transform: &Transform) -> pod::Light2D {
+    let matrix = transform.global_matrix();
+    let position = matrix.column(3).xy();
+    let (color, intensity, radius, height, cast_shadows, source_radius) = match light {
+        Light2D::Point(l) => (
+            l.color,
+            l.intensity,
+            l.radius,
+            l.height,
+            l.cast_shadows,
+            l.source_radius,
+        ),
+        Light2D::Spot(l) => (
+            l.color,
+            l.intensity,
+            l.radius,
+            l.height,
+            l.cast_shadows,
+            l.source_radius,
+        ),
+    };
+    let (direction, cone_outer, cone_inner) = match light {
+        Light2D::Point(_) => (Vector2::new(1.0, 0.0), -2.0, -1.0),
+        Light2D::Spot(l) => {
+            let direction = (matrix * Vector4::new(l.direction.x, l.direction.y, 0.0, 0.0))
+                .xy()
+                .try_normalize(std::f32::EPSILON)
+                .unwrap_or_else(|| Vector2::new(0.0, -1.0));
+            let half_angle = l.angle / 2.0;
+            let smoothness = l.smoothness.clamp(0.0, 1.0);
+            (
+                direction,
+                half_angle.cos(),
+                (half_angle * (1.0 - smoothness)).cos(),
+            )
+        }
+    };
+    let (r, g, b) = color.into_linear().into_components();
+    pod::Light2D {
+        color: [r * intensity, g * intensity, b * intensity].into(),
+        radius,
+        position: [position.x, position.y].into(),
+        height,
+        source_radius,
+        direction: [direction.x, direction.y].into(),
+        cone_outer,
+        cone_inner,
+        cast_shadows: cast_shadows as i32,
+    }
+}
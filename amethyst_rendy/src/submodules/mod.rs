@@ -1,7 +1,10 @@
 //! Various helpers and implementations for sub functions of render passes.
 mod environment;
+mod environment_map;
 mod flat_environment;
+mod lighting_2d;
 mod material;
+mod shadow;
 mod skinning;
 mod texture;
 mod uniform;
@@ -10,8 +13,11 @@ mod vertex;
 pub mod gather;
 
 pub use environment::*;
+pub use environment_map::*;
 pub use flat_environment::*;
+pub use lighting_2d::*;
 pub use material::*;
+pub use shadow::*;
 pub use skinning::*;
 pub use texture::*;
 pub use uniform::*;
@@ -0,0 +1,219 @@
+//! Shadow submodule for binding the shadow map and the shadow views of the lights.
+use crate::{
+    pod,
+    rendy::{
+        command::RenderPassEncoder,
+        factory::Factory,
+        graph::{GraphContext, NodeImage},
+        hal::{
+            self,
+            adapter::PhysicalDevice,
+            device::Device,
+            format::Swizzle,
+            image::{Filter, SamplerInfo, ViewKind, WrapMode},
+            pso::Descriptor,
+        },
+        memory::Write as _,
+        resource::{
+            Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+            ImageViewInfo, Sampler,
+        },
+    },
+    shadow::{shadow_config, ShadowViews, MAX_SHADOW_VIEWS},
+    submodules::MAX_POINT_LIGHTS,
+    types::Backend,
+    util,
+};
+use amethyst_core::ecs::World;
+use glsl_layout::*;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Submodule for binding the shadow map atlas along with the shadow views of the lights,
+/// with per-image submissions.
+#[derive(Debug)]
+pub struct ShadowSub<B: Backend> {
+    layout: RendyHandle<DescriptorSetLayout<B>>,
+    view: Escape<ImageView<B>>,
+    sampler: RendyHandle<Sampler<B>>,
+    image_layout: hal::image::Layout,
+    atlas: (u32, u32),
+    per_image: Vec<PerImageShadowSub<B>>,
+}
+
+#[derive(Debug)]
+struct PerImageShadowSub<B: Backend> {
+    buffer: Option<Escape<Buffer<B>>>,
+    set: Escape<DescriptorSet<B>>,
+}
+
+impl<B: Backend> ShadowSub<B> {
+    /// Create a new `ShadowSub` sampling the given shadow map image of the render graph.
+    pub fn new(
+        ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        shadow_map: &NodeImage,
+    ) -> Result<Self, failure::Error> {
+        let image = ctx
+            .get_image(shadow_map.id)
+            .ok_or_else(|| failure::format_err!("Shadow map {:?} doesn't exist", shadow_map.id))?;
+        let extent = image.kind().extent();
+        let view = factory.create_image_view(
+            image.clone(),
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: image.format(),
+                swizzle: Swizzle::NO,
+                range: shadow_map.range.clone(),
+            },
+        )?;
+        Ok(Self {
+            layout: set_layout! {
+                factory,
+                [2] UniformBuffer hal::pso::ShaderStageFlags::FRAGMENT,
+                [1] CombinedImageSampler hal::pso::ShaderStageFlags::FRAGMENT
+            },
+            view,
+            sampler: factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?,
+            image_layout: shadow_map.layout,
+            atlas: (extent.width, extent.height),
+            per_image: Vec::new(),
+        })
+    }
+
+    /// Returns the raw `DescriptorSetLayout` of the shadows.
+    pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
+        self.layout.raw()
+    }
+
+    /// Writes the shadow views of the lights in the world to GPU memory.
+    pub fn process(&mut self, factory: &Factory<B>, index: usize, world: &World) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("process");
+
+        while self.per_image.len() <= index {
+            let set = factory.create_descriptor_set(self.layout.clone()).unwrap();
+            unsafe {
+                factory.write_descriptor_sets(Some(util::desc_write(
+                    set.raw(),
+                    2,
+                    Descriptor::CombinedImageSampler(
+                        self.view.raw(),
+                        self.image_layout,
+                        self.sampler.raw(),
+                    ),
+                )));
+            }
+            self.per_image.push(PerImageShadowSub { buffer: None, set });
+        }
+        self.per_image[index].process(factory, world, self.atlas);
+    }
+
+    /// Binds the shadows for the given image.
+    #[inline]
+    pub fn bind(
+        &self,
+        index: usize,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                pipeline_layout,
+                set_id,
+                Some(self.per_image[index].set.raw()),
+                std::iter::empty(),
+            );
+        }
+    }
+}
+
+impl<B: Backend> PerImageShadowSub<B> {
+    fn process(&mut self, factory: &Factory<B>, world: &World, atlas: (u32, u32)) {
+        let align = factory
+            .physical()
+            .limits()
+            .min_uniform_buffer_offset_alignment;
+
+        let shadows_size = util::align_size::<pod::Shadows>(align, 1);
+        let views_size = util::align_size::<pod::ShadowView>(align, MAX_SHADOW_VIEWS);
+        let shadows_range = 0..shadows_size;
+        let views_range = util::next_range(&shadows_range, views_size);
+        let whole_range = 0..views_range.end;
+
+        let new_buffer = util::ensure_buffer(
+            &factory,
+            &mut self.buffer,
+            hal::buffer::Usage::UNIFORM,
+            rendy::memory::Dynamic,
+            whole_range.end,
+        )
+        .unwrap();
+        let buffer = match self.buffer.as_mut() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        if new_buffer {
+            use util::{desc_write, opt_range};
+            let raw = buffer.raw();
+            unsafe {
+                factory.write_descriptor_sets(vec![
+                    desc_write(
+                        self.set.raw(),
+                        0,
+                        Descriptor::Buffer(raw, opt_range(shadows_range.clone())),
+                    ),
+                    desc_write(
+                        self.set.raw(),
+                        1,
+                        Descriptor::Buffer(raw, opt_range(views_range.clone())),
+                    ),
+                ]);
+            }
+        }
+
+        let config = shadow_config(world);
+        let shadows = ShadowViews::gather(world, &config);
+
+        let mut first_views = [-1; MAX_POINT_LIGHTS];
+        for &(light, view) in &shadows.point_shadows {
+            first_views[light] = view as i32;
+        }
+        let mut point_shadows: [ivec4; MAX_POINT_LIGHTS / 4] =
+            [[-1; 4].into(); MAX_POINT_LIGHTS / 4];
+        for (packed, views) in point_shadows.iter_mut().zip(first_views.chunks(4)) {
+            *packed = [views[0], views[1], views[2], views[3]].into();
+        }
+        let forward = shadows.camera_forward;
+        let uniform = pod::Shadows {
+            cascade_splits: shadows.cascade_splits.into(),
+            camera_forward: [forward.x, forward.y, forward.z].into(),
+            cascade_count: shadows.cascade_count as i32,
+            shadow_light: shadows.shadow_light.map_or(-1, |light| light as i32),
+            directional_bias: config.directional.bias,
+            directional_filter: config.directional.filter_radius as i32,
+            point_bias: config.point.bias,
+            point_filter: config.point.filter_radius as i32,
+            point_shadows,
+        }
+        .std140();
+        let views = shadows.views.iter().map(|view| {
+            let proj_view: [[f32; 4]; 4] = view.proj_view.into();
+            pod::ShadowView {
+                proj_view: proj_view.into(),
+                rect: view.rect(atlas).into(),
+            }
+            .std140()
+        });
+
+        let mut mapped = buffer.map(factory, whole_range.clone()).unwrap();
+        let mut writer = unsafe { mapped.write::<u8>(factory, whole_range).unwrap() };
+        let dst_slice = unsafe { writer.slice() };
+
+        use util::{usize_range, write_into_slice};
+        write_into_slice(&mut dst_slice[usize_range(shadows_range)], Some(uniform));
+        write_into_slice(&mut dst_slice[usize_range(views_range)], views);
+    }
+}
@@ -30,7 +30,7 @@ enum TextureState<B: Backend> {
 }
 
 /// Texture ID newtype, preventing users from creating arbitrary `TextureId`. Represented as a `u32`
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TextureId(u32);
 
 /// Texture helper submodule for allocating and binding textures and abstracting per-image submissions.
@@ -12,7 +12,7 @@ use crate::{
     types::Backend,
     util,
 };
-use core::{marker::PhantomData, ops::Range};
+use core::marker::PhantomData;
 
 /// Type alias for a set of dynamic vertex buffer data to be managed. See the documentation
 /// for [DynamicVertexData] for implementation details.
@@ -150,7 +150,7 @@ impl<B: Backend, V: VertexDataBufferType, T: 'static> DynamicVertexData<B, V, T>
         };
 
         let buf_size = max_num_items * core::mem::size_of::<T>() as u64;
-        if let Some((allocated, mut mapped)) = this_image.map(factory, 0..buf_size) {
+        if let Some((allocated, mut mapped)) = this_image.map(factory, buf_size) {
             let mut writer = unsafe { mapped.write::<u8>(factory.device(), 0..buf_size).unwrap() };
             let mut slice = unsafe { writer.slice() };
 
@@ -235,15 +235,18 @@ impl<B: Backend, V: VertexDataBufferType> PerImageDynamicVertexData<B, V> {
         .unwrap()
     }
 
-    /// Maps the allocated buffer for writing.
+    /// Maps the allocated buffer for writing at least `min_size` bytes. The whole buffer is
+    /// always mapped, so that its memory stays persistently mapped between frames instead of
+    /// being remapped whenever the written size grows.
     fn map<'a>(
         &'a mut self,
         factory: &Factory<B>,
-        range: Range<u64>,
+        min_size: u64,
     ) -> Option<(bool, MappedRange<'a, B>)> {
-        let alloc = self.ensure(factory, range.end);
+        let alloc = self.ensure(factory, min_size);
         if let Some(buffer) = &mut self.buffer {
-            Some((alloc, buffer.map(factory.device(), range).unwrap()))
+            let size = buffer.size();
+            Some((alloc, buffer.map(factory.device(), 0..size).unwrap()))
         } else {
             None
         }
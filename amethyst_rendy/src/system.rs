@@ -7,8 +7,9 @@ use crate::{
     resources::Tint,
     skinning::JointTransforms,
     sprite::SpriteRender,
+    stats::RenderStats,
     transparent::Transparent,
-    types::{Backend, Mesh, Texture},
+    types::{Backend, Mesh, MeshData, Texture},
     visibility::Visibility,
 };
 use amethyst_assets::{AssetStorage, Handle, HotReloadStrategy, ProcessingState, ThreadPool};
@@ -126,7 +127,13 @@ where
         self.graph
             .as_mut()
             .unwrap()
-            .run(&mut factory, self.families.as_mut().unwrap(), world)
+            .run(&mut factory, self.families.as_mut().unwrap(), world);
+
+        let mut stats = world.fetch_mut::<RenderStats>();
+        stats.finish_frame();
+        if let Some(textures) = world.try_fetch::<AssetStorage<Texture>>() {
+            stats.update_memory(&factory, &textures);
+        }
     }
 }
 
@@ -155,6 +162,7 @@ where
         self.families = Some(families);
         world.insert(factory);
         world.insert(queue_id);
+        world.insert(RenderStats::default());
 
         SetupData::setup(world);
 
@@ -182,7 +190,35 @@ where
     }
 }
 
-/// Asset processing system for `Mesh` asset type.
+/// Resource queueing new vertex data for loaded meshes, e.g. for geometry changing over time.
+/// The `MeshProcessorSystem` rebuilds the meshes with the new data, replacing them under the same
+/// handles.
+///
+/// Updates of meshes that aren't loaded yet are applied once they are.
+#[derive(Debug, Default)]
+pub struct MeshUpdates {
+    updates: Vec<(Handle<Mesh>, MeshData)>,
+}
+
+impl MeshUpdates {
+    /// Queue new data for the mesh, replacing data queued before for the same mesh.
+    pub fn update(&mut self, handle: &Handle<Mesh>, data: impl Into<MeshData>) {
+        self.updates.retain(|(queued, _)| queued != handle);
+        self.updates.push((handle.clone(), data.into()));
+    }
+
+    /// Returns the number of meshes with queued updates.
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    /// Returns `true` if no updates are queued.
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+}
+
+/// Asset processing system for `Mesh` asset type. Also applies the `MeshUpdates`.
 #[derive(Debug, derivative::Derivative)]
 #[derivative(Default(bound = ""))]
 pub struct MeshProcessorSystem<B: Backend>(PhantomData<B>);
@@ -194,11 +230,12 @@ impl<'a, B: Backend> System<'a> for MeshProcessorSystem<B> {
         ReadExpect<'a, Arc<ThreadPool>>,
         Option<Read<'a, HotReloadStrategy>>,
         ReadExpect<'a, Factory<B>>,
+        Write<'a, MeshUpdates>,
     );
 
     fn run(
         &mut self,
-        (mut mesh_storage, queue_id, time, pool, strategy, factory): Self::SystemData,
+        (mut mesh_storage, queue_id, time, pool, strategy, factory, mut updates): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("mesh_processor");
@@ -217,6 +254,19 @@ impl<'a, B: Backend> System<'a> for MeshProcessorSystem<B> {
             &**pool,
             strategy.as_deref(),
         );
+
+        updates.updates.retain(|(handle, data)| {
+            if !mesh_storage.contains(handle) {
+                return true;
+            }
+            match data.0.build(*queue_id, &factory) {
+                Ok(mesh) => {
+                    mesh_storage.replace(handle, B::wrap_mesh(mesh));
+                }
+                Err(e) => log::error!("Failed to update mesh {:?}: {}", handle.id(), e),
+            }
+            false
+        });
     }
 }
 
@@ -299,5 +349,6 @@ fn create_default_mat<B: Backend>(world: &mut World) -> Material {
         ambient_occlusion,
         cavity,
         uv_offset: TextureOffset::default(),
+        queue: None,
     }
 }
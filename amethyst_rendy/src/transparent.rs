@@ -11,6 +11,35 @@ impl Component for Transparent {
     type Storage = NullStorage<Self>;
 }
 
+/// Queue in which a mesh is drawn, sorted by `VisibilitySortingSystem`.
+///
+/// Meshes are in the `Opaque` queue unless they have the `Transparent` component, which puts
+/// them in the `Transparent` queue. The `queue` of their `Material` overrides both.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub enum RenderQueue {
+    /// Drawn first, in any order.
+    Opaque,
+    /// Drawn with the opaque meshes, with the pixels below the alpha cutoff of the material
+    /// discarded.
+    AlphaTest,
+    /// Drawn last and blended, from back to front for every camera.
+    Transparent,
+}
+
+impl RenderQueue {
+    /// Returns the queue of a mesh, given the queue override of its material and whether it has
+    /// the `Transparent` component.
+    pub fn resolve(material: Option<RenderQueue>, transparent: bool) -> Self {
+        match (material, transparent) {
+            (Some(queue), _) => queue,
+            (None, true) => RenderQueue::Transparent,
+            (None, false) => RenderQueue::Opaque,
+        }
+    }
+}
+
 impl<'a> PrefabData<'a> for Transparent {
     type SystemData = WriteStorage<'a, Transparent>;
     type Result = ();
@@ -0,0 +1,883 @@
+//! Video playback into textures, e.g. for intro movies or in-game screens.
+//!
+//! Videos are decoded on the CPU by a `VideoDecoder` and the frames are streamed into a texture,
+//! which can be used like any other texture on a `Material` or a `UiImage`.
+//!
+//! Two decoders are built in, both reading the video from a `Read + Seek` source like a `File`
+//! while playing it instead of loading it into memory first:
+//!
+//! * `Av1Decoder` decodes AV1 videos in the IVF container with `dav1d`. WebM or MP4 videos are
+//!   remuxed into IVF without transcoding with `ffmpeg -i intro.webm -c:v copy intro.ivf`.
+//! * `Y4mDecoder` reads uncompressed YUV4MPEG2 videos, e.g. `ffmpeg -i intro.webm intro.y4m`.
+//!
+//! Other codecs are supported by implementing `VideoDecoder`.
+use crate::types::{Backend, Texture, TextureData};
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage, Join, Read, ReadExpect, System, WriteStorage},
+    timing::Time,
+};
+use amethyst_error::{format_err, Error};
+use dav1d::{PixelLayout, PlanarImageComponent};
+use rendy::{
+    command::QueueId,
+    factory::{Factory, ImageState},
+    hal::{
+        self,
+        format::{Aspects, Format},
+        image::{Extent, Filter, Kind, Offset, SubresourceLayers},
+    },
+    texture::{pixel::Rgba8Srgb, TextureBuilder},
+};
+use std::{
+    io::{self, BufRead, BufReader, Read as IoRead, Seek, SeekFrom},
+    marker::PhantomData,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Decodes the frames of a video, in order.
+pub trait VideoDecoder: Send + Sync + 'static {
+    /// Width and height of the frames in pixels.
+    fn size(&self) -> (u32, u32);
+
+    /// Number of frames per second.
+    fn frame_rate(&self) -> f32;
+
+    /// Decode the next frame into `rgba`, as sRGB encoded RGBA8 pixels from the top left corner.
+    /// Returns `false` at the end of the video.
+    fn decode_frame(&mut self, rgba: &mut [u8]) -> Result<bool, Error>;
+
+    /// Continue decoding from the first frame.
+    fn rewind(&mut self) -> Result<(), Error>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Chroma {
+    C420,
+    C422,
+    C444,
+    Mono,
+}
+
+impl Chroma {
+    /// Width and height of the chroma planes of a frame of the given size.
+    fn plane_size(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Chroma::C420 => (width.div_ceil(2), height.div_ceil(2)),
+            Chroma::C422 => (width.div_ceil(2), height),
+            Chroma::C444 => (width, height),
+            Chroma::Mono => (0, 0),
+        }
+    }
+}
+
+/// Coefficients of the Y'CbCr to R'G'B' conversion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Matrix {
+    Bt601,
+    Bt709,
+}
+
+/// An 8 bit Y'CbCr frame, with each plane given as its samples and the length of its rows.
+struct YuvFrame<'a> {
+    width: usize,
+    height: usize,
+    chroma: Chroma,
+    matrix: Matrix,
+    full_range: bool,
+    planes: [(&'a [u8], usize); 3],
+}
+
+impl YuvFrame<'_> {
+    fn to_rgba(&self, rgba: &mut [u8]) {
+        let (chroma_width, chroma_height) = self.chroma.plane_size(self.width, self.height);
+        let [(luma, luma_stride), (u_plane, u_stride), (v_plane, v_stride)] = self.planes;
+        for (y, row) in rgba
+            .chunks_exact_mut(self.width * 4)
+            .take(self.height)
+            .enumerate()
+        {
+            let chroma_row = y * chroma_height / self.height;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let luma = luma[y * luma_stride + x];
+                let (u, v) = if self.chroma == Chroma::Mono {
+                    (128, 128)
+                } else {
+                    let column = x * chroma_width / self.width;
+                    (
+                        u_plane[chroma_row * u_stride + column],
+                        v_plane[chroma_row * v_stride + column],
+                    )
+                };
+                let [r, g, b] = yuv_to_rgb(luma, u, v, self.matrix, self.full_range);
+                pixel.copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+}
+
+/// Decoder of uncompressed 8 bit YUV4MPEG2 videos, as written by e.g. `ffmpeg -pix_fmt yuv420p
+/// video.y4m`.
+///
+/// The 4:2:0, 4:2:2, 4:4:4 and monochrome color spaces are supported. Colors are converted with
+/// the BT.601 coefficients, in limited range unless the header has `XCOLORRANGE=FULL`.
+#[derive(Debug)]
+pub struct Y4mDecoder<R> {
+    reader: BufReader<R>,
+    width: u32,
+    height: u32,
+    frame_rate: f32,
+    chroma: Chroma,
+    full_range: bool,
+    first_frame: u64,
+    planes: Vec<u8>,
+}
+
+impl<R: IoRead + Seek> Y4mDecoder<R> {
+    /// Create a decoder of a `.y4m` stream, e.g. a `File` or a `Cursor` over the bytes of a file,
+    /// reading the stream header. The frames are read from the stream while they are decoded.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = BufReader::new(reader);
+        let mut header = Vec::new();
+        reader
+            .read_until(b'\n', &mut header)
+            .map_err(|err| format_err!("Failed to read the Y4M stream header: {}", err))?;
+        if header.pop() != Some(b'\n') {
+            return Err(format_err!("Y4M stream header isn't terminated"));
+        }
+        let header = std::str::from_utf8(&header)
+            .map_err(|_| format_err!("Y4M stream header isn't valid UTF-8"))?;
+        let mut params = header.split(' ');
+        if params.next() != Some("YUV4MPEG2") {
+            return Err(format_err!("Data isn't a Y4M stream"));
+        }
+
+        let (mut width, mut height) = (None, None);
+        let mut frame_rate = None;
+        let mut chroma = Chroma::C420;
+        let mut full_range = false;
+        for param in params.filter(|param| !param.is_empty()) {
+            let mut chars = param.chars();
+            let tag = chars.next();
+            let value = chars.as_str();
+            match tag {
+                Some('W') => width = value.parse::<u32>().ok(),
+                Some('H') => height = value.parse::<u32>().ok(),
+                Some('F') => {
+                    let mut ratio = value.split(':').map(|part| part.parse::<f32>().ok());
+                    frame_rate = match (ratio.next().flatten(), ratio.next().flatten()) {
+                        (Some(num), Some(den)) if num > 0.0 && den > 0.0 => Some(num / den),
+                        _ => return Err(format_err!("Invalid Y4M frame rate {}", value)),
+                    };
+                }
+                Some('C') => {
+                    chroma = match value {
+                        "420" | "420jpeg" | "420paldv" | "420mpeg2" => Chroma::C420,
+                        "422" => Chroma::C422,
+                        "444" => Chroma::C444,
+                        "mono" => Chroma::Mono,
+                        _ => return Err(format_err!("Unsupported Y4M color space {}", value)),
+                    }
+                }
+                Some('X') if value == "COLORRANGE=FULL" => full_range = true,
+                _ => {}
+            }
+        }
+
+        match (width, height, frame_rate) {
+            (Some(width), Some(height), Some(frame_rate)) if width > 0 && height > 0 => {
+                let first_frame = reader
+                    .seek(SeekFrom::Current(0))
+                    .map_err(|err| format_err!("Failed to seek in the Y4M stream: {}", err))?;
+                let (chroma_width, chroma_height) =
+                    chroma.plane_size(width as usize, height as usize);
+                Ok(Y4mDecoder {
+                    reader,
+                    width,
+                    height,
+                    frame_rate,
+                    chroma,
+                    full_range,
+                    first_frame,
+                    planes: vec![
+                        0;
+                        width as usize * height as usize + 2 * chroma_width * chroma_height
+                    ],
+                })
+            }
+            _ => Err(format_err!(
+                "Y4M stream header is missing the frame size or rate"
+            )),
+        }
+    }
+}
+
+impl<R: IoRead + Seek + Send + Sync + 'static> VideoDecoder for Y4mDecoder<R> {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn frame_rate(&self) -> f32 {
+        self.frame_rate
+    }
+
+    fn decode_frame(&mut self, rgba: &mut [u8]) -> Result<bool, Error> {
+        let mut header = Vec::new();
+        let read = self
+            .reader
+            .read_until(b'\n', &mut header)
+            .map_err(|err| format_err!("Failed to read Y4M frame: {}", err))?;
+        if read == 0 {
+            return Ok(false);
+        }
+        if !header.starts_with(b"FRAME") || !header.ends_with(b"\n") {
+            return Err(format_err!("Invalid Y4M frame header"));
+        }
+        self.reader
+            .read_exact(&mut self.planes)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => format_err!("Y4M frame is truncated"),
+                _ => format_err!("Failed to read Y4M frame: {}", err),
+            })?;
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (chroma_width, chroma_height) = self.chroma.plane_size(width, height);
+        let (luma, chroma) = self.planes.split_at(width * height);
+        let (u_plane, v_plane) = chroma.split_at(chroma_width * chroma_height);
+        YuvFrame {
+            width,
+            height,
+            chroma: self.chroma,
+            matrix: Matrix::Bt601,
+            full_range: self.full_range,
+            planes: [
+                (luma, width),
+                (u_plane, chroma_width),
+                (v_plane, chroma_width),
+            ],
+        }
+        .to_rgba(rgba);
+        Ok(true)
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.reader
+            .seek(SeekFrom::Start(self.first_frame))
+            .map_err(|err| format_err!("Failed to seek in the Y4M stream: {}", err))?;
+        Ok(())
+    }
+}
+
+/// Reader of the frames of an IVF container.
+#[derive(Debug)]
+struct IvfReader<R> {
+    reader: R,
+    fourcc: [u8; 4],
+    width: u32,
+    height: u32,
+    frame_rate: f32,
+    first_frame: u64,
+}
+
+impl<R: IoRead + Seek> IvfReader<R> {
+    fn new(mut reader: R) -> Result<Self, Error> {
+        let mut header = [0; 32];
+        reader
+            .read_exact(&mut header)
+            .map_err(|err| format_err!("Failed to read the IVF header: {}", err))?;
+        if &header[..4] != b"DKIF" {
+            return Err(format_err!("Data isn't an IVF stream"));
+        }
+        let u16_at = |at: usize| u32::from(u16::from_le_bytes([header[at], header[at + 1]]));
+        let u32_at = |at: usize| {
+            u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+        };
+        let (rate, scale) = (u32_at(16), u32_at(20));
+        if rate == 0 || scale == 0 {
+            return Err(format_err!("Invalid IVF frame rate {}:{}", rate, scale));
+        }
+        // The header length is stored, later versions of the format may extend it.
+        let first_frame = u64::from(u16_at(6).max(32));
+        reader
+            .seek(SeekFrom::Start(first_frame))
+            .map_err(|err| format_err!("Failed to seek in the IVF stream: {}", err))?;
+        Ok(IvfReader {
+            reader,
+            fourcc: [header[8], header[9], header[10], header[11]],
+            width: u16_at(12),
+            height: u16_at(14),
+            frame_rate: rate as f32 / scale as f32,
+            first_frame,
+        })
+    }
+
+    /// Reads the data of the next frame, or `None` at the end of the stream.
+    fn next_frame(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut header = [0; 12];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(format_err!("Failed to read IVF frame: {}", err)),
+        }
+        let size = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let mut data = vec![0; size as usize];
+        self.reader
+            .read_exact(&mut data)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => format_err!("IVF frame is truncated"),
+                _ => format_err!("Failed to read IVF frame: {}", err),
+            })?;
+        Ok(Some(data))
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.reader
+            .seek(SeekFrom::Start(self.first_frame))
+            .map_err(|err| format_err!("Failed to seek in the IVF stream: {}", err))?;
+        Ok(())
+    }
+}
+
+/// Decoder of AV1 videos in the IVF container, using `dav1d`.
+///
+/// Every chroma subsampling is supported, and frames of more than 8 bits per sample are reduced to
+/// 8 bits. Colors are converted with the BT.709 coefficients if the video signals them and with the
+/// BT.601 ones otherwise, in the color range signalled by the video.
+#[derive(derivative::Derivative)]
+#[derivative(Debug(bound = "R: std::fmt::Debug"))]
+pub struct Av1Decoder<R> {
+    ivf: IvfReader<BufReader<R>>,
+    #[derivative(Debug = "ignore")]
+    decoder: dav1d::Decoder,
+    pending: bool,
+}
+
+impl<R: IoRead + Seek> Av1Decoder<R> {
+    /// Create a decoder of an `.ivf` stream of AV1 frames, e.g. a `File`, reading the stream
+    /// header. The frames are read from the stream while they are decoded.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let ivf = IvfReader::new(BufReader::new(reader))?;
+        if &ivf.fourcc != b"AV01" {
+            return Err(format_err!(
+                "IVF stream has the unsupported codec {}",
+                String::from_utf8_lossy(&ivf.fourcc)
+            ));
+        }
+        if ivf.width == 0 || ivf.height == 0 {
+            return Err(format_err!("IVF stream header is missing the frame size"));
+        }
+        let decoder = dav1d::Decoder::new()
+            .map_err(|err| format_err!("Failed to create the AV1 decoder: {}", err))?;
+        Ok(Av1Decoder {
+            ivf,
+            decoder,
+            pending: false,
+        })
+    }
+}
+
+impl<R: IoRead + Seek + Send + Sync + 'static> VideoDecoder for Av1Decoder<R> {
+    fn size(&self) -> (u32, u32) {
+        (self.ivf.width, self.ivf.height)
+    }
+
+    fn frame_rate(&self) -> f32 {
+        self.ivf.frame_rate
+    }
+
+    fn decode_frame(&mut self, rgba: &mut [u8]) -> Result<bool, Error> {
+        loop {
+            match self.decoder.get_picture() {
+                Ok(picture) => {
+                    av1_picture_to_rgba(&picture, self.size(), rgba)?;
+                    return Ok(true);
+                }
+                Err(dav1d::Error::Again) => {}
+                Err(err) => return Err(format_err!("Failed to decode AV1 frame: {}", err)),
+            }
+
+            // The decoder needs more data before it can output the next picture.
+            let sent = if self.pending {
+                self.decoder.send_pending_data()
+            } else {
+                match self.ivf.next_frame()? {
+                    Some(data) => self.decoder.send_data(data, None, None, None),
+                    // Every picture was drained, since `get_picture` drains the decoder once
+                    // no data is left.
+                    None => return Ok(false),
+                }
+            };
+            match sent {
+                Ok(()) => self.pending = false,
+                // Part of the data is kept until pictures are taken out of the decoder.
+                Err(dav1d::Error::Again) => self.pending = true,
+                Err(err) => return Err(format_err!("Failed to decode AV1 frame: {}", err)),
+            }
+        }
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.ivf.rewind()?;
+        self.decoder.flush();
+        self.pending = false;
+        Ok(())
+    }
+}
+
+/// Converts a picture decoded by `dav1d` to RGBA8 pixels of the video size.
+fn av1_picture_to_rgba(
+    picture: &dav1d::Picture,
+    (width, height): (u32, u32),
+    rgba: &mut [u8],
+) -> Result<(), Error> {
+    if picture.width() != width || picture.height() != height {
+        return Err(format_err!(
+            "AV1 frame of {}x{} doesn't have the video size {}x{}",
+            picture.width(),
+            picture.height(),
+            width,
+            height
+        ));
+    }
+    let chroma = match picture.pixel_layout() {
+        PixelLayout::I400 => Chroma::Mono,
+        PixelLayout::I420 => Chroma::C420,
+        PixelLayout::I422 => Chroma::C422,
+        PixelLayout::I444 => Chroma::C444,
+    };
+    let components = [
+        PlanarImageComponent::Y,
+        PlanarImageComponent::U,
+        PlanarImageComponent::V,
+    ];
+    let plane_count = if chroma == Chroma::Mono { 1 } else { 3 };
+    let planes = components[..plane_count]
+        .iter()
+        .map(|&component| picture.plane(component))
+        .collect::<Vec<_>>();
+    let planes = planes
+        .iter()
+        .map(|plane| plane.as_ref())
+        .collect::<Vec<&[u8]>>();
+
+    // Samples of more than 8 bits are stored as 16 bit little endian values.
+    let bit_depth = picture.bit_depth();
+    let sample_size = if bit_depth > 8 { 2 } else { 1 };
+    let reduced = planes
+        .iter()
+        .filter(|_| bit_depth > 8)
+        .map(|plane| {
+            plane
+                .chunks_exact(2)
+                .map(|sample| (u16::from_le_bytes([sample[0], sample[1]]) >> (bit_depth - 8)) as u8)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let samples = if bit_depth > 8 {
+        reduced.iter().map(Vec::as_slice).collect()
+    } else {
+        planes
+    };
+    // Monochrome frames have no chroma planes, which aren't read.
+    let plane = |index: usize| {
+        let index = index.min(plane_count - 1);
+        (
+            samples[index],
+            picture.stride(components[index]) as usize / sample_size,
+        )
+    };
+
+    YuvFrame {
+        width: width as usize,
+        height: height as usize,
+        chroma,
+        matrix: match picture.matrix_coefficients() {
+            dav1d::pixel::MatrixCoefficients::BT709 => Matrix::Bt709,
+            _ => Matrix::Bt601,
+        },
+        full_range: matches!(picture.color_range(), dav1d::pixel::YUVRange::Full),
+        planes: [plane(0), plane(1), plane(2)],
+    }
+    .to_rgba(rgba);
+    Ok(())
+}
+
+/// Converts Y'CbCr to R'G'B' with the coefficients of the given matrix.
+fn yuv_to_rgb(y: u8, u: u8, v: u8, matrix: Matrix, full_range: bool) -> [u8; 3] {
+    let (u, v) = (f32::from(u) - 128.0, f32::from(v) - 128.0);
+    let (y, u, v) = if full_range {
+        (f32::from(y), u, v)
+    } else {
+        (
+            (f32::from(y) - 16.0) * 255.0 / 219.0,
+            u * 255.0 / 224.0,
+            v * 255.0 / 224.0,
+        )
+    };
+    let (kr, kb) = match matrix {
+        Matrix::Bt601 => (0.299, 0.114),
+        Matrix::Bt709 => (0.2126, 0.0722),
+    };
+    let kg = 1.0 - kr - kb;
+    let channel = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    [
+        channel(y + 2.0 * (1.0 - kr) * v),
+        channel(y - 2.0 * kb * (1.0 - kb) / kg * u - 2.0 * kr * (1.0 - kr) / kg * v),
+        channel(y + 2.0 * (1.0 - kb) * u),
+    ]
+}
+
+/// Plays a video into its texture, created when the player is created. The texture is black until
+/// the first frame is shown.
+///
+/// Requires the `RenderVideo` plugin.
+#[derive(derivative::Derivative)]
+#[derivative(Debug)]
+pub struct VideoPlayer {
+    #[derivative(Debug = "ignore")]
+    decoder: Box<dyn VideoDecoder>,
+    texture: Handle<Texture>,
+    #[derivative(Debug = "ignore")]
+    frame: Vec<u8>,
+    elapsed: f32,
+    looping: bool,
+    playing: bool,
+    started: bool,
+    finished: bool,
+    uploaded: bool,
+}
+
+impl Component for VideoPlayer {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl VideoPlayer {
+    /// Create a player of the video decoded by `decoder`, loading a texture of the video size.
+    pub fn new(
+        decoder: impl VideoDecoder,
+        loader: &Loader,
+        storage: &AssetStorage<Texture>,
+    ) -> Self {
+        let (width, height) = decoder.size();
+        let data: TextureData = TextureBuilder::new()
+            .with_kind(Kind::D2(width, height, 1, 1))
+            .with_view_kind(hal::image::ViewKind::D2)
+            .with_data_width(width)
+            .with_data_height(height)
+            .with_sampler_info(hal::image::SamplerInfo::new(
+                Filter::Linear,
+                hal::image::WrapMode::Clamp,
+            ))
+            .with_data(vec![
+                Rgba8Srgb {
+                    repr: [0, 0, 0, 255]
+                };
+                width as usize * height as usize
+            ])
+            .into();
+        VideoPlayer {
+            decoder: Box::new(decoder),
+            texture: loader.load_from_data(data, (), storage),
+            frame: vec![0; width as usize * height as usize * 4],
+            elapsed: 0.0,
+            looping: false,
+            playing: true,
+            started: false,
+            finished: false,
+            uploaded: true,
+        }
+    }
+
+    /// Set whether the video starts over after the last frame, `false` by default.
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Texture the video is played into.
+    pub fn texture(&self) -> &Handle<Texture> {
+        &self.texture
+    }
+
+    /// Returns `true` if the video isn't paused or finished.
+    pub fn is_playing(&self) -> bool {
+        self.playing && !self.finished
+    }
+
+    /// Returns `true` if the last frame was shown and the video doesn't loop, or decoding failed.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Resume the video.
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    /// Pause the video on the current frame.
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Play the video again from the first frame.
+    pub fn restart(&mut self) -> Result<(), Error> {
+        self.decoder.rewind()?;
+        self.elapsed = 0.0;
+        self.started = false;
+        self.finished = false;
+        self.playing = true;
+        Ok(())
+    }
+
+    /// Advances the video by `delta_seconds`, decoding every frame which became due.
+    pub(crate) fn update(&mut self, delta_seconds: f32) -> Result<(), Error> {
+        if !self.is_playing() {
+            return Ok(());
+        }
+        if !self.started {
+            self.started = true;
+            return self.decode_next().map(|_| ());
+        }
+
+        let frame_duration = 1.0 / self.decoder.frame_rate();
+        self.elapsed += delta_seconds;
+        while self.elapsed >= frame_duration {
+            self.elapsed -= frame_duration;
+            if !self.decode_next()? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_next(&mut self) -> Result<bool, Error> {
+        let mut decoded = self.decoder.decode_frame(&mut self.frame)?;
+        if !decoded && self.looping {
+            self.decoder.rewind()?;
+            decoded = self.decoder.decode_frame(&mut self.frame)?;
+        }
+        if decoded {
+            self.uploaded = false;
+        } else {
+            self.finished = true;
+        }
+        Ok(decoded)
+    }
+}
+
+/// Decodes the frames of every `VideoPlayer` which became due and uploads the latest one into the
+/// texture of the player.
+///
+/// Added by the `RenderVideo` plugin.
+#[derive(Debug, derivative::Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct VideoSystem<B: Backend>(PhantomData<B>);
+
+impl<'a, B: Backend> System<'a> for VideoSystem<B> {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, AssetStorage<Texture>>,
+        ReadExpect<'a, QueueId>,
+        ReadExpect<'a, Factory<B>>,
+        WriteStorage<'a, VideoPlayer>,
+    );
+
+    fn run(&mut self, (time, textures, queue_id, factory, mut players): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("video_system");
+
+        for player in (&mut players).join() {
+            if let Err(err) = player.update(time.delta_seconds()) {
+                log::error!("Failed to decode video frame: {}", err);
+                player.finished = true;
+            }
+            if player.uploaded {
+                continue;
+            }
+            // Frames decoded before the texture is loaded are uploaded once it is.
+            let texture = match textures.get(&player.texture).and_then(B::unwrap_texture) {
+                Some(texture) => texture,
+                None => continue,
+            };
+            let image = texture.image();
+            if image.format() != Format::Rgba8Srgb {
+                log::error!(
+                    "Video texture has the unsupported format {:?}",
+                    image.format()
+                );
+                player.finished = true;
+                player.uploaded = true;
+                continue;
+            }
+
+            let (width, height) = player.decoder.size();
+            let state = ImageState {
+                queue: *queue_id,
+                stage: hal::pso::PipelineStage::VERTEX_SHADER
+                    | hal::pso::PipelineStage::FRAGMENT_SHADER,
+                access: hal::image::Access::SHADER_READ,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+            };
+            let result = unsafe {
+                factory.upload_image(
+                    image.clone(),
+                    width,
+                    height,
+                    SubresourceLayers {
+                        aspects: Aspects::COLOR,
+                        level: 0,
+                        layers: 0..1,
+                    },
+                    Offset::ZERO,
+                    Extent {
+                        width,
+                        height,
+                        depth: 1,
+                    },
+                    &player.frame,
+                    state,
+                    state,
+                )
+            };
+            match result {
+                Ok(()) => player.uploaded = true,
+                Err(err) => log::error!("Failed to upload video frame: {}", err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::ThreadPoolBuilder;
+    use std::{io::Cursor, sync::Arc};
+
+    /// A 2x2 4:2:0 video of frames with the given luma and neutral chroma.
+    fn video(lumas: &[u8]) -> Cursor<Vec<u8>> {
+        let mut data = b"YUV4MPEG2 W2 H2 F10:1 Ip A1:1 C420jpeg\n".to_vec();
+        for &luma in lumas {
+            data.extend_from_slice(b"FRAME\n");
+            data.extend_from_slice(&[luma; 4]);
+            data.extend_from_slice(&[128, 128]);
+        }
+        Cursor::new(data)
+    }
+
+    fn player(decoder: impl VideoDecoder) -> VideoPlayer {
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        VideoPlayer::new(decoder, &loader, &AssetStorage::default())
+    }
+
+    #[test]
+    fn decodes_y4m_frames() {
+        let mut data = b"YUV4MPEG2 W2 H1 F30000:1001 C444\n".to_vec();
+        data.extend_from_slice(b"FRAME\n");
+        data.extend_from_slice(&[235, 16, 128, 128, 128, 128]);
+        let mut decoder = Y4mDecoder::new(Cursor::new(data)).unwrap();
+        assert_eq!(decoder.size(), (2, 1));
+        assert!((decoder.frame_rate() - 29.97).abs() < 0.01);
+
+        let mut rgba = vec![0; 8];
+        assert!(decoder.decode_frame(&mut rgba).unwrap());
+        assert_eq!(rgba, vec![255, 255, 255, 255, 0, 0, 0, 255]);
+        assert!(!decoder.decode_frame(&mut rgba).unwrap());
+
+        decoder.rewind().unwrap();
+        assert!(decoder.decode_frame(&mut rgba).unwrap());
+
+        assert!(Y4mDecoder::new(Cursor::new(&b"YUV4MPEG2 W2 H1 F30:1 C420p10\n"[..])).is_err());
+        assert!(Y4mDecoder::new(Cursor::new(&b"YUV4MPEG2 W2 F30:1\n"[..])).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_frames() {
+        let mut data = video(&[16]).into_inner();
+        data.pop();
+        let mut decoder = Y4mDecoder::new(Cursor::new(data)).unwrap();
+        assert!(decoder.decode_frame(&mut [0; 16]).is_err());
+    }
+
+    #[test]
+    fn player_decodes_due_frames() {
+        let mut player = player(Y4mDecoder::new(video(&[16, 126, 235])).unwrap());
+
+        player.update(0.5).unwrap();
+        assert_eq!(player.frame[0], 0);
+        assert!(!player.uploaded);
+
+        player.update(0.15).unwrap();
+        assert_eq!(player.frame[0], 128);
+        player.update(0.1).unwrap();
+        assert_eq!(player.frame[0], 255);
+
+        player.update(0.1).unwrap();
+        assert!(player.is_finished());
+        assert_eq!(player.frame[0], 255);
+
+        player.restart().unwrap();
+        player.update(0.0).unwrap();
+        assert_eq!(player.frame[0], 0);
+    }
+
+    #[test]
+    fn looping_player_starts_over() {
+        let mut player = player(Y4mDecoder::new(video(&[16, 235])).unwrap()).with_looping(true);
+        player.update(0.0).unwrap();
+        player.update(0.25).unwrap();
+        assert_eq!(player.frame[0], 0);
+        assert!(!player.is_finished());
+    }
+
+    /// An IVF stream header of a 2x2 video at 10 frames per second.
+    fn ivf_header(fourcc: &[u8; 4]) -> Vec<u8> {
+        let mut data = b"DKIF".to_vec();
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&32u16.to_le_bytes());
+        data.extend_from_slice(fourcc);
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&10u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        data
+    }
+
+    #[test]
+    fn reads_ivf_frames() {
+        let mut data = ivf_header(b"AV01");
+        for frame in &[&b"first"[..], &b"second"[..]] {
+            data.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            data.extend_from_slice(&[0; 8]);
+            data.extend_from_slice(frame);
+        }
+        let mut ivf = IvfReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(&ivf.fourcc, b"AV01");
+        assert_eq!((ivf.width, ivf.height), (2, 2));
+        assert!((ivf.frame_rate - 10.0).abs() < f32::EPSILON);
+
+        assert_eq!(ivf.next_frame().unwrap(), Some(b"first".to_vec()));
+        assert_eq!(ivf.next_frame().unwrap(), Some(b"second".to_vec()));
+        assert_eq!(ivf.next_frame().unwrap(), None);
+
+        ivf.rewind().unwrap();
+        assert_eq!(ivf.next_frame().unwrap(), Some(b"first".to_vec()));
+    }
+
+    #[test]
+    fn rejects_invalid_ivf_streams() {
+        let mut data = ivf_header(b"AV01");
+        data.extend_from_slice(&8u32.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(b"short");
+        let mut ivf = IvfReader::new(Cursor::new(data)).unwrap();
+        assert!(ivf.next_frame().is_err());
+
+        assert!(IvfReader::new(Cursor::new(b"DKIF".to_vec())).is_err());
+        assert!(Av1Decoder::new(Cursor::new(ivf_header(b"VP90"))).is_err());
+        assert!(Av1Decoder::new(Cursor::new(video(&[16]).into_inner())).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! Cameras rendering into rectangles of the window, e.g. for split-screen local multiplayer.
+use crate::bundle::Target;
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage, Entities, Entity, Join, ReadStorage, World},
+    math::{Point2, Vector2},
+};
+use rendy::hal::pso::Rect;
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of the window in normalized coordinates, from the top left corner at `(0.0, 0.0)` to
+/// the bottom right corner at `(1.0, 1.0)`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ViewportRect {
+    /// Left edge of the rectangle.
+    pub x: f32,
+    /// Top edge of the rectangle.
+    pub y: f32,
+    /// Width of the rectangle.
+    pub width: f32,
+    /// Height of the rectangle.
+    pub height: f32,
+}
+
+impl Default for ViewportRect {
+    fn default() -> Self {
+        Self::new(0.0, 0.0, 1.0, 1.0)
+    }
+}
+
+impl ViewportRect {
+    /// Create a rectangle from its top left corner and size.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the cell at `index` of the window divided into a grid, counting row by row from
+    /// the top left, e.g. `grid(2, 1, 1)` for the right half of the window.
+    pub fn grid(columns: u32, rows: u32, index: u32) -> Self {
+        let width = 1.0 / columns as f32;
+        let height = 1.0 / rows as f32;
+        Self::new(
+            (index % columns) as f32 * width,
+            (index / columns) as f32 * height,
+            width,
+            height,
+        )
+    }
+
+    /// Returns the rectangle in pixels of a window of the given size, at least one pixel large.
+    ///
+    /// The edges are rounded to whole pixels, so that rectangles sharing an edge neither overlap
+    /// nor leave a gap between them.
+    pub fn pixels(&self, width: u32, height: u32) -> Rect {
+        let (width, height) = (width as f32, height as f32);
+        let left = (self.x * width).round().max(0.0).min(width - 1.0);
+        let top = (self.y * height).round().max(0.0).min(height - 1.0);
+        let right = ((self.x + self.width) * width).round().min(width);
+        let bottom = ((self.y + self.height) * height).round().min(height);
+        Rect {
+            x: left as i16,
+            y: top as i16,
+            w: (right - left).max(1.0) as i16,
+            h: (bottom - top).max(1.0) as i16,
+        }
+    }
+
+    /// Returns the largest rectangle with the given aspect ratio of width to height in pixels of
+    /// a window of the given size, centered in this rectangle. The rest of this rectangle is left
+    /// as bars above and below or to the sides.
+    pub fn fit_aspect(&self, aspect_ratio: f32, width: u32, height: u32) -> Self {
+        let (width, height) = (width as f32, height as f32);
+        let (pixel_width, pixel_height) = (self.width * width, self.height * height);
+        if pixel_width > pixel_height * aspect_ratio {
+            let fitted = pixel_height * aspect_ratio / width;
+            Self::new(
+                self.x + (self.width - fitted) / 2.0,
+                self.y,
+                fitted,
+                self.height,
+            )
+        } else {
+            let fitted = pixel_width / aspect_ratio / height;
+            Self::new(
+                self.x,
+                self.y + (self.height - fitted) / 2.0,
+                self.width,
+                fitted,
+            )
+        }
+    }
+
+    /// Maps a position in pixels of a window of the given size to pixels relative to the top left
+    /// corner of the rectangle, or returns `None` if the rectangle doesn't contain it.
+    ///
+    /// Together with the size of `pixels`, the position can be passed to `Camera::screen_ray` of
+    /// the viewport's camera, e.g. to pick objects with the mouse.
+    pub fn to_local(
+        &self,
+        position: Point2<f32>,
+        screen_diagonal: Vector2<f32>,
+    ) -> Option<Point2<f32>> {
+        let rect = self.pixels(screen_diagonal.x as u32, screen_diagonal.y as u32);
+        let local = Point2::new(position.x - rect.x as f32, position.y - rect.y as f32);
+        if local.x >= 0.0 && local.y >= 0.0 && local.x < rect.w as f32 && local.y < rect.h as f32 {
+            Some(local)
+        } else {
+            None
+        }
+    }
+}
+
+/// Renders the view of the `Camera` on the same entity into a rectangle of the window, e.g. one
+/// per player for split-screen local multiplayer.
+///
+/// Like for a `RenderTexture`, the scene is rendered into the `Target::Custom` named by the
+/// viewport, so plugins have to be pointed at it with `with_target` to draw anything, e.g.
+/// `RenderPbr3D::default().with_target(viewport.target())`. The projection of the camera should
+/// match the aspect ratio of the rectangle.
+///
+/// With an aspect ratio, the view is letterboxed or pillarboxed to the ratio within its
+/// rectangle, e.g. to keep a fixed ratio for a single viewport covering the whole window, while
+/// plugins drawing to the window target like a `RenderUi` still cover the whole window. The bars
+/// show what's drawn to the window target, i.e. its clear color.
+///
+/// Requires the `RenderViewports` plugin, which draws the viewports into the window ordered by
+/// `order`, so that higher orders are drawn over lower ones where they overlap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraViewport {
+    name: &'static str,
+    rect: ViewportRect,
+    order: i32,
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    aspect_ratio: Option<f32>,
+}
+
+impl Component for CameraViewport {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl CameraViewport {
+    /// Creates a viewport covering the given rectangle of the window, cleared to opaque black.
+    /// `name` identifies the render target and must be unique.
+    pub fn new(name: &'static str, rect: ViewportRect) -> Self {
+        Self {
+            name,
+            rect,
+            order: 0,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            clear_depth: 0.0,
+            aspect_ratio: None,
+        }
+    }
+
+    /// Sets the order the viewport is drawn into the window in, lowest first.
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets the color the viewport is cleared to before rendering.
+    pub fn with_clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// Sets the value the depth buffer of the viewport is cleared to before rendering, 0.0 by
+    /// default for the reversed depth of the `Camera` projections.
+    pub fn with_clear_depth(mut self, clear_depth: f32) -> Self {
+        self.clear_depth = clear_depth;
+        self
+    }
+
+    /// Keeps the view at the given aspect ratio of width to height, adding bars to the sides or
+    /// above and below within the rectangle of the viewport.
+    pub fn with_aspect_ratio(mut self, aspect_ratio: f32) -> Self {
+        self.aspect_ratio = Some(aspect_ratio);
+        self
+    }
+
+    /// Returns the render target the scene is rendered into.
+    pub fn target(&self) -> Target {
+        Target::Custom(self.name)
+    }
+
+    /// Returns the rectangle of the window covered by the viewport.
+    pub fn rect(&self) -> ViewportRect {
+        self.rect
+    }
+
+    /// Returns the rectangle the view is drawn into in a window of the given size, i.e. the
+    /// rectangle of the viewport fitted to its aspect ratio.
+    ///
+    /// Use its `to_local` to map positions in the window to the view of the camera.
+    pub fn window_rect(&self, width: u32, height: u32) -> ViewportRect {
+        match self.aspect_ratio {
+            Some(aspect_ratio) => self.rect.fit_aspect(aspect_ratio, width, height),
+            None => self.rect,
+        }
+    }
+
+    /// Returns the order the viewport is drawn into the window in.
+    pub fn order(&self) -> i32 {
+        self.order
+    }
+
+    /// Returns the color the viewport is cleared to before rendering.
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Returns the value the depth buffer is cleared to before rendering.
+    pub fn clear_depth(&self) -> f32 {
+        self.clear_depth
+    }
+
+    /// Returns the aspect ratio the view is kept at, if any.
+    pub fn aspect_ratio(&self) -> Option<f32> {
+        self.aspect_ratio
+    }
+}
+
+/// Returns the camera entity rendering into the given target, if the target belongs to a
+/// `CameraViewport`.
+pub fn viewport_camera(world: &World, target: Target) -> Option<Entity> {
+    if target == Target::Main {
+        return None;
+    }
+    let (entities, viewports) =
+        world.system_data::<(Entities<'_>, ReadStorage<'_, CameraViewport>)>();
+    (&entities, &viewports)
+        .join()
+        .find(|(_, viewport)| viewport.target() == target)
+        .map(|(entity, _)| entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_cells_tile_window() {
+        let (width, height) = (801, 600);
+        let cells = (0..4)
+            .map(|index| ViewportRect::grid(2, 2, index).pixels(width, height))
+            .map(|rect| (rect.x, rect.y, rect.w, rect.h))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cells,
+            vec![
+                (0, 0, 401, 300),
+                (401, 0, 400, 300),
+                (0, 300, 401, 300),
+                (401, 300, 400, 300)
+            ]
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_adds_bars() {
+        let viewport = CameraViewport::new("view", ViewportRect::default());
+        assert_eq!(viewport.window_rect(800, 600), ViewportRect::default());
+
+        let viewport = viewport.with_aspect_ratio(16.0 / 9.0);
+        let rect = viewport.window_rect(800, 600).pixels(800, 600);
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (0, 75, 800, 450));
+        let rect = viewport.window_rect(1000, 450).pixels(1000, 450);
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (100, 0, 800, 450));
+
+        let rect = ViewportRect::grid(2, 1, 1)
+            .fit_aspect(1.0, 800, 600)
+            .pixels(800, 600);
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (400, 100, 400, 400));
+    }
+
+    #[test]
+    fn to_local_maps_into_rect() {
+        let rect = ViewportRect::grid(2, 1, 1);
+        let diagonal = Vector2::new(800.0, 600.0);
+        assert_eq!(rect.to_local(Point2::new(100.0, 50.0), diagonal), None);
+        assert_eq!(
+            rect.to_local(Point2::new(500.0, 50.0), diagonal),
+            Some(Point2::new(100.0, 50.0))
+        );
+    }
+}
@@ -1,8 +1,13 @@
-//! Transparency, visibility sorting and camera centroid culling for 3D Meshes.
+//! Transparency, visibility sorting and frustum culling for 3D Meshes.
 use crate::{
     camera::{ActiveCamera, Camera},
-    transparent::Transparent,
+    mtl::Material,
+    render_texture::RenderTexture,
+    transparent::{RenderQueue, Transparent},
+    types::Mesh,
+    viewport::CameraViewport,
 };
+use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
     ecs::{
         hibitset::BitSet,
@@ -15,24 +20,74 @@ use amethyst_core::{
 };
 
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::HashMap};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 /// Resource for controlling what entities should be rendered, and whether to draw them ordered or
 /// not, which is useful for transparent surfaces.
+///
+/// Entities in the `Opaque` and `AlphaTest` render queues are unordered, while entities in the
+/// `Transparent` queue are ordered from back to front.
 #[derive(Default, Debug)]
 pub struct Visibility {
     /// Visible entities that can be drawn in any order
     pub visible_unordered: BitSet,
+    /// Visible entities of `visible_unordered` in the `AlphaTest` render queue
+    pub visible_alpha_test: BitSet,
     /// Visible entities that need to be drawn in the given order
     pub visible_ordered: Vec<Entity>,
 }
 
+/// Resource holding the visibility of every camera rendering into a `RenderTexture` or a
+/// `CameraViewport`, by camera entity.
+#[derive(Default, Debug)]
+pub struct CameraVisibility {
+    cameras: HashMap<Entity, Visibility>,
+}
+
+impl CameraVisibility {
+    /// Returns the visibility of the given camera.
+    pub fn get(&self, camera: Entity) -> Option<&Visibility> {
+        self.cameras.get(&camera)
+    }
+}
+
+/// Number of drawable entities inside and outside the frustum of a camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullingCounts {
+    /// Entities inside the frustum, which are drawn.
+    pub visible: usize,
+    /// Entities outside the frustum, which are skipped.
+    pub culled: usize,
+}
+
+/// Resource holding the frustum culling statistics of the active camera in the last frame.
+///
+/// Hidden entities are counted in neither.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    /// Meshes culled by `VisibilitySortingSystem`.
+    pub meshes: CullingCounts,
+    /// Sprites culled by `SpriteVisibilitySortingSystem`.
+    pub sprites: CullingCounts,
+}
+
 /// Determine what entities are visible to the camera, and which are not. Will also sort transparent
 /// entities back to front based on distance from camera.
 ///
+/// The render queue of an entity is the `queue` of its `Material` when set, and otherwise
+/// `Transparent` for entities with the `Transparent` component and `Opaque` for the others.
+///
+/// Meshes are culled against the frustum of the camera with their `BoundingSphere`, or a sphere
+/// of radius 1.0 around their origin when they don't have one. The counts of the active camera
+/// are written to `CullingStats`.
+///
+/// The visibility of the active camera is written to `Visibility`, while the visibility of the
+/// cameras rendering into a `RenderTexture` or a `CameraViewport` is written to
+/// `CameraVisibility`.
+///
 /// Note that this should run after `Transform` has been updated for the current frame, and
 /// before rendering occurs.
 #[derive(Default, Debug)]
@@ -72,16 +127,96 @@ impl BoundingSphere {
             radius,
         }
     }
+
+    /// Returns a sphere enclosing this sphere transformed by the given matrix, e.g. the global
+    /// matrix of the entity to get the sphere in world space.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let scale = (0..3)
+            .map(|i| matrix.column(i).xyz().magnitude())
+            .fold(0.0, f32::max);
+        Self {
+            center: matrix.transform_point(&self.center),
+            radius: self.radius * scale,
+        }
+    }
 }
 
 impl Component for BoundingSphere {
     type Storage = DenseVecStorage<Self>;
 }
 
+/// Bitmask of the 32 layers an entity is drawn on, or the layers a camera draws. A camera only
+/// draws the entities sharing one of its layers, e.g. a minimap camera drawing only the map icons
+/// on layer 1, which the main camera on the default layer doesn't draw.
+///
+/// Entities and cameras without the component are on layer 0.
+///
+/// Meshes are filtered by the layers of every camera, sprites by the layers of the active camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RenderLayers(pub u32);
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
+
+impl RenderLayers {
+    /// All of the layers.
+    pub const ALL: Self = RenderLayers(u32::MAX);
+
+    /// None of the layers, hiding an entity from every camera.
+    pub const NONE: Self = RenderLayers(0);
+
+    /// Returns the mask of only the given layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer isn't below 32.
+    pub fn layer(layer: u8) -> Self {
+        RenderLayers::NONE.with(layer)
+    }
+
+    /// Returns the mask with the given layer added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer isn't below 32.
+    pub fn with(self, layer: u8) -> Self {
+        assert!(layer < 32, "Render layer {} isn't below 32", layer);
+        RenderLayers(self.0 | 1 << layer)
+    }
+
+    /// Returns the mask with the given layer removed.
+    pub fn without(self, layer: u8) -> Self {
+        RenderLayers(self.0 & !1u32.checked_shl(layer.into()).unwrap_or(0))
+    }
+
+    /// Returns `true` if the given layer is in the mask.
+    pub fn contains(self, layer: u8) -> bool {
+        self.0 & 1u32.checked_shl(layer.into()).unwrap_or(0) != 0
+    }
+
+    /// Returns `true` if the masks share a layer, in which case a camera with one of them draws
+    /// the entities with the other.
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Component for RenderLayers {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Returns whether a camera on the given layers draws an entity on the given layers.
+pub(crate) fn layers_visible(camera: RenderLayers, entity: Option<&RenderLayers>) -> bool {
+    camera.intersects(entity.copied().unwrap_or_default())
+}
+
 #[derive(Debug, Clone)]
 struct Internals {
     entity: Entity,
-    transparent: bool,
+    queue: RenderQueue,
     centroid: Point3<f32>,
     camera_distance: f32,
 }
@@ -91,48 +226,31 @@ impl VisibilitySortingSystem {
     pub fn new() -> Self {
         Self::default()
     }
-}
-
-impl<'a> System<'a> for VisibilitySortingSystem {
-    type SystemData = (
-        Entities<'a>,
-        Write<'a, Visibility>,
-        ReadStorage<'a, Hidden>,
-        ReadStorage<'a, HiddenPropagate>,
-        Read<'a, ActiveCamera>,
-        ReadStorage<'a, Camera>,
-        ReadStorage<'a, Transparent>,
-        ReadStorage<'a, Transform>,
-        ReadStorage<'a, BoundingSphere>,
-    );
 
-    fn run(
+    /// Sorts the visible objects of the given camera into `visibility`.
+    fn sort(
         &mut self,
-        (
+        camera: &Camera,
+        camera_transform: &Transform,
+        camera_layers: RenderLayers,
+        objects: Objects<'_, '_>,
+        visibility: &mut Visibility,
+    ) -> CullingCounts {
+        let (
             entities,
-            mut visibility,
             hidden,
             hidden_prop,
-            active,
-            camera,
             transparent,
             transform,
             bound,
-        ): Self::SystemData,
-    ) {
-        #[cfg(feature = "profiler")]
-        profile_scope!("visibility_sorting_system");
-
+            meshes,
+            materials,
+            storage,
+            layers,
+        ) = objects;
         let origin = Point3::origin();
-        let defcam = Camera::standard_2d(1.0, 1.0);
-        let identity = Transform::default();
-
-        let mut camera_join = (&camera, &transform).join();
-        let (camera, camera_transform) = active
-            .entity
-            .and_then(|a| camera_join.get(a, &entities))
-            .or_else(|| camera_join.next())
-            .unwrap_or((&defcam, &identity));
+        let default_sphere = BoundingSphere::default();
+        let mut counts = CullingCounts::default();
 
         let camera_centroid = camera_transform.global_matrix().transform_point(&origin);
         let frustum = Frustum::new(
@@ -143,34 +261,60 @@ impl<'a> System<'a> for VisibilitySortingSystem {
         self.centroids.clear();
         self.centroids.extend(
             (
-                &*entities,
-                &transform,
+                &**entities,
+                transform,
+                meshes,
                 bound.maybe(),
-                !&hidden,
-                !&hidden_prop,
+                materials.maybe(),
+                layers.maybe(),
+                !hidden,
+                !hidden_prop,
             )
                 .join()
-                .map(|(entity, transform, sphere, _, _)| {
-                    let pos = sphere.map_or(&origin, |s| &s.center);
-                    let matrix = transform.global_matrix();
-                    (
-                        entity,
-                        matrix.transform_point(&pos),
-                        sphere.map_or(1.0, |s| s.radius)
-                            * matrix[(0, 0)].max(matrix[(1, 1)]).max(matrix[(2, 2)]),
-                    )
+                .filter(|(_, _, _, _, _, layers, _, _)| layers_visible(camera_layers, *layers))
+                .map(|(entity, transform, _, sphere, material, _, _, _)| {
+                    let sphere = sphere
+                        .unwrap_or(&default_sphere)
+                        .transformed(transform.global_matrix());
+                    let queue = RenderQueue::resolve(
+                        material
+                            .and_then(|material| storage.get(material))
+                            .and_then(|material| material.queue),
+                        transparent.contains(entity),
+                    );
+                    (entity, queue, sphere)
+                })
+                .filter(|(_, _, sphere)| {
+                    let visible = frustum.check_sphere(&sphere.center, sphere.radius);
+                    if visible {
+                        counts.visible += 1;
+                    } else {
+                        counts.culled += 1;
+                    }
+                    visible
                 })
-                .filter(|(_, centroid, radius)| frustum.check_sphere(centroid, *radius))
-                .map(|(entity, centroid, _)| Internals {
-                    entity,
-                    transparent: transparent.contains(entity),
-                    centroid,
-                    camera_distance: distance_squared(&centroid, &camera_centroid),
-                }),
+                .map(
+                    |(
+                        entity,
+                        queue,
+                        BoundingSphere {
+                            center: centroid, ..
+                        },
+                    )| Internals {
+                        entity,
+                        queue,
+                        centroid,
+                        camera_distance: distance_squared(&centroid, &camera_centroid),
+                    },
+                ),
         );
         self.transparent.clear();
-        self.transparent
-            .extend(self.centroids.iter().filter(|c| c.transparent).cloned());
+        self.transparent.extend(
+            self.centroids
+                .iter()
+                .filter(|c| c.queue == RenderQueue::Transparent)
+                .cloned(),
+        );
 
         self.transparent.sort_by(|a, b| {
             b.camera_distance
@@ -182,7 +326,14 @@ impl<'a> System<'a> for VisibilitySortingSystem {
         visibility.visible_unordered.extend(
             self.centroids
                 .iter()
-                .filter(|c| !c.transparent)
+                .filter(|c| c.queue != RenderQueue::Transparent)
+                .map(|c| c.entity.id()),
+        );
+        visibility.visible_alpha_test.clear();
+        visibility.visible_alpha_test.extend(
+            self.centroids
+                .iter()
+                .filter(|c| c.queue == RenderQueue::AlphaTest)
                 .map(|c| c.entity.id()),
         );
 
@@ -190,6 +341,111 @@ impl<'a> System<'a> for VisibilitySortingSystem {
         visibility
             .visible_ordered
             .extend(self.transparent.iter().map(|c| c.entity));
+        counts
+    }
+}
+
+type Objects<'r, 'a> = (
+    &'r Entities<'a>,
+    &'r ReadStorage<'a, Hidden>,
+    &'r ReadStorage<'a, HiddenPropagate>,
+    &'r ReadStorage<'a, Transparent>,
+    &'r ReadStorage<'a, Transform>,
+    &'r ReadStorage<'a, BoundingSphere>,
+    &'r ReadStorage<'a, Handle<Mesh>>,
+    &'r ReadStorage<'a, Handle<Material>>,
+    &'r Read<'a, AssetStorage<Material>>,
+    &'r ReadStorage<'a, RenderLayers>,
+);
+
+impl<'a> System<'a> for VisibilitySortingSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Visibility>,
+        Write<'a, CameraVisibility>,
+        Write<'a, CullingStats>,
+        ReadStorage<'a, Hidden>,
+        ReadStorage<'a, HiddenPropagate>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transparent>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, BoundingSphere>,
+        ReadStorage<'a, Handle<Mesh>>,
+        ReadStorage<'a, Handle<Material>>,
+        Read<'a, AssetStorage<Material>>,
+        ReadStorage<'a, RenderTexture>,
+        ReadStorage<'a, CameraViewport>,
+        ReadStorage<'a, RenderLayers>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut visibility,
+            mut camera_visibility,
+            mut stats,
+            hidden,
+            hidden_prop,
+            active,
+            camera,
+            transparent,
+            transform,
+            bound,
+            meshes,
+            materials,
+            material_storage,
+            render_textures,
+            viewports,
+            layers,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("visibility_sorting_system");
+
+        let defcam = Camera::standard_2d(1.0, 1.0);
+        let identity = Transform::default();
+
+        let mut camera_join = (&entities, &camera, &transform).join();
+        let (active_camera, active_transform, active_layers) = active
+            .entity
+            .and_then(|a| camera_join.get(a, &entities))
+            .or_else(|| camera_join.next())
+            .map(|(entity, camera, transform)| (camera, transform, layers.get(entity).copied()))
+            .unwrap_or((&defcam, &identity, None));
+
+        let objects = (
+            &entities,
+            &hidden,
+            &hidden_prop,
+            &transparent,
+            &transform,
+            &bound,
+            &meshes,
+            &materials,
+            &material_storage,
+            &layers,
+        );
+        stats.meshes = self.sort(
+            active_camera,
+            active_transform,
+            active_layers.unwrap_or_default(),
+            objects,
+            &mut visibility,
+        );
+
+        let mut offscreen = render_textures.mask().clone();
+        offscreen |= viewports.mask();
+        let cameras = &mut camera_visibility.cameras;
+        cameras.retain(|entity, _| offscreen.contains(entity.id()));
+        for (entity, camera, camera_transform, _) in
+            (&entities, &camera, &transform, &offscreen).join()
+        {
+            let visibility = cameras.entry(entity).or_default();
+            let camera_layers = layers.get(entity).copied().unwrap_or_default();
+            self.sort(camera, camera_transform, camera_layers, objects, visibility);
+        }
     }
 }
 
@@ -233,3 +489,175 @@ impl Frustum {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        formats::texture::TextureGenerator,
+        mtl::TextureOffset,
+        rendy::mesh::MeshBuilder,
+        types::{MeshData, Texture},
+    };
+    use amethyst_assets::Loader;
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn mesh_world() -> (World, Handle<Mesh>, Material) {
+        let mut world = World::new();
+        world.register::<Camera>();
+        world.register::<Transform>();
+        world.register::<Handle<Mesh>>();
+        world.register::<Handle<Material>>();
+        world.register::<Transparent>();
+        world.register::<Hidden>();
+        world.register::<HiddenPropagate>();
+
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        let mesh = loader.load_from_data(
+            MeshData(MeshBuilder::new()),
+            (),
+            &AssetStorage::<Mesh>::default(),
+        );
+        let texture = loader.load_from_data(
+            TextureGenerator::Srgba(1.0, 1.0, 1.0, 1.0).data(),
+            (),
+            &AssetStorage::<Texture>::default(),
+        );
+        let material = Material {
+            alpha_cutoff: 0.01,
+            albedo: texture.clone(),
+            emission: texture.clone(),
+            normal: texture.clone(),
+            metallic_roughness: texture.clone(),
+            ambient_occlusion: texture.clone(),
+            cavity: texture,
+            uv_offset: TextureOffset::default(),
+            queue: None,
+        };
+        world.insert(AssetStorage::<Material>::default());
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(100.0, 100.0))
+            .with(Transform::default())
+            .build();
+        (world, mesh, material)
+    }
+
+    fn add_mesh(
+        world: &mut World,
+        mesh: &Handle<Mesh>,
+        material: Option<Handle<Material>>,
+        transparent: bool,
+        z: f32,
+    ) -> Entity {
+        let mut transform = Transform::default();
+        transform.set_translation_z(z);
+        transform.copy_local_to_global();
+        let mut builder = world.create_entity().with(mesh.clone()).with(transform);
+        if let Some(material) = material {
+            builder = builder.with(material);
+        }
+        if transparent {
+            builder = builder.with(Transparent);
+        }
+        builder.build()
+    }
+
+    fn run(world: &mut World) {
+        let mut system = VisibilitySortingSystem::new();
+        RunNow::setup(&mut system, world);
+        system.run_now(world);
+    }
+
+    #[test]
+    fn transparent_meshes_are_sorted_back_to_front() {
+        let (mut world, mesh, _) = mesh_world();
+        let near = add_mesh(&mut world, &mesh, None, true, -5.0);
+        let far = add_mesh(&mut world, &mesh, None, true, -20.0);
+        let middle = add_mesh(&mut world, &mesh, None, true, -10.0);
+        let opaque = add_mesh(&mut world, &mesh, None, false, -10.0);
+        run(&mut world);
+
+        let visibility = world.read_resource::<Visibility>();
+        assert_eq!(visibility.visible_ordered, vec![far, middle, near]);
+        assert!(visibility.visible_unordered.contains(opaque.id()));
+        assert!(!visibility.visible_unordered.contains(near.id()));
+        assert!(!visibility.visible_alpha_test.contains(opaque.id()));
+    }
+
+    #[test]
+    fn material_queue_overrides_transparent() {
+        let (mut world, mesh, material) = mesh_world();
+        let (alpha_test, transparent) = {
+            let mut storage = world.write_resource::<AssetStorage<Material>>();
+            let alpha_test = storage.insert(Material {
+                queue: Some(RenderQueue::AlphaTest),
+                ..material.clone()
+            });
+            let transparent = storage.insert(Material {
+                queue: Some(RenderQueue::Transparent),
+                ..material.clone()
+            });
+            (alpha_test, transparent)
+        };
+        let cutout = add_mesh(&mut world, &mesh, Some(alpha_test), true, -5.0);
+        let glass = add_mesh(&mut world, &mesh, Some(transparent), false, -5.0);
+        run(&mut world);
+
+        let visibility = world.read_resource::<Visibility>();
+        assert_eq!(visibility.visible_ordered, vec![glass]);
+        assert!(visibility.visible_unordered.contains(cutout.id()));
+        assert!(visibility.visible_alpha_test.contains(cutout.id()));
+        assert!(!visibility.visible_unordered.contains(glass.id()));
+    }
+
+    #[test]
+    fn render_layers_masks() {
+        let layers = RenderLayers::layer(1).with(31);
+        assert!(layers.contains(1) && layers.contains(31));
+        assert!(!layers.contains(0) && !layers.contains(40));
+        assert_eq!(layers.without(31), RenderLayers::layer(1));
+        assert_eq!(RenderLayers::default(), RenderLayers::layer(0));
+        assert!(layers.intersects(RenderLayers::ALL));
+        assert!(!layers.intersects(RenderLayers::default()));
+        assert!(!RenderLayers::NONE.intersects(RenderLayers::ALL));
+    }
+
+    #[test]
+    fn cameras_draw_meshes_on_their_layers() {
+        let (mut world, mesh, _) = mesh_world();
+        world.register::<RenderLayers>();
+        world.register::<CameraViewport>();
+        let minimap = world
+            .create_entity()
+            .with(Camera::standard_3d(100.0, 100.0))
+            .with(Transform::default())
+            .with(CameraViewport::new("minimap", Default::default()))
+            .with(RenderLayers::layer(1))
+            .build();
+        let terrain = add_mesh(&mut world, &mesh, None, false, -10.0);
+        let icon = add_mesh(&mut world, &mesh, None, false, -10.0);
+        let everywhere = add_mesh(&mut world, &mesh, None, false, -10.0);
+        let mut layers = world.write_storage::<RenderLayers>();
+        layers.insert(icon, RenderLayers::layer(1)).unwrap();
+        layers.insert(everywhere, RenderLayers::ALL).unwrap();
+        drop(layers);
+        run(&mut world);
+
+        let visibility = world.read_resource::<Visibility>();
+        assert!(visibility.visible_unordered.contains(terrain.id()));
+        assert!(!visibility.visible_unordered.contains(icon.id()));
+        assert!(visibility.visible_unordered.contains(everywhere.id()));
+        assert_eq!(world.read_resource::<CullingStats>().meshes.visible, 2);
+
+        let camera_visibility = world.read_resource::<CameraVisibility>();
+        let minimap = camera_visibility.get(minimap).unwrap();
+        assert!(!minimap.visible_unordered.contains(terrain.id()));
+        assert!(minimap.visible_unordered.contains(icon.id()));
+        assert!(minimap.visible_unordered.contains(everywhere.id()));
+    }
+}
@@ -0,0 +1,73 @@
+//! Cameras rendering into additional windows, e.g. for editor views or a second monitor.
+use crate::bundle::Target;
+use amethyst_core::ecs::{Component, DenseVecStorage, Entities, Entity, Join, ReadStorage, World};
+
+/// Renders the view of the `Camera` on the same entity into the additional window of the given
+/// name, opened with the `Windows` resource and presented by `RenderToAdditionalWindow`.
+///
+/// The scene is rendered into the `Target::Custom` named after the window, so plugins have to be
+/// pointed at it with `with_target` to draw anything, e.g.
+/// `RenderPbr3D::default().with_target(window_camera.target())`. The plugins look the camera up
+/// by its target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowCamera {
+    window: &'static str,
+}
+
+impl Component for WindowCamera {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl WindowCamera {
+    /// Renders the camera into the additional window named `window`.
+    pub fn new(window: &'static str) -> Self {
+        Self { window }
+    }
+
+    /// Returns the name of the window the camera renders into.
+    pub fn window(&self) -> &'static str {
+        self.window
+    }
+
+    /// Returns the render target the scene is rendered into.
+    pub fn target(&self) -> Target {
+        Target::Custom(self.window)
+    }
+}
+
+/// Returns the camera entity rendering into the given target, if the target belongs to a
+/// `WindowCamera`.
+pub fn window_camera(world: &World, target: Target) -> Option<Entity> {
+    if target == Target::Main {
+        return None;
+    }
+    let (entities, window_cameras) =
+        world.system_data::<(Entities<'_>, ReadStorage<'_, WindowCamera>)>();
+    (&entities, &window_cameras)
+        .join()
+        .find(|(_, window_camera)| window_camera.target() == target)
+        .map(|(entity, _)| entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, WorldExt};
+
+    #[test]
+    fn finds_camera_by_window() {
+        let mut world = World::new();
+        world.register::<WindowCamera>();
+        let editor = world
+            .create_entity()
+            .with(WindowCamera::new("editor"))
+            .build();
+
+        assert_eq!(
+            window_camera(&world, Target::Custom("editor")),
+            Some(editor)
+        );
+        assert_eq!(window_camera(&world, Target::Custom("minimap")), None);
+        assert_eq!(window_camera(&world, Target::Main), None);
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    core::{math::Vector3, Parent, Transform, TransformBundle},
+    ecs::prelude::*,
+    renderer::{
+        camera::Camera,
+        loaders::load_from_srgba,
+        palette::Srgba,
+        sprite::{Sprite, SpriteRender, SpriteSheet},
+        sprite_visibility::SpriteVisibilitySortingSystem,
+        types::TextureData,
+        Texture,
+    },
+    ui::{Anchor, UiTransform, UiTransformSystemDesc},
+    window::ScreenDimensions,
+};
+use amethyst_test::{SystemBench, SystemBenchDispatcher};
+use criterion::{criterion_group, criterion_main, Criterion};
+use rayon::ThreadPoolBuilder;
+
+// Moves every transform, so the systems propagate changes to all entities each frame
+#[derive(Debug)]
+struct MoveTransforms;
+impl<'s> System<'s> for MoveTransforms {
+    type SystemData = WriteStorage<'s, Transform>;
+
+    fn run(&mut self, mut transforms: Self::SystemData) {
+        (&mut transforms).join().for_each(|transform| {
+            transform.prepend_translation_x(1.0);
+        });
+    }
+}
+
+#[derive(Debug)]
+struct MoveUiTransforms;
+impl<'s> System<'s> for MoveUiTransforms {
+    type SystemData = WriteStorage<'s, UiTransform>;
+
+    fn run(&mut self, mut transforms: Self::SystemData) {
+        (&mut transforms)
+            .join()
+            .for_each(|transform| transform.local_x += 1.0);
+    }
+}
+
+fn bench_frames(c: &mut Criterion, name: &str, mut bench: SystemBenchDispatcher) {
+    c.bench_function(name, move |b| {
+        b.iter_custom(|iterations| (0..iterations).map(|_| bench.frame()).sum())
+    });
+}
+
+// 1000 hierarchies of 10 transforms each
+pub fn transform_propagation_10k(c: &mut Criterion) {
+    let bench = SystemBench::new()
+        .with_system(MoveTransforms, "move_transforms", &[])
+        .with_bundle(TransformBundle::new().with_dep(&["move_transforms"]))
+        .with_setup(|world| {
+            for _ in 0..1000 {
+                let mut parent = world.create_entity().with(Transform::default()).build();
+                for _ in 0..9 {
+                    parent = world
+                        .create_entity()
+                        .with(Transform::default())
+                        .with(Parent::new(parent))
+                        .build();
+                }
+            }
+        })
+        .build()
+        .expect("Failed to build the benchmark");
+    bench_frames(c, "transform_propagation_10k", bench);
+}
+
+// 100 panels of 10 stretched and anchored widgets each
+pub fn ui_transform_1k(c: &mut Criterion) {
+    let bench = SystemBench::new()
+        .with_system(MoveUiTransforms, "move_ui_transforms", &[])
+        .with_bundle(TransformBundle::new().with_dep(&["move_ui_transforms"]))
+        .with_system_desc(UiTransformSystemDesc, "ui_transform", &["transform_system"])
+        .with_resource(ScreenDimensions::new(1920, 1080, 1.0))
+        .with_setup(|world| {
+            for i in 0..100 {
+                let panel = world
+                    .create_entity()
+                    .with(UiTransform::new(
+                        format!("panel_{}", i),
+                        Anchor::TopLeft,
+                        Anchor::TopLeft,
+                        (i % 10) as f32 * 190.0,
+                        (i / 10) as f32 * -100.0,
+                        0.0,
+                        180.0,
+                        90.0,
+                    ))
+                    .build();
+                for j in 0..9 {
+                    world
+                        .create_entity()
+                        .with(UiTransform::new(
+                            format!("widget_{}_{}", i, j),
+                            Anchor::Middle,
+                            Anchor::Middle,
+                            0.0,
+                            j as f32 * 10.0 - 40.0,
+                            1.0,
+                            160.0,
+                            8.0,
+                        ))
+                        .with(Parent::new(panel))
+                        .build();
+                }
+            }
+        })
+        .build()
+        .expect("Failed to build the benchmark");
+    bench_frames(c, "ui_transform_1k", bench);
+}
+
+// 10k sprites on a 320 x 32 grid, half of them outside of the camera
+pub fn sprite_visibility_10k(c: &mut Criterion) {
+    let bench = SystemBench::new()
+        .with_system(MoveTransforms, "move_transforms", &[])
+        .with_bundle(TransformBundle::new().with_dep(&["move_transforms"]))
+        .with_system(
+            SpriteVisibilitySortingSystem::new(),
+            "sprite_visibility",
+            &["transform_system"],
+        )
+        .with_setup(|world| {
+            let pool = ThreadPoolBuilder::new()
+                .build()
+                .expect("Failed to create the thread pool");
+            let loader = Loader::new(".", Arc::new(pool));
+            world.insert(AssetStorage::<Texture>::new());
+            let texture = loader.load_from_data::<Texture, _>(
+                TextureData(load_from_srgba(Srgba::new(1.0, 1.0, 1.0, 1.0))),
+                (),
+                &world.read_resource::<AssetStorage<Texture>>(),
+            );
+            let sprite_sheet =
+                world
+                    .write_resource::<AssetStorage<SpriteSheet>>()
+                    .insert(SpriteSheet {
+                        texture,
+                        sprites: vec![Sprite::from_pixel_values(
+                            32,
+                            32,
+                            32,
+                            32,
+                            0,
+                            0,
+                            [16.0, 16.0],
+                            false,
+                            false,
+                        )],
+                        durations: Vec::new(),
+                    });
+
+            world
+                .create_entity()
+                .with(Camera::standard_2d(5120.0, 1024.0))
+                .with(Transform::from(Vector3::new(2560.0, 512.0, 10.0)))
+                .build();
+            for i in 0..10_000 {
+                let mut transform = Transform::default();
+                transform.set_translation_xyz(
+                    (i % 320) as f32 * 32.0,
+                    (i / 320) as f32 * 32.0,
+                    0.0,
+                );
+                world
+                    .create_entity()
+                    .with(transform)
+                    .with(SpriteRender {
+                        sprite_sheet: sprite_sheet.clone(),
+                        sprite_number: 0,
+                    })
+                    .build();
+            }
+        })
+        .build()
+        .expect("Failed to build the benchmark");
+    bench_frames(c, "sprite_visibility_10k", bench);
+}
+
+criterion_group!(
+    systems,
+    transform_propagation_10k,
+    ui_transform_1k,
+    sprite_visibility_10k
+);
+criterion_main!(systems);
@@ -1,24 +1,26 @@
-use std::{any::Any, marker::PhantomData, panic, path::PathBuf, sync::Mutex};
+use std::{any::Any, marker::PhantomData, panic, path::PathBuf, sync::Mutex, time::Duration};
 
 use amethyst::{
     self,
-    core::{transform::TransformBundle, EventReader, RunNowDesc, SystemBundle, SystemDesc},
+    core::{transform::TransformBundle, EventReader, RunNowDesc, SystemBundle, SystemDesc, Time},
     ecs::prelude::*,
     error::Error,
     input::{BindingTypes, InputBundle},
     prelude::*,
     shred::Resource,
-    ui::UiBundle,
+    ui::{UiBundle, UiEvent},
     utils::application_root_dir,
     window::ScreenDimensions,
+    winit::{ElementState, MouseButton, VirtualKeyCode},
     StateEventReader,
 };
 use derivative::Derivative;
 use lazy_static::lazy_static;
 
 use crate::{
-    CustomDispatcherStateBuilder, FunctionState, GameUpdate, SequencerState,
-    SystemDescInjectionBundle, SystemInjectionBundle, ThreadLocalInjectionBundle,
+    ui_input, CustomDispatcherStateBuilder, FunctionState, GameUpdate, RunFramesState,
+    SequencerState, SystemDescInjectionBundle, SystemInjectionBundle, ThreadLocalInjectionBundle,
+    UiEventRecorder,
 };
 
 type BundleAddFn = Box<
@@ -542,6 +544,27 @@ where
         self.with_state(move || FunctionState::new(func))
     }
 
+    /// Runs the systems for the given number of frames before the next state.
+    ///
+    /// # Parameters
+    ///
+    /// * `frames`: Number of frames to run.
+    pub fn with_frames(self, frames: usize) -> Self {
+        self.with_state(move || RunFramesState::new(frames))
+    }
+
+    /// Advances the `Time` by the given duration every frame instead of the time measured by the
+    /// clock, so that systems depending on the time behave the same in every run.
+    ///
+    /// # Parameters
+    ///
+    /// * `delta`: Duration of every frame.
+    pub fn with_manual_time(self, delta: Duration) -> Self {
+        self.with_setup(move |world| {
+            world.write_resource::<Time>().set_manual_delta(Some(delta));
+        })
+    }
+
     /// Registers a function that sets up the `World`.
     ///
     /// This is an alias to `.with_fn(F)`.
@@ -584,21 +607,106 @@ where
     {
         self.with_fn(assertion_fn)
     }
+
+    /// Moves the mouse cursor, processed by the systems in the next frame.
+    ///
+    /// # Parameters
+    ///
+    /// * `x`: Pixels from the left of the window.
+    /// * `y`: Pixels from the top of the window.
+    pub fn with_mouse_move(self, x: f64, y: f64) -> Self {
+        self.with_fn(move |world| ui_input::mouse_move(world, x, y))
+    }
+
+    /// Presses a mouse button, processed by the systems in the next frame.
+    ///
+    /// # Parameters
+    ///
+    /// * `button`: Mouse button to press.
+    pub fn with_mouse_press(self, button: MouseButton) -> Self {
+        self.with_fn(move |world| ui_input::mouse_input(world, button, ElementState::Pressed))
+    }
+
+    /// Releases a mouse button, processed by the systems in the next frame.
+    ///
+    /// # Parameters
+    ///
+    /// * `button`: Mouse button to release.
+    pub fn with_mouse_release(self, button: MouseButton) -> Self {
+        self.with_fn(move |world| ui_input::mouse_input(world, button, ElementState::Released))
+    }
+
+    /// Presses a mouse button, then releases it in the following frame.
+    ///
+    /// # Parameters
+    ///
+    /// * `button`: Mouse button to click.
+    pub fn with_mouse_click(self, button: MouseButton) -> Self {
+        self.with_mouse_press(button).with_mouse_release(button)
+    }
+
+    /// Presses a key, then releases it in the following frame.
+    ///
+    /// # Parameters
+    ///
+    /// * `key`: Key to press.
+    pub fn with_key_press(self, key: VirtualKeyCode) -> Self {
+        self.with_fn(move |world| ui_input::key_input(world, key, ElementState::Pressed))
+            .with_fn(move |world| ui_input::key_input(world, key, ElementState::Released))
+    }
+
+    /// Types text, processed by the systems in the next frame.
+    ///
+    /// # Parameters
+    ///
+    /// * `text`: Characters to type.
+    pub fn with_text_input<S: Into<String>>(self, text: S) -> Self {
+        let text = text.into();
+        self.with_fn(move |world| ui_input::text_input(world, &text))
+    }
+
+    /// Resizes the screen, processed by the systems in the next frame.
+    ///
+    /// # Parameters
+    ///
+    /// * `width`: New width of the screen.
+    /// * `height`: New height of the screen.
+    pub fn with_screen_resize(self, width: f64, height: f64) -> Self {
+        self.with_fn(move |world| ui_input::screen_resize(world, width, height))
+    }
+
+    /// Registers a function to assert the `UiEvent`s written since the previous UI event
+    /// assertion, or since the start of the application.
+    ///
+    /// # Parameters
+    ///
+    /// * `assertion_fn`: Function that asserts the expected events.
+    pub fn with_ui_event_assertion<F>(self, assertion_fn: F) -> Self
+    where
+        F: FnOnce(&mut World, &[UiEvent]) + Send + Sync + 'static,
+    {
+        self.with_setup(UiEventRecorder::setup)
+            .with_fn(move |world| {
+                let events = world.write_resource::<UiEventRecorder>().read(world);
+                assertion_fn(world, &events);
+            })
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::marker::PhantomData;
+    use std::{marker::PhantomData, time::Duration};
 
     use amethyst::{
         assets::{Asset, AssetStorage, Handle, Loader, ProcessingState, Processor},
-        core::{bundle::SystemBundle, SystemDesc},
+        core::{bundle::SystemBundle, SystemDesc, Time},
         derive::SystemDesc,
         ecs::prelude::*,
         error::Error,
         prelude::*,
-        ui::FontAsset,
+        ui::{Anchor, FontAsset, Interactable, UiEvent, UiEventType, UiTransform},
         window::ScreenDimensions,
+        winit::MouseButton,
     };
 
     use super::AmethystApplication;
@@ -792,6 +900,59 @@ mod test {
             .run()
     }
 
+    #[test]
+    fn ui_input_clicks_interactable() -> Result<(), Error> {
+        let effect_fn = |world: &mut World| {
+            let transform = UiTransform::new(
+                "button".to_string(),
+                Anchor::Middle,
+                Anchor::Middle,
+                0.,
+                0.,
+                1.,
+                100.,
+                50.,
+            );
+            let entity = world
+                .create_entity()
+                .with(transform)
+                .with(Interactable)
+                .build();
+
+            world.insert(EffectReturn(entity));
+        };
+
+        fn event_types(world: &mut World, events: &[UiEvent]) -> Vec<UiEventType> {
+            let entity = world.read_resource::<EffectReturn<Entity>>().0;
+            events
+                .iter()
+                .filter(|event| event.target == entity)
+                .map(|event| event.event_type.clone())
+                .collect()
+        }
+
+        AmethystApplication::ui_base::<amethyst::input::StringBindings>()
+            .with_effect(effect_fn)
+            .with_mouse_move(400., 300.)
+            .with_mouse_click(MouseButton::Left)
+            .with_ui_event_assertion(|world, events| {
+                assert_eq!(
+                    vec![
+                        UiEventType::HoverStart,
+                        UiEventType::ClickStart,
+                        UiEventType::Click,
+                        UiEventType::ClickStop,
+                    ],
+                    event_types(world, events)
+                );
+            })
+            .with_mouse_move(10., 10.)
+            .with_ui_event_assertion(|world, events| {
+                assert_eq!(vec![UiEventType::HoverStop], event_types(world, events));
+            })
+            .run()
+    }
+
     #[test]
     fn with_system_runs_system_every_tick() -> Result<(), Error> {
         let effect_fn = |world: &mut World| {
@@ -819,6 +980,53 @@ mod test {
             .run()
     }
 
+    #[test]
+    fn with_frames_runs_system_every_frame() -> Result<(), Error> {
+        let effect_fn = |world: &mut World| {
+            let entity = world.create_entity().with(ComponentZero(0)).build();
+
+            world.insert(EffectReturn(entity));
+        };
+
+        let assertion_fn = |world: &mut World| {
+            let entity = world.read_resource::<EffectReturn<Entity>>().0;
+
+            let component_zero_storage = world.read_storage::<ComponentZero>();
+            let component_zero = component_zero_storage
+                .get(entity)
+                .expect("Entity should have a `ComponentZero` component.");
+
+            // The assertion runs after the systems of its own frame.
+            assert_eq!(4, component_zero.0);
+        };
+
+        AmethystApplication::blank()
+            .with_system(SystemEffect, "system_effect", &[])
+            .with_effect(effect_fn)
+            .with_frames(3)
+            .with_assertion(assertion_fn)
+            .run()
+    }
+
+    #[test]
+    fn with_manual_time_advances_time_exactly() -> Result<(), Error> {
+        let assertion_fn = |world: &mut World| {
+            let time = world.read_resource::<Time>();
+
+            assert_eq!(Duration::from_millis(100), time.delta_time());
+            assert_eq!(
+                Duration::from_millis(100) * time.frame_number() as u32,
+                time.absolute_time()
+            );
+        };
+
+        AmethystApplication::blank()
+            .with_manual_time(Duration::from_millis(100))
+            .with_frames(3)
+            .with_assertion(assertion_fn)
+            .run()
+    }
+
     #[test]
     fn with_system_invoked_twice_should_not_panic() {
         AmethystApplication::blank()
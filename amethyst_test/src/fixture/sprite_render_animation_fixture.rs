@@ -99,6 +99,7 @@ impl SpriteRenderAnimationFixture {
                 offsets: [5.; 2],
                 tex_coords: [0.0, 1.0, 0.0, 1.0].into(),
             }],
+            durations: Vec::new(),
         }
     }
 }
@@ -326,8 +326,10 @@ pub use crate::{
     in_memory_source::{InMemorySource, IN_MEMORY_SOURCE_ID},
     state::{
         CustomDispatcherState, CustomDispatcherStateBuilder, FunctionState, PopState,
-        SequencerState,
+        RunFramesState, SequencerState,
     },
+    system_bench::{BenchReport, SystemBench, SystemBenchDispatcher},
+    ui_input::UiEventRecorder,
     wait_for_load::WaitForLoad,
 };
 pub(crate) use crate::{
@@ -346,7 +348,9 @@ mod game_update;
 mod in_memory_source;
 pub mod prelude;
 mod state;
+mod system_bench;
 mod system_desc_injection_bundle;
 mod system_injection_bundle;
 mod thread_local_injection_bundle;
+pub mod ui_input;
 mod wait_for_load;
@@ -2,10 +2,12 @@ pub use self::{
     custom_dispatcher_state::{CustomDispatcherState, CustomDispatcherStateBuilder},
     function_state::FunctionState,
     pop_state::PopState,
+    run_frames_state::RunFramesState,
     sequencer_state::SequencerState,
 };
 
 mod custom_dispatcher_state;
 mod function_state;
 mod pop_state;
+mod run_frames_state;
 mod sequencer_state;
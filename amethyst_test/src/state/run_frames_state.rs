@@ -0,0 +1,38 @@
+use amethyst::prelude::*;
+
+use crate::GameUpdate;
+
+/// Runs the dispatcher for a number of frames, then `Pop`s itself.
+#[derive(Debug)]
+pub struct RunFramesState {
+    /// Number of frames left to run.
+    frames: usize,
+}
+
+impl RunFramesState {
+    /// Returns a new `RunFramesState` running the given number of frames.
+    pub fn new(frames: usize) -> Self {
+        RunFramesState { frames }
+    }
+}
+
+impl<T, E> State<T, E> for RunFramesState
+where
+    T: GameUpdate,
+    E: Send + Sync + 'static,
+{
+    fn update(&mut self, data: StateData<'_, T>) -> Trans<T, E> {
+        if self.frames == 0 {
+            return Trans::Pop;
+        }
+
+        data.data.update(data.world);
+        self.frames -= 1;
+
+        if self.frames == 0 {
+            Trans::Pop
+        } else {
+            Trans::None
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use amethyst::{
+    core::{bundle::SystemBundle, SystemDesc},
+    ecs::{prelude::*, shred::Resource},
+    error::Error,
+};
+use derivative::Derivative;
+
+type BundleAddFn =
+    Box<dyn FnOnce(&mut World, &mut DispatcherBuilder<'static, 'static>) -> Result<(), Error>>;
+type FnSetup = Box<dyn FnOnce(&mut World)>;
+
+/// Measures how long systems take to run on a world with many entities.
+///
+/// The systems and bundles are dispatched like in an application, once per frame, followed by
+/// `World::maintain`. The frames run before measuring let the systems warm up their caches and
+/// settle changes made when the entities are created.
+///
+/// ```rust,no_run
+/// use amethyst::{
+///     core::{Transform, TransformBundle},
+///     ecs::prelude::*,
+/// };
+/// use amethyst_test::SystemBench;
+///
+/// let report = SystemBench::new()
+///     .with_bundle(TransformBundle::new())
+///     .with_entities(10_000, |i, builder| {
+///         builder.with(Transform::default().set_translation_x(i as f32).clone())
+///     })
+///     .run(100)
+///     .expect("Failed to run the benchmark");
+/// println!("transform propagation: {}", report);
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SystemBench {
+    /// Functions to add bundles and systems to the dispatcher.
+    #[derivative(Debug = "ignore")]
+    bundle_add_fns: Vec<BundleAddFn>,
+    /// Functions setting up the `World` once the dispatcher is set up, in user specified order.
+    #[derivative(Debug = "ignore")]
+    setup_fns: Vec<FnSetup>,
+    /// Number of frames run before measuring.
+    warmup: usize,
+}
+
+impl SystemBench {
+    /// Returns a benchmark without systems, running 10 frames before measuring.
+    pub fn new() -> Self {
+        SystemBench {
+            bundle_add_fns: Vec::new(),
+            setup_fns: Vec::new(),
+            warmup: 10,
+        }
+    }
+
+    /// Adds a bundle to the dispatcher.
+    ///
+    /// # Parameters
+    ///
+    /// * `bundle`: Bundle to add.
+    pub fn with_bundle<B>(mut self, bundle: B) -> Self
+    where
+        B: SystemBundle<'static, 'static> + 'static,
+    {
+        self.bundle_add_fns
+            .push(Box::new(move |world, builder| bundle.build(world, builder)));
+        self
+    }
+
+    /// Adds a system to the dispatcher.
+    ///
+    /// # Parameters
+    ///
+    /// * `system`: `System` to run.
+    /// * `name`: Name to register the system with.
+    /// * `deps`: Names of the systems this one depends on.
+    pub fn with_system<S>(mut self, system: S, name: &str, deps: &[&str]) -> Self
+    where
+        S: for<'s> System<'s> + Send + 'static,
+    {
+        let name = name.to_string();
+        let deps = deps.iter().map(|dep| dep.to_string()).collect::<Vec<_>>();
+        self.bundle_add_fns.push(Box::new(move |_, builder| {
+            let deps = deps.iter().map(String::as_str).collect::<Vec<_>>();
+            builder.add(system, &name, &deps);
+            Ok(())
+        }));
+        self
+    }
+
+    /// Adds a system built from its `SystemDesc` to the dispatcher.
+    ///
+    /// # Parameters
+    ///
+    /// * `system_desc`: Descriptor to build the `System` with.
+    /// * `name`: Name to register the system with.
+    /// * `deps`: Names of the systems this one depends on.
+    pub fn with_system_desc<SD, S>(mut self, system_desc: SD, name: &str, deps: &[&str]) -> Self
+    where
+        SD: SystemDesc<'static, 'static, S> + 'static,
+        S: for<'s> System<'s> + Send + 'static,
+    {
+        let name = name.to_string();
+        let deps = deps.iter().map(|dep| dep.to_string()).collect::<Vec<_>>();
+        self.bundle_add_fns.push(Box::new(move |world, builder| {
+            let deps = deps.iter().map(String::as_str).collect::<Vec<_>>();
+            builder.add(system_desc.build(world), &name, &deps);
+            Ok(())
+        }));
+        self
+    }
+
+    /// Adds a resource to the `World`.
+    ///
+    /// # Parameters
+    ///
+    /// * `resource`: Resource to add.
+    pub fn with_resource<R: Resource>(self, resource: R) -> Self {
+        self.with_setup(move |world| world.insert(resource))
+    }
+
+    /// Creates entities, with components added by the given function to each entity builder.
+    ///
+    /// # Parameters
+    ///
+    /// * `count`: Number of entities to create.
+    /// * `entity_fn`: Function adding components to the entity with the given index.
+    pub fn with_entities<F>(self, count: usize, entity_fn: F) -> Self
+    where
+        F: for<'w> Fn(usize, EntityBuilder<'w>) -> EntityBuilder<'w> + 'static,
+    {
+        self.with_setup(move |world| {
+            (0..count).for_each(|i| {
+                entity_fn(i, world.create_entity()).build();
+            })
+        })
+    }
+
+    /// Registers a function that sets up the `World`, run once the dispatcher is set up.
+    ///
+    /// # Parameters
+    ///
+    /// * `setup_fn`: Function to execute.
+    pub fn with_setup<F>(mut self, setup_fn: F) -> Self
+    where
+        F: FnOnce(&mut World) + 'static,
+    {
+        self.setup_fns.push(Box::new(setup_fn));
+        self
+    }
+
+    /// Sets the number of frames run before measuring.
+    ///
+    /// # Parameters
+    ///
+    /// * `frames`: Number of frames to run.
+    pub fn with_warmup(mut self, frames: usize) -> Self {
+        self.warmup = frames;
+        self
+    }
+
+    /// Sets up the world and the dispatcher and runs the warmup frames, returning the dispatcher
+    /// to measure frames with.
+    pub fn build(self) -> Result<SystemBenchDispatcher, Error> {
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+        for function in self.bundle_add_fns {
+            function(&mut world, &mut builder)?;
+        }
+        let mut dispatcher = builder.build();
+        dispatcher.setup(&mut world);
+        for function in self.setup_fns {
+            function(&mut world);
+        }
+
+        let mut bench = SystemBenchDispatcher { world, dispatcher };
+        (0..self.warmup).for_each(|_| {
+            bench.frame();
+        });
+        Ok(bench)
+    }
+
+    /// Runs the given number of frames after the warmup, returning their timings.
+    ///
+    /// # Parameters
+    ///
+    /// * `frames`: Number of frames to measure.
+    pub fn run(self, frames: usize) -> Result<BenchReport, Error> {
+        Ok(self.build()?.run(frames))
+    }
+}
+
+/// World and dispatcher set up by a `SystemBench`, e.g. to measure frames with `criterion`:
+///
+/// ```rust,ignore
+/// let mut bench = SystemBench::new().with_bundle(TransformBundle::new()).build()?;
+/// c.bench_function("transform", |b| {
+///     b.iter_custom(|iterations| (0..iterations).map(|_| bench.frame()).sum())
+/// });
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SystemBenchDispatcher {
+    /// World the systems run on.
+    #[derivative(Debug = "ignore")]
+    world: World,
+    /// Dispatcher of the systems.
+    #[derivative(Debug = "ignore")]
+    dispatcher: Dispatcher<'static, 'static>,
+}
+
+impl SystemBenchDispatcher {
+    /// Runs the systems once, returning how long it took.
+    pub fn frame(&mut self) -> Duration {
+        let start = Instant::now();
+        self.dispatcher.dispatch(&self.world);
+        self.world.maintain();
+        start.elapsed()
+    }
+
+    /// Runs the given number of frames, returning their timings.
+    ///
+    /// # Parameters
+    ///
+    /// * `frames`: Number of frames to measure.
+    pub fn run(&mut self, frames: usize) -> BenchReport {
+        BenchReport::new((0..frames).map(|_| self.frame()).collect())
+    }
+
+    /// Returns the `World` the systems run on, e.g. to change it between frames.
+    pub fn world(&mut self) -> &mut World {
+        &mut self.world
+    }
+}
+
+/// Timings of the frames measured by a `SystemBench`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchReport {
+    /// Frame timings, sorted from the fastest.
+    sorted: Vec<Duration>,
+    /// Frame timings, in the order the frames were run.
+    frames: Vec<Duration>,
+}
+
+impl BenchReport {
+    /// Returns a report of the given frame timings.
+    pub fn new(frames: Vec<Duration>) -> Self {
+        let mut sorted = frames.clone();
+        sorted.sort();
+        BenchReport { sorted, frames }
+    }
+
+    /// Returns the timings of the frames, in the order they were run.
+    pub fn frames(&self) -> &[Duration] {
+        &self.frames
+    }
+
+    /// Returns the total time of the frames.
+    pub fn total(&self) -> Duration {
+        self.frames.iter().sum()
+    }
+
+    /// Returns the mean time of a frame.
+    pub fn mean(&self) -> Duration {
+        match self.frames.len() {
+            0 => Duration::default(),
+            frames => self.total() / frames as u32,
+        }
+    }
+
+    /// Returns the time of the fastest frame.
+    pub fn min(&self) -> Duration {
+        self.percentile(0.0)
+    }
+
+    /// Returns the time of the slowest frame.
+    pub fn max(&self) -> Duration {
+        self.percentile(100.0)
+    }
+
+    /// Returns the median time of a frame.
+    pub fn median(&self) -> Duration {
+        self.percentile(50.0)
+    }
+
+    /// Returns the time under which the given percentage of the frames ran, e.g. 95.0 for the
+    /// 95th percentile.
+    ///
+    /// # Parameters
+    ///
+    /// * `percent`: Percentage of the frames, from 0.0 to 100.0.
+    pub fn percentile(&self, percent: f64) -> Duration {
+        if self.sorted.is_empty() {
+            return Duration::default();
+        }
+        let last = self.sorted.len() - 1;
+        let index = (percent.clamp(0.0, 100.0) / 100.0 * last as f64).round() as usize;
+        self.sorted[index]
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames: mean {:?}, median {:?}, p95 {:?}, min {:?}, max {:?}",
+            self.frames.len(),
+            self.mean(),
+            self.median(),
+            self.percentile(95.0),
+            self.min(),
+            self.max()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use amethyst::ecs::prelude::*;
+
+    use super::{BenchReport, SystemBench};
+
+    #[test]
+    fn system_bench_runs_systems_on_entities() {
+        let mut bench = SystemBench::new()
+            .with_system(SystemIncrement, "increment", &[])
+            .with_entities(100, |i, builder| builder.with(Counter(i)))
+            .with_warmup(5)
+            .build()
+            .expect("Failed to build the benchmark");
+
+        let report = bench.run(20);
+        assert_eq!(report.frames().len(), 20);
+        assert!(report.min() <= report.median() && report.median() <= report.max());
+
+        let counters = bench.world().read_storage::<Counter>();
+        let mut counts = counters.join().map(|counter| counter.0).collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, (25..125).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bench_report_computes_statistics() {
+        let report = BenchReport::new(
+            [4, 1, 3, 2, 5]
+                .iter()
+                .map(|&millis| Duration::from_millis(millis))
+                .collect(),
+        );
+
+        assert_eq!(report.total(), Duration::from_millis(15));
+        assert_eq!(report.mean(), Duration::from_millis(3));
+        assert_eq!(report.median(), Duration::from_millis(3));
+        assert_eq!(report.min(), Duration::from_millis(1));
+        assert_eq!(report.max(), Duration::from_millis(5));
+        assert_eq!(report.percentile(75.0), Duration::from_millis(4));
+        assert_eq!(report.frames()[0], Duration::from_millis(4));
+        assert_eq!(BenchReport::new(Vec::new()).median(), Duration::default());
+    }
+
+    #[derive(Debug)]
+    struct Counter(usize);
+    impl Component for Counter {
+        type Storage = VecStorage<Self>;
+    }
+
+    #[derive(Debug)]
+    struct SystemIncrement;
+    impl<'s> System<'s> for SystemIncrement {
+        type SystemData = WriteStorage<'s, Counter>;
+
+        fn run(&mut self, mut counters: Self::SystemData) {
+            (&mut counters).join().for_each(|counter| counter.0 += 1);
+        }
+    }
+}
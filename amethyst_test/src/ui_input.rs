@@ -0,0 +1,119 @@
+//! Synthesized window input and recorded `UiEvent`s, to test how UI widgets react to input.
+
+use amethyst::{
+    ecs::{World, WorldExt},
+    shrev::{EventChannel, ReaderId},
+    ui::UiEvent,
+    window::ScreenDimensions,
+    winit::{
+        dpi::{LogicalPosition, LogicalSize},
+        DeviceId, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode,
+        WindowEvent, WindowId,
+    },
+};
+
+/// Records the `UiEvent`s written since it was created or last read.
+///
+/// `AmethystApplication::with_ui_event_assertion` inserts one in the `World` before the first
+/// frame, so that the events of all frames are recorded.
+#[derive(Debug)]
+pub struct UiEventRecorder {
+    reader: ReaderId<UiEvent>,
+}
+
+impl UiEventRecorder {
+    /// Returns a recorder of the `UiEvent`s written from now on.
+    pub fn new(world: &mut World) -> Self {
+        let reader = world
+            .entry::<EventChannel<UiEvent>>()
+            .or_insert_with(EventChannel::new)
+            .register_reader();
+        UiEventRecorder { reader }
+    }
+
+    /// Inserts a recorder in the `World` if there is none.
+    pub(crate) fn setup(world: &mut World) {
+        if !world.has_value::<UiEventRecorder>() {
+            let recorder = UiEventRecorder::new(world);
+            world.insert(recorder);
+        }
+    }
+
+    /// Returns the `UiEvent`s written since the last read.
+    pub fn read(&mut self, world: &World) -> Vec<UiEvent> {
+        world
+            .read_resource::<EventChannel<UiEvent>>()
+            .read(&mut self.reader)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Writes a window event into the `EventChannel<Event>` read by the `InputSystem` and the UI
+/// systems, as if the window had received it.
+pub fn send_window_event(world: &mut World, event: WindowEvent) {
+    let event = Event::WindowEvent {
+        // Safe as the id is only compared, never passed to the platform.
+        window_id: unsafe { WindowId::dummy() },
+        event,
+    };
+    world
+        .entry::<EventChannel<Event>>()
+        .or_insert_with(EventChannel::new)
+        .single_write(event);
+}
+
+/// Moves the mouse cursor to the given position, in pixels from the top left of the window.
+pub fn mouse_move(world: &mut World, x: f64, y: f64) {
+    send_window_event(
+        world,
+        WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: LogicalPosition::new(x, y),
+            modifiers: ModifiersState::default(),
+        },
+    );
+}
+
+/// Presses or releases a mouse button.
+pub fn mouse_input(world: &mut World, button: MouseButton, state: ElementState) {
+    send_window_event(
+        world,
+        WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button,
+            modifiers: ModifiersState::default(),
+        },
+    );
+}
+
+/// Presses or releases a key.
+pub fn key_input(world: &mut World, key: VirtualKeyCode, state: ElementState) {
+    send_window_event(
+        world,
+        WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::default(),
+            },
+        },
+    );
+}
+
+/// Types the characters of the text, as received by the focused text field.
+pub fn text_input(world: &mut World, text: &str) {
+    text.chars()
+        .for_each(|c| send_window_event(world, WindowEvent::ReceivedCharacter(c)));
+}
+
+/// Resizes the screen, updating the `ScreenDimensions` if there are some.
+pub fn screen_resize(world: &mut World, width: f64, height: f64) {
+    if let Some(mut screen_dimensions) = world.try_fetch_mut::<ScreenDimensions>() {
+        screen_dimensions.update(width, height);
+    }
+    send_window_event(world, WindowEvent::Resized(LogicalSize::new(width, height)));
+}
@@ -7,6 +7,8 @@ layout(std140, set = 0, binding = 0) uniform TileMapArgs {
     uniform mat4 map_transform;
     // We assume coordinates are uniform for tiles, so we can actually store the sprite information here
     uniform vec2 sprite_dimensions;
+    // Offsets of the odd rows in xy and of the odd columns in zw, in tile coordinates
+    uniform vec4 stagger;
 };
 
 // Quad transform.
@@ -14,6 +16,8 @@ layout(location = 0) in vec2 u_offset;
 layout(location = 1) in vec2 v_offset;
 layout(location = 2) in vec4 color;
 layout(location = 3) in uvec3 tile_coordinate;
+// Offset of the z level in world space, for parallax scrolling
+layout(location = 4) in vec2 layer_offset;
 
 layout(location = 0) out VertexData {
     vec2 tex_uv;
@@ -40,10 +44,12 @@ void main() {
     vec2 ddir_x = (map_transform[0] * sprite_dimensions.x).xy;
     vec2 ddir_y = (map_transform[1] * -sprite_dimensions.y).xy;
 
-    vec4 coord = vec4(float(tile_coordinate.x), -float(tile_coordinate.y), float(tile_coordinate.z), 1.0);
+    vec2 offset = float(tile_coordinate.y & 1u) * stagger.xy + float(tile_coordinate.x & 1u) * stagger.zw;
+    vec4 coord = vec4(float(tile_coordinate.x) + offset.x, -(float(tile_coordinate.y) + offset.y), float(tile_coordinate.z), 1.0);
 
     vec4 world_coordinate = map_coordinate_transform * coord;
     world_coordinate = world_coordinate * transpose(map_transform);
+    world_coordinate.xy += layer_offset;
 
     vertex.tex_uv = texture_coords(vec2(tex_u, tex_v), u_offset, v_offset);
     vertex.color = color;
@@ -0,0 +1,288 @@
+//! Animation of the sprites of tiles, e.g. for water or torches.
+
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::{Read, System, Write},
+    timing::Time,
+};
+use amethyst_rendy::SpriteSheet;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+static NEXT_ANIMATIONS_VERSION: AtomicU64 = AtomicU64::new(1);
+
+// Versions are unique across the resources, so that replacing the resource changes the version.
+fn next_animations_version() -> u64 {
+    NEXT_ANIMATIONS_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Looping sequence of sprites replacing a sprite of the tiles drawn by `DrawTiles2D`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TileAnimation {
+    /// Sprite numbers of the frames, in the order they are played.
+    pub frames: Vec<usize>,
+    /// Duration of each frame in seconds, in the order of `frames`.
+    pub durations: Vec<f32>,
+    #[serde(skip)]
+    frame: usize,
+    #[serde(skip)]
+    elapsed: f32,
+}
+
+impl TileAnimation {
+    /// Create an animation of the given sprite numbers, played at `fps` frames per second.
+    #[must_use]
+    pub fn new(frames: Vec<usize>, fps: f32) -> Self {
+        let durations = vec![1.0 / fps; frames.len()];
+        Self::with_durations(frames, durations)
+    }
+
+    /// Create an animation of the given sprite numbers, with the duration of each frame in
+    /// seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't a duration for every frame.
+    #[must_use]
+    pub fn with_durations(frames: Vec<usize>, durations: Vec<f32>) -> Self {
+        assert_eq!(
+            frames.len(),
+            durations.len(),
+            "Every frame of a tile animation needs a duration"
+        );
+        Self {
+            frames,
+            durations,
+            frame: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Returns the sprite number of the current frame, or `None` if the animation has no frames.
+    #[must_use]
+    pub fn sprite_number(&self) -> Option<usize> {
+        self.frames.get(self.frame).copied()
+    }
+
+    /// Restarts the animation from its first frame.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+    }
+
+    /// Advances the animation by the given number of seconds, returning whether the frame changed.
+    pub fn update(&mut self, delta_seconds: f32) -> bool {
+        let total = self.durations.iter().sum::<f32>();
+        if total <= 0.0 {
+            return false;
+        }
+        let frame = self.frame;
+        // Skip the whole loops at once after long frames.
+        self.elapsed = (self.elapsed + delta_seconds) % total;
+        while self.elapsed >= self.durations[self.frame] {
+            self.elapsed -= self.durations[self.frame];
+            self.frame = (self.frame + 1) % self.frames.len();
+        }
+        self.frame != frame
+    }
+}
+
+/// Resource holding the animations of the sprites of tiles, by sprite sheet and sprite number.
+///
+/// Tiles whose `Tile::sprite` returns an animated sprite are drawn with the current frame of the
+/// animation instead, so every tile showing the sprite plays the animation in sync without
+/// changing the `TileMap`. The animations are advanced by the `TileAnimationSystem`, which the
+/// `RenderTiles2D` plugin adds.
+#[derive(Debug, Default)]
+pub struct TileAnimations {
+    animations: HashMap<(Handle<SpriteSheet>, usize), TileAnimation>,
+    version: u64,
+}
+
+impl TileAnimations {
+    /// Animates a sprite of a sprite sheet, returning its previous animation.
+    pub fn insert(
+        &mut self,
+        sprite_sheet: &Handle<SpriteSheet>,
+        sprite_number: usize,
+        animation: TileAnimation,
+    ) -> Option<TileAnimation> {
+        self.version = next_animations_version();
+        self.animations
+            .insert((sprite_sheet.clone(), sprite_number), animation)
+    }
+
+    /// Stops animating a sprite of a sprite sheet, returning its animation.
+    pub fn remove(
+        &mut self,
+        sprite_sheet: &Handle<SpriteSheet>,
+        sprite_number: usize,
+    ) -> Option<TileAnimation> {
+        self.version = next_animations_version();
+        self.animations
+            .remove(&(sprite_sheet.clone(), sprite_number))
+    }
+
+    /// Returns the animation of a sprite of a sprite sheet.
+    #[must_use]
+    pub fn get(
+        &self,
+        sprite_sheet: &Handle<SpriteSheet>,
+        sprite_number: usize,
+    ) -> Option<&TileAnimation> {
+        self.animations.get(&(sprite_sheet.clone(), sprite_number))
+    }
+
+    /// Returns the sprite number drawn in place of a sprite of a sprite sheet, which is the
+    /// sprite itself if it isn't animated.
+    #[must_use]
+    pub fn sprite_number(&self, sprite_sheet: &Handle<SpriteSheet>, sprite_number: usize) -> usize {
+        self.get(sprite_sheet, sprite_number)
+            .and_then(TileAnimation::sprite_number)
+            .unwrap_or(sprite_number)
+    }
+
+    /// Advances every animation by the given number of seconds.
+    pub fn update(&mut self, delta_seconds: f32) {
+        let mut changed = false;
+        for animation in self.animations.values_mut() {
+            changed |= animation.update(delta_seconds);
+        }
+        if changed {
+            self.version = next_animations_version();
+        }
+    }
+
+    /// Versioning of the animations, changing whenever a sprite is drawn with another frame.
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+/// Marks that the `TileAnimationSystem` was added, as several `RenderTiles2D` plugins can be used.
+#[derive(Debug, Default)]
+pub(crate) struct TileAnimationSystemAdded;
+
+/// Advances the `TileAnimations` by the duration of the frame.
+///
+/// Added by the `RenderTiles2D` plugin.
+#[derive(Debug, Default)]
+pub struct TileAnimationSystem;
+
+impl TileAnimationSystem {
+    /// Returns a new tile animation system
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<'a> System<'a> for TileAnimationSystem {
+    type SystemData = (Read<'a, Time>, Write<'a, TileAnimations>);
+
+    fn run(&mut self, (time, mut animations): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("tile_animation_system");
+
+        animations.update(time.delta_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::ecs::{RunNow, World, WorldExt};
+    use amethyst_rendy::{formats::texture::TextureGenerator, Texture};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn play(animation: &mut TileAnimation, delta_seconds: f32, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                animation.update(delta_seconds);
+                animation.sprite_number().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn animations_loop_through_frames() {
+        let mut animation = TileAnimation::with_durations(vec![4, 5, 6], vec![0.25, 0.5, 0.25]);
+        assert_eq!(animation.sprite_number(), Some(4));
+        assert_eq!(
+            play(&mut animation, 0.125, 9),
+            vec![4, 5, 5, 5, 5, 6, 6, 4, 4]
+        );
+
+        animation.restart();
+        // A long frame skips the whole loops.
+        assert_eq!(play(&mut animation, 2.375, 1), vec![5]);
+        assert!(TileAnimation::new(Vec::new(), 10.0)
+            .sprite_number()
+            .is_none());
+    }
+
+    #[test]
+    fn system_animates_sprites_of_a_sheet() {
+        let mut world = World::new();
+        let pool = Arc::new(ThreadPoolBuilder::new().build().expect("Invalid config"));
+        let loader = Loader::new("/examples/assets", pool);
+        let texture = loader.load_from_data(
+            TextureGenerator::Srgba(1.0, 1.0, 1.0, 1.0).data(),
+            (),
+            &AssetStorage::<Texture>::default(),
+        );
+        let sheets = AssetStorage::<SpriteSheet>::default();
+        let sheet = loader.load_from_data(
+            SpriteSheet {
+                texture: texture.clone(),
+                sprites: Vec::new(),
+                durations: Vec::new(),
+            },
+            (),
+            &sheets,
+        );
+        let other_sheet = loader.load_from_data(
+            SpriteSheet {
+                texture,
+                sprites: Vec::new(),
+                durations: Vec::new(),
+            },
+            (),
+            &sheets,
+        );
+
+        let mut system = TileAnimationSystem::new();
+        RunNow::setup(&mut system, &mut world);
+        world.write_resource::<TileAnimations>().insert(
+            &sheet,
+            2,
+            TileAnimation::new(vec![2, 3], 10.0),
+        );
+        let mut time = Time::default();
+        time.set_delta_seconds(0.1);
+        world.insert(time);
+        system.run_now(&world);
+
+        let animations = world.read_resource::<TileAnimations>();
+        assert_eq!(animations.sprite_number(&sheet, 2), 3);
+        assert_eq!(animations.sprite_number(&sheet, 1), 1);
+        assert_eq!(animations.sprite_number(&other_sheet, 2), 2);
+        let version = animations.version();
+        drop(animations);
+
+        // The version only changes with the frames.
+        world.write_resource::<Time>().set_delta_seconds(0.05);
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<TileAnimations>().version(), version);
+        system.run_now(&world);
+        assert_ne!(world.read_resource::<TileAnimations>().version(), version);
+    }
+}
@@ -0,0 +1,415 @@
+//! Rule based auto-tiling, choosing the sprites of tiles from the terrains of their neighbors.
+//!
+//! The terrain of each tile is an integer id stored in a data layer of the `TileMap`, `terrain`
+//! by default. Each tile with a terrain gets the sprite its `TerrainRule` selects for the mask of
+//! its neighbors of the same terrain, so painting terrains draws their edges and corners.
+//!
+//! Masks hold a bit for each neighbor in the same z level, clockwise from the top in tile
+//! coordinates. Square-like projections use the `NORTH` to `NORTH_WEST` bits below, where `NORTH`
+//! is the tile on the row above and, for staggered isometric maps, the tile up and to the right.
+//! Hexagons use the lowest six bits, starting from the tile to the right for pointy tops and from
+//! the tile above for flat tops. Neighbors outside of the map count as the same terrain, so maps
+//! don't show the edges of the terrains along their borders.
+
+use crate::{CoordinateEncoder, Map, MapProjection, MapStorage, Tile, TileMap};
+use amethyst_core::math::Point3;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::TryFrom};
+
+/// Bit of the neighbor above.
+pub const NORTH: u8 = 1;
+/// Bit of the neighbor at the top right corner.
+pub const NORTH_EAST: u8 = 1 << 1;
+/// Bit of the neighbor to the right.
+pub const EAST: u8 = 1 << 2;
+/// Bit of the neighbor at the bottom right corner.
+pub const SOUTH_EAST: u8 = 1 << 3;
+/// Bit of the neighbor below.
+pub const SOUTH: u8 = 1 << 4;
+/// Bit of the neighbor at the bottom left corner.
+pub const SOUTH_WEST: u8 = 1 << 5;
+/// Bit of the neighbor to the left.
+pub const WEST: u8 = 1 << 6;
+/// Bit of the neighbor at the top left corner.
+pub const NORTH_WEST: u8 = 1 << 7;
+
+/// Bits of the edge neighbors of square-like projections.
+const EDGES: u8 = NORTH | EAST | SOUTH | WEST;
+
+/// `Tile`s whose sprite can be chosen by `AutoTileRules`.
+pub trait AutoTile: Tile {
+    /// Sets the sprite of the tile.
+    fn set_sprite(&mut self, sprite: Option<usize>);
+}
+
+/// Which neighbors are part of the masks of a terrain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AutoTileMode {
+    /// Only the neighbors sharing an edge, for the 16 sprites of simple edge tiling.
+    Edges,
+    /// The corner neighbors too, for the 47 sprites of blob tiling. Corners are only part of the
+    /// mask when both edges next to them are too, as they can't be seen otherwise.
+    #[default]
+    Blob,
+}
+
+/// The sprites of a terrain by the mask of its neighbors.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerrainRule {
+    /// Which neighbors are part of the masks.
+    pub mode: AutoTileMode,
+    /// Sprite of the tiles whose mask has no sprite.
+    pub default_sprite: usize,
+    /// Sprites by the masks of the neighbors.
+    pub sprites: HashMap<u8, usize>,
+}
+
+impl TerrainRule {
+    /// Create a rule drawing every tile with `default_sprite` until sprites are added.
+    #[must_use]
+    pub fn new(mode: AutoTileMode, default_sprite: usize) -> Self {
+        Self {
+            mode,
+            default_sprite,
+            sprites: HashMap::default(),
+        }
+    }
+
+    /// Draw the tiles whose neighbors match `mask` with `sprite`.
+    #[must_use]
+    pub fn with_sprite(mut self, mask: u8, sprite: usize) -> Self {
+        self.sprites.insert(mask, sprite);
+        self
+    }
+
+    /// Returns the sprite for a mask. Masks without a sprite fall back to the sprite of their edges
+    /// on square-like maps, so blob tile sets can leave out corner variations, and then to the
+    /// default sprite.
+    #[must_use]
+    pub fn sprite(&self, mask: u8) -> usize {
+        self.sprites
+            .get(&mask)
+            .or_else(|| match self.mode {
+                AutoTileMode::Blob => self.sprites.get(&(mask & EDGES)),
+                AutoTileMode::Edges => None,
+            })
+            .copied()
+            .unwrap_or(self.default_sprite)
+    }
+}
+
+/// Rules choosing the sprites of the tiles of a `TileMap` from the terrains in one of its data
+/// layers.
+///
+/// Apply them to a whole map with `apply`, e.g. after importing it, and keep it up to date by
+/// changing terrains with `paint` and `erase`, which update the sprites of the neighbors too.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutoTileRules {
+    layer: String,
+    terrains: HashMap<i64, TerrainRule>,
+}
+
+impl Default for AutoTileRules {
+    fn default() -> Self {
+        Self {
+            layer: "terrain".into(),
+            terrains: HashMap::default(),
+        }
+    }
+}
+
+impl AutoTileRules {
+    /// Create rules without terrains, reading them from the `terrain` data layer.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the terrains from another data layer.
+    #[must_use]
+    pub fn with_layer(mut self, layer: impl Into<String>) -> Self {
+        self.layer = layer.into();
+        self
+    }
+
+    /// Add the rule of a terrain.
+    #[must_use]
+    pub fn with_terrain(mut self, terrain: i64, rule: TerrainRule) -> Self {
+        self.insert(terrain, rule);
+        self
+    }
+
+    /// Sets the rule of a terrain, returning its previous rule.
+    pub fn insert(&mut self, terrain: i64, rule: TerrainRule) -> Option<TerrainRule> {
+        self.terrains.insert(terrain, rule)
+    }
+
+    /// Returns the rule of a terrain.
+    #[must_use]
+    pub fn rule(&self, terrain: i64) -> Option<&TerrainRule> {
+        self.terrains.get(&terrain)
+    }
+
+    /// Returns the name of the data layer holding the terrains.
+    #[must_use]
+    pub fn layer(&self) -> &str {
+        &self.layer
+    }
+
+    /// Returns the mask of the neighbors of a tile sharing its terrain, or `None` if the tile
+    /// has no terrain with a rule.
+    #[must_use]
+    pub fn mask<T: Tile, E: CoordinateEncoder>(
+        &self,
+        map: &TileMap<T, E>,
+        coordinates: &Point3<u32>,
+    ) -> Option<u8> {
+        let terrain = map.data::<i64>(&self.layer, coordinates)?;
+        let rule = self.rule(terrain)?;
+        let (x, y) = (i64::from(coordinates.x), i64::from(coordinates.y));
+        let dimensions = map.dimensions();
+        let connected = |(x, y): (i64, i64)| {
+            if x < 0 || y < 0 || x >= i64::from(dimensions.x) || y >= i64::from(dimensions.y) {
+                return true;
+            }
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let neighbor = Point3::new(x as u32, y as u32, coordinates.z);
+            map.data::<i64>(&self.layer, &neighbor) == Some(terrain)
+        };
+        let projection = map.projection();
+        let edges = projection
+            .edge_neighbors(x, y)
+            .into_iter()
+            .map(connected)
+            .collect::<Vec<_>>();
+        let mut mask = 0;
+        match projection {
+            MapProjection::HexPointyTop | MapProjection::HexFlatTop => {
+                for (bit, _) in edges.iter().enumerate().filter(|(_, edge)| **edge) {
+                    mask |= 1 << bit;
+                }
+            }
+            _ => {
+                for (bit, _) in edges.iter().enumerate().filter(|(_, edge)| **edge) {
+                    mask |= 1 << (2 * bit);
+                }
+                if rule.mode == AutoTileMode::Blob {
+                    for (bit, corner) in projection.corner_neighbors(x, y).into_iter().enumerate() {
+                        if edges[bit] && edges[(bit + 1) % 4] && connected(corner) {
+                            mask |= 1 << (2 * bit + 1);
+                        }
+                    }
+                }
+            }
+        }
+        Some(mask)
+    }
+
+    /// Returns the sprite the rules choose for a tile, or `None` if it has no terrain with a rule.
+    #[must_use]
+    pub fn sprite<T: Tile, E: CoordinateEncoder>(
+        &self,
+        map: &TileMap<T, E>,
+        coordinates: &Point3<u32>,
+    ) -> Option<usize> {
+        let terrain = map.data::<i64>(&self.layer, coordinates)?;
+        Some(self.rule(terrain)?.sprite(self.mask(map, coordinates)?))
+    }
+
+    /// Updates the sprites of every tile with a terrain.
+    pub fn apply<T: AutoTile, E: CoordinateEncoder>(&self, map: &mut TileMap<T, E>) {
+        let tiles = map
+            .data_iter(&self.layer)
+            .map(|(coordinates, _)| coordinates)
+            .collect::<Vec<_>>();
+        for coordinates in tiles {
+            self.update_tile(map, &coordinates);
+        }
+    }
+
+    /// Sets the terrain of a tile and updates the sprites of the tile and its neighbors.
+    /// Coordinates outside of the map are ignored.
+    pub fn paint<T: AutoTile, E: CoordinateEncoder>(
+        &self,
+        map: &mut TileMap<T, E>,
+        coordinates: &Point3<u32>,
+        terrain: i64,
+    ) {
+        if map.set_data(&self.layer, coordinates, terrain).is_none()
+            && map.data::<i64>(&self.layer, coordinates) != Some(terrain)
+        {
+            return;
+        }
+        self.update(map, coordinates);
+    }
+
+    /// Removes the terrain of a tile, clearing its sprite, and updates the sprites of its
+    /// neighbors.
+    pub fn erase<T: AutoTile, E: CoordinateEncoder>(
+        &self,
+        map: &mut TileMap<T, E>,
+        coordinates: &Point3<u32>,
+    ) {
+        if map.remove_data(&self.layer, coordinates).is_some() {
+            if let Some(tile) = map.get_mut(coordinates) {
+                tile.set_sprite(None);
+            }
+            self.update(map, coordinates);
+        }
+    }
+
+    /// Updates the sprites of a tile and its neighbors, e.g. after changing its terrain directly
+    /// in the data layer.
+    pub fn update<T: AutoTile, E: CoordinateEncoder>(
+        &self,
+        map: &mut TileMap<T, E>,
+        coordinates: &Point3<u32>,
+    ) {
+        let (x, y) = (i64::from(coordinates.x), i64::from(coordinates.y));
+        let projection = map.projection();
+        let neighbors = projection
+            .edge_neighbors(x, y)
+            .into_iter()
+            .chain(projection.corner_neighbors(x, y))
+            .filter_map(|(x, y)| Some((u32::try_from(x).ok()?, u32::try_from(y).ok()?)));
+        self.update_tile(map, coordinates);
+        for (x, y) in neighbors {
+            self.update_tile(map, &Point3::new(x, y, coordinates.z));
+        }
+    }
+
+    fn update_tile<T: AutoTile, E: CoordinateEncoder>(
+        &self,
+        map: &mut TileMap<T, E>,
+        coordinates: &Point3<u32>,
+    ) {
+        if let Some(sprite) = self.sprite(map, coordinates) {
+            if let Some(tile) = map.get_mut(coordinates) {
+                tile.set_sprite(Some(sprite));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MortonEncoder2D;
+    use amethyst_core::math::Vector3;
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    struct TestTile(Option<usize>);
+
+    impl Tile for TestTile {}
+
+    impl AutoTile for TestTile {
+        fn set_sprite(&mut self, sprite: Option<usize>) {
+            self.0 = sprite;
+        }
+    }
+
+    fn map(projection: MapProjection) -> TileMap<TestTile, MortonEncoder2D> {
+        TileMap::new(Vector3::new(5, 5, 1), Vector3::new(16, 16, 1), None)
+            .with_projection(projection)
+    }
+
+    fn sprite(map: &TileMap<TestTile, MortonEncoder2D>, x: u32, y: u32) -> Option<usize> {
+        map.get(&Point3::new(x, y, 0)).unwrap().0
+    }
+
+    #[test]
+    fn blob_masks_only_hold_visible_corners() {
+        let rules = AutoTileRules::new().with_terrain(1, TerrainRule::new(AutoTileMode::Blob, 0));
+        let mut map = map(MapProjection::Square);
+        for (x, y) in &[(1, 1), (2, 1), (1, 2), (2, 2), (3, 3)] {
+            map.set_data("terrain", &Point3::new(*x, *y, 0), 1);
+        }
+        assert_eq!(
+            rules.mask(&map, &Point3::new(1, 1, 0)),
+            Some(EAST | SOUTH_EAST | SOUTH)
+        );
+        // The corner at (3, 3) is hidden by the missing edges next to it.
+        assert_eq!(
+            rules.mask(&map, &Point3::new(2, 2, 0)),
+            Some(NORTH | NORTH_WEST | WEST)
+        );
+        assert_eq!(rules.mask(&map, &Point3::new(3, 3, 0)), Some(0));
+        assert_eq!(rules.mask(&map, &Point3::new(0, 0, 0)), None);
+
+        map.set_data("terrain", &Point3::new(4, 4, 0), 1);
+        assert_eq!(
+            rules.mask(&map, &Point3::new(4, 4, 0)),
+            Some(EAST | SOUTH_EAST | SOUTH),
+            "the outside of the map counts as the same terrain"
+        );
+    }
+
+    #[test]
+    fn edge_masks_ignore_corners() {
+        let rules = AutoTileRules::new()
+            .with_layer("ground")
+            .with_terrain(2, TerrainRule::new(AutoTileMode::Edges, 0));
+        let mut map = map(MapProjection::Square);
+        for (x, y) in &[(1, 1), (2, 1), (1, 2), (2, 2)] {
+            map.set_data("ground", &Point3::new(*x, *y, 0), 2);
+        }
+        assert_eq!(rules.mask(&map, &Point3::new(1, 1, 0)), Some(EAST | SOUTH));
+    }
+
+    #[test]
+    fn hexagon_masks_hold_six_edges() {
+        let rules = AutoTileRules::new().with_terrain(1, TerrainRule::new(AutoTileMode::Blob, 0));
+        let mut map = map(MapProjection::HexPointyTop);
+        for (x, y) in &[(2, 2), (3, 2), (2, 3), (1, 1)] {
+            map.set_data("terrain", &Point3::new(*x, *y, 0), 1);
+        }
+        // Right, bottom right and top left of a tile in an even row.
+        assert_eq!(rules.mask(&map, &Point3::new(2, 2, 0)), Some(0b01_0011));
+    }
+
+    #[test]
+    fn missing_sprites_fall_back_to_their_edges() {
+        let rule = TerrainRule::new(AutoTileMode::Blob, 7)
+            .with_sprite(EAST | SOUTH, 1)
+            .with_sprite(EAST | SOUTH_EAST | SOUTH, 2);
+        assert_eq!(rule.sprite(EAST | SOUTH_EAST | SOUTH), 2);
+        assert_eq!(rule.sprite(EAST | SOUTH), 1);
+        assert_eq!(rule.sprite(NORTH | EAST | SOUTH_EAST | SOUTH), 7);
+
+        let rule = TerrainRule::new(AutoTileMode::Edges, 7).with_sprite(EAST, 1);
+        assert_eq!(rule.sprite(EAST | NORTH_EAST), 7);
+    }
+
+    #[test]
+    fn painting_updates_the_neighbors() {
+        let rules = AutoTileRules::new().with_terrain(
+            1,
+            TerrainRule::new(AutoTileMode::Edges, 0)
+                .with_sprite(0, 1)
+                .with_sprite(EAST, 2)
+                .with_sprite(WEST, 3)
+                .with_sprite(EAST | WEST, 4),
+        );
+        let mut map = map(MapProjection::Square);
+        map.set_data("terrain", &Point3::new(1, 2, 0), 1);
+        rules.apply(&mut map);
+        assert_eq!(sprite(&map, 1, 2), Some(1));
+        assert_eq!(sprite(&map, 2, 2), None);
+
+        rules.paint(&mut map, &Point3::new(2, 2, 0), 1);
+        assert_eq!(sprite(&map, 1, 2), Some(2));
+        assert_eq!(sprite(&map, 2, 2), Some(3));
+
+        rules.paint(&mut map, &Point3::new(3, 2, 0), 1);
+        assert_eq!(sprite(&map, 2, 2), Some(4));
+
+        rules.erase(&mut map, &Point3::new(2, 2, 0));
+        assert_eq!(sprite(&map, 1, 2), Some(1));
+        assert_eq!(sprite(&map, 2, 2), None);
+        assert_eq!(sprite(&map, 3, 2), Some(1));
+
+        // Painting outside of the map does nothing.
+        rules.paint(&mut map, &Point3::new(9, 2, 0), 1);
+        assert_eq!(map.data::<i64>("terrain", &Point3::new(9, 2, 0)), None);
+    }
+}
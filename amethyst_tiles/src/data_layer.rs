@@ -0,0 +1,148 @@
+//! Non-rendered layers of data of the tiles of maps, e.g. collision flags or navigation costs.
+
+use fnv::FnvHashMap;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Value of a tile in a data layer of a `TileMap`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TileValue {
+    /// A flag, e.g. whether the tile blocks movement.
+    Bool(bool),
+    /// An integer, e.g. the id of a spawn point.
+    Int(i64),
+    /// A number, e.g. the cost of moving through the tile.
+    Float(f32),
+    /// A string, e.g. the kind of a marker.
+    String(String),
+}
+
+impl From<bool> for TileValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i32> for TileValue {
+    fn from(value: i32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<i64> for TileValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<u32> for TileValue {
+    fn from(value: u32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<f32> for TileValue {
+    fn from(value: f32) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<String> for TileValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for TileValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+/// Types which can be read from the `TileValue`s of a data layer with `TileMap::data`.
+pub trait FromTileValue: Sized {
+    /// Converts the value, returning `None` if it holds another type.
+    fn from_tile_value(value: &TileValue) -> Option<Self>;
+}
+
+impl FromTileValue for TileValue {
+    fn from_tile_value(value: &TileValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromTileValue for bool {
+    fn from_tile_value(value: &TileValue) -> Option<Self> {
+        match *value {
+            TileValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromTileValue for i64 {
+    fn from_tile_value(value: &TileValue) -> Option<Self> {
+        match *value {
+            TileValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromTileValue for i32 {
+    fn from_tile_value(value: &TileValue) -> Option<Self> {
+        i64::from_tile_value(value).and_then(|value| Self::try_from(value).ok())
+    }
+}
+
+impl FromTileValue for u32 {
+    fn from_tile_value(value: &TileValue) -> Option<Self> {
+        i64::from_tile_value(value).and_then(|value| Self::try_from(value).ok())
+    }
+}
+
+impl FromTileValue for f32 {
+    /// Integers are converted too, as editors often store whole numbers as integers.
+    #[allow(clippy::cast_precision_loss)]
+    fn from_tile_value(value: &TileValue) -> Option<Self> {
+        match *value {
+            TileValue::Float(value) => Some(value),
+            TileValue::Int(value) => Some(value as f32),
+            _ => None,
+        }
+    }
+}
+
+impl FromTileValue for String {
+    fn from_tile_value(value: &TileValue) -> Option<Self> {
+        match value {
+            TileValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Sparse layer of `TileValue`s, by the encoded coordinates of their tiles.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TileDataLayer {
+    pub(crate) values: FnvHashMap<u32, TileValue>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_read_as_their_type() {
+        assert_eq!(bool::from_tile_value(&true.into()), Some(true));
+        assert_eq!(bool::from_tile_value(&1.into()), None);
+        assert_eq!(u32::from_tile_value(&TileValue::Int(-1)), None);
+        assert_eq!(i32::from_tile_value(&TileValue::Int(-1)), Some(-1));
+        assert_eq!(f32::from_tile_value(&3.into()), Some(3.0));
+        assert_eq!(f32::from_tile_value(&2.5_f32.into()), Some(2.5));
+        assert_eq!(
+            String::from_tile_value(&"spawn".into()).as_deref(),
+            Some("spawn")
+        );
+    }
+}
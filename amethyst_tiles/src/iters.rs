@@ -135,7 +135,7 @@ impl Region {
     }
 }
 
-impl<'a> IntoIterator for &'a Region {
+impl IntoIterator for &Region {
     type Item = Point3<u32>;
     type IntoIter = RegionLinearIter;
 
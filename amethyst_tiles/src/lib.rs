@@ -6,24 +6,50 @@
     html_root_url = "https://docs.amethyst.rs/stable"
 )]
 #![deny(clippy::all, clippy::pedantic, missing_docs)]
-#![allow(dead_code, clippy::module_name_repetitions)]
+#![allow(
+    dead_code,
+    clippy::module_name_repetitions,
+    clippy::non_std_lazy_statics
+)]
 
+mod data_layer;
 mod map;
 mod morton;
+mod parallax;
 mod pass;
+mod picking;
+mod projection;
+mod streaming;
 
+pub mod animation;
+pub mod autotile;
 pub mod error;
 pub mod iters;
+pub mod pathfinding;
 pub mod pod;
 pub mod prefab;
+#[cfg(feature = "tiled")]
+pub mod tiled;
 
+pub use animation::{TileAnimation, TileAnimationSystem, TileAnimations};
+pub use autotile::{AutoTile, AutoTileMode, AutoTileRules, TerrainRule};
+pub use data_layer::{FromTileValue, TileValue};
 pub use error::TileOutOfBoundsError;
 pub use iters::{MortonRegion, Region};
 pub use map::{Map, MapStorage, Tile, TileMap};
 pub use morton::{MortonEncoder, MortonEncoder2D};
+pub use parallax::LayerParallax;
 pub use pass::{
-    DrawTiles2D, DrawTiles2DBounds, DrawTiles2DBoundsDefault, DrawTiles2DDesc, RenderTiles2D,
+    DrawTiles2D, DrawTiles2DBounds, DrawTiles2DBoundsCamera, DrawTiles2DBoundsDefault,
+    DrawTiles2DDesc, RenderTiles2D,
+};
+pub use pathfinding::{
+    find_path, Path, PathAlgorithm, PathEvent, PathFollower, PathFollowingSystem, PathOptions,
+    PathRequest, PathfindingSystem,
 };
+pub use picking::{TilePickEvent, TilePickingSystem, TilePickingSystemDesc};
+pub use projection::MapProjection;
+pub use streaming::{ChunkEvent, ChunkGenerator, TileChunks, TileMapStreamer};
 
 use amethyst_core::math::Vector3;
 
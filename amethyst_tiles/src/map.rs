@@ -1,6 +1,9 @@
 #![allow(unused_variables)]
 
-use crate::{CoordinateEncoder, TileOutOfBoundsError};
+use crate::{
+    data_layer::TileDataLayer, iters::Region, CoordinateEncoder, FromTileValue, LayerParallax,
+    MapProjection, TileOutOfBoundsError, TileValue,
+};
 use amethyst_assets::{Asset, Handle};
 use amethyst_core::{
     ecs::{Component, HashMapStorage, World},
@@ -8,6 +11,23 @@ use amethyst_core::{
     Transform,
 };
 use amethyst_rendy::{palette::Srgba, SpriteSheet};
+use derivative::Derivative;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Width and height in tiles of the render chunks of a `TileMap`, whose changes are tracked so
+/// that `DrawTiles2D` only rebuilds the tiles of the chunks which changed.
+pub(crate) const RENDER_CHUNK_SIZE: u32 = 16;
+
+static NEXT_RENDER_CHUNK_VERSION: AtomicU64 = AtomicU64::new(1);
+
+// Render chunk versions are unique across maps, so that a map replacing another one on an entity
+// doesn't reuse the tiles cached for the previous map.
+fn next_render_chunk_version() -> u64 {
+    NEXT_RENDER_CHUNK_VERSION.fetch_add(1, Ordering::Relaxed)
+}
 
 /// Trait providing generic rendering functionality to all tiles. Using a tilemap requires you to provide a `Tile` type,
 /// which must implement this trait to provide the `RenderPass` with the appropriate sprite and tint values.
@@ -21,6 +41,14 @@ pub trait Tile: 'static + Clone + Send + Sync + Default {
     fn tint(&self, coordinates: Point3<u32>, world: &World) -> Srgba {
         Srgba::new(1.0, 1.0, 1.0, 1.0)
     }
+
+    /// Whether the sprite and tint of this tile only change when the tile is mutably accessed
+    /// through its `TileMap`, or marked as changed with `TileMap::mark_changed`, so that they can
+    /// be cached by `DrawTiles2D`. Tiles returning `false` have their sprite and tint evaluated
+    /// every frame, e.g. when they depend on other data of the `World`.
+    fn is_cacheable(&self) -> bool {
+        true
+    }
 }
 
 /// Trait for providing access to an underlying storage type of a 3-dimensional Tile data. This is abstracted to provide
@@ -105,20 +133,36 @@ pub trait MapStorage<T: Tile> {
 /// The default encoding scheme is `MortonEncoder2D`, which allows for arbitrary X, Y and Z coordinate sizes while
 /// still spatially partitioning each z-level. For more efficient Z-order encoding, use `MortonEncoder` which requires
 /// cubic map dimensions but provides for much greater spatial efficiency.
-#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, Derivative, serde::Serialize, serde::Deserialize)]
+#[derivative(PartialEq)]
 pub struct TileMap<T: Tile, E: CoordinateEncoder = crate::MortonEncoder2D> {
     pub(crate) origin: Point3<f32>,
     pub(crate) tile_dimensions: Vector3<u32>,
     pub(crate) dimensions: Vector3<u32>,
     pub(crate) transform: Matrix4<f32>,
+    #[serde(default)]
+    pub(crate) projection: MapProjection,
+    #[serde(default)]
+    pub(crate) parallax: Vec<LayerParallax>,
 
     pub(crate) version: u64,
 
+    #[derivative(PartialEq = "ignore")]
+    #[serde(skip, default = "next_render_chunk_version")]
+    pub(crate) render_chunk_base_version: u64,
+
+    #[derivative(PartialEq = "ignore")]
+    #[serde(skip)]
+    pub(crate) render_chunk_versions: Vec<u64>,
+
     #[serde(skip)]
     pub(crate) sprite_sheet: Option<Handle<SpriteSheet>>,
 
     pub(crate) data: Vec<T>,
 
+    #[serde(default)]
+    pub(crate) data_layers: HashMap<String, TileDataLayer>,
+
     #[serde(skip)]
     pub(crate) encoder: E,
 }
@@ -145,7 +189,8 @@ impl<T: Tile, E: CoordinateEncoder> TileMap<T, E> {
         sprite_sheet: Option<Handle<SpriteSheet>>,
     ) -> Self {
         let origin = Point3::new(0.0, 0.0, 0.0);
-        let transform = create_transform(&dimensions, &tile_dimensions);
+        let projection = MapProjection::default();
+        let transform = create_transform(&dimensions, &tile_dimensions, projection);
 
         // Round the dimensions to the nearest multiplier for morton rounding
         let size = E::allocation_size(dimensions);
@@ -156,14 +201,213 @@ impl<T: Tile, E: CoordinateEncoder> TileMap<T, E> {
 
         Self {
             data,
+            data_layers: HashMap::new(),
             origin,
             dimensions,
             tile_dimensions,
             sprite_sheet,
             transform,
+            projection,
+            parallax: Vec::new(),
             encoder,
             version: 1,
+            render_chunk_base_version: next_render_chunk_version(),
+            render_chunk_versions: Vec::new(),
+        }
+    }
+
+    /// Returns the number of render chunks along each axis, render chunks being a single z level
+    /// deep.
+    pub(crate) fn render_chunks(&self) -> Vector3<u32> {
+        Vector3::new(
+            self.dimensions.x.div_ceil(RENDER_CHUNK_SIZE),
+            self.dimensions.y.div_ceil(RENDER_CHUNK_SIZE),
+            self.dimensions.z,
+        )
+    }
+
+    /// Returns the render chunk of the tile at the given coordinates.
+    pub(crate) fn render_chunk_of(coord: &Point3<u32>) -> Point3<u32> {
+        Point3::new(
+            coord.x / RENDER_CHUNK_SIZE,
+            coord.y / RENDER_CHUNK_SIZE,
+            coord.z,
+        )
+    }
+
+    /// Returns the index of a render chunk in `render_chunk_versions`.
+    pub(crate) fn render_chunk_index(&self, chunk: &Point3<u32>) -> Option<usize> {
+        let chunks = self.render_chunks();
+        if chunk.x >= chunks.x || chunk.y >= chunks.y || chunk.z >= chunks.z {
+            return None;
+        }
+        Some(((chunk.z * chunks.y + chunk.y) * chunks.x + chunk.x) as usize)
+    }
+
+    /// Returns the region of the tiles of a render chunk.
+    pub(crate) fn render_chunk_region(&self, chunk: &Point3<u32>) -> Region {
+        let min = Point3::new(
+            chunk.x * RENDER_CHUNK_SIZE,
+            chunk.y * RENDER_CHUNK_SIZE,
+            chunk.z,
+        );
+        let max = Point3::new(
+            (min.x + RENDER_CHUNK_SIZE).min(self.dimensions.x) - 1,
+            (min.y + RENDER_CHUNK_SIZE).min(self.dimensions.y) - 1,
+            chunk.z,
+        );
+        Region::new(min, max)
+    }
+
+    /// Versioning of the tiles of a render chunk, changing whenever one of its tiles is mutably
+    /// accessed.
+    pub(crate) fn render_chunk_version(&self, chunk: &Point3<u32>) -> u64 {
+        self.render_chunk_index(chunk)
+            .and_then(|index| self.render_chunk_versions.get(index))
+            .copied()
+            .unwrap_or(self.render_chunk_base_version)
+    }
+
+    fn touch_render_chunk(&mut self, coord: &Point3<u32>) {
+        if let Some(index) = self.render_chunk_index(&Self::render_chunk_of(coord)) {
+            if self.render_chunk_versions.is_empty() {
+                let chunks = self.render_chunks();
+                self.render_chunk_versions.resize(
+                    (chunks.x * chunks.y * chunks.z) as usize,
+                    self.render_chunk_base_version,
+                );
+            }
+            self.render_chunk_versions[index] = next_render_chunk_version();
+        }
+    }
+
+    fn touch_render_chunks(&mut self) {
+        self.render_chunk_base_version = next_render_chunk_version();
+        self.render_chunk_versions.clear();
+    }
+
+    /// Marks the tile at the given coordinates as changed, so that its sprite and tint are
+    /// evaluated again, e.g. after the data of the `World` they depend on changed.
+    pub fn mark_changed(&mut self, coord: &Point3<u32>) {
+        self.version += 1;
+        self.touch_render_chunk(coord);
+    }
+
+    /// Marks every tile of the map as changed, so that their sprites and tints are evaluated
+    /// again.
+    pub fn mark_all_changed(&mut self) {
+        self.version += 1;
+        self.touch_render_chunks();
+    }
+
+    /// Lay the tiles out with the given projection, e.g. for isometric or hexagonal tiles.
+    #[must_use]
+    pub fn with_projection(mut self, projection: MapProjection) -> Self {
+        self.projection = projection;
+        self.transform = create_transform(&self.dimensions, &self.tile_dimensions, projection);
+        self
+    }
+
+    /// Returns the projection of the tile coordinates of this map into the world.
+    #[must_use]
+    pub fn projection(&self) -> MapProjection {
+        self.projection
+    }
+
+    /// Returns how the z level `z` scrolls with the camera.
+    #[must_use]
+    pub fn layer_parallax(&self, z: u32) -> LayerParallax {
+        self.parallax.get(z as usize).copied().unwrap_or_default()
+    }
+
+    /// Set how the z level `z` scrolls with the camera, e.g. slower than the other levels for a
+    /// distant background.
+    ///
+    /// The parallax depends on the camera, so it isn't taken into account by `to_world` and
+    /// `to_tile`, unlike `screen_to_tile`, and `DrawTiles2DBounds` culling the tiles around the
+    /// camera should leave a margin for the shifted levels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map has no z level `z`.
+    pub fn set_layer_parallax(&mut self, z: u32, parallax: LayerParallax) {
+        assert!(z < self.dimensions.z, "The map has no z level {}", z);
+        if self.parallax.len() <= z as usize {
+            self.parallax
+                .resize_with(z as usize + 1, LayerParallax::default);
+        }
+        self.parallax[z as usize] = parallax;
+    }
+
+    /// Returns the value of a tile in a data layer, if the tile has one of type `V`.
+    ///
+    /// Data layers hold values of the tiles which aren't rendered, e.g. collision flags,
+    /// navigation costs or spawn markers, at the same coordinates as the tiles.
+    pub fn data<V: FromTileValue>(&self, layer: &str, coord: &Point3<u32>) -> Option<V> {
+        self.data_value(layer, coord).and_then(V::from_tile_value)
+    }
+
+    /// Returns the value of a tile in a data layer, whatever its type.
+    pub fn data_value(&self, layer: &str, coord: &Point3<u32>) -> Option<&TileValue> {
+        let index = self.data_index(coord)?;
+        self.data_layers.get(layer)?.values.get(&index)
+    }
+
+    /// Sets the value of a tile in a data layer, creating the layer if needed, and returns its
+    /// previous value. Coordinates outside of the map are ignored.
+    pub fn set_data(
+        &mut self,
+        layer: &str,
+        coord: &Point3<u32>,
+        value: impl Into<TileValue>,
+    ) -> Option<TileValue> {
+        let index = self.data_index(coord)?;
+        if !self.data_layers.contains_key(layer) {
+            self.data_layers
+                .insert(layer.to_owned(), TileDataLayer::default());
         }
+        self.data_layers
+            .get_mut(layer)?
+            .values
+            .insert(index, value.into())
+    }
+
+    /// Removes the value of a tile in a data layer, returning it.
+    pub fn remove_data(&mut self, layer: &str, coord: &Point3<u32>) -> Option<TileValue> {
+        let index = self.data_index(coord)?;
+        self.data_layers.get_mut(layer)?.values.remove(&index)
+    }
+
+    /// Iterates over the tiles with a value in a data layer, e.g. to find the spawn markers.
+    pub fn data_iter<'a>(
+        &'a self,
+        layer: &str,
+    ) -> impl Iterator<Item = (Point3<u32>, &'a TileValue)> + 'a {
+        self.data_layers
+            .get(layer)
+            .into_iter()
+            .flat_map(|layer| layer.values.iter())
+            .filter_map(move |(&index, value)| Some((self.decode(index)?, value)))
+    }
+
+    /// Returns the names of the data layers of this map.
+    pub fn data_layers(&self) -> impl Iterator<Item = &str> {
+        self.data_layers.keys().map(String::as_str)
+    }
+
+    /// Removes a data layer with all its values, returning whether it existed.
+    pub fn remove_data_layer(&mut self, layer: &str) -> bool {
+        self.data_layers.remove(layer).is_some()
+    }
+
+    fn data_index(&self, coord: &Point3<u32>) -> Option<u32> {
+        if coord.x >= self.dimensions.x
+            || coord.y >= self.dimensions.y
+            || coord.z >= self.dimensions.z
+        {
+            return None;
+        }
+        self.encode(coord)
     }
 }
 
@@ -190,7 +434,7 @@ impl<T: Tile, E: CoordinateEncoder> Map for TileMap<T, E> {
 
     #[inline]
     fn to_world(&self, coord: &Point3<u32>, map_transform: Option<&Transform>) -> Vector3<f32> {
-        to_world(&self.transform, coord, map_transform)
+        to_world(&self.transform, self.projection, coord, map_transform)
     }
 
     #[inline]
@@ -200,7 +444,13 @@ impl<T: Tile, E: CoordinateEncoder> Map for TileMap<T, E> {
         coord: &Vector3<f32>,
         map_transform: Option<&Transform>,
     ) -> Result<Point3<u32>, TileOutOfBoundsError> {
-        to_tile(&self.transform, coord, self.dimensions(), map_transform)
+        to_tile(
+            &self.transform,
+            self.projection,
+            coord,
+            self.dimensions(),
+            map_transform,
+        )
     }
 
     #[inline]
@@ -237,7 +487,9 @@ impl<T: Tile, E: CoordinateEncoder> MapStorage<T> for TileMap<T, E> {
 
     #[inline]
     fn get_mut(&mut self, coord: &Point3<u32>) -> Option<&mut T> {
-        self.get_raw_mut(self.encode(coord)?)
+        self.version += 1;
+        self.touch_render_chunk(coord);
+        self.get_raw_mut_nochange(self.encode(coord)?)
     }
 
     #[inline]
@@ -259,7 +511,10 @@ impl<T: Tile, E: CoordinateEncoder> MapStorage<T> for TileMap<T, E> {
 
     #[inline]
     fn get_raw_mut(&mut self, coord: u32) -> Option<&mut T> {
+        // Decoding the coordinates panics with the default encoder of a deserialized map, so every
+        // render chunk is marked as changed instead of the one of the tile.
         self.version += 1;
+        self.touch_render_chunks();
         self.data.get_mut(coord as usize)
     }
 
@@ -270,38 +525,50 @@ impl<T: Tile, E: CoordinateEncoder> MapStorage<T> for TileMap<T, E> {
 }
 
 #[allow(clippy::cast_precision_loss)]
-fn create_transform(map_dimensions: &Vector3<u32>, tile_dimensions: &Vector3<u32>) -> Matrix4<f32> {
+pub(crate) fn create_transform(
+    map_dimensions: &Vector3<u32>,
+    tile_dimensions: &Vector3<u32>,
+    projection: MapProjection,
+) -> Matrix4<f32> {
     let tile_dimensions = Vector3::new(
         tile_dimensions.x as f32,
         tile_dimensions.y as f32,
         tile_dimensions.z as f32,
     );
 
-    let half_dimensions = Vector3::new(
-        -1.0 * (map_dimensions.x as f32 / 2.0),
-        map_dimensions.y as f32 / 2.0,
+    let basis = projection.basis();
+    let half_dimensions = -basis.transform_vector(&Vector3::new(
+        map_dimensions.x as f32 / 2.0,
+        -(map_dimensions.y as f32 / 2.0),
         0.0,
-    );
+    ));
 
-    Matrix4::new_translation(&half_dimensions).append_nonuniform_scaling(&tile_dimensions)
+    Matrix4::new_translation(&half_dimensions).append_nonuniform_scaling(&tile_dimensions) * basis
 }
 
 #[allow(clippy::cast_precision_loss)]
 fn to_world(
     transform: &Matrix4<f32>,
+    projection: MapProjection,
     coord: &Point3<u32>,
     map_transform: Option<&Transform>,
 ) -> Vector3<f32> {
-    let coord_f = Point3::new(coord.x as f32, -1.0 * coord.y as f32, coord.z as f32);
+    let coord_f =
+        projection.staggered_coordinates(i64::from(coord.x), i64::from(coord.y), coord.z as f32);
     let point = transform.transform_point(&coord_f);
     map_transform.map_or(point.coords, |map_trans| {
         map_trans.global_matrix().transform_point(&point).coords
     })
 }
 
-#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+#[allow(
+    clippy::cast_sign_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss
+)]
 fn to_tile(
     transform: &Matrix4<f32>,
+    projection: MapProjection,
     coord: &Vector3<f32>,
     max_dimensions: &Vector3<u32>,
     map_transform: Option<&Transform>,
@@ -317,8 +584,9 @@ fn to_tile(
         .transform_point(&point)
         .coords;
 
-    inverse.x = inverse.x.round();
-    inverse.y = inverse.y.round() * -1.0;
+    let (x, y) = projection.nearest_tile(&Point3::from(inverse));
+    inverse.x = x as f32;
+    inverse.y = y as f32;
     inverse.z = inverse.z.floor();
 
     if inverse.x < 0.0
@@ -377,7 +645,7 @@ mod tests {
         impl<E: CoordinateEncoder> UnsafeWrapper<E> {
             pub fn new(map: &mut TileMap<TestTile, E>) -> Self {
                 Self {
-                    ptr: map as *mut TileMap<TestTile, E>,
+                    ptr: std::ptr::from_mut(map),
                 }
             }
             pub fn get(&self) -> &TileMap<TestTile, E> {
@@ -429,7 +697,7 @@ mod tests {
             Vector3::new(1, 2, 5),
         ];
 
-        test_dimensions.into_par_iter().for_each(|dimensions| {
+        test_dimensions.par_iter().for_each(|dimensions| {
             test_single_map::<MortonEncoder>(*dimensions);
             test_single_map::<MortonEncoder2D>(*dimensions);
             test_single_map::<FlatEncoder>(*dimensions);
@@ -437,22 +705,38 @@ mod tests {
     }
 
     pub fn test_coord(transform: &Matrix4<f32>, tile: Point3<u32>, world: Point3<f32>) {
-        let world_result = to_world(transform, &tile, None);
+        let world_result = to_world(transform, MapProjection::Square, &tile, None);
         assert_eq!(world_result, world.coords);
-        let tile_result =
-            to_tile(transform, &world.coords, &Vector3::new(100, 100, 100), None).unwrap();
+        let tile_result = to_tile(
+            transform,
+            MapProjection::Square,
+            &world.coords,
+            &Vector3::new(100, 100, 100),
+            None,
+        )
+        .unwrap();
         assert_eq!(tile_result, tile);
 
-        let world_reverse =
-            to_tile(transform, &world_result, &Vector3::new(100, 100, 100), None).unwrap();
+        let world_reverse = to_tile(
+            transform,
+            MapProjection::Square,
+            &world_result,
+            &Vector3::new(100, 100, 100),
+            None,
+        )
+        .unwrap();
         assert_eq!(world_reverse, tile);
-        let tile_reverse = to_world(transform, &tile_result, None);
+        let tile_reverse = to_world(transform, MapProjection::Square, &tile_result, None);
         assert_eq!(tile_reverse, world.coords);
     }
 
     #[test]
     pub fn tilemap_coord_conversions() {
-        let transform = create_transform(&Vector3::new(64, 64, 64), &Vector3::new(10, 10, 1));
+        let transform = create_transform(
+            &Vector3::new(64, 64, 64),
+            &Vector3::new(10, 10, 1),
+            MapProjection::Square,
+        );
 
         test_coord(
             &transform,
@@ -483,10 +767,11 @@ mod tests {
         world: Point3<f32>,
         map_transform: &Transform,
     ) {
-        let world_result = to_world(transform, &tile, Some(map_transform));
+        let world_result = to_world(transform, MapProjection::Square, &tile, Some(map_transform));
         assert_eq!(world_result, world.coords);
         let tile_result = to_tile(
             transform,
+            MapProjection::Square,
             &world.coords,
             &Vector3::new(100, 100, 100),
             Some(map_transform),
@@ -496,19 +781,29 @@ mod tests {
 
         let world_reverse = to_tile(
             transform,
+            MapProjection::Square,
             &world_result,
             &Vector3::new(100, 100, 100),
             Some(map_transform),
         )
         .unwrap();
         assert_eq!(world_reverse, tile);
-        let tile_reverse = to_world(transform, &tile_result, Some(map_transform));
+        let tile_reverse = to_world(
+            transform,
+            MapProjection::Square,
+            &tile_result,
+            Some(map_transform),
+        );
         assert_eq!(tile_reverse, world.coords);
     }
 
     #[test]
     pub fn tilemap_coord_conversions_with_map_transform() {
-        let transform = create_transform(&Vector3::new(64, 64, 64), &Vector3::new(10, 10, 1));
+        let transform = create_transform(
+            &Vector3::new(64, 64, 64),
+            &Vector3::new(10, 10, 1),
+            MapProjection::Square,
+        );
         let mut map_transform = Transform::default();
         map_transform.set_translation_xyz(-10.0, 10.0, 0.0);
         map_transform.copy_local_to_global();
@@ -539,4 +834,124 @@ mod tests {
             &map_transform,
         );
     }
+
+    #[test]
+    pub fn tilemap_coord_conversions_with_projections() {
+        for &projection in &[
+            MapProjection::IsometricDiamond,
+            MapProjection::IsometricStaggered,
+            MapProjection::HexPointyTop,
+            MapProjection::HexFlatTop,
+        ] {
+            let map = TileMap::<TestTile, FlatEncoder>::new(
+                Vector3::new(8, 8, 2),
+                Vector3::new(32, 16, 1),
+                None,
+            )
+            .with_projection(projection);
+            for coord in &[
+                Point3::new(0, 0, 0),
+                Point3::new(1, 0, 0),
+                Point3::new(0, 1, 1),
+                Point3::new(3, 5, 0),
+                Point3::new(7, 7, 1),
+            ] {
+                let world = map.to_world(coord, None);
+                assert_eq!(map.to_tile(&world, None).unwrap(), *coord);
+            }
+        }
+    }
+
+    #[test]
+    pub fn tilemap_data_layers() {
+        let mut map = TileMap::<TestTile, MortonEncoder2D>::new(
+            Vector3::new(5, 3, 2),
+            Vector3::new(10, 10, 1),
+            None,
+        );
+        let coord = Point3::new(4, 2, 1);
+        assert_eq!(map.set_data("solid", &coord, true), None);
+        assert_eq!(map.set_data("cost", &coord, 2.5_f32), None);
+        assert_eq!(
+            map.set_data("solid", &coord, false),
+            Some(TileValue::Bool(true))
+        );
+        assert_eq!(map.set_data("solid", &Point3::new(5, 0, 0), true), None);
+        map.set_data("spawn", &Point3::new(1, 1, 0), "player");
+
+        assert_eq!(map.data::<bool>("solid", &coord), Some(false));
+        assert_eq!(map.data::<f32>("cost", &coord), Some(2.5));
+        assert_eq!(map.data::<i64>("cost", &coord), None);
+        assert_eq!(map.data::<bool>("solid", &Point3::new(5, 0, 0)), None);
+        assert_eq!(
+            map.data_iter("spawn").collect::<Vec<_>>(),
+            vec![(Point3::new(1, 1, 0), &TileValue::from("player"))]
+        );
+        let mut layers = map.data_layers().collect::<Vec<_>>();
+        layers.sort_unstable();
+        assert_eq!(layers, vec!["cost", "solid", "spawn"]);
+
+        assert_eq!(map.remove_data("cost", &coord), Some(TileValue::Float(2.5)));
+        assert!(map.remove_data_layer("solid"));
+        assert_eq!(map.data_value("solid", &coord), None);
+    }
+
+    #[test]
+    pub fn tilemap_render_chunk_versions() {
+        let mut map = TileMap::<TestTile, MortonEncoder2D>::new(
+            Vector3::new(40, 20, 2),
+            Vector3::new(10, 10, 1),
+            None,
+        );
+        assert_eq!(map.render_chunks(), Vector3::new(3, 2, 2));
+        assert_eq!(
+            map.render_chunk_region(&Point3::new(2, 1, 1)),
+            Region::new(Point3::new(32, 16, 1), Point3::new(39, 19, 1))
+        );
+
+        let chunks = [
+            Point3::new(0, 0, 0),
+            Point3::new(2, 1, 0),
+            Point3::new(2, 1, 1),
+        ];
+        let versions = |map: &TileMap<TestTile, MortonEncoder2D>| {
+            chunks
+                .iter()
+                .map(|chunk| map.render_chunk_version(chunk))
+                .collect::<Vec<_>>()
+        };
+        let before = versions(&map);
+
+        map.get_mut(&Point3::new(35, 17, 0));
+        let after = versions(&map);
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert_eq!(before[2], after[2]);
+
+        // A new map doesn't share the versions of the chunks of another map.
+        let other = TileMap::<TestTile, MortonEncoder2D>::new(
+            Vector3::new(40, 20, 2),
+            Vector3::new(10, 10, 1),
+            None,
+        );
+        assert_ne!(versions(&other)[0], after[0]);
+
+        map.get_raw_mut(0);
+        let after = versions(&map);
+        assert!(after
+            .iter()
+            .zip(&before)
+            .all(|(version, previous)| version != previous));
+
+        map.mark_changed(&Point3::new(3, 2, 0));
+        let marked = versions(&map);
+        assert_ne!(after[0], marked[0]);
+        assert_eq!(after[1..], marked[1..]);
+
+        map.mark_all_changed();
+        assert!(versions(&map)
+            .iter()
+            .zip(&marked)
+            .all(|(version, previous)| version != previous));
+    }
 }
@@ -53,7 +53,7 @@ pub fn morton_encode_lut(x: u32, y: u32, z: u32) -> u32 {
 pub fn morton_decode_lut(morton: u32) -> (u32, u32, u32) {
     let single_coord = |morton, shift, table: &[u8]| -> u32 {
         let mut a: u32 = 0;
-        for i in 0 as u32..4 {
+        for i in 0_u32..4 {
             a |= u32::from(table[(morton >> ((i * 9) + shift) & 0x0000_01FF) as usize]) << (3 * i);
         }
 
@@ -150,12 +150,11 @@ impl CoordinateEncoder for MortonEncoder2D {
 
         #[cfg(debug_assertions)]
         {
-            let check = u32::max_value() / 3;
-            if x > check || y > check || z > check {
-                panic!(
-                    "These provided coordinates are outside of the encodable coordinate range for a u32"
-                )
-            }
+            let check = u32::MAX / 3;
+            assert!(
+                x <= check && y <= check && z <= check,
+                "These provided coordinates are outside of the encodable coordinate range for a u32"
+            );
         }
 
         let morton = (x.pdep(0x5555_5555) | y.pdep(0xAAAA_AAAA)) + (z * self.len);
@@ -243,13 +242,13 @@ mod tests {
         ];
 
         test_dimensions
-            .into_par_iter()
+            .par_iter()
             .for_each(|dimensions| test_encoder::<crate::FlatEncoder>(*dimensions));
         test_dimensions
-            .into_par_iter()
+            .par_iter()
             .for_each(|dimensions| test_encoder::<MortonEncoder>(*dimensions));
         test_dimensions
-            .into_par_iter()
+            .par_iter()
             .for_each(|dimensions| test_encoder::<MortonEncoder2D>(*dimensions));
     }
 
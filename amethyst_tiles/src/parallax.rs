@@ -0,0 +1,70 @@
+//! Scrolling of the z levels of maps at their own pace, e.g. for distant backgrounds.
+
+use amethyst_core::math::Vector2;
+use serde::{Deserialize, Serialize};
+
+/// How a z level of a `TileMap` scrolls with the camera, so that background layers can move
+/// slower than the foreground without being separate maps.
+///
+/// The layer is shifted in world space by the camera position times one minus `factor`, plus its
+/// `scroll` offset.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerParallax {
+    /// How fast the layer moves along with the world as the camera moves, along x and y. The
+    /// default 1.0 moves with the world, 0.5 moves half as fast like a distant layer, and 0.0
+    /// stays fixed on the screen.
+    pub factor: Vector2<f32>,
+    /// Offset of the layer in world units, e.g. moved over time for clouds or flowing water.
+    pub scroll: Vector2<f32>,
+}
+
+impl Default for LayerParallax {
+    fn default() -> Self {
+        Self::new(Vector2::new(1.0, 1.0))
+    }
+}
+
+impl LayerParallax {
+    /// Create a parallax moving by `factor` along with the world.
+    #[must_use]
+    pub fn new(factor: Vector2<f32>) -> Self {
+        Self {
+            factor,
+            scroll: Vector2::zeros(),
+        }
+    }
+
+    /// Offset the layer in world units.
+    #[must_use]
+    pub fn with_scroll(mut self, scroll: Vector2<f32>) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    /// Returns the offset of the layer in world space for a camera at the given position.
+    #[must_use]
+    pub fn offset(&self, camera_position: &Vector2<f32>) -> Vector2<f32> {
+        camera_position.component_mul(&(Vector2::repeat(1.0) - self.factor)) + self.scroll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_trail_the_camera() {
+        let camera = Vector2::new(100.0, -40.0);
+        assert_eq!(LayerParallax::default().offset(&camera), Vector2::zeros());
+        assert_eq!(
+            LayerParallax::new(Vector2::new(0.5, 0.0)).offset(&camera),
+            Vector2::new(50.0, -40.0)
+        );
+        assert_eq!(
+            LayerParallax::new(Vector2::new(0.0, 1.0))
+                .with_scroll(Vector2::new(2.0, 3.0))
+                .offset(&camera),
+            Vector2::new(102.0, 3.0)
+        );
+    }
+}
@@ -3,7 +3,8 @@
 
 use amethyst_core::{
     ecs::{
-        DispatcherBuilder, Entities, Join, Read, ReadExpect, ReadStorage, System, SystemData, World,
+        DispatcherBuilder, Entities, Entity, Join, Read, ReadExpect, ReadStorage, System,
+        SystemData, World,
     },
     geometry::{Plane, Ray},
     math::{self, clamp, convert, Matrix4, Point2, Point3, Vector2, Vector3, Vector4},
@@ -16,8 +17,9 @@ use amethyst_rendy::{
     batch::{GroupIterator, OneLevelBatch, OrderedTwoLevelBatch},
     bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
     camera::{ActiveCamera, Camera},
+    palette::Srgba,
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
-    pod::IntoPod,
+    pod::{IntoPod, ViewArgs},
     rendy::{
         command::{QueueId, RenderPassEncoder},
         factory::Factory,
@@ -46,9 +48,10 @@ use amethyst_rendy::{
 use amethyst_window::ScreenDimensions;
 use derivative::Derivative;
 use glsl_layout::AsStd140;
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
 use crate::{
+    animation::{TileAnimationSystem, TileAnimationSystemAdded, TileAnimations},
     iters::Region,
     map::{Map, MapStorage, Tile, TileMap},
     pod::{TileArgs, TileMapArgs},
@@ -81,6 +84,16 @@ lazy_static::lazy_static! {
 pub trait DrawTiles2DBounds: 'static + std::fmt::Debug + Send + Sync {
     /// Returns the region to render the tiles
     fn bounds<T: Tile, E: CoordinateEncoder>(map: &TileMap<T, E>, world: &World) -> Region;
+
+    /// Returns the region to render the tiles of a map with the given transform. Defaults to
+    /// `bounds`, for bounds which don't depend on where the map is.
+    fn transformed_bounds<T: Tile, E: CoordinateEncoder>(
+        map: &TileMap<T, E>,
+        map_transform: Option<&Transform>,
+        world: &World,
+    ) -> Region {
+        Self::bounds(map, world)
+    }
 }
 
 /// Default bounds that returns the entire tilemap
@@ -95,6 +108,78 @@ impl DrawTiles2DBounds for DrawTiles2DBoundsDefault {
     }
 }
 
+/// Bounds culling the tiles outside of the view of the active camera, so that only the render
+/// chunks in view are gathered and uploaded.
+///
+/// The view is padded by a tile for sprites overhanging their tile, and takes the `LayerParallax`
+/// of the z levels into account. The whole map is drawn when there's no camera, or when the
+/// camera looks along the z levels of the map.
+#[derive(Default, Debug)]
+pub struct DrawTiles2DBoundsCamera;
+impl DrawTiles2DBounds for DrawTiles2DBoundsCamera {
+    fn bounds<T: Tile, E: CoordinateEncoder>(map: &TileMap<T, E>, world: &World) -> Region {
+        Self::transformed_bounds(map, None, world)
+    }
+
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    fn transformed_bounds<T: Tile, E: CoordinateEncoder>(
+        map: &TileMap<T, E>,
+        map_transform: Option<&Transform>,
+        world: &World,
+    ) -> Region {
+        let (cameras, transforms) =
+            <(ReadStorage<'_, Camera>, ReadStorage<'_, Transform>)>::fetch(world);
+        let camera = CameraGatherer::gather_camera_entity(world)
+            .and_then(|entity| Some((cameras.get(entity)?, transforms.get(entity)?)));
+
+        // Tiles under the corners of the view on every z level, in screen coordinates from 0 to 1.
+        let view = camera.and_then(|(camera, camera_transform)| {
+            let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+            let mut min = Point2::new(i32::MAX, i32::MAX);
+            let mut max = Point2::new(i32::MIN, i32::MIN);
+            for z in 0..map.dimensions().z {
+                for &(x, y) in &corners {
+                    let point = map.screen_to_level(
+                        Point2::new(x, y),
+                        Vector2::new(1.0, 1.0),
+                        camera,
+                        camera_transform,
+                        map_transform,
+                        z,
+                    )?;
+                    let tile = match map.to_tile(&point.coords, map_transform) {
+                        Ok(tile) => Point2::new(tile.x as i32, tile.y as i32),
+                        Err(error) => error.point_dimensions.xy(),
+                    };
+                    min = Point2::new(min.x.min(tile.x), min.y.min(tile.y));
+                    max = Point2::new(max.x.max(tile.x), max.y.max(tile.y));
+                }
+            }
+            Some((min, max))
+        });
+
+        let dimensions = map.dimensions();
+        match view {
+            Some((min, max)) if dimensions.z > 0 => {
+                let clamp = |value: i32, size: u32| (value.max(0) as u32).min(size - 1);
+                Region::new(
+                    Point3::new(
+                        clamp(min.x - 1, dimensions.x),
+                        clamp(min.y - 1, dimensions.y),
+                        0,
+                    ),
+                    Point3::new(
+                        clamp(max.x + 1, dimensions.x),
+                        clamp(max.y + 1, dimensions.y),
+                        dimensions.z - 1,
+                    ),
+                )
+            }
+            _ => DrawTiles2DBoundsDefault::bounds(map, world),
+        }
+    }
+}
+
 /// Draw opaque tilemap without lighting.
 #[derive(Clone, PartialEq, Derivative)]
 #[derivative(Default(bound = ""), Debug(bound = ""))]
@@ -103,10 +188,20 @@ pub struct DrawTiles2DDesc<
     E: CoordinateEncoder,
     Z: DrawTiles2DBounds = DrawTiles2DBoundsDefault,
 > {
+    samples: hal::image::NumSamples,
     #[derivative(Debug = "ignore")]
     _marker: PhantomData<(T, E, Z)>,
 }
 
+impl<T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> DrawTiles2DDesc<T, E, Z> {
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    #[must_use]
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
 impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderGroupDesc<B, World>
     for DrawTiles2DDesc<T, E, Z>
 {
@@ -135,6 +230,7 @@ impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderGrou
             subpass,
             framebuffer_width,
             framebuffer_height,
+            self.samples,
             vec![env.raw_layout(), textures.raw_layout()],
         )?;
 
@@ -145,7 +241,11 @@ impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderGrou
             vertex,
             env: vec![env],
             sprites: Default::default(),
-            _marker: PhantomData::default(),
+            maps: HashMap::new(),
+            batch: Vec::new(),
+            batch_version: 0,
+            written: Vec::new(),
+            _marker: PhantomData,
             change: Default::default(),
         }))
     }
@@ -155,9 +255,18 @@ impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderGrou
 ///
 /// Notes on use:
 /// - Due to the use of transparency and Z-order, the `TileMap` entity must be viewed from a Z-up perspective
-/// for  transparency to occur correctly. If viewed from "underneath", transparency ordering issues will occur.
+///   for transparency to occur correctly. If viewed from "underneath", transparency ordering issues will occur.
 ///
 /// In shorter terms, this means that the camera must "Look Down" at the tiles.
+///
+/// Sprites animated by the `TileAnimations` resource are drawn with the current frame of their
+/// animation.
+///
+/// The sprites and tints of the tiles are cached by render chunks of 16 x 16 tiles, which are only
+/// rebuilt once one of their tiles is mutably accessed through the `TileMap`, or marked as changed
+/// with `TileMap::mark_changed`. The chunks holding a tile whose `Tile::is_cacheable` is `false`
+/// are rebuilt every frame instead. The vertex data of the tiles is only uploaded again when they,
+/// their animations or the parallax of their z levels changed.
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""))]
 pub struct DrawTiles2D<
@@ -171,6 +280,12 @@ pub struct DrawTiles2D<
     textures: TextureSub<B>,
     vertex: DynamicVertexBuffer<B, TileArgs>,
     sprites: OrderedTwoLevelBatch<TextureId, usize, TileArgs>,
+    maps: HashMap<Entity, CachedTileMap>,
+    /// Maps drawn in the last frame, with their texture and the version of their vertex data.
+    batch: Vec<(Entity, TextureId, u64)>,
+    batch_version: u64,
+    /// Version of the vertex data written to the buffer of each frame in flight.
+    written: Vec<Option<u64>>,
     change: util::ChangeDetection,
 
     env: Vec<DynamicUniform<B, TileMapArgs>>,
@@ -182,7 +297,6 @@ pub struct DrawTiles2D<
 impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderGroup<B, World>
     for DrawTiles2D<B, T, E, Z>
 {
-    #[allow(clippy::cast_precision_loss)]
     fn prepare(
         &mut self,
         factory: &Factory<B>,
@@ -195,97 +309,105 @@ impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderGrou
         profile_scope!("prepare");
 
         let mut changed = false;
-        let (sprite_sheet_storage, tex_storage, hiddens, tile_maps, transforms) =
-            <(
-                Read<'_, AssetStorage<SpriteSheet>>,
-                Read<'_, AssetStorage<Texture>>,
-                ReadStorage<'_, Hidden>,
-                ReadStorage<'_, TileMap<T, E>>,
-                ReadStorage<'_, Transform>,
-            )>::fetch(world);
+        let (
+            entities,
+            sprite_sheet_storage,
+            tex_storage,
+            hiddens,
+            tile_maps,
+            transforms,
+            animations,
+        ) = <(
+            Entities<'_>,
+            Read<'_, AssetStorage<SpriteSheet>>,
+            Read<'_, AssetStorage<Texture>>,
+            ReadStorage<'_, Hidden>,
+            ReadStorage<'_, TileMap<T, E>>,
+            ReadStorage<'_, Transform>,
+            Option<Read<'_, TileAnimations>>,
+        )>::fetch(world);
 
         let sprites_ref = &mut self.sprites;
         let textures_ref = &mut self.textures;
+        let maps_ref = &mut self.maps;
 
         sprites_ref.swap_clear();
 
-        let CameraGatherer { projview, .. } = CameraGatherer::gather(world);
+        let CameraGatherer {
+            camera_position,
+            projview,
+        } = CameraGatherer::gather(world);
+        let camera_position: &[f32; 3] = camera_position.as_ref();
+        let camera_position = Vector2::new(camera_position[0], camera_position[1]);
 
         let mut tilemap_args = vec![];
+        let mut batch = vec![];
+
+        let visible_maps = (&entities, &tile_maps, !&hiddens, transforms.maybe())
+            .join()
+            .filter_map(|(entity, tile_map, (), transform)| {
+                let handle = tile_map.sprite_sheet.as_ref()?;
+                let sprite_sheet = sprite_sheet_storage
+                    .get(handle)
+                    .filter(|sheet| tex_storage.contains(&sheet.texture))?;
+                let (tex_id, this_changed) = textures_ref.insert(
+                    factory,
+                    world,
+                    &sprite_sheet.texture,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                )?;
+                changed = changed || this_changed;
+                Some((entity, tile_map, transform, handle, sprite_sheet, tex_id))
+            });
 
-        for (tile_map, _, transform) in (&tile_maps, !&hiddens, transforms.maybe()).join() {
-            let maybe_sheet = tile_map
-                .sprite_sheet
-                .as_ref()
-                .and_then(|handle| sprite_sheet_storage.get(handle))
-                .filter(|sheet| tex_storage.contains(&sheet.texture));
-
-            let sprite_sheet = match maybe_sheet {
-                Some(sheet) => sheet,
-                None => continue,
-            };
-
+        for (entity, tile_map, transform, handle, sprite_sheet, tex_id) in visible_maps {
             let tilemap_args_index = tilemap_args.len();
-            let map_coordinate_transform: [[f32; 4]; 4] = (*tile_map.transform()).into();
-            let map_transform: [[f32; 4]; 4] = transform.map_or_else(
-                || Matrix4::identity().into(),
-                |transform| (*transform.global_matrix()).into(),
-            );
-
-            tilemap_args.push(TileMapArgs {
-                proj: projview.proj,
-                view: projview.view,
-                map_coordinate_transform: map_coordinate_transform.into(),
-                map_transform: map_transform.into(),
-                sprite_dimensions: [
-                    tile_map.tile_dimensions().x as f32,
-                    tile_map.tile_dimensions().y as f32,
-                ]
-                .into(),
-            });
+            tilemap_args.push(tile_map_args(tile_map, transform, &projview));
 
-            compute_region::<T, E, Z>(&tile_map, &world)
-                .iter()
-                .filter_map(|coord| {
-                    let tile = tile_map.get(&coord).unwrap();
-                    if let Some(sprite_number) = tile.sprite(coord, world) {
-                        let (batch_data, texture) = TileArgs::from_data(
-                            &tex_storage,
-                            &sprite_sheet,
-                            sprite_number,
-                            Some(&TintComponent(tile.tint(coord, world))),
-                            &coord,
-                        )?;
-
-                        let (tex_id, this_changed) = textures_ref.insert(
-                            factory,
-                            world,
-                            texture,
-                            hal::image::Layout::ShaderReadOnlyOptimal,
-                        )?;
-                        changed = changed || this_changed;
-
-                        return Some((tex_id, batch_data));
-                    }
-                    None
+            let layer_offsets = (0..tile_map.dimensions().z)
+                .map(|z| {
+                    let offset = tile_map.layer_parallax(z).offset(&camera_position);
+                    [offset.x, offset.y]
                 })
-                .for_each_group(|tex_id, batch_data| {
-                    sprites_ref.insert(tex_id, tilemap_args_index, batch_data.drain(..))
-                });
+                .collect::<Vec<_>>();
+
+            let region = compute_region::<T, E, Z>(tile_map, transform, world);
+            let cached = maps_ref.entry(entity).or_default();
+            cached.update(tile_map, region, world);
+            let args = cached.args(handle, sprite_sheet, animations.as_deref(), layer_offsets);
+            if !args.is_empty() {
+                sprites_ref.insert(tex_id, tilemap_args_index, args.iter().copied());
+            }
+            batch.push((entity, tex_id, cached.args_version));
         }
 
+        // Forget the tiles of the maps which were removed.
+        maps_ref.retain(|entity, _| tile_maps.contains(*entity));
+
         self.textures.maintain(factory, world);
         changed = changed || self.sprites.changed();
 
+        // The vertex data only changes with the tiles of the maps drawn, and their order.
+        if batch != self.batch {
+            self.batch = batch;
+            self.batch_version += 1;
+        }
+
         {
             #[cfg(feature = "profiler")]
             profile_scope!("write");
-            self.vertex.write(
-                factory,
-                index,
-                self.sprites.count() as u64,
-                Some(self.sprites.data()),
-            );
+            if self.written.len() <= index {
+                self.written.resize(index + 1, None);
+            }
+            if self.written[index] != Some(self.batch_version) {
+                self.vertex.write(
+                    factory,
+                    index,
+                    self.sprites.count() as u64,
+                    Some(self.sprites.data()),
+                );
+                self.written[index] = Some(self.batch_version);
+            }
 
             // grow tilemap_args cache if necessary, or shrink it
             if self.env.len() < tilemap_args.len() || self.env.len() <= tilemap_args.len() / 2 {
@@ -342,22 +464,216 @@ impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderGrou
     }
 }
 
+/// Sprite and tint of a tile, as returned by its `Tile` implementation.
+#[derive(Clone, Copy, Debug)]
+struct CachedTile {
+    coord: Point3<u32>,
+    sprite_number: usize,
+    tint: Srgba,
+}
+
+/// Tiles of a render chunk with a sprite, cached until the chunk changes.
+#[derive(Debug, Default)]
+struct CachedChunk {
+    version: u64,
+    tiles: Vec<CachedTile>,
+    /// Whether one of the tiles isn't cacheable, so that the chunk is rebuilt every frame.
+    uncacheable: bool,
+}
+
+impl CachedChunk {
+    /// Evaluates the sprites and tints of the tiles of the chunk again.
+    fn rebuild<T: Tile, E: CoordinateEncoder>(
+        &mut self,
+        tile_map: &TileMap<T, E>,
+        chunk: &Point3<u32>,
+        world: &World,
+    ) {
+        self.tiles.clear();
+        self.uncacheable = false;
+        for coord in &tile_map.render_chunk_region(chunk) {
+            if let Some(tile) = tile_map.get(&coord) {
+                self.uncacheable = self.uncacheable || !tile.is_cacheable();
+                if let Some(sprite_number) = tile.sprite(coord, world) {
+                    self.tiles.push(CachedTile {
+                        coord,
+                        sprite_number,
+                        tint: tile.tint(coord, world),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Sprite sheet and animations the vertex data of a map was built with.
+#[derive(Debug, PartialEq)]
+struct CachedArgsSource {
+    sprite_sheet: u32,
+    animations: u64,
+}
+
+/// Tiles of a map cached by `DrawTiles2D`.
+#[derive(Debug, Default)]
+struct CachedTileMap {
+    chunks: Vec<CachedChunk>,
+    /// Region the visible tiles were gathered from.
+    region: Option<Region>,
+    /// Tiles of the region, in draw order.
+    visible: Vec<CachedTile>,
+    /// Vertex data of the visible tiles, and what it was built with. The source is reset when the
+    /// visible tiles change.
+    args: Vec<TileArgs>,
+    args_source: Option<CachedArgsSource>,
+    layer_offsets: Vec<[f32; 2]>,
+    /// Version of `args`, changing whenever they're modified.
+    args_version: u64,
+}
+
+impl CachedTileMap {
+    /// Rebuilds the render chunks of the region whose tiles changed. The visible tiles are only
+    /// gathered and sorted again when the region or one of its chunks changed.
+    fn update<T: Tile, E: CoordinateEncoder>(
+        &mut self,
+        tile_map: &TileMap<T, E>,
+        region: Region,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("update_chunks");
+
+        let chunks = tile_map.render_chunks();
+        self.chunks.resize_with(
+            (chunks.x * chunks.y * chunks.z) as usize,
+            CachedChunk::default,
+        );
+        let chunk_region = Region::new(
+            TileMap::<T, E>::render_chunk_of(&region.min),
+            TileMap::<T, E>::render_chunk_of(&region.max),
+        );
+
+        let mut changed = self.region != Some(region);
+        for chunk in &chunk_region {
+            let version = tile_map.render_chunk_version(&chunk);
+            if let Some(index) = tile_map.render_chunk_index(&chunk) {
+                let cached = &mut self.chunks[index];
+                if cached.version != version || cached.uncacheable {
+                    cached.version = version;
+                    cached.rebuild(tile_map, &chunk, world);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            self.region = Some(region);
+            self.args_source = None;
+            self.visible.clear();
+            for chunk in &chunk_region {
+                if let Some(index) = tile_map.render_chunk_index(&chunk) {
+                    self.visible.extend(
+                        self.chunks[index]
+                            .tiles
+                            .iter()
+                            .filter(|tile| region.contains(&tile.coord)),
+                    );
+                }
+            }
+            let projection = tile_map.projection();
+            self.visible
+                .sort_by_key(|tile| projection.draw_order(&tile.coord));
+        }
+    }
+
+    /// Returns the vertex data of the visible tiles, which is only built again when they or their
+    /// animations changed. Only the offsets are updated when the parallax of the z levels changed.
+    fn args(
+        &mut self,
+        handle: &Handle<SpriteSheet>,
+        sprite_sheet: &SpriteSheet,
+        animations: Option<&TileAnimations>,
+        layer_offsets: Vec<[f32; 2]>,
+    ) -> &[TileArgs] {
+        let source = CachedArgsSource {
+            sprite_sheet: handle.id(),
+            animations: animations.map_or(0, TileAnimations::version),
+        };
+        if self.args_source.as_ref() != Some(&source) {
+            #[cfg(feature = "profiler")]
+            profile_scope!("update_args");
+
+            self.args.clear();
+            self.args.extend(self.visible.iter().map(|tile| {
+                let sprite_number = animations.map_or(tile.sprite_number, |animations| {
+                    animations.sprite_number(handle, tile.sprite_number)
+                });
+                let sprite = &sprite_sheet.sprites[sprite_number];
+                TileArgs {
+                    u_offset: [sprite.tex_coords.left, sprite.tex_coords.right].into(),
+                    v_offset: [sprite.tex_coords.top, sprite.tex_coords.bottom].into(),
+                    tint: tile.tint.into_pod(),
+                    tile_coordinate: [tile.coord.x, tile.coord.y, tile.coord.z].into(),
+                    layer_offset: layer_offsets[tile.coord.z as usize].into(),
+                }
+            }));
+            self.args_source = Some(source);
+            self.layer_offsets = layer_offsets;
+            self.args_version += 1;
+        } else if self.layer_offsets != layer_offsets {
+            for (args, tile) in self.args.iter_mut().zip(&self.visible) {
+                args.layer_offset = layer_offsets[tile.coord.z as usize].into();
+            }
+            self.layer_offsets = layer_offsets;
+            self.args_version += 1;
+        }
+        &self.args
+    }
+}
+
+/// Returns the uniform arguments of a map seen from the camera.
+#[allow(clippy::cast_precision_loss)]
+fn tile_map_args<T: Tile, E: CoordinateEncoder>(
+    tile_map: &TileMap<T, E>,
+    transform: Option<&Transform>,
+    projview: &<ViewArgs as AsStd140>::Std140,
+) -> TileMapArgs {
+    let map_coordinate_transform: [[f32; 4]; 4] = (*tile_map.transform()).into();
+    let map_transform: [[f32; 4]; 4] = transform.map_or_else(
+        || Matrix4::identity().into(),
+        |transform| (*transform.global_matrix()).into(),
+    );
+
+    TileMapArgs {
+        proj: projview.proj,
+        view: projview.view,
+        map_coordinate_transform: map_coordinate_transform.into(),
+        map_transform: map_transform.into(),
+        sprite_dimensions: [
+            tile_map.tile_dimensions().x as f32,
+            tile_map.tile_dimensions().y as f32,
+        ]
+        .into(),
+        stagger: tile_map.projection().stagger().into(),
+    }
+}
+
 fn compute_region<T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds>(
     tile_map: &TileMap<T, E>,
+    map_transform: Option<&Transform>,
     world: &World,
 ) -> Region {
-    let mut region = Z::bounds(tile_map, world);
+    let mut region = Z::transformed_bounds(tile_map, map_transform, world);
     let max_value = tile_map.dimensions() - Vector3::new(1, 1, 1);
 
     region.min = Point3::new(
-        region.min.x.max(0).min(max_value.x),
-        region.min.y.max(0).min(max_value.y),
-        region.min.z.max(0).min(max_value.z),
+        region.min.x.min(max_value.x),
+        region.min.y.min(max_value.y),
+        region.min.z.min(max_value.z),
     );
     region.max = Point3::new(
-        region.max.x.max(0).min(max_value.x),
-        region.max.y.max(0).min(max_value.y),
-        region.max.z.max(0).min(max_value.z),
+        region.max.x.min(max_value.x),
+        region.max.y.min(max_value.y),
+        region.max.z.min(max_value.z),
     );
 
     region
@@ -368,6 +684,7 @@ fn build_tiles_pipeline<B: Backend>(
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
+    samples: hal::image::NumSamples,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
@@ -387,6 +704,7 @@ fn build_tiles_pipeline<B: Backend>(
                 .with_layout(&pipeline_layout)
                 .with_subpass(subpass)
                 .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_samples(samples)
                 .with_blend_targets(vec![pso::ColorBlendDesc {
                     mask: pso::ColorMask::ALL,
                     blend: Some(pso::BlendState::PREMULTIPLIED_ALPHA),
@@ -411,14 +729,9 @@ fn build_tiles_pipeline<B: Backend>(
     }
 }
 
-#[allow(
-    clippy::cast_possible_truncation,
-    clippy::cast_sign_loss,
-    clippy::cast_precision_loss,
-    clippy::similar_names
-)]
-
 /// A `RenderPlugin` for rendering a 2D Tiles entity.
+///
+/// Also adds the `TileAnimationSystem` playing the `TileAnimations` of the tiles.
 #[derive(Clone, Derivative)]
 #[derivative(Debug(bound = ""), Default(bound = ""))]
 pub struct RenderTiles2D<
@@ -449,13 +762,19 @@ type SetupData<'a, T, E> = (
 impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderPlugin<B>
     for RenderTiles2D<T, E, Z>
 {
-    fn on_build<'a, 'b>(
+    fn on_build(
         &mut self,
         world: &mut World,
-        builder: &mut DispatcherBuilder<'a, 'b>,
+        builder: &mut DispatcherBuilder<'_, '_>,
     ) -> Result<(), amethyst_error::Error> {
         SetupData::<T, E>::setup(world);
 
+        // Tile maps of every tile type share the animations, so they're only advanced once.
+        if !world.has_value::<TileAnimationSystemAdded>() {
+            world.insert(TileAnimationSystemAdded);
+            builder.add(TileAnimationSystem::new(), "tile_animation_system", &[]);
+        }
+
         Ok(())
     }
 
@@ -468,7 +787,9 @@ impl<B: Backend, T: Tile, E: CoordinateEncoder, Z: DrawTiles2DBounds> RenderPlug
         plan.extend_target(self.target, |ctx| {
             ctx.add(
                 RenderOrder::BeforeTransparent,
-                DrawTiles2DDesc::<T, E, Z>::default().builder(),
+                DrawTiles2DDesc::<T, E, Z>::default()
+                    .with_samples(ctx.samples())
+                    .builder(),
             )?;
             Ok(())
         });
@@ -0,0 +1,1047 @@
+//! Pathfinding over the tiles of maps, avoiding blocked tiles and preferring cheap ones.
+//!
+//! Paths are found with A* over the data layers of a `TileMap`: tiles flagged in the blocked
+//! layer can't be entered, and the cost layer gives the cost of entering each tile, 1.0 by
+//! default. The tiles are connected according to the `MapProjection` of the map, e.g. to their six
+//! neighbors on hexagonal maps.
+//!
+//! Paths can be found directly with `find_path`, or by adding a `PathRequest` to an entity, which
+//! the `PathfindingSystem` answers with a `Path`, optionally searching on the thread pool for
+//! long paths. Entities with a `PathFollower` are moved along their `Path` by the
+//! `PathFollowingSystem`.
+
+use crate::{CoordinateEncoder, Map, MapProjection, Tile, TileMap};
+use amethyst_core::{
+    ecs::{
+        Component, DenseVecStorage, Entities, Entity, Join, Read, ReadExpect, ReadStorage, System,
+        SystemData, World, Write, WriteStorage,
+    },
+    math::Point3,
+    shrev::EventChannel,
+    timing::Time,
+    ArcThreadPool, Transform,
+};
+use smallvec::SmallVec;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    marker::PhantomData,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Search algorithm used to find paths.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathAlgorithm {
+    /// A* search, supporting every projection and tile costs.
+    #[default]
+    AStar,
+    /// Jump point search, much faster than A* on large open maps. It requires a square grid, i.e.
+    /// a `Square` or `IsometricDiamond` projection, with diagonal moves and the same cost for every
+    /// tile, and falls back to A* otherwise.
+    JumpPoint,
+}
+
+/// Options of the search for a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathOptions {
+    /// Data layer flagging the tiles which can't be entered with `true`, `"solid"` by default.
+    pub blocked_layer: String,
+    /// Data layer holding the cost of entering each tile, `"cost"` by default. Tiles without a
+    /// cost cost 1.0, and tiles with an infinite cost can't be entered.
+    pub cost_layer: Option<String>,
+    /// Whether paths can move diagonally between tiles only touching by a corner, unless one of
+    /// the tiles along the corner is blocked. True by default.
+    pub diagonal: bool,
+    /// Search algorithm, `AStar` by default.
+    pub algorithm: PathAlgorithm,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        Self {
+            blocked_layer: "solid".into(),
+            cost_layer: Some("cost".into()),
+            diagonal: true,
+            algorithm: PathAlgorithm::default(),
+        }
+    }
+}
+
+/// Returns the cheapest path from `start` to `goal` on their z level of the map, including both,
+/// or `None` if there is no such path.
+#[must_use]
+pub fn find_path<T: Tile, E: CoordinateEncoder>(
+    map: &TileMap<T, E>,
+    start: Point3<u32>,
+    goal: Point3<u32>,
+    options: &PathOptions,
+) -> Option<Vec<Point3<u32>>> {
+    if start.z != goal.z || start.z >= map.dimensions().z {
+        return None;
+    }
+    let grid = PathGrid::new(map, start.z, options);
+    grid.find_path((start.x, start.y), (goal.x, goal.y))
+        .map(|path| {
+            path.into_iter()
+                .map(|(x, y)| Point3::new(x, y, start.z))
+                .collect()
+        })
+}
+
+/// Costs of entering the tiles of a z level, copied out of the map so that paths can be searched
+/// for on other threads.
+#[derive(Clone, Debug)]
+struct PathGrid {
+    width: u32,
+    height: u32,
+    projection: MapProjection,
+    diagonal: bool,
+    algorithm: PathAlgorithm,
+    /// Cost of entering each tile row by row, infinite for blocked tiles.
+    costs: Vec<f32>,
+    min_cost: f32,
+    uniform: bool,
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+impl PathGrid {
+    fn new<T: Tile, E: CoordinateEncoder>(
+        map: &TileMap<T, E>,
+        z: u32,
+        options: &PathOptions,
+    ) -> Self {
+        let dimensions = map.dimensions();
+        let mut costs = Vec::with_capacity(dimensions.x as usize * dimensions.y as usize);
+        for y in 0..dimensions.y {
+            for x in 0..dimensions.x {
+                let coord = Point3::new(x, y, z);
+                let cost = if map.data::<bool>(&options.blocked_layer, &coord) == Some(true) {
+                    f32::INFINITY
+                } else {
+                    options
+                        .cost_layer
+                        .as_ref()
+                        .and_then(|layer| map.data::<f32>(layer, &coord))
+                        .filter(|cost| *cost >= 0.0)
+                        .unwrap_or(1.0)
+                };
+                costs.push(cost);
+            }
+        }
+        let passable = costs.iter().copied().filter(|cost| cost.is_finite());
+        let min_cost = passable.clone().fold(f32::INFINITY, f32::min);
+        let uniform = passable.fold(min_cost, f32::max) <= min_cost;
+        Self {
+            width: dimensions.x,
+            height: dimensions.y,
+            projection: map.projection(),
+            diagonal: options.diagonal,
+            algorithm: options.algorithm,
+            costs,
+            min_cost: if min_cost.is_finite() { min_cost } else { 0.0 },
+            uniform,
+        }
+    }
+
+    /// Returns the cost of entering a tile, or `None` if it's blocked or outside of the map.
+    fn cost(&self, (x, y): (i64, i64)) -> Option<f32> {
+        if !self.inside((x, y)) {
+            return None;
+        }
+        Some(self.costs[self.index((x, y))]).filter(|cost| cost.is_finite())
+    }
+
+    fn passable(&self, tile: (i64, i64)) -> bool {
+        self.cost(tile).is_some()
+    }
+
+    fn blocked(&self, tile: (i64, i64)) -> bool {
+        self.inside(tile) && !self.passable(tile)
+    }
+
+    fn inside(&self, (x, y): (i64, i64)) -> bool {
+        x >= 0 && y >= 0 && x < i64::from(self.width) && y < i64::from(self.height)
+    }
+
+    fn index(&self, (x, y): (i64, i64)) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    fn tile(&self, index: usize) -> (i64, i64) {
+        (
+            (index % self.width as usize) as i64,
+            (index / self.width as usize) as i64,
+        )
+    }
+
+    fn edge_neighbors(&self, (x, y): (i64, i64)) -> SmallVec<[(i64, i64); 6]> {
+        self.projection.edge_neighbors(x, y)
+    }
+
+    fn corner_neighbors(&self, (x, y): (i64, i64)) -> SmallVec<[(i64, i64); 6]> {
+        self.projection.corner_neighbors(x, y)
+    }
+
+    /// Returns the position of a tile in a grid where the edge neighbors are one step apart
+    /// along the axes, and corner neighbors diagonally, or for hexagons its cube coordinates.
+    fn axial(&self, (x, y): (i64, i64)) -> (i64, i64) {
+        match self.projection {
+            MapProjection::Square | MapProjection::IsometricDiamond => (x, y),
+            MapProjection::IsometricStaggered => {
+                let s = 2 * x + (y & 1);
+                (y.midpoint(s), (y - s) / 2)
+            }
+            MapProjection::HexPointyTop => (x - (y - (y & 1)) / 2, y),
+            MapProjection::HexFlatTop => (x, y - (x - (x & 1)) / 2),
+        }
+    }
+
+    /// Returns the lowest number of steps between two tiles, each weighted by its length.
+    fn distance(&self, from: (i64, i64), to: (i64, i64)) -> f32 {
+        let (a, b) = (self.axial(from), self.axial(to));
+        let (dx, dy) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+        match self.projection {
+            MapProjection::HexPointyTop | MapProjection::HexFlatTop => {
+                ((dx + dy + (a.0 - b.0 + a.1 - b.1).abs()) / 2) as f32
+            }
+            _ if self.diagonal => {
+                let (short, long) = (dx.min(dy) as f32, dx.max(dy) as f32);
+                long - short + short * std::f32::consts::SQRT_2
+            }
+            _ => (dx + dy) as f32,
+        }
+    }
+
+    /// Returns the neighbors which can be entered from a tile, with the length of the step.
+    fn neighbors(&self, tile: (i64, i64)) -> SmallVec<[((i64, i64), f32); 12]> {
+        let edges = self.edge_neighbors(tile);
+        let mut neighbors = edges
+            .iter()
+            .filter(|neighbor| self.passable(**neighbor))
+            .map(|neighbor| (*neighbor, 1.0))
+            .collect::<SmallVec<_>>();
+        if self.diagonal {
+            for corner in self.corner_neighbors(tile) {
+                // Corners can't be cut through the blocked tiles touching both tiles, while the
+                // edges of the map can be followed.
+                if self.passable(corner)
+                    && !self
+                        .edge_neighbors(corner)
+                        .iter()
+                        .filter(|neighbor| edges.contains(neighbor))
+                        .any(|neighbor| self.blocked(*neighbor))
+                {
+                    neighbors.push((corner, std::f32::consts::SQRT_2));
+                }
+            }
+        }
+        neighbors
+    }
+
+    fn find_path(&self, start: (u32, u32), goal: (u32, u32)) -> Option<Vec<(u32, u32)>> {
+        let start = (i64::from(start.0), i64::from(start.1));
+        let goal = (i64::from(goal.0), i64::from(goal.1));
+        if !self.passable(start) || !self.passable(goal) {
+            return None;
+        }
+        let jump_points = self.algorithm == PathAlgorithm::JumpPoint
+            && self.diagonal
+            && self.uniform
+            && matches!(
+                self.projection,
+                MapProjection::Square | MapProjection::IsometricDiamond
+            );
+        let path = self.search(start, goal, |tile, parent| {
+            if jump_points {
+                self.jump_successors(tile, parent, goal)
+            } else {
+                self.neighbors(tile)
+                    .into_iter()
+                    .map(|(neighbor, length)| (neighbor, length * self.costs[self.index(neighbor)]))
+                    .collect()
+            }
+        })?;
+        let mut tiles = vec![path[0]];
+        // Jump points are joined by straight or diagonal lines of tiles.
+        for &(x, y) in &path[1..] {
+            let &(mut from_x, mut from_y) = tiles.last().unwrap();
+            while (from_x, from_y) != (x, y) {
+                from_x += (x - from_x).signum();
+                from_y += (y - from_y).signum();
+                tiles.push((from_x, from_y));
+            }
+        }
+        Some(
+            tiles
+                .into_iter()
+                .map(|(x, y)| (x as u32, y as u32))
+                .collect(),
+        )
+    }
+
+    /// Runs A* with the given successors of a tile, returning the visited tiles from the start to
+    /// the goal.
+    fn search<S>(
+        &self,
+        start: (i64, i64),
+        goal: (i64, i64),
+        successors: S,
+    ) -> Option<Vec<(i64, i64)>>
+    where
+        S: Fn((i64, i64), Option<(i64, i64)>) -> SmallVec<[((i64, i64), f32); 12]>,
+    {
+        let mut costs = vec![f32::INFINITY; self.costs.len()];
+        let mut parents = vec![usize::MAX; self.costs.len()];
+        let mut open = BinaryHeap::new();
+        let start_index = self.index(start);
+        costs[start_index] = 0.0;
+        open.push(Open {
+            estimate: self.distance(start, goal) * self.min_cost,
+            cost: 0.0,
+            index: start_index,
+        });
+        while let Some(Open { cost, index, .. }) = open.pop() {
+            if cost > costs[index] {
+                continue;
+            }
+            let tile = self.tile(index);
+            if tile == goal {
+                let mut path = vec![tile];
+                let mut index = index;
+                while parents[index] != usize::MAX {
+                    index = parents[index];
+                    path.push(self.tile(index));
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let parent = Some(parents[index])
+                .filter(|parent| *parent != usize::MAX)
+                .map(|parent| self.tile(parent));
+            for (successor, step) in successors(tile, parent) {
+                let successor_index = self.index(successor);
+                let successor_cost = cost + step;
+                if successor_cost < costs[successor_index] {
+                    costs[successor_index] = successor_cost;
+                    parents[successor_index] = index;
+                    open.push(Open {
+                        estimate: successor_cost + self.distance(successor, goal) * self.min_cost,
+                        cost: successor_cost,
+                        index: successor_index,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the jump points reached from a tile in the directions left after pruning the ones
+    /// better reached through its parent, with the cost of getting there.
+    fn jump_successors(
+        &self,
+        (x, y): (i64, i64),
+        parent: Option<(i64, i64)>,
+        goal: (i64, i64),
+    ) -> SmallVec<[((i64, i64), f32); 12]> {
+        let walkable = |tile| self.passable(tile);
+        let mut directions = SmallVec::<[(i64, i64); 8]>::new();
+        match parent {
+            None => {
+                for ((nx, ny), _) in self.neighbors((x, y)) {
+                    directions.push((nx - x, ny - y));
+                }
+            }
+            Some((px, py)) => {
+                let (dx, dy) = ((x - px).signum(), (y - py).signum());
+                if dx != 0 && dy != 0 {
+                    let vertical = walkable((x, y + dy));
+                    let horizontal = walkable((x + dx, y));
+                    if vertical {
+                        directions.push((0, dy));
+                    }
+                    if horizontal {
+                        directions.push((dx, 0));
+                    }
+                    if vertical && horizontal {
+                        directions.push((dx, dy));
+                    }
+                } else {
+                    // The sides across the direction of the move.
+                    let (sx, sy) = (dy.abs(), dx.abs());
+                    let next = walkable((x + dx, y + dy));
+                    let left = walkable((x - sx, y - sy));
+                    let right = walkable((x + sx, y + sy));
+                    if next {
+                        directions.push((dx, dy));
+                        if left {
+                            directions.push((dx - sx, dy - sy));
+                        }
+                        if right {
+                            directions.push((dx + sx, dy + sy));
+                        }
+                    }
+                    if left {
+                        directions.push((-sx, -sy));
+                    }
+                    if right {
+                        directions.push((sx, sy));
+                    }
+                }
+            }
+        }
+        directions
+            .into_iter()
+            .filter_map(|direction| {
+                let jump_point = self.jump((x + direction.0, y + direction.1), direction, goal)?;
+                let cost = self.distance((x, y), jump_point) * self.min_cost;
+                Some((jump_point, cost))
+            })
+            .collect()
+    }
+
+    /// Moves from a tile in a direction until reaching a jump point, i.e. the goal or a tile
+    /// where the path may turn because of a blocked tile, or a blocked tile.
+    fn jump(
+        &self,
+        (mut x, mut y): (i64, i64),
+        (dx, dy): (i64, i64),
+        goal: (i64, i64),
+    ) -> Option<(i64, i64)> {
+        let walkable = |tile| self.passable(tile);
+        loop {
+            if !walkable((x, y)) {
+                return None;
+            }
+            if (x, y) == goal {
+                return Some((x, y));
+            }
+            if dx != 0 && dy != 0 {
+                if self.jump((x + dx, y), (dx, 0), goal).is_some()
+                    || self.jump((x, y + dy), (0, dy), goal).is_some()
+                {
+                    return Some((x, y));
+                }
+            } else {
+                // A side opening up behind a blocked tile forces a turn.
+                let (sx, sy) = (dy.abs(), dx.abs());
+                if (walkable((x - sx, y - sy)) && !walkable((x - sx - dx, y - sy - dy)))
+                    || (walkable((x + sx, y + sy)) && !walkable((x + sx - dx, y + sy - dy)))
+                {
+                    return Some((x, y));
+                }
+            }
+            if !walkable((x + dx, y)) || !walkable((x, y + dy)) {
+                return None;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+}
+
+/// Tile waiting in the open set of A*, ordered so that the lowest estimate is popped first.
+#[derive(Debug)]
+struct Open {
+    estimate: f32,
+    cost: f32,
+    index: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties go to the tile closest to the goal, i.e. with the highest cost so far.
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                self.cost
+                    .partial_cmp(&other.cost)
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+}
+
+/// Component asking the `PathfindingSystem` for a path over the tiles of a map, which replaces
+/// the request with a `Path` if there is one.
+#[derive(Clone, Debug)]
+pub struct PathRequest {
+    /// Entity of the `TileMap`.
+    pub map: Entity,
+    /// Tile the path starts from.
+    pub start: Point3<u32>,
+    /// Tile the path leads to.
+    pub goal: Point3<u32>,
+    /// Options of the search.
+    pub options: PathOptions,
+    /// Whether to search on the thread pool, for long paths which would take too much of a
+    /// frame. The path is then found in a later frame.
+    pub asynchronous: bool,
+}
+
+impl Component for PathRequest {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl PathRequest {
+    /// Create a request for a path from `start` to `goal` over the map of the given entity, with
+    /// the default options.
+    #[must_use]
+    pub fn new(map: Entity, start: Point3<u32>, goal: Point3<u32>) -> Self {
+        Self {
+            map,
+            start,
+            goal,
+            options: PathOptions::default(),
+            asynchronous: false,
+        }
+    }
+
+    /// Search with the given options.
+    #[must_use]
+    pub fn with_options(mut self, options: PathOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Search on the thread pool.
+    #[must_use]
+    pub fn asynchronous(mut self) -> Self {
+        self.asynchronous = true;
+        self
+    }
+}
+
+/// Path over the tiles of a map, as found by the `PathfindingSystem`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    /// Entity of the `TileMap`.
+    pub map: Entity,
+    /// Tiles from the start to the goal of the path.
+    pub tiles: Vec<Point3<u32>>,
+    /// Index of the next tile to move to, advanced by the `PathFollowingSystem`.
+    pub next: usize,
+}
+
+impl Component for Path {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Path {
+    /// Returns the next tile to move to, or `None` once the goal was reached.
+    #[must_use]
+    pub fn next_tile(&self) -> Option<Point3<u32>> {
+        self.tiles.get(self.next).copied()
+    }
+
+    /// Returns whether the goal was reached.
+    #[must_use]
+    pub fn finished(&self) -> bool {
+        self.next >= self.tiles.len()
+    }
+}
+
+/// Event sent by the `PathfindingSystem` when it answered a `PathRequest`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathEvent {
+    /// A `Path` was added to the entity.
+    Found {
+        /// Entity of the request.
+        entity: Entity,
+    },
+    /// There is no path from the start to the goal of the request.
+    NotFound {
+        /// Entity of the request.
+        entity: Entity,
+    },
+}
+
+type PathResult = (Entity, u64, Entity, Option<Vec<Point3<u32>>>);
+
+/// Answers the `PathRequest`s over `TileMap<T, E>` entities with a `Path`, sending a `PathEvent`
+/// for each.
+///
+/// Asynchronous requests are searched on the `ArcThreadPool` and answered in a later frame, unless
+/// another request was made for the entity since.
+#[derive(Debug)]
+pub struct PathfindingSystem<T, E = crate::MortonEncoder2D> {
+    sender: Sender<PathResult>,
+    receiver: Receiver<PathResult>,
+    /// Latest request of each entity waiting for an asynchronous search.
+    pending: HashMap<Entity, u64>,
+    requests: u64,
+    marker: PhantomData<(T, E)>,
+}
+
+impl<T, E> Default for PathfindingSystem<T, E> {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver,
+            pending: HashMap::new(),
+            requests: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, E> PathfindingSystem<T, E> {
+    /// Returns a new pathfinding system.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a, T: Tile, E: CoordinateEncoder> System<'a> for PathfindingSystem<T, E> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, TileMap<T, E>>,
+        WriteStorage<'a, PathRequest>,
+        WriteStorage<'a, Path>,
+        ReadExpect<'a, ArcThreadPool>,
+        Write<'a, EventChannel<PathEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, maps, mut requests, mut paths, pool, mut events): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("pathfinding_system");
+
+        let mut results = Vec::new();
+        for (entity, request) in (&entities, requests.drain()).join() {
+            self.requests += 1;
+            self.pending.remove(&entity);
+            // Requests which aren't searched in the background are answered right away.
+            let map = match maps.get(request.map) {
+                Some(map)
+                    if request.asynchronous
+                        && request.start.z == request.goal.z
+                        && request.start.z < map.dimensions().z =>
+                {
+                    map
+                }
+                map => {
+                    let path = map.and_then(|map| {
+                        find_path(map, request.start, request.goal, &request.options)
+                    });
+                    results.push((entity, self.requests, request.map, path));
+                    continue;
+                }
+            };
+            self.pending.insert(entity, self.requests);
+            let grid = PathGrid::new(map, request.start.z, &request.options);
+            let sender = self.sender.clone();
+            let id = self.requests;
+            pool.spawn(move || {
+                let z = request.start.z;
+                let path = grid
+                    .find_path(
+                        (request.start.x, request.start.y),
+                        (request.goal.x, request.goal.y),
+                    )
+                    .map(|path| {
+                        path.into_iter()
+                            .map(|(x, y)| Point3::new(x, y, z))
+                            .collect()
+                    });
+                // The system is gone if sending fails, so nobody is waiting for the path.
+                let _ = sender.send((entity, id, request.map, path));
+            });
+        }
+        while let Ok(result) = self.receiver.try_recv() {
+            if self.pending.get(&result.0) == Some(&result.1) {
+                self.pending.remove(&result.0);
+                results.push(result);
+            }
+        }
+
+        for (entity, _, map, path) in results {
+            if !entities.is_alive(entity) {
+                continue;
+            }
+            if let Some(tiles) = path {
+                paths
+                    .insert(
+                        entity,
+                        Path {
+                            map,
+                            tiles,
+                            next: 0,
+                        },
+                    )
+                    .expect("Unreachable: the entity is alive");
+                events.single_write(PathEvent::Found { entity });
+            } else {
+                paths.remove(entity);
+                events.single_write(PathEvent::NotFound { entity });
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+    }
+}
+
+/// Component moving its entity along its `Path` with the `PathFollowingSystem`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathFollower {
+    /// Speed in world units per second.
+    pub speed: f32,
+}
+
+impl Component for PathFollower {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl PathFollower {
+    /// Create a follower moving at the given speed in world units per second.
+    #[must_use]
+    pub fn new(speed: f32) -> Self {
+        Self { speed }
+    }
+}
+
+/// Moves the entities with a `PathFollower` towards the center of the next tile of their `Path`
+/// over a `TileMap<T, E>`, keeping their z translation. The followers are expected to have no
+/// parent, so that their translation is in world space.
+#[derive(Debug)]
+pub struct PathFollowingSystem<T, E = crate::MortonEncoder2D> {
+    marker: PhantomData<(T, E)>,
+}
+
+impl<T, E> Default for PathFollowingSystem<T, E> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, E> PathFollowingSystem<T, E> {
+    /// Returns a new path following system.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a, T: Tile, E: CoordinateEncoder> System<'a> for PathFollowingSystem<T, E> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, TileMap<T, E>>,
+        ReadStorage<'a, PathFollower>,
+        WriteStorage<'a, Path>,
+        WriteStorage<'a, Transform>,
+        Read<'a, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, maps, followers, mut paths, mut transforms, time): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("path_following_system");
+
+        for (entity, follower, path) in (&entities, &followers, &mut paths).join() {
+            let map_transform = transforms.get(path.map).cloned();
+            if let (Some(map), Some(transform)) = (maps.get(path.map), transforms.get_mut(entity)) {
+                let mut step = follower.speed * time.delta_seconds();
+                while let Some(tile) = path.next_tile() {
+                    let target = map.to_world(&tile, map_transform.as_ref()).xy();
+                    let position = transform.translation().xy();
+                    let distance = (target - position).norm();
+                    if distance > step {
+                        let position = position + (target - position) * (step / distance);
+                        transform.set_translation_x(position.x);
+                        transform.set_translation_y(position.y);
+                        break;
+                    }
+                    transform.set_translation_x(target.x);
+                    transform.set_translation_y(target.y);
+                    step -= distance;
+                    path.next += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MortonEncoder2D;
+    use amethyst_core::{
+        ecs::{Builder, RunNow, WorldExt},
+        math::Vector3,
+    };
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    #[derive(Clone, Debug, Default)]
+    struct TestTile;
+    impl Tile for TestTile {}
+
+    fn test_map(
+        width: u32,
+        height: u32,
+        projection: MapProjection,
+    ) -> TileMap<TestTile, MortonEncoder2D> {
+        TileMap::new(
+            Vector3::new(width, height, 1),
+            Vector3::new(10, 10, 1),
+            None,
+        )
+        .with_projection(projection)
+    }
+
+    /// Returns the cost of a path, checking that every step is allowed.
+    fn path_cost(
+        map: &TileMap<TestTile, MortonEncoder2D>,
+        path: &[Point3<u32>],
+        options: &PathOptions,
+    ) -> f32 {
+        let grid = PathGrid::new(map, 0, options);
+        path.windows(2)
+            .map(|step| {
+                let from = (i64::from(step[0].x), i64::from(step[0].y));
+                let to = (i64::from(step[1].x), i64::from(step[1].y));
+                let (_, length) = grid
+                    .neighbors(from)
+                    .into_iter()
+                    .find(|(neighbor, _)| *neighbor == to)
+                    .unwrap_or_else(|| panic!("Invalid step from {:?} to {:?}", from, to));
+                length * grid.costs[grid.index(to)]
+            })
+            .sum()
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn paths_go_around_blocked_tiles() {
+        let mut map = test_map(5, 5, MapProjection::Square);
+        for y in 0..4 {
+            map.set_data("solid", &Point3::new(2, y, 0), true);
+        }
+        let options = PathOptions {
+            diagonal: false,
+            ..PathOptions::default()
+        };
+        let path = find_path(&map, Point3::new(0, 0, 0), Point3::new(4, 0, 0), &options).unwrap();
+        assert_eq!(path.len(), 13);
+        assert_eq!(path[6], Point3::new(2, 4, 0));
+        assert_eq!(path_cost(&map, &path, &options), 12.0);
+
+        // Diagonal moves can't cut the corner of the wall.
+        let options = PathOptions::default();
+        let path = find_path(&map, Point3::new(0, 0, 0), Point3::new(4, 0, 0), &options).unwrap();
+        assert!(path.contains(&Point3::new(2, 4, 0)));
+        assert_eq!(path.len(), 11);
+        path_cost(&map, &path, &options);
+
+        map.set_data("solid", &Point3::new(2, 4, 0), true);
+        assert_eq!(
+            find_path(&map, Point3::new(0, 0, 0), Point3::new(4, 0, 0), &options),
+            None
+        );
+        assert_eq!(
+            find_path(&map, Point3::new(0, 0, 0), Point3::new(2, 0, 0), &options),
+            None
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn paths_prefer_cheap_tiles() {
+        let mut map = test_map(5, 3, MapProjection::Square);
+        for x in 1..4 {
+            map.set_data("cost", &Point3::new(x, 1, 0), 5.0_f32);
+        }
+        let options = PathOptions {
+            diagonal: false,
+            ..PathOptions::default()
+        };
+        let path = find_path(&map, Point3::new(0, 1, 0), Point3::new(4, 1, 0), &options).unwrap();
+        assert!(path.contains(&Point3::new(2, 0, 0)) || path.contains(&Point3::new(2, 2, 0)));
+        assert_eq!(path_cost(&map, &path, &options), 6.0);
+    }
+
+    #[test]
+    fn paths_follow_the_projection() {
+        let options = PathOptions {
+            diagonal: false,
+            ..PathOptions::default()
+        };
+        for &(projection, goal, steps) in &[
+            (MapProjection::HexPointyTop, Point3::new(5, 5, 0), 8),
+            (MapProjection::HexFlatTop, Point3::new(5, 5, 0), 8),
+            (MapProjection::IsometricStaggered, Point3::new(3, 4, 0), 6),
+            (MapProjection::IsometricDiamond, Point3::new(3, 4, 0), 7),
+        ] {
+            let map = test_map(6, 6, projection);
+            let path = find_path(&map, Point3::new(0, 0, 0), goal, &options).unwrap();
+            assert_eq!(path.len(), steps + 1, "{projection:?}");
+            path_cost(&map, &path, &options);
+        }
+
+        // Corners of staggered isometric tiles are a step between two edges.
+        let map = test_map(6, 6, MapProjection::IsometricStaggered);
+        let path = find_path(
+            &map,
+            Point3::new(0, 0, 0),
+            Point3::new(3, 0, 0),
+            &PathOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(path.len(), 4);
+    }
+
+    #[test]
+    fn jump_points_find_paths_as_cheap_as_a_star() {
+        let mut seed = 0x2545_f491_u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+        let a_star = PathOptions::default();
+        let jump_point = PathOptions {
+            algorithm: PathAlgorithm::JumpPoint,
+            ..PathOptions::default()
+        };
+        for _ in 0..50 {
+            let mut map = test_map(16, 12, MapProjection::Square);
+            for y in 0..12 {
+                for x in 0..16 {
+                    if random() % 4 == 0 {
+                        map.set_data("solid", &Point3::new(x, y, 0), true);
+                    }
+                }
+            }
+            let start = Point3::new(random() % 16, random() % 12, 0);
+            let goal = Point3::new(random() % 16, random() % 12, 0);
+            let expected = find_path(&map, start, goal, &a_star);
+            let found = find_path(&map, start, goal, &jump_point);
+            assert_eq!(found.is_some(), expected.is_some());
+            if let (Some(expected), Some(found)) = (expected, found) {
+                assert_eq!(found.first(), Some(&start));
+                assert_eq!(found.last(), Some(&goal));
+                let expected = path_cost(&map, &expected, &a_star);
+                let found = path_cost(&map, &found, &jump_point);
+                assert!((expected - found).abs() < 1e-3, "{} {}", expected, found);
+            }
+        }
+    }
+
+    #[test]
+    fn systems_find_and_follow_paths() {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<PathFollower>();
+        world.insert::<ArcThreadPool>(Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let mut pathfinding = PathfindingSystem::<TestTile>::new();
+        let mut following = PathFollowingSystem::<TestTile>::new();
+        System::setup(&mut pathfinding, &mut world);
+        System::setup(&mut following, &mut world);
+        let mut reader = world
+            .fetch_mut::<EventChannel<PathEvent>>()
+            .register_reader();
+
+        let mut map = test_map(4, 1, MapProjection::Square);
+        map.set_data("solid", &Point3::new(2, 0, 0), true);
+        let map = world.create_entity().with(map).build();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(-20.0, 5.0, 3.0);
+        let follower = world
+            .create_entity()
+            .with(transform)
+            .with(PathFollower::new(15.0))
+            .with(PathRequest::new(
+                map,
+                Point3::new(0, 0, 0),
+                Point3::new(1, 0, 0),
+            ))
+            .build();
+        let blocked = world
+            .create_entity()
+            .with(PathRequest::new(map, Point3::new(0, 0, 0), Point3::new(3, 0, 0)).asynchronous())
+            .build();
+
+        let mut time = Time::default();
+        time.set_delta_seconds(0.5);
+        world.insert(time);
+        let mut events = Vec::new();
+        for _ in 0..1000 {
+            pathfinding.run_now(&world);
+            events.extend(
+                world
+                    .read_resource::<EventChannel<PathEvent>>()
+                    .read(&mut reader)
+                    .copied(),
+            );
+            if events.len() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(
+            events,
+            vec![
+                PathEvent::Found { entity: follower },
+                PathEvent::NotFound { entity: blocked },
+            ]
+        );
+
+        following.run_now(&world);
+        {
+            let transforms = world.read_storage::<Transform>();
+            let paths = world.read_storage::<Path>();
+            assert_eq!(
+                paths.get(follower).unwrap().next_tile(),
+                Some(Point3::new(1, 0, 0))
+            );
+            assert_eq!(
+                *transforms.get(follower).unwrap().translation(),
+                Vector3::new(-12.5, 5.0, 3.0)
+            );
+        }
+        following.run_now(&world);
+        let paths = world.read_storage::<Path>();
+        assert!(paths.get(follower).unwrap().finished());
+        assert_eq!(
+            *world
+                .read_storage::<Transform>()
+                .get(follower)
+                .unwrap()
+                .translation(),
+            Vector3::new(-10.0, 5.0, 3.0)
+        );
+    }
+}
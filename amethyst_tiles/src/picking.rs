@@ -0,0 +1,472 @@
+//! Picking of the tiles under the mouse cursor, e.g. for map editors or strategy games.
+
+use crate::{CoordinateEncoder, Map, Tile, TileMap};
+use amethyst_core::{
+    ecs::{
+        Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, World, Write,
+    },
+    math::{Point2, Point3, Vector2, Vector3},
+    shrev::{EventChannel, ReaderId},
+    SystemDesc, Transform,
+};
+use amethyst_input::{BindingTypes, InputEvent, InputHandler, StringBindings};
+use amethyst_rendy::{
+    camera::{ActiveCamera, Camera},
+    viewport::CameraViewport,
+};
+use amethyst_window::ScreenDimensions;
+use derivative::Derivative;
+use std::{collections::HashMap, marker::PhantomData};
+use winit::MouseButton;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+impl<T: Tile, E: CoordinateEncoder> TileMap<T, E> {
+    /// Returns the coordinates of the tile of the z level `z` under a position on the screen, or
+    /// `None` if the position isn't over a tile of the level.
+    ///
+    /// The position and the screen diagonal are in pixels like for `Camera::screen_ray`, so the
+    /// transform and projection of the camera, and thereby its zoom, are taken into account along
+    /// with the `MapProjection` of the map and the `LayerParallax` of the z level.
+    #[must_use]
+    pub fn screen_to_tile(
+        &self,
+        screen_position: Point2<f32>,
+        screen_diagonal: Vector2<f32>,
+        camera: &Camera,
+        camera_transform: &Transform,
+        map_transform: Option<&Transform>,
+        z: u32,
+    ) -> Option<Point3<u32>> {
+        if z >= self.dimensions().z {
+            return None;
+        }
+        let point = self.screen_to_level(
+            screen_position,
+            screen_diagonal,
+            camera,
+            camera_transform,
+            map_transform,
+            z,
+        )?;
+        let tile = self.to_tile(&point.coords, map_transform).ok()?;
+        Some(Point3::new(tile.x, tile.y, z))
+    }
+
+    /// Returns the point of the z level `z` under a position on the screen in world space, moved
+    /// against the `LayerParallax` of the level, or `None` if the camera looks along the level.
+    pub(crate) fn screen_to_level(
+        &self,
+        screen_position: Point2<f32>,
+        screen_diagonal: Vector2<f32>,
+        camera: &Camera,
+        camera_transform: &Transform,
+        map_transform: Option<&Transform>,
+        z: u32,
+    ) -> Option<Point3<f32>> {
+        let ray = camera.screen_ray(screen_position, screen_diagonal, camera_transform);
+
+        // Intersect the ray with the plane of the z level.
+        let level = self.to_world(&Point3::new(0, 0, z), map_transform);
+        let normal = map_transform.map_or_else(Vector3::z, |transform| {
+            transform.global_matrix().transform_vector(&Vector3::z())
+        });
+        let facing = ray.direction.dot(&normal);
+        if facing.abs() <= f32::EPSILON {
+            return None;
+        }
+        let distance = (level - ray.origin.coords).dot(&normal) / facing;
+        let mut point = ray.origin + ray.direction * distance;
+        let camera_position = camera_transform.global_matrix().column(3).xy();
+        point -= self.layer_parallax(z).offset(&camera_position).push(0.0);
+        Some(point)
+    }
+}
+
+/// Event sent by the `TilePickingSystem` when the mouse cursor interacts with the tiles of a
+/// `TileMap`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TilePickEvent {
+    /// The cursor moved over a tile.
+    Hovered {
+        /// Entity of the `TileMap`.
+        entity: Entity,
+        /// Coordinates of the tile.
+        tile: Point3<u32>,
+    },
+    /// The cursor left the tile it was over, which is sent before `Hovered` for the next tile.
+    Unhovered {
+        /// Entity of the `TileMap`.
+        entity: Entity,
+        /// Coordinates of the tile.
+        tile: Point3<u32>,
+    },
+    /// A mouse button was pressed over a tile.
+    Clicked {
+        /// Entity of the `TileMap`.
+        entity: Entity,
+        /// Coordinates of the tile.
+        tile: Point3<u32>,
+        /// The button which was pressed.
+        button: MouseButton,
+    },
+}
+
+/// Builds a `TilePickingSystem`.
+#[derive(Derivative, Debug)]
+#[derivative(Default(bound = ""))]
+pub struct TilePickingSystemDesc<T, E = crate::MortonEncoder2D, B = StringBindings> {
+    z: u32,
+    marker: PhantomData<(T, E, B)>,
+}
+
+impl<T, E, B> TilePickingSystemDesc<T, E, B> {
+    /// Pick the tiles of the z level `z` of the maps, the bottom one by default.
+    #[must_use]
+    pub fn with_z_level(mut self, z: u32) -> Self {
+        self.z = z;
+        self
+    }
+}
+
+impl<T, E, B> SystemDesc<'_, '_, TilePickingSystem<T, E, B>> for TilePickingSystemDesc<T, E, B>
+where
+    T: Tile,
+    E: CoordinateEncoder,
+    B: BindingTypes,
+{
+    fn build(self, world: &mut World) -> TilePickingSystem<T, E, B> {
+        <TilePickingSystem<T, E, B> as System<'_>>::SystemData::setup(world);
+
+        let input_reader = world
+            .fetch_mut::<EventChannel<InputEvent<B>>>()
+            .register_reader();
+
+        TilePickingSystem {
+            z: self.z,
+            input_reader,
+            hovered: HashMap::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Sends a `TilePickEvent` when the mouse cursor moves over the tiles of the `TileMap<T, E>`
+/// entities, and when a mouse button is pressed over them.
+///
+/// The tiles are picked through the camera whose `CameraViewport` contains the cursor, or
+/// otherwise through the `ActiveCamera` or the first camera, covering the whole window. The system
+/// should run after the `InputSystem` of the bindings `B`.
+#[derive(Debug)]
+pub struct TilePickingSystem<T, E = crate::MortonEncoder2D, B = StringBindings>
+where
+    B: BindingTypes,
+{
+    z: u32,
+    input_reader: ReaderId<InputEvent<B>>,
+    hovered: HashMap<Entity, Point3<u32>>,
+    marker: PhantomData<(T, E)>,
+}
+
+impl<T, E, B> TilePickingSystem<T, E, B>
+where
+    B: BindingTypes,
+{
+    /// Updates the tile hovered on each map, sending the events of the tiles left and entered.
+    fn hover(
+        &mut self,
+        tiles: impl Iterator<Item = (Entity, Option<Point3<u32>>)>,
+        events: &mut EventChannel<TilePickEvent>,
+    ) {
+        let mut hovered = HashMap::with_capacity(self.hovered.len());
+        for (entity, tile) in tiles {
+            let previous = self.hovered.remove(&entity);
+            if previous != tile {
+                if let Some(tile) = previous {
+                    events.single_write(TilePickEvent::Unhovered { entity, tile });
+                }
+                if let Some(tile) = tile {
+                    events.single_write(TilePickEvent::Hovered { entity, tile });
+                }
+            }
+            if let Some(tile) = tile {
+                hovered.insert(entity, tile);
+            }
+        }
+        // The rest were deleted or lost their map.
+        self.hovered = hovered;
+    }
+}
+
+impl<'a, T, E, B> System<'a> for TilePickingSystem<T, E, B>
+where
+    T: Tile,
+    E: CoordinateEncoder,
+    B: BindingTypes,
+{
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, CameraViewport>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, TileMap<T, E>>,
+        ReadExpect<'a, ScreenDimensions>,
+        Read<'a, InputHandler<B>>,
+        Read<'a, EventChannel<InputEvent<B>>>,
+        Write<'a, EventChannel<TilePickEvent>>,
+    );
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn run(
+        &mut self,
+        (
+            entities,
+            active,
+            cameras,
+            viewports,
+            transforms,
+            maps,
+            screen,
+            input,
+            input_events,
+            mut events,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("tile_picking_system");
+
+        let diagonal = Vector2::new(screen.width(), screen.height());
+        let cursor = input
+            .mouse_position()
+            .map(|(x, y)| Point2::new(x, y))
+            .and_then(|position| {
+                // The topmost viewport containing the cursor, with the cursor in its pixels.
+                let mut ordered = (&cameras, &transforms, &viewports)
+                    .join()
+                    .collect::<Vec<_>>();
+                ordered.sort_by_key(|(_, _, viewport)| -viewport.order());
+                let in_viewport = ordered
+                    .into_iter()
+                    .find_map(|(camera, transform, viewport)| {
+                        let rect = viewport.window_rect(diagonal.x as u32, diagonal.y as u32);
+                        let local = rect.to_local(position, diagonal)?;
+                        let pixels = rect.pixels(diagonal.x as u32, diagonal.y as u32);
+                        let local_diagonal = Vector2::new(f32::from(pixels.w), f32::from(pixels.h));
+                        Some((camera, transform, local, local_diagonal))
+                    });
+                in_viewport.or_else(|| {
+                    let mut window_cameras = (&entities, &cameras, &transforms, !&viewports).join();
+                    let (_, camera, transform, ()) = active
+                        .entity
+                        .and_then(|entity| window_cameras.get(entity, &entities))
+                        .or_else(|| window_cameras.next())?;
+                    Some((camera, transform, position, diagonal))
+                })
+            });
+
+        let z = self.z;
+        self.hover(
+            (&entities, &maps, transforms.maybe())
+                .join()
+                .map(|(entity, map, map_transform)| {
+                    let tile = cursor.and_then(|(camera, transform, position, diagonal)| {
+                        map.screen_to_tile(position, diagonal, camera, transform, map_transform, z)
+                    });
+                    (entity, tile)
+                }),
+            &mut events,
+        );
+
+        for event in input_events.read(&mut self.input_reader) {
+            if let InputEvent::MouseButtonPressed(button) = *event {
+                for (&entity, &tile) in &self.hovered {
+                    events.single_write(TilePickEvent::Clicked {
+                        entity,
+                        tile,
+                        button,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LayerParallax, MapProjection, MortonEncoder2D};
+    use amethyst_core::ecs::{Builder, RunNow, WorldExt};
+    use winit::{DeviceId, ElementState, Event, ModifiersState, WindowEvent, WindowId};
+
+    #[derive(Clone, Debug, Default)]
+    struct TestTile;
+    impl Tile for TestTile {}
+
+    fn camera_transform(x: f32, y: f32, zoom: f32) -> Transform {
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(x, y, 10.0);
+        transform.set_scale(Vector3::new(zoom, zoom, 1.0));
+        transform.copy_local_to_global();
+        transform
+    }
+
+    #[test]
+    fn screen_positions_are_picked_through_the_camera() {
+        let map = TileMap::<TestTile, MortonEncoder2D>::new(
+            Vector3::new(4, 4, 2),
+            Vector3::new(10, 10, 1),
+            None,
+        );
+        let camera = Camera::standard_2d(100.0, 100.0);
+        let diagonal = Vector2::new(100.0, 100.0);
+        let pick = |x, y, transform: &Transform| {
+            map.screen_to_tile(Point2::new(x, y), diagonal, &camera, transform, None, 1)
+        };
+
+        // The tiles are centered on the screen, 10 pixels apart.
+        let centered = camera_transform(0.0, 0.0, 1.0);
+        assert_eq!(pick(31.0, 31.0, &centered), Some(Point3::new(0, 0, 1)));
+        assert_eq!(pick(52.0, 62.0, &centered), Some(Point3::new(2, 3, 1)));
+        assert_eq!(pick(20.0, 50.0, &centered), None);
+
+        let moved = camera_transform(20.0, -20.0, 1.0);
+        assert_eq!(pick(31.0, 31.0, &moved), Some(Point3::new(2, 2, 1)));
+
+        // Zoomed out, the tiles are only 5 pixels apart.
+        let zoomed = camera_transform(0.0, 0.0, 2.0);
+        assert_eq!(pick(41.0, 56.0, &zoomed), Some(Point3::new(0, 3, 1)));
+        assert_eq!(pick(31.0, 31.0, &zoomed), None);
+
+        let isometric = map.clone().with_projection(MapProjection::IsometricDiamond);
+        assert_eq!(
+            isometric.screen_to_tile(
+                Point2::new(50.0, 32.0),
+                diagonal,
+                &camera,
+                &centered,
+                None,
+                0
+            ),
+            Some(Point3::new(0, 0, 0))
+        );
+        assert_eq!(
+            map.screen_to_tile(
+                Point2::new(50.0, 50.0),
+                diagonal,
+                &camera,
+                &centered,
+                None,
+                2
+            ),
+            None
+        );
+
+        // A level moving at half the speed of the camera lags behind it.
+        let mut parallax = map;
+        parallax.set_layer_parallax(1, LayerParallax::new(Vector2::new(0.5, 0.5)));
+        assert_eq!(
+            parallax.screen_to_tile(Point2::new(31.0, 31.0), diagonal, &camera, &moved, None, 1),
+            Some(Point3::new(1, 1, 1))
+        );
+    }
+
+    fn window_event(event: WindowEvent) -> Event {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event,
+        }
+    }
+
+    #[test]
+    fn system_sends_hover_and_click_events() {
+        let mut world = World::new();
+        world.register::<Camera>();
+        world.register::<CameraViewport>();
+        world.register::<Transform>();
+        world.insert(ScreenDimensions::new(100, 100, 1.0));
+        world.insert(InputHandler::<StringBindings>::new());
+        world.insert(EventChannel::<InputEvent<StringBindings>>::new());
+        let mut system = TilePickingSystemDesc::<TestTile>::default().build(&mut world);
+        let mut reader = world
+            .fetch_mut::<EventChannel<TilePickEvent>>()
+            .register_reader();
+
+        world
+            .create_entity()
+            .with(Camera::standard_2d(100.0, 100.0))
+            .with(camera_transform(0.0, 0.0, 1.0))
+            .build();
+        let map = world
+            .create_entity()
+            .with(TileMap::<TestTile, MortonEncoder2D>::new(
+                Vector3::new(4, 4, 1),
+                Vector3::new(10, 10, 1),
+                None,
+            ))
+            .build();
+
+        let send = |world: &mut World, event: WindowEvent| {
+            let mut channel = world.write_resource::<EventChannel<InputEvent<StringBindings>>>();
+            world
+                .write_resource::<InputHandler<StringBindings>>()
+                .send_event(&window_event(event), &mut channel, 1.0);
+        };
+        let mut step = |world: &mut World, event: WindowEvent| {
+            send(world, event);
+            system.run_now(world);
+            world
+                .read_resource::<EventChannel<TilePickEvent>>()
+                .read(&mut reader)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let cursor = |x, y| WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: (x, y).into(),
+            modifiers: ModifiersState::default(),
+        };
+
+        let tile = Point3::new(0, 0, 0);
+        assert_eq!(
+            step(&mut world, cursor(31.0, 31.0)),
+            vec![TilePickEvent::Hovered { entity: map, tile }]
+        );
+        assert_eq!(step(&mut world, cursor(32.0, 32.0)), vec![]);
+        assert_eq!(
+            step(
+                &mut world,
+                WindowEvent::MouseInput {
+                    device_id: unsafe { DeviceId::dummy() },
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    modifiers: ModifiersState::default(),
+                }
+            ),
+            vec![TilePickEvent::Clicked {
+                entity: map,
+                tile,
+                button: MouseButton::Left,
+            }]
+        );
+        assert_eq!(
+            step(&mut world, cursor(40.0, 31.0)),
+            vec![
+                TilePickEvent::Unhovered { entity: map, tile },
+                TilePickEvent::Hovered {
+                    entity: map,
+                    tile: Point3::new(1, 0, 0),
+                },
+            ]
+        );
+        assert_eq!(
+            step(&mut world, cursor(10.0, 10.0)),
+            vec![TilePickEvent::Unhovered {
+                entity: map,
+                tile: Point3::new(1, 0, 0),
+            }]
+        );
+    }
+}
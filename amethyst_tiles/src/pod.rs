@@ -21,6 +21,8 @@ use glsl_layout::{mat4, uvec3, vec2, vec4, AsStd140};
 ///    uniform mat4 view;
 ///    uniform mat4 map_coordinate_transform;
 ///    uniform mat4 map_transform;
+///    uniform vec2 sprite_dimensions;
+///    uniform vec4 stagger;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -36,6 +38,9 @@ pub struct TileMapArgs {
     pub map_transform: mat4,
     /// Sprite Dimensions. Because we assume tiles are uniform for a map, we can store these here.
     pub sprite_dimensions: vec2,
+    /// Offsets in tile coordinates of the odd rows in `xy` and of the odd columns in `zw`, for
+    /// staggered map projections.
+    pub stagger: vec4,
 }
 
 /// Tile Vertex Data
@@ -47,6 +52,7 @@ pub struct TileMapArgs {
 /// vec2 v_offset;
 /// float depth;
 /// vec4 tint;
+/// vec2 layer_offset;
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, AsStd140)]
 #[repr(C, align(4))]
@@ -59,6 +65,8 @@ pub struct TileArgs {
     pub tint: vec4,
     /// Tile coordinate
     pub tile_coordinate: uvec3,
+    /// Offset of the z level of the tile in world space, from its `LayerParallax`
+    pub layer_offset: vec2,
 }
 
 impl AsVertex for TileArgs {
@@ -69,6 +77,7 @@ impl AsVertex for TileArgs {
             (Format::Rg32Sfloat, "v_offset"),
             (Format::Rgba32Sfloat, "tint"),
             (Format::Rgb32Uint, "tile_coordinate"),
+            (Format::Rg32Sfloat, "layer_offset"),
         ))
     }
 }
@@ -83,6 +92,8 @@ impl TileArgs {
     /// * `sprite_number` - The number index of the sprite in the sprite sheet.
     /// * `tint` - An optional `TintComponent` reference for tinting this tile, if applicable.
     /// * `tile_coordinate` - The  Point3<u32> position of this tile (in Tile Coordinate Space)
+    ///
+    /// The `layer_offset` is left at zero, for the render pass to fill in.
     pub fn from_data<'a>(
         tex_storage: &AssetStorage<Texture>,
         sprite_sheet: &'a SpriteSheet,
@@ -102,6 +113,7 @@ impl TileArgs {
                 v_offset: [sprite.tex_coords.top, sprite.tex_coords.bottom].into(),
                 tint: tint.map_or([1.0; 4].into(), |t| t.0.into_pod()),
                 tile_coordinate: [tile_coordinate.x, tile_coordinate.y, tile_coordinate.z].into(),
+                layer_offset: [0.0; 2].into(),
             },
             &sprite_sheet.texture,
         ))
@@ -58,7 +58,7 @@ impl<'a, T: Tile + Serialize + for<'b> Deserialize<'b>, E: CoordinateEncoder> Pr
         _: &mut ProgressCounter,
         system_data: &mut Self::SystemData,
     ) -> Result<bool, Error> {
-        if let Some(handle) = (*system_data.1).get(&self.sheet.as_ref().unwrap()) {
+        if let Some(handle) = (*system_data.1).get(self.sheet.as_ref().unwrap()) {
             self.handle = Some(handle);
             Ok(false)
         } else {
@@ -0,0 +1,298 @@
+//! Layouts of the tiles of maps, projecting tile coordinates into the world.
+
+use amethyst_core::math::{Matrix4, Point3, Vector2};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+/// Layout of the tiles of a `TileMap`, used to project its tile coordinates into the world and to
+/// order the tiles so that the lower ones are drawn over the ones behind them.
+///
+/// Rows are numbered from the top of the map and columns from its left. Staggered layouts shift
+/// every odd row or column by half a tile so that the tiles interlock, like the layouts of the
+/// Tiled editor with an odd stagger index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MapProjection {
+    /// Rectangular tiles in a square grid.
+    #[default]
+    Square,
+    /// Isometric diamonds forming a diamond shaped map, with the x axis running down to the right
+    /// and the y axis down to the left from the tile at the top.
+    IsometricDiamond,
+    /// Isometric diamonds in rows half a tile high, with the odd rows shifted right by half a tile,
+    /// forming a rectangular map.
+    IsometricStaggered,
+    /// Hexagons with a pointy top, in rows three quarters of a tile high, with the odd rows
+    /// shifted right by half a tile.
+    HexPointyTop,
+    /// Hexagons with a flat top, in columns three quarters of a tile wide, with the odd columns
+    /// shifted down by half a tile.
+    HexFlatTop,
+}
+
+impl MapProjection {
+    /// Returns the matrix projecting staggered tile coordinates, with y pointing up, into tile
+    /// sizes.
+    pub(crate) fn basis(self) -> Matrix4<f32> {
+        let (x, y) = self.axes();
+        #[rustfmt::skip]
+        let basis = Matrix4::new(
+            x[0], y[0], 0.0, 0.0,
+            x[1], y[1], 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        );
+        basis
+    }
+
+    /// Returns the tile sizes covered by a step along the x and y axes of the tile coordinates,
+    /// with y pointing up.
+    fn axes(self) -> ([f32; 2], [f32; 2]) {
+        match self {
+            Self::Square => ([1.0, 0.0], [0.0, 1.0]),
+            Self::IsometricDiamond => ([0.5, -0.5], [0.5, 0.5]),
+            Self::IsometricStaggered => ([1.0, 0.0], [0.0, 0.5]),
+            Self::HexPointyTop => ([1.0, 0.0], [0.0, 0.75]),
+            Self::HexFlatTop => ([0.75, 0.0], [0.0, 1.0]),
+        }
+    }
+
+    /// Returns the offsets in tile coordinates of the odd rows in `xy` and of the odd columns in
+    /// `zw`, as passed to the tile shader.
+    pub(crate) fn stagger(self) -> [f32; 4] {
+        match self {
+            Self::Square | Self::IsometricDiamond => [0.0; 4],
+            Self::IsometricStaggered | Self::HexPointyTop => [0.5, 0.0, 0.0, 0.0],
+            Self::HexFlatTop => [0.0, 0.0, 0.0, 0.5],
+        }
+    }
+
+    /// Returns the tile coordinates of the center of a tile, with y pointing up, before the
+    /// `basis`.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn staggered_coordinates(self, x: i64, y: i64, z: f32) -> Point3<f32> {
+        let stagger = self.stagger();
+        let odd_row = (y & 1) as f32;
+        let odd_column = (x & 1) as f32;
+        Point3::new(
+            x as f32 + odd_row * stagger[0] + odd_column * stagger[2],
+            -(y as f32 + odd_row * stagger[1] + odd_column * stagger[3]),
+            z,
+        )
+    }
+
+    /// Returns how far an offset in tile sizes is from the center of a tile, reaching 1.0 on the
+    /// edges of the tile.
+    fn distance(self, offset: Vector2<f32>) -> f32 {
+        let (x, y) = (offset.x.abs(), offset.y.abs());
+        match self {
+            Self::Square => 2.0 * x.max(y),
+            Self::IsometricDiamond | Self::IsometricStaggered => 2.0 * (x + y),
+            Self::HexPointyTop => (2.0 * x).max(2.0 * y + x),
+            Self::HexFlatTop => (2.0 * y).max(2.0 * x + y),
+        }
+    }
+
+    /// Returns the x and y tile coordinates of the tile covering a point, given in tile
+    /// coordinates with y pointing up before the `basis`. The coordinates are negative for points
+    /// left of or above the map.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub(crate) fn nearest_tile(self, point: &Point3<f32>) -> (i64, i64) {
+        let (axis_x, axis_y) = self.axes();
+        let (x, y) = (point.x.round() as i64, (-point.y).round() as i64);
+        let mut nearest = (x, y);
+        let mut nearest_distance = f32::MAX;
+        // The tile is next to the one found by rounding, which is tested first to win ties.
+        for &(dx, dy) in &[
+            (0, 0),
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+            (1, 1),
+        ] {
+            let center = self.staggered_coordinates(x + dx, y + dy, point.z);
+            let (x_offset, y_offset) = (point.x - center.x, point.y - center.y);
+            let offset = Vector2::new(
+                axis_x[0] * x_offset + axis_y[0] * y_offset,
+                axis_x[1] * x_offset + axis_y[1] * y_offset,
+            );
+            let distance = self.distance(offset);
+            if distance < nearest_distance {
+                nearest = (x + dx, y + dy);
+                nearest_distance = distance;
+            }
+        }
+        nearest
+    }
+
+    /// Returns the key sorting tile coordinates from the back to the front of the map, so that the
+    /// tiles lower in the world are drawn over the ones behind them. Layouts drawn in row order are
+    /// sorted by z level, then row, then column.
+    pub(crate) fn draw_order(self, coord: &Point3<u32>) -> (u32, u32, u32) {
+        match self {
+            Self::Square | Self::IsometricStaggered | Self::HexPointyTop => {
+                (coord.z, coord.y, coord.x)
+            }
+            Self::IsometricDiamond => (coord.z, coord.x + coord.y, coord.x),
+            Self::HexFlatTop => (coord.z, coord.y * 2 + (coord.x & 1), coord.x),
+        }
+    }
+
+    /// Returns the tiles sharing an edge with a tile, clockwise from the top in tile coordinates.
+    /// Square-like layouts start with the tile above, pointy hexagons with the tile to the right
+    /// and flat hexagons with the tile above.
+    pub(crate) fn edge_neighbors(self, x: i64, y: i64) -> SmallVec<[(i64, i64); 6]> {
+        // Tiles in the odd rows or columns of staggered projections are shifted by half a tile.
+        let s = x + (y & 1);
+        let t = y + (x & 1);
+        match self {
+            Self::Square | Self::IsometricDiamond => {
+                SmallVec::from_slice(&[(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)])
+            }
+            Self::IsometricStaggered => {
+                SmallVec::from_slice(&[(s, y - 1), (s, y + 1), (s - 1, y + 1), (s - 1, y - 1)])
+            }
+            Self::HexPointyTop => SmallVec::from_slice(&[
+                (x + 1, y),
+                (s, y + 1),
+                (s - 1, y + 1),
+                (x - 1, y),
+                (s - 1, y - 1),
+                (s, y - 1),
+            ]),
+            Self::HexFlatTop => SmallVec::from_slice(&[
+                (x, y - 1),
+                (x + 1, t - 1),
+                (x + 1, t),
+                (x, y + 1),
+                (x - 1, t),
+                (x - 1, t - 1),
+            ]),
+        }
+    }
+
+    /// Returns the tiles only touching a tile by a corner, with the n-th one between the n-th
+    /// and the next edge neighbor. Hexagons have none.
+    pub(crate) fn corner_neighbors(self, x: i64, y: i64) -> SmallVec<[(i64, i64); 6]> {
+        match self {
+            Self::Square | Self::IsometricDiamond => SmallVec::from_slice(&[
+                (x + 1, y - 1),
+                (x + 1, y + 1),
+                (x - 1, y + 1),
+                (x - 1, y - 1),
+            ]),
+            Self::IsometricStaggered => {
+                SmallVec::from_slice(&[(x + 1, y), (x, y + 2), (x - 1, y), (x, y - 2)])
+            }
+            Self::HexPointyTop | Self::HexFlatTop => SmallVec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECTIONS: [MapProjection; 5] = [
+        MapProjection::Square,
+        MapProjection::IsometricDiamond,
+        MapProjection::IsometricStaggered,
+        MapProjection::HexPointyTop,
+        MapProjection::HexFlatTop,
+    ];
+
+    #[test]
+    fn points_are_in_the_tile_they_are_drawn_in() {
+        for &projection in &PROJECTIONS {
+            let basis = projection.basis();
+            let inverse = basis.try_inverse().unwrap();
+            for y in 0..6 {
+                for x in 0..6 {
+                    let center =
+                        basis.transform_point(&projection.staggered_coordinates(x, y, 0.0));
+                    // Points inside the tile, in tile sizes from its center.
+                    for offset in &[
+                        [0.0, 0.0],
+                        [0.2, 0.2],
+                        [-0.2, 0.2],
+                        [0.1, -0.35],
+                        [-0.35, 0.0],
+                    ] {
+                        let offset = match projection {
+                            MapProjection::HexFlatTop => [offset[1], offset[0]],
+                            _ => *offset,
+                        };
+                        let point = Point3::new(center.x + offset[0], center.y + offset[1], 0.0);
+                        assert_eq!(
+                            projection.nearest_tile(&inverse.transform_point(&point)),
+                            (x, y),
+                            "{projection:?} {offset:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn tiles_interlock() {
+        let center = |projection: MapProjection, x, y| {
+            let point = projection
+                .basis()
+                .transform_point(&projection.staggered_coordinates(x, y, 0.0));
+            [point.x, point.y]
+        };
+        assert_eq!(center(MapProjection::IsometricDiamond, 1, 1), [0.0, -1.0]);
+        assert_eq!(center(MapProjection::IsometricStaggered, 0, 1), [0.5, -0.5]);
+        assert_eq!(center(MapProjection::HexPointyTop, 1, 1), [1.5, -0.75]);
+        assert_eq!(center(MapProjection::HexFlatTop, 1, 1), [0.75, -1.5]);
+    }
+
+    #[test]
+    fn tiles_are_drawn_back_to_front() {
+        let mut coordinates = vec![
+            Point3::new(1, 0, 0),
+            Point3::new(0, 1, 0),
+            Point3::new(2, 0, 0),
+            Point3::new(0, 0, 1),
+            Point3::new(0, 0, 0),
+        ];
+        coordinates.sort_by_key(|coord| MapProjection::IsometricDiamond.draw_order(coord));
+        assert_eq!(
+            coordinates,
+            vec![
+                Point3::new(0, 0, 0),
+                Point3::new(0, 1, 0),
+                Point3::new(1, 0, 0),
+                Point3::new(2, 0, 0),
+                Point3::new(0, 0, 1),
+            ]
+        );
+        coordinates.sort_by_key(|coord| MapProjection::HexFlatTop.draw_order(coord));
+        assert_eq!(
+            coordinates,
+            vec![
+                Point3::new(0, 0, 0),
+                Point3::new(2, 0, 0),
+                Point3::new(1, 0, 0),
+                Point3::new(0, 1, 0),
+                Point3::new(0, 0, 1),
+            ]
+        );
+        coordinates.sort_by_key(|coord| MapProjection::Square.draw_order(coord));
+        assert_eq!(
+            coordinates,
+            vec![
+                Point3::new(0, 0, 0),
+                Point3::new(1, 0, 0),
+                Point3::new(2, 0, 0),
+                Point3::new(0, 1, 0),
+                Point3::new(0, 0, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,507 @@
+//! Streaming of tile maps too large to keep in memory, split into chunks loaded around the camera.
+
+use crate::{
+    map::{Map, Tile, TileMap},
+    CoordinateEncoder, MapProjection, MortonEncoder2D,
+};
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::{
+        Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, Write, WriteExpect,
+        WriteStorage,
+    },
+    math::{Matrix4, Point2, Point3, Vector3},
+    shrev::EventChannel,
+    Transform,
+};
+use amethyst_rendy::{
+    camera::{ActiveCamera, Camera},
+    SpriteSheet,
+};
+use derivative::Derivative;
+use std::{collections::HashMap, marker::PhantomData};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Event sent by the `TileMapStreamer` to the `EventChannel<ChunkEvent>` when a chunk is loaded or
+/// unloaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkEvent {
+    /// The chunk was loaded into a new entity holding its `TileMap`.
+    Loaded {
+        /// Coordinates of the chunk.
+        chunk: Point2<i32>,
+        /// Entity of the chunk.
+        entity: Entity,
+    },
+    /// The chunk was unloaded, deleting its entity.
+    Unloaded {
+        /// Coordinates of the chunk.
+        chunk: Point2<i32>,
+        /// Entity of the chunk.
+        entity: Entity,
+    },
+}
+
+/// Loads or generates the tiles of the chunks streamed by a `TileMapStreamer`.
+///
+/// Implemented by closures filling the `TileMap` of a chunk.
+pub trait ChunkGenerator<T: Tile, E: CoordinateEncoder>: 'static + Send + Sync {
+    /// Fills the `TileMap` of a chunk, whose tiles are all `T::default()`.
+    ///
+    /// The tile at `(x, y, z)` of the chunk is the tile at
+    /// `(chunk.x * width + x, chunk.y * height + y, z)` of the whole map, given the dimensions of
+    /// the chunks.
+    fn load(&mut self, chunk: Point2<i32>, map: &mut TileMap<T, E>);
+
+    /// Called with the `TileMap` of a chunk before it's unloaded, e.g. to save its changes.
+    fn unload(&mut self, _chunk: Point2<i32>, _map: &TileMap<T, E>) {}
+}
+
+impl<T, E, F> ChunkGenerator<T, E> for F
+where
+    T: Tile,
+    E: CoordinateEncoder,
+    F: FnMut(Point2<i32>, &mut TileMap<T, E>) + 'static + Send + Sync,
+{
+    fn load(&mut self, chunk: Point2<i32>, map: &mut TileMap<T, E>) {
+        self(chunk, map);
+    }
+}
+
+/// Resource holding the chunks loaded by the `TileMapStreamer` of the `T` tiles, and locating the
+/// tiles of the whole map in the chunks.
+///
+/// The tile `(0, 0)` of the whole map is centered on the world origin, with x going right and y
+/// going down like the tiles of a `TileMap`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct TileChunks<T: Tile, E: CoordinateEncoder = MortonEncoder2D> {
+    chunk_dimensions: Vector3<u32>,
+    tile_dimensions: Vector3<u32>,
+    projection: MapProjection,
+    chunks: HashMap<Point2<i32>, Entity>,
+    #[derivative(Debug = "ignore")]
+    _marker: PhantomData<(T, E)>,
+}
+
+impl<T: Tile, E: CoordinateEncoder> TileChunks<T, E> {
+    fn new(
+        chunk_dimensions: Vector3<u32>,
+        tile_dimensions: Vector3<u32>,
+        projection: MapProjection,
+    ) -> Self {
+        Self {
+            chunk_dimensions,
+            tile_dimensions,
+            projection,
+            chunks: HashMap::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the dimensions of the chunks in tiles.
+    #[must_use]
+    pub fn chunk_dimensions(&self) -> &Vector3<u32> {
+        &self.chunk_dimensions
+    }
+
+    /// Returns the entity of a chunk, if it's loaded.
+    #[must_use]
+    pub fn entity(&self, chunk: Point2<i32>) -> Option<Entity> {
+        self.chunks.get(&chunk).copied()
+    }
+
+    /// Returns the loaded chunks and their entities.
+    pub fn iter(&self) -> impl Iterator<Item = (Point2<i32>, Entity)> + '_ {
+        self.chunks.iter().map(|(&chunk, &entity)| (chunk, entity))
+    }
+
+    /// Returns the chunk holding a tile of the whole map, and the coordinates of the tile in the
+    /// chunk.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn locate(&self, tile: Point2<i64>) -> (Point2<i32>, Point2<u32>) {
+        let width = i64::from(self.chunk_dimensions.x);
+        let height = i64::from(self.chunk_dimensions.y);
+        (
+            Point2::new(
+                tile.x.div_euclid(width) as i32,
+                tile.y.div_euclid(height) as i32,
+            ),
+            Point2::new(
+                tile.x.rem_euclid(width) as u32,
+                tile.y.rem_euclid(height) as u32,
+            ),
+        )
+    }
+
+    /// Returns the transform projecting the tile coordinates of the whole map into the world,
+    /// like `Map::transform` for the tiles of a `TileMap`.
+    #[allow(clippy::cast_precision_loss)]
+    fn world_transform(&self) -> Matrix4<f32> {
+        Matrix4::new_nonuniform_scaling(&Vector3::new(
+            self.tile_dimensions.x as f32,
+            self.tile_dimensions.y as f32,
+            self.tile_dimensions.z as f32,
+        )) * self.projection.basis()
+    }
+
+    /// Returns the chunk under a point of the world.
+    #[allow(clippy::cast_possible_truncation)]
+    fn chunk_at(&self, point: &Point3<f32>) -> Point2<i32> {
+        let inverse = self
+            .world_transform()
+            .try_inverse()
+            .expect("Tiles must have a size");
+        let (x, y) = self
+            .projection
+            .nearest_tile(&inverse.transform_point(point));
+        self.locate(Point2::new(x, y)).0
+    }
+
+    /// Returns the translation of the entity of a chunk, lining its tiles up with the other
+    /// chunks.
+    fn chunk_translation(&self, chunk: Point2<i32>, map: &TileMap<T, E>) -> Vector3<f32> {
+        let first_tile = self.projection.staggered_coordinates(
+            i64::from(chunk.x) * i64::from(self.chunk_dimensions.x),
+            i64::from(chunk.y) * i64::from(self.chunk_dimensions.y),
+            0.0,
+        );
+        self.world_transform().transform_point(&first_tile).coords
+            - map.to_world(&Point3::new(0, 0, 0), None)
+    }
+}
+
+/// Streams a tile map of unbounded size, split into chunks held by entities with a `TileMap`
+/// which are drawn like any other `TileMap`.
+///
+/// The chunks around the active camera are loaded by a `ChunkGenerator`, and the chunks which are
+/// further than the unload radius are unloaded. The loaded chunks are listed in the `TileChunks`
+/// resource, and their loading and unloading is sent to the `EventChannel<ChunkEvent>`.
+///
+/// This system should run before the `TransformSystem`, so that new chunks are drawn in place.
+/// Maps with staggered projections need chunks of even dimensions, so that the odd rows and
+/// columns line up across chunks.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct TileMapStreamer<T: Tile, G: ChunkGenerator<T, E>, E: CoordinateEncoder = MortonEncoder2D>
+{
+    #[derivative(Debug = "ignore")]
+    generator: G,
+    chunk_dimensions: Vector3<u32>,
+    tile_dimensions: Vector3<u32>,
+    projection: MapProjection,
+    sprite_sheet: Option<Handle<SpriteSheet>>,
+    load_radius: u32,
+    unload_radius: u32,
+    max_loads_per_frame: usize,
+    #[derivative(Debug = "ignore")]
+    _marker: PhantomData<(T, E)>,
+}
+
+impl<T: Tile, G: ChunkGenerator<T, E>, E: CoordinateEncoder> TileMapStreamer<T, G, E> {
+    /// Creates a streamer of chunks with the given dimensions in tiles, and with tiles of the given
+    /// dimensions, like `TileMap::new`. The chunks around the camera are loaded with the
+    /// generator.
+    #[must_use]
+    pub fn new(
+        chunk_dimensions: Vector3<u32>,
+        tile_dimensions: Vector3<u32>,
+        sprite_sheet: Option<Handle<SpriteSheet>>,
+        generator: G,
+    ) -> Self {
+        Self {
+            generator,
+            chunk_dimensions,
+            tile_dimensions,
+            projection: MapProjection::default(),
+            sprite_sheet,
+            load_radius: 1,
+            unload_radius: 2,
+            max_loads_per_frame: usize::MAX,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Lay the tiles of the chunks out with the given projection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the projection is staggered and the chunks have odd dimensions.
+    #[must_use]
+    pub fn with_projection(mut self, projection: MapProjection) -> Self {
+        let stagger = projection.stagger();
+        assert!(
+            (stagger[0] == 0.0 && stagger[1] == 0.0) || self.chunk_dimensions.y.is_multiple_of(2),
+            "Chunks of maps with staggered rows need an even height"
+        );
+        assert!(
+            (stagger[2] == 0.0 && stagger[3] == 0.0) || self.chunk_dimensions.x.is_multiple_of(2),
+            "Chunks of maps with staggered columns need an even width"
+        );
+        self.projection = projection;
+        self
+    }
+
+    /// Set how many chunks around the chunk under the camera are loaded, 1 by default for a
+    /// square of 3x3 chunks. Chunks are unloaded once they're further than the unload radius,
+    /// which is kept at least one chunk larger.
+    #[must_use]
+    pub fn with_load_radius(mut self, radius: u32) -> Self {
+        self.load_radius = radius;
+        self.unload_radius = self.unload_radius.max(radius + 1);
+        self
+    }
+
+    /// Set how many chunks around the chunk under the camera are kept loaded, 2 by default.
+    /// Keeping more chunks loaded than needed avoids reloading chunks when the camera moves
+    /// back and forth across the edge of a chunk.
+    #[must_use]
+    pub fn with_unload_radius(mut self, radius: u32) -> Self {
+        self.unload_radius = radius.max(self.load_radius);
+        self
+    }
+
+    /// Set how many chunks can be loaded in a frame, spreading the generation of chunks over
+    /// several frames. The chunks closer to the camera are loaded first.
+    #[must_use]
+    pub fn with_max_loads_per_frame(mut self, max_loads: usize) -> Self {
+        self.max_loads_per_frame = max_loads;
+        self
+    }
+}
+
+fn chunk_distance(a: Point2<i32>, b: Point2<i32>) -> u32 {
+    let x = (i64::from(a.x) - i64::from(b.x)).abs();
+    let y = (i64::from(a.y) - i64::from(b.y)).abs();
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let distance = x.max(y) as u32;
+    distance
+}
+
+impl<'a, T: Tile, G: ChunkGenerator<T, E>, E: CoordinateEncoder> System<'a>
+    for TileMapStreamer<T, G, E>
+{
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, TileMap<T, E>>,
+        WriteExpect<'a, TileChunks<T, E>>,
+        Write<'a, EventChannel<ChunkEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, active, cameras, mut transforms, mut maps, mut chunks, mut events): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("tile_map_streamer");
+
+        let camera = active
+            .entity
+            .filter(|entity| cameras.contains(*entity))
+            .or_else(|| {
+                (&entities, &cameras)
+                    .join()
+                    .map(|(entity, _)| entity)
+                    .next()
+            })
+            .and_then(|entity| transforms.get(entity));
+        let center = match camera {
+            Some(transform) => {
+                chunks.chunk_at(&transform.global_matrix().transform_point(&Point3::origin()))
+            }
+            None => return,
+        };
+
+        let unload_radius = self.unload_radius;
+        let unloaded = chunks
+            .chunks
+            .iter()
+            .filter(|(&chunk, _)| chunk_distance(chunk, center) > unload_radius)
+            .map(|(&chunk, &entity)| (chunk, entity))
+            .collect::<Vec<_>>();
+        for (chunk, entity) in unloaded {
+            chunks.chunks.remove(&chunk);
+            if let Some(map) = maps.get(entity) {
+                self.generator.unload(chunk, map);
+            }
+            entities
+                .delete(entity)
+                .expect("Chunk entity was deleted by another system");
+            events.single_write(ChunkEvent::Unloaded { chunk, entity });
+        }
+
+        #[allow(clippy::cast_possible_wrap)]
+        let radius = self.load_radius as i32;
+        let mut missing = (-radius..=radius)
+            .flat_map(|y| (-radius..=radius).map(move |x| Point2::new(x, y)))
+            .map(|offset| Point2::new(center.x + offset.x, center.y + offset.y))
+            .filter(|chunk| !chunks.chunks.contains_key(chunk))
+            .collect::<Vec<_>>();
+        missing.sort_by_key(|&chunk| chunk_distance(chunk, center));
+        for chunk in missing.into_iter().take(self.max_loads_per_frame) {
+            let mut map = TileMap::new(
+                chunks.chunk_dimensions,
+                chunks.tile_dimensions,
+                self.sprite_sheet.clone(),
+            )
+            .with_projection(chunks.projection);
+            self.generator.load(chunk, &mut map);
+
+            let mut transform = Transform::default();
+            transform.set_translation(chunks.chunk_translation(chunk, &map));
+            let entity = entities
+                .build_entity()
+                .with(map, &mut maps)
+                .with(transform, &mut transforms)
+                .build();
+            chunks.chunks.insert(chunk, entity);
+            events.single_write(ChunkEvent::Loaded { chunk, entity });
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        world.insert(TileChunks::<T, E>::new(
+            self.chunk_dimensions,
+            self.tile_dimensions,
+            self.projection,
+        ));
+        Self::SystemData::setup(world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, ReaderId, RunNow, WorldExt};
+    use std::collections::HashSet;
+
+    #[derive(Clone, Debug, Default)]
+    struct ChunkTile(Option<Point2<i32>>);
+
+    impl Tile for ChunkTile {}
+
+    fn streamer_world(
+        projection: MapProjection,
+    ) -> (
+        World,
+        TileMapStreamer<ChunkTile, impl ChunkGenerator<ChunkTile, MortonEncoder2D>>,
+        Entity,
+        ReaderId<ChunkEvent>,
+    ) {
+        let mut world = World::new();
+        let mut streamer = TileMapStreamer::new(
+            Vector3::new(4, 4, 1),
+            Vector3::new(16, 16, 1),
+            None,
+            |chunk: Point2<i32>, map: &mut TileMap<ChunkTile>| {
+                *crate::MapStorage::get_mut(map, &Point3::new(0, 0, 0)).unwrap() =
+                    ChunkTile(Some(chunk));
+            },
+        )
+        .with_projection(projection);
+        RunNow::setup(&mut streamer, &mut world);
+        let reader = world
+            .write_resource::<EventChannel<ChunkEvent>>()
+            .register_reader();
+        let camera = world
+            .create_entity()
+            .with(Camera::standard_2d(100.0, 100.0))
+            .with(Transform::default())
+            .build();
+        (world, streamer, camera, reader)
+    }
+
+    fn move_camera(world: &mut World, camera: Entity, x: f32, y: f32) {
+        let mut transforms = world.write_storage::<Transform>();
+        let transform = transforms.get_mut(camera).unwrap();
+        transform.set_translation_xyz(x, y, 0.0);
+        transform.copy_local_to_global();
+    }
+
+    fn loaded(world: &World) -> HashSet<Point2<i32>> {
+        world
+            .read_resource::<TileChunks<ChunkTile>>()
+            .iter()
+            .map(|(chunk, _)| chunk)
+            .collect()
+    }
+
+    fn square(x: i32, y: i32, radius: i32) -> HashSet<Point2<i32>> {
+        (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| Point2::new(x + dx, y + dy)))
+            .collect()
+    }
+
+    #[test]
+    fn chunks_are_streamed_around_the_camera() {
+        let (mut world, mut streamer, camera, mut reader) = streamer_world(MapProjection::Square);
+        streamer.run_now(&world);
+        world.maintain();
+        assert_eq!(loaded(&world), square(0, 0, 1));
+        assert_eq!(
+            world
+                .read_resource::<EventChannel<ChunkEvent>>()
+                .read(&mut reader)
+                .count(),
+            9
+        );
+
+        // Two chunks to the right and one chunk down, as tile y coordinates go down.
+        move_camera(&mut world, camera, 2.0 * 64.0, -64.0);
+        streamer.run_now(&world);
+        world.maintain();
+        let mut expected = square(2, 1, 1);
+        expected.extend(square(0, 0, 1).into_iter().filter(|chunk| chunk.x >= 0));
+        assert_eq!(loaded(&world), expected);
+        let events = world
+            .read_resource::<EventChannel<ChunkEvent>>()
+            .read(&mut reader)
+            .copied()
+            .collect::<Vec<_>>();
+        let unloaded = events
+            .iter()
+            .filter_map(|event| match event {
+                ChunkEvent::Unloaded { chunk, entity } => Some((*chunk, *entity)),
+                ChunkEvent::Loaded { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(unloaded.len(), 3);
+        assert!(unloaded
+            .iter()
+            .all(|(chunk, entity)| chunk.x == -1 && !world.is_alive(*entity)));
+    }
+
+    #[test]
+    fn chunks_line_up_into_one_map() {
+        for &projection in &[MapProjection::Square, MapProjection::HexFlatTop] {
+            let (mut world, mut streamer, _, _) = streamer_world(projection);
+            streamer.run_now(&world);
+            world.maintain();
+
+            let chunks = world.read_resource::<TileChunks<ChunkTile>>();
+            let maps = world.read_storage::<TileMap<ChunkTile>>();
+            let transforms = world.read_storage::<Transform>();
+            let world_transform = chunks.world_transform();
+            for (chunk, entity) in chunks.iter() {
+                let map = maps.get(entity).unwrap();
+                let tile = crate::MapStorage::get(map, &Point3::new(0, 0, 0)).unwrap();
+                assert_eq!(tile.0, Some(chunk));
+
+                let transform = transforms.get(entity).unwrap();
+                let local = Point3::new(3, 1, 0);
+                let global = Point2::new(i64::from(chunk.x) * 4 + 3, i64::from(chunk.y) * 4 + 1);
+                assert_eq!(chunks.locate(global), (chunk, Point2::new(3, 1)));
+                let expected = world_transform
+                    .transform_point(&projection.staggered_coordinates(global.x, global.y, 0.0));
+                let position = map.to_world(&local, None) + transform.translation();
+                assert!((position - expected.coords).norm() < 1e-3);
+            }
+        }
+    }
+}
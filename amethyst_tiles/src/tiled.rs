@@ -0,0 +1,1336 @@
+//! Importer of maps made with the [Tiled](https://www.mapeditor.org/) map editor.
+//!
+//! `TmxFormat` loads a `.tmx` map as a `TiledMap` asset, along with the external `.tsx` tilesets it
+//! references. The images of the tilesets are loaded as sprite sheets with
+//! `TiledMap::load_sprite_sheets`, and the tile layers are turned into `TileMap`s with
+//! `TiledMap::tile_map`. Animated tiles are played by inserting their animations into the
+//! `TileAnimations` resource:
+//!
+//! ```rust,no_run
+//! # use amethyst_assets::{AssetStorage, Loader, ProgressCounter};
+//! # use amethyst_core::ecs::{Builder, World, WorldExt};
+//! # use amethyst_rendy::{SpriteSheet, Texture};
+//! # use amethyst_tiles::{
+//! #     tiled::{TiledMap, TiledTile},
+//! #     MortonEncoder2D, TileAnimations, TileMap,
+//! # };
+//! # fn spawn_map(world: &mut World, map: &TiledMap) {
+//! let mut progress = ProgressCounter::new();
+//! let sheets = map.load_sprite_sheets(
+//!     &world.read_resource::<Loader>(),
+//!     &world.read_resource::<AssetStorage<Texture>>(),
+//!     &world.read_resource::<AssetStorage<SpriteSheet>>(),
+//!     &mut progress,
+//! );
+//! map.insert_animations(&sheets, &mut world.write_resource::<TileAnimations>());
+//! for (tileset, sheet) in sheets.into_iter().enumerate() {
+//!     let tiles: TileMap<TiledTile, MortonEncoder2D> =
+//!         map.tile_map(tileset, sheet, TiledTile::from);
+//!     world.create_entity().with(tiles).build();
+//! }
+//! # }
+//! ```
+//!
+//! The custom properties of the tiles in Tiled are copied into the data layers of the `TileMap`s,
+//! so that e.g. a `solid` property can be queried with `TileMap::data`. Tile layers with a `data`
+//! property set to true only fill the data layers and aren't drawn.
+//!
+//! Maps of a fixed size are supported, with tile layer data encoded as CSV, XML or base64,
+//! uncompressed or compressed with zlib or gzip. Staggered and hexagonal maps need an odd stagger
+//! index, and hexagons sides half as long as the tiles, to be laid out by a `MapProjection`.
+
+use crate::{
+    animation::{TileAnimation, TileAnimations},
+    autotile::{
+        AutoTile, AutoTileMode, AutoTileRules, TerrainRule, NORTH_EAST, NORTH_WEST, SOUTH_EAST,
+        SOUTH_WEST,
+    },
+    map::{create_transform, MapStorage, Tile, TileMap},
+    CoordinateEncoder, LayerParallax, MapProjection, TileValue,
+};
+use amethyst_assets::{
+    Asset, AssetStorage, Format, FormatValue, Handle, Loader, ProgressCounter, Source,
+};
+use amethyst_core::{
+    ecs::{VecStorage, World},
+    math::{Point3, Vector2, Vector3},
+};
+use amethyst_error::{format_err, Error, ResultExt};
+use amethyst_rendy::{formats::texture::ImageFormat, palette::Srgba, Sprite, SpriteSheet, Texture};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::TryFrom, str::FromStr, sync::Arc};
+use xml::reader::{EventReader, XmlEvent};
+
+/// Flag of a global tile id set when the tile is flipped horizontally.
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+/// Flag of a global tile id set when the tile is flipped vertically.
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+/// Flag of a global tile id set when the tile is flipped along its diagonal.
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Flags of a global tile id which aren't part of the id.
+pub const FLIP_FLAGS: u32 = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY;
+
+/// Custom properties of a map, layer, tile or object, by name.
+pub type TiledProperties = HashMap<String, TiledProperty>;
+
+/// Value of a custom property.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TiledProperty {
+    /// A `string` property.
+    String(String),
+    /// An `int` property, or an `object` property referencing the object with this id.
+    Int(i64),
+    /// A `float` property.
+    Float(f64),
+    /// A `bool` property.
+    Bool(bool),
+    /// A `color` property.
+    Color(Srgba),
+    /// A `file` property, with the path resolved relative to the asset directory.
+    File(String),
+}
+
+impl TiledProperty {
+    /// Converts the property to the value of a tile in a data layer of a `TileMap`, with files as
+    /// their path. Colors have no such value.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_tile_value(&self) -> Option<TileValue> {
+        match self {
+            Self::String(value) | Self::File(value) => Some(TileValue::String(value.clone())),
+            Self::Int(value) => Some(TileValue::Int(*value)),
+            Self::Float(value) => Some(TileValue::Float(*value as f32)),
+            Self::Bool(value) => Some(TileValue::Bool(*value)),
+            Self::Color(_) => None,
+        }
+    }
+}
+
+/// Image of a tileset, with the path resolved relative to the asset directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TiledImage {
+    /// Asset path of the image.
+    pub source: String,
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+}
+
+/// Data attached to a tile of a tileset in Tiled.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TiledTileData {
+    /// Type, or class, of the tile.
+    pub ty: Option<String>,
+    /// Custom properties of the tile.
+    pub properties: TiledProperties,
+    /// Animation of the tile, with frames numbered like the sprites of the tileset.
+    pub animation: Option<TileAnimation>,
+}
+
+/// Tileset of a map, whose image is split into a grid of tiles.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TiledTileset {
+    /// Global id of the first tile of the tileset.
+    pub first_gid: u32,
+    /// Name of the tileset.
+    pub name: String,
+    /// Width of the tiles in pixels.
+    pub tile_width: u32,
+    /// Height of the tiles in pixels.
+    pub tile_height: u32,
+    /// Pixels between the tiles of the image.
+    pub spacing: u32,
+    /// Pixels around the tiles of the image.
+    pub margin: u32,
+    /// Number of tiles of the tileset.
+    pub tile_count: u32,
+    /// Number of tile columns of the image.
+    pub columns: u32,
+    /// Image of the tileset, `None` for collections of images, which aren't supported.
+    pub image: Option<TiledImage>,
+    /// Data of the tiles with a type or custom properties, by the id of the tile in the tileset.
+    pub tiles: HashMap<u32, TiledTileData>,
+    /// Custom properties of the tileset.
+    pub properties: TiledProperties,
+}
+
+impl TiledTileset {
+    /// Returns the sprites of the tiles, in the order of their ids.
+    #[must_use]
+    pub fn sprites(&self) -> Vec<Sprite> {
+        let columns = self.columns.max(1);
+        // Tilesets without an image have no sprites.
+        self.image
+            .iter()
+            .flat_map(|image| {
+                (0..self.tile_count).map(move |id| {
+                    let x = self.margin + (id % columns) * (self.tile_width + self.spacing);
+                    let y = self.margin + (id / columns) * (self.tile_height + self.spacing);
+                    Sprite::from_pixel_values(
+                        image.width,
+                        image.height,
+                        self.tile_width,
+                        self.tile_height,
+                        x,
+                        y,
+                        [0.0; 2],
+                        false,
+                        false,
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Layer of tiles covering the map.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TiledTileLayer {
+    /// Name of the layer.
+    pub name: String,
+    /// Whether the layer is shown.
+    pub visible: bool,
+    /// Opacity of the layer, from 0.0 to 1.0.
+    pub opacity: f32,
+    /// Parallax factor of the layer along x and y.
+    pub parallax: [f32; 2],
+    /// Offset of the layer in pixels, to the right and down.
+    pub offset: [f32; 2],
+    /// Global ids of the tiles row by row, from the top left corner, with 0 for empty tiles. The
+    /// upper bits hold the `FLIP_FLAGS` of the tile.
+    pub tiles: Vec<u32>,
+    /// Custom properties of the layer.
+    pub properties: TiledProperties,
+}
+
+/// Shape of an object.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TiledShape {
+    /// A rectangle of the size of the object, also used for tile objects.
+    Rectangle,
+    /// An ellipse filling the size of the object.
+    Ellipse,
+    /// A point at the position of the object.
+    Point,
+    /// A closed polygon, with points relative to the position of the object.
+    Polygon(Vec<[f32; 2]>),
+    /// An open polyline, with points relative to the position of the object.
+    Polyline(Vec<[f32; 2]>),
+}
+
+/// Object placed on an object layer, e.g. to spawn an entity.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TiledObject {
+    /// Unique id of the object in the map.
+    pub id: u32,
+    /// Name of the object.
+    pub name: String,
+    /// Type, or class, of the object.
+    pub ty: String,
+    /// Horizontal position in pixels from the left of the map.
+    pub x: f32,
+    /// Vertical position in pixels from the top of the map. Tile objects are positioned by their
+    /// bottom left corner, other objects by their top left corner.
+    pub y: f32,
+    /// Width in pixels.
+    pub width: f32,
+    /// Height in pixels.
+    pub height: f32,
+    /// Clockwise rotation in degrees around the position.
+    pub rotation: f32,
+    /// Global id of the tile shown by a tile object, with the `FLIP_FLAGS`.
+    pub gid: Option<u32>,
+    /// Whether the object is shown.
+    pub visible: bool,
+    /// Shape of the object.
+    pub shape: TiledShape,
+    /// Custom properties of the object.
+    pub properties: TiledProperties,
+}
+
+/// Layer of objects.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TiledObjectGroup {
+    /// Name of the layer.
+    pub name: String,
+    /// Whether the layer is shown.
+    pub visible: bool,
+    /// Objects of the layer.
+    pub objects: Vec<TiledObject>,
+    /// Custom properties of the layer.
+    pub properties: TiledProperties,
+}
+
+/// Map made with Tiled, loaded with the `TmxFormat`.
+///
+/// The layers of group layers are flattened into `layers` and `object_groups`, in the order of the
+/// map.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TiledMap {
+    /// Width of the map in tiles.
+    pub width: u32,
+    /// Height of the map in tiles.
+    pub height: u32,
+    /// Width of the tiles in pixels.
+    pub tile_width: u32,
+    /// Height of the tiles in pixels.
+    pub tile_height: u32,
+    /// Layout of the tiles, from the orientation of the map.
+    pub projection: MapProjection,
+    /// Tilesets of the map, sorted by their first global id.
+    pub tilesets: Vec<TiledTileset>,
+    /// Tile layers from bottom to top.
+    pub layers: Vec<TiledTileLayer>,
+    /// Object layers from bottom to top.
+    pub object_groups: Vec<TiledObjectGroup>,
+    /// Custom properties of the map.
+    pub properties: TiledProperties,
+}
+
+impl Asset for TiledMap {
+    const NAME: &'static str = "tiles::TiledMap";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+/// Tile of a layer passed to the function creating the tiles of `TiledMap::tile_map`.
+#[derive(Clone, Copy, Debug)]
+pub struct TiledTileRef<'a> {
+    /// Global id of the tile, without the `FLIP_FLAGS`.
+    pub gid: u32,
+    /// Index of the tile in the sprite sheet of its tileset.
+    pub sprite: usize,
+    /// `FLIP_FLAGS` of the tile.
+    pub flip: u32,
+    /// Layer of the tile.
+    pub layer: &'a TiledTileLayer,
+    /// Type and custom properties of the tile, if any.
+    pub data: Option<&'a TiledTileData>,
+}
+
+/// Tile of a `TileMap` created from a `TiledMap`.
+///
+/// The custom properties of the tile can be looked up with `TiledMap::tile_properties` from the
+/// global id, e.g. in the `Tile` implementation of a wrapping type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TiledTile {
+    /// Index of the sprite of the tile, `None` for empty tiles.
+    pub sprite: Option<usize>,
+    /// Global id of the tile, without the `FLIP_FLAGS`, or 0 for empty tiles.
+    pub gid: u32,
+}
+
+impl<'a> From<TiledTileRef<'a>> for TiledTile {
+    fn from(tile: TiledTileRef<'a>) -> Self {
+        Self {
+            sprite: Some(tile.sprite),
+            gid: tile.gid,
+        }
+    }
+}
+
+impl Tile for TiledTile {
+    fn sprite(&self, _: Point3<u32>, _: &World) -> Option<usize> {
+        self.sprite
+    }
+}
+
+/// The `gid` keeps referring to the tile placed in Tiled, whose properties hold its terrain.
+impl AutoTile for TiledTile {
+    fn set_sprite(&mut self, sprite: Option<usize>) {
+        self.sprite = sprite;
+    }
+}
+
+impl TiledMap {
+    /// Returns the index of the tileset holding the tile with the given global id, and the id of
+    /// the tile in the tileset.
+    #[must_use]
+    pub fn tileset_of(&self, gid: u32) -> Option<(usize, u32)> {
+        let gid = gid & !FLIP_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+        let index = self
+            .tilesets
+            .iter()
+            .rposition(|tileset| tileset.first_gid <= gid)?;
+        Some((index, gid - self.tilesets[index].first_gid))
+    }
+
+    /// Returns the custom properties of the tile with the given global id, if it has any.
+    #[must_use]
+    pub fn tile_properties(&self, gid: u32) -> Option<&TiledProperties> {
+        let (tileset, id) = self.tileset_of(gid)?;
+        self.tilesets[tileset]
+            .tiles
+            .get(&id)
+            .map(|data| &data.properties)
+    }
+
+    /// Returns the auto-tiling rules of a tileset, from its tiles with integer `terrain` and
+    /// `terrain_mask` properties, drawing the tiles of that terrain whose neighbors match the mask.
+    ///
+    /// Terrains with corner bits in their masks are blob tiled, and the others only look at the
+    /// edges. Masks without a tile are drawn with the tile of the highest mask, which is the one
+    /// surrounded by the terrain.
+    #[must_use]
+    pub fn auto_tile_rules(&self, tileset: usize) -> AutoTileRules {
+        let mut terrains = HashMap::<i64, Vec<(u8, usize)>>::new();
+        for (&id, data) in self
+            .tilesets
+            .get(tileset)
+            .iter()
+            .flat_map(|tileset| &tileset.tiles)
+        {
+            let property = |name| match data.properties.get(name) {
+                Some(TiledProperty::Int(value)) => Some(*value),
+                _ => None,
+            };
+            if let (Some(terrain), Some(mask)) = (property("terrain"), property("terrain_mask")) {
+                if let Ok(mask) = u8::try_from(mask) {
+                    terrains
+                        .entry(terrain)
+                        .or_default()
+                        .push((mask, id as usize));
+                }
+            }
+        }
+        let mut rules = AutoTileRules::new();
+        for (terrain, sprites) in terrains {
+            let blob = sprites
+                .iter()
+                .any(|(mask, _)| mask & (NORTH_EAST | SOUTH_EAST | SOUTH_WEST | NORTH_WEST) != 0);
+            let mode = if blob {
+                AutoTileMode::Blob
+            } else {
+                AutoTileMode::Edges
+            };
+            let default = sprites.iter().max().map_or(0, |(_, sprite)| *sprite);
+            let rule = sprites
+                .into_iter()
+                .fold(TerrainRule::new(mode, default), |rule, (mask, sprite)| {
+                    rule.with_sprite(mask, sprite)
+                });
+            rules.insert(terrain, rule);
+        }
+        rules
+    }
+
+    /// Returns the position of an object in the world space of the `TileMap`s created from this
+    /// map, before the `Transform` of their entity.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn object_position(&self, object: &TiledObject) -> Vector3<f32> {
+        let transform = create_transform(
+            &Vector3::new(self.width, self.height, 1),
+            &Vector3::new(self.tile_width, self.tile_height, 1),
+            self.projection,
+        );
+        let width = self.tile_width as f32;
+        let height = self.tile_height as f32;
+        if self.projection == MapProjection::IsometricDiamond {
+            // Objects of isometric maps are positioned along the axes of the tiles, in pixels of
+            // the tile height, from the top corner of the map.
+            let tile = Point3::new(object.x / height - 0.5, 0.5 - object.y / height, 0.0);
+            return transform.transform_point(&tile).coords;
+        }
+        // Tiles are centered on their coordinates, with the first one at the top left.
+        transform.transform_point(&Point3::origin()).coords
+            + Vector3::new(object.x - width / 2.0, height / 2.0 - object.y, 0.0)
+    }
+
+    /// Loads the images of the tilesets as sprite sheets, returning the handle of every tileset in
+    /// the order of `tilesets`, or `None` for the tilesets without an image.
+    pub fn load_sprite_sheets(
+        &self,
+        loader: &Loader,
+        textures: &AssetStorage<Texture>,
+        sprite_sheets: &AssetStorage<SpriteSheet>,
+        progress: &mut ProgressCounter,
+    ) -> Vec<Option<Handle<SpriteSheet>>> {
+        self.tilesets
+            .iter()
+            .map(|tileset| {
+                let image = tileset.image.as_ref()?;
+                let texture = loader.load(
+                    image.source.as_str(),
+                    ImageFormat::default(),
+                    &mut *progress,
+                    textures,
+                );
+                let sheet = SpriteSheet {
+                    texture,
+                    sprites: tileset.sprites(),
+                    durations: Vec::new(),
+                };
+                Some(loader.load_from_data(sheet, &mut *progress, sprite_sheets))
+            })
+            .collect()
+    }
+
+    /// Animates the tiles of the tilesets with an animation in Tiled, with the sprite sheets
+    /// returned by `load_sprite_sheets`.
+    pub fn insert_animations(
+        &self,
+        sprite_sheets: &[Option<Handle<SpriteSheet>>],
+        animations: &mut TileAnimations,
+    ) {
+        let tilesets = self
+            .tilesets
+            .iter()
+            .zip(sprite_sheets)
+            .filter_map(|(tileset, sheet)| Some((tileset, sheet.as_ref()?)));
+        for (tileset, sheet) in tilesets {
+            for (&id, data) in &tileset.tiles {
+                if let Some(animation) = &data.animation {
+                    animations.insert(sheet, id as usize, animation.clone());
+                }
+            }
+        }
+    }
+
+    /// Creates a `TileMap` holding the tiles of the given tileset, with every tile layer as a z
+    /// level from the bottom. The tiles are created by `create_tile`, and the other tiles are left
+    /// to their default.
+    ///
+    /// A `TileMap` draws the sprites of a single sprite sheet, so maps using several tilesets are
+    /// drawn with a `TileMap` for each. The custom properties of the tiles of every tileset are
+    /// copied into the data layers named after them, so each of these maps holds the data of the
+    /// whole map. Tile layers with a `data` property set to true only fill the data layers.
+    ///
+    /// The parallax factors and offsets of the layers become the `LayerParallax` of their level.
+    /// Terrains painted with the tiles of `auto_tile_rules` are drawn with their edges once the
+    /// rules are applied to the map.
+    pub fn tile_map<'a, T: Tile, E: CoordinateEncoder>(
+        &'a self,
+        tileset: usize,
+        sprite_sheet: Option<Handle<SpriteSheet>>,
+        mut create_tile: impl FnMut(TiledTileRef<'a>) -> T,
+    ) -> TileMap<T, E> {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut map = TileMap::new(
+            Vector3::new(self.width, self.height, self.layers.len().max(1) as u32),
+            Vector3::new(self.tile_width, self.tile_height, 1),
+            sprite_sheet,
+        )
+        .with_projection(self.projection);
+        for (z, layer) in self.layers.iter().enumerate() {
+            let parallax = LayerParallax::new(Vector2::new(layer.parallax[0], layer.parallax[1]))
+                .with_scroll(Vector2::new(layer.offset[0], -layer.offset[1]));
+            if parallax != LayerParallax::default() {
+                #[allow(clippy::cast_possible_truncation)]
+                map.set_layer_parallax(z as u32, parallax);
+            }
+            let drawn = layer.properties.get("data") != Some(&TiledProperty::Bool(true));
+            let tiles = layer
+                .tiles
+                .iter()
+                .enumerate()
+                .filter_map(|(index, &gid)| Some((index, gid, self.tileset_of(gid)?)));
+            for (index, gid, (tile_tileset, id)) in tiles {
+                let data = self.tilesets[tile_tileset].tiles.get(&id);
+                #[allow(clippy::cast_possible_truncation)]
+                let coordinates = Point3::new(
+                    index as u32 % self.width,
+                    index as u32 / self.width,
+                    z as u32,
+                );
+                for (name, property) in data.iter().flat_map(|data| &data.properties) {
+                    if let Some(value) = property.to_tile_value() {
+                        map.set_data(name, &coordinates, value);
+                    }
+                }
+                if !drawn || tile_tileset != tileset {
+                    continue;
+                }
+                let tile = TiledTileRef {
+                    gid: gid & !FLIP_FLAGS,
+                    sprite: id as usize,
+                    flip: gid & FLIP_FLAGS,
+                    layer,
+                    data,
+                };
+                if let Some(slot) = map.get_mut(&coordinates) {
+                    *slot = create_tile(tile);
+                }
+            }
+        }
+        map
+    }
+}
+
+/// Loads a `TiledMap` from a `.tmx` map saved by Tiled. External `.tsx` tilesets and the images
+/// of the tilesets are resolved relative to the file referencing them.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct TmxFormat;
+
+impl Format<TiledMap> for TmxFormat {
+    fn name(&self) -> &'static str {
+        "TMX"
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        _create_reload: Option<Box<dyn Format<TiledMap>>>,
+    ) -> Result<FormatValue<TiledMap>, Error> {
+        let bytes = source.load(&name)?;
+        let map = parse_map(&bytes, &name, &*source)
+            .with_context(|_| format_err!("Failed to load Tiled map {}", name))?;
+        Ok(FormatValue::data(map))
+    }
+}
+
+/// Parses a `.tmx` map, loading its external tilesets from the source.
+fn parse_map(bytes: &[u8], path: &str, source: &dyn Source) -> Result<TiledMap, Error> {
+    let root = Element::parse(bytes)?;
+    if root.name != "map" {
+        return Err(format_err!("Expected a map, found {}", root.name));
+    }
+    if root.attr("infinite") == Some("1") {
+        return Err(format_err!("Infinite maps aren't supported"));
+    }
+
+    let mut map = TiledMap {
+        width: root.required("width")?,
+        height: root.required("height")?,
+        tile_width: root.required("tilewidth")?,
+        tile_height: root.required("tileheight")?,
+        projection: parse_projection(&root)?,
+        tilesets: Vec::new(),
+        layers: Vec::new(),
+        object_groups: Vec::new(),
+        properties: parse_properties(&root, path)?,
+    };
+    for element in root.children_named("tileset") {
+        let first_gid = element.required("firstgid")?;
+        let tileset = if let Some(tileset_path) = element.attr("source") {
+            let tileset_path = resolve_path(path, tileset_path);
+            let bytes = source
+                .load(&tileset_path)
+                .with_context(|_| format_err!("Failed to load tileset {}", tileset_path))?;
+            let root = Element::parse(&bytes)?;
+            parse_tileset(&root, first_gid, &tileset_path)?
+        } else {
+            parse_tileset(element, first_gid, path)?
+        };
+        map.tilesets.push(tileset);
+    }
+    map.tilesets.sort_by_key(|tileset| tileset.first_gid);
+    parse_layers(&root, &mut map, path)?;
+    Ok(map)
+}
+
+fn parse_projection(root: &Element) -> Result<MapProjection, Error> {
+    let orientation = root.attr("orientation").unwrap_or("orthogonal");
+    let axis = root.attr("staggeraxis").unwrap_or("y");
+    if (orientation == "staggered" || orientation == "hexagonal")
+        && root.attr("staggerindex") == Some("even")
+    {
+        return Err(format_err!("Only odd stagger indices are supported"));
+    }
+    let projection = match (orientation, axis) {
+        ("orthogonal", _) => MapProjection::Square,
+        ("isometric", _) => MapProjection::IsometricDiamond,
+        ("staggered", "y") => MapProjection::IsometricStaggered,
+        ("hexagonal", "y") => MapProjection::HexPointyTop,
+        ("hexagonal", "x") => MapProjection::HexFlatTop,
+        _ => {
+            return Err(format_err!(
+                "Unsupported {} map with stagger axis {}",
+                orientation,
+                axis
+            ))
+        }
+    };
+    if orientation == "hexagonal" {
+        let side: u32 = root.required("hexsidelength")?;
+        let tile_size: u32 = root.required(if axis == "y" {
+            "tileheight"
+        } else {
+            "tilewidth"
+        })?;
+        if side * 2 != tile_size {
+            return Err(format_err!(
+                "Hexagon sides of {} pixels aren't half as long as the tiles",
+                side
+            ));
+        }
+    }
+    Ok(projection)
+}
+
+fn parse_tileset(element: &Element, first_gid: u32, path: &str) -> Result<TiledTileset, Error> {
+    let image = element
+        .children_named("image")
+        .next()
+        .map(|image| {
+            Ok::<_, Error>(TiledImage {
+                source: resolve_path(path, image.required::<String>("source")?.as_str()),
+                width: image.required("width")?,
+                height: image.required("height")?,
+            })
+        })
+        .transpose()?;
+    let tile_width = element.required("tilewidth")?;
+    let spacing = element.optional("spacing")?.unwrap_or(0);
+    let margin = element.optional("margin")?.unwrap_or(0);
+    let columns = match (element.optional("columns")?, &image) {
+        (Some(columns), _) => columns,
+        (None, Some(image)) => (image.width - margin * 2 + spacing) / (tile_width + spacing),
+        (None, None) => 0,
+    };
+    let mut tiles = HashMap::new();
+    for tile in element.children_named("tile") {
+        let data = TiledTileData {
+            ty: tile
+                .attr("type")
+                .or_else(|| tile.attr("class"))
+                .map(Into::into),
+            properties: parse_properties(tile, path)?,
+            animation: tile
+                .children_named("animation")
+                .next()
+                .map(parse_animation)
+                .transpose()?,
+        };
+        tiles.insert(tile.required("id")?, data);
+    }
+    Ok(TiledTileset {
+        first_gid,
+        name: element.attr("name").unwrap_or_default().into(),
+        tile_width,
+        tile_height: element.required("tileheight")?,
+        spacing,
+        margin,
+        tile_count: element.optional("tilecount")?.unwrap_or(0),
+        columns,
+        image,
+        tiles,
+        properties: parse_properties(element, path)?,
+    })
+}
+
+/// Parses the layers of the map or of a group layer.
+fn parse_layers(parent: &Element, map: &mut TiledMap, path: &str) -> Result<(), Error> {
+    for element in &parent.children {
+        match element.name.as_str() {
+            "layer" => {
+                let tiles = parse_tile_data(element)?;
+                let expected = map.width as usize * map.height as usize;
+                if tiles.len() != expected {
+                    return Err(format_err!(
+                        "Layer has {} tiles instead of {}",
+                        tiles.len(),
+                        expected
+                    ));
+                }
+                map.layers.push(TiledTileLayer {
+                    name: element.attr("name").unwrap_or_default().into(),
+                    visible: element.attr("visible") != Some("0"),
+                    opacity: element.optional("opacity")?.unwrap_or(1.0),
+                    parallax: [
+                        element.optional("parallaxx")?.unwrap_or(1.0),
+                        element.optional("parallaxy")?.unwrap_or(1.0),
+                    ],
+                    offset: [
+                        element.optional("offsetx")?.unwrap_or(0.0),
+                        element.optional("offsety")?.unwrap_or(0.0),
+                    ],
+                    tiles,
+                    properties: parse_properties(element, path)?,
+                });
+            }
+            "objectgroup" => {
+                let objects = element
+                    .children_named("object")
+                    .map(|object| parse_object(object, path))
+                    .collect::<Result<_, _>>()?;
+                map.object_groups.push(TiledObjectGroup {
+                    name: element.attr("name").unwrap_or_default().into(),
+                    visible: element.attr("visible") != Some("0"),
+                    objects,
+                    properties: parse_properties(element, path)?,
+                });
+            }
+            "group" => parse_layers(element, map, path)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn parse_tile_data(layer: &Element) -> Result<Vec<u32>, Error> {
+    let data = layer
+        .children_named("data")
+        .next()
+        .ok_or_else(|| format_err!("Layer has no data"))?;
+    if data.children_named("chunk").next().is_some() {
+        return Err(format_err!("Infinite maps aren't supported"));
+    }
+    match data.attr("encoding") {
+        None => data
+            .children_named("tile")
+            .map(|tile| Ok(tile.optional("gid")?.unwrap_or(0)))
+            .collect(),
+        Some("csv") => data
+            .text
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| {
+                gid.parse()
+                    .with_context(|_| format_err!("Invalid tile {}", gid))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = base64::decode(data.text.trim())
+                .with_context(|_| format_err!("Invalid base64 tile data"))?;
+            let bytes = match data.attr("compression") {
+                None => bytes,
+                Some("zlib") => miniz_oxide::inflate::decompress_to_vec_zlib(&bytes)
+                    .map_err(|err| format_err!("Invalid zlib tile data: {:?}", err))?,
+                Some("gzip") => miniz_oxide::inflate::decompress_to_vec(gzip_deflate_data(&bytes)?)
+                    .map_err(|err| format_err!("Invalid gzip tile data: {:?}", err))?,
+                Some(compression) => {
+                    return Err(format_err!("Unsupported compression {}", compression))
+                }
+            };
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(encoding) => Err(format_err!("Unsupported encoding {}", encoding)),
+    }
+}
+
+/// Returns the deflate stream of gzip data, skipping the header.
+fn gzip_deflate_data(bytes: &[u8]) -> Result<&[u8], Error> {
+    const EXTRA: u8 = 4;
+    const NAME: u8 = 8;
+    const COMMENT: u8 = 16;
+    const HEADER_CRC: u8 = 2;
+
+    if bytes.len() < 10 || bytes[0..3] != [0x1f, 0x8b, 8] {
+        return Err(format_err!("Invalid gzip header"));
+    }
+    let flags = bytes[3];
+    let mut start = 10;
+    if flags & EXTRA != 0 {
+        let len = bytes
+            .get(start..start + 2)
+            .ok_or_else(|| format_err!("Invalid gzip header"))?;
+        start += 2 + usize::from(u16::from_le_bytes([len[0], len[1]]));
+    }
+    for flag in &[NAME, COMMENT] {
+        if flags & flag != 0 {
+            let end = bytes
+                .get(start..)
+                .and_then(|rest| rest.iter().position(|&byte| byte == 0))
+                .ok_or_else(|| format_err!("Invalid gzip header"))?;
+            start += end + 1;
+        }
+    }
+    if flags & HEADER_CRC != 0 {
+        start += 2;
+    }
+    bytes
+        .get(start..)
+        .ok_or_else(|| format_err!("Invalid gzip header"))
+}
+
+/// Parses the frames of an animated tile, whose durations are in milliseconds.
+fn parse_animation(element: &Element) -> Result<TileAnimation, Error> {
+    let mut frames = Vec::new();
+    let mut durations = Vec::new();
+    for frame in element.children_named("frame") {
+        frames.push(frame.required("tileid")?);
+        durations.push(frame.required::<f32>("duration")? / 1000.0);
+    }
+    Ok(TileAnimation::with_durations(frames, durations))
+}
+
+fn parse_object(element: &Element, path: &str) -> Result<TiledObject, Error> {
+    let points = |shape: &Element| {
+        shape
+            .required::<String>("points")?
+            .split_whitespace()
+            .map(|point| {
+                let mut coords = point.split(',').map(f32::from_str);
+                match (coords.next(), coords.next()) {
+                    (Some(Ok(x)), Some(Ok(y))) => Ok([x, y]),
+                    _ => Err(format_err!("Invalid point {}", point)),
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    };
+    let mut shape = TiledShape::Rectangle;
+    for child in &element.children {
+        shape = match child.name.as_str() {
+            "ellipse" => TiledShape::Ellipse,
+            "point" => TiledShape::Point,
+            "polygon" => TiledShape::Polygon(points(child)?),
+            "polyline" => TiledShape::Polyline(points(child)?),
+            _ => continue,
+        };
+    }
+    Ok(TiledObject {
+        id: element.optional("id")?.unwrap_or(0),
+        name: element.attr("name").unwrap_or_default().into(),
+        ty: element
+            .attr("type")
+            .or_else(|| element.attr("class"))
+            .unwrap_or_default()
+            .into(),
+        x: element.optional("x")?.unwrap_or(0.0),
+        y: element.optional("y")?.unwrap_or(0.0),
+        width: element.optional("width")?.unwrap_or(0.0),
+        height: element.optional("height")?.unwrap_or(0.0),
+        rotation: element.optional("rotation")?.unwrap_or(0.0),
+        gid: element.optional("gid")?,
+        visible: element.attr("visible") != Some("0"),
+        shape,
+        properties: parse_properties(element, path)?,
+    })
+}
+
+/// Parses the custom properties of an element, resolving `file` properties relative to `path`.
+fn parse_properties(element: &Element, path: &str) -> Result<TiledProperties, Error> {
+    let mut properties = TiledProperties::new();
+    for property in element
+        .children_named("properties")
+        .flat_map(|properties| properties.children_named("property"))
+    {
+        let name: String = property.required("name")?;
+        // Multiline strings are stored in the text of the property.
+        let value = property.attr("value").unwrap_or(&property.text);
+        let value = match property.attr("type").unwrap_or("string") {
+            "int" | "object" => TiledProperty::Int(property.parse_value(value)?),
+            "float" => TiledProperty::Float(property.parse_value(value)?),
+            "bool" => TiledProperty::Bool(value == "true"),
+            "color" => TiledProperty::Color(parse_color(value)?),
+            "file" => TiledProperty::File(resolve_path(path, value)),
+            _ => TiledProperty::String(value.into()),
+        };
+        properties.insert(name, value);
+    }
+    Ok(properties)
+}
+
+/// Parses a color saved as `#AARRGGBB`, or `#RRGGBB`.
+fn parse_color(color: &str) -> Result<Srgba, Error> {
+    let invalid = || format_err!("Invalid color {}", color);
+    let hex = color.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+    let (alpha, rgb) = match hex.len() {
+        8 => (value >> 24, value),
+        6 => (0xff, value),
+        _ => return Err(invalid()),
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let channel = |value: u32| f32::from(value as u8) / 255.0;
+    Ok(Srgba::new(
+        channel(rgb >> 16),
+        channel(rgb >> 8),
+        channel(rgb),
+        channel(alpha),
+    ))
+}
+
+/// Resolves a path relative to the directory of the asset at `base`.
+fn resolve_path(base: &str, relative: &str) -> String {
+    let mut segments = base.split('/').collect::<Vec<_>>();
+    segments.pop();
+    for segment in relative.split('/') {
+        match segment {
+            "." | "" => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Element of an XML document.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    /// Parses the root element of a document.
+    fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let mut stack = vec![Element::default()];
+        for event in EventReader::new(bytes) {
+            match event.with_context(|_| format_err!("Invalid XML"))? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect(),
+                    ..Element::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().expect("Unbalanced XML elements");
+                    stack
+                        .last_mut()
+                        .expect("Unbalanced XML elements")
+                        .children
+                        .push(element);
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        stack
+            .pop()
+            .and_then(|document| document.children.into_iter().next())
+            .ok_or_else(|| format_err!("Empty XML document"))
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Parses a value of an attribute of this element.
+    fn parse_value<T: FromStr>(&self, value: &str) -> Result<T, Error> {
+        value
+            .parse()
+            .map_err(|_| format_err!("Invalid value {} in {}", value, self.name))
+    }
+
+    fn optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, Error> {
+        self.attr(name)
+            .map(|value| self.parse_value(value))
+            .transpose()
+    }
+
+    fn required<T: FromStr>(&self, name: &str) -> Result<T, Error> {
+        self.optional(name)?
+            .ok_or_else(|| format_err!("Missing attribute {} in {}", name, self.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        autotile::{EAST, NORTH, WEST},
+        Map, MortonEncoder2D,
+    };
+
+    #[derive(Debug)]
+    struct MemorySource(HashMap<&'static str, &'static str>);
+
+    impl Source for MemorySource {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            self.0
+                .get(path)
+                .map(|file| file.as_bytes().to_vec())
+                .ok_or_else(|| format_err!("Missing file {}", path))
+        }
+    }
+
+    const TILESET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.4" name="terrain" tilewidth="16" tileheight="16" spacing="2" margin="1" tilecount="6" columns="3">
+ <image source="../images/terrain.png" width="56" height="38"/>
+ <tile id="4" type="water">
+  <properties>
+   <property name="speed" type="float" value="0.5"/>
+   <property name="solid" type="bool" value="false"/>
+  </properties>
+  <animation>
+   <frame tileid="4" duration="250"/>
+   <frame tileid="5" duration="500"/>
+  </animation>
+ </tile>
+</tileset>
+"#;
+
+    fn map(layers: &str) -> String {
+        format!(
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.4" orientation="orthogonal" renderorder="right-down" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <properties>
+  <property name="music" type="file" value="../music/theme.ogg"/>
+  <property name="tint" type="color" value="#80ff0000"/>
+ </properties>
+ <tileset firstgid="1" source="../tilesets/terrain.tsx"/>
+ <tileset firstgid="7" name="items" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+  <image source="items.png" width="32" height="32"/>
+ </tileset>
+ {layers}
+ <objectgroup name="spawns">
+  <object id="3" name="player" type="spawn" x="24" y="8"/>
+  <object id="4" gid="8" x="0" y="32" width="16" height="16"/>
+  <object id="5" x="8" y="8">
+   <polygon points="0,0 16,0 8,-8.5"/>
+  </object>
+ </objectgroup>
+</map>
+"##
+        )
+    }
+
+    fn load(map: &str) -> Result<TiledMap, Error> {
+        let mut files = HashMap::new();
+        files.insert("tilesets/terrain.tsx", TILESET);
+        parse_map(map.as_bytes(), "maps/level.tmx", &MemorySource(files))
+    }
+
+    #[test]
+    fn loads_tilesets_layers_and_objects() {
+        let map = load(&map(r#"
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">
+1,2,3,
+4,5,2147483654
+</data>
+ </layer>
+ <group name="decoration">
+  <layer id="2" name="items" width="3" height="2" visible="0" opacity="0.5">
+   <data>
+    <tile gid="7"/><tile/><tile/><tile/><tile/><tile gid="10"/>
+   </data>
+  </layer>
+ </group>"#))
+        .unwrap();
+
+        assert_eq!(
+            map.properties["music"],
+            TiledProperty::File("music/theme.ogg".into())
+        );
+        assert_eq!(
+            map.properties["tint"],
+            TiledProperty::Color(Srgba::new(1.0, 0.0, 0.0, 128.0 / 255.0))
+        );
+
+        let terrain = &map.tilesets[0];
+        assert_eq!(terrain.image.as_ref().unwrap().source, "images/terrain.png");
+        assert_eq!(terrain.tiles[&4].ty.as_deref(), Some("water"));
+        assert_eq!(
+            terrain.tiles[&4].animation,
+            Some(TileAnimation::with_durations(vec![4, 5], vec![0.25, 0.5]))
+        );
+        assert_eq!(
+            terrain.sprites()[4],
+            Sprite::from_pixel_values(56, 38, 16, 16, 19, 19, [0.0; 2], false, false)
+        );
+        assert_eq!(
+            map.tilesets[1].image.as_ref().unwrap().source,
+            "maps/items.png"
+        );
+        assert_eq!(
+            map.tile_properties(5).unwrap()["speed"],
+            TiledProperty::Float(0.5)
+        );
+        assert_eq!(map.tileset_of(6 | FLIPPED_HORIZONTALLY), Some((0, 5)));
+        assert_eq!(map.tileset_of(10), Some((1, 3)));
+
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(
+            map.layers[0].tiles,
+            vec![1, 2, 3, 4, 5, 6 | FLIPPED_HORIZONTALLY]
+        );
+        assert!(!map.layers[1].visible);
+        assert_eq!(map.layers[1].tiles, vec![7, 0, 0, 0, 0, 10]);
+
+        let objects = &map.object_groups[0].objects;
+        assert_eq!(objects[0].ty, "spawn");
+        assert_eq!(
+            map.object_position(&objects[0]),
+            Vector3::new(24.0 - 32.0, 24.0 - 8.0, 0.0)
+        );
+        assert_eq!(objects[1].gid, Some(8));
+        assert_eq!(
+            objects[2].shape,
+            TiledShape::Polygon(vec![[0.0, 0.0], [16.0, 0.0], [8.0, -8.5]])
+        );
+    }
+
+    #[test]
+    fn tile_maps_hold_the_tiles_of_a_tileset() {
+        let map = load(&map(r#"
+ <layer name="ground" width="3" height="2">
+  <data encoding="csv">1,2,3,4,5,6</data>
+ </layer>
+ <layer name="items" width="3" height="2" parallaxx="0.5" offsety="4">
+  <data encoding="csv">7,0,0,0,0,10</data>
+ </layer>"#))
+        .unwrap();
+
+        let terrain: TileMap<TiledTile, MortonEncoder2D> = map.tile_map(0, None, TiledTile::from);
+        assert_eq!(*terrain.dimensions(), Vector3::new(3, 2, 2));
+        let tile = |map: &TileMap<TiledTile, MortonEncoder2D>, x, y, z| {
+            map.get(&Point3::new(x, y, z)).unwrap().sprite
+        };
+        assert_eq!(tile(&terrain, 2, 1, 0), Some(5));
+        assert_eq!(tile(&terrain, 0, 0, 1), None);
+
+        let items: TileMap<TiledTile, MortonEncoder2D> = map.tile_map(1, None, TiledTile::from);
+        assert_eq!(tile(&items, 0, 0, 0), None);
+        assert_eq!(tile(&items, 0, 0, 1), Some(0));
+        assert_eq!(tile(&items, 2, 1, 1), Some(3));
+        assert_eq!(items.layer_parallax(0), LayerParallax::default());
+        assert_eq!(
+            items.layer_parallax(1),
+            LayerParallax::new(Vector2::new(0.5, 1.0)).with_scroll(Vector2::new(0.0, -4.0))
+        );
+    }
+
+    #[test]
+    fn tile_properties_fill_data_layers() {
+        let map = load(&map(r#"
+ <layer name="ground" width="3" height="2">
+  <data encoding="csv">1,2,3,4,5,6</data>
+ </layer>
+ <layer name="collision" width="3" height="2">
+  <properties>
+   <property name="data" type="bool" value="true"/>
+  </properties>
+  <data encoding="csv">0,5,0,0,0,0</data>
+ </layer>"#))
+        .unwrap();
+
+        // The data of the terrain tiles is in the maps of every tileset.
+        let items: TileMap<TiledTile, MortonEncoder2D> = map.tile_map(1, None, TiledTile::from);
+        assert_eq!(items.data::<f32>("speed", &Point3::new(1, 1, 0)), Some(0.5));
+        assert_eq!(
+            items.data::<bool>("solid", &Point3::new(1, 1, 0)),
+            Some(false)
+        );
+        assert_eq!(items.data::<bool>("solid", &Point3::new(0, 0, 0)), None);
+
+        let terrain: TileMap<TiledTile, MortonEncoder2D> = map.tile_map(0, None, TiledTile::from);
+        assert_eq!(
+            terrain.data::<bool>("solid", &Point3::new(1, 0, 1)),
+            Some(false)
+        );
+        assert_eq!(terrain.get(&Point3::new(1, 0, 1)).unwrap().sprite, None);
+        assert_eq!(terrain.get(&Point3::new(1, 1, 0)).unwrap().sprite, Some(4));
+    }
+
+    #[test]
+    fn terrain_tiles_are_auto_tiled() {
+        let mut map = load(&map(r#"
+ <layer name="ground" width="3" height="2">
+  <data encoding="csv">1,1,0,0,0,0</data>
+ </layer>"#))
+        .unwrap();
+        for &(id, mask) in &[(0, NORTH | EAST | WEST), (1, NORTH | WEST), (2, 85)] {
+            let mut properties = TiledProperties::new();
+            properties.insert("terrain".into(), TiledProperty::Int(1));
+            properties.insert("terrain_mask".into(), TiledProperty::Int(mask.into()));
+            map.tilesets[0].tiles.insert(
+                id,
+                TiledTileData {
+                    ty: None,
+                    properties,
+                    animation: None,
+                },
+            );
+        }
+
+        let rules = map.auto_tile_rules(0);
+        let rule = rules.rule(1).unwrap();
+        assert_eq!(rule.mode, AutoTileMode::Edges);
+        assert_eq!(rule.default_sprite, 2);
+        assert_eq!(rules.rule(0), None);
+
+        let mut tiles: TileMap<TiledTile, MortonEncoder2D> = map.tile_map(0, None, TiledTile::from);
+        rules.apply(&mut tiles);
+        assert_eq!(tiles.get(&Point3::new(0, 0, 0)).unwrap().sprite, Some(0));
+        assert_eq!(tiles.get(&Point3::new(1, 0, 0)).unwrap().sprite, Some(1));
+        assert_eq!(tiles.get(&Point3::new(2, 0, 0)).unwrap().sprite, None);
+    }
+
+    #[test]
+    fn maps_are_laid_out_with_their_orientation() {
+        let orthogonal = map("");
+        let hexagonal = orthogonal.replace(
+            r#"orientation="orthogonal""#,
+            r#"orientation="hexagonal" hexsidelength="8" staggeraxis="y" staggerindex="odd""#,
+        );
+        let map = load(&hexagonal).unwrap();
+        assert_eq!(map.projection, MapProjection::HexPointyTop);
+        let tiles: TileMap<TiledTile, MortonEncoder2D> = map.tile_map(0, None, TiledTile::from);
+        assert_eq!(tiles.projection(), MapProjection::HexPointyTop);
+        // The object at the center of the first tile.
+        let mut object = map.object_groups[0].objects[0].clone();
+        object.x = 8.0;
+        object.y = 8.0;
+        assert_eq!(
+            map.object_position(&object),
+            tiles.to_world(&Point3::new(0, 0, 0), None)
+        );
+
+        let isometric = load(&orthogonal.replace("orthogonal", "isometric")).unwrap();
+        let tiles: TileMap<TiledTile, MortonEncoder2D> =
+            isometric.tile_map(0, None, TiledTile::from);
+        object.x = 24.0;
+        assert_eq!(
+            isometric.object_position(&object),
+            tiles.to_world(&Point3::new(1, 0, 0), None)
+        );
+
+        let even = hexagonal.replace("staggerindex=\"odd\"", "staggerindex=\"even\"");
+        assert!(load(&even).is_err());
+        let irregular = hexagonal.replace("hexsidelength=\"8\"", "hexsidelength=\"6\"");
+        assert!(load(&irregular).is_err());
+    }
+
+    #[test]
+    fn decodes_compressed_base64_data() {
+        let tiles = [1_u32, 2, 3, 4, 5, 6]
+            .iter()
+            .flat_map(|gid| gid.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let zlib = base64::encode(&miniz_oxide::deflate::compress_to_vec_zlib(&tiles, 6));
+        let mut gzip = vec![0x1f, 0x8b, 8, 8, 0, 0, 0, 0, 0, 3];
+        gzip.extend(b"tiles\0");
+        gzip.extend(miniz_oxide::deflate::compress_to_vec(&tiles, 6));
+        let gzip = base64::encode(&gzip);
+
+        for (compression, data) in &[("zlib", zlib), ("gzip", gzip)] {
+            let map = load(&map(&format!(
+                r#"<layer name="ground" width="3" height="2">
+  <data encoding="base64" compression="{compression}">
+   {data}
+  </data>
+ </layer>"#
+            )))
+            .unwrap();
+            assert_eq!(map.layers[0].tiles, vec![1, 2, 3, 4, 5, 6]);
+        }
+
+        let error = load(&map(r#"<layer name="ground" width="3" height="2">
+  <data encoding="base64" compression="zstd">AAAA</data>
+ </layer>"#))
+        .unwrap_err();
+        assert!(format!("{error:?}").contains("zstd"));
+    }
+}
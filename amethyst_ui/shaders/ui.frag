@@ -10,7 +10,8 @@ layout(location = 2) in vec4 in_color_bias;
 layout(location = 0) out vec4 out_color;
 
 void main() {
-    vec4 color = (texture(tex, in_tex_coords) + in_color_bias) * in_color;
+    // A bias of 1.0 saturates the texture, filling solid quads with their color.
+    vec4 color = min(texture(tex, in_tex_coords) + in_color_bias, 1.0) * in_color;
     if (color.a == 0.0) {
         discard;
     }
@@ -4,17 +4,17 @@ use crate::{
     BlinkSystem, CacheSelectionOrderSystem, DragWidgetSystemDesc, FontAsset, NoCustomUi,
     ResizeSystemDesc, SelectionKeyboardSystemDesc, SelectionMouseSystemDesc,
     TextEditingInputSystemDesc, TextEditingMouseSystemDesc, ToNativeWidget,
-    UiButtonActionRetriggerSystemDesc, UiButtonSystemDesc, UiLoaderSystemDesc, UiMouseSystem,
-    UiSoundRetriggerSystemDesc, UiSoundSystemDesc, UiTransformSystemDesc, WidgetId,
+    UiButtonActionRetriggerSystemDesc, UiButtonSystemDesc, UiCursorSystemDesc, UiLoaderSystemDesc,
+    UiMouseSystem, UiSoundRetriggerSystemDesc, UiSoundSystemDesc, UiTransformSystemDesc, WidgetId,
 };
 use amethyst_assets::Processor;
 use amethyst_core::{
-    bundle::SystemBundle,
+    bundle::{BundleError, SystemBundle},
     ecs::prelude::{DispatcherBuilder, World},
-    SystemDesc,
+    ParentHierarchy, SystemDesc,
 };
 use amethyst_error::Error;
-use amethyst_input::BindingTypes;
+use amethyst_input::{BindingTypes, InputHandler};
 use derive_new::new;
 use std::marker::PhantomData;
 
@@ -23,7 +23,7 @@ use std::marker::PhantomData;
 /// Will register all necessary components and systems needed for UI, along with any resources.
 /// The generic type T represent the T generic parameter of the InputHandler<T>.
 ///
-/// Will fail with error 'No resource with the given id' if either the InputBundle or TransformBundle are not added.
+/// Fails with a `BundleError` if the `TransformBundle` or the `InputBundle` aren't added before it.
 #[derive(new, Debug)]
 pub struct UiBundle<T: BindingTypes, C = NoCustomUi, W = u32, G = ()> {
     #[new(default)]
@@ -42,6 +42,19 @@ where
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
+        if !world.has_value::<ParentHierarchy>() {
+            return Err(
+                BundleError::missing_resource_named("UiBundle", "ParentHierarchy")
+                    .with_suggestion("add TransformBundle before UiBundle")
+                    .into(),
+            );
+        }
+        if !world.has_value::<InputHandler<T>>() {
+            return Err(BundleError::missing_resource::<InputHandler<T>>("UiBundle")
+                .with_suggestion("add InputBundle before UiBundle")
+                .into());
+        }
+
         builder.add(
             UiLoaderSystemDesc::<<C as ToNativeWidget>::PrefabData, W>::default().build(world),
             "ui_loader",
@@ -104,6 +117,11 @@ where
             "ui_drag_system",
             &["ui_mouse_system"],
         );
+        builder.add(
+            UiCursorSystemDesc::<T>::default().build(world),
+            "ui_cursor_system",
+            &["ui_mouse_system", "ui_drag_system"],
+        );
 
         builder.add(
             UiButtonActionRetriggerSystemDesc::default().build(world),
@@ -121,9 +139,53 @@ where
             &["ui_sound_system"],
         );
 
+        #[cfg(feature = "locale")]
+        builder.add(
+            crate::UiLocalizationSystemDesc::default().build(world),
+            "ui_localization_system",
+            &[],
+        );
+
         // Required for text editing. You want the cursor image to blink.
         builder.add(BlinkSystem, "blink_system", &[]);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::{
+        bundle::SystemBundle,
+        ecs::prelude::{DispatcherBuilder, World, WorldExt},
+        TransformBundle,
+    };
+    use amethyst_input::StringBindings;
+
+    use super::UiBundle;
+
+    #[test]
+    fn build_suggests_adding_missing_bundles_first() {
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+
+        let error = UiBundle::<StringBindings>::new()
+            .build(&mut world, &mut builder)
+            .expect_err("UiBundle built without TransformBundle");
+        assert!(error.to_string().contains("ParentHierarchy"));
+        assert!(error
+            .to_string()
+            .ends_with("add TransformBundle before UiBundle"));
+
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .expect("Failed to build TransformBundle");
+        let error = UiBundle::<StringBindings>::new()
+            .build(&mut world, &mut builder)
+            .expect_err("UiBundle built without InputBundle");
+        assert!(error.to_string().contains("InputHandler"));
+        assert!(error
+            .to_string()
+            .ends_with("add InputBundle before UiBundle"));
+    }
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+use amethyst_core::{
+    ecs::{
+        Component, DenseVecStorage, Entities, Entity, Join, NullStorage, Read, ReadExpect,
+        ReadStorage, ReaderId, System, SystemData, Write, WriteStorage,
+    },
+    shrev::EventChannel,
+    Hidden, HiddenPropagate,
+};
+use amethyst_derive::SystemDesc;
+use amethyst_input::{BindingTypes, InputHandler};
+use amethyst_window::{CursorState, MouseCursor, ScreenDimensions};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use crate::{Anchor, ScaleMode, UiEvent, UiEventType, UiTransform};
+
+/// Component showing a system cursor icon while the widget is hovered, e.g. a hand over links or
+/// a text beam over text fields. Requires `Interactable` to receive the hover events.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HoverCursor(pub MouseCursor);
+
+impl Component for HoverCursor {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Component drawing the `UiImage` of a root widget in place of the system cursor, e.g. for a
+/// cursor texture matching the art of the game.
+///
+/// The pivot of its `UiTransform` is kept at the mouse position, so use `Anchor::TopLeft` for
+/// an arrow pointing up and left. Give it a high `z` to draw it over the other widgets, and
+/// leave it without `Interactable` so that it doesn't catch the clicks. The system cursor is
+/// hidden while the widget isn't `Hidden` and the mouse is over the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UiCursor;
+
+impl Component for UiCursor {
+    type Storage = NullStorage<Self>;
+}
+
+/// System updating the `CursorState` with the `HoverCursor` of the hovered widget, and moving
+/// the `UiCursor` widgets to the mouse.
+#[derive(Debug, SystemDesc)]
+#[system_desc(name(UiCursorSystemDesc))]
+pub struct UiCursorSystem<T: BindingTypes> {
+    #[system_desc(event_channel_reader)]
+    ui_reader_id: ReaderId<UiEvent>,
+
+    #[system_desc(skip)]
+    hovered: Option<Entity>,
+
+    phantom: PhantomData<T>,
+}
+
+impl<T> UiCursorSystem<T>
+where
+    T: BindingTypes,
+{
+    /// Creates a new `UiCursorSystem` reading the given `UiEvent`s.
+    pub fn new(ui_reader_id: ReaderId<UiEvent>) -> Self {
+        Self {
+            ui_reader_id,
+            hovered: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'s, T> System<'s> for UiCursorSystem<T>
+where
+    T: BindingTypes,
+{
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, InputHandler<T>>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, EventChannel<UiEvent>>,
+        Write<'s, CursorState>,
+        ReadStorage<'s, HoverCursor>,
+        ReadStorage<'s, UiCursor>,
+        ReadStorage<'s, Hidden>,
+        ReadStorage<'s, HiddenPropagate>,
+        WriteStorage<'s, UiTransform>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            input_handler,
+            screen_dimensions,
+            ui_events,
+            mut cursor,
+            hover_cursors,
+            ui_cursors,
+            hiddens,
+            hidden_props,
+            mut ui_transforms,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("ui_cursor_system");
+
+        for event in ui_events.read(&mut self.ui_reader_id) {
+            match event.event_type {
+                UiEventType::HoverStart if hover_cursors.contains(event.target) => {
+                    self.hovered = Some(event.target);
+                }
+                UiEventType::HoverStop if self.hovered == Some(event.target) => {
+                    self.hovered = None;
+                }
+                _ => (),
+            }
+        }
+        let hover_icon = self
+            .hovered
+            .and_then(|hovered| hover_cursors.get(hovered))
+            .map(|hover_cursor| hover_cursor.0);
+        cursor.set_hover_icon(hover_icon);
+
+        let mouse_pos = input_handler
+            .mouse_position()
+            .map(|(x, y)| (x, screen_dimensions.height() - y));
+        let mut custom_image = false;
+        let mut moved = Vec::new();
+        for (entity, ui_transform, _, _, _) in (
+            &entities,
+            &ui_transforms,
+            &ui_cursors,
+            !&hiddens,
+            !&hidden_props,
+        )
+            .join()
+        {
+            if let Some(mouse_pos) = mouse_pos {
+                custom_image = true;
+                let local = cursor_local_position(
+                    ui_transform.anchor,
+                    &ui_transform.scale_mode,
+                    mouse_pos,
+                    (screen_dimensions.width(), screen_dimensions.height()),
+                );
+                if (ui_transform.local_x, ui_transform.local_y) != local {
+                    moved.push((entity, local));
+                }
+            }
+        }
+        // Only the moved cursors are fetched mutably, so that the others aren't flagged as
+        // modified.
+        for (entity, (x, y)) in moved {
+            if let Some(ui_transform) = ui_transforms.get_mut(entity) {
+                ui_transform.local_x = x;
+                ui_transform.local_y = y;
+            }
+        }
+        cursor.set_custom_image(custom_image);
+    }
+}
+
+/// Returns the local position putting the pivot of a root widget at the given screen position,
+/// from the bottom left of the screen.
+fn cursor_local_position(
+    anchor: Anchor,
+    scale_mode: &ScaleMode,
+    position: (f32, f32),
+    screen_size: (f32, f32),
+) -> (f32, f32) {
+    let (anchor_x, anchor_y) = anchor.norm_offset();
+    let x = position.0 - (anchor_x + 0.5) * screen_size.0;
+    let y = position.1 - (anchor_y + 0.5) * screen_size.1;
+    match scale_mode {
+        ScaleMode::Pixel => (x, y),
+        ScaleMode::Percent => (x / screen_size.0, y / screen_size.1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_follows_the_mouse_from_any_anchor() {
+        let screen = (800.0, 600.0);
+        assert_eq!(
+            cursor_local_position(Anchor::BottomLeft, &ScaleMode::Pixel, (10.0, 20.0), screen),
+            (10.0, 20.0)
+        );
+        assert_eq!(
+            cursor_local_position(Anchor::Middle, &ScaleMode::Pixel, (10.0, 20.0), screen),
+            (-390.0, -280.0)
+        );
+        assert_eq!(
+            cursor_local_position(
+                Anchor::TopRight,
+                &ScaleMode::Percent,
+                (400.0, 300.0),
+                screen
+            ),
+            (-0.5, -0.5)
+        );
+    }
+}
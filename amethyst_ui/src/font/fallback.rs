@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::FontHandle;
+
+/// Fonts drawing the characters missing from the font of a `UiText`, so that strings mixing
+/// scripts (e.g. Latin, CJK and emoji) don't render missing characters as boxes.
+///
+/// Every character is drawn with the first font of the chain having it, starting with the font of
+/// the `UiText`. A chain can be declared per language, like a CJK font preferring the Japanese
+/// forms of the characters for `ja` and the Chinese ones for `zh`; the fonts of the current
+/// language are used when it has a chain, and the default chain otherwise.
+///
+/// With the `locale` feature, the current language follows the language of the `Localization`.
+#[derive(Clone, Debug, Default)]
+pub struct UiFontFallbacks {
+    fonts: Vec<FontHandle>,
+    languages: HashMap<String, Vec<FontHandle>>,
+    language: Option<String>,
+}
+
+impl UiFontFallbacks {
+    /// Creates fallbacks using the given fonts, in order, for every language.
+    pub fn new(fonts: Vec<FontHandle>) -> Self {
+        UiFontFallbacks {
+            fonts,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a font at the end of the default chain.
+    pub fn with_font(mut self, font: FontHandle) -> Self {
+        self.fonts.push(font);
+        self
+    }
+
+    /// Sets the chain of the language, given as a language tag like `ja` or `zh-Hant`.
+    pub fn with_language<S: Into<String>>(mut self, language: S, fonts: Vec<FontHandle>) -> Self {
+        self.set_language_fonts(language, fonts);
+        self
+    }
+
+    /// Sets the chain of the language, given as a language tag like `ja` or `zh-Hant`.
+    pub fn set_language_fonts<S: Into<String>>(&mut self, language: S, fonts: Vec<FontHandle>) {
+        self.languages.insert(language.into(), fonts);
+    }
+
+    /// Returns the current language.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Switches the current language.
+    pub fn set_language(&mut self, language: Option<String>) {
+        self.language = language;
+    }
+
+    /// Returns the chain of the current language.
+    ///
+    /// A language without a chain uses the one of its less specific tag, `zh` for `zh-Hant-TW`,
+    /// falling back to the default chain.
+    pub fn fonts(&self) -> &[FontHandle] {
+        self.language_tag()
+            .map_or(&self.fonts, |tag| &self.languages[tag])
+    }
+
+    fn language_tag(&self) -> Option<&str> {
+        let mut tag = self.language.as_deref()?;
+        loop {
+            if self.languages.contains_key(tag) {
+                return Some(tag);
+            }
+            tag = &tag[..tag.rfind('-')?];
+        }
+    }
+}
+
+/// Splits the text into runs drawn with the same font, returning each run with the index of its
+/// font in the chain. `has_glyph` tells if the font at the index has the character.
+///
+/// Grapheme clusters are drawn with the font of their first character, and whitespace keeps the
+/// font of the previous run. Characters that no font has are drawn with the first font.
+pub(crate) fn font_runs<F>(text: &str, fonts: usize, has_glyph: F) -> Vec<(&str, usize)>
+where
+    F: Fn(usize, char) -> bool,
+{
+    use unicode_segmentation::UnicodeSegmentation;
+
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        let c = match grapheme.chars().next() {
+            Some(c) if !c.is_whitespace() => c,
+            _ => continue,
+        };
+        let font = (0..fonts).find(|font| has_glyph(*font, c)).unwrap_or(0);
+        match current {
+            Some(current) if current == font => {}
+            Some(current) => {
+                runs.push((&text[start..index], current));
+                start = index;
+            }
+            None => {}
+        }
+        current = Some(font);
+    }
+    if start < text.len() {
+        runs.push((&text[start..], current.unwrap_or(0)));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(text: &str) -> Vec<(&str, usize)> {
+        // font 0 is latin, font 1 is CJK, font 2 has everything
+        font_runs(text, 3, |font, c| match font {
+            0 => c.is_ascii(),
+            1 => ('\u{4e00}'..='\u{9fff}').contains(&c),
+            _ => c != '\u{fffd}',
+        })
+    }
+
+    #[test]
+    fn splits_runs_by_font() {
+        assert_eq!(runs("hello"), vec![("hello", 0)]);
+        assert_eq!(runs(""), vec![]);
+        assert_eq!(
+            runs("hi 你好 😀!"),
+            vec![("hi ", 0), ("你好 ", 1), ("😀", 2), ("!", 0)]
+        );
+        assert_eq!(runs(" 你"), vec![(" 你", 1)]);
+        assert_eq!(runs("a\u{fffd}"), vec![("a\u{fffd}", 0)]);
+        // the combining accent stays with its base character
+        assert_eq!(runs("你e\u{301}"), vec![("你", 1), ("e\u{301}", 0)]);
+    }
+
+    #[test]
+    fn chain_of_language() {
+        let mut fallbacks = UiFontFallbacks::default()
+            .with_language("zh", Vec::new())
+            .with_language("zh-Hant", Vec::new());
+        assert_eq!(fallbacks.language_tag(), None);
+        fallbacks.set_language(Some("zh-Hant-TW".to_string()));
+        assert_eq!(fallbacks.language(), Some("zh-Hant-TW"));
+        assert_eq!(fallbacks.language_tag(), Some("zh-Hant"));
+        fallbacks.set_language(Some("zh-CN".to_string()));
+        assert_eq!(fallbacks.language_tag(), Some("zh"));
+        fallbacks.set_language(Some("ja".to_string()));
+        assert_eq!(fallbacks.language_tag(), None);
+    }
+}
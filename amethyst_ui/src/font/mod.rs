@@ -1,2 +1,3 @@
 pub mod default;
+pub mod fallback;
 pub mod systemfont;
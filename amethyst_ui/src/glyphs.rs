@@ -1,8 +1,10 @@
 //! Module containing the system managing glyphbrush state for visible UI Text components.
 
 use crate::{
-    pass::UiArgs, text::CachedGlyph, FontAsset, LineMode, Selected, TextEditing, UiText,
-    UiTransform,
+    font::fallback::font_runs,
+    pass::{UiArgs, SOLID_COLOR_BIAS},
+    text::CachedGlyph,
+    FontAsset, FontHandle, LineMode, Selected, TextEditing, UiFontFallbacks, UiText, UiTransform,
 };
 use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
@@ -127,6 +129,7 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
         Write<'a, AssetStorage<Texture>>,
         Read<'a, AssetStorage<FontAsset>>,
         WriteExpect<'a, UiGlyphsResource>,
+        Read<'a, UiFontFallbacks>,
     );
 
     fn run(
@@ -146,6 +149,7 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
             mut tex_storage,
             font_storage,
             mut glyphs_res,
+            fallbacks,
         ): Self::SystemData,
     ) {
         let (factory, queue) =
@@ -171,6 +175,15 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
         let fonts_map_ref = &mut self.fonts_map;
         let glyph_brush_ref = &mut self.glyph_brush;
 
+        let fallback_fonts = fallbacks
+            .fonts()
+            .iter()
+            .filter_map(|handle| {
+                let font_id = load_font(fonts_map_ref, glyph_brush_ref, &font_storage, handle)?;
+                font_storage.get(handle).map(|font| (font_id, &font.0))
+            })
+            .collect::<Vec<_>>();
+
         for (entity, transform, ui_text, editing, tint, _, _) in (
             &entities,
             &transforms,
@@ -185,16 +198,9 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
             ui_text.cached_glyphs.clear();
 
             let font_asset = font_storage.get(&ui_text.font).map(|font| font.0.clone());
-            let font_lookup = fonts_map_ref
-                .entry(ui_text.font.id())
-                .or_insert(FontState::NotFound);
-            if font_lookup.id().is_none() {
-                if let Some(font) = font_storage.get(&ui_text.font) {
-                    *font_lookup = FontState::Ready(glyph_brush_ref.add_font(font.0.clone()));
-                }
-            }
+            let font_id = load_font(fonts_map_ref, glyph_brush_ref, &font_storage, &ui_text.font);
 
-            if let (Some(font_id), Some(font_asset)) = (font_lookup.id(), font_asset) {
+            if let (Some(font_id), Some(font_asset)) = (font_id, font_asset) {
                 let tint_color = tint.map_or([1., 1., 1., 1.], |t| {
                     let (r, g, b, a) = t.0.into_components();
                     [r, g, b, a]
@@ -278,6 +284,36 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
                     }
                 };
 
+                // characters missing from the font are drawn with the first fallback having them
+                let text = if fallback_fonts.is_empty() {
+                    text
+                } else {
+                    let fallback_fonts = &fallback_fonts;
+                    text.into_iter()
+                        .flat_map(|section| {
+                            let has_glyph = |font: usize, c: char| {
+                                let font = if font == 0 {
+                                    &font_asset
+                                } else {
+                                    fallback_fonts[font - 1].1
+                                };
+                                font.glyph(c).id().0 != 0
+                            };
+                            font_runs(section.text, fallback_fonts.len() + 1, has_glyph)
+                                .into_iter()
+                                .map(move |(text, font)| SectionText {
+                                    text,
+                                    font_id: if font == 0 {
+                                        section.font_id
+                                    } else {
+                                        fallback_fonts[font - 1].0
+                                    },
+                                    ..section
+                                })
+                        })
+                        .collect()
+                };
+
                 let layout = match ui_text.line_mode {
                     LineMode::Single => Layout::SingleLine {
                         line_breaker: CustomLineBreaker::None,
@@ -544,7 +580,7 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
                                 dimensions: [g.advance_width, height].into(),
                                 tex_coord_bounds: [0., 0., 1., 1.].into(),
                                 color: bg_color.into(),
-                                color_bias: [1., 1., 1., 0.].into(),
+                                color_bias: SOLID_COLOR_BIAS.into(),
                             });
                             let mut glyph_data = glyphs.get_mut(entity).unwrap();
                             glyph_data.sel_vertices.extend(iter);
@@ -604,6 +640,21 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
     }
 }
 
+fn load_font(
+    fonts_map: &mut HashMap<u32, FontState>,
+    glyph_brush: &mut GlyphBrush<'static, (u32, UiArgs)>,
+    font_storage: &AssetStorage<FontAsset>,
+    font: &FontHandle,
+) -> Option<FontId> {
+    let font_lookup = fonts_map.entry(font.id()).or_insert(FontState::NotFound);
+    if font_lookup.id().is_none() {
+        if let Some(font) = font_storage.get(font) {
+            *font_lookup = FontState::Ready(glyph_brush.add_font(font.0.clone()));
+        }
+    }
+    font_lookup.id()
+}
+
 fn update_cursor_position(
     glyph_data: &mut UiGlyphs,
     ui_text: &UiText,
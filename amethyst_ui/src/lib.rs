@@ -21,6 +21,7 @@ pub use self::{
         UiButtonActionRetriggerSystemDesc, UiButtonActionType, UiButtonBuilder,
         UiButtonBuilderResources, UiButtonSystem, UiButtonSystemDesc,
     },
+    cursor::{HoverCursor, UiCursor, UiCursorSystem, UiCursorSystemDesc},
     drag::{DragWidgetSystemDesc, Draggable},
     event::{
         targeted, targeted_below, Interactable, TargetedEvent, UiEvent, UiEventType, UiMouseSystem,
@@ -30,6 +31,7 @@ pub use self::{
     },
     font::{
         default::get_default_font,
+        fallback::UiFontFallbacks,
         systemfont::{default_system_font, get_all_font_handles, list_system_font_families},
     },
     format::{FontAsset, FontHandle, TtfFormat},
@@ -43,6 +45,7 @@ pub use self::{
         UiImageLoadPrefab, UiImagePrefab, UiLoader, UiLoaderSystem, UiLoaderSystemDesc, UiPrefab,
         UiTextData, UiTransformData, UiWidget,
     },
+    quad::{UiClip, UiQuad, UiQuads},
     resize::{ResizeSystem, ResizeSystemDesc, UiResize},
     selection::{
         Selectable, Selected, SelectionKeyboardSystem, SelectionKeyboardSystemDesc,
@@ -59,11 +62,15 @@ pub use self::{
     widgets::{Widget, WidgetId, Widgets},
 };
 
+#[cfg(feature = "locale")]
+pub use self::localization::{UiLocalizationSystem, UiLocalizationSystemDesc, UiTextLocalized};
+
 pub(crate) use amethyst_core::ecs::prelude::Entity;
 
 mod blink;
 mod bundle;
 mod button;
+mod cursor;
 mod drag;
 mod event;
 mod event_retrigger;
@@ -73,8 +80,11 @@ mod glyphs;
 mod image;
 mod label;
 mod layout;
+#[cfg(feature = "locale")]
+mod localization;
 mod pass;
 mod prefab;
+mod quad;
 mod resize;
 mod selection;
 mod selection_order_cache;
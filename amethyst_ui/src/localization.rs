@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use amethyst_core::{
+    ecs::prelude::{
+        BitSet, Component, ComponentEvent, DenseVecStorage, Entities, FlaggedStorage, Join, Read,
+        ReadStorage, System, SystemData, Write, WriteStorage,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+use amethyst_derive::SystemDesc;
+use amethyst_locale::{LocaleArg, Localization, LocalizationEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::{UiFontFallbacks, UiText};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Sets the text of the `UiText` of the entity to a message of the `Localization`, formatted
+/// again whenever the language is switched or the arguments change.
+///
+/// The text is left as is while no language has the message.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UiTextLocalized {
+    /// Key of the message.
+    pub key: String,
+    /// Arguments of the message.
+    #[serde(default)]
+    pub args: HashMap<String, LocaleArg>,
+}
+
+impl UiTextLocalized {
+    /// Creates a localized text of the message without arguments.
+    pub fn new<S: Into<String>>(key: S) -> Self {
+        UiTextLocalized {
+            key: key.into(),
+            args: HashMap::new(),
+        }
+    }
+
+    /// Adds an argument of the message.
+    pub fn with_arg<S: Into<String>, A: Into<LocaleArg>>(mut self, name: S, value: A) -> Self {
+        self.args.insert(name.into(), value.into());
+        self
+    }
+}
+
+impl Component for UiTextLocalized {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+/// System writing the messages of `UiTextLocalized`s into their `UiText`s, and switching the
+/// language of the `UiFontFallbacks` to the one of the `Localization`.
+#[derive(Debug, SystemDesc)]
+#[system_desc(name(UiLocalizationSystemDesc))]
+pub struct UiLocalizationSystem {
+    #[system_desc(event_channel_reader)]
+    event_reader: ReaderId<LocalizationEvent>,
+    #[system_desc(flagged_storage_reader(UiTextLocalized))]
+    text_reader: ReaderId<ComponentEvent>,
+    #[system_desc(skip)]
+    modified: BitSet,
+}
+
+impl UiLocalizationSystem {
+    /// Creates a new `UiLocalizationSystem` with the given readers.
+    pub fn new(
+        event_reader: ReaderId<LocalizationEvent>,
+        text_reader: ReaderId<ComponentEvent>,
+    ) -> Self {
+        UiLocalizationSystem {
+            event_reader,
+            text_reader,
+            modified: BitSet::default(),
+        }
+    }
+}
+
+impl<'a> System<'a> for UiLocalizationSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Localization>,
+        Read<'a, EventChannel<LocalizationEvent>>,
+        ReadStorage<'a, UiTextLocalized>,
+        WriteStorage<'a, UiText>,
+        Write<'a, UiFontFallbacks>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, localization, events, localized, mut texts, mut fallbacks): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("ui_localization_system");
+
+        // every text is resolved again when the messages change
+        let all = events.read(&mut self.event_reader).count() > 0;
+        if all {
+            fallbacks.set_language(localization.language().map(ToString::to_string));
+        }
+        self.modified.clear();
+        for event in localized.channel().read(&mut self.text_reader) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    self.modified.add(*id);
+                }
+                ComponentEvent::Removed(_) => {}
+            }
+        }
+
+        for (entity, localized, text) in (&entities, &localized, &mut texts).join() {
+            if !all && !self.modified.contains(entity.id()) {
+                continue;
+            }
+            if let Some(value) = localization.format_with(&localized.key, &localized.args) {
+                text.text = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Anchor, FontAsset, LineMode, TtfFormat};
+    use amethyst_assets::{AssetStorage, Format};
+    use amethyst_core::{
+        ecs::{Builder, Entity, RunNow, World, WorldExt},
+        SystemDesc,
+    };
+    use amethyst_locale::{langid, Locale, LocaleFormat, LocalizationSystem};
+
+    /// Creates a world with English and French locales, English being the current language, and
+    /// an entity with a `UiText` localized with the `greeting` message.
+    fn world() -> (World, UiLocalizationSystem, Entity) {
+        let mut world = World::new();
+        let mut locales = AssetStorage::<Locale>::new();
+        let mut localization = Localization::new(vec![langid!("en"), langid!("fr")]);
+        for (language, source) in &[
+            (langid!("en"), "greeting = Hello { $name }"),
+            (langid!("fr"), "greeting = Bonjour { $name }"),
+        ] {
+            let locale = LocaleFormat
+                .import_simple(source.as_bytes().to_vec())
+                .unwrap();
+            localization.add_locale(language.clone(), locales.insert(locale));
+        }
+        world.insert(locales);
+        world.insert(localization);
+        world.insert(EventChannel::<LocalizationEvent>::new());
+
+        let font = TtfFormat
+            .import_simple(include_bytes!("font/square.ttf").to_vec())
+            .unwrap();
+        let font = AssetStorage::<FontAsset>::new().insert(FontAsset(font.0));
+        let system = UiLocalizationSystemDesc::default().build(&mut world);
+        let entity = world
+            .create_entity()
+            .with(UiText::new(
+                font,
+                "placeholder".to_string(),
+                [1.0; 4],
+                10.0,
+                LineMode::Single,
+                Anchor::Middle,
+            ))
+            .with(UiTextLocalized::new("greeting").with_arg("name", "Ann"))
+            .build();
+        (world, system, entity)
+    }
+
+    /// Runs the `LocalizationSystem` and the `UiLocalizationSystem`, returning the text of the
+    /// entity.
+    fn run(world: &mut World, system: &mut UiLocalizationSystem, entity: Entity) -> String {
+        LocalizationSystem.run_now(world);
+        system.run_now(world);
+        world.maintain();
+        world
+            .read_storage::<UiText>()
+            .get(entity)
+            .unwrap()
+            .text
+            .clone()
+    }
+
+    fn set_text(world: &World, entity: Entity, text: &str) {
+        world
+            .write_storage::<UiText>()
+            .get_mut(entity)
+            .unwrap()
+            .text = text.to_string();
+    }
+
+    #[test]
+    fn text_is_resolved_when_the_language_changes() {
+        let (mut world, mut system, entity) = world();
+        assert_eq!(run(&mut world, &mut system, entity), "Hello Ann");
+        assert_eq!(
+            world.read_resource::<UiFontFallbacks>().language(),
+            Some("en")
+        );
+
+        // nothing changed, the text is left as is
+        set_text(&world, entity, "edited");
+        assert_eq!(run(&mut world, &mut system, entity), "edited");
+
+        world
+            .write_resource::<Localization>()
+            .set_language(langid!("fr"));
+        assert_eq!(run(&mut world, &mut system, entity), "Bonjour Ann");
+        assert_eq!(
+            world.read_resource::<UiFontFallbacks>().language(),
+            Some("fr")
+        );
+    }
+
+    #[test]
+    fn text_is_resolved_when_the_args_change() {
+        let (mut world, mut system, entity) = world();
+        assert_eq!(run(&mut world, &mut system, entity), "Hello Ann");
+
+        set_text(&world, entity, "edited");
+        world
+            .write_storage::<UiTextLocalized>()
+            .get_mut(entity)
+            .unwrap()
+            .args
+            .insert("name".to_string(), "Bob".into());
+        assert_eq!(run(&mut world, &mut system, entity), "Hello Bob");
+
+        set_text(&world, entity, "edited");
+        assert_eq!(run(&mut world, &mut system, entity), "edited");
+    }
+
+    #[test]
+    fn text_without_message_is_left_as_is() {
+        let (mut world, mut system, entity) = world();
+        world
+            .write_storage::<UiTextLocalized>()
+            .insert(entity, UiTextLocalized::new("missing"))
+            .unwrap();
+        assert_eq!(run(&mut world, &mut system, entity), "placeholder");
+    }
+}
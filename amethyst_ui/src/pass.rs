@@ -1,18 +1,17 @@
 use crate::{
     glyphs::{UiGlyphs, UiGlyphsResource},
-    Selected, TextEditing, UiGlyphsSystemDesc, UiImage, UiTransform,
+    Selected, TextEditing, UiClip, UiGlyphsSystemDesc, UiImage, UiQuads, UiTransform,
 };
 use amethyst_assets::{AssetStorage, Handle, Loader};
 use amethyst_core::{
     ecs::{
         hibitset::BitSet, DispatcherBuilder, Entities, Entity, Join, Read, ReadExpect, ReadStorage,
-        SystemData, World,
+        SystemData, World, WorldExt,
     },
-    Hidden, HiddenPropagate, SystemDesc,
+    Hidden, HiddenPropagate, Parent, SystemDesc,
 };
 use amethyst_error::Error;
 use amethyst_rendy::{
-    batch::OrderedOneLevelBatch,
     bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
     palette,
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
@@ -35,6 +34,7 @@ use amethyst_rendy::{
     },
     resources::Tint,
     simple_shader_set,
+    stats::{DrawCounts, RenderStats},
     submodules::{DynamicUniform, DynamicVertexBuffer, TextureId, TextureSub},
     types::{Backend, Texture},
     ChangeDetection, SpriteSheet,
@@ -42,7 +42,7 @@ use amethyst_rendy::{
 use amethyst_window::ScreenDimensions;
 use derivative::Derivative;
 use glsl_layout::{vec2, vec4, AsStd140};
-use std::cmp::Ordering;
+use std::{cmp::Ordering, ops::Range};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
@@ -67,6 +67,8 @@ impl<B: Backend> RenderPlugin<B> for RenderUi {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
+        world.register::<UiClip>();
+        world.register::<UiQuads>();
         builder.add(
             UiGlyphsSystemDesc::<B>::default().build(world),
             "ui_glyphs_system",
@@ -82,7 +84,10 @@ impl<B: Backend> RenderPlugin<B> for RenderUi {
         _world: &World,
     ) -> Result<(), Error> {
         plan.extend_target(self.target, |ctx| {
-            ctx.add(RenderOrder::Overlay, DrawUiDesc::new().builder())?;
+            ctx.add(
+                RenderOrder::Overlay,
+                DrawUiDesc::new().with_samples(ctx.samples()).builder(),
+            )?;
             Ok(())
         });
         Ok(())
@@ -132,13 +137,21 @@ lazy_static::lazy_static! {
 
 /// A UI drawing pass that draws UI elements and text in screen-space
 #[derive(Clone, Debug, Default, PartialEq)]
-pub struct DrawUiDesc;
+pub struct DrawUiDesc {
+    samples: hal::image::NumSamples,
+}
 
 impl DrawUiDesc {
     /// Create new DrawUI pass description
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawUiDesc {
@@ -166,6 +179,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawUiDesc {
             subpass,
             framebuffer_width,
             framebuffer_height,
+            self.samples,
             vec![env.raw_layout(), textures.raw_layout()],
         )?;
 
@@ -187,11 +201,23 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawUiDesc {
             cached_draw_order: Default::default(),
             batches: Default::default(),
             white_tex,
+            white_tex_id: None,
+            framebuffer_size: (framebuffer_width, framebuffer_height),
+            draws: DrawCounts::default(),
         }))
     }
 }
 
-/// A UI drawing pass that draws UI elements and text in screen-space
+/// A UI drawing pass that draws UI elements and text in screen-space.
+///
+/// Every element is drawn as quads in the order of `UiTransform::global_z`: first its `UiImage`,
+/// then its custom `UiQuads`, then its text with the selection and the cursor. The quads of all
+/// elements share one vertex buffer and consecutive quads are drawn together as long as they use
+/// the same texture and clip rectangle. Solid color quads are drawn with any texture, so they
+/// don't break the batch they're in.
+///
+/// Custom widgets submit their quads by filling the `UiQuads` component of their entity, and
+/// scroll views or panels clip their descendants with the `UiClip` component.
 #[derive(Debug)]
 pub struct DrawUi<B: Backend> {
     pipeline: B::GraphicsPipeline,
@@ -199,10 +225,13 @@ pub struct DrawUi<B: Backend> {
     env: DynamicUniform<B, UiViewArgs>,
     textures: TextureSub<B>,
     vertex: DynamicVertexBuffer<B, UiArgs>,
-    batches: OrderedOneLevelBatch<TextureId, UiArgs>,
+    batches: UiBatches<TextureId>,
     change: ChangeDetection,
     cached_draw_order: CachedDrawOrder,
     white_tex: Handle<Texture>,
+    white_tex_id: Option<TextureId>,
+    framebuffer_size: (u32, u32),
+    draws: DrawCounts,
 }
 
 #[derive(Clone, Debug, Derivative)]
@@ -212,6 +241,75 @@ struct CachedDrawOrder {
     pub cache: Vec<(f32, Entity)>,
 }
 
+/// Color bias making the shader ignore the texture, so the quad is filled with its color.
+pub(crate) const SOLID_COLOR_BIAS: [f32; 4] = [1., 1., 1., 1.];
+
+/// Quads drawn with one texture and scissor rectangle.
+#[derive(Clone, Debug, PartialEq)]
+struct UiBatch<T> {
+    /// Texture of the batch, `None` if all of its quads are solid.
+    texture: Option<T>,
+    scissor: pso::Rect,
+    range: Range<u32>,
+}
+
+/// Quads of the UI in drawing order, split into batches of consecutive quads sharing their
+/// texture and scissor rectangle.
+#[derive(Debug, Derivative)]
+#[derivative(Default(bound = ""))]
+struct UiBatches<T> {
+    data: Vec<UiArgs>,
+    batches: Vec<UiBatch<T>>,
+    previous: Vec<UiBatch<T>>,
+}
+
+impl<T: Copy + PartialEq> UiBatches<T> {
+    /// Clears the quads, keeping the batches to detect changes.
+    fn swap_clear(&mut self) {
+        std::mem::swap(&mut self.batches, &mut self.previous);
+        self.batches.clear();
+        self.data.clear();
+    }
+
+    /// Appends quads drawn with the given texture, or solid ones without a texture.
+    fn insert(
+        &mut self,
+        texture: Option<T>,
+        scissor: pso::Rect,
+        quads: impl IntoIterator<Item = UiArgs>,
+    ) {
+        let start = self.data.len() as u32;
+        self.data.extend(quads);
+        let end = self.data.len() as u32;
+        if start == end {
+            return;
+        }
+        match self.batches.last_mut() {
+            Some(last)
+                if last.scissor == scissor
+                    && (texture.is_none() || last.texture.is_none() || last.texture == texture) =>
+            {
+                last.texture = last.texture.or(texture);
+                last.range.end = end;
+            }
+            _ => self.batches.push(UiBatch {
+                texture,
+                scissor,
+                range: start..end,
+            }),
+        }
+    }
+
+    /// Returns `true` if the batches differ from the ones before the last `swap_clear`.
+    fn changed(&self) -> bool {
+        self.batches != self.previous
+    }
+
+    fn count(&self) -> usize {
+        self.data.len()
+    }
+}
+
 impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
     fn prepare(
         &mut self,
@@ -224,6 +322,9 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
         #[cfg(feature = "profiler")]
         profile_scope!("prepare");
 
+        // The recorded draw calls may be reused.
+        <Read<'_, RenderStats>>::fetch(resources).report_draws(&self.draws);
+
         let (
             entities,
             images,
@@ -236,6 +337,7 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
             glyphs,
             glyphs_res,
             screen_dimesnions,
+            (parents, clips, quads),
         ) = <(
             Entities<'_>,
             ReadStorage<'_, UiImage>,
@@ -248,6 +350,11 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
             ReadStorage<'_, UiGlyphs>,
             ReadExpect<'_, UiGlyphsResource>,
             ReadExpect<'_, ScreenDimensions>,
+            (
+                ReadStorage<'_, Parent>,
+                ReadStorage<'_, UiClip>,
+                ReadStorage<'_, UiQuads>,
+            ),
         ) as SystemData>::fetch(resources);
 
         self.batches.swap_clear();
@@ -278,6 +385,7 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
                 return PrepareResult::DrawReuse;
             }
         };
+        self.white_tex_id = Some(white_tex_id);
 
         // Populate and update the draw order cache.
         let bitset = &mut self.cached_draw_order.cached;
@@ -331,6 +439,7 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
             .cache
             .sort_unstable_by(|&(z1, _), &(z2, _)| z1.partial_cmp(&z2).unwrap_or(Ordering::Equal));
 
+        let screen_size = (screen_dimesnions.width(), screen_dimesnions.height());
         for &(_z, entity) in &self.cached_draw_order.cache {
             // Skip hidden entities
             if hiddens.contains(entity) || hidden_propagates.contains(entity) {
@@ -341,6 +450,16 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
                 .get(entity)
                 .expect("Unreachable: Entity is guaranteed to be present based on earlier actions");
 
+            // Skip entities clipped entirely
+            let scissor = match scissor_rect(
+                clip_bounds(entity, &transforms, &parents, &clips),
+                screen_size,
+                self.framebuffer_size,
+            ) {
+                Some(scissor) => scissor,
+                None => continue,
+            };
+
             let tint = tints.get(entity).map(|t| {
                 let (r, g, b, a) = t.0.into_components();
                 [r, g, b, a]
@@ -354,18 +473,51 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
                     transform,
                     image,
                     &tint,
-                    white_tex_id,
+                    scissor,
                     &mut self.textures,
                     &mut self.batches,
                 );
                 changed = changed || this_changed;
             };
 
-            if let Some(glyph_data) = glyphs.get(entity) {
-                if !glyph_data.sel_vertices.is_empty() {
-                    self.batches
-                        .insert(white_tex_id, glyph_data.sel_vertices.iter().cloned());
+            if let Some(quads) = quads.get(entity) {
+                for quad in &quads.0 {
+                    let (texture, tex_coords, color_bias) = match &quad.texture {
+                        Some((tex, tex_coords)) => match self.textures.insert(
+                            factory,
+                            resources,
+                            tex,
+                            hal::image::Layout::ShaderReadOnlyOptimal,
+                        ) {
+                            Some((tex_id, this_changed)) => {
+                                changed = changed || this_changed;
+                                (Some(tex_id), *tex_coords, [0.; 4])
+                            }
+                            None => continue,
+                        },
+                        None => (None, [0., 0., 1., 1.], SOLID_COLOR_BIAS),
+                    };
+                    let color = match &tint {
+                        Some(t) => mul_blend(&quad.color, t),
+                        None => quad.color,
+                    };
+                    self.batches.insert(
+                        texture,
+                        scissor,
+                        Some(UiArgs {
+                            coords: quad.position.into(),
+                            dimensions: quad.size.into(),
+                            tex_coord_bounds: tex_coords.into(),
+                            color: color.into(),
+                            color_bias: color_bias.into(),
+                        }),
+                    );
                 }
+            }
+
+            if let Some(glyph_data) = glyphs.get(entity) {
+                self.batches
+                    .insert(None, scissor, glyph_data.sel_vertices.iter().cloned());
 
                 // blinking cursor
                 if selected.contains(entity) {
@@ -400,22 +552,24 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
                         let h = bottom - top;
 
                         self.batches.insert(
-                            white_tex_id,
+                            None,
+                            scissor,
                             Some(UiArgs {
                                 coords: [x, y].into(),
                                 dimensions: [w, h].into(),
                                 tex_coord_bounds: [0., 0., 1., 1.].into(),
                                 color: tint.unwrap_or([1., 1., 1., 1.]).into(),
-                                color_bias: [0., 0., 0., 0.].into(),
+                                color_bias: SOLID_COLOR_BIAS.into(),
                             }),
                         )
                     }
                 }
 
-                if !glyph_data.vertices.is_empty() {
-                    self.batches
-                        .insert(glyph_tex_id, glyph_data.vertices.iter().cloned());
-                }
+                self.batches.insert(
+                    Some(glyph_tex_id),
+                    scissor,
+                    glyph_data.vertices.iter().cloned(),
+                );
             }
         }
 
@@ -426,12 +580,12 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
             #[cfg(feature = "profiler")]
             profile_scope!("write");
 
-            self.vertex.write(
+            changed = self.vertex.write(
                 factory,
                 index,
                 self.batches.count() as u64,
-                Some(self.batches.data()),
-            );
+                Some(&self.batches.data),
+            ) || changed;
 
             let view_args = UiViewArgs {
                 inverse_window_size: [
@@ -451,23 +605,31 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
         mut encoder: RenderPassEncoder<'_, B>,
         index: usize,
         _subpass: hal::pass::Subpass<'_, B>,
-        _resources: &World,
+        resources: &World,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("draw");
 
-        if self.batches.count() > 0 {
+        let stats = <Read<'_, RenderStats>>::fetch(resources);
+        self.draws.clear();
+
+        if let (true, Some(white_tex_id)) = (self.batches.count() > 0, self.white_tex_id) {
             let layout = &self.pipeline_layout;
             encoder.bind_graphics_pipeline(&self.pipeline);
             self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
             self.vertex.bind(index, 0, 0, &mut encoder);
-            for (&tex, range) in self.batches.iter() {
+            for batch in &self.batches.batches {
+                let tex = batch.texture.unwrap_or(white_tex_id);
                 self.textures.bind(layout, 1, tex, &mut encoder);
+                self.draws
+                    .record_draw(hal::Primitive::TriangleStrip, 4, batch.range.len() as u32);
                 unsafe {
-                    encoder.draw(0..4, range);
+                    encoder.set_scissors(0, Some(&batch.scissor));
+                    encoder.draw(0..4, batch.range.clone());
                 }
             }
         }
+        stats.report_draws(&self.draws);
     }
 
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
@@ -480,11 +642,74 @@ impl<B: Backend> RenderGroup<B, World> for DrawUi<B> {
     }
 }
 
+/// Returns the bounds in pixels as `[left, bottom, right, top]` the entity is clipped to by
+/// itself and its ancestors with a `UiClip`, `None` if it isn't clipped.
+fn clip_bounds(
+    entity: Entity,
+    transforms: &ReadStorage<'_, UiTransform>,
+    parents: &ReadStorage<'_, Parent>,
+    clips: &ReadStorage<'_, UiClip>,
+) -> Option<[f32; 4]> {
+    let mut bounds: Option<[f32; 4]> = None;
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if let (true, Some(transform)) = (clips.contains(entity), transforms.get(entity)) {
+            let clip = [
+                transform.pixel_x - transform.pixel_width * 0.5,
+                transform.pixel_y - transform.pixel_height * 0.5,
+                transform.pixel_x + transform.pixel_width * 0.5,
+                transform.pixel_y + transform.pixel_height * 0.5,
+            ];
+            bounds = Some(match bounds {
+                Some(bounds) => [
+                    bounds[0].max(clip[0]),
+                    bounds[1].max(clip[1]),
+                    bounds[2].min(clip[2]),
+                    bounds[3].min(clip[3]),
+                ],
+                None => clip,
+            });
+        }
+        current = parents.get(entity).map(|parent| parent.entity);
+    }
+    bounds
+}
+
+/// Returns the scissor rectangle in the framebuffer for the clip bounds in pixels of the screen,
+/// covering the whole framebuffer without bounds, or `None` if nothing is left to draw.
+fn scissor_rect(
+    bounds: Option<[f32; 4]>,
+    (screen_width, screen_height): (f32, f32),
+    (framebuffer_width, framebuffer_height): (u32, u32),
+) -> Option<pso::Rect> {
+    let (width, height) = (framebuffer_width as f32, framebuffer_height as f32);
+    let [left, bottom, right, top] = match bounds {
+        Some(bounds) => bounds,
+        None => [0., 0., screen_width, screen_height],
+    };
+    // UI pixels grow upwards from the bottom, the framebuffer downwards from the top.
+    let (scale_x, scale_y) = (width / screen_width, height / screen_height);
+    let left = (left * scale_x).floor().max(0.);
+    let right = (right * scale_x).ceil().min(width);
+    let top = (height - top * scale_y).floor().max(0.);
+    let bottom = (height - bottom * scale_y).ceil().min(height);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some(pso::Rect {
+        x: left as i16,
+        y: top as i16,
+        w: (right - left) as i16,
+        h: (bottom - top) as i16,
+    })
+}
+
 fn build_ui_pipeline<B: Backend>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
+    samples: hal::image::NumSamples,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
@@ -505,6 +730,22 @@ fn build_ui_pipeline<B: Backend>(
                 .with_layout(&pipeline_layout)
                 .with_subpass(subpass)
                 .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                // Elements are clipped with a dynamic scissor.
+                .with_baked_states(pso::BakedStates {
+                    viewport: Some(pso::Viewport {
+                        rect: pso::Rect {
+                            x: 0,
+                            y: 0,
+                            w: framebuffer_width as i16,
+                            h: framebuffer_height as i16,
+                        },
+                        depth: 0.0..1.0,
+                    }),
+                    scissor: None,
+                    blend_color: None,
+                    depth_bounds: None,
+                })
+                .with_samples(samples)
                 .with_blend_targets(vec![pso::ColorBlendDesc {
                     mask: pso::ColorMask::ALL,
                     blend: Some(pso::BlendState::ALPHA),
@@ -538,9 +779,9 @@ fn render_image<B: Backend>(
     transform: &UiTransform,
     raw_image: &UiImage,
     tint: &Option<[f32; 4]>,
-    white_tex_id: TextureId,
+    scissor: pso::Rect,
     textures: &mut TextureSub<B>,
-    batches: &mut OrderedOneLevelBatch<TextureId, UiArgs>,
+    batches: &mut UiBatches<TextureId>,
 ) -> bool {
     let color = match (raw_image, tint.as_ref()) {
         (UiImage::SolidColor(color), Some(t)) => mul_blend(color, t),
@@ -590,7 +831,7 @@ fn render_image<B: Backend>(
                 tex,
                 hal::image::Layout::ShaderReadOnlyOptimal,
             ) {
-                batches.insert(tex_id, Some(args));
+                batches.insert(Some(tex_id), scissor, Some(args));
                 this_changed
             } else {
                 false
@@ -603,7 +844,7 @@ fn render_image<B: Backend>(
                 tex,
                 hal::image::Layout::ShaderReadOnlyOptimal,
             ) {
-                batches.insert(tex_id, Some(args));
+                batches.insert(Some(tex_id), scissor, Some(args));
                 this_changed
             } else {
                 false
@@ -618,7 +859,7 @@ fn render_image<B: Backend>(
                     &sprite_sheet.texture,
                     hal::image::Layout::ShaderReadOnlyOptimal,
                 ) {
-                    batches.insert(tex_id, Some(args));
+                    batches.insert(Some(tex_id), scissor, Some(args));
                     this_changed
                 } else {
                     false
@@ -696,7 +937,7 @@ fn render_image<B: Backend>(
                         .into();
                         temp_args.dimensions = [x_dimensions[x], y_dimensions[y]].into();
                         temp_args.coords = [x_coords[x], y_coords[y]].into();
-                        batches.insert(tex_id, Some(temp_args));
+                        batches.insert(Some(tex_id), scissor, Some(temp_args));
                     }
                 }
 
@@ -705,9 +946,87 @@ fn render_image<B: Backend>(
                 false
             }
         }
-        _ => {
-            batches.insert(white_tex_id, Some(args));
+        UiImage::SolidColor(_) => {
+            batches.insert(
+                None,
+                scissor,
+                Some(UiArgs {
+                    color_bias: SOLID_COLOR_BIAS.into(),
+                    ..args
+                }),
+            );
             false
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(x: f32) -> UiArgs {
+        UiArgs {
+            coords: [x, 0.].into(),
+            dimensions: [1., 1.].into(),
+            tex_coord_bounds: [0., 0., 1., 1.].into(),
+            color: [1.; 4].into(),
+            color_bias: [0.; 4].into(),
+        }
+    }
+
+    fn rect(x: i16, y: i16, w: i16, h: i16) -> pso::Rect {
+        pso::Rect { x, y, w, h }
+    }
+
+    #[test]
+    fn solid_quads_join_texture_batches() {
+        let full = rect(0, 0, 100, 100);
+        let mut batches = UiBatches::default();
+        batches.insert(None, full, vec![quad(0.), quad(1.)]);
+        batches.insert(Some(1), full, Some(quad(2.)));
+        batches.insert(None, full, Some(quad(3.)));
+        batches.insert(Some(2), full, Some(quad(4.)));
+        batches.insert(Some(2), rect(0, 0, 10, 10), Some(quad(5.)));
+        batches.insert(Some(2), full, None);
+
+        let batches_of = |batches: &UiBatches<u32>| {
+            batches
+                .batches
+                .iter()
+                .map(|batch| (batch.texture, batch.range.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            batches_of(&batches),
+            vec![(Some(1), 0..4), (Some(2), 4..5), (Some(2), 5..6)]
+        );
+        assert_eq!(batches.count(), 6);
+
+        batches.swap_clear();
+        batches.insert(None, full, vec![quad(0.); 4]);
+        batches.insert(Some(2), full, Some(quad(4.)));
+        batches.insert(Some(2), rect(0, 0, 10, 10), Some(quad(5.)));
+        assert!(batches.changed());
+        assert_eq!(batches_of(&batches), vec![(Some(2), 0..5), (Some(2), 5..6)]);
+    }
+
+    #[test]
+    fn scissor_flips_and_scales_clip_bounds() {
+        assert_eq!(
+            scissor_rect(None, (400., 300.), (800, 600)),
+            Some(rect(0, 0, 800, 600))
+        );
+        assert_eq!(
+            scissor_rect(Some([10., 20., 110., 70.]), (400., 300.), (800, 600)),
+            Some(rect(20, 460, 200, 100))
+        );
+        assert_eq!(
+            scissor_rect(Some([-10., -10., 500., 500.]), (400., 300.), (400, 300)),
+            Some(rect(0, 0, 400, 300))
+        );
+        assert_eq!(
+            scissor_rect(Some([50., 50., 40., 60.]), (400., 300.), (400, 300)),
+            None
+        );
+    }
+}
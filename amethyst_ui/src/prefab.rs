@@ -228,6 +228,10 @@ pub struct UiTextData {
     /// Optionally make the text editable
     #[serde(default)]
     pub editable: Option<TextEditingPrefab>,
+    /// Optionally set the text from a message of the `Localization`
+    #[cfg(feature = "locale")]
+    #[serde(default)]
+    pub localized: Option<crate::UiTextLocalized>,
 }
 impl Debug for UiTextData {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -239,7 +243,8 @@ impl Debug for UiTextData {
             _ => "<Font>".to_string(),
         };
 
-        f.debug_struct("UiTextData")
+        let mut debug = f.debug_struct("UiTextData");
+        debug
             .field("text", &self.text)
             .field("font_size", &self.font_size)
             .field("font", &font)
@@ -247,11 +252,18 @@ impl Debug for UiTextData {
             .field("password", &self.password)
             .field("line_mode", &self.line_mode)
             .field("align", &self.align)
-            .field("editable", &self.editable)
-            .finish()
+            .field("editable", &self.editable);
+        #[cfg(feature = "locale")]
+        debug.field("localized", &self.localized);
+        debug.finish()
     }
 }
 
+#[cfg(feature = "locale")]
+type LocalizedTexts<'a> = WriteStorage<'a, crate::UiTextLocalized>;
+#[cfg(not(feature = "locale"))]
+type LocalizedTexts<'a> = ();
+
 /// Loadable `TextEditing` data
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
@@ -282,6 +294,7 @@ impl<'a> PrefabData<'a> for UiTextData {
         WriteStorage<'a, UiText>,
         WriteStorage<'a, TextEditing>,
         <AssetPrefab<FontAsset> as PrefabData<'a>>::SystemData,
+        LocalizedTexts<'a>,
     );
     type Result = ();
 
@@ -292,7 +305,7 @@ impl<'a> PrefabData<'a> for UiTextData {
         _: &[Entity],
         _: &[Entity],
     ) -> Result<(), Error> {
-        let (ref mut texts, ref mut editables, ref mut fonts) = system_data;
+        let (ref mut texts, ref mut editables, ref mut fonts, _) = system_data;
         let font_handle = self
             .font
             .as_ref()
@@ -330,6 +343,12 @@ impl<'a> PrefabData<'a> for UiTextData {
                 ),
             )?;
         }
+        #[cfg(feature = "locale")]
+        {
+            if let Some(ref localized) = self.localized {
+                system_data.3.insert(entity, localized.clone())?;
+            }
+        }
         Ok(())
     }
 
@@ -338,7 +357,7 @@ impl<'a> PrefabData<'a> for UiTextData {
         progress: &mut ProgressCounter,
         system_data: &mut Self::SystemData,
     ) -> Result<bool, Error> {
-        let (_, _, ref mut fonts) = system_data;
+        let (_, _, ref mut fonts, _) = system_data;
 
         self.font
             .get_or_insert_with(|| {
@@ -973,6 +992,8 @@ fn walk_ui_tree<C, W>(
                 line_mode: None,
                 text: button.text.clone(),
                 font_size: button.font_size,
+                #[cfg(feature = "locale")]
+                localized: None,
             };
 
             prefab
@@ -0,0 +1,76 @@
+//! Module for the components clipping UI elements and drawing custom quads.
+
+use amethyst_assets::Handle;
+use amethyst_core::ecs::{Component, DenseVecStorage, NullStorage};
+use amethyst_rendy::Texture;
+use serde::{Deserialize, Serialize};
+
+/// Clips the rendering of the entity and all of its descendants to the rectangle of its
+/// `UiTransform`, e.g. for scrolling lists. Nested clips intersect.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UiClip;
+
+impl Component for UiClip {
+    type Storage = NullStorage<Self>;
+}
+
+/// A quad drawn by the `DrawUi` pass as part of a custom widget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiQuad {
+    /// Center of the quad in pixels, in the coordinates of `UiTransform::pixel_x` and
+    /// `UiTransform::pixel_y`.
+    pub position: [f32; 2],
+    /// Width and height of the quad in pixels.
+    pub size: [f32; 2],
+    /// Linear RGBA color, multiplied with the texture.
+    pub color: [f32; 4],
+    /// Texture and its coordinates as `[left, top, right, bottom]`. Quads without a texture are
+    /// filled with `color`.
+    pub texture: Option<(Handle<Texture>, [f32; 4])>,
+}
+
+impl UiQuad {
+    /// Create a quad filled with a solid color.
+    pub fn solid(position: [f32; 2], size: [f32; 2], color: [f32; 4]) -> Self {
+        UiQuad {
+            position,
+            size,
+            color,
+            texture: None,
+        }
+    }
+
+    /// Create a quad showing the given area of a texture.
+    pub fn textured(
+        position: [f32; 2],
+        size: [f32; 2],
+        texture: Handle<Texture>,
+        tex_coords: [f32; 4],
+    ) -> Self {
+        UiQuad {
+            position,
+            size,
+            color: [1.0; 4],
+            texture: Some((texture, tex_coords)),
+        }
+    }
+
+    /// Set the color the texture is multiplied with.
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Custom quads of a widget, drawn after the `UiImage` of the entity and before its text.
+///
+/// Widgets with their own look can fill this component from a system running before the
+/// rendering, e.g. with the bars of a graph in pixel coordinates computed from the
+/// `UiTransform` of the entity. The quads are drawn in the order of the list, batched with the
+/// rest of the UI, and hidden and clipped like the entity.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UiQuads(pub Vec<UiQuad>);
+
+impl Component for UiQuads {
+    type Storage = DenseVecStorage<Self>;
+}
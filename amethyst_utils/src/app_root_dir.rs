@@ -1,12 +1,19 @@
-//! Provides the directory of the executable.
+//! Provides the directory of the executable, and the platform directories for configuration,
+//! saves and caches.
 
-use std::{env, io, path};
+use std::{env, fs, io, path};
+
+/// Environment variable overriding the application root, see `application_root_dir`.
+pub const APPLICATION_ROOT_ENV: &str = "AMETHYST_APP_ROOT";
 
 /// Returns the cargo manifest directory when running the executable with cargo or the directory in
 /// which the executable resides otherwise, traversing symlinks if necessary.
 ///
 /// The algorithm used is:
 ///
+/// * If the `AMETHYST_APP_ROOT` environment variable is defined it is used as application root.
+///   This lets packagers install the assets elsewhere than next to the executable, and can be set
+///   from the game itself with `set_application_root_dir`.
 /// * If the `CARGO_MANIFEST_DIR` environment variable is defined it is used as application root.
 ///   This simplifies running development projects through `cargo run`.
 ///   See the [cargo reference documentation][cargo-ref] for more details.
@@ -18,6 +25,10 @@ use std::{env, io, path};
 /// [cargo-ref]: https://doc.rust-lang.org/cargo/reference/environment-variables.html
 /// [`std::env::current_exe`]: https://doc.rust-lang.org/std/env/fn.current_exe.html
 pub fn application_root_dir() -> Result<path::PathBuf, io::Error> {
+    if let Some(root_dir) = env::var_os(APPLICATION_ROOT_ENV) {
+        return Ok(path::PathBuf::from(root_dir));
+    }
+
     if let Some(manifest_dir) = env::var_os("CARGO_MANIFEST_DIR") {
         return Ok(path::PathBuf::from(manifest_dir));
     }
@@ -42,3 +53,92 @@ where
 {
     Ok(application_root_dir()?.join(path))
 }
+
+/// Overrides the application root returned by `application_root_dir` for this process, by setting
+/// the `AMETHYST_APP_ROOT` environment variable.
+///
+/// Call it before loading anything relative to the application root, e.g. from a path read in a
+/// launcher configuration.
+pub fn set_application_root_dir<P>(path: P)
+where
+    P: AsRef<path::Path>,
+{
+    env::set_var(APPLICATION_ROOT_ENV, path.as_ref());
+}
+
+/// Returns the directory for the configuration files of the application named `app_name`,
+/// creating it if needed.
+///
+/// | Platform | Example                                             |
+/// | -------- | --------------------------------------------------- |
+/// | Linux    | `$XDG_CONFIG_HOME/app_name` or `~/.config/app_name` |
+/// | macOS    | `~/Library/Preferences/app_name`                    |
+/// | Windows  | `%APPDATA%\app_name`                                |
+pub fn application_config_dir(app_name: &str) -> Result<path::PathBuf, io::Error> {
+    platform_dir(dirs::config_dir(), "configuration", app_name)
+}
+
+/// Returns the directory for the saved games of the application named `app_name`, creating it if
+/// needed.
+///
+/// | Platform | Example                                                    |
+/// | -------- | ---------------------------------------------------------- |
+/// | Linux    | `$XDG_DATA_HOME/app_name` or `~/.local/share/app_name`     |
+/// | macOS    | `~/Library/Application Support/app_name`                   |
+/// | Windows  | `%APPDATA%\app_name`                                       |
+pub fn application_save_dir(app_name: &str) -> Result<path::PathBuf, io::Error> {
+    platform_dir(dirs::data_dir(), "data", app_name)
+}
+
+/// Returns the directory for the cached files of the application named `app_name`, which may be
+/// deleted between runs, creating it if needed.
+///
+/// | Platform | Example                                            |
+/// | -------- | -------------------------------------------------- |
+/// | Linux    | `$XDG_CACHE_HOME/app_name` or `~/.cache/app_name`  |
+/// | macOS    | `~/Library/Caches/app_name`                        |
+/// | Windows  | `%LOCALAPPDATA%\app_name`                          |
+pub fn application_cache_dir(app_name: &str) -> Result<path::PathBuf, io::Error> {
+    platform_dir(dirs::cache_dir(), "cache", app_name)
+}
+
+fn platform_dir(
+    base: Option<path::PathBuf>,
+    kind: &str,
+    app_name: &str,
+) -> Result<path::PathBuf, io::Error> {
+    let dir = base
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Failed to find the {} directory of the platform", kind),
+            )
+        })?
+        .join(app_name);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn root_dir_can_be_overridden() {
+        let root = env::temp_dir().join("amethyst_app_root_test");
+        set_application_root_dir(&root);
+        assert_eq!(application_root_dir().unwrap(), root);
+        assert_eq!(application_dir("assets").unwrap(), root.join("assets"));
+        env::remove_var(APPLICATION_ROOT_ENV);
+        assert_ne!(application_root_dir().unwrap(), root);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn platform_dirs_are_per_application() {
+        let cache = application_cache_dir("amethyst_app_dirs_test").unwrap();
+        assert!(cache.is_dir());
+        assert!(cache.ends_with("amethyst_app_dirs_test"));
+        fs::remove_dir(cache).unwrap();
+    }
+}
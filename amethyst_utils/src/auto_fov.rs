@@ -14,13 +14,43 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
+/// Which field of view of a camera is kept when the aspect ratio of the screen changes.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum FovStrategy {
+    /// Keep the vertical fov, in radians, showing more of the scene on the sides of wider screens.
+    Vertical(f32),
+    /// Keep the horizontal fov, in radians, showing more of the scene above and below on taller
+    /// screens.
+    Horizontal(f32),
+}
+
+impl FovStrategy {
+    /// Returns the vertical fov of a camera with this strategy on a screen with the given aspect
+    /// ratio.
+    pub fn fov_y(self, aspect_ratio: f32) -> f32 {
+        match self {
+            FovStrategy::Vertical(fov_y) => fov_y,
+            FovStrategy::Horizontal(fov_x) => 2.0 * ((fov_x / 2.0).tan() / aspect_ratio).atan(),
+        }
+    }
+}
+
+impl Default for FovStrategy {
+    fn default() -> Self {
+        FovStrategy::Vertical(std::f32::consts::FRAC_PI_3)
+    }
+}
+
 /// A component that stores the parameters that the associated camera should have
 /// when it is managed by the AutoFovSystem.
+///
+/// Each camera selects its own `FovStrategy`, e.g. to keep the horizontal fov of a first person
+/// camera while a minimap keeps its vertical fov.
 #[derive(Clone, Debug, Deserialize, PrefabData, Serialize)]
 #[prefab(Component)]
 #[serde(default)]
 pub struct AutoFov {
-    fov_y: f32,
+    strategy: FovStrategy,
     z_near: f32,
     // FOV has to be adjusted when the camera parameters change or when a new
     // camera is created.
@@ -33,12 +63,35 @@ impl AutoFov {
         Default::default()
     }
 
+    /// Creates a new instance keeping the horizontal fov, with a near plane of 0.125.
+    pub fn horizontal(fov: f32) -> Self {
+        Self {
+            strategy: FovStrategy::Horizontal(fov),
+            ..Default::default()
+        }
+    }
+
     /// Set the vertical fov
     pub fn set_fov(&mut self, fov: f32) {
-        self.fov_y = fov;
+        self.set_strategy(FovStrategy::Vertical(fov));
+    }
+
+    /// Set the horizontal fov, keeping it instead of the vertical fov
+    pub fn set_horizontal_fov(&mut self, fov: f32) {
+        self.set_strategy(FovStrategy::Horizontal(fov));
+    }
+
+    /// Set which fov is kept when the aspect ratio changes
+    pub fn set_strategy(&mut self, strategy: FovStrategy) {
+        self.strategy = strategy;
         self.dirty = true;
     }
 
+    /// Returns which fov is kept when the aspect ratio changes
+    pub fn strategy(&self) -> FovStrategy {
+        self.strategy
+    }
+
     /// Set the distance to the near plane
     pub fn set_near(&mut self, near: f32) {
         self.z_near = near;
@@ -49,7 +102,7 @@ impl AutoFov {
 impl Default for AutoFov {
     fn default() -> Self {
         Self {
-            fov_y: std::f32::consts::FRAC_PI_3,
+            strategy: FovStrategy::default(),
             z_near: 0.125,
             dirty: true,
         }
@@ -60,7 +113,8 @@ impl Component for AutoFov {
     type Storage = HashMapStorage<Self>;
 }
 
-/// System that automatically adjusts the horizontal FOV based on the screen dimensions
+/// System that automatically adjusts the FOV of cameras based on the screen dimensions, keeping
+/// the vertical or horizontal FOV selected by their `FovStrategy`
 ///
 /// For a camera component to be managed by this system, the entity with the camera component should
 /// also have an `AutoFov` component attached to it.
@@ -93,8 +147,12 @@ impl<'a> System<'a> for AutoFovSystem {
 
         for (camera, auto_fov) in (&mut cameras, &mut auto_fovs).join() {
             if self.last_dimensions != *screen || auto_fov.dirty {
-                *camera =
-                    Camera::perspective(screen.aspect_ratio(), auto_fov.fov_y, auto_fov.z_near);
+                let aspect_ratio = screen.aspect_ratio();
+                *camera = Camera::perspective(
+                    aspect_ratio,
+                    auto_fov.strategy.fov_y(aspect_ratio),
+                    auto_fov.z_near,
+                );
                 auto_fov.dirty = false;
             }
         }
@@ -109,3 +167,22 @@ impl Default for AutoFovSystem {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn horizontal_fov_is_kept_across_aspect_ratios() {
+        let strategy = FovStrategy::Horizontal(FRAC_PI_2);
+        assert_close(strategy.fov_y(1.0), FRAC_PI_2);
+        // A 90 degree horizontal fov on a screen twice as wide as high spans half the height.
+        assert_close(strategy.fov_y(2.0), 2.0 * 0.5_f32.atan());
+        assert_close(FovStrategy::Vertical(1.0).fov_y(2.0), 1.0);
+    }
+}
@@ -0,0 +1,317 @@
+//! Trauma based screen shake of cameras, e.g. for hits and explosions.
+
+use amethyst_assets::PrefabData;
+use amethyst_core::{
+    ecs::{
+        Component, DenseVecStorage, Entity, Join, Read, ReaderId, System, SystemData, World,
+        WriteStorage,
+    },
+    math::{UnitQuaternion, Vector2, Vector3},
+    shrev::EventChannel,
+    timing::Time,
+    SystemDesc, Transform,
+};
+use amethyst_derive::PrefabData;
+use amethyst_error::Error;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Shakes the camera of its entity by offsetting its `Transform` with smooth noise, scaled by a
+/// trauma level that gameplay raises and that decays over time.
+///
+/// The shake grows with the square of the trauma by default, so small hits barely move the camera
+/// while big ones shake it hard, and several hits add up instead of restarting the shake.
+///
+/// The offset is removed again before the next one is applied, unless the translation or rotation
+/// was set in between, e.g. by a system following the player, in which case that value is kept.
+///
+/// # Example
+///
+/// ```rust
+/// # use amethyst_core::{math::Vector2, Transform};
+/// # use amethyst_utils::camera_shake::CameraShake;
+/// let mut shake = CameraShake::new(Vector2::new(16.0, 16.0), 15.0, 1.0).with_roll(0.1);
+/// shake.add_trauma(0.5);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct CameraShake {
+    /// Largest offset of the camera along its local x and y axes, reached at full trauma.
+    pub amplitude: Vector2<f32>,
+    /// Largest rotation of the camera around its local z axis in radians, reached at full trauma.
+    pub max_roll: f32,
+    /// How fast the shake changes direction, in noise periods per second.
+    pub frequency: f32,
+    /// Trauma removed per second.
+    pub decay: f32,
+    /// Power of the trauma giving the strength of the shake.
+    pub exponent: f32,
+    /// Seed of the noise, so that several cameras don't shake in sync.
+    pub seed: u32,
+    trauma: f32,
+    #[serde(skip)]
+    time: f32,
+    #[serde(skip)]
+    applied: Option<AppliedShake>,
+}
+
+/// The transform of a camera before and after the shake of the last frame.
+#[derive(Debug, Clone, PartialEq)]
+struct AppliedShake {
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    shaken_translation: Vector3<f32>,
+    shaken_rotation: UnitQuaternion<f32>,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        CameraShake::new(Vector2::new(1.0, 1.0), 15.0, 1.0)
+    }
+}
+
+impl CameraShake {
+    /// Creates a shake moving the camera up to `amplitude` at `frequency`, losing `decay` trauma
+    /// per second, without rolling it.
+    pub fn new(amplitude: Vector2<f32>, frequency: f32, decay: f32) -> Self {
+        CameraShake {
+            amplitude,
+            max_roll: 0.0,
+            frequency,
+            decay,
+            exponent: 2.0,
+            seed: 0,
+            trauma: 0.0,
+            time: 0.0,
+            applied: None,
+        }
+    }
+
+    /// Rolls the camera up to `max_roll` radians too.
+    pub fn with_roll(mut self, max_roll: f32) -> Self {
+        self.max_roll = max_roll;
+        self
+    }
+
+    /// Uses another seed for the noise.
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Returns the trauma, between 0 and 1.
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Adds trauma, up to 1.
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma.max(0.0)).min(1.0);
+    }
+
+    /// Stops the shake.
+    pub fn clear(&mut self) {
+        self.trauma = 0.0;
+    }
+
+    /// Returns the strength of the shake, between 0 and 1.
+    pub fn shake(&self) -> f32 {
+        self.trauma.powf(self.exponent)
+    }
+
+    /// Returns the offset along the local x and y axes and the roll of the camera at the current
+    /// time.
+    pub fn offset(&self) -> (Vector2<f32>, f32) {
+        let shake = self.shake();
+        let t = self.time * self.frequency;
+        (
+            Vector2::new(
+                self.amplitude.x * shake * noise(self.seed, t),
+                self.amplitude.y * shake * noise(self.seed.wrapping_add(1), t),
+            ),
+            self.max_roll * shake * noise(self.seed.wrapping_add(2), t),
+        )
+    }
+
+    fn update(&mut self, delta_seconds: f32, transform: &mut Transform) {
+        if let Some(applied) = self.applied.take() {
+            if *transform.translation() == applied.shaken_translation {
+                *transform.translation_mut() = applied.translation;
+            }
+            if *transform.rotation() == applied.shaken_rotation {
+                *transform.rotation_mut() = applied.rotation;
+            }
+        }
+        if self.trauma <= 0.0 {
+            self.time = 0.0;
+            return;
+        }
+
+        self.time += delta_seconds;
+        let (offset, roll) = self.offset();
+        let translation = *transform.translation();
+        let rotation = *transform.rotation();
+        *transform.translation_mut() += rotation * Vector3::new(offset.x, offset.y, 0.0);
+        *transform.rotation_mut() = rotation * UnitQuaternion::from_euler_angles(0.0, 0.0, roll);
+        self.applied = Some(AppliedShake {
+            translation,
+            rotation,
+            shaken_translation: *transform.translation(),
+            shaken_rotation: *transform.rotation(),
+        });
+        self.trauma = (self.trauma - self.decay * delta_seconds).max(0.0);
+    }
+}
+
+impl Component for CameraShake {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Gradient noise between -1 and 1, smoothly changing with `x` and crossing 0 at whole numbers.
+fn noise(seed: u32, x: f32) -> f32 {
+    let gradient = |i: i32| {
+        // Integer hash of the lattice point, mapped to a slope between -1 and 1.
+        let mut h = (i as u32).wrapping_mul(0x27d4_eb2d) ^ seed.wrapping_mul(0x1656_67b1);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x85eb_ca6b);
+        h ^= h >> 13;
+        (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+    };
+    let i = x.floor();
+    let f = x - i;
+    let (left, right) = (gradient(i as i32) * f, gradient(i as i32 + 1) * (f - 1.0));
+    let t = f * f * (3.0 - 2.0 * f);
+    // Slopes reach at most half a unit between lattice points.
+    2.0 * (left + (right - left) * t)
+}
+
+/// Event adding trauma to the `CameraShake` of a camera, or of every camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraShakeEvent {
+    /// The camera to shake, or `None` for every camera with a `CameraShake`.
+    pub camera: Option<Entity>,
+    /// The trauma to add.
+    pub trauma: f32,
+}
+
+/// Builds a `CameraShakeSystem`.
+#[derive(Default, Debug)]
+pub struct CameraShakeSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, CameraShakeSystem> for CameraShakeSystemDesc {
+    fn build(self, world: &mut World) -> CameraShakeSystem {
+        <CameraShakeSystem as System<'_>>::SystemData::setup(world);
+
+        let event_reader = world
+            .fetch_mut::<EventChannel<CameraShakeEvent>>()
+            .register_reader();
+
+        CameraShakeSystem::new(event_reader)
+    }
+}
+
+/// System adding the trauma of `CameraShakeEvent`s and shaking the cameras with a `CameraShake`.
+///
+/// Add it before the `TransformBundle`, so that the shake is part of the global matrix of the
+/// camera in the same frame.
+#[derive(Debug)]
+pub struct CameraShakeSystem {
+    event_reader: ReaderId<CameraShakeEvent>,
+}
+
+impl CameraShakeSystem {
+    /// Creates a new `CameraShakeSystem` reading the given `CameraShakeEvent`s.
+    pub fn new(event_reader: ReaderId<CameraShakeEvent>) -> Self {
+        CameraShakeSystem { event_reader }
+    }
+}
+
+impl<'a> System<'a> for CameraShakeSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, EventChannel<CameraShakeEvent>>,
+        WriteStorage<'a, CameraShake>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (time, events, mut shakes, mut transforms): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("camera_shake_system");
+
+        for event in events.read(&mut self.event_reader) {
+            match event.camera {
+                Some(camera) => {
+                    if let Some(shake) = shakes.get_mut(camera) {
+                        shake.add_trauma(event.trauma);
+                    }
+                }
+                None => {
+                    for shake in (&mut shakes).join() {
+                        shake.add_trauma(event.trauma);
+                    }
+                }
+            }
+        }
+
+        for (shake, transform) in (&mut shakes, &mut transforms).join() {
+            if shake.trauma > 0.0 || shake.applied.is_some() {
+                shake.update(time.delta_seconds(), transform);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn noise_is_smooth_and_bounded() {
+        let samples = (0..1000)
+            .map(|i| noise(7, i as f32 * 0.01))
+            .collect::<Vec<_>>();
+        assert!(samples.iter().all(|n| (-1.0..=1.0).contains(n)));
+        assert!(samples.windows(2).all(|w| (w[0] - w[1]).abs() < 0.1));
+        assert!(samples.iter().any(|n| n.abs() > 0.1));
+        assert_eq!(noise(7, 3.0), 0.0);
+    }
+
+    #[test]
+    fn shake_is_removed_once_the_trauma_decays() {
+        let mut shake = CameraShake::new(Vector2::new(10.0, 10.0), 3.3, 2.0).with_roll(0.2);
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(1.0, 2.0, 3.0);
+        let rest = transform.clone();
+
+        shake.add_trauma(0.8);
+        shake.update(0.1, &mut transform);
+        assert_ne!(transform.translation(), rest.translation());
+        assert_ne!(transform.rotation(), rest.rotation());
+        assert!((shake.trauma() - 0.6).abs() < 1.0e-6);
+
+        for _ in 0..4 {
+            shake.update(0.1, &mut transform);
+        }
+        assert_eq!(shake.trauma(), 0.0);
+        shake.update(0.1, &mut transform);
+        assert_eq!(transform.translation(), rest.translation());
+        assert_eq!(transform.rotation(), rest.rotation());
+    }
+
+    #[test]
+    fn moved_cameras_keep_their_new_position() {
+        let mut shake = CameraShake::new(Vector2::new(10.0, 10.0), 3.3, 1.0);
+        let mut transform = Transform::default();
+        shake.add_trauma(1.0);
+        shake.update(0.1, &mut transform);
+
+        transform.set_translation_xyz(50.0, 0.0, 0.0);
+        shake.clear();
+        shake.update(0.1, &mut transform);
+        assert_eq!(*transform.translation(), Vector3::new(50.0, 0.0, 0.0));
+    }
+}
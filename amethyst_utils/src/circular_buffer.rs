@@ -17,8 +17,10 @@ use std::collections::VecDeque;
 /// assert!(buf.push(3).is_some());
 /// assert_eq!(*buf.queue(), VecDeque::<u32>::from(vec![2, 3]));
 /// assert_eq!(buf.capacity(), 2);
+/// assert_eq!(buf.newest(), Some(&3));
+/// assert_eq!(buf.iter().rev().collect::<Vec<_>>(), vec![&3, &2]);
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CircularBuffer<A> {
     queue: VecDeque<A>,
     cap: usize,
@@ -56,4 +58,44 @@ impl<A> CircularBuffer<A> {
     pub fn capacity(&self) -> usize {
         self.cap
     }
+
+    /// Returns the number of values in the CircularBuffer.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if the CircularBuffer holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Returns true if the next push will drop the oldest value.
+    pub fn is_full(&self) -> bool {
+        self.queue.len() == self.cap
+    }
+
+    /// Returns the value at the given index, from the oldest one at 0.
+    pub fn get(&self, index: usize) -> Option<&A> {
+        self.queue.get(index)
+    }
+
+    /// Returns the oldest value.
+    pub fn oldest(&self) -> Option<&A> {
+        self.queue.front()
+    }
+
+    /// Returns the newest value.
+    pub fn newest(&self) -> Option<&A> {
+        self.queue.back()
+    }
+
+    /// Iterates over the values, from the oldest to the newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &A> + ExactSizeIterator {
+        self.queue.iter()
+    }
+
+    /// Removes all the values.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
 }
@@ -0,0 +1,174 @@
+//! Reloads configuration files when they change on disk, so changes apply while the game runs.
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use amethyst_config::{Config, ConfigWatcher, Validate};
+use amethyst_core::{
+    ecs::{System, Write},
+    shrev::EventChannel,
+};
+
+use log::{error, info};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Event sent by the `ConfigReloadSystem` with the configuration it reloaded.
+#[derive(Clone, Debug)]
+pub struct ConfigChanged<T>(pub T);
+
+/// Reloads a configuration file when it's modified, e.g. the `FrameRateLimitConfig` or the
+/// volumes of the audio.
+///
+/// A reloaded configuration that passes the validation replaces the `T` resource, if there is one,
+/// and is sent in a `ConfigChanged<T>` event for systems to apply it. A configuration that fails
+/// to load or validate is logged and ignored, keeping the previous one.
+///
+/// ```rust,ignore
+/// let game_data = GameDataBuilder::default().with(
+///     ConfigReloadSystem::<DisplayConfig>::new(display_config_path),
+///     "display_config_reload",
+///     &[],
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ConfigReloadSystem<T> {
+    watcher: ConfigWatcher<T>,
+    interval: Duration,
+    last: Instant,
+}
+
+impl<T: Config> ConfigReloadSystem<T> {
+    /// Creates a system reloading the file, checking it for changes every second.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        ConfigReloadSystem {
+            watcher: ConfigWatcher::new(path),
+            interval: Duration::from_secs(1),
+            last: Instant::now(),
+        }
+    }
+
+    /// Rejects the reloaded configurations for which `Validate::validate` returns an error.
+    pub fn with_validation(mut self) -> Self
+    where
+        T: Validate,
+    {
+        self.watcher = self.watcher.with_validation();
+        self
+    }
+
+    /// Sets the interval at which the file is checked for changes.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl<'a, T> System<'a> for ConfigReloadSystem<T>
+where
+    T: Config + Clone + Send + Sync + 'static,
+{
+    type SystemData = (
+        Option<Write<'a, T>>,
+        Write<'a, EventChannel<ConfigChanged<T>>>,
+    );
+
+    fn run(&mut self, (config, mut events): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("config_reload_system");
+
+        if self.last.elapsed() < self.interval {
+            return;
+        }
+        self.last = Instant::now();
+
+        match self.watcher.poll() {
+            Some(Ok(reloaded)) => {
+                info!("Reloaded config {}", self.watcher.path().display());
+                if let Some(mut config) = config {
+                    *config = reloaded.clone();
+                }
+                events.single_write(ConfigChanged(reloaded));
+            }
+            Some(Err(err)) => error!(
+                "Failed to reload config {}: {}",
+                self.watcher.path().display(),
+                err
+            ),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use amethyst_core::ecs::{RunNow, World, WorldExt};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Volume {
+        music: f32,
+    }
+
+    impl Validate for Volume {
+        fn validate(&self) -> Result<(), String> {
+            if self.music <= 1.0 {
+                Ok(())
+            } else {
+                Err("music is too loud".to_string())
+            }
+        }
+    }
+
+    fn write(path: &Path, music: f32) {
+        Volume { music }.write(path).unwrap();
+    }
+
+    #[test]
+    fn reloads_modified_config() {
+        let dir = std::env::temp_dir().join(format!("config_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("volume.ron");
+        write(&path, 0.5);
+
+        let mut world = World::new();
+        world.insert(Volume { music: 0.5 });
+        let mut reader = world
+            .entry::<EventChannel<ConfigChanged<Volume>>>()
+            .or_insert_with(EventChannel::new)
+            .register_reader();
+        let mut system = ConfigReloadSystem::<Volume>::new(&path)
+            .with_validation()
+            .with_interval(Duration::from_secs(0));
+
+        system.run_now(&world);
+        let events = world.read_resource::<EventChannel<ConfigChanged<Volume>>>();
+        assert_eq!(events.read(&mut reader).count(), 0);
+        drop(events);
+
+        write(&path, 0.8);
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<Volume>().music, 0.8);
+        let events = world.read_resource::<EventChannel<ConfigChanged<Volume>>>();
+        let changed = events
+            .read(&mut reader)
+            .map(|e| e.0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(changed, vec![Volume { music: 0.8 }]);
+        drop(events);
+
+        write(&path, 2.0);
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<Volume>().music, 0.8);
+        let events = world.read_resource::<EventChannel<ConfigChanged<Volume>>>();
+        assert_eq!(events.read(&mut reader).count(), 0);
+        drop(events);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
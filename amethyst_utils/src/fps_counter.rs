@@ -6,6 +6,7 @@ use amethyst_core::{
     SystemBundle,
 };
 use amethyst_error::Error;
+use std::time::Duration;
 
 use crate::circular_buffer::CircularBuffer;
 
@@ -24,6 +25,8 @@ use thread_profiler::profile_scope;
 /// game would be running if all frames were exactly like this one.
 /// sampled_fps will return the averaged framerate. This gives a better approximation of the "felt"
 /// framerate by the user.
+/// frame_time_stats will return the percentiles of the frame times over the samples, showing the
+/// stutters an average hides, e.g. for a debug HUD.
 ///
 /// # Example
 /// ```rust
@@ -79,6 +82,59 @@ impl FpsCounter {
         }
         1.0e9 * self.buf.queue().len() as f32 / self.sum as f32
     }
+
+    /// Returns the number of frames the statistics are computed over.
+    pub fn sample_size(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Get the frame time below which the given percentage of the sampled frames were, using the
+    /// nearest rank. Returns `None` until a frame was pushed.
+    pub fn frame_time_percentile(&self, percentile: f32) -> Option<Duration> {
+        let mut samples = self.buf.queue().iter().copied().collect::<Vec<_>>();
+        samples.sort_unstable();
+        Self::percentile(&samples, percentile)
+    }
+
+    /// Get the statistics of the frame times over the samplesize frames. Returns `None` until a
+    /// frame was pushed.
+    pub fn frame_time_stats(&self) -> Option<FrameTimeStats> {
+        let mut samples = self.buf.queue().iter().copied().collect::<Vec<_>>();
+        samples.sort_unstable();
+        Some(FrameTimeStats {
+            min: Duration::from_nanos(*samples.first()?),
+            max: Duration::from_nanos(*samples.last()?),
+            mean: Duration::from_nanos(self.sum / samples.len() as u64),
+            p50: Self::percentile(&samples, 50.0)?,
+            p95: Self::percentile(&samples, 95.0)?,
+            p99: Self::percentile(&samples, 99.0)?,
+        })
+    }
+
+    fn percentile(sorted: &[u64], percentile: f32) -> Option<Duration> {
+        if sorted.is_empty() {
+            return None;
+        }
+        let rank = (percentile / 100.0 * sorted.len() as f32).ceil().max(1.0) as usize;
+        Some(Duration::from_nanos(sorted[rank.min(sorted.len()) - 1]))
+    }
+}
+
+/// Statistics of the frame times sampled by a `FpsCounter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTimeStats {
+    /// The shortest frame time.
+    pub min: Duration,
+    /// The longest frame time.
+    pub max: Duration,
+    /// The average frame time.
+    pub mean: Duration,
+    /// The median frame time.
+    pub p50: Duration,
+    /// The frame time 95% of the frames were at or below.
+    pub p95: Duration,
+    /// The frame time 99% of the frames were at or below.
+    pub p99: Duration,
 }
 
 /// Add this system to your game to automatically push FPS values
@@ -103,16 +159,64 @@ impl<'a> System<'a> for FpsCounterSystem {
 }
 
 ///Automatically adds a FpsCounterSystem and a FpsCounter resource with the specified sample size.
-#[derive(Default, Debug)]
-pub struct FpsCounterBundle;
+#[derive(Debug)]
+pub struct FpsCounterBundle {
+    sample_size: usize,
+}
+
+impl Default for FpsCounterBundle {
+    fn default() -> Self {
+        FpsCounterBundle::new(20)
+    }
+}
+
+impl FpsCounterBundle {
+    ///Creates a new FpsCounterBundle computing the statistics over the last sample_size frames.
+    pub fn new(sample_size: usize) -> Self {
+        FpsCounterBundle { sample_size }
+    }
+}
 
 impl<'a, 'b> SystemBundle<'a, 'b> for FpsCounterBundle {
     fn build(
         self,
-        _world: &mut World,
+        world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
+        world.insert(FpsCounter::new(self.sample_size));
         builder.add(FpsCounterSystem, "fps_counter_system", &[]);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_time_percentiles_use_the_rolling_window() {
+        let mut counter = FpsCounter::new(100);
+        assert_eq!(counter.frame_time_stats(), None);
+        // An old slow frame, dropped from the window by the next 100 frames.
+        counter.push(1_000);
+        for frame in 1..=100 {
+            counter.push(frame);
+        }
+
+        let stats = counter.frame_time_stats().unwrap();
+        assert_eq!(stats.min, Duration::from_nanos(1));
+        assert_eq!(stats.max, Duration::from_nanos(100));
+        assert_eq!(stats.mean, Duration::from_nanos(50));
+        assert_eq!(stats.p50, Duration::from_nanos(50));
+        assert_eq!(stats.p95, Duration::from_nanos(95));
+        assert_eq!(stats.p99, Duration::from_nanos(99));
+        assert_eq!(
+            counter.frame_time_percentile(0.0),
+            Some(Duration::from_nanos(1))
+        );
+        assert_eq!(
+            counter.frame_time_percentile(100.0),
+            Some(Duration::from_nanos(100))
+        );
+    }
+}
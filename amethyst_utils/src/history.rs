@@ -0,0 +1,198 @@
+//! Records the past values of a component, e.g. for kill cams, rollback or debugging.
+
+use std::marker::PhantomData;
+
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage, Join, Read, ReadStorage, System, WriteStorage},
+    timing::Time,
+};
+
+use crate::circular_buffer::CircularBuffer;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// `Component` holding snapshots of the `T` component of its entity, taken every `interval`
+/// frames by the `HistorySystem<T>`, along with the frame number they were taken at.
+///
+/// Only the last `capacity` snapshots are kept, so a history of a 60 fps game snapshotting every
+/// frame with a capacity of 180 holds the last three seconds.
+///
+/// # Example
+///
+/// ```rust
+/// # use amethyst_core::Transform;
+/// # use amethyst_utils::history::History;
+/// let history = History::<Transform>::new(180, 1);
+/// assert!(history.latest().is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct History<T>
+where
+    T: Component + Clone + Send + Sync,
+{
+    snapshots: CircularBuffer<(u64, T)>,
+    interval: u64,
+    last_frame: Option<u64>,
+}
+
+impl<T> History<T>
+where
+    T: Component + Clone + Send + Sync,
+{
+    /// Creates a history keeping `capacity` snapshots, taken every `interval` frames.
+    ///
+    /// An `interval` of 0 is treated as 1.
+    pub fn new(capacity: usize, interval: u64) -> Self {
+        History {
+            snapshots: CircularBuffer::new(capacity),
+            interval: interval.max(1),
+            last_frame: None,
+        }
+    }
+
+    /// Returns the number of frames between snapshots.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Returns the snapshots with their frame numbers, from the oldest to the newest.
+    pub fn snapshots(&self) -> &CircularBuffer<(u64, T)> {
+        &self.snapshots
+    }
+
+    /// Returns the newest snapshot with its frame number.
+    pub fn latest(&self) -> Option<(u64, &T)> {
+        self.snapshots
+            .newest()
+            .map(|(frame, value)| (*frame, value))
+    }
+
+    /// Returns the newest snapshot taken at or before `frame`, with its frame number.
+    pub fn at_frame(&self, frame: u64) -> Option<(u64, &T)> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|(snapshot_frame, _)| *snapshot_frame <= frame)
+            .map(|(snapshot_frame, value)| (*snapshot_frame, value))
+    }
+
+    /// Returns true if a snapshot is due at `frame`.
+    pub fn is_due(&self, frame: u64) -> bool {
+        match self.last_frame {
+            Some(last_frame) => frame >= last_frame + self.interval,
+            None => true,
+        }
+    }
+
+    /// Records a snapshot taken at `frame`, dropping the oldest one if the history is full.
+    pub fn record(&mut self, frame: u64, value: T) {
+        self.last_frame = Some(frame);
+        self.snapshots.push((frame, value));
+    }
+
+    /// Removes every snapshot, e.g. after teleporting the entity.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.last_frame = None;
+    }
+}
+
+impl<T> Component for History<T>
+where
+    T: Component + Clone + Send + Sync,
+{
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// System recording the `T` component of the entities with a `History<T>` when their snapshot is
+/// due.
+///
+/// Add it after the systems changing `T`, so that the snapshots hold the values of the frame.
+#[derive(Debug)]
+pub struct HistorySystem<T> {
+    _m: PhantomData<T>,
+}
+
+impl<T> HistorySystem<T> {
+    /// Creates a new `HistorySystem<T>`.
+    pub fn new() -> Self {
+        HistorySystem { _m: PhantomData }
+    }
+}
+
+impl<T> Default for HistorySystem<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> System<'a> for HistorySystem<T>
+where
+    T: Component + Clone + Send + Sync,
+{
+    type SystemData = (
+        Read<'a, Time>,
+        ReadStorage<'a, T>,
+        WriteStorage<'a, History<T>>,
+    );
+
+    fn run(&mut self, (time, values, mut histories): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("history_system");
+
+        let frame = time.frame_number();
+        for (value, history) in (&values, &mut histories).join() {
+            if history.is_due(frame) {
+                history.record(frame, value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+
+    impl Component for Health {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    #[test]
+    fn snapshots_are_taken_every_interval() {
+        let mut world = World::new();
+        world.insert(Time::default());
+        world.register::<Health>();
+        world.register::<History<Health>>();
+        let entity = world
+            .create_entity()
+            .with(Health(100))
+            .with(History::<Health>::new(3, 2))
+            .build();
+
+        let mut system = HistorySystem::<Health>::new();
+        for frame in 0..8 {
+            world.write_storage::<Health>().get_mut(entity).unwrap().0 = 100 - frame as u32;
+            system.run_now(&world);
+            world.maintain();
+            world.write_resource::<Time>().increment_frame_number();
+        }
+
+        let histories = world.read_storage::<History<Health>>();
+        let history = histories.get(entity).unwrap();
+        let frames = history
+            .snapshots()
+            .iter()
+            .map(|(frame, health)| (*frame, health.0))
+            .collect::<Vec<_>>();
+        assert_eq!(frames, vec![(2, 98), (4, 96), (6, 94)]);
+        assert_eq!(history.latest(), Some((6, &Health(94))));
+        assert_eq!(history.at_frame(5), Some((4, &Health(96))));
+        assert_eq!(history.at_frame(1), None);
+    }
+}
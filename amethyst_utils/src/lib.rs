@@ -17,10 +17,16 @@ pub use self::app_root_dir::*;
 
 pub mod app_root_dir;
 pub mod auto_fov;
+pub mod camera_shake;
 pub mod circular_buffer;
+pub mod config_reload;
 pub mod fps_counter;
+pub mod history;
 pub mod ortho_camera;
+pub mod pixel_camera;
+pub mod projection_transition;
 pub mod removal;
 pub mod scene;
+pub mod spatial_grid;
 pub mod tag;
 pub mod time_destroy;
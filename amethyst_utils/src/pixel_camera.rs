@@ -0,0 +1,216 @@
+//! Provides an orthographic camera following the size of the window, for pixel-perfect 2D.
+
+use amethyst_assets::PrefabData;
+use amethyst_core::ecs::{
+    Component, DenseVecStorage, Entity, Join, ReadExpect, System, WriteStorage,
+};
+use amethyst_derive::PrefabData;
+use amethyst_error::Error;
+use amethyst_rendy::camera::Camera;
+use amethyst_window::ScreenDimensions;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// How a `PixelCamera` maps window pixels to world units.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PixelCameraMode {
+    /// Shows `scale` window pixels per world unit, so that sprites sized in pixels are drawn at
+    /// their size times the scale, and resizing the window shows more or less of the world.
+    Window {
+        /// Window pixels per world unit.
+        scale: f32,
+    },
+    /// Keeps a virtual resolution of `width` by `height` world units visible, scaled by the
+    /// largest integer factor fitting in the window so that every virtual pixel covers the same
+    /// number of window pixels. The rest of the window shows more of the world around it, which
+    /// a `CameraViewport` can hide.
+    Virtual {
+        /// Width of the virtual resolution.
+        width: u32,
+        /// Height of the virtual resolution.
+        height: u32,
+    },
+}
+
+impl Default for PixelCameraMode {
+    fn default() -> Self {
+        PixelCameraMode::Window { scale: 1.0 }
+    }
+}
+
+/// `Component` attached to the camera's entity that keeps its orthographic projection matched to
+/// the size of the window, centered on the camera's `Transform`.
+///
+/// The edges of the projection are rounded so that window pixels line up with world pixels when
+/// the camera sits at whole world units, even in windows with an odd size.
+/// You must add the `PixelCameraSystem` to your dispatcher for this to take effect.
+///
+/// # Example
+///
+/// ```rust
+/// # use amethyst_core::ecs::{Builder, World, WorldExt};
+/// # use amethyst_core::Transform;
+/// # use amethyst_rendy::camera::Camera;
+/// # use amethyst_utils::pixel_camera::*;
+/// # let mut world = World::new();
+/// # world.register::<Transform>();
+/// # world.register::<Camera>();
+/// # world.register::<PixelCamera>();
+/// world
+///     .create_entity()
+///     .with(Transform::default())
+///     .with(Camera::standard_2d(320.0, 180.0))
+///     .with(PixelCamera::virtual_resolution(320, 180))
+///     .build();
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct PixelCamera {
+    mode: PixelCameraMode,
+    near: f32,
+    far: f32,
+    #[serde(skip)]
+    window_size_cache: Option<(u32, u32)>,
+}
+
+impl PixelCamera {
+    /// Creates a camera showing `scale` window pixels per world unit.
+    pub fn window(scale: f32) -> Self {
+        Self::new(PixelCameraMode::Window { scale })
+    }
+
+    /// Creates a camera keeping a virtual resolution visible with integer scaling.
+    pub fn virtual_resolution(width: u32, height: u32) -> Self {
+        Self::new(PixelCameraMode::Virtual { width, height })
+    }
+
+    /// Creates a camera with the given mode, and the depth range of `Camera::standard_2d`.
+    pub fn new(mode: PixelCameraMode) -> Self {
+        PixelCamera {
+            mode,
+            near: 0.125,
+            far: 2000.0,
+            window_size_cache: None,
+        }
+    }
+
+    /// Sets the distances to the near and far planes.
+    pub fn with_depth(mut self, near: f32, far: f32) -> Self {
+        self.near = near;
+        self.far = far;
+        self
+    }
+
+    /// Returns how window pixels are mapped to world units.
+    pub fn mode(&self) -> PixelCameraMode {
+        self.mode
+    }
+
+    /// Changes how window pixels are mapped to world units.
+    pub fn set_mode(&mut self, mode: PixelCameraMode) {
+        self.mode = mode;
+        self.window_size_cache = None;
+    }
+
+    /// Returns the number of window pixels per world unit in a window of the given size in
+    /// physical pixels.
+    pub fn scale(&self, window_width: u32, window_height: u32) -> f32 {
+        match self.mode {
+            PixelCameraMode::Window { scale } => scale,
+            PixelCameraMode::Virtual { width, height } => (window_width / width.max(1))
+                .min(window_height / height.max(1))
+                .max(1) as f32,
+        }
+    }
+
+    /// Returns the left, right, bottom and top edges of the projection, relative to the camera,
+    /// in a window of the given size in physical pixels.
+    pub fn camera_offsets(&self, window_width: u32, window_height: u32) -> (f32, f32, f32, f32) {
+        let scale = self.scale(window_width, window_height);
+        // Odd sizes put the center on a window pixel, so the edges are moved by half a pixel to
+        // keep them on the boundaries of world pixels.
+        let left = -((window_width / 2) as f32) / scale;
+        let bottom = -((window_height / 2) as f32) / scale;
+        (
+            left,
+            left + window_width as f32 / scale,
+            bottom,
+            bottom + window_height as f32 / scale,
+        )
+    }
+}
+
+impl Default for PixelCamera {
+    fn default() -> Self {
+        Self::new(PixelCameraMode::default())
+    }
+}
+
+impl Component for PixelCamera {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// System that updates the projection of the cameras with a `PixelCamera` when the size of the
+/// window changes.
+#[derive(Default, Debug)]
+pub struct PixelCameraSystem;
+
+impl<'a> System<'a> for PixelCameraSystem {
+    type SystemData = (
+        ReadExpect<'a, ScreenDimensions>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, PixelCamera>,
+    );
+
+    fn run(&mut self, (dimensions, mut cameras, mut pixel_cameras): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("pixel_camera_system");
+
+        let size = (dimensions.width() as u32, dimensions.height() as u32);
+        for (camera, pixel_camera) in (&mut cameras, &mut pixel_cameras).join() {
+            if pixel_camera.window_size_cache != Some(size) {
+                pixel_camera.window_size_cache = Some(size);
+                let offsets = pixel_camera.camera_offsets(size.0, size.1);
+                *camera = Camera::orthographic(
+                    offsets.0,
+                    offsets.1,
+                    offsets.2,
+                    offsets.3,
+                    pixel_camera.near,
+                    pixel_camera.far,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn virtual_resolution_scales_by_integers() {
+        let camera = PixelCamera::virtual_resolution(320, 180);
+        assert_eq!(camera.scale(1920, 1080), 6.0);
+        assert_eq!(camera.scale(1280, 1080), 4.0);
+        // Windows smaller than the virtual resolution show it unscaled and cropped.
+        assert_eq!(camera.scale(200, 100), 1.0);
+        assert_eq!(
+            camera.camera_offsets(1280, 1080),
+            (-160.0, 160.0, -135.0, 135.0)
+        );
+    }
+
+    #[test]
+    fn odd_windows_stay_on_world_pixels() {
+        let camera = PixelCamera::window(2.0);
+        assert_eq!(
+            camera.camera_offsets(801, 600),
+            (-200.0, 200.5, -150.0, 150.0)
+        );
+    }
+}
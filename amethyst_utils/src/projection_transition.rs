@@ -0,0 +1,196 @@
+//! Utility to smoothly transition cameras between projections, e.g. for cutscene zooms.
+
+use amethyst_core::{
+    ecs::{Component, Entities, HashMapStorage, Join, Read, System, WriteStorage},
+    math::Matrix4,
+    timing::Time,
+};
+use amethyst_rendy::camera::Camera;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Transitions the `Camera` on the same entity from one projection to another over time, managed
+/// by the `ProjectionTransitionSystem`.
+///
+/// The projection matrices are interpolated with an ease in and out, so it works between two
+/// perspective or two orthographic projections as well as from one kind to the other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectionTransition {
+    from: Matrix4<f32>,
+    to: Camera,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl ProjectionTransition {
+    /// Creates a transition from the projection of `from` to the projection of `to` over
+    /// `duration` seconds.
+    pub fn new(from: &Camera, to: Camera, duration: f32) -> Self {
+        Self {
+            from: from.matrix,
+            to,
+            duration,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Returns the completed fraction of the transition, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the projection matrix at the current progress.
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let t = self.progress();
+        let eased = t * t * (3.0 - 2.0 * t);
+        self.from * (1.0 - eased) + self.to.matrix * eased
+    }
+}
+
+impl Component for ProjectionTransition {
+    type Storage = HashMapStorage<Self>;
+}
+
+/// System advancing `ProjectionTransition`s and updating their cameras, removing the transitions
+/// once they are complete.
+///
+/// Cameras adjusted by other systems, like the `AutoFovSystem`, may be overwritten by them during
+/// the transition.
+#[derive(Debug, Default)]
+pub struct ProjectionTransitionSystem;
+
+impl<'a> System<'a> for ProjectionTransitionSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        WriteStorage<'a, ProjectionTransition>,
+        WriteStorage<'a, Camera>,
+    );
+
+    fn run(&mut self, (entities, time, mut transitions, mut cameras): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("projection_transition_system");
+
+        let mut finished = Vec::new();
+        for (entity, transition, camera) in (&entities, &mut transitions, &mut cameras).join() {
+            transition.elapsed += time.delta_seconds();
+            if transition.progress() >= 1.0 {
+                *camera = transition.to.clone();
+                finished.push(entity);
+            } else {
+                let matrix = transition.matrix();
+                // Blends of the two projections are degenerate only in contrived cases, keep the
+                // previous projection for those frames.
+                if let Some(inverse) = matrix.try_inverse() {
+                    camera.matrix = matrix;
+                    camera.inverse = inverse;
+                }
+            }
+        }
+        for entity in finished {
+            transitions.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+    use std::time::Duration;
+
+    fn assert_matrix_eq(left: &Matrix4<f32>, right: &Matrix4<f32>) {
+        assert!((left - right).amax() < 1.0e-5, "{} is not {}", left, right);
+    }
+
+    #[test]
+    fn matrix_is_eased_from_start_to_end() {
+        let from = Camera::standard_3d(800.0, 600.0);
+        let to = Camera::standard_3d(400.0, 600.0);
+        let mut transition = ProjectionTransition::new(&from, to.clone(), 2.0);
+
+        assert_eq!(transition.progress(), 0.0);
+        assert_matrix_eq(&transition.matrix(), &from.matrix);
+
+        transition.elapsed = 0.5;
+        assert_eq!(transition.progress(), 0.25);
+        // the ease in and out moves slower than linearly near the start
+        let eased = 0.25 * 0.25 * (3.0 - 2.0 * 0.25);
+        assert_matrix_eq(
+            &transition.matrix(),
+            &(from.matrix * (1.0 - eased) + to.matrix * eased),
+        );
+
+        transition.elapsed = 1.0;
+        assert_eq!(transition.progress(), 0.5);
+        assert_matrix_eq(&transition.matrix(), &((from.matrix + to.matrix) * 0.5));
+
+        transition.elapsed = 3.0;
+        assert_eq!(transition.progress(), 1.0);
+        assert_matrix_eq(&transition.matrix(), &to.matrix);
+    }
+
+    #[test]
+    fn transitions_without_duration_are_complete() {
+        let camera = Camera::standard_2d(800.0, 600.0);
+        let transition = ProjectionTransition::new(&camera, camera.clone(), 0.0);
+        assert_eq!(transition.progress(), 1.0);
+    }
+
+    /// Runs the transition from `from` to `to` over a second in steps of a quarter second,
+    /// returning the matrices of the camera after each step.
+    fn run_transition(from: Camera, to: Camera) -> Vec<Matrix4<f32>> {
+        let mut world = World::new();
+        let mut system = ProjectionTransitionSystem;
+        RunNow::setup(&mut system, &mut world);
+        world
+            .write_resource::<Time>()
+            .set_delta_time(Duration::from_millis(250));
+        let transition = ProjectionTransition::new(&from, to.clone(), 1.0);
+        let entity = world.create_entity().with(from).with(transition).build();
+
+        let mut matrices = Vec::new();
+        for step in 1..=4 {
+            system.run_now(&world);
+            world.maintain();
+            let cameras = world.read_storage::<Camera>();
+            let camera = cameras.get(entity).unwrap();
+            assert_matrix_eq(&(camera.matrix * camera.inverse), &Matrix4::identity());
+            let transitions = world.read_storage::<ProjectionTransition>();
+            assert_eq!(transitions.get(entity).is_some(), step < 4);
+            matrices.push(camera.matrix);
+        }
+        assert_matrix_eq(&matrices[3], &to.matrix);
+        matrices
+    }
+
+    #[test]
+    fn system_switches_orthographic_to_perspective() {
+        let ortho = Camera::standard_2d(800.0, 600.0);
+        let perspective = Camera::standard_3d(800.0, 600.0);
+        let matrices = run_transition(ortho.clone(), perspective.clone());
+
+        // the perspective divide is blended in, and only complete at the end
+        assert_eq!(ortho.matrix[(3, 3)], 1.0);
+        assert_eq!(perspective.matrix[(3, 3)], 0.0);
+        let w = matrices.iter().map(|m| m[(3, 3)]).collect::<Vec<_>>();
+        assert!(w[0] < 1.0 && w[0] > w[1] && w[1] > w[2] && w[2] > 0.0);
+        assert_eq!(w[3], 0.0);
+    }
+
+    #[test]
+    fn system_switches_perspective_to_orthographic() {
+        let perspective = Camera::standard_3d(800.0, 600.0);
+        let ortho = Camera::standard_2d(800.0, 600.0);
+        let matrices = run_transition(perspective, ortho);
+
+        let w = matrices.iter().map(|m| m[(3, 3)]).collect::<Vec<_>>();
+        assert!(w[0] > 0.0 && w[0] < w[1] && w[1] < w[2] && w[2] < 1.0);
+        assert_eq!(w[3], 1.0);
+    }
+}
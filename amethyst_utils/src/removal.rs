@@ -3,9 +3,12 @@
 use std::{fmt::Debug, ops::Deref};
 
 use amethyst_assets::PrefabData;
-use amethyst_core::ecs::{
-    storage::MaskedStorage, world::EntitiesRes, Component, DenseVecStorage, Entity, Join, Storage,
-    WriteStorage,
+use amethyst_core::{
+    ecs::{
+        storage::MaskedStorage, world::EntitiesRes, Component, DenseVecStorage, Entity, Join,
+        Storage, World, WorldExt, WriteStorage,
+    },
+    ParentHierarchy,
 };
 use amethyst_derive::PrefabData;
 use amethyst_error::Error;
@@ -89,6 +92,71 @@ pub fn exec_removal<I, D>(
     }
 }
 
+/// Removes all entities that have the `Removal<I>` component with the specified removal_id, along
+/// with all their children in the `ParentHierarchy`, so that e.g. the UI elements of a removed
+/// window don't leak.
+///
+/// The hierarchy is updated by the `ParentHierarchy` system, so children parented since it last
+/// ran are only removed if they have the `Removal` component too.
+pub fn exec_removal_with_children<I, D>(
+    entities: &EntitiesRes,
+    removal_storage: &Storage<'_, Removal<I>, D>,
+    hierarchy: &ParentHierarchy,
+    removal_id: I,
+) where
+    I: Debug + Clone + PartialEq + Send + Sync + 'static,
+    D: Deref<Target = MaskedStorage<Removal<I>>>,
+{
+    for (e, _) in (entities, removal_storage)
+        .join()
+        .filter(|(_, r)| r.id == removal_id)
+    {
+        for child in hierarchy.all_children_iter(e).chain(Some(e)) {
+            // Children tagged with the same id, or below another removed entity, are visited
+            // more than once.
+            if entities.is_alive(child) {
+                if let Err(err) = entities.delete(child) {
+                    error!(
+                        "Failed to delete entity during exec_removal_with_children: {:?}",
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Removes all entities of the world that have the `Removal<I>` component with the specified
+/// removal_id, along with their children if the world has a `ParentHierarchy`.
+/// Usually called when leaving a state, to clean up the entities it created.
+///
+/// # Example
+///
+/// ```rust
+/// # use amethyst_core::ecs::*;
+/// # use amethyst_utils::removal::*;
+/// # let mut world = World::new();
+/// # world.register::<Removal<u32>>();
+/// world.create_entity().with(Removal::new(1_u32)).build();
+/// world.create_entity().with(Removal::new(2_u32)).build();
+///
+/// remove_entities(&world, 1_u32);
+/// world.maintain();
+///
+/// assert_eq!((&*world.entities(),).join().count(), 1);
+/// ```
+pub fn remove_entities<I>(world: &World, removal_id: I)
+where
+    I: Debug + Clone + PartialEq + Send + Sync + 'static,
+{
+    let entities = world.entities();
+    let removals = world.read_storage::<Removal<I>>();
+    match world.try_fetch::<ParentHierarchy>() {
+        Some(hierarchy) => exec_removal_with_children(&entities, &removals, &hierarchy, removal_id),
+        None => exec_removal(&entities, &removals, removal_id),
+    }
+}
+
 /// Adds a `Removal` component with the specified id to the specified entity.
 /// Usually used with prefabs, when you want to add a `Removal` component at the root of the loaded prefab.
 pub fn add_removal_to_entity<T: PartialEq + Clone + Debug + Send + Sync + 'static>(
@@ -105,3 +173,34 @@ pub fn add_removal_to_entity<T: PartialEq + Clone + Debug + Send + Sync + 'stati
             )
         });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use amethyst_core::{
+        ecs::{Builder, RunNow},
+        Parent,
+    };
+    use specs_hierarchy::HierarchySystem;
+
+    #[test]
+    fn children_are_removed_with_their_parent() {
+        let mut world = World::new();
+        world.register::<Removal<u32>>();
+        let mut hierarchy = HierarchySystem::<Parent>::new(&mut world);
+
+        let window = world.create_entity().with(Removal::new(1_u32)).build();
+        let button = world.create_entity().with(Parent::new(window)).build();
+        let label = world.create_entity().with(Parent::new(button)).build();
+        let other = world.create_entity().with(Removal::new(2_u32)).build();
+        hierarchy.run_now(&world);
+
+        remove_entities(&world, 1_u32);
+        world.maintain();
+
+        assert!(!world.is_alive(window));
+        assert!(!world.is_alive(button));
+        assert!(!world.is_alive(label));
+        assert!(world.is_alive(other));
+    }
+}
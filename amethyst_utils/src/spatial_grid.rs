@@ -0,0 +1,415 @@
+//! Spatial hash grid of the positions of entities, for neighborhood queries without a physics
+//! engine.
+
+use std::collections::HashMap;
+
+use amethyst_core::{
+    ecs::{
+        storage::ComponentEvent, DispatcherBuilder, Entities, Entity, ReaderId, System, SystemData,
+        World, Write, WriteStorage,
+    },
+    math::{Point3, Vector3},
+    shrev::EventChannel,
+    SystemBundle, SystemDesc, Transform,
+};
+use amethyst_error::Error;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+type Cell = (i32, i32, i32);
+
+/// Resource holding the positions of entities in cubic cells, to find the entities near a point
+/// or in a box without going through all of them, e.g. for audio attenuation, AI perception or
+/// simple collisions.
+///
+/// The `SpatialGridSystem` keeps it up to date with the global positions of the entities with a
+/// `Transform`. The cells should be about as large as the usual queries: smaller cells make large
+/// queries visit many cells, and larger cells make small queries check many entities.
+///
+/// # Example
+///
+/// ```rust
+/// # use amethyst_core::{ecs::{World, WorldExt, Builder}, math::Point3};
+/// # use amethyst_utils::spatial_grid::SpatialGrid;
+/// # let mut world = World::new();
+/// # let entity = world.create_entity().build();
+/// let mut grid = SpatialGrid::new(4.0);
+/// grid.insert(entity, Point3::new(1.0, 2.0, 0.0));
+///
+/// assert_eq!(grid.within_radius(&Point3::origin(), 3.0), vec![entity]);
+/// assert!(grid
+///     .in_aabb(&Point3::new(2.0, 0.0, -1.0), &Point3::new(5.0, 5.0, 1.0))
+///     .is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<Entity>>,
+    positions: HashMap<Entity, (Point3<f32>, Cell)>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        SpatialGrid::new(1.0)
+    }
+}
+
+impl SpatialGrid {
+    /// Creates an empty grid with cubic cells of the given size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size isn't positive.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(
+            cell_size > 0.0,
+            "The cells of a SpatialGrid must have a size"
+        );
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Returns the size of the cells.
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns the number of entities in the grid.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns true if there are no entities in the grid.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns the position of an entity in the grid.
+    pub fn position(&self, entity: Entity) -> Option<Point3<f32>> {
+        self.positions.get(&entity).map(|(position, _)| *position)
+    }
+
+    /// Inserts an entity at a position, or moves it there.
+    pub fn insert(&mut self, entity: Entity, position: Point3<f32>) {
+        let cell = self.cell(&position);
+        if let Some((old_position, old_cell)) = self.positions.get_mut(&entity) {
+            *old_position = position;
+            if *old_cell == cell {
+                return;
+            }
+            let old_cell = std::mem::replace(old_cell, cell);
+            self.remove_from_cell(entity, old_cell);
+        } else {
+            self.positions.insert(entity, (position, cell));
+        }
+        self.cells.entry(cell).or_default().push(entity);
+    }
+
+    /// Removes an entity from the grid, returning its position.
+    pub fn remove(&mut self, entity: Entity) -> Option<Point3<f32>> {
+        let (position, cell) = self.positions.remove(&entity)?;
+        self.remove_from_cell(entity, cell);
+        Some(position)
+    }
+
+    /// Removes all entities from the grid.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.positions.clear();
+    }
+
+    /// Returns the entities whose position is at most `radius` away from `center`.
+    pub fn within_radius(&self, center: &Point3<f32>, radius: f32) -> Vec<Entity> {
+        let extent = Vector3::repeat(radius);
+        let radius_squared = radius * radius;
+        self.query(&(center - extent), &(center + extent), |position| {
+            (position - center).norm_squared() <= radius_squared
+        })
+    }
+
+    /// Returns the entities whose position is inside the axis aligned box from `min` to `max`,
+    /// including its faces.
+    pub fn in_aabb(&self, min: &Point3<f32>, max: &Point3<f32>) -> Vec<Entity> {
+        self.query(min, max, |position| {
+            (0..3).all(|axis| min[axis] <= position[axis] && position[axis] <= max[axis])
+        })
+    }
+
+    fn query(
+        &self,
+        min: &Point3<f32>,
+        max: &Point3<f32>,
+        filter: impl Fn(&Point3<f32>) -> bool,
+    ) -> Vec<Entity> {
+        let (low, high) = (self.cell(min), self.cell(max));
+        let cells = (0..3).fold(1_u64, |cells, axis| {
+            let (low, high) = (cell_axis(low, axis), cell_axis(high, axis));
+            cells.saturating_mul((i64::from(high) - i64::from(low) + 1).max(0) as u64)
+        });
+        let keep = |entity: &Entity| filter(&self.positions[entity].0);
+        if cells > self.cells.len() as u64 {
+            // Checking every occupied cell is cheaper than visiting the empty ones of the box.
+            return self
+                .cells
+                .iter()
+                .filter(|(cell, _)| {
+                    (0..3).all(|axis| {
+                        let coordinate = cell_axis(**cell, axis);
+                        cell_axis(low, axis) <= coordinate && coordinate <= cell_axis(high, axis)
+                    })
+                })
+                .flat_map(|(_, entities)| entities.iter().copied())
+                .filter(keep)
+                .collect();
+        }
+        let mut entities = Vec::new();
+        for x in low.0..=high.0 {
+            for y in low.1..=high.1 {
+                for z in low.2..=high.2 {
+                    if let Some(cell) = self.cells.get(&(x, y, z)) {
+                        entities.extend(cell.iter().copied().filter(keep));
+                    }
+                }
+            }
+        }
+        entities
+    }
+
+    fn cell(&self, position: &Point3<f32>) -> Cell {
+        let cell = position.coords / self.cell_size;
+        (
+            cell.x.floor() as i32,
+            cell.y.floor() as i32,
+            cell.z.floor() as i32,
+        )
+    }
+
+    fn remove_from_cell(&mut self, entity: Entity, cell: Cell) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.retain(|e| *e != entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+}
+
+fn cell_axis(cell: Cell, axis: usize) -> i32 {
+    match axis {
+        0 => cell.0,
+        1 => cell.1,
+        _ => cell.2,
+    }
+}
+
+/// Builds a `SpatialGridSystem`.
+#[derive(Default, Debug)]
+pub struct SpatialGridSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, SpatialGridSystem> for SpatialGridSystemDesc {
+    fn build(self, world: &mut World) -> SpatialGridSystem {
+        <SpatialGridSystem as System<'_>>::SystemData::setup(world);
+
+        let transform_events_id = WriteStorage::<Transform>::fetch(world).register_reader();
+
+        SpatialGridSystem::new(transform_events_id)
+    }
+}
+
+/// System moving the entities of the `SpatialGrid` to the global position of their `Transform`
+/// when it changes, and removing them along with their `Transform`.
+///
+/// It should run after the `TransformSystem`, which computes the global positions.
+#[derive(Debug)]
+pub struct SpatialGridSystem {
+    transform_events_id: ReaderId<ComponentEvent>,
+}
+
+impl SpatialGridSystem {
+    /// Creates a new `SpatialGridSystem` reading the events of the `Transform` storage.
+    pub fn new(transform_events_id: ReaderId<ComponentEvent>) -> Self {
+        SpatialGridSystem {
+            transform_events_id,
+        }
+    }
+}
+
+impl<'a> System<'a> for SpatialGridSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        Write<'a, SpatialGrid>,
+    );
+
+    fn run(&mut self, (entities, transforms, mut grid): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("spatial_grid_system");
+
+        let mut deleted = false;
+        let channel: &EventChannel<ComponentEvent> = transforms.channel();
+        for event in channel.read(&mut self.transform_events_id) {
+            match *event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    let entity = entities.entity(id);
+                    if let Some(transform) = transforms.get(entity) {
+                        let position = transform.global_matrix().column(3).xyz();
+                        grid.insert(entity, Point3::from(position));
+                    }
+                }
+                ComponentEvent::Removed(id) => {
+                    // The events of deleted entities only hold their index, so their entity in
+                    // the grid is found by looking for dead entities.
+                    let entity = entities.entity(id);
+                    if !entity.gen().is_alive() || grid.remove(entity).is_none() {
+                        deleted = true;
+                    }
+                }
+            }
+        }
+        if deleted {
+            let dead = grid
+                .positions
+                .keys()
+                .filter(|entity| !entities.is_alive(**entity))
+                .copied()
+                .collect::<Vec<_>>();
+            for entity in dead {
+                grid.remove(entity);
+            }
+        }
+    }
+}
+
+/// Adds a `SpatialGrid` resource with the given cell size, and the `SpatialGridSystem` keeping it
+/// up to date, after the "transform_system" of the `TransformBundle`.
+#[derive(Debug)]
+pub struct SpatialGridBundle {
+    cell_size: f32,
+}
+
+impl Default for SpatialGridBundle {
+    fn default() -> Self {
+        SpatialGridBundle::new(1.0)
+    }
+}
+
+impl SpatialGridBundle {
+    /// Creates a new `SpatialGridBundle` with cubic cells of the given size.
+    pub fn new(cell_size: f32) -> Self {
+        SpatialGridBundle { cell_size }
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for SpatialGridBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.insert(SpatialGrid::new(self.cell_size));
+        builder.add(
+            SpatialGridSystemDesc::default().build(world),
+            "spatial_grid_system",
+            &["transform_system"],
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use amethyst_core::{
+        ecs::{Builder, WorldExt},
+        TransformBundle,
+    };
+
+    fn entities(count: usize) -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        let entities = (0..count).map(|_| world.create_entity().build()).collect();
+        (world, entities)
+    }
+
+    #[test]
+    fn queries_find_entities_in_range() {
+        let (_world, e) = entities(4);
+        let mut grid = SpatialGrid::new(2.0);
+        grid.insert(e[0], Point3::new(0.5, 0.5, 0.0));
+        grid.insert(e[1], Point3::new(-1.5, 0.0, 0.0));
+        grid.insert(e[2], Point3::new(3.0, 3.0, 0.0));
+        grid.insert(e[3], Point3::new(100.0, -40.0, 7.0));
+
+        let mut found = grid.within_radius(&Point3::origin(), 2.0);
+        found.sort();
+        assert_eq!(found, vec![e[0], e[1]]);
+        let mut found = grid.in_aabb(&Point3::new(0.0, 0.0, -1.0), &Point3::new(3.0, 3.0, 1.0));
+        found.sort();
+        assert_eq!(found, vec![e[0], e[2]]);
+        // Boxes much larger than the occupied cells check the cells instead of the box.
+        assert_eq!(grid.within_radius(&Point3::origin(), 1.0e6).len(), 4);
+
+        grid.insert(e[0], Point3::new(3.5, 3.0, 0.0));
+        assert_eq!(grid.within_radius(&Point3::origin(), 2.0), vec![e[1]]);
+        assert_eq!(grid.remove(e[1]), Some(Point3::new(-1.5, 0.0, 0.0)));
+        assert!(grid.within_radius(&Point3::origin(), 2.0).is_empty());
+        assert_eq!(grid.len(), 3);
+    }
+
+    #[test]
+    fn system_follows_global_positions() {
+        let mut world = World::new();
+        let mut dispatcher = {
+            let mut builder = DispatcherBuilder::new();
+            TransformBundle::new()
+                .build(&mut world, &mut builder)
+                .unwrap();
+            SpatialGridBundle::new(1.0)
+                .build(&mut world, &mut builder)
+                .unwrap();
+            builder.build()
+        };
+        dispatcher.setup(&mut world);
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(5.0, 0.0, 0.0);
+        let parent = world.create_entity().with(transform).build();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 2.0, 0.0);
+        let child = world
+            .create_entity()
+            .with(transform)
+            .with(amethyst_core::Parent::new(parent))
+            .build();
+        dispatcher.dispatch(&world);
+        world.maintain();
+
+        {
+            let grid = world.read_resource::<SpatialGrid>();
+            assert_eq!(grid.position(child), Some(Point3::new(5.0, 2.0, 0.0)));
+            assert_eq!(
+                grid.within_radius(&Point3::new(5.0, 2.5, 0.0), 1.0),
+                vec![child]
+            );
+        }
+
+        world
+            .write_storage::<Transform>()
+            .get_mut(parent)
+            .unwrap()
+            .set_translation_x(-5.0);
+        dispatcher.dispatch(&world);
+        assert_eq!(
+            world.read_resource::<SpatialGrid>().position(child),
+            Some(Point3::new(-5.0, 2.0, 0.0))
+        );
+
+        world.delete_entity(child).unwrap();
+        world.maintain();
+        dispatcher.dispatch(&world);
+        assert_eq!(world.read_resource::<SpatialGrid>().len(), 1);
+    }
+}
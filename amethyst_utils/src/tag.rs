@@ -5,7 +5,8 @@ use std::marker::PhantomData;
 use amethyst_assets::PrefabData;
 use amethyst_core::ecs::{
     shred::{ResourceId, SystemData},
-    Component, Entities, Entity, Join, NullStorage, ReadStorage, World, WriteStorage,
+    Component, Entities, Entity, Join, NullStorage, ReadStorage, System, World, Write,
+    WriteStorage,
 };
 use amethyst_derive::PrefabData;
 use amethyst_error::Error;
@@ -63,4 +64,140 @@ where
             .map(|(entity, _)| entity)
             .next()
     }
+
+    /// Returns every entity with the tag in question.
+    pub fn find_all(&self) -> impl Iterator<Item = Entity> + '_ {
+        (&*self.entities, &self.tags)
+            .join()
+            .map(|(entity, _)| entity)
+    }
+
+    /// Returns the number of entities with the tag in question.
+    pub fn count(&self) -> usize {
+        (&self.tags).join().count()
+    }
+}
+
+/// Resource caching the entities tagged with `Tag<T>`, rebuilt once per frame by the
+/// `TagIndexSystem<T>`, for hot paths looking up tags many times per frame.
+///
+/// Tags added or removed after the `TagIndexSystem<T>` ran are only seen the next frame, use a
+/// `TagFinder` when that matters.
+#[derive(Debug)]
+pub struct TagIndex<T> {
+    entities: Vec<Entity>,
+    _m: PhantomData<T>,
+}
+
+impl<T> Default for TagIndex<T> {
+    fn default() -> Self {
+        TagIndex {
+            entities: Vec::new(),
+            _m: PhantomData,
+        }
+    }
+}
+
+impl<T> TagIndex<T> {
+    /// Returns the first tagged entity.
+    pub fn find(&self) -> Option<Entity> {
+        self.entities.first().cloned()
+    }
+
+    /// Returns every tagged entity, ordered by id.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns the number of tagged entities.
+    pub fn count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns true if the entity was tagged.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities
+            .binary_search_by_key(&entity.id(), |tagged| tagged.id())
+            .ok()
+            .map(|index| self.entities[index])
+            == Some(entity)
+    }
+}
+
+/// System rebuilding the `TagIndex<T>` of the entities tagged with `Tag<T>`.
+///
+/// Add it after the systems adding and removing tags.
+#[derive(Debug)]
+pub struct TagIndexSystem<T> {
+    _m: PhantomData<T>,
+}
+
+impl<T> TagIndexSystem<T> {
+    /// Creates a new `TagIndexSystem<T>`.
+    pub fn new() -> Self {
+        TagIndexSystem { _m: PhantomData }
+    }
+}
+
+impl<T> Default for TagIndexSystem<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> System<'a> for TagIndexSystem<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Tag<T>>,
+        Write<'a, TagIndex<T>>,
+    );
+
+    fn run(&mut self, (entities, tags, mut index): Self::SystemData) {
+        index.entities.clear();
+        index
+            .entities
+            .extend((&*entities, &tags).join().map(|(entity, _)| entity));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use amethyst_core::ecs::{Builder, RunNow, WorldExt};
+
+    #[derive(Clone, Debug)]
+    struct Enemy;
+
+    #[test]
+    fn finder_and_index_agree() {
+        let mut world = World::new();
+        world.register::<Tag<Enemy>>();
+        let first = world.create_entity().with(Tag::<Enemy>::default()).build();
+        let untagged = world.create_entity().build();
+        let second = world.create_entity().with(Tag::<Enemy>::default()).build();
+
+        {
+            let finder = world.system_data::<TagFinder<'_, Enemy>>();
+            assert_eq!(finder.find(), Some(first));
+            assert_eq!(finder.find_all().collect::<Vec<_>>(), vec![first, second]);
+            assert_eq!(finder.count(), 2);
+        }
+
+        let mut system = TagIndexSystem::<Enemy>::new();
+        System::setup(&mut system, &mut world);
+        system.run_now(&world);
+        world.delete_entity(first).unwrap();
+        let recycled = world.create_entity().build();
+
+        let index = world.read_resource::<TagIndex<Enemy>>();
+        assert_eq!(index.entities(), &[first, second]);
+        assert_eq!(index.count(), 2);
+        assert!(index.contains(second));
+        assert!(!index.contains(untagged));
+        assert!(!index.contains(recycled));
+    }
 }
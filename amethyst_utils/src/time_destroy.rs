@@ -1,7 +1,12 @@
 //! Allows you to automatically delete an entity after a set time has elapsed.
 
+use std::{fmt, sync::Arc};
+
 use amethyst_core::{
-    ecs::{Component, DenseVecStorage, Entities, Join, Read, ReadStorage, System, WriteStorage},
+    ecs::{
+        Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, RunNow, System,
+        World, WorldExt, WriteStorage,
+    },
     timing::Time,
 };
 
@@ -35,6 +40,49 @@ impl Component for DestroyInTime {
     type Storage = DenseVecStorage<Self>;
 }
 
+/// Destroys the entity to which this is attached after the specified number of frames, i.e. on
+/// the `frames`th run of the `TimedDestroySystem`. Zero destroys it on the next run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DestroyInFrames {
+    /// The number of frames before the entity should be destroyed.
+    pub frames: u32,
+}
+
+impl Component for DestroyInFrames {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Destroys the entity to which this is attached once a predicate on the world and the entity is
+/// true, e.g. when a projectile leaves the screen.
+///
+/// Only the `TimedDestroySystem` evaluates the predicate, as it needs the whole world.
+#[derive(Clone)]
+pub struct DestroyWhen {
+    predicate: Arc<dyn Fn(&World, Entity) -> bool + Send + Sync>,
+}
+
+impl DestroyWhen {
+    /// Creates a new `DestroyWhen` component destroying its entity when the predicate is true.
+    pub fn new<F>(predicate: F) -> Self
+    where
+        F: Fn(&World, Entity) -> bool + Send + Sync + 'static,
+    {
+        DestroyWhen {
+            predicate: Arc::new(predicate),
+        }
+    }
+}
+
+impl fmt::Debug for DestroyWhen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DestroyWhen").finish()
+    }
+}
+
+impl Component for DestroyWhen {
+    type Storage = DenseVecStorage<Self>;
+}
+
 /// The system in charge of destroying entities with the `DestroyAtTime` component.
 #[derive(Debug)]
 pub struct DestroyAtTimeSystem;
@@ -79,3 +127,119 @@ impl<'a> System<'a> for DestroyInTimeSystem {
         }
     }
 }
+
+/// Thread local system destroying the entities with any of the `DestroyAtTime`, `DestroyInTime`,
+/// `DestroyInFrames` and `DestroyWhen` components.
+///
+/// It runs thread local, e.g. added with `GameDataBuilder::with_thread_local`, as the predicates
+/// of `DestroyWhen` need the whole world. Don't add the `DestroyAtTimeSystem` or the
+/// `DestroyInTimeSystem` along with it.
+#[derive(Debug, Default)]
+pub struct TimedDestroySystem;
+
+impl<'a> RunNow<'a> for TimedDestroySystem {
+    fn run_now(&mut self, world: &'a World) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("timed_destroy_system");
+
+        DestroyAtTimeSystem.run_now(world);
+        DestroyInTimeSystem.run_now(world);
+
+        let entities = world.entities();
+        for (e, d) in (&entities, &mut world.write_storage::<DestroyInFrames>()).join() {
+            d.frames = d.frames.saturating_sub(1);
+            if d.frames == 0 {
+                if let Err(err) = entities.delete(e) {
+                    error!("Failed to delete entity: {:?}", err);
+                }
+            }
+        }
+
+        // The predicates are called without holding any storage, so they can read any of them.
+        let predicates = (&entities, &world.read_storage::<DestroyWhen>())
+            .join()
+            .map(|(e, d)| (e, d.clone()))
+            .collect::<Vec<_>>();
+        for (e, d) in predicates {
+            if (d.predicate)(world, e) {
+                if let Err(err) = entities.delete(e) {
+                    error!("Failed to delete entity: {:?}", err);
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        world.register::<DestroyAtTime>();
+        world.register::<DestroyInTime>();
+        world.register::<DestroyInFrames>();
+        world.register::<DestroyWhen>();
+        world.entry::<Time>().or_insert_with(Time::default);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use amethyst_core::ecs::Builder;
+    use std::time::Duration;
+
+    #[test]
+    fn entities_are_destroyed_by_time_frames_and_predicates() {
+        let mut world = World::new();
+        let mut system = TimedDestroySystem;
+        RunNow::setup(&mut system, &mut world);
+        world
+            .write_resource::<Time>()
+            .set_delta_time(Duration::from_millis(500));
+
+        let timed = world
+            .create_entity()
+            .with(DestroyInTime { timer: 0.5 })
+            .build();
+        let framed = world
+            .create_entity()
+            .with(DestroyInFrames { frames: 2 })
+            .build();
+        let marked = world.create_entity().build();
+        let predicated = world
+            .create_entity()
+            .with(DestroyWhen::new(move |world, _| !world.is_alive(marked)))
+            .build();
+
+        let alive = |world: &mut World, system: &mut TimedDestroySystem| {
+            system.run_now(world);
+            world.maintain();
+            [timed, framed, predicated]
+                .iter()
+                .map(|e| world.is_alive(*e))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(alive(&mut world, &mut system), [true, true, true]);
+        assert_eq!(alive(&mut world, &mut system), [false, false, true]);
+        world.delete_entity(marked).unwrap();
+        assert_eq!(alive(&mut world, &mut system), [false, false, false]);
+    }
+
+    #[test]
+    fn destroy_in_frames_counts_the_current_frame() {
+        let mut world = World::new();
+        let mut system = TimedDestroySystem;
+        RunNow::setup(&mut system, &mut world);
+        let next = world
+            .create_entity()
+            .with(DestroyInFrames { frames: 1 })
+            .build();
+        let later = world
+            .create_entity()
+            .with(DestroyInFrames { frames: 3 })
+            .build();
+
+        for run in 1..=3 {
+            system.run_now(&world);
+            world.maintain();
+            assert!(!world.is_alive(next));
+            assert_eq!(world.is_alive(later), run < 3);
+        }
+    }
+}
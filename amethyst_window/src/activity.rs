@@ -0,0 +1,255 @@
+use amethyst_core::{
+    ecs::{Read, ReadExpect, ReaderId, System, World, Write},
+    frame_limiter::{FrameLimiter, FrameRateLimitConfig, FrameRateLimitStrategy},
+    shrev::EventChannel,
+    timing::Time,
+};
+use serde::{Deserialize, Serialize};
+use winit::{Event, Window, WindowEvent};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Event sent by the `WindowActivitySystem` when the main window gains or loses the focus, or is
+/// minimized or restored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowActivityEvent {
+    /// The window receives the keyboard input again.
+    FocusGained,
+    /// Another window receives the keyboard input, e.g. after alt-tab.
+    FocusLost,
+    /// The window was minimized.
+    Minimized,
+    /// The window was restored after being minimized.
+    Restored,
+}
+
+/// What the application does while the main window is in the background.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum BackgroundMode {
+    /// Keeps running as usual.
+    Run,
+    /// Limits the frame rate to the given frames per second.
+    Throttle(u32),
+    /// Stops the game time with a time scale of 0.0 and limits the frame rate to the given
+    /// frames per second. Systems using the real time keep running.
+    Pause(u32),
+}
+
+impl Default for BackgroundMode {
+    fn default() -> Self {
+        BackgroundMode::Run
+    }
+}
+
+/// World resource tracking whether the main window is focused or minimized, and what to do while
+/// it's in the background, e.g. lowering the frame rate to save laptop batteries while the
+/// player is alt-tabbed.
+///
+/// The frame rate limit and time scale are restored once the window is focused again, so they
+/// shouldn't be changed while the window is in the background.
+///
+/// `winit` 0.19 has no event for windows covered by other windows, and only reports minimized
+/// windows as resized to a size of zero, which not every platform does.
+#[derive(Clone, Debug)]
+pub struct WindowActivity {
+    /// What to do while the window is unfocused.
+    pub unfocused: BackgroundMode,
+    /// What to do while the window is minimized.
+    pub minimized: BackgroundMode,
+    focused: bool,
+    is_minimized: bool,
+    applied: BackgroundMode,
+    saved: Option<(FrameRateLimitConfig, f32)>,
+}
+
+impl Default for WindowActivity {
+    fn default() -> Self {
+        WindowActivity {
+            unfocused: BackgroundMode::Run,
+            minimized: BackgroundMode::Run,
+            focused: true,
+            is_minimized: false,
+            applied: BackgroundMode::Run,
+            saved: None,
+        }
+    }
+}
+
+impl WindowActivity {
+    /// Creates a `WindowActivity` applying the given modes while the window is unfocused and
+    /// while it's minimized.
+    pub fn new(unfocused: BackgroundMode, minimized: BackgroundMode) -> Self {
+        WindowActivity {
+            unfocused,
+            minimized,
+            ..Default::default()
+        }
+    }
+
+    /// Returns true if the window receives the keyboard input.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Returns true if the window is minimized.
+    pub fn is_minimized(&self) -> bool {
+        self.is_minimized
+    }
+
+    /// Returns the mode for the current state of the window.
+    pub fn mode(&self) -> BackgroundMode {
+        if self.is_minimized {
+            self.minimized
+        } else if !self.focused {
+            self.unfocused
+        } else {
+            BackgroundMode::Run
+        }
+    }
+
+    fn handle(&mut self, event: &WindowEvent) -> Option<WindowActivityEvent> {
+        match *event {
+            WindowEvent::Focused(focused) if focused != self.focused => {
+                self.focused = focused;
+                Some(if focused {
+                    WindowActivityEvent::FocusGained
+                } else {
+                    WindowActivityEvent::FocusLost
+                })
+            }
+            WindowEvent::Resized(size) => {
+                let minimized = size.width <= 0.0 || size.height <= 0.0;
+                if minimized == self.is_minimized {
+                    return None;
+                }
+                self.is_minimized = minimized;
+                Some(if minimized {
+                    WindowActivityEvent::Minimized
+                } else {
+                    WindowActivityEvent::Restored
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn apply(&mut self, limiter: &mut FrameLimiter, time: &mut Time) {
+        let mode = self.mode();
+        if mode == self.applied {
+            return;
+        }
+        self.applied = mode;
+        let (config, time_scale) = self
+            .saved
+            .get_or_insert_with(|| (limiter.config(), time.time_scale()))
+            .clone();
+        match mode {
+            BackgroundMode::Run => {
+                limiter.set_rate(config.strategy, config.fps);
+                time.set_time_scale(time_scale);
+                self.saved = None;
+            }
+            BackgroundMode::Throttle(fps) => {
+                limiter.set_rate(FrameRateLimitStrategy::Sleep, fps);
+                time.set_time_scale(time_scale);
+            }
+            BackgroundMode::Pause(fps) => {
+                limiter.set_rate(FrameRateLimitStrategy::Sleep, fps);
+                time.set_time_scale(0.0);
+            }
+        }
+    }
+}
+
+/// System updating the `WindowActivity` from the events of the main window, sending
+/// `WindowActivityEvent`s and applying its `BackgroundMode`.
+#[derive(Debug)]
+pub struct WindowActivitySystem {
+    event_reader: ReaderId<Event>,
+}
+
+impl WindowActivitySystem {
+    /// Creates a new `WindowActivitySystem` reading the window events of the world.
+    pub fn new(world: &mut World) -> Self {
+        world
+            .entry::<WindowActivity>()
+            .or_insert_with(Default::default);
+        let event_reader = world
+            .entry::<EventChannel<Event>>()
+            .or_insert_with(Default::default)
+            .register_reader();
+        Self { event_reader }
+    }
+}
+
+impl<'a> System<'a> for WindowActivitySystem {
+    type SystemData = (
+        Option<ReadExpect<'a, Window>>,
+        Read<'a, EventChannel<Event>>,
+        Write<'a, WindowActivity>,
+        Write<'a, EventChannel<WindowActivityEvent>>,
+        Write<'a, FrameLimiter>,
+        Write<'a, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("window_activity_system");
+
+        let (window, window_events, mut activity, mut events, mut limiter, mut time) = data;
+        let main_window = window.map(|window| window.id());
+        for event in window_events.read(&mut self.event_reader) {
+            if let Event::WindowEvent {
+                ref event,
+                window_id,
+            } = *event
+            {
+                if main_window.is_none() || main_window == Some(window_id) {
+                    if let Some(event) = activity.handle(event) {
+                        events.single_write(event);
+                    }
+                }
+            }
+        }
+        activity.apply(&mut limiter, &mut time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::LogicalSize;
+
+    #[test]
+    fn background_modes_are_applied_and_restored() {
+        let mut activity =
+            WindowActivity::new(BackgroundMode::Throttle(30), BackgroundMode::Pause(5));
+        let mut limiter = FrameLimiter::new(FrameRateLimitStrategy::Yield, 144);
+        let mut time = Time::default();
+        time.set_time_scale(2.0);
+
+        assert_eq!(
+            activity.handle(&WindowEvent::Focused(false)),
+            Some(WindowActivityEvent::FocusLost)
+        );
+        activity.apply(&mut limiter, &mut time);
+        assert_eq!(limiter.config().fps, 30);
+        assert_eq!(time.time_scale(), 2.0);
+
+        assert_eq!(
+            activity.handle(&WindowEvent::Resized(LogicalSize::new(0.0, 0.0))),
+            Some(WindowActivityEvent::Minimized)
+        );
+        activity.apply(&mut limiter, &mut time);
+        assert_eq!(limiter.config().fps, 5);
+        assert_eq!(time.time_scale(), 0.0);
+
+        activity.handle(&WindowEvent::Resized(LogicalSize::new(800.0, 600.0)));
+        activity.handle(&WindowEvent::Focused(true));
+        activity.apply(&mut limiter, &mut time);
+        assert_eq!(limiter.config().fps, 144);
+        assert_eq!(time.time_scale(), 2.0);
+        assert_eq!(activity.handle(&WindowEvent::Focused(true)), None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{DisplayConfig, EventsLoopSystem, WindowSystem};
+use crate::{DisplayConfig, EventsLoopSystem, WindowActivitySystem, WindowSystem};
 use amethyst_config::{Config, ConfigError};
 use amethyst_core::{bundle::SystemBundle, ecs::World, shred::DispatcherBuilder};
 use amethyst_error::Error;
@@ -13,6 +13,9 @@ pub const SCREEN_HEIGHT: u32 = 600;
 
 /// Bundle providing easy initializing of the appopriate `Window`, `WindowSystem` `EventLoop` and
 /// `EventLoopSystem` constructs used for creating the rendering window of amethyst with `winit`
+///
+/// It also adds the `WindowActivitySystem`, which keeps running while the window is in the
+/// background until the `BackgroundMode`s of the `WindowActivity` resource are changed.
 #[derive(Debug)]
 pub struct WindowBundle {
     config: DisplayConfig,
@@ -61,6 +64,11 @@ impl<'a, 'b> SystemBundle<'a, 'b> for WindowBundle {
             "window",
             &[],
         );
+        builder.add(
+            WindowActivitySystem::new(world),
+            "window_activity",
+            &["window"],
+        );
         builder.add_thread_local(EventsLoopSystem::new(event_loop));
         Ok(())
     }
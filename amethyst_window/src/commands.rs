@@ -0,0 +1,491 @@
+use crate::{
+    monitor::{MonitorIdent, MonitorInfo, Monitors, VideoMode},
+    resources::ScreenDimensions,
+    video_mode::ModeChange,
+};
+use amethyst_core::shrev::EventChannel;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use winit::{dpi::LogicalPosition, Icon, Window};
+
+/// Whether the main window covers a monitor.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum FullscreenMode {
+    /// A regular window.
+    Windowed,
+    /// A borderless window covering the given monitor at its current resolution.
+    Borderless(MonitorIdent),
+    /// A window covering the given monitor, switched to one of its `MonitorInfo::video_modes`.
+    ///
+    /// The monitor is switched back to its previous mode when the window leaves exclusive
+    /// fullscreen or the `WindowCommands` are dropped. Monitors whose mode can't be changed, e.g.
+    /// on Wayland, get a borderless window instead.
+    Exclusive(MonitorIdent, VideoMode),
+}
+
+impl Default for FullscreenMode {
+    fn default() -> Self {
+        FullscreenMode::Windowed
+    }
+}
+
+/// Change to apply to the main window, queued in `WindowCommands`.
+#[derive(Clone, Debug, PartialEq)]
+enum WindowCommand {
+    Fullscreen(FullscreenMode),
+    ToggleFullscreen,
+    Resolution(u32, u32),
+    Position(i32, i32),
+    Center(Option<MonitorIdent>),
+    SavePlacement,
+    RestorePlacement(WindowPlacement),
+    RefreshMonitors,
+    Title(String),
+    Icon(Option<Icon>),
+    Resizable(bool),
+    MinDimensions(Option<(u32, u32)>),
+    MaxDimensions(Option<(u32, u32)>),
+    Decorations(bool),
+    AlwaysOnTop(bool),
+}
+
+/// Where the main window is and how large it is, saved with `WindowCommands::save_placement`
+/// and restored with `WindowCommands::restore_placement`, e.g. in the next session.
+///
+/// It can be written to and loaded from a RON file with `amethyst_config::Config`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WindowPlacement {
+    /// The monitor showing the window.
+    pub monitor: MonitorIdent,
+    /// Horizontal offset of the window from the left edge of the monitor, in physical pixels.
+    pub x: i32,
+    /// Vertical offset of the window from the top edge of the monitor, in physical pixels.
+    pub y: i32,
+    /// Width of the window in physical pixels.
+    pub width: u32,
+    /// Height of the window in physical pixels.
+    pub height: u32,
+    /// Whether the window is fullscreen on the monitor.
+    pub fullscreen: bool,
+}
+
+impl WindowPlacement {
+    /// Returns the desktop position of the window placed on the given monitor, moved to fit on
+    /// it if the monitor became smaller since the placement was saved.
+    pub fn position_on(&self, monitor: &MonitorInfo) -> (i32, i32) {
+        let fit = |offset: i32, size: u32, monitor_size: u32| {
+            let max = (i64::from(monitor_size) - i64::from(size)).max(0);
+            i64::from(offset).min(max).max(0) as i32
+        };
+        (
+            monitor.x + fit(self.x, self.width, monitor.width),
+            monitor.y + fit(self.y, self.height, monitor.height),
+        )
+    }
+}
+
+/// Event sent once a change queued in `WindowCommands` was applied to the main window.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowCommandEvent {
+    /// The window entered or left fullscreen.
+    FullscreenChanged(FullscreenMode),
+    /// The window was resized to the given size in physical pixels.
+    ResolutionChanged(u32, u32),
+    /// The window was moved to the given desktop position in physical pixels.
+    Moved(i32, i32),
+    /// The placement of the window was saved.
+    PlacementSaved(WindowPlacement),
+    /// The `Monitors` resource was updated.
+    MonitorsChanged,
+    /// The title of the window was changed.
+    TitleChanged(String),
+    /// The icon of the window was changed.
+    IconChanged,
+    /// The window was made resizable or fixed in size.
+    ResizableChanged(bool),
+    /// The minimum size of the window was changed.
+    MinDimensionsChanged(Option<(u32, u32)>),
+    /// The maximum size of the window was changed.
+    MaxDimensionsChanged(Option<(u32, u32)>),
+    /// The borders and title bar of the window were shown or hidden.
+    DecorationsChanged(bool),
+    /// The window was put above or among the other windows.
+    AlwaysOnTopChanged(bool),
+}
+
+/// World resource changing the fullscreen mode, resolution, placement and attributes of the main
+/// window after it was opened from the `DisplayConfig`, e.g. from a video settings menu.
+///
+/// Changes are applied by the `WindowSystem` in the next frame, which sends a
+/// `WindowCommandEvent` for each of them.
+///
+/// # Example
+///
+/// ```rust
+/// # use amethyst_window::{FullscreenMode, Monitors, WindowCommands};
+/// # let monitors = Monitors::default();
+/// let mut commands = WindowCommands::default();
+/// commands.set_resolution(1280, 720);
+/// if let Some(monitor) = monitors.iter().last() {
+///     let mode = match monitor.video_mode(1920, 1080) {
+///         Some(video_mode) => FullscreenMode::Exclusive(monitor.ident.clone(), video_mode),
+///         None => FullscreenMode::Borderless(monitor.ident.clone()),
+///     };
+///     commands.set_fullscreen(mode);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct WindowCommands {
+    commands: Vec<WindowCommand>,
+    fullscreen: FullscreenMode,
+    mode_change: Option<ModeChange>,
+}
+
+impl WindowCommands {
+    /// Makes the window fullscreen on a monitor, or windowed.
+    ///
+    /// The `WindowCommandEvent::FullscreenChanged` sent once it's applied has the borderless mode
+    /// if the monitor couldn't be switched to the video mode of an exclusive one.
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        self.commands.push(WindowCommand::Fullscreen(mode));
+    }
+
+    /// Makes the window fullscreen on its current monitor if it's windowed, or windowed if it's
+    /// fullscreen.
+    pub fn toggle_fullscreen(&mut self) {
+        self.commands.push(WindowCommand::ToggleFullscreen);
+    }
+
+    /// Resizes the window to the given size in physical pixels.
+    ///
+    /// Fullscreen windows keep the resolution of their monitor, so this only takes effect in
+    /// windowed mode.
+    pub fn set_resolution(&mut self, width: u32, height: u32) {
+        self.commands.push(WindowCommand::Resolution(width, height));
+    }
+
+    /// Moves the top left corner of the window to the given desktop position in physical pixels.
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.commands.push(WindowCommand::Position(x, y));
+    }
+
+    /// Centers the window on its current monitor.
+    pub fn center(&mut self) {
+        self.commands.push(WindowCommand::Center(None));
+    }
+
+    /// Moves the window to the center of the given monitor.
+    pub fn center_on(&mut self, monitor: MonitorIdent) {
+        self.commands.push(WindowCommand::Center(Some(monitor)));
+    }
+
+    /// Sends the current `WindowPlacement` in a `WindowCommandEvent::PlacementSaved`.
+    pub fn save_placement(&mut self) {
+        self.commands.push(WindowCommand::SavePlacement);
+    }
+
+    /// Moves and resizes the window to a saved placement. Placements on monitors that are gone
+    /// are restored on the primary monitor.
+    pub fn restore_placement(&mut self, placement: WindowPlacement) {
+        self.commands
+            .push(WindowCommand::RestorePlacement(placement));
+    }
+
+    /// Lists the monitors again, e.g. after one was plugged in.
+    pub fn refresh_monitors(&mut self) {
+        self.commands.push(WindowCommand::RefreshMonitors);
+    }
+
+    /// Sets the title of the window.
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.commands.push(WindowCommand::Title(title.into()));
+    }
+
+    /// Sets the icon of the window, or removes it with `None`.
+    ///
+    /// Icons can be loaded with `Icon::from_path` or `Icon::from_rgba`, or as `WindowIcon` assets
+    /// with `amethyst_rendy`.
+    pub fn set_icon(&mut self, icon: Option<Icon>) {
+        self.commands.push(WindowCommand::Icon(icon));
+    }
+
+    /// Sets whether the window can be resized by the user.
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.commands.push(WindowCommand::Resizable(resizable));
+    }
+
+    /// Sets the minimum size of the window in logical pixels, like
+    /// `DisplayConfig::min_dimensions`, or removes it with `None`.
+    pub fn set_min_dimensions(&mut self, dimensions: Option<(u32, u32)>) {
+        self.commands.push(WindowCommand::MinDimensions(dimensions));
+    }
+
+    /// Sets the maximum size of the window in logical pixels, like
+    /// `DisplayConfig::max_dimensions`, or removes it with `None`.
+    pub fn set_max_dimensions(&mut self, dimensions: Option<(u32, u32)>) {
+        self.commands.push(WindowCommand::MaxDimensions(dimensions));
+    }
+
+    /// Shows or hides the borders and title bar of the window.
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.commands.push(WindowCommand::Decorations(decorations));
+    }
+
+    /// Keeps the window above the other windows, e.g. for overlays, or lets them cover it again.
+    ///
+    /// Transparency can't be changed once the window is open, it's set with
+    /// `DisplayConfig::transparent`.
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.commands
+            .push(WindowCommand::AlwaysOnTop(always_on_top));
+    }
+
+    /// Returns the fullscreen mode of the window as of the last frame.
+    pub fn fullscreen(&self) -> &FullscreenMode {
+        &self.fullscreen
+    }
+
+    /// Reads the current state of the window.
+    pub(crate) fn sync(&mut self, window: &Window) {
+        self.fullscreen = fullscreen_mode(window);
+    }
+
+    /// Applies the queued changes to the window.
+    pub(crate) fn apply(
+        &mut self,
+        window: &Window,
+        screen_dimensions: &mut ScreenDimensions,
+        monitors: &mut Monitors,
+        events: &mut EventChannel<WindowCommandEvent>,
+    ) {
+        let commands = std::mem::take(&mut self.commands);
+        for command in commands {
+            match command {
+                WindowCommand::Fullscreen(mode) => {
+                    let mode = self.apply_fullscreen(window, mode);
+                    events.single_write(WindowCommandEvent::FullscreenChanged(mode));
+                }
+                WindowCommand::ToggleFullscreen => {
+                    let mode = match fullscreen_mode(window) {
+                        FullscreenMode::Windowed => {
+                            MonitorIdent::from_monitor_id(window, window.get_current_monitor())
+                                .map_or(FullscreenMode::Windowed, FullscreenMode::Borderless)
+                        }
+                        _ => FullscreenMode::Windowed,
+                    };
+                    let mode = self.apply_fullscreen(window, mode);
+                    events.single_write(WindowCommandEvent::FullscreenChanged(mode));
+                }
+                WindowCommand::Resolution(width, height) => {
+                    screen_dimensions.update(f64::from(width), f64::from(height));
+                    events.single_write(WindowCommandEvent::ResolutionChanged(width, height));
+                }
+                WindowCommand::Position(x, y) => {
+                    set_position(window, x, y);
+                    events.single_write(WindowCommandEvent::Moved(x, y));
+                }
+                WindowCommand::Center(monitor) => {
+                    let monitor = match monitor {
+                        Some(monitor) => monitors.get(&monitor),
+                        None => current_monitor(window, monitors),
+                    };
+                    if let Some(monitor) = monitor {
+                        let (x, y) = monitor.centered(
+                            screen_dimensions.width() as u32,
+                            screen_dimensions.height() as u32,
+                        );
+                        set_position(window, x, y);
+                        events.single_write(WindowCommandEvent::Moved(x, y));
+                    }
+                }
+                WindowCommand::SavePlacement => {
+                    if let Some(placement) = placement(window, screen_dimensions, monitors) {
+                        events.single_write(WindowCommandEvent::PlacementSaved(placement));
+                    }
+                }
+                WindowCommand::RestorePlacement(placement) => {
+                    let monitor = monitors
+                        .get(&placement.monitor)
+                        .or_else(|| monitors.primary());
+                    if let Some(monitor) = monitor {
+                        let mode = if placement.fullscreen {
+                            FullscreenMode::Borderless(monitor.ident.clone())
+                        } else {
+                            FullscreenMode::Windowed
+                        };
+                        if mode != self.fullscreen {
+                            let mode = self.apply_fullscreen(window, mode);
+                            events.single_write(WindowCommandEvent::FullscreenChanged(mode));
+                        }
+                        if !placement.fullscreen {
+                            let (x, y) = placement.position_on(monitor);
+                            screen_dimensions
+                                .update(f64::from(placement.width), f64::from(placement.height));
+                            set_position(window, x, y);
+                            events.single_write(WindowCommandEvent::ResolutionChanged(
+                                placement.width,
+                                placement.height,
+                            ));
+                            events.single_write(WindowCommandEvent::Moved(x, y));
+                        }
+                    }
+                }
+                WindowCommand::RefreshMonitors => {
+                    *monitors = Monitors::new(window);
+                    events.single_write(WindowCommandEvent::MonitorsChanged);
+                }
+                WindowCommand::Title(title) => {
+                    window.set_title(&title);
+                    events.single_write(WindowCommandEvent::TitleChanged(title));
+                }
+                WindowCommand::Icon(icon) => {
+                    window.set_window_icon(icon);
+                    events.single_write(WindowCommandEvent::IconChanged);
+                }
+                WindowCommand::Resizable(resizable) => {
+                    window.set_resizable(resizable);
+                    events.single_write(WindowCommandEvent::ResizableChanged(resizable));
+                }
+                WindowCommand::MinDimensions(dimensions) => {
+                    window.set_min_dimensions(dimensions.map(Into::into));
+                    events.single_write(WindowCommandEvent::MinDimensionsChanged(dimensions));
+                }
+                WindowCommand::MaxDimensions(dimensions) => {
+                    window.set_max_dimensions(dimensions.map(Into::into));
+                    events.single_write(WindowCommandEvent::MaxDimensionsChanged(dimensions));
+                }
+                WindowCommand::Decorations(decorations) => {
+                    window.set_decorations(decorations);
+                    events.single_write(WindowCommandEvent::DecorationsChanged(decorations));
+                }
+                WindowCommand::AlwaysOnTop(always_on_top) => {
+                    window.set_always_on_top(always_on_top);
+                    events.single_write(WindowCommandEvent::AlwaysOnTopChanged(always_on_top));
+                }
+            }
+        }
+    }
+
+    /// Applies the fullscreen mode to the window, changing the video mode of the monitor for
+    /// exclusive fullscreen, and returns the mode the window ended up in.
+    fn apply_fullscreen(&mut self, window: &Window, mode: FullscreenMode) -> FullscreenMode {
+        let mode = match mode {
+            FullscreenMode::Exclusive(monitor, video_mode) => {
+                let monitor_id = monitor.monitor_id(window);
+                // the monitor keeps its original mode when switching between its video modes
+                let same_monitor = self
+                    .mode_change
+                    .as_ref()
+                    .map_or(false, |change| change.is_on(&monitor_id));
+                if !same_monitor {
+                    self.mode_change = None;
+                }
+                if self.mode_change.is_none() {
+                    self.mode_change = ModeChange::new(&monitor_id);
+                }
+                let changed = self
+                    .mode_change
+                    .as_mut()
+                    .map_or(false, |change| change.set(&video_mode));
+                if changed {
+                    // the window is resized to the new mode when it enters fullscreen again
+                    window.set_fullscreen(None);
+                    FullscreenMode::Exclusive(monitor, video_mode)
+                } else {
+                    warn!(
+                        "Unable to switch monitor {} to {}x{} at {} Hz, using borderless fullscreen",
+                        monitor.name(),
+                        video_mode.width,
+                        video_mode.height,
+                        video_mode.refresh_rate
+                    );
+                    self.mode_change = None;
+                    FullscreenMode::Borderless(monitor)
+                }
+            }
+            mode => {
+                self.mode_change = None;
+                mode
+            }
+        };
+        window.set_fullscreen(match &mode {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless(monitor) | FullscreenMode::Exclusive(monitor, _) => {
+                Some(monitor.monitor_id(window))
+            }
+        });
+        self.fullscreen = mode.clone();
+        mode
+    }
+}
+
+fn fullscreen_mode(window: &Window) -> FullscreenMode {
+    window
+        .get_fullscreen()
+        .and_then(|monitor| MonitorIdent::from_monitor_id(window, monitor))
+        .map_or(FullscreenMode::Windowed, FullscreenMode::Borderless)
+}
+
+fn current_monitor<'a>(window: &Window, monitors: &'a Monitors) -> Option<&'a MonitorInfo> {
+    MonitorIdent::from_monitor_id(window, window.get_current_monitor())
+        .and_then(|ident| monitors.get(&ident))
+}
+
+fn placement(
+    window: &Window,
+    screen_dimensions: &ScreenDimensions,
+    monitors: &Monitors,
+) -> Option<WindowPlacement> {
+    let monitor = current_monitor(window, monitors)?;
+    let (x, y): (i32, i32) = window
+        .get_position()?
+        .to_physical(window.get_hidpi_factor())
+        .into();
+    Some(WindowPlacement {
+        monitor: monitor.ident.clone(),
+        x: x - monitor.x,
+        y: y - monitor.y,
+        width: screen_dimensions.width() as u32,
+        height: screen_dimensions.height() as u32,
+        fullscreen: window.get_fullscreen().is_some(),
+    })
+}
+
+fn set_position(window: &Window, x: i32, y: i32) {
+    window.set_position(LogicalPosition::from_physical(
+        (x, y),
+        window.get_hidpi_factor(),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_config::Config;
+
+    #[test]
+    fn placements_stay_on_smaller_monitors() {
+        let placement = WindowPlacement::load_bytes(
+            b"(monitor: (1, \"HDMI-1\"), x: 1800, y: 100, width: 800, height: 600, \
+              fullscreen: false)",
+        )
+        .unwrap();
+        let monitor = MonitorInfo {
+            ident: placement.monitor.clone(),
+            x: 1920,
+            y: 0,
+            width: 1920,
+            height: 1080,
+            hidpi_factor: 1.0,
+            video_modes: Vec::new(),
+        };
+        assert_eq!(placement.position_on(&monitor), (1920 + 1120, 100));
+
+        let small = MonitorInfo {
+            width: 640,
+            height: 480,
+            ..monitor
+        };
+        assert_eq!(placement.position_on(&small), (1920, 0));
+    }
+}
@@ -50,6 +50,10 @@ pub struct DisplayConfig {
     /// Whether the the window should be transparent. If this is true, writing
     /// colors with alpha values different than 1.0 will produce a transparent
     /// window.
+    ///
+    /// Unlike the decorations and `always_on_top`, this can't be changed with
+    /// `WindowCommands` once the window is open. Combine it with a clear color
+    /// with an alpha of 0.0 and no decorations for overlays.
     #[serde(default)]
     pub transparent: bool,
 
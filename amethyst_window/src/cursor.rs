@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use winit::{MouseCursor, Window};
+
+/// Whether the cursor can leave the main window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CursorGrab {
+    /// The cursor moves freely.
+    Free,
+    /// The cursor is kept inside the window, e.g. for first person cameras or edge scrolling.
+    ///
+    /// `winit` 0.19 can't lock the cursor in place, hide it too and read the mouse motion
+    /// instead of its position for that.
+    Confined,
+}
+
+/// World resource controlling the cursor over the main window, applied by the `WindowSystem`
+/// whenever it changes.
+///
+/// The system cursor icon can be replaced by a texture with the `UiCursor` of `amethyst_ui`,
+/// which hides the system cursor while it's drawn, and widgets with a `HoverCursor` change the
+/// icon while they're hovered.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CursorState {
+    /// Whether the cursor is shown.
+    pub visible: bool,
+    /// Whether the cursor is kept inside the window.
+    pub grab: CursorGrab,
+    /// The system cursor icon shown when no hovered widget sets one.
+    pub icon: MouseCursor,
+    #[serde(skip)]
+    hover_icon: Option<MouseCursor>,
+    #[serde(skip)]
+    custom_image: bool,
+}
+
+impl Default for CursorState {
+    fn default() -> Self {
+        CursorState {
+            visible: true,
+            grab: CursorGrab::Free,
+            icon: MouseCursor::Default,
+            hover_icon: None,
+            custom_image: false,
+        }
+    }
+}
+
+impl CursorState {
+    /// Sets the icon shown while a widget is hovered, or `None` to show `icon` again.
+    pub fn set_hover_icon(&mut self, icon: Option<MouseCursor>) {
+        self.hover_icon = icon;
+    }
+
+    /// Hides the system cursor while a custom cursor image is drawn in its place.
+    pub fn set_custom_image(&mut self, custom_image: bool) {
+        self.custom_image = custom_image;
+    }
+
+    /// Returns true if a custom cursor image is drawn instead of the system cursor.
+    pub fn has_custom_image(&self) -> bool {
+        self.custom_image
+    }
+
+    /// Returns the system cursor icon to show.
+    pub fn current_icon(&self) -> MouseCursor {
+        self.hover_icon.unwrap_or(self.icon)
+    }
+
+    /// Returns true if the system cursor is shown.
+    pub fn shows_system_cursor(&self) -> bool {
+        self.visible && !self.custom_image
+    }
+
+    /// Applies the changes since the `applied` state to the window.
+    pub(crate) fn apply(&self, window: &Window, applied: Option<&CursorState>) {
+        if applied.map(CursorState::shows_system_cursor) != Some(self.shows_system_cursor()) {
+            window.hide_cursor(!self.shows_system_cursor());
+        }
+        if applied.map(|applied| applied.grab) != Some(self.grab) {
+            if let Err(err) = window.grab_cursor(self.grab == CursorGrab::Confined) {
+                log::error!("Unable to change the cursor grab. Error: {:?}", err);
+            }
+        }
+        if applied.map(CursorState::current_icon) != Some(self.current_icon()) {
+            window.set_cursor(self.current_icon());
+        }
+    }
+}
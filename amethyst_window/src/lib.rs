@@ -14,19 +14,28 @@
 #![warn(clippy::all)]
 #![allow(clippy::new_without_default)]
 
+mod activity;
 mod bundle;
+mod commands;
 mod config;
+mod cursor;
 mod monitor;
 mod resources;
 mod system;
+mod video_mode;
+mod windows;
 
 #[cfg(feature = "test-support")]
 pub use crate::bundle::{SCREEN_HEIGHT, SCREEN_WIDTH};
 pub use crate::{
+    activity::{BackgroundMode, WindowActivity, WindowActivityEvent, WindowActivitySystem},
     bundle::WindowBundle,
+    commands::{FullscreenMode, WindowCommandEvent, WindowCommands, WindowPlacement},
     config::DisplayConfig,
-    monitor::{MonitorIdent, MonitorsAccess},
+    cursor::{CursorGrab, CursorState},
+    monitor::{MonitorIdent, MonitorInfo, Monitors, MonitorsAccess, VideoMode},
     resources::ScreenDimensions,
     system::{EventsLoopSystem, WindowSystem},
+    windows::Windows,
 };
-pub use winit::{Icon, Window};
+pub use winit::{Icon, MouseCursor, Window, WindowId};
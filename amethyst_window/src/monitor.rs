@@ -1,3 +1,4 @@
+use crate::video_mode;
 use serde::{Deserialize, Serialize};
 use winit::{AvailableMonitorsIter, EventsLoop, MonitorId, Window};
 
@@ -79,4 +80,209 @@ impl MonitorIdent {
             .map(|(_, m)| m)
             .unwrap_or_else(|| monitors.primary())
     }
+
+    /// Returns the index of the monitor in the list of all monitors when it was identified.
+    pub fn index(&self) -> u16 {
+        self.0
+    }
+
+    /// Returns the name of the monitor.
+    pub fn name(&self) -> &str {
+        &self.1
+    }
+}
+
+/// A resolution and refresh rate a monitor can be switched to for exclusive fullscreen.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
+pub struct VideoMode {
+    /// Width of the mode in physical pixels.
+    pub width: u32,
+    /// Height of the mode in physical pixels.
+    pub height: u32,
+    /// Refresh rate of the mode in hertz, or 0 if the monitor doesn't report it.
+    pub refresh_rate: u16,
+}
+
+/// A monitor as listed by the `Monitors` resource.
+///
+/// The size is the resolution the monitor is currently in, which a borderless fullscreen window
+/// gets on it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MonitorInfo {
+    /// Identifier of the monitor.
+    pub ident: MonitorIdent,
+    /// Horizontal position of the top left corner of the monitor on the desktop, in physical
+    /// pixels.
+    pub x: i32,
+    /// Vertical position of the top left corner of the monitor on the desktop, in physical
+    /// pixels.
+    pub y: i32,
+    /// Width of the monitor in physical pixels.
+    pub width: u32,
+    /// Height of the monitor in physical pixels.
+    pub height: u32,
+    /// The ratio between physical and logical pixels on the monitor.
+    pub hidpi_factor: f64,
+    /// The modes the monitor supports for exclusive fullscreen, largest and fastest first.
+    ///
+    /// Modes are listed on Windows, macOS and X11, and empty on other platforms.
+    #[serde(default)]
+    pub video_modes: Vec<VideoMode>,
+}
+
+impl MonitorInfo {
+    /// Returns true if the given desktop position in physical pixels is on the monitor.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && i64::from(x) < i64::from(self.x) + i64::from(self.width)
+            && i64::from(y) < i64::from(self.y) + i64::from(self.height)
+    }
+
+    /// Returns the mode of the given resolution with the highest refresh rate.
+    pub fn video_mode(&self, width: u32, height: u32) -> Option<VideoMode> {
+        self.video_modes
+            .iter()
+            .find(|mode| mode.width == width && mode.height == height)
+            .cloned()
+    }
+
+    /// Returns the desktop position of the top left corner of a window of the given size
+    /// centered on the monitor, in physical pixels.
+    pub fn centered(&self, width: u32, height: u32) -> (i32, i32) {
+        (
+            self.x + (i64::from(self.width) - i64::from(width)) as i32 / 2,
+            self.y + (i64::from(self.height) - i64::from(height)) as i32 / 2,
+        )
+    }
+}
+
+/// World resource listing the monitors, filled in by the `WindowSystem` and updated with
+/// `WindowCommands::refresh_monitors`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Monitors {
+    monitors: Vec<MonitorInfo>,
+    primary: Option<usize>,
+}
+
+impl Monitors {
+    /// Lists the available monitors.
+    pub fn new(monitors: &impl MonitorsAccess) -> Self {
+        let primary = MonitorIdent::from_monitor_id(monitors, monitors.primary());
+        let monitors = monitors
+            .iter()
+            .enumerate()
+            .filter_map(|(index, monitor)| {
+                let (width, height) = monitor.get_dimensions().into();
+                let (x, y) = monitor.get_position().into();
+                monitor.get_name().map(|name| MonitorInfo {
+                    ident: MonitorIdent(index as u16, name),
+                    x,
+                    y,
+                    width,
+                    height,
+                    hidpi_factor: monitor.get_hidpi_factor(),
+                    video_modes: video_mode::video_modes(&monitor),
+                })
+            })
+            .collect::<Vec<_>>();
+        Self::from_monitors(monitors, primary.as_ref())
+    }
+
+    fn from_monitors(monitors: Vec<MonitorInfo>, primary: Option<&MonitorIdent>) -> Self {
+        let primary = primary.and_then(|primary| {
+            monitors
+                .iter()
+                .position(|monitor| &monitor.ident == primary)
+        });
+        Monitors { monitors, primary }
+    }
+
+    /// Returns the monitors.
+    pub fn iter(&self) -> impl Iterator<Item = &MonitorInfo> {
+        self.monitors.iter()
+    }
+
+    /// Returns the number of monitors.
+    pub fn len(&self) -> usize {
+        self.monitors.len()
+    }
+
+    /// Returns true if no monitor was found.
+    pub fn is_empty(&self) -> bool {
+        self.monitors.is_empty()
+    }
+
+    /// Returns the primary monitor.
+    pub fn primary(&self) -> Option<&MonitorInfo> {
+        self.primary.map(|index| &self.monitors[index])
+    }
+
+    /// Returns the monitor matching the identifier most closely, i.e. the one with the same name
+    /// nearest to its index, like `MonitorIdent::monitor_id`.
+    pub fn get(&self, ident: &MonitorIdent) -> Option<&MonitorInfo> {
+        self.monitors
+            .iter()
+            .filter(|monitor| monitor.ident.1 == ident.1)
+            .min_by_key(|monitor| (i32::from(monitor.ident.0) - i32::from(ident.0)).abs())
+    }
+
+    /// Returns the monitor showing the given desktop position in physical pixels.
+    pub fn at(&self, x: i32, y: i32) -> Option<&MonitorInfo> {
+        self.monitors.iter().find(|monitor| monitor.contains(x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(index: u16, name: &str, x: i32, width: u32) -> MonitorInfo {
+        MonitorInfo {
+            ident: MonitorIdent(index, name.to_string()),
+            x,
+            y: 0,
+            width,
+            height: 1080,
+            hidpi_factor: 1.0,
+            video_modes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn monitors_are_found_by_ident_and_position() {
+        let left = monitor(0, "DP-1", -1920, 1920);
+        let right = monitor(1, "HDMI-1", 0, 2560);
+        let monitors =
+            Monitors::from_monitors(vec![left.clone(), right.clone()], Some(&right.ident));
+
+        assert_eq!(monitors.primary(), Some(&right));
+        assert_eq!(monitors.get(&MonitorIdent(3, "DP-1".into())), Some(&left));
+        assert_eq!(monitors.get(&MonitorIdent(0, "eDP-1".into())), None);
+        assert_eq!(monitors.at(-1, 500), Some(&left));
+        assert_eq!(monitors.at(2559, 1079), Some(&right));
+        assert_eq!(monitors.at(2560, 0), None);
+        assert_eq!(left.centered(1280, 720), (-1600, 180));
+    }
+
+    #[test]
+    fn video_modes_are_found_by_resolution() {
+        let mode = |width, height, refresh_rate| VideoMode {
+            width,
+            height,
+            refresh_rate,
+        };
+        let monitor = MonitorInfo {
+            video_modes: vec![
+                mode(1920, 1080, 144),
+                mode(1920, 1080, 60),
+                mode(1280, 720, 60),
+            ],
+            ..monitor(0, "DP-1", 0, 1920)
+        };
+
+        assert_eq!(monitor.video_mode(1920, 1080), Some(mode(1920, 1080, 144)));
+        assert_eq!(monitor.video_mode(1280, 720), Some(mode(1280, 720, 60)));
+        assert_eq!(monitor.video_mode(800, 600), None);
+    }
 }
@@ -1,7 +1,14 @@
-use crate::{config::DisplayConfig, resources::ScreenDimensions};
+use crate::{
+    commands::{WindowCommandEvent, WindowCommands},
+    config::DisplayConfig,
+    cursor::CursorState,
+    monitor::Monitors,
+    resources::ScreenDimensions,
+    windows::Windows,
+};
 use amethyst_config::{Config, ConfigError};
 use amethyst_core::{
-    ecs::{ReadExpect, RunNow, System, SystemData, World, Write, WriteExpect},
+    ecs::{Read, ReadExpect, RunNow, System, SystemData, World, Write, WriteExpect},
     shrev::EventChannel,
 };
 use std::path::Path;
@@ -9,7 +16,9 @@ use winit::{Event, EventsLoop, Window};
 
 /// System for opening and managing the window.
 #[derive(Debug)]
-pub struct WindowSystem;
+pub struct WindowSystem {
+    cursor: Option<CursorState>,
+}
 
 impl WindowSystem {
     /// Builds and spawns a new `Window`, using the provided `DisplayConfig` and `EventsLoop` as
@@ -45,11 +54,15 @@ impl WindowSystem {
             .to_physical(hidpi)
             .into();
         world.insert(ScreenDimensions::new(width, height, hidpi));
+        let mut commands = WindowCommands::default();
+        commands.sync(&window);
+        world.insert(commands);
+        world.insert(Monitors::new(&window));
         world.insert(window);
-        Self
+        Self { cursor: None }
     }
 
-    fn manage_dimensions(&mut self, mut screen_dimensions: &mut ScreenDimensions, window: &Window) {
+    pub(crate) fn manage_dimensions(screen_dimensions: &mut ScreenDimensions, window: &Window) {
         let width = screen_dimensions.w;
         let height = screen_dimensions.h;
 
@@ -78,13 +91,38 @@ impl WindowSystem {
 }
 
 impl<'a> System<'a> for WindowSystem {
-    type SystemData = (WriteExpect<'a, ScreenDimensions>, ReadExpect<'a, Window>);
-
-    fn run(&mut self, (mut screen_dimensions, window): Self::SystemData) {
+    type SystemData = (
+        WriteExpect<'a, ScreenDimensions>,
+        ReadExpect<'a, Window>,
+        Write<'a, Windows>,
+        Write<'a, WindowCommands>,
+        Write<'a, Monitors>,
+        Write<'a, EventChannel<WindowCommandEvent>>,
+        Read<'a, CursorState>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("window_system");
 
-        self.manage_dimensions(&mut screen_dimensions, &window);
+        let (
+            mut screen_dimensions,
+            window,
+            mut windows,
+            mut commands,
+            mut monitors,
+            mut events,
+            cursor,
+        ) = data;
+
+        commands.apply(&window, &mut screen_dimensions, &mut monitors, &mut events);
+        Self::manage_dimensions(&mut screen_dimensions, &window);
+        windows.manage_dimensions();
+
+        if self.cursor.as_ref() != Some(&*cursor) {
+            cursor.apply(&window, self.cursor.as_ref());
+            self.cursor = Some(cursor.clone());
+        }
     }
 }
 
@@ -110,6 +148,9 @@ impl EventsLoopSystem {
 
 impl<'a> RunNow<'a> for EventsLoopSystem {
     fn run_now(&mut self, world: &'a World) {
+        if let Some(mut windows) = world.try_fetch_mut::<Windows>() {
+            windows.create_pending(&self.events_loop);
+        }
         let mut event_handler = <Write<'a, EventChannel<Event>>>::fetch(world);
 
         let events = &mut self.events;
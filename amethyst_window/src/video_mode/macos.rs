@@ -0,0 +1,119 @@
+//! Video modes of the displays, with Core Graphics.
+
+use crate::monitor::VideoMode;
+use std::{os::raw::c_void, ptr};
+use winit::{os::macos::MonitorIdExt, MonitorId};
+
+type CGDirectDisplayID = u32;
+type CGDisplayModeRef = *mut c_void;
+type CFArrayRef = *const c_void;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGDisplayCopyAllDisplayModes(
+        display: CGDirectDisplayID,
+        options: *const c_void,
+    ) -> CFArrayRef;
+    fn CGDisplayCopyDisplayMode(display: CGDirectDisplayID) -> CGDisplayModeRef;
+    fn CGDisplayModeGetPixelWidth(mode: CGDisplayModeRef) -> usize;
+    fn CGDisplayModeGetPixelHeight(mode: CGDisplayModeRef) -> usize;
+    fn CGDisplayModeGetRefreshRate(mode: CGDisplayModeRef) -> f64;
+    fn CGDisplayModeRelease(mode: CGDisplayModeRef);
+    fn CGDisplaySetDisplayMode(
+        display: CGDirectDisplayID,
+        mode: CGDisplayModeRef,
+        options: *const c_void,
+    ) -> i32;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFArrayGetCount(array: CFArrayRef) -> isize;
+    fn CFArrayGetValueAtIndex(array: CFArrayRef, index: isize) -> *const c_void;
+    fn CFRelease(object: *const c_void);
+}
+
+const SUCCESS: i32 = 0;
+
+fn video_mode(mode: CGDisplayModeRef) -> VideoMode {
+    unsafe {
+        VideoMode {
+            width: CGDisplayModeGetPixelWidth(mode) as u32,
+            height: CGDisplayModeGetPixelHeight(mode) as u32,
+            refresh_rate: CGDisplayModeGetRefreshRate(mode).round() as u16,
+        }
+    }
+}
+
+/// Calls `f` with each mode of the display, until it returns true.
+fn find_mode(display: CGDirectDisplayID, mut f: impl FnMut(CGDisplayModeRef) -> bool) -> bool {
+    unsafe {
+        let modes = CGDisplayCopyAllDisplayModes(display, ptr::null());
+        if modes.is_null() {
+            return false;
+        }
+        let found = (0..CFArrayGetCount(modes))
+            .any(|index| f(CFArrayGetValueAtIndex(modes, index) as CGDisplayModeRef));
+        CFRelease(modes);
+        found
+    }
+}
+
+pub fn video_modes(monitor: &MonitorId) -> Vec<VideoMode> {
+    let mut modes = Vec::new();
+    find_mode(monitor.native_id(), |mode| {
+        modes.push(video_mode(mode));
+        false
+    });
+    modes
+}
+
+/// A display switched to another video mode, switched back to its previous mode when dropped.
+#[derive(Debug)]
+pub struct ModeChange {
+    display: CGDirectDisplayID,
+    original: CGDisplayModeRef,
+}
+
+// Display modes are immutable.
+unsafe impl Send for ModeChange {}
+unsafe impl Sync for ModeChange {}
+
+impl ModeChange {
+    pub fn new(monitor: &MonitorId) -> Option<Self> {
+        let display = monitor.native_id();
+        let original = unsafe { CGDisplayCopyDisplayMode(display) };
+        if original.is_null() {
+            None
+        } else {
+            Some(ModeChange { display, original })
+        }
+    }
+
+    pub fn is_on(&self, monitor: &MonitorId) -> bool {
+        monitor.native_id() == self.display
+    }
+
+    pub fn set(&mut self, mode: &VideoMode) -> bool {
+        let display = self.display;
+        let mut success = false;
+        find_mode(display, |candidate| {
+            if video_mode(candidate) != *mode {
+                return false;
+            }
+            success =
+                unsafe { CGDisplaySetDisplayMode(display, candidate, ptr::null()) } == SUCCESS;
+            true
+        });
+        success
+    }
+}
+
+impl Drop for ModeChange {
+    fn drop(&mut self) {
+        unsafe {
+            CGDisplaySetDisplayMode(self.display, self.original, ptr::null());
+            CGDisplayModeRelease(self.original);
+        }
+    }
+}
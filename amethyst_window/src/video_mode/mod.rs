@@ -0,0 +1,77 @@
+//! Video modes of the monitors for exclusive fullscreen, which `winit` 0.19 doesn't support, so
+//! they're listed and changed with the display API of each platform.
+
+use crate::monitor::VideoMode;
+use winit::MonitorId;
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+mod x11;
+
+#[cfg(target_os = "macos")]
+use self::macos as platform;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use self::x11 as platform;
+
+pub(crate) use self::platform::ModeChange;
+
+/// Lists the video modes of the monitor, largest and fastest first.
+pub(crate) fn video_modes(monitor: &MonitorId) -> Vec<VideoMode> {
+    let mut modes = platform::video_modes(monitor);
+    modes.sort_by(|a, b| b.cmp(a));
+    modes.dedup();
+    modes
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+mod platform {
+    use crate::monitor::VideoMode;
+    use winit::MonitorId;
+
+    pub fn video_modes(_: &MonitorId) -> Vec<VideoMode> {
+        Vec::new()
+    }
+
+    /// The mode of monitors can't be changed on this platform.
+    #[derive(Debug)]
+    pub struct ModeChange;
+
+    impl ModeChange {
+        pub fn new(_: &MonitorId) -> Option<Self> {
+            None
+        }
+
+        pub fn is_on(&self, _: &MonitorId) -> bool {
+            false
+        }
+
+        pub fn set(&mut self, _: &VideoMode) -> bool {
+            false
+        }
+    }
+}
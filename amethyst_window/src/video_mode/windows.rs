@@ -0,0 +1,111 @@
+//! Video modes of the display devices, with the display settings of the Windows API.
+
+use crate::monitor::VideoMode;
+use std::{ffi::OsStr, iter, mem, os::windows::ffi::OsStrExt, ptr};
+use winapi::um::{
+    wingdi::{DEVMODEW, DM_BITSPERPEL, DM_DISPLAYFREQUENCY, DM_PELSHEIGHT, DM_PELSWIDTH},
+    winuser::{
+        ChangeDisplaySettingsExW, EnumDisplaySettingsW, CDS_FULLSCREEN, DISP_CHANGE_SUCCESSFUL,
+    },
+};
+use winit::{os::windows::MonitorIdExt, MonitorId};
+
+/// Modes of other color depths are left out.
+const BITS_PER_PIXEL: u32 = 32;
+
+/// Returns the null terminated name of the display device showing the monitor.
+fn device_name(monitor: &MonitorId) -> Vec<u16> {
+    OsStr::new(&monitor.native_id())
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect()
+}
+
+fn display_modes(device: &[u16]) -> Vec<DEVMODEW> {
+    let mut modes = Vec::new();
+    for index in 0.. {
+        let mut mode: DEVMODEW = unsafe { mem::zeroed() };
+        mode.dmSize = mem::size_of::<DEVMODEW>() as u16;
+        if unsafe { EnumDisplaySettingsW(device.as_ptr(), index, &mut mode) } == 0 {
+            break;
+        }
+        if mode.dmBitsPerPel == BITS_PER_PIXEL {
+            modes.push(mode);
+        }
+    }
+    modes
+}
+
+fn video_mode(mode: &DEVMODEW) -> VideoMode {
+    VideoMode {
+        width: mode.dmPelsWidth,
+        height: mode.dmPelsHeight,
+        // 0 and 1 stand for the default rate of the hardware
+        refresh_rate: if mode.dmDisplayFrequency > 1 {
+            mode.dmDisplayFrequency as u16
+        } else {
+            0
+        },
+    }
+}
+
+pub fn video_modes(monitor: &MonitorId) -> Vec<VideoMode> {
+    display_modes(&device_name(monitor))
+        .iter()
+        .map(video_mode)
+        .collect()
+}
+
+/// A display device switched to another video mode, switched back to the mode of the registry
+/// when dropped.
+#[derive(Debug)]
+pub struct ModeChange {
+    device: Vec<u16>,
+}
+
+impl ModeChange {
+    pub fn new(monitor: &MonitorId) -> Option<Self> {
+        Some(ModeChange {
+            device: device_name(monitor),
+        })
+    }
+
+    pub fn is_on(&self, monitor: &MonitorId) -> bool {
+        device_name(monitor) == self.device
+    }
+
+    pub fn set(&mut self, mode: &VideoMode) -> bool {
+        let found = display_modes(&self.device)
+            .into_iter()
+            .find(|candidate| video_mode(candidate) == *mode);
+        let mut found = match found {
+            Some(found) => found,
+            None => return false,
+        };
+        found.dmFields = DM_BITSPERPEL | DM_PELSWIDTH | DM_PELSHEIGHT | DM_DISPLAYFREQUENCY;
+        let result = unsafe {
+            ChangeDisplaySettingsExW(
+                self.device.as_ptr(),
+                &mut found,
+                ptr::null_mut(),
+                CDS_FULLSCREEN,
+                ptr::null_mut(),
+            )
+        };
+        result == DISP_CHANGE_SUCCESSFUL
+    }
+}
+
+impl Drop for ModeChange {
+    fn drop(&mut self) {
+        unsafe {
+            ChangeDisplaySettingsExW(
+                self.device.as_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+                ptr::null_mut(),
+            );
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Video modes of the CRTCs showing the monitors, with the RandR extension of X11.
+
+use crate::monitor::VideoMode;
+use std::{
+    fmt,
+    os::raw::{c_int, c_ulong},
+    ptr, slice,
+};
+use winit::{os::unix::MonitorIdExt, MonitorId};
+use x11_dl::{
+    xlib,
+    xrandr::{self, RRCrtc, RRMode, XRRCrtcInfo, XRRModeInfo, XRRScreenResources},
+};
+
+const RR_INTERLACE: c_ulong = 0x10;
+const RR_DOUBLE_SCAN: c_ulong = 0x20;
+const RR_ROTATE_90_OR_270: u16 = 0x2 | 0x8;
+const RR_SET_CONFIG_SUCCESS: c_int = 0;
+
+/// A connection to the X server, separate from the one of `winit`.
+struct Randr {
+    xlib: xlib::Xlib,
+    xrandr: xrandr::Xrandr,
+    display: *mut xlib::Display,
+}
+
+impl Randr {
+    fn open() -> Option<Self> {
+        let xlib = xlib::Xlib::open().ok()?;
+        let xrandr = xrandr::Xrandr::open().ok()?;
+        let display = unsafe { (xlib.XOpenDisplay)(ptr::null()) };
+        if display.is_null() {
+            return None;
+        }
+        Some(Randr {
+            xlib,
+            xrandr,
+            display,
+        })
+    }
+}
+
+impl Drop for Randr {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xlib.XCloseDisplay)(self.display);
+        }
+    }
+}
+
+/// Returns the items of an array allocated by Xlib.
+unsafe fn array<'a, T>(items: *const T, len: c_int) -> &'a [T] {
+    if items.is_null() || len <= 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(items, len as usize)
+    }
+}
+
+/// A CRTC, which scans out a part of the screen to its outputs.
+struct Crtc<'a> {
+    randr: &'a Randr,
+    id: RRCrtc,
+    resources: *mut XRRScreenResources,
+    info: *mut XRRCrtcInfo,
+}
+
+impl<'a> Crtc<'a> {
+    fn new(randr: &'a Randr, id: RRCrtc) -> Option<Self> {
+        unsafe {
+            let root = (randr.xlib.XDefaultRootWindow)(randr.display);
+            let resources = (randr.xrandr.XRRGetScreenResourcesCurrent)(randr.display, root);
+            if resources.is_null() {
+                return None;
+            }
+            let mut crtc = Crtc {
+                randr,
+                id,
+                resources,
+                info: ptr::null_mut(),
+            };
+            if !array((*resources).crtcs, (*resources).ncrtc).contains(&id) {
+                return None;
+            }
+            crtc.info = (randr.xrandr.XRRGetCrtcInfo)(randr.display, resources, id);
+            if crtc.info.is_null() || (*crtc.info).noutput <= 0 {
+                return None;
+            }
+            Some(crtc)
+        }
+    }
+
+    /// Returns the CRTC `winit` identifies the monitor with.
+    fn of_monitor(randr: &'a Randr, monitor: &MonitorId) -> Option<Self> {
+        let crtc = Self::new(randr, RRCrtc::from(monitor.native_id()))?;
+        // other backends identify monitors differently, e.g. Wayland
+        let info = crtc.info();
+        let position: (i32, i32) = monitor.get_position().into();
+        let size: (u32, u32) = monitor.get_dimensions().into();
+        if (info.x, info.y) == position && (info.width, info.height) == size {
+            Some(crtc)
+        } else {
+            None
+        }
+    }
+
+    fn info(&self) -> &XRRCrtcInfo {
+        unsafe { &*self.info }
+    }
+
+    /// Returns the modes of the first output of the CRTC which fit on the screen.
+    fn modes(&self) -> Vec<(RRMode, VideoMode)> {
+        let (randr, info) = (self.randr, self.info());
+        unsafe {
+            let output =
+                (randr.xrandr.XRRGetOutputInfo)(randr.display, self.resources, *info.outputs);
+            if output.is_null() {
+                return Vec::new();
+            }
+            let ids = array((*output).modes, (*output).nmode).to_vec();
+            (randr.xrandr.XRRFreeOutputInfo)(output);
+
+            let screen = (randr.xlib.XDefaultScreen)(randr.display);
+            let screen_width = (randr.xlib.XDisplayWidth)(randr.display, screen);
+            let screen_height = (randr.xlib.XDisplayHeight)(randr.display, screen);
+            let rotated = info.rotation & RR_ROTATE_90_OR_270 != 0;
+            array((*self.resources).modes, (*self.resources).nmode)
+                .iter()
+                .filter(|mode| ids.contains(&mode.id))
+                .filter_map(|mode| {
+                    let (width, height) = if rotated {
+                        (mode.height, mode.width)
+                    } else {
+                        (mode.width, mode.height)
+                    };
+                    // larger modes would need the screen to be resized
+                    if i64::from(info.x) + i64::from(width) > i64::from(screen_width)
+                        || i64::from(info.y) + i64::from(height) > i64::from(screen_height)
+                    {
+                        return None;
+                    }
+                    let refresh_rate = refresh_rate(mode);
+                    Some((
+                        mode.id,
+                        VideoMode {
+                            width,
+                            height,
+                            refresh_rate,
+                        },
+                    ))
+                })
+                .collect()
+        }
+    }
+
+    fn set_mode(&self, mode: RRMode) -> bool {
+        let (randr, info) = (self.randr, self.info());
+        unsafe {
+            let status = (randr.xrandr.XRRSetCrtcConfig)(
+                randr.display,
+                self.resources,
+                self.id,
+                xlib::CurrentTime,
+                info.x,
+                info.y,
+                mode,
+                info.rotation,
+                info.outputs,
+                info.noutput,
+            );
+            (randr.xlib.XSync)(randr.display, xlib::False);
+            status == RR_SET_CONFIG_SUCCESS
+        }
+    }
+}
+
+impl Drop for Crtc<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.info.is_null() {
+                (self.randr.xrandr.XRRFreeCrtcInfo)(self.info);
+            }
+            (self.randr.xrandr.XRRFreeScreenResources)(self.resources);
+        }
+    }
+}
+
+fn refresh_rate(mode: &XRRModeInfo) -> u16 {
+    let mut lines = f64::from(mode.vTotal);
+    if mode.modeFlags & RR_DOUBLE_SCAN != 0 {
+        lines *= 2.0;
+    }
+    if mode.modeFlags & RR_INTERLACE != 0 {
+        lines /= 2.0;
+    }
+    let dots = f64::from(mode.hTotal) * lines;
+    if dots > 0.0 {
+        (mode.dotClock as f64 / dots).round() as u16
+    } else {
+        0
+    }
+}
+
+pub fn video_modes(monitor: &MonitorId) -> Vec<VideoMode> {
+    Randr::open()
+        .and_then(|randr| {
+            let crtc = Crtc::of_monitor(&randr, monitor)?;
+            let modes = crtc.modes().into_iter().map(|(_, mode)| mode).collect();
+            Some(modes)
+        })
+        .unwrap_or_default()
+}
+
+/// A monitor switched to another video mode, switched back to its previous mode when dropped.
+pub struct ModeChange {
+    randr: Randr,
+    crtc: RRCrtc,
+    original: RRMode,
+}
+
+// The connection is only used through `&mut ModeChange` and when it's dropped.
+unsafe impl Send for ModeChange {}
+unsafe impl Sync for ModeChange {}
+
+impl ModeChange {
+    pub fn new(monitor: &MonitorId) -> Option<Self> {
+        let randr = Randr::open()?;
+        let (crtc, original) = {
+            let crtc = Crtc::of_monitor(&randr, monitor)?;
+            (crtc.id, crtc.info().mode)
+        };
+        Some(ModeChange {
+            randr,
+            crtc,
+            original,
+        })
+    }
+
+    pub fn is_on(&self, monitor: &MonitorId) -> bool {
+        RRCrtc::from(monitor.native_id()) == self.crtc
+    }
+
+    pub fn set(&mut self, mode: &VideoMode) -> bool {
+        let crtc = match Crtc::new(&self.randr, self.crtc) {
+            Some(crtc) => crtc,
+            None => return false,
+        };
+        let found = crtc.modes().into_iter().find(|(_, m)| m == mode);
+        found.map_or(false, |(id, _)| crtc.set_mode(id))
+    }
+}
+
+impl Drop for ModeChange {
+    fn drop(&mut self) {
+        if let Some(crtc) = Crtc::new(&self.randr, self.crtc) {
+            crtc.set_mode(self.original);
+        }
+    }
+}
+
+impl fmt::Debug for ModeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModeChange")
+            .field("crtc", &self.crtc)
+            .field("original", &self.original)
+            .finish()
+    }
+}
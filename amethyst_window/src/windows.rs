@@ -0,0 +1,125 @@
+use crate::{config::DisplayConfig, resources::ScreenDimensions, system::WindowSystem};
+use log::{error, warn};
+use winit::{EventsLoop, Window, WindowId};
+
+/// World resource holding the windows opened at runtime in addition to the main `Window`, e.g.
+/// for tool windows or a second monitor.
+///
+/// Windows are identified by a name, which is also the name of the `Target::Custom` they are
+/// rendered from by `RenderToAdditionalWindow`. They are created by the `EventsLoopSystem` at the
+/// start of the next frame, and each has its own `ScreenDimensions` kept up to date by the
+/// `WindowSystem`.
+///
+/// Window events of every window go to the same `EventChannel<Event>`, use `name` with the
+/// `window_id` of an event to tell which window it belongs to. Closing an additional window
+/// doesn't happen on its own, call `close` when its `CloseRequested` event arrives.
+///
+/// Each window gets the transparency, decorations and `always_on_top` of its own `DisplayConfig`.
+/// They can be changed at runtime through `window`, like `WindowCommands` does for the main
+/// window.
+#[derive(Debug, Default)]
+pub struct Windows {
+    windows: Vec<AdditionalWindow>,
+    pending: Vec<(&'static str, DisplayConfig)>,
+}
+
+#[derive(Debug)]
+struct AdditionalWindow {
+    name: &'static str,
+    window: Window,
+    dimensions: ScreenDimensions,
+}
+
+impl Windows {
+    /// Opens a window named `name` with the given config at the start of the next frame.
+    ///
+    /// Does nothing if a window with this name is already open or about to be.
+    pub fn open(&mut self, name: &'static str, config: DisplayConfig) {
+        if self.contains(name) || self.pending.iter().any(|(pending, _)| *pending == name) {
+            warn!("A window named {} is already open", name);
+            return;
+        }
+        self.pending.push((name, config));
+    }
+
+    /// Closes the window named `name`, returning false if there is none.
+    pub fn close(&mut self, name: &str) -> bool {
+        let count = self.windows.len() + self.pending.len();
+        self.windows.retain(|window| window.name != name);
+        self.pending.retain(|(pending, _)| *pending != name);
+        count != self.windows.len() + self.pending.len()
+    }
+
+    /// Returns true if the window named `name` is open.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns the window named `name`.
+    pub fn window(&self, name: &str) -> Option<&Window> {
+        self.get(name).map(|window| &window.window)
+    }
+
+    /// Returns the dimensions of the window named `name`.
+    pub fn dimensions(&self, name: &str) -> Option<&ScreenDimensions> {
+        self.get(name).map(|window| &window.dimensions)
+    }
+
+    /// Returns the dimensions of the window named `name`, which resizes the window when updated.
+    pub fn dimensions_mut(&mut self, name: &str) -> Option<&mut ScreenDimensions> {
+        self.windows
+            .iter_mut()
+            .find(|window| window.name == name)
+            .map(|window| &mut window.dimensions)
+    }
+
+    /// Returns the id of the window named `name`, as found in its window events.
+    pub fn id(&self, name: &str) -> Option<WindowId> {
+        self.get(name).map(|window| window.window.id())
+    }
+
+    /// Returns the name of the window with the given id, or `None` for the main window.
+    pub fn name(&self, id: WindowId) -> Option<&'static str> {
+        self.windows
+            .iter()
+            .find(|window| window.window.id() == id)
+            .map(|window| window.name)
+    }
+
+    /// Returns the names of the open windows.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.windows.iter().map(|window| window.name)
+    }
+
+    fn get(&self, name: &str) -> Option<&AdditionalWindow> {
+        self.windows.iter().find(|window| window.name == name)
+    }
+
+    /// Creates the windows opened since the last call.
+    pub(crate) fn create_pending(&mut self, events_loop: &EventsLoop) {
+        for (name, config) in self.pending.drain(..) {
+            match config.into_window_builder(events_loop).build(events_loop) {
+                Ok(window) => {
+                    let hidpi = window.get_hidpi_factor();
+                    let (width, height) = window
+                        .get_inner_size()
+                        .map(|size| size.to_physical(hidpi).into())
+                        .unwrap_or((1, 1));
+                    self.windows.push(AdditionalWindow {
+                        name,
+                        window,
+                        dimensions: ScreenDimensions::new(width, height, hidpi),
+                    });
+                }
+                Err(err) => error!("Failed to open the window {}: {}", name, err),
+            }
+        }
+    }
+
+    /// Syncs the size of the windows with their dimensions.
+    pub(crate) fn manage_dimensions(&mut self) {
+        for window in &mut self.windows {
+            WindowSystem::manage_dimensions(&mut window.dimensions, &window.window);
+        }
+    }
+}
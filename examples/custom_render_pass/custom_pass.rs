@@ -71,13 +71,21 @@ lazy_static::lazy_static! {
 /// Draw triangles.
 #[derive(Clone, Debug, PartialEq, Derivative)]
 #[derivative(Default(bound = ""))]
-pub struct DrawCustomDesc;
+pub struct DrawCustomDesc {
+    samples: hal::image::NumSamples,
+}
 
 impl DrawCustomDesc {
     /// Create instance of `DrawCustomDesc` render group
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Create instance drawing into a target with the given number of samples per pixel.
+    pub fn with_samples(mut self, samples: hal::image::NumSamples) -> Self {
+        self.samples = samples;
+        self
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawCustomDesc {
@@ -101,6 +109,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawCustomDesc {
             subpass,
             framebuffer_width,
             framebuffer_height,
+            self.samples,
             vec![env.raw_layout()],
         )?;
 
@@ -205,6 +214,7 @@ fn build_custom_pipeline<B: Backend>(
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
+    samples: hal::image::NumSamples,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
@@ -232,6 +242,8 @@ fn build_custom_pipeline<B: Backend>(
                 .with_layout(&pipeline_layout)
                 .with_subpass(subpass)
                 .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                // Match the samples of the target, which is multisampled with `RenderSettings`
+                .with_samples(samples)
                 // We are using alpha blending
                 .with_blend_targets(vec![pso::ColorBlendDesc {
                     mask: pso::ColorMask::ALL,
@@ -282,7 +294,10 @@ impl<B: Backend> RenderPlugin<B> for RenderCustom {
     ) -> Result<(), Error> {
         plan.extend_target(Target::Main, |ctx| {
             // Add our Description
-            ctx.add(RenderOrder::Transparent, DrawCustomDesc::new().builder())?;
+            ctx.add(
+                RenderOrder::Transparent,
+                DrawCustomDesc::new().with_samples(ctx.samples()).builder(),
+            )?;
             Ok(())
         });
         Ok(())
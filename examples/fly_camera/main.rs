@@ -2,10 +2,10 @@
 
 use amethyst::{
     assets::{PrefabLoader, PrefabLoaderSystemDesc, RonFormat},
-    controls::{FlyControlBundle, HideCursor},
+    controls::FlyControlBundle,
     core::transform::TransformBundle,
     ecs::WorldExt,
-    input::{is_key_down, is_mouse_button_down, InputBundle, StringBindings},
+    input::{InputBundle, StringBindings},
     prelude::*,
     renderer::{
         plugins::{RenderShaded3D, RenderToWindow},
@@ -14,7 +14,6 @@ use amethyst::{
         RenderingBundle,
     },
     utils::{application_root_dir, scene::BasicScenePrefab},
-    winit::{MouseButton, VirtualKeyCode},
     Error,
 };
 
@@ -33,24 +32,6 @@ impl SimpleState for ExampleState {
             .with(prefab_handle)
             .build();
     }
-
-    fn handle_event(
-        &mut self,
-        data: StateData<'_, GameData<'_, '_>>,
-        event: StateEvent,
-    ) -> SimpleTrans {
-        let StateData { world, .. } = data;
-        if let StateEvent::Window(event) = &event {
-            if is_key_down(&event, VirtualKeyCode::Escape) {
-                let mut hide_cursor = world.write_resource::<HideCursor>();
-                hide_cursor.hide = false;
-            } else if is_mouse_button_down(&event, MouseButton::Left) {
-                let mut hide_cursor = world.write_resource::<HideCursor>();
-                hide_cursor.hide = true;
-            }
-        }
-        Trans::None
-    }
 }
 
 fn main() -> Result<(), Error> {
@@ -66,18 +47,19 @@ fn main() -> Result<(), Error> {
 
     let game_data = GameDataBuilder::default()
         .with_system_desc(PrefabLoaderSystemDesc::<MyPrefabData>::default(), "", &[])
+        .with_bundle(
+            InputBundle::<StringBindings>::new().with_bindings_from_file(&key_bindings_path)?,
+        )?
         .with_bundle(
             FlyControlBundle::<StringBindings>::new(
                 Some(String::from("move_x")),
                 Some(String::from("move_y")),
                 Some(String::from("move_z")),
             )
-            .with_sensitivity(0.1, 0.1),
+            .with_sensitivity(0.1, 0.1)
+            .with_sprint(String::from("sprint"), 3.0),
         )?
         .with_bundle(TransformBundle::new().with_dep(&["fly_movement"]))?
-        .with_bundle(
-            InputBundle::<StringBindings>::new().with_bindings_from_file(&key_bindings_path)?,
-        )?
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
                 .with_plugin(
@@ -13,7 +13,7 @@ use amethyst::{
     core::transform::{Transform, TransformBundle},
     derive::PrefabData,
     ecs::{Entity, ReadStorage, Write, WriteStorage},
-    input::{is_close_requested, is_key_down, StringBindings, VirtualKeyCode},
+    input::{is_close_requested, is_key_down, InputBundle, StringBindings, VirtualKeyCode},
     prelude::*,
     renderer::{
         camera::CameraPrefab,
@@ -198,6 +198,7 @@ fn main() -> Result<(), amethyst::Error> {
             AnimationBundle::<usize, Transform>::new("animation_control", "sampler_interpolation")
                 .with_dep(&["gltf_loader"]),
         )?
+        .with_bundle(InputBundle::<StringBindings>::new())?
         .with_bundle(
             FlyControlBundle::<StringBindings>::new(None, None, None)
                 .with_sensitivity(0.1, 0.1)
@@ -1,2 +1,6 @@
 hello = Hello, world!
 bye = See you later!
+apples = { $count ->
+    [one] One apple
+   *[other] { $count } apples
+}
@@ -1,2 +1,6 @@
 hello = Bonjour!
 bye = Au revoir!
+apples = { $count ->
+    [one] { $count } pomme
+   *[other] { $count } pommes
+}
@@ -1,7 +1,8 @@
-//! Example showing how to load a Locale file as an Asset using the Loader.
+//! Example showing how to load Locale files as Assets using the Loader, and format their messages
+//! with the `Localization` resource.
 
 use amethyst::{
-    assets::{AssetStorage, Handle, Loader, Processor, ProgressCounter},
+    assets::{AssetStorage, Loader, ProgressCounter},
     ecs::{Read, ReadExpect, WorldExt},
     locale::*,
     prelude::*,
@@ -11,72 +12,73 @@ use amethyst::{
 
 struct Example {
     progress_counter: Option<ProgressCounter>,
-    handle_en: Option<Handle<Locale>>,
-    handle_fr: Option<Handle<Locale>>,
+    switched: bool,
 }
 
 impl Example {
     pub fn new() -> Self {
         Example {
             progress_counter: None,
-            handle_en: None,
-            handle_fr: None,
+            switched: false,
         }
     }
 }
 
+fn print_messages(localization: &Localization) {
+    for id in &["hello", "bye"] {
+        println!(
+            "{}",
+            localization.format(id, None).expect("Missing message")
+        );
+    }
+    for count in &[1, 2] {
+        let args = fluent_args!["count" => *count];
+        println!(
+            "{}",
+            localization
+                .format("apples", Some(&args))
+                .expect("Missing message")
+        );
+    }
+}
+
 impl SimpleState for Example {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        data.world.insert(AssetStorage::<Locale>::new());
         let mut progress_counter = ProgressCounter::default();
-        self.handle_en = Some(data.world.exec(
-            |(loader, storage): (ReadExpect<'_, Loader>, Read<'_, AssetStorage<Locale>>)| {
-                loader.load(
-                    "locale/locale_en.ftl",
-                    LocaleFormat,
-                    &mut progress_counter,
-                    &storage,
-                )
-            },
-        ));
-        self.handle_fr = Some(data.world.exec(
+        let (handle_en, handle_fr) = data.world.exec(
             |(loader, storage): (ReadExpect<'_, Loader>, Read<'_, AssetStorage<Locale>>)| {
-                loader.load(
-                    "locale/locale_fr.ftl",
-                    LocaleFormat,
-                    &mut progress_counter,
-                    &storage,
-                )
+                let mut load =
+                    |path: &str| loader.load(path, LocaleFormat, &mut progress_counter, &storage);
+                (load("locale/locale_en.ftl"), load("locale/locale_fr.ftl"))
             },
-        ));
+        );
+        // English first, falling back to French for messages English doesn't have
+        data.world.insert(
+            Localization::new(vec![langid!("en"), langid!("fr")])
+                .with_locale(langid!("en"), handle_en)
+                .with_locale(langid!("fr"), handle_fr),
+        );
         self.progress_counter = Some(progress_counter);
     }
 
     fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        // Check if the locale has been loaded.
-        if self.progress_counter.as_ref().unwrap().is_complete() {
-            let store = data.world.read_resource::<AssetStorage<Locale>>();
-            for h in [&self.handle_en, &self.handle_fr].iter() {
-                if let Some(locale) = h.as_ref().and_then(|h| store.get(h)) {
-                    let bundle = &locale.bundle;
-                    let msg_hello = bundle
-                        .get_message("hello")
-                        .expect("Failed to load message for hello");
-                    let msg_bye = bundle
-                        .get_message("bye")
-                        .expect("Failed to load message for bye");
-                    let hello_value = msg_hello.value.expect("Hello message has no value");
-                    let bye_value = msg_bye.value.expect("Bye message has no value");
+        // Check if the locales have been loaded, and built by the `LocalizationSystem`.
+        let mut localization = data.world.write_resource::<Localization>();
+        if !self.progress_counter.as_ref().unwrap().is_complete()
+            || !localization.has_message("hello")
+        {
+            return Trans::None;
+        }
 
-                    let mut errors = vec![];
-                    println!("{}", bundle.format_pattern(hello_value, None, &mut errors));
-                    println!("{}", bundle.format_pattern(bye_value, None, &mut errors));
-                    assert_eq!(errors.len(), 0);
-                }
-            }
-            Trans::Quit
-        } else {
+        if !self.switched {
+            print_messages(&localization);
+            // The messages are built again in French on the next frame.
+            localization.set_language(langid!("fr"));
+            self.switched = true;
             Trans::None
+        } else {
+            print_messages(&localization);
+            Trans::Quit
         }
     }
 }
@@ -86,7 +88,7 @@ fn main() -> Result<(), Error> {
 
     let assets_dir = application_root_dir()?.join("examples/locale/assets");
 
-    let game_data = GameDataBuilder::default().with(Processor::<Locale>::new(), "proc", &[]);
+    let game_data = GameDataBuilder::default().with_bundle(LocaleBundle::new())?;
 
     let mut game = Application::new(assets_dir, Example::new(), game_data)?;
     game.run();
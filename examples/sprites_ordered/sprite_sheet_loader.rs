@@ -58,7 +58,11 @@ pub fn load(texture: Handle<Texture>, definition: &sprite::SpriteSheetDefinition
         }
     }
     println!("Sheet: {:?}", sprites);
-    SpriteSheet { texture, sprites }
+    SpriteSheet {
+        texture,
+        sprites,
+        durations: Vec::new(),
+    }
 }
 
 /// Returns the pixel offset distances per sprite.
@@ -63,7 +63,7 @@ pub fn main() -> amethyst::Result<()> {
         // Necessary for the FPS counter in the upper left corner to work.
         // (simply uncommenting will fail at runtime, since the resource is expected to exist, you
         // need to uncomment line 107-114 in game.rs for it to still work)
-        .with_bundle(FpsCounterBundle)?
+        .with_bundle(FpsCounterBundle::default())?
         // Without this, we would not get a picture.
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
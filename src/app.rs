@@ -4,7 +4,7 @@ use std::{env, marker::PhantomData, path::Path, sync::Arc, time::Duration};
 
 use crate::shred::Resource;
 use derivative::Derivative;
-use log::{debug, info, log_enabled, trace, Level};
+use log::{debug, error, info, log_enabled, trace, Level};
 use rayon::ThreadPoolBuilder;
 #[cfg(feature = "sentry")]
 use sentry::integrations::panic::register_panic_handler;
@@ -20,7 +20,7 @@ use crate::{
         frame_limiter::{FrameLimiter, FrameRateLimitConfig, FrameRateLimitStrategy},
         shrev::{EventChannel, ReaderId},
         timing::{Stopwatch, Time},
-        ArcThreadPool, EventReader, Named,
+        ArcThreadPool, EventReader, Named, SystemError,
     },
     ecs::prelude::{Component, Read, World, WorldExt, Write},
     error::Error,
@@ -60,6 +60,7 @@ where
     event_reader_id: ReaderId<Event>,
     #[derivative(Debug = "ignore")]
     trans_reader_id: ReaderId<TransEvent<T, E>>,
+    system_error_reader_id: ReaderId<SystemError>,
     states: StateMachine<'a, T, E>,
     ignore_window_close: bool,
     data: T,
@@ -251,8 +252,8 @@ where
             {
                 let elapsed = self.world.read_resource::<Stopwatch>().elapsed();
                 let mut time = self.world.write_resource::<Time>();
-                time.increment_frame_number();
-                time.set_delta_time(elapsed);
+                let delta = time.manual_delta().unwrap_or(elapsed);
+                time.advance(delta);
             }
             let mut stopwatch = self.world.write_resource::<Stopwatch>();
             stopwatch.stop();
@@ -381,6 +382,12 @@ where
             self.states
                 .update(StateData::new(&mut self.world, &mut self.data));
         }
+        {
+            let errors = self.world.read_resource::<EventChannel<SystemError>>();
+            for system_error in errors.read(&mut self.system_error_reader_id) {
+                error!("{}", system_error);
+            }
+        }
 
         #[cfg(feature = "profiler")]
         profile_scope!("maintain");
@@ -550,6 +557,7 @@ where
         #[cfg(feature = "ui")]
         world.insert(EventChannel::<UiEvent>::with_capacity(40));
         world.insert(EventChannel::<TransEvent<T, StateEvent>>::with_capacity(2));
+        world.insert(EventChannel::<SystemError>::new());
         world.insert(FrameLimiter::default());
         world.insert(Stopwatch::default());
         world.insert(Time::default());
@@ -904,6 +912,10 @@ where
             .world
             .exec(|mut ev: Write<'_, EventChannel<TransEvent<T, E>>>| ev.register_reader());
 
+        let system_error_reader_id = self
+            .world
+            .exec(|mut ev: Write<'_, EventChannel<SystemError>>| ev.register_reader());
+
         Ok(CoreApplication {
             world: self.world,
             states: StateMachine::new(self.initial_state),
@@ -913,6 +925,7 @@ where
             data,
             event_reader_id,
             trans_reader_id,
+            system_error_reader_id,
         })
     }
 }
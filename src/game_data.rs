@@ -472,7 +472,10 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
             .try_for_each(|dispatcher_operation| {
                 dispatcher_operation.exec(world, &mut dispatcher_builder)
             })
-            .unwrap_or_else(|e| panic!("Failed to set up dispatcher: {}", e));
+            .unwrap_or_else(|e| {
+                let causes = e.causes().map(ToString::to_string).collect::<Vec<_>>();
+                panic!("Failed to set up dispatcher: {}", causes.join(": "))
+            });
 
         #[cfg(not(no_threading))]
         let mut dispatcher = dispatcher_builder.with_pool(pool).build();